@@ -0,0 +1,167 @@
+use criterion::{
+    criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion,
+};
+use lair_keystore::bench::*;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::ipc::*;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+struct BenchStatic {
+    pub tokio: tokio::runtime::Handle,
+    pub server: BenchServer,
+    pub fixture: BenchFixture,
+    pub pools: Vec<LairClientPool>,
+}
+
+const POOL_SIZES: &[usize] = &[1, 2, 4, 8];
+
+// enough concurrent requests to keep the largest pool busy
+const POOLED_REQUESTS: usize = 64;
+
+impl BenchStatic {
+    pub fn new() -> Self {
+        let mut runtime = tokio::runtime::Builder::new()
+            .enable_all()
+            .threaded_scheduler()
+            .build()
+            .unwrap();
+
+        let tokio = runtime.handle().clone();
+
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                futures::future::pending::<()>().await;
+            });
+        });
+
+        let (server, fixture, pools) = tokio.enter(move || {
+            futures::executor::block_on(async move {
+                let server =
+                    spawn_bench_server(Default::default()).await.unwrap();
+                let fixture = BenchFixture::new(&server.api).await.unwrap();
+                let mut pools = Vec::new();
+                for size in POOL_SIZES {
+                    let (pool, _evt_recv) =
+                        spawn_client_pool(server.config.clone(), *size)
+                            .await
+                            .unwrap();
+                    pools.push(pool);
+                }
+                (server, fixture, pools)
+            })
+        });
+
+        Self {
+            tokio,
+            server,
+            fixture,
+            pools,
+        }
+    }
+}
+
+static STATIC: Lazy<Arc<BenchStatic>> =
+    Lazy::new(|| Arc::new(BenchStatic::new()));
+
+fn run_once(op: BenchOp) {
+    STATIC.tokio.block_on(async move {
+        op.run_once(&STATIC.server.api, &STATIC.fixture)
+            .await
+            .unwrap();
+    });
+}
+
+fn sign_pooled(pool: &LairClientPool) {
+    STATIC.tokio.block_on(async move {
+        futures::future::try_join_all((0..POOLED_REQUESTS).map(|_| {
+            pool.sign_ed25519_sign_by_index(
+                STATIC.fixture.sign_idx,
+                STATIC.fixture.message.clone(),
+            )
+        }))
+        .await
+        .unwrap();
+    });
+}
+
+fn new_entries() -> Vec<KeystoreIndex> {
+    STATIC.tokio.block_on(async move {
+        let mut indices = Vec::with_capacity(ERASE_BATCH_SIZE);
+        for _ in 0..ERASE_BATCH_SIZE {
+            indices.push(
+                STATIC
+                    .server
+                    .api
+                    .sign_ed25519_new_from_entropy()
+                    .await
+                    .unwrap()
+                    .0,
+            );
+        }
+        indices
+    })
+}
+
+fn erase(indices: Vec<KeystoreIndex>, bulk: bool) {
+    STATIC.tokio.block_on(async move {
+        let api = &STATIC.server.api;
+        if bulk {
+            api.lair_erase_entries(indices).await.unwrap();
+        } else {
+            for index in indices {
+                api.lair_erase_entries(vec![index]).await.unwrap();
+            }
+        }
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("operations");
+    // cert generation is slow, keep the suite to a few minutes
+    group.sample_size(10);
+    for op in BenchOp::all() {
+        group.bench_function(op.name(), |b| b.iter(|| run_once(op)));
+    }
+    group.finish();
+
+    // criterion only reports times, log the syncs saved alongside
+    for bulk in [false, true].iter() {
+        let syncs = STATIC
+            .tokio
+            .block_on(erase_syncs(&STATIC.server.api, *bulk))
+            .unwrap();
+        println!(
+            "erasing {} entries {}: {} store syncs",
+            ERASE_BATCH_SIZE,
+            if *bulk { "in bulk" } else { "one by one" },
+            syncs,
+        );
+    }
+    let mut group = c.benchmark_group("erase_entries");
+    group.sample_size(10);
+    group.bench_function("one_by_one", |b| {
+        b.iter_batched(
+            new_entries,
+            |i| erase(i, false),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("bulk", |b| {
+        b.iter_batched(new_entries, |i| erase(i, true), BatchSize::PerIteration)
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("pooled_signing");
+    for pool in STATIC.pools.iter() {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(pool.size()),
+            pool,
+            |b, pool| b.iter(|| sign_pooled(pool)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -7,8 +7,9 @@ use std::sync::Arc;
 
 struct BenchStatic {
     pub tokio: tokio::runtime::Handle,
+    #[allow(dead_code)]
     pub tmpdir: tempfile::TempDir,
-    pub api_send: ghost_actor::GhostSender<LairClientApi>,
+    pub api_send: LairClientSender,
     pub sign_idx: KeystoreIndex,
 }
 
@@ -32,6 +33,7 @@ impl BenchStatic {
             futures::executor::block_on(async move {
                 let tmpdir = tempfile::tempdir().unwrap();
                 std::env::set_var("LAIR_DIR", tmpdir.path());
+                std::env::set_var("LAIR_ALLOW_WEAK_PASSPHRASE", "1");
 
                 lair_keystore::execute_lair().await.unwrap();
 
@@ -39,21 +41,22 @@ impl BenchStatic {
                     Config::builder().set_root_path(tmpdir.path()).build();
 
                 let (api_send, mut evt_recv) =
-                    ipc::spawn_client_ipc(config).await.unwrap();
+                    ipc::connect(ipc::ConnectOptions::from_config(&config))
+                        .await
+                        .unwrap();
 
                 tokio::task::spawn(async move {
                     while let Some(msg) = evt_recv.next().await {
-                        match msg {
-                            LairClientEvent::RequestUnlockPassphrase {
-                                respond,
-                                ..
-                            } => {
-                                respond.respond(Ok(async move {
-                                    Ok("passphrase".to_string())
-                                }
-                                .boxed()
-                                .into()));
+                        if let LairClientEvent::RequestUnlockPassphrase {
+                            respond,
+                            ..
+                        } = msg
+                        {
+                            respond.respond(Ok(async move {
+                                Ok("passphrase".into())
                             }
+                            .boxed()
+                            .into()));
                         }
                     }
                 });
@@ -95,7 +98,7 @@ fn sign_small() {
 
 fn bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("signature_generation");
-    group.bench_function("sign_small_message", |b| b.iter(|| sign_small()));
+    group.bench_function("sign_small_message", |b| b.iter(sign_small));
     group.finish();
 }
 
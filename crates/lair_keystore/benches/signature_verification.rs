@@ -0,0 +1,65 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::*;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+struct BenchStatic {
+    pub pub_key: SignEd25519PubKey,
+    pub message: Vec<u8>,
+    pub signature: SignEd25519Signature,
+}
+
+impl BenchStatic {
+    pub fn new() -> Self {
+        futures::executor::block_on(async move {
+            let keypair = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                entropy::OsEntropy::new_handle(),
+            )
+            .await
+            .unwrap();
+            let message = vec![0xdb; 32];
+            let signature = sign_ed25519::sign_ed25519(
+                keypair.priv_key,
+                Arc::new(message.clone()),
+            )
+            .await
+            .unwrap();
+            Self {
+                pub_key: keypair.pub_key,
+                message,
+                signature,
+            }
+        })
+    }
+}
+
+static STATIC: Lazy<BenchStatic> = Lazy::new(BenchStatic::new);
+
+// the copies a caller holding borrowed data makes for the async api
+fn verify_async() {
+    let message = Arc::new(black_box(&STATIC.message).to_vec());
+    let signature = STATIC.signature.clone();
+    assert!(futures::executor::block_on(
+        STATIC.pub_key.verify(message, signature)
+    )
+    .unwrap());
+}
+
+fn verify_sync() {
+    assert!(STATIC
+        .pub_key
+        .verify_sync(black_box(&STATIC.message), &STATIC.signature)
+        .unwrap());
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature_verification");
+    group
+        .bench_function("verify_async_small_message", |b| b.iter(verify_async));
+    group.bench_function("verify_sync_small_message", |b| b.iter(verify_sync));
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,324 @@
+//! Throughput benchmarks of a lair server, for capacity planning.
+//! Run by `lair-keystore bench`, the criterion suite in
+//! `benches/operations.rs` runs the same operations to track
+//! regressions.
+
+use crate::*;
+use lair_keystore_api::actor::*;
+
+/// The number of signatures requested at once by `BenchOp::SignBatch`.
+pub const SIGN_BATCH_SIZE: usize = 32;
+
+/// The byte length of the messages signed and verified.
+pub const BENCH_MESSAGE_LEN: usize = 32;
+
+/// A benchmarked operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BenchOp {
+    /// Sign a message with an ed25519 keypair by index.
+    Sign,
+
+    /// Sign `SIGN_BATCH_SIZE` messages concurrently,
+    /// pipelined over one connection.
+    SignBatch,
+
+    /// Verify an ed25519 signature, client side.
+    Verify,
+
+    /// Create a self-signed tls certificate from entropy.
+    CertNew(TlsCertAlg),
+
+    /// Create a new ed25519 keypair, a write to the store file.
+    StoreWrite,
+}
+
+impl BenchOp {
+    /// Every benchmarked operation, in the order they are run.
+    pub fn all() -> Vec<BenchOp> {
+        vec![
+            BenchOp::Sign,
+            BenchOp::SignBatch,
+            BenchOp::Verify,
+            BenchOp::CertNew(TlsCertAlg::PkcsEd25519),
+            BenchOp::CertNew(TlsCertAlg::PkcsEcdsaP256Sha256),
+            BenchOp::CertNew(TlsCertAlg::PkcsEcdsaP384Sha384),
+            #[cfg(feature = "ed448")]
+            BenchOp::CertNew(TlsCertAlg::PkcsEd448),
+            BenchOp::StoreWrite,
+        ]
+    }
+
+    /// The name reported for this operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchOp::Sign => "sign",
+            BenchOp::SignBatch => "sign_batch",
+            BenchOp::Verify => "verify",
+            BenchOp::CertNew(TlsCertAlg::PkcsEd25519) => "cert_new_ed25519",
+            BenchOp::CertNew(TlsCertAlg::PkcsEcdsaP256Sha256) => {
+                "cert_new_ecdsa_p256"
+            }
+            BenchOp::CertNew(TlsCertAlg::PkcsEcdsaP384Sha384) => {
+                "cert_new_ecdsa_p384"
+            }
+            BenchOp::CertNew(TlsCertAlg::PkcsEd448) => "cert_new_ed448",
+            BenchOp::CertNew(_) => "cert_new",
+            BenchOp::StoreWrite => "store_write",
+        }
+    }
+
+    /// Run this operation once, resolving to the number of
+    /// operations done (`SIGN_BATCH_SIZE` for a `SignBatch`).
+    pub async fn run_once(
+        &self,
+        api: &LairClientSender,
+        fixture: &BenchFixture,
+    ) -> LairResult<u64> {
+        match self {
+            BenchOp::Sign => {
+                api.sign_ed25519_sign_by_index(
+                    fixture.sign_idx,
+                    fixture.message.clone(),
+                )
+                .await?;
+                Ok(1)
+            }
+            BenchOp::SignBatch => {
+                futures::future::try_join_all((0..SIGN_BATCH_SIZE).map(|_| {
+                    api.sign_ed25519_sign_by_index(
+                        fixture.sign_idx,
+                        fixture.message.clone(),
+                    )
+                }))
+                .await?;
+                Ok(SIGN_BATCH_SIZE as u64)
+            }
+            BenchOp::Verify => {
+                if !lair_keystore_api::internal::sign_ed25519::sign_ed25519_verify(
+                    fixture.sign_pub_key.clone(),
+                    fixture.message.clone(),
+                    fixture.signature.clone(),
+                )
+                .await?
+                {
+                    return Err("benchmark signature failed to verify".into());
+                }
+                Ok(1)
+            }
+            BenchOp::CertNew(alg) => {
+                let mut options = TlsCertOptions::default();
+                options.alg = *alg;
+                api.tls_cert_new_self_signed_from_entropy(options).await?;
+                Ok(1)
+            }
+            BenchOp::StoreWrite => {
+                api.sign_ed25519_new_from_entropy().await?;
+                Ok(1)
+            }
+        }
+    }
+}
+
+/// The number of entries `erase_syncs` erases.
+pub const ERASE_BATCH_SIZE: usize = 32;
+
+/// The number of store file syncs this process has done so far.
+pub fn store_sync_count() -> u64 {
+    crate::store::store_file::sync_count()
+}
+
+/// Create `ERASE_BATCH_SIZE` keypairs, and erase them, one per
+/// request, or with `bulk` all in one `lair_erase_entries`, resolving
+/// to the store file syncs the erasing took. Only meaningful against
+/// a server in this process, with no other store writes under way.
+pub async fn erase_syncs(
+    api: &LairClientSender,
+    bulk: bool,
+) -> LairResult<u64> {
+    let mut indices = Vec::with_capacity(ERASE_BATCH_SIZE);
+    for _ in 0..ERASE_BATCH_SIZE {
+        indices.push(api.sign_ed25519_new_from_entropy().await?.0);
+    }
+    let start = store_sync_count();
+    let results = if bulk {
+        api.lair_erase_entries(indices).await?
+    } else {
+        let mut results = Vec::with_capacity(ERASE_BATCH_SIZE);
+        for index in indices {
+            results.append(&mut api.lair_erase_entries(vec![index]).await?);
+        }
+        results
+    };
+    results.into_iter().collect::<LairResult<Vec<_>>>()?;
+    Ok(store_sync_count() - start)
+}
+
+/// The keypair, message and signature the operations work on.
+pub struct BenchFixture {
+    /// The keystore index of the signing keypair.
+    pub sign_idx: KeystoreIndex,
+
+    /// The pub key of the signing keypair.
+    pub sign_pub_key: SignEd25519PubKey,
+
+    /// The signed message.
+    pub message: Arc<Vec<u8>>,
+
+    /// The signature of `message` by the signing keypair.
+    pub signature: SignEd25519Signature,
+}
+
+impl BenchFixture {
+    /// Create the signing keypair, and sign the message.
+    pub async fn new(api: &LairClientSender) -> LairResult<Self> {
+        let (sign_idx, sign_pub_key) =
+            api.sign_ed25519_new_from_entropy().await?;
+        let message = Arc::new(vec![0xdb; BENCH_MESSAGE_LEN]);
+        let signature = api
+            .sign_ed25519_sign_by_index(sign_idx, message.clone())
+            .await?;
+        Ok(Self {
+            sign_idx,
+            sign_pub_key,
+            message,
+            signature,
+        })
+    }
+}
+
+/// The outcome of benchmarking one operation.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    /// The benchmarked operation.
+    pub op: BenchOp,
+
+    /// The number of operations done.
+    pub ops: u64,
+
+    /// The time taken to do them.
+    pub elapsed: std::time::Duration,
+}
+
+impl BenchResult {
+    /// Operations per second.
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Repeat `op` until `duration` has passed, at least once.
+pub async fn run_bench(
+    api: &LairClientSender,
+    fixture: &BenchFixture,
+    op: BenchOp,
+    duration: std::time::Duration,
+) -> LairResult<BenchResult> {
+    let start = std::time::Instant::now();
+    let mut ops = 0;
+    loop {
+        ops += op.run_once(api, fixture).await?;
+        if start.elapsed() >= duration {
+            break;
+        }
+    }
+    Ok(BenchResult {
+        op,
+        ops,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// A lair server on a new temporary store, removed when dropped.
+pub struct BenchServer {
+    /// A connection to the server, unlocked.
+    pub api: LairClientSender,
+
+    /// The config of the server, for further connections.
+    pub config: Arc<Config>,
+
+    /// The time taken to set the passphrase of, and so unlock,
+    /// the new store.
+    pub unlock_latency: std::time::Duration,
+
+    _tmpdir: tempfile::TempDir,
+}
+
+/// Spawn a lair server on a new temporary store, in this process,
+/// deriving its unlock key with `unlock_kdf_limits`.
+/// It never touches an existing store.
+pub async fn spawn_bench_server(
+    unlock_kdf_limits: lair_keystore_api::internal::unlock::UnlockKdfLimits,
+) -> LairResult<BenchServer> {
+    let tmpdir = tempfile::tempdir().map_err(LairError::other)?;
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_unlock_kdf_limits(unlock_kdf_limits)
+        // the passphrase dies with the store
+        .set_allow_weak_passphrase(true)
+        .build();
+
+    crate::internal::perms::check_permissions(&config)?;
+    let crate::internal::pid_check::PidCheckResult { store_file } =
+        crate::internal::pid_check::pid_check(&config)?;
+    ipc::spawn_bind_server_ipc(config.clone(), store_file).await?;
+
+    let start = std::time::Instant::now();
+    let (api, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, _progress) =
+        lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+            evt_recv,
+            "bench-passphrase".into(),
+        );
+    unlock.await?;
+    let unlock_latency = start.elapsed();
+
+    Ok(BenchServer {
+        api,
+        config,
+        unlock_latency,
+        _tmpdir: tmpdir,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_benches_every_op() -> LairResult<()> {
+        let server = spawn_bench_server(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 1,
+            },
+        )
+        .await?;
+        let fixture = BenchFixture::new(&server.api).await?;
+
+        let mut names = Vec::new();
+        for op in BenchOp::all() {
+            let res = run_bench(
+                &server.api,
+                &fixture,
+                op,
+                std::time::Duration::from_millis(1),
+            )
+            .await?;
+            assert!(res.ops >= 1, "{}", op.name());
+            assert!(res.ops_per_sec() > 0.0);
+            names.push(op.name());
+        }
+        assert_eq!(SIGN_BATCH_SIZE as u64, {
+            BenchOp::SignBatch.run_once(&server.api, &fixture).await?
+        });
+
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(BenchOp::all().len(), names.len());
+
+        Ok(())
+    }
+}
@@ -15,6 +15,1320 @@ struct Opt {
     /// Set the lair data directory.
     #[structopt(short = "d", long, env = "LAIR_DIR")]
     lair_dir: Option<std::path::PathBuf>,
+
+    /// Enable the ssh-agent protocol listener at this socket path.
+    #[structopt(long, env = "LAIR_SSH_AGENT_SOCKET")]
+    ssh_agent_socket: Option<std::path::PathBuf>,
+
+    /// Also listen on this connection url, e.g. a socket in a volume
+    /// shared with a sandbox. A url carrying a `?token=` requires
+    /// clients to present that token. On linux, an abstract unix socket
+    /// is given as `lair-unix-abstract://name`. May be given more than once.
+    /// Also set by the whitespace separated LAIR_EXTRA_LISTENERS env var.
+    #[structopt(long = "listen")]
+    listen: Vec<lair_keystore_api::LairConnectionUrl>,
+
+    /// Limit the capabilities of a listener, as `<url>=<capabilities>`,
+    /// e.g. `unix:///shared/lair.sock?token=abc=read_public,sign`.
+    /// Capabilities are read_public, sign, create, export_private, admin,
+    /// can_unlock and audit, listeners grant all by default. Only connections
+    /// granted can_unlock are asked for the unlock passphrase, only those
+    /// granted audit are sent audit events. May be given more than
+    /// once. Also set by the whitespace separated
+    /// LAIR_LISTENER_CAPABILITIES env var. Additional tokens, granting
+    /// fewer capabilities, are only set by the whitespace separated
+    /// `<token>=<capabilities>` LAIR_CONNECTION_TOKENS env var.
+    #[structopt(
+        long = "listener-capabilities",
+        parse(try_from_str = lair_keystore::parse_capability_grant)
+    )]
+    listener_capabilities:
+        Vec<(String, lair_keystore_api::actor::LairCapabilities)>,
+
+    /// Let connections elevate their capabilities for a time, as
+    /// `<capabilities>:<max secs>`, e.g. `export_private,admin:300`:
+    /// those of the listed that their listener grants, for at most that
+    /// long. Never can_unlock or audit. Connections may not elevate
+    /// by default.
+    #[structopt(
+        long,
+        env = "LAIR_ELEVATION",
+        parse(try_from_str = lair_keystore::parse_elevation)
+    )]
+    elevation: Option<lair_keystore_api::ElevationOptions>,
+
+    /// Refuse to tell connected clients where the store lives.
+    /// Also enabled by setting the LAIR_HIDE_PATHS env var.
+    #[structopt(long)]
+    hide_paths: bool,
+
+    /// Agree to compress large frames with clients asking to, when built
+    /// with the `compression` feature.
+    /// Also enabled by setting the LAIR_WIRE_COMPRESSION env var.
+    #[structopt(long)]
+    wire_compression: bool,
+
+    /// The minimum strength score, 0 to 4, a new unlock passphrase
+    /// must have. Defaults to 3.
+    #[structopt(
+        long,
+        env = "LAIR_MIN_PASSPHRASE_SCORE",
+        parse(try_from_str = lair_keystore::parse_passphrase_score)
+    )]
+    min_passphrase_score: Option<u8>,
+
+    /// Serve at most this many connections at once, refusing any
+    /// past it. Unlimited by default.
+    #[structopt(
+        long,
+        env = "LAIR_MAX_CONNECTIONS",
+        parse(try_from_str = lair_keystore::parse_max_connections)
+    )]
+    max_connections: Option<usize>,
+
+    /// Close connections without traffic for this many seconds.
+    /// Idle connections are kept by default.
+    #[structopt(
+        long = "idle-connection-timeout-secs",
+        env = "LAIR_IDLE_CONNECTION_TIMEOUT_SECS",
+        parse(try_from_str = lair_keystore::parse_idle_connection_timeout)
+    )]
+    idle_connection_timeout: Option<std::time::Duration>,
+
+    /// Read the settings that may change while the server runs, e.g.
+    /// LAIR_MAX_CONNECTIONS, from this file of `NAME=value` lines, at
+    /// startup, over the flags, and again on SIGHUP or `reload`.
+    #[structopt(long, env = "LAIR_SETTINGS_FILE")]
+    settings_file: Option<std::path::PathBuf>,
+
+    /// The octal permission bits of the sockets lair listens on,
+    /// e.g. 660 to let the group connect. Defaults to 600.
+    #[structopt(
+        long,
+        env = "LAIR_SOCKET_MODE",
+        parse(try_from_str = lair_keystore::parse_socket_mode)
+    )]
+    socket_mode: Option<u32>,
+
+    /// Hold requests using secret material while the store is locked,
+    /// as `<max requests>:<max wait secs>`, running them once it
+    /// unlocks. By default they are served as the lock allows.
+    #[structopt(
+        long,
+        env = "LAIR_QUEUE_WHILE_LOCKED",
+        parse(try_from_str = lair_keystore::parse_queue_while_locked)
+    )]
+    queue_while_locked: Option<lair_keystore_api::LockedQueueOptions>,
+
+    /// Accept a new unlock passphrase scoring below the minimum.
+    /// Also enabled by setting the LAIR_ALLOW_WEAK_PASSPHRASE env var.
+    #[structopt(long)]
+    allow_weak_passphrase: bool,
+
+    /// DANGER - start even if the lair data directory, or the files
+    /// in it, are writable by other users. Also enabled by setting the
+    /// LAIR_DANGER_ALLOW_INSECURE_PERMISSIONS env var.
+    #[structopt(long)]
+    danger_allow_insecure_permissions: bool,
+
+    /// Refuse to start if any store entry cannot be decoded, instead of
+    /// quarantining it. Also enabled by setting the LAIR_STRICT_LOAD
+    /// env var.
+    #[structopt(long)]
+    strict_load: bool,
+
+    /// Refuse `export-seed` for every keypair, exportable or not.
+    /// Also enabled by setting the LAIR_FORBID_SEED_EXPORT env var.
+    #[structopt(long)]
+    forbid_seed_export: bool,
+
+    /// Let imported keys, seeds and totp secrets be used right away,
+    /// instead of only once activated. Also enabled by setting the
+    /// LAIR_AUTO_ACTIVATE_IMPORTS env var.
+    #[structopt(long)]
+    auto_activate_imports: bool,
+
+    /// Migrate stores in a legacy layout, keeping a backup of each,
+    /// when they are unlocked, instead of refusing to start until
+    /// `lair-keystore migrate` is run. Also enabled by setting the
+    /// LAIR_AUTO_MIGRATE env var.
+    #[structopt(long)]
+    auto_migrate: bool,
+
+    /// What signing with keypairs that have an expiry does while the
+    /// clock is skewed, i.e. has been set back: `fail-closed`, the
+    /// default, refuses, `fail-open` signs without checking the expiry.
+    #[structopt(
+        long,
+        env = "LAIR_CLOCK_SKEW_POLICY",
+        parse(try_from_str = lair_keystore::parse_clock_skew_policy)
+    )]
+    clock_skew_policy: Option<lair_keystore_api::ClockSkewPolicy>,
+
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Cmd {
+    /// Create the lair data directory, optionally binding it to this
+    /// device, and exit. The server sets each store passphrase
+    /// as it is first unlocked.
+    Init {
+        /// Keep a random device secret in the platform keystore (Windows
+        /// Credential Manager, macOS Keychain, Linux Secret Service), and
+        /// mix it into the unlock key of every store, alongside its
+        /// passphrase. A copy of the store is then of no use off this
+        /// device. Only a reference to the secret is kept in the data
+        /// directory. Needs lair built with the keychain feature,
+        /// and a data directory with no store passphrase set.
+        #[structopt(long)]
+        bind_to_device: bool,
+
+        /// With --bind-to-device, read the base64 device secret, as
+        /// printed by `export-device-secret` on another device, from
+        /// stdin, instead of creating one. This moves a bound data
+        /// directory to this device.
+        #[structopt(long, requires = "bind-to-device")]
+        import_device_secret: bool,
+    },
+
+    /// Print the device secret the running lair server is bound to,
+    /// base64 encoded, to move its data directory to another device
+    /// with `init --bind-to-device --import-device-secret`. The store
+    /// must be unlocked, and the connection needs the admin capability.
+    /// Anyone holding the secret and a copy of the data directory only
+    /// needs the passphrase to unlock it, keep it safe.
+    ExportDeviceSecret,
+
+    /// Have the running lair server re-read its settings file, as it
+    /// does on SIGHUP, and exit. Settings that only apply on restart
+    /// are logged and skipped; a file with an invalid setting is
+    /// refused whole. The connection needs the admin capability.
+    Reload,
+
+    /// Print info about the running lair server and exit.
+    Status {
+        /// Output as json.
+        #[structopt(long)]
+        json: bool,
+
+        /// Report on this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Manage the named stores served alongside the default store.
+    Stores(StoresCmd),
+
+    /// Migrate stores in a legacy layout, prompting for the passphrase
+    /// of each. Each store is backed up to a `legacy-backup` directory
+    /// beside it first. The server must not be running.
+    Migrate {
+        /// Encrypt the tags, trash and inactive entries index of stores
+        /// that keep it in plaintext.
+        #[structopt(long)]
+        encrypt_index: bool,
+    },
+
+    /// Scan the entries of the running lair server for problems.
+    Check {
+        /// Report entries sharing a public identifier, as left behind
+        /// by imports predating duplicate detection.
+        #[structopt(long)]
+        dedupe: bool,
+
+        /// Check this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Run the known-answer self-test of the crypto primitives in this
+    /// build, as the server does before it starts, and exit.
+    SelfTest,
+
+    /// Inspect the entries the running lair server could not decode.
+    Quarantine(QuarantineCmd),
+
+    /// Benchmark signing, verification, certificate generation
+    /// and store writes against a new temporary store, and print
+    /// the operations per second of each.
+    Bench {
+        /// How long to run each benchmark for.
+        #[structopt(long, default_value = "1000")]
+        duration_ms: u64,
+
+        /// Output as json.
+        #[structopt(long)]
+        json: bool,
+
+        /// Benchmark the running lair server instead, which must be
+        /// unlocked. This writes new keypairs and certificates
+        /// to its store.
+        #[structopt(long)]
+        against_running_server: bool,
+    },
+
+    /// Check the health of the running lair server, without unlocking it,
+    /// e.g. as a container probe. Exits 0 if ready, 1 if degraded (locked,
+    /// or holding quarantined entries), 2 if not answering in time,
+    /// and 3 if not accepting connections.
+    Health {
+        /// How long the server may take to answer each call.
+        #[structopt(long, default_value = "1000")]
+        timeout_ms: u64,
+    },
+
+    /// Import an OpenSSH ed25519 private key, such as ~/.ssh/id_ed25519,
+    /// as a new signing keypair tagged with the key comment.
+    /// Prompts for the key passphrase if the key is encrypted.
+    ImportSshKey {
+        /// The private key file.
+        path: std::path::PathBuf,
+
+        /// Import into this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Print the pub key of an ed25519 signing keypair.
+    ExportPubkey {
+        /// The keystore index of the keypair.
+        keystore_index: u32,
+
+        /// The output format: ssh (an authorized_keys line),
+        /// jwk (an OKP / Ed25519 JSON Web Key) or b64 (base64).
+        #[structopt(
+            long,
+            default_value = "b64",
+            possible_values = &["ssh", "jwk", "b64"]
+        )]
+        format: PubKeyFormat,
+
+        /// The comment of an ssh format pub key.
+        #[structopt(long, default_value = "")]
+        comment: String,
+
+        /// Export from this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Import a 32 byte ed25519 seed, e.g. as printed by `export-seed`,
+    /// as a new signing keypair, and print its pub key. Give exactly one
+    /// of --mnemonic, --hex or --file.
+    ImportSeed {
+        /// Prompt for a 24 word bip39 mnemonic on stdin,
+        /// not echoing it if stdin is a terminal.
+        #[structopt(long)]
+        mnemonic: bool,
+
+        /// The seed as 64 hex characters. Pass `-` to be prompted for
+        /// it on stdin instead, keeping it out of your shell history.
+        #[structopt(long)]
+        hex: Option<String>,
+
+        /// Read the seed, as a mnemonic or 64 hex characters,
+        /// from this file.
+        #[structopt(long)]
+        file: Option<std::path::PathBuf>,
+
+        /// Tag the new keypair.
+        #[structopt(long)]
+        tag: Option<String>,
+
+        /// Import into this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Print the 32 byte seed of an ed25519 signing keypair, from which
+    /// `import-seed` restores it. Anyone holding the seed holds the key.
+    /// Asks to confirm on stdin first. Keypairs created not exportable,
+    /// or held in a hardware token, are refused, as is every keypair of
+    /// a server started with --forbid-seed-export. The connection needs
+    /// the export_private capability.
+    ExportSeed {
+        /// The keystore index of the keypair.
+        #[structopt(long)]
+        index: u32,
+
+        /// The output format: mnemonic (24 bip39 words) or hex.
+        #[structopt(
+            long,
+            default_value = "mnemonic",
+            possible_values = &["mnemonic", "hex"]
+        )]
+        format: lair_keystore::SeedFormat,
+
+        /// Export from this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Find the entries whose signing pub key or cert digest starts
+    /// with a prefix, e.g. a truncated key pasted from a log.
+    /// Prints the index and type of every match.
+    Find {
+        /// The prefix, base64 (standard or url safe) unless --hex.
+        /// At least 4 whole bytes, 6 base64 or 8 hex characters.
+        prefix: String,
+
+        /// Parse the prefix as hex.
+        #[structopt(long)]
+        hex: bool,
+
+        /// What to search: sign (pub keys), cert (digests) or any.
+        #[structopt(
+            long,
+            default_value = "any",
+            parse(try_from_str = lair_keystore::parse_prefix_kind)
+        )]
+        kind: lair_keystore_api::actor::PrefixKind,
+
+        /// Search this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Print the events of the running lair server as they happen, one
+    /// timestamped line each: entries created by any connection, and the
+    /// unlock requests, progress and completions of this connection.
+    /// Connects again whenever the server restarts. Runs until killed.
+    Watch {
+        /// Output each event as a json object.
+        #[structopt(long)]
+        json: bool,
+
+        /// Prompt for the passphrase the server asks for, on stdin,
+        /// instead of declining. The server asks each new connection
+        /// for the passphrase of the default store.
+        #[structopt(long)]
+        approve_prompts: bool,
+    },
+
+    /// Manage the unlock passphrase stored in the OS keychain.
+    #[cfg(feature = "keychain")]
+    Keychain(KeychainCmd),
+}
+
+#[derive(Debug)]
+enum PubKeyFormat {
+    Ssh,
+    Jwk,
+    B64,
+}
+
+impl std::str::FromStr for PubKeyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ssh" => Ok(Self::Ssh),
+            "jwk" => Ok(Self::Jwk),
+            "b64" => Ok(Self::B64),
+            _ => Err(format!("unknown pub key format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum StoresCmd {
+    /// List the named stores.
+    List,
+
+    /// Create a new, empty, named store. Its passphrase
+    /// is set by the first client to select it.
+    Create {
+        /// The store name: ascii alphanumerics, '-' or '_'.
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum QuarantineCmd {
+    /// List the quarantined entries, and why they failed to load.
+    List {
+        /// List for this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+
+    /// Write the raw record of each quarantined entry
+    /// to `<keystore_index>.entry` in a directory.
+    Export {
+        /// The output directory, created if missing.
+        out_dir: std::path::PathBuf,
+
+        /// Export from this named store, instead of the default store.
+        #[structopt(long)]
+        store_name: Option<String>,
+    },
+}
+
+#[cfg(feature = "keychain")]
+#[derive(Debug, StructOpt)]
+enum KeychainCmd {
+    /// Read the unlock passphrase from stdin and store it in the keychain.
+    Store,
+
+    /// Remove the unlock passphrase from the keychain.
+    Clear,
+}
+
+#[cfg(feature = "keychain")]
+fn exec_keychain(
+    opt: &Opt,
+    cmd: KeychainCmd,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::keychain::*;
+    use lair_keystore_api::passphrase_strength::*;
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = &opt.lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+    let keychain = OsKeychain::new_handle(&config.build());
+
+    match cmd {
+        KeychainCmd::Store => {
+            let mut passphrase = zeroize::Zeroizing::new(String::new());
+            std::io::stdin()
+                .read_line(&mut passphrase)
+                .map_err(lair_keystore_api::LairError::other)?;
+            let passphrase = passphrase.trim_end_matches(&['\r', '\n'][..]);
+            if passphrase.is_empty() {
+                return Err("refusing to store an empty passphrase".into());
+            }
+            let strength = estimate_passphrase_strength(passphrase);
+            println!("passphrase strength: {}", strength);
+            let min_score = opt
+                .min_passphrase_score
+                .unwrap_or(DEFAULT_MIN_PASSPHRASE_SCORE);
+            if strength.score < min_score
+                && !opt.allow_weak_passphrase
+                && std::env::var_os("LAIR_ALLOW_WEAK_PASSPHRASE").is_none()
+            {
+                return Err(lair_keystore_api::LairError::WeakPassphrase(
+                    format!(
+                        "{}, at least {} required, \
+                        pass --allow-weak-passphrase to store it anyway",
+                        strength, min_score,
+                    ),
+                ));
+            }
+            keychain.set_passphrase(passphrase)?;
+            println!("passphrase stored in keychain");
+        }
+        KeychainCmd::Clear => {
+            keychain.clear_passphrase()?;
+            println!("passphrase cleared from keychain");
+        }
+    }
+
+    Ok(())
+}
+
+fn exec_init(
+    lair_dir: Option<std::path::PathBuf>,
+    bind_to_device: bool,
+    import_device_secret: bool,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::device_secret::DEVICE_SECRET_LEN;
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+    // building the config creates the data directory
+    let config = config.build();
+    println!("lair directory: {}", config.get_root_path().display());
+
+    if bind_to_device {
+        let device_secret = if import_device_secret {
+            let mut encoded = String::new();
+            std::io::stdin()
+                .read_line(&mut encoded)
+                .map_err(lair_keystore_api::LairError::other)?;
+            let decoded = base64::decode(encoded.trim())
+                .map_err(lair_keystore_api::LairError::other)?;
+            if decoded.len() != DEVICE_SECRET_LEN {
+                return Err(format!(
+                    "invalid device secret, expected {} bytes",
+                    DEVICE_SECRET_LEN
+                )
+                .into());
+            }
+            let mut device_secret = [0; DEVICE_SECRET_LEN];
+            device_secret.copy_from_slice(&decoded);
+            Some(device_secret)
+        } else {
+            None
+        };
+        let reference = lair_keystore::device_binding::bind_to_device(
+            &config,
+            device_secret,
+        )?;
+        println!("bound to device secret: {}", reference);
+    }
+
+    Ok(())
+}
+
+/// Connect to the running server of `lair_dir` as the cli subcommands
+/// do, selecting the store `store`, if given. Keep the returned event
+/// receiver alive as long as the connection is used.
+async fn connect_admin(
+    lair_dir: Option<std::path::PathBuf>,
+    store: Option<String>,
+) -> lair_keystore_api::LairResult<(
+    lair_keystore_api::ipc::LairClientSender,
+    impl Drop,
+)> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+
+    // we never answer passphrase requests,
+    // but hold the receiver so they are not errors
+    let (api, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config.build()),
+    )
+    .await?;
+    if let Some(store) = store {
+        api.lair_select_store(store).await?;
+    }
+
+    Ok((api, evt_recv))
+}
+
+async fn exec_export_device_secret(
+    lair_dir: Option<std::path::PathBuf>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let (api, _evt_recv) = connect_admin(lair_dir, None).await?;
+
+    let device_secret = api.lair_export_device_secret().await?;
+    println!("{}", base64::encode(&*device_secret));
+
+    Ok(())
+}
+
+async fn exec_reload(
+    lair_dir: Option<std::path::PathBuf>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let (api, _evt_recv) = connect_admin(lair_dir, None).await?;
+
+    api.lair_reload_settings().await?;
+    println!("reloaded settings");
+
+    Ok(())
+}
+
+fn exec_stores(
+    lair_dir: Option<std::path::PathBuf>,
+    cmd: StoresCmd,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore::stores::*;
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+    let config = config.build();
+
+    match cmd {
+        StoresCmd::List => {
+            for store in list_stores(&config)? {
+                println!("{} {}", store.number, store.name);
+            }
+        }
+        StoresCmd::Create { name } => {
+            let store = create_store(&config, &name)?;
+            println!("created store {} {}", store.number, store.name);
+        }
+    }
+
+    Ok(())
+}
+
+async fn exec_migrate(
+    lair_dir: Option<std::path::PathBuf>,
+    encrypt_index: bool,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore::migrate::*;
+
+    if !encrypt_index {
+        return Err("nothing to migrate, pass `--encrypt-index`".into());
+    }
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+    let config = config.build();
+
+    // hold the pid file, so no server opens the stores meanwhile
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    drop(store_file);
+
+    let res = async {
+        let mut stores = vec![(
+            lair_keystore_api::actor::DEFAULT_STORE_NAME.to_string(),
+            config.clone(),
+        )];
+        for store in lair_keystore::stores::list_stores(&config)? {
+            stores.push((
+                store.name,
+                config.for_named_store(store.number, &store.path),
+            ));
+        }
+
+        for (name, config) in stores {
+            if !has_legacy_index(&config)? {
+                println!("store {}: up to date", name);
+                continue;
+            }
+            let passphrase = zeroize::Zeroizing::new(read_passphrase(
+                &format!("Enter the passphrase of store {}: ", name),
+            )?);
+            encrypt_legacy_index(config.clone(), passphrase.as_str().into())
+                .await
+                .map_err(|e| format!("store {}: {}", name, e))?;
+            println!(
+                "store {}: migrated, backed up to {}",
+                name,
+                backup_path(&config).display()
+            );
+        }
+        Ok(())
+    }
+    .await;
+
+    let _ = std::fs::remove_file(config.get_pid_path());
+    res
+}
+
+async fn exec_status(
+    lair_dir: Option<std::path::PathBuf>,
+    json: bool,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let store_name = store_name.unwrap_or_else(|| {
+        lair_keystore_api::actor::DEFAULT_STORE_NAME.to_string()
+    });
+    let (api, _evt_recv) =
+        connect_admin(lair_dir, Some(store_name.clone())).await?;
+
+    let info = api.lair_get_server_info().await?;
+    let last_entry_index = api.lair_get_last_entry_index().await?;
+    let entry_count = api.lair_get_entry_count().await?;
+    let stats = if info.wire_protocol_version
+        >= lair_keystore_api::internal::wire::spec::STORE_STATS_VERSION
+    {
+        Some(api.lair_get_store_stats().await?)
+    } else {
+        None
+    };
+    let paths = match api.lair_get_server_paths().await {
+        Ok(paths) => Some(paths),
+        Err(lair_keystore_api::LairError::Forbidden(_)) => None,
+        Err(e) => return Err(e),
+    };
+    // this connection included, unless it may not list them
+    let connections = match api.lair_list_connections().await {
+        Ok(connections) => Some(connections),
+        Err(lair_keystore_api::LairError::Forbidden(_)) => None,
+        Err(e) => return Err(e),
+    };
+
+    if json {
+        let paths = paths.map(|paths| {
+            serde_json::json!({
+                "root_path": paths.root_path.to_string_lossy(),
+                "store_path": paths.store_path.to_string_lossy(),
+                "connection_url": paths.connection_url.to_string(),
+            })
+        });
+        let status = serde_json::json!({
+            "name": info.name,
+            "version": info.version,
+            "store_name": store_name,
+            "last_entry_index": last_entry_index.map(|idx| idx.0),
+            "entry_count": entry_count,
+            "stats": stats.map(|stats| {
+                serde_json::json!({
+                    "file_size": stats.file_size,
+                    "entry_counts": stats
+                        .entry_counts
+                        .iter()
+                        .map(|(entry_type, count)| {
+                            (format!("{:?}", entry_type), (*count).into())
+                        })
+                        .collect::<serde_json::Map<_, _>>(),
+                    "erased_count": stats.erased_count,
+                    "quarantined_count": stats.quarantined_count,
+                    "trashed_count": stats.trashed_count,
+                })
+            }),
+            "paths": paths,
+            "connections": connections.map(|connections| {
+                connections
+                    .iter()
+                    .map(|con| {
+                        serde_json::json!({
+                            "connection_id": con.connection_id,
+                            "idle_ms": con.idle.as_millis() as u64,
+                            "in_flight": con.in_flight,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            }),
+        });
+        println!("{}", status);
+    } else {
+        println!("name: {}", info.name);
+        println!("version: {}", info.version);
+        println!("store name: {}", store_name);
+        match last_entry_index {
+            Some(idx) => println!("last entry index: {}", idx.0),
+            None => println!("last entry index: none"),
+        }
+        println!("entry count: {}", entry_count);
+        match stats {
+            Some(stats) => {
+                println!("store file size: {}", stats.file_size);
+                for (entry_type, count) in stats.entry_counts.iter() {
+                    println!("{:?} entries: {}", entry_type, count);
+                }
+                println!("erased entries: {}", stats.erased_count);
+                println!("quarantined entries: {}", stats.quarantined_count);
+                println!("trashed entries: {}", stats.trashed_count);
+            }
+            None => println!("store stats: unsupported by the server"),
+        }
+        match paths {
+            Some(paths) => {
+                println!("root path: {}", paths.root_path.display());
+                println!("store path: {}", paths.store_path.display());
+                println!("connection url: {}", paths.connection_url);
+            }
+            None => println!("paths: hidden"),
+        }
+        match connections {
+            Some(connections) => {
+                println!("connections: {}", connections.len());
+                for con in connections {
+                    println!(
+                        "connection {}: idle {:.3}s, {} in flight",
+                        con.connection_id,
+                        con.idle.as_secs_f64(),
+                        con.in_flight,
+                    );
+                }
+            }
+            None => println!("connections: hidden"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn exec_check(
+    lair_dir: Option<std::path::PathBuf>,
+    dedupe: bool,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use futures::stream::StreamExt;
+    use lair_keystore_api::actor::*;
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let entries = lair_list_entries_stream(api, MAX_LIST_ENTRIES_PAGE_LIMIT)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<lair_keystore_api::LairResult<Vec<_>>>()?;
+    println!("entries: {}", entries.len());
+    println!(
+        "trashed entries: {}",
+        entries.iter().filter(|info| info.trashed).count()
+    );
+    println!(
+        "entries not activated: {}",
+        entries.iter().filter(|info| !info.activated).count()
+    );
+
+    if dedupe {
+        let duplicates = find_duplicate_entries(&entries);
+        for group in duplicates.iter() {
+            println!(
+                "duplicate entries: {} (lookups use {})",
+                group
+                    .iter()
+                    .map(|idx| idx.0.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                group[0].0,
+            );
+        }
+        if !duplicates.is_empty() {
+            return Err(format!(
+                "found {} sets of duplicate entries",
+                duplicates.len()
+            )
+            .into());
+        }
+        println!("no duplicate entries");
+    }
+
+    Ok(())
+}
+
+async fn exec_self_test() -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::internal::self_test::*;
+
+    let mut failed = Vec::new();
+    for (primitive, res) in self_test_report().await {
+        match res {
+            Ok(()) => println!("{}: ok", primitive),
+            Err(err) => {
+                println!("{}: FAILED - {}", primitive, err);
+                failed.push(primitive);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!(
+            "crypto self-test failed for {}",
+            failed.join(", ")
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+async fn exec_quarantine(
+    lair_dir: Option<std::path::PathBuf>,
+    cmd: QuarantineCmd,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+    use lair_keystore_api::internal::util::*;
+
+    let store_name = match &cmd {
+        QuarantineCmd::List { store_name } => store_name.clone(),
+        QuarantineCmd::Export { store_name, .. } => store_name.clone(),
+    };
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let quarantined = api.lair_list_quarantined().await?;
+    match cmd {
+        QuarantineCmd::List { .. } => {
+            for entry in quarantined.iter() {
+                println!("{} {}", entry.keystore_index.0, entry.reason);
+            }
+            println!("quarantined entries: {}", quarantined.len());
+        }
+        QuarantineCmd::Export { out_dir, .. } => {
+            // the records may hold secrets, keep them private
+            create_dir_all_private(&out_dir)
+                .map_err(lair_keystore_api::LairError::other)?;
+            for entry in quarantined.iter() {
+                let path =
+                    out_dir.join(format!("{}.entry", entry.keystore_index.0));
+                // restrict the file before anything is written to it
+                let mut file = std::fs::File::create(&path)
+                    .map_err(lair_keystore_api::LairError::other)?;
+                set_private_permissions(&path)
+                    .map_err(lair_keystore_api::LairError::other)?;
+                std::io::Write::write_all(&mut file, &entry.data)
+                    .map_err(lair_keystore_api::LairError::other)?;
+                println!("exported {}", path.display());
+            }
+            println!("exported entries: {}", quarantined.len());
+        }
+    }
+
+    Ok(())
+}
+
+async fn exec_bench(
+    lair_dir: Option<std::path::PathBuf>,
+    duration_ms: u64,
+    json: bool,
+    against_running_server: bool,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore::bench::*;
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    // keep the temporary server, or the receiver, alive until done
+    let mut _server = None;
+    let mut _evt_recv = None;
+    let (api, unlock_latency) = if against_running_server {
+        let (api, evt_recv) = connect_admin(lair_dir, None).await?;
+        _evt_recv = Some(evt_recv);
+        if api.lair_get_server_info().await?.is_locked {
+            return Err(
+                "unlock the running server before benchmarking it".into()
+            );
+        }
+        (api, None)
+    } else {
+        let server = spawn_bench_server(Default::default()).await?;
+        let res = (server.api.clone(), Some(server.unlock_latency));
+        _server = Some(server);
+        res
+    };
+
+    let fixture = BenchFixture::new(&api).await?;
+    let duration = std::time::Duration::from_millis(duration_ms);
+    let mut results = Vec::new();
+    for op in BenchOp::all() {
+        results.push(run_bench(&api, &fixture, op, duration).await?);
+    }
+
+    if json {
+        let results = results
+            .iter()
+            .map(|res| {
+                serde_json::json!({
+                    "op": res.op.name(),
+                    "ops": res.ops,
+                    "elapsed_ms": res.elapsed.as_secs_f64() * 1000.0,
+                    "ops_per_sec": res.ops_per_sec(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let bench = serde_json::json!({
+            "unlock_ms": unlock_latency
+                .map(|latency| latency.as_secs_f64() * 1000.0),
+            "results": results,
+        });
+        println!("{}", bench);
+    } else {
+        if let Some(latency) = unlock_latency {
+            println!("unlock: {:.1} ms", latency.as_secs_f64() * 1000.0);
+        }
+        println!("{:<24}{:>12}{:>16}", "op", "ops", "ops/sec");
+        for res in results.iter() {
+            println!(
+                "{:<24}{:>12}{:>16.1}",
+                res.op.name(),
+                res.ops,
+                res.ops_per_sec()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn exec_health(
+    lair_dir: Option<std::path::PathBuf>,
+    timeout_ms: u64,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::health::*;
+
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+
+    let report = check_health(
+        config.build(),
+        std::time::Duration::from_millis(timeout_ms),
+    )
+    .await;
+    println!("status: {}", report.status);
+    for reason in report.reasons.iter() {
+        println!("reason: {}", reason);
+    }
+    std::process::exit(report.status.exit_code());
+}
+
+/// Turns off terminal echo on stdin until dropped.
+#[cfg(unix)]
+struct EchoOff(libc::termios);
+
+#[cfg(unix)]
+impl EchoOff {
+    /// None if stdin is not a terminal.
+    fn new() -> Option<Self> {
+        // safe: the termios is only used after tcgetattr filled it
+        unsafe {
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::isatty(0) != 1 || libc::tcgetattr(0, &mut term) != 0 {
+                return None;
+            }
+            let orig = term;
+            term.c_lflag &= !libc::ECHO;
+            if libc::tcsetattr(0, libc::TCSANOW, &term) != 0 {
+                return None;
+            }
+            Some(Self(orig))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(0, libc::TCSANOW, &self.0);
+        }
+        // the newline typed was not echoed either
+        eprintln!();
+    }
+}
+
+/// Prompt on stderr, then read a line from stdin,
+/// not echoing it if stdin is a terminal.
+fn read_passphrase(prompt: &str) -> lair_keystore_api::LairResult<String> {
+    use std::io::Write;
+
+    eprint!("{}", prompt);
+    let _ = std::io::stderr().flush();
+    #[cfg(unix)]
+    let echo_off = EchoOff::new();
+    let mut passphrase = String::new();
+    let res = std::io::stdin().read_line(&mut passphrase);
+    #[cfg(unix)]
+    drop(echo_off);
+    res.map_err(lair_keystore_api::LairError::other)?;
+    passphrase.truncate(passphrase.trim_end_matches(&['\r', '\n'][..]).len());
+    Ok(passphrase)
+}
+
+async fn exec_import_ssh_key(
+    lair_dir: Option<std::path::PathBuf>,
+    path: std::path::PathBuf,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+    use lair_keystore_api::internal::ssh_key::*;
+
+    let key = std::fs::read(&path).map_err(|e| {
+        format!("cannot read ssh key {}: {}", path.display(), e)
+    })?;
+    let key = std::sync::Arc::new(key);
+    let passphrase = if ssh_key_is_encrypted(&key)? {
+        Some(read_passphrase(&format!(
+            "Enter passphrase for {}: ",
+            path.display()
+        ))?)
+    } else {
+        None
+    };
+
+    // read it here too, to fail early on a wrong passphrase,
+    // and for the comment
+    let comment =
+        match ssh_key_read_ed25519(key.clone(), passphrase.clone()).await {
+            Ok(read) => read.comment,
+            Err(lair_keystore_api::LairError::AuthenticationFailed) => {
+                return Err("incorrect ssh key passphrase".into())
+            }
+            Err(e) => return Err(e),
+        };
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let (keystore_index, pub_key, already_existed) =
+        api.sign_ed25519_import_ssh_key(key, passphrase).await?;
+
+    if already_existed {
+        println!("already imported");
+    }
+    println!("keystore index: {}", keystore_index.0);
+    println!("pub key: {}", base64::encode(&*pub_key.0));
+    println!(
+        "ssh pub key: {}",
+        ssh_ed25519_pub_key_line(&pub_key, &comment)
+    );
+
+    Ok(())
+}
+
+async fn exec_export_pubkey(
+    lair_dir: Option<std::path::PathBuf>,
+    keystore_index: u32,
+    format: PubKeyFormat,
+    comment: String,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let pub_key = api.sign_ed25519_get(keystore_index.into()).await?;
+    match format {
+        PubKeyFormat::Ssh => {
+            println!("{}", pub_key.to_ssh_public_key(&comment))
+        }
+        PubKeyFormat::Jwk => println!("{}", pub_key.to_jwk()),
+        PubKeyFormat::B64 => println!("{}", base64::encode(&*pub_key.0)),
+    }
+
+    Ok(())
+}
+
+async fn exec_import_seed(
+    lair_dir: Option<std::path::PathBuf>,
+    mnemonic: bool,
+    hex: Option<String>,
+    file: Option<std::path::PathBuf>,
+    tag: Option<String>,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore::SeedFormat;
+    use lair_keystore_api::actor::*;
+
+    let seed = match (mnemonic, hex, file) {
+        (true, None, None) => {
+            let text = zeroize::Zeroizing::new(read_passphrase(
+                "Enter the 24 word seed mnemonic: ",
+            )?);
+            lair_keystore::parse_seed(&text, Some(SeedFormat::Mnemonic))?
+        }
+        (false, Some(hex), None) => {
+            let hex = zeroize::Zeroizing::new(hex);
+            if hex.as_str() == "-" {
+                let text = zeroize::Zeroizing::new(read_passphrase(
+                    "Enter the 64 character hex seed: ",
+                )?);
+                lair_keystore::parse_seed(&text, Some(SeedFormat::Hex))?
+            } else {
+                lair_keystore::parse_seed(&hex, Some(SeedFormat::Hex))?
+            }
+        }
+        (false, None, Some(path)) => {
+            let text = zeroize::Zeroizing::new(
+                std::fs::read_to_string(&path).map_err(|e| {
+                    format!("cannot read seed {}: {}", path.display(), e)
+                })?,
+            );
+            lair_keystore::parse_seed(&text, None)?
+        }
+        _ => {
+            return Err("give exactly one of --mnemonic, --hex or --file".into())
+        }
+    };
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let mut options = SignKeyOptions::default();
+    options.tag = tag;
+    let (keystore_index, pub_key, already_existed) = api
+        .sign_ed25519_new_from_seed_with_options(*seed, options)
+        .await?;
+
+    if already_existed {
+        println!("already imported");
+    }
+    println!("keystore index: {}", keystore_index.0);
+    println!("pub key: {}", base64::encode(&*pub_key.0));
+
+    Ok(())
+}
+
+async fn exec_export_seed(
+    lair_dir: Option<std::path::PathBuf>,
+    keystore_index: u32,
+    format: lair_keystore::SeedFormat,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+    use std::io::Write;
+
+    let confirm = format!("export seed {}", keystore_index);
+    eprint!(
+        "The seed is the private key, anyone reading it holds the key.\n\
+        Type \"{}\" to print it: ",
+        confirm
+    );
+    let _ = std::io::stderr().flush();
+    let mut typed = String::new();
+    std::io::stdin()
+        .read_line(&mut typed)
+        .map_err(lair_keystore_api::LairError::other)?;
+    if typed.trim() != confirm {
+        return Err("not confirmed, the seed was not exported".into());
+    }
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let seed = zeroize::Zeroizing::new(
+        api.sign_ed25519_export_seed(keystore_index.into()).await?,
+    );
+    println!("{}", *lair_keystore::format_seed(&seed, format)?);
+
+    Ok(())
+}
+
+async fn exec_find(
+    lair_dir: Option<std::path::PathBuf>,
+    prefix: String,
+    hex: bool,
+    kind: lair_keystore_api::actor::PrefixKind,
+    store_name: Option<String>,
+) -> lair_keystore_api::LairResult<()> {
+    use lair_keystore_api::actor::LairClientApiSender;
+
+    let prefix = lair_keystore::parse_entry_prefix(&prefix, hex)?;
+    lair_keystore_api::actor::check_entry_prefix(&prefix)?;
+
+    let (api, _evt_recv) = connect_admin(lair_dir, store_name).await?;
+
+    let entries = api
+        .lair_find_entries_by_prefix(std::sync::Arc::new(prefix), kind)
+        .await?;
+    if entries.is_empty() {
+        return Err("no entries match".into());
+    }
+    for (keystore_index, entry_type) in entries {
+        println!("{} {:?}", keystore_index.0, entry_type);
+    }
+
+    Ok(())
+}
+
+async fn exec_watch(
+    lair_dir: Option<std::path::PathBuf>,
+    json: bool,
+    approve_prompts: bool,
+) -> lair_keystore_api::LairResult<()> {
+    let mut config = lair_keystore_api::Config::builder();
+    if let Some(lair_dir) = lair_dir {
+        config = config.set_root_path(lair_dir);
+    }
+
+    let prompt = if approve_prompts {
+        let prompt: lair_keystore::watch::PassphrasePrompt =
+            std::sync::Arc::new(|store_name: String| {
+                let passphrase = zeroize::Zeroizing::new(read_passphrase(
+                    &format!("Enter passphrase for store {}: ", store_name),
+                )?);
+                Ok(passphrase.as_str().into())
+            });
+        Some(prompt)
+    } else {
+        None
+    };
+
+    lair_keystore::watch::watch(config.build(), json, prompt, |line| {
+        println!("{}", line)
+    })
+    .await
 }
 
 /// main entry point
@@ -27,17 +1341,242 @@ pub async fn main() -> lair_keystore_api::LairResult<()> {
     );
     trace!("tracing initialized");
 
-    let opt = Opt::from_args();
+    let mut opt = Opt::from_args();
 
     if opt.version {
         println!("lair-keystore {}", lair_keystore::LAIR_VER);
         return Ok(());
     }
 
+    match opt.cmd.take() {
+        Some(Cmd::Init {
+            bind_to_device,
+            import_device_secret,
+        }) => {
+            return exec_init(
+                opt.lair_dir,
+                bind_to_device,
+                import_device_secret,
+            );
+        }
+        Some(Cmd::ExportDeviceSecret) => {
+            return exec_export_device_secret(opt.lair_dir).await;
+        }
+        Some(Cmd::Reload) => {
+            return exec_reload(opt.lair_dir).await;
+        }
+        Some(Cmd::Status { json, store_name }) => {
+            return exec_status(opt.lair_dir, json, store_name).await;
+        }
+        Some(Cmd::Stores(cmd)) => {
+            return exec_stores(opt.lair_dir, cmd);
+        }
+        Some(Cmd::Migrate { encrypt_index }) => {
+            return exec_migrate(opt.lair_dir, encrypt_index).await;
+        }
+        Some(Cmd::Check { dedupe, store_name }) => {
+            return exec_check(opt.lair_dir, dedupe, store_name).await;
+        }
+        Some(Cmd::SelfTest) => {
+            return exec_self_test().await;
+        }
+        Some(Cmd::Quarantine(cmd)) => {
+            return exec_quarantine(opt.lair_dir, cmd).await;
+        }
+        Some(Cmd::Bench {
+            duration_ms,
+            json,
+            against_running_server,
+        }) => {
+            return exec_bench(
+                opt.lair_dir,
+                duration_ms,
+                json,
+                against_running_server,
+            )
+            .await;
+        }
+        Some(Cmd::Health { timeout_ms }) => {
+            return exec_health(opt.lair_dir, timeout_ms).await;
+        }
+        Some(Cmd::ImportSshKey { path, store_name }) => {
+            return exec_import_ssh_key(opt.lair_dir, path, store_name).await;
+        }
+        Some(Cmd::ExportPubkey {
+            keystore_index,
+            format,
+            comment,
+            store_name,
+        }) => {
+            return exec_export_pubkey(
+                opt.lair_dir,
+                keystore_index,
+                format,
+                comment,
+                store_name,
+            )
+            .await;
+        }
+        Some(Cmd::ImportSeed {
+            mnemonic,
+            hex,
+            file,
+            tag,
+            store_name,
+        }) => {
+            return exec_import_seed(
+                opt.lair_dir,
+                mnemonic,
+                hex,
+                file,
+                tag,
+                store_name,
+            )
+            .await;
+        }
+        Some(Cmd::ExportSeed {
+            index,
+            format,
+            store_name,
+        }) => {
+            return exec_export_seed(opt.lair_dir, index, format, store_name)
+                .await;
+        }
+        Some(Cmd::Find {
+            prefix,
+            hex,
+            kind,
+            store_name,
+        }) => {
+            return exec_find(opt.lair_dir, prefix, hex, kind, store_name)
+                .await;
+        }
+        Some(Cmd::Watch {
+            json,
+            approve_prompts,
+        }) => {
+            return exec_watch(opt.lair_dir, json, approve_prompts).await;
+        }
+        #[cfg(feature = "keychain")]
+        Some(Cmd::Keychain(cmd)) => {
+            return exec_keychain(&opt, cmd);
+        }
+        None => (),
+    }
+
     if let Some(lair_dir) = opt.lair_dir {
         std::env::set_var("LAIR_DIR", lair_dir);
     }
 
+    if !opt.listen.is_empty() {
+        std::env::set_var(
+            "LAIR_EXTRA_LISTENERS",
+            opt.listen
+                .iter()
+                .map(|url| url.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+    }
+
+    if !opt.listener_capabilities.is_empty() {
+        std::env::set_var(
+            "LAIR_LISTENER_CAPABILITIES",
+            opt.listener_capabilities
+                .iter()
+                .map(|(url, capabilities)| format!("{}={}", url, capabilities))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+    }
+
+    if opt.hide_paths {
+        std::env::set_var("LAIR_HIDE_PATHS", "1");
+    }
+
+    if opt.wire_compression {
+        std::env::set_var("LAIR_WIRE_COMPRESSION", "1");
+    }
+
+    if let Some(elevation) = opt.elevation {
+        std::env::set_var(
+            "LAIR_ELEVATION",
+            format!(
+                "{}:{}",
+                elevation.capabilities,
+                elevation.max_duration.as_secs()
+            ),
+        );
+    }
+
+    if let Some(score) = opt.min_passphrase_score {
+        std::env::set_var("LAIR_MIN_PASSPHRASE_SCORE", score.to_string());
+    }
+
+    if let Some(max) = opt.max_connections {
+        std::env::set_var("LAIR_MAX_CONNECTIONS", max.to_string());
+    }
+
+    if let Some(mode) = opt.socket_mode {
+        std::env::set_var("LAIR_SOCKET_MODE", format!("{:o}", mode));
+    }
+
+    if let Some(timeout) = opt.idle_connection_timeout {
+        std::env::set_var(
+            "LAIR_IDLE_CONNECTION_TIMEOUT_SECS",
+            timeout.as_secs().to_string(),
+        );
+    }
+
+    if let Some(settings_file) = opt.settings_file {
+        std::env::set_var("LAIR_SETTINGS_FILE", settings_file);
+    }
+
+    if let Some(queue) = opt.queue_while_locked {
+        std::env::set_var(
+            "LAIR_QUEUE_WHILE_LOCKED",
+            format!("{}:{}", queue.max_len, queue.max_wait.as_secs()),
+        );
+    }
+
+    if opt.allow_weak_passphrase {
+        std::env::set_var("LAIR_ALLOW_WEAK_PASSPHRASE", "1");
+    }
+
+    if opt.danger_allow_insecure_permissions {
+        std::env::set_var("LAIR_DANGER_ALLOW_INSECURE_PERMISSIONS", "1");
+    }
+
+    if opt.strict_load {
+        std::env::set_var("LAIR_STRICT_LOAD", "1");
+    }
+
+    if opt.forbid_seed_export {
+        std::env::set_var("LAIR_FORBID_SEED_EXPORT", "1");
+    }
+
+    if opt.auto_activate_imports {
+        std::env::set_var("LAIR_AUTO_ACTIVATE_IMPORTS", "1");
+    }
+
+    if opt.auto_migrate {
+        std::env::set_var("LAIR_AUTO_MIGRATE", "1");
+    }
+
+    if let Some(policy) = opt.clock_skew_policy {
+        std::env::set_var(
+            "LAIR_CLOCK_SKEW_POLICY",
+            match policy {
+                lair_keystore_api::ClockSkewPolicy::FailClosed => "fail-closed",
+                lair_keystore_api::ClockSkewPolicy::FailOpen => "fail-open",
+            },
+        );
+    }
+
+    if let Some(ssh_agent_socket) = opt.ssh_agent_socket {
+        std::env::set_var("LAIR_SSH_AGENT_SOCKET", ssh_agent_socket);
+    }
+
     trace!("executing lair main tasks");
     lair_keystore::execute_lair().await?;
 
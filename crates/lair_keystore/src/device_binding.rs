@@ -0,0 +1,165 @@
+//! Binding a lair directory to this device, see
+//! `lair_keystore_api::device_secret`.
+//!
+//! The device secret is only mixed into the unlock key of stores whose
+//! passphrase is set after binding, so a directory is only bound while
+//! none of its stores has a passphrase, i.e. right after `init`.
+//! Binding a directory copied from another device to the secret
+//! exported there is always allowed.
+
+use crate::*;
+use lair_keystore_api::device_secret::*;
+use lair_keystore_api::internal::unlock::UnlockEntry;
+
+/// Bind the lair directory of `config` to this device, see
+/// `device_secret::bind_to_device`. Fails if the passphrase of any store
+/// is already set, unless importing the secret of a directory bound
+/// on another device. Returns the reference to the secret.
+pub fn bind_to_device(
+    config: &Config,
+    device_secret: Option<DeviceSecret>,
+) -> LairResult<String> {
+    if get_device_binding(config)?.is_none() {
+        let mut store_paths = vec![config.get_store_path().to_path_buf()];
+        for store in stores::list_stores(config)? {
+            store_paths.push(store.path.join("store"));
+        }
+        for store_path in store_paths {
+            if has_passphrase(&store_path)? {
+                return Err(format!(
+                    "the passphrase of {} is already set, \
+                    only new lair directories can be bound to a device",
+                    store_path.display(),
+                )
+                .into());
+            }
+        }
+    }
+    device_secret::bind_to_device(config, device_secret)
+}
+
+/// True if the store file at `store_path` has its passphrase set.
+fn has_passphrase(store_path: &std::path::Path) -> LairResult<bool> {
+    use std::io::Read;
+
+    let mut store_file = match std::fs::File::open(store_path) {
+        Ok(store_file) => store_file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(LairError::other(e)),
+    };
+    let mut unlock_entry = vec![0; entry::ENTRY_SIZE];
+    match store_file.read_exact(&mut unlock_entry) {
+        Ok(()) => Ok(UnlockEntry::decode(&unlock_entry)?.is_some()),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(LairError::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream::StreamExt;
+    use store::EntryStoreSender;
+
+    async fn open(
+        config: Arc<Config>,
+    ) -> ghost_actor::GhostSender<store::EntryStore> {
+        let store_file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(config.get_store_path())
+            .await
+            .unwrap();
+        store::spawn_entry_store_actor(config, store_file)
+            .await
+            .unwrap()
+    }
+
+    async fn unlock(
+        store: &ghost_actor::GhostSender<store::EntryStore>,
+        passphrase: &str,
+    ) -> LairResult<()> {
+        let (progress_send, progress_recv) =
+            futures::channel::mpsc::unbounded();
+        let res = store.unlock(passphrase.into(), progress_send).await;
+        let _ = progress_recv.collect::<Vec<_>>().await;
+        res
+    }
+
+    fn builder(
+        root: &std::path::Path,
+        device: &DeviceSecretStoreHandle,
+    ) -> ConfigBuilder {
+        Config::builder()
+            .set_root_path(root)
+            .set_allow_weak_passphrase(true)
+            .set_unlock_kdf_limits(
+                lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                    mem_limit_kib: 8,
+                    ops_limit: 1,
+                },
+            )
+            .set_device_secret_store(device.clone())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_binds_stores_to_the_device() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let device = MockDeviceSecretStore::new_handle();
+        let config = builder(tmpdir.path(), &device).build();
+
+        bind_to_device(&config, None).unwrap();
+        let device_secret = load_device_secret(&config).unwrap().unwrap();
+
+        let store = open(config.clone()).await;
+        unlock(&store, "passphrase").await.unwrap();
+        drop(store);
+
+        // too late to bind anything else
+        let other_tmpdir = tempfile::tempdir().unwrap();
+        let other_config = builder(other_tmpdir.path(), &device).build();
+        let store = open(other_config.clone()).await;
+        unlock(&store, "passphrase").await.unwrap();
+        drop(store);
+        assert!(bind_to_device(&other_config, None).is_err());
+
+        // the passphrase alone unlocks nothing on another device
+        let other_device = MockDeviceSecretStore::new_handle();
+        let copy_config = builder(tmpdir.path(), &other_device).build();
+        let store = open(copy_config.clone()).await;
+        assert!(matches!(
+            unlock(&store, "passphrase").await,
+            Err(LairError::DeviceSecretMissing(_)),
+        ));
+
+        // even with the binding removed
+        std::fs::remove_file(copy_config.get_device_binding_path()).unwrap();
+        assert!(matches!(
+            unlock(&store, "passphrase").await,
+            Err(LairError::DeviceSecretMissing(_)),
+        ));
+        drop(store);
+
+        // or bound to another secret
+        device_secret::bind_to_device(
+            &copy_config,
+            Some([0; DEVICE_SECRET_LEN]),
+        )
+        .unwrap();
+        let store = open(copy_config.clone()).await;
+        assert!(matches!(
+            unlock(&store, "passphrase").await,
+            Err(LairError::AuthenticationFailed),
+        ));
+        drop(store);
+
+        // the exported secret moves it
+        let moved_device = MockDeviceSecretStore::new_handle();
+        let moved_config = builder(tmpdir.path(), &moved_device).build();
+        bind_to_device(&moved_config, Some(device_secret)).unwrap();
+        let store = open(moved_config).await;
+        unlock(&store, "passphrase").await.unwrap();
+    }
+}
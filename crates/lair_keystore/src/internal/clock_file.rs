@@ -0,0 +1,111 @@
+//! Persisting the last wall clock time the server saw, so a clock set
+//! back while the server was down is detected as skew at startup,
+//! see `lair_keystore_api::internal::clock`.
+
+use crate::*;
+use lair_keystore_api::internal::clock::CLOCK_CHECK_INTERVAL;
+use std::convert::TryFrom;
+
+/// Load the last wall clock time persisted at `Config::get_clock_path`,
+/// in unix time seconds, if any.
+pub async fn load_last_seen(config: &Config) -> LairResult<Option<u64>> {
+    let path = config.get_clock_path();
+    match store::store_file::load_file(path).await? {
+        None => Ok(None),
+        Some(data) => match <[u8; 8]>::try_from(data.as_slice()) {
+            Ok(data) => Ok(Some(u64::from_le_bytes(data))),
+            Err(_) => Err(format!(
+                "invalid last seen time in {}, {} bytes",
+                path.display(),
+                data.len()
+            )
+            .into()),
+        },
+    }
+}
+
+/// Persist `last_seen`, in unix time seconds, at `Config::get_clock_path`.
+pub async fn write_last_seen(
+    config: &Config,
+    last_seen: u64,
+) -> LairResult<()> {
+    store::store_file::write_file(
+        config.get_clock_path(),
+        last_seen.to_le_bytes().to_vec(),
+    )
+    .await
+}
+
+/// Check the clock against the persisted last wall clock time, then
+/// spawn a task checking it every `CLOCK_CHECK_INTERVAL`, persisting
+/// the last wall clock time each time. An unreadable record is
+/// replaced, with a warning.
+pub async fn spawn_clock_check(config: Arc<Config>) -> LairResult<()> {
+    let monitor = config.get_clock_monitor().clone();
+    match load_last_seen(&config).await {
+        Ok(None) => (),
+        Ok(Some(last_seen)) => {
+            monitor.observe_last_seen(last_seen);
+        }
+        Err(err) => tracing::warn!(?err, "ignoring the last seen time"),
+    }
+    write_last_seen(&config, monitor.last_seen_secs()).await?;
+
+    tokio::task::spawn(async move {
+        loop {
+            tokio::time::delay_for(CLOCK_CHECK_INTERVAL).await;
+            monitor.check();
+            if let Err(err) =
+                write_last_seen(&config, monitor.last_seen_secs()).await
+            {
+                tracing::warn!(?err, "failed to persist the last seen time");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lair_keystore_api::internal::clock::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_detects_clocks_set_back_across_restarts() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let clock = ManualClock::new(1_600_000_000);
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_clock(clock.handle())
+            .build();
+        assert_eq!(None, load_last_seen(&config).await.unwrap());
+        spawn_clock_check(config.clone()).await.unwrap();
+        assert_eq!(Some(1_600_000_000), load_last_seen(&config).await.unwrap());
+        assert_eq!(None, config.get_clock_monitor().check());
+
+        // restarted, with the clock set back a day
+        clock.set_wall(1_600_000_000 - 86400);
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_clock(clock.handle())
+            .build();
+        let mut skews = config.get_clock_monitor().subscribe();
+        spawn_clock_check(config.clone()).await.unwrap();
+        assert_eq!(
+            Some(ClockSkew {
+                wall_secs: 1_600_000_000 - 86400,
+                expected_secs: 1_600_000_000,
+            }),
+            config.get_clock_monitor().check()
+        );
+        assert!(skews.try_recv().is_ok());
+        // the expected time is kept, not the skewed one
+        assert_eq!(Some(1_600_000_000), load_last_seen(&config).await.unwrap());
+
+        // a corrupt record is replaced
+        std::fs::write(config.get_clock_path(), b"bad").unwrap();
+        assert!(load_last_seen(&config).await.is_err());
+        spawn_clock_check(config.clone()).await.unwrap();
+        assert!(load_last_seen(&config).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,176 @@
+//! Refuse to run on top of files other users could tamper with.
+
+use crate::*;
+use std::path::Path;
+
+/// Verify the lair root directory, and every file lair keeps in it,
+/// are owned by the current user, and are not writable by group / other.
+/// Files that do not exist yet are skipped.
+/// With `danger_set_allow_insecure_permissions`,
+/// problems are only logged as warnings.
+pub fn check_permissions(config: &Config) -> LairResult<()> {
+    match check_permissions_inner(config) {
+        Err(e) if config.get_danger_allow_insecure_permissions() => {
+            tracing::warn!("ignoring insecure permissions: {}", e);
+            Ok(())
+        }
+        res => res,
+    }
+}
+
+fn check_permissions_inner(config: &Config) -> LairResult<()> {
+    check_dir(config.get_root_path())?;
+    for path in &[
+        config.get_store_path(),
+        config.get_tags_path(),
+        config.get_trash_path(),
+        config.get_server_id_path(),
+        config.get_device_binding_path(),
+        config.get_pid_path(),
+        config.get_socket_path(),
+        config.get_connection_url_path(),
+    ] {
+        check_path(path)?;
+    }
+    for path in &[
+        config.get_tags_path(),
+        config.get_trash_path(),
+        config.get_inactive_path(),
+    ] {
+        check_path(&store::store_file::sealed_path(path))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn check_dir(path: &Path) -> LairResult<()> {
+    let mode = check_path(path)?;
+    if mode & 0o077 != 0 {
+        tracing::warn!("{} has mode {:04o}, prefer 0700", path.display(), mode);
+    }
+    Ok(())
+}
+
+/// Returns the permission bits of `path`, or 0 if it does not exist.
+#[cfg(unix)]
+fn check_path(path: &Path) -> LairResult<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(LairError::other(e)),
+    };
+    let mode = meta.mode() & 0o7777;
+
+    // safe: geteuid has no preconditions and cannot fail
+    let euid = unsafe { libc::geteuid() };
+    if meta.uid() != euid {
+        return Err(format!(
+            "insecure permissions: {} is owned by uid {}, not the current user (uid {})",
+            path.display(),
+            meta.uid(),
+            euid,
+        )
+        .into());
+    }
+
+    if mode & 0o022 != 0 {
+        return Err(format!(
+            "insecure permissions: {} has mode {:04o}, writable by group / other",
+            path.display(),
+            mode,
+        )
+        .into());
+    }
+
+    Ok(mode)
+}
+
+// the windows ipc server is not yet implemented (see win_ipc.rs),
+// so owner-only DACL checks are deferred until it is.
+#[cfg(not(unix))]
+fn check_dir(_path: &Path) -> LairResult<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_path(_path: &Path) -> LairResult<u32> {
+    Ok(0)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn chmod(path: &Path, mode: u32) {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .unwrap();
+    }
+
+    fn config(root: &Path, allow: bool) -> Arc<Config> {
+        Config::builder()
+            .set_root_path(root)
+            .danger_set_allow_insecure_permissions(allow)
+            .build()
+    }
+
+    #[test]
+    fn it_creates_a_private_root() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("lair");
+        let config = config(&root, false);
+        let mode = std::fs::metadata(config.get_root_path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o700, mode & 0o777);
+        check_permissions(&config).unwrap();
+    }
+
+    #[test]
+    fn it_refuses_writable_dirs_and_files() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = config(tmpdir.path(), false);
+
+        // readable is tolerated, writable is not
+        chmod(config.get_root_path(), 0o755);
+        check_permissions(&config).unwrap();
+
+        chmod(config.get_root_path(), 0o775);
+        let err = check_permissions(&config).unwrap_err().to_string();
+        assert!(err.contains(&config.get_root_path().display().to_string()));
+        assert!(err.contains("0775"));
+        chmod(config.get_root_path(), 0o700);
+
+        for path in &[
+            config.get_store_path(),
+            config.get_server_id_path(),
+            config.get_device_binding_path(),
+            config.get_connection_url_path(),
+        ] {
+            std::fs::write(path, b"").unwrap();
+            chmod(path, 0o644);
+            check_permissions(&config).unwrap();
+
+            chmod(path, 0o602);
+            let err = check_permissions(&config).unwrap_err().to_string();
+            assert!(err.contains(&path.display().to_string()));
+            assert!(err.contains("0602"));
+            chmod(path, 0o600);
+        }
+
+        check_permissions(&config).unwrap();
+    }
+
+    #[test]
+    fn it_can_be_overridden() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = config(tmpdir.path(), true);
+        chmod(config.get_root_path(), 0o777);
+        std::fs::write(config.get_store_path(), b"").unwrap();
+        chmod(config.get_store_path(), 0o666);
+        check_permissions(&config).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 //! Utilities for dealing with pid files.
 
 use crate::*;
+use lair_keystore_api::internal::util;
 use std::{
     io::{Read, Write},
     str::FromStr,
@@ -27,7 +28,7 @@ pub fn pid_check(config: &Config) -> LairResult<PidCheckResult> {
         if i != 0 {
             std::thread::sleep(std::time::Duration::from_millis(10));
         }
-        match pid_check_write(&config, &mut sys) {
+        match pid_check_write(config, &mut sys) {
             Ok(_) => {
                 last_err = None;
                 break;
@@ -42,9 +43,10 @@ pub fn pid_check(config: &Config) -> LairResult<PidCheckResult> {
         return Err(e);
     }
 
-    let mut store_file = std::fs::OpenOptions::new();
+    let mut store_file = util::private_open_options();
     let store_file = store_file
-        .append(true)
+        // not append, the unlock entry is rewritten in place
+        .write(true)
         .read(true)
         .create(true)
         .open(config.get_store_path())
@@ -60,7 +62,7 @@ fn pid_check_write(
     config: &Config,
     sys: &mut sysinfo::System,
 ) -> LairResult<()> {
-    std::fs::create_dir_all(config.get_root_path())
+    util::create_dir_all_private(config.get_root_path())
         .map_err(LairError::other)?;
 
     {
@@ -95,7 +97,7 @@ fn pid_check_write(
         }
     }
 
-    let mut write_pid = std::fs::OpenOptions::new();
+    let mut write_pid = util::private_open_options();
     let mut write_pid = write_pid
         .write(true)
         .create_new(true)
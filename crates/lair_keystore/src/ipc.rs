@@ -5,329 +5,3039 @@ use crate::store::EntryStoreSender;
 use crate::*;
 use futures::{future::FutureExt, stream::StreamExt};
 use lair_keystore_api::{actor::*, internal::*};
+use std::convert::TryInto;
 
 /// Spawn a new IPC server binding to serve out the Lair client api.
 pub async fn spawn_bind_server_ipc(
     config: Arc<Config>,
     store_file: tokio::fs::File,
 ) -> LairResult<()> {
+    internal::clock_file::spawn_clock_check(config.clone()).await?;
+
     let store_actor =
         store::spawn_entry_store_actor(config.clone(), store_file).await?;
+    let stores =
+        stores::StoreRegistry::new(config.clone(), store_actor).await?;
 
-    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
-
-    let api_sender = builder
-        .channel_factory()
-        .create_channel::<LairClientApi>()
-        .await?;
-
-    let i_s = builder
-        .channel_factory()
-        .create_channel::<InternalApi>()
-        .await?;
-
-    let mut con_recv = lair_keystore_api::ipc::spawn_bind_server_ipc(
-        config.clone(),
-        api_sender,
+    let con_config = config.clone();
+    let ssh_agent_config = config.clone();
+    let in_proc = lair_keystore_api::ipc::spawn_bind_server_ipc_per_connection(
+        config,
+        move |evt_send| {
+            // every connection starts on the default store
+            spawn_unlock(
+                DEFAULT_STORE_NAME.to_string(),
+                evt_send.clone(),
+                stores.default_store(),
+            );
+            let con = Internal::new(
+                con_config.clone(),
+                stores.clone(),
+                Some(evt_send),
+            );
+            async move { Ok(spawn_con_api(con)) }.boxed()
+        },
     )
     .await?;
 
+    #[cfg(not(windows))]
+    crate::ssh_agent::spawn_bind_ssh_agent(ssh_agent_config, in_proc).await?;
+    #[cfg(windows)]
+    let _ = (ssh_agent_config, in_proc);
+
+    Ok(())
+}
+
+/// Serve a single client connection.
+/// This is not an actor, so the task ends with the connection,
+/// and a store selection completes before the next request is handled.
+fn spawn_con_api(
+    mut con: Internal,
+) -> futures::channel::mpsc::Sender<LairClientApi> {
+    let (send, mut recv) = futures::channel::mpsc::channel(10);
     tokio::task::spawn(async move {
-        while let Some(con) = con_recv.next().await {
-            i_s.incoming_con(con).await?;
+        while let Some(msg) = recv.next().await {
+            match msg {
+                LairClientApi::LairSelectStore { respond, name, .. } => {
+                    let res = con.select_store(name).await;
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                msg => ghost_actor::GhostHandler::ghost_actor_dispatch(
+                    &mut con, msg,
+                ),
+            }
         }
-        LairResult::<()>::Ok(())
     });
+    send
+}
+
+/// The hardware token a hardware backed entry is used with.
+/// The entry outlives its token if lair is restarted without one.
+fn require_hardware_token(
+    hardware_token: Option<hardware::HardwareTokenHandle>,
+) -> LairResult<hardware::HardwareTokenHandle> {
+    hardware_token.ok_or_else(|| {
+        "entry is held in a hardware token, but none is configured".into()
+    })
+}
+
+/// Sign `message` with the ed25519 keypair entry at `keystore_index`,
+/// software or hardware backed, returning the pub key that signed
+/// alongside the signature.
+async fn sign_ed25519_entry(
+    keystore_index: KeystoreIndex,
+    entry: Arc<LairEntry>,
+    hardware_token: Option<hardware::HardwareTokenHandle>,
+    message: Arc<Vec<u8>>,
+) -> LairResult<(SignEd25519PubKey, SignEd25519Signature)> {
+    match &*entry {
+        LairEntry::SignEd25519(entry) => {
+            let signature =
+                sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
+                    .await?;
+            Ok((entry.pub_key.clone(), signature))
+        }
+        LairEntry::SignEd25519Hardware(entry) => {
+            let signature = entry
+                .sign(require_hardware_token(hardware_token)?, message)
+                .await?;
+            Ok((entry.pub_key.clone(), signature))
+        }
+        entry => {
+            Err(entry.wrong_type(keystore_index, LairEntryType::SignEd25519))
+        }
+    }
+}
 
-    tokio::task::spawn(
-        builder.spawn(Internal::new(config.clone(), store_actor)?),
-    );
+/// Error with `TagInUse` if the tag of new keypair `options` is taken.
+async fn check_tag_free(
+    store_actor: &ghost_actor::GhostSender<store::EntryStore>,
+    options: &SignKeyOptions,
+) -> LairResult<()> {
+    if let Some(tag) = &options.tag {
+        if store_actor.get_entry_by_tag(tag.clone()).await.is_ok() {
+            return Err(LairError::TagInUse(tag.clone()));
+        }
+    }
+    Ok(())
+}
 
+/// Check the policy of an ed25519 keypair entry before signing with it.
+/// An expired keypair errors with `KeyExpired`, a keypair requiring
+/// approval errors with `Forbidden` unless the client approves it.
+/// While the clock is skewed, expiry is not checked, and keypairs with
+/// one error with `Forbidden` unless `ClockSkewPolicy::FailOpen`.
+async fn check_sign_policy(
+    config: &Config,
+    keystore_index: KeystoreIndex,
+    entry: &LairEntry,
+    evt_send: Option<futures::channel::mpsc::Sender<LairClientEvent>>,
+) -> LairResult<()> {
+    let entry = match entry {
+        LairEntry::SignEd25519(entry) => entry,
+        _ => return Ok(()),
+    };
+    if entry.policy.expires_at.is_some() {
+        match config.get_clock_monitor().check() {
+            None => {
+                entry.check_not_expired(config.get_clock().unix_now_secs())?
+            }
+            Some(skew) => match config.get_clock_skew_policy() {
+                ClockSkewPolicy::FailClosed => {
+                    return Err(LairError::Forbidden(format!(
+                        "KeystoreIndex {} may have expired, \
+                        the clock is skewed {} seconds behind",
+                        keystore_index,
+                        skew.expected_secs - skew.wall_secs,
+                    )));
+                }
+                ClockSkewPolicy::FailOpen => tracing::warn!(
+                    ?skew,
+                    %keystore_index,
+                    "the clock is skewed, signing without checking expiry"
+                ),
+            },
+        }
+    }
+    if !entry.policy.requires_approval {
+        return Ok(());
+    }
+    // connections not subscribed to the event fail it right away
+    let approved = match evt_send {
+        Some(evt_send) => evt_send
+            .request_sign_approval(keystore_index, entry.pub_key.clone())
+            .await
+            .unwrap_or(false),
+        None => false,
+    };
+    if !approved {
+        return Err(LairError::Forbidden(format!(
+            "signing with KeystoreIndex {} was not approved",
+            keystore_index
+        )));
+    }
     Ok(())
 }
 
-ghost_actor::ghost_chan! {
-    chan InternalApi<LairError> {
-        fn incoming_con(evt_send: futures::channel::mpsc::Sender<LairClientEvent>) -> ();
+/// Request the passphrase of `store_name` from a client, and unlock
+/// the store with it, forwarding progress and completion to the client.
+fn spawn_unlock(
+    store_name: String,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    store_actor: ghost_actor::GhostSender<store::EntryStore>,
+) {
+    tokio::task::spawn(async move {
+        let passphrase = evt_send.request_unlock_passphrase(store_name).await?;
+
+        let (progress_send, mut progress_recv) =
+            futures::channel::mpsc::unbounded();
+        let progress_evt_send = evt_send.clone();
+        let forward = tokio::task::spawn(async move {
+            while let Some(percent) = progress_recv.next().await {
+                // progress is best effort
+                let _ = progress_evt_send.unlock_progress(percent).await;
+            }
+        });
+
+        let res = store_actor.unlock(passphrase, progress_send).await;
+
+        // the store drops the progress sender once the derivation
+        // is done, drain it so no progress follows the completion
+        let _ = forward.await;
+        evt_send
+            .unlock_complete(res.err().map(|e| e.to_string()))
+            .await
+    });
+}
+
+/// Send a connection an `entry_created` event for every entry created
+/// in any store, an `entry_activated` event for every entry activated,
+/// an `entry_removed` event for every entry trashed, purged or erased,
+/// and a `clock_skew_detected` event for every clock skew detected,
+/// until dropped. The ipc server drops them unless the client subscribed
+/// to `EventKind::EntryCreated` / `EntryActivated` / `EntryRemoved` /
+/// `ClockSkewDetected`.
+struct ServerEvents(futures::future::AbortHandle);
+
+impl ServerEvents {
+    fn spawn(
+        mut new_entries: store::NewEntryReceiver,
+        mut activated_entries: tokio::sync::broadcast::Receiver<KeystoreIndex>,
+        mut removed_entries: store::RemovedEntryReceiver,
+        mut clock_skews: tokio::sync::broadcast::Receiver<clock::ClockSkew>,
+        evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    ) -> Self {
+        use tokio::sync::broadcast::RecvError;
+
+        let skew_evt_send = evt_send.clone();
+        let activated_evt_send = evt_send.clone();
+        let removed_evt_send = evt_send.clone();
+        let tracked =
+            debug_stats::Tracked::new(debug_stats::Counter::ConnectionTask);
+        let (task, abort) = futures::future::abortable(async move {
+            let _tracked = tracked;
+            let new_entries = async move {
+                loop {
+                    match new_entries.recv().await {
+                        Ok((keystore_index, entry_type)) => {
+                            // informational, the client need not answer
+                            let _ = evt_send
+                                .entry_created(keystore_index, entry_type)
+                                .await;
+                        }
+                        // fell behind, carry on from the oldest kept
+                        Err(RecvError::Lagged(_)) => (),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            };
+            let activated_entries = async move {
+                loop {
+                    match activated_entries.recv().await {
+                        Ok(keystore_index) => {
+                            let _ = activated_evt_send
+                                .entry_activated(keystore_index)
+                                .await;
+                        }
+                        Err(RecvError::Lagged(_)) => (),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            };
+            let removed_entries = async move {
+                loop {
+                    match removed_entries.recv().await {
+                        Ok(keystore_index) => {
+                            let _ = removed_evt_send
+                                .entry_removed(keystore_index)
+                                .await;
+                        }
+                        Err(RecvError::Lagged(_)) => (),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            };
+            let clock_skews = async move {
+                loop {
+                    match clock_skews.recv().await {
+                        Ok(skew) => {
+                            let _ = skew_evt_send
+                                .clock_skew_detected(
+                                    skew.wall_secs,
+                                    skew.expected_secs,
+                                )
+                                .await;
+                        }
+                        Err(RecvError::Lagged(_)) => (),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            };
+            futures::future::join4(
+                new_entries,
+                activated_entries,
+                removed_entries,
+                clock_skews,
+            )
+            .await;
+        });
+        tokio::task::spawn(task);
+        Self(abort)
+    }
+}
+
+impl Drop for ServerEvents {
+    fn drop(&mut self) {
+        self.0.abort();
     }
 }
 
 struct Internal {
-    #[allow(dead_code)]
+    config: Arc<Config>,
+    stores: Arc<stores::StoreRegistry>,
+    // the store selected by this connection
     store_actor: ghost_actor::GhostSender<store::EntryStore>,
+    evt_send: Option<futures::channel::mpsc::Sender<LairClientEvent>>,
+    _server_events: Option<ServerEvents>,
+    // dropped, with the keys, once the connection closes
+    ephemeral: ephemeral::EphemeralKeys,
+    _tracked: debug_stats::Tracked,
 }
 
 impl Internal {
     pub fn new(
-        _config: Arc<Config>,
-        store_actor: ghost_actor::GhostSender<store::EntryStore>,
-    ) -> LairResult<Self> {
-        Ok(Internal { store_actor })
+        config: Arc<Config>,
+        stores: Arc<stores::StoreRegistry>,
+        evt_send: Option<futures::channel::mpsc::Sender<LairClientEvent>>,
+    ) -> Self {
+        let store_actor = stores.default_store();
+        let server_events = evt_send.as_ref().map(|evt_send| {
+            ServerEvents::spawn(
+                stores.subscribe_new_entries(),
+                stores.subscribe_activated_entries(),
+                stores.subscribe_removed_entries(),
+                config.get_clock_monitor().subscribe(),
+                evt_send.clone(),
+            )
+        });
+        let ephemeral =
+            ephemeral::EphemeralKeys::new(config.get_max_ephemeral_keys());
+        Internal {
+            config,
+            stores,
+            store_actor,
+            evt_send,
+            _server_events: server_events,
+            ephemeral,
+            _tracked: debug_stats::Tracked::new(
+                debug_stats::Counter::ConnectionApi,
+            ),
+        }
+    }
+
+    /// Run `fut` once the selected store is unlocked, if the
+    /// server parks requests using secret material while locked,
+    /// see `Config::get_queue_while_locked`.
+    fn when_unlocked<T: 'static + Send>(
+        &self,
+        fut: impl std::future::Future<Output = LairResult<T>> + 'static + Send,
+    ) -> LairClientApiHandlerResult<T> {
+        let turn_fut = self.store_actor.wait_unlocked();
+        Ok(async move {
+            let _turn = turn_fut.await?;
+            fut.await
+        }
+        .boxed()
+        .into())
+    }
+
+    async fn select_store(&mut self, name: String) -> LairResult<()> {
+        let store_actor = self.stores.get(&name).await?;
+        self.store_actor = store_actor.clone();
+        if let Some(evt_send) = &self.evt_send {
+            if store_actor.is_locked().await? {
+                spawn_unlock(name, evt_send.clone(), store_actor);
+            }
+        }
+        Ok(())
     }
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
 
-impl ghost_actor::GhostHandler<InternalApi> for Internal {}
-
-impl InternalApiHandler for Internal {
-    fn handle_incoming_con(
-        &mut self,
-        evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
-    ) -> InternalApiHandlerResult<()> {
-        tokio::task::spawn(async move {
-            let _passphrase = evt_send.request_unlock_passphrase().await;
+impl ghost_actor::GhostHandler<LairClientApi> for Internal {
+    fn ghost_actor_dispatch(&mut self, d: LairClientApi) {
+        // a panicking handler fails its request, not the connection
+        util::catch_dispatch_panic("lair-api", || {
+            ghost_actor::GhostDispatch::ghost_actor_dispatch(d, self)
         });
-        Ok(async move { Ok(()) }.boxed().into())
     }
 }
 
-impl ghost_actor::GhostHandler<LairClientApi> for Internal {}
-
 impl lair_keystore_api::actor::LairClientApiHandler for Internal {
+    fn handle_lair_select_store(
+        &mut self,
+        _name: String,
+    ) -> LairClientApiHandlerResult<()> {
+        // connections select stores in `spawn_con_api`,
+        // the ssh-agent has no way to ask for one
+        Err(LairError::Forbidden(
+            "store selection needs a client connection".into(),
+        ))
+    }
+
     fn handle_lair_get_server_info(
         &mut self,
     ) -> LairClientApiHandlerResult<LairServerInfo> {
-        let mut out = LairServerInfo::default();
-        out.name = "lair-keystore".to_string();
-        out.version = crate::LAIR_VER.to_string();
+        let is_locked_fut = self.store_actor.is_locked();
+        let server_id_fut = self.store_actor.get_server_id();
+        let entropy_health = self.config.get_entropy_health().clone();
+        Ok(async move {
+            let mut out = LairServerInfo::default();
+            out.name = "lair-keystore".to_string();
+            out.version = crate::LAIR_VER.to_string();
+            out.is_locked = is_locked_fut.await?;
+            out.entropy_status = entropy_health.status();
+            out.id_pub_key = match server_id_fut.await {
+                Ok(server_id) => server_id.pub_key,
+                // even the identity is hidden while deep locked
+                Err(LairError::KeystoreLocked) => Default::default(),
+                Err(e) => return Err(e),
+            };
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_server_paths(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairServerPaths> {
+        let res = if self.config.get_hide_paths() {
+            Err(LairError::Forbidden("server paths are hidden".into()))
+        } else {
+            Ok(LairServerPaths {
+                root_path: self.config.get_root_path().to_path_buf(),
+                store_path: self.config.get_store_path().to_path_buf(),
+                connection_url: self.config.get_connection_url().clone(),
+            })
+        };
+        let fut = self.store_actor.check_deep_lock();
+
+        Ok(async move {
+            fut.await?;
+            res
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_export_device_secret(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let is_locked_fut = self.store_actor.is_locked();
+        let config = self.config.clone();
+        self.when_unlocked(async move {
+            // proves the passphrase, not just access to the socket
+            if is_locked_fut.await? {
+                return Err(LairError::KeystoreLocked);
+            }
+            match device_secret::load_device_secret(&config)? {
+                Some(device_secret) => Ok(Arc::new(device_secret.to_vec())),
+                None => Err("lair is not bound to a device".into()),
+            }
+        })
+    }
+
+    fn handle_lair_get_capabilities(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairCapabilities> {
+        // connections are limited by the ipc server, ahead of the api
+        Ok(async move { Ok(LairCapabilities::ALL) }.boxed().into())
+    }
+
+    fn handle_lair_request_elevation(
+        &mut self,
+        _capabilities: LairCapabilities,
+        _duration: std::time::Duration,
+    ) -> LairClientApiHandlerResult<LairElevation> {
+        // connections are elevated by the ipc server, ahead of the api
+        Ok(async move {
+            Err(LairError::Forbidden(
+                "in-process keystores grant every capability".to_string(),
+            ))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_elevation(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Option<LairElevation>> {
+        Ok(async move { Ok(None) }.boxed().into())
+    }
+
+    fn handle_lair_drop_elevation(&mut self) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_list_connections(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<LairConnectionInfo>> {
+        // connections are tracked by the ipc server, ahead of the api
+        Ok(async move { Ok(Vec::new()) }.boxed().into())
+    }
 
-        Ok(async move { Ok(out) }.boxed().into())
+    fn handle_lair_reload_settings(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        let config = self.config.clone();
+        Ok(
+            async move { crate::settings::reload_settings(&config).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_lair_set_event_filter(
+        &mut self,
+        _kinds: Vec<EventKind>,
+    ) -> LairClientApiHandlerResult<()> {
+        // events are filtered by the ipc server, ahead of the api
+        Ok(async move { Ok(()) }.boxed().into())
     }
 
     fn handle_lair_get_last_entry_index(
         &mut self,
-    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex>> {
         Ok(self.store_actor.get_last_entry_index().boxed().into())
     }
 
-    fn handle_lair_get_entry_type(
+    fn handle_lair_get_last_entry_index_64(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex64>> {
+        let fut = self.store_actor.get_last_entry_index();
+        Ok(async move { Ok(fut.await?.map(KeystoreIndex64::from)) }
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_get_entry_count(
+        &mut self,
+    ) -> LairClientApiHandlerResult<u64> {
+        Ok(self.store_actor.get_entry_count().boxed().into())
+    }
+
+    fn handle_lair_get_store_stats(
+        &mut self,
+    ) -> LairClientApiHandlerResult<StoreStats> {
+        Ok(self.store_actor.get_store_stats().boxed().into())
+    }
+
+    fn handle_lair_list_entries_page(
+        &mut self,
+        start: KeystoreIndex,
+        limit: u32,
+    ) -> LairClientApiHandlerResult<(Vec<LairEntryInfo>, Option<KeystoreIndex>)>
+    {
+        Ok(self
+            .store_actor
+            .list_entries_page(start, limit)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_get_entry_type(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            match fut.await {
+                Err(LairError::KeystoreLocked) => {
+                    Err(LairError::KeystoreLocked)
+                }
+                Err(LairError::WrongStore(reason)) => {
+                    Err(LairError::WrongStore(reason))
+                }
+                Err(LairError::EntryTrashed(reason)) => {
+                    Err(LairError::EntryTrashed(reason))
+                }
+                Err(LairError::EntryNotActivated(reason)) => {
+                    Err(LairError::EntryNotActivated(reason))
+                }
+                Err(_) => Ok(LairEntryType::Invalid),
+                Ok(entry) => Ok(entry.entry_type()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    /// Stores hold no entries above `u32::MAX`, wider indexes
+    /// fail with `LairError::IndexOutOfRange`.
+    fn handle_lair_get_entry_type_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
+        self.handle_lair_get_entry_type(keystore_index.try_into()?)
+    }
+
+    fn handle_lair_get_entry_types(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        Ok(self.store_actor.get_entry_types(indices).boxed().into())
+    }
+
+    fn handle_lair_set_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: Option<String>,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self
+            .store_actor
+            .set_entry_tag(keystore_index, tag)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_add_entry_alias(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self
+            .store_actor
+            .add_entry_alias(keystore_index, alias)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_remove_entry_alias(
+        &mut self,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self.store_actor.remove_entry_alias(alias).boxed().into())
+    }
+
+    fn handle_lair_trash_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self.store_actor.trash_entry(keystore_index).boxed().into())
+    }
+
+    fn handle_lair_restore_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self
+            .store_actor
+            .restore_entry(keystore_index)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_purge_trashed(
+        &mut self,
+        older_than: std::time::Duration,
+    ) -> LairClientApiHandlerResult<u64> {
+        Ok(self.store_actor.purge_trashed(older_than).boxed().into())
+    }
+
+    fn handle_lair_erase_entries(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        Ok(self.store_actor.erase_entries(indices).boxed().into())
+    }
+
+    fn handle_lair_set_entry_tags(
+        &mut self,
+        tags: Vec<(KeystoreIndex, String)>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        Ok(self.store_actor.set_entry_tags(tags).boxed().into())
+    }
+
+    fn handle_lair_activate_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.store_actor.activate_entry(keystore_index);
+        let stores = self.stores.clone();
+        Ok(async move {
+            if fut.await? {
+                stores.entry_activated(keystore_index);
+            }
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_list_quarantined(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<QuarantinedEntry>> {
+        Ok(self.store_actor.list_quarantined().boxed().into())
+    }
+
+    fn handle_lair_self_test(&mut self) -> LairClientApiHandlerResult<()> {
+        // touches no entries, so health checks work while deep locked
+        Ok(self_test::self_test().boxed().into())
+    }
+
+    fn handle_lair_find_entries_by_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+        kind: PrefixKind,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        Ok(self
+            .store_actor
+            .find_entries_by_prefix(prefix, kind)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let fut = self.store_actor.get_entry_by_tag(tag);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(_) => {
+                    Ok((keystore_index, LairEntryType::TlsCert))
+                }
+                LairEntry::SignEd25519(_)
+                | LairEntry::SignEd25519Hardware(_) => {
+                    Ok((keystore_index, LairEntryType::SignEd25519))
+                }
+                LairEntry::X25519(_) => {
+                    Ok((keystore_index, LairEntryType::X25519))
+                }
+                LairEntry::PwPepper(_) => {
+                    Ok((keystore_index, LairEntryType::PwPepper))
+                }
+                LairEntry::SignSecp256k1(_) => {
+                    Ok((keystore_index, LairEntryType::SignSecp256k1))
+                }
+                #[cfg(feature = "ed448")]
+                LairEntry::SignEd448(_) => {
+                    Ok((keystore_index, LairEntryType::SignEd448))
+                }
+                LairEntry::SignBls(_) => {
+                    Ok((keystore_index, LairEntryType::SignBls))
+                }
+                LairEntry::SignSr25519(_) => {
+                    Ok((keystore_index, LairEntryType::SignSr25519))
+                }
+                LairEntry::TotpSecret(_) => {
+                    Ok((keystore_index, LairEntryType::TotpSecret))
+                }
+                _ => Err(format!("unhandled entry type {:?}", entry).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_attest_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        challenge: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Attestation> {
+        let server_id_fut = self.store_actor.get_server_id();
+        let entry_fut = self.store_actor.get_entry_by_index(keystore_index);
+        let clock = self.config.get_clock().clone();
+        self.when_unlocked(async move {
+            let server_id = server_id_fut.await?;
+            let entry = entry_fut.await?;
+            let timestamp = clock.unix_now_secs();
+            attest::attest_entry(&server_id, &entry, challenge, timestamp).await
+        })
+    }
+
+    fn handle_lair_prove_server_identity(
+        &mut self,
+        nonce: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<ServerIdentityProof> {
+        let server_id_fut = self.store_actor.get_server_id();
+        Ok(async move {
+            let server_id = server_id_fut.await?;
+            attest::prove_server_identity(
+                &server_id,
+                crate::LAIR_VER.to_string(),
+                nonce,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_wrap_entry(
+        &mut self,
+        target_index: KeystoreIndex,
+        recipient_pub_key: X25519PubKey,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        self.when_unlocked(
+            self.store_actor.wrap_entry(target_index, recipient_pub_key),
+        )
+    }
+
+    fn handle_lair_unwrap_entry(
+        &mut self,
+        recipient_index: KeystoreIndex,
+        wrapped: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType, bool)> {
+        let fut = self.store_actor.unwrap_entry(recipient_index, wrapped);
+        self.when_unlocked(async move {
+            let (keystore_index, entry, already_existed) = fut.await?;
+            Ok((
+                keystore_index,
+                entry.to_info(keystore_index).entry_type,
+                already_existed,
+            ))
+        })
+    }
+
+    fn handle_tls_cert_new_self_signed_from_entropy(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self
+            .store_actor
+            .tls_cert_self_signed_new_from_entropy(options);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    keystore_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                )),
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_tls_cert_new_self_signed_from_sign_key(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self
+            .store_actor
+            .tls_cert_self_signed_new_from_sign_key(keystore_index, options);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    keystore_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                )),
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_tls_cert_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => {
+                    Ok((entry.sni.clone(), entry.cert_digest.clone()))
+                }
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_info(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, TlsCertAlg)> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                    entry.alg(),
+                )),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_digest(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest_alg: DigestAlg,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        Ok(self
+            .store_actor
+            .get_cert_digest(keystore_index, digest_alg)
+            .boxed()
+            .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_typed_digest(
+        &mut self,
+        digest_alg: DigestAlg,
+        digest: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self
+            .store_actor
+            .get_entry_by_cert_digest(digest_alg, digest);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_resolve_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0.clone());
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(_) => Ok(keystore_index),
+                _ => Err(LairError::EntryNotFound(format!(
+                    "no tls cert with digest {:?}",
+                    cert_digest
+                ))),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_resolve_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.get_entry_by_sni(cert_sni.clone());
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(_) => Ok(keystore_index),
+                _ => Err(LairError::EntryNotFound(format!(
+                    "no tls cert with sni {:?}",
+                    cert_sni
+                ))),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        })
+    }
+
+    fn handle_tls_cert_get_priv_key_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        })
+    }
+
+    fn handle_tls_cert_get_priv_key_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        })
+    }
+
+    fn handle_tls_cert_get_bundle_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, Cert, CertPrivKey)>
+    {
+        let fut = self.store_actor.get_entry_by_tag(tag);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                    entry.cert_der.clone(),
+                    entry.priv_key_der.clone(),
+                )),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TlsCert))
+                }
+            }
+        })
+    }
+
+    fn handle_sign_ed25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        let fut = self.store_actor.sign_ed25519_keypair_new_from_entropy();
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_sign_ed25519_new(
+        &mut self,
+        backing: KeyBacking,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        let fut = match backing {
+            KeyBacking::Software => {
+                return self.handle_sign_ed25519_new_from_entropy()
+            }
+            KeyBacking::Hardware => {
+                self.store_actor.sign_ed25519_hardware_keypair_new()
+            }
+            _ => {
+                return Err(
+                    format!("unhandled key backing {:?}", backing).into()
+                )
+            }
+        };
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519Hardware(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_with_options(
+        &mut self,
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        if let Some(tag) = &options.tag {
+            entry::check_entry_tag(tag)?;
+        }
+        let store_actor = self.store_actor.clone();
+        self.when_unlocked(async move {
+            check_tag_free(&store_actor, &options).await?;
+            let (keystore_index, entry) = store_actor
+                .sign_ed25519_keypair_new_with_policy((&options).into())
+                .await?;
+            let pub_key = match &*entry {
+                LairEntry::SignEd25519(entry) => entry.pub_key.clone(),
+                _ => return Err("invalid entry type".into()),
+            };
+            if let Some(tag) = options.tag {
+                store_actor.set_entry_tag(keystore_index, Some(tag)).await?;
+            }
+            Ok((keystore_index, pub_key))
+        })
+    }
+
+    fn handle_sign_ed25519_new_from_seed_with_options(
+        &mut self,
+        seed: [u8; 32],
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        if let Some(tag) = &options.tag {
+            entry::check_entry_tag(tag)?;
+        }
+        let store_actor = self.store_actor.clone();
+        self.when_unlocked(async move {
+            check_tag_free(&store_actor, &options).await?;
+            let mut keypair =
+                sign_ed25519::sign_ed25519_keypair_new_from_seed(seed).await?;
+            keypair.policy = (&options).into();
+            let pub_key = keypair.pub_key.clone();
+            let (keystore_index, _, already_existed) =
+                store_actor.sign_ed25519_keypair_import(keypair).await?;
+            // an existing entry keeps its options and tags
+            if !already_existed {
+                if let Some(tag) = options.tag {
+                    store_actor
+                        .set_entry_tag(keystore_index, Some(tag))
+                        .await?;
+                }
+            }
+            Ok((keystore_index, pub_key, already_existed))
+        })
+    }
+
+    fn handle_sign_ed25519_get_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignKeyOptions> {
+        let entry_fut = self.store_actor.get_entry_by_index(keystore_index);
+        let tag_fut = self.store_actor.get_entry_tag(keystore_index);
+        Ok(async move {
+            let entry = entry_fut.await?;
+            let policy = match &*entry {
+                LairEntry::SignEd25519(entry) => entry.policy,
+                LairEntry::SignEd25519Hardware(_) => {
+                    entry::SignKeyPolicy::default()
+                }
+                entry => {
+                    return Err(entry.wrong_type(
+                        keystore_index,
+                        LairEntryType::SignEd25519,
+                    ))
+                }
+            };
+            Ok(policy.to_options(tag_fut.await?))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_rotate(
+        &mut self,
+        old_keystore_index: KeystoreIndex,
+        options: RotateKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, RotationRecord)> {
+        if let Some(tag) = &options.key_options.tag {
+            entry::check_entry_tag(tag)?;
+        }
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let store_actor = self.store_actor.clone();
+        self.when_unlocked(async move {
+            let old =
+                store_actor.get_entry_by_index(old_keystore_index).await?;
+            check_sign_policy(&config, old_keystore_index, &old, evt_send)
+                .await?;
+            check_tag_free(&store_actor, &options.key_options).await?;
+            let old_pub_key = match &*old {
+                LairEntry::SignEd25519(entry) => entry.pub_key.clone(),
+                LairEntry::SignEd25519Hardware(entry) => entry.pub_key.clone(),
+                entry => {
+                    return Err(entry.wrong_type(
+                        old_keystore_index,
+                        LairEntryType::SignEd25519,
+                    ))
+                }
+            };
+
+            let mut policy = entry::SignKeyPolicy::from(&options.key_options);
+            policy.rotated_from = Some(old_keystore_index);
+            let (keystore_index, new) = store_actor
+                .sign_ed25519_keypair_new_with_policy(policy)
+                .await?;
+            let new = match &*new {
+                LairEntry::SignEd25519(entry) => entry.clone(),
+                _ => return Err("invalid entry type".into()),
+            };
+
+            let mut record = rotate::unsigned_rotation_record(
+                old_pub_key,
+                new.pub_key,
+                config.get_clock().unix_now_secs(),
+            );
+            let signed = Arc::new(record.signed_bytes());
+            record.old_signature = sign_ed25519_entry(
+                old_keystore_index,
+                old,
+                hardware_token,
+                signed.clone(),
+            )
+            .await?
+            .1;
+            record.new_signature =
+                sign_ed25519::sign_ed25519(new.priv_key, signed).await?;
+
+            if let Some(tag) = options.key_options.tag {
+                store_actor.set_entry_tag(keystore_index, Some(tag)).await?;
+            }
+            if options.trash_old_key {
+                store_actor.trash_entry(old_keystore_index).await?;
+            }
+            Ok((keystore_index, record))
+        })
+    }
+
+    fn handle_sign_ed25519_export_seed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<[u8; 32]> {
+        if self.config.get_forbid_seed_export() {
+            return Err(LairError::ExportForbidden(
+                "this server is configured to forbid seed export".into(),
+            ));
+        }
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            if entry.entry_type() != LairEntryType::SignEd25519 {
+                return Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519));
+            }
+            sign_ed25519::sign_ed25519_export_seed(&entry)
+        })
+    }
+
+    fn handle_sign_ed25519_import_ssh_key(
+        &mut self,
+        key: Arc<Vec<u8>>,
+        passphrase: Option<String>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        let store_actor = self.store_actor.clone();
+        self.when_unlocked(async move {
+            let key = ssh_key::ssh_key_read_ed25519(key, passphrase).await?;
+            let pub_key = key.keypair.pub_key.clone();
+            let (keystore_index, _, already_existed) =
+                store_actor.sign_ed25519_keypair_import(key.keypair).await?;
+            // an unusable comment leaves the entry untagged,
+            // an existing entry keeps its tags
+            if !already_existed && entry::check_entry_tag(&key.comment).is_ok()
+            {
+                store_actor
+                    .set_entry_tag(keystore_index, Some(key.comment))
+                    .await?;
+            }
+            Ok((keystore_index, pub_key, already_existed))
+        })
+    }
+
+    fn handle_sign_ed25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignEd25519PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => Ok(entry.pub_key.clone()),
+                LairEntry::SignEd25519Hardware(entry) => {
+                    Ok(entry.pub_key.clone())
+                }
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_resolve_by_pub_key(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0.clone());
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(_)
+                | LairEntry::SignEd25519Hardware(_) => Ok(keystore_index),
+                _ => Err(LairError::EntryNotFound(format!(
+                    "no ed25519 keypair with pub key {:?}",
+                    pub_key
+                ))),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
+                        .await
+                }
+                LairEntry::SignEd25519Hardware(entry) => {
+                    entry
+                        .sign(require_hardware_token(hardware_token)?, message)
+                        .await
+                }
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519)),
+            }
+        })
+    }
+
+    /// As `lair_get_entry_type_64`, wider indexes fail.
+    fn handle_sign_ed25519_sign_by_index_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        self.handle_sign_ed25519_sign_by_index(
+            keystore_index.try_into()?,
+            message,
+        )
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
+                        .await
+                }
+                LairEntry::SignEd25519Hardware(entry) => {
+                    entry
+                        .sign(require_hardware_token(hardware_token)?, message)
+                        .await
+                }
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519)),
+            }
+        })
+    }
+
+    fn handle_sign_ed25519_sign_by_index_v2(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            sign_ed25519_entry(keystore_index, entry, hardware_token, message)
+                .await
+        })
+    }
+
+    fn handle_sign_ed25519_sign_by_index_with_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        options: SignOptions,
+    ) -> LairClientApiHandlerResult<(SignEd25519Signature, Option<u64>)> {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            // timestamped once approved, as it signs
+            let timestamp = match options.include_timestamp {
+                true => Some(config.get_clock().unix_now_millis()),
+                false => None,
+            };
+            let message = match timestamp {
+                Some(timestamp) => {
+                    Arc::new(timestamped_message(timestamp, &message))
+                }
+                None => message,
+            };
+            let (_, signature) = sign_ed25519_entry(
+                keystore_index,
+                entry,
+                hardware_token,
+                message,
+            )
+            .await?;
+            Ok((signature, timestamp))
+        })
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key_v2(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            sign_ed25519_entry(keystore_index, entry, hardware_token, message)
+                .await
+        })
+    }
+
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let hardware_token = self.config.get_hardware_token().cloned();
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_tag(tag);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            sign_ed25519_entry(keystore_index, entry, hardware_token, message)
+                .await
+        })
+    }
+
+    fn handle_sign_ed25519_ph_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        prehash: [u8; 64],
+        context: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let config = self.config.clone();
+        let evt_send = self.evt_send.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            check_sign_policy(&config, keystore_index, &entry, evt_send)
+                .await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519ph(
+                        entry.priv_key.clone(),
+                        context,
+                        prehash,
+                    )
+                    .await
+                }
+                LairEntry::SignEd25519Hardware(_) => Err(
+                    "hardware backed ed25519 keypairs cannot sign pre-hashed"
+                        .into(),
+                ),
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519)),
+            }
+        })
+    }
+
+    fn handle_x25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, X25519PubKey)> {
+        let fut = self.store_actor.x25519_keypair_new_from_entropy();
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_x25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<X25519PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => Ok(entry.pub_key.clone()),
+                entry => {
+                    Err(entry.wrong_type(keystore_index, LairEntryType::X25519))
+                }
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_to_sign_pub_key(
+        &mut self,
+        sender_x25519_index: KeystoreIndex,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let recipient_pub_key = recipient_sign_pub_key.to_x25519_pub_key()?;
+        let entropy = self.config.get_entropy().clone();
+        let fut = self.store_actor.get_entry_by_index(sender_x25519_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    x25519::crypto_box(
+                        entropy,
+                        entry.priv_key.clone(),
+                        recipient_pub_key,
+                        data,
+                    )
+                    .await
+                }
+                entry => Err(entry
+                    .wrong_type(sender_x25519_index, LairEntryType::X25519)),
+            }
+        })
+    }
+
+    fn handle_crypto_box_open_by_sign_pub_key(
+        &mut self,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        sender_x25519_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(recipient_sign_pub_key.0);
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    let priv_key = x25519::sign_ed25519_priv_key_to_x25519(
+                        &entry.priv_key,
+                    )?;
+                    x25519::crypto_box_open(
+                        priv_key,
+                        sender_x25519_pub_key,
+                        data,
+                    )
+                    .await
+                }
+                LairEntry::SignEd25519Hardware(_) => Err(
+                    "hardware backed ed25519 keypairs cannot open crypto boxes"
+                        .into(),
+                ),
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignEd25519)),
+            }
+        })
+    }
+
+    fn handle_crypto_box_seal_open_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    x25519::crypto_box_seal_open(entry.priv_key.clone(), data)
+                        .await
+                }
+                entry => {
+                    Err(entry.wrong_type(keystore_index, LairEntryType::X25519))
+                }
+            }
+        })
+    }
+
+    fn handle_age_decrypt_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        stanzas: Vec<AgeStanza>,
+    ) -> LairClientApiHandlerResult<[u8; AGE_FILE_KEY_LEN]> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    lair_keystore_api::internal::age::age_x25519_unwrap(
+                        entry.priv_key.clone(),
+                        stanzas,
+                    )
+                    .await
+                }
+                entry => {
+                    Err(entry.wrong_type(keystore_index, LairEntryType::X25519))
+                }
+            }
+        })
+    }
+
+    fn handle_lair_get_random_bytes(
+        &mut self,
+        len: u32,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let out = entropy::random_bytes(self.config.get_entropy(), len);
+        let fut = self.store_actor.check_deep_lock();
+        Ok(async move {
+            fut.await?;
+            out
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_new_uuid(&mut self) -> LairClientApiHandlerResult<[u8; 16]> {
+        let out = entropy::new_uuid(self.config.get_entropy());
+        let fut = self.store_actor.check_deep_lock();
+        Ok(async move {
+            fut.await?;
+            out
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_pepper_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.pw_pepper_new_from_entropy();
+        self.when_unlocked(async move {
+            let (keystore_index, _) = fut.await?;
+            Ok(keystore_index)
+        })
+    }
+
+    fn handle_pw_hash(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        options: PwHashOptions,
+    ) -> LairClientApiHandlerResult<String> {
+        let entropy = self.config.get_entropy().clone();
+        let fut = self.store_actor.get_entry_by_index(pepper_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::PwPepper(entry) => {
+                    entry.pw_hash(entropy, password, options).await
+                }
+                entry => {
+                    Err(entry.wrong_type(pepper_index, LairEntryType::PwPepper))
+                }
+            }
+        })
+    }
+
+    fn handle_pw_verify(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        hash: String,
+    ) -> LairClientApiHandlerResult<bool> {
+        let fut = self.store_actor.get_entry_by_index(pepper_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::PwPepper(entry) => {
+                    entry.pw_verify(password, hash).await
+                }
+                entry => {
+                    Err(entry.wrong_type(pepper_index, LairEntryType::PwPepper))
+                }
+            }
+        })
+    }
+
+    fn handle_totp_new_from_entropy(
+        &mut self,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.totp_new_from_entropy(options);
+        self.when_unlocked(async move {
+            let (keystore_index, _) = fut.await?;
+            Ok(keystore_index)
+        })
+    }
+
+    fn handle_totp_import(
+        &mut self,
+        secret_b32: String,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.store_actor.totp_import(secret_b32, options);
+        self.when_unlocked(async move {
+            let (keystore_index, _) = fut.await?;
+            Ok(keystore_index)
+        })
+    }
+
+    fn handle_totp_generate(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        at: Option<u64>,
+    ) -> LairClientApiHandlerResult<String> {
+        let at = at.unwrap_or_else(|| self.config.get_clock().unix_now_secs());
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TotpSecret(entry) => entry.generate(Some(at)),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TotpSecret))
+                }
+            }
+        })
+    }
+
+    fn handle_totp_export_provisioning_uri(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        label: String,
+        issuer: String,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TotpSecret(entry) => {
+                    entry.provisioning_uri(&label, &issuer)
+                }
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::TotpSecret))
+                }
+            }
+        })
+    }
+
+    fn handle_sign_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey)> {
+        let fut = self.store_actor.sign_secp256k1_keypair_new_from_entropy();
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_sign_secp256k1_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey, bool)>
+    {
+        let fut = self.store_actor.sign_secp256k1_keypair_new_from_seed(seed);
+        self.when_unlocked(async move {
+            let (keystore_index, entry, already_existed) = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone(), already_existed))
+                }
+                _ => Err("invalid entry type".into()),
+            }
+        })
+    }
+
+    fn handle_sign_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignSecp256k1PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => Ok(entry.pub_key.clone()),
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignSecp256k1)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message_hash: [u8; 32],
+    ) -> LairClientApiHandlerResult<(SignSecp256k1Signature, u8)> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => {
+                    entry.sign(message_hash).await
+                }
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignSecp256k1)),
+            }
+        })
+    }
+
+    fn handle_sign_ed448_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd448PubKey)> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let fut = self.store_actor.sign_ed448_keypair_new_from_entropy();
+            self.when_unlocked(async move {
+                let (keystore_index, entry) = fut.await?;
+                match &*entry {
+                    LairEntry::SignEd448(entry) => {
+                        Ok((keystore_index, entry.pub_key.clone()))
+                    }
+                    _ => Err("invalid entry type".into()),
+                }
+            })
+        }
+    }
+
+    fn handle_sign_ed448_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignEd448PubKey> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = keystore_index;
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let fut = self.store_actor.get_entry_by_index(keystore_index);
+            Ok(async move {
+                let entry = fut.await?;
+                match &*entry {
+                    LairEntry::SignEd448(entry) => Ok(entry.pub_key.clone()),
+                    entry => Err(entry
+                        .wrong_type(keystore_index, LairEntryType::SignEd448)),
+                }
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    fn handle_sign_ed448_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = (keystore_index, message);
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let fut = self.store_actor.get_entry_by_index(keystore_index);
+            self.when_unlocked(async move {
+                let entry = fut.await?;
+                match &*entry {
+                    LairEntry::SignEd448(entry) => entry.sign(message).await,
+                    entry => Err(entry
+                        .wrong_type(keystore_index, LairEntryType::SignEd448)),
+                }
+            })
+        }
+    }
+
+    fn handle_sign_ed448_sign_by_pub_key(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<LairEntryType> {
-        let fut = self.store_actor.get_entry_by_index(keystore_index);
-        Ok(async move {
-            match fut.await {
-                Err(_) => Ok(LairEntryType::Invalid),
-                Ok(entry) => match &*entry {
-                    LairEntry::TlsCert(_) => Ok(LairEntryType::TlsCert),
-                    LairEntry::SignEd25519(_) => Ok(LairEntryType::SignEd25519),
-                    _ => {
-                        Err(format!("unhandled entry type {:?}", entry).into())
-                    }
-                },
-            }
+        pub_key: SignEd448PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = (pub_key, message);
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let fut = self.store_actor.get_entry_by_pub_id(pub_key.into());
+            self.when_unlocked(async move {
+                let (keystore_index, entry) = fut.await?;
+                match &*entry {
+                    LairEntry::SignEd448(entry) => entry.sign(message).await,
+                    entry => Err(entry
+                        .wrong_type(keystore_index, LairEntryType::SignEd448)),
+                }
+            })
         }
-        .boxed()
-        .into())
     }
 
-    fn handle_tls_cert_new_self_signed_from_entropy(
+    fn handle_sign_bls_new_from_entropy(
         &mut self,
-        options: TlsCertOptions,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
-        let fut = self
-            .store_actor
-            .tls_cert_self_signed_new_from_entropy(options);
-        Ok(async move {
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignBlsPubKey)> {
+        let fut = self.store_actor.sign_bls_keypair_new_from_entropy();
+        self.when_unlocked(async move {
             let (keystore_index, entry) = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok((
-                    keystore_index,
-                    entry.sni.clone(),
-                    entry.cert_digest.clone(),
-                )),
+                LairEntry::SignBls(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
                 _ => Err("invalid entry type".into()),
             }
-        }
-        .boxed()
-        .into())
+        })
     }
 
-    fn handle_tls_cert_get(
+    fn handle_sign_bls_get(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+    ) -> LairClientApiHandlerResult<SignBlsPubKey> {
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => {
-                    Ok((entry.sni.clone(), entry.cert_digest.clone()))
+                LairEntry::SignBls(entry) => Ok(entry.pub_key.clone()),
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::SignBls))
                 }
-                _ => Err("invalid entry type".into()),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_index(
+    fn handle_sign_bls_sign_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<Cert> {
+        message: Arc<Vec<u8>>,
+        dst: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignBlsSignature> {
         let fut = self.store_actor.get_entry_by_index(keystore_index);
-        Ok(async move {
+        self.when_unlocked(async move {
             let entry = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
-                _ => Err("invalid entry type".into()),
+                LairEntry::SignBls(entry) => entry.sign(message, dst).await,
+                entry => {
+                    Err(entry
+                        .wrong_type(keystore_index, LairEntryType::SignBls))
+                }
             }
-        }
-        .boxed()
-        .into())
+        })
     }
 
-    fn handle_tls_cert_get_cert_by_digest(
+    fn handle_sign_sr25519_new_from_entropy(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
-        Ok(async move {
-            let (_, entry) = fut.await?;
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey)> {
+        let fut = self.store_actor.sign_sr25519_keypair_new_from_entropy();
+        self.when_unlocked(async move {
+            let (keystore_index, entry) = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                LairEntry::SignSr25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
                 _ => Err("invalid entry type".into()),
             }
-        }
-        .boxed()
-        .into())
+        })
     }
 
-    fn handle_tls_cert_get_cert_by_sni(
+    fn handle_sign_sr25519_new_from_seed(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.store_actor.get_entry_by_sni(cert_sni);
-        Ok(async move {
-            let (_, entry) = fut.await?;
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey, bool)>
+    {
+        let fut = self.store_actor.sign_sr25519_keypair_new_from_seed(seed);
+        self.when_unlocked(async move {
+            let (keystore_index, entry, already_existed) = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                LairEntry::SignSr25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone(), already_existed))
+                }
                 _ => Err("invalid entry type".into()),
             }
-        }
-        .boxed()
-        .into())
+        })
     }
 
-    fn handle_tls_cert_get_priv_key_by_index(
+    fn handle_sign_sr25519_get(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
+    ) -> LairClientApiHandlerResult<SignSr25519PubKey> {
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
+                LairEntry::SignSr25519(entry) => Ok(entry.pub_key.clone()),
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignSr25519)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_digest(
+    fn handle_sign_sr25519_sign_by_index(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
-        Ok(async move {
-            let (_, entry) = fut.await?;
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        context: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignSr25519Signature> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        self.when_unlocked(async move {
+            let entry = fut.await?;
             match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
+                LairEntry::SignSr25519(entry) => {
+                    entry.sign(message, context).await
+                }
+                entry => Err(entry
+                    .wrong_type(keystore_index, LairEntryType::SignSr25519)),
             }
+        })
+    }
+
+    // ephemeral keys never touch the store, they work while it is locked
+
+    fn handle_ephemeral_x25519_new(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, X25519PubKey)> {
+        let config = self.config.clone();
+        let ephemeral = self.ephemeral.clone();
+        Ok(async move {
+            let entropy = store::checked_entropy(&config).await?;
+            ephemeral.x25519_new(entropy).await
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_sni(
+    fn handle_ephemeral_sign_ed25519_new(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, SignEd25519PubKey)> {
+        let config = self.config.clone();
+        let ephemeral = self.ephemeral.clone();
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let entropy = store::checked_entropy(&config).await?;
+            ephemeral.sign_ed25519_new(entropy).await
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_new_from_entropy(
+    fn handle_ephemeral_sign_ed25519_sign(
         &mut self,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
-        let fut = self.store_actor.sign_ed25519_keypair_new_from_entropy();
+        handle: EphemeralHandle,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(
+            async move { ephemeral.sign_ed25519_sign(handle, message).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_ephemeral_crypto_box(
+        &mut self,
+        sender_handle: EphemeralHandle,
+        recipient_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let entropy = self.config.get_entropy().clone();
+        let ephemeral = self.ephemeral.clone();
         Ok(async move {
-            let (keystore_index, entry) = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    Ok((keystore_index, entry.pub_key.clone()))
-                }
-                _ => Err("invalid entry type".into()),
-            }
+            ephemeral
+                .crypto_box(entropy, sender_handle, recipient_pub_key, data)
+                .await
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_get(
+    fn handle_ephemeral_crypto_box_open(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<SignEd25519PubKey> {
-        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        recipient_handle: EphemeralHandle,
+        sender_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let ephemeral = self.ephemeral.clone();
         Ok(async move {
-            let entry = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => Ok(entry.pub_key.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            ephemeral
+                .crypto_box_open(recipient_handle, sender_pub_key, data)
+                .await
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_index(
+    fn handle_ephemeral_drop(
         &mut self,
-        keystore_index: KeystoreIndex,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
-        let fut = self.store_actor.get_entry_by_index(keystore_index);
-        Ok(async move {
-            let entry = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
-                        .await
+        handle: EphemeralHandle,
+    ) -> LairClientApiHandlerResult<()> {
+        let res = self.ephemeral.remove(handle);
+        Ok(async move { res }.boxed().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawn a server and an unlocked client connected to it.
+    async fn spawn_unlocked(
+        config: Arc<Config>,
+    ) -> LairResult<LairClientSender> {
+        let internal::pid_check::PidCheckResult { store_file } =
+            internal::pid_check::pid_check(&config)?;
+        spawn_bind_server_ipc(config.clone(), store_file).await?;
+
+        let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+        )
+        .await?;
+        loop {
+            match evt_recv.next().await.expect("an event") {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond, ..
+                } => {
+                    respond.respond(Ok(async move { Ok("passphrase".into()) }
+                        .boxed()
+                        .into()));
                 }
-                _ => Err("invalid entry type".into()),
+                LairClientEvent::UnlockProgress { respond, .. } => {
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                }
+                LairClientEvent::UnlockComplete { respond, error, .. } => {
+                    assert_eq!(None, error);
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                    break;
+                }
+                evt => panic!("unexpected event: {:?}", EventKind::of(&evt)),
             }
         }
-        .boxed()
-        .into())
+        Ok(api_send)
     }
 
-    fn handle_sign_ed25519_sign_by_pub_key(
-        &mut self,
-        pub_key: SignEd25519PubKey,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
-        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
-        Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
-                        .await
+    /// The `{:#}` display of `e` and its sources.
+    fn chain_display(e: &(dyn std::error::Error + 'static)) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut cause = Some(e);
+        while let Some(e) = cause {
+            out.push(format!("{:#}", e));
+            cause = e.source();
+        }
+        out
+    }
+
+    /// Fails as many fills as its count, then fills from the os.
+    struct FailingEntropy(Arc<std::sync::atomic::AtomicU32>);
+
+    impl entropy::EntropySource for FailingEntropy {
+        fn fill(&self, buf: &mut [u8]) -> LairResult<()> {
+            use std::sync::atomic::Ordering::SeqCst;
+            if self
+                .0
+                .fetch_update(SeqCst, SeqCst, |f| f.checked_sub(1))
+                .is_ok()
+            {
+                return Err("getrandom failed".into());
+            }
+            entropy::OsEntropy::new_handle().fill(buf)
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_checks_entropy_before_key_generation() -> LairResult<()> {
+        for policy in [EntropyFailurePolicy::Fail, EntropyFailurePolicy::Block]
+        {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let failures = Arc::new(std::sync::atomic::AtomicU32::new(0));
+            let config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_allow_weak_passphrase(true)
+                .set_unlock_kdf_limits(
+                    lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                        mem_limit_kib: 1024,
+                        ops_limit: 1,
+                    },
+                )
+                .set_entropy_source(Arc::new(FailingEntropy(failures.clone())))
+                .set_entropy_failure_policy(policy)
+                .build();
+            let api_send = spawn_unlocked(config).await?;
+            // the server identity was generated after a check
+            assert_eq!(
+                EntropyStatus::Healthy,
+                api_send.lair_get_server_info().await?.entropy_status,
+            );
+
+            failures.store(1, std::sync::atomic::Ordering::SeqCst);
+            let res = api_send.x25519_new_from_entropy().await;
+            let status = api_send.lair_get_server_info().await?.entropy_status;
+            match policy {
+                EntropyFailurePolicy::Fail => {
+                    assert!(
+                        matches!(res, Err(LairError::EntropyUnavailable(_))),
+                        "{:?}",
+                        res
+                    );
+                    assert_eq!(EntropyStatus::Unavailable, status);
+                    // the source recovered
+                    api_send.x25519_new_from_entropy().await?;
+                }
+                _ => {
+                    // blocked until the retried check passed
+                    res?;
+                    assert_eq!(EntropyStatus::Healthy, status);
                 }
-                _ => Err("invalid entry type".into()),
             }
+            assert_eq!(
+                EntropyStatus::Healthy,
+                api_send.lair_get_server_info().await?.entropy_status,
+            );
         }
-        .boxed()
-        .into())
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_carries_store_failure_causes() -> LairResult<()> {
+        for hide_paths in [false, true] {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_allow_weak_passphrase(true)
+                .set_hide_paths(hide_paths)
+                .set_unlock_kdf_limits(
+                    lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                        mem_limit_kib: 1024,
+                        ops_limit: 1,
+                    },
+                )
+                .build();
+            let api_send = spawn_unlocked(config.clone()).await?;
+
+            let (index, _) = api_send.sign_ed25519_new_from_entropy().await?;
+            // unlocked, the tags are sealed
+            store::fault::inject(
+                &store::store_file::sealed_path(config.get_tags_path()),
+                std::io::ErrorKind::PermissionDenied,
+            );
+            let err = api_send
+                .lair_set_entry_tag(index, Some("tag".to_string()))
+                .await
+                .unwrap_err();
+
+            // the client rebuilt the io error, and the context around it
+            let mut io_kind = None;
+            let mut cause = std::error::Error::source(&err);
+            while let Some(e) = cause {
+                if let Some(e) = e.downcast_ref::<std::io::Error>() {
+                    io_kind = Some(e.kind());
+                }
+                cause = e.source();
+            }
+            assert_eq!(Some(std::io::ErrorKind::PermissionDenied), io_kind);
+            let chain = chain_display(&err);
+            assert!(chain[0].starts_with("writing"), "{:?}", chain);
+            assert!(chain.iter().any(|m| m == "injected fault"), "{:?}", chain);
+
+            let tmp_name = tmpdir.path().to_string_lossy().to_string();
+            assert_eq!(
+                !hide_paths,
+                chain.iter().any(|m| m.contains(&tmp_name)),
+                "{:?}",
+                chain
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_scopes_ephemeral_keys_to_the_connection() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_allow_weak_passphrase(true)
+            .set_unlock_kdf_limits(
+                lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                    mem_limit_kib: 1024,
+                    ops_limit: 1,
+                },
+            )
+            .set_max_ephemeral_keys(3)
+            .build();
+        let api_send = spawn_unlocked(config.clone()).await?;
+        let entry_count = api_send.lair_get_entry_count().await?;
+
+        let message = Arc::new(vec![1; 8]);
+        let (sign, sign_pub) = api_send.ephemeral_sign_ed25519_new().await?;
+        let signature = api_send
+            .ephemeral_sign_ed25519_sign(sign, message.clone())
+            .await?;
+        assert!(
+            sign_ed25519::sign_ed25519_verify(
+                sign_pub.clone(),
+                message.clone(),
+                signature
+            )
+            .await?
+        );
+
+        let (alice, alice_pub) = api_send.ephemeral_x25519_new().await?;
+        let (bob, bob_pub) = api_send.ephemeral_x25519_new().await?;
+        let boxed = api_send
+            .ephemeral_crypto_box(alice, bob_pub, message.clone())
+            .await?;
+        assert_eq!(
+            message,
+            api_send
+                .ephemeral_crypto_box_open(bob, alice_pub, boxed)
+                .await?,
+        );
+
+        // the cap holds until a key is dropped
+        assert!(matches!(
+            api_send.ephemeral_x25519_new().await,
+            Err(LairError::Forbidden(_)),
+        ));
+        api_send.ephemeral_drop(bob).await?;
+        assert!(matches!(
+            api_send.ephemeral_drop(bob).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        api_send.ephemeral_x25519_new().await?;
+
+        // nothing reached the store
+        assert_eq!(entry_count, api_send.lair_get_entry_count().await?);
+        assert!(matches!(
+            api_send
+                .sign_ed25519_sign_by_pub_key(sign_pub, message.clone())
+                .await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        // other connections cannot use them
+        let (other_send, _other_evt) = lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+        )
+        .await?;
+        assert!(matches!(
+            other_send.ephemeral_sign_ed25519_sign(sign, message).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rotates_ed25519_keypairs() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_allow_weak_passphrase(true)
+            .set_unlock_kdf_limits(
+                lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                    mem_limit_kib: 1024,
+                    ops_limit: 1,
+                },
+            )
+            .build();
+        let api_send = spawn_unlocked(config).await?;
+
+        let (old_index, old_pub_key) =
+            api_send.sign_ed25519_new_from_entropy().await?;
+        let mut options = RotateKeyOptions::default();
+        options.key_options.tag = Some("rotated".to_string());
+        let (new_index, record) =
+            api_send.sign_ed25519_rotate(old_index, options).await?;
+        assert!(record.verify());
+        assert_eq!(old_pub_key, record.old_pub_key);
+        assert_eq!(
+            record.new_pub_key,
+            api_send.sign_ed25519_get(new_index).await?
+        );
+        assert_eq!(
+            new_index,
+            api_send.lair_get_entry_by_tag("rotated".into()).await?.0
+        );
+
+        let (page, _) = api_send.lair_list_entries_page(old_index, 8).await?;
+        let info = |idx| page.iter().find(|i| i.keystore_index == idx);
+        assert_eq!(None, info(old_index).unwrap().rotated_from);
+        assert_eq!(Some(old_index), info(new_index).unwrap().rotated_from);
+        assert!(!info(old_index).unwrap().trashed);
+
+        // rotating again can retire the replaced keypair
+        let mut options = RotateKeyOptions::default();
+        options.trash_old_key = true;
+        let (newest_index, record) =
+            api_send.sign_ed25519_rotate(new_index, options).await?;
+        assert!(record.verify());
+        let (page, _) = api_send.lair_list_entries_page(new_index, 8).await?;
+        assert!(page[0].trashed);
+        assert_eq!(Some(new_index), page[1].rotated_from);
+        assert_eq!(newest_index, page[1].keystore_index);
+        assert!(matches!(
+            api_send
+                .sign_ed25519_rotate(new_index, Default::default())
+                .await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+
+        Ok(())
+    }
+
+    /// Every getter of entries of one type, at `index`, boxing to
+    /// `sign_pub_key` where one is needed.
+    fn getters(
+        api_send: &LairClientSender,
+        index: KeystoreIndex,
+        sign_pub_key: &SignEd25519PubKey,
+    ) -> Vec<(
+        &'static str,
+        LairEntryType,
+        futures::future::BoxFuture<'static, LairResult<()>>,
+    )> {
+        use LairEntryType::*;
+        let data = Arc::new(vec![0x42; 32]);
+        macro_rules! getter {
+            ($entry_type:ident, $f:ident($($arg:expr),*)) => {
+                (
+                    stringify!($f),
+                    $entry_type,
+                    api_send.$f($($arg),*).map(|r| r.map(|_| ())).boxed(),
+                )
+            };
+        }
+        vec![
+            getter!(TlsCert, tls_cert_get(index)),
+            getter!(TlsCert, tls_cert_get_info(index)),
+            getter!(TlsCert, tls_cert_get_cert_by_index(index)),
+            getter!(TlsCert, tls_cert_get_priv_key_by_index(index)),
+            getter!(TlsCert, tls_cert_get_digest(index, DigestAlg::Blake2b)),
+            getter!(
+                SignEd25519,
+                tls_cert_new_self_signed_from_sign_key(
+                    index,
+                    TlsCertOptions::default()
+                )
+            ),
+            getter!(SignEd25519, sign_ed25519_get(index)),
+            getter!(SignEd25519, sign_ed25519_get_options(index)),
+            getter!(
+                SignEd25519,
+                sign_ed25519_sign_by_index(index, data.clone())
+            ),
+            getter!(
+                SignEd25519,
+                sign_ed25519_sign_by_index_v2(index, data.clone())
+            ),
+            getter!(
+                SignEd25519,
+                sign_ed25519_ph_sign_by_index(index, [0x42; 64], data.clone())
+            ),
+            getter!(SignEd25519, sign_ed25519_export_seed(index)),
+            getter!(
+                SignEd25519,
+                sign_ed25519_rotate(index, RotateKeyOptions::default())
+            ),
+            getter!(X25519, x25519_get(index)),
+            getter!(
+                X25519,
+                crypto_box_to_sign_pub_key(
+                    index,
+                    sign_pub_key.clone(),
+                    data.clone()
+                )
+            ),
+            getter!(X25519, crypto_box_seal_open_by_index(index, data.clone())),
+            getter!(X25519, age_decrypt_by_index(index, Vec::new())),
+            getter!(X25519, lair_unwrap_entry(index, data.clone())),
+            getter!(
+                PwPepper,
+                pw_hash(index, data.clone(), PwHashOptions::default())
+            ),
+            getter!(PwPepper, pw_verify(index, data.clone(), "hash".into())),
+            getter!(TotpSecret, totp_generate(index, None)),
+            getter!(
+                TotpSecret,
+                totp_export_provisioning_uri(index, "l".into(), "i".into())
+            ),
+            getter!(SignSecp256k1, sign_secp256k1_get(index)),
+            getter!(
+                SignSecp256k1,
+                sign_secp256k1_sign_by_index(index, [0x42; 32])
+            ),
+            #[cfg(feature = "ed448")]
+            getter!(SignEd448, sign_ed448_get(index)),
+            #[cfg(feature = "ed448")]
+            getter!(SignEd448, sign_ed448_sign_by_index(index, data.clone())),
+            getter!(SignBls, sign_bls_get(index)),
+            getter!(
+                SignBls,
+                sign_bls_sign_by_index(index, data.clone(), b"dst".to_vec())
+            ),
+            getter!(SignSr25519, sign_sr25519_get(index)),
+            getter!(
+                SignSr25519,
+                sign_sr25519_sign_by_index(
+                    index,
+                    data.clone(),
+                    b"ctx".to_vec()
+                )
+            ),
+        ]
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_refuses_entries_of_the_wrong_type() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_allow_weak_passphrase(true)
+            .set_unlock_kdf_limits(
+                lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                    mem_limit_kib: 1024,
+                    ops_limit: 1,
+                },
+            )
+            .build();
+        let api_send = spawn_unlocked(config).await?;
+
+        let (_, sign_pub_key) =
+            api_send.sign_ed25519_new_from_entropy().await?;
+        let entries = [
+            (
+                LairEntryType::TlsCert,
+                api_send
+                    .tls_cert_new_self_signed_from_entropy(
+                        TlsCertOptions::default(),
+                    )
+                    .await?
+                    .0,
+            ),
+            (
+                LairEntryType::SignEd25519,
+                api_send.sign_ed25519_new_from_entropy().await?.0,
+            ),
+            (
+                LairEntryType::X25519,
+                api_send.x25519_new_from_entropy().await?.0,
+            ),
+            (
+                LairEntryType::PwPepper,
+                api_send.pw_pepper_new_from_entropy().await?,
+            ),
+            (
+                LairEntryType::TotpSecret,
+                api_send
+                    .totp_new_from_entropy(TotpOptions::default())
+                    .await?,
+            ),
+            (
+                LairEntryType::SignSecp256k1,
+                api_send.sign_secp256k1_new_from_entropy().await?.0,
+            ),
+            #[cfg(feature = "ed448")]
+            (
+                LairEntryType::SignEd448,
+                api_send.sign_ed448_new_from_entropy().await?.0,
+            ),
+            (
+                LairEntryType::SignBls,
+                api_send.sign_bls_new_from_entropy().await?.0,
+            ),
+            (
+                LairEntryType::SignSr25519,
+                api_send.sign_sr25519_new_from_entropy().await?.0,
+            ),
+        ];
+
+        for (actual, index) in entries.iter().copied() {
+            for (name, expected, res) in
+                getters(&api_send, index, &sign_pub_key)
+            {
+                if expected == actual {
+                    continue;
+                }
+                match res.await {
+                    Err(LairError::WrongEntryType {
+                        index: i,
+                        expected: e,
+                        actual: a,
+                    }) if i == index && e == expected && a == actual => (),
+                    res => panic!("{} of {:?}: {:?}", name, actual, res),
+                }
+            }
+        }
+
+        // an index holding nothing is not found, whatever the getter
+        let missing = KeystoreIndex(entries.len() as u32 + 42);
+        for (name, _, res) in getters(&api_send, missing, &sign_pub_key) {
+            match res.await {
+                Err(LairError::EntryNotFound(_)) => (),
+                res => panic!("{} of nothing: {:?}", name, res),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_exports_ed25519_seeds() -> LairResult<()> {
+        let config = |tmpdir: &tempfile::TempDir, forbid| {
+            Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_allow_weak_passphrase(true)
+                .set_unlock_kdf_limits(
+                    lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                        mem_limit_kib: 1024,
+                        ops_limit: 1,
+                    },
+                )
+                .set_forbid_seed_export(forbid)
+                .build()
+        };
+        let tmpdir = tempfile::tempdir().unwrap();
+        let api_send = spawn_unlocked(config(&tmpdir, false)).await?;
+
+        let (idx, _, _) = api_send
+            .sign_ed25519_new_from_seed_with_options(
+                [0x42; 32],
+                Default::default(),
+            )
+            .await?;
+        api_send.lair_activate_entry(idx).await?;
+        assert_eq!([0x42; 32], api_send.sign_ed25519_export_seed(idx).await?);
+
+        // a generated keypair derives from its exported seed again
+        let (idx, _) = api_send.sign_ed25519_new_from_entropy().await?;
+        let seed = api_send.sign_ed25519_export_seed(idx).await?;
+        assert_eq!(
+            api_send.sign_ed25519_get(idx).await?,
+            sign_ed25519::sign_ed25519_keypair_new_from_seed(seed)
+                .await?
+                .pub_key
+        );
+
+        let mut options = SignKeyOptions::default();
+        options.exportable = false;
+        let (idx, _) = api_send
+            .sign_ed25519_new_from_entropy_with_options(options)
+            .await?;
+        assert!(matches!(
+            api_send.sign_ed25519_export_seed(idx).await,
+            Err(LairError::ExportForbidden(_)),
+        ));
+        let (idx, _) = api_send.x25519_new_from_entropy().await?;
+        assert!(api_send.sign_ed25519_export_seed(idx).await.is_err());
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let api_send = spawn_unlocked(config(&tmpdir, true)).await?;
+        let (idx, _) = api_send.sign_ed25519_new_from_entropy().await?;
+        assert!(matches!(
+            api_send.sign_ed25519_export_seed(idx).await,
+            Err(LairError::ExportForbidden(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_activates_imported_entries() -> LairResult<()> {
+        for auto_activate in [false, true] {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_allow_weak_passphrase(true)
+                .set_unlock_kdf_limits(
+                    lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                        mem_limit_kib: 1024,
+                        ops_limit: 1,
+                    },
+                )
+                .set_auto_activate_imports(auto_activate)
+                .build();
+            let api_send = spawn_unlocked(config.clone()).await?;
+            let (watch_send, mut watch_recv) = lair_keystore_api::ipc::connect(
+                lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+            )
+            .await?;
+            watch_send
+                .lair_set_event_filter(vec![EventKind::EntryActivated])
+                .await?;
+            let message = Arc::new(b"test message".to_vec());
+
+            // entries created from entropy are active right away
+            let (created, _) = api_send.sign_ed25519_new_from_entropy().await?;
+            api_send
+                .sign_ed25519_sign_by_index(created, message.clone())
+                .await?;
+            let (page, _) = api_send.lair_list_entries_page(created, 1).await?;
+            assert!(page[0].activated);
+
+            let (imported, pub_key, _) = api_send
+                .sign_ed25519_new_from_seed_with_options(
+                    [0x42; 32],
+                    Default::default(),
+                )
+                .await?;
+            let (page, _) =
+                api_send.lair_list_entries_page(imported, 1).await?;
+            assert_eq!(auto_activate, page[0].activated);
+            if auto_activate {
+                api_send
+                    .sign_ed25519_sign_by_index(imported, message.clone())
+                    .await?;
+                // activating an active entry does nothing
+                api_send.lair_activate_entry(imported).await?;
+                continue;
+            }
+
+            // imports refuse all use until activated
+            assert!(matches!(
+                api_send
+                    .sign_ed25519_sign_by_index(imported, message.clone())
+                    .await,
+                Err(LairError::EntryNotActivated(_)),
+            ));
+            assert!(matches!(
+                api_send.sign_ed25519_resolve_by_pub_key(pub_key).await,
+                Err(LairError::EntryNotActivated(_)),
+            ));
+            assert!(matches!(
+                api_send.lair_activate_entry(42.into()).await,
+                Err(LairError::EntryNotFound(_)),
+            ));
+            api_send.lair_activate_entry(imported).await?;
+            api_send
+                .sign_ed25519_sign_by_index(imported, message.clone())
+                .await?;
+            let (page, _) =
+                api_send.lair_list_entries_page(imported, 1).await?;
+            assert!(page[0].activated);
+
+            loop {
+                match watch_recv.next().await.expect("an event") {
+                    LairClientEvent::EntryActivated {
+                        respond,
+                        keystore_index,
+                        ..
+                    } => {
+                        assert_eq!(imported, keystore_index);
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                        break;
+                    }
+                    // the passphrase request sent before the filter
+                    LairClientEvent::RequestUnlockPassphrase {
+                        respond,
+                        ..
+                    } => {
+                        respond.respond(Ok(
+                            async move { Ok("passphrase".into()) }
+                                .boxed()
+                                .into(),
+                        ));
+                    }
+                    LairClientEvent::UnlockComplete { respond, .. } => {
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    evt => {
+                        panic!("unexpected event: {:?}", EventKind::of(&evt))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_applies_the_clock_skew_policy_to_expiry() -> LairResult<()> {
+        use lair_keystore_api::internal::clock::ManualClock;
+
+        const NOW: u64 = 1_600_000_000;
+        for policy in [ClockSkewPolicy::FailClosed, ClockSkewPolicy::FailOpen] {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let clock = ManualClock::new(NOW);
+            let config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_allow_weak_passphrase(true)
+                .set_unlock_kdf_limits(
+                    lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                        mem_limit_kib: 1024,
+                        ops_limit: 1,
+                    },
+                )
+                .set_clock(clock.handle())
+                .set_clock_skew_policy(policy)
+                .build();
+            let api_send = spawn_unlocked(config.clone()).await?;
+            let (watch_send, mut watch_recv) = lair_keystore_api::ipc::connect(
+                lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+            )
+            .await?;
+            watch_send
+                .lair_set_event_filter(vec![EventKind::ClockSkewDetected])
+                .await?;
+
+            let mut options = SignKeyOptions::default();
+            options.expires_at = Some(NOW + 3600);
+            let (expiring, _) = api_send
+                .sign_ed25519_new_from_entropy_with_options(options)
+                .await?;
+            let (plain, _) = api_send.sign_ed25519_new_from_entropy().await?;
+            let message = Arc::new(b"test message".to_vec());
+            api_send
+                .sign_ed25519_sign_by_index(expiring, message.clone())
+                .await?;
+
+            // jumping forwards is not skew, the keypair expired
+            clock.set_wall(NOW + 7200);
+            assert!(matches!(
+                api_send
+                    .sign_ed25519_sign_by_index(expiring, message.clone())
+                    .await,
+                Err(LairError::KeyExpired(_)),
+            ));
+
+            // jumping back is, keypairs with an expiry follow the policy
+            clock.set_wall(NOW);
+            let res = api_send
+                .sign_ed25519_sign_by_index(expiring, message.clone())
+                .await;
+            match policy {
+                ClockSkewPolicy::FailClosed => assert!(
+                    matches!(res, Err(LairError::Forbidden(_))),
+                    "{:?}",
+                    res
+                ),
+                ClockSkewPolicy::FailOpen => assert!(res.is_ok(), "{:?}", res),
+            }
+            api_send
+                .sign_ed25519_sign_by_index(plain, message.clone())
+                .await?;
+            loop {
+                match watch_recv.next().await.expect("an event") {
+                    LairClientEvent::ClockSkewDetected {
+                        respond,
+                        wall_secs,
+                        expected_secs,
+                        ..
+                    } => {
+                        assert_eq!(NOW, wall_secs);
+                        assert_eq!(NOW + 7200, expected_secs);
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                        break;
+                    }
+                    // the passphrase request sent before the filter
+                    LairClientEvent::RequestUnlockPassphrase {
+                        respond,
+                        ..
+                    } => {
+                        respond.respond(Ok(
+                            async move { Ok("passphrase".into()) }
+                                .boxed()
+                                .into(),
+                        ));
+                    }
+                    LairClientEvent::UnlockComplete { respond, .. } => {
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    evt => {
+                        panic!("unexpected event: {:?}", EventKind::of(&evt))
+                    }
+                }
+            }
+
+            // set right again, expiry is enforced again
+            clock.set_wall(NOW + 7200);
+            assert!(matches!(
+                api_send.sign_ed25519_sign_by_index(expiring, message).await,
+                Err(LairError::KeyExpired(_)),
+            ));
+        }
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_serves_clients_with_and_without_compression() -> LairResult<()>
+    {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_allow_weak_passphrase(true)
+            .set_unlock_kdf_limits(
+                lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                    mem_limit_kib: 1024,
+                    ops_limit: 1,
+                },
+            )
+            .set_wire_compression(true)
+            .build();
+        let api_send = spawn_unlocked(config.clone()).await?;
+        let (index, pub_key) = api_send.sign_ed25519_new_from_entropy().await?;
+
+        // a client that did not opt in talks to the same server
+        let plain_config =
+            Config::builder().set_root_path(tmpdir.path()).build();
+        let (plain_send, _plain_recv) = lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&plain_config),
+        )
+        .await?;
+
+        for api_send in &[api_send, plain_send] {
+            let message = Arc::new(vec![0xa5; 1024 * 1024]);
+            let signature = api_send
+                .sign_ed25519_sign_by_index(index, message.clone())
+                .await?;
+            assert!(pub_key.verify(message, signature).await?);
+            assert_eq!(1, api_send.lair_get_entry_count().await?);
+        }
+        Ok(())
     }
 }
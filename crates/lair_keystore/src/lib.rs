@@ -11,22 +11,619 @@ pub mod internal;
 
 pub mod store;
 
+pub mod stores;
+
+pub mod migrate;
+
+pub mod device_binding;
+
 pub mod ipc;
 
+pub mod bench;
+
+pub mod watch;
+
+pub mod settings;
+
+#[cfg(not(windows))]
+pub mod ssh_agent;
+
+/// Parse a minimum passphrase score, 0 to `MAX_PASSPHRASE_SCORE`.
+pub fn parse_passphrase_score(score: &str) -> LairResult<u8> {
+    use passphrase_strength::MAX_PASSPHRASE_SCORE;
+    match score.parse::<u8>() {
+        Ok(score) if score <= MAX_PASSPHRASE_SCORE => Ok(score),
+        _ => Err(format!(
+            "invalid passphrase score {:?}, expected 0 to {}",
+            score, MAX_PASSPHRASE_SCORE,
+        )
+        .into()),
+    }
+}
+
+/// Parse a maximum number of connections, at least 1.
+pub fn parse_max_connections(max: &str) -> LairResult<usize> {
+    match max.parse::<usize>() {
+        Ok(max) if max >= 1 => Ok(max),
+        _ => Err(format!(
+            "invalid max connections {:?}, expected at least 1",
+            max
+        )
+        .into()),
+    }
+}
+
+/// Parse an octal socket mode, e.g. `600` or `0o660`, readable and
+/// writable by the owner, and granting nothing past `0777`.
+pub fn parse_socket_mode(mode: &str) -> LairResult<u32> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    match u32::from_str_radix(digits, 8) {
+        Ok(m) if m & !0o777 == 0 && m & 0o600 == 0o600 => Ok(m),
+        _ => Err(format!(
+            "invalid socket mode {:?}, expected octal, at most 777, \
+            readable and writable by the owner",
+            mode
+        )
+        .into()),
+    }
+}
+
+/// Parse an idle connection timeout, in whole seconds, at least 1.
+pub fn parse_idle_connection_timeout(
+    secs: &str,
+) -> LairResult<std::time::Duration> {
+    match secs.parse::<u64>() {
+        Ok(secs) if secs >= 1 => Ok(std::time::Duration::from_secs(secs)),
+        _ => Err(format!(
+            "invalid idle connection timeout {:?}, expected at least 1 second",
+            secs
+        )
+        .into()),
+    }
+}
+
+/// Parse a `<max requests>:<max wait secs>` locked queue, e.g. `64:60`,
+/// both at least 1. See `ConfigBuilder::set_queue_while_locked`.
+pub fn parse_queue_while_locked(queue: &str) -> LairResult<LockedQueueOptions> {
+    let parsed = queue.split_once(':').and_then(|(max_len, secs)| {
+        Some((max_len.parse::<usize>().ok()?, secs.parse::<u64>().ok()?))
+    });
+    match parsed {
+        Some((max_len, secs)) if max_len >= 1 && secs >= 1 => {
+            Ok(LockedQueueOptions {
+                max_len,
+                max_wait: std::time::Duration::from_secs(secs),
+            })
+        }
+        _ => Err(format!(
+            "invalid locked queue {:?}, expected <max requests>:<max wait secs>, both at least 1",
+            queue
+        )
+        .into()),
+    }
+}
+
+/// Parse a `<capabilities>:<max secs>` elevation policy, e.g.
+/// `export_private,admin:300`, the seconds at least 1.
+/// See `ConfigBuilder::set_elevation`.
+pub fn parse_elevation(elevation: &str) -> LairResult<ElevationOptions> {
+    let parsed = elevation.rsplit_once(':').and_then(|(capabilities, secs)| {
+        Some((capabilities.parse().ok()?, secs.parse::<u64>().ok()?))
+    });
+    match parsed {
+        Some((capabilities, secs)) if secs >= 1 => Ok(ElevationOptions {
+            capabilities,
+            max_duration: std::time::Duration::from_secs(secs),
+        }),
+        _ => Err(format!(
+            "invalid elevation {:?}, expected <capabilities>:<max secs>, at least 1 second",
+            elevation
+        )
+        .into()),
+    }
+}
+
+/// Parse what expiry enforcement does while the clock is skewed:
+/// `fail-closed` or `fail-open`. See `ConfigBuilder::set_clock_skew_policy`.
+pub fn parse_clock_skew_policy(policy: &str) -> LairResult<ClockSkewPolicy> {
+    match policy {
+        "fail-closed" => Ok(ClockSkewPolicy::FailClosed),
+        "fail-open" => Ok(ClockSkewPolicy::FailOpen),
+        _ => Err(format!(
+            "invalid clock skew policy {:?}, expected fail-closed or fail-open",
+            policy
+        )
+        .into()),
+    }
+}
+
+/// Parse the kind of public identifier `lair-keystore find` searches:
+/// `sign`, `cert` or `any`.
+pub fn parse_prefix_kind(
+    kind: &str,
+) -> LairResult<lair_keystore_api::actor::PrefixKind> {
+    use lair_keystore_api::actor::PrefixKind;
+    match kind {
+        "sign" => Ok(PrefixKind::SignPubKey),
+        "cert" => Ok(PrefixKind::CertDigest),
+        "any" => Ok(PrefixKind::Any),
+        _ => Err(format!(
+            "invalid prefix kind {:?}, expected sign, cert or any",
+            kind
+        )
+        .into()),
+    }
+}
+
+/// Parse a truncated pub key or cert digest, as pasted by a human,
+/// into the whole bytes it encodes: hex if `hex`, else base64 in the
+/// standard or url safe alphabet, with or without padding.
+/// A prefix cut mid-byte, as most base64 lengths and odd hex lengths
+/// are, has the bits of its partial last byte dropped, not guessed.
+pub fn parse_entry_prefix(prefix: &str, hex: bool) -> LairResult<Vec<u8>> {
+    let prefix = prefix.trim();
+    if hex {
+        if !prefix.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex prefix {:?}", prefix).into());
+        }
+        let whole = prefix.len() - prefix.len() % 2;
+        return Ok((0..whole)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&prefix[i..i + 2], 16).unwrap())
+            .collect());
+    }
+
+    let mut out = Vec::new();
+    let mut bits = 0_u32;
+    let mut bit_count = 0;
+    for c in prefix.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => {
+                return Err(format!("invalid base64 prefix {:?}", prefix).into())
+            }
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Ok(out)
+}
+
+/// How an ed25519 seed is written out for a human,
+/// see `parse_seed` and `format_seed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedFormat {
+    /// A 24 word english bip39 mnemonic.
+    Mnemonic,
+
+    /// 64 hex characters.
+    Hex,
+}
+
+impl std::str::FromStr for SeedFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mnemonic" => Ok(Self::Mnemonic),
+            "hex" => Ok(Self::Hex),
+            _ => Err(format!("unknown seed format: {}", s)),
+        }
+    }
+}
+
+/// Parse a 32 byte ed25519 seed written as `format`, or as either if
+/// None. Errors never repeat the text, which is secret.
+pub fn parse_seed(
+    text: &str,
+    format: Option<SeedFormat>,
+) -> LairResult<zeroize::Zeroizing<[u8; 32]>> {
+    let text = text.trim();
+    let is_hex =
+        text.len() == 64 && text.bytes().all(|c| c.is_ascii_hexdigit());
+    let mut seed = zeroize::Zeroizing::new([0; 32]);
+    match format {
+        Some(SeedFormat::Hex) | None if is_hex => {
+            for (i, byte) in seed.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16)
+                    .map_err(LairError::other)?;
+            }
+        }
+        Some(SeedFormat::Hex) => {
+            return Err("a hex seed is exactly 64 hex characters".into())
+        }
+        Some(SeedFormat::Mnemonic) | None => {
+            let mnemonic = bip39::Mnemonic::parse(text)
+                .map_err(|e| format!("invalid mnemonic: {}", e))?;
+            if mnemonic.word_count() != 24 {
+                return Err(format!(
+                    "a seed mnemonic is 24 words, not {}",
+                    mnemonic.word_count()
+                )
+                .into());
+            }
+            let (mut entropy, _) = mnemonic.to_entropy_array();
+            seed.copy_from_slice(&entropy[..32]);
+            zeroize::Zeroize::zeroize(&mut entropy);
+        }
+    }
+    Ok(seed)
+}
+
+/// Write out a 32 byte ed25519 seed as `format`.
+pub fn format_seed(
+    seed: &[u8; 32],
+    format: SeedFormat,
+) -> LairResult<zeroize::Zeroizing<String>> {
+    let mut out = zeroize::Zeroizing::new(String::with_capacity(64 * 4));
+    match format {
+        SeedFormat::Hex => {
+            for byte in seed.iter() {
+                out.push_str(&format!("{:02x}", byte));
+            }
+        }
+        SeedFormat::Mnemonic => {
+            let mnemonic = bip39::Mnemonic::from_entropy(seed)
+                .map_err(|e| format!("invalid seed: {}", e))?;
+            for (i, word) in mnemonic.words().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                out.push_str(word);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a `<name>=<capabilities>` grant, e.g. a listener url or
+/// connection token followed by a comma separated list of capability
+/// names: `unix:///run/lair.sock?token=abc=read_public,sign`.
+/// The name may contain `=`, the capabilities never do.
+pub fn parse_capability_grant(
+    grant: &str,
+) -> LairResult<(String, lair_keystore_api::actor::LairCapabilities)> {
+    match grant.rsplit_once('=') {
+        Some((name, capabilities)) if !name.is_empty() => {
+            Ok((name.to_string(), capabilities.parse()?))
+        }
+        _ => Err(format!(
+            "invalid capability grant {:?}, expected <name>=<capabilities>",
+            grant
+        )
+        .into()),
+    }
+}
+
 /// Main loop of lair executable.
 pub async fn execute_lair() -> LairResult<()> {
+    // refuse to touch a store with miscompiled crypto
+    lair_keystore_api::internal::self_test::self_test().await?;
+
     let mut config = Config::builder();
 
     if let Some(lair_dir) = std::env::var_os("LAIR_DIR") {
         config = config.set_root_path(lair_dir);
     }
 
+    if let Some(ssh_agent_socket) = std::env::var_os("LAIR_SSH_AGENT_SOCKET") {
+        config = config.set_ssh_agent_socket_path(ssh_agent_socket);
+    }
+
+    // whitespace separated, urls percent-encode any whitespace
+    if let Some(listeners) = std::env::var_os("LAIR_EXTRA_LISTENERS") {
+        for url in listeners.to_string_lossy().split_whitespace() {
+            config = config.add_listener(url.parse()?);
+        }
+    }
+
+    // whitespace separated <url>=<capabilities>
+    if let Some(grants) = std::env::var_os("LAIR_LISTENER_CAPABILITIES") {
+        for grant in grants.to_string_lossy().split_whitespace() {
+            let (url, capabilities) = parse_capability_grant(grant)?;
+            config =
+                config.set_listener_capabilities(url.parse()?, capabilities);
+        }
+    }
+
+    // whitespace separated <token>=<capabilities>, kept off the
+    // command line, where other users could read the tokens
+    if let Some(grants) = std::env::var_os("LAIR_CONNECTION_TOKENS") {
+        for grant in grants.to_string_lossy().split_whitespace() {
+            let (token, capabilities) = parse_capability_grant(grant)?;
+            config = config.add_connection_token(token, capabilities);
+        }
+    }
+
+    if let Some(elevation) = std::env::var_os("LAIR_ELEVATION") {
+        config = config
+            .set_elevation(parse_elevation(&elevation.to_string_lossy())?);
+    }
+
+    if std::env::var_os("LAIR_HIDE_PATHS").is_some() {
+        config = config.set_hide_paths(true);
+    }
+
+    if std::env::var_os("LAIR_WIRE_COMPRESSION").is_some() {
+        config = config.set_wire_compression(true);
+    }
+
+    if std::env::var_os("LAIR_DEEP_LOCK").is_some() {
+        config = config.set_deep_lock(true);
+    }
+
+    if let Some(score) = std::env::var_os("LAIR_MIN_PASSPHRASE_SCORE") {
+        config = config.set_min_passphrase_score(parse_passphrase_score(
+            &score.to_string_lossy(),
+        )?);
+    }
+
+    if let Some(max) = std::env::var_os("LAIR_MAX_CONNECTIONS") {
+        config = config.set_max_connections(parse_max_connections(
+            &max.to_string_lossy(),
+        )?);
+    }
+
+    if let Some(secs) = std::env::var_os("LAIR_IDLE_CONNECTION_TIMEOUT_SECS") {
+        config = config.set_idle_connection_timeout(
+            parse_idle_connection_timeout(&secs.to_string_lossy())?,
+        );
+    }
+
+    if let Some(path) = std::env::var_os("LAIR_SETTINGS_FILE") {
+        config = config.set_settings_path(path);
+    }
+
+    if let Some(mode) = std::env::var_os("LAIR_SOCKET_MODE") {
+        config =
+            config.set_socket_mode(parse_socket_mode(&mode.to_string_lossy())?);
+    }
+
+    if let Some(queue) = std::env::var_os("LAIR_QUEUE_WHILE_LOCKED") {
+        config = config.set_queue_while_locked(parse_queue_while_locked(
+            &queue.to_string_lossy(),
+        )?);
+    }
+
+    if std::env::var_os("LAIR_ALLOW_WEAK_PASSPHRASE").is_some() {
+        config = config.set_allow_weak_passphrase(true);
+    }
+
+    if std::env::var_os("LAIR_DANGER_ALLOW_INSECURE_PERMISSIONS").is_some() {
+        config = config.danger_set_allow_insecure_permissions(true);
+    }
+
+    if std::env::var_os("LAIR_STRICT_LOAD").is_some() {
+        config = config.set_strict_load(true);
+    }
+
+    if std::env::var_os("LAIR_FORBID_SEED_EXPORT").is_some() {
+        config = config.set_forbid_seed_export(true);
+    }
+
+    if std::env::var_os("LAIR_AUTO_ACTIVATE_IMPORTS").is_some() {
+        config = config.set_auto_activate_imports(true);
+    }
+
+    if std::env::var_os("LAIR_AUTO_MIGRATE").is_some() {
+        config = config.set_auto_migrate(true);
+    }
+
+    if let Some(policy) = std::env::var_os("LAIR_CLOCK_SKEW_POLICY") {
+        config = config.set_clock_skew_policy(parse_clock_skew_policy(
+            &policy.to_string_lossy(),
+        )?);
+    }
+
+    // the pin is kept off the command line, like connection tokens
+    #[cfg(feature = "pkcs11")]
+    if let Some(module) = std::env::var_os("LAIR_PKCS11_MODULE") {
+        let token = std::env::var("LAIR_PKCS11_TOKEN").map_err(|_| {
+            LairError::from("LAIR_PKCS11_MODULE needs LAIR_PKCS11_TOKEN")
+        })?;
+        let pin = std::env::var("LAIR_PKCS11_PIN").map_err(|_| {
+            LairError::from("LAIR_PKCS11_MODULE needs LAIR_PKCS11_PIN")
+        })?;
+        config = config.set_hardware_token(hardware::Pkcs11Token::new_handle(
+            module.as_ref(),
+            &token,
+            pin,
+        )?);
+    }
+
     let config = config.build();
 
+    if config.get_settings_path().is_some() {
+        settings::reload_settings(&config).await?;
+        #[cfg(unix)]
+        settings::spawn_reload_on_hangup(config.clone())?;
+    }
+
+    internal::perms::check_permissions(&config)?;
+
     let internal::pid_check::PidCheckResult { store_file } =
         internal::pid_check::pid_check(&config)?;
 
-    ipc::spawn_bind_server_ipc(config, store_file).await?;
+    ipc::spawn_bind_server_ipc(config.clone(), store_file).await?;
+
+    // let clients discover how to connect to us
+    tokio::fs::write(
+        config.get_connection_url_path(),
+        config
+            .get_listener_urls()
+            .iter()
+            .map(|url| url.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+    .await
+    .map_err(LairError::other)?;
+    lair_keystore_api::internal::util::set_private_permissions(
+        config.get_connection_url_path(),
+    )
+    .map_err(LairError::other)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_entry_prefixes_to_whole_bytes() {
+        let key = (0..32).map(|i| i * 7).collect::<Vec<u8>>();
+        let b64 = base64::encode(&key);
+        let b64_url = base64::encode_config(&key, base64::URL_SAFE_NO_PAD);
+
+        // 8 chars are 6 whole bytes, 7 chars 5 whole bytes and 4 bits
+        assert_eq!(key[..6], parse_entry_prefix(&b64[..8], false).unwrap()[..]);
+        assert_eq!(key[..5], parse_entry_prefix(&b64[..7], false).unwrap()[..]);
+        assert_eq!(
+            key[..5],
+            parse_entry_prefix(&b64_url[..7], false).unwrap()[..]
+        );
+        assert_eq!(key, parse_entry_prefix(&b64, false).unwrap());
+        assert_eq!(key, parse_entry_prefix(&b64_url, false).unwrap());
+
+        assert_eq!(
+            vec![0xde, 0xad, 0xbe],
+            parse_entry_prefix("deadbeE", true).unwrap()
+        );
+        assert!(parse_entry_prefix("dead!", true).is_err());
+        assert!(parse_entry_prefix("abc*", false).is_err());
+    }
+
+    #[test]
+    fn it_parses_and_formats_seeds() {
+        // the all zero seed is 23 times "abandon" then "art"
+        let zero = [0_u8; 32];
+        let mnemonic = format_seed(&zero, SeedFormat::Mnemonic).unwrap();
+        assert_eq!(format!("{}art", "abandon ".repeat(23)), mnemonic.as_str());
+        assert_eq!(zero, *parse_seed(&mnemonic, None).unwrap());
+
+        let seed: [u8; 32] = std::array::from_fn(|i| (i * 7) as u8);
+        for format in &[SeedFormat::Mnemonic, SeedFormat::Hex] {
+            let text = format_seed(&seed, *format).unwrap();
+            assert_eq!(seed, *parse_seed(&text, Some(*format)).unwrap());
+            assert_eq!(
+                seed,
+                *parse_seed(&format!(" {}\n", *text), None).unwrap()
+            );
+        }
+        let hex = format_seed(&seed, SeedFormat::Hex).unwrap();
+        assert_eq!(64, hex.len());
+        assert_eq!(seed, *parse_seed(&hex.to_uppercase(), None).unwrap());
+
+        assert!(parse_seed(&hex, Some(SeedFormat::Mnemonic)).is_err());
+        assert!(parse_seed(&mnemonic, Some(SeedFormat::Hex)).is_err());
+        assert!(parse_seed(&hex[..62], None).is_err());
+        // a 12 word mnemonic is too short, a bad checksum is refused
+        assert!(parse_seed(&format!("{}about", "abandon ".repeat(11)), None)
+            .is_err());
+        assert!(
+            parse_seed(&format!("{}abandon", "abandon ".repeat(23)), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn it_parses_capability_grants() {
+        use lair_keystore_api::actor::*;
+        let (url, caps) = parse_capability_grant(
+            "unix:///run/lair.sock?token=abc==read_public,sign",
+        )
+        .unwrap();
+        assert_eq!("unix:///run/lair.sock?token=abc=", url);
+        assert_eq!(
+            LairCapabilities::NONE
+                .with(LairCapability::ReadPublic)
+                .with(LairCapability::Sign),
+            caps
+        );
+        let (token, caps) = parse_capability_grant("tok=").unwrap();
+        assert_eq!("tok", token);
+        assert_eq!(LairCapabilities::NONE, caps);
+        assert!(parse_capability_grant("tok").is_err());
+        assert!(parse_capability_grant("=sign").is_err());
+        assert!(parse_capability_grant("tok=sign,launch").is_err());
+    }
+
+    #[test]
+    fn it_parses_connection_limits() {
+        assert_eq!(3, parse_max_connections("3").unwrap());
+        assert!(parse_max_connections("0").is_err());
+        assert!(parse_max_connections("-1").is_err());
+        assert_eq!(
+            std::time::Duration::from_secs(90),
+            parse_idle_connection_timeout("90").unwrap()
+        );
+        assert!(parse_idle_connection_timeout("0").is_err());
+        assert!(parse_idle_connection_timeout("1.5").is_err());
+    }
+
+    #[test]
+    fn it_parses_socket_modes() {
+        assert_eq!(0o600, parse_socket_mode("600").unwrap());
+        assert_eq!(0o600, parse_socket_mode("0600").unwrap());
+        assert_eq!(0o660, parse_socket_mode("0o660").unwrap());
+        assert_eq!(0o777, parse_socket_mode("777").unwrap());
+        assert!(parse_socket_mode("400").is_err());
+        assert!(parse_socket_mode("1600").is_err());
+        assert!(parse_socket_mode("680").is_err());
+        assert!(parse_socket_mode("").is_err());
+    }
+
+    #[test]
+    fn it_parses_locked_queues() {
+        assert_eq!(
+            LockedQueueOptions {
+                max_len: 8,
+                max_wait: std::time::Duration::from_secs(30),
+            },
+            parse_queue_while_locked("8:30").unwrap()
+        );
+        for bad in &["8", "0:30", "8:0", "8:1.5", ":30", "a:b"] {
+            assert!(parse_queue_while_locked(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn it_parses_elevations() {
+        use lair_keystore_api::actor::*;
+        assert_eq!(
+            ElevationOptions {
+                capabilities: LairCapabilities::NONE
+                    .with(LairCapability::ExportPrivate)
+                    .with(LairCapability::Admin),
+                max_duration: std::time::Duration::from_secs(300),
+            },
+            parse_elevation("export_private,admin:300").unwrap()
+        );
+        for bad in &["admin", "admin:0", "admin:1.5", "nope:30", "admin:"] {
+            assert!(parse_elevation(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn it_parses_clock_skew_policies() {
+        assert_eq!(
+            ClockSkewPolicy::FailClosed,
+            parse_clock_skew_policy("fail-closed").unwrap()
+        );
+        assert_eq!(
+            ClockSkewPolicy::FailOpen,
+            parse_clock_skew_policy("fail-open").unwrap()
+        );
+        assert!(parse_clock_skew_policy("open").is_err());
+    }
+}
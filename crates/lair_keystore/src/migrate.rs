@@ -0,0 +1,144 @@
+//! Migrating stores in a legacy layout, see `lair-keystore migrate`.
+//!
+//! Stores written before the index was encrypted keep their entries in
+//! plaintext in the store file, and their tags, trash and inactive
+//! entries in plaintext beside it. They are
+//! refused until migrated, either offline by `encrypt_legacy_index`,
+//! or by the first unlock of a server configured to `set_auto_migrate`.
+//! Either way the legacy files are first backed up to `backup_path`.
+
+use crate::*;
+use lair_keystore_api::internal::{unlock, util};
+use lair_keystore_api::passphrase::Passphrase;
+use std::path::PathBuf;
+use store::store_file::{self, EntryStoreFileSender};
+
+/// The directory the store described by `config` is backed up to,
+/// as it was before it was migrated.
+pub fn backup_path(config: &Config) -> PathBuf {
+    let store_path = config.get_store_path();
+    store_path
+        .parent()
+        .unwrap_or(store_path)
+        .join("legacy-backup")
+}
+
+/// True if the store described by `config` has a legacy unencrypted
+/// index. A store that does not exist yet, or was never unlocked,
+/// has none, nor does one whose migration committed, and is finished
+/// when it is next opened.
+pub fn has_legacy_index(config: &Config) -> LairResult<bool> {
+    use std::io::Read;
+
+    if store_file::sealing_path(config.get_store_path()).exists() {
+        return Ok(false);
+    }
+
+    let mut store_file = match std::fs::File::open(config.get_store_path()) {
+        Ok(store_file) => store_file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => {
+            return Err(LairError::path_context(
+                "reading",
+                config.get_store_path(),
+                e,
+            ))
+        }
+    };
+    let mut unlock_entry = vec![0; entry::ENTRY_SIZE];
+    match store_file.read_exact(&mut unlock_entry) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Ok(false)
+        }
+        Err(e) => {
+            return Err(LairError::path_context(
+                "reading",
+                config.get_store_path(),
+                e,
+            ))
+        }
+    }
+    Ok(matches!(
+        unlock::UnlockEntry::decode(&unlock_entry)?,
+        Some(unlock_entry) if !unlock_entry.encrypted_index
+    ))
+}
+
+/// Migrate the store described by `config` to an encrypted index,
+/// verifying `passphrase` first. Nothing may be serving the store.
+/// Returns false, changing nothing, if it had no legacy index.
+pub async fn encrypt_legacy_index(
+    config: Arc<Config>,
+    passphrase: Passphrase,
+) -> LairResult<bool> {
+    if !has_legacy_index(&config)? {
+        return Ok(false);
+    }
+    crate::internal::perms::check_permissions(&config)?;
+
+    let store_file = util::private_open_options()
+        // not append, the unlock entry is rewritten in place
+        .write(true)
+        .read(true)
+        .open(config.get_store_path())
+        .map_err(|e| {
+            LairError::path_context("opening", config.get_store_path(), e)
+        })?;
+    let store_file = store_file::spawn_entry_store_file_task(
+        tokio::fs::File::from_std(store_file),
+        config.get_store_path().to_path_buf(),
+        config.get_store_number(),
+        config.get_entropy().clone(),
+        config.get_tags_path().to_path_buf(),
+        config.get_trash_path().to_path_buf(),
+        config.get_inactive_path().to_path_buf(),
+        config.get_server_id_path().to_path_buf(),
+    )
+    .await?;
+    let unlock_entry = match store_file.init_load_unlock().await? {
+        Some(unlock_entry) => unlock::UnlockEntry::decode(&unlock_entry)?
+            .ok_or_else(|| LairError::from("store was never unlocked"))?,
+        None => return Err("store was never unlocked".into()),
+    };
+
+    let device_secret = match unlock_entry.device_bound {
+        true => device_secret::load_device_secret(&config)?,
+        false => None,
+    };
+    let index_key = unlock::unlock_entry_verify(
+        &unlock_entry,
+        passphrase,
+        device_secret,
+        |_| (),
+    )
+    .await?;
+    encrypt_index(&config, &store_file, unlock_entry, index_key).await?;
+    Ok(true)
+}
+
+/// Seal the index of a store whose legacy `unlock_entry` was verified,
+/// deriving `index_key`, backing it up first. Returns the unlock entry
+/// recording the encrypted index.
+pub(crate) async fn encrypt_index(
+    config: &Config,
+    store_file: &futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    unlock_entry: unlock::UnlockEntry,
+    index_key: unlock::IndexKey,
+) -> LairResult<unlock::UnlockEntry> {
+    let backup = backup_path(config);
+    let unlock_entry = unlock::UnlockEntry {
+        encrypted_index: true,
+        ..unlock_entry
+    };
+    store_file
+        .encrypt_index(index_key, unlock_entry.encode()?, Some(backup.clone()))
+        .await?;
+    tracing::info!(
+        "migrated store {} to an encrypted index, the legacy store is \
+        backed up to {}",
+        config.get_store_path().display(),
+        backup.display(),
+    );
+    Ok(unlock_entry)
+}
@@ -0,0 +1,210 @@
+//! The settings file of a running lair server, see
+//! `ConfigBuilder::set_settings_path`: the `RuntimeSettings` it may change
+//! without a restart, read at startup, and again on SIGHUP or
+//! `lair_reload_settings`.
+//!
+//! Each line is a `NAME=value` setting, named as its env var, e.g.
+//! `LAIR_MAX_CONNECTIONS=64`. Blank lines and `#` comments are skipped.
+//! A setting left out keeps its current value, an optional one given
+//! empty is unset, e.g. `LAIR_MAX_CONNECTIONS=` lifts the limit.
+
+use crate::*;
+
+/// Parse a per-connection event buffer size, at least 1.
+pub fn parse_event_buffer_size(size: &str) -> LairResult<usize> {
+    match size.parse::<usize>() {
+        Ok(size) if size >= 1 => Ok(size),
+        _ => Err(format!(
+            "invalid event buffer size {:?}, expected at least 1",
+            size
+        )
+        .into()),
+    }
+}
+
+/// Parse what happens to a connection's events once its buffer is full:
+/// `drop-oldest` or `disconnect`.
+/// See `ConfigBuilder::set_slow_consumer_policy`.
+pub fn parse_slow_consumer_policy(
+    policy: &str,
+) -> LairResult<SlowConsumerPolicy> {
+    match policy {
+        "drop-oldest" => Ok(SlowConsumerPolicy::DropOldest),
+        "disconnect" => Ok(SlowConsumerPolicy::Disconnect),
+        _ => Err(format!(
+            "invalid slow consumer policy {:?}, expected drop-oldest or disconnect",
+            policy
+        )
+        .into()),
+    }
+}
+
+/// Parse a trash retention, in whole seconds.
+pub fn parse_trash_retention(secs: &str) -> LairResult<std::time::Duration> {
+    match secs.parse::<u64>() {
+        Ok(secs) => Ok(std::time::Duration::from_secs(secs)),
+        _ => Err(format!(
+            "invalid trash retention {:?}, expected whole seconds",
+            secs
+        )
+        .into()),
+    }
+}
+
+/// Apply the settings file `contents` over the `current` settings.
+/// Settings that only apply on restart are logged and skipped.
+/// Fails on the first invalid line, so a file is applied whole or not
+/// at all.
+pub fn parse_settings(
+    current: RuntimeSettings,
+    contents: &str,
+) -> LairResult<RuntimeSettings> {
+    fn optional<T>(
+        value: &str,
+        parse: impl FnOnce(&str) -> LairResult<T>,
+    ) -> LairResult<Option<T>> {
+        match value {
+            "" => Ok(None),
+            value => parse(value).map(Some),
+        }
+    }
+
+    let mut settings = current;
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let res = match line.split_once('=') {
+            None => Err("expected NAME=value".into()),
+            Some((name, value)) => match (name.trim(), value.trim()) {
+                ("LAIR_MAX_CONNECTIONS", value) => {
+                    optional(value, parse_max_connections)
+                        .map(|max| settings.max_connections = max)
+                }
+                ("LAIR_IDLE_CONNECTION_TIMEOUT_SECS", value) => {
+                    optional(value, parse_idle_connection_timeout).map(
+                        |timeout| settings.idle_connection_timeout = timeout,
+                    )
+                }
+                ("LAIR_EVENT_BUFFER_SIZE", value) => {
+                    parse_event_buffer_size(value)
+                        .map(|size| settings.event_buffer_size = size)
+                }
+                ("LAIR_SLOW_CONSUMER_POLICY", value) => {
+                    parse_slow_consumer_policy(value)
+                        .map(|policy| settings.slow_consumer_policy = policy)
+                }
+                ("LAIR_TRASH_RETENTION_SECS", value) => {
+                    optional(value, parse_trash_retention)
+                        .map(|retention| settings.trash_retention = retention)
+                }
+                (name, _) => {
+                    tracing::warn!(
+                        "skipped setting {}, it only applies on restart",
+                        name
+                    );
+                    Ok(())
+                }
+            },
+        };
+        res.map_err(|err: LairError| {
+            LairError::from(format!("settings line {}: {}", line_no + 1, err))
+        })?;
+    }
+    Ok(settings)
+}
+
+/// Read the settings file of `config` and apply it, see `parse_settings`.
+/// Fails, logging why and leaving the settings as they were, if the
+/// file is invalid, or `config` has none.
+pub async fn reload_settings(config: &Config) -> LairResult<()> {
+    let res = async {
+        let path = config.get_settings_path().ok_or_else(|| {
+            LairError::from("no settings file, see LAIR_SETTINGS_FILE")
+        })?;
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(LairError::other)?;
+        parse_settings(config.get_runtime_settings(), &contents)
+    }
+    .await;
+    match res {
+        Ok(settings) => {
+            tracing::info!("applied settings {:?}", settings);
+            config.set_runtime_settings(settings);
+            Ok(())
+        }
+        Err(err) => {
+            tracing::warn!("refused settings reload: {:?}", err);
+            Err(err)
+        }
+    }
+}
+
+/// Reload the settings file of `config` on each SIGHUP,
+/// for as long as the process runs.
+#[cfg(unix)]
+pub fn spawn_reload_on_hangup(config: Arc<Config>) -> LairResult<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangups = signal(SignalKind::hangup()).map_err(LairError::other)?;
+    tokio::task::spawn(async move {
+        while hangups.recv().await.is_some() {
+            // refusals are logged, the server runs on as it was
+            let _ = reload_settings(&config).await;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_settings_files() {
+        let current = RuntimeSettings::default();
+        let settings = parse_settings(
+            current,
+            "# limits\n\
+            LAIR_MAX_CONNECTIONS = 8\n\
+            \n\
+            LAIR_IDLE_CONNECTION_TIMEOUT_SECS=90\n\
+            LAIR_EVENT_BUFFER_SIZE=32\n\
+            LAIR_SLOW_CONSUMER_POLICY=disconnect\n\
+            LAIR_TRASH_RETENTION_SECS=0\n\
+            LAIR_SOCKET_MODE=660\n",
+        )
+        .unwrap();
+        assert_eq!(Some(8), settings.max_connections);
+        assert_eq!(
+            Some(std::time::Duration::from_secs(90)),
+            settings.idle_connection_timeout
+        );
+        assert_eq!(32, settings.event_buffer_size);
+        assert_eq!(
+            SlowConsumerPolicy::Disconnect,
+            settings.slow_consumer_policy
+        );
+        assert_eq!(
+            Some(std::time::Duration::from_secs(0)),
+            settings.trash_retention
+        );
+
+        // left out settings are kept, empty ones unset
+        let settings =
+            parse_settings(settings, "LAIR_MAX_CONNECTIONS=\n").unwrap();
+        assert_eq!(None, settings.max_connections);
+        assert_eq!(32, settings.event_buffer_size);
+
+        for bad in &[
+            "LAIR_MAX_CONNECTIONS=0",
+            "LAIR_EVENT_BUFFER_SIZE=",
+            "LAIR_SLOW_CONSUMER_POLICY=drop",
+            "LAIR_TRASH_RETENTION_SECS=1.5",
+            "LAIR_MAX_CONNECTIONS=8\nLAIR_MAX_CONNECTIONS",
+        ] {
+            assert!(parse_settings(current, bad).is_err());
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! ssh-agent protocol listener exposing lair ed25519 keys to ssh / git.
+//!
+//! Only the identity listing and signing requests are supported,
+//! so private keys never leave the keystore. Requests to add or
+//! remove identities (and anything else) receive SSH_AGENT_FAILURE.
+//!
+//! Each agent connection is served over its own in-process lair
+//! connection, granted `SSH_AGENT_CAPABILITIES` only, so it is
+//! authorized, audited and counted as any other connection. It is never
+//! asked for the unlock passphrase, and only offers activated keys.
+
+use crate::*;
+use lair_keystore_api::{actor::*, internal::util::*};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_ED25519: &[u8] = b"ssh-ed25519";
+
+/// refuse to buffer agent messages larger than this
+const MAX_MSG_LEN: usize = 256 * 1024;
+
+/// The capabilities of the lair connection serving an agent connection.
+pub const SSH_AGENT_CAPABILITIES: LairCapabilities = LairCapabilities(
+    LairCapability::ReadPublic as u32 | LairCapability::Sign as u32,
+);
+
+/// Bind the ssh-agent listener at the configured socket path, serving
+/// each connection over a lair connection opened with `in_proc`.
+/// Does nothing if the config does not specify an ssh-agent socket path.
+pub async fn spawn_bind_ssh_agent(
+    config: Arc<Config>,
+    in_proc: lair_keystore_api::ipc::LairInProcListener,
+) -> LairResult<()> {
+    let path = match config.get_ssh_agent_socket_path() {
+        None => return Ok(()),
+        Some(path) => path,
+    };
+
+    let srv = bind_private_unix_socket(path, config.get_socket_mode())?;
+    srv.set_nonblocking(true).map_err(LairError::other)?;
+    let mut srv =
+        tokio::net::UnixListener::from_std(srv).map_err(LairError::other)?;
+
+    err_spawn("ssh-agent-bind", async move {
+        loop {
+            let (con, _) = srv.accept().await.map_err(LairError::other)?;
+            err_spawn("ssh-agent-con", handle_con(con, in_proc.clone()));
+        }
+    });
+
+    Ok(())
+}
+
+async fn handle_con(
+    con: tokio::net::UnixStream,
+    in_proc: lair_keystore_api::ipc::LairInProcListener,
+) -> LairResult<()> {
+    use ghost_actor::GhostControlSender;
+
+    let (api_sender, mut evt_recv) =
+        in_proc.connect(SSH_AGENT_CAPABILITIES).await?;
+    // the agent has nothing to add to any event, nor can it ask the
+    // ssh client for a passphrase
+    tokio::task::spawn(async move {
+        use futures::stream::StreamExt;
+        while let Some(evt) = evt_recv.next().await {
+            evt.respond_default();
+        }
+    });
+    let res = serve_con(con, &api_sender).await;
+    let _ = api_sender.ghost_actor_shutdown().await;
+    res
+}
+
+async fn serve_con(
+    mut con: tokio::net::UnixStream,
+    api_sender: &ghost_actor::GhostSender<LairClientApi>,
+) -> LairResult<()> {
+    loop {
+        let len = match con.read_u32().await {
+            Ok(len) => len as usize,
+            // the client hung up
+            Err(_) => return Ok(()),
+        };
+        if len == 0 || len > MAX_MSG_LEN {
+            return Err(format!("invalid ssh-agent msg len: {}", len).into());
+        }
+        let mut msg = vec![0; len];
+        con.read_exact(&mut msg).await.map_err(LairError::other)?;
+
+        let res = match handle_msg(&msg, api_sender).await {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::warn!(error = ?e, "ssh-agent request failed");
+                vec![SSH_AGENT_FAILURE]
+            }
+        };
+
+        let mut out = Vec::with_capacity(4 + res.len());
+        out.extend_from_slice(&(res.len() as u32).to_be_bytes());
+        out.extend_from_slice(&res);
+        con.write_all(&out).await.map_err(LairError::other)?;
+    }
+}
+
+async fn handle_msg(
+    msg: &[u8],
+    api_sender: &ghost_actor::GhostSender<LairClientApi>,
+) -> LairResult<Vec<u8>> {
+    let mut reader = SshReader(&msg[1..]);
+    match msg[0] {
+        SSH_AGENTC_REQUEST_IDENTITIES => {
+            use futures::stream::StreamExt;
+            let mut entries = Box::pin(lair_list_entries_stream(
+                api_sender.clone(),
+                MAX_LIST_ENTRIES_PAGE_LIMIT,
+            ));
+            let mut keys = Vec::new();
+            while let Some(info) = entries.next().await {
+                let info = info?;
+                if info.entry_type == LairEntryType::SignEd25519
+                    && !info.trashed
+                    && info.activated
+                {
+                    keys.push(info);
+                }
+            }
+
+            let mut out = vec![SSH_AGENT_IDENTITIES_ANSWER];
+            out.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+            for info in keys {
+                write_string(&mut out, &ed25519_key_blob(&info.pub_id));
+                write_string(&mut out, key_comment(&info).as_bytes());
+            }
+            Ok(out)
+        }
+        SSH_AGENTC_SIGN_REQUEST => {
+            let pub_key = {
+                let mut blob = SshReader(reader.read_string()?);
+                if blob.read_string()? != SSH_ED25519 {
+                    return Err("unsupported ssh key type".into());
+                }
+                blob.read_string()?.to_vec()
+            };
+            let data = reader.read_string()?.to_vec();
+            // flags only affect rsa signatures, ignore them
+
+            let sig = api_sender
+                .sign_ed25519_sign_by_pub_key(pub_key.into(), Arc::new(data))
+                .await?;
+
+            let mut sig_blob = Vec::new();
+            write_string(&mut sig_blob, SSH_ED25519);
+            write_string(&mut sig_blob, &sig.0);
+
+            let mut out = vec![SSH_AGENT_SIGN_RESPONSE];
+            write_string(&mut out, &sig_blob);
+            Ok(out)
+        }
+        _ => Ok(vec![SSH_AGENT_FAILURE]),
+    }
+}
+
+/// name a listed key by its entry tag, or by its index if untagged
+fn key_comment(info: &LairEntryInfo) -> String {
+    match &info.tag {
+        Some(tag) => format!("lair-keystore:{}", tag),
+        None => format!("lair-keystore:{}", info.keystore_index.0),
+    }
+}
+
+/// encode an ed25519 public key in ssh wire format
+fn ed25519_key_blob(pub_key: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_string(&mut out, SSH_ED25519);
+    write_string(&mut out, pub_key);
+    out
+}
+
+/// write an ssh length-prefixed "string"
+fn write_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+struct SshReader<'a>(&'a [u8]);
+
+impl<'a> SshReader<'a> {
+    /// read an ssh length-prefixed "string"
+    fn read_string(&mut self) -> LairResult<&'a [u8]> {
+        if self.0.len() < 4 {
+            return Err("truncated ssh-agent msg".into());
+        }
+        let len =
+            u32::from_be_bytes([self.0[0], self.0[1], self.0[2], self.0[3]])
+                as usize;
+        if self.0.len() < 4 + len {
+            return Err("truncated ssh-agent msg".into());
+        }
+        let out = &self.0[4..4 + len];
+        self.0 = &self.0[4 + len..];
+        Ok(out)
+    }
+}
@@ -3,8 +3,8 @@
 use crate::*;
 use entry::LairEntry;
 use futures::future::FutureExt;
-use lair_keystore_api::{actor::*, internal::*};
-use std::collections::HashMap;
+use lair_keystore_api::{actor::*, internal::*, passphrase::Passphrase};
+use std::collections::{BTreeMap, HashMap};
 
 ghost_actor::ghost_chan! {
     /// persistence manager for entry storage
@@ -14,12 +14,147 @@ ghost_actor::ghost_chan! {
             options: TlsCertOptions,
         ) -> (KeystoreIndex, Arc<LairEntry>);
 
+        /// generate a new tls cert entry for the keypair of a signature
+        /// ed25519 entry, bound to (referencing) that entry && return it
+        fn tls_cert_self_signed_new_from_sign_key(
+            sign_key_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
         /// generate a new signature ed25519 keypair entry && return it
         fn sign_ed25519_keypair_new_from_entropy() ->
             (KeystoreIndex, Arc<LairEntry>);
 
+        /// generate a new signature ed25519 keypair entry
+        /// governed by `policy` && return it
+        fn sign_ed25519_keypair_new_with_policy(
+            policy: entry::SignKeyPolicy,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new signature ed25519 keypair entry on the
+        /// configured hardware token && return it
+        fn sign_ed25519_hardware_keypair_new() ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// import a signature ed25519 keypair entry && return it,
+        /// or return the existing entry with the same pub key,
+        /// flagged as already existing
+        fn sign_ed25519_keypair_import(keypair: entry::EntrySignEd25519) ->
+            (KeystoreIndex, Arc<LairEntry>, bool);
+
+        /// generate a new x25519 keypair entry && return it
+        fn x25519_keypair_new_from_entropy() -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new signature secp256k1 keypair entry && return it
+        fn sign_secp256k1_keypair_new_from_entropy() ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new signature ed448 keypair entry && return it
+        fn sign_ed448_keypair_new_from_entropy() ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new signature bls keypair entry && return it
+        fn sign_bls_keypair_new_from_entropy() ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// import a signature secp256k1 keypair entry && return it,
+        /// or return the existing entry with the same pub key,
+        /// flagged as already existing
+        fn sign_secp256k1_keypair_new_from_seed(seed: [u8; 32]) ->
+            (KeystoreIndex, Arc<LairEntry>, bool);
+
+        /// generate a new signature sr25519 keypair entry && return it
+        fn sign_sr25519_keypair_new_from_entropy() ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// import a signature sr25519 keypair entry && return it,
+        /// or return the existing entry with the same pub key,
+        /// flagged as already existing
+        fn sign_sr25519_keypair_new_from_seed(seed: [u8; 32]) ->
+            (KeystoreIndex, Arc<LairEntry>, bool);
+
+        /// generate a new pw pepper entry && return it
+        fn pw_pepper_new_from_entropy() -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new totp secret entry && return it
+        fn totp_new_from_entropy(options: TotpOptions) ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// import a base32 totp secret entry && return it
+        fn totp_import(secret_b32: String, options: TotpOptions) ->
+            (KeystoreIndex, Arc<LairEntry>);
+
+        /// wrap the secret material of an entry to a recovery x25519 pub key
+        fn wrap_entry(
+            target_index: KeystoreIndex,
+            recipient_pub_key: X25519PubKey,
+        ) -> Arc<Vec<u8>>;
+
+        /// open a wrapped entry with a stored x25519 keypair,
+        /// import it as a new entry && return it, or return the existing
+        /// entry with the same pub id, flagged as already existing
+        fn unwrap_entry(
+            recipient_index: KeystoreIndex,
+            wrapped: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, Arc<LairEntry>, bool);
+
+        /// unlock the store with the passphrase supplied by a client,
+        /// setting it if the store has none yet, sending the percent
+        /// complete of the key derivation to `progress`
+        /// (unlocking an unlocked store does nothing)
+        fn unlock(
+            passphrase: Passphrase,
+            progress: futures::channel::mpsc::UnboundedSender<u8>,
+        ) -> ();
+
+        /// true until the store has been unlocked
+        fn is_locked() -> bool;
+
+        /// error with `KeystoreLocked` if deep locked
+        fn check_deep_lock() -> ();
+
+        /// with a `queue_while_locked` config, wait until the store is
+        /// unlocked, behind earlier waiting requests, holding the
+        /// returned turn while the request runs
+        fn wait_unlocked() -> unlock_queue::UnlockTurn;
+
+        /// fetch the server identity keypair, created at init
+        fn get_server_id() -> entry::EntrySignEd25519;
+
         /// fetch the highest / most recently added keystore_index
-        fn get_last_entry_index() -> KeystoreIndex;
+        fn get_last_entry_index() -> Option<KeystoreIndex>;
+
+        /// be told of every entry created in the store from now on
+        fn subscribe_new_entries() -> NewEntryReceiver;
+
+        /// be told of every entry trashed, purged or erased
+        /// in the store from now on
+        fn subscribe_removed_entries() -> RemovedEntryReceiver;
+
+        /// get the number of entries in the store
+        fn get_entry_count() -> u64;
+
+        /// get the counters describing the store
+        fn get_store_stats() -> StoreStats;
+
+        /// list a page of entry summaries in keystore index order
+        fn list_entries_page(
+            start: KeystoreIndex,
+            limit: u32,
+        ) -> (Vec<LairEntryInfo>, Option<KeystoreIndex>);
+
+        /// get the entry types of a batch of indices, in request order,
+        /// Invalid for any index with no entry in this store
+        fn get_entry_types(
+            indices: Vec<KeystoreIndex>,
+        ) -> Vec<(KeystoreIndex, LairEntryType)>;
+
+        /// find the entries whose pub id, of a kind searched,
+        /// starts with prefix, in index order, trashed entries excluded
+        fn find_entries_by_prefix(
+            prefix: Arc<Vec<u8>>,
+            kind: PrefixKind,
+        ) -> Vec<(KeystoreIndex, LairEntryType)>;
 
         /// fetch an entry from the store by keystore index
         fn get_entry_by_index(index: KeystoreIndex) -> Arc<LairEntry>;
@@ -31,15 +166,102 @@ ghost_actor::ghost_chan! {
 
         /// get a tls cert entry by sni
         fn get_entry_by_sni(sni: CertSni) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// get the digest of a tls cert entry computed with `digest_alg`
+        fn get_cert_digest(
+            index: KeystoreIndex,
+            digest_alg: DigestAlg,
+        ) -> Arc<Vec<u8>>;
+
+        /// get a tls cert entry by a digest of any algorithm
+        fn get_entry_by_cert_digest(
+            digest_alg: DigestAlg,
+            digest: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// set (or with None clear) the unique tag of an entry
+        fn set_entry_tag(index: KeystoreIndex, tag: Option<String>) -> ();
+
+        /// add an alias to an entry, persisted alongside the tags
+        fn add_entry_alias(index: KeystoreIndex, alias: String) -> ();
+
+        /// remove an alias
+        fn remove_entry_alias(alias: String) -> ();
+
+        /// get the entry currently holding a tag or alias
+        fn get_entry_by_tag(tag: String) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// get the unique tag of an entry, if it has one
+        fn get_entry_tag(index: KeystoreIndex) -> Option<String>;
+
+        /// error with `Forbidden` while the entry may not be erased:
+        /// a signature ed25519 entry cannot be erased while a tls cert
+        /// is bound to it, erase the certs first
+        fn check_entry_erasable(index: KeystoreIndex) -> ();
+
+        /// move an entry to the trash, it can no longer be used
+        /// until it is restored
+        fn trash_entry(index: KeystoreIndex) -> ();
+
+        /// take an entry back out of the trash
+        fn restore_entry(index: KeystoreIndex) -> ();
+
+        /// permanently erase entries trashed at least `older_than` ago,
+        /// returning how many were erased
+        fn purge_trashed(older_than: std::time::Duration) -> u64;
+
+        /// permanently erase entries, trashed or not, in one write,
+        /// with a result per index in request order
+        fn erase_entries(indices: Vec<KeystoreIndex>) -> Vec<LairResult<()>>;
+
+        /// set the unique tags of entries in request order, in one
+        /// write, with a result per tag
+        fn set_entry_tags(
+            tags: Vec<(KeystoreIndex, String)>,
+        ) -> Vec<LairResult<()>>;
+
+        /// let an imported entry be used, returning false
+        /// if it already could
+        fn activate_entry(index: KeystoreIndex) -> bool;
+
+        /// List the entries that could not be decoded on load.
+        fn list_quarantined() -> Vec<QuarantinedEntry>;
     }
 }
 
+/// raw entries, tags, trash and inactive entries,
+/// as read from the store files
+type LoadedEntries = (
+    Vec<(KeystoreIndex, Vec<u8>)>,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+/// a tls cert digest, with the algorithm computing it
+type TypedCertDigest = (DigestAlg, Arc<Vec<u8>>);
+
 ghost_actor::ghost_chan! {
     chan EntryStoreInternal<LairError> {
         fn finalize_new_entry(
             entry_index: KeystoreIndex,
             entry: Arc<LairEntry>,
         ) -> ();
+
+        /// hold back a newly imported entry until it is activated,
+        /// unless the config activates imports
+        fn deactivate_import(entry_index: KeystoreIndex) -> ();
+
+        fn get_unlock_state() -> (bool, Option<unlock::UnlockEntry>);
+
+        fn find_existing_entry(
+            pub_id: Arc<Vec<u8>>,
+        ) -> Option<(KeystoreIndex, Arc<LairEntry>)>;
+
+        fn finalize_unlock(
+            unlock_entry: unlock::UnlockEntry,
+            loaded: Option<LoadedEntries>,
+        ) -> ();
     }
 }
 
@@ -61,26 +283,106 @@ pub async fn spawn_entry_store_actor(
         .await?;
 
     tokio::task::spawn(
-        builder.spawn(EntryStoreImpl::new(i_s, config, store_file).await?),
+        builder
+            .spawn(EntryStoreImpl::new(i_s, config.clone(), store_file).await?),
     );
 
+    // runs without a retention too, one may be set by a settings reload
+    tokio::task::spawn(auto_purge_task(sender.clone(), config));
+
     Ok(sender)
 }
 
+/// purge expired trash until the store actor shuts down
+async fn auto_purge_task(
+    sender: ghost_actor::GhostSender<EntryStore>,
+    config: Arc<Config>,
+) {
+    use ghost_actor::GhostControlSender;
+    loop {
+        let res = match config.get_trash_retention() {
+            Some(retention) => sender.purge_trashed(retention).await,
+            None => Ok(0),
+        };
+        match res {
+            Ok(0) => (),
+            Ok(purged) => {
+                tracing::info!("auto purged {} trashed entries", purged)
+            }
+            // a deep locked store has nothing loaded to purge yet
+            Err(LairError::KeystoreLocked) => (),
+            Err(LairError::GhostError(_)) => break,
+            Err(err) => {
+                tracing::warn!("failed to auto purge trash: {:?}", err)
+            }
+        }
+        if !sender.ghost_actor_is_active() {
+            break;
+        }
+        tokio::time::delay_for(AUTO_PURGE_INTERVAL).await;
+    }
+}
+
 // -- internal -- //
 
-mod store_file;
+/// the digests indexed alongside the blake2b `CertDigest`
+const ALT_DIGEST_ALGS: &[DigestAlg] =
+    &[DigestAlg::Sha256, DigestAlg::Sha1Legacy];
+
+pub(crate) mod store_file;
+#[cfg(test)]
+pub(crate) use store_file::fault;
 use store_file::EntryStoreFileSender;
 
+/// The index and type of each entry created in a store.
+pub type NewEntryReceiver =
+    tokio::sync::broadcast::Receiver<(KeystoreIndex, LairEntryType)>;
+
+/// The index of each entry trashed, purged or erased in a store.
+pub type RemovedEntryReceiver = tokio::sync::broadcast::Receiver<KeystoreIndex>;
+
+/// Entry creations not yet taken by the slowest subscriber
+/// before it starts missing them.
+pub const NEW_ENTRY_BACKLOG: usize = 64;
+
+pub mod unlock_queue;
+
 struct EntryStoreImpl {
     i_s: ghost_actor::GhostSender<EntryStoreInternal>,
     #[allow(dead_code)]
     config: Arc<Config>,
     store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
-    last_entry_index: KeystoreIndex,
-    entries_by_index: HashMap<KeystoreIndex, Arc<LairEntry>>,
+    server_id: entry::EntrySignEd25519,
+    unlock_entry: Option<unlock::UnlockEntry>,
+    // the index files are sealed, or will be by the first unlock
+    // migrating them, nothing is loaded until then
+    index_sealed: bool,
+    // serializes unlocks, so a passphrase is only ever set once
+    unlock_lock: Arc<tokio::sync::Mutex<()>>,
+    unlock_queue: Arc<unlock_queue::UnlockQueue>,
+    // serializes imports, so racing imports cannot duplicate material
+    import_lock: Arc<tokio::sync::Mutex<()>>,
+    new_entries: tokio::sync::broadcast::Sender<(KeystoreIndex, LairEntryType)>,
+    removed_entries: tokio::sync::broadcast::Sender<KeystoreIndex>,
+    locked: bool,
+    last_entry_index: Option<KeystoreIndex>,
+    entries_by_index: BTreeMap<KeystoreIndex, Arc<LairEntry>>,
     entries_by_pub_id: HashMap<Arc<Vec<u8>>, (KeystoreIndex, Arc<LairEntry>)>,
     entries_by_sni: HashMap<CertSni, (KeystoreIndex, Arc<LairEntry>)>,
+    // the `ALT_DIGEST_ALGS` digests of every tls cert, computed once
+    alt_cert_digests: HashMap<KeystoreIndex, Vec<TypedCertDigest>>,
+    entries_by_alt_digest:
+        HashMap<TypedCertDigest, (KeystoreIndex, Arc<LairEntry>)>,
+    tags: entry::EntryTags,
+    tags_generation: u64,
+    trash: entry::EntryTrash,
+    trash_generation: u64,
+    inactive: entry::EntryInactive,
+    inactive_generation: u64,
+    quarantined: BTreeMap<KeystoreIndex, QuarantinedEntry>,
+    // kept up to date as entries change, for `get_store_stats`
+    live_counts: HashMap<LairEntryType, u64>,
+    erased_count: u64,
 }
 
 impl EntryStoreImpl {
@@ -89,290 +391,3342 @@ impl EntryStoreImpl {
         config: Arc<Config>,
         store_file: tokio::fs::File,
     ) -> LairResult<Self> {
-        let store_file =
-            store_file::spawn_entry_store_file_task(store_file).await?;
+        crate::internal::perms::check_permissions(&config)?;
+
+        let store_number = config.get_store_number();
+        let store_file = store_file::spawn_entry_store_file_task(
+            store_file,
+            config.get_store_path().to_path_buf(),
+            store_number,
+            config.get_entropy().clone(),
+            config.get_tags_path().to_path_buf(),
+            config.get_trash_path().to_path_buf(),
+            config.get_inactive_path().to_path_buf(),
+            config.get_server_id_path().to_path_buf(),
+        )
+        .await?;
 
-        match store_file.init_load_unlock().await? {
+        let unlock_entry = match store_file.init_load_unlock().await? {
             None => {
-                // write a STUB unlock entry of all zeroes for now
+                // all zeroes until the first unlock sets a passphrase
                 let unlock_entry = vec![0_u8; entry::ENTRY_SIZE];
                 store_file.write_unlock(unlock_entry).await?;
+                None
             }
-            Some(_unlock_entry) => {
-                // someday, do some crypto stuff to read other entries
+            Some(unlock_entry) => unlock::UnlockEntry::decode(&unlock_entry)?,
+        };
+
+        let index_sealed = match &unlock_entry {
+            None => false,
+            Some(unlock_entry) if unlock_entry.encrypted_index => true,
+            Some(_) if config.get_auto_migrate() => {
+                tracing::warn!(
+                    "store {} has a legacy unencrypted index, migrating it \
+                    when it is unlocked, backing it up to {}",
+                    config.get_store_path().display(),
+                    migrate::backup_path(&config).display(),
+                );
+                true
             }
-        }
+            Some(_) => {
+                return Err(format!(
+                    "store {} has a legacy unencrypted index layout, \
+                    migrate it with `lair-keystore migrate --encrypt-index`, \
+                    or start lair with `--auto-migrate`",
+                    config.get_store_path().display(),
+                )
+                .into())
+            }
+        };
+
+        let server_id = match store_file.load_server_id().await? {
+            Some(server_id) => match LairEntry::decode(&server_id)? {
+                LairEntry::SignEd25519(server_id) => server_id,
+                _ => return Err("invalid server id entry type".into()),
+            },
+            None => {
+                let server_id =
+                    sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                        checked_entropy(&config).await?,
+                    )
+                    .await?;
+                store_file
+                    .write_server_id(
+                        LairEntry::from(server_id.clone()).encode()?,
+                    )
+                    .await?;
+                server_id
+            }
+        };
+
+        let unlock_queue = unlock_queue::UnlockQueue::new(
+            config.get_store_number(),
+            config.get_queue_while_locked(),
+        );
 
         let mut out = Self {
             i_s,
             config,
             store_file,
-            last_entry_index: 0.into(),
-            entries_by_index: HashMap::new(),
+            server_id,
+            unlock_entry,
+            index_sealed,
+            unlock_lock: Arc::new(tokio::sync::Mutex::new(())),
+            unlock_queue,
+            import_lock: Arc::new(tokio::sync::Mutex::new(())),
+            new_entries: tokio::sync::broadcast::channel(NEW_ENTRY_BACKLOG).0,
+            removed_entries: tokio::sync::broadcast::channel(NEW_ENTRY_BACKLOG)
+                .0,
+            locked: true,
+            last_entry_index: None,
+            entries_by_index: BTreeMap::new(),
             entries_by_pub_id: HashMap::new(),
             entries_by_sni: HashMap::new(),
+            alt_cert_digests: HashMap::new(),
+            entries_by_alt_digest: HashMap::new(),
+            tags: entry::EntryTags::default(),
+            tags_generation: 0,
+            trash: entry::EntryTrash::default(),
+            trash_generation: 0,
+            inactive: entry::EntryInactive::default(),
+            inactive_generation: 0,
+            quarantined: BTreeMap::new(),
+            live_counts: HashMap::new(),
+            erased_count: 0,
         };
 
-        // load / decode all entries
-        for (entry_index, entry) in out.store_file.load_all_entries().await? {
-            let entry = Arc::new(entry::LairEntry::decode(&entry)?);
-            out.track_new_entry(entry_index, entry);
-            if entry_index.0 > out.last_entry_index.0 {
-                out.last_entry_index = entry_index;
-            }
+        // a deep locked store does not even index public material
+        // until it is unlocked, nor can a sealed index be read
+        if !out.config.get_deep_lock() && !out.index_sealed {
+            let entries = out
+                .store_file
+                .load_all_entries(out.config.get_strict_load())
+                .await?;
+            let tags = out.store_file.load_tags().await?;
+            let trash = out.store_file.load_trash().await?;
+            let inactive = out.store_file.load_inactive().await?;
+            out.load_entries(entries, tags, trash, inactive)?;
         }
 
         Ok(out)
     }
 
+    fn check_deep_lock(&self) -> LairResult<()> {
+        if self.locked && (self.config.get_deep_lock() || self.index_sealed) {
+            return Err(LairError::KeystoreLocked);
+        }
+        Ok(())
+    }
+
+    /// error with `WrongStore` if `index` belongs to another store
+    fn check_store(&self, index: KeystoreIndex) -> LairResult<()> {
+        if index.store_number() != self.config.get_store_number() {
+            return Err(LairError::WrongStore(format!(
+                "KeystoreIndex {} belongs to store number {}, not {}",
+                index,
+                index.store_number(),
+                self.config.get_store_number(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// error with `EntryTrashed` if `index` is in the trash
+    fn check_not_trashed(&self, index: KeystoreIndex) -> LairResult<()> {
+        if self.trash.is_trashed(index) {
+            return Err(LairError::EntryTrashed(format!("entry {}", index)));
+        }
+        Ok(())
+    }
+
+    /// error with `EntryNotActivated` if `index` was imported,
+    /// and not activated yet
+    fn check_activated(&self, index: KeystoreIndex) -> LairResult<()> {
+        if !self.inactive.is_active(index) {
+            return Err(LairError::EntryNotActivated(format!(
+                "entry {}",
+                index
+            )));
+        }
+        Ok(())
+    }
+
+    /// error with `Forbidden` if a tls cert is bound to `index`
+    fn check_not_bound(&self, index: KeystoreIndex) -> LairResult<()> {
+        let bound = self
+            .entries_by_index
+            .iter()
+            .filter_map(|(cert_index, entry)| match &**entry {
+                LairEntry::TlsCert(cert)
+                    if cert.sign_key_index == Some(index) =>
+                {
+                    Some(cert_index.to_string())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !bound.is_empty() {
+            return Err(LairError::Forbidden(format!(
+                "entry {} is referenced by bound tls certs: {}",
+                index,
+                bound.join(", "),
+            )));
+        }
+        Ok(())
+    }
+
+    /// add (or with `live` false remove) an entry
+    /// to / from the live entry counts
+    fn count_live(&mut self, index: KeystoreIndex, live: bool) {
+        let entry_type = match self.entries_by_index.get(&index) {
+            Some(entry) => entry.entry_type(),
+            // quarantined entries are not live
+            None => return,
+        };
+        let count = self.live_counts.entry(entry_type).or_insert(0);
+        if live {
+            *count += 1;
+        } else {
+            *count -= 1;
+        }
+    }
+
+    /// write the tags as changed so far, ordered after earlier writes
+    fn write_tags(&mut self) -> EntryStoreHandlerResult<()> {
+        self.tags_generation += 1;
+        let generation = self.tags_generation;
+        let tags_data = self.tags.encode()?;
+        let store_file = self.store_file.clone();
+        Ok(
+            async move { store_file.write_tags(generation, tags_data).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    /// write the trash as changed so far, ordered after earlier writes
+    fn write_trash(&mut self) -> EntryStoreHandlerResult<()> {
+        self.trash_generation += 1;
+        let generation = self.trash_generation;
+        let trash_data = self.trash.encode()?;
+        let store_file = self.store_file.clone();
+        Ok(
+            async move { store_file.write_trash(generation, trash_data).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    /// write the inactive entries as changed so far,
+    /// ordered after earlier writes
+    fn write_inactive(&mut self) -> EntryStoreHandlerResult<()> {
+        self.inactive_generation += 1;
+        let generation = self.inactive_generation;
+        let inactive_data = self.inactive.encode()?;
+        let store_file = self.store_file.clone();
+        Ok(
+            async move {
+                store_file.write_inactive(generation, inactive_data).await
+            }
+            .boxed()
+            .into(),
+        )
+    }
+
+    /// decode all entries, and build the pub id / sni indexes
+    fn load_entries(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+        tags: Option<Vec<u8>>,
+        trash: Option<Vec<u8>>,
+        inactive: Option<Vec<u8>>,
+    ) -> LairResult<()> {
+        for (entry_index, data) in entries {
+            // damaged, purged and quarantined entries all keep their slot
+            if Some(entry_index) > self.last_entry_index {
+                self.last_entry_index = Some(entry_index);
+            }
+            match self.decode_entry(entry_index, &data) {
+                Ok(Some(entry)) => {
+                    self.track_new_entry(entry_index, Arc::new(entry))
+                }
+                // purged
+                Ok(None) => self.erased_count += 1,
+                Err(err) if self.config.get_strict_load() => return Err(err),
+                Err(err) => {
+                    let mut reason = err.to_string();
+                    // truncate on a char boundary
+                    while reason.len() > MAX_QUARANTINE_REASON_LEN {
+                        reason.pop();
+                    }
+                    self.quarantined.insert(
+                        entry_index,
+                        QuarantinedEntry {
+                            keystore_index: entry_index,
+                            reason,
+                            data: Arc::new(data),
+                        },
+                    );
+                }
+            }
+        }
+
+        if !self.quarantined.is_empty() {
+            tracing::error!(
+                "STORE {} IS DAMAGED - quarantined {} undecodable entries, \
+                serving the rest. Affected indices: {:?}. \
+                Run `lair-keystore quarantine export` to recover them.",
+                self.config.get_store_number(),
+                self.quarantined.len(),
+                self.quarantined.keys().map(|i| i.0).collect::<Vec<_>>(),
+            );
+        }
+
+        if let Some(tags) = tags {
+            self.tags = entry::EntryTags::decode(&tags)?;
+        }
+
+        if let Some(trash) = trash {
+            self.trash = entry::EntryTrash::decode(&trash)?;
+            // a purge may have erased entries before the trash was written
+            let gone = self
+                .trash
+                .trashed_before(u64::MAX)
+                .into_iter()
+                .filter(|idx| {
+                    !self.entries_by_index.contains_key(idx)
+                        && !self.quarantined.contains_key(idx)
+                })
+                .collect::<Vec<_>>();
+            for idx in gone {
+                self.trash.remove(idx);
+            }
+            for idx in self.trash.trashed_before(u64::MAX) {
+                self.count_live(idx, false);
+            }
+        }
+
+        if let Some(inactive) = inactive {
+            self.inactive = entry::EntryInactive::decode(&inactive)?;
+        }
+
+        Ok(())
+    }
+
+    /// decode a single stored entry, `None` if it was purged
+    fn decode_entry(
+        &self,
+        entry_index: KeystoreIndex,
+        data: &[u8],
+    ) -> LairResult<Option<LairEntry>> {
+        // a sealed entry that failed to open is left as read
+        if data.len() != entry::ENTRY_SIZE {
+            return Err("sealed entry failed authentication".into());
+        }
+        if entry::is_erased_entry(data)? {
+            return Ok(None);
+        }
+        let mut entry = entry::LairEntry::decode(data)?;
+        // bound certs follow their signing entry, rebuild their key
+        if let LairEntry::TlsCert(cert) = &mut entry {
+            if let Some(sign_key_index) = cert.sign_key_index {
+                match self.entries_by_index.get(&sign_key_index) {
+                    Some(sign) => match &**sign {
+                        LairEntry::SignEd25519(sign) => {
+                            cert.bind_sign_key(sign)?
+                        }
+                        _ => {
+                            return Err(format!(
+                                "tls cert {} bound to non-signing entry {}",
+                                entry_index, sign_key_index,
+                            )
+                            .into())
+                        }
+                    },
+                    None => {
+                        return Err(format!(
+                            "tls cert {} bound to missing entry {}",
+                            entry_index, sign_key_index,
+                        )
+                        .into())
+                    }
+                }
+            }
+        }
+        Ok(Some(entry))
+    }
+
     fn track_new_entry(
         &mut self,
         entry_index: KeystoreIndex,
         entry: Arc<LairEntry>,
     ) {
         self.entries_by_index.insert(entry_index, entry.clone());
+        self.count_live(entry_index, true);
 
-        match &*entry {
-            LairEntry::TlsCert(e) => {
-                self.entries_by_sni
-                    .insert(e.sni.clone(), (entry_index, entry.clone()));
-                self.entries_by_pub_id
-                    .insert(e.cert_digest.0.clone(), (entry_index, entry));
-            }
-            LairEntry::SignEd25519(e) => {
-                self.entries_by_pub_id
-                    .insert(e.pub_key.0.clone(), (entry_index, entry));
-            }
-            _ => {
-                tracing::warn!(
-                    "silently ignoring unhandled entry type {:?}",
-                    entry
-                );
+        // entries are tracked in ascending index order, so with legacy
+        // duplicates the lowest index keeps resolving
+        if let LairEntry::TlsCert(e) = &*entry {
+            self.entries_by_sni
+                .entry(e.sni.normalized())
+                .or_insert_with(|| (entry_index, entry.clone()));
+            let digests = ALT_DIGEST_ALGS
+                .iter()
+                .map(|alg| (*alg, e.digest(*alg)))
+                .collect::<Vec<_>>();
+            for digest in digests.iter() {
+                self.entries_by_alt_digest
+                    .entry(digest.clone())
+                    .or_insert_with(|| (entry_index, entry.clone()));
             }
+            self.alt_cert_digests.insert(entry_index, digests);
+        }
+        // peppers and totp secrets have no public identifier to index
+        if let Some(pub_id) = entry.pub_id() {
+            self.entries_by_pub_id
+                .entry(pub_id)
+                .or_insert_with(|| (entry_index, entry.clone()));
+        }
+
+        if Some(entry_index) > self.last_entry_index {
+            self.last_entry_index = Some(entry_index);
         }
+    }
 
-        if entry_index.0 > self.last_entry_index.0 {
-            self.last_entry_index = entry_index;
+    /// drop a purged entry from every index
+    fn untrack_entry(&mut self, entry_index: KeystoreIndex) {
+        if !self.trash.is_trashed(entry_index) {
+            self.count_live(entry_index, false);
+        }
+        if let Some(entry) = self.entries_by_index.remove(&entry_index) {
+            // a legacy duplicate, if any, takes over the lookups
+            if let LairEntry::TlsCert(e) = &*entry {
+                let sni = e.sni.normalized();
+                if matches!(
+                    self.entries_by_sni.get(&sni),
+                    Some((index, _)) if *index == entry_index
+                ) {
+                    self.entries_by_sni.remove(&sni);
+                    if let Some(dup) =
+                        self.entries_by_index.iter().find(|(_, dup)| {
+                            matches!(&***dup,
+                            LairEntry::TlsCert(dup) if dup.sni.normalized() == sni)
+                        })
+                    {
+                        self.entries_by_sni
+                            .insert(sni, (*dup.0, dup.1.clone()));
+                    }
+                }
+                let digests = self
+                    .alt_cert_digests
+                    .remove(&entry_index)
+                    .unwrap_or_default();
+                for digest in digests {
+                    if matches!(
+                        self.entries_by_alt_digest.get(&digest),
+                        Some((index, _)) if *index == entry_index
+                    ) {
+                        self.entries_by_alt_digest.remove(&digest);
+                        // equal digests are an equal cert
+                        if let Some(dup) =
+                            self.entries_by_index.iter().find(|(_, dup)| {
+                                matches!(&***dup,
+                                LairEntry::TlsCert(dup)
+                                    if dup.cert_der == e.cert_der)
+                            })
+                        {
+                            self.entries_by_alt_digest
+                                .insert(digest, (*dup.0, dup.1.clone()));
+                        }
+                    }
+                }
+            }
+            if let Some(pub_id) = entry.pub_id() {
+                if matches!(
+                    self.entries_by_pub_id.get(&pub_id),
+                    Some((index, _)) if *index == entry_index
+                ) {
+                    self.entries_by_pub_id.remove(&pub_id);
+                    if let Some(dup) = self
+                        .entries_by_index
+                        .iter()
+                        .find(|(_, dup)| dup.pub_id().as_ref() == Some(&pub_id))
+                    {
+                        self.entries_by_pub_id
+                            .insert(pub_id, (*dup.0, dup.1.clone()));
+                    }
+                }
+            }
         }
+        self.tags.remove_index(entry_index);
+        self.trash.remove(entry_index);
+        self.inactive.remove(entry_index);
     }
 }
 
 impl ghost_actor::GhostControlHandler for EntryStoreImpl {}
 
-impl ghost_actor::GhostHandler<EntryStore> for EntryStoreImpl {}
+impl ghost_actor::GhostHandler<EntryStore> for EntryStoreImpl {
+    fn ghost_actor_dispatch(&mut self, d: EntryStore) {
+        // a panicking handler fails its request, not the store
+        util::catch_dispatch_panic("entry-store", || {
+            ghost_actor::GhostDispatch::ghost_actor_dispatch(d, self)
+        });
+    }
+}
 
 impl EntryStoreHandler for EntryStoreImpl {
     fn handle_tls_cert_self_signed_new_from_entropy(
         &mut self,
         options: TlsCertOptions,
     ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        Ok(
-            new_tls_cert(self.i_s.clone(), self.store_file.clone(), options)
-                .boxed()
-                .into(),
+        self.check_deep_lock()?;
+        Ok(new_tls_cert(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.clone(),
+            options,
         )
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_self_signed_new_from_sign_key(
+        &mut self,
+        sign_key_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        self.check_store(sign_key_index)?;
+        self.check_not_trashed(sign_key_index)?;
+        self.check_activated(sign_key_index)?;
+        let sign = match self.entries_by_index.get(&sign_key_index) {
+            Some(entry) => match &**entry {
+                LairEntry::SignEd25519(sign) => sign.clone(),
+                entry => {
+                    return Err(entry.wrong_type(
+                        sign_key_index,
+                        LairEntryType::SignEd25519,
+                    ))
+                }
+            },
+            None => return Err(entry_not_found(sign_key_index)),
+        };
+        // a bound cert is exported with a copy of its key
+        if !sign.policy.exportable {
+            return Err(LairError::ExportForbidden(format!(
+                "KeystoreIndex {} is not exportable",
+                sign_key_index
+            )));
+        }
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let entropy = self.config.get_entropy().clone();
+        Ok(async move {
+            let cert = tls::tls_cert_self_signed_new_from_sign_key(
+                sign_key_index,
+                sign,
+                options,
+                entropy,
+            )
+            .await?;
+            import_entry(i_s, store_file, LairEntry::TlsCert(cert)).await
+        }
+        .boxed()
+        .into())
     }
 
     fn handle_sign_ed25519_keypair_new_from_entropy(
         &mut self,
     ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        Ok(
-            new_sign_ed25519_keypair(self.i_s.clone(), self.store_file.clone())
-                .boxed()
-                .into(),
+        self.check_deep_lock()?;
+        Ok(new_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.clone(),
+            entry::SignKeyPolicy::default(),
         )
+        .boxed()
+        .into())
     }
 
-    fn handle_get_last_entry_index(
+    fn handle_sign_ed25519_keypair_new_with_policy(
         &mut self,
-    ) -> EntryStoreHandlerResult<KeystoreIndex> {
-        let idx = self.last_entry_index;
-        Ok(async move { Ok(idx) }.boxed().into())
+        policy: entry::SignKeyPolicy,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        Ok(new_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.clone(),
+            policy,
+        )
+        .boxed()
+        .into())
     }
 
-    fn handle_get_entry_by_index(
+    fn handle_sign_ed25519_hardware_keypair_new(
         &mut self,
-        index: KeystoreIndex,
-    ) -> EntryStoreHandlerResult<Arc<LairEntry>> {
-        match self.entries_by_index.get(&index) {
-            Some(entry) => {
-                let entry = entry.clone();
-                Ok(async move { Ok(entry) }.boxed().into())
-            }
-            None => Err(format!("invalid KeystoreIndex: {}", index).into()),
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let hardware_token = match self.config.get_hardware_token() {
+            Some(hardware_token) => hardware_token.clone(),
+            None => return Err("no hardware token configured".into()),
+        };
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let entropy = self.config.get_entropy().clone();
+        Ok(async move {
+            let keypair = hardware::sign_ed25519_hardware_keypair_new(
+                hardware_token,
+                entropy,
+            )
+            .await?;
+            import_entry(
+                i_s,
+                store_file,
+                LairEntry::SignEd25519Hardware(keypair),
+            )
+            .await
         }
+        .boxed()
+        .into())
     }
 
-    fn handle_get_entry_by_pub_id(
+    fn handle_sign_ed25519_keypair_import(
+        &mut self,
+        keypair: entry::EntrySignEd25519,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>, bool)> {
+        self.check_deep_lock()?;
+        Ok(import_unique_entry(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.import_lock.clone(),
+            LairEntry::SignEd25519(keypair),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_keypair_new_from_entropy(
         &mut self,
-        id: Arc<Vec<u8>>,
     ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        match self.entries_by_pub_id.get(&id) {
-            Some(entry) => {
-                let entry = entry.clone();
-                Ok(async move { Ok(entry) }.boxed().into())
+        self.check_deep_lock()?;
+        Ok(new_x25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.clone(),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_keypair_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let config = self.config.clone();
+        Ok(new_sign_secp256k1_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            async move {
+                let entropy = checked_entropy(&config).await?;
+                sign_secp256k1::sign_secp256k1_keypair_new_from_entropy(entropy)
+                    .await
+            },
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed448_keypair_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        #[cfg(not(feature = "ed448"))]
+        {
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let i_s = self.i_s.clone();
+            let store_file = self.store_file.clone();
+            let config = self.config.clone();
+            Ok(async move {
+                let entropy = checked_entropy(&config).await?;
+                let keypair =
+                    sign_ed448::sign_ed448_keypair_new_from_entropy(entropy)
+                        .await?;
+                import_entry(i_s, store_file, LairEntry::SignEd448(keypair))
+                    .await
             }
-            None => Err(format!("invalid pub id: {:?}", id).into()),
+            .boxed()
+            .into())
         }
     }
 
-    fn handle_get_entry_by_sni(
+    fn handle_sign_bls_keypair_new_from_entropy(
         &mut self,
-        sni: CertSni,
     ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        match self.entries_by_sni.get(&sni) {
-            Some(entry) => {
-                let entry = entry.clone();
-                Ok(async move { Ok(entry) }.boxed().into())
-            }
-            None => Err(format!("invalid sni: {:?}", sni).into()),
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let config = self.config.clone();
+        Ok(async move {
+            let entropy = checked_entropy(&config).await?;
+            let keypair =
+                sign_bls::sign_bls_keypair_new_from_entropy(entropy).await?;
+            import_entry(i_s, store_file, LairEntry::SignBls(keypair)).await
         }
+        .boxed()
+        .into())
     }
-}
 
-impl ghost_actor::GhostHandler<EntryStoreInternal> for EntryStoreImpl {}
+    fn handle_sign_secp256k1_keypair_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>, bool)> {
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let import_lock = self.import_lock.clone();
+        Ok(async move {
+            let keypair =
+                sign_secp256k1::sign_secp256k1_keypair_new_from_seed(seed)
+                    .await?;
+            import_unique_entry(
+                i_s,
+                store_file,
+                import_lock,
+                LairEntry::SignSecp256k1(keypair),
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
 
-impl EntryStoreInternalHandler for EntryStoreImpl {
-    fn handle_finalize_new_entry(
+    fn handle_sign_sr25519_keypair_new_from_entropy(
         &mut self,
-        entry_index: KeystoreIndex,
-        entry: Arc<LairEntry>,
-    ) -> EntryStoreInternalHandlerResult<()> {
-        self.track_new_entry(entry_index, entry);
-        Ok(async move { Ok(()) }.boxed().into())
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let config = self.config.clone();
+        Ok(async move {
+            let entropy = checked_entropy(&config).await?;
+            let keypair =
+                sign_sr25519::sign_sr25519_keypair_new_from_entropy(entropy)
+                    .await?;
+            import_entry(i_s, store_file, LairEntry::SignSr25519(keypair)).await
+        }
+        .boxed()
+        .into())
     }
-}
 
-async fn new_tls_cert(
-    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
-    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
-    options: TlsCertOptions,
-) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
-    let cert = Arc::new(LairEntry::TlsCert(
-        tls::tls_cert_self_signed_new_from_entropy(options).await?,
-    ));
-    let encoded_cert = cert.encode()?;
-    let entry_index = store_file.write_next_entry(encoded_cert).await?;
-    i_s.finalize_new_entry(entry_index, cert.clone()).await?;
-    Ok((entry_index, cert))
+    fn handle_sign_sr25519_keypair_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>, bool)> {
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let import_lock = self.import_lock.clone();
+        Ok(async move {
+            let keypair =
+                sign_sr25519::sign_sr25519_keypair_new_from_seed(seed).await?;
+            import_unique_entry(
+                i_s,
+                store_file,
+                import_lock,
+                LairEntry::SignSr25519(keypair),
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_pepper_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        Ok(new_pw_pepper(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.clone(),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_new_from_entropy(
+        &mut self,
+        options: TotpOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let config = self.config.clone();
+        Ok(async move {
+            let entropy = checked_entropy(&config).await?;
+            let secret = totp::totp_new_from_entropy(entropy, options).await?;
+            import_entry(i_s, store_file, LairEntry::TotpSecret(secret)).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_import(
+        &mut self,
+        secret_b32: String,
+        options: TotpOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        Ok(async move {
+            let secret = totp::totp_import(secret_b32, options).await?;
+            import_external_entry(
+                i_s,
+                store_file,
+                LairEntry::TotpSecret(secret),
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_wrap_entry(
+        &mut self,
+        target_index: KeystoreIndex,
+        recipient_pub_key: X25519PubKey,
+    ) -> EntryStoreHandlerResult<Arc<Vec<u8>>> {
+        self.check_deep_lock()?;
+        self.check_store(target_index)?;
+        self.check_not_trashed(target_index)?;
+        self.check_activated(target_index)?;
+        let entry = match self.entries_by_index.get(&target_index) {
+            Some(entry) => entry.clone(),
+            None => return Err(entry_not_found(target_index)),
+        };
+        let entropy = self.config.get_entropy().clone();
+        Ok(
+            async move {
+                wrap::wrap_entry(entropy, &entry, recipient_pub_key).await
+            }
+            .boxed()
+            .into(),
+        )
+    }
+
+    fn handle_unwrap_entry(
+        &mut self,
+        recipient_index: KeystoreIndex,
+        wrapped: Arc<Vec<u8>>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>, bool)> {
+        self.check_deep_lock()?;
+        self.check_store(recipient_index)?;
+        self.check_not_trashed(recipient_index)?;
+        self.check_activated(recipient_index)?;
+        let priv_key = match self.entries_by_index.get(&recipient_index) {
+            Some(entry) => match &**entry {
+                LairEntry::X25519(entry) => entry.priv_key.clone(),
+                entry => {
+                    return Err(entry
+                        .wrong_type(recipient_index, LairEntryType::X25519))
+                }
+            },
+            None => return Err(entry_not_found(recipient_index)),
+        };
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let import_lock = self.import_lock.clone();
+        let allow_invalid_sni = self.config.get_danger_allow_invalid_sni();
+        Ok(async move {
+            let mut entry = wrap::unwrap_entry(priv_key, wrapped).await?;
+            if let LairEntry::TlsCert(cert) = &mut entry {
+                cert.sni = tls::tls_cert_import_sni(cert, allow_invalid_sni)?;
+            }
+            import_unique_entry(i_s, store_file, import_lock, entry).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_unlock(
+        &mut self,
+        passphrase: Passphrase,
+        progress: futures::channel::mpsc::UnboundedSender<u8>,
+    ) -> EntryStoreHandlerResult<()> {
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let entropy = self.config.get_entropy().clone();
+        let limits = self.config.get_unlock_kdf_limits();
+        let min_score = self.config.get_min_passphrase_score();
+        let allow_weak = self.config.get_allow_weak_passphrase();
+        // loaded at unlock, not when the store was opened
+        let deferred = self.config.get_deep_lock() || self.index_sealed;
+        let unlock_lock = self.unlock_lock.clone();
+        let config = self.config.clone();
+        Ok(async move {
+            let _guard = unlock_lock.lock().await;
+            let (locked, unlock_entry) = i_s.get_unlock_state().await?;
+            if !locked {
+                return Ok(());
+            }
+
+            let progress = move |percent| {
+                let _ = progress.unbounded_send(percent);
+            };
+            let unlock_entry = match unlock_entry {
+                Some(unlock_entry) => {
+                    let device_secret = match unlock_entry.device_bound {
+                        true => device_secret::load_device_secret(&config)?,
+                        false => None,
+                    };
+                    let index_key = unlock::unlock_entry_verify(
+                        &unlock_entry,
+                        passphrase,
+                        device_secret,
+                        progress,
+                    )
+                    .await?;
+                    if unlock_entry.encrypted_index {
+                        store_file.set_index_key(index_key).await?;
+                        unlock_entry
+                    } else {
+                        migrate::encrypt_index(
+                            &config,
+                            &store_file,
+                            unlock_entry,
+                            index_key,
+                        )
+                        .await?
+                    }
+                }
+                None => {
+                    check_new_passphrase(
+                        passphrase.read(),
+                        min_score,
+                        allow_weak,
+                    )?;
+                    // a store first unlocked once lair is bound
+                    // to the device is bound too
+                    let device_secret =
+                        device_secret::load_device_secret(&config)?;
+                    let (unlock_entry, index_key) = unlock::unlock_entry_new(
+                        entropy,
+                        passphrase,
+                        device_secret,
+                        limits,
+                        progress,
+                    )
+                    .await?;
+                    // seals whatever was written while locked
+                    store_file
+                        .encrypt_index(index_key, unlock_entry.encode()?, None)
+                        .await?;
+                    unlock_entry
+                }
+            };
+
+            // rebuild the indexes from the store file
+            let loaded = if deferred {
+                Some((
+                    store_file
+                        .load_all_entries(config.get_strict_load())
+                        .await?,
+                    store_file.load_tags().await?,
+                    store_file.load_trash().await?,
+                    store_file.load_inactive().await?,
+                ))
+            } else {
+                None
+            };
+            i_s.finalize_unlock(unlock_entry, loaded).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_is_locked(&mut self) -> EntryStoreHandlerResult<bool> {
+        let locked = self.locked;
+        Ok(async move { Ok(locked) }.boxed().into())
+    }
+
+    fn handle_check_deep_lock(&mut self) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_wait_unlocked(
+        &mut self,
+    ) -> EntryStoreHandlerResult<unlock_queue::UnlockTurn> {
+        Ok(self.unlock_queue.park()?.into())
+    }
+
+    fn handle_get_server_id(
+        &mut self,
+    ) -> EntryStoreHandlerResult<entry::EntrySignEd25519> {
+        self.check_deep_lock()?;
+        let server_id = self.server_id.clone();
+        Ok(async move { Ok(server_id) }.boxed().into())
+    }
+
+    fn handle_get_last_entry_index(
+        &mut self,
+    ) -> EntryStoreHandlerResult<Option<KeystoreIndex>> {
+        self.check_deep_lock()?;
+        let idx = self.last_entry_index;
+        Ok(async move { Ok(idx) }.boxed().into())
+    }
+
+    fn handle_subscribe_new_entries(
+        &mut self,
+    ) -> EntryStoreHandlerResult<NewEntryReceiver> {
+        let recv = self.new_entries.subscribe();
+        Ok(async move { Ok(recv) }.boxed().into())
+    }
+
+    fn handle_subscribe_removed_entries(
+        &mut self,
+    ) -> EntryStoreHandlerResult<RemovedEntryReceiver> {
+        let recv = self.removed_entries.subscribe();
+        Ok(async move { Ok(recv) }.boxed().into())
+    }
+
+    fn handle_get_entry_count(&mut self) -> EntryStoreHandlerResult<u64> {
+        self.check_deep_lock()?;
+        let count = self.entries_by_index.len() as u64;
+        Ok(async move { Ok(count) }.boxed().into())
+    }
+
+    fn handle_get_store_stats(
+        &mut self,
+    ) -> EntryStoreHandlerResult<StoreStats> {
+        self.check_deep_lock()?;
+        let mut entry_counts = self
+            .live_counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(entry_type, count)| (*entry_type, *count))
+            .collect::<Vec<_>>();
+        entry_counts.sort_by_key(|(entry_type, _)| *entry_type as u32);
+        let mut stats = StoreStats::default();
+        stats.entry_counts = entry_counts;
+        stats.erased_count = self.erased_count;
+        stats.quarantined_count = self.quarantined.len() as u64;
+        stats.trashed_count = self.trash.len() as u64;
+        let store_file = self.store_file.clone();
+        Ok(async move {
+            stats.file_size = store_file.get_file_size().await?;
+            Ok(stats)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_list_entries_page(
+        &mut self,
+        start: KeystoreIndex,
+        limit: u32,
+    ) -> EntryStoreHandlerResult<(Vec<LairEntryInfo>, Option<KeystoreIndex>)>
+    {
+        self.check_deep_lock()?;
+        let out = entry::list_entries_page(
+            &self.entries_by_index,
+            &self.tags,
+            &self.trash,
+            &self.inactive,
+            start,
+            limit,
+        );
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_get_entry_types(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> EntryStoreHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        self.check_deep_lock()?;
+        let out = entry::get_entry_types(&self.entries_by_index, &indices);
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_get_entry_by_index(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<Arc<LairEntry>> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        self.check_not_trashed(index)?;
+        self.check_activated(index)?;
+        if self.quarantined.contains_key(&index) {
+            return Err(format!("entry {} is quarantined", index).into());
+        }
+        match self.entries_by_index.get(&index) {
+            Some(entry) => {
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(entry_not_found(index)),
+        }
+    }
+
+    fn handle_find_entries_by_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+        kind: PrefixKind,
+    ) -> EntryStoreHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        self.check_deep_lock()?;
+        let out = entry::find_entries_by_prefix(
+            &self.entries_by_index,
+            &self.trash,
+            &prefix,
+            kind,
+        );
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_get_entry_by_pub_id(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        match self.entries_by_pub_id.get(&id) {
+            Some(entry) => {
+                self.check_not_trashed(entry.0)?;
+                self.check_activated(entry.0)?;
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(LairError::EntryNotFound(format!("pub id {:?}", id))),
+        }
+    }
+
+    fn handle_get_entry_by_sni(
+        &mut self,
+        sni: CertSni,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        match self.entries_by_sni.get(&sni.normalized()) {
+            Some(entry) => {
+                self.check_not_trashed(entry.0)?;
+                self.check_activated(entry.0)?;
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(LairError::EntryNotFound(format!("sni {:?}", sni))),
+        }
+    }
+
+    fn handle_get_cert_digest(
+        &mut self,
+        index: KeystoreIndex,
+        digest_alg: DigestAlg,
+    ) -> EntryStoreHandlerResult<Arc<Vec<u8>>> {
+        self.check_deep_lock()?;
+        self.check_not_trashed(index)?;
+        self.check_activated(index)?;
+        let digest = match self.entries_by_index.get(&index).map(|e| &**e) {
+            Some(LairEntry::TlsCert(e)) if digest_alg == DigestAlg::Blake2b => {
+                e.cert_digest.0.clone()
+            }
+            Some(LairEntry::TlsCert(_)) => self
+                .alt_cert_digests
+                .get(&index)
+                .and_then(|digests| {
+                    digests.iter().find(|(alg, _)| *alg == digest_alg)
+                })
+                .map(|(_, digest)| digest.clone())
+                .ok_or_else(|| {
+                    LairError::from(format!(
+                        "unsupported digest alg: {:?}",
+                        digest_alg
+                    ))
+                })?,
+            Some(entry) => {
+                return Err(entry.wrong_type(index, LairEntryType::TlsCert))
+            }
+            None => return Err(entry_not_found(index)),
+        };
+        Ok(async move { Ok(digest) }.boxed().into())
+    }
+
+    fn handle_get_entry_by_cert_digest(
+        &mut self,
+        digest_alg: DigestAlg,
+        digest: Arc<Vec<u8>>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let entry = if digest_alg == DigestAlg::Blake2b {
+            self.entries_by_pub_id
+                .get(&digest)
+                .filter(|(_, entry)| matches!(&**entry, LairEntry::TlsCert(_)))
+        } else {
+            self.entries_by_alt_digest
+                .get(&(digest_alg, digest.clone()))
+        };
+        match entry {
+            Some(entry) => {
+                self.check_not_trashed(entry.0)?;
+                self.check_activated(entry.0)?;
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(LairError::EntryNotFound(format!(
+                "tls cert with {:?} digest {:?}",
+                digest_alg, digest
+            ))),
+        }
+    }
+
+    fn handle_set_entry_tag(
+        &mut self,
+        index: KeystoreIndex,
+        tag: Option<String>,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(entry_not_found(index));
+        }
+        self.tags.set(index, tag)?;
+        self.write_tags()
+    }
+
+    fn handle_add_entry_alias(
+        &mut self,
+        index: KeystoreIndex,
+        alias: String,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(entry_not_found(index));
+        }
+        self.tags.add_alias(index, alias)?;
+        self.write_tags()
+    }
+
+    fn handle_remove_entry_alias(
+        &mut self,
+        alias: String,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.tags.remove_alias(&alias)?;
+        self.write_tags()
+    }
+
+    fn handle_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_deep_lock()?;
+        let index = self.tags.resolve(&tag)?;
+        self.check_not_trashed(index)?;
+        self.check_activated(index)?;
+        match self.entries_by_index.get(&index) {
+            Some(entry) => {
+                let entry = (index, entry.clone());
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(entry_not_found(index)),
+        }
+    }
+
+    fn handle_get_entry_tag(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<Option<String>> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        let tag = self.tags.get(index).map(|tag| tag.to_string());
+        Ok(async move { Ok(tag) }.boxed().into())
+    }
+
+    fn handle_check_entry_erasable(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(entry_not_found(index));
+        }
+        self.check_not_bound(index)?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_trash_entry(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(entry_not_found(index));
+        }
+        if !self.trash.is_trashed(index) {
+            self.count_live(index, false);
+        }
+        self.trash
+            .trash(index, self.config.get_clock().unix_now_secs());
+        // no subscribers is not an error
+        let _ = self.removed_entries.send(index);
+        self.write_trash()
+    }
+
+    fn handle_restore_entry(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        self.trash.restore(index)?;
+        self.count_live(index, true);
+        self.write_trash()
+    }
+
+    fn handle_purge_trashed(
+        &mut self,
+        older_than: std::time::Duration,
+    ) -> EntryStoreHandlerResult<u64> {
+        self.check_deep_lock()?;
+        let cutoff = self
+            .config
+            .get_clock()
+            .unix_now_secs()
+            .saturating_sub(older_than.as_secs());
+        // descending, so bound certs go before the keys they reference
+        let mut purge = Vec::new();
+        for index in self.trash.trashed_before(cutoff) {
+            let bound = self.entries_by_index.values().any(|e| match &**e {
+                LairEntry::TlsCert(cert) => cert.sign_key_index == Some(index),
+                _ => false,
+            });
+            if bound {
+                continue;
+            }
+            self.untrack_entry(index);
+            self.quarantined.remove(&index);
+            let _ = self.removed_entries.send(index);
+            purge.push(index);
+        }
+        if purge.is_empty() {
+            return Ok(async move { Ok(0) }.boxed().into());
+        }
+        self.erased_count += purge.len() as u64;
+        // erase the records first, a trash / tags file still naming
+        // erased entries is cleaned up on load
+        let store_file = self.store_file.clone();
+        let write_trash = self.write_trash()?;
+        let write_tags = self.write_tags()?;
+        let write_inactive = self.write_inactive()?;
+        let count = purge.len() as u64;
+        Ok(async move {
+            store_file.erase_entries(purge).await?;
+            write_trash.await?;
+            write_tags.await?;
+            write_inactive.await?;
+            Ok(count)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_erase_entries(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> EntryStoreHandlerResult<Vec<LairResult<()>>> {
+        self.check_deep_lock()?;
+        check_bulk_entries_len(indices.len())?;
+        let mut results = Vec::with_capacity(indices.len());
+        results.resize_with(indices.len(), || Ok(()));
+        // descending, so bound certs go before the keys they reference
+        let mut order = (0..indices.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| indices[*b].cmp(&indices[*a]));
+        let mut erase = Vec::new();
+        for i in order {
+            let index = indices[i];
+            results[i] = self.check_store(index).and_then(|_| {
+                if !self.entries_by_index.contains_key(&index)
+                    && !self.quarantined.contains_key(&index)
+                {
+                    return Err(entry_not_found(index));
+                }
+                self.check_not_bound(index)
+            });
+            if results[i].is_ok() {
+                self.untrack_entry(index);
+                self.quarantined.remove(&index);
+                let _ = self.removed_entries.send(index);
+                erase.push(index);
+            }
+        }
+        if erase.is_empty() {
+            return Ok(async move { Ok(results) }.boxed().into());
+        }
+        self.erased_count += erase.len() as u64;
+        // as a purge, erase the records first
+        let store_file = self.store_file.clone();
+        let write_trash = self.write_trash()?;
+        let write_tags = self.write_tags()?;
+        let write_inactive = self.write_inactive()?;
+        Ok(async move {
+            store_file.erase_entries(erase).await?;
+            write_trash.await?;
+            write_tags.await?;
+            write_inactive.await?;
+            Ok(results)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_set_entry_tags(
+        &mut self,
+        tags: Vec<(KeystoreIndex, String)>,
+    ) -> EntryStoreHandlerResult<Vec<LairResult<()>>> {
+        self.check_deep_lock()?;
+        check_bulk_entries_len(tags.len())?;
+        let results = tags
+            .into_iter()
+            .map(|(index, tag)| {
+                self.check_store(index)?;
+                if !self.entries_by_index.contains_key(&index) {
+                    return Err(entry_not_found(index));
+                }
+                self.tags.set(index, Some(tag))
+            })
+            .collect::<Vec<_>>();
+        if !results.iter().any(|res| res.is_ok()) {
+            return Ok(async move { Ok(results) }.boxed().into());
+        }
+        let write_tags = self.write_tags()?;
+        Ok(async move {
+            write_tags.await?;
+            Ok(results)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_activate_entry(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<bool> {
+        self.check_deep_lock()?;
+        self.check_store(index)?;
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(entry_not_found(index));
+        }
+        if !self.inactive.activate(index) {
+            return Ok(async move { Ok(false) }.boxed().into());
+        }
+        let write_inactive = self.write_inactive()?;
+        Ok(async move {
+            write_inactive.await?;
+            Ok(true)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_list_quarantined(
+        &mut self,
+    ) -> EntryStoreHandlerResult<Vec<QuarantinedEntry>> {
+        self.check_deep_lock()?;
+        let quarantined = self.quarantined.values().cloned().collect();
+        Ok(async move { Ok(quarantined) }.boxed().into())
+    }
 }
 
-async fn new_sign_ed25519_keypair(
+impl ghost_actor::GhostHandler<EntryStoreInternal> for EntryStoreImpl {
+    fn ghost_actor_dispatch(&mut self, d: EntryStoreInternal) {
+        util::catch_dispatch_panic("entry-store-internal", || {
+            ghost_actor::GhostDispatch::ghost_actor_dispatch(d, self)
+        });
+    }
+}
+
+impl EntryStoreInternalHandler for EntryStoreImpl {
+    fn handle_finalize_new_entry(
+        &mut self,
+        entry_index: KeystoreIndex,
+        entry: Arc<LairEntry>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        let entry_type = entry.to_info(entry_index).entry_type;
+        self.track_new_entry(entry_index, entry);
+        // no subscribers is not an error
+        let _ = self.new_entries.send((entry_index, entry_type));
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_deactivate_import(
+        &mut self,
+        entry_index: KeystoreIndex,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        if self.config.get_auto_activate_imports() {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        self.inactive.deactivate(entry_index);
+        self.write_inactive()
+    }
+
+    fn handle_get_unlock_state(
+        &mut self,
+    ) -> EntryStoreInternalHandlerResult<(bool, Option<unlock::UnlockEntry>)>
+    {
+        let state = (self.locked, self.unlock_entry.clone());
+        Ok(async move { Ok(state) }.boxed().into())
+    }
+
+    fn handle_find_existing_entry(
+        &mut self,
+        pub_id: Arc<Vec<u8>>,
+    ) -> EntryStoreInternalHandlerResult<Option<(KeystoreIndex, Arc<LairEntry>)>>
+    {
+        let existing = self.entries_by_pub_id.get(&pub_id).cloned();
+        if let Some((index, _)) = &existing {
+            self.check_not_trashed(*index)?;
+        }
+        Ok(async move { Ok(existing) }.boxed().into())
+    }
+
+    fn handle_finalize_unlock(
+        &mut self,
+        unlock_entry: unlock::UnlockEntry,
+        loaded: Option<LoadedEntries>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        if let Some((entries, tags, trash, inactive)) = loaded {
+            self.load_entries(entries, tags, trash, inactive)?;
+        }
+        self.unlock_entry = Some(unlock_entry);
+        self.locked = false;
+        self.unlock_queue.release();
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+}
+
+/// Run the entropy health check ahead of generating key material,
+/// waiting for the entropy source to recover if the config's
+/// `EntropyFailurePolicy` is `Block`.
+pub(crate) async fn checked_entropy(
+    config: &Config,
+) -> LairResult<entropy::EntropySourceHandle> {
+    let entropy = config.get_entropy();
+    let health = config.get_entropy_health();
+    let sanity_check = config.get_entropy_sanity_check();
+    let mut blocked = false;
+    loop {
+        match health.check(entropy, sanity_check) {
+            Ok(()) => {
+                if blocked {
+                    tracing::info!(
+                        "entropy available, resuming key generation"
+                    );
+                }
+                return Ok(entropy.clone());
+            }
+            Err(e) => match config.get_entropy_failure_policy() {
+                EntropyFailurePolicy::Block => {
+                    if !blocked {
+                        tracing::warn!(
+                            "entropy unavailable, blocking key generation until it is: {:?}",
+                            e,
+                        );
+                        blocked = true;
+                    }
+                    tokio::time::delay_for(ENTROPY_RETRY_INTERVAL).await;
+                }
+                EntropyFailurePolicy::Fail => {
+                    tracing::error!(
+                        "entropy unavailable, refusing key generation: {:?}",
+                        e,
+                    );
+                    return Err(e);
+                }
+            },
+        }
+    }
+}
+
+async fn new_tls_cert(
     i_s: ghost_actor::GhostSender<EntryStoreInternal>,
     store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    config: Arc<Config>,
+    options: TlsCertOptions,
 ) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
-    let entry = Arc::new(LairEntry::SignEd25519(
-        sign_ed25519::sign_ed25519_keypair_new_from_entropy().await?,
+    let entropy = checked_entropy(&config).await?;
+    let cert = Arc::new(LairEntry::TlsCert(
+        tls::tls_cert_self_signed_new_from_entropy(options, entropy).await?,
     ));
-    let encoded_entry = entry.encode()?;
-    let entry_index = store_file.write_next_entry(encoded_entry).await?;
-    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
-    Ok((entry_index, entry))
-}
+    let encoded_cert = cert.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_cert).await?;
+    i_s.finalize_new_entry(entry_index, cert.clone()).await?;
+    Ok((entry_index, cert))
+}
+
+async fn new_sign_ed25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    config: Arc<Config>,
+    policy: entry::SignKeyPolicy,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entropy = checked_entropy(&config).await?;
+    let mut keypair =
+        sign_ed25519::sign_ed25519_keypair_new_from_entropy(entropy).await?;
+    keypair.policy = policy;
+    let entry = Arc::new(LairEntry::SignEd25519(keypair));
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+async fn new_x25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    config: Arc<Config>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entropy = checked_entropy(&config).await?;
+    let entry = Arc::new(LairEntry::X25519(
+        x25519::x25519_keypair_new_from_entropy(entropy).await?,
+    ));
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+async fn new_sign_secp256k1_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    keypair: impl std::future::Future<
+        Output = LairResult<entry::EntrySignSecp256k1>,
+    >,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SignSecp256k1(keypair.await?));
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+async fn import_entry(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    entry: LairEntry,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(entry);
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+/// import an entry from outside lair, held back until it is
+/// activated, unless the config activates imports
+async fn import_external_entry(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    entry: LairEntry,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(entry);
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    // deactivated before it is indexed, so it is never usable early
+    i_s.deactivate_import(entry_index).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+/// import an entry from outside lair, unless an entry with the same
+/// public identifier exists: then return that one, flagged as already
+/// existing, and activated or not as it was.
+/// A trashed existing entry errors with `EntryTrashed`.
+async fn import_unique_entry(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    import_lock: Arc<tokio::sync::Mutex<()>>,
+    entry: LairEntry,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>, bool)> {
+    let _guard = import_lock.lock().await;
+    if let Some(pub_id) = entry.pub_id() {
+        if let Some((index, existing)) = i_s.find_existing_entry(pub_id).await?
+        {
+            return Ok((index, existing, true));
+        }
+    }
+    let (index, entry) = import_external_entry(i_s, store_file, entry).await?;
+    Ok((index, entry, false))
+}
+
+async fn new_pw_pepper(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    config: Arc<Config>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entropy = checked_entropy(&config).await?;
+    let entry = Arc::new(LairEntry::PwPepper(
+        pw_hash::pw_pepper_new_from_entropy(entropy).await?,
+    ));
+    let encoded_entry = entry.encode()?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
+    Ok((entry_index, entry))
+}
+
+/// The `LairError::EntryNotFound` for an `index` holding no entry.
+/// Error if `len` exceeds `MAX_BULK_ENTRIES_LEN`.
+fn check_bulk_entries_len(len: usize) -> LairResult<()> {
+    if len > MAX_BULK_ENTRIES_LEN as usize {
+        return Err(format!(
+            "at most {} entries per bulk request, got {}",
+            MAX_BULK_ENTRIES_LEN, len
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn entry_not_found(index: KeystoreIndex) -> LairError {
+    LairError::EntryNotFound(format!("invalid KeystoreIndex: {}", index))
+}
+
+/// Refuse a new passphrase scoring below `min_score`, unless
+/// `allow_weak`. Only the strength is ever logged.
+fn check_new_passphrase(
+    passphrase: &[u8],
+    min_score: u8,
+    allow_weak: bool,
+) -> LairResult<()> {
+    let passphrase =
+        std::str::from_utf8(passphrase).map_err(LairError::other)?;
+    let strength =
+        passphrase_strength::estimate_passphrase_strength(passphrase);
+    if strength.score >= min_score {
+        return Ok(());
+    }
+    if allow_weak {
+        tracing::warn!("accepting weak passphrase, {}", strength);
+        return Ok(());
+    }
+    Err(LairError::WeakPassphrase(format!(
+        "{}, at least {} required",
+        strength, min_score,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! as_cert {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::TlsCert(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    macro_rules! as_sign {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::SignEd25519(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    const FAST: unlock::UnlockKdfLimits = unlock::UnlockKdfLimits {
+        mem_limit_kib: 8,
+        ops_limit: 1,
+    };
+
+    const PASSPHRASE: &str = "ochre-fjord-tangent-47";
+
+    async fn unlock(
+        store: &ghost_actor::GhostSender<EntryStore>,
+        passphrase: &str,
+    ) -> LairResult<Vec<u8>> {
+        use futures::stream::StreamExt;
+        let (progress_send, progress_recv) =
+            futures::channel::mpsc::unbounded();
+        store.unlock(passphrase.into(), progress_send).await?;
+        // the sender is dropped once the unlock completes
+        Ok(progress_recv.collect().await)
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_store_and_retrieve_entries_from_disk() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let (cert, sign) = {
+            let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+            let store_file_path = config.get_store_path().to_owned();
+
+            let store_file =
+                tokio::fs::File::create(&store_file_path).await.unwrap();
+
+            let store =
+                spawn_entry_store_actor(config, store_file).await.unwrap();
+
+            let (cert_index, cert) =
+                store
+                    .tls_cert_self_signed_new_from_entropy(
+                        TlsCertOptions::default(),
+                    )
+                    .await
+                    .unwrap();
+            assert_eq!(1, cert_index.0);
+
+            let (sign_index, sign) =
+                store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+            assert_eq!(2, sign_index.0);
+
+            use ghost_actor::GhostControlSender;
+            store.ghost_actor_shutdown().await.unwrap();
+            drop(store);
+
+            (cert, sign)
+        };
+        as_cert!(cert);
+        as_sign!(sign);
+
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file_path = config.get_store_path().to_owned();
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file = store_file.open(&store_file_path).await.unwrap();
+
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+
+        let r_cert = store.get_entry_by_index(1.into()).await.unwrap();
+        let r_sign = store.get_entry_by_index(2.into()).await.unwrap();
+        as_cert!(r_cert);
+        as_sign!(r_sign);
+
+        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+        assert_eq!(sign.pub_key, r_sign.pub_key);
+
+        let (r_cert_index, r_cert) = store
+            .get_entry_by_pub_id(cert.cert_digest.0.clone())
+            .await
+            .unwrap();
+        as_cert!(r_cert);
+        assert_eq!(1, r_cert_index.0);
+        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+
+        let (r_sign_index, r_sign) = store
+            .get_entry_by_pub_id(sign.pub_key.0.clone())
+            .await
+            .unwrap();
+        as_sign!(r_sign);
+        assert_eq!(2, r_sign_index.0);
+        assert_eq!(sign.pub_key, r_sign.pub_key);
+
+        let (r_cert_index, r_cert) =
+            store.get_entry_by_sni(cert.sni.clone()).await.unwrap();
+        as_cert!(r_cert);
+        assert_eq!(1, r_cert_index.0);
+        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+        // lookups match regardless of case
+        let upper = cert.sni.to_ascii_uppercase();
+        assert_eq!(
+            r_cert_index,
+            store.get_entry_by_sni(upper.into()).await.unwrap().0
+        );
+
+        assert!(matches!(
+            store.get_entry_by_pub_id(Arc::new(vec![0; 32])).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        assert!(matches!(
+            store.get_entry_by_sni("nope.test".to_string().into()).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+        drop(tmpdir);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_persists_the_server_id() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        let server_id = store.get_server_id().await.unwrap();
+        assert_eq!(32, server_id.pub_key.len());
+
+        // the identity is not a keystore entry
+        assert_eq!(None, store.get_last_entry_index().await.unwrap());
+        assert_eq!(0, store.get_entry_count().await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        assert_eq!(
+            server_id.pub_key,
+            store.get_server_id().await.unwrap().pub_key
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_hides_entries_while_deep_locked() {
+        use ghost_actor::GhostControlSender;
+
+        async fn open(
+            config: Arc<Config>,
+            create: bool,
+        ) -> ghost_actor::GhostSender<EntryStore> {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            store_file.create(create);
+            let store_file =
+                store_file.open(config.get_store_path()).await.unwrap();
+            spawn_entry_store_actor(config, store_file).await.unwrap()
+        }
+
+        macro_rules! assert_locked {
+            ($e:expr) => {
+                assert!(matches!($e.await, Err(LairError::KeystoreLocked)));
+            };
+        }
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_unlock_kdf_limits(FAST)
+            .build();
+        let store = open(config.clone(), true).await;
+        let (cert_index, cert) = store
+            .tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+            .await
+            .unwrap();
+        as_cert!(cert);
+        let (sign_index, sign) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        as_sign!(sign);
+        store
+            .set_entry_tag(sign_index, Some("a".into()))
+            .await
+            .unwrap();
+
+        // without deep lock, public material is available while locked
+        assert!(store.is_locked().await.unwrap());
+        store.check_deep_lock().await.unwrap();
+        store.get_server_id().await.unwrap();
+        store.get_entry_by_index(sign_index).await.unwrap();
+        unlock(&store, PASSPHRASE).await.unwrap();
+        assert!(!store.is_locked().await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_deep_lock(true)
+            .build();
+        let store = open(config, false).await;
+        assert!(store.is_locked().await.unwrap());
+        assert_locked!(store.check_deep_lock());
+        assert_locked!(store.get_server_id());
+        assert_locked!(store.get_last_entry_index());
+        assert_locked!(store.get_entry_count());
+        assert_locked!(store.list_entries_page(0.into(), 10));
+        assert_locked!(store.get_entry_types(vec![1.into()]));
+        assert_locked!(store.get_entry_by_index(sign_index));
+        assert_locked!(store.get_entry_by_pub_id(sign.pub_key.0.clone()));
+        assert_locked!(store.get_entry_by_sni(cert.sni.clone()));
+        assert_locked!(store.get_entry_by_tag("a".into()));
+        assert_locked!(store.set_entry_tag(sign_index, None));
+        assert_locked!(store.sign_ed25519_keypair_new_from_entropy());
+        assert_locked!(store.pw_pepper_new_from_entropy());
+        assert_locked!(store.totp_new_from_entropy(Default::default()));
+
+        // the indexes are rebuilt on unlock, with the right passphrase
+        assert!(matches!(
+            unlock(&store, "wrong").await,
+            Err(LairError::AuthenticationFailed),
+        ));
+        assert_locked!(store.check_deep_lock());
+        unlock(&store, PASSPHRASE).await.unwrap();
+        assert!(!store.is_locked().await.unwrap());
+        store.check_deep_lock().await.unwrap();
+        assert_eq!(
+            Some(sign_index),
+            store.get_last_entry_index().await.unwrap()
+        );
+        assert_eq!(
+            sign_index,
+            store
+                .get_entry_by_pub_id(sign.pub_key.0.clone())
+                .await
+                .unwrap()
+                .0
+        );
+        assert_eq!(
+            cert_index,
+            store.get_entry_by_sni(cert.sni.clone()).await.unwrap().0
+        );
+        assert_eq!(
+            sign_index,
+            store.get_entry_by_tag("a".into()).await.unwrap().0
+        );
+        let (new_index, _) =
+            store.x25519_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(3, new_index.0);
+
+        // unlocking twice is harmless
+        assert!(unlock(&store, PASSPHRASE).await.unwrap().is_empty());
+        assert_eq!(
+            3,
+            store.list_entries_page(0.into(), 10).await.unwrap().0.len()
+        );
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_refuses_a_weak_first_passphrase() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let open = |config: Arc<Config>| async move {
+            let store_file = tokio::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(config.get_store_path())
+                .await
+                .unwrap();
+            spawn_entry_store_actor(config, store_file).await.unwrap()
+        };
+        let builder = || {
+            Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_unlock_kdf_limits(FAST)
+        };
+
+        let store = open(builder().build()).await;
+        assert!(matches!(
+            unlock(&store, "password").await,
+            Err(LairError::WeakPassphrase(_)),
+        ));
+        assert!(store.is_locked().await.unwrap());
+
+        // the threshold is configurable
+        store.ghost_actor_shutdown().await.unwrap();
+        let store = open(builder().set_min_passphrase_score(0).build()).await;
+        unlock(&store, "password").await.unwrap();
+        assert!(!store.is_locked().await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // only new passphrases are checked, an existing weak one
+        // still unlocks under the default threshold
+        let store = open(builder().build()).await;
+        unlock(&store, "password").await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // or a weak passphrase can be allowed outright
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_unlock_kdf_limits(FAST)
+            .set_allow_weak_passphrase(true)
+            .build();
+        let store = open(config).await;
+        unlock(&store, "password").await.unwrap();
+        assert!(!store.is_locked().await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_sets_and_verifies_the_unlock_passphrase() {
+        use ghost_actor::GhostControlSender;
+
+        // enough rounds to report progress, still quick in debug builds
+        let limits = unlock::UnlockKdfLimits {
+            mem_limit_kib: 1024,
+            ops_limit: 10,
+        };
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_unlock_kdf_limits(limits)
+            .build();
+        // read too, the first unlock seals the entry written before it
+        let store_file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        let (sign_index, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+
+        // the first unlock sets the passphrase
+        assert!(store.is_locked().await.unwrap());
+        let progress = unlock(&store, PASSPHRASE).await.unwrap();
+        assert_eq!((1..=10).map(|i| i * 10).collect::<Vec<u8>>(), progress);
+        assert!(!store.is_locked().await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // the limits are persisted with the passphrase
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_unlock_kdf_limits(FAST)
+            .build();
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+
+        let progress = unlock(&store, "wrong").await;
+        assert!(matches!(progress, Err(LairError::AuthenticationFailed)));
+        assert!(store.is_locked().await.unwrap());
+
+        let progress = unlock(&store, PASSPHRASE).await.unwrap();
+        assert_eq!(10, progress.len());
+        assert!(!store.is_locked().await.unwrap());
+
+        // the unlock entry is not a keystore entry
+        assert_eq!(
+            Some(sign_index),
+            store.get_last_entry_index().await.unwrap()
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_persists_and_rotates_tags() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (idx1, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let (idx2, sign2) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        as_sign!(sign2);
+
+        assert!(store
+            .set_entry_tag(42.into(), Some("a".into()))
+            .await
+            .is_err());
+        store.set_entry_tag(idx1, Some("a".into())).await.unwrap();
+        assert_eq!(idx1, store.get_entry_by_tag("a".into()).await.unwrap().0);
+
+        // the tag moves to the new key
+        store.set_entry_tag(idx2, Some("a".into())).await.unwrap();
+        assert_eq!(idx2, store.get_entry_by_tag("a".into()).await.unwrap().0);
+
+        store.set_entry_tag(idx1, Some("b".into())).await.unwrap();
+        store.set_entry_tag(idx1, None).await.unwrap();
+
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+
+        let (r_idx, r_sign) = store.get_entry_by_tag("a".into()).await.unwrap();
+        as_sign!(r_sign);
+        assert_eq!(idx2, r_idx);
+        assert_eq!(sign2.pub_key, r_sign.pub_key);
+        assert!(matches!(
+            store.get_entry_by_tag("b".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_persists_aliases() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (idx, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        store.set_entry_tag(idx, Some("new".into())).await.unwrap();
+        store.add_entry_alias(idx, "old".into()).await.unwrap();
+        store.add_entry_alias(idx, "older".into()).await.unwrap();
+        store.add_entry_alias(idx, "gone".into()).await.unwrap();
+        store.remove_entry_alias("gone".into()).await.unwrap();
+        assert!(matches!(
+            store.add_entry_alias(idx, "new".into()).await,
+            Err(LairError::TagInUse(_)),
+        ));
+        assert!(store.add_entry_alias(42.into(), "x".into()).await.is_err());
+
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+
+        assert_eq!(idx, store.get_entry_by_tag("old".into()).await.unwrap().0);
+        assert_eq!(
+            idx,
+            store.get_entry_by_tag("older".into()).await.unwrap().0
+        );
+        assert!(matches!(
+            store.get_entry_by_tag("gone".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+        let (page, _) = store.list_entries_page(idx, 1).await.unwrap();
+        assert_eq!(vec!["old", "older"], page[0].aliases);
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_trashes_restores_and_purges_entries() {
+        use ghost_actor::GhostControlSender;
+
+        async fn reopen(
+            config: Arc<Config>,
+        ) -> ghost_actor::GhostSender<EntryStore> {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            let store_file =
+                store_file.open(config.get_store_path()).await.unwrap();
+            spawn_entry_store_actor(config, store_file).await.unwrap()
+        }
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (keep, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let (drop_idx, drop_sign) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        as_sign!(drop_sign);
+        store
+            .set_entry_tag(keep, Some("keep".into()))
+            .await
+            .unwrap();
+        store
+            .set_entry_tag(drop_idx, Some("drop".into()))
+            .await
+            .unwrap();
+
+        store.trash_entry(keep).await.unwrap();
+        store.trash_entry(drop_idx).await.unwrap();
+        assert!(matches!(
+            store.get_entry_by_index(keep).await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+        assert!(matches!(
+            store.get_entry_by_tag("drop".into()).await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+        assert!(matches!(
+            store.get_entry_by_pub_id(drop_sign.pub_key.0.clone()).await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+
+        // nothing is old enough to purge yet
+        assert_eq!(
+            0,
+            store
+                .purge_trashed(std::time::Duration::from_secs(3600))
+                .await
+                .unwrap()
+        );
+
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // trashed state survives a restart
+        let store = reopen(config.clone()).await;
+        let (page, _) = store.list_entries_page(keep, 2).await.unwrap();
+        assert!(page.iter().all(|info| info.trashed));
+
+        store.restore_entry(keep).await.unwrap();
+        assert!(store.restore_entry(keep).await.is_err());
+        assert_eq!(
+            keep,
+            store.get_entry_by_tag("keep".into()).await.unwrap().0
+        );
+
+        assert_eq!(
+            1,
+            store
+                .purge_trashed(std::time::Duration::from_secs(0))
+                .await
+                .unwrap()
+        );
+        assert!(store.restore_entry(drop_idx).await.is_err());
+        assert!(store.get_entry_by_index(drop_idx).await.is_err());
+
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // the purged record is erased, but keeps its slot
+        let store = reopen(config.clone()).await;
+        assert_eq!(1, store.get_entry_count().await.unwrap());
+        assert_eq!(Some(drop_idx), store.get_last_entry_index().await.unwrap());
+        assert!(store.restore_entry(drop_idx).await.is_err());
+        assert!(matches!(
+            store.get_entry_by_tag("drop".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+        assert!(!store.list_entries_page(keep, 2).await.unwrap().0[0].trashed);
+
+        // with a retention configured, expired trash goes on its own
+        store.trash_entry(keep).await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_trash_retention(std::time::Duration::from_secs(0))
+            .build();
+        let store = reopen(config).await;
+        let mut purged = false;
+        for _ in 0..50 {
+            if store.get_entry_count().await.unwrap() == 0 {
+                purged = true;
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(purged);
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_dedupes_imports_and_resolves_legacy_duplicates() {
+        use ghost_actor::GhostControlSender;
+
+        async fn reopen(config: Arc<Config>) -> tokio::fs::File {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            store_file.open(config.get_store_path()).await.unwrap()
+        }
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // write the same keypair twice, as imports used to
+        let keypair = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+            config.get_entropy().clone(),
+        )
+        .await
+        .unwrap();
+        let encoded = LairEntry::from(keypair.clone()).encode().unwrap();
+        let file_task = store_file::spawn_entry_store_file_task(
+            reopen(config.clone()).await,
+            config.get_store_path().to_path_buf(),
+            0,
+            config.get_entropy().clone(),
+            config.get_tags_path().to_path_buf(),
+            config.get_trash_path().to_path_buf(),
+            config.get_inactive_path().to_path_buf(),
+            config.get_server_id_path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let first = file_task.write_next_entry(encoded.clone()).await.unwrap();
+        let second = file_task.write_next_entry(encoded).await.unwrap();
+        drop(file_task);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            reopen(config.clone()).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, store.get_entry_count().await.unwrap());
+
+        // the lowest index wins lookups, and further imports
+        let pub_id = keypair.pub_key.0.clone();
+        assert_eq!(
+            first,
+            store.get_entry_by_pub_id(pub_id.clone()).await.unwrap().0
+        );
+        let (index, _, existed) =
+            store.sign_ed25519_keypair_import(keypair).await.unwrap();
+        assert_eq!((first, true), (index, existed));
+        assert_eq!(2, store.get_entry_count().await.unwrap());
+
+        // once the winner is purged, the duplicate takes over
+        store.trash_entry(first).await.unwrap();
+        assert!(matches!(
+            store.get_entry_by_pub_id(pub_id.clone()).await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+        store
+            .purge_trashed(std::time::Duration::from_secs(0))
+            .await
+            .unwrap();
+        assert_eq!(second, store.get_entry_by_pub_id(pub_id).await.unwrap().0);
+
+        // racing imports of the same seed create a single entry
+        let seed = [0x42; 32];
+        let (a, b) = futures::future::join(
+            store.sign_secp256k1_keypair_new_from_seed(seed),
+            store.sign_secp256k1_keypair_new_from_seed(seed),
+        )
+        .await;
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.0, b.0);
+        assert!(a.2 != b.2);
+        assert_eq!(2, store.get_entry_count().await.unwrap());
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_holds_back_imports_until_activated() {
+        use ghost_actor::GhostControlSender;
+
+        async fn open(config: Arc<Config>) -> tokio::fs::File {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            store_file.create(true);
+            store_file.open(config.get_store_path()).await.unwrap()
+        }
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store =
+            spawn_entry_store_actor(config.clone(), open(config.clone()).await)
+                .await
+                .unwrap();
+
+        let created =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let keypair = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+            config.get_entropy().clone(),
+        )
+        .await
+        .unwrap();
+        let pub_id = keypair.pub_key.0.clone();
+        let (imported, _, _) = store
+            .sign_ed25519_keypair_import(keypair.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.get_entry_by_index(imported).await,
+            Err(LairError::EntryNotActivated(_)),
+        ));
+        assert!(matches!(
+            store.get_entry_by_pub_id(pub_id.clone()).await,
+            Err(LairError::EntryNotActivated(_)),
+        ));
+        store.get_entry_by_index(created.0).await.unwrap();
+
+        // importing it again does not activate it
+        let (index, _, existed) =
+            store.sign_ed25519_keypair_import(keypair).await.unwrap();
+        assert_eq!((imported, true), (index, existed));
+        assert!(store.get_entry_by_index(imported).await.is_err());
+
+        // held back across restarts
+        store.ghost_actor_shutdown().await.unwrap();
+        let store =
+            spawn_entry_store_actor(config.clone(), open(config.clone()).await)
+                .await
+                .unwrap();
+        assert!(matches!(
+            store.get_entry_by_index(imported).await,
+            Err(LairError::EntryNotActivated(_)),
+        ));
+
+        assert!(store.activate_entry(imported).await.unwrap());
+        assert!(!store.activate_entry(imported).await.unwrap());
+        assert!(!store.activate_entry(created.0).await.unwrap());
+        assert!(matches!(
+            store.activate_entry(42.into()).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        assert_eq!(
+            imported,
+            store.get_entry_by_pub_id(pub_id).await.unwrap().0
+        );
+
+        // activated across restarts
+        store.ghost_actor_shutdown().await.unwrap();
+        let store =
+            spawn_entry_store_actor(config.clone(), open(config.clone()).await)
+                .await
+                .unwrap();
+        store.get_entry_by_index(imported).await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // unless configured to activate imports right away
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_auto_activate_imports(true)
+            .build();
+        let store =
+            spawn_entry_store_actor(config.clone(), open(config.clone()).await)
+                .await
+                .unwrap();
+        let (imported, _, _) = store
+            .sign_secp256k1_keypair_new_from_seed([0x42; 32])
+            .await
+            .unwrap();
+        store.get_entry_by_index(imported).await.unwrap();
+        assert!(!store.activate_entry(imported).await.unwrap());
+        store.ghost_actor_shutdown().await.unwrap();
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test(threaded_scheduler)]
+    async fn it_indexes_tls_certs_by_every_digest_alg() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
 
-    macro_rules! as_cert {
-        ($e:ident) => {
-            let $e = match &*$e {
-                LairEntry::TlsCert(e) => e,
-                _ => panic!("unexpected"),
-            };
-        };
-    }
+        let (cert_index, cert) = store
+            .tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+            .await
+            .unwrap();
+        let (sign_index, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        as_cert!(cert);
 
-    macro_rules! as_sign {
-        ($e:ident) => {
-            let $e = match &*$e {
-                LairEntry::SignEd25519(e) => e,
-                _ => panic!("unexpected"),
-            };
-        };
+        for alg in
+            &[DigestAlg::Blake2b, DigestAlg::Sha256, DigestAlg::Sha1Legacy]
+        {
+            let digest = alg.compute(&cert.cert_der);
+            assert_eq!(
+                digest,
+                store.get_cert_digest(cert_index, *alg).await.unwrap(),
+            );
+            assert_eq!(
+                cert_index,
+                store
+                    .get_entry_by_cert_digest(*alg, digest)
+                    .await
+                    .unwrap()
+                    .0,
+            );
+        }
+        assert!(store
+            .get_cert_digest(sign_index, DigestAlg::Sha256)
+            .await
+            .is_err());
+
+        let sha256 = DigestAlg::Sha256.compute(&cert.cert_der);
+        store.trash_entry(cert_index).await.unwrap();
+        assert!(matches!(
+            store
+                .get_entry_by_cert_digest(DigestAlg::Sha256, sha256.clone())
+                .await,
+            Err(LairError::EntryTrashed(_)),
+        ));
+        store
+            .purge_trashed(std::time::Duration::from_secs(0))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .get_entry_by_cert_digest(DigestAlg::Sha256, sha256)
+                .await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
     }
 
     #[tokio::test(threaded_scheduler)]
-    async fn it_can_store_and_retrieve_entries_from_disk() {
+    async fn it_binds_tls_certs_to_sign_keys() {
+        use ghost_actor::GhostControlSender;
+
         let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
 
-        let (cert, sign) = {
-            let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
 
-            let store_file_path = config.get_store_path().to_owned();
+        let (sign_index, sign) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        as_sign!(sign);
+        let (cert_index, cert) = store
+            .tls_cert_self_signed_new_from_sign_key(
+                sign_index,
+                TlsCertOptions::default(),
+            )
+            .await
+            .unwrap();
+        as_cert!(cert);
+        assert_eq!(Some(sign_index), cert.sign_key_index);
+        assert_eq!(
+            sign.pub_key,
+            tls::tls_cert_self_signed_ed25519_pub_key(&cert.cert_der).unwrap(),
+        );
 
-            let store_file =
-                tokio::fs::File::create(&store_file_path).await.unwrap();
+        // only signing entries can back a cert
+        assert!(store
+            .tls_cert_self_signed_new_from_sign_key(
+                cert_index,
+                TlsCertOptions::default(),
+            )
+            .await
+            .is_err());
+
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
 
+        // the private key is rebuilt from the signing entry on load
+        let r_cert = store.get_entry_by_index(cert_index).await.unwrap();
+        as_cert!(r_cert);
+        assert_eq!(Some(sign_index), r_cert.sign_key_index);
+        assert_eq!(cert.priv_key_der, r_cert.priv_key_der);
+        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+
+        // the signing entry cannot be erased while the cert references it
+        assert!(matches!(
+            store.check_entry_erasable(sign_index).await,
+            Err(LairError::Forbidden(_)),
+        ));
+        store.check_entry_erasable(cert_index).await.unwrap();
+
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_generates_identical_entries_from_seeded_entropy() {
+        async fn gen(
+            seed: u64,
+        ) -> (tempfile::TempDir, Arc<LairEntry>, Arc<LairEntry>) {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .danger_set_test_entropy_seed(seed)
+                .build();
+            let store_file = tokio::fs::File::create(config.get_store_path())
+                .await
+                .unwrap();
             let store =
                 spawn_entry_store_actor(config, store_file).await.unwrap();
-
-            let (cert_index, cert) =
+            let (_, cert) =
                 store
                     .tls_cert_self_signed_new_from_entropy(
                         TlsCertOptions::default(),
                     )
                     .await
                     .unwrap();
-            assert_eq!(1, cert_index.0);
-
-            let (sign_index, sign) =
+            let (_, sign) =
                 store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
-            assert_eq!(2, sign_index.0);
-
             use ghost_actor::GhostControlSender;
             store.ghost_actor_shutdown().await.unwrap();
-            drop(store);
+            (tmpdir, cert, sign)
+        }
 
-            (cert, sign)
+        let (_t1, cert1, sign1) = gen(42).await;
+        let (_t2, cert2, sign2) = gen(42).await;
+        as_cert!(cert1);
+        as_cert!(cert2);
+        as_sign!(sign1);
+        as_sign!(sign2);
+        assert_eq!(cert1.sni, cert2.sni);
+        assert_eq!(cert1.cert_digest, cert2.cert_digest);
+        assert_eq!(sign1.pub_key, sign2.pub_key);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_wraps_entries_between_stores() {
+        use ghost_actor::GhostControlSender;
+
+        async fn open(
+            config: Arc<Config>,
+            create: bool,
+        ) -> ghost_actor::GhostSender<EntryStore> {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            store_file.create(create);
+            let store_file =
+                store_file.open(config.get_store_path()).await.unwrap();
+            spawn_entry_store_actor(config, store_file).await.unwrap()
+        }
+
+        fn secret(entry: &LairEntry) -> (LairEntryType, Vec<u8>) {
+            let info = entry.to_info(0.into());
+            let secret = match entry {
+                LairEntry::TlsCert(e) => e.priv_key_der.to_vec(),
+                LairEntry::SignEd25519(e) => e.priv_key.to_vec(),
+                LairEntry::X25519(e) => e.priv_key.to_vec(),
+                LairEntry::PwPepper(e) => e.pepper.to_vec(),
+                LairEntry::SignSecp256k1(e) => e.priv_key.to_vec(),
+                LairEntry::TotpSecret(e) => e.secret.to_vec(),
+                _ => panic!("unexpected"),
+            };
+            (info.entry_type, secret)
+        }
+
+        let tmpdir_a = tempfile::tempdir().unwrap();
+        let tmpdir_b = tempfile::tempdir().unwrap();
+        let config_a = Config::builder().set_root_path(tmpdir_a.path()).build();
+        let config_b = Config::builder().set_root_path(tmpdir_b.path()).build();
+        let store_a = open(config_a, true).await;
+        let store_b = open(config_b.clone(), true).await;
+
+        let (recovery_index, recovery) =
+            store_b.x25519_keypair_new_from_entropy().await.unwrap();
+        let recovery_pub_key = match &*recovery {
+            LairEntry::X25519(e) => e.pub_key.clone(),
+            _ => panic!("unexpected"),
         };
-        as_cert!(cert);
-        as_sign!(sign);
+        let (other_index, _) =
+            store_b.x25519_keypair_new_from_entropy().await.unwrap();
+
+        let mut targets = vec![
+            store_a
+                .tls_cert_self_signed_new_from_entropy(
+                    TlsCertOptions::default(),
+                )
+                .await
+                .unwrap(),
+            store_a.sign_ed25519_keypair_new_from_entropy().await.unwrap(),
+            store_a.x25519_keypair_new_from_entropy().await.unwrap(),
+            store_a.pw_pepper_new_from_entropy().await.unwrap(),
+            store_a
+                .sign_secp256k1_keypair_new_from_entropy()
+                .await
+                .unwrap(),
+            store_a
+                .totp_new_from_entropy(Default::default())
+                .await
+                .unwrap(),
+        ];
+
+        let mut imported = Vec::new();
+        for (index, entry) in targets.iter() {
+            let wrapped = store_a
+                .wrap_entry(*index, recovery_pub_key.clone())
+                .await
+                .unwrap();
+
+            // wrapped to another key
+            assert!(matches!(
+                store_b.unwrap_entry(other_index, wrapped.clone()).await,
+                Err(LairError::AuthenticationFailed),
+            ));
+
+            // tampered
+            let mut bad = (*wrapped).clone();
+            bad[100] ^= 0x01;
+            assert!(matches!(
+                store_b.unwrap_entry(recovery_index, Arc::new(bad)).await,
+                Err(LairError::AuthenticationFailed),
+            ));
+
+            let (u_index, u_entry, existed) =
+                store_b.unwrap_entry(recovery_index, wrapped).await.unwrap();
+            assert!(!existed);
+            assert_eq!(secret(entry), secret(&u_entry));
+            imported.push(u_index);
+        }
+
+        // unwrapping needs an x25519 recipient entry
+        let wrapped = store_a
+            .wrap_entry(targets[0].0, recovery_pub_key.clone())
+            .await
+            .unwrap();
+        assert!(store_b.unwrap_entry(imported[1], wrapped).await.is_err());
+        assert!(store_a
+            .wrap_entry(42.into(), recovery_pub_key)
+            .await
+            .is_err());
+
+        store_a.ghost_actor_shutdown().await.unwrap();
+        store_b.ghost_actor_shutdown().await.unwrap();
+        drop(store_b);
+
+        // the imported entries are persisted, still inactive,
+        // and indexed by pub id
+        let store_b = open(config_b, false).await;
+        for ((_, entry), u_index) in targets.drain(..).zip(imported) {
+            assert!(matches!(
+                store_b.get_entry_by_index(u_index).await,
+                Err(LairError::EntryNotActivated(_)),
+            ));
+            assert!(store_b.activate_entry(u_index).await.unwrap());
+            let r_entry = store_b.get_entry_by_index(u_index).await.unwrap();
+            assert_eq!(secret(&entry), secret(&r_entry));
+            let pub_id = entry.to_info(u_index).pub_id;
+            if !pub_id.is_empty() {
+                assert_eq!(
+                    u_index,
+                    store_b.get_entry_by_pub_id(pub_id).await.unwrap().0
+                );
+            }
+        }
+        store_b.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_keeps_store_stats_across_changes() {
+        use ghost_actor::GhostControlSender;
+
+        async fn open(
+            config: Arc<Config>,
+        ) -> ghost_actor::GhostSender<EntryStore> {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            store_file.create(true);
+            let store_file =
+                store_file.open(config.get_store_path()).await.unwrap();
+            spawn_entry_store_actor(config, store_file).await.unwrap()
+        }
+
+        /// the counters must always agree with a full scan
+        async fn check(
+            store: &ghost_actor::GhostSender<EntryStore>,
+            records: u64,
+        ) -> StoreStats {
+            let stats = store.get_store_stats().await.unwrap();
+            let (entries, next) = store
+                .list_entries_page(0.into(), MAX_LIST_ENTRIES_PAGE_LIMIT)
+                .await
+                .unwrap();
+            assert_eq!(None, next);
+            let mut scanned = BTreeMap::new();
+            for info in entries.iter().filter(|info| !info.trashed) {
+                *scanned.entry(info.entry_type as u32).or_insert(0) += 1;
+            }
+            assert_eq!(
+                scanned.into_iter().collect::<Vec<_>>(),
+                stats
+                    .entry_counts
+                    .iter()
+                    .map(|(entry_type, count)| (*entry_type as u32, *count))
+                    .collect::<Vec<_>>(),
+            );
+            assert_eq!(
+                entries.iter().filter(|info| info.trashed).count() as u64,
+                stats.trashed_count,
+            );
+            // the unlock entry, then every entry ever written
+            assert_eq!(
+                (records + 1) * entry::ENTRY_SIZE as u64,
+                stats.file_size
+            );
+            stats
+        }
 
+        let tmpdir = tempfile::tempdir().unwrap();
         let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store = open(config.clone()).await;
+        assert_eq!(0, check(&store, 0).await.entry_counts.len());
 
-        let store_file_path = config.get_store_path().to_owned();
+        let mut signs = Vec::new();
+        for _ in 0..3 {
+            signs.push(
+                store
+                    .sign_ed25519_keypair_new_from_entropy()
+                    .await
+                    .unwrap()
+                    .0,
+            );
+        }
+        let (x, _) = store.x25519_keypair_new_from_entropy().await.unwrap();
+        store
+            .tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+            .await
+            .unwrap();
+        let stats = check(&store, 5).await;
+        assert_eq!(3, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(1, stats.entry_count(LairEntryType::X25519));
+        assert_eq!(1, stats.entry_count(LairEntryType::TlsCert));
 
-        let mut store_file = tokio::fs::OpenOptions::new();
-        store_file.read(true);
-        store_file.append(true);
-        let store_file = store_file.open(&store_file_path).await.unwrap();
+        // trashing twice, or restoring an entry not in the trash,
+        // counts nothing twice
+        store.trash_entry(signs[0]).await.unwrap();
+        store.trash_entry(signs[0]).await.unwrap();
+        let stats = check(&store, 5).await;
+        assert_eq!(2, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(1, stats.trashed_count);
+        store.restore_entry(signs[0]).await.unwrap();
+        assert!(store.restore_entry(signs[0]).await.is_err());
+        let stats = check(&store, 5).await;
+        assert_eq!(3, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(0, stats.trashed_count);
 
-        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        store.trash_entry(signs[0]).await.unwrap();
+        store.trash_entry(x).await.unwrap();
+        assert_eq!(
+            2,
+            store
+                .purge_trashed(std::time::Duration::from_secs(0))
+                .await
+                .unwrap()
+        );
+        store.trash_entry(signs[1]).await.unwrap();
+        let stats = check(&store, 5).await;
+        assert_eq!(1, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(0, stats.entry_count(LairEntryType::X25519));
+        assert_eq!(2, stats.erased_count);
+        assert_eq!(1, stats.trashed_count);
 
-        let r_cert = store.get_entry_by_index(1.into()).await.unwrap();
-        let r_sign = store.get_entry_by_index(2.into()).await.unwrap();
-        as_cert!(r_cert);
-        as_sign!(r_sign);
+        // rebuilt on load
+        store.ghost_actor_shutdown().await.unwrap();
+        let store = open(config.clone()).await;
+        assert_eq!(stats, check(&store, 5).await);
+        store.ghost_actor_shutdown().await.unwrap();
 
-        assert_eq!(cert.cert_digest, r_cert.cert_digest);
-        assert_eq!(sign.pub_key, r_sign.pub_key);
+        // damage the live ed25519 entry
+        let mut data = std::fs::read(config.get_store_path()).unwrap();
+        let start = signs[2].entry() as usize * entry::ENTRY_SIZE;
+        for b in data[start + 4..start + 8].iter_mut() {
+            *b ^= 0xff;
+        }
+        std::fs::write(config.get_store_path(), &data).unwrap();
+        let store = open(config.clone()).await;
+        let stats = check(&store, 5).await;
+        assert_eq!(0, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(1, stats.quarantined_count);
+        assert_eq!(2, stats.erased_count);
 
-        let (r_cert_index, r_cert) = store
-            .get_entry_by_pub_id(cert.cert_digest.0.clone())
+        // purging a trashed entry erases its record
+        assert_eq!(
+            1,
+            store
+                .purge_trashed(std::time::Duration::from_secs(0))
+                .await
+                .unwrap()
+        );
+        store.x25519_keypair_new_from_entropy().await.unwrap();
+        let stats = check(&store, 6).await;
+        assert_eq!(3, stats.erased_count);
+        assert_eq!(0, stats.trashed_count);
+        assert_eq!(
+            vec![(LairEntryType::TlsCert, 1), (LairEntryType::X25519, 1)],
+            stats.entry_counts,
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_quarantines_undecodable_entries() {
+        use ghost_actor::GhostControlSender;
+
+        async fn reopen(
+            config: Arc<Config>,
+        ) -> LairResult<ghost_actor::GhostSender<EntryStore>> {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.write(true);
+            let store_file =
+                store_file.open(config.get_store_path()).await.unwrap();
+            spawn_entry_store_actor(config, store_file).await
+        }
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
             .await
             .unwrap();
-        as_cert!(r_cert);
-        assert_eq!(1, r_cert_index.0);
-        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        let mut entries = Vec::new();
+        for _ in 0..3 {
+            let (index, entry) =
+                store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+            as_sign!(entry);
+            entries.push((index, entry.pub_key.0.clone()));
+        }
+        assert!(store.list_quarantined().await.unwrap().is_empty());
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
 
-        let (r_sign_index, r_sign) = store
-            .get_entry_by_pub_id(sign.pub_key.0.clone())
+        // flip the pre-padding length of the middle entry
+        let damaged = entries[1].0;
+        let mut data = std::fs::read(config.get_store_path()).unwrap();
+        let start = damaged.entry() as usize * entry::ENTRY_SIZE;
+        for b in data[start + 4..start + 8].iter_mut() {
+            *b ^= 0xff;
+        }
+        std::fs::write(config.get_store_path(), &data).unwrap();
+        let record = data[start..start + entry::ENTRY_SIZE].to_vec();
+
+        let store = reopen(config.clone()).await.unwrap();
+        assert_eq!(2, store.get_entry_count().await.unwrap());
+        assert!(store.get_entry_by_index(damaged).await.is_err());
+        for (index, pub_id) in [&entries[0], &entries[2]].iter() {
+            assert_eq!(
+                *index,
+                store.get_entry_by_pub_id(pub_id.clone()).await.unwrap().0
+            );
+        }
+
+        let quarantined = store.list_quarantined().await.unwrap();
+        assert_eq!(1, quarantined.len());
+        assert_eq!(damaged, quarantined[0].keystore_index);
+        assert_eq!(record, *quarantined[0].data);
+
+        // the damaged slot is never reused
+        let (next, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(entries[2].0.entry() + 1, next.entry());
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // strict loading refuses the damaged store outright
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_strict_load(true)
+            .build();
+        assert!(reopen(config).await.is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_truncates_a_partial_trailing_entry() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
             .await
             .unwrap();
-        as_sign!(r_sign);
-        assert_eq!(2, r_sign_index.0);
-        assert_eq!(sign.pub_key, r_sign.pub_key);
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        let mut entries = Vec::new();
+        for _ in 0..2 {
+            let (index, entry) =
+                store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+            as_sign!(entry);
+            entries.push((index, entry.pub_key.0.clone()));
+        }
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
 
-        let (r_cert_index, r_cert) =
-            store.get_entry_by_sni(cert.sni.clone()).await.unwrap();
-        as_cert!(r_cert);
-        assert_eq!(1, r_cert_index.0);
-        assert_eq!(cert.cert_digest, r_cert.cert_digest);
+        // as left by a write interrupted part way
+        let mut data = std::fs::read(config.get_store_path()).unwrap();
+        let whole_size = data.len() as u64;
+        let garbage = vec![0xdb; 100];
+        data.extend_from_slice(&garbage);
+        std::fs::write(config.get_store_path(), &data).unwrap();
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        assert_eq!(2, store.get_entry_count().await.unwrap());
+        assert!(store.list_quarantined().await.unwrap().is_empty());
+        for (index, pub_id) in entries.iter() {
+            assert_eq!(
+                *index,
+                store.get_entry_by_pub_id(pub_id.clone()).await.unwrap().0
+            );
+        }
+        assert_eq!(
+            whole_size,
+            std::fs::metadata(config.get_store_path()).unwrap().len()
+        );
+        let partial_path = store_file::partial_record_path(
+            config.get_store_path(),
+            whole_size,
+        );
+        assert_eq!(garbage, std::fs::read(partial_path).unwrap());
+
+        // new entries follow the last whole one
+        let (next, _) =
+            store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(entries[1].0.entry() + 1, next.entry());
+        assert_eq!(
+            whole_size + entry::ENTRY_SIZE as u64,
+            std::fs::metadata(config.get_store_path()).unwrap().len()
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+    }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn it_strictly_refuses_a_partial_trailing_entry() {
         use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_strict_load(true)
+            .build();
+
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+        store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
         store.ghost_actor_shutdown().await.unwrap();
         drop(store);
-        drop(tmpdir);
+
+        // as left by a write interrupted part way
+        let mut data = std::fs::read(config.get_store_path()).unwrap();
+        let whole_size = data.len() as u64;
+        data.extend_from_slice(&[0xdb; 100]);
+        std::fs::write(config.get_store_path(), &data).unwrap();
+
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.write(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        assert!(spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .is_err());
+
+        // the store is left for the operator to inspect
+        assert_eq!(data, std::fs::read(config.get_store_path()).unwrap());
+        assert!(!store_file::partial_record_path(
+            config.get_store_path(),
+            whole_size,
+        )
+        .exists());
+    }
+
+    /// A store file task over a new store, with its index sealed.
+    async fn spawn_sealed_store_file(
+        config: &Config,
+    ) -> futures::channel::mpsc::Sender<store_file::EntryStoreFile> {
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true).write(true).create(true);
+        let store_file =
+            store_file.open(config.get_store_path()).await.unwrap();
+        let file_task = store_file::spawn_entry_store_file_task(
+            store_file,
+            config.get_store_path().to_path_buf(),
+            0,
+            config.get_entropy().clone(),
+            config.get_tags_path().to_path_buf(),
+            config.get_trash_path().to_path_buf(),
+            config.get_inactive_path().to_path_buf(),
+            config.get_server_id_path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        assert!(file_task.init_load_unlock().await.unwrap().is_none());
+        file_task
+            .write_unlock(vec![0; entry::ENTRY_SIZE])
+            .await
+            .unwrap();
+        let (unlock_entry, index_key) = unlock::unlock_entry_new(
+            config.get_entropy().clone(),
+            PASSPHRASE.into(),
+            None,
+            FAST,
+            |_| (),
+        )
+        .await
+        .unwrap();
+        file_task
+            .encrypt_index(index_key, unlock_entry.encode().unwrap(), None)
+            .await
+            .unwrap();
+        file_task
+    }
+
+    /// The nonce of a sealed record, as written by `IndexKey::seal`:
+    /// `version (4 bytes) || nonce (24) || mac (16) || ciphertext`.
+    fn sealed_nonce(sealed: &[u8]) -> Vec<u8> {
+        sealed[4..4 + secretbox::NONCE_BYTES].to_vec()
+    }
+
+    /// The nonce of the sealed record of `index`, read from disk.
+    fn entry_nonce(config: &Config, index: KeystoreIndex) -> Vec<u8> {
+        let data = std::fs::read(config.get_store_path()).unwrap();
+        let start = entry::ENTRY_SIZE
+            + (index.entry() as usize - 1) * unlock::SEALED_ENTRY_SIZE;
+        sealed_nonce(&data[start..start + unlock::SEALED_ENTRY_SIZE])
+    }
+
+    /// The nonce of the sealed tags file, read from disk.
+    fn tags_nonce(config: &Config) -> Vec<u8> {
+        let sealed_tags_path = store_file::sealed_path(config.get_tags_path());
+        sealed_nonce(&std::fs::read(sealed_tags_path).unwrap())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_seals_each_write_under_a_new_nonce() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let file_task = spawn_sealed_store_file(&config).await;
+
+        let keypair = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+            config.get_entropy().clone(),
+        )
+        .await
+        .unwrap();
+        let encoded = LairEntry::from(keypair).encode().unwrap();
+        let index = file_task.write_next_entry(encoded).await.unwrap();
+        let written = entry_nonce(&config, index);
+
+        // erasing rewrites the record in place, twice with the same
+        // plaintext, each time under a fresh nonce
+        file_task.erase_entries(vec![index]).await.unwrap();
+        let erased = entry_nonce(&config, index);
+        file_task.erase_entries(vec![index]).await.unwrap();
+        let erased_again = entry_nonce(&config, index);
+        assert_ne!(written, erased);
+        assert_ne!(written, erased_again);
+        assert_ne!(erased, erased_again);
+
+        // so does writing the same tag set twice
+        let tags = entry::EntryTags::default().encode().unwrap();
+        file_task.write_tags(1, tags.clone()).await.unwrap();
+        let tagged = tags_nonce(&config);
+        file_task.write_tags(2, tags).await.unwrap();
+        let tagged_again = tags_nonce(&config);
+        assert_ne!(tagged, tagged_again);
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn it_never_reuses_a_nonce_on_disk(
+            // 0: write an entry, 1: erase the last one, 2: write the tags
+            ops in proptest::collection::vec(0_u8..3, 1..48),
+        ) {
+            let mut runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let tmpdir = tempfile::tempdir().unwrap();
+                let config =
+                    Config::builder().set_root_path(tmpdir.path()).build();
+                let file_task = spawn_sealed_store_file(&config).await;
+
+                let data = vec![0xdb; entry::ENTRY_SIZE];
+                let mut last = None;
+                let mut generation = 0;
+                let mut nonces = std::collections::HashSet::new();
+                for op in ops {
+                    let nonce = match (op, last) {
+                        (1, Some(index)) => {
+                            file_task.erase_entries(vec![index]).await.unwrap();
+                            entry_nonce(&config, index)
+                        }
+                        (2, _) => {
+                            generation += 1;
+                            file_task
+                                .write_tags(generation, data.clone())
+                                .await
+                                .unwrap();
+                            tags_nonce(&config)
+                        }
+                        _ => {
+                            let index = file_task
+                                .write_next_entry(data.clone())
+                                .await
+                                .unwrap();
+                            last = Some(index);
+                            entry_nonce(&config, index)
+                        }
+                    };
+                    assert!(nonces.insert(nonce), "a nonce was reused");
+                }
+            });
+        }
     }
 }
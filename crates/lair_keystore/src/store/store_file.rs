@@ -1,6 +1,8 @@
 //! internal ghost actor file wrapper
 
 use crate::*;
+use lair_keystore_api::internal::{entropy::EntropySourceHandle, unlock, util};
+use std::path::{Path, PathBuf};
 
 ghost_actor::ghost_chan! {
     /// chan wrapper for file access
@@ -11,20 +13,148 @@ ghost_actor::ghost_chan! {
         /// write the unlock entry to the file
         fn write_unlock(entry_data: Vec<u8>) -> ();
 
-        /// loading all entries from the file
-        fn load_all_entries() -> Vec<(super::KeystoreIndex, Vec<u8>)>;
+        /// seal the index files of a store with an encrypted index,
+        /// once its unlock entry is verified
+        fn set_index_key(index_key: unlock::IndexKey) -> ();
+
+        /// move a store with a plaintext index to an encrypted one:
+        /// back up the store into `backup`, if set, seal the index files,
+        /// then rewrite the store with the unlock entry `entry_data`
+        /// recording the new layout, and its entries sealed,
+        /// see `encrypt_index`
+        fn encrypt_index(
+            index_key: unlock::IndexKey,
+            entry_data: Vec<u8>,
+            backup: Option<PathBuf>,
+        ) -> ();
+
+        /// loading all entries from the file, a `strict` load refuses
+        /// a partial trailing entry, rather than quarantine it,
+        /// see `quarantine_partial_record`
+        fn load_all_entries(
+            strict: bool,
+        ) -> Vec<(super::KeystoreIndex, Vec<u8>)>;
+
+        /// the size of the store file on disk, in bytes
+        fn get_file_size() -> u64;
 
         /// write a new entry to the store file
         fn write_next_entry(entry_data: Vec<u8>) -> super::KeystoreIndex;
+
+        /// overwrite entries with erased entries, syncing once
+        /// they are all written, see `entry::encode_erased_entry`
+        fn erase_entries(indices: Vec<super::KeystoreIndex>) -> ();
+
+        /// load the encoded entry tags, if any have been written
+        fn load_tags() -> Option<Vec<u8>>;
+
+        /// replace the encoded entry tags, unless a later
+        /// generation has already been written
+        fn write_tags(generation: u64, tags_data: Vec<u8>) -> ();
+
+        /// load the encoded entry trash, if it has been written
+        fn load_trash() -> Option<Vec<u8>>;
+
+        /// replace the encoded entry trash, unless a later
+        /// generation has already been written
+        fn write_trash(generation: u64, trash_data: Vec<u8>) -> ();
+
+        /// load the encoded inactive entries, if they have been written
+        fn load_inactive() -> Option<Vec<u8>>;
+
+        /// replace the encoded inactive entries, unless a later
+        /// generation has already been written
+        fn write_inactive(generation: u64, inactive_data: Vec<u8>) -> ();
+
+        /// load the encoded server identity keypair, if it has been written
+        fn load_server_id() -> Option<Vec<u8>>;
+
+        /// write the encoded server identity keypair
+        fn write_server_id(server_id_data: Vec<u8>) -> ();
+    }
+}
+
+/// The path the sealed copy of the index file at `path` is kept at,
+/// in a store with an encrypted index.
+pub(crate) fn sealed_path(path: &Path) -> PathBuf {
+    path.with_extension("sealed")
+}
+
+/// The path a sealed copy of the store file at `path` is written to
+/// while its index is encrypted, see `encrypt_index`.
+pub(crate) fn sealing_path(path: &Path) -> PathBuf {
+    path.with_extension("sealing")
+}
+
+/// The path a trailing partial record found at `offset` of the store
+/// file at `path` is moved to, see `quarantine_partial_record`.
+pub(crate) fn partial_record_path(path: &Path, offset: u64) -> PathBuf {
+    path.with_extension(format!("partial.{}", offset))
+}
+
+/// How the entries, and the tags, trash and inactive index files
+/// are kept.
+enum IndexFiles {
+    /// in plaintext, the index files at their configured paths
+    Plain,
+    /// sealed, with the key once the unlock entry is verified
+    Sealed(Option<unlock::IndexKey>),
+}
+
+impl IndexFiles {
+    /// The byte length of each entry record following the unlock entry.
+    fn record_size(&self) -> u64 {
+        match self {
+            IndexFiles::Plain => entry::ENTRY_SIZE as u64,
+            IndexFiles::Sealed(_) => unlock::SEALED_ENTRY_SIZE as u64,
+        }
+    }
+
+    /// The key to seal and open entries with, if they are sealed.
+    fn entry_key(&self) -> LairResult<Option<&unlock::IndexKey>> {
+        match self {
+            IndexFiles::Plain => Ok(None),
+            IndexFiles::Sealed(None) => Err(LairError::KeystoreLocked),
+            IndexFiles::Sealed(Some(index_key)) => Ok(Some(index_key)),
+        }
+    }
+}
+
+/// The index files of a store: name, as sealed, and plaintext path.
+struct IndexPaths([(&'static str, PathBuf); 3]);
+
+impl IndexPaths {
+    fn get(&self, name: &str) -> &Path {
+        &self.0.iter().find(|(n, _)| *n == name).unwrap().1
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn spawn_entry_store_file_task(
     store_file: tokio::fs::File,
+    store_path: PathBuf,
+    store_number: u8,
+    entropy: EntropySourceHandle,
+    tags_path: PathBuf,
+    trash_path: PathBuf,
+    inactive_path: PathBuf,
+    server_id_path: PathBuf,
 ) -> LairResult<futures::channel::mpsc::Sender<EntryStoreFile>> {
     let (s, r) = futures::channel::mpsc::channel(10);
 
-    tokio::task::spawn(entry_store_file_task(store_file, r));
+    tokio::task::spawn(entry_store_file_task(
+        store_file,
+        store_path,
+        store_number,
+        entropy,
+        IndexPaths([
+            ("tags", tags_path),
+            ("trash", trash_path),
+            ("inactive", inactive_path),
+        ]),
+        server_id_path,
+        r,
+    ));
 
     Ok(s)
 }
@@ -33,14 +163,40 @@ pub(crate) async fn spawn_entry_store_file_task(
 /// we actually need to process requests in series.
 async fn entry_store_file_task(
     mut store_file: tokio::fs::File,
+    store_path: PathBuf,
+    store_number: u8,
+    entropy: EntropySourceHandle,
+    index_paths: IndexPaths,
+    server_id_path: PathBuf,
     mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
 ) -> LairResult<()> {
     use futures::{future::FutureExt, stream::StreamExt};
 
+    let sealing_path = sealing_path(&store_path);
+
+    // plaintext until the unlock entry says otherwise
+    let mut index_files = IndexFiles::Plain;
+
+    // tag / trash / inactive writes may arrive out of order,
+    // only keep the newest
+    let mut tags_generation = 0;
+    let mut trash_generation = 0;
+    let mut inactive_generation = 0;
+
     while let Some(req) = recv.next().await {
         match req {
             EntryStoreFile::InitLoadUnlock { respond, .. } => {
-                let res = init_load_unlock(&mut store_file).await;
+                let res =
+                    init_load_unlock(&mut store_file, &sealing_path).await;
+                if let Ok(Some(entry_data)) = &res {
+                    if let Ok(Some(unlock_entry)) =
+                        unlock::UnlockEntry::decode(entry_data)
+                    {
+                        if unlock_entry.encrypted_index {
+                            index_files = IndexFiles::Sealed(None);
+                        }
+                    }
+                }
                 respond.r(Ok(async move { res }.boxed().into()));
             }
             EntryStoreFile::WriteUnlock {
@@ -51,8 +207,79 @@ async fn entry_store_file_task(
                 let res = write_unlock(&mut store_file, entry_data).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
-            EntryStoreFile::LoadAllEntries { respond, .. } => {
-                let res = load_all_entries(&mut store_file).await;
+            EntryStoreFile::SetIndexKey {
+                respond, index_key, ..
+            } => {
+                let res = match index_files {
+                    IndexFiles::Sealed(None) => {
+                        index_files = IndexFiles::Sealed(Some(index_key));
+                        // left behind by a migration interrupted
+                        // after it wrote the unlock entry
+                        remove_plain_files(&index_paths).await
+                    }
+                    _ => Err("the store index is not sealed".into()),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::EncryptIndex {
+                respond,
+                index_key,
+                entry_data,
+                backup,
+                ..
+            } => {
+                let res = match index_files {
+                    IndexFiles::Plain => {
+                        encrypt_index(
+                            &mut store_file,
+                            &sealing_path,
+                            store_number,
+                            &entropy,
+                            &index_paths,
+                            &index_key,
+                            entry_data,
+                            backup,
+                        )
+                        .await
+                    }
+                    _ => Err("the store index is already sealed".into()),
+                };
+                if res.is_ok() {
+                    index_files = IndexFiles::Sealed(Some(index_key));
+                }
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadAllEntries {
+                respond, strict, ..
+            } => {
+                // strict, the store is left as is,
+                // and `load_all_entries` refuses its size
+                let res = match strict {
+                    true => Ok(()),
+                    false => {
+                        quarantine_partial_record(
+                            &mut store_file,
+                            &store_path,
+                            index_files.record_size(),
+                        )
+                        .await
+                    }
+                };
+                let res = match res {
+                    Ok(()) => {
+                        load_all_entries(
+                            &mut store_file,
+                            store_number,
+                            &index_files,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::GetFileSize { respond, .. } => {
+                let res = get_file_size(&mut store_file).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
             EntryStoreFile::WriteNextEntry {
@@ -60,7 +287,118 @@ async fn entry_store_file_task(
                 entry_data,
                 ..
             } => {
-                let res = write_next_entry(&mut store_file, entry_data).await;
+                let res = write_next_entry(
+                    &mut store_file,
+                    store_number,
+                    &index_files,
+                    &entropy,
+                    entry_data,
+                )
+                .await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::EraseEntries {
+                respond, indices, ..
+            } => {
+                let res = erase_entries(
+                    &mut store_file,
+                    store_number,
+                    &index_files,
+                    &entropy,
+                    indices,
+                )
+                .await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadTags { respond, .. } => {
+                let res =
+                    load_index_file(&index_files, &index_paths, "tags").await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteTags {
+                respond,
+                generation,
+                tags_data,
+                ..
+            } => {
+                let res = if generation > tags_generation {
+                    tags_generation = generation;
+                    write_index_file(
+                        &index_files,
+                        &entropy,
+                        &index_paths,
+                        "tags",
+                        tags_data,
+                    )
+                    .await
+                } else {
+                    Ok(())
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadTrash { respond, .. } => {
+                let res =
+                    load_index_file(&index_files, &index_paths, "trash").await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteTrash {
+                respond,
+                generation,
+                trash_data,
+                ..
+            } => {
+                let res = if generation > trash_generation {
+                    trash_generation = generation;
+                    write_index_file(
+                        &index_files,
+                        &entropy,
+                        &index_paths,
+                        "trash",
+                        trash_data,
+                    )
+                    .await
+                } else {
+                    Ok(())
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadInactive { respond, .. } => {
+                let res =
+                    load_index_file(&index_files, &index_paths, "inactive")
+                        .await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteInactive {
+                respond,
+                generation,
+                inactive_data,
+                ..
+            } => {
+                let res = if generation > inactive_generation {
+                    inactive_generation = generation;
+                    write_index_file(
+                        &index_files,
+                        &entropy,
+                        &index_paths,
+                        "inactive",
+                        inactive_data,
+                    )
+                    .await
+                } else {
+                    Ok(())
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadServerId { respond, .. } => {
+                let res = load_file(&server_id_path).await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteServerId {
+                respond,
+                server_id_data,
+                ..
+            } => {
+                let res = write_file(&server_id_path, server_id_data).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
         }
@@ -69,24 +407,190 @@ async fn entry_store_file_task(
     Ok(())
 }
 
+/// Load the index file `name`, opening it if the index is sealed.
+async fn load_index_file(
+    index_files: &IndexFiles,
+    index_paths: &IndexPaths,
+    name: &str,
+) -> LairResult<Option<Vec<u8>>> {
+    let path = index_paths.get(name);
+    match index_files {
+        IndexFiles::Plain => load_file(path).await,
+        IndexFiles::Sealed(None) => Err(LairError::KeystoreLocked),
+        IndexFiles::Sealed(Some(index_key)) => load_file(&sealed_path(path))
+            .await?
+            .map(|sealed| index_key.open(name, &sealed))
+            .transpose(),
+    }
+}
+
+/// Write the index file `name`, sealing it if the index is sealed.
+async fn write_index_file(
+    index_files: &IndexFiles,
+    entropy: &EntropySourceHandle,
+    index_paths: &IndexPaths,
+    name: &str,
+    data: Vec<u8>,
+) -> LairResult<()> {
+    let path = index_paths.get(name);
+    match index_files {
+        IndexFiles::Plain => write_file(path, data).await,
+        IndexFiles::Sealed(None) => Err(LairError::KeystoreLocked),
+        IndexFiles::Sealed(Some(index_key)) => {
+            write_file(
+                &sealed_path(path),
+                index_key.seal(entropy, name, &data)?,
+            )
+            .await
+        }
+    }
+}
+
+/// Seal the plaintext index files, then write a sealed copy of the
+/// store, beginning with the unlock entry `entry_data` recording the
+/// encrypted index, to `sealing_path`. That copy is the commit point:
+/// until it is written the store opens as it did, sealed files it left
+/// are rewritten by the next attempt. Once it is, it is copied over the
+/// store file, again when the store is next opened if that is
+/// interrupted, see `apply_sealing`. The plaintext files are only
+/// removed after that. A `backup` directory already holding a file
+/// is from an interrupted attempt, that file is kept.
+#[allow(clippy::too_many_arguments)]
+async fn encrypt_index(
+    store_file: &mut tokio::fs::File,
+    sealing_path: &Path,
+    store_number: u8,
+    entropy: &EntropySourceHandle,
+    index_paths: &IndexPaths,
+    index_key: &unlock::IndexKey,
+    entry_data: Vec<u8>,
+    backup: Option<PathBuf>,
+) -> LairResult<()> {
+    use tokio::io::AsyncReadExt;
+
+    if let Some(backup) = backup {
+        util::create_dir_all_private(&backup)
+            .map_err(|e| LairError::path_context("creating", &backup, e))?;
+        let store_backup = backup.join("store");
+        if load_file(&store_backup).await?.is_none() {
+            store_file
+                .seek(std::io::SeekFrom::Start(0))
+                .await
+                .map_err(|e| LairError::context("backing up the store", e))?;
+            let mut data = Vec::new();
+            store_file
+                .read_to_end(&mut data)
+                .await
+                .map_err(|e| LairError::context("backing up the store", e))?;
+            write_file(&store_backup, data).await?;
+        }
+        for (name, path) in index_paths.0.iter() {
+            let index_backup = backup.join(name);
+            if load_file(&index_backup).await?.is_some() {
+                continue;
+            }
+            if let Some(data) = load_file(path).await? {
+                write_file(&index_backup, data).await?;
+            }
+        }
+    }
+
+    for (name, path) in index_paths.0.iter() {
+        match load_file(path).await? {
+            Some(data) => {
+                write_file(
+                    &sealed_path(path),
+                    index_key.seal(entropy, name, &data)?,
+                )
+                .await?
+            }
+            None => remove_file(&sealed_path(path)).await?,
+        }
+    }
+
+    let mut sealed = entry_data;
+    for (index, data) in
+        load_all_entries(store_file, store_number, &IndexFiles::Plain).await?
+    {
+        sealed.extend_from_slice(&index_key.seal_entry(
+            entropy,
+            index.entry(),
+            &data,
+        )?);
+    }
+    write_file(sealing_path, sealed).await?;
+    apply_sealing(store_file, sealing_path).await?;
+
+    remove_plain_files(index_paths).await
+}
+
+/// Copy the sealed copy of the store left at `sealing_path` by
+/// `encrypt_index` over the store file, if there is one, then remove it.
+/// Copying it again is harmless, so one left by an interrupted
+/// migration is applied when the store is next opened.
+async fn apply_sealing(
+    store_file: &mut tokio::fs::File,
+    sealing_path: &Path,
+) -> LairResult<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let sealed = match load_file(sealing_path).await? {
+        Some(sealed) => sealed,
+        None => return Ok(()),
+    };
+    store_file
+        .seek(std::io::SeekFrom::Start(0))
+        .await
+        .map_err(|e| LairError::context("sealing the store", e))?;
+    store_file
+        .write_all(&sealed)
+        .await
+        .map_err(|e| LairError::context("sealing the store", e))?;
+    store_file
+        .set_len(sealed.len() as u64)
+        .await
+        .map_err(|e| LairError::context("sealing the store", e))?;
+    sync_all(store_file)
+        .await
+        .map_err(|e| LairError::context("sealing the store", e))?;
+
+    remove_file(sealing_path).await
+}
+
+/// Remove the plaintext index files of a store with a sealed index.
+async fn remove_plain_files(index_paths: &IndexPaths) -> LairResult<()> {
+    for (_, path) in index_paths.0.iter() {
+        remove_file(path).await?;
+    }
+    Ok(())
+}
+
 async fn init_load_unlock(
     store_file: &mut tokio::fs::File,
+    sealing_path: &Path,
 ) -> LairResult<Option<Vec<u8>>> {
     use tokio::io::AsyncReadExt;
 
-    let meta = store_file.metadata().await.map_err(LairError::other)?;
+    // finish a migration interrupted after it committed
+    apply_sealing(store_file, sealing_path).await?;
+
+    let meta = store_file
+        .metadata()
+        .await
+        .map_err(|e| LairError::context("reading the store unlock entry", e))?;
     let total_size = meta.len();
     if total_size >= entry::ENTRY_SIZE as u64 {
         store_file
             .seek(std::io::SeekFrom::Start(0))
             .await
-            .map_err(LairError::other)?;
+            .map_err(|e| {
+                LairError::context("reading the store unlock entry", e)
+            })?;
 
         let mut buf = vec![0; entry::ENTRY_SIZE];
-        store_file
-            .read_exact(&mut buf)
-            .await
-            .map_err(LairError::other)?;
+        store_file.read_exact(&mut buf).await.map_err(|e| {
+            LairError::context("reading the store unlock entry", e)
+        })?;
 
         Ok(Some(buf))
     } else {
@@ -103,44 +607,123 @@ async fn write_unlock(
     store_file
         .seek(std::io::SeekFrom::Start(0))
         .await
-        .map_err(LairError::other)?;
+        .map_err(|e| LairError::context("writing the store unlock entry", e))?;
 
     store_file
         .write_all(&entry_data)
         .await
-        .map_err(LairError::other)?;
+        .map_err(|e| LairError::context("writing the store unlock entry", e))?;
 
-    store_file.sync_all().await.map_err(LairError::other)?;
+    sync_all(store_file)
+        .await
+        .map_err(|e| LairError::context("writing the store unlock entry", e))?;
 
     Ok(())
 }
 
+async fn get_file_size(store_file: &mut tokio::fs::File) -> LairResult<u64> {
+    let meta = store_file
+        .metadata()
+        .await
+        .map_err(|e| LairError::context("reading the store size", e))?;
+    Ok(meta.len())
+}
+
+/// The offset of entry number `entry`, in a store file whose
+/// entries following the unlock entry are `record_size` bytes.
+fn record_offset(entry: u64, record_size: u64) -> u64 {
+    match entry {
+        0 => 0,
+        _ => entry::ENTRY_SIZE as u64 + (entry - 1) * record_size,
+    }
+}
+
 async fn query_entry_count(
     store_file: &mut tokio::fs::File,
+    record_size: u64,
 ) -> LairResult<u64> {
-    let meta = store_file.metadata().await.map_err(LairError::other)?;
-    let total_size = meta.len();
-    let entry_count = total_size / entry::ENTRY_SIZE as u64;
+    let total_size = get_file_size(store_file).await?;
+    let entry_count = match total_size.checked_sub(entry::ENTRY_SIZE as u64) {
+        None => 0,
+        Some(records_size) => 1 + records_size / record_size,
+    };
 
-    if entry_count * entry::ENTRY_SIZE as u64 != total_size {
-        // @todo - panic for now... eventually cover over invalid entry
-        panic!(
-            "BAD entry size {} count * {} size != {} file size",
-            entry_count,
-            entry::ENTRY_SIZE,
-            total_size
-        );
+    if record_offset(entry_count, record_size) != total_size {
+        return Err(format!(
+            "bad store size, {} entries of {} bytes != {} bytes",
+            entry_count, record_size, total_size,
+        )
+        .into());
     }
 
     Ok(entry_count)
 }
 
+/// A write interrupted part way leaves a partial record at the end of
+/// the store file. Move it aside, to `partial_record_path`, and truncate
+/// the store file to its last whole record, so the entries before it
+/// still load, and later entries are written at record boundaries.
+async fn quarantine_partial_record(
+    store_file: &mut tokio::fs::File,
+    store_path: &Path,
+    record_size: u64,
+) -> LairResult<()> {
+    use tokio::io::AsyncReadExt;
+
+    let total_size = get_file_size(store_file).await?;
+    let partial_size = match total_size.checked_sub(entry::ENTRY_SIZE as u64) {
+        None => return Ok(()),
+        Some(records_size) => records_size % record_size,
+    };
+    if partial_size == 0 {
+        return Ok(());
+    }
+    let offset = total_size - partial_size;
+
+    store_file
+        .seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| LairError::context("reading a partial store entry", e))?;
+    let mut partial = vec![0; partial_size as usize];
+    store_file
+        .read_exact(&mut partial)
+        .await
+        .map_err(|e| LairError::context("reading a partial store entry", e))?;
+
+    let partial_path = partial_record_path(store_path, offset);
+    write_file(&partial_path, partial).await?;
+
+    tracing::warn!(
+        "store {} ends in a partial entry of {} bytes at offset {}, \
+        moved it to {} and truncated the store to its last whole entry",
+        store_path.display(),
+        partial_size,
+        offset,
+        partial_path.display(),
+    );
+
+    store_file.set_len(offset).await.map_err(|e| {
+        LairError::context("truncating a partial store entry", e)
+    })?;
+    sync_all(store_file).await.map_err(|e| {
+        LairError::context("truncating a partial store entry", e)
+    })?;
+
+    Ok(())
+}
+
+/// Sealed entries are opened, those that fail to open are returned
+/// as read, a `SEALED_ENTRY_SIZE` record, to be quarantined.
 async fn load_all_entries(
     store_file: &mut tokio::fs::File,
+    store_number: u8,
+    index_files: &IndexFiles,
 ) -> LairResult<Vec<(super::KeystoreIndex, Vec<u8>)>> {
     use tokio::io::AsyncReadExt;
 
-    let entry_count = query_entry_count(store_file).await?;
+    let entry_key = index_files.entry_key()?;
+    let record_size = index_files.record_size();
+    let entry_count = query_entry_count(store_file, record_size).await?;
 
     if entry_count <= 1 {
         return Ok(Vec::with_capacity(0));
@@ -149,17 +732,22 @@ async fn load_all_entries(
     store_file
         .seek(std::io::SeekFrom::Start(entry::ENTRY_SIZE as u64))
         .await
-        .map_err(LairError::other)?;
+        .map_err(|e| LairError::context("reading store entries", e))?;
 
     let mut out = Vec::new();
 
     for i in 1..(entry_count as u32) {
-        let mut buf = vec![0; entry::ENTRY_SIZE];
+        let mut buf = vec![0; record_size as usize];
         store_file
             .read_exact(&mut buf)
             .await
-            .map_err(LairError::other)?;
-        out.push((i.into(), buf));
+            .map_err(|e| LairError::context("reading store entries", e))?;
+        if let Some(entry_key) = entry_key {
+            if let Ok(entry) = entry_key.open_entry(i, &buf) {
+                buf = entry;
+            }
+        }
+        out.push((super::KeystoreIndex::new(store_number, i), buf));
     }
 
     Ok(out)
@@ -167,6 +755,9 @@ async fn load_all_entries(
 
 async fn write_next_entry(
     store_file: &mut tokio::fs::File,
+    store_number: u8,
+    index_files: &IndexFiles,
+    entropy: &EntropySourceHandle,
     entry_data: Vec<u8>,
 ) -> LairResult<super::KeystoreIndex> {
     use tokio::io::AsyncWriteExt;
@@ -180,21 +771,191 @@ async fn write_next_entry(
         .into());
     }
 
-    let entry_count = query_entry_count(store_file).await?;
+    let entry_key = index_files.entry_key()?;
+    let record_size = index_files.record_size();
+    let entry_count = query_entry_count(store_file, record_size).await?;
 
-    let start_loc = entry_count * entry::ENTRY_SIZE as u64;
+    // the entry number must fit in the low bits of a KeystoreIndex
+    if entry_count > super::KeystoreIndex::MAX_ENTRY as u64 {
+        return Err(format!(
+            "store is full, it cannot hold more than {} entries",
+            super::KeystoreIndex::MAX_ENTRY,
+        )
+        .into());
+    }
+
+    let entry_data = match entry_key {
+        Some(entry_key) => {
+            entry_key.seal_entry(entropy, entry_count as u32, &entry_data)?
+        }
+        None => entry_data,
+    };
+
+    let start_loc = record_offset(entry_count, record_size);
 
     store_file
         .seek(std::io::SeekFrom::Start(start_loc))
         .await
-        .map_err(LairError::other)?;
+        .map_err(|e| LairError::context("writing a store entry", e))?;
 
     store_file
         .write_all(&entry_data)
         .await
-        .map_err(LairError::other)?;
+        .map_err(|e| LairError::context("writing a store entry", e))?;
+
+    sync_all(store_file)
+        .await
+        .map_err(|e| LairError::context("writing a store entry", e))?;
+
+    Ok(super::KeystoreIndex::new(store_number, entry_count as u32))
+}
+
+async fn erase_entries(
+    store_file: &mut tokio::fs::File,
+    store_number: u8,
+    index_files: &IndexFiles,
+    entropy: &EntropySourceHandle,
+    indices: Vec<super::KeystoreIndex>,
+) -> LairResult<()> {
+    use tokio::io::AsyncWriteExt;
 
-    store_file.sync_all().await.map_err(LairError::other)?;
+    let entry_key = index_files.entry_key()?;
+    let record_size = index_files.record_size();
+    let entry_count = query_entry_count(store_file, record_size).await?;
+
+    // entry 0 is the unlock entry, never an erasable one
+    for index in indices.iter() {
+        let entry = index.entry() as u64;
+        if index.store_number() != store_number
+            || entry == 0
+            || entry >= entry_count
+        {
+            return Err(format!("cannot erase KeystoreIndex {}", index).into());
+        }
+    }
+
+    let erased = entry::encode_erased_entry()?;
+    for index in indices.iter() {
+        let erased = match entry_key {
+            Some(entry_key) => {
+                entry_key.seal_entry(entropy, index.entry(), &erased)?
+            }
+            None => erased.clone(),
+        };
+        store_file
+            .seek(std::io::SeekFrom::Start(record_offset(
+                index.entry() as u64,
+                record_size,
+            )))
+            .await
+            .map_err(|e| LairError::context("erasing a store entry", e))?;
+
+        store_file
+            .write_all(&erased)
+            .await
+            .map_err(|e| LairError::context("erasing a store entry", e))?;
+    }
+
+    sync_all(store_file)
+        .await
+        .map_err(|e| LairError::context("erasing a store entry", e))?;
+
+    Ok(())
+}
+
+/// Syncs of store files done by this process, see `sync_count`.
+static SYNC_COUNT: std::sync::atomic::AtomicU64 =
+    std::sync::atomic::AtomicU64::new(0);
+
+/// The number of store file syncs this process has done,
+/// the entry store file and the files beside it alike.
+pub(crate) fn sync_count() -> u64 {
+    SYNC_COUNT.load(std::sync::atomic::Ordering::Relaxed)
+}
 
-    Ok((entry_count as u32).into())
+/// `sync_all` a store file, counting it in `sync_count`.
+async fn sync_all(file: &mut tokio::fs::File) -> std::io::Result<()> {
+    SYNC_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    file.sync_all().await
+}
+
+pub(crate) async fn load_file(
+    path: &std::path::Path,
+) -> LairResult<Option<Vec<u8>>> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(LairError::path_context("reading", path, e)),
+    }
+}
+
+/// Remove the file at `path`, if there is one.
+async fn remove_file(path: &Path) -> LairResult<()> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(LairError::path_context("removing", path, e)),
+    }
+}
+
+pub(crate) async fn write_file(
+    path: &std::path::Path,
+    data: Vec<u8>,
+) -> LairResult<()> {
+    use tokio::io::AsyncWriteExt;
+
+    // write a temp file and rename it over the old one,
+    // so a crash never leaves a partially written file
+    let mut tmp_path = path.to_path_buf();
+    tmp_path.set_extension("tmp");
+
+    let mut tmp_file = tokio::fs::OpenOptions::from({
+        let mut options = util::private_open_options();
+        options.write(true).create(true).truncate(true);
+        options
+    })
+    .open(&tmp_path)
+    .await
+    .map_err(|e| LairError::path_context("writing", &tmp_path, e))?;
+    #[cfg(test)]
+    fault::check(path)
+        .map_err(|e| LairError::path_context("writing", &tmp_path, e))?;
+    tmp_file
+        .write_all(&data)
+        .await
+        .map_err(|e| LairError::path_context("writing", &tmp_path, e))?;
+    sync_all(&mut tmp_file)
+        .await
+        .map_err(|e| LairError::path_context("writing", &tmp_path, e))?;
+    drop(tmp_file);
+
+    tokio::fs::rename(&tmp_path, path)
+        .await
+        .map_err(|e| LairError::path_context("replacing", path, e))?;
+
+    Ok(())
+}
+
+/// Io errors for `write_file` to fail with, letting tests exercise
+/// store failures.
+#[cfg(test)]
+pub(crate) mod fault {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    static FAULTS: once_cell::sync::Lazy<
+        std::sync::Mutex<HashMap<PathBuf, std::io::ErrorKind>>,
+    > = once_cell::sync::Lazy::new(Default::default);
+
+    /// Fail the next write of the file at `path` with `kind`.
+    pub(crate) fn inject(path: &Path, kind: std::io::ErrorKind) {
+        FAULTS.lock().unwrap().insert(path.to_path_buf(), kind);
+    }
+
+    pub(super) fn check(path: &Path) -> std::io::Result<()> {
+        match FAULTS.lock().unwrap().remove(path) {
+            Some(kind) => Err(std::io::Error::new(kind, "injected fault")),
+            None => Ok(()),
+        }
+    }
 }
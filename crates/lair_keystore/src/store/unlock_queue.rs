@@ -0,0 +1,209 @@
+//! Requests parked until their store unlocks,
+//! see `ConfigBuilder::set_queue_while_locked`.
+
+use crate::*;
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt};
+use std::collections::VecDeque;
+
+/// A parked request's turn to run. Dropping it, once the request
+/// is done, lets the next parked request run.
+pub struct UnlockTurn {
+    // signals the next request as it drops
+    _done: Option<oneshot::Sender<()>>,
+}
+
+struct Inner {
+    locked: bool,
+    // releasing parked requests, one at a time
+    draining: bool,
+    waiting: VecDeque<oneshot::Sender<UnlockTurn>>,
+}
+
+/// The requests of a store waiting for it to unlock, in arrival order.
+pub struct UnlockQueue {
+    store_number: u8,
+    options: Option<LockedQueueOptions>,
+    inner: std::sync::Mutex<Inner>,
+}
+
+impl UnlockQueue {
+    /// The queue of a locked store. With no `options`,
+    /// nothing ever waits.
+    pub fn new(
+        store_number: u8,
+        options: Option<LockedQueueOptions>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            store_number,
+            options,
+            inner: std::sync::Mutex::new(Inner {
+                locked: true,
+                draining: false,
+                waiting: VecDeque::new(),
+            }),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // only pushed / popped, never held across a panic point
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The number of requests waiting.
+    pub fn depth(&self) -> usize {
+        self.lock()
+            .waiting
+            .iter()
+            .filter(|waiter| !waiter.is_canceled())
+            .count()
+    }
+
+    /// Wait for the store to unlock, behind any earlier request.
+    /// `LairError::KeystoreLocked` if the queue is full already,
+    /// or once the wait expires.
+    pub fn park(
+        &self,
+    ) -> LairResult<BoxFuture<'static, LairResult<UnlockTurn>>> {
+        let options = match self.options {
+            Some(options) => options,
+            None => {
+                return Ok(async move { Ok(UnlockTurn { _done: None }) }.boxed())
+            }
+        };
+
+        let mut inner = self.lock();
+        if !inner.locked && !inner.draining {
+            return Ok(async move { Ok(UnlockTurn { _done: None }) }.boxed());
+        }
+
+        // requests that gave up no longer hold a place
+        inner.waiting.retain(|waiter| !waiter.is_canceled());
+        if inner.waiting.len() >= options.max_len {
+            tracing::warn!(
+                store = self.store_number,
+                depth = inner.waiting.len(),
+                "locked queue full, refusing request",
+            );
+            return Err(LairError::KeystoreLocked);
+        }
+
+        let (send, recv) = oneshot::channel();
+        inner.waiting.push_back(send);
+        tracing::info!(
+            store = self.store_number,
+            depth = inner.waiting.len(),
+            "parked request until the store unlocks",
+        );
+
+        Ok(async move {
+            match tokio::time::timeout(options.max_wait, recv).await {
+                Ok(Ok(turn)) => Ok(turn),
+                _ => Err(LairError::KeystoreLocked),
+            }
+        }
+        .boxed())
+    }
+
+    /// The store is unlocked, run the parked requests in order.
+    pub fn release(self: &Arc<Self>) {
+        {
+            let mut inner = self.lock();
+            if !inner.locked {
+                return;
+            }
+            inner.locked = false;
+            inner.draining = true;
+        }
+
+        let queue = self.clone();
+        tokio::task::spawn(async move {
+            loop {
+                let waiter = {
+                    let mut inner = queue.lock();
+                    match inner.waiting.pop_front() {
+                        Some(waiter) => waiter,
+                        None => {
+                            inner.draining = false;
+                            return;
+                        }
+                    }
+                };
+                let (done_send, done_recv) = oneshot::channel();
+                if waiter
+                    .send(UnlockTurn {
+                        _done: Some(done_send),
+                    })
+                    .is_ok()
+                {
+                    // resolves as the turn is dropped
+                    let _ = done_recv.await;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn options(max_len: usize, max_wait: Duration) -> LockedQueueOptions {
+        LockedQueueOptions { max_len, max_wait }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_runs_parked_requests_in_order() {
+        let queue =
+            UnlockQueue::new(0, Some(options(8, Duration::from_secs(60))));
+        let (order_send, order_recv) = futures::channel::mpsc::unbounded();
+        let mut tasks = Vec::new();
+        for i in 0..8_u32 {
+            let parked = queue.park().unwrap();
+            let order_send = order_send.clone();
+            tasks.push(tokio::task::spawn(async move {
+                let _turn = parked.await.unwrap();
+                // later requests would overtake a slow one, unless
+                // each waits its turn
+                tokio::time::delay_for(Duration::from_millis(
+                    (8 - i as u64) * 5,
+                ))
+                .await;
+                order_send.unbounded_send(i).unwrap();
+            }));
+        }
+        assert_eq!(8, queue.depth());
+        drop(order_send);
+
+        queue.release();
+        futures::future::try_join_all(tasks).await.unwrap();
+        assert_eq!(
+            (0..8).collect::<Vec<_>>(),
+            futures::StreamExt::collect::<Vec<_>>(order_recv).await,
+        );
+        assert_eq!(0, queue.depth());
+        assert!(queue.park().unwrap().await.is_ok());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_refuses_requests_past_its_limits() {
+        let queue =
+            UnlockQueue::new(0, Some(options(2, Duration::from_millis(50))));
+        let first = queue.park().unwrap();
+        let _second = queue.park().unwrap();
+        assert!(matches!(queue.park(), Err(LairError::KeystoreLocked)));
+
+        assert!(matches!(first.await, Err(LairError::KeystoreLocked)));
+        // the expired request gave up its place
+        assert_eq!(1, queue.depth());
+        assert!(queue.park().is_ok());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_parks_nothing_unless_configured() {
+        let queue = UnlockQueue::new(0, None);
+        assert!(queue.park().unwrap().await.is_ok());
+        assert_eq!(0, queue.depth());
+    }
+}
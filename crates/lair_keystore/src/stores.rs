@@ -0,0 +1,367 @@
+//! Named stores, served alongside the default store by one server.
+//!
+//! Each named store lives in its own `<number>-<name>` directory under
+//! `Config::get_stores_path`, holding its own store and tags files, so
+//! it has its own passphrase and lock state. The store number (1 - 255)
+//! is scoped into the keystore index of each of its entries,
+//! see `KeystoreIndex::new`. The default store is store number 0.
+
+use crate::*;
+use lair_keystore_api::{actor::*, internal::util};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A named store, as found in the stores directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedStore {
+    /// The store number, scoped into the keystore indexes of its entries.
+    pub number: u8,
+
+    /// The store name.
+    pub name: String,
+
+    /// The directory holding the store files.
+    pub path: PathBuf,
+}
+
+/// Error unless `name` is a valid store name: 1 to `MAX_STORE_NAME_LEN`
+/// ascii alphanumerics, `-` or `_`.
+pub fn check_store_name(name: &str) -> LairResult<()> {
+    if name.is_empty()
+        || name.len() > MAX_STORE_NAME_LEN
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    {
+        return Err(format!(
+            "invalid store name {:?}, expected 1 to {} ascii alphanumerics, '-' or '_'",
+            name, MAX_STORE_NAME_LEN,
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// List the named stores in store number order.
+/// The default store is not included.
+pub fn list_stores(config: &Config) -> LairResult<Vec<NamedStore>> {
+    let dir = match std::fs::read_dir(config.get_stores_path()) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(LairError::other(e)),
+    };
+
+    let mut out = Vec::new();
+    for dir_entry in dir {
+        let dir_entry = dir_entry.map_err(LairError::other)?;
+        let path = dir_entry.path();
+        let file_name = dir_entry.file_name();
+        // skip anything we did not create
+        let (number, name) = match file_name.to_str().and_then(|f| {
+            let (number, name) = f.split_once('-')?;
+            Some((number.parse::<u8>().ok()?, name))
+        }) {
+            Some((number, name)) if number > 0 && path.is_dir() => {
+                (number, name)
+            }
+            _ => continue,
+        };
+        if check_store_name(name).is_err() {
+            continue;
+        }
+        out.push(NamedStore {
+            number,
+            name: name.to_string(),
+            path,
+        });
+    }
+    out.sort_by_key(|store| store.number);
+    Ok(out)
+}
+
+/// Find a named store by name.
+pub fn find_store(config: &Config, name: &str) -> LairResult<NamedStore> {
+    list_stores(config)?
+        .into_iter()
+        .find(|store| store.name == name)
+        .ok_or_else(|| format!("no such store: {}", name).into())
+}
+
+/// Create a new, empty, named store, with the lowest free store number.
+/// Its passphrase is set by the first client to unlock it.
+pub fn create_store(config: &Config, name: &str) -> LairResult<NamedStore> {
+    check_store_name(name)?;
+    if name == DEFAULT_STORE_NAME {
+        return Err(format!("store name {} is reserved", name).into());
+    }
+
+    let stores = list_stores(config)?;
+    if stores.iter().any(|store| store.name == name) {
+        return Err(format!("store {} already exists", name).into());
+    }
+    let number = (1..=u8::MAX)
+        .find(|number| !stores.iter().any(|store| store.number == *number))
+        .ok_or_else(|| {
+            LairError::from(format!(
+                "cannot create more than {} named stores",
+                u8::MAX
+            ))
+        })?;
+
+    let path = config
+        .get_stores_path()
+        .join(format!("{}-{}", number, name));
+    util::create_dir_all_private(&path).map_err(LairError::other)?;
+
+    Ok(NamedStore {
+        number,
+        name: name.to_string(),
+        path,
+    })
+}
+
+/// The stores served by a running server.
+/// Named stores are opened the first time they are selected,
+/// then kept open.
+pub struct StoreRegistry {
+    config: Arc<Config>,
+    default_store: ghost_actor::GhostSender<store::EntryStore>,
+    named: tokio::sync::Mutex<
+        HashMap<String, ghost_actor::GhostSender<store::EntryStore>>,
+    >,
+    new_entries: tokio::sync::broadcast::Sender<(KeystoreIndex, LairEntryType)>,
+    removed_entries: tokio::sync::broadcast::Sender<KeystoreIndex>,
+    activated_entries: tokio::sync::broadcast::Sender<KeystoreIndex>,
+}
+
+impl StoreRegistry {
+    /// Serve the named stores of `config`, next to its default store.
+    pub async fn new(
+        config: Arc<Config>,
+        default_store: ghost_actor::GhostSender<store::EntryStore>,
+    ) -> LairResult<Arc<Self>> {
+        let new_entries =
+            tokio::sync::broadcast::channel(store::NEW_ENTRY_BACKLOG).0;
+        let removed_entries =
+            tokio::sync::broadcast::channel(store::NEW_ENTRY_BACKLOG).0;
+        forward_entries(
+            &default_store,
+            new_entries.clone(),
+            removed_entries.clone(),
+        )
+        .await?;
+        Ok(Arc::new(Self {
+            config,
+            default_store,
+            named: tokio::sync::Mutex::new(HashMap::new()),
+            new_entries,
+            removed_entries,
+            activated_entries: tokio::sync::broadcast::channel(
+                store::NEW_ENTRY_BACKLOG,
+            )
+            .0,
+        }))
+    }
+
+    /// The default store.
+    pub fn default_store(&self) -> ghost_actor::GhostSender<store::EntryStore> {
+        self.default_store.clone()
+    }
+
+    /// Be told of every entry created in any of the stores from now on,
+    /// including named stores not yet opened.
+    pub fn subscribe_new_entries(&self) -> store::NewEntryReceiver {
+        self.new_entries.subscribe()
+    }
+
+    /// Be told of every entry trashed, purged or erased in any of the
+    /// stores from now on.
+    pub fn subscribe_removed_entries(&self) -> store::RemovedEntryReceiver {
+        self.removed_entries.subscribe()
+    }
+
+    /// Be told of every imported entry activated in any of the stores
+    /// from now on.
+    pub fn subscribe_activated_entries(
+        &self,
+    ) -> tokio::sync::broadcast::Receiver<KeystoreIndex> {
+        self.activated_entries.subscribe()
+    }
+
+    /// Tell the subscribers the entry at `keystore_index` was activated.
+    pub fn entry_activated(&self, keystore_index: KeystoreIndex) {
+        // no subscribers is not an error
+        let _ = self.activated_entries.send(keystore_index);
+    }
+
+    /// Get a store by name, opening it on first use.
+    pub async fn get(
+        &self,
+        name: &str,
+    ) -> LairResult<ghost_actor::GhostSender<store::EntryStore>> {
+        if name == DEFAULT_STORE_NAME {
+            return Ok(self.default_store());
+        }
+
+        // held across the open, so a store is only ever opened once
+        let mut named = self.named.lock().await;
+        if let Some(store) = named.get(name) {
+            return Ok(store.clone());
+        }
+
+        let found = find_store(&self.config, name)?;
+        let config = self.config.for_named_store(found.number, &found.path);
+        let store_file = util::private_open_options()
+            // not append, the unlock entry is rewritten in place
+            .write(true)
+            .read(true)
+            .create(true)
+            .open(config.get_store_path())
+            .map_err(LairError::other)?;
+        let store = store::spawn_entry_store_actor(
+            config,
+            tokio::fs::File::from_std(store_file),
+        )
+        .await?;
+        forward_entries(
+            &store,
+            self.new_entries.clone(),
+            self.removed_entries.clone(),
+        )
+        .await?;
+
+        named.insert(name.to_string(), store.clone());
+        Ok(store)
+    }
+}
+
+/// Forward the entries created in, and removed from, `store` to the
+/// registry's subscribers, for as long as the store runs.
+async fn forward_entries(
+    store: &ghost_actor::GhostSender<store::EntryStore>,
+    new_entries: tokio::sync::broadcast::Sender<(KeystoreIndex, LairEntryType)>,
+    removed_entries: tokio::sync::broadcast::Sender<KeystoreIndex>,
+) -> LairResult<()> {
+    use store::EntryStoreSender;
+
+    forward(store.subscribe_new_entries().await?, new_entries);
+    forward(store.subscribe_removed_entries().await?, removed_entries);
+    Ok(())
+}
+
+fn forward<T: Clone + Send + 'static>(
+    mut recv: tokio::sync::broadcast::Receiver<T>,
+    to: tokio::sync::broadcast::Sender<T>,
+) {
+    use tokio::sync::broadcast::RecvError;
+
+    tokio::task::spawn(async move {
+        loop {
+            match recv.recv().await {
+                Ok(item) => {
+                    // no subscribers is not an error
+                    let _ = to.send(item);
+                }
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::EntryStoreSender;
+
+    #[test]
+    fn it_creates_and_lists_named_stores() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        assert!(list_stores(&config).unwrap().is_empty());
+
+        let alpha = create_store(&config, "alpha").unwrap();
+        assert_eq!(1, alpha.number);
+        let beta = create_store(&config, "beta_2").unwrap();
+        assert_eq!(2, beta.number);
+        assert_eq!(vec![alpha.clone(), beta], list_stores(&config).unwrap());
+        assert_eq!(alpha, find_store(&config, "alpha").unwrap());
+
+        assert!(find_store(&config, "gamma").is_err());
+        assert!(create_store(&config, "alpha").is_err());
+        assert!(create_store(&config, DEFAULT_STORE_NAME).is_err());
+        for bad in &["", "a/b", "..", "with space", &"x".repeat(65)] {
+            assert!(create_store(&config, bad).is_err());
+        }
+
+        // freed numbers are reused, and stray files are ignored
+        std::fs::remove_dir_all(&alpha.path).unwrap();
+        std::fs::write(config.get_stores_path().join("7-stray"), b"").unwrap();
+        assert_eq!(1, create_store(&config, "gamma").unwrap().number);
+        assert_eq!(2, list_stores(&config).unwrap().len());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_scopes_indexes_to_their_store() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let store_file = util::private_open_options()
+            .write(true)
+            .read(true)
+            .create(true)
+            .open(config.get_store_path())
+            .unwrap();
+        let default = store::spawn_entry_store_actor(
+            config.clone(),
+            tokio::fs::File::from_std(store_file),
+        )
+        .await
+        .unwrap();
+        let stores = StoreRegistry::new(config.clone(), default.clone())
+            .await
+            .unwrap();
+        let mut new_entries = stores.subscribe_new_entries();
+
+        create_store(&config, "alpha").unwrap();
+        let alpha = stores.get("alpha").await.unwrap();
+        assert!(stores.get("beta").await.is_err());
+
+        let (d_idx, _) = default
+            .sign_ed25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let (a_idx, a_entry) =
+            alpha.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(KeystoreIndex::new(0, 1), d_idx);
+        assert_eq!(KeystoreIndex::new(1, 1), a_idx);
+        for idx in &[d_idx, a_idx] {
+            assert_eq!(
+                (*idx, LairEntryType::SignEd25519),
+                new_entries.recv().await.unwrap()
+            );
+        }
+        assert_eq!(Some(a_idx), alpha.get_last_entry_index().await.unwrap());
+        assert_eq!(Some(d_idx), default.get_last_entry_index().await.unwrap());
+        assert_eq!(1, alpha.get_entry_count().await.unwrap());
+
+        assert!(matches!(
+            alpha.get_entry_by_index(d_idx).await,
+            Err(LairError::WrongStore(_)),
+        ));
+        assert!(matches!(
+            default.set_entry_tag(a_idx, Some("tag".into())).await,
+            Err(LairError::WrongStore(_)),
+        ));
+
+        // a store opened again by name is the same store
+        let alpha = stores.get("alpha").await.unwrap();
+        assert!(Arc::ptr_eq(
+            &a_entry,
+            &alpha.get_entry_by_index(a_idx).await.unwrap()
+        ));
+    }
+}
@@ -0,0 +1,384 @@
+//! Follow the events of a running lair server, as `lair-keystore watch`
+//! does: every event kind is subscribed to, and each event is reported
+//! as a timestamped line of text, or a json object.
+
+use crate::*;
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::{actor::*, passphrase::Passphrase};
+
+/// How long to wait before connecting again, once the server is gone.
+pub const WATCH_RECONNECT_DELAY: std::time::Duration =
+    std::time::Duration::from_secs(1);
+
+/// Answers the unlock passphrase requests of the named store,
+/// blocking until it does.
+pub type PassphrasePrompt =
+    Arc<dyn Fn(String) -> LairResult<Passphrase> + 'static + Send + Sync>;
+
+/// Report the events of the server at `config` to `on_line`, one line
+/// each, as text, or json objects if `json`. Passphrase requests are
+/// answered by `prompt`, or declined without one. Connects again
+/// whenever the server goes away, so this never returns.
+pub async fn watch(
+    config: Arc<Config>,
+    json: bool,
+    prompt: Option<PassphrasePrompt>,
+    mut on_line: impl FnMut(String) + Send,
+) -> LairResult<()> {
+    let mut waiting = false;
+    loop {
+        let (api, mut evt_recv) = match lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+        )
+        .await
+        {
+            Ok(con) => con,
+            Err(err) => {
+                // report the wait once, not every retry
+                if !waiting {
+                    on_line(format_line(
+                        json,
+                        "waiting",
+                        format!("waiting for the server: {}", err),
+                        serde_json::json!({ "error": err.to_string() }),
+                    ));
+                    waiting = true;
+                }
+                tokio::time::delay_for(WATCH_RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        waiting = false;
+
+        // events sent meanwhile, such as the passphrase request
+        // of every new connection, wait in the receiver
+        let error =
+            match api.lair_set_event_filter(EventKind::ALL.to_vec()).await {
+                Ok(()) => {
+                    on_line(format_line(
+                        json,
+                        "connected",
+                        format!("connected to {}", config.get_connection_url()),
+                        serde_json::json!({
+                            "connection_url":
+                                config.get_connection_url().to_string(),
+                        }),
+                    ));
+                    while let Some(evt) = evt_recv.next().await {
+                        on_line(answer_event(json, &prompt, evt));
+                    }
+                    None
+                }
+                // e.g. the server went away in between
+                Err(err) => Some(err.to_string()),
+            };
+        drop(api);
+
+        let text = match &error {
+            None => "disconnected, reconnecting".to_string(),
+            Some(error) => format!("disconnected: {}, reconnecting", error),
+        };
+        on_line(format_line(
+            json,
+            "disconnected",
+            text,
+            serde_json::json!({ "error": error }),
+        ));
+        tokio::time::delay_for(WATCH_RECONNECT_DELAY).await;
+    }
+}
+
+/// Answer `evt`, returning its report.
+fn answer_event(
+    json: bool,
+    prompt: &Option<PassphrasePrompt>,
+    evt: LairClientEvent,
+) -> String {
+    match evt {
+        LairClientEvent::RequestUnlockPassphrase {
+            respond,
+            store_name,
+            ..
+        } => {
+            let line = format_line(
+                json,
+                "request_unlock_passphrase",
+                format!("unlock passphrase requested for store {}", store_name),
+                serde_json::json!({
+                    "store_name": store_name,
+                    "prompted": prompt.is_some(),
+                }),
+            );
+            match prompt.clone() {
+                Some(prompt) => {
+                    // the prompt blocks, keep reporting meanwhile
+                    tokio::task::spawn(async move {
+                        let res = tokio::task::spawn_blocking(move || {
+                            prompt(store_name)
+                        })
+                        .await
+                        .map_err(LairError::other)
+                        .and_then(|res| res);
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    });
+                }
+                None => {
+                    respond.respond(Ok(async move {
+                        Err("declined by lair-keystore watch".into())
+                    }
+                    .boxed()
+                    .into()));
+                }
+            }
+            line
+        }
+        LairClientEvent::UnlockProgress {
+            respond, percent, ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "unlock_progress",
+                format!("unlock progress {}%", percent),
+                serde_json::json!({ "percent": percent }),
+            )
+        }
+        LairClientEvent::UnlockComplete { respond, error, .. } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            let text = match &error {
+                None => "unlock complete".to_string(),
+                Some(error) => format!("unlock failed: {}", error),
+            };
+            format_line(
+                json,
+                "unlock_complete",
+                text,
+                serde_json::json!({ "error": error }),
+            )
+        }
+        LairClientEvent::EventsDropped { respond, count, .. } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "events_dropped",
+                format!("{} events dropped", count),
+                serde_json::json!({ "count": count }),
+            )
+        }
+        LairClientEvent::EntryCreated {
+            respond,
+            keystore_index,
+            entry_type,
+            ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "entry_created",
+                format!("entry created: {} {:?}", keystore_index.0, entry_type),
+                serde_json::json!({
+                    "keystore_index": keystore_index.0,
+                    "store_number": keystore_index.store_number(),
+                    "entry_type": format!("{:?}", entry_type),
+                }),
+            )
+        }
+        LairClientEvent::RequestSignApproval {
+            respond,
+            keystore_index,
+            pub_key,
+            ..
+        } => {
+            // watching only reports, it never approves signing
+            respond.respond(Ok(async move { Ok(false) }.boxed().into()));
+            format_line(
+                json,
+                "request_sign_approval",
+                format!(
+                    "sign approval requested for entry {}, declined",
+                    keystore_index.0
+                ),
+                serde_json::json!({
+                    "keystore_index": keystore_index.0,
+                    "store_number": keystore_index.store_number(),
+                    "pub_key": format!("{:?}", pub_key),
+                }),
+            )
+        }
+        LairClientEvent::EntryActivated {
+            respond,
+            keystore_index,
+            ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "entry_activated",
+                format!("entry activated: {}", keystore_index.0),
+                serde_json::json!({
+                    "keystore_index": keystore_index.0,
+                    "store_number": keystore_index.store_number(),
+                }),
+            )
+        }
+        LairClientEvent::EntryRemoved {
+            respond,
+            keystore_index,
+            ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "entry_removed",
+                format!("entry removed: {}", keystore_index.0),
+                serde_json::json!({
+                    "keystore_index": keystore_index.0,
+                    "store_number": keystore_index.store_number(),
+                }),
+            )
+        }
+        LairClientEvent::AuditEvent {
+            respond,
+            record,
+            dropped,
+            ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            let entry = match (&record.keystore_index, &record.pub_key) {
+                (Some(keystore_index), _) => {
+                    format!(" entry {}", keystore_index)
+                }
+                (None, Some(pub_key)) => {
+                    format!(" entry {}", base64::encode(&***pub_key))
+                }
+                (None, None) => String::new(),
+            };
+            let outcome = match &record.error {
+                None => "ok".to_string(),
+                Some(error) => format!("failed: {}", error),
+            };
+            format_line(
+                json,
+                "audit_event",
+                format!(
+                    "audit: connection {} request {} {}{} {}{}",
+                    record.connection_id,
+                    record.request_id,
+                    record.operation,
+                    entry,
+                    outcome,
+                    match dropped {
+                        0 => String::new(),
+                        dropped => format!(" ({} records dropped)", dropped),
+                    },
+                ),
+                serde_json::json!({
+                    "operation": record.operation,
+                    "keystore_index": record.keystore_index.map(|i| i.0),
+                    "pub_key": record.pub_key.as_ref().map(|k| base64::encode(&***k)),
+                    "connection_id": record.connection_id,
+                    "capabilities": record.capabilities.to_string(),
+                    "request_id": record.request_id,
+                    "error": record.error,
+                    "dropped": dropped,
+                }),
+            )
+        }
+        LairClientEvent::ClockSkewDetected {
+            respond,
+            wall_secs,
+            expected_secs,
+            ..
+        } => {
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            format_line(
+                json,
+                "clock_skew_detected",
+                format!(
+                    "clock skew detected: the clock reads {}, expected {}",
+                    wall_secs, expected_secs
+                ),
+                serde_json::json!({
+                    "wall_secs": wall_secs,
+                    "expected_secs": expected_secs,
+                }),
+            )
+        }
+    }
+}
+
+/// A timestamped report line, of `text`, or if `json` the `event`
+/// named object, with the `fields` object merged in.
+fn format_line(
+    json: bool,
+    event: &str,
+    text: String,
+    mut fields: serde_json::Value,
+) -> String {
+    let time = format_timestamp(std::time::SystemTime::now());
+    if !json {
+        return format!("{} {}", time, text);
+    }
+    if let Some(fields) = fields.as_object_mut() {
+        fields.insert("time".to_string(), time.into());
+        fields.insert("event".to_string(), event.into());
+    }
+    fields.to_string()
+}
+
+/// Format `time` as an RFC 3339 UTC timestamp, to the millisecond.
+pub fn format_timestamp(time: std::time::SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86400);
+    let secs_of_day = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// The proleptic gregorian (year, month, day) of `days` since
+/// 1970-01-01, after Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_formats_timestamps() {
+        let at = |secs: u64, millis: u64| {
+            std::time::UNIX_EPOCH
+                + std::time::Duration::from_millis(secs * 1000 + millis)
+        };
+        assert_eq!("1970-01-01T00:00:00.000Z", format_timestamp(at(0, 0)));
+        assert_eq!(
+            "2000-02-29T23:59:59.999Z",
+            format_timestamp(at(951_868_799, 999))
+        );
+        assert_eq!(
+            "2026-10-15T08:30:05.042Z",
+            format_timestamp(at(1_792_053_005, 42))
+        );
+    }
+}
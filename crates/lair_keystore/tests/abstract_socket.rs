@@ -0,0 +1,125 @@
+#![cfg(target_os = "linux")]
+
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::{LairClientApiSender, LairEntryType};
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_abstract_socket_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let url: lair_keystore_api::LairConnectionUrl =
+        format!("lair-unix-abstract://lair-test-{}", std::process::id())
+            .parse()?;
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+    std::env::set_var("LAIR_ALLOW_WEAK_PASSPHRASE", "1");
+    std::env::set_var("LAIR_EXTRA_LISTENERS", url.to_string());
+
+    lair_keystore::execute_lair().await?;
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    // the server advertises the abstract url beside its socket file
+    let urls = std::fs::read_to_string(config.get_connection_url_path())
+        .unwrap()
+        .lines()
+        .map(|url| url.parse())
+        .collect::<lair_keystore_api::LairResult<Vec<_>>>()?;
+    assert_eq!(vec![config.get_connection_url().clone(), url.clone()], urls);
+
+    // the name is taken while the server holds it
+    {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(format!(
+            "lair-test-{}",
+            std::process::id()
+        ))
+        .unwrap();
+        assert!(std::os::unix::net::UnixListener::bind_addr(&addr).is_err());
+    }
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_connection_url(url)
+        .build();
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, progress) =
+        lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+            evt_recv,
+            "passphrase".into(),
+        );
+    unlock.await?;
+    tokio::task::spawn(progress.for_each(|_| async {}));
+
+    let info = api_send.lair_get_server_info().await?;
+    assert_eq!("lair-keystore", &info.name);
+    assert_eq!(lair_keystore::LAIR_VER, &info.version);
+    assert_eq!(
+        lair_keystore_api::actor::LairCapabilities::ALL,
+        api_send.lair_get_capabilities().await?
+    );
+
+    let (cert_index, cert_sni, cert_digest) = api_send
+        .tls_cert_new_self_signed_from_entropy(
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await?;
+    assert_eq!(
+        (cert_sni, cert_digest),
+        api_send.tls_cert_get(cert_index).await?
+    );
+
+    let (sign_index, sign_pub_key) =
+        api_send.sign_ed25519_new_from_entropy().await?;
+    let data = std::sync::Arc::new(b"test-data".to_vec());
+    let signature = api_send
+        .sign_ed25519_sign_by_index(sign_index, data.clone())
+        .await?;
+    assert!(
+        lair_keystore_api::internal::sign_ed25519::sign_ed25519_verify(
+            sign_pub_key,
+            data.clone(),
+            signature
+        )
+        .await?
+    );
+
+    let (x25519_index, x25519_pub_key) =
+        api_send.x25519_new_from_entropy().await?;
+    let sealed =
+        lair_keystore_api::actor::crypto_box_seal(x25519_pub_key, data.clone())
+            .await?;
+    assert_eq!(
+        data,
+        api_send
+            .crypto_box_seal_open_by_index(x25519_index, sealed)
+            .await?,
+    );
+
+    api_send
+        .lair_set_entry_tag(sign_index, Some("abstract".to_string()))
+        .await?;
+    assert_eq!(
+        (sign_index, LairEntryType::SignEd25519),
+        api_send
+            .lair_get_entry_by_tag("abstract".to_string())
+            .await?
+    );
+    assert_eq!(
+        vec![
+            (cert_index, LairEntryType::TlsCert),
+            (sign_index, LairEntryType::SignEd25519),
+            (x25519_index, LairEntryType::X25519),
+        ],
+        lair_keystore_api::actor::lair_entry_indices_stream(api_send.clone())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<lair_keystore_api::LairResult<Vec<_>>>()?,
+    );
+
+    Ok(())
+}
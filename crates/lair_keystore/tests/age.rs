@@ -0,0 +1,123 @@
+use lair_keystore_api::actor::*;
+use lair_keystore_api::age_identity::LairAgeIdentity;
+use lair_keystore_api::{Config, LairError, LairResult};
+use std::io::{Read, Write};
+
+/// Encrypt `data` to the age `recipients` with the age crate,
+/// interoperable with the reference age implementation.
+fn age_encrypt(recipients: &[&str], data: &[u8], armor: bool) -> Vec<u8> {
+    let recipients = recipients
+        .iter()
+        .map(|r| r.parse::<age::x25519::Recipient>().unwrap())
+        .collect::<Vec<_>>();
+    let encryptor = age::Encryptor::with_recipients(
+        recipients.iter().map(|r| r as &dyn age::Recipient),
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    {
+        let format = match armor {
+            true => age::armor::Format::AsciiArmor,
+            false => age::armor::Format::Binary,
+        };
+        let armored =
+            age::armor::ArmoredWriter::wrap_output(&mut out, format).unwrap();
+        let mut writer = encryptor.wrap_output(armored).unwrap();
+        writer.write_all(data).unwrap();
+        writer
+            .finish()
+            .and_then(|armored| armored.finish())
+            .unwrap();
+    }
+    out
+}
+
+/// Decrypt an age file with `identity`, off the async executor.
+async fn age_decrypt(
+    identity: LairAgeIdentity,
+    file: Vec<u8>,
+) -> Result<Vec<u8>, age::DecryptError> {
+    tokio::task::spawn_blocking(move || {
+        let decryptor =
+            age::Decryptor::new(age::armor::ArmoredReader::new(&file[..]))?;
+        let mut reader = decryptor.decrypt(std::iter::once(&identity as _))?;
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+        Ok(out)
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_age_interop_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 1,
+            },
+        )
+        .build();
+
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file)
+        .await?;
+
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, _progress) =
+        lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+            evt_recv,
+            "passphrase".into(),
+        );
+    unlock.await?;
+
+    let (index, pub_key) = api_send.x25519_new_from_entropy().await?;
+    let identity = LairAgeIdentity::new(api_send.clone(), index).await?;
+    assert_eq!(identity.recipient(), age_recipient(&pub_key)?);
+    assert!(identity.recipient().starts_with("age1"));
+
+    // another recipient's own identity, and a lair one
+    let other = age::x25519::Identity::generate();
+    let other_recipient = other.to_public().to_string();
+    let (other_index, _) = api_send.x25519_new_from_entropy().await?;
+    let other_identity =
+        LairAgeIdentity::new(api_send.clone(), other_index).await?;
+
+    for armor in [false, true] {
+        let data = vec![0x42; 100 * 1024];
+        let file = age_encrypt(
+            &[other_recipient.as_str(), identity.recipient()],
+            &data,
+            armor,
+        );
+        assert_eq!(
+            data,
+            age_decrypt(identity.clone(), file.clone()).await.unwrap()
+        );
+        assert!(matches!(
+            age_decrypt(other_identity.clone(), file).await,
+            Err(age::DecryptError::NoMatchingKeys),
+        ));
+    }
+
+    // only x25519 keypairs are age identities
+    let (sign_index, _) = api_send.sign_ed25519_new_from_entropy().await?;
+    assert!(api_send
+        .age_decrypt_by_index(sign_index, Vec::new())
+        .await
+        .is_err());
+    assert!(matches!(
+        api_send.age_decrypt_by_index(index, Vec::new()).await,
+        Err(LairError::AuthenticationFailed),
+    ));
+
+    Ok(())
+}
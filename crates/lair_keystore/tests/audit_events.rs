@@ -0,0 +1,127 @@
+mod common;
+
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{Config, LairConnectionUrl, LairError, LairResult};
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_audit_events_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path().canonicalize().unwrap();
+    let url = |token: &str| {
+        LairConnectionUrl::new_unix_socket(root.join("socket"))
+            .unwrap()
+            .with_token(token)
+            .unwrap()
+    };
+    let app = LairCapabilities::from(
+        &[
+            LairCapability::ReadPublic,
+            LairCapability::Sign,
+            LairCapability::Create,
+        ][..],
+    );
+    let config = common::config_builder(&root)
+        .set_connection_url(url("admin-token"))
+        .add_connection_token("app-token", app)
+        .build();
+    common::spawn_server(&config).await?;
+    let by_app = |record: &LairAuditRecord| record.capabilities == app;
+
+    let connect = |token: &str| {
+        lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(
+                &Config::builder()
+                    .set_root_path(&root)
+                    .set_connection_url(url(token))
+                    .build(),
+            ),
+        )
+    };
+
+    // audit records are opt in, even with the audit capability
+    let (admin_send, mut admin_evt) = connect("admin-token").await?;
+    assert!(admin_send
+        .lair_get_capabilities()
+        .await?
+        .contains(LairCapability::Audit));
+    admin_send
+        .lair_set_event_filter(vec![
+            EventKind::RequestUnlockPassphrase,
+            EventKind::AuditEvent,
+        ])
+        .await?;
+
+    // another connection is watched
+    let (app_send, mut app_evt) = connect("app-token").await?;
+    let (authenticate, _) = common::next_audit(&mut admin_evt, |r| {
+        r.capabilities == LairCapabilities::NONE
+    })
+    .await;
+    assert_eq!("authenticate", authenticate.operation);
+    assert_eq!(None, authenticate.error);
+
+    let (keystore_index, pub_key) =
+        app_send.sign_ed25519_new_from_entropy().await?;
+    let (record, _) = common::next_audit(&mut admin_evt, by_app).await;
+    assert_eq!("sign_ed25519_new_from_entropy", record.operation);
+    assert_eq!(authenticate.connection_id, record.connection_id);
+    assert_ne!(0, record.request_id);
+    assert_eq!(None, record.error);
+
+    app_send
+        .sign_ed25519_sign_by_index(keystore_index, b"hello".to_vec().into())
+        .await?;
+    let (signed, _) = common::next_audit(&mut admin_evt, by_app).await;
+    assert_eq!("sign_ed25519_sign_by_index", signed.operation);
+    assert_eq!(Some(keystore_index.into()), signed.keystore_index);
+    assert_ne!(record.request_id, signed.request_id);
+
+    app_send
+        .sign_ed25519_sign_by_pub_key(pub_key.clone(), b"hello".to_vec().into())
+        .await?;
+    let (signed, _) = common::next_audit(&mut admin_evt, by_app).await;
+    assert_eq!(Some(pub_key.to_vec().into()), signed.pub_key);
+
+    // failures are recorded with their error
+    let missing: KeystoreIndex = (keystore_index.0 + 1000).into();
+    assert!(app_send
+        .sign_ed25519_sign_by_index(missing, b"hello".to_vec().into())
+        .await
+        .is_err());
+    let (failed, _) = common::next_audit(&mut admin_evt, by_app).await;
+    assert_eq!(Some(missing.into()), failed.keystore_index);
+    assert!(failed.error.is_some());
+
+    // as are requests refused for a missing capability
+    assert!(matches!(
+        app_send.sign_ed25519_export_seed(keystore_index).await,
+        Err(LairError::Forbidden(_)),
+    ));
+    let (mut refused, mut dropped) =
+        common::next_audit(&mut admin_evt, by_app).await;
+    // the client checks the server version first
+    if refused.operation == "lair_get_server_info" {
+        let (next, next_dropped) =
+            common::next_audit(&mut admin_evt, by_app).await;
+        refused = next;
+        dropped += next_dropped;
+    }
+    assert_eq!("sign_ed25519_export_seed", refused.operation);
+    assert!(refused.error.is_some());
+    assert_eq!(0, dropped);
+
+    // a connection without the capability gets none
+    app_send
+        .lair_set_event_filter(vec![EventKind::AuditEvent])
+        .await?;
+    app_send.lair_get_server_info().await?;
+    assert!(tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        app_evt.next(),
+    )
+    .await
+    .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use lair_keystore::bench::*;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{LairError, LairResult};
+
+// the only test in this binary, so no other store writes
+// share the process wide sync count
+#[tokio::test(threaded_scheduler)]
+async fn lair_bulk_entries_test() -> LairResult<()> {
+    let server = spawn_bench_server(
+        lair_keystore_api::internal::unlock::UnlockKdfLimits {
+            mem_limit_kib: 8,
+            ops_limit: 1,
+        },
+    )
+    .await?;
+    let api = &server.api;
+
+    let (a_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+    let (b_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+    let missing: KeystoreIndex = (b_idx.0 + 1000).into();
+
+    // tags are set in request order, failures are per item
+    let results = api
+        .lair_set_entry_tags(vec![
+            (a_idx, "one".to_string()),
+            (missing, "two".to_string()),
+            (b_idx, "".to_string()),
+            (b_idx, "one".to_string()),
+        ])
+        .await?;
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(LairError::EntryNotFound(_))));
+    assert!(results[2].is_err());
+    assert!(results[3].is_ok());
+    assert_eq!(b_idx, api.lair_get_entry_by_tag("one".into()).await?.0);
+
+    // a signing entry stays while a cert bound to it is not erased too
+    let (cert_idx, _, _) = api
+        .tls_cert_new_self_signed_from_sign_key(a_idx, Default::default())
+        .await?;
+    let results = api.lair_erase_entries(vec![a_idx, missing]).await?;
+    assert!(matches!(results[0], Err(LairError::Forbidden(_))));
+    assert!(matches!(results[1], Err(LairError::EntryNotFound(_))));
+    api.sign_ed25519_sign_by_index(a_idx, vec![1; 8].into())
+        .await?;
+
+    let erased = api.lair_get_store_stats().await?.erased_count;
+    let results = api
+        .lair_erase_entries(vec![a_idx, b_idx, cert_idx, missing])
+        .await?;
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert!(results[2].is_ok());
+    assert!(results[3].is_err());
+    assert_eq!(erased + 3, api.lair_get_store_stats().await?.erased_count);
+    assert!(api.lair_get_entry_by_tag("one".into()).await.is_err());
+    assert!(api
+        .sign_ed25519_sign_by_index(b_idx, vec![1; 8].into())
+        .await
+        .is_err());
+
+    let max = MAX_BULK_ENTRIES_LEN as usize;
+    assert!(api.lair_erase_entries(vec![b_idx; max + 1]).await.is_err());
+
+    // one sync per entry erased one by one, a constant few
+    // for the store file and the records beside it in bulk
+    let one_by_one = erase_syncs(api, false).await?;
+    let bulk = erase_syncs(api, true).await?;
+    assert!(one_by_one >= ERASE_BATCH_SIZE as u64, "{}", one_by_one);
+    assert!(bulk <= 4, "{}", bulk);
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::ipc::LairClientCache;
+use lair_keystore_api::LairResult;
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_client_cache_drops_entries_removed_elsewhere() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = common::config_builder(tmpdir.path()).build();
+    common::spawn_server(&config).await?;
+
+    let (other, _other_evt) = common::connect_unlocked(&config).await?;
+    let (trashed, _) = other.sign_ed25519_new_from_entropy().await?;
+    let (erased, _) = other.sign_ed25519_new_from_entropy().await?;
+    let (kept, kept_pub_key) = other.x25519_new_from_entropy().await?;
+
+    // cached for longer than the test runs
+    let (api, evt_recv) = common::connect_unlocked(&config).await?;
+    let (cache, _evt_recv) = LairClientCache::with_events(
+        api,
+        evt_recv,
+        std::time::Duration::from_secs(600),
+    )
+    .await?;
+    assert_eq!(3, cache.stats().entries);
+    cache.sign_ed25519_get(trashed).await?;
+    cache.sign_ed25519_get(erased).await?;
+
+    // removed by the other client, bypassing the cache
+    other.lair_trash_entry(trashed).await?;
+    for res in other.lair_erase_entries(vec![erased]).await? {
+        res?;
+    }
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while cache.stats().entries > 1 {
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the removals reach the cache");
+    assert!(cache.sign_ed25519_get(trashed).await.is_err());
+    assert!(cache.sign_ed25519_get(erased).await.is_err());
+    assert_eq!(kept_pub_key, cache.x25519_get(kept).await?);
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Fixtures shared by the lair_keystore integration tests, which serve
+//! real stores (`lair_keystore_api::test_keystore` is in-memory).
+
+// every test binary uses only some of them
+#![allow(dead_code)]
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{Config, ConfigBuilder, LairResult};
+use std::sync::Arc;
+
+/// The passphrase the fixtures set up and unlock stores with.
+pub const PASSPHRASE: &str = "passphrase";
+
+/// A config for a store in `root`, taking the weak `PASSPHRASE`,
+/// with the cheapest unlock key derivation.
+pub fn config_builder(root: &std::path::Path) -> ConfigBuilder {
+    Config::builder()
+        .set_root_path(root)
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 1,
+            },
+        )
+}
+
+/// Bind a server to the store of `config`.
+pub async fn spawn_server(config: &Arc<Config>) -> LairResult<()> {
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file).await
+}
+
+/// Connect to the server of `config`, unlocking it with `PASSPHRASE`.
+pub async fn connect_unlocked(
+    config: &Arc<Config>,
+) -> LairResult<(LairClientSender, LairClientEventReceiver)> {
+    let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(config),
+    )
+    .await?;
+    unlock(&mut evt_recv, PASSPHRASE).await;
+    Ok((api_send, evt_recv))
+}
+
+/// Bind a server to the store of `config`, and return a client that
+/// unlocked it with `PASSPHRASE`. Its later events get the default
+/// answers.
+pub async fn spawn_unlocked(
+    config: &Arc<Config>,
+) -> LairResult<LairClientSender> {
+    spawn_server(config).await?;
+    let (api_send, mut evt_recv) = connect_unlocked(config).await?;
+    tokio::task::spawn(async move {
+        while let Some(evt) = evt_recv.next().await {
+            evt.respond_default();
+        }
+    });
+    Ok(api_send)
+}
+
+/// Answer the next event, with `passphrase` if it asks for one, with
+/// `approve` if it asks for a sign approval, and return its kind.
+/// Unlocks must succeed.
+pub async fn answer_next(
+    evt_recv: &mut LairClientEventReceiver,
+    passphrase: &str,
+    approve: bool,
+) -> EventKind {
+    let evt = evt_recv.next().await.expect("an event");
+    let kind = EventKind::of(&evt);
+    match evt {
+        LairClientEvent::RequestUnlockPassphrase { respond, .. } => {
+            let passphrase = passphrase.into();
+            respond.respond(Ok(async move { Ok(passphrase) }.boxed().into()));
+        }
+        LairClientEvent::UnlockComplete { respond, error, .. } => {
+            assert_eq!(None, error);
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+        }
+        LairClientEvent::RequestSignApproval { respond, .. } => {
+            respond.respond(Ok(async move { Ok(approve) }.boxed().into()));
+        }
+        evt => evt.respond_default(),
+    }
+    kind
+}
+
+/// Answer events, with `passphrase` if asked for one, until the unlock
+/// completes.
+pub async fn unlock(evt_recv: &mut LairClientEventReceiver, passphrase: &str) {
+    while answer_next(evt_recv, passphrase, false).await
+        != EventKind::UnlockComplete
+    {}
+}
+
+/// The next audit record on `evt_recv` that is `wanted`, with the count
+/// of records dropped before it, answering every other event, with
+/// `PASSPHRASE` if asked for one.
+pub async fn next_audit(
+    evt_recv: &mut LairClientEventReceiver,
+    wanted: impl Fn(&LairAuditRecord) -> bool,
+) -> (LairAuditRecord, u64) {
+    loop {
+        let evt = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            evt_recv.next(),
+        )
+        .await
+        .expect("an audit record in time")
+        .expect("an event");
+        match evt {
+            LairClientEvent::AuditEvent {
+                respond,
+                record,
+                dropped,
+                ..
+            } => {
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                if wanted(&record) {
+                    return (record, dropped);
+                }
+            }
+            LairClientEvent::RequestUnlockPassphrase { respond, .. } => {
+                respond.respond(Ok(async move { Ok(PASSPHRASE.into()) }
+                    .boxed()
+                    .into()));
+            }
+            evt => evt.respond_default(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::ipc::{connect, ConnectOptions, ReconnectPolicy};
+use lair_keystore_api::{LairConnectionUrl, LairError, LairResult};
+use std::sync::Arc;
+
+const IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Bind an unlocked server at `url`, closing connections idle for
+/// `IDLE_TIMEOUT`.
+async fn spawn_server(
+    root: &std::path::Path,
+    url: LairConnectionUrl,
+) -> LairResult<()> {
+    let config = common::config_builder(root)
+        .set_connection_url(url)
+        .set_idle_connection_timeout(IDLE_TIMEOUT)
+        .build();
+    let api_send = common::spawn_unlocked(&config).await?;
+    assert!(!api_send.lair_get_server_info().await?.is_locked);
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_connect_options_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path().canonicalize().unwrap();
+    let url = LairConnectionUrl::new_unix_socket(root.join("socket"))?;
+    spawn_server(&root, url.clone().with_token("token")?).await?;
+
+    // the url has no token, the server serves nothing without it
+    let (no_token, _evt) = connect(ConnectOptions::new(url.clone())).await?;
+    assert!(matches!(
+        no_token.lair_get_server_info().await,
+        Err(LairError::Forbidden(_)),
+    ));
+
+    // auth_token presents it
+    let (api_send, _evt) =
+        connect(ConnectOptions::new(url.clone()).auth_token("token")).await?;
+    let id_pub_key = api_send.lair_get_server_info().await?.id_pub_key;
+    assert!(!id_pub_key.is_empty());
+    assert!(matches!(
+        connect(ConnectOptions::new(url.clone()).auth_token("")).await,
+        Err(LairError::InvalidConnectionUrl { .. }),
+    ));
+
+    // expected_server_identity checks the server proves it
+    let (api_send, _evt) = connect(
+        ConnectOptions::new(url.clone())
+            .auth_token("token")
+            .expected_server_identity(id_pub_key),
+    )
+    .await?;
+    api_send.lair_get_server_info().await?;
+    assert!(matches!(
+        connect(
+            ConnectOptions::new(url.clone())
+                .auth_token("token")
+                .expected_server_identity(vec![0x42; 32].into()),
+        )
+        .await,
+        Err(LairError::ServerIdentityMismatch(_)),
+    ));
+
+    // event_buffer sizes the receiver, events still arrive
+    let (api_send, mut evt_recv) = connect(
+        ConnectOptions::new(url.clone())
+            .auth_token("token")
+            .event_buffer(1),
+    )
+    .await?;
+    api_send
+        .lair_set_event_filter(vec![EventKind::RequestSignApproval])
+        .await?;
+    let mut options = SignKeyOptions::default();
+    options.requires_approval = true;
+    let (approval_index, _) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options)
+        .await?;
+    let sign = tokio::task::spawn(
+        api_send
+            .sign_ed25519_sign_by_index(approval_index, Arc::new(vec![1; 8])),
+    );
+    loop {
+        match evt_recv.next().await {
+            Some(LairClientEvent::RequestSignApproval {
+                respond,
+                keystore_index,
+                ..
+            }) => {
+                assert_eq!(approval_index, keystore_index);
+                respond.respond(Ok(async move { Ok(true) }.boxed().into()));
+                break;
+            }
+            // asked for the passphrase on connecting
+            Some(_) => (),
+            None => panic!("expected a sign approval request"),
+        }
+    }
+    sign.await.unwrap()?;
+
+    // timeout gives up on the request the approval holds up
+    let (api_send, _evt_recv) = connect(
+        ConnectOptions::new(url.clone())
+            .auth_token("token")
+            .timeout(std::time::Duration::from_millis(200)),
+    )
+    .await?;
+    api_send
+        .lair_set_event_filter(vec![EventKind::RequestSignApproval])
+        .await?;
+    assert!(matches!(
+        api_send
+            .sign_ed25519_sign_by_index(approval_index, Arc::new(vec![2; 8]))
+            .await,
+        Err(LairError::Timeout),
+    ));
+
+    // by default, a connection closed while idle stays closed
+    let (never, _never_evt) =
+        connect(ConnectOptions::new(url.clone()).auth_token("token")).await?;
+    let (reconnecting, _reconnecting_evt) = connect(
+        ConnectOptions::new(url)
+            .auth_token("token")
+            .reconnect(ReconnectPolicy::OnNextRequest),
+    )
+    .await?;
+    never.lair_get_server_info().await?;
+    reconnecting.lair_get_server_info().await?;
+    tokio::time::delay_for(IDLE_TIMEOUT * 3).await;
+    assert!(matches!(
+        never.lair_get_server_info().await,
+        Err(LairError::ConnectionIdle),
+    ));
+
+    // OnNextRequest replaces it
+    reconnecting.lair_get_server_info().await?;
+
+    drop(tmpdir);
+
+    Ok(())
+}
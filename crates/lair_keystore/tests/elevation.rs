@@ -0,0 +1,169 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::clock::ManualClock;
+use lair_keystore_api::{
+    Config, ElevationOptions, LairConnectionUrl, LairError, LairResult,
+};
+use std::time::Duration;
+
+/// The next audit record on `evt_recv` of a connection not granted
+/// every capability, answering every other event.
+async fn next_audit(evt_recv: &mut LairClientEventReceiver) -> LairAuditRecord {
+    common::next_audit(evt_recv, |record| {
+        // the client checks the server version once, skipped here
+        record.capabilities != LairCapabilities::ALL
+            && record.operation != "lair_get_server_info"
+    })
+    .await
+    .0
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_elevation_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path().canonicalize().unwrap();
+    let url = |token: &str| {
+        LairConnectionUrl::new_unix_socket(root.join("socket"))
+            .unwrap()
+            .with_token(token)
+            .unwrap()
+    };
+    let app = LairCapabilities::from(
+        &[
+            LairCapability::ReadPublic,
+            LairCapability::Sign,
+            LairCapability::Create,
+        ][..],
+    );
+    let export = LairCapabilities::NONE.with(LairCapability::ExportPrivate);
+    let clock = ManualClock::new(1_600_000_000);
+    let config = common::config_builder(&root)
+        .set_connection_url(url("admin-token"))
+        .add_connection_token("app-token", app)
+        .set_elevation(ElevationOptions {
+            capabilities: export,
+            max_duration: Duration::from_secs(60),
+        })
+        .set_clock(clock.handle())
+        .build();
+    common::spawn_server(&config).await?;
+
+    let connect = |token: &str| {
+        lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(
+                &Config::builder()
+                    .set_root_path(&root)
+                    .set_connection_url(url(token))
+                    .build(),
+            ),
+        )
+    };
+
+    let (admin_send, mut audit) = connect("admin-token").await?;
+    admin_send
+        .lair_set_event_filter(vec![
+            EventKind::RequestUnlockPassphrase,
+            EventKind::AuditEvent,
+        ])
+        .await?;
+
+    let (app_send, _app_evt) = connect("app-token").await?;
+    assert_eq!("authenticate", next_audit(&mut audit).await.operation);
+    let (keystore_index, _) = app_send.sign_ed25519_new_from_entropy().await?;
+    next_audit(&mut audit).await;
+    let export_seed = || app_send.sign_ed25519_export_seed(keystore_index);
+
+    // refused without elevation
+    assert!(matches!(export_seed().await, Err(LairError::Forbidden(_))));
+    assert!(next_audit(&mut audit).await.error.is_some());
+
+    // only as far as the policy allows
+    for (capabilities, secs) in &[
+        (LairCapabilities::NONE.with(LairCapability::Admin), 1),
+        (export, 61),
+    ] {
+        assert!(matches!(
+            app_send
+                .lair_request_elevation(
+                    *capabilities,
+                    Duration::from_secs(*secs)
+                )
+                .await,
+            Err(LairError::Forbidden(_)),
+        ));
+        let refused = next_audit(&mut audit).await;
+        assert_eq!("lair_request_elevation", refused.operation);
+        assert!(refused.error.is_some());
+    }
+
+    // granted, and used
+    let elevation = app_send
+        .lair_request_elevation(export, Duration::from_secs(30))
+        .await?;
+    assert_eq!(export, elevation.capabilities);
+    assert_eq!(Duration::from_secs(30), elevation.remaining);
+    let granted = next_audit(&mut audit).await;
+    assert_eq!("lair_request_elevation", granted.operation);
+    assert_eq!(app, granted.capabilities);
+    assert_eq!(None, granted.error);
+
+    assert_eq!(app.union(export), app_send.lair_get_capabilities().await?);
+    next_audit(&mut audit).await;
+    export_seed().await?;
+    let exported = next_audit(&mut audit).await;
+    assert_eq!("sign_ed25519_export_seed", exported.operation);
+    assert_eq!(app.union(export), exported.capabilities);
+    assert_eq!(None, exported.error);
+
+    // expires mid sequence
+    clock.advance(Duration::from_secs(20));
+    let elevation = app_send.lair_get_elevation().await?.unwrap();
+    assert_eq!(Duration::from_secs(10), elevation.remaining);
+    next_audit(&mut audit).await;
+    export_seed().await?;
+    next_audit(&mut audit).await;
+    clock.advance(Duration::from_secs(10));
+    assert!(matches!(export_seed().await, Err(LairError::Forbidden(_))));
+    let expired = next_audit(&mut audit).await;
+    assert_eq!("elevation_expired", expired.operation);
+    assert_eq!(granted.connection_id, expired.connection_id);
+    assert_eq!(app.union(export), expired.capabilities);
+    let refused = next_audit(&mut audit).await;
+    assert_eq!("sign_ed25519_export_seed", refused.operation);
+    assert_eq!(app, refused.capabilities);
+    assert!(refused.error.is_some());
+    assert_eq!(None, app_send.lair_get_elevation().await?);
+    next_audit(&mut audit).await;
+
+    // revoked before it expires
+    app_send
+        .lair_request_elevation(export, Duration::from_secs(60))
+        .await?;
+    assert_eq!(
+        "lair_request_elevation",
+        next_audit(&mut audit).await.operation
+    );
+    export_seed().await?;
+    next_audit(&mut audit).await;
+    app_send.lair_drop_elevation().await?;
+    let dropped = next_audit(&mut audit).await;
+    assert_eq!("lair_drop_elevation", dropped.operation);
+    assert_eq!(app.union(export), dropped.capabilities);
+    assert_eq!(None, app_send.lair_get_elevation().await?);
+    next_audit(&mut audit).await;
+    assert!(matches!(export_seed().await, Err(LairError::Forbidden(_))));
+    // a dropped elevation never expires
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(app, app_send.lair_get_capabilities().await?);
+    assert_eq!(
+        "sign_ed25519_export_seed",
+        next_audit(&mut audit).await.operation
+    );
+    assert_eq!(
+        "lair_get_capabilities",
+        next_audit(&mut audit).await.operation
+    );
+
+    Ok(())
+}
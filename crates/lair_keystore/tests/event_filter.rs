@@ -0,0 +1,98 @@
+mod common;
+
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::LairResult;
+
+/// Answer events until the unlock completes, returning their kinds.
+async fn answer_unlock(
+    evt_recv: &mut LairClientEventReceiver,
+    passphrase: &str,
+) -> Vec<EventKind> {
+    let mut kinds = Vec::new();
+    loop {
+        let kind = common::answer_next(evt_recv, passphrase, false).await;
+        kinds.push(kind);
+        if kind == EventKind::UnlockComplete {
+            return kinds;
+        }
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_event_filter_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = common::config_builder(tmpdir.path())
+        // enough rounds to report progress
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 1024,
+                ops_limit: 10,
+            },
+        )
+        .build();
+    lair_keystore::stores::create_store(&config, "alpha")?;
+    lair_keystore::stores::create_store(&config, "beta")?;
+
+    common::spawn_server(&config).await?;
+
+    // every event kind is sent by default
+    let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let kinds = answer_unlock(&mut evt_recv, common::PASSPHRASE).await;
+    assert_eq!(EventKind::RequestUnlockPassphrase, kinds[0]);
+    assert!(kinds.contains(&EventKind::UnlockProgress));
+
+    // filtered out progress is never sent
+    api_send
+        .lair_set_event_filter(vec![
+            EventKind::RequestUnlockPassphrase,
+            EventKind::UnlockComplete,
+        ])
+        .await?;
+    api_send.lair_select_store("alpha".to_string()).await?;
+    assert_eq!(
+        vec![
+            EventKind::RequestUnlockPassphrase,
+            EventKind::UnlockComplete
+        ],
+        answer_unlock(&mut evt_recv, "alpha-passphrase").await,
+    );
+    assert!(!api_send.lair_get_server_info().await?.is_locked);
+
+    // filters are per connection
+    let (beta_send, mut beta_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    answer_unlock(&mut beta_recv, common::PASSPHRASE).await;
+    beta_send
+        .lair_set_event_filter(vec![EventKind::UnlockComplete])
+        .await?;
+
+    // a filtered out passphrase request fails,
+    // the store stays locked and nothing follows
+    beta_send.lair_select_store("beta".to_string()).await?;
+    assert!(tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        beta_recv.next(),
+    )
+    .await
+    .is_err());
+    assert!(beta_send.lair_get_server_info().await?.is_locked);
+
+    // the first connection is unaffected
+    api_send.lair_select_store("beta".to_string()).await?;
+    assert_eq!(
+        vec![
+            EventKind::RequestUnlockPassphrase,
+            EventKind::UnlockComplete
+        ],
+        answer_unlock(&mut evt_recv, "beta-passphrase").await,
+    );
+    assert!(!beta_send.lair_get_server_info().await?.is_locked);
+
+    Ok(())
+}
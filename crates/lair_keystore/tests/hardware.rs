@@ -0,0 +1,104 @@
+use lair_keystore_api::actor::*;
+use lair_keystore_api::hardware::*;
+use lair_keystore_api::{Config, LairError, LairResult};
+use std::sync::Arc;
+
+/// A SoftHSM2 (or any PKCS#11) token, if one is set up for the tests,
+/// e.g. with `softhsm2-util --init-token --free --label lair-test`
+/// and the LAIR_TEST_PKCS11_MODULE, LAIR_TEST_PKCS11_TOKEN and
+/// LAIR_TEST_PKCS11_PIN env vars, otherwise a mock token.
+fn hardware_token() -> LairResult<HardwareTokenHandle> {
+    #[cfg(feature = "pkcs11")]
+    if let Some(module) = std::env::var_os("LAIR_TEST_PKCS11_MODULE") {
+        return Pkcs11Token::new_handle(
+            module.as_ref(),
+            &std::env::var("LAIR_TEST_PKCS11_TOKEN").unwrap(),
+            std::env::var("LAIR_TEST_PKCS11_PIN").unwrap(),
+        );
+    }
+    Ok(MockHardwareToken::new_handle())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_hardware_backed_keys_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 1,
+            },
+        )
+        .set_hardware_token(hardware_token()?)
+        .build();
+
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file)
+        .await?;
+
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, _progress) =
+        lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+            evt_recv,
+            "passphrase".into(),
+        );
+    unlock.await?;
+
+    let (index, pub_key) =
+        api_send.sign_ed25519_new(KeyBacking::Hardware).await?;
+    api_send
+        .lair_set_entry_tag(index, Some("hsm".to_string()))
+        .await?;
+
+    // to clients, an ed25519 keypair like any other
+    assert_eq!(
+        LairEntryType::SignEd25519,
+        api_send.lair_get_entry_type(index).await?
+    );
+    assert_eq!(pub_key, api_send.sign_ed25519_get(index).await?);
+    assert_eq!(
+        index,
+        api_send
+            .sign_ed25519_resolve_by_pub_key(pub_key.clone())
+            .await?
+    );
+
+    let message = Arc::new(b"hello".to_vec());
+    let signature = api_send
+        .sign_ed25519_sign_by_index(index, message.clone())
+        .await?;
+    assert!(pub_key.verify(message.clone(), signature).await?);
+    let signature = api_send
+        .sign_ed25519_sign_by_pub_key(pub_key.clone(), message.clone())
+        .await?;
+    assert!(pub_key.verify(message.clone(), signature).await?);
+    let (tag_pub_key, signature) = api_send
+        .sign_ed25519_sign_by_tag("hsm".to_string(), message.clone())
+        .await?;
+    assert_eq!(pub_key, tag_pub_key);
+    assert!(pub_key.verify(message, signature).await?);
+
+    // the key never leaves the token
+    let (_, recovery_pub_key) = api_send.x25519_new_from_entropy().await?;
+    assert!(matches!(
+        api_send.lair_wrap_entry(index, recovery_pub_key).await,
+        Err(LairError::ExportForbidden(_)),
+    ));
+    assert!(api_send
+        .sign_ed25519_ph_sign_by_index(index, [0; 64], Arc::new(Vec::new()))
+        .await
+        .is_err());
+
+    // software backing is a plain entry
+    let (index, pub_key) =
+        api_send.sign_ed25519_new(KeyBacking::Software).await?;
+    assert_eq!(pub_key, api_send.sign_ed25519_get(index).await?);
+
+    Ok(())
+}
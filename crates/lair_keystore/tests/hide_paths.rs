@@ -0,0 +1,32 @@
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::LairClientApiSender;
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_hide_paths_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+    std::env::set_var("LAIR_HIDE_PATHS", "1");
+
+    lair_keystore::execute_lair().await?;
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    tokio::task::spawn(async move { while evt_recv.next().await.is_some() {} });
+
+    // other requests are still served
+    let info = api_send.lair_get_server_info().await?;
+    assert_eq!("lair-keystore", &info.name);
+
+    match api_send.lair_get_server_paths().await {
+        Err(lair_keystore_api::LairError::Forbidden(_)) => (),
+        res => panic!("expected forbidden, got: {:?}", res),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::entry::ENTRY_SIZE;
+use lair_keystore_api::internal::unlock::SEALED_ENTRY_SIZE;
+use lair_keystore_api::internal::util;
+use lair_keystore_api::{Config, LairResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A store written before the index was encrypted, with the passphrase
+/// "passphrase", holding tagged, aliased, trashed, inactive and
+/// activated entries. `listing` is what `listing` returned for it.
+const FIXTURE: &str = "tests/fixtures/legacy_store";
+
+const INDEX_FILES: &[&str] = &["tags", "trash", "inactive"];
+
+const TAGS: &[&str] = &["signer", "signer-alias", "boxer", "seeded"];
+
+/// Every entry of the store served by `api`, then each of `TAGS`.
+async fn listing(api: &LairClientSender) -> LairResult<String> {
+    let (entries, _) = api.lair_list_entries_page(0.into(), 100).await?;
+    let mut out = String::new();
+    for entry in entries {
+        out.push_str(&format!("{:?}\n", entry));
+    }
+    for tag in TAGS {
+        out.push_str(&format!(
+            "{} {:?}\n",
+            tag,
+            api.lair_get_entry_by_tag(tag.to_string()).await?
+        ));
+    }
+    Ok(out)
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(Path::new(FIXTURE).join(name)).unwrap()
+}
+
+/// A private lair directory holding a copy of the fixture store.
+fn copy_fixture(tmpdir: &tempfile::TempDir) -> PathBuf {
+    let root = tmpdir.path().join("lair");
+    util::create_dir_all_private(&root).unwrap();
+    for name in &["store", "server_id", "tags", "trash", "inactive"] {
+        let path = root.join(name);
+        std::fs::write(&path, fixture(name)).unwrap();
+        util::set_private_permissions(&path).unwrap();
+    }
+    root
+}
+
+fn config(root: &Path, auto_migrate: bool) -> Arc<Config> {
+    Config::builder()
+        .set_root_path(root)
+        .set_auto_migrate(auto_migrate)
+        .set_allow_weak_passphrase(true)
+        .build()
+}
+
+/// Serve the store at `config`, returning a client that unlocked it.
+async fn serve_unlocked(config: Arc<Config>) -> LairResult<LairClientSender> {
+    common::spawn_unlocked(&config).await
+}
+
+/// Check the store served by `api` serves as the fixture did.
+async fn check_served(api: &LairClientSender) -> LairResult<()> {
+    assert_eq!(
+        String::from_utf8(fixture("listing")).unwrap(),
+        listing(api).await?
+    );
+
+    let (index, _) = api.lair_get_entry_by_tag("signer".to_string()).await?;
+    let pub_key = api.sign_ed25519_get(index).await?;
+    let message = Arc::new(b"hello".to_vec());
+    let signature = api
+        .sign_ed25519_sign_by_index(index, message.clone())
+        .await?;
+    assert!(
+        lair_keystore_api::internal::sign_ed25519::sign_ed25519_verify(
+            pub_key, message, signature
+        )
+        .await?
+    );
+    Ok(())
+}
+
+/// Check the store in `root` has an encrypted index, and that `backup`
+/// holds the fixture store.
+fn check_migrated(root: &Path, backup: &Path) {
+    let store = std::fs::read(root.join("store")).unwrap();
+    assert_eq!(3, store[0], "the unlock entry records the new layout");
+    // past the unlock entry, every entry is sealed
+    let legacy = fixture("store");
+    assert_eq!(
+        (legacy.len() - ENTRY_SIZE) / ENTRY_SIZE * SEALED_ENTRY_SIZE,
+        store.len() - ENTRY_SIZE,
+    );
+    for chunk in legacy[ENTRY_SIZE..].chunks(32) {
+        assert!(
+            !store.windows(32).any(|w| w == chunk),
+            "entries are left in plaintext"
+        );
+    }
+    for name in INDEX_FILES {
+        assert!(!root.join(name).exists(), "{} is left in plaintext", name);
+        let sealed =
+            std::fs::read(root.join(name).with_extension("sealed")).unwrap();
+        assert!(!sealed.windows(6).any(|w| w == b"signer"));
+    }
+    for name in &["store", "tags", "trash", "inactive"] {
+        assert_eq!(fixture(name), std::fs::read(backup.join(name)).unwrap());
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_migrate_encrypt_index_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = copy_fixture(&tmpdir);
+    let config = config(&root, false);
+    assert!(lair_keystore::migrate::has_legacy_index(&config)?);
+
+    // refused until migrated
+    let store_file = util::private_open_options()
+        .read(true)
+        .write(true)
+        .open(config.get_store_path())
+        .unwrap();
+    let err = lair_keystore::store::spawn_entry_store_actor(
+        config.clone(),
+        tokio::fs::File::from_std(store_file),
+    )
+    .await
+    .err()
+    .expect("a legacy store is refused");
+    assert!(
+        err.to_string()
+            .contains("lair-keystore migrate --encrypt-index"),
+        "{}",
+        err
+    );
+
+    // a wrong passphrase changes nothing
+    assert!(lair_keystore::migrate::encrypt_legacy_index(
+        config.clone(),
+        "wrong".into()
+    )
+    .await
+    .is_err());
+    for name in &["store", "tags", "trash", "inactive"] {
+        assert_eq!(fixture(name), std::fs::read(root.join(name)).unwrap());
+    }
+
+    assert!(
+        lair_keystore::migrate::encrypt_legacy_index(
+            config.clone(),
+            "passphrase".into()
+        )
+        .await?
+    );
+    let backup = lair_keystore::migrate::backup_path(&config);
+    assert_eq!(root.join("legacy-backup"), backup);
+    check_migrated(&root, &backup);
+    assert!(!lair_keystore::migrate::has_legacy_index(&config)?);
+    // migrating again is a no-op
+    assert!(
+        !lair_keystore::migrate::encrypt_legacy_index(
+            config.clone(),
+            "passphrase".into()
+        )
+        .await?
+    );
+
+    let api = serve_unlocked(config).await?;
+    check_served(&api).await?;
+
+    // writes after the migration are sealed too
+    let (index, _) = api.sign_ed25519_new_from_entropy().await?;
+    api.lair_set_entry_tag(index, Some("after".to_string()))
+        .await?;
+    assert!(!root.join("tags").exists());
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_auto_migrate_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = copy_fixture(&tmpdir);
+    let config = config(&root, true);
+
+    let api = serve_unlocked(config.clone()).await?;
+    check_migrated(&root, &lair_keystore::migrate::backup_path(&config));
+    check_served(&api).await?;
+
+    Ok(())
+}
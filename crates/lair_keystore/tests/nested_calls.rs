@@ -0,0 +1,131 @@
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{Config, LairResult};
+use std::sync::{Arc, Mutex};
+
+/// Fails rather than hanging on a deadlock.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Answer each event in its own task, calling back into lair first.
+/// The unlock passphrase is sent once the server info is read, and a
+/// sign approval is granted once the keypair left in `nested`, if any,
+/// has signed, itself asking for approval.
+fn spawn_answering(
+    api_send: LairClientSender,
+    mut evt_recv: LairClientEventReceiver,
+    nested: Arc<Mutex<Option<KeystoreIndex>>>,
+    unlocked: tokio::sync::mpsc::UnboundedSender<Option<String>>,
+) {
+    tokio::task::spawn(async move {
+        while let Some(evt) = evt_recv.next().await {
+            let api_send = api_send.clone();
+            let nested = nested.clone();
+            let unlocked = unlocked.clone();
+            tokio::task::spawn(async move {
+                match evt {
+                    LairClientEvent::RequestUnlockPassphrase {
+                        respond,
+                        ..
+                    } => {
+                        let res = api_send
+                            .lair_get_server_info()
+                            .await
+                            .map(|_| "passphrase".into());
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairClientEvent::RequestSignApproval {
+                        respond, ..
+                    } => {
+                        let nested = nested.lock().unwrap().take();
+                        let approved = match nested {
+                            Some(keystore_index) => api_send
+                                .sign_ed25519_sign_by_index(
+                                    keystore_index,
+                                    Arc::new(vec![2; 8]),
+                                )
+                                .await
+                                .is_ok(),
+                            None => true,
+                        };
+                        respond.respond(Ok(async move { Ok(approved) }
+                            .boxed()
+                            .into()));
+                    }
+                    LairClientEvent::UnlockComplete {
+                        respond, error, ..
+                    } => {
+                        let _ = unlocked.send(error);
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    evt => evt.respond_default(),
+                }
+            });
+        }
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_nested_calls_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 1024,
+                ops_limit: 1,
+            },
+        )
+        .build();
+
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file)
+        .await?;
+
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let nested = Arc::new(Mutex::new(None));
+    let (unlocked_send, mut unlocked) = tokio::sync::mpsc::unbounded_channel();
+    spawn_answering(api_send.clone(), evt_recv, nested.clone(), unlocked_send);
+
+    // the unlock handler reads the server info before answering
+    let error = tokio::time::timeout(TIMEOUT, unlocked.recv())
+        .await
+        .expect("unlocking with a nested call hung");
+    assert_eq!(Some(None), error);
+    assert!(!api_send.lair_get_server_info().await?.is_locked);
+
+    api_send
+        .lair_set_event_filter(vec![EventKind::RequestSignApproval])
+        .await?;
+    let mut options = SignKeyOptions::default();
+    options.requires_approval = true;
+    let (outer, outer_pub_key) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options.clone())
+        .await?;
+    let (inner, _) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options)
+        .await?;
+
+    // approving the outer signature signs with the inner keypair,
+    // whose own approval is asked for in the meantime
+    *nested.lock().unwrap() = Some(inner);
+    let signature = tokio::time::timeout(
+        TIMEOUT,
+        api_send.sign_ed25519_sign_by_index(outer, Arc::new(vec![1; 8])),
+    )
+    .await
+    .expect("approving with a nested approval hung")?;
+    assert!(nested.lock().unwrap().is_none());
+    assert!(
+        outer_pub_key
+            .verify(Arc::new(vec![1; 8]), signature)
+            .await?
+    );
+
+    Ok(())
+}
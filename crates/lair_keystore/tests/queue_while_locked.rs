@@ -0,0 +1,91 @@
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::{LairClientApiSender, LairClientEvent};
+use lair_keystore_api::LairError;
+
+/// Wait until the server holds `parked` requests, besides the one asking.
+async fn wait_parked(
+    api_send: &lair_keystore_api::actor::LairClientSender,
+    parked: u32,
+) -> lair_keystore_api::LairResult<()> {
+    loop {
+        let in_flight = api_send
+            .lair_list_connections()
+            .await?
+            .iter()
+            .map(|con| con.in_flight)
+            .sum::<u32>();
+        if in_flight > parked {
+            break;
+        }
+        tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+    }
+    // in flight once read, parked once it reaches the store
+    tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_queue_while_locked_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+    std::env::set_var("LAIR_ALLOW_WEAK_PASSPHRASE", "1");
+    std::env::set_var("LAIR_QUEUE_WHILE_LOCKED", "2:30");
+
+    lair_keystore::execute_lair().await?;
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+
+    // hold on to the passphrase, keeping the store locked
+    let respond = match evt_recv.next().await {
+        Some(LairClientEvent::RequestUnlockPassphrase { respond, .. }) => {
+            respond
+        }
+        _ => panic!("expected an unlock passphrase request"),
+    };
+    tokio::task::spawn(async move { while evt_recv.next().await.is_some() {} });
+
+    // reading public material is never parked
+    assert!(api_send.lair_get_server_info().await?.is_locked);
+    assert_eq!(0, api_send.lair_get_entry_count().await?);
+
+    let first =
+        tokio::task::spawn(api_send.clone().sign_ed25519_new_from_entropy());
+    wait_parked(&api_send, 1).await?;
+    let second =
+        tokio::task::spawn(api_send.clone().sign_ed25519_new_from_entropy());
+    wait_parked(&api_send, 2).await?;
+
+    // the queue is full
+    assert!(matches!(
+        api_send.sign_ed25519_new_from_entropy().await,
+        Err(LairError::KeystoreLocked),
+    ));
+
+    respond.respond(Ok(async move { Ok("passphrase".into()) }.boxed().into()));
+
+    // run in the order they were parked
+    let (first_index, _) = first.await.unwrap()?;
+    let (second_index, second_pub_key) = second.await.unwrap()?;
+    assert_eq!(1, first_index.0);
+    assert_eq!(2, second_index.0);
+
+    // and nothing waits once unlocked
+    assert!(!api_send.lair_get_server_info().await?.is_locked);
+    let signature = api_send
+        .sign_ed25519_sign_by_index(second_index, b"hello".to_vec().into())
+        .await?;
+    assert!(
+        second_pub_key
+            .verify(b"hello".to_vec().into(), signature)
+            .await?
+    );
+
+    Ok(())
+}
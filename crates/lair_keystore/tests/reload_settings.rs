@@ -0,0 +1,76 @@
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::LairError;
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_reload_settings_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let settings_dir = tempfile::tempdir().unwrap();
+    let settings_file = settings_dir.path().join("lair-settings");
+    std::fs::write(&settings_file, "LAIR_MAX_CONNECTIONS=3\n").unwrap();
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+    std::env::set_var("LAIR_SETTINGS_FILE", &settings_file);
+
+    lair_keystore::execute_lair().await?;
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    let connect = || async {
+        let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+        )
+        .await?;
+        tokio::task::spawn(
+            async move { while evt_recv.next().await.is_some() {} },
+        );
+        lair_keystore_api::LairResult::Ok(api_send)
+    };
+
+    let cli1 = connect().await?;
+    let cli2 = connect().await?;
+    cli1.lair_get_server_info().await?;
+    cli2.lair_get_server_info().await?;
+
+    // a lower limit refuses the next connection, settings that
+    // only apply on restart are skipped
+    std::fs::write(
+        &settings_file,
+        "LAIR_MAX_CONNECTIONS=2\nLAIR_SOCKET_MODE=660\n",
+    )
+    .unwrap();
+    cli1.lair_reload_settings().await?;
+    let cli3 = connect().await?;
+    assert!(matches!(
+        cli3.lair_get_server_info().await,
+        Err(LairError::TooManyConnections),
+    ));
+
+    // while those served already are unaffected
+    cli1.lair_get_server_info().await?;
+    assert_eq!(2, cli2.lair_list_connections().await?.len());
+
+    // an invalid setting refuses the whole file, the limit holds
+    std::fs::write(
+        &settings_file,
+        "LAIR_MAX_CONNECTIONS=\nLAIR_EVENT_BUFFER_SIZE=0\n",
+    )
+    .unwrap();
+    assert!(cli1.lair_reload_settings().await.is_err());
+    let cli4 = connect().await?;
+    assert!(matches!(
+        cli4.lair_get_server_info().await,
+        Err(LairError::TooManyConnections),
+    ));
+
+    // lifting the limit serves new connections again
+    std::fs::write(&settings_file, "LAIR_MAX_CONNECTIONS=\n").unwrap();
+    cli1.lair_reload_settings().await?;
+    let cli5 = connect().await?;
+    cli5.lair_get_server_info().await?;
+
+    drop(tmpdir);
+
+    Ok(())
+}
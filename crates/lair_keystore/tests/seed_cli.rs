@@ -0,0 +1,187 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::sign_ed25519;
+use lair_keystore_api::{LairError, LairResult};
+use std::io::Write;
+
+/// The all zero seed.
+const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon \
+    abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+    abandon abandon abandon abandon abandon abandon abandon abandon art";
+const HEX: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Run lair-keystore against the store in `tmpdir`, piping `stdin`,
+/// and return whether it succeeded, and its stdout.
+async fn run(
+    tmpdir: &tempfile::TempDir,
+    args: &[&str],
+    stdin: &str,
+) -> (bool, String) {
+    let mut cmd =
+        std::process::Command::new(env!("CARGO_BIN_EXE_lair-keystore"));
+    cmd.arg("-d")
+        .arg(tmpdir.path())
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let stdin = stdin.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut child = cmd.spawn().unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let out = child.wait_with_output().unwrap();
+        (out.status.success(), String::from_utf8(out.stdout).unwrap())
+    })
+    .await
+    .unwrap()
+}
+
+/// The pub key line of `import-seed` output.
+fn pub_key_line(out: &str) -> &str {
+    out.lines()
+        .find(|line| line.starts_with("pub key: "))
+        .expect("a pub key")
+}
+
+/// The keystore index of `import-seed` output.
+fn keystore_index_line(out: &str) -> &str {
+    out.lines()
+        .find_map(|line| line.strip_prefix("keystore index: "))
+        .expect("a keystore index")
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_seed_cli_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let api_send =
+        common::spawn_unlocked(&common::config_builder(tmpdir.path()).build())
+            .await?;
+
+    let expected = sign_ed25519::sign_ed25519_keypair_new_from_seed([0; 32])
+        .await?
+        .pub_key;
+    let expected = format!("pub key: {}", base64::encode(&*expected.0));
+
+    // a mnemonic on stdin, tagged
+    let (ok, out) = run(
+        &tmpdir,
+        &["import-seed", "--mnemonic", "--tag", "restored"],
+        &format!("{}\n", MNEMONIC),
+    )
+    .await;
+    assert!(ok, "{}", out);
+    assert_eq!(expected, pub_key_line(&out));
+    // imported seeds are used once activated
+    let index = keystore_index_line(&out).parse::<u32>().unwrap().into();
+    assert!(matches!(
+        api_send.lair_get_entry_by_tag("restored".into()).await,
+        Err(LairError::EntryNotActivated(_)),
+    ));
+    api_send.lair_activate_entry(index).await?;
+    let (index, _) = api_send.lair_get_entry_by_tag("restored".into()).await?;
+
+    // the same seed, as hex on stdin, or in a file, is already there
+    let (ok, out) = run(
+        &tmpdir,
+        &["import-seed", "--hex", "-"],
+        &format!("{}\n", HEX),
+    )
+    .await;
+    assert!(ok, "{}", out);
+    assert!(out.contains("already imported"));
+    assert_eq!(expected, pub_key_line(&out));
+    let path = tmpdir.path().join("seed.txt");
+    std::fs::write(&path, MNEMONIC).unwrap();
+    let (ok, out) = run(
+        &tmpdir,
+        &["import-seed", "--file", path.to_str().unwrap()],
+        "",
+    )
+    .await;
+    assert!(ok, "{}", out);
+    assert_eq!(expected, pub_key_line(&out));
+
+    // bad seeds, and no or two sources, are refused
+    let (ok, _) =
+        run(&tmpdir, &["import-seed", "--hex", "-"], "00112233\n").await;
+    assert!(!ok);
+    let (ok, _) =
+        run(&tmpdir, &["import-seed", "--mnemonic"], "abandon\n").await;
+    assert!(!ok);
+    let (ok, _) = run(&tmpdir, &["import-seed"], "").await;
+    assert!(!ok);
+    let (ok, _) =
+        run(&tmpdir, &["import-seed", "--mnemonic", "--hex", HEX], "").await;
+    assert!(!ok);
+
+    // export round trips, once confirmed
+    let index = index.0.to_string();
+    let confirm = format!("export seed {}\n", index);
+    let (ok, out) =
+        run(&tmpdir, &["export-seed", "--index", &index], &confirm).await;
+    assert!(ok, "{}", out);
+    assert_eq!(MNEMONIC, out.trim());
+    let (ok, out) = run(
+        &tmpdir,
+        &["export-seed", "--index", &index, "--format", "hex"],
+        &confirm,
+    )
+    .await;
+    assert!(ok, "{}", out);
+    assert_eq!(HEX, out.trim());
+    let (ok, out) =
+        run(&tmpdir, &["export-seed", "--index", &index], "yes\n").await;
+    assert!(!ok);
+    assert!(out.is_empty());
+
+    // keypairs created not exportable are refused
+    let mut options = SignKeyOptions::default();
+    options.exportable = false;
+    let (locked, _) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options)
+        .await?;
+    let locked = locked.0.to_string();
+    let (ok, out) = run(
+        &tmpdir,
+        &["export-seed", "--index", &locked],
+        &format!("export seed {}\n", locked),
+    )
+    .await;
+    assert!(!ok);
+    assert!(out.is_empty());
+
+    // as is every keypair, if the server forbids seed export
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = common::config_builder(tmpdir.path())
+        .set_forbid_seed_export(true)
+        .build();
+    let api_send = common::spawn_unlocked(&config).await?;
+    let (ok, out) = run(
+        &tmpdir,
+        &["import-seed", "--hex", "-"],
+        &format!("{}\n", HEX),
+    )
+    .await;
+    assert!(ok, "{}", out);
+    let index = keystore_index_line(&out);
+    api_send
+        .lair_activate_entry(index.parse::<u32>().unwrap().into())
+        .await?;
+    let (ok, out) = run(
+        &tmpdir,
+        &["export-seed", "--index", index],
+        &format!("export seed {}\n", index),
+    )
+    .await;
+    assert!(!ok);
+    assert!(out.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{LairError, LairResult};
+use std::sync::Arc;
+
+/// Sign with the keypair at `keystore_index`, answering events
+/// until the approval request is answered with `approve`.
+async fn sign_answering(
+    api_send: &LairClientSender,
+    evt_recv: &mut LairClientEventReceiver,
+    keystore_index: KeystoreIndex,
+    approve: bool,
+) -> LairResult<SignEd25519Signature> {
+    let sign = tokio::task::spawn(
+        api_send
+            .sign_ed25519_sign_by_index(keystore_index, Arc::new(vec![1; 8])),
+    );
+    while common::answer_next(evt_recv, common::PASSPHRASE, approve).await
+        != EventKind::RequestSignApproval
+    {}
+    sign.await.unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_sign_key_options_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = common::config_builder(tmpdir.path()).build();
+    common::spawn_server(&config).await?;
+    let (api_send, mut evt_recv) = common::connect_unlocked(&config).await?;
+    // sign approval requests are only sent once subscribed to
+    api_send
+        .lair_set_event_filter(vec![EventKind::RequestSignApproval])
+        .await?;
+
+    // keypairs created without options have default options
+    let (plain_index, _) = api_send.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(
+        SignKeyOptions::default(),
+        api_send.sign_ed25519_get_options(plain_index).await?,
+    );
+
+    // every option is kept
+    let mut options = SignKeyOptions::default();
+    options.tag = Some("locked-down".to_string());
+    options.exportable = false;
+    options.expires_at = Some(u64::MAX);
+    let (index, pub_key) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options.clone())
+        .await?;
+    assert_eq!(options, api_send.sign_ed25519_get_options(index).await?);
+    assert_eq!(
+        index,
+        api_send
+            .lair_get_entry_by_tag("locked-down".to_string())
+            .await?
+            .0
+    );
+    api_send
+        .sign_ed25519_sign_by_pub_key(pub_key, Arc::new(vec![1; 8]))
+        .await?;
+
+    // a taken tag refuses the new keypair
+    assert!(matches!(
+        api_send
+            .sign_ed25519_new_from_entropy_with_options(options)
+            .await,
+        Err(LairError::TagInUse(_)),
+    ));
+
+    // not exportable keypairs cannot be wrapped, nor bound to a tls cert
+    let (_, recipient) = api_send.x25519_new_from_entropy().await?;
+    assert!(matches!(
+        api_send.lair_wrap_entry(index, recipient.clone()).await,
+        Err(LairError::ExportForbidden(_)),
+    ));
+    assert!(matches!(
+        api_send
+            .tls_cert_new_self_signed_from_sign_key(
+                index,
+                TlsCertOptions::default(),
+            )
+            .await,
+        Err(LairError::ExportForbidden(_)),
+    ));
+    api_send.lair_wrap_entry(plain_index, recipient).await?;
+
+    // expired keypairs no longer sign
+    let mut expired = SignKeyOptions::default();
+    expired.expires_at = Some(1);
+    let (expired_index, _) = api_send
+        .sign_ed25519_new_from_entropy_with_options(expired)
+        .await?;
+    assert!(matches!(
+        api_send
+            .sign_ed25519_sign_by_index(expired_index, Arc::new(vec![1; 8]))
+            .await,
+        Err(LairError::KeyExpired(_)),
+    ));
+
+    // seed imports are deterministic, re-imports keep the first options
+    let mut approval = SignKeyOptions::default();
+    approval.requires_approval = true;
+    let (seed_index, seed_pub_key, already_existed) = api_send
+        .sign_ed25519_new_from_seed_with_options([0x42; 32], approval.clone())
+        .await?;
+    assert!(!already_existed);
+    assert_eq!(
+        (seed_index, seed_pub_key, true),
+        api_send
+            .sign_ed25519_new_from_seed_with_options(
+                [0x42; 32],
+                SignKeyOptions::default(),
+            )
+            .await?,
+    );
+    api_send.lair_activate_entry(seed_index).await?;
+    assert_eq!(
+        approval,
+        api_send.sign_ed25519_get_options(seed_index).await?
+    );
+
+    // approval is asked for every signature
+    sign_answering(&api_send, &mut evt_recv, seed_index, true).await?;
+    assert!(matches!(
+        sign_answering(&api_send, &mut evt_recv, seed_index, false).await,
+        Err(LairError::Forbidden(_)),
+    ));
+
+    // unsubscribed connections are never approved
+    api_send.lair_set_event_filter(Vec::new()).await?;
+    assert!(matches!(
+        api_send
+            .sign_ed25519_sign_by_index(seed_index, Arc::new(vec![1; 8]))
+            .await,
+        Err(LairError::Forbidden(_)),
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use ghost_actor::GhostControlSender;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::debug_stats::{debug_stats, DebugStats};
+use lair_keystore_api::{Config, LairResult};
+
+const CONNECTIONS: usize = 10_000;
+
+/// Wait for the connections closed so far to be cleaned up, which
+/// happens in their tasks, after the client is gone.
+async fn settle(baseline: DebugStats) -> DebugStats {
+    let mut stats = debug_stats();
+    for _ in 0..500 {
+        if stats == baseline {
+            break;
+        }
+        tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        stats = debug_stats();
+    }
+    stats
+}
+
+/// The only test in this binary, the debug stats count the whole process.
+#[tokio::test(threaded_scheduler)]
+async fn lair_connection_state_soak_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 1024,
+                ops_limit: 1,
+            },
+        )
+        .build();
+
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file)
+        .await?;
+
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, _) = lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+        evt_recv,
+        "passphrase".into(),
+    );
+    unlock.await?;
+    let (_, pub_key) = api_send.sign_ed25519_new_from_entropy().await?;
+
+    // the unlock events of the first connection are answered by now
+    tokio::time::delay_for(std::time::Duration::from_millis(200)).await;
+    let baseline = debug_stats();
+    assert_eq!(1, baseline.server_connections);
+
+    for i in 0..CONNECTIONS {
+        let (con, _evt_recv) = lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+        )
+        .await?;
+        con.sign_ed25519_sign_by_pub_key(
+            pub_key.clone(),
+            vec![i as u8; 32].into(),
+        )
+        .await?;
+        let (handle, _) = con.ephemeral_sign_ed25519_new().await?;
+        if i % 2 == 0 {
+            con.ephemeral_sign_ed25519_sign(handle, vec![0; 32].into())
+                .await?;
+            con.ghost_actor_shutdown().await?;
+        } else {
+            // an abnormal close, with a request still being handled
+            let sign =
+                con.ephemeral_sign_ed25519_sign(handle, vec![0; 32].into());
+            let sign = tokio::task::spawn(sign);
+            con.ghost_actor_shutdown_immediate().await?;
+            let _ = sign.await;
+        }
+
+        if i % 1_000 == 999 {
+            let stats = settle(baseline).await;
+            assert_eq!(baseline, stats, "after {} connections", i + 1);
+        }
+    }
+
+    // the first connection is still served
+    api_send.lair_get_server_info().await?;
+    Ok(())
+}
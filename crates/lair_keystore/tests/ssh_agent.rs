@@ -0,0 +1,197 @@
+#![cfg(not(windows))]
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::LairClientApiSender;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_AGENTC_ADD_IDENTITY: u8 = 17;
+const SSH_AGENTC_REMOVE_IDENTITY: u8 = 18;
+
+fn write_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn read_u32(data: &mut &[u8]) -> u32 {
+    let out = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    *data = &data[4..];
+    out
+}
+
+fn read_string(data: &mut &[u8]) -> Vec<u8> {
+    let len = read_u32(data) as usize;
+    let out = data[..len].to_vec();
+    *data = &data[len..];
+    out
+}
+
+/// minimal hand-rolled ssh-agent client request
+async fn agent_request(
+    con: &mut tokio::net::UnixStream,
+    msg: &[u8],
+) -> Vec<u8> {
+    con.write_u32(msg.len() as u32).await.unwrap();
+    con.write_all(msg).await.unwrap();
+    let len = con.read_u32().await.unwrap();
+    let mut res = vec![0; len as usize];
+    con.read_exact(&mut res).await.unwrap();
+    res
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_ssh_agent_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let mut agent_path = tmpdir.path().to_owned();
+    agent_path.push("ssh-agent-socket");
+
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+    std::env::set_var("LAIR_ALLOW_WEAK_PASSPHRASE", "1");
+    std::env::set_var("LAIR_SSH_AGENT_SOCKET", &agent_path);
+
+    lair_keystore::execute_lair().await?;
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    let (api_send, mut evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+
+    tokio::task::spawn(async move {
+        while let Some(msg) = evt_recv.next().await {
+            if let lair_keystore_api::actor::LairClientEvent::RequestUnlockPassphrase {
+                respond, ..
+            } = msg
+            {
+                respond.respond(Ok(async move { Ok("passphrase".into()) }
+                    .boxed()
+                    .into()));
+            }
+        }
+    });
+
+    // the agent should only list ed25519 keys, not tls certs
+    api_send
+        .tls_cert_new_self_signed_from_entropy(
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await?;
+    let (sign_index, sign_pub_key) =
+        api_send.sign_ed25519_new_from_entropy().await?;
+    // nor imported keys not yet activated
+    let (_, imported_pub_key, _) = api_send
+        .sign_ed25519_new_from_seed_with_options([0x42; 32], Default::default())
+        .await?;
+
+    let mut con = tokio::net::UnixStream::connect(&agent_path).await.unwrap();
+
+    // -- list identities -- //
+
+    let res = agent_request(&mut con, &[SSH_AGENTC_REQUEST_IDENTITIES]).await;
+    assert_eq!(SSH_AGENT_IDENTITIES_ANSWER, res[0]);
+    let mut res = &res[1..];
+    assert_eq!(1, read_u32(&mut res));
+    let key_blob = read_string(&mut res);
+    let comment = read_string(&mut res);
+    assert!(res.is_empty());
+
+    let mut expect_blob = Vec::new();
+    write_string(&mut expect_blob, b"ssh-ed25519");
+    write_string(&mut expect_blob, &sign_pub_key.0);
+    assert_eq!(expect_blob, key_blob);
+    assert_eq!(
+        format!("lair-keystore:{}", sign_index.0).as_bytes(),
+        &comment[..],
+    );
+
+    // served over a lair connection of its own
+    assert_eq!(2, api_send.lair_list_connections().await?.len());
+
+    // -- sign -- //
+
+    let data = b"test-data";
+    let mut req = vec![SSH_AGENTC_SIGN_REQUEST];
+    write_string(&mut req, &key_blob);
+    write_string(&mut req, data);
+    req.extend_from_slice(&0_u32.to_be_bytes());
+
+    let res = agent_request(&mut con, &req).await;
+    assert_eq!(SSH_AGENT_SIGN_RESPONSE, res[0]);
+    let mut res = &res[1..];
+    let sig_blob = read_string(&mut res);
+    assert!(res.is_empty());
+
+    let mut sig_blob = &sig_blob[..];
+    assert_eq!(b"ssh-ed25519", &read_string(&mut sig_blob)[..]);
+    let sig = read_string(&mut sig_blob);
+    assert!(sig_blob.is_empty());
+    assert_eq!(64, sig.len());
+
+    ring::signature::UnparsedPublicKey::new(
+        &ring::signature::ED25519,
+        &sign_pub_key.0[..],
+    )
+    .verify(data, &sig)
+    .expect("valid ssh signature");
+
+    // -- unknown key -- //
+
+    let mut bad_blob = Vec::new();
+    write_string(&mut bad_blob, b"ssh-ed25519");
+    write_string(&mut bad_blob, &[0; 32]);
+    let mut req = vec![SSH_AGENTC_SIGN_REQUEST];
+    write_string(&mut req, &bad_blob);
+    write_string(&mut req, data);
+    req.extend_from_slice(&0_u32.to_be_bytes());
+    let res = agent_request(&mut con, &req).await;
+    assert_eq!(&[SSH_AGENT_FAILURE], &res[..]);
+
+    // -- keys not yet activated -- //
+
+    let mut inactive_blob = Vec::new();
+    write_string(&mut inactive_blob, b"ssh-ed25519");
+    write_string(&mut inactive_blob, &imported_pub_key.0);
+    let mut req = vec![SSH_AGENTC_SIGN_REQUEST];
+    write_string(&mut req, &inactive_blob);
+    write_string(&mut req, data);
+    req.extend_from_slice(&0_u32.to_be_bytes());
+    let res = agent_request(&mut con, &req).await;
+    assert_eq!(&[SSH_AGENT_FAILURE], &res[..]);
+
+    // -- add / remove are rejected -- //
+
+    let res = agent_request(&mut con, &[SSH_AGENTC_ADD_IDENTITY]).await;
+    assert_eq!(&[SSH_AGENT_FAILURE], &res[..]);
+
+    let mut req = vec![SSH_AGENTC_REMOVE_IDENTITY];
+    write_string(&mut req, &key_blob);
+    let res = agent_request(&mut con, &req).await;
+    assert_eq!(&[SSH_AGENT_FAILURE], &res[..]);
+
+    // the keypair is still listed
+    let res = agent_request(&mut con, &[SSH_AGENTC_REQUEST_IDENTITIES]).await;
+    let mut res = &res[1..];
+    assert_eq!(1, read_u32(&mut res));
+
+    // -- tagged keys are named by their tag -- //
+
+    api_send
+        .lair_set_entry_tag(sign_index, Some("deploy-key".to_string()))
+        .await?;
+    let res = agent_request(&mut con, &[SSH_AGENTC_REQUEST_IDENTITIES]).await;
+    let mut res = &res[1..];
+    assert_eq!(1, read_u32(&mut res));
+    assert_eq!(expect_blob, read_string(&mut res));
+    assert_eq!(b"lair-keystore:deploy-key", &read_string(&mut res)[..]);
+
+    drop(tmpdir);
+
+    Ok(())
+}
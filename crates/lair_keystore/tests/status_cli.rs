@@ -0,0 +1,58 @@
+mod common;
+
+use lair_keystore_api::actor::*;
+use lair_keystore_api::LairResult;
+
+/// Run `lair-keystore status --json` against the store in `tmpdir`.
+async fn status_json(tmpdir: &tempfile::TempDir) -> serde_json::Value {
+    let mut cmd =
+        std::process::Command::new(env!("CARGO_BIN_EXE_lair-keystore"));
+    cmd.arg("-d")
+        .arg(tmpdir.path())
+        .args(["status", "--json"])
+        .stdin(std::process::Stdio::null());
+    let out = tokio::task::spawn_blocking(move || cmd.output().unwrap())
+        .await
+        .unwrap();
+    assert!(out.status.success());
+    serde_json::from_slice(&out.stdout).unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_status_cli_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let api_send =
+        common::spawn_unlocked(&common::config_builder(tmpdir.path()).build())
+            .await?;
+
+    let (sign_idx, _) = api_send.sign_ed25519_new_from_entropy().await?;
+    api_send.sign_ed25519_new_from_entropy().await?;
+    api_send.x25519_new_from_entropy().await?;
+    api_send.lair_trash_entry(sign_idx).await?;
+
+    let stats = api_send.lair_get_store_stats().await?;
+    let status = status_json(&tmpdir).await;
+    assert_eq!(
+        serde_json::json!({
+            "file_size": stats.file_size,
+            "entry_counts": {
+                "SignEd25519": 1,
+                "X25519": 1,
+            },
+            "erased_count": 0,
+            "quarantined_count": 0,
+            "trashed_count": 1,
+        }),
+        status["stats"],
+    );
+    assert!(stats.file_size > 0);
+
+    api_send
+        .lair_purge_trashed(std::time::Duration::from_secs(0))
+        .await?;
+    let status = status_json(&tmpdir).await;
+    assert_eq!(1, status["stats"]["erased_count"]);
+    assert_eq!(0, status["stats"]["trashed_count"]);
+
+    Ok(())
+}
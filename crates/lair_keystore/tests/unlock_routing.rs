@@ -0,0 +1,115 @@
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{Config, LairConnectionUrl, LairError, LairResult};
+
+/// No unlock passphrase request arrives on `evt_recv` for a while.
+async fn assert_not_asked(evt_recv: &mut LairClientEventReceiver) {
+    let evt = tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        evt_recv.next(),
+    )
+    .await;
+    assert!(
+        !matches!(
+            evt,
+            Ok(Some(LairClientEvent::RequestUnlockPassphrase { .. }))
+        ),
+        "asked a connection without can_unlock for the passphrase",
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_unlock_routing_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path().canonicalize().unwrap();
+    let url = |token: &str| {
+        LairConnectionUrl::new_unix_socket(root.join("socket"))
+            .unwrap()
+            .with_token(token)
+            .unwrap()
+    };
+    let no_unlock = LairCapabilities::from(
+        &[
+            LairCapability::ReadPublic,
+            LairCapability::Sign,
+            LairCapability::Create,
+        ][..],
+    );
+    let config = Config::builder()
+        .set_root_path(&root)
+        .set_connection_url(url("admin-token"))
+        .add_connection_token("app-token", no_unlock)
+        .set_allow_weak_passphrase(true)
+        // refuse the store to the other connection while locked
+        .set_deep_lock(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 1024,
+                ops_limit: 1,
+            },
+        )
+        .build();
+
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config, store_file).await?;
+
+    let connect = |token: &str| {
+        lair_keystore_api::ipc::connect(
+            lair_keystore_api::ipc::ConnectOptions::from_config(
+                &Config::builder()
+                    .set_root_path(&root)
+                    .set_connection_url(url(token))
+                    .build(),
+            ),
+        )
+    };
+
+    // a connection that may not unlock is never asked,
+    // and cannot use the store while it is locked
+    let (app_send, mut app_evt) = connect("app-token").await?;
+    assert_eq!(no_unlock, app_send.lair_get_capabilities().await?);
+    assert_not_asked(&mut app_evt).await;
+    assert!(app_send.lair_get_server_info().await?.is_locked);
+    assert!(matches!(
+        app_send.sign_ed25519_new_from_entropy().await,
+        Err(LairError::KeystoreLocked),
+    ));
+
+    // the store waits for one that may
+    let (admin_send, mut admin_evt) = connect("admin-token").await?;
+    match admin_evt.next().await {
+        Some(LairClientEvent::RequestUnlockPassphrase {
+            respond,
+            store_name,
+            ..
+        }) => {
+            assert_eq!("default", store_name);
+            respond.respond(Ok(async move { Ok("passphrase".into()) }
+                .boxed()
+                .into()));
+        }
+        _ => panic!("expected an unlock passphrase request"),
+    }
+    loop {
+        match admin_evt.next().await {
+            Some(LairClientEvent::UnlockComplete { error, .. }) => {
+                assert_eq!(None, error);
+                break;
+            }
+            Some(_) => (),
+            None => panic!("expected the unlock to complete"),
+        }
+    }
+    assert!(!admin_send.lair_get_server_info().await?.is_locked);
+
+    // once unlocked, it serves the other connection too
+    let (_, pub_key) = app_send.sign_ed25519_new_from_entropy().await?;
+    let signature = app_send
+        .sign_ed25519_sign_by_pub_key(pub_key.clone(), b"hello".to_vec().into())
+        .await?;
+    assert!(pub_key.verify(b"hello".to_vec().into(), signature).await?);
+    assert_not_asked(&mut app_evt).await;
+
+    Ok(())
+}
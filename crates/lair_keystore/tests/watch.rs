@@ -0,0 +1,85 @@
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::*;
+use lair_keystore_api::{Config, LairResult};
+
+/// The next watch line reporting `event`, skipping any others.
+async fn next_event(
+    lines: &mut futures::channel::mpsc::UnboundedReceiver<String>,
+    event: &str,
+) -> serde_json::Value {
+    tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            let line = lines.next().await.expect("a watch line");
+            let line: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert!(line["time"].as_str().unwrap().ends_with('Z'));
+            if line["event"] == event {
+                return line;
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("no {} event", event))
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lair_watch_test() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_allow_weak_passphrase(true)
+        .set_unlock_kdf_limits(
+            lair_keystore_api::internal::unlock::UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 1,
+            },
+        )
+        .build();
+
+    let (line_send, mut lines) = futures::channel::mpsc::unbounded();
+    tokio::task::spawn(lair_keystore::watch::watch(
+        config.clone(),
+        true,
+        None,
+        move |line| {
+            let _ = line_send.unbounded_send(line);
+        },
+    ));
+
+    // the watcher waits for the server to come up
+    next_event(&mut lines, "waiting").await;
+    let lair_keystore::internal::pid_check::PidCheckResult { store_file } =
+        lair_keystore::internal::pid_check::pid_check(&config)?;
+    lair_keystore::ipc::spawn_bind_server_ipc(config.clone(), store_file)
+        .await?;
+    next_event(&mut lines, "connected").await;
+
+    // and declines passphrase requests, without --approve-prompts
+    let line = next_event(&mut lines, "request_unlock_passphrase").await;
+    assert_eq!(DEFAULT_STORE_NAME, line["store_name"]);
+    assert_eq!(false, line["prompted"]);
+
+    let (api_send, evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(&config),
+    )
+    .await?;
+    let (unlock, _progress) =
+        lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+            evt_recv,
+            "passphrase".into(),
+        );
+    unlock.await?;
+
+    // entries created by other connections are reported
+    let (index, _) = api_send.sign_ed25519_new_from_entropy().await?;
+    let line = next_event(&mut lines, "entry_created").await;
+    assert_eq!(index.0, line["keystore_index"]);
+    assert_eq!(0, line["store_number"]);
+    assert_eq!("SignEd25519", line["entry_type"]);
+
+    let (index, _) = api_send.x25519_new_from_entropy().await?;
+    let line = next_event(&mut lines, "entry_created").await;
+    assert_eq!(index.0, line["keystore_index"]);
+    assert_eq!("X25519", line["entry_type"]);
+
+    Ok(())
+}
@@ -3,40 +3,266 @@
 use crate::*;
 use derive_more::*;
 
+#[cfg(feature = "full")]
 ghost_actor::ghost_chan! {
     /// "Event" types emitted by Lair Client Actor Api.
     pub chan LairClientEvent<LairError> {
-        /// The keystore is currently locked - the user
-        /// must supply a passphrase in order to unlock.
-        fn request_unlock_passphrase() -> String;
+        /// The keystore `store_name` is currently locked - the user
+        /// must supply its passphrase in order to unlock.
+        /// The default store is named `DEFAULT_STORE_NAME`.
+        fn request_unlock_passphrase(
+            store_name: String,
+        ) -> crate::passphrase::Passphrase;
+
+        /// Percent complete of the unlock key derivation,
+        /// sent to the client that supplied the passphrase.
+        fn unlock_progress(percent: u8) -> ();
+
+        /// The unlock started with this client's passphrase finished.
+        /// `error` is None if the keystore is now unlocked.
+        /// No progress events follow this.
+        fn unlock_complete(error: Option<String>) -> ();
+
+        /// The server dropped `count` events, that did not fit in this
+        /// connection's event buffer while the client was not reading
+        /// them. See `SlowConsumerPolicy::DropOldest`.
+        fn events_dropped(count: u64) -> ();
+
+        /// An entry was created in one of the server's stores, by any
+        /// connection. Only sent once subscribed to with
+        /// `lair_set_event_filter`, see `EventKind::EntryCreated`.
+        fn entry_created(
+            keystore_index: KeystoreIndex,
+            entry_type: LairEntryType,
+        ) -> ();
+
+        /// This client asked to sign with the ed25519 keypair at
+        /// `keystore_index`, created with `SignKeyOptions::requires_approval`.
+        /// Answer `true` to let it sign, anything else refuses with
+        /// `LairError::Forbidden`. Only sent once subscribed to with
+        /// `lair_set_event_filter`, see `EventKind::RequestSignApproval`.
+        fn request_sign_approval(
+            keystore_index: KeystoreIndex,
+            pub_key: SignEd25519PubKey,
+        ) -> bool;
+
+        /// The server's wall clock read `wall_secs`, more than
+        /// `clock::CLOCK_SKEW_THRESHOLD` behind the `expected_secs` it
+        /// last saw plus the time since, see `ClockSkewPolicy`.
+        /// Sent once per skew, only once subscribed to with
+        /// `lair_set_event_filter`, see `EventKind::ClockSkewDetected`.
+        fn clock_skew_detected(wall_secs: u64, expected_secs: u64) -> ();
+
+        /// The imported entry at `keystore_index` was activated with
+        /// `lair_activate_entry`, by any connection. Only sent once
+        /// subscribed to with `lair_set_event_filter`,
+        /// see `EventKind::EntryActivated`.
+        fn entry_activated(keystore_index: KeystoreIndex) -> ();
+
+        /// The server handled a request, from any connection, as
+        /// described by `record`. `dropped` audit records were not
+        /// delivered to this connection since the last one: the feed is
+        /// best effort. Only sent to connections granted
+        /// `LairCapability::Audit`, once subscribed to with
+        /// `lair_set_event_filter`, see `EventKind::AuditEvent`.
+        fn audit_event(record: LairAuditRecord, dropped: u64) -> ();
+
+        /// The entry at `keystore_index` was trashed, purged or erased
+        /// in one of the server's stores, by any connection. Only sent
+        /// once subscribed to with `lair_set_event_filter`,
+        /// see `EventKind::EntryRemoved`.
+        fn entry_removed(keystore_index: KeystoreIndex) -> ();
     }
 }
 
 /// Lair Client Event Sender Type.
+#[cfg(feature = "full")]
 pub type LairClientEventSenderType =
     futures::channel::mpsc::Sender<LairClientEvent>;
 
 /// Lair Client Event Receiver Type.
+#[cfg(feature = "full")]
 pub type LairClientEventReceiver =
     futures::channel::mpsc::Receiver<LairClientEvent>;
 
+#[cfg(feature = "full")]
+impl LairClientEvent {
+    /// Answer this event as a client with nothing to add does:
+    /// informational events are acknowledged, sign approvals are
+    /// declined, and passphrase requests fail. For event loops that
+    /// only handle some of the events themselves.
+    pub fn respond_default(self) {
+        use futures::future::FutureExt;
+        match self {
+            Self::RequestUnlockPassphrase { respond, .. } => {
+                respond.respond(Ok(async move {
+                    Err("no unlock passphrase to give".into())
+                }
+                .boxed()
+                .into()));
+            }
+            Self::RequestSignApproval { respond, .. } => {
+                respond.respond(Ok(async move { Ok(false) }.boxed().into()));
+            }
+            Self::UnlockProgress { respond, .. }
+            | Self::UnlockComplete { respond, .. }
+            | Self::EventsDropped { respond, .. }
+            | Self::EntryCreated { respond, .. }
+            | Self::ClockSkewDetected { respond, .. }
+            | Self::EntryActivated { respond, .. }
+            | Self::AuditEvent { respond, .. }
+            | Self::EntryRemoved { respond, .. } => {
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            }
+        }
+    }
+
+    /// Fail this event with `error`, e.g. one that will not reach the
+    /// client, so the operation awaiting its answer errors instead of
+    /// hanging.
+    pub fn respond_error(self, error: LairError) {
+        use futures::future::FutureExt;
+        match self {
+            Self::RequestUnlockPassphrase { respond, .. } => {
+                respond.respond(Ok(async move { Err(error) }.boxed().into()));
+            }
+            Self::RequestSignApproval { respond, .. } => {
+                respond.respond(Ok(async move { Err(error) }.boxed().into()));
+            }
+            Self::UnlockProgress { respond, .. }
+            | Self::UnlockComplete { respond, .. }
+            | Self::EventsDropped { respond, .. }
+            | Self::EntryCreated { respond, .. }
+            | Self::ClockSkewDetected { respond, .. }
+            | Self::EntryActivated { respond, .. }
+            | Self::AuditEvent { respond, .. }
+            | Self::EntryRemoved { respond, .. } => {
+                respond.respond(Ok(async move { Err(error) }.boxed().into()));
+            }
+        }
+    }
+}
+
+/// The kinds of `LairClientEvent`, see `lair_set_event_filter`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// `LairClientEvent::RequestUnlockPassphrase`
+    RequestUnlockPassphrase = 0x00000001,
+    /// `LairClientEvent::UnlockProgress`
+    UnlockProgress = 0x00000002,
+    /// `LairClientEvent::UnlockComplete`
+    UnlockComplete = 0x00000003,
+    /// `LairClientEvent::EventsDropped`
+    EventsDropped = 0x00000004,
+    /// `LairClientEvent::EntryCreated`
+    EntryCreated = 0x00000005,
+    /// `LairClientEvent::RequestSignApproval`
+    RequestSignApproval = 0x00000006,
+    /// `LairClientEvent::ClockSkewDetected`
+    ClockSkewDetected = 0x00000007,
+    /// `LairClientEvent::EntryActivated`
+    EntryActivated = 0x00000008,
+    /// `LairClientEvent::AuditEvent`
+    AuditEvent = 0x00000009,
+    /// `LairClientEvent::EntryRemoved`
+    EntryRemoved = 0x0000000a,
+}
+
+/// The maximum number of kinds `lair_set_event_filter` takes,
+/// repeats included.
+pub const MAX_EVENT_FILTER_KINDS: usize = 16;
+
+impl EventKind {
+    /// Every event kind.
+    pub const ALL: &'static [EventKind] = &[
+        EventKind::RequestUnlockPassphrase,
+        EventKind::UnlockProgress,
+        EventKind::UnlockComplete,
+        EventKind::EventsDropped,
+        EventKind::EntryCreated,
+        EventKind::RequestSignApproval,
+        EventKind::ClockSkewDetected,
+        EventKind::EntryActivated,
+        EventKind::AuditEvent,
+        EventKind::EntryRemoved,
+    ];
+
+    /// The kinds connections are subscribed to until they call
+    /// `lair_set_event_filter`: every kind but `EntryCreated`,
+    /// `ClockSkewDetected`, `EntryActivated` and `AuditEvent`, which are
+    /// only of interest to monitoring tools, `EntryRemoved`, only of
+    /// interest to caches, e.g. `LairClientCache::with_events`, and
+    /// `RequestSignApproval`, which clients unaware of it cannot answer:
+    /// signing with a keypair requiring approval fails on their
+    /// connections right away.
+    pub const DEFAULT: &'static [EventKind] = &[
+        EventKind::RequestUnlockPassphrase,
+        EventKind::UnlockProgress,
+        EventKind::UnlockComplete,
+        EventKind::EventsDropped,
+    ];
+
+    /// parse a u32 into an EventKind enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use EventKind::*;
+        Ok(match d {
+            x if x == RequestUnlockPassphrase as u32 => RequestUnlockPassphrase,
+            x if x == UnlockProgress as u32 => UnlockProgress,
+            x if x == UnlockComplete as u32 => UnlockComplete,
+            x if x == EventsDropped as u32 => EventsDropped,
+            x if x == EntryCreated as u32 => EntryCreated,
+            x if x == RequestSignApproval as u32 => RequestSignApproval,
+            x if x == ClockSkewDetected as u32 => ClockSkewDetected,
+            x if x == EntryActivated as u32 => EntryActivated,
+            x if x == AuditEvent as u32 => AuditEvent,
+            x if x == EntryRemoved as u32 => EntryRemoved,
+            _ => return Err("invalid event kind".into()),
+        })
+    }
+
+    /// The kind of `evt`.
+    #[cfg(feature = "full")]
+    pub fn of(evt: &LairClientEvent) -> Self {
+        match evt {
+            LairClientEvent::RequestUnlockPassphrase { .. } => {
+                EventKind::RequestUnlockPassphrase
+            }
+            LairClientEvent::UnlockProgress { .. } => EventKind::UnlockProgress,
+            LairClientEvent::UnlockComplete { .. } => EventKind::UnlockComplete,
+            LairClientEvent::EventsDropped { .. } => EventKind::EventsDropped,
+            LairClientEvent::EntryCreated { .. } => EventKind::EntryCreated,
+            LairClientEvent::RequestSignApproval { .. } => {
+                EventKind::RequestSignApproval
+            }
+            LairClientEvent::ClockSkewDetected { .. } => {
+                EventKind::ClockSkewDetected
+            }
+            LairClientEvent::EntryActivated { .. } => EventKind::EntryActivated,
+            LairClientEvent::AuditEvent { .. } => EventKind::AuditEvent,
+            LairClientEvent::EntryRemoved { .. } => EventKind::EntryRemoved,
+        }
+    }
+}
+
 /// Tls keypair algorithm to use.
 #[non_exhaustive]
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum TlsCertAlg {
     /// Ed25519 Curve.
+    #[default]
     PkcsEd25519 = 0x00000200,
     /// Ecdsa Curve 256.
     PkcsEcdsaP256Sha256 = 0x00000201,
     /// Ecdsa Curve 384.
     PkcsEcdsaP384Sha384 = 0x00000202,
-}
-
-impl Default for TlsCertAlg {
-    fn default() -> Self {
-        Self::PkcsEd25519
-    }
+    /// Ed448 Curve.
+    PkcsEd448 = 0x00000203,
+    /// A certificate key of an algorithm lair does not generate,
+    /// or one it could not parse. Never valid for generation.
+    Unknown = 0x00000000,
 }
 
 impl TlsCertAlg {
@@ -47,6 +273,8 @@ impl TlsCertAlg {
             x if x == PkcsEd25519 as u32 => PkcsEd25519,
             x if x == PkcsEcdsaP256Sha256 as u32 => PkcsEcdsaP256Sha256,
             x if x == PkcsEcdsaP384Sha384 as u32 => PkcsEcdsaP384Sha384,
+            x if x == PkcsEd448 as u32 => PkcsEd448,
+            x if x == Unknown as u32 => Unknown,
             _ => return Err("invalide tls cert alg".into()),
         })
     }
@@ -67,6 +295,251 @@ impl Default for TlsCertOptions {
     }
 }
 
+/// Configuration for signature ed25519 keypair creation,
+/// see `sign_ed25519_new_from_entropy_with_options`.
+/// Every option but the tag is stored with the keypair for good.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignKeyOptions {
+    /// Tag the new entry, see `lair_set_entry_tag`. Default `None`.
+    pub tag: Option<String>,
+
+    /// May the private key leave the keystore, with `lair_wrap_entry`
+    /// or as the key of a tls cert bound to it? Refused exports fail with
+    /// `LairError::ExportForbidden`. Default `true`.
+    pub exportable: bool,
+
+    /// Unix time, in seconds, from which the keypair refuses to sign,
+    /// with `LairError::KeyExpired`. Default `None`, it never expires.
+    pub expires_at: Option<u64>,
+
+    /// Must the requesting client approve each signature, answering
+    /// `LairClientEvent::RequestSignApproval`? Default `false`.
+    pub requires_approval: bool,
+}
+
+impl Default for SignKeyOptions {
+    fn default() -> Self {
+        Self {
+            tag: None,
+            exportable: true,
+            expires_at: None,
+            requires_approval: false,
+        }
+    }
+}
+
+/// Configuration for signature ed25519 keypair rotation,
+/// see `sign_ed25519_rotate`.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct RotateKeyOptions {
+    /// The options of the new keypair. Default `SignKeyOptions::default()`.
+    pub key_options: SignKeyOptions,
+
+    /// Move the old keypair to the trash once it has signed the
+    /// delegation, see `lair_trash_entry`. Default `false`.
+    pub trash_old_key: bool,
+}
+
+/// Configuration for a single signature,
+/// see `sign_ed25519_sign_by_index_with_options`.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct SignOptions {
+    /// Sign the time of signing along with the message, see
+    /// `timestamped_message`. The server reads the time from its own
+    /// clock, and returns it with the signature, verify both with
+    /// `SignEd25519PubKey::verify_timestamped`. Default `false`.
+    pub include_timestamp: bool,
+}
+
+/// The maximum argon2id memory cost, in KiB (1 GiB).
+pub const MAX_PW_HASH_MEM_COST_KIB: u32 = 1024 * 1024;
+
+/// The maximum argon2id time cost (iteration count).
+pub const MAX_PW_HASH_TIME_COST: u32 = 16;
+
+/// The maximum argon2id lane count (degree of parallelism).
+pub const MAX_PW_HASH_LANES: u32 = 16;
+
+/// Argon2id parameters for `pw_hash`. The defaults follow the
+/// OWASP recommendation for argon2id: 19 MiB memory,
+/// 2 iterations, 1 lane. Hashes record their own parameters,
+/// so they may be changed at any time without affecting `pw_verify`.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PwHashOptions {
+    /// Memory cost in KiB, at least `8 * lanes`,
+    /// at most `MAX_PW_HASH_MEM_COST_KIB`. Default 19456.
+    pub mem_cost_kib: u32,
+
+    /// Time cost (iteration count), 1 to `MAX_PW_HASH_TIME_COST`.
+    /// Default 2.
+    pub time_cost: u32,
+
+    /// Lane count, 1 to `MAX_PW_HASH_LANES`. Default 1.
+    pub lanes: u32,
+}
+
+impl Default for PwHashOptions {
+    fn default() -> Self {
+        Self {
+            mem_cost_kib: 19 * 1024,
+            time_cost: 2,
+            lanes: 1,
+        }
+    }
+}
+
+impl PwHashOptions {
+    /// Check these parameters are within the documented bounds.
+    pub fn check(&self) -> LairResult<()> {
+        if self.lanes < 1 || self.lanes > MAX_PW_HASH_LANES {
+            return Err(format!(
+                "pw hash lanes must be between 1 and {}",
+                MAX_PW_HASH_LANES
+            )
+            .into());
+        }
+        if self.time_cost < 1 || self.time_cost > MAX_PW_HASH_TIME_COST {
+            return Err(format!(
+                "pw hash time cost must be between 1 and {}",
+                MAX_PW_HASH_TIME_COST
+            )
+            .into());
+        }
+        if self.mem_cost_kib < 8 * self.lanes
+            || self.mem_cost_kib > MAX_PW_HASH_MEM_COST_KIB
+        {
+            return Err(format!(
+                "pw hash mem cost must be between 8 * lanes and {} KiB",
+                MAX_PW_HASH_MEM_COST_KIB
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// The minimum byte length of a TOTP secret, the 128 bits RFC 4226
+/// requires of HOTP secrets.
+pub const MIN_TOTP_SECRET_LEN: usize = 16;
+
+/// The maximum byte length of a TOTP secret.
+pub const MAX_TOTP_SECRET_LEN: usize = 64;
+
+/// The maximum length of a base32 TOTP secret to import,
+/// spaces and padding included.
+pub const MAX_TOTP_SECRET_B32_LEN: usize = 256;
+
+/// The minimum number of digits in a TOTP code.
+pub const MIN_TOTP_DIGITS: u32 = 6;
+
+/// The maximum number of digits in a TOTP code, the most
+/// the 31 bit truncated HMAC can fill.
+pub const MAX_TOTP_DIGITS: u32 = 10;
+
+/// The maximum TOTP time step, in seconds (1 hour).
+pub const MAX_TOTP_PERIOD: u32 = 3600;
+
+/// The maximum byte length of the label, and of the issuer,
+/// of a TOTP provisioning uri.
+pub const MAX_TOTP_LABEL_LEN: usize = 256;
+
+/// The HMAC hash function of a TOTP secret (RFC 6238).
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum TotpAlg {
+    /// HMAC-SHA-1, what authenticator apps expect by default.
+    #[default]
+    Sha1 = 0x00000601,
+    /// HMAC-SHA-256.
+    Sha256 = 0x00000602,
+    /// HMAC-SHA-512.
+    Sha512 = 0x00000603,
+}
+
+impl TotpAlg {
+    /// parse a u32 into a TotpAlg enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use TotpAlg::*;
+        Ok(match d {
+            x if x == Sha1 as u32 => Sha1,
+            x if x == Sha256 as u32 => Sha256,
+            x if x == Sha512 as u32 => Sha512,
+            _ => return Err("invalid totp alg".into()),
+        })
+    }
+
+    /// The name of this algorithm in provisioning uris.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TotpAlg::Sha1 => "SHA1",
+            TotpAlg::Sha256 => "SHA256",
+            TotpAlg::Sha512 => "SHA512",
+        }
+    }
+
+    /// The byte length of the hash output, the recommended
+    /// length of new secrets.
+    pub fn hash_len(&self) -> usize {
+        match self {
+            TotpAlg::Sha1 => 20,
+            TotpAlg::Sha256 => 32,
+            TotpAlg::Sha512 => 64,
+        }
+    }
+}
+
+/// The code parameters of a TOTP secret, stored with it.
+/// The defaults, 6 digits every 30 seconds with HMAC-SHA-1,
+/// are what authenticator apps assume when not told otherwise.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TotpOptions {
+    /// Code length, `MIN_TOTP_DIGITS` to `MAX_TOTP_DIGITS`. Default 6.
+    pub digits: u32,
+
+    /// Time step in seconds, 1 to `MAX_TOTP_PERIOD`. Default 30.
+    pub period: u32,
+
+    /// HMAC hash function. Default `TotpAlg::Sha1`.
+    pub alg: TotpAlg,
+}
+
+impl Default for TotpOptions {
+    fn default() -> Self {
+        Self {
+            digits: 6,
+            period: 30,
+            alg: TotpAlg::Sha1,
+        }
+    }
+}
+
+impl TotpOptions {
+    /// Check these parameters are within the documented bounds.
+    pub fn check(&self) -> LairResult<()> {
+        if self.digits < MIN_TOTP_DIGITS || self.digits > MAX_TOTP_DIGITS {
+            return Err(format!(
+                "totp digits must be between {} and {}",
+                MIN_TOTP_DIGITS, MAX_TOTP_DIGITS
+            )
+            .into());
+        }
+        if self.period < 1 || self.period > MAX_TOTP_PERIOD {
+            return Err(format!(
+                "totp period must be between 1 and {} seconds",
+                MAX_TOTP_PERIOD
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
 /// Keystore index type.
 #[derive(
     Clone,
@@ -84,6 +557,92 @@ impl Default for TlsCertOptions {
 )]
 pub struct KeystoreIndex(pub u32);
 
+impl KeystoreIndex {
+    /// The low bits of an index number the entry within its store,
+    /// the high bits hold the store number.
+    pub const ENTRY_BITS: u32 = 24;
+
+    /// The highest entry number a single store can hold.
+    pub const MAX_ENTRY: u32 = (1 << Self::ENTRY_BITS) - 1;
+
+    /// Build the index of entry number `entry` in store `store_number`.
+    /// The default store is store number 0, so its indexes are
+    /// just entry numbers.
+    pub fn new(store_number: u8, entry: u32) -> Self {
+        Self(((store_number as u32) << Self::ENTRY_BITS) | entry)
+    }
+
+    /// The number of the store this index belongs to.
+    pub fn store_number(&self) -> u8 {
+        (self.0 >> Self::ENTRY_BITS) as u8
+    }
+
+    /// The entry number within its store.
+    pub fn entry(&self) -> u32 {
+        self.0 & Self::MAX_ENTRY
+    }
+}
+
+impl std::convert::TryFrom<KeystoreIndex64> for KeystoreIndex {
+    type Error = LairError;
+
+    /// Indexes above `u32::MAX` fail with `LairError::IndexOutOfRange`.
+    fn try_from(keystore_index: KeystoreIndex64) -> LairResult<Self> {
+        match u32::try_from(keystore_index.0) {
+            Ok(keystore_index) => Ok(Self(keystore_index)),
+            Err(_) => Err(LairError::IndexOutOfRange(format!(
+                "keystore index {} does not fit in 32 bits",
+                keystore_index,
+            ))),
+        }
+    }
+}
+
+/// Keystore index type, twice the width of `KeystoreIndex`. Every
+/// `KeystoreIndex` converts to the `KeystoreIndex64` of the same value,
+/// only values up to `u32::MAX` convert back. Servers since
+/// `spec::KEYSTORE_INDEX64_VERSION` take these in the `_64` requests,
+/// clients send older servers the `KeystoreIndex` requests instead.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deref,
+    From,
+    Into,
+)]
+pub struct KeystoreIndex64(pub u64);
+
+impl From<KeystoreIndex> for KeystoreIndex64 {
+    fn from(keystore_index: KeystoreIndex) -> Self {
+        Self(keystore_index.0 as u64)
+    }
+}
+
+/// Handle of an ephemeral key, see `ephemeral_x25519_new`. Only
+/// meaningful on the connection that created the key.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deref,
+    From,
+    Into,
+)]
+pub struct EphemeralHandle(pub u64);
+
 /// Der encoded Tls Certificate bytes.
 #[derive(
     Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
@@ -120,6 +679,85 @@ impl From<String> for CertSni {
     }
 }
 
+/// The maximum byte length of a `CertSni`. Below that of a DNS name
+/// (253 bytes), it is the limit of the wire encoding.
+pub const MAX_CERT_SNI_LEN: usize = 128;
+
+/// The maximum byte length of each dot separated `CertSni` label.
+pub const MAX_CERT_SNI_LABEL_LEN: usize = 63;
+
+impl CertSni {
+    /// Validate `sni` as a tls server name (RFC 6066), returning it
+    /// normalized to lowercase. It must be a hostname (RFC 1123) of at
+    /// most `MAX_CERT_SNI_LEN` bytes: dot separated labels, of 1 to
+    /// `MAX_CERT_SNI_LABEL_LEN` ascii letters, digits and hyphens,
+    /// neither starting nor ending with a hyphen. The last label may not
+    /// be all digits, ruling out ip addresses. The first label may
+    /// instead be a `*` wildcard, if more labels follow.
+    pub fn parse(sni: &str) -> LairResult<Self> {
+        let out = Self::parse_lenient(sni)?;
+        let err = |reason: &str| -> LairResult<Self> {
+            Err(format!("invalid sni {:?}: {}", sni, reason).into())
+        };
+        let labels = out.split('.').collect::<Vec<_>>();
+        for (i, label) in labels.iter().enumerate() {
+            if i == 0 && *label == "*" {
+                if labels.len() < 2 {
+                    return err("a wildcard must cover further labels");
+                }
+                continue;
+            }
+            if label.is_empty() {
+                return err("empty label");
+            }
+            if label.len() > MAX_CERT_SNI_LABEL_LEN {
+                return err("label too long");
+            }
+            if !label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            {
+                return err("labels hold only letters, digits and hyphens");
+            }
+            if label.starts_with('-') || label.ends_with('-') {
+                return err("labels may not start or end with a hyphen");
+            }
+        }
+        if labels.last().unwrap().bytes().all(|b| b.is_ascii_digit()) {
+            return err("the last label may not be all digits");
+        }
+        Ok(out)
+    }
+
+    /// Normalize `sni` to lowercase, without the hostname rules of
+    /// `parse`, which certs from older lair versions, or imported with
+    /// `ConfigBuilder::danger_set_allow_invalid_sni`, may not meet.
+    /// Still refuses anything no server name holds: an empty name, one
+    /// longer than `MAX_CERT_SNI_LEN`, or any bytes but printable ascii.
+    pub fn parse_lenient(sni: &str) -> LairResult<Self> {
+        if sni.is_empty() || sni.len() > MAX_CERT_SNI_LEN {
+            return Err(format!(
+                "invalid sni {:?}: must be 1 to {} bytes",
+                sni, MAX_CERT_SNI_LEN,
+            )
+            .into());
+        }
+        if !sni.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(format!(
+                "invalid sni {:?}: must be printable ascii",
+                sni
+            )
+            .into());
+        }
+        Ok(Self::from(sni.to_ascii_lowercase()))
+    }
+
+    /// This sni, lowercased, as lookups compare it.
+    pub fn normalized(&self) -> Self {
+        Self::from(self.to_ascii_lowercase())
+    }
+}
+
 /// The 32 byte blake2b digest of given Tls Certificate.
 #[derive(
     Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
@@ -132,9 +770,88 @@ impl From<Vec<u8>> for CertDigest {
     }
 }
 
+impl CertDigest {
+    /// Compute the digest of a der encoded certificate, as lair does,
+    /// so a held cert can be checked against a digest from lair.
+    pub fn compute(cert: &Cert) -> CertDigest {
+        hash::blake2b(cert, 32, None)
+            .expect("32 byte unkeyed blake2b is within bounds")
+            .into()
+    }
+}
+
+/// The algorithm of a tls certificate digest. Lair identifies certs by
+/// their `Blake2b` `CertDigest`, the others are for interop with
+/// systems pinning certs by fingerprint.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum DigestAlg {
+    /// The 32 byte unkeyed blake2b `CertDigest`.
+    #[default]
+    Blake2b = 0x00000300,
+    /// 32 byte sha-256, the usual certificate fingerprint.
+    Sha256 = 0x00000301,
+    /// 20 byte sha-1, for legacy pinning only.
+    /// Sha-1 is not collision resistant, prefer `Sha256`.
+    Sha1Legacy = 0x00000302,
+}
+
+impl DigestAlg {
+    /// parse a u32 into a DigestAlg enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use DigestAlg::*;
+        Ok(match d {
+            x if x == Blake2b as u32 => Blake2b,
+            x if x == Sha256 as u32 => Sha256,
+            x if x == Sha1Legacy as u32 => Sha1Legacy,
+            _ => return Err("invalid digest alg".into()),
+        })
+    }
+
+    /// The byte length of digests of this algorithm.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            DigestAlg::Blake2b | DigestAlg::Sha256 => 32,
+            DigestAlg::Sha1Legacy => 20,
+        }
+    }
+
+    /// Compute the digest of a der encoded certificate.
+    #[cfg(feature = "full")]
+    pub fn compute(&self, cert: &Cert) -> Arc<Vec<u8>> {
+        match self {
+            DigestAlg::Blake2b => CertDigest::compute(cert).0,
+            DigestAlg::Sha256 => Arc::new(
+                ring::digest::digest(&ring::digest::SHA256, cert)
+                    .as_ref()
+                    .to_vec(),
+            ),
+            DigestAlg::Sha1Legacy => Arc::new(
+                ring::digest::digest(
+                    &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+                    cert,
+                )
+                .as_ref()
+                .to_vec(),
+            ),
+        }
+    }
+}
+
 /// The 32 byte signature ed25519 public key.
 #[derive(
-    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deref,
+    From,
+    Into,
 )]
 pub struct SignEd25519PubKey(pub Arc<Vec<u8>>);
 
@@ -146,53 +863,615 @@ impl From<Vec<u8>> for SignEd25519PubKey {
 
 impl SignEd25519PubKey {
     /// Verify signature on given message with given public key.
+    /// See `verify_sync`, which spares the message copy.
     pub async fn verify(
         &self,
         message: Arc<Vec<u8>>,
         signature: SignEd25519Signature,
     ) -> LairResult<bool> {
-        internal::sign_ed25519::sign_ed25519_verify(
-            self.clone(),
+        self.verify_sync(&message, &signature)
+    }
+
+    /// Verify signature on given message with given public key,
+    /// synchronously, on borrowed data. The signature is a
+    /// `SignEd25519Signature` or a `[u8; 64]`, a signature of another
+    /// length does not verify.
+    pub fn verify_sync<S: AsRef<[u8]> + ?Sized>(
+        &self,
+        message: &[u8],
+        signature: &S,
+    ) -> LairResult<bool> {
+        Ok(ed25519_backend::verify(
+            &self.0,
             message,
+            signature.as_ref(),
+        ))
+    }
+
+    /// Verify signature on given message with given public key,
+    /// synchronously, using a pure-rust (wasm compatible) backend
+    /// whichever backend the `ed25519_*` features select.
+    pub fn verify_pure(
+        &self,
+        message: &[u8],
+        signature: &SignEd25519Signature,
+    ) -> bool {
+        use ed25519_backend::Ed25519Backend;
+        ed25519_backend::Dalek::verify(&self.0, message, signature)
+    }
+
+    /// Verify an Ed25519ph signature of the sha-512 `prehash` of a
+    /// message, under `context`, as made by `sign_ed25519_ph_sign_by_index`
+    /// or `SigningKeyHandle::sign_stream`. Pure rust (wasm compatible).
+    pub fn verify_ph(
+        &self,
+        context: &[u8],
+        prehash: &[u8; ed25519ph::PREHASH_LEN],
+        signature: &SignEd25519Signature,
+    ) -> bool {
+        ed25519ph::verify(&self.0, context, prehash, &signature.0)
+    }
+
+    /// Verify a signature made with `SignOptions::include_timestamp`,
+    /// of `message` at `timestamp_millis`, the timestamp returned
+    /// with it, see `timestamped_message`. Pure rust (wasm compatible).
+    pub fn verify_timestamped(
+        &self,
+        message: &[u8],
+        timestamp_millis: u64,
+        signature: &SignEd25519Signature,
+    ) -> bool {
+        self.verify_pure(
+            &timestamped_message(timestamp_millis, message),
             signature,
         )
-        .await
+    }
+
+    /// Verify an Ed25519ph signature, with no context, of everything
+    /// `reader` yields, as made by `SigningKeyHandle::sign_stream`.
+    /// Hashes the message as it streams, see `ed25519ph::prehash_reader`.
+    #[cfg(feature = "full")]
+    pub async fn verify_stream(
+        &self,
+        reader: impl tokio::io::AsyncRead + Unpin,
+        signature: &SignEd25519Signature,
+    ) -> LairResult<bool> {
+        let prehash = ed25519ph::prehash_reader(reader).await?;
+        Ok(self.verify_ph(b"", &prehash, signature))
+    }
+
+    /// Format as an OpenSSH public key line, `ssh-ed25519 AAAA... comment`,
+    /// as found in `authorized_keys`. An empty comment is left out.
+    #[cfg(feature = "full")]
+    pub fn to_ssh_public_key(&self, comment: &str) -> String {
+        internal::ssh_key::ssh_ed25519_pub_key_line(self, comment)
+    }
+
+    /// Parse an OpenSSH `ssh-ed25519` public key line,
+    /// ignoring its comment. Other key types are refused.
+    #[cfg(feature = "full")]
+    pub fn from_ssh_public_key(line: &str) -> LairResult<Self> {
+        internal::ssh_key::ssh_ed25519_pub_key_parse(line)
+    }
+
+    /// Format as an RFC 8037 `OKP` / `Ed25519` JSON Web Key,
+    /// with members in RFC 7638 thumbprint order.
+    #[cfg(feature = "jose")]
+    pub fn to_jwk(&self) -> String {
+        jose::ed25519_pub_key_to_jwk(self)
+    }
+
+    /// Parse an RFC 8037 `OKP` / `Ed25519` JSON Web Key.
+    /// Keys of other types, and keys holding a private key, are refused.
+    #[cfg(feature = "jose")]
+    pub fn from_jwk(jwk: &str) -> LairResult<Self> {
+        jose::ed25519_pub_key_from_jwk(jwk)
+    }
+
+    /// Derive the x25519 public key for this ed25519 identity,
+    /// as libsodium's `crypto_sign_ed25519_pk_to_curve25519` does.
+    /// Boxes encrypted to this identity can be opened by the
+    /// holder of the matching signature private key.
+    /// Returns `LairError::InvalidKeyConversion` for keys that are not
+    /// valid points, or that are of low order.
+    pub fn to_x25519_pub_key(&self) -> LairResult<X25519PubKey> {
+        let pub_key = ed25519_compact::PublicKey::from_slice(&self.0)
+            .map_err(|e| LairError::InvalidKeyConversion(format!("{:?}", e)))?;
+        let pub_key =
+            ed25519_compact::x25519::PublicKey::from_ed25519(&pub_key)
+                .map_err(|e| {
+                    LairError::InvalidKeyConversion(format!("{:?}", e))
+                })?;
+        Ok(pub_key.to_vec().into())
     }
 }
 
-/// The 64 byte detached ed25519 signature data.
+/// The 33 byte compressed secp256k1 signature public key.
 #[derive(
     Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
 )]
-pub struct SignEd25519Signature(pub Arc<Vec<u8>>);
+pub struct SignSecp256k1PubKey(pub Arc<Vec<u8>>);
 
-impl From<Vec<u8>> for SignEd25519Signature {
+impl From<Vec<u8>> for SignSecp256k1PubKey {
     fn from(d: Vec<u8>) -> Self {
         Self(Arc::new(d))
     }
 }
 
-/// The entry type for a given entry.
-#[non_exhaustive]
-#[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum LairEntryType {
-    /// This entry index was deleted or corrupted.
-    Invalid = 0x00000000,
+impl SignSecp256k1PubKey {
+    /// Parse a 33 byte compressed or 65 byte uncompressed
+    /// sec1 encoded public key, checking it is on the curve.
+    pub fn from_bytes(pub_key: &[u8]) -> LairResult<Self> {
+        Ok(secp256k1::compress_pub_key(pub_key)?.into())
+    }
 
-    /// Tls Certificate & private key.
-    TlsCert = 0x00000100,
+    /// Get the 65 byte uncompressed sec1 encoding of this public key.
+    pub fn to_uncompressed(&self) -> LairResult<Vec<u8>> {
+        secp256k1::decompress_pub_key(&self.0)
+    }
 
-    /// Ed25519 algorithm signature keypair.
-    SignEd25519 = 0x00000200,
-}
+    /// Verify signature on given 32 byte message hash with this public key,
+    /// synchronously, using a pure-rust (wasm compatible) backend.
+    pub fn verify(
+        &self,
+        message_hash: &[u8; 32],
+        signature: &SignSecp256k1Signature,
+    ) -> bool {
+        secp256k1::verify(&self.0, message_hash, &signature.0)
+    }
 
-impl Default for LairEntryType {
-    fn default() -> Self {
-        Self::Invalid
+    /// Recover the public key that produced a signature
+    /// on given 32 byte message hash, from its recovery id.
+    pub fn recover(
+        message_hash: &[u8; 32],
+        signature: &SignSecp256k1Signature,
+        recovery_id: u8,
+    ) -> LairResult<Self> {
+        Ok(secp256k1::recover(message_hash, &signature.0, recovery_id)?.into())
     }
 }
 
+/// The 64 byte `r || s` secp256k1 signature data.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignSecp256k1Signature(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignSecp256k1Signature {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// The byte length of an ed448 public key.
+pub const SIGN_ED448_PUB_KEY_LEN: usize = 57;
+
+/// The byte length of an ed448 signature.
+pub const SIGN_ED448_SIGNATURE_LEN: usize = 114;
+
+/// The 57 byte signature ed448 public key.
+/// Only ever holds 57 bytes, build it with `from_bytes` or `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignEd448PubKey(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_ED448_PUB_KEY_LEN]> for SignEd448PubKey {
+    fn from(d: [u8; SIGN_ED448_PUB_KEY_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignEd448PubKey {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_ED448_PUB_KEY_LEN {
+            return Err(format!(
+                "ed448 pub key must be {} bytes, got {}",
+                SIGN_ED448_PUB_KEY_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignEd448PubKey {
+    /// Parse a 57 byte ed448 public key. Other lengths are refused.
+    pub fn from_bytes(pub_key: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(pub_key.to_vec())
+    }
+
+    /// Verify an RFC 8032 Ed448 signature, with an empty context,
+    /// on given message with this public key,
+    /// synchronously, using a pure-rust (wasm compatible) backend.
+    #[cfg(feature = "ed448")]
+    pub fn verify(
+        &self,
+        message: &[u8],
+        signature: &SignEd448Signature,
+    ) -> bool {
+        let mut pub_key = [0; SIGN_ED448_PUB_KEY_LEN];
+        pub_key.copy_from_slice(&self.0);
+        let pub_key = match ed448_goldilocks::VerifyingKey::from_bytes(&pub_key)
+        {
+            Ok(pub_key) => pub_key,
+            Err(_) => return false,
+        };
+        let signature =
+            match ed448_goldilocks::Signature::from_slice(&signature.0) {
+                Ok(signature) => signature,
+                Err(_) => return false,
+            };
+        pub_key.verify_raw(&signature, message).is_ok()
+    }
+}
+
+/// The 114 byte detached ed448 signature data.
+/// Only ever holds 114 bytes, build it with `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignEd448Signature(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_ED448_SIGNATURE_LEN]> for SignEd448Signature {
+    fn from(d: [u8; SIGN_ED448_SIGNATURE_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignEd448Signature {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_ED448_SIGNATURE_LEN {
+            return Err(format!(
+                "ed448 signature must be {} bytes, got {}",
+                SIGN_ED448_SIGNATURE_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignEd448Signature {
+    /// Parse a 114 byte ed448 signature. Other lengths are refused.
+    pub fn from_bytes(signature: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(signature.to_vec())
+    }
+}
+
+/// The byte length of a bls public key.
+pub const SIGN_BLS_PUB_KEY_LEN: usize = bls::PUB_KEY_LEN;
+
+/// The byte length of a bls signature.
+pub const SIGN_BLS_SIGNATURE_LEN: usize = bls::SIGNATURE_LEN;
+
+/// The 48 byte compressed G1 bls12-381 signature public key.
+/// Only ever holds 48 bytes, build it with `from_bytes` or `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignBlsPubKey(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_BLS_PUB_KEY_LEN]> for SignBlsPubKey {
+    fn from(d: [u8; SIGN_BLS_PUB_KEY_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignBlsPubKey {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_BLS_PUB_KEY_LEN {
+            return Err(format!(
+                "bls pub key must be {} bytes, got {}",
+                SIGN_BLS_PUB_KEY_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignBlsPubKey {
+    /// Parse a 48 byte bls public key. Other lengths are refused.
+    pub fn from_bytes(pub_key: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(pub_key.to_vec())
+    }
+
+    /// Verify a bls signature on given message with this public key,
+    /// under the domain separation tag `dst`, synchronously.
+    /// See the `bls` module.
+    pub fn verify(
+        &self,
+        message: &[u8],
+        signature: &SignBlsSignature,
+        dst: &[u8],
+    ) -> bool {
+        bls::verify(&self.0, message, &signature.0, dst)
+    }
+}
+
+/// The 96 byte compressed G2 bls12-381 signature data,
+/// either of a single message or an aggregate.
+/// Only ever holds 96 bytes, build it with `from_bytes` or `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignBlsSignature(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_BLS_SIGNATURE_LEN]> for SignBlsSignature {
+    fn from(d: [u8; SIGN_BLS_SIGNATURE_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignBlsSignature {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_BLS_SIGNATURE_LEN {
+            return Err(format!(
+                "bls signature must be {} bytes, got {}",
+                SIGN_BLS_SIGNATURE_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignBlsSignature {
+    /// Parse a 96 byte bls signature. Other lengths are refused.
+    pub fn from_bytes(signature: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(signature.to_vec())
+    }
+
+    /// Aggregate signatures, of any messages by any keys, into one.
+    /// Errors on an empty list or a signature that is not a valid point.
+    pub fn aggregate_signatures(signatures: Vec<Self>) -> LairResult<Self> {
+        let signatures = signatures
+            .iter()
+            .map(|s| s.0.as_slice())
+            .collect::<Vec<_>>();
+        Ok(bls::aggregate_signatures(&signatures)?.into())
+    }
+
+    /// Verify this aggregate signature of each `(pub_key, message)` pair,
+    /// under the domain separation tag `dst`. Messages need not be
+    /// distinct, see the `bls` module for when that is sound.
+    pub fn aggregate_verify(
+        &self,
+        pub_keys_messages: Vec<(SignBlsPubKey, Vec<u8>)>,
+        dst: &[u8],
+    ) -> bool {
+        let pub_keys_messages = pub_keys_messages
+            .iter()
+            .map(|(pub_key, message)| {
+                (pub_key.0.as_slice(), message.as_slice())
+            })
+            .collect::<Vec<_>>();
+        bls::aggregate_verify(&pub_keys_messages, &self.0, dst)
+    }
+}
+
+/// The byte length of an sr25519 public key.
+pub const SIGN_SR25519_PUB_KEY_LEN: usize = sr25519::PUB_KEY_LEN;
+
+/// The byte length of an sr25519 signature.
+pub const SIGN_SR25519_SIGNATURE_LEN: usize = sr25519::SIGNATURE_LEN;
+
+/// The 32 byte ristretto255 sr25519 signature public key.
+/// Only ever holds 32 bytes, build it with `from_bytes` or `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignSr25519PubKey(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_SR25519_PUB_KEY_LEN]> for SignSr25519PubKey {
+    fn from(d: [u8; SIGN_SR25519_PUB_KEY_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignSr25519PubKey {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_SR25519_PUB_KEY_LEN {
+            return Err(format!(
+                "sr25519 pub key must be {} bytes, got {}",
+                SIGN_SR25519_PUB_KEY_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignSr25519PubKey {
+    /// Parse a 32 byte sr25519 public key. Other lengths are refused.
+    pub fn from_bytes(pub_key: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(pub_key.to_vec())
+    }
+
+    /// Verify an sr25519 signature on given message with this public key,
+    /// in the signing context `context`, synchronously.
+    /// Matches schnorrkel's `verify_simple`, see the `sr25519` module.
+    pub fn verify(
+        &self,
+        message: &[u8],
+        signature: &SignSr25519Signature,
+        context: &[u8],
+    ) -> bool {
+        sr25519::verify(&self.0, message, &signature.0, context)
+    }
+}
+
+/// The 64 byte sr25519 signature data.
+/// Only ever holds 64 bytes, build it with `from_bytes` or `TryFrom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Into)]
+pub struct SignSr25519Signature(pub(crate) Arc<Vec<u8>>);
+
+impl From<[u8; SIGN_SR25519_SIGNATURE_LEN]> for SignSr25519Signature {
+    fn from(d: [u8; SIGN_SR25519_SIGNATURE_LEN]) -> Self {
+        Self(Arc::new(d.to_vec()))
+    }
+}
+
+impl std::convert::TryFrom<Vec<u8>> for SignSr25519Signature {
+    type Error = LairError;
+
+    fn try_from(d: Vec<u8>) -> LairResult<Self> {
+        if d.len() != SIGN_SR25519_SIGNATURE_LEN {
+            return Err(format!(
+                "sr25519 signature must be {} bytes, got {}",
+                SIGN_SR25519_SIGNATURE_LEN,
+                d.len(),
+            )
+            .into());
+        }
+        Ok(Self(Arc::new(d)))
+    }
+}
+
+impl SignSr25519Signature {
+    /// Parse a 64 byte sr25519 signature. Other lengths are refused.
+    pub fn from_bytes(signature: &[u8]) -> LairResult<Self> {
+        use std::convert::TryFrom;
+        Self::try_from(signature.to_vec())
+    }
+}
+
+/// The 32 byte x25519 public key.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct X25519PubKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for X25519PubKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// The byte length of the file key of an age file.
+pub const AGE_FILE_KEY_LEN: usize = 16;
+
+/// The maximum number of recipient stanzas `age_decrypt_by_index` takes.
+pub const MAX_AGE_STANZAS: usize = 256;
+
+/// The maximum number of arguments of an age stanza.
+pub const MAX_AGE_STANZA_ARGS: usize = 16;
+
+/// The maximum byte length of the tag, and of each argument,
+/// of an age stanza.
+pub const MAX_AGE_STANZA_ARG_LEN: usize = 256;
+
+/// The maximum byte length of the body of an age stanza.
+pub const MAX_AGE_STANZA_BODY_LEN: usize = 4096;
+
+/// A recipient stanza of an age (age-encryption.org/v1) file header,
+/// `-> <tag> <args>...` followed by its decoded body,
+/// see `age_decrypt_by_index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgeStanza {
+    /// The stanza type, e.g. `X25519`.
+    pub tag: String,
+
+    /// The stanza arguments, e.g. the base64 ephemeral share
+    /// of an `X25519` stanza.
+    pub args: Vec<String>,
+
+    /// The stanza body, e.g. the sealed file key.
+    pub body: Arc<Vec<u8>>,
+}
+
+/// The 64 byte detached ed25519 signature data.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignEd25519Signature(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignEd25519Signature {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+impl AsRef<[u8]> for SignEd25519Signature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Where the private key of a new keypair lives, see `sign_ed25519_new`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum KeyBacking {
+    /// In the lair store, like every other entry.
+    #[default]
+    Software = 0x00000001,
+    /// Generated on, and never leaving, the hardware token the server
+    /// is configured with, see `hardware`. Such keys cannot be exported,
+    /// nor used for anything but signing.
+    Hardware = 0x00000002,
+}
+
+impl KeyBacking {
+    /// parse a u32 into a KeyBacking enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use KeyBacking::*;
+        Ok(match d {
+            x if x == Software as u32 => Software,
+            x if x == Hardware as u32 => Hardware,
+            _ => return Err("invalid key backing".into()),
+        })
+    }
+}
+
+/// The entry type for a given entry.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum LairEntryType {
+    /// This entry index was deleted or corrupted.
+    #[default]
+    Invalid = 0x00000000,
+
+    /// Tls Certificate & private key.
+    TlsCert = 0x00000100,
+
+    /// Ed25519 algorithm signature keypair.
+    SignEd25519 = 0x00000200,
+
+    /// X25519 key agreement keypair.
+    X25519 = 0x00000300,
+
+    /// Secret pepper for argon2id password hashing.
+    PwPepper = 0x00000400,
+
+    /// Secp256k1 ecdsa signature keypair.
+    SignSecp256k1 = 0x00000500,
+
+    /// Time-based one-time password secret.
+    TotpSecret = 0x00000600,
+
+    /// Ed448 algorithm signature keypair.
+    SignEd448 = 0x00000700,
+
+    /// Bls12-381 signature keypair.
+    SignBls = 0x00000800,
+
+    /// Sr25519 schnorrkel signature keypair.
+    SignSr25519 = 0x00000900,
+}
+
 impl LairEntryType {
     /// parse a u32 into a LairEntryType enum variant.
     pub fn parse(d: u32) -> LairResult<Self> {
@@ -201,47 +1480,1027 @@ impl LairEntryType {
             x if x == Invalid as u32 => Invalid,
             x if x == TlsCert as u32 => TlsCert,
             x if x == SignEd25519 as u32 => SignEd25519,
+            x if x == X25519 as u32 => X25519,
+            x if x == PwPepper as u32 => PwPepper,
+            x if x == SignSecp256k1 as u32 => SignSecp256k1,
+            x if x == TotpSecret as u32 => TotpSecret,
+            x if x == SignEd448 as u32 => SignEd448,
+            x if x == SignBls as u32 => SignBls,
+            x if x == SignSr25519 as u32 => SignSr25519,
             _ => return Err("invalide lair entry type".into()),
         })
     }
 }
 
-/// Get information about the server we are connected to.
-#[non_exhaustive]
-#[derive(Debug, Default, Clone, PartialEq)]
-pub struct LairServerInfo {
-    /// Server name / identifier.
-    pub name: String,
+/// Summary of a single keystore entry, as returned by entry listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LairEntryInfo {
+    /// The keystore index of this entry.
+    pub keystore_index: KeystoreIndex,
+
+    /// The type of this entry.
+    pub entry_type: LairEntryType,
+
+    /// The public identifier of this entry, at most
+    /// `MAX_ENTRY_PUB_ID_LEN` bytes.
+    /// For SignEd25519 and X25519 this is the 32 byte pub key,
+    /// for SignSecp256k1 the 33 byte compressed pub key,
+    /// for SignEd448 the 57 byte pub key,
+    /// for SignBls the 48 byte compressed pub key,
+    /// for SignSr25519 the 32 byte pub key,
+    /// for TlsCert this is the 32 byte cert digest.
+    /// PwPepper and TotpSecret entries have no public component,
+    /// this is empty.
+    pub pub_id: Arc<Vec<u8>>,
+
+    /// The tag of this entry, see `lair_set_entry_tag`.
+    pub tag: Option<String>,
+
+    /// The aliases of this entry, sorted.
+    pub aliases: Vec<String>,
+
+    /// Is this entry in the trash, see `lair_trash_entry`?
+    pub trashed: bool,
+
+    /// The entry this ed25519 keypair replaced,
+    /// if it was created by `sign_ed25519_rotate`.
+    pub rotated_from: Option<KeystoreIndex>,
+
+    /// May this entry be used? Imported entries may not until
+    /// activated, see `lair_activate_entry`.
+    pub activated: bool,
+}
+
+/// A store entry that failed to load, see `lair_list_quarantined`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarantinedEntry {
+    /// The keystore index of the entry.
+    pub keystore_index: KeystoreIndex,
+
+    /// Why the entry could not be loaded,
+    /// at most `MAX_QUARANTINE_REASON_LEN` bytes.
+    pub reason: String,
+
+    /// The raw entry record, exactly as read from the store file.
+    pub data: Arc<Vec<u8>>,
+}
+
+/// The maximum byte length of a `QuarantinedEntry` reason.
+pub const MAX_QUARANTINE_REASON_LEN: usize = 1024;
+
+/// Counters describing a store, see `lair_get_store_stats`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StoreStats {
+    /// The size of the store file on disk, in bytes.
+    pub file_size: u64,
+
+    /// The number of live entries of each type, trashed entries
+    /// excluded, in ascending type order. Types with none are left out.
+    pub entry_counts: Vec<(LairEntryType, u64)>,
+
+    /// The records purged entries left behind in the store file.
+    pub erased_count: u64,
+
+    /// The entries that could not be decoded on load,
+    /// see `lair_list_quarantined`.
+    pub quarantined_count: u64,
+
+    /// The entries in the trash, still taking up a record
+    /// until they are purged.
+    pub trashed_count: u64,
+}
+
+impl StoreStats {
+    /// The number of live entries of `entry_type`.
+    pub fn entry_count(&self, entry_type: LairEntryType) -> u64 {
+        self.entry_counts
+            .iter()
+            .find(|(t, _)| *t == entry_type)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+}
+
+/// The maximum byte length of a `LairEntryInfo` public identifier.
+pub const MAX_ENTRY_PUB_ID_LEN: usize = 64;
+
+/// The minimum byte length of a `lair_find_entries_by_prefix` prefix,
+/// shorter prefixes would match too many entries to be useful.
+pub const MIN_ENTRY_PREFIX_LEN: usize = 4;
+
+/// Error if `prefix` is too short, or too long,
+/// to search with `lair_find_entries_by_prefix`.
+pub fn check_entry_prefix(prefix: &[u8]) -> LairResult<()> {
+    if prefix.len() < MIN_ENTRY_PREFIX_LEN {
+        return Err(format!(
+            "prefix must be at least {} bytes, got {}",
+            MIN_ENTRY_PREFIX_LEN,
+            prefix.len()
+        )
+        .into());
+    }
+    if prefix.len() > MAX_ENTRY_PUB_ID_LEN {
+        return Err(format!(
+            "prefix must be at most {} bytes, got {}",
+            MAX_ENTRY_PUB_ID_LEN,
+            prefix.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The public identifiers searched by `lair_find_entries_by_prefix`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefixKind {
+    /// The pub keys of ed25519 and secp256k1 signing keypairs.
+    SignPubKey = 0x00000001,
+    /// The `CertDigest`s of tls certs.
+    CertDigest = 0x00000002,
+    /// Both signing pub keys and cert digests.
+    Any = 0x00000003,
+}
+
+impl PrefixKind {
+    /// parse a u32 into a PrefixKind enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use PrefixKind::*;
+        Ok(match d {
+            x if x == SignPubKey as u32 => SignPubKey,
+            x if x == CertDigest as u32 => CertDigest,
+            x if x == Any as u32 => Any,
+            _ => return Err("invalid prefix kind".into()),
+        })
+    }
+
+    /// Are the public identifiers of entries of `entry_type` searched?
+    pub fn searches(&self, entry_type: LairEntryType) -> bool {
+        use LairEntryType::*;
+        match self {
+            PrefixKind::SignPubKey => {
+                matches!(entry_type, SignEd25519 | SignSecp256k1)
+            }
+            PrefixKind::CertDigest => entry_type == TlsCert,
+            PrefixKind::Any => {
+                matches!(entry_type, SignEd25519 | SignSecp256k1 | TlsCert)
+            }
+        }
+    }
+}
+
+/// The maximum byte length of an entry tag.
+pub const MAX_ENTRY_TAG_LEN: usize = 256;
+
+/// The maximum number of aliases a single entry may hold.
+pub const MAX_ENTRY_ALIASES: usize = 16;
+
+/// The name of the store clients use until they select another.
+pub const DEFAULT_STORE_NAME: &str = "default";
+
+/// Maximum byte length of a store name.
+/// Names may only contain ascii alphanumerics, `-` and `_`.
+pub const MAX_STORE_NAME_LEN: usize = 64;
+
+/// The maximum byte count of a single `lair_get_random_bytes` call.
+pub const MAX_RANDOM_BYTES_LEN: u32 = 64 * 1024;
+
+/// The maximum number of entries returned in a single
+/// `lair_list_entries_page` response. Larger limits are clamped.
+pub const MAX_LIST_ENTRIES_PAGE_LIMIT: u32 = 1000;
+
+/// The maximum number of indices in a single `lair_get_entry_types` call.
+pub const MAX_GET_ENTRY_TYPES_LEN: u32 = 1000;
+
+/// The maximum number of items in a single bulk entry call,
+/// `lair_erase_entries` or `lair_set_entry_tags`.
+pub const MAX_BULK_ENTRIES_LEN: u32 = 1000;
+
+/// The maximum byte length of an OpenSSH private key file
+/// passed to `sign_ed25519_import_ssh_key`.
+pub const MAX_SSH_KEY_LEN: usize = 16 * 1024;
+
+/// The maximum byte length of an OpenSSH private key passphrase.
+pub const MAX_SSH_KEY_PASSPHRASE_LEN: usize = 1024;
+
+/// The maximum byte length of a message signed whole, leaving room
+/// for the rest of the request in a 16 MiB wire message. Clients refuse
+/// larger messages with `LairError::MessageTooLarge` before sending them,
+/// sign them pre-hashed with `sign_ed25519_ph_sign_by_index`, or
+/// `SigningKeyHandle::sign_stream`, instead.
+pub const MAX_SIGN_MESSAGE_LEN: usize = 16 * 1024 * 1024 - 1024;
+
+/// The byte length of the timestamp prefixed to messages signed with
+/// `SignOptions::include_timestamp`, see `timestamped_message`.
+pub const SIGN_TIMESTAMP_LEN: usize = 8;
+
+/// The bytes a signature made with `SignOptions::include_timestamp`
+/// signs: `timestamp_millis`, the unix time in milliseconds the server
+/// signed at, as `SIGN_TIMESTAMP_LEN` bytes big-endian, followed by
+/// `message` unchanged. The signature is a plain ed25519 signature of
+/// them, anyone holding the pub key, the message and the timestamp can
+/// verify it without lair. See `test_vectors::TIMESTAMPED_ED25519_VECTOR`.
+pub fn timestamped_message(timestamp_millis: u64, message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(SIGN_TIMESTAMP_LEN + message.len());
+    out.extend_from_slice(&timestamp_millis.to_be_bytes());
+    out.extend_from_slice(message);
+    out
+}
+
+/// Refuse messages longer than `MAX_SIGN_MESSAGE_LEN`
+/// with `LairError::MessageTooLarge`.
+pub fn check_sign_message_len(len: usize) -> LairResult<()> {
+    if len > MAX_SIGN_MESSAGE_LEN {
+        return Err(LairError::MessageTooLarge {
+            len,
+            max: MAX_SIGN_MESSAGE_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// A class of requests a connection may be granted.
+/// Connections are granted them per listener and per connection token,
+/// see `Config`, and refused requests they lack with a
+/// `LairError::Forbidden` naming the missing capability.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LairCapability {
+    /// Read public data: entry types, listings, tags,
+    /// public keys and certificates, random bytes.
+    ReadPublic = 0x00000001,
+
+    /// Use private keys without revealing them: signing, attestation,
+    /// crypto box, password hashing and TOTP codes.
+    Sign = 0x00000002,
+
+    /// Create or import entries, and tag them.
+    Create = 0x00000004,
+
+    /// Read private keys, plain or wrapped,
+    /// and TOTP provisioning uris.
+    ExportPrivate = 0x00000008,
+
+    /// Trash, restore and purge entries, self test,
+    /// read the server paths, and list its connections.
+    Admin = 0x00000010,
+
+    /// Be asked for, and answer with, the unlock passphrase of a store.
+    /// Connections without it are never asked.
+    CanUnlock = 0x00000020,
+
+    /// Be sent the audit record of every request the server handles,
+    /// see `LairClientEvent::AuditEvent`.
+    /// Connections without it are never sent one.
+    Audit = 0x00000040,
+}
+
+impl LairCapability {
+    /// Every capability.
+    pub const ALL: [LairCapability; 7] = [
+        LairCapability::ReadPublic,
+        LairCapability::Sign,
+        LairCapability::Create,
+        LairCapability::ExportPrivate,
+        LairCapability::Admin,
+        LairCapability::CanUnlock,
+        LairCapability::Audit,
+    ];
+
+    /// The name of this capability in configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LairCapability::ReadPublic => "read_public",
+            LairCapability::Sign => "sign",
+            LairCapability::Create => "create",
+            LairCapability::ExportPrivate => "export_private",
+            LairCapability::Admin => "admin",
+            LairCapability::CanUnlock => "can_unlock",
+            LairCapability::Audit => "audit",
+        }
+    }
+
+    /// Parse the name of a capability.
+    pub fn parse_name(name: &str) -> LairResult<Self> {
+        Self::ALL
+            .iter()
+            .find(|cap| cap.name() == name)
+            .copied()
+            .ok_or_else(|| format!("invalid capability: {:?}", name).into())
+    }
+}
+
+impl std::fmt::Display for LairCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A set of `LairCapability`s, written as a comma separated list
+/// of capability names, e.g. `read_public,sign`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LairCapabilities(pub u32);
+
+impl LairCapabilities {
+    /// No capabilities.
+    pub const NONE: LairCapabilities = LairCapabilities(0);
+
+    /// Every capability, granted unless configured otherwise.
+    pub const ALL: LairCapabilities = LairCapabilities(0x0000007f);
+
+    /// Is `cap` in this set?
+    pub fn contains(&self, cap: LairCapability) -> bool {
+        self.0 & cap as u32 != 0
+    }
+
+    /// This set, with `cap` added.
+    pub fn with(self, cap: LairCapability) -> Self {
+        Self(self.0 | cap as u32)
+    }
+
+    /// The capabilities in both this set and `other`.
+    pub fn intersect(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// The capabilities in either this set or `other`.
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The capabilities in this set.
+    pub fn iter(&self) -> impl Iterator<Item = LairCapability> + '_ {
+        LairCapability::ALL
+            .iter()
+            .copied()
+            .filter(move |cap| self.contains(*cap))
+    }
+}
+
+impl Default for LairCapabilities {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl From<&[LairCapability]> for LairCapabilities {
+    fn from(caps: &[LairCapability]) -> Self {
+        caps.iter().fold(Self::NONE, |set, cap| set.with(*cap))
+    }
+}
+
+impl std::fmt::Display for LairCapabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.iter().map(|cap| cap.name()).collect::<Vec<_>>();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl std::str::FromStr for LairCapabilities {
+    type Err = LairError;
+
+    fn from_str(s: &str) -> LairResult<Self> {
+        s.split(',')
+            .filter(|name| !name.is_empty())
+            .try_fold(Self::NONE, |set, name| {
+                Ok(set.with(LairCapability::parse_name(name.trim())?))
+            })
+    }
+}
+
+/// A connection a lair server is serving.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LairConnectionInfo {
+    /// Unique (per server process) id of the connection.
+    pub connection_id: u64,
+
+    /// Time since the last request, response or event,
+    /// to the millisecond.
+    pub idle: std::time::Duration,
+
+    /// The number of requests the server is still handling.
+    pub in_flight: u32,
+}
+
+/// Capabilities granted a connection on top of its own, for a time,
+/// see `LairClientApi::lair_request_elevation`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LairElevation {
+    /// The capabilities granted.
+    pub capabilities: LairCapabilities,
+
+    /// Time until they expire, to the millisecond.
+    pub remaining: std::time::Duration,
+}
+
+/// A request a lair server handled, see `LairClientEvent::AuditEvent`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LairAuditRecord {
+    /// The request, named as the `LairClientApi` function making it,
+    /// e.g. `sign_ed25519_sign_by_index`.
+    pub operation: String,
+
+    /// The entry the request names by index, if any.
+    pub keystore_index: Option<KeystoreIndex64>,
+
+    /// The pub key of the entry the request names by pub key, if any.
+    pub pub_key: Option<Arc<Vec<u8>>>,
+
+    /// The connection making the request, see `LairConnectionInfo`.
+    pub connection_id: u64,
+
+    /// The capabilities granted to that connection.
+    pub capabilities: LairCapabilities,
+
+    /// The id the server gave the request, as in its logs and
+    /// `LairError::request_id`. Zero if the server gave it none.
+    pub request_id: u64,
+
+    /// None if the request succeeded, else its error message,
+    /// never naming server paths.
+    pub error: Option<String>,
+}
+
+/// The outcome of the last entropy health check a server ran
+/// before generating key material, see `LairServerInfo::entropy_status`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum EntropyStatus {
+    /// No check has run yet, or the server predates health checks.
+    #[default]
+    Unchecked = 0x00000000,
+    /// The entropy source produced good looking random bytes.
+    Healthy = 0x00000001,
+    /// The entropy source failed, or its output failed the sanity check.
+    Unavailable = 0x00000002,
+}
+
+impl EntropyStatus {
+    /// parse a u32 into an EntropyStatus enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use EntropyStatus::*;
+        Ok(match d {
+            x if x == Unchecked as u32 => Unchecked,
+            x if x == Healthy as u32 => Healthy,
+            x if x == Unavailable as u32 => Unavailable,
+            _ => return Err("invalid entropy status".into()),
+        })
+    }
+}
+
+/// Get information about the server we are connected to.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LairServerInfo {
+    /// Server name / identifier.
+    pub name: String,
+
+    /// Server version.
+    pub version: String,
+
+    /// The server identity ed25519 pub key, created when the
+    /// keystore is initialized. Attestations are signed by it.
+    /// Empty if the server has no identity,
+    /// or hides it while deep locked.
+    pub id_pub_key: SignEd25519PubKey,
+
+    /// True until a client has supplied the unlock passphrase.
+    pub is_locked: bool,
+
+    /// The `spec::WIRE_PROTOCOL_VERSION` of the server,
+    /// `0` for servers predating it.
+    pub wire_protocol_version: u32,
+
+    /// The last entropy health check status of the server.
+    pub entropy_status: EntropyStatus,
+}
+
+/// The maximum byte length of an attestation challenge.
+pub const MAX_ATTEST_CHALLENGE_LEN: usize = 256;
+
+/// A statement, signed by a server identity key,
+/// that the server holds the entry with this public identifier.
+/// See `LairClientApi::lair_attest_entry`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attestation {
+    /// The identity pub key of the attesting server.
+    pub server_id_pub_key: SignEd25519PubKey,
+
+    /// The type of the attested entry.
+    pub entry_type: LairEntryType,
+
+    /// The public identifier of the attested entry,
+    /// as in `LairEntryInfo::pub_id`.
+    pub entry_pub_id: Arc<Vec<u8>>,
+
+    /// The caller supplied challenge.
+    pub challenge: Arc<Vec<u8>>,
+
+    /// When the attestation was made, in seconds since the unix epoch.
+    pub timestamp: u64,
+
+    /// The server identity signature over `Attestation::signed_bytes`.
+    pub signature: SignEd25519Signature,
+}
+
+impl Attestation {
+    const CONTEXT: &'static [u8] = b"lair-attestation-v1";
+
+    /// The canonical bytes covered by the signature:
+    /// context string, server id pub key, entry type (u32 LE),
+    /// then length (u64 LE) prefixed entry pub id and challenge,
+    /// and the timestamp (u64 LE).
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            Self::CONTEXT.len()
+                + self.server_id_pub_key.len()
+                + 4
+                + 8
+                + self.entry_pub_id.len()
+                + 8
+                + self.challenge.len()
+                + 8,
+        );
+        out.extend_from_slice(Self::CONTEXT);
+        out.extend_from_slice(&self.server_id_pub_key);
+        out.extend_from_slice(&(self.entry_type as u32).to_le_bytes());
+        out.extend_from_slice(&(self.entry_pub_id.len() as u64).to_le_bytes());
+        out.extend_from_slice(&self.entry_pub_id);
+        out.extend_from_slice(&(self.challenge.len() as u64).to_le_bytes());
+        out.extend_from_slice(&self.challenge);
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out
+    }
+
+    /// Verify, offline, that this attestation was signed by the
+    /// server identity `server_id_pub_key`, in response to `challenge`.
+    /// Callers must obtain `server_id_pub_key` from a source they
+    /// trust, checking the embedded key alone proves nothing.
+    pub fn verify(
+        &self,
+        server_id_pub_key: &SignEd25519PubKey,
+        challenge: &[u8],
+    ) -> bool {
+        if &self.server_id_pub_key != server_id_pub_key
+            || self.challenge.as_slice() != challenge
+        {
+            return false;
+        }
+        server_id_pub_key.verify_pure(&self.signed_bytes(), &self.signature)
+    }
+}
+
+/// The byte length of the nonces clients send the server to prove
+/// its identity on, see `LairClientApi::lair_prove_server_identity`.
+pub const SERVER_IDENTITY_NONCE_LEN: usize = 32;
+
+/// A statement, signed by a server identity key, of the wire protocol
+/// version and server version the server runs, in response to a client
+/// nonce. See `LairClientApi::lair_prove_server_identity`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerIdentityProof {
+    /// The identity pub key of the server.
+    pub server_id_pub_key: SignEd25519PubKey,
+
+    /// The `spec::WIRE_PROTOCOL_VERSION` of the server.
+    pub wire_protocol_version: u32,
+
+    /// The server version, as in `LairServerInfo::version`.
+    pub server_version: String,
+
+    /// The client supplied nonce.
+    pub nonce: Arc<Vec<u8>>,
+
+    /// The server identity signature over
+    /// `ServerIdentityProof::signed_bytes`.
+    pub signature: SignEd25519Signature,
+}
+
+impl ServerIdentityProof {
+    const CONTEXT: &'static [u8] = b"lair-server-identity-v1";
+
+    /// The canonical bytes covered by the signature:
+    /// context string, server id pub key, wire protocol version (u32 LE),
+    /// then length (u64 LE) prefixed server version and nonce.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            Self::CONTEXT.len()
+                + self.server_id_pub_key.len()
+                + 4
+                + 8
+                + self.server_version.len()
+                + 8
+                + self.nonce.len(),
+        );
+        out.extend_from_slice(Self::CONTEXT);
+        out.extend_from_slice(&self.server_id_pub_key);
+        out.extend_from_slice(&self.wire_protocol_version.to_le_bytes());
+        out.extend_from_slice(
+            &(self.server_version.len() as u64).to_le_bytes(),
+        );
+        out.extend_from_slice(self.server_version.as_bytes());
+        out.extend_from_slice(&(self.nonce.len() as u64).to_le_bytes());
+        out.extend_from_slice(&self.nonce);
+        out
+    }
+
+    /// Verify that this proof was signed by the server identity
+    /// `server_id_pub_key`, in response to `nonce`. As with
+    /// `Attestation::verify`, `server_id_pub_key` must come from
+    /// a source the caller trusts.
+    pub fn verify(
+        &self,
+        server_id_pub_key: &SignEd25519PubKey,
+        nonce: &[u8],
+    ) -> bool {
+        if &self.server_id_pub_key != server_id_pub_key
+            || self.nonce.as_slice() != nonce
+        {
+            return false;
+        }
+        server_id_pub_key.verify_pure(&self.signed_bytes(), &self.signature)
+    }
+}
+
+/// A statement, signed by both keypairs, that an ed25519 keypair
+/// delegates to the keypair replacing it.
+/// See `LairClientApi::sign_ed25519_rotate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationRecord {
+    /// The pub key of the replaced keypair.
+    pub old_pub_key: SignEd25519PubKey,
 
-    /// Server version.
-    pub version: String,
+    /// The pub key of the replacing keypair.
+    pub new_pub_key: SignEd25519PubKey,
+
+    /// When the delegation was made, in seconds since the unix epoch.
+    pub timestamp: u64,
+
+    /// The old keypair's signature over `RotationRecord::signed_bytes`.
+    pub old_signature: SignEd25519Signature,
+
+    /// The new keypair's signature over `RotationRecord::signed_bytes`.
+    pub new_signature: SignEd25519Signature,
+}
+
+impl RotationRecord {
+    const CONTEXT: &'static [u8] = b"lair-rotation-v1";
+
+    /// The canonical delegation statement covered by both signatures:
+    /// context string, old pub key, new pub key,
+    /// then the timestamp (u64 LE).
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            Self::CONTEXT.len()
+                + self.old_pub_key.len()
+                + self.new_pub_key.len()
+                + 8,
+        );
+        out.extend_from_slice(Self::CONTEXT);
+        out.extend_from_slice(&self.old_pub_key);
+        out.extend_from_slice(&self.new_pub_key);
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out
+    }
+
+    /// Verify, offline, that both keypairs signed this delegation.
+    /// It proves continuity only to callers that already trust
+    /// `old_pub_key`.
+    pub fn verify(&self) -> bool {
+        let signed = self.signed_bytes();
+        self.old_pub_key.verify_pure(&signed, &self.old_signature)
+            && self.new_pub_key.verify_pure(&signed, &self.new_signature)
+    }
+}
+
+/// Where a running lair server keeps its on-disk state,
+/// and the url it accepts connections on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LairServerPaths {
+    /// The lair data directory.
+    pub root_path: std::path::PathBuf,
+
+    /// The lair store file.
+    pub store_path: std::path::PathBuf,
+
+    /// The url the server is bound to.
+    pub connection_url: crate::LairConnectionUrl,
 }
 
+#[cfg(feature = "full")]
 ghost_actor::ghost_chan! {
     /// Lair Client Actor Api.
     pub chan LairClientApi<LairError> {
         /// Get lair server info.
         fn lair_get_server_info() -> LairServerInfo;
 
-        /// Get the highest entry index.
-        /// Note, some entries my be stubs / erased values.
-        fn lair_get_last_entry_index() -> KeystoreIndex;
+        /// Get the capabilities granted to this connection, those it
+        /// holds elevated included, see `lair_request_elevation`.
+        /// Requests needing others fail with `LairError::Forbidden`.
+        /// In-process keystores grant every capability.
+        fn lair_get_capabilities() -> LairCapabilities;
+
+        /// Grant this connection `capabilities` on top of its own, e.g.
+        /// to export a seed, for `duration`, after which they expire on
+        /// their own. Only as far as the server allows, see
+        /// `ConfigBuilder::set_elevation`, else this fails with
+        /// `LairError::Forbidden`. Replaces any elevation already held.
+        /// In-process keystores, granting every capability, refuse it.
+        fn lair_request_elevation(
+            capabilities: LairCapabilities,
+            duration: std::time::Duration,
+        ) -> LairElevation;
+
+        /// The elevation this connection holds, None if none,
+        /// or if it expired.
+        fn lair_get_elevation() -> Option<LairElevation>;
+
+        /// Drop the elevation this connection holds, if any, before
+        /// it expires.
+        fn lair_drop_elevation() -> ();
+
+        /// List the connections the server is serving,
+        /// this one included. In-process keystores have none.
+        fn lair_list_connections() -> Vec<LairConnectionInfo>;
+
+        /// Re-read the server's settings file, see
+        /// `ConfigBuilder::set_settings_path`, applying the settings that
+        /// may change while it runs, see `RuntimeSettings`. Fails if any
+        /// is invalid, applying none, or if the server has no settings
+        /// file. In-process keystores have none.
+        fn lair_reload_settings() -> ();
+
+        /// Only send this connection events of the given `kinds`.
+        /// Connections start subscribed to every kind (`EventKind::ALL`),
+        /// so clients unaware of filters miss nothing. The server never
+        /// queues events of other kinds for the connection: those awaiting
+        /// an answer, e.g. `request_unlock_passphrase`, fail right away.
+        /// Events queued before the call are filtered too.
+        /// In-process keystores send every event.
+        fn lair_set_event_filter(kinds: Vec<EventKind>) -> ();
+
+        /// Get the paths of the server's on-disk state.
+        /// Servers configured with `hide_paths` respond
+        /// with a `LairError::Forbidden` error.
+        fn lair_get_server_paths() -> LairServerPaths;
+
+        /// Export the device secret the server's lair directory is bound
+        /// to, see `device_secret`, e.g. to move the directory to another
+        /// device. Fails with `LairError::KeystoreLocked` unless the
+        /// selected store is unlocked, and fails if the directory
+        /// is not bound to a device.
+        fn lair_export_device_secret() -> Arc<Vec<u8>>;
+
+        /// Switch this connection to the named store. Every later request
+        /// on the connection, and every keystore index it returns, is
+        /// scoped to that store. Using an index from another store fails
+        /// with `LairError::WrongStore`. If the store is locked, the
+        /// server requests its passphrase, as it does for the default
+        /// store on connect.
+        fn lair_select_store(name: String) -> ();
+
+        /// Get the highest entry index, or None if the selected store has
+        /// never held an entry. Lower indices may be stubs / erased values,
+        /// see `lair_entry_indices_stream` to visit only live entries.
+        fn lair_get_last_entry_index() -> Option<KeystoreIndex>;
+
+        /// As `lair_get_last_entry_index`, as a `KeystoreIndex64`.
+        fn lair_get_last_entry_index_64() -> Option<KeystoreIndex64>;
+
+        /// Get the number of entries in the selected store.
+        fn lair_get_entry_count() -> u64;
+
+        /// Get counters describing the selected store, for capacity
+        /// monitoring. The server keeps them up to date as entries are
+        /// created, trashed, restored and purged, so they are cheap
+        /// to poll. Servers predating `spec::STORE_STATS_VERSION`
+        /// fail this request.
+        fn lair_get_store_stats() -> StoreStats;
+
+        /// List up to `limit` entries with keystore index >= `start`,
+        /// in ascending index order. Also returns the `start` to use
+        /// for the next page, or None if there are no further entries.
+        /// Indices are never reused, so paging will not return duplicate
+        /// or omit pre-existing entries, even while entries are created.
+        fn lair_list_entries_page(
+            start: KeystoreIndex,
+            limit: u32,
+        ) -> (Vec<LairEntryInfo>, Option<KeystoreIndex>);
 
-        /// Get the entry type for a given index.
+        /// Get the entry type for a given index. Trashed entries, and
+        /// imports not activated yet, fail with `LairError::EntryTrashed`
+        /// and `LairError::EntryNotActivated`, where a batch from
+        /// `lair_get_entry_types` still reports their type.
         fn lair_get_entry_type(
             keystore_index: KeystoreIndex,
         ) -> LairEntryType;
 
+        /// As `lair_get_entry_type`, by `KeystoreIndex64`. Against
+        /// servers predating `spec::KEYSTORE_INDEX64_VERSION`, indexes
+        /// above `u32::MAX` fail with `LairError::IndexOutOfRange`.
+        fn lair_get_entry_type_64(
+            keystore_index: KeystoreIndex64,
+        ) -> LairEntryType;
+
+        /// Get the entry types for a batch of indices, in request order,
+        /// duplicates included. Indices with no entry in the selected
+        /// store (erased, out of range, or from another store) are
+        /// `LairEntryType::Invalid`, rather than failing the batch.
+        /// At most `MAX_GET_ENTRY_TYPES_LEN` indices may be requested.
+        fn lair_get_entry_types(
+            indices: Vec<KeystoreIndex>,
+        ) -> Vec<(KeystoreIndex, LairEntryType)>;
+
+        /// Get `len` bytes from the server's cryptographically secure
+        /// random number generator. `len` may not exceed
+        /// `MAX_RANDOM_BYTES_LEN`.
+        fn lair_get_random_bytes(len: u32) -> Arc<Vec<u8>>;
+
+        /// Get a new random (version 4) uuid generated from the
+        /// server's cryptographically secure random number generator.
+        fn lair_new_uuid() -> [u8; 16];
+
+        /// Set (or with `None` clear) the tag of an entry.
+        /// Tags are unique, setting a tag already held by another
+        /// entry moves it to this entry, so a tag can be rotated
+        /// onto a new key in a single call.
+        fn lair_set_entry_tag(
+            keystore_index: KeystoreIndex,
+            tag: Option<String>,
+        ) -> ();
+
+        /// Resolve a tag, or an alias, to the index and type of the
+        /// entry holding it. Returns `LairError::TagNotFound` if no entry
+        /// holds the tag or alias.
+        fn lair_get_entry_by_tag(
+            tag: String,
+        ) -> (KeystoreIndex, LairEntryType);
+
+        /// Find the entries whose public identifier, of a `kind` searched,
+        /// starts with `prefix`, e.g. to look up a truncated pub key.
+        /// All matches are returned, in ascending index order, trashed
+        /// entries excluded. The prefix must be `MIN_ENTRY_PREFIX_LEN`
+        /// to `MAX_ENTRY_PUB_ID_LEN` bytes.
+        fn lair_find_entries_by_prefix(
+            prefix: Arc<Vec<u8>>,
+            kind: PrefixKind,
+        ) -> Vec<(KeystoreIndex, LairEntryType)>;
+
+        /// Add an alias to an entry, resolvable like its tag, e.g. so
+        /// an old identifier keeps resolving during a migration.
+        /// Entries may hold up to `MAX_ENTRY_ALIASES` aliases.
+        /// Aliases and tags share one namespace: a name already held,
+        /// as a tag or as an alias, is `LairError::TagInUse`.
+        fn lair_add_entry_alias(
+            keystore_index: KeystoreIndex,
+            alias: String,
+        ) -> ();
+
+        /// Remove an alias. Returns `LairError::TagNotFound`
+        /// if no entry holds the alias.
+        fn lair_remove_entry_alias(alias: String) -> ();
+
+        /// Move an entry to the trash. The entry keeps its key material,
+        /// but any use of it - signing, export, lookups by index, pub key,
+        /// sni or tag - fails with `LairError::EntryTrashed` until it is
+        /// restored with `lair_restore_entry`, or purged for good with
+        /// `lair_purge_trashed`. Trashed entries are still listed,
+        /// flagged as `LairEntryInfo::trashed`.
+        fn lair_trash_entry(keystore_index: KeystoreIndex) -> ();
+
+        /// Take an entry back out of the trash.
+        /// Purged entries can no longer be restored.
+        fn lair_restore_entry(keystore_index: KeystoreIndex) -> ();
+
+        /// Activate the imported entry at `keystore_index`. Entries
+        /// imported from outside lair - seeds, private keys, totp secrets,
+        /// unwrapped entries - land inactive, and any use of them fails
+        /// with `LairError::EntryNotActivated` until they are activated,
+        /// unless the server is configured with
+        /// `ConfigBuilder::set_auto_activate_imports`. Entries created from
+        /// entropy are active right away. Activating an active entry
+        /// does nothing. Servers predating `spec::ACTIVATION_VERSION`
+        /// activate every entry on import, and fail this request.
+        fn lair_activate_entry(keystore_index: KeystoreIndex) -> ();
+
+        /// Permanently erase the entries trashed at least `older_than`
+        /// ago, returning how many were erased. Their key material is
+        /// overwritten, and their tags and aliases are dropped.
+        /// A signature entry bound to a tls cert stays in the trash
+        /// until the cert is purged too.
+        fn lair_purge_trashed(older_than: std::time::Duration) -> u64;
+
+        /// Permanently erase the entries at `indices`, trashed or not,
+        /// as `lair_purge_trashed` does, in a single store write.
+        /// Each entry succeeds or fails on its own: the results follow
+        /// request order, a failing entry, e.g. one not found or still
+        /// bound to a tls cert, leaves the others erased. Certs erased
+        /// in the same call no longer bind their signing entry.
+        /// At most `MAX_BULK_ENTRIES_LEN` indices may be requested.
+        fn lair_erase_entries(
+            indices: Vec<KeystoreIndex>,
+        ) -> Vec<LairResult<()>>;
+
+        /// Set the tags of many entries, as `lair_set_entry_tag` does,
+        /// in a single store write. Tags are set in request order, so a
+        /// later item may move a tag an earlier one set. Each item
+        /// succeeds or fails on its own, the results follow request
+        /// order. At most `MAX_BULK_ENTRIES_LEN` items may be requested.
+        fn lair_set_entry_tags(
+            tags: Vec<(KeystoreIndex, String)>,
+        ) -> Vec<LairResult<()>>;
+
+        /// List the entries of the selected store that could not be
+        /// decoded as it was loaded, in ascending index order. The store
+        /// keeps serving its other entries, these report
+        /// `LairEntryType::Invalid`, and their raw records are kept
+        /// for manual recovery. Empty for a store loaded with
+        /// `ConfigBuilder::set_strict_load`, which fails instead.
+        fn lair_list_quarantined() -> Vec<QuarantinedEntry>;
+
+        /// Run the server's known-answer self-test of its crypto
+        /// primitives, as it does before it starts. Fails naming the
+        /// first primitive that gave a wrong answer. The vectors are
+        /// in `crate::test_vectors`.
+        fn lair_self_test() -> ();
+
+        /// Have the server identity key attest that this keystore holds
+        /// the entry at `keystore_index`. The caller supplied `challenge`
+        /// (non-empty, at most `MAX_ATTEST_CHALLENGE_LEN` bytes) is signed
+        /// into the attestation, protecting against replays.
+        /// Entries without a public identifier cannot be attested.
+        fn lair_attest_entry(
+            keystore_index: KeystoreIndex,
+            challenge: Arc<Vec<u8>>,
+        ) -> Attestation;
+
+        /// Have the server identity key sign the server's wire protocol
+        /// version and server version, along with the caller supplied
+        /// `nonce` (non-empty, at most `MAX_ATTEST_CHALLENGE_LEN` bytes),
+        /// which keeps captured proofs from being replayed. Clients
+        /// configured with `ConfigBuilder::set_expected_server_identity`
+        /// check it as they connect. Needs no capability, but fails
+        /// while the server is deep locked.
+        fn lair_prove_server_identity(
+            nonce: Arc<Vec<u8>>,
+        ) -> ServerIdentityProof;
+
+        /// Wrap the secret material of the entry at `target_index` to
+        /// an (offline) recovery x25519 pub key, server-side, so the
+        /// plaintext never crosses the wire. The result is a versioned
+        /// sealed box container, see `lair_unwrap_entry`.
+        /// Hardware backed keypairs fail with `LairError::ExportForbidden`.
+        fn lair_wrap_entry(
+            target_index: KeystoreIndex,
+            recipient_pub_key: X25519PubKey,
+        ) -> Arc<Vec<u8>>;
+
+        /// Open a `lair_wrap_entry` container with the x25519 keypair at
+        /// `recipient_index`, and import the wrapped entry as a new entry,
+        /// preserving its type. Tampered containers, or containers
+        /// wrapped to another key, fail with
+        /// `LairError::AuthenticationFailed`.
+        /// If the store already holds the same material, its existing
+        /// index is returned with the `already_existed` flag set.
+        fn lair_unwrap_entry(
+            recipient_index: KeystoreIndex,
+            wrapped: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, LairEntryType, bool);
+
         /// Create a new self-signed tls certificate.
         fn tls_cert_new_self_signed_from_entropy(
             options: TlsCertOptions,
         ) -> (KeystoreIndex, CertSni, CertDigest);
 
+        /// Create a new tls certificate for the keypair of the sign ed25519
+        /// entry at `keystore_index`, self-signed with that same key.
+        /// The new cert entry references the signing entry, rather than
+        /// copying its key. Only `TlsCertAlg::PkcsEd25519` is supported.
+        fn tls_cert_new_self_signed_from_sign_key(
+            keystore_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
         /// Get tls cert info by keystore index.
         fn tls_cert_get(
             keystore_index: KeystoreIndex,
         ) -> (CertSni, CertDigest);
 
+        /// Get tls cert info by keystore index, with the algorithm of
+        /// its key. Against servers predating
+        /// `spec::TLS_CERT_INFO_VERSION`, clients fetch the certificate
+        /// and read the algorithm from it.
+        fn tls_cert_get_info(
+            keystore_index: KeystoreIndex,
+        ) -> (CertSni, CertDigest, TlsCertAlg);
+
         /// Fetch the certificate by entry index.
         fn tls_cert_get_cert_by_index(
             keystore_index: KeystoreIndex,
@@ -252,11 +2511,37 @@ ghost_actor::ghost_chan! {
             cert_digest: CertDigest,
         ) -> Cert;
 
+        /// Get the digest of the tls cert at `keystore_index`, computed
+        /// with `digest_alg`. `DigestAlg::Blake2b` is its `CertDigest`.
+        fn tls_cert_get_digest(
+            keystore_index: KeystoreIndex,
+            digest_alg: DigestAlg,
+        ) -> Arc<Vec<u8>>;
+
+        /// Fetch the certificate by a digest of any `DigestAlg`,
+        /// e.g. the sha-256 fingerprint it is pinned by elsewhere.
+        fn tls_cert_get_cert_by_typed_digest(
+            digest_alg: DigestAlg,
+            digest: Arc<Vec<u8>>,
+        ) -> Cert;
+
         /// Fetch the certificate by sni.
         fn tls_cert_get_cert_by_sni(
             cert_sni: CertSni,
         ) -> Cert;
 
+        /// Resolve a certificate digest to the keystore index of its entry.
+        /// Returns `LairError::EntryNotFound` if no tls cert has the digest.
+        fn tls_cert_resolve_by_digest(
+            cert_digest: CertDigest,
+        ) -> KeystoreIndex;
+
+        /// Resolve a certificate sni to the keystore index of its entry.
+        /// Returns `LairError::EntryNotFound` if no tls cert has the sni.
+        fn tls_cert_resolve_by_sni(
+            cert_sni: CertSni,
+        ) -> KeystoreIndex;
+
         /// Fetch the certificate private key by entry index.
         fn tls_cert_get_priv_key_by_index(
             keystore_index: KeystoreIndex,
@@ -272,28 +2557,899 @@ ghost_actor::ghost_chan! {
             cert_sni: CertSni,
         ) -> CertPrivKey;
 
+        /// Resolve a tag and fetch the full tls cert bundle
+        /// (sni, digest, cert, priv key) in one atomic server-side step.
+        fn tls_cert_get_bundle_by_tag(
+            tag: String,
+        ) -> (CertSni, CertDigest, Cert, CertPrivKey);
+
         /// Create a new signature ed25519 keypair from entropy.
+        /// Same as `sign_ed25519_new_from_entropy_with_options`
+        /// with default options.
         fn sign_ed25519_new_from_entropy(
         ) -> (KeystoreIndex, SignEd25519PubKey);
 
+        /// Create a new signature ed25519 keypair from entropy,
+        /// configured by `options`. Servers predating
+        /// `spec::SIGN_KEY_OPTIONS_VERSION` only create keypairs
+        /// with default options.
+        fn sign_ed25519_new_from_entropy_with_options(
+            options: SignKeyOptions,
+        ) -> (KeystoreIndex, SignEd25519PubKey);
+
+        /// Import the signature ed25519 keypair of a 32 byte `seed`,
+        /// configured by `options`. A key the store already holds is not
+        /// imported again, its existing index is returned with the
+        /// `already_existed` flag set, and its options are left alone.
+        fn sign_ed25519_new_from_seed_with_options(
+            seed: [u8; 32],
+            options: SignKeyOptions,
+        ) -> (KeystoreIndex, SignEd25519PubKey, bool);
+
+        /// Get the options of the ed25519 keypair at `keystore_index`,
+        /// with the tag it currently holds. Keypairs created without
+        /// options, or held in a hardware token, have default options.
+        fn sign_ed25519_get_options(
+            keystore_index: KeystoreIndex,
+        ) -> SignKeyOptions;
+
+        /// Replace the ed25519 keypair at `old_keystore_index` with a new
+        /// one, created with `options.key_options`. Both sign a
+        /// `RotationRecord` delegating from the old pub key to the new,
+        /// for the caller to publish, and the new entry links back to
+        /// the old one in `LairEntryInfo::rotated_from`. Signing with the
+        /// old keypair follows its options, as any other signature.
+        fn sign_ed25519_rotate(
+            old_keystore_index: KeystoreIndex,
+            options: RotateKeyOptions,
+        ) -> (KeystoreIndex, RotationRecord);
+
+        /// The 32 byte seed of the ed25519 keypair at `keystore_index`,
+        /// as taken by `sign_ed25519_new_from_seed_with_options`.
+        /// Requires `LairCapability::ExportPrivate`. Hardware backed
+        /// keypairs, keypairs created not exportable, and every keypair
+        /// of a server configured with `set_forbid_seed_export`, fail
+        /// with `LairError::ExportForbidden`.
+        fn sign_ed25519_export_seed(
+            keystore_index: KeystoreIndex,
+        ) -> [u8; 32];
+
+        /// Create a new signature ed25519 keypair, with its private key
+        /// held as `backing` says. `KeyBacking::Software` is
+        /// `sign_ed25519_new_from_entropy`. `KeyBacking::Hardware` fails
+        /// unless the server is configured with a hardware token.
+        fn sign_ed25519_new(
+            backing: KeyBacking,
+        ) -> (KeystoreIndex, SignEd25519PubKey);
+
+        /// Import the ed25519 keypair of an OpenSSH private key file,
+        /// as written by `ssh-keygen`, decrypting it with `passphrase`
+        /// if it is encrypted. A wrong passphrase fails with
+        /// `LairError::AuthenticationFailed`, other key types are refused.
+        /// The new entry is tagged with the key comment, if it has one
+        /// that is a valid tag. A key the store already holds is not
+        /// imported again, its existing index is returned with the
+        /// `already_existed` flag set, and its tags are left alone.
+        fn sign_ed25519_import_ssh_key(
+            key: Arc<Vec<u8>>,
+            passphrase: Option<String>,
+        ) -> (KeystoreIndex, SignEd25519PubKey, bool);
+
         /// Get ed25519 keypair info by keystore index.
         fn sign_ed25519_get(
             keystore_index: KeystoreIndex,
         ) -> SignEd25519PubKey;
 
+        /// Resolve an ed25519 pub key to the keystore index of its keypair.
+        /// Returns `LairError::EntryNotFound` if no ed25519 keypair has
+        /// the pub key. Should the store hold duplicates, imported
+        /// before duplicate detection, the lowest index wins, as it does
+        /// for every lookup by pub key, cert digest or sni.
+        fn sign_ed25519_resolve_by_pub_key(
+            pub_key: SignEd25519PubKey,
+        ) -> KeystoreIndex;
+
         /// Generate a signature for message by keystore index.
+        /// Messages longer than `MAX_SIGN_MESSAGE_LEN` fail with
+        /// `LairError::MessageTooLarge`, as do those of the by pub key
+        /// and by tag variants.
         fn sign_ed25519_sign_by_index(
             keystore_index: KeystoreIndex,
             message: Arc<Vec<u8>>,
         ) -> SignEd25519Signature;
 
+        /// As `sign_ed25519_sign_by_index`, configured by `options`.
+        /// Returns the signature, and the unix time in milliseconds
+        /// it signed at, if `SignOptions::include_timestamp`. Against
+        /// servers predating `spec::SIGN_OPTIONS_VERSION`, clients sign
+        /// with the default options as `sign_ed25519_sign_by_index`,
+        /// and refuse any other.
+        fn sign_ed25519_sign_by_index_with_options(
+            keystore_index: KeystoreIndex,
+            message: Arc<Vec<u8>>,
+            options: SignOptions,
+        ) -> (SignEd25519Signature, Option<u64>);
+
+        /// As `sign_ed25519_sign_by_index`, by `KeystoreIndex64`, as
+        /// `lair_get_entry_type_64` against older servers.
+        fn sign_ed25519_sign_by_index_64(
+            keystore_index: KeystoreIndex64,
+            message: Arc<Vec<u8>>,
+        ) -> SignEd25519Signature;
+
         /// Generate a signature for message by signature pub key.
         fn sign_ed25519_sign_by_pub_key(
             pub_key: SignEd25519PubKey,
             message: Arc<Vec<u8>>,
         ) -> SignEd25519Signature;
+
+        /// Generate a signature for message by keystore index,
+        /// returning the pub key of the keypair that signed it, in
+        /// the same round trip. Against servers predating
+        /// `spec::SIGN_WITH_PUB_KEY_VERSION`, clients sign, fetch the
+        /// pub key, then check the signature verifies under it.
+        fn sign_ed25519_sign_by_index_v2(
+            keystore_index: KeystoreIndex,
+            message: Arc<Vec<u8>>,
+        ) -> (SignEd25519PubKey, SignEd25519Signature);
+
+        /// Generate a signature for message by signature pub key,
+        /// returning the pub key as the server holds it.
+        /// Against older servers, as `sign_ed25519_sign_by_index_v2`,
+        /// clients echo the given pub key.
+        fn sign_ed25519_sign_by_pub_key_v2(
+            pub_key: SignEd25519PubKey,
+            message: Arc<Vec<u8>>,
+        ) -> (SignEd25519PubKey, SignEd25519Signature);
+
+        /// Resolve a tag and sign with the keypair holding it,
+        /// atomically server-side. Returns the pub key that was
+        /// actually used, so callers can record it.
+        fn sign_ed25519_sign_by_tag(
+            tag: String,
+            message: Arc<Vec<u8>>,
+        ) -> (SignEd25519PubKey, SignEd25519Signature);
+
+        /// Generate an Ed25519ph signature of the 64 byte sha-512 `prehash`
+        /// of a message, under a `context` of at most
+        /// `ed25519ph::MAX_CONTEXT_LEN` bytes, for messages too large to
+        /// sign whole. Verify with `SignEd25519PubKey::verify_ph`.
+        fn sign_ed25519_ph_sign_by_index(
+            keystore_index: KeystoreIndex,
+            prehash: [u8; ed25519ph::PREHASH_LEN],
+            context: Arc<Vec<u8>>,
+        ) -> SignEd25519Signature;
+
+        /// Create a new secp256k1 signature keypair from entropy.
+        fn sign_secp256k1_new_from_entropy(
+        ) -> (KeystoreIndex, SignSecp256k1PubKey);
+
+        /// Import a secp256k1 signature keypair from its 32 byte
+        /// big-endian private key. Keys that are zero, or not less than
+        /// the curve order, are rejected. A key the store already holds
+        /// is not imported again, its existing index is returned with
+        /// the `already_existed` flag set.
+        fn sign_secp256k1_new_from_seed(
+            seed: [u8; 32],
+        ) -> (KeystoreIndex, SignSecp256k1PubKey, bool);
+
+        /// Get secp256k1 keypair info by keystore index.
+        fn sign_secp256k1_get(
+            keystore_index: KeystoreIndex,
+        ) -> SignSecp256k1PubKey;
+
+        /// Sign a 32 byte message hash by keystore index. Returns the
+        /// deterministic (RFC 6979) low-s signature and its recovery id.
+        fn sign_secp256k1_sign_by_index(
+            keystore_index: KeystoreIndex,
+            message_hash: [u8; 32],
+        ) -> (SignSecp256k1Signature, u8);
+
+        /// Create a new signature ed448 keypair from entropy.
+        fn sign_ed448_new_from_entropy() -> (KeystoreIndex, SignEd448PubKey);
+
+        /// Get ed448 keypair info by keystore index.
+        fn sign_ed448_get(keystore_index: KeystoreIndex) -> SignEd448PubKey;
+
+        /// Generate an RFC 8032 Ed448 signature, with an empty context,
+        /// for message by keystore index. Messages longer than
+        /// `MAX_SIGN_MESSAGE_LEN` fail with `LairError::MessageTooLarge`,
+        /// as do those of the by pub key variant.
+        fn sign_ed448_sign_by_index(
+            keystore_index: KeystoreIndex,
+            message: Arc<Vec<u8>>,
+        ) -> SignEd448Signature;
+
+        /// Generate an Ed448 signature for message by signature pub key.
+        fn sign_ed448_sign_by_pub_key(
+            pub_key: SignEd448PubKey,
+            message: Arc<Vec<u8>>,
+        ) -> SignEd448Signature;
+
+        /// Create a new bls12-381 signature keypair from entropy.
+        fn sign_bls_new_from_entropy() -> (KeystoreIndex, SignBlsPubKey);
+
+        /// Get bls keypair info by keystore index.
+        fn sign_bls_get(keystore_index: KeystoreIndex) -> SignBlsPubKey;
+
+        /// Generate a bls signature for message by keystore index, under
+        /// the domain separation tag `dst`, e.g. `bls::DST_POP`.
+        /// Messages longer than `MAX_SIGN_MESSAGE_LEN` fail with
+        /// `LairError::MessageTooLarge`.
+        fn sign_bls_sign_by_index(
+            keystore_index: KeystoreIndex,
+            message: Arc<Vec<u8>>,
+            dst: Vec<u8>,
+        ) -> SignBlsSignature;
+
+        /// Create a new sr25519 signature keypair from entropy.
+        fn sign_sr25519_new_from_entropy(
+        ) -> (KeystoreIndex, SignSr25519PubKey);
+
+        /// Import an sr25519 signature keypair from its 32 byte mini
+        /// secret key (the substrate "seed"). A key the store already
+        /// holds is not imported again, its existing index is returned
+        /// with the `already_existed` flag set.
+        fn sign_sr25519_new_from_seed(
+            seed: [u8; 32],
+        ) -> (KeystoreIndex, SignSr25519PubKey, bool);
+
+        /// Get sr25519 keypair info by keystore index.
+        fn sign_sr25519_get(
+            keystore_index: KeystoreIndex,
+        ) -> SignSr25519PubKey;
+
+        /// Generate an sr25519 signature for message by keystore index,
+        /// in the signing context `context`, e.g. `b"substrate"`.
+        /// Messages longer than `MAX_SIGN_MESSAGE_LEN` fail with
+        /// `LairError::MessageTooLarge`.
+        fn sign_sr25519_sign_by_index(
+            keystore_index: KeystoreIndex,
+            message: Arc<Vec<u8>>,
+            context: Vec<u8>,
+        ) -> SignSr25519Signature;
+
+        /// Create a new x25519 key agreement keypair from entropy.
+        fn x25519_new_from_entropy() -> (KeystoreIndex, X25519PubKey);
+
+        /// Get x25519 keypair info by keystore index.
+        fn x25519_get(keystore_index: KeystoreIndex) -> X25519PubKey;
+
+        /// Encrypt data from the x25519 keypair at `sender_x25519_index`
+        /// to the holder of an ed25519 signature identity. The recipient
+        /// pub key is converted to x25519 server-side, invalid / low order
+        /// keys are rejected with `LairError::InvalidKeyConversion`.
+        fn crypto_box_to_sign_pub_key(
+            sender_x25519_index: KeystoreIndex,
+            recipient_sign_pub_key: SignEd25519PubKey,
+            data: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Open data boxed by `crypto_box_to_sign_pub_key`, using the
+        /// ed25519 signature keypair held by this keystore for
+        /// `recipient_sign_pub_key`.
+        fn crypto_box_open_by_sign_pub_key(
+            recipient_sign_pub_key: SignEd25519PubKey,
+            sender_x25519_pub_key: X25519PubKey,
+            data: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Open a libsodium compatible sealed box (see `crypto_box_seal`)
+        /// with the x25519 keypair at `keystore_index`. Tampered data, or
+        /// data sealed to another key, fails with
+        /// `LairError::AuthenticationFailed`.
+        fn crypto_box_seal_open_by_index(
+            keystore_index: KeystoreIndex,
+            data: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Unwrap the file key of an age (age-encryption.org/v1) file
+        /// encrypted to the x25519 keypair at `keystore_index`, whose age
+        /// recipient is `age_recipient`, from the recipient `stanzas` of
+        /// the file header. Fails with `LairError::AuthenticationFailed`
+        /// if no `X25519` stanza is to this keypair. Malformed `X25519`
+        /// stanzas are errors, stanzas of other types are skipped.
+        fn age_decrypt_by_index(
+            keystore_index: KeystoreIndex,
+            stanzas: Vec<AgeStanza>,
+        ) -> [u8; AGE_FILE_KEY_LEN];
+
+        /// Create a new random secret pepper for password hashing.
+        fn pw_pepper_new_from_entropy() -> KeystoreIndex;
+
+        /// Hash a password with argon2id, keyed with the pepper at
+        /// `pepper_index`, under a random salt. Returns a PHC format
+        /// string (`$argon2id$v=19$m=..,t=..,p=..$salt$hash`) that
+        /// can only be verified with access to the same pepper.
+        fn pw_hash(
+            pepper_index: KeystoreIndex,
+            password: Arc<Vec<u8>>,
+            options: PwHashOptions,
+        ) -> String;
+
+        /// Verify a password against a `pw_hash` result, in constant
+        /// time, with the pepper at `pepper_index`. Hashes that are not
+        /// argon2id PHC strings, or whose parameters exceed the
+        /// `PwHashOptions` bounds, are rejected with an error.
+        fn pw_verify(
+            pepper_index: KeystoreIndex,
+            password: Arc<Vec<u8>>,
+            hash: String,
+        ) -> bool;
+
+        /// Create a new random TOTP secret, as long as the hash
+        /// output of `options.alg`. Enroll it in an authenticator app
+        /// with `totp_export_provisioning_uri`.
+        fn totp_new_from_entropy(options: TotpOptions) -> KeystoreIndex;
+
+        /// Import a base32 (RFC 4648) TOTP secret, as shown by services
+        /// enabling two factor authentication. Case, spaces and padding
+        /// are ignored. The secret must decode to `MIN_TOTP_SECRET_LEN`
+        /// to `MAX_TOTP_SECRET_LEN` bytes.
+        fn totp_import(
+            secret_b32: String,
+            options: TotpOptions,
+        ) -> KeystoreIndex;
+
+        /// Generate the RFC 6238 code of the TOTP secret at
+        /// `keystore_index` for the unix time `at` in seconds,
+        /// or for the server's current time if None.
+        /// The secret never leaves the keystore.
+        fn totp_generate(
+            keystore_index: KeystoreIndex,
+            at: Option<u64>,
+        ) -> String;
+
+        /// The `otpauth://totp/` provisioning uri of the TOTP secret at
+        /// `keystore_index`, for enrolling authenticator apps. It holds
+        /// the secret itself, so requires `LairCapability::ExportPrivate`.
+        /// Neither `label` (required) nor `issuer` (may be empty) may
+        /// contain a colon, or exceed `MAX_TOTP_LABEL_LEN` bytes.
+        fn totp_export_provisioning_uri(
+            keystore_index: KeystoreIndex,
+            label: String,
+            issuer: String,
+        ) -> String;
+
+        /// Create an x25519 keypair held only in server memory, never in
+        /// the store. It belongs to this connection, and is destroyed
+        /// with `ephemeral_drop`, or once the connection closes. Each
+        /// connection holds at most `Config::get_max_ephemeral_keys`,
+        /// creating more fails with `LairError::Forbidden`.
+        fn ephemeral_x25519_new() -> (EphemeralHandle, X25519PubKey);
+
+        /// Create an ed25519 signature keypair held only in server
+        /// memory, like `ephemeral_x25519_new`.
+        fn ephemeral_sign_ed25519_new() -> (EphemeralHandle, SignEd25519PubKey);
+
+        /// Sign `message` with the ephemeral ed25519 keypair `handle`.
+        /// Unknown handles, e.g. of another connection, fail with
+        /// `LairError::EntryNotFound`, as in every ephemeral request.
+        fn ephemeral_sign_ed25519_sign(
+            handle: EphemeralHandle,
+            message: Arc<Vec<u8>>,
+        ) -> SignEd25519Signature;
+
+        /// Encrypt data from the ephemeral x25519 keypair
+        /// `sender_handle` to `recipient_pub_key`, as `crypto_box_to_sign_pub_key`
+        /// does.
+        fn ephemeral_crypto_box(
+            sender_handle: EphemeralHandle,
+            recipient_pub_key: X25519PubKey,
+            data: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Open data boxed by `sender_pub_key` to the ephemeral x25519
+        /// keypair `recipient_handle`.
+        fn ephemeral_crypto_box_open(
+            recipient_handle: EphemeralHandle,
+            sender_pub_key: X25519PubKey,
+            data: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Destroy the ephemeral key `handle`.
+        fn ephemeral_drop(handle: EphemeralHandle) -> ();
+    }
+}
+
+#[cfg(feature = "full")]
+pub use crate::ipc::LairClientSender;
+
+/// Anonymously seal data to an x25519 pub key, following libsodium's
+/// `crypto_box_seal` construction exactly. This is purely client-side,
+/// with a fresh ephemeral sender keypair for every call.
+/// Open with `crypto_box_seal_open_by_index` on the recipient's keystore.
+#[cfg(feature = "full")]
+pub async fn crypto_box_seal(
+    recipient_pub_key: X25519PubKey,
+    data: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    internal::x25519::crypto_box_seal(
+        internal::entropy::OsEntropy::new_handle(),
+        recipient_pub_key,
+        data,
+    )
+    .await
+}
+
+/// The age recipient (`age1...`) of an x25519 pub key. age files
+/// encrypted to it are decrypted with `age_decrypt_by_index`,
+/// or an `age_identity::LairAgeIdentity`.
+#[cfg(feature = "full")]
+pub fn age_recipient(pub_key: &X25519PubKey) -> LairResult<String> {
+    internal::age::age_recipient(pub_key)
+}
+
+/// An ed25519 signing keypair of a keystore, by its index.
+#[cfg(feature = "full")]
+#[derive(Clone)]
+pub struct SigningKeyHandle {
+    client: LairClientSender,
+    keystore_index: KeystoreIndex,
+}
+
+#[cfg(feature = "full")]
+impl SigningKeyHandle {
+    /// The ed25519 keypair at `keystore_index`, signing through `client`.
+    pub fn new(
+        client: impl Into<LairClientSender>,
+        keystore_index: KeystoreIndex,
+    ) -> Self {
+        Self {
+            client: client.into(),
+            keystore_index,
+        }
+    }
+
+    /// The keystore index of the keypair.
+    pub fn keystore_index(&self) -> KeystoreIndex {
+        self.keystore_index
+    }
+
+    /// Sign `message` whole. Messages longer than `MAX_SIGN_MESSAGE_LEN`
+    /// fail with `LairError::MessageTooLarge`, see `sign_stream`.
+    pub async fn sign(
+        &self,
+        message: Arc<Vec<u8>>,
+    ) -> LairResult<SignEd25519Signature> {
+        self.client
+            .sign_ed25519_sign_by_index(self.keystore_index, message)
+            .await
+    }
+
+    /// Sign `message` whole, as `sign`, into a `SignedReceipt`
+    /// recording the pub key that signed it.
+    pub async fn sign_receipt(
+        &self,
+        message: Arc<Vec<u8>>,
+    ) -> LairResult<SignedReceipt> {
+        let message_digest = SignedReceipt::digest(&message);
+        let (pub_key, signature) = self
+            .client
+            .sign_ed25519_sign_by_index_v2(self.keystore_index, message)
+            .await?;
+        Ok(SignedReceipt {
+            pub_key,
+            message_digest,
+            signature,
+            signed_at: internal::util::unix_now_secs(),
+        })
+    }
+
+    /// Read `reader` to its end, hashing it as it streams, and
+    /// Ed25519ph sign the hash, with no context. Messages of any size
+    /// sign in memory bounded by `ed25519ph::READ_CHUNK_LEN`, reading
+    /// no faster than they hash. Verify with
+    /// `SignEd25519PubKey::verify_stream`, or `verify_ph` over the sha-512
+    /// of the message.
+    pub async fn sign_stream(
+        &self,
+        reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> LairResult<SignEd25519Signature> {
+        let prehash = ed25519ph::prehash_reader(reader).await?;
+        self.client
+            .sign_ed25519_ph_sign_by_index(
+                self.keystore_index,
+                prehash,
+                Arc::new(Vec::new()),
+            )
+            .await
+    }
+
+    /// `sign_stream`, by its earlier name.
+    pub async fn sign_large(
+        &self,
+        reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> LairResult<SignEd25519Signature> {
+        self.sign_stream(reader).await
+    }
+}
+
+/// A verifiable record of an ed25519 signature, as made by
+/// `SigningKeyHandle::sign_receipt`. It holds a digest of the message,
+/// not the message itself.
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedReceipt {
+    /// The pub key of the keypair that signed.
+    pub pub_key: SignEd25519PubKey,
+
+    /// The sha-512 of the signed message.
+    pub message_digest: [u8; 64],
+
+    /// The signature over the message.
+    pub signature: SignEd25519Signature,
+
+    /// When the signature was requested, in seconds since the unix
+    /// epoch, by the client clock. Not covered by the signature.
+    pub signed_at: u64,
+}
+
+#[cfg(feature = "full")]
+impl SignedReceipt {
+    /// The sha-512 `message_digest` of `message`.
+    pub fn digest(message: &[u8]) -> [u8; 64] {
+        let mut out = [0; 64];
+        out.copy_from_slice(
+            ring::digest::digest(&ring::digest::SHA512, message).as_ref(),
+        );
+        out
+    }
+
+    /// True if `message` is the one the receipt records,
+    /// and the signature over it verifies under `pub_key`.
+    pub fn verify(&self, message: &[u8]) -> bool {
+        Self::digest(message) == self.message_digest
+            && self.pub_key.verify_pure(message, &self.signature)
     }
 }
 
-/// Lair Client Sender Type.
-pub type LairClientSender = futures::channel::mpsc::Sender<LairClientApi>;
+/// Stream all keystore entries in ascending index order, fetching
+/// `page_size` entries at a time via `lair_list_entries_page`.
+/// Pages are only requested as the stream is polled,
+/// so dropping the stream early avoids fetching the remainder.
+#[cfg(feature = "full")]
+pub fn lair_list_entries_stream(
+    client: impl LairClientApiSender,
+    page_size: u32,
+) -> impl futures::stream::Stream<Item = LairResult<LairEntryInfo>> + Send {
+    use futures::stream::StreamExt;
+    futures::stream::unfold(
+        Some((client, KeystoreIndex::from(0))),
+        move |state| async move {
+            let (client, start) = state?;
+            match client.lair_list_entries_page(start, page_size).await {
+                Ok((page, next_start)) => {
+                    let next = next_start.map(|next| (client, next));
+                    Some((page.into_iter().map(Ok).collect::<Vec<_>>(), next))
+                }
+                // end the stream after reporting the error
+                Err(e) => Some((vec![Err(e)], None)),
+            }
+        },
+    )
+    .flat_map(futures::stream::iter)
+}
+
+/// Stream the index and type of every entry in the selected store, in
+/// ascending index order, skipping erased / invalid stubs and trashed
+/// entries. Ends at once for an empty store. Built on
+/// `lair_list_entries_stream`.
+#[cfg(feature = "full")]
+pub fn lair_entry_indices_stream(
+    client: impl LairClientApiSender,
+) -> impl futures::stream::Stream<
+    Item = LairResult<(KeystoreIndex, LairEntryType)>,
+> + Send {
+    use futures::stream::StreamExt;
+    lair_list_entries_stream(client, MAX_LIST_ENTRIES_PAGE_LIMIT).filter_map(
+        |info| async move {
+            match info {
+                Ok(info)
+                    if info.entry_type == LairEntryType::Invalid
+                        || info.trashed =>
+                {
+                    None
+                }
+                Ok(info) => Some(Ok((info.keystore_index, info.entry_type))),
+                Err(e) => Some(Err(e)),
+            }
+        },
+    )
+}
+
+/// Group listed entries sharing a type and public identifier, as left
+/// behind by imports predating duplicate detection. Each group is in
+/// ascending index order: the first entry is the one lookups by public
+/// identifier resolve to. Entries without a public identifier are never
+/// duplicates.
+pub fn find_duplicate_entries(
+    entries: &[LairEntryInfo],
+) -> Vec<Vec<KeystoreIndex>> {
+    let mut groups = std::collections::BTreeMap::new();
+    for info in entries {
+        if info.pub_id.is_empty() || info.entry_type == LairEntryType::Invalid {
+            continue;
+        }
+        groups
+            .entry((info.entry_type as u32, info.pub_id.clone()))
+            .or_insert_with(Vec::new)
+            .push(info.keystore_index);
+    }
+    let mut out = groups
+        .into_values()
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<_>>();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032 ed25519 test vector 2
+    const PUB_KEY: &str =
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+    const MESSAGE: &str = "72";
+    const SIGNATURE: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_can_verify_rfc8032_vector() {
+        let pub_key: SignEd25519PubKey = hex(PUB_KEY).into();
+        let message = Arc::new(hex(MESSAGE));
+        let signature: SignEd25519Signature = hex(SIGNATURE).into();
+
+        assert!(pub_key.verify_pure(&message, &signature));
+        assert!(futures::executor::block_on(
+            pub_key.verify(message.clone(), signature.clone())
+        )
+        .unwrap());
+
+        assert!(!pub_key.verify_pure(b"bad-message", &signature));
+
+        let mut bad_sig = (*signature.0).clone();
+        bad_sig[0] ^= 1;
+        assert!(!pub_key.verify_pure(&message, &bad_sig.into()));
+
+        let short: SignEd25519Signature = vec![0; 12].into();
+        assert!(!pub_key.verify_pure(&message, &short));
+    }
+
+    #[test]
+    fn it_agrees_on_sync_and_async_verification() {
+        let pub_key: SignEd25519PubKey = hex(PUB_KEY).into();
+        let message = hex(MESSAGE);
+        let signature: SignEd25519Signature = hex(SIGNATURE).into();
+        let mut array = [0; 64];
+        array.copy_from_slice(&signature);
+        let mut bad_sig = array;
+        bad_sig[0] ^= 1;
+
+        let cases: Vec<(&[u8], SignEd25519Signature)> = vec![
+            (&message, signature.clone()),
+            (b"bad-message", signature.clone()),
+            (&message, bad_sig.to_vec().into()),
+            (&message, vec![0; 12].into()),
+            (&message, vec![0; 64].into()),
+        ];
+        for (i, (message, signature)) in cases.into_iter().enumerate() {
+            let sync = pub_key.verify_sync(message, &signature).unwrap();
+            assert_eq!(i == 0, sync);
+            assert_eq!(
+                sync,
+                futures::executor::block_on(
+                    pub_key.verify(Arc::new(message.to_vec()), signature)
+                )
+                .unwrap()
+            );
+        }
+
+        assert!(pub_key.verify_sync(&message, &array).unwrap());
+        assert!(!pub_key.verify_sync(&message, &bad_sig).unwrap());
+        let bad_key: SignEd25519PubKey = vec![0; 12].into();
+        assert!(!bad_key.verify_sync(&message, &array).unwrap());
+    }
+
+    #[test]
+    fn it_scopes_keystore_indexes_to_a_store() {
+        // default store indexes are plain entry numbers
+        assert_eq!(KeystoreIndex(42), KeystoreIndex::new(0, 42));
+
+        let idx = KeystoreIndex::new(3, KeystoreIndex::MAX_ENTRY);
+        assert_eq!(0x03ff_ffff, idx.0);
+        assert_eq!(3, idx.store_number());
+        assert_eq!(KeystoreIndex::MAX_ENTRY, idx.entry());
+
+        let idx = KeystoreIndex::new(u8::MAX, 1);
+        assert_eq!(u8::MAX, idx.store_number());
+        assert_eq!(1, idx.entry());
+    }
+
+    #[test]
+    fn it_finds_duplicate_entries() {
+        fn info(idx: u32, entry_type: LairEntryType, id: u8) -> LairEntryInfo {
+            LairEntryInfo {
+                keystore_index: idx.into(),
+                entry_type,
+                pub_id: Arc::new(if id == 0 { vec![] } else { vec![id; 32] }),
+                tag: None,
+                aliases: Vec::new(),
+                trashed: false,
+                rotated_from: None,
+                activated: true,
+            }
+        }
+
+        let entries = vec![
+            info(1, LairEntryType::SignEd25519, 1),
+            info(2, LairEntryType::X25519, 1),
+            info(3, LairEntryType::PwPepper, 0),
+            info(4, LairEntryType::SignEd25519, 2),
+            info(5, LairEntryType::PwPepper, 0),
+            info(6, LairEntryType::SignEd25519, 1),
+            info(7, LairEntryType::SignEd25519, 2),
+            info(8, LairEntryType::SignEd25519, 1),
+        ];
+        assert_eq!(
+            vec![
+                vec![KeystoreIndex(1), KeystoreIndex(6), KeystoreIndex(8)],
+                vec![KeystoreIndex(4), KeystoreIndex(7)],
+            ],
+            find_duplicate_entries(&entries),
+        );
+        assert!(find_duplicate_entries(&entries[..3]).is_empty());
+    }
+
+    #[test]
+    fn it_parses_capabilities() {
+        let caps: LairCapabilities = "sign, read_public,".parse().unwrap();
+        assert_eq!(
+            LairCapabilities::from(
+                &[LairCapability::ReadPublic, LairCapability::Sign][..]
+            ),
+            caps
+        );
+        assert_eq!("read_public,sign", caps.to_string());
+        assert!(caps.contains(LairCapability::Sign));
+        assert!(!caps.contains(LairCapability::Admin));
+        assert_eq!(
+            LairCapabilities::ALL,
+            LairCapabilities::from(&LairCapability::ALL[..])
+        );
+        assert_eq!(LairCapabilities::NONE, "".parse().unwrap());
+        assert!("sign,launch".parse::<LairCapabilities>().is_err());
+    }
+
+    #[test]
+    fn it_validates_cert_snis() {
+        let label = "a".repeat(MAX_CERT_SNI_LABEL_LEN);
+        let long_label = "a".repeat(MAX_CERT_SNI_LABEL_LEN + 1);
+        let max = format!("{0}.{0}", label);
+        let too_long = format!("{}.a", "a".repeat(MAX_CERT_SNI_LEN - 1));
+        // (sni, normalized if valid, valid for parse_lenient)
+        let table: &[(&str, Option<&str>, bool)] = &[
+            ("example.com", Some("example.com"), true),
+            ("localhost", Some("localhost"), true),
+            ("Ex-Ample.COM", Some("ex-ample.com"), true),
+            ("a1.b2.c3", Some("a1.b2.c3"), true),
+            ("xn--bcher-kva.example", Some("xn--bcher-kva.example"), true),
+            ("*.example.com", Some("*.example.com"), true),
+            (&label, Some(&label), true),
+            (&max, Some(&max), true),
+            ("", None, false),
+            (&too_long, None, false),
+            ("exa\0mple.com", None, false),
+            ("exa mple.com", None, false),
+            ("example.com\n", None, false),
+            ("\u{e9}xample.com", None, false),
+            (&long_label, None, true),
+            ("under_score.com", None, true),
+            ("-example.com", None, true),
+            ("example-.com", None, true),
+            ("example..com", None, true),
+            (".example.com", None, true),
+            ("example.com.", None, true),
+            ("*", None, true),
+            ("*.*.example.com", None, true),
+            ("www.*.example.com", None, true),
+            ("w*.example.com", None, true),
+            ("127.0.0.1", None, true),
+        ];
+        for (sni, normalized, lenient) in table {
+            assert_eq!(
+                normalized.map(|n| CertSni::from(n.to_string())),
+                CertSni::parse(sni).ok(),
+                "{:?}",
+                sni
+            );
+            assert_eq!(
+                *lenient,
+                CertSni::parse_lenient(sni).is_ok(),
+                "{:?}",
+                sni
+            );
+        }
+        assert_eq!(
+            CertSni::parse("example.com").unwrap(),
+            CertSni::from("EXAMPLE.com".to_string()).normalized(),
+        );
+    }
+
+    #[test]
+    fn it_can_compute_cert_digest() {
+        let cert: Cert = b"test-cert-der".to_vec().into();
+        assert_eq!(
+            CertDigest::from(hex("b972b8d8faf0355b324b3770e17edc0e935e0d6ea523e54128354547109d445e")),
+            CertDigest::compute(&cert),
+        );
+    }
+
+    #[test]
+    fn it_verifies_the_timestamped_vector() {
+        let v = test_vectors::TIMESTAMPED_ED25519_VECTOR;
+        let pub_key: SignEd25519PubKey = hex(v.vector.public_key).into();
+        let message = hex(v.vector.message);
+        let signature: SignEd25519Signature = hex(v.vector.signature).into();
+
+        let timestamped = timestamped_message(v.timestamp_millis, &message);
+        assert_eq!(hex(v.timestamped_message), timestamped);
+        assert_eq!(SIGN_TIMESTAMP_LEN + message.len(), timestamped.len());
+
+        // a plain signature of the timestamped message
+        assert!(pub_key.verify_pure(&timestamped, &signature));
+        assert!(pub_key.verify_timestamped(
+            &message,
+            v.timestamp_millis,
+            &signature
+        ));
+        assert!(!pub_key.verify_timestamped(
+            &message,
+            v.timestamp_millis + 1,
+            &signature
+        ));
+        assert!(!pub_key.verify_timestamped(
+            b"bad-message",
+            v.timestamp_millis,
+            &signature
+        ));
+        assert!(!pub_key.verify_pure(&message, &signature));
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn it_matches_openssl_cert_fingerprints() {
+        let cert: Cert = hex(test_vectors::FIXTURE_CERT_DER).into();
+        assert_eq!(
+            hex(test_vectors::FIXTURE_CERT_SHA256),
+            *DigestAlg::Sha256.compute(&cert),
+        );
+        assert_eq!(
+            hex(test_vectors::FIXTURE_CERT_SHA1),
+            *DigestAlg::Sha1Legacy.compute(&cert),
+        );
+        assert_eq!(
+            CertDigest::compute(&cert).0,
+            DigestAlg::Blake2b.compute(&cert)
+        );
+
+        for alg in
+            [DigestAlg::Blake2b, DigestAlg::Sha256, DigestAlg::Sha1Legacy]
+        {
+            assert_eq!(alg.digest_len(), alg.compute(&cert).len());
+            assert_eq!(alg, DigestAlg::parse(alg as u32).unwrap());
+        }
+        assert!(DigestAlg::parse(0).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! An `age::Identity` backed by an x25519 keypair of a lair keystore.
+//!
+//! Files encrypted to the keypair's age recipient (`age1...`, see
+//! `actor::age_recipient`) by any age implementation decrypt with a
+//! `LairAgeIdentity`: the `X25519` stanzas of the file header are sent to
+//! the keystore, which unwraps the file key (`age_decrypt_by_index`), so
+//! no identity file ever exists on disk.
+//!
+//! age decrypts synchronously, and the identity blocks on the keystore,
+//! so decrypt off the async executor, e.g. in `tokio::task::spawn_blocking`.
+
+use crate::actor::*;
+use crate::*;
+use age_core::format::{FileKey, Stanza};
+use internal::age::AGE_X25519_STANZA_TAG;
+
+/// The age identity of the x25519 keypair at `keystore_index`.
+#[derive(Clone)]
+pub struct LairAgeIdentity {
+    client: LairClientSender,
+    keystore_index: KeystoreIndex,
+    recipient: String,
+}
+
+impl LairAgeIdentity {
+    /// The age identity of the x25519 keypair at `keystore_index`.
+    pub async fn new(
+        client: impl Into<LairClientSender>,
+        keystore_index: KeystoreIndex,
+    ) -> LairResult<Self> {
+        let client = client.into();
+        let pub_key = client.x25519_get(keystore_index).await?;
+        let recipient = age_recipient(&pub_key)?;
+        Ok(Self {
+            client,
+            keystore_index,
+            recipient,
+        })
+    }
+
+    /// The keystore index of the x25519 keypair.
+    pub fn keystore_index(&self) -> KeystoreIndex {
+        self.keystore_index
+    }
+
+    /// The age recipient (`age1...`) to encrypt to.
+    pub fn recipient(&self) -> &str {
+        &self.recipient
+    }
+}
+
+impl age::Identity for LairAgeIdentity {
+    fn unwrap_stanza(
+        &self,
+        stanza: &Stanza,
+    ) -> Option<Result<FileKey, age::DecryptError>> {
+        self.unwrap_stanzas(std::slice::from_ref(stanza))
+    }
+
+    /// Unwraps all stanzas in a single keystore request.
+    fn unwrap_stanzas(
+        &self,
+        stanzas: &[Stanza],
+    ) -> Option<Result<FileKey, age::DecryptError>> {
+        let stanzas = stanzas
+            .iter()
+            .filter(|stanza| stanza.tag == AGE_X25519_STANZA_TAG)
+            .map(|stanza| AgeStanza {
+                tag: stanza.tag.clone(),
+                args: stanza.args.clone(),
+                body: Arc::new(stanza.body.clone()),
+            })
+            .collect::<Vec<_>>();
+        if stanzas.is_empty() {
+            return None;
+        }
+        match futures::executor::block_on(
+            self.client
+                .age_decrypt_by_index(self.keystore_index, stanzas),
+        ) {
+            Ok(file_key) => Some(Ok(FileKey::new(Box::new(file_key)))),
+            // no stanza to this identity
+            Err(LairError::AuthenticationFailed) => None,
+            Err(e) => {
+                ghost_actor::dependencies::tracing::warn!(
+                    ?e,
+                    "age unwrap failed"
+                );
+                Some(Err(age::DecryptError::InvalidHeader))
+            }
+        }
+    }
+}
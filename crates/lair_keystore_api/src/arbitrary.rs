@@ -0,0 +1,747 @@
+//! `proptest` strategies for the api types, behind the "proptest"
+//! feature, for property testing code built on lair.
+//!
+//! Generated values are valid on the wire: byte newtypes have the
+//! sizes lair encodes, enums take their defined discriminants,
+//! and variable length data stays within the wire maximums.
+//! Use `invalid_discriminant` for the discriminants a peer
+//! must refuse.
+
+use crate::actor::*;
+use crate::*;
+use ::proptest::collection::vec;
+use ::proptest::prelude::*;
+
+/// The byte length of an ed25519 pub key, and of a blake2b `CertDigest`.
+const KEY_LEN: usize = 32;
+
+/// The maximum der byte length of a `Cert` on the wire.
+const MAX_CERT_LEN: usize = 968;
+
+/// The maximum der byte length of a `CertPrivKey` on the wire.
+const MAX_CERT_PRIV_KEY_LEN: usize = 220;
+
+/// The maximum byte length of quarantined entry data,
+/// the size of a store entry.
+const MAX_QUARANTINED_DATA_LEN: usize = 1024;
+
+/// Short, printable strings, for names, aliases and reasons.
+fn short_string() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 _.-]{0,32}"
+}
+
+fn bytes(
+    len: impl Into<::proptest::collection::SizeRange>,
+) -> BoxedStrategy<Arc<Vec<u8>>> {
+    vec(any::<u8>(), len).prop_map(Arc::new).boxed()
+}
+
+/// u32s that `parse` refuses, e.g.
+/// `invalid_discriminant(LairEntryType::parse)`.
+pub fn invalid_discriminant<T: 'static>(
+    parse: fn(u32) -> LairResult<T>,
+) -> BoxedStrategy<u32> {
+    any::<u32>()
+        .prop_filter("valid discriminant", move |d| parse(*d).is_err())
+        .boxed()
+}
+
+macro_rules! arbitrary_bytes {
+    ($($t:ty: $len:expr,)*) => {$(
+        impl Arbitrary for $t {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                bytes($len).prop_map(Self).boxed()
+            }
+        }
+    )*};
+}
+
+arbitrary_bytes! {
+    Cert: 0..=MAX_CERT_LEN,
+    CertPrivKey: 0..=MAX_CERT_PRIV_KEY_LEN,
+    CertDigest: KEY_LEN,
+    SignEd25519PubKey: KEY_LEN,
+    SignEd25519Signature: 64,
+    X25519PubKey: KEY_LEN,
+    SignSecp256k1PubKey: 33,
+    SignSecp256k1Signature: 64,
+    SignEd448PubKey: SIGN_ED448_PUB_KEY_LEN,
+    SignEd448Signature: SIGN_ED448_SIGNATURE_LEN,
+    SignBlsPubKey: SIGN_BLS_PUB_KEY_LEN,
+    SignBlsSignature: SIGN_BLS_SIGNATURE_LEN,
+    SignSr25519PubKey: SIGN_SR25519_PUB_KEY_LEN,
+    SignSr25519Signature: SIGN_SR25519_SIGNATURE_LEN,
+}
+
+macro_rules! arbitrary_enum {
+    ($($t:ident { $($variant:ident,)* })*) => {$(
+        impl Arbitrary for $t {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                prop_oneof![$(Just($t::$variant),)*].boxed()
+            }
+        }
+    )*};
+}
+
+arbitrary_enum! {
+    TlsCertAlg {
+        PkcsEd25519,
+        PkcsEcdsaP256Sha256,
+        PkcsEcdsaP384Sha384,
+        PkcsEd448,
+        Unknown,
+    }
+    DigestAlg {
+        Blake2b,
+        Sha256,
+        Sha1Legacy,
+    }
+    LairEntryType {
+        Invalid,
+        TlsCert,
+        SignEd25519,
+        X25519,
+        PwPepper,
+        SignSecp256k1,
+        TotpSecret,
+        SignEd448,
+        SignBls,
+        SignSr25519,
+    }
+    TotpAlg {
+        Sha1,
+        Sha256,
+        Sha512,
+    }
+    PrefixKind {
+        SignPubKey,
+        CertDigest,
+        Any,
+    }
+    KeyBacking {
+        Software,
+        Hardware,
+    }
+    EntropyStatus {
+        Unchecked,
+        Healthy,
+        Unavailable,
+    }
+    EventKind {
+        RequestUnlockPassphrase,
+        UnlockProgress,
+        UnlockComplete,
+        EventsDropped,
+        EntryCreated,
+        RequestSignApproval,
+        ClockSkewDetected,
+        EntryActivated,
+    }
+}
+
+impl Arbitrary for KeystoreIndex {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<u32>().prop_map(Self).boxed()
+    }
+}
+
+impl Arbitrary for KeystoreIndex64 {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<u64>().prop_map(Self).boxed()
+    }
+}
+
+impl Arbitrary for EphemeralHandle {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<u64>().prop_map(Self).boxed()
+    }
+}
+
+#[cfg(feature = "full")]
+impl Arbitrary for crate::passphrase::Passphrase {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        vec(any::<u8>(), 0..=128)
+            .prop_map(|passphrase| Self::from(&passphrase[..]))
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairCapabilities {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0..=Self::ALL.0).prop_map(Self).boxed()
+    }
+}
+
+impl Arbitrary for LairConnectionInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<u64>(), any::<u64>(), any::<u32>())
+            .prop_map(|(connection_id, idle_ms, in_flight)| Self {
+                connection_id,
+                idle: std::time::Duration::from_millis(idle_ms),
+                in_flight,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairElevation {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<LairCapabilities>(), any::<u64>())
+            .prop_map(|(capabilities, remaining_ms)| Self {
+                capabilities,
+                remaining: std::time::Duration::from_millis(remaining_ms),
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairAuditRecord {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            "[a-z0-9_]{1,64}",
+            any::<Option<KeystoreIndex64>>(),
+            proptest::option::of(bytes(KEY_LEN)),
+            any::<(u64, LairCapabilities, u64)>(),
+            proptest::option::of(short_string()),
+        )
+            .prop_map(
+                |(
+                    operation,
+                    keystore_index,
+                    pub_key,
+                    (connection_id, capabilities, request_id),
+                    error,
+                )| Self {
+                    operation,
+                    keystore_index,
+                    pub_key,
+                    connection_id,
+                    capabilities,
+                    request_id,
+                    error,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for StoreStats {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<u64>(),
+            vec(any::<(LairEntryType, u64)>(), 0..8),
+            any::<(u64, u64, u64)>(),
+        )
+            .prop_map(
+                |(
+                    file_size,
+                    entry_counts,
+                    (erased_count, quarantined_count, trashed_count),
+                )| Self {
+                    file_size,
+                    entry_counts,
+                    erased_count,
+                    quarantined_count,
+                    trashed_count,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for CertSni {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        "[a-z0-9]{1,16}(\\.[a-z0-9]{1,16}){0,3}"
+            .prop_map(|sni| Self(Arc::new(sni)))
+            .boxed()
+    }
+}
+
+impl Arbitrary for PwHashOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (1..=MAX_PW_HASH_LANES, 1..=MAX_PW_HASH_TIME_COST)
+            .prop_flat_map(|(lanes, time_cost)| {
+                (8 * lanes..=MAX_PW_HASH_MEM_COST_KIB).prop_map(
+                    move |mem_cost_kib| Self {
+                        mem_cost_kib,
+                        time_cost,
+                        lanes,
+                    },
+                )
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for TotpOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            MIN_TOTP_DIGITS..=MAX_TOTP_DIGITS,
+            1..=MAX_TOTP_PERIOD,
+            any::<TotpAlg>(),
+        )
+            .prop_map(|(digits, period, alg)| Self {
+                digits,
+                period,
+                alg,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for SignOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<bool>()
+            .prop_map(|include_timestamp| Self { include_timestamp })
+            .boxed()
+    }
+}
+
+impl Arbitrary for SignKeyOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            proptest::option::of(short_string()),
+            any::<bool>(),
+            any::<Option<u64>>(),
+            any::<bool>(),
+        )
+            .prop_map(|(tag, exportable, expires_at, requires_approval)| Self {
+                tag,
+                exportable,
+                expires_at,
+                requires_approval,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairEntryInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<KeystoreIndex>(),
+            any::<LairEntryType>(),
+            bytes(0..=MAX_ENTRY_PUB_ID_LEN),
+            proptest::option::of(short_string()),
+            vec(short_string(), 0..=MAX_ENTRY_ALIASES),
+            any::<bool>(),
+            any::<Option<KeystoreIndex>>(),
+            any::<bool>(),
+        )
+            .prop_map(
+                |(
+                    keystore_index,
+                    entry_type,
+                    pub_id,
+                    tag,
+                    aliases,
+                    trashed,
+                    rotated_from,
+                    activated,
+                )| Self {
+                    keystore_index,
+                    entry_type,
+                    pub_id,
+                    tag,
+                    aliases,
+                    trashed,
+                    rotated_from,
+                    activated,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for RotateKeyOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<SignKeyOptions>(), any::<bool>())
+            .prop_map(|(key_options, trash_old_key)| Self {
+                key_options,
+                trash_old_key,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for RotationRecord {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<SignEd25519PubKey>(),
+            any::<SignEd25519PubKey>(),
+            any::<u64>(),
+            any::<SignEd25519Signature>(),
+            any::<SignEd25519Signature>(),
+        )
+            .prop_map(
+                |(
+                    old_pub_key,
+                    new_pub_key,
+                    timestamp,
+                    old_signature,
+                    new_signature,
+                )| Self {
+                    old_pub_key,
+                    new_pub_key,
+                    timestamp,
+                    old_signature,
+                    new_signature,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for QuarantinedEntry {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<KeystoreIndex>(),
+            short_string(),
+            bytes(0..=MAX_QUARANTINED_DATA_LEN),
+        )
+            .prop_map(|(keystore_index, reason, data)| Self {
+                keystore_index,
+                reason,
+                data,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for AgeStanza {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            short_string(),
+            vec(short_string(), 0..=MAX_AGE_STANZA_ARGS),
+            bytes(0..=64),
+        )
+            .prop_map(|(tag, args, body)| Self { tag, args, body })
+            .boxed()
+    }
+}
+
+#[cfg(feature = "full")]
+impl Arbitrary for internal::wire::BulkItemError {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<u32>(), short_string())
+            .prop_map(|(kind, message)| Self { kind, message })
+            .boxed()
+    }
+}
+
+#[cfg(feature = "full")]
+impl Arbitrary for internal::wire::ErrorCause {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use internal::wire::spec;
+        let io_codes = spec::ERROR_CAUSE_IO
+            ..spec::ERROR_CAUSE_IO + spec::ERROR_CAUSE_IO_KINDS.len() as u32;
+        (
+            prop_oneof![Just(spec::ERROR_CAUSE_OTHER), io_codes],
+            short_string(),
+        )
+            .prop_map(|(code, message)| Self { code, message })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairServerInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            short_string(),
+            short_string(),
+            // servers predating server identity keys send none
+            prop_oneof![
+                Just(SignEd25519PubKey(Arc::new(Vec::new()))),
+                any::<SignEd25519PubKey>(),
+            ],
+            any::<bool>(),
+            any::<u32>(),
+            any::<EntropyStatus>(),
+        )
+            .prop_map(
+                |(
+                    name,
+                    version,
+                    id_pub_key,
+                    is_locked,
+                    wire_protocol_version,
+                    entropy_status,
+                )| {
+                    Self {
+                        name,
+                        version,
+                        id_pub_key,
+                        is_locked,
+                        wire_protocol_version,
+                        entropy_status,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for Attestation {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<SignEd25519PubKey>(),
+            any::<LairEntryType>(),
+            bytes(0..=MAX_ENTRY_PUB_ID_LEN),
+            bytes(0..=MAX_ATTEST_CHALLENGE_LEN),
+            any::<u64>(),
+            any::<SignEd25519Signature>(),
+        )
+            .prop_map(
+                |(
+                    server_id_pub_key,
+                    entry_type,
+                    entry_pub_id,
+                    challenge,
+                    timestamp,
+                    signature,
+                )| Self {
+                    server_id_pub_key,
+                    entry_type,
+                    entry_pub_id,
+                    challenge,
+                    timestamp,
+                    signature,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for ServerIdentityProof {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<SignEd25519PubKey>(),
+            any::<u32>(),
+            short_string(),
+            bytes(0..=MAX_ATTEST_CHALLENGE_LEN),
+            any::<SignEd25519Signature>(),
+        )
+            .prop_map(
+                |(
+                    server_id_pub_key,
+                    wire_protocol_version,
+                    server_version,
+                    nonce,
+                    signature,
+                )| Self {
+                    server_id_pub_key,
+                    wire_protocol_version,
+                    server_version,
+                    nonce,
+                    signature,
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Absolute, normalized unix paths.
+fn unix_path() -> impl Strategy<Value = std::path::PathBuf> {
+    "(/[a-zA-Z0-9_-][a-zA-Z0-9_.-]{0,15}){1,4}".prop_map(Into::into)
+}
+
+impl Arbitrary for LairConnectionUrl {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let url = prop_oneof![
+            unix_path().prop_map(|path| Self::new_unix_socket(path).unwrap()),
+            "[a-zA-Z0-9_.-]{1,32}"
+                .prop_map(|name| Self::new_windows_pipe(name).unwrap()),
+            ("[a-z0-9]{1,16}(\\.[a-z0-9]{1,16}){0,3}", 1..=u16::MAX)
+                .prop_map(|(host, port)| Self::new_tcp(host, port).unwrap()),
+        ];
+        // only linux parses abstract socket urls
+        #[cfg(target_os = "linux")]
+        let url = prop_oneof![
+            3 => url,
+            1 => proptest::collection::vec(1..=u8::MAX, 1..=32)
+                .prop_map(|name| Self::new_unix_abstract(name).unwrap()),
+        ];
+        (url, proptest::option::of("[!-~]{1,32}"))
+            .prop_map(|(url, token)| match token {
+                Some(token) => url.with_token(token).unwrap(),
+                None => url,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LairServerPaths {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (unix_path(), any::<LairConnectionUrl>())
+            .prop_map(|(root_path, connection_url)| Self {
+                store_path: root_path.join("store_file"),
+                root_path,
+                connection_url,
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn it_sizes_byte_newtypes(
+            digest in any::<CertDigest>(),
+            pub_key in any::<SignEd25519PubKey>(),
+            sig in any::<SignEd25519Signature>(),
+            k1_pub_key in any::<SignSecp256k1PubKey>(),
+            ed448_pub_key in any::<SignEd448PubKey>(),
+            ed448_sig in any::<SignEd448Signature>(),
+            bls_pub_key in any::<SignBlsPubKey>(),
+            bls_sig in any::<SignBlsSignature>(),
+            sr25519_pub_key in any::<SignSr25519PubKey>(),
+            sr25519_sig in any::<SignSr25519Signature>(),
+        ) {
+            prop_assert_eq!(32, digest.len());
+            prop_assert_eq!(32, pub_key.len());
+            prop_assert_eq!(64, sig.len());
+            prop_assert_eq!(33, k1_pub_key.len());
+            prop_assert_eq!(57, ed448_pub_key.len());
+            prop_assert_eq!(114, ed448_sig.len());
+            prop_assert_eq!(48, bls_pub_key.len());
+            prop_assert_eq!(96, bls_sig.len());
+            prop_assert_eq!(32, sr25519_pub_key.len());
+            prop_assert_eq!(64, sr25519_sig.len());
+        }
+
+        #[test]
+        fn it_generates_valid_discriminants(
+            entry_type in any::<LairEntryType>(),
+            alg in any::<TlsCertAlg>(),
+            kind in any::<PrefixKind>(),
+            backing in any::<KeyBacking>(),
+            event_kind in any::<EventKind>(),
+        ) {
+            prop_assert_eq!(
+                entry_type,
+                LairEntryType::parse(entry_type as u32).unwrap()
+            );
+            prop_assert_eq!(alg, TlsCertAlg::parse(alg as u32).unwrap());
+            prop_assert_eq!(kind, PrefixKind::parse(kind as u32).unwrap());
+            prop_assert_eq!(backing, KeyBacking::parse(backing as u32).unwrap());
+            prop_assert_eq!(
+                event_kind,
+                EventKind::parse(event_kind as u32).unwrap()
+            );
+        }
+
+        #[test]
+        fn it_generates_invalid_discriminants(
+            d in invalid_discriminant(LairEntryType::parse),
+        ) {
+            prop_assert!(LairEntryType::parse(d).is_err());
+        }
+
+        #[test]
+        fn it_generates_checked_pw_hash_options(
+            options in any::<PwHashOptions>(),
+        ) {
+            prop_assert!(options.check().is_ok());
+        }
+
+        #[test]
+        fn it_generates_checked_totp_options(
+            options in any::<TotpOptions>(),
+        ) {
+            prop_assert!(options.check().is_ok());
+        }
+
+        #[test]
+        fn it_generates_parseable_connection_urls(
+            url in any::<LairConnectionUrl>(),
+        ) {
+            prop_assert_eq!(&url, &url.to_string().parse().unwrap());
+        }
+    }
+}
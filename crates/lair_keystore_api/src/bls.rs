@@ -0,0 +1,378 @@
+//! BLS12-381 signatures, as used by `SignBls` entries, on the `blst` crate.
+//!
+//! Follows the IETF BLS signature draft (draft-irtf-cfrg-bls-signature),
+//! minimal-pubkey-size variant: public keys are 48 byte compressed G1
+//! points, signatures are 96 byte compressed G2 points, and messages
+//! are hashed to G2 with the RFC 9380 `BLS12381G2_XMD:SHA-256_SSWU_RO_`
+//! suite. The domain separation tag is a parameter, see `DST_BASIC` and
+//! `DST_POP` for the ciphersuites of the draft. As RFC 9380 requires,
+//! it is between 1 and `MAX_DST_LEN` bytes.
+//!
+//! `aggregate_verify` does not require the messages to be distinct.
+//! That is only sound for keys with a checked proof of possession
+//! (`DST_POP`), callers of the basic scheme must ensure distinct
+//! messages themselves.
+
+use crate::*;
+use blst::min_pk::{AggregateSignature, PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+
+/// Byte length of a compressed public key.
+pub const PUB_KEY_LEN: usize = 48;
+
+/// Byte length of a compressed signature.
+pub const SIGNATURE_LEN: usize = 96;
+
+/// Domain separation tag of the draft's basic scheme ciphersuite.
+pub const DST_BASIC: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// Domain separation tag of the draft's proof of possession ciphersuite,
+/// as used by ethereum consensus.
+pub const DST_POP: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The maximum byte length of a domain separation tag.
+pub const MAX_DST_LEN: usize = 255;
+
+/// Byte length of a private key.
+const PRIV_KEY_LEN: usize = 32;
+
+fn check_dst(dst: &[u8]) -> LairResult<()> {
+    if dst.is_empty() || dst.len() > MAX_DST_LEN {
+        return Err(format!(
+            "bls dst must be 1 to {} bytes, got {}",
+            MAX_DST_LEN,
+            dst.len(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn decode_priv_key(priv_key: &[u8]) -> LairResult<SecretKey> {
+    if priv_key.len() != PRIV_KEY_LEN {
+        return Err("invalid bls priv key".into());
+    }
+    SecretKey::from_bytes(priv_key).map_err(|_| "invalid bls priv key".into())
+}
+
+fn decode_pub_key(pub_key: &[u8]) -> Option<PublicKey> {
+    if pub_key.len() != PUB_KEY_LEN {
+        return None;
+    }
+    // refuses the identity and points outside the G1 subgroup
+    PublicKey::key_validate(pub_key).ok()
+}
+
+fn decode_signature(signature: &[u8]) -> Option<Signature> {
+    if signature.len() != SIGNATURE_LEN {
+        return None;
+    }
+    Signature::from_bytes(signature).ok()
+}
+
+/// Derive a private key from at least 32 bytes of keying material,
+/// with the draft's KeyGen (as also used by EIP-2333).
+pub fn key_gen(ikm: &[u8], key_info: &[u8]) -> LairResult<[u8; 32]> {
+    if ikm.len() < 32 {
+        return Err("bls ikm must be at least 32 bytes".into());
+    }
+    let key = SecretKey::key_gen(ikm, key_info)
+        .map_err(|e| format!("bls key_gen: {:?}", e))?;
+    Ok(key.to_bytes())
+}
+
+/// Check a private key is a valid (non-zero, less than the group order)
+/// big-endian scalar, and derive its compressed public key.
+pub fn pub_key_from_priv_key(priv_key: &[u8]) -> LairResult<Vec<u8>> {
+    let key = decode_priv_key(priv_key)?;
+    Ok(key.sk_to_pk().compress().to_vec())
+}
+
+/// Sign a message under the domain separation tag `dst`.
+pub fn sign(
+    priv_key: &[u8],
+    message: &[u8],
+    dst: &[u8],
+) -> LairResult<[u8; SIGNATURE_LEN]> {
+    check_dst(dst)?;
+    let key = decode_priv_key(priv_key)?;
+    Ok(key.sign(message, dst, &[]).compress())
+}
+
+/// Verify a signature of `message` under the domain separation tag `dst`.
+pub fn verify(
+    pub_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+    dst: &[u8],
+) -> bool {
+    if check_dst(dst).is_err() {
+        return false;
+    }
+    let pub_key = match decode_pub_key(pub_key) {
+        Some(pub_key) => pub_key,
+        None => return false,
+    };
+    let signature = match decode_signature(signature) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    signature.verify(true, message, dst, &[], &pub_key, false)
+        == BLST_ERROR::BLST_SUCCESS
+}
+
+/// Aggregate signatures into a single signature of the same size.
+/// Errors on an empty list or an invalid signature.
+pub fn aggregate_signatures(
+    signatures: &[&[u8]],
+) -> LairResult<[u8; SIGNATURE_LEN]> {
+    if signatures.is_empty() {
+        return Err("no bls signatures to aggregate".into());
+    }
+    let signatures = signatures
+        .iter()
+        .map(|signature| decode_signature(signature))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("invalid bls signature")?;
+    let signatures = signatures.iter().collect::<Vec<_>>();
+    let aggregate = AggregateSignature::aggregate(&signatures, true)
+        .map_err(|_| "invalid bls signature")?;
+    Ok(aggregate.to_signature().compress())
+}
+
+/// Verify an aggregate signature of each `(pub_key, message)` pair,
+/// under the domain separation tag `dst`.
+/// An empty list of pairs never verifies.
+pub fn aggregate_verify(
+    pub_keys_messages: &[(&[u8], &[u8])],
+    signature: &[u8],
+    dst: &[u8],
+) -> bool {
+    if pub_keys_messages.is_empty() || check_dst(dst).is_err() {
+        return false;
+    }
+    let pub_keys = match pub_keys_messages
+        .iter()
+        .map(|(pub_key, _)| decode_pub_key(pub_key))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(pub_keys) => pub_keys,
+        None => return false,
+    };
+    let signature = match decode_signature(signature) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let pub_keys = pub_keys.iter().collect::<Vec<_>>();
+    let messages = pub_keys_messages
+        .iter()
+        .map(|(_, message)| *message)
+        .collect::<Vec<_>>();
+    signature.aggregate_verify(true, &messages, dst, &pub_keys, false)
+        == BLST_ERROR::BLST_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// RFC 9380 appendix J.10.1, the `BLS12381G2_XMD:SHA-256_SSWU_RO_`
+    /// vectors (message, compressed point). Signing with the private
+    /// key `1` is exactly hash to G2.
+    const RFC9380_DST: &[u8] =
+        b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+    const RFC9380_VECTORS: &[(&str, &str)] = &[
+        (
+            "",
+            "a5cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff5bf5dd71b72418717047f5b0f37da03d0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d4ac44c1038e9dcdd5393faf5c41fb78a",
+        ),
+        (
+            "abc",
+            "939cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6",
+        ),
+    ];
+
+    /// The ethereum consensus spec `sign` vectors, `DST_POP`
+    /// (message byte, repeated 32 times, signature).
+    const ETH_PRIV_KEY: &str =
+        "263dbd792f5b1be47ed85f8938c0f29586af0d3ac7b977f21c278fe1462040e3";
+    const ETH_PUB_KEY: &str = "a491d1b0ecd9bb917989f0e74f0dea0422eac4a873e5e2644f368dffb9a6e20fd6e10c1b77654d067c0618f6e5a7f79a";
+    const ETH_SIGN_VECTORS: &[(u8, &str)] = &[
+        (
+            0x00,
+            "b6ed936746e01f8ecf281f020953fbf1f01debd5657c4a383940b020b26507f6076334f91e2366c96e9ab279fb5158090352ea1c5b0c9274504f4f0e7053af24802e51e4568d164fe986834f41e55c8e850ce1f98458c0cfc9ab380b55285a55",
+        ),
+        (
+            0x56,
+            "882730e5d03f6b42c3abc26d3372625034e1d871b65a8a6b900a56dae22da98abbe1b68f85e49fe7652a55ec3d0591c20767677e33e5cbb1207315c41a9ac03be39c2e7668edc043d6cb1d9fd93033caa8a1c5b0e84bedaeb6c64972503a43eb",
+        ),
+        (
+            0xab,
+            "91347bccf740d859038fcdcaf233eeceb2a436bcaaee9b2aa3bfb70efe29dfb2677562ccbea1c8e061fb9971b0753c240622fab78489ce96768259fc01360346da5b9f579e5da0d941e4c6ba18a0e64906082375394f337fa1af2b7127b0d121",
+        ),
+    ];
+
+    /// The aggregate of the three `ETH_SIGN_VECTORS` signatures.
+    const ETH_AGGREGATE: &str = "a0ea0bf29ba501a384b4b060c4f61db954fc18e1aa5cfe69bb285cc2683f16e9f82f99e6898329af9794dd504d4b80740e37f02b3b6531a35f3026c02510d153c3385d64e5f0023b6e5ef3385c0e9e0eea3157b8cf4b1b1eda29623290b8cf08";
+
+    fn test_priv_key(i: usize) -> [u8; 32] {
+        let mut ikm = [0; 32];
+        ikm[..8].copy_from_slice(&(i as u64).to_le_bytes());
+        key_gen(&ikm, &[]).unwrap()
+    }
+
+    #[test]
+    fn it_passes_rfc9380_hash_to_g2_vectors() {
+        let mut one = [0; 32];
+        one[31] = 1;
+        for (message, point) in RFC9380_VECTORS {
+            let signature = sign(&one, message.as_bytes(), RFC9380_DST);
+            assert_eq!(hex(point), signature.unwrap().to_vec());
+        }
+    }
+
+    #[test]
+    fn it_passes_eip2333_key_gen_vector() {
+        let seed = hex("c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04");
+        assert_eq!(
+            hex("0d7359d57963ab8fbbde1852dcf553fedbc31f464d80ee7d40ae683122b45070"),
+            key_gen(&seed, &[]).unwrap().to_vec(),
+        );
+        assert!(key_gen(&[0; 31], &[]).is_err());
+    }
+
+    #[test]
+    fn it_passes_eth_sign_vectors() {
+        let priv_key = hex(ETH_PRIV_KEY);
+        let pub_key = hex(ETH_PUB_KEY);
+        assert_eq!(pub_key, pub_key_from_priv_key(&priv_key).unwrap());
+
+        let mut signatures = Vec::new();
+        let mut pairs = Vec::new();
+        for (byte, signature) in ETH_SIGN_VECTORS {
+            let message = vec![*byte; 32];
+            let signature = hex(signature);
+            assert_eq!(
+                signature,
+                sign(&priv_key, &message, DST_POP).unwrap().to_vec(),
+            );
+            assert!(verify(&pub_key, &message, &signature, DST_POP));
+            assert!(!verify(&pub_key, &message, &signature, DST_BASIC));
+            signatures.push(signature);
+            pairs.push((pub_key.clone(), message));
+        }
+
+        let signatures =
+            signatures.iter().map(|s| s.as_slice()).collect::<Vec<_>>();
+        let aggregate = aggregate_signatures(&signatures).unwrap();
+        assert_eq!(hex(ETH_AGGREGATE), aggregate.to_vec());
+        let pairs = pairs
+            .iter()
+            .map(|(p, m)| (p.as_slice(), m.as_slice()))
+            .collect::<Vec<_>>();
+        assert!(aggregate_verify(&pairs, &aggregate, DST_POP));
+    }
+
+    #[test]
+    fn it_refuses_bad_keys() {
+        assert!(pub_key_from_priv_key(&[0; 32]).is_err());
+        assert!(pub_key_from_priv_key(&[0xff; 32]).is_err());
+        assert!(pub_key_from_priv_key(&[1; 31]).is_err());
+
+        let priv_key = test_priv_key(0);
+        let signature = sign(&priv_key, b"msg", DST_BASIC).unwrap();
+        // the compressed identity
+        let mut identity = [0; PUB_KEY_LEN];
+        identity[0] = 0xc0;
+        assert!(!verify(&identity, b"msg", &signature, DST_BASIC));
+        assert!(!verify(&[0x42; PUB_KEY_LEN], b"msg", &signature, DST_BASIC));
+    }
+
+    fn check_aggregate(count: usize) {
+        let keys = (0..count)
+            .map(|i| {
+                let priv_key = test_priv_key(i);
+                let pub_key = pub_key_from_priv_key(&priv_key).unwrap();
+                let message = format!("message {}", i).into_bytes();
+                let signature = sign(&priv_key, &message, DST_BASIC).unwrap();
+                (pub_key, message, signature)
+            })
+            .collect::<Vec<_>>();
+        let signatures =
+            keys.iter().map(|(_, _, s)| &s[..]).collect::<Vec<_>>();
+        let pairs = keys
+            .iter()
+            .map(|(p, m, _)| (p.as_slice(), m.as_slice()))
+            .collect::<Vec<_>>();
+
+        let aggregate = aggregate_signatures(&signatures).unwrap();
+        assert!(aggregate_verify(&pairs, &aggregate, DST_BASIC));
+        assert!(!aggregate_verify(&pairs, &aggregate, DST_POP));
+        if count == 1 {
+            assert_eq!(keys[0].2, aggregate);
+        } else {
+            // a missing member fails
+            assert!(!aggregate_verify(&pairs[1..], &aggregate, DST_BASIC));
+        }
+
+        // a deliberately corrupted member fails: a valid signature,
+        // but of another message
+        let corrupt = count / 2;
+        let other = sign(&test_priv_key(corrupt), b"other", DST_BASIC).unwrap();
+        let mut corrupted = signatures.clone();
+        corrupted[corrupt] = &other[..];
+        let aggregate = aggregate_signatures(&corrupted).unwrap();
+        assert!(!aggregate_verify(&pairs, &aggregate, DST_BASIC));
+
+        // as does one that no longer decodes
+        let mut flipped = keys[corrupt].2;
+        flipped[SIGNATURE_LEN - 1] ^= 1;
+        corrupted[corrupt] = &flipped[..];
+        match aggregate_signatures(&corrupted) {
+            Err(_) => (),
+            Ok(aggregate) => {
+                assert!(!aggregate_verify(&pairs, &aggregate, DST_BASIC))
+            }
+        }
+    }
+
+    #[test]
+    fn it_aggregates_one_signature() {
+        check_aggregate(1);
+    }
+
+    #[test]
+    fn it_aggregates_two_signatures() {
+        check_aggregate(2);
+    }
+
+    #[test]
+    fn it_aggregates_a_hundred_signatures() {
+        check_aggregate(100);
+    }
+
+    #[test]
+    fn it_refuses_bad_dsts() {
+        let priv_key = test_priv_key(0);
+        let pub_key = pub_key_from_priv_key(&priv_key).unwrap();
+        assert!(sign(&priv_key, b"msg", &[]).is_err());
+        assert!(sign(&priv_key, b"msg", &[b'a'; MAX_DST_LEN + 1]).is_err());
+        let signature = sign(&priv_key, b"msg", &[b'a'; MAX_DST_LEN]).unwrap();
+        assert!(verify(&pub_key, b"msg", &signature, &[b'a'; MAX_DST_LEN]));
+        assert!(!verify(&pub_key, b"msg", &signature, &[]));
+    }
+
+    #[test]
+    fn it_refuses_empty_aggregates() {
+        assert!(aggregate_signatures(&[]).is_err());
+        let signature = sign(&test_priv_key(0), b"msg", DST_BASIC).unwrap();
+        assert!(!aggregate_verify(&[], &signature, DST_BASIC));
+    }
+}
@@ -1,21 +1,209 @@
+use crate::actor::{LairCapabilities, SignEd25519PubKey};
+use crate::device_secret::*;
+use crate::hardware::*;
+use crate::internal::clock::*;
+use crate::internal::entropy::*;
+use crate::internal::unlock::UnlockKdfLimits;
+use crate::passphrase_strength::*;
+use crate::LairConnectionUrl;
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+/// What the server does with events for a client that is not reading
+/// them fast enough, once the connection's event buffer is full.
+/// Events the client must answer, such as `request_unlock_passphrase`,
+/// are never silently dropped - if one cannot be buffered, it fails,
+/// failing the operation that raised it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SlowConsumerPolicy {
+    /// Drop the oldest droppable (progress) event, and tell the client
+    /// how many were dropped with an `events_dropped` event,
+    /// sent ahead of its next event.
+    #[default]
+    DropOldest,
+
+    /// Disconnect the client.
+    Disconnect,
+}
+
+/// What generating key material does while the entropy source fails
+/// its health check, see `entropy::health_check`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EntropyFailurePolicy {
+    /// Fail the request with `LairError::EntropyUnavailable`.
+    #[default]
+    Fail,
+
+    /// Log a warning, and retry the check every
+    /// `ENTROPY_RETRY_INTERVAL` until it passes, or the request
+    /// times out.
+    Block,
+}
+
+/// What expiry enforcement does while the wall clock is skewed,
+/// i.e. has jumped backwards, see `clock::ClockMonitor`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockSkewPolicy {
+    /// Refuse to sign with keys that have an expiry, failing with
+    /// `LairError::Forbidden`, as they may have expired.
+    #[default]
+    FailClosed,
+
+    /// Log a warning, and sign with keys that have an expiry,
+    /// without checking it.
+    FailOpen,
+}
+
+/// How often a request blocked by `EntropyFailurePolicy::Block`
+/// retries the entropy health check.
+pub const ENTROPY_RETRY_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(500);
+
+/// The default number of ephemeral keys each connection may hold,
+/// see `ConfigBuilder::set_max_ephemeral_keys`.
+pub const DEFAULT_MAX_EPHEMERAL_KEYS: usize = 64;
+
+/// The default permission bits of the unix sockets lair binds,
+/// owner only, see `ConfigBuilder::set_socket_mode`.
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+/// How often a server with a trash retention (see
+/// `ConfigBuilder::set_trash_retention`) checks for entries to purge.
+pub const AUTO_PURGE_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(10 * 60);
+
+/// How requests using secret material wait for a locked store,
+/// see `ConfigBuilder::set_queue_while_locked`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockedQueueOptions {
+    /// The most requests waiting on each store at once. Any more
+    /// fail with `LairError::KeystoreLocked` straight away.
+    pub max_len: usize,
+
+    /// How long a request waits, before failing with
+    /// `LairError::KeystoreLocked`.
+    pub max_wait: std::time::Duration,
+}
+
+impl Default for LockedQueueOptions {
+    fn default() -> Self {
+        Self {
+            max_len: 64,
+            max_wait: std::time::Duration::from_secs(60),
+        }
+    }
+}
+
+/// How far connections may elevate their capabilities,
+/// see `ConfigBuilder::set_elevation`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElevationOptions {
+    /// The capabilities a connection may be granted on top of its own,
+    /// those its listener also grants. `LairCapability::CanUnlock` and
+    /// `LairCapability::Audit`, deciding the events a connection is
+    /// sent, are never granted.
+    pub capabilities: LairCapabilities,
+
+    /// The longest a connection may hold them at once.
+    pub max_duration: std::time::Duration,
+}
+
+/// The settings of a running server that may change without a restart,
+/// see `Config::set_runtime_settings`. Each is read as it is used: a
+/// change applies to the connections accepted, and the trash purged,
+/// after it. Connections already open keep the settings they were
+/// accepted with.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuntimeSettings {
+    /// See `ConfigBuilder::set_max_connections`.
+    pub max_connections: Option<usize>,
+
+    /// See `ConfigBuilder::set_idle_connection_timeout`.
+    pub idle_connection_timeout: Option<std::time::Duration>,
+
+    /// See `ConfigBuilder::set_event_buffer_size`.
+    pub event_buffer_size: usize,
+
+    /// See `ConfigBuilder::set_slow_consumer_policy`.
+    pub slow_consumer_policy: SlowConsumerPolicy,
+
+    /// See `ConfigBuilder::set_trash_retention`.
+    pub trash_retention: Option<std::time::Duration>,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            idle_connection_timeout: None,
+            event_buffer_size: 10,
+            slow_consumer_policy: SlowConsumerPolicy::default(),
+            trash_retention: None,
+        }
+    }
+}
+
 /// Lair configuration struct.
+#[derive(Clone)]
 pub struct Config {
     root_path: PathBuf,
     store_path: PathBuf,
+    tags_path: PathBuf,
+    trash_path: PathBuf,
+    inactive_path: PathBuf,
+    stores_path: PathBuf,
+    store_number: u8,
+    server_id_path: PathBuf,
+    clock_path: PathBuf,
+    device_binding_path: PathBuf,
     pid_path: PathBuf,
     socket_path: PathBuf,
+    connection_url: Option<LairConnectionUrl>,
+    extra_listeners: Vec<LairConnectionUrl>,
+    listener_capabilities: HashMap<LairConnectionUrl, LairCapabilities>,
+    connection_tokens: Vec<(String, LairCapabilities)>,
+    elevation: Option<ElevationOptions>,
+    connection_url_path: PathBuf,
     stdout_path: PathBuf,
     stderr_path: PathBuf,
+    ssh_agent_socket_path: Option<PathBuf>,
+    socket_mode: u32,
+    hide_paths: bool,
+    wire_compression: bool,
+    expected_server_identity: Option<SignEd25519PubKey>,
+    allow_unproven_server_identity: bool,
+    deep_lock: bool,
+    danger_allow_insecure_permissions: bool,
+    danger_allow_invalid_sni: bool,
+    unlock_kdf_limits: UnlockKdfLimits,
+    min_passphrase_score: u8,
+    allow_weak_passphrase: bool,
+    runtime_settings: Arc<std::sync::RwLock<RuntimeSettings>>,
+    settings_path: Option<PathBuf>,
+    request_timeout: Option<std::time::Duration>,
+    max_ephemeral_keys: usize,
+    queue_while_locked: Option<LockedQueueOptions>,
+    strict_load: bool,
+    forbid_seed_export: bool,
+    auto_activate_imports: bool,
+    auto_migrate: bool,
+    entropy: EntropySourceHandle,
+    entropy_failure_policy: EntropyFailurePolicy,
+    entropy_sanity_check: bool,
+    entropy_health: Arc<EntropyHealth>,
+    clock_monitor: Arc<ClockMonitor>,
+    clock_skew_policy: ClockSkewPolicy,
+    device_secret_store: DeviceSecretStoreHandle,
+    hardware_token: Option<HardwareTokenHandle>,
 }
 
 impl Config {
     pub(crate) fn finalize(mut self) -> Arc<Config> {
-        std::fs::create_dir_all(self.root_path.as_path())
+        crate::internal::util::create_dir_all_private(self.root_path.as_path())
             .expect("can cannonicalize root path");
         self.root_path = self
             .root_path
@@ -23,10 +211,29 @@ impl Config {
             .expect("can cannonicalize root path");
         self.store_path = self.root_path.clone();
         self.store_path.push("store");
+        self.tags_path = self.root_path.clone();
+        self.tags_path.push("tags");
+        self.trash_path = self.root_path.clone();
+        self.trash_path.push("trash");
+        self.inactive_path = self.root_path.clone();
+        self.inactive_path.push("inactive");
+        self.stores_path = self.root_path.clone();
+        self.stores_path.push("stores");
+        self.server_id_path = self.root_path.clone();
+        self.server_id_path.push("server_id");
+        self.clock_path = self.root_path.clone();
+        self.clock_path.push("clock");
+        self.device_binding_path = self.root_path.clone();
+        self.device_binding_path.push("device_binding");
         self.pid_path = self.root_path.clone();
         self.pid_path.push("pid");
         self.socket_path = self.root_path.clone();
         self.socket_path.push("socket");
+        self.connection_url_path = self.root_path.clone();
+        self.connection_url_path.push("connection_url");
+        if self.connection_url.is_none() {
+            self.connection_url = Some(default_connection_url(&self));
+        }
         self.stdout_path = self.root_path.clone();
         self.stdout_path.push("stdout");
         self.stderr_path = self.root_path.clone();
@@ -49,6 +256,69 @@ impl Config {
         self.store_path.as_path()
     }
 
+    /// Get the path to the file persisting entry tags.
+    pub fn get_tags_path(&self) -> &Path {
+        self.tags_path.as_path()
+    }
+
+    /// Get the path to the file persisting which entries are trashed.
+    pub fn get_trash_path(&self) -> &Path {
+        self.trash_path.as_path()
+    }
+
+    /// Get the path to the file persisting which imported entries
+    /// are not activated yet.
+    pub fn get_inactive_path(&self) -> &Path {
+        self.inactive_path.as_path()
+    }
+
+    /// Get the directory holding the named stores,
+    /// each in a sub-directory of its own.
+    pub fn get_stores_path(&self) -> &Path {
+        self.stores_path.as_path()
+    }
+
+    /// Get the number of the store this config describes,
+    /// 0 for the default store. See `KeystoreIndex::new`.
+    pub fn get_store_number(&self) -> u8 {
+        self.store_number
+    }
+
+    /// Derive the config of the named store number `store_number`, kept
+    /// in `store_dir`. Only the store, tags, trash and inactive files differ, the
+    /// server identity and all other settings are shared with this config.
+    pub fn for_named_store(
+        &self,
+        store_number: u8,
+        store_dir: &Path,
+    ) -> Arc<Config> {
+        let mut out = self.clone();
+        out.store_number = store_number;
+        out.store_path = store_dir.join("store");
+        out.tags_path = store_dir.join("tags");
+        out.trash_path = store_dir.join("trash");
+        out.inactive_path = store_dir.join("inactive");
+        Arc::new(out)
+    }
+
+    /// Get the path to the file persisting the server identity keypair.
+    pub fn get_server_id_path(&self) -> &Path {
+        self.server_id_path.as_path()
+    }
+
+    /// Get the path to the file persisting the last wall clock time
+    /// the server saw, to detect clock skew across restarts.
+    pub fn get_clock_path(&self) -> &Path {
+        self.clock_path.as_path()
+    }
+
+    /// Get the path to the file recording the reference to the device
+    /// secret the lair directory is bound to, if it is bound,
+    /// see `device_secret`.
+    pub fn get_device_binding_path(&self) -> &Path {
+        self.device_binding_path.as_path()
+    }
+
     /// Get the path to the lair pidfile.
     pub fn get_pid_path(&self) -> &Path {
         self.pid_path.as_path()
@@ -59,6 +329,54 @@ impl Config {
         self.socket_path.as_path()
     }
 
+    /// Get the url clients use to connect to lair.
+    /// Defaults to the ipc socket at [`Config::get_socket_path`].
+    pub fn get_connection_url(&self) -> &LairConnectionUrl {
+        self.connection_url
+            .as_ref()
+            .expect("connection url is set on finalize")
+    }
+
+    /// Get every url the server listens on: the connection url first,
+    /// then any added with [`ConfigBuilder::add_listener`].
+    pub fn get_listener_urls(&self) -> Vec<&LairConnectionUrl> {
+        std::iter::once(self.get_connection_url())
+            .chain(self.extra_listeners.iter())
+            .collect()
+    }
+
+    /// Get the capabilities the listener at `url` grants
+    /// its connections, every capability unless set with
+    /// [`ConfigBuilder::set_listener_capabilities`].
+    pub fn get_listener_capabilities(
+        &self,
+        url: &LairConnectionUrl,
+    ) -> LairCapabilities {
+        self.listener_capabilities
+            .get(url)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get the connection tokens added with
+    /// [`ConfigBuilder::add_connection_token`], with their capabilities.
+    pub fn get_connection_tokens(&self) -> &[(String, LairCapabilities)] {
+        &self.connection_tokens
+    }
+
+    /// Get how far connections may elevate their capabilities,
+    /// if at all.
+    pub fn get_elevation(&self) -> Option<ElevationOptions> {
+        self.elevation
+    }
+
+    /// Get the path to the file the running server
+    /// writes its listener urls into, one per line,
+    /// the connection url first.
+    pub fn get_connection_url_path(&self) -> &Path {
+        self.connection_url_path.as_path()
+    }
+
     /// Get the path to the lair stdout file.
     pub fn get_stdout_path(&self) -> &Path {
         self.stdout_path.as_path()
@@ -68,6 +386,227 @@ impl Config {
     pub fn get_stderr_path(&self) -> &Path {
         self.stderr_path.as_path()
     }
+
+    /// Get the path to the ssh-agent protocol socket, if enabled.
+    pub fn get_ssh_agent_socket_path(&self) -> Option<&Path> {
+        self.ssh_agent_socket_path.as_deref()
+    }
+
+    /// Get the permission bits of the unix sockets the server binds.
+    /// Default `DEFAULT_SOCKET_MODE`.
+    pub fn get_socket_mode(&self) -> u32 {
+        self.socket_mode
+    }
+
+    /// If true, the server refuses to tell clients its on-disk paths.
+    pub fn get_hide_paths(&self) -> bool {
+        self.hide_paths
+    }
+
+    /// If true, connections negotiate compressing large frames,
+    /// when built with the `compression` feature. Default `false`.
+    pub fn get_wire_compression(&self) -> bool {
+        self.wire_compression
+    }
+
+    /// The server identity pub key clients require the server
+    /// to prove it holds as they connect, if any. Default `None`.
+    pub fn get_expected_server_identity(&self) -> Option<&SignEd25519PubKey> {
+        self.expected_server_identity.as_ref()
+    }
+
+    /// If true, clients expecting a server identity still connect to
+    /// servers too old to prove theirs. Default `false`.
+    pub fn get_allow_unproven_server_identity(&self) -> bool {
+        self.allow_unproven_server_identity
+    }
+
+    /// If true, the server refuses all requests but
+    /// `lair_get_server_info` until it is unlocked.
+    pub fn get_deep_lock(&self) -> bool {
+        self.deep_lock
+    }
+
+    /// If true, the server starts even if its files are writable
+    /// by other users, only logging a warning.
+    pub fn get_danger_allow_insecure_permissions(&self) -> bool {
+        self.danger_allow_insecure_permissions
+    }
+
+    /// If true, imported tls certs need only a printable sni,
+    /// not a valid hostname.
+    pub fn get_danger_allow_invalid_sni(&self) -> bool {
+        self.danger_allow_invalid_sni
+    }
+
+    /// Get the argon2id limits a new unlock passphrase is derived with.
+    pub fn get_unlock_kdf_limits(&self) -> UnlockKdfLimits {
+        self.unlock_kdf_limits
+    }
+
+    /// Get the minimum `estimate_passphrase_strength` score
+    /// a new unlock passphrase must have.
+    pub fn get_min_passphrase_score(&self) -> u8 {
+        self.min_passphrase_score
+    }
+
+    /// If true, new unlock passphrases scoring below
+    /// `get_min_passphrase_score` are accepted anyway.
+    pub fn get_allow_weak_passphrase(&self) -> bool {
+        self.allow_weak_passphrase
+    }
+
+    /// Get the settings that may change while the server runs.
+    pub fn get_runtime_settings(&self) -> RuntimeSettings {
+        // plain values, never left half written
+        *self
+            .runtime_settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the settings that may change while the server runs,
+    /// for this config and every config sharing them, such as those
+    /// of its named stores. See `RuntimeSettings`.
+    pub fn set_runtime_settings(&self, settings: RuntimeSettings) {
+        *self
+            .runtime_settings
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = settings;
+    }
+
+    /// Get the file the runtime settings are reloaded from, if any.
+    pub fn get_settings_path(&self) -> Option<&Path> {
+        self.settings_path.as_deref()
+    }
+
+    /// Get the number of events buffered for each connection,
+    /// while its client is not reading them.
+    pub fn get_event_buffer_size(&self) -> usize {
+        self.get_runtime_settings().event_buffer_size
+    }
+
+    /// Get what happens to a connection's events once its buffer is full.
+    pub fn get_slow_consumer_policy(&self) -> SlowConsumerPolicy {
+        self.get_runtime_settings().slow_consumer_policy
+    }
+
+    /// Get how long a client waits on each request, if it gives up at all.
+    pub fn get_request_timeout(&self) -> Option<std::time::Duration> {
+        self.request_timeout
+    }
+
+    /// Get the number of connections the server serves at once,
+    /// if it limits them at all.
+    pub fn get_max_connections(&self) -> Option<usize> {
+        self.get_runtime_settings().max_connections
+    }
+
+    /// Get the number of ephemeral keys each connection may hold at once.
+    pub fn get_max_ephemeral_keys(&self) -> usize {
+        self.max_ephemeral_keys
+    }
+
+    /// Get how long a connection may go without traffic before the
+    /// server closes it, if it closes idle connections at all.
+    pub fn get_idle_connection_timeout(&self) -> Option<std::time::Duration> {
+        self.get_runtime_settings().idle_connection_timeout
+    }
+
+    /// Get how long entries stay in the trash before they are purged
+    /// automatically, if they are purged automatically at all.
+    pub fn get_trash_retention(&self) -> Option<std::time::Duration> {
+        self.get_runtime_settings().trash_retention
+    }
+
+    /// Get how requests using secret material wait for a locked store,
+    /// if they wait at all.
+    pub fn get_queue_while_locked(&self) -> Option<LockedQueueOptions> {
+        self.queue_while_locked
+    }
+
+    /// If true, a store with any undecodable entry fails to load,
+    /// instead of quarantining those entries.
+    pub fn get_strict_load(&self) -> bool {
+        self.strict_load
+    }
+
+    /// If true, `sign_ed25519_export_seed` fails for every entry.
+    pub fn get_forbid_seed_export(&self) -> bool {
+        self.forbid_seed_export
+    }
+
+    /// If true, imported entries may be used right away,
+    /// without `lair_activate_entry`.
+    pub fn get_auto_activate_imports(&self) -> bool {
+        self.auto_activate_imports
+    }
+
+    /// If true, stores in a legacy layout are migrated when they are
+    /// first unlocked, instead of refusing to open them.
+    pub fn get_auto_migrate(&self) -> bool {
+        self.auto_migrate
+    }
+
+    /// Get the entropy source used for key and certificate generation.
+    pub fn get_entropy(&self) -> &EntropySourceHandle {
+        &self.entropy
+    }
+
+    /// Get what generating key material does while the entropy source
+    /// fails its health check.
+    pub fn get_entropy_failure_policy(&self) -> EntropyFailurePolicy {
+        self.entropy_failure_policy
+    }
+
+    /// If true, the entropy health check also checks the entropy
+    /// source output does not look stuck.
+    pub fn get_entropy_sanity_check(&self) -> bool {
+        self.entropy_sanity_check
+    }
+
+    /// Get the record of the last entropy health check.
+    pub fn get_entropy_health(&self) -> &Arc<EntropyHealth> {
+        &self.entropy_health
+    }
+
+    /// Get the clock time dependent features read.
+    pub fn get_clock(&self) -> &ClockHandle {
+        self.clock_monitor.clock()
+    }
+
+    /// Get the monitor detecting skew of the configured clock.
+    pub fn get_clock_monitor(&self) -> &Arc<ClockMonitor> {
+        &self.clock_monitor
+    }
+
+    /// Get what expiry enforcement does while the wall clock is skewed.
+    pub fn get_clock_skew_policy(&self) -> ClockSkewPolicy {
+        self.clock_skew_policy
+    }
+
+    /// Get the platform keystore holding device secrets.
+    pub fn get_device_secret_store(&self) -> &DeviceSecretStoreHandle {
+        &self.device_secret_store
+    }
+
+    /// Get the hardware token `KeyBacking::Hardware` keys are made on,
+    /// if one is configured.
+    pub fn get_hardware_token(&self) -> Option<&HardwareTokenHandle> {
+        self.hardware_token.as_ref()
+    }
+}
+
+#[cfg(not(windows))]
+fn default_connection_url(config: &Config) -> LairConnectionUrl {
+    LairConnectionUrl::new_unix_socket(config.socket_path.clone())
+        .expect("canonicalized socket path is absolute")
+}
+
+#[cfg(windows)]
+fn default_connection_url(_config: &Config) -> LairConnectionUrl {
+    LairConnectionUrl::new_windows_pipe("lair-keystore")
+        .expect("valid pipe name")
 }
 
 /// Lair configuration builder.
@@ -80,10 +619,57 @@ impl Default for ConfigBuilder {
         Self(Config {
             root_path: pdir.data_local_dir().to_path_buf(),
             store_path: PathBuf::new(),
+            tags_path: PathBuf::new(),
+            trash_path: PathBuf::new(),
+            inactive_path: PathBuf::new(),
+            stores_path: PathBuf::new(),
+            store_number: 0,
+            server_id_path: PathBuf::new(),
+            clock_path: PathBuf::new(),
+            device_binding_path: PathBuf::new(),
             pid_path: PathBuf::new(),
             socket_path: PathBuf::new(),
+            connection_url: None,
+            extra_listeners: Vec::new(),
+            listener_capabilities: HashMap::new(),
+            connection_tokens: Vec::new(),
+            elevation: None,
+            connection_url_path: PathBuf::new(),
             stdout_path: PathBuf::new(),
             stderr_path: PathBuf::new(),
+            ssh_agent_socket_path: None,
+            socket_mode: DEFAULT_SOCKET_MODE,
+            hide_paths: false,
+            wire_compression: false,
+            expected_server_identity: None,
+            allow_unproven_server_identity: false,
+            deep_lock: false,
+            danger_allow_insecure_permissions: false,
+            danger_allow_invalid_sni: false,
+            unlock_kdf_limits: UnlockKdfLimits::default(),
+            min_passphrase_score: DEFAULT_MIN_PASSPHRASE_SCORE,
+            allow_weak_passphrase: false,
+            runtime_settings: Arc::new(std::sync::RwLock::new(
+                RuntimeSettings::default(),
+            )),
+            settings_path: None,
+            request_timeout: None,
+            max_ephemeral_keys: DEFAULT_MAX_EPHEMERAL_KEYS,
+            queue_while_locked: None,
+            strict_load: false,
+            forbid_seed_export: false,
+            auto_activate_imports: false,
+            auto_migrate: false,
+            entropy: OsEntropy::new_handle(),
+            entropy_failure_policy: EntropyFailurePolicy::default(),
+            entropy_sanity_check: true,
+            entropy_health: Arc::new(EntropyHealth::default()),
+            clock_monitor: Arc::new(ClockMonitor::new(
+                SystemClock::new_handle(),
+            )),
+            clock_skew_policy: ClockSkewPolicy::default(),
+            device_secret_store: default_device_secret_store(),
+            hardware_token: None,
         })
     }
 }
@@ -99,6 +685,19 @@ impl ConfigBuilder {
         self.0.finalize()
     }
 
+    /// A builder starting from an already built `config`.
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Self(config.clone())
+    }
+
+    /// Obtain a Config for a client connecting to the connection url,
+    /// which must be set. Unlike `build`, nothing is created on disk,
+    /// clients only read the connection url and their own options.
+    pub(crate) fn build_client(self) -> Arc<Config> {
+        debug_assert!(self.0.connection_url.is_some());
+        Arc::new(self.0)
+    }
+
     /// Override the default data directory.
     pub fn set_root_path<P>(mut self, p: P) -> Self
     where
@@ -107,4 +706,377 @@ impl ConfigBuilder {
         self.0.root_path = p.into();
         self
     }
+
+    /// Override the default connection url (the ipc socket
+    /// in the data directory). Clients connect to this url,
+    /// and the server binds it.
+    pub fn set_connection_url(mut self, url: LairConnectionUrl) -> Self {
+        self.0.connection_url = Some(url);
+        self
+    }
+
+    /// Have the server also listen on `url`, e.g. a socket in a volume
+    /// shared with a sandbox. If `url` carries a token, connections to
+    /// this listener must present it before anything else is served.
+    /// Clients are unaffected, they connect to the connection url.
+    pub fn add_listener(mut self, url: LairConnectionUrl) -> Self {
+        self.0.extra_listeners.push(url);
+        self
+    }
+
+    /// Limit the capabilities of connections to the listener at `url`,
+    /// the connection url or one added with `add_listener`.
+    /// Requests needing others are refused as forbidden.
+    /// Listeners grant every capability by default.
+    pub fn set_listener_capabilities(
+        mut self,
+        url: LairConnectionUrl,
+        capabilities: LairCapabilities,
+    ) -> Self {
+        self.0.listener_capabilities.insert(url, capabilities);
+        self
+    }
+
+    /// Accept `token` on every listener requiring a token, besides the
+    /// token of its url, granting the connections presenting it
+    /// `capabilities` - those the listener also grants.
+    pub fn add_connection_token(
+        mut self,
+        token: impl Into<String>,
+        capabilities: LairCapabilities,
+    ) -> Self {
+        self.0.connection_tokens.push((token.into(), capabilities));
+        self
+    }
+
+    /// Let connections elevate their capabilities, for a time, as far as
+    /// `options` allows, see `LairClientApi::lair_request_elevation`.
+    /// Every grant, drop and expiry is audited. By default connections
+    /// may not elevate.
+    pub fn set_elevation(mut self, options: ElevationOptions) -> Self {
+        self.0.elevation = Some(options);
+        self
+    }
+
+    /// Enable the ssh-agent protocol listener, binding it at this path.
+    /// The listener is disabled by default.
+    pub fn set_ssh_agent_socket_path<P>(mut self, p: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.0.ssh_agent_socket_path = Some(p.into());
+        self
+    }
+
+    /// Set the permission bits of the unix sockets the server binds -
+    /// the connection url, those added with `add_listener`, and the
+    /// ssh-agent socket - e.g. `0o660` to let a group connect.
+    /// They are set before the socket appears at its path. Sockets
+    /// must stay readable and writable by their owner. On platforms
+    /// where connecting ignores the socket's own permissions, only
+    /// the permissions of its directory protect it. Ignored on windows.
+    /// Defaults to `DEFAULT_SOCKET_MODE`.
+    pub fn set_socket_mode(mut self, mode: u32) -> Self {
+        self.0.socket_mode = mode;
+        self
+    }
+
+    /// Refuse `lair_get_server_paths` requests with a
+    /// `LairError::Forbidden` error. Paths are exposed by default.
+    pub fn set_hide_paths(mut self, hide_paths: bool) -> Self {
+        self.0.hide_paths = hide_paths;
+        self
+    }
+
+    /// Negotiate compressing frames longer than
+    /// `internal::wire::spec::COMPRESSION_THRESHOLD`, on clients and
+    /// servers built with the `compression` feature. Both sides must
+    /// enable it, clients then negotiate it as they connect, after
+    /// authenticating. Disabled by default.
+    pub fn set_wire_compression(mut self, wire_compression: bool) -> Self {
+        self.0.wire_compression = wire_compression;
+        self
+    }
+
+    /// Have clients require the server to prove it holds the identity
+    /// keypair of `id_pub_key`, as in `LairServerInfo::id_pub_key`, as
+    /// they connect, after authenticating and before answering any event.
+    /// The server signs its versions and a fresh client nonce, see
+    /// `LairClientApi::lair_prove_server_identity`. Connecting fails with
+    /// `LairError::ServerIdentityMismatch` if it cannot, e.g. as another
+    /// build or a deep locked server. Servers are not checked by default.
+    pub fn set_expected_server_identity(
+        mut self,
+        id_pub_key: SignEd25519PubKey,
+    ) -> Self {
+        self.0.expected_server_identity = Some(id_pub_key);
+        self
+    }
+
+    /// With an expected server identity, still connect to servers
+    /// predating `internal::wire::spec::SERVER_IDENTITY_VERSION`, which
+    /// cannot prove theirs, logging a warning. Refused by default.
+    pub fn set_allow_unproven_server_identity(mut self, allow: bool) -> Self {
+        self.0.allow_unproven_server_identity = allow;
+        self
+    }
+
+    /// While locked, refuse every request but `lair_get_server_info`
+    /// with a `LairError::KeystoreLocked` error, and keep no entries,
+    /// nor any public key / sni indexes, in memory.
+    /// By default, public material stays available while locked.
+    pub fn set_deep_lock(mut self, deep_lock: bool) -> Self {
+        self.0.deep_lock = deep_lock;
+        self
+    }
+
+    /// Set the argon2id limits the unlock passphrase is derived with,
+    /// when it is first set. Existing stores keep the limits they
+    /// were created with.
+    pub fn set_unlock_kdf_limits(mut self, limits: UnlockKdfLimits) -> Self {
+        self.0.unlock_kdf_limits = limits;
+        self
+    }
+
+    /// Set the minimum `estimate_passphrase_strength` score a new unlock
+    /// passphrase must have, when it is first set. A weaker passphrase
+    /// is refused with `LairError::WeakPassphrase`. Defaults to
+    /// `DEFAULT_MIN_PASSPHRASE_SCORE`, at most `MAX_PASSPHRASE_SCORE`.
+    pub fn set_min_passphrase_score(mut self, score: u8) -> Self {
+        self.0.min_passphrase_score = score.min(MAX_PASSPHRASE_SCORE);
+        self
+    }
+
+    /// Accept new unlock passphrases scoring below the minimum,
+    /// only logging a warning. Weak passphrases are refused by default.
+    pub fn set_allow_weak_passphrase(mut self, allow: bool) -> Self {
+        self.0.allow_weak_passphrase = allow;
+        self
+    }
+
+    /// Set the number of events buffered for each connection, while
+    /// its client is not reading them. Defaults to 10, at least 1.
+    pub fn set_event_buffer_size(mut self, event_buffer_size: usize) -> Self {
+        self.update_runtime_settings(|s| {
+            s.event_buffer_size = event_buffer_size.max(1)
+        });
+        self
+    }
+
+    /// Set what happens to a connection's events once its buffer is full.
+    /// Defaults to `SlowConsumerPolicy::DropOldest`.
+    pub fn set_slow_consumer_policy(
+        mut self,
+        policy: SlowConsumerPolicy,
+    ) -> Self {
+        self.update_runtime_settings(|s| s.slow_consumer_policy = policy);
+        self
+    }
+
+    /// Give up on client requests taking longer than `timeout`,
+    /// with a `LairError::Timeout`. The remaining time travels with each
+    /// request, so the server also stops working on it once it is up.
+    /// Requests wait indefinitely by default.
+    pub fn set_request_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.0.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Serve at most `max` connections at once, across all listeners.
+    /// Connections past the limit are refused with a
+    /// `LairError::TooManyConnections`, and closed. Connections are
+    /// unlimited by default.
+    pub fn set_max_connections(mut self, max: usize) -> Self {
+        self.update_runtime_settings(|s| s.max_connections = Some(max));
+        self
+    }
+
+    /// Let each connection hold at most `max` ephemeral keys at once.
+    /// Creating more fails with a `LairError::Forbidden`, until some
+    /// are dropped. Defaults to `DEFAULT_MAX_EPHEMERAL_KEYS`.
+    pub fn set_max_ephemeral_keys(mut self, max: usize) -> Self {
+        self.0.max_ephemeral_keys = max;
+        self
+    }
+
+    /// Close connections that go `timeout` without a request, a
+    /// response, or an event, and with no request in flight.
+    /// Their clients fail any later request with a
+    /// `LairError::ConnectionIdle`. Idle connections are kept by default.
+    pub fn set_idle_connection_timeout(
+        mut self,
+        timeout: std::time::Duration,
+    ) -> Self {
+        self.update_runtime_settings(|s| {
+            s.idle_connection_timeout = Some(timeout)
+        });
+        self
+    }
+
+    /// Purge trashed entries automatically once they have been in the
+    /// trash for `retention`. The trash is checked as each store is
+    /// loaded, and every `AUTO_PURGE_INTERVAL` after. By default trashed
+    /// entries are kept until they are purged with `lair_purge_trashed`.
+    pub fn set_trash_retention(
+        mut self,
+        retention: std::time::Duration,
+    ) -> Self {
+        self.update_runtime_settings(|s| s.trash_retention = Some(retention));
+        self
+    }
+
+    /// Read the runtime settings from the file at `path` as the server
+    /// starts, and again on `lair_reload_settings`. See `RuntimeSettings`.
+    pub fn set_settings_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.0.settings_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Builders start from a clone of a config that may be serving,
+    /// so they edit a copy of its runtime settings, never the shared ones.
+    fn update_runtime_settings(
+        &mut self,
+        f: impl FnOnce(&mut RuntimeSettings),
+    ) {
+        let mut settings = self.0.get_runtime_settings();
+        f(&mut settings);
+        self.0.runtime_settings = Arc::new(std::sync::RwLock::new(settings));
+    }
+
+    /// Park requests using secret material (signing, decrypting,
+    /// exporting or creating entries) while their store is locked,
+    /// instead of serving or refusing them as the lock allows. They run
+    /// in arrival order once it unlocks, or fail with
+    /// `LairError::KeystoreLocked` if the queue is full, or they wait
+    /// too long. Requests only reading public material, or tags,
+    /// are never parked. By default no request waits.
+    pub fn set_queue_while_locked(
+        mut self,
+        options: LockedQueueOptions,
+    ) -> Self {
+        self.0.queue_while_locked = Some(options);
+        self
+    }
+
+    /// Fail to load a store if any of its entries cannot be decoded.
+    /// By default such entries are quarantined, see
+    /// `lair_list_quarantined`, and the rest of the store is served.
+    pub fn set_strict_load(mut self, strict_load: bool) -> Self {
+        self.0.strict_load = strict_load;
+        self
+    }
+
+    /// Refuse `sign_ed25519_export_seed` for every entry, exportable
+    /// or not. Wrapping entries with `lair_wrap_entry` is unaffected.
+    pub fn set_forbid_seed_export(mut self, forbid: bool) -> Self {
+        self.0.forbid_seed_export = forbid;
+        self
+    }
+
+    /// Let imported entries be used right away. By default entries
+    /// imported from outside lair (seeds, ssh keys, totp secrets, wrapped
+    /// entries) land inactive, failing every use with
+    /// `LairError::EntryNotActivated` until `lair_activate_entry`.
+    pub fn set_auto_activate_imports(mut self, auto_activate: bool) -> Self {
+        self.0.auto_activate_imports = auto_activate;
+        self
+    }
+
+    /// Migrate stores in a legacy layout, e.g. one with an unencrypted
+    /// index, when they are first unlocked, keeping a backup beside them.
+    /// By default they are refused until migrated with
+    /// `lair-keystore migrate`.
+    pub fn set_auto_migrate(mut self, auto_migrate: bool) -> Self {
+        self.0.auto_migrate = auto_migrate;
+        self
+    }
+
+    /// DANGER - start the server even if the lair directory, or the
+    /// files in it, are not owned by the current user, or are writable
+    /// by group / other. Anyone able to write them may take over the
+    /// keystore!
+    pub fn danger_set_allow_insecure_permissions(
+        mut self,
+        allow: bool,
+    ) -> Self {
+        self.0.danger_allow_insecure_permissions = allow;
+        self
+    }
+
+    /// DANGER - import tls certs, e.g. with `lair_unwrap_entry`, whose
+    /// sni is not a valid hostname, see `CertSni::parse`, as long as it
+    /// is printable ascii. Only for legacy certs, such snis may break
+    /// the tls stacks they are handed to!
+    pub fn danger_set_allow_invalid_sni(mut self, allow: bool) -> Self {
+        self.0.danger_allow_invalid_sni = allow;
+        self
+    }
+
+    /// Replace the os entropy source, e.g. with a failing source
+    /// in tests.
+    pub fn set_entropy_source(mut self, entropy: EntropySourceHandle) -> Self {
+        self.0.entropy = entropy;
+        self
+    }
+
+    /// Set what generating key material does while the entropy source
+    /// fails its health check. Defaults to `EntropyFailurePolicy::Fail`.
+    pub fn set_entropy_failure_policy(
+        mut self,
+        policy: EntropyFailurePolicy,
+    ) -> Self {
+        self.0.entropy_failure_policy = policy;
+        self
+    }
+
+    /// If true, the default, the entropy health check draws a second
+    /// sample, and also fails if the samples repeat or look stuck.
+    pub fn set_entropy_sanity_check(mut self, sanity_check: bool) -> Self {
+        self.0.entropy_sanity_check = sanity_check;
+        self
+    }
+
+    /// Replace the os clocks, e.g. with a `clock::ManualClock`
+    /// in tests.
+    pub fn set_clock(mut self, clock: ClockHandle) -> Self {
+        self.0.clock_monitor = Arc::new(ClockMonitor::new(clock));
+        self
+    }
+
+    /// Set what expiry enforcement does while the wall clock is skewed.
+    /// Defaults to `ClockSkewPolicy::FailClosed`.
+    pub fn set_clock_skew_policy(mut self, policy: ClockSkewPolicy) -> Self {
+        self.0.clock_skew_policy = policy;
+        self
+    }
+
+    /// Replace the platform keystore holding device secrets,
+    /// e.g. with a `MockDeviceSecretStore` in tests.
+    pub fn set_device_secret_store(
+        mut self,
+        device_secret_store: DeviceSecretStoreHandle,
+    ) -> Self {
+        self.0.device_secret_store = device_secret_store;
+        self
+    }
+
+    /// Generate `KeyBacking::Hardware` keys on `hardware_token`,
+    /// e.g. a `hardware::Pkcs11Token`, see `hardware`.
+    /// Without one, creating hardware backed keys fails.
+    pub fn set_hardware_token(
+        mut self,
+        hardware_token: HardwareTokenHandle,
+    ) -> Self {
+        self.0.hardware_token = Some(hardware_token);
+        self
+    }
+
+    /// DANGER - replace the os entropy source with a deterministic
+    /// stream derived from `seed`. All generated keys and certificates
+    /// will be predictable, and a restarted server repeats its crypto box
+    /// nonces. Only for use in tests!
+    pub fn danger_set_test_entropy_seed(mut self, seed: u64) -> Self {
+        self.0.entropy = DangerSeededEntropy::danger_new_from_seed(seed);
+        self
+    }
 }
@@ -0,0 +1,805 @@
+//! Parsing / validation of lair connection urls.
+//!
+//! Supported schemes:
+//!
+//! - `unix:///absolute/path/to/socket` - a unix domain socket
+//! - `lair-unix-abstract://name` - a unix domain socket in the linux
+//!   abstract namespace, with no file behind it. Only supported on linux,
+//!   elsewhere parsing it fails with a `Scheme` error.
+//! - `pipe:///pipe-name` - a windows named pipe (`\\.\pipe\pipe-name`)
+//! - `tcp://host:port` - reserved for a future tcp transport
+//!
+//! Any scheme may carry a `?token=...` query parameter. A server
+//! listening on a url with a token requires clients to present it.
+//! Path, pipe name, abstract socket name, and token components are
+//! percent-encoded.
+
+use crate::*;
+use std::path::{Path, PathBuf};
+
+/// The part of a connection url that failed to validate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LairConnectionUrlComponent {
+    /// The url scheme, i.e. `unix` in `unix:///tmp/socket`.
+    Scheme,
+
+    /// The url host.
+    Host,
+
+    /// The url port.
+    Port,
+
+    /// The url path.
+    Path,
+
+    /// The url query string.
+    Query,
+
+    /// The value of the `token` query parameter.
+    Token,
+
+    /// The url fragment.
+    Fragment,
+}
+
+impl std::fmt::Display for LairConnectionUrlComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Scheme => "scheme",
+            Self::Host => "host",
+            Self::Port => "port",
+            Self::Path => "path",
+            Self::Query => "query",
+            Self::Token => "token",
+            Self::Fragment => "fragment",
+        };
+        f.write_str(s)
+    }
+}
+
+fn url_err(
+    component: LairConnectionUrlComponent,
+    reason: impl Into<String>,
+) -> LairError {
+    LairError::InvalidConnectionUrl {
+        component,
+        reason: reason.into(),
+    }
+}
+
+/// The maximum byte length of a connection url token.
+pub const MAX_CONNECTION_TOKEN_LEN: usize = 1024;
+
+/// The maximum byte length of an abstract unix socket name:
+/// `sun_path`, less the leading NUL marking it abstract.
+pub const MAX_ABSTRACT_SOCKET_NAME_LEN: usize = 107;
+
+/// The transport endpoint portion of a [`LairConnectionUrl`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LairConnectionEndpoint {
+    /// A unix domain socket at an absolute path.
+    UnixSocket(PathBuf),
+
+    /// A linux abstract namespace unix domain socket, by name
+    /// (without the leading NUL). File permissions do not apply to
+    /// these, the peer credentials of each connection are checked.
+    UnixAbstract(Vec<u8>),
+
+    /// A windows named pipe, by name (without the `\\.\pipe\` prefix).
+    WindowsPipe(String),
+
+    /// A tcp host and port. Reserved for a future transport.
+    Tcp {
+        /// Hostname, ipv4 address, or bracketed ipv6 address.
+        host: String,
+
+        /// Tcp port.
+        port: u16,
+    },
+}
+
+/// A validated lair connection url.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LairConnectionUrl {
+    endpoint: LairConnectionEndpoint,
+    token: Option<String>,
+}
+
+impl LairConnectionUrl {
+    /// Construct a unix domain socket connection url.
+    pub fn new_unix_socket<P>(path: P) -> LairResult<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        check_unix_path(&path_bytes(&path)?)?;
+        Ok(Self {
+            endpoint: LairConnectionEndpoint::UnixSocket(path),
+            token: None,
+        })
+    }
+
+    /// Construct a linux abstract namespace unix socket connection url.
+    /// Errors on other platforms.
+    pub fn new_unix_abstract<N>(name: N) -> LairResult<Self>
+    where
+        N: Into<Vec<u8>>,
+    {
+        let name = name.into();
+        check_abstract_supported()?;
+        check_abstract_name(&name)?;
+        Ok(Self {
+            endpoint: LairConnectionEndpoint::UnixAbstract(name),
+            token: None,
+        })
+    }
+
+    /// Construct a windows named pipe connection url.
+    pub fn new_windows_pipe<N>(name: N) -> LairResult<Self>
+    where
+        N: Into<String>,
+    {
+        let name = name.into();
+        check_pipe_name(&name)?;
+        Ok(Self {
+            endpoint: LairConnectionEndpoint::WindowsPipe(name),
+            token: None,
+        })
+    }
+
+    /// Construct a tcp connection url.
+    pub fn new_tcp<H>(host: H, port: u16) -> LairResult<Self>
+    where
+        H: Into<String>,
+    {
+        let host = host.into();
+        check_host(&host)?;
+        check_port(port)?;
+        Ok(Self {
+            endpoint: LairConnectionEndpoint::Tcp { host, port },
+            token: None,
+        })
+    }
+
+    /// Attach an authentication token to this url.
+    pub fn with_token<T>(mut self, token: T) -> LairResult<Self>
+    where
+        T: Into<String>,
+    {
+        let token = token.into();
+        check_token(&token)?;
+        self.token = Some(token);
+        Ok(self)
+    }
+
+    /// The transport endpoint.
+    pub fn endpoint(&self) -> &LairConnectionEndpoint {
+        &self.endpoint
+    }
+
+    /// The authentication token, if any.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+}
+
+// -- validation -- //
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> LairResult<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(path.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> LairResult<Vec<u8>> {
+    path.to_str()
+        .map(|s| s.as_bytes().to_vec())
+        .ok_or_else(|| url_err(LairConnectionUrlComponent::Path, "not utf8"))
+}
+
+#[cfg(unix)]
+fn bytes_path(bytes: Vec<u8>) -> LairResult<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(std::ffi::OsString::from_vec(bytes).into())
+}
+
+#[cfg(not(unix))]
+fn bytes_path(bytes: Vec<u8>) -> LairResult<PathBuf> {
+    String::from_utf8(bytes)
+        .map(PathBuf::from)
+        .map_err(|_| url_err(LairConnectionUrlComponent::Path, "not utf8"))
+}
+
+fn check_unix_path(path: &[u8]) -> LairResult<()> {
+    use LairConnectionUrlComponent::Path;
+    if path.first() != Some(&b'/') {
+        return Err(url_err(Path, "unix socket path must be absolute"));
+    }
+    if path.len() == 1 || path.ends_with(b"/") {
+        return Err(url_err(Path, "unix socket path must name a file"));
+    }
+    if path.contains(&0) {
+        return Err(url_err(Path, "unix socket path cannot contain NUL"));
+    }
+    for segment in path[1..].split(|b| *b == b'/') {
+        match segment {
+            b"" => return Err(url_err(Path, "empty path segment")),
+            b"." | b".." => {
+                return Err(url_err(Path, "path must be normalized"))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn check_abstract_supported() -> LairResult<()> {
+    if cfg!(target_os = "linux") {
+        Ok(())
+    } else {
+        Err(url_err(
+            LairConnectionUrlComponent::Scheme,
+            "abstract unix sockets are only supported on linux",
+        ))
+    }
+}
+
+fn check_abstract_name(name: &[u8]) -> LairResult<()> {
+    use LairConnectionUrlComponent::Path;
+    if name.is_empty() {
+        return Err(url_err(Path, "abstract socket name cannot be empty"));
+    }
+    if name.len() > MAX_ABSTRACT_SOCKET_NAME_LEN {
+        return Err(url_err(
+            Path,
+            format!(
+                "abstract socket name exceeds {} bytes",
+                MAX_ABSTRACT_SOCKET_NAME_LEN
+            ),
+        ));
+    }
+    if name.contains(&0) {
+        return Err(url_err(Path, "abstract socket name cannot contain NUL"));
+    }
+    Ok(())
+}
+
+fn check_pipe_name(name: &str) -> LairResult<()> {
+    use LairConnectionUrlComponent::Path;
+    if name.is_empty() {
+        return Err(url_err(Path, "pipe name cannot be empty"));
+    }
+    if name.len() > 256 {
+        return Err(url_err(Path, "pipe name exceeds 256 bytes"));
+    }
+    if name.contains(|c: char| c == '/' || c == '\\' || c.is_control()) {
+        return Err(url_err(
+            Path,
+            "pipe name cannot contain slashes or control characters",
+        ));
+    }
+    Ok(())
+}
+
+fn check_host(host: &str) -> LairResult<()> {
+    use LairConnectionUrlComponent::Host;
+    if host.is_empty() {
+        return Err(url_err(Host, "tcp host cannot be empty"));
+    }
+    if let Some(inner) = host.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| url_err(Host, "unterminated ipv6 literal"))?;
+        return inner
+            .parse::<std::net::Ipv6Addr>()
+            .map(|_| ())
+            .map_err(|_| url_err(Host, "invalid ipv6 address"));
+    }
+    if host.starts_with('.') || host.ends_with('.') || host.contains("..") {
+        return Err(url_err(Host, "empty host label"));
+    }
+    if let Some(c) = host
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '.'))
+    {
+        return Err(url_err(Host, format!("invalid character {:?}", c)));
+    }
+    Ok(())
+}
+
+fn check_port(port: u16) -> LairResult<()> {
+    if port == 0 {
+        return Err(url_err(
+            LairConnectionUrlComponent::Port,
+            "port cannot be 0",
+        ));
+    }
+    Ok(())
+}
+
+fn check_token(token: &str) -> LairResult<()> {
+    use LairConnectionUrlComponent::Token;
+    if token.is_empty() {
+        return Err(url_err(Token, "token cannot be empty"));
+    }
+    if token.len() > MAX_CONNECTION_TOKEN_LEN {
+        return Err(url_err(
+            Token,
+            format!("token exceeds {} bytes", MAX_CONNECTION_TOKEN_LEN),
+        ));
+    }
+    if token.contains(char::is_control) {
+        return Err(url_err(Token, "token cannot contain control characters"));
+    }
+    Ok(())
+}
+
+// -- percent-encoding -- //
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+        || b == b'-'
+        || b == b'.'
+        || b == b'_'
+        || b == b'~'
+}
+
+/// rfc 3986 "pchar" minus '%', which is handled separately
+fn is_pchar(b: u8) -> bool {
+    is_unreserved(b) || b"!$&'()*+,;=:@".contains(&b)
+}
+
+fn encode(out: &mut String, data: &[u8], keep_slash: bool) {
+    for b in data {
+        if is_unreserved(*b) || (keep_slash && *b == b'/') {
+            out.push(*b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+}
+
+fn decode(
+    component: LairConnectionUrlComponent,
+    data: &str,
+    allow: impl Fn(u8) -> bool,
+) -> LairResult<Vec<u8>> {
+    let bytes = data.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| {
+                    url_err(
+                        component,
+                        format!("invalid percent-encoding at byte {}", i),
+                    )
+                })?;
+            out.push(hex);
+            i += 3;
+        } else if allow(b) {
+            out.push(b);
+            i += 1;
+        } else {
+            // report the full (possibly multi-byte) char
+            let c = data[i..].chars().next().unwrap_or('?');
+            return Err(url_err(
+                component,
+                format!("invalid character {:?} (must be percent-encoded)", c),
+            ));
+        }
+    }
+    Ok(out)
+}
+
+fn decode_utf8(
+    component: LairConnectionUrlComponent,
+    data: &str,
+    allow: impl Fn(u8) -> bool,
+) -> LairResult<String> {
+    String::from_utf8(decode(component, data, allow)?)
+        .map_err(|_| url_err(component, "decodes to invalid utf8"))
+}
+
+// -- Display / FromStr -- //
+
+impl std::fmt::Display for LairConnectionUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        match &self.endpoint {
+            LairConnectionEndpoint::UnixSocket(path) => {
+                out.push_str("unix://");
+                // validated on construction
+                let bytes = path_bytes(path).map_err(|_| std::fmt::Error)?;
+                encode(&mut out, &bytes, true);
+            }
+            LairConnectionEndpoint::UnixAbstract(name) => {
+                out.push_str("lair-unix-abstract://");
+                encode(&mut out, name, false);
+            }
+            LairConnectionEndpoint::WindowsPipe(name) => {
+                out.push_str("pipe:///");
+                encode(&mut out, name.as_bytes(), false);
+            }
+            LairConnectionEndpoint::Tcp { host, port } => {
+                out.push_str(&format!("tcp://{}:{}", host, port));
+            }
+        }
+        if let Some(token) = &self.token {
+            out.push_str("?token=");
+            encode(&mut out, token.as_bytes(), false);
+        }
+        f.write_str(&out)
+    }
+}
+
+impl std::str::FromStr for LairConnectionUrl {
+    type Err = LairError;
+
+    fn from_str(s: &str) -> LairResult<Self> {
+        use LairConnectionUrlComponent::*;
+
+        let (scheme, rest) = match s.find("://") {
+            Some(idx) => (&s[..idx], &s[idx + 3..]),
+            None => return Err(url_err(Scheme, "missing '://' separator")),
+        };
+
+        if rest.contains('#') {
+            return Err(url_err(Fragment, "fragments are not supported"));
+        }
+
+        let (rest, query) = match rest.find('?') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+
+        let mut out = match scheme.to_ascii_lowercase().as_str() {
+            "unix" => {
+                if rest.is_empty() {
+                    return Err(url_err(Path, "missing unix socket path"));
+                }
+                if !rest.starts_with('/') {
+                    return Err(url_err(
+                        Host,
+                        "unix socket urls cannot specify a host",
+                    ));
+                }
+                let path = decode(Path, rest, |b| is_pchar(b) || b == b'/')?;
+                check_unix_path(&path)?;
+                Self {
+                    endpoint: LairConnectionEndpoint::UnixSocket(bytes_path(
+                        path,
+                    )?),
+                    token: None,
+                }
+            }
+            "lair-unix-abstract" => {
+                // before the name, a bad one is beside the point here
+                check_abstract_supported()?;
+                let name = decode(Path, rest, is_pchar)?;
+                Self::new_unix_abstract(name)?
+            }
+            "pipe" => {
+                let name = rest.strip_prefix('/').ok_or_else(|| {
+                    url_err(Host, "pipe urls cannot specify a host")
+                })?;
+                let name = decode_utf8(Path, name, is_pchar)?;
+                Self::new_windows_pipe(name)?
+            }
+            "tcp" => {
+                let authority = match rest.find('/') {
+                    Some(idx) => {
+                        if &rest[idx..] != "/" {
+                            return Err(url_err(
+                                Path,
+                                "tcp urls cannot specify a path",
+                            ));
+                        }
+                        &rest[..idx]
+                    }
+                    None => rest,
+                };
+                let idx = authority
+                    .rfind(':')
+                    .filter(|idx| !authority[*idx..].contains(']'))
+                    .ok_or_else(|| url_err(Port, "tcp urls require a port"))?;
+                let host = &authority[..idx];
+                let port = &authority[idx + 1..];
+                if port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit())
+                {
+                    return Err(url_err(Port, "port must be a decimal number"));
+                }
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| url_err(Port, "port exceeds 65535"))?;
+                Self::new_tcp(host.to_ascii_lowercase(), port)?
+            }
+            "" => return Err(url_err(Scheme, "scheme cannot be empty")),
+            other => {
+                return Err(url_err(
+                    Scheme,
+                    format!("unsupported scheme {:?}", other),
+                ))
+            }
+        };
+
+        if let Some(query) = query {
+            if query.is_empty() {
+                return Err(url_err(Query, "empty query string"));
+            }
+            for param in query.split('&') {
+                let (key, value) = match param.find('=') {
+                    Some(idx) => (&param[..idx], &param[idx + 1..]),
+                    None => {
+                        return Err(url_err(
+                            Query,
+                            format!("parameter {:?} has no value", param),
+                        ))
+                    }
+                };
+                let key = decode_utf8(Query, key, is_pchar)?;
+                if key != "token" {
+                    return Err(url_err(
+                        Query,
+                        format!("unsupported parameter {:?}", key),
+                    ));
+                }
+                if out.token.is_some() {
+                    return Err(url_err(Token, "token specified twice"));
+                }
+                let token = decode_utf8(Token, value, is_pchar)?;
+                out = out.with_token(token)?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl serde::Serialize for LairConnectionUrl {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for LairConnectionUrl {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LairConnectionUrlComponent as C;
+
+    fn parse(s: &str) -> LairResult<LairConnectionUrl> {
+        s.parse()
+    }
+
+    fn round_trip(s: &str) -> LairConnectionUrl {
+        let url = parse(s).unwrap();
+        assert_eq!(s, &url.to_string());
+        assert_eq!(url, parse(&url.to_string()).unwrap());
+        let json = serde_json::to_string(&url).unwrap();
+        assert_eq!(url, serde_json::from_str(&json).unwrap());
+        url
+    }
+
+    fn expect_err(s: &str, expect: LairConnectionUrlComponent) {
+        match parse(s) {
+            Err(LairError::InvalidConnectionUrl { component, .. }) => {
+                assert_eq!(expect, component, "{}", s);
+            }
+            oth => {
+                panic!("expected {:?} error for {}, got {:?}", expect, s, oth)
+            }
+        }
+    }
+
+    #[test]
+    fn it_round_trips_unix_socket() {
+        let url = round_trip("unix:///tmp/lair/socket");
+        assert_eq!(
+            &LairConnectionEndpoint::UnixSocket("/tmp/lair/socket".into()),
+            url.endpoint(),
+        );
+        assert_eq!(None, url.token());
+
+        let url = round_trip("unix:///tmp/my%20lair/socket?token=a%2Bb%3Dc");
+        assert_eq!(
+            &LairConnectionEndpoint::UnixSocket("/tmp/my lair/socket".into()),
+            url.endpoint(),
+        );
+        assert_eq!(Some("a+b=c"), url.token());
+
+        // unnecessary escapes and scheme case are normalized
+        let url = parse("UNIX:///tmp/%6Cair").unwrap();
+        assert_eq!("unix:///tmp/lair", &url.to_string());
+
+        let url = LairConnectionUrl::new_unix_socket("/tmp/a?b#c")
+            .unwrap()
+            .with_token("t&k")
+            .unwrap();
+        assert_eq!("unix:///tmp/a%3Fb%23c?token=t%26k", &url.to_string());
+        assert_eq!(url, round_trip(&url.to_string()));
+    }
+
+    #[test]
+    fn it_round_trips_windows_pipe() {
+        let url = round_trip("pipe:///lair-keystore?token=abc");
+        assert_eq!(
+            &LairConnectionEndpoint::WindowsPipe("lair-keystore".into()),
+            url.endpoint(),
+        );
+        assert_eq!(Some("abc"), url.token());
+        round_trip("pipe:///my%20pipe");
+    }
+
+    #[test]
+    fn it_round_trips_tcp() {
+        let url = round_trip("tcp://127.0.0.1:8080?token=abc");
+        assert_eq!(
+            &LairConnectionEndpoint::Tcp {
+                host: "127.0.0.1".into(),
+                port: 8080,
+            },
+            url.endpoint(),
+        );
+        round_trip("tcp://lair.example.com:1");
+        round_trip("tcp://[::1]:65535");
+        assert_eq!(
+            "tcp://localhost:42",
+            &parse("tcp://LocalHost:42/").unwrap().to_string(),
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn it_round_trips_unix_abstract() {
+        let url = round_trip("lair-unix-abstract://lair-keystore?token=abc");
+        assert_eq!(
+            &LairConnectionEndpoint::UnixAbstract(b"lair-keystore".to_vec()),
+            url.endpoint(),
+        );
+        assert_eq!(Some("abc"), url.token());
+
+        // slashes and non utf8 bytes are escaped
+        let url =
+            LairConnectionUrl::new_unix_abstract(&b"a/b\xff"[..]).unwrap();
+        assert_eq!("lair-unix-abstract://a%2Fb%FF", &url.to_string());
+        assert_eq!(url, round_trip(&url.to_string()));
+        assert_eq!(
+            "lair-unix-abstract://lair",
+            &parse("LAIR-UNIX-ABSTRACT://%6Cair").unwrap().to_string(),
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn it_rejects_bad_unix_abstract() {
+        expect_err("lair-unix-abstract://", C::Path);
+        expect_err("lair-unix-abstract:///name", C::Path);
+        expect_err("lair-unix-abstract://a%00b", C::Path);
+        expect_err("lair-unix-abstract://a b", C::Path);
+        expect_err(
+            &format!(
+                "lair-unix-abstract://{}",
+                "a".repeat(MAX_ABSTRACT_SOCKET_NAME_LEN + 1),
+            ),
+            C::Path,
+        );
+        round_trip(&format!(
+            "lair-unix-abstract://{}",
+            "a".repeat(MAX_ABSTRACT_SOCKET_NAME_LEN),
+        ));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "linux"))]
+    fn it_rejects_unix_abstract_off_linux() {
+        expect_err("lair-unix-abstract://lair-keystore", C::Scheme);
+        expect_err("lair-unix-abstract://", C::Scheme);
+        assert!(matches!(
+            LairConnectionUrl::new_unix_abstract("lair-keystore"),
+            Err(LairError::InvalidConnectionUrl {
+                component: C::Scheme,
+                ..
+            }),
+        ));
+    }
+
+    #[test]
+    fn it_rejects_bad_scheme() {
+        expect_err("/tmp/socket", C::Scheme);
+        expect_err("://tmp/socket", C::Scheme);
+        expect_err("http://localhost:80", C::Scheme);
+    }
+
+    #[test]
+    fn it_rejects_bad_unix_socket() {
+        expect_err("unix://host/tmp/socket", C::Host);
+        expect_err("unix://", C::Path);
+        expect_err("unix:///", C::Path);
+        expect_err("unix:///tmp/", C::Path);
+        expect_err("unix:///tmp//socket", C::Path);
+        expect_err("unix:///tmp/../socket", C::Path);
+        expect_err("unix:///tmp/./socket", C::Path);
+        expect_err("unix:///tmp/a%00b", C::Path);
+        expect_err("unix:///tmp/a b", C::Path);
+        expect_err("unix:///tmp/%zz", C::Path);
+        expect_err("unix:///tmp/%4", C::Path);
+        expect_err("unix:///tmp/\u{e9}", C::Path);
+        assert!(LairConnectionUrl::new_unix_socket("relative/socket").is_err());
+    }
+
+    #[test]
+    fn it_rejects_bad_windows_pipe() {
+        expect_err("pipe://host/name", C::Host);
+        expect_err("pipe:///", C::Path);
+        expect_err("pipe:///a/b", C::Path);
+        expect_err("pipe:///a%5Cb", C::Path);
+        expect_err("pipe:///%FF", C::Path);
+    }
+
+    #[test]
+    fn it_rejects_bad_tcp() {
+        expect_err("tcp://localhost", C::Port);
+        expect_err("tcp://localhost:", C::Port);
+        expect_err("tcp://localhost:http", C::Port);
+        expect_err("tcp://localhost:-1", C::Port);
+        expect_err("tcp://localhost:65536", C::Port);
+        expect_err("tcp://localhost:0", C::Port);
+        expect_err("tcp://:80", C::Host);
+        expect_err("tcp://local_host:80", C::Host);
+        expect_err("tcp://local..host:80", C::Host);
+        expect_err("tcp://[::1:80", C::Host);
+        expect_err("tcp://[::1]", C::Port);
+        expect_err("tcp://[::g]:80", C::Host);
+        expect_err("tcp://localhost:80/path", C::Path);
+    }
+
+    #[test]
+    fn it_rejects_bad_query() {
+        expect_err("unix:///tmp/socket?", C::Query);
+        expect_err("unix:///tmp/socket?token", C::Query);
+        expect_err("unix:///tmp/socket?other=1", C::Query);
+        expect_err("unix:///tmp/socket?token=", C::Token);
+        expect_err("unix:///tmp/socket?token=a&token=b", C::Token);
+        expect_err("unix:///tmp/socket?token=%0A", C::Token);
+        expect_err(
+            &format!(
+                "unix:///tmp/socket?token={}",
+                "a".repeat(MAX_CONNECTION_TOKEN_LEN + 1),
+            ),
+            C::Token,
+        );
+        expect_err("unix:///tmp/socket?token=a%", C::Token);
+        expect_err("unix:///tmp/socket#frag", C::Fragment);
+    }
+
+    #[test]
+    fn it_reports_component_and_reason() {
+        let err = parse("tcp://localhost:99999").unwrap_err();
+        assert_eq!(
+            "InvalidConnectionUrl(port): port exceeds 65535",
+            &err.to_string(),
+        );
+        let err = serde_json::from_str::<LairConnectionUrl>("\"ftp://x\"")
+            .unwrap_err();
+        assert!(err.to_string().contains("unsupported scheme"));
+    }
+}
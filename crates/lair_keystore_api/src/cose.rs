@@ -0,0 +1,282 @@
+//! COSE_Sign1 (RFC 9052) EdDSA helpers backed by lair ed25519 signing keys.
+//!
+//! Structures are encoded and parsed with `coset`. The protected header
+//! is emitted in the core deterministic CBOR encoding (RFC 8949 section
+//! 4.2.1), with its map keys sorted by their encoded bytes rather than in
+//! `coset`'s field order, so signing the same payload and headers with
+//! the same key always produces the same bytes.
+
+use crate::actor::*;
+use crate::*;
+use coset::cbor::value::Value;
+use coset::{CborSerializable, TaggedCborSerializable};
+
+/// The COSE `alg` header parameter label.
+pub const COSE_HEADER_ALG: i64 = 1;
+
+/// The COSE `kid` header parameter label.
+pub const COSE_HEADER_KID: i64 = 4;
+
+/// The COSE algorithm identifier of EdDSA.
+pub const COSE_ALG_EDDSA: i64 = -8;
+
+/// The value of a COSE header parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoseHeaderValue {
+    /// A CBOR integer, e.g. a `content type` registered in CoAP.
+    Int(i64),
+
+    /// A CBOR byte string.
+    Bytes(Vec<u8>),
+
+    /// A CBOR text string, e.g. a `content type` media type.
+    Text(String),
+}
+
+/// Sign `payload` as a tagged COSE_Sign1 with the ed25519 keypair at
+/// `keystore_index`. The protected header holds `alg` (EdDSA) and the
+/// `protected_headers`, the unprotected header holds `kid`, the signer
+/// pub key. `protected_headers` may neither set `alg` nor `kid`,
+/// nor repeat a label.
+pub async fn cose_sign1(
+    client: &impl LairClientApiSender,
+    keystore_index: KeystoreIndex,
+    payload: &[u8],
+    protected_headers: &[(i64, CoseHeaderValue)],
+) -> LairResult<Vec<u8>> {
+    let mut protected =
+        vec![(COSE_HEADER_ALG, CoseHeaderValue::Int(COSE_ALG_EDDSA))];
+    for (label, value) in protected_headers {
+        if protected.iter().any(|(l, _)| l == label)
+            || *label == COSE_HEADER_KID
+        {
+            return Err(format!(
+                "cose header label {} is reserved, or repeated",
+                label
+            )
+            .into());
+        }
+        protected.push((*label, value.clone()));
+    }
+    // keeps the deterministic bytes as the original data
+    let protected = coset::ProtectedHeader::from_cbor_bstr(Value::Bytes(
+        header_map(&protected)?,
+    ))
+    .map_err(cose_error)?;
+
+    let pub_key = client.sign_ed25519_get(keystore_index).await?;
+    let mut sign1 = coset::CoseSign1 {
+        protected,
+        unprotected: coset::HeaderBuilder::new()
+            .key_id(pub_key.0.to_vec())
+            .build(),
+        payload: Some(payload.to_vec()),
+        signature: Vec::new(),
+    };
+
+    let signature = client
+        .sign_ed25519_sign_by_index(
+            keystore_index,
+            Arc::new(sign1.tbs_data(b"")),
+        )
+        .await?;
+    sign1.signature = signature.0.to_vec();
+    sign1.to_tagged_vec().map_err(cose_error)
+}
+
+/// Verify a COSE_Sign1, tagged or not, against `pub_key`, returning its
+/// payload on success. The protected `alg` must be EdDSA, the signature
+/// must be over an empty external aad. Detached payloads are refused.
+pub async fn cose_verify1(
+    pub_key: &SignEd25519PubKey,
+    bytes: &[u8],
+) -> LairResult<Vec<u8>> {
+    let sign1 = match coset::CoseSign1::from_tagged_slice(bytes) {
+        Ok(sign1) => sign1,
+        Err(_) => coset::CoseSign1::from_slice(bytes).map_err(cose_error)?,
+    };
+    if sign1.protected.header.alg
+        != Some(coset::RegisteredLabelWithPrivate::Assigned(
+            coset::iana::Algorithm::EdDSA,
+        ))
+    {
+        return Err("cose alg is not EdDSA".into());
+    }
+    if sign1.payload.is_none() {
+        return Err("detached cose_sign1 payloads are not supported".into());
+    }
+
+    // the signature is over the protected header, exactly as sent
+    let sig_structure = sign1.tbs_data(b"");
+    if !pub_key
+        .verify(Arc::new(sig_structure), sign1.signature.into())
+        .await?
+    {
+        return Err("invalid cose signature".into());
+    }
+    Ok(sign1.payload.unwrap_or_default())
+}
+
+// -- local helpers -- //
+
+fn cose_error(e: coset::CoseError) -> LairError {
+    format!("invalid cose_sign1, {}", e).into()
+}
+
+/// A header map, with keys sorted by their encoded bytes.
+fn header_map(headers: &[(i64, CoseHeaderValue)]) -> LairResult<Vec<u8>> {
+    let mut entries = headers
+        .iter()
+        .map(|(label, value)| {
+            let value = match value {
+                CoseHeaderValue::Int(v) => Value::from(*v),
+                CoseHeaderValue::Bytes(b) => Value::Bytes(b.clone()),
+                CoseHeaderValue::Text(t) => Value::Text(t.clone()),
+            };
+            Ok((
+                cbor_vec(&Value::from(*label))?,
+                (Value::from(*label), value),
+            ))
+        })
+        .collect::<LairResult<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    cbor_vec(&Value::Map(entries.into_iter().map(|(_, e)| e).collect()))
+}
+
+fn cbor_vec(value: &Value) -> LairResult<Vec<u8>> {
+    let mut out = Vec::new();
+    coset::cbor::ser::into_writer(value, &mut out)
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_deterministic_header_maps() {
+        let protected = header_map(&[
+            (-70000, CoseHeaderValue::Int(-1)),
+            (3, CoseHeaderValue::Text("application/cbor".to_string())),
+            (COSE_HEADER_ALG, CoseHeaderValue::Int(COSE_ALG_EDDSA)),
+            (256, CoseHeaderValue::Bytes(vec![0x42; 2])),
+            (-1, CoseHeaderValue::Int(24)),
+        ])
+        .unwrap();
+        let mut expect = vec![0xa5, 0x01, 0x27, 0x03, 0x70];
+        expect.extend_from_slice(b"application/cbor");
+        expect.extend_from_slice(&[0x19, 0x01, 0x00, 0x42, 0x42, 0x42]);
+        expect.extend_from_slice(&[0x20, 0x18, 0x18]);
+        expect.extend_from_slice(&[0x3a, 0x00, 0x01, 0x11, 0x6f, 0x20]);
+        assert_eq!(expect, protected);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_cose_sign1() {
+        let (api, _evt) =
+            test::spawn_test_keystore(vec![], vec![]).await.unwrap();
+        let (idx, pub_key) = api.sign_ed25519_new_from_entropy().await.unwrap();
+        let ring_pub_key = ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ED25519,
+            pub_key.0.to_vec(),
+        );
+
+        let payload = b"cbor-credential-bytes";
+        let headers = [(3, CoseHeaderValue::Int(60))];
+        let signed = cose_sign1(&api, idx, payload, &headers).await.unwrap();
+        assert_eq!(
+            signed,
+            cose_sign1(&api, idx, payload, &headers).await.unwrap()
+        );
+        assert_eq!(
+            payload.to_vec(),
+            cose_verify1(&pub_key, &signed).await.unwrap()
+        );
+
+        // verify with an independent COSE implementation
+        let sign1 = coset::CoseSign1::from_tagged_slice(&signed).unwrap();
+        assert_eq!(
+            Some(coset::RegisteredLabelWithPrivate::Assigned(
+                coset::iana::Algorithm::EdDSA
+            )),
+            sign1.protected.header.alg,
+        );
+        assert_eq!(
+            Some(coset::ContentType::Assigned(
+                coset::iana::CoapContentFormat::Cbor
+            )),
+            sign1.protected.header.content_type,
+        );
+        assert_eq!(pub_key.0.to_vec(), sign1.unprotected.key_id);
+        sign1
+            .verify_signature(b"", |sig, data| ring_pub_key.verify(data, sig))
+            .unwrap();
+
+        // and verify what it signs, tagged or not
+        let (other_idx, _) = api.sign_ed25519_new_from_entropy().await.unwrap();
+        let mut signature = None;
+        let sign1 = coset::CoseSign1Builder::new()
+            .protected(
+                coset::HeaderBuilder::new()
+                    .algorithm(coset::iana::Algorithm::EdDSA)
+                    .build(),
+            )
+            .payload(payload.to_vec())
+            .create_signature(b"", |data| {
+                signature = Some(data.to_vec());
+                Vec::new()
+            })
+            .build();
+        let signature = api
+            .sign_ed25519_sign_by_index(
+                idx,
+                Arc::new(signature.take().unwrap()),
+            )
+            .await
+            .unwrap();
+        let sign1 = coset::CoseSign1 {
+            signature: signature.0.to_vec(),
+            ..sign1
+        };
+        assert_eq!(
+            payload.to_vec(),
+            cose_verify1(&pub_key, &sign1.clone().to_tagged_vec().unwrap())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(
+            payload.to_vec(),
+            cose_verify1(&pub_key, &sign1.to_vec().unwrap())
+                .await
+                .unwrap(),
+        );
+
+        let other_pub_key = api.sign_ed25519_get(other_idx).await.unwrap();
+        assert!(cose_verify1(&other_pub_key, &signed).await.is_err());
+        let mut evil = signed.clone();
+        let at = evil.len() - 70;
+        evil[at] ^= 1;
+        assert!(cose_verify1(&pub_key, &evil).await.is_err());
+        assert!(cose_verify1(&pub_key, &signed[..signed.len() - 1])
+            .await
+            .is_err());
+
+        assert!(cose_sign1(
+            &api,
+            idx,
+            payload,
+            &[(COSE_HEADER_ALG, CoseHeaderValue::Int(-7))],
+        )
+        .await
+        .is_err());
+        assert!(cose_sign1(
+            &api,
+            idx,
+            payload,
+            &[(3, CoseHeaderValue::Int(60)), (3, CoseHeaderValue::Int(61))],
+        )
+        .await
+        .is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! Bind lair stores to the device they were set up on.
+//!
+//! A lair directory bound to a device has a random device secret in the
+//! platform keystore (Windows Credential Manager / DPAPI, macOS Keychain,
+//! Linux Secret Service). The secret is mixed into the unlock key
+//! derivation of every store whose passphrase is set after binding,
+//! alongside the passphrase (see `internal::unlock`), so a copy of the
+//! store file is of no use off the device, even with the passphrase.
+//! Unlocking on a device without the secret fails with
+//! `LairError::DeviceSecretMissing`.
+//!
+//! The lair directory records only a reference to the platform keystore
+//! entry, at `Config::get_device_binding_path`, never the secret itself.
+//!
+//! To move a bound lair directory to another device, export the secret
+//! from the running server with `lair_export_device_secret`, which needs
+//! `LairCapability::Admin`, copy the directory over, and import the
+//! secret there before starting lair
+//! (`lair-keystore init --bind-to-device --import-device-secret`).
+
+use crate::*;
+use internal::entropy::new_uuid;
+use internal::util::private_open_options;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The byte length of a device secret.
+pub const DEVICE_SECRET_LEN: usize = 32;
+
+/// A device secret, mixed into the unlock key derivation.
+pub type DeviceSecret = [u8; DEVICE_SECRET_LEN];
+
+/// Platform abstraction over the keystore holding device secrets.
+/// Implementations must never log the secrets.
+pub trait DeviceSecretStore: 'static + Send + Sync {
+    /// Fetch the device secret stored under `reference`, if any.
+    fn get_device_secret(
+        &self,
+        reference: &str,
+    ) -> LairResult<Option<DeviceSecret>>;
+
+    /// Store (or replace) the device secret under `reference`.
+    fn set_device_secret(
+        &self,
+        reference: &str,
+        device_secret: &DeviceSecret,
+    ) -> LairResult<()>;
+}
+
+/// Shared handle to a DeviceSecretStore.
+pub type DeviceSecretStoreHandle = Arc<dyn DeviceSecretStore>;
+
+/// The platform keystore, if this build has keychain support,
+/// otherwise a keystore holding no secrets, that refuses to store any.
+pub fn default_device_secret_store() -> DeviceSecretStoreHandle {
+    #[cfg(feature = "keychain")]
+    {
+        OsDeviceSecretStore::new_handle()
+    }
+    #[cfg(not(feature = "keychain"))]
+    {
+        NoDeviceSecretStore::new_handle()
+    }
+}
+
+#[cfg(feature = "keychain")]
+const DEVICE_SECRET_SERVICE: &str = "lair-keystore-device-secret";
+
+/// The platform keystore. Secrets are stored base64 encoded,
+/// as not every platform keystore takes binary secrets.
+#[cfg(feature = "keychain")]
+pub struct OsDeviceSecretStore;
+
+#[cfg(feature = "keychain")]
+impl OsDeviceSecretStore {
+    /// Get a handle to the platform keystore.
+    pub fn new_handle() -> DeviceSecretStoreHandle {
+        Arc::new(Self)
+    }
+}
+
+#[cfg(feature = "keychain")]
+impl DeviceSecretStore for OsDeviceSecretStore {
+    fn get_device_secret(
+        &self,
+        reference: &str,
+    ) -> LairResult<Option<DeviceSecret>> {
+        let encoded =
+            match keyring::Entry::new(DEVICE_SECRET_SERVICE, reference)
+                .get_password()
+            {
+                Ok(encoded) => zeroize::Zeroizing::new(encoded),
+                Err(keyring::Error::NoEntry) => return Ok(None),
+                Err(e) => return Err(LairError::other(e)),
+            };
+        let decoded = zeroize::Zeroizing::new(
+            base64::decode(&*encoded).map_err(LairError::other)?,
+        );
+        if decoded.len() != DEVICE_SECRET_LEN {
+            return Err(format!(
+                "invalid device secret {}, expected {} bytes",
+                reference, DEVICE_SECRET_LEN,
+            )
+            .into());
+        }
+        let mut device_secret = [0; DEVICE_SECRET_LEN];
+        device_secret.copy_from_slice(&decoded);
+        Ok(Some(device_secret))
+    }
+
+    fn set_device_secret(
+        &self,
+        reference: &str,
+        device_secret: &DeviceSecret,
+    ) -> LairResult<()> {
+        let encoded = zeroize::Zeroizing::new(base64::encode(device_secret));
+        keyring::Entry::new(DEVICE_SECRET_SERVICE, reference)
+            .set_password(&encoded)
+            .map_err(LairError::other)
+    }
+}
+
+/// The device secret store of builds without keychain support.
+/// It holds no secrets, so bound stores cannot be unlocked.
+#[cfg(not(feature = "keychain"))]
+pub struct NoDeviceSecretStore;
+
+#[cfg(not(feature = "keychain"))]
+impl NoDeviceSecretStore {
+    /// Get a handle to the empty device secret store.
+    pub fn new_handle() -> DeviceSecretStoreHandle {
+        Arc::new(Self)
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+impl DeviceSecretStore for NoDeviceSecretStore {
+    fn get_device_secret(
+        &self,
+        _reference: &str,
+    ) -> LairResult<Option<DeviceSecret>> {
+        Ok(None)
+    }
+
+    fn set_device_secret(
+        &self,
+        _reference: &str,
+        _device_secret: &DeviceSecret,
+    ) -> LairResult<()> {
+        Err("device binding needs lair built with the keychain feature".into())
+    }
+}
+
+/// An in-memory device secret store, for use in tests.
+/// A fresh one stands in for another device.
+#[derive(Default)]
+pub struct MockDeviceSecretStore(
+    std::sync::Mutex<HashMap<String, DeviceSecret>>,
+);
+
+impl MockDeviceSecretStore {
+    /// Get a handle to a new, empty mock device secret store.
+    pub fn new_handle() -> DeviceSecretStoreHandle {
+        Arc::new(Self::default())
+    }
+}
+
+impl DeviceSecretStore for MockDeviceSecretStore {
+    fn get_device_secret(
+        &self,
+        reference: &str,
+    ) -> LairResult<Option<DeviceSecret>> {
+        Ok(self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned device secret lock"))?
+            .get(reference)
+            .copied())
+    }
+
+    fn set_device_secret(
+        &self,
+        reference: &str,
+        device_secret: &DeviceSecret,
+    ) -> LairResult<()> {
+        self.0
+            .lock()
+            .map_err(|_| LairError::from("poisoned device secret lock"))?
+            .insert(reference.to_string(), *device_secret);
+        Ok(())
+    }
+}
+
+/// The reference of the device secret the lair directory
+/// of `config` is bound to, if it is bound.
+pub fn get_device_binding(config: &Config) -> LairResult<Option<String>> {
+    match std::fs::read_to_string(config.get_device_binding_path()) {
+        Ok(reference) => Ok(Some(reference.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(LairError::other(e)),
+    }
+}
+
+/// Bind the lair directory of `config` to this device, storing a new
+/// random device secret, or `device_secret` if given (as exported from
+/// another device), in `Config::get_device_secret_store`.
+/// Returns the reference to the secret.
+///
+/// A directory already bound (copied from another device) only takes
+/// an imported `device_secret`, under its existing reference, and only
+/// if this device holds no secret for it yet.
+/// Only stores whose passphrase is set after binding are bound,
+/// `lair_keystore::device_binding` checks there are none set before.
+pub fn bind_to_device(
+    config: &Config,
+    device_secret: Option<DeviceSecret>,
+) -> LairResult<String> {
+    let device_secrets = config.get_device_secret_store();
+    if let Some(reference) = get_device_binding(config)? {
+        return match device_secret {
+            Some(device_secret)
+                if device_secrets.get_device_secret(&reference)?.is_none() =>
+            {
+                device_secrets.set_device_secret(&reference, &device_secret)?;
+                Ok(reference)
+            }
+            _ => {
+                Err(format!("already bound to device secret {}", reference)
+                    .into())
+            }
+        };
+    }
+
+    let device_secret = match device_secret {
+        Some(device_secret) => device_secret,
+        None => {
+            let mut device_secret = [0; DEVICE_SECRET_LEN];
+            config.get_entropy().fill(&mut device_secret)?;
+            device_secret
+        }
+    };
+    let reference = format!(
+        "lair-device-{}",
+        new_uuid(config.get_entropy())?
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>(),
+    );
+    device_secrets.set_device_secret(&reference, &device_secret)?;
+
+    // only written once the secret is stored,
+    // a failed binding leaves the directory unbound
+    let mut file = private_open_options()
+        .write(true)
+        .create_new(true)
+        .open(config.get_device_binding_path())
+        .map_err(LairError::other)?;
+    writeln!(file, "{}", reference).map_err(LairError::other)?;
+    file.sync_all().map_err(LairError::other)?;
+
+    Ok(reference)
+}
+
+/// The device secret the lair directory of `config` is bound to,
+/// or None if it is not bound. Fails with `LairError::DeviceSecretMissing`
+/// if it is bound, but the device secret store does not hold the secret.
+pub fn load_device_secret(config: &Config) -> LairResult<Option<DeviceSecret>> {
+    let reference = match get_device_binding(config)? {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+    match config
+        .get_device_secret_store()
+        .get_device_secret(&reference)?
+    {
+        Some(device_secret) => Ok(Some(device_secret)),
+        None => Err(LairError::DeviceSecretMissing(format!(
+            "no device secret {} in the platform keystore of this machine",
+            reference,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_binds_a_lair_directory_to_a_device() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let device = MockDeviceSecretStore::new_handle();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_device_secret_store(device.clone())
+            .build();
+
+        assert_eq!(None, get_device_binding(&config).unwrap());
+        assert_eq!(None, load_device_secret(&config).unwrap());
+
+        let reference = bind_to_device(&config, None).unwrap();
+        assert_eq!(
+            Some(&reference),
+            get_device_binding(&config).unwrap().as_ref()
+        );
+        let device_secret = load_device_secret(&config).unwrap().unwrap();
+
+        // the directory only holds the reference
+        let binding = std::fs::read(config.get_device_binding_path()).unwrap();
+        assert!(!binding
+            .windows(DEVICE_SECRET_LEN)
+            .any(|w| w == &device_secret[..]));
+        assert!(bind_to_device(&config, None).is_err());
+
+        // a copy of the directory on another device has no secret
+        let other_config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_device_secret_store(MockDeviceSecretStore::new_handle())
+            .build();
+        assert!(matches!(
+            load_device_secret(&other_config),
+            Err(LairError::DeviceSecretMissing(_)),
+        ));
+
+        // until the secret is imported there, under the same reference
+        assert!(bind_to_device(&other_config, None).is_err());
+        assert_eq!(
+            reference,
+            bind_to_device(&other_config, Some(device_secret)).unwrap(),
+        );
+        assert_eq!(
+            Some(device_secret),
+            load_device_secret(&other_config).unwrap(),
+        );
+        // once there, it is never replaced
+        assert!(bind_to_device(&other_config, Some([0; 32])).is_err());
+    }
+}
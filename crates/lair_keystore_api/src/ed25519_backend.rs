@@ -0,0 +1,287 @@
+//! The ed25519 implementation lair signs and verifies with, selected by
+//! cargo feature: `ed25519_dalek` (the default, pure rust) or
+//! `ed25519_libsodium` (the system libsodium). The public api is the same
+//! whichever is selected.
+//!
+//! Signing is deterministic RFC 8032, so both backends make the same
+//! signature of a message. Verification is pinned to the strict policy of
+//! libsodium 1.0.16 and later, in both backends:
+//!
+//! - `S` must be canonical, below the group order, so a signature can not
+//!   be made malleable by adding the order to it.
+//! - The pub key must be a canonical encoding of a point, and the point
+//!   must not be of small order.
+//! - `R` must not be of small order.
+//! - The cofactorless equation `[S]B = R + [k]A` must hold, compared as
+//!   canonical encodings, so `R` must be canonically encoded too.
+//!
+//! Signatures or pub keys of another length never verify. Ed25519ph, see
+//! the `ed25519ph` module, is always on the dalek backend, under the
+//! same policy.
+
+use crate::*;
+
+mod dalek;
+pub(crate) use dalek::{sign_with_dom, verify_with_dom, Dalek};
+
+// cross-check builds only sign with it in tests
+#[cfg(all(
+    feature = "ed25519_libsodium",
+    any(test, not(feature = "ed25519_cross_check"))
+))]
+mod libsodium;
+#[cfg(all(
+    feature = "ed25519_libsodium",
+    any(test, not(feature = "ed25519_cross_check"))
+))]
+pub(crate) use libsodium::Libsodium;
+
+#[cfg(all(
+    feature = "ed25519_dalek",
+    feature = "ed25519_libsodium",
+    not(feature = "ed25519_cross_check")
+))]
+compile_error!(
+    "the ed25519_dalek and ed25519_libsodium features are mutually \
+    exclusive, disable the default features to select ed25519_libsodium"
+);
+
+/// The backend selected by the cargo features. Cross-check builds,
+/// compiling both in, sign with the dalek backend.
+#[cfg(any(
+    not(feature = "ed25519_libsodium"),
+    feature = "ed25519_cross_check"
+))]
+pub(crate) type Backend = Dalek;
+
+/// The backend selected by the cargo features.
+#[cfg(all(
+    feature = "ed25519_libsodium",
+    not(feature = "ed25519_cross_check")
+))]
+pub(crate) type Backend = Libsodium;
+
+/// Byte length of an ed25519 seed.
+pub(crate) const SEED_LEN: usize = 32;
+
+/// Byte length of an ed25519 pub key.
+pub(crate) const PUB_KEY_LEN: usize = 32;
+
+/// Byte length of an ed25519 signature, `R || S`.
+pub(crate) const SIGNATURE_LEN: usize = 64;
+
+/// An ed25519 implementation, see the module docs
+/// for the behavior every one must share.
+pub(crate) trait Ed25519Backend {
+    /// The pub key of the keypair of `seed`.
+    fn pub_key_from_seed(
+        seed: &[u8; SEED_LEN],
+    ) -> LairResult<[u8; PUB_KEY_LEN]>;
+
+    /// Sign `message` with the keypair of `seed`.
+    fn sign(
+        seed: &[u8; SEED_LEN],
+        message: &[u8],
+    ) -> LairResult<[u8; SIGNATURE_LEN]>;
+
+    /// Verify `signature` of `message` by `pub_key`,
+    /// under the pinned verification policy.
+    fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// The seed of a private key, as lair stores it.
+pub(crate) fn seed_from_slice(seed: &[u8]) -> LairResult<[u8; SEED_LEN]> {
+    let mut out = [0; SEED_LEN];
+    if seed.len() != SEED_LEN {
+        return Err("invalid ed25519 seed length".into());
+    }
+    out.copy_from_slice(seed);
+    Ok(out)
+}
+
+/// The pub key of the keypair of `seed`, by the selected backend.
+pub(crate) fn pub_key_from_seed(seed: &[u8]) -> LairResult<Vec<u8>> {
+    Ok(Backend::pub_key_from_seed(&seed_from_slice(seed)?)?.to_vec())
+}
+
+/// Sign `message` with the keypair of `seed`, by the selected backend.
+pub(crate) fn sign(seed: &[u8], message: &[u8]) -> LairResult<Vec<u8>> {
+    Ok(Backend::sign(&seed_from_slice(seed)?, message)?.to_vec())
+}
+
+/// Verify `signature` of `message` by `pub_key`, by the selected backend.
+pub(crate) fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    Backend::verify(pub_key, message, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use test_vectors::from_hex as hex;
+
+    /// The group order, little endian.
+    const L: [u8; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2,
+        0xde, 0xf9, 0xde, 0x14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0x10,
+    ];
+
+    /// An encoding of a point of order 8.
+    const SMALL_ORDER: [u8; 32] = [
+        0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89,
+        0xf2, 0xef, 0x98, 0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+        0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x05,
+    ];
+
+    /// `a + b` of little endian 256 bit integers.
+    fn add_le(a: &[u8], b: &[u8]) -> Vec<u8> {
+        let mut carry = 0u16;
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| {
+                let sum = *a as u16 + *b as u16 + carry;
+                carry = sum >> 8;
+                sum as u8
+            })
+            .collect()
+    }
+
+    fn check_vectors<B: Ed25519Backend>() {
+        for v in test_vectors::ED25519_VECTORS {
+            let seed = seed_from_slice(&hex(v.secret_key)).unwrap();
+            let message = hex(v.message);
+            let pub_key = B::pub_key_from_seed(&seed).unwrap();
+            assert_eq!(hex(v.public_key), pub_key.to_vec());
+            let signature = B::sign(&seed, &message).unwrap();
+            assert_eq!(hex(v.signature), signature.to_vec());
+            assert!(B::verify(&pub_key, &message, &signature));
+            assert!(!B::verify(&pub_key, b"other", &signature));
+        }
+    }
+
+    /// The policy the module docs pin, for any backend.
+    fn check_policy<B: Ed25519Backend>() {
+        let v = &test_vectors::ED25519_VECTORS[1];
+        let seed = seed_from_slice(&hex(v.secret_key)).unwrap();
+        let pub_key = B::pub_key_from_seed(&seed).unwrap().to_vec();
+        let message = hex(v.message);
+        let signature = B::sign(&seed, &message).unwrap().to_vec();
+        assert!(B::verify(&pub_key, &message, &signature));
+
+        // S + l passes the cofactorless equation, but is not canonical
+        let mut malleated = signature.clone();
+        let s_plus_l = add_le(&signature[32..], &L);
+        malleated[32..].copy_from_slice(&s_plus_l);
+        assert!(!B::verify(&pub_key, &message, &malleated));
+
+        // a small order pub key, with the identity as R and a zero S,
+        // passes the equation for any message
+        let mut forged = vec![0; SIGNATURE_LEN];
+        forged[0] = 1;
+        assert!(!B::verify(&SMALL_ORDER, &message, &forged));
+        let mut identity = [0; PUB_KEY_LEN];
+        identity[0] = 1;
+        assert!(!B::verify(&identity, &message, &forged));
+
+        // a non canonical pub key, y = p + 1 encoding the identity
+        let mut non_canonical = [0xff; PUB_KEY_LEN];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        assert!(!B::verify(&non_canonical, &message, &forged));
+
+        // a small order R
+        let mut small_r = signature.clone();
+        small_r[..32].copy_from_slice(&SMALL_ORDER);
+        assert!(!B::verify(&pub_key, &message, &small_r));
+
+        // other lengths
+        assert!(!B::verify(&pub_key[..31], &message, &signature));
+        assert!(!B::verify(&pub_key, &message, &signature[..63]));
+        let mut long = signature.clone();
+        long.push(0);
+        assert!(!B::verify(&pub_key, &message, &long));
+    }
+
+    #[test]
+    fn dalek_matches_rfc8032_vectors() {
+        check_vectors::<Dalek>();
+        check_policy::<Dalek>();
+    }
+
+    /// Ed25519ph of the sha-512 of the message, under an empty context.
+    struct Ed25519ph;
+
+    impl Ed25519Backend for Ed25519ph {
+        fn pub_key_from_seed(
+            seed: &[u8; SEED_LEN],
+        ) -> LairResult<[u8; PUB_KEY_LEN]> {
+            Dalek::pub_key_from_seed(seed)
+        }
+
+        fn sign(
+            seed: &[u8; SEED_LEN],
+            message: &[u8],
+        ) -> LairResult<[u8; SIGNATURE_LEN]> {
+            ed25519ph::sign(seed, b"", &ed25519ph::prehash(message))
+        }
+
+        fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+            let prehash = ed25519ph::prehash(message);
+            ed25519ph::verify(pub_key, b"", &prehash, signature)
+        }
+    }
+
+    #[test]
+    fn ed25519ph_follows_the_policy() {
+        check_policy::<Ed25519ph>();
+    }
+
+    #[cfg(feature = "ed25519_libsodium")]
+    #[test]
+    fn libsodium_matches_rfc8032_vectors() {
+        check_vectors::<Libsodium>();
+        check_policy::<Libsodium>();
+    }
+
+    #[cfg(feature = "ed25519_libsodium")]
+    proptest! {
+        #[test]
+        fn backends_cross_verify(
+            seed in any::<[u8; SEED_LEN]>(),
+            message in proptest::collection::vec(any::<u8>(), 0..256),
+            flip in any::<(usize, u8)>(),
+        ) {
+            let pub_key = Dalek::pub_key_from_seed(&seed).unwrap();
+            prop_assert_eq!(
+                pub_key,
+                Libsodium::pub_key_from_seed(&seed).unwrap()
+            );
+            let dalek = Dalek::sign(&seed, &message).unwrap();
+            let libsodium = Libsodium::sign(&seed, &message).unwrap();
+            prop_assert_eq!(&dalek[..], &libsodium[..]);
+            prop_assert!(Libsodium::verify(&pub_key, &message, &dalek));
+            prop_assert!(Dalek::verify(&pub_key, &message, &libsodium));
+
+            // and both refuse the same tampered signatures
+            let mut tampered = dalek;
+            tampered[flip.0 % SIGNATURE_LEN] ^= flip.1.max(1);
+            prop_assert_eq!(
+                Dalek::verify(&pub_key, &message, &tampered),
+                Libsodium::verify(&pub_key, &message, &tampered)
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn dalek_verifies_what_it_signs(
+            seed in any::<[u8; SEED_LEN]>(),
+            message in proptest::collection::vec(any::<u8>(), 0..256),
+        ) {
+            let pub_key = Dalek::pub_key_from_seed(&seed).unwrap();
+            let signature = Dalek::sign(&seed, &message).unwrap();
+            prop_assert!(Backend::verify(&pub_key, &message, &signature));
+        }
+    }
+}
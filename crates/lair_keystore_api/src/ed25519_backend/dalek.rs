@@ -0,0 +1,118 @@
+//! Pure rust ed25519, on the curve25519-dalek group arithmetic.
+
+use super::*;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{clamp_integer, Scalar};
+use sha2::{Digest, Sha512};
+
+/// The curve25519-dalek backend.
+pub(crate) struct Dalek;
+
+/// The secret scalar and nonce prefix RFC 8032 expands a seed into.
+fn expand(seed: &[u8; SEED_LEN]) -> ([u8; 32], [u8; 32]) {
+    let hash = Sha512::digest(seed);
+    let mut lower = [0; 32];
+    let mut prefix = [0; 32];
+    lower.copy_from_slice(&hash[..32]);
+    prefix.copy_from_slice(&hash[32..]);
+    (clamp_integer(lower), prefix)
+}
+
+/// `Sha512(parts..)` reduced to a scalar.
+fn hash_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut wide = [0; 64];
+    wide.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_mod_order_wide(&wide)
+}
+
+/// Decompress a point, only from its canonical encoding,
+/// and never a point of small order.
+fn decompress_strict(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+    let point = CompressedEdwardsY(*bytes).decompress()?;
+    if point.compress().as_bytes() != bytes || point.is_small_order() {
+        return None;
+    }
+    Some(point)
+}
+
+/// Sign `message` with the keypair of `seed`, prefixing the hashes with
+/// `dom`: empty for pure ed25519, RFC 8032 `dom2` for Ed25519ph.
+pub(crate) fn sign_with_dom(
+    seed: &[u8; SEED_LEN],
+    dom: &[u8],
+    message: &[u8],
+) -> [u8; SIGNATURE_LEN] {
+    let (scalar, prefix) = expand(seed);
+    let a = Scalar::from_bytes_mod_order(scalar);
+    let pub_key = EdwardsPoint::mul_base(&a).compress();
+    let r = hash_scalar(&[dom, &prefix, message]);
+    let big_r = EdwardsPoint::mul_base(&r).compress();
+    let k = hash_scalar(&[dom, big_r.as_bytes(), pub_key.as_bytes(), message]);
+    let s = r + k * a;
+    let mut out = [0; SIGNATURE_LEN];
+    out[..32].copy_from_slice(big_r.as_bytes());
+    out[32..].copy_from_slice(s.as_bytes());
+    out
+}
+
+/// Verify `signature` of `message` by `pub_key` under the pinned policy,
+/// prefixing the hash with `dom`, as `sign_with_dom` does.
+pub(crate) fn verify_with_dom(
+    pub_key: &[u8],
+    dom: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    if pub_key.len() != PUB_KEY_LEN || signature.len() != SIGNATURE_LEN {
+        return false;
+    }
+    let mut a_bytes = [0; 32];
+    let mut r_bytes = [0; 32];
+    let mut s_bytes = [0; 32];
+    a_bytes.copy_from_slice(pub_key);
+    r_bytes.copy_from_slice(&signature[..32]);
+    s_bytes.copy_from_slice(&signature[32..]);
+
+    let s: Option<Scalar> = Scalar::from_canonical_bytes(s_bytes).into();
+    let s = match s {
+        Some(s) => s,
+        None => return false,
+    };
+    let a = match decompress_strict(&a_bytes) {
+        Some(a) => a,
+        None => return false,
+    };
+    // a non canonical R fails the comparison below anyway
+    match CompressedEdwardsY(r_bytes).decompress() {
+        Some(r) if !r.is_small_order() => (),
+        _ => return false,
+    }
+    let k = hash_scalar(&[dom, &r_bytes, &a_bytes, message]);
+    let check =
+        EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &(-a), &s);
+    check.compress().as_bytes() == &r_bytes
+}
+
+impl Ed25519Backend for Dalek {
+    fn pub_key_from_seed(
+        seed: &[u8; SEED_LEN],
+    ) -> LairResult<[u8; PUB_KEY_LEN]> {
+        let (scalar, _) = expand(seed);
+        Ok(EdwardsPoint::mul_base_clamped(scalar).compress().to_bytes())
+    }
+
+    fn sign(
+        seed: &[u8; SEED_LEN],
+        message: &[u8],
+    ) -> LairResult<[u8; SIGNATURE_LEN]> {
+        Ok(sign_with_dom(seed, &[], message))
+    }
+
+    fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        verify_with_dom(pub_key, &[], message, signature)
+    }
+}
@@ -0,0 +1,130 @@
+//! ed25519 through the system libsodium, which must be 1.0.16 or later
+//! for the verification policy the module docs pin.
+
+use super::*;
+use std::os::raw::{c_int, c_uchar, c_ulonglong, c_void};
+
+#[link(name = "sodium")]
+extern "C" {
+    fn sodium_init() -> c_int;
+    fn sodium_memzero(pnt: *mut c_void, len: usize);
+    fn crypto_sign_seed_keypair(
+        pk: *mut c_uchar,
+        sk: *mut c_uchar,
+        seed: *const c_uchar,
+    ) -> c_int;
+    fn crypto_sign_detached(
+        sig: *mut c_uchar,
+        siglen_p: *mut c_ulonglong,
+        m: *const c_uchar,
+        mlen: c_ulonglong,
+        sk: *const c_uchar,
+    ) -> c_int;
+    fn crypto_sign_verify_detached(
+        sig: *const c_uchar,
+        m: *const c_uchar,
+        mlen: c_ulonglong,
+        pk: *const c_uchar,
+    ) -> c_int;
+}
+
+/// Byte length of a libsodium secret key, `seed || pub_key`.
+const SECRET_KEY_LEN: usize = 64;
+
+/// The libsodium backend.
+pub(crate) struct Libsodium;
+
+fn init() -> LairResult<()> {
+    static INIT: once_cell::sync::Lazy<c_int> =
+        once_cell::sync::Lazy::new(|| {
+            // safety: sodium_init is safe to call from any thread, and
+            // returns 1 if already initialized
+            unsafe { sodium_init() }
+        });
+    if *INIT < 0 {
+        return Err("libsodium failed to initialize".into());
+    }
+    Ok(())
+}
+
+/// The libsodium secret key of `seed`, wiped when dropped.
+struct SecretKey([u8; SECRET_KEY_LEN]);
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        // safety: the pointer and length are of our own array
+        unsafe {
+            sodium_memzero(self.0.as_mut_ptr() as *mut c_void, SECRET_KEY_LEN)
+        }
+    }
+}
+
+fn seed_keypair(
+    seed: &[u8; SEED_LEN],
+) -> LairResult<([u8; PUB_KEY_LEN], SecretKey)> {
+    init()?;
+    let mut pub_key = [0; PUB_KEY_LEN];
+    let mut secret_key = SecretKey([0; SECRET_KEY_LEN]);
+    // safety: every buffer is the length libsodium expects
+    let res = unsafe {
+        crypto_sign_seed_keypair(
+            pub_key.as_mut_ptr(),
+            secret_key.0.as_mut_ptr(),
+            seed.as_ptr(),
+        )
+    };
+    if res != 0 {
+        return Err("libsodium failed to derive ed25519 keypair".into());
+    }
+    Ok((pub_key, secret_key))
+}
+
+impl Ed25519Backend for Libsodium {
+    fn pub_key_from_seed(
+        seed: &[u8; SEED_LEN],
+    ) -> LairResult<[u8; PUB_KEY_LEN]> {
+        Ok(seed_keypair(seed)?.0)
+    }
+
+    fn sign(
+        seed: &[u8; SEED_LEN],
+        message: &[u8],
+    ) -> LairResult<[u8; SIGNATURE_LEN]> {
+        let (_, secret_key) = seed_keypair(seed)?;
+        let mut signature = [0; SIGNATURE_LEN];
+        // safety: the signature and secret key buffers are the lengths
+        // libsodium expects, the message pointer is valid for its length
+        let res = unsafe {
+            crypto_sign_detached(
+                signature.as_mut_ptr(),
+                std::ptr::null_mut(),
+                message.as_ptr(),
+                message.len() as c_ulonglong,
+                secret_key.0.as_ptr(),
+            )
+        };
+        if res != 0 {
+            return Err("libsodium failed to sign".into());
+        }
+        Ok(signature)
+    }
+
+    fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        if pub_key.len() != PUB_KEY_LEN || signature.len() != SIGNATURE_LEN {
+            return false;
+        }
+        if init().is_err() {
+            return false;
+        }
+        // safety: the lengths are checked above,
+        // the message pointer is valid for its length
+        unsafe {
+            crypto_sign_verify_detached(
+                signature.as_ptr(),
+                message.as_ptr(),
+                message.len() as c_ulonglong,
+                pub_key.as_ptr(),
+            ) == 0
+        }
+    }
+}
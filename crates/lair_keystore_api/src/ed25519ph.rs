@@ -0,0 +1,157 @@
+//! Pure Ed25519ph, the pre-hashed ed25519 variant of RFC 8032 section 5.1,
+//! for signing messages too large to send to the keystore whole.
+//!
+//! The message is hashed with sha-512 by the caller, and only the 64 byte
+//! hash is signed, under an optional context of up to 255 bytes. An
+//! Ed25519ph signature never verifies as a pure ed25519 signature, nor
+//! the reverse, even for the same key and bytes.
+//!
+//! Signing and verification are those of the dalek ed25519 backend,
+//! under its strict verification policy, with the `dom2` prefix.
+
+use crate::*;
+
+/// Byte length of the sha-512 hash of the message that is signed.
+pub const PREHASH_LEN: usize = 64;
+
+/// Maximum byte length of a signature context.
+pub const MAX_CONTEXT_LEN: usize = 255;
+
+/// Byte length of a signature, `R || S`.
+pub const SIGNATURE_LEN: usize = 64;
+
+const DOM2_PREFIX: &[u8] = b"SigEd25519 no Ed25519 collisions";
+
+fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    use sha2::{Digest, Sha512};
+    let mut hash = Sha512::new();
+    for part in parts {
+        hash.update(part);
+    }
+    hash.finalize().into()
+}
+
+fn dom2(context: &[u8]) -> LairResult<Vec<u8>> {
+    if context.len() > MAX_CONTEXT_LEN {
+        return Err(format!(
+            "ed25519ph context too long: {} > {}",
+            context.len(),
+            MAX_CONTEXT_LEN,
+        )
+        .into());
+    }
+    let mut out = DOM2_PREFIX.to_vec();
+    // phflag 1, the message is pre-hashed
+    out.push(1);
+    out.push(context.len() as u8);
+    out.extend_from_slice(context);
+    Ok(out)
+}
+
+/// The sha-512 hash of `message` to sign. Use `prehash_reader`, or
+/// `ring::digest` incrementally, for messages too large to hold in memory.
+pub fn prehash(message: &[u8]) -> [u8; PREHASH_LEN] {
+    sha512(&[message])
+}
+
+/// Byte length of the chunks `prehash_reader` reads at once.
+pub const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// The sha-512 hash of everything `reader` yields, to its end. Reads a
+/// chunk at a time, only once the last is hashed, so memory stays bounded
+/// by `READ_CHUNK_LEN` whatever the length of the message.
+#[cfg(feature = "full")]
+pub async fn prehash_reader(
+    mut reader: impl tokio::io::AsyncRead + Unpin,
+) -> LairResult<[u8; PREHASH_LEN]> {
+    use tokio::io::AsyncReadExt;
+    let mut hash = ring::digest::Context::new(&ring::digest::SHA512);
+    let mut buf = vec![0; READ_CHUNK_LEN];
+    loop {
+        let read = reader.read(&mut buf).await.map_err(LairError::other)?;
+        if read == 0 {
+            break;
+        }
+        hash.update(&buf[..read]);
+    }
+    let mut out = [0; PREHASH_LEN];
+    out.copy_from_slice(hash.finish().as_ref());
+    Ok(out)
+}
+
+/// Ed25519ph sign the sha-512 `prehash` of a message, under `context`,
+/// with the 32 byte ed25519 seed (the lair ed25519 private key).
+pub fn sign(
+    seed: &[u8],
+    context: &[u8],
+    prehash: &[u8; PREHASH_LEN],
+) -> LairResult<[u8; SIGNATURE_LEN]> {
+    let dom = dom2(context)?;
+    let seed = ed25519_backend::seed_from_slice(seed)?;
+    Ok(ed25519_backend::sign_with_dom(&seed, &dom, prehash))
+}
+
+/// Verify an Ed25519ph signature of the sha-512 `prehash`
+/// of a message, under `context`.
+pub fn verify(
+    pub_key: &[u8],
+    context: &[u8],
+    prehash: &[u8; PREHASH_LEN],
+    signature: &[u8],
+) -> bool {
+    match dom2(context) {
+        Ok(dom) => {
+            ed25519_backend::verify_with_dom(pub_key, &dom, prehash, signature)
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_vectors::{from_hex as hex, ED25519PH_VECTOR as V};
+
+    #[test]
+    fn it_matches_rfc8032_vector() {
+        let hash = prehash(&hex(V.message));
+        let sig = sign(&hex(V.secret_key), b"", &hash).unwrap();
+        assert_eq!(hex(V.signature), sig.to_vec());
+        assert!(verify(&hex(V.public_key), b"", &hash, &sig));
+    }
+
+    #[test]
+    fn it_rejects_bad_signatures() {
+        let pub_key = hex(V.public_key);
+        let message = hex(V.message);
+        let hash = prehash(&message);
+        let sig = hex(V.signature);
+
+        let mut bad = sig.clone();
+        bad[40] ^= 1;
+        assert!(!verify(&pub_key, b"", &hash, &bad));
+        assert!(!verify(&pub_key, b"ctx", &hash, &sig));
+        assert!(!verify(&pub_key, b"", &prehash(b"abd"), &sig));
+        assert!(!verify(&pub_key, b"", &hash, &sig[..63]));
+
+        // not a pure ed25519 signature of the message, nor of its hash
+        let pk = ed25519_compact::PublicKey::from_slice(&pub_key).unwrap();
+        let sig = ed25519_compact::Signature::from_slice(&sig).unwrap();
+        assert!(pk.verify(&message, &sig).is_err());
+        assert!(pk.verify(&hash[..], &sig).is_err());
+    }
+
+    #[test]
+    fn it_signs_under_a_context() {
+        let seed = hex(V.secret_key);
+        let pub_key = hex(V.public_key);
+        let hash = prehash(&hex(V.message));
+        let sig = sign(&seed, b"lair", &hash).unwrap();
+        assert!(verify(&pub_key, b"lair", &hash, &sig));
+        assert!(!verify(&pub_key, b"", &hash, &sig));
+
+        assert!(sign(&seed, &[0; MAX_CONTEXT_LEN], &hash).is_ok());
+        assert!(sign(&seed, &[0; MAX_CONTEXT_LEN + 1], &hash).is_err());
+        assert!(sign(&seed[..31], b"", &hash).is_err());
+    }
+}
@@ -4,11 +4,49 @@ use crate::*;
 
 use actor::*;
 use internal::codec;
+use internal::pw_hash::PwPepper;
+use internal::sign_bls::SignBlsPrivKey;
 use internal::sign_ed25519::SignEd25519PrivKey;
+#[cfg(feature = "ed448")]
+use internal::sign_ed448::SignEd448PrivKey;
+use internal::sign_secp256k1::SignSecp256k1PrivKey;
+use internal::sign_sr25519::SignSr25519PrivKey;
+use internal::totp::TotpSecret;
+use internal::x25519::X25519PrivKey;
 
 /// Fixed serialized entry byte count.
 pub const ENTRY_SIZE: usize = 1024;
 
+/// Tags and trash written with `KeystoreIndex64` indexes start with
+/// this, those written before start with their (far smaller) count.
+/// Older files are read, and rewritten wide on their next write.
+const WIDE_INDEX_FORMAT: u32 = u32::MAX;
+
+/// Read the count opening encoded tags or trash, and whether
+/// their indexes are wide.
+fn read_index_format(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<(u32, bool)> {
+    match reader.read_u32()? {
+        WIDE_INDEX_FORMAT => Ok((reader.read_u32()?, true)),
+        count => Ok((count, false)),
+    }
+}
+
+/// Read an index of encoded tags or trash. Stores hold no entries
+/// above `u32::MAX`, wider indexes fail with `IndexOutOfRange`.
+fn read_stored_index(
+    reader: &mut codec::CodecReader<'_>,
+    wide: bool,
+) -> LairResult<KeystoreIndex> {
+    match wide {
+        true => {
+            std::convert::TryFrom::try_from(KeystoreIndex64(reader.read_u64()?))
+        }
+        false => Ok(reader.read_u32()?.into()),
+    }
+}
+
 /// Enum of lair entry types for decoding.
 #[non_exhaustive]
 #[derive(Debug, Clone)]
@@ -18,6 +56,31 @@ pub enum LairEntry {
 
     /// Sign Ed25519
     SignEd25519(EntrySignEd25519),
+
+    /// Sign Ed25519 held in a hardware token
+    SignEd25519Hardware(EntrySignEd25519Hardware),
+
+    /// X25519
+    X25519(EntryX25519),
+
+    /// Pw Pepper
+    PwPepper(EntryPwPepper),
+
+    /// Sign Secp256k1
+    SignSecp256k1(EntrySignSecp256k1),
+
+    /// Totp Secret
+    TotpSecret(EntryTotpSecret),
+
+    /// Sign Ed448
+    #[cfg(feature = "ed448")]
+    SignEd448(EntrySignEd448),
+
+    /// Sign Bls
+    SignBls(EntrySignBls),
+
+    /// Sign Sr25519
+    SignSr25519(EntrySignSr25519),
 }
 
 impl From<EntryTlsCert> for LairEntry {
@@ -32,6 +95,55 @@ impl From<EntrySignEd25519> for LairEntry {
     }
 }
 
+impl From<EntrySignEd25519Hardware> for LairEntry {
+    fn from(o: EntrySignEd25519Hardware) -> Self {
+        Self::SignEd25519Hardware(o)
+    }
+}
+
+impl From<EntryX25519> for LairEntry {
+    fn from(o: EntryX25519) -> Self {
+        Self::X25519(o)
+    }
+}
+
+impl From<EntryPwPepper> for LairEntry {
+    fn from(o: EntryPwPepper) -> Self {
+        Self::PwPepper(o)
+    }
+}
+
+impl From<EntrySignSecp256k1> for LairEntry {
+    fn from(o: EntrySignSecp256k1) -> Self {
+        Self::SignSecp256k1(o)
+    }
+}
+
+impl From<EntryTotpSecret> for LairEntry {
+    fn from(o: EntryTotpSecret) -> Self {
+        Self::TotpSecret(o)
+    }
+}
+
+#[cfg(feature = "ed448")]
+impl From<EntrySignEd448> for LairEntry {
+    fn from(o: EntrySignEd448) -> Self {
+        Self::SignEd448(o)
+    }
+}
+
+impl From<EntrySignBls> for LairEntry {
+    fn from(o: EntrySignBls) -> Self {
+        Self::SignBls(o)
+    }
+}
+
+impl From<EntrySignSr25519> for LairEntry {
+    fn from(o: EntrySignSr25519) -> Self {
+        Self::SignSr25519(o)
+    }
+}
+
 impl LairEntry {
     /// Decode a disk entry.
     /// @todo - once we're integrated with sodoken, this should decrypt too
@@ -46,10 +158,56 @@ impl LairEntry {
 
         Ok(match entry_type {
             codec::EntryType::TlsCert => {
-                LairEntry::TlsCert(entry_decode_tls_cert(reader)?)
+                LairEntry::TlsCert(entry_decode_tls_cert(reader, false)?)
+            }
+            codec::EntryType::TlsCertWithAlg => {
+                LairEntry::TlsCert(entry_decode_tls_cert(reader, true)?)
+            }
+            codec::EntryType::TlsCertBound => {
+                LairEntry::TlsCert(entry_decode_tls_cert_bound(reader)?)
+            }
+            codec::EntryType::SignEd25519 => LairEntry::SignEd25519(
+                entry_decode_sign_ed25519(reader, false, false)?,
+            ),
+            codec::EntryType::SignEd25519WithPolicy => LairEntry::SignEd25519(
+                entry_decode_sign_ed25519(reader, true, false)?,
+            ),
+            codec::EntryType::SignEd25519Rotated => LairEntry::SignEd25519(
+                entry_decode_sign_ed25519(reader, true, true)?,
+            ),
+            codec::EntryType::SignEd25519Hardware => {
+                LairEntry::SignEd25519Hardware(
+                    entry_decode_sign_ed25519_hardware(reader)?,
+                )
+            }
+            codec::EntryType::X25519 => {
+                LairEntry::X25519(entry_decode_x25519(reader)?)
+            }
+            codec::EntryType::PwPepper => {
+                LairEntry::PwPepper(entry_decode_pw_pepper(reader)?)
+            }
+            codec::EntryType::SignSecp256k1 => {
+                LairEntry::SignSecp256k1(entry_decode_sign_secp256k1(reader)?)
+            }
+            codec::EntryType::TotpSecret => {
+                LairEntry::TotpSecret(entry_decode_totp_secret(reader)?)
+            }
+            #[cfg(feature = "ed448")]
+            codec::EntryType::SignEd448 => {
+                LairEntry::SignEd448(entry_decode_sign_ed448(reader)?)
             }
-            codec::EntryType::SignEd25519 => {
-                LairEntry::SignEd25519(entry_decode_sign_ed25519(reader)?)
+            #[cfg(not(feature = "ed448"))]
+            codec::EntryType::SignEd448 => {
+                return Err(LairError::unsupported_alg("Ed448 entry", "ed448"))
+            }
+            codec::EntryType::SignBls => {
+                LairEntry::SignBls(entry_decode_sign_bls(reader)?)
+            }
+            codec::EntryType::SignSr25519 => {
+                LairEntry::SignSr25519(entry_decode_sign_sr25519(reader)?)
+            }
+            codec::EntryType::Erased => {
+                return Err("entry has been erased".into())
             }
         })
     }
@@ -61,12 +219,514 @@ impl LairEntry {
         match self {
             LairEntry::TlsCert(e) => e.encode(),
             LairEntry::SignEd25519(e) => e.encode(),
+            LairEntry::SignEd25519Hardware(e) => e.encode(),
+            LairEntry::X25519(e) => e.encode(),
+            LairEntry::PwPepper(e) => e.encode(),
+            LairEntry::SignSecp256k1(e) => e.encode(),
+            LairEntry::TotpSecret(e) => e.encode(),
+            #[cfg(feature = "ed448")]
+            LairEntry::SignEd448(e) => e.encode(),
+            LairEntry::SignBls(e) => e.encode(),
+            LairEntry::SignSr25519(e) => e.encode(),
+        }
+    }
+
+    /// The public identifier of this entry (cert digest or public key),
+    /// None for peppers and totp secrets, which have none.
+    pub fn pub_id(&self) -> Option<Arc<Vec<u8>>> {
+        match self {
+            LairEntry::TlsCert(e) => Some(e.cert_digest.0.clone()),
+            LairEntry::SignEd25519(e) => Some(e.pub_key.0.clone()),
+            LairEntry::SignEd25519Hardware(e) => Some(e.pub_key.0.clone()),
+            LairEntry::X25519(e) => Some(e.pub_key.0.clone()),
+            LairEntry::PwPepper(_) => None,
+            LairEntry::SignSecp256k1(e) => Some(e.pub_key.0.clone()),
+            LairEntry::TotpSecret(_) => None,
+            #[cfg(feature = "ed448")]
+            LairEntry::SignEd448(e) => Some(e.pub_key.0.clone()),
+            LairEntry::SignBls(e) => Some(e.pub_key.0.clone()),
+            LairEntry::SignSr25519(e) => Some(e.pub_key.0.clone()),
+        }
+    }
+
+    /// The type of this entry, as clients see it.
+    pub fn entry_type(&self) -> LairEntryType {
+        match self {
+            LairEntry::TlsCert(_) => LairEntryType::TlsCert,
+            LairEntry::SignEd25519(_) => LairEntryType::SignEd25519,
+            // to clients, an ed25519 keypair like any other
+            LairEntry::SignEd25519Hardware(_) => LairEntryType::SignEd25519,
+            LairEntry::X25519(_) => LairEntryType::X25519,
+            LairEntry::PwPepper(_) => LairEntryType::PwPepper,
+            LairEntry::SignSecp256k1(_) => LairEntryType::SignSecp256k1,
+            LairEntry::TotpSecret(_) => LairEntryType::TotpSecret,
+            #[cfg(feature = "ed448")]
+            LairEntry::SignEd448(_) => LairEntryType::SignEd448,
+            LairEntry::SignBls(_) => LairEntryType::SignBls,
+            LairEntry::SignSr25519(_) => LairEntryType::SignSr25519,
+        }
+    }
+
+    /// The `LairError::WrongEntryType` for using this entry,
+    /// at `keystore_index`, as an `expected` entry.
+    pub fn wrong_type(
+        &self,
+        keystore_index: KeystoreIndex,
+        expected: LairEntryType,
+    ) -> LairError {
+        LairError::WrongEntryType {
+            index: keystore_index,
+            expected,
+            actual: self.entry_type(),
+        }
+    }
+
+    /// Summarize this entry for entry listing.
+    pub fn to_info(&self, keystore_index: KeystoreIndex) -> LairEntryInfo {
+        let entry_type = self.entry_type();
+        let rotated_from = match self {
+            LairEntry::SignEd25519(entry) => entry.policy.rotated_from,
+            _ => None,
+        };
+        LairEntryInfo {
+            keystore_index,
+            entry_type,
+            pub_id: self.pub_id().unwrap_or_default(),
+            tag: None,
+            aliases: Vec::new(),
+            trashed: false,
+            rotated_from,
+            activated: true,
+        }
+    }
+}
+
+/// Encode an erased entry for writing to disk, in place of a purged entry,
+/// so the keystore indices of the entries following it do not change.
+pub fn encode_erased_entry() -> LairResult<Vec<u8>> {
+    let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+    // pre padding
+    writer.write_pre_padding(64)?;
+
+    // erased entry type, nothing else is kept
+    writer.write_entry_type(codec::EntryType::Erased)?;
+
+    Ok(writer.into_vec())
+}
+
+/// Is this disk entry an erased entry, see `encode_erased_entry`?
+pub fn is_erased_entry(data: &[u8]) -> LairResult<bool> {
+    let mut reader = codec::CodecReader::new(data);
+    reader.read_pre_padding()?;
+    Ok(reader.read_entry_type()? == codec::EntryType::Erased)
+}
+
+/// Build a `lair_list_entries_page` response from an index-ordered map.
+pub fn list_entries_page<E>(
+    entries: &std::collections::BTreeMap<KeystoreIndex, E>,
+    tags: &EntryTags,
+    trash: &EntryTrash,
+    inactive: &EntryInactive,
+    start: KeystoreIndex,
+    limit: u32,
+) -> LairResult<(Vec<LairEntryInfo>, Option<KeystoreIndex>)>
+where
+    E: std::borrow::Borrow<LairEntry>,
+{
+    if limit == 0 {
+        return Err("list entries page limit must be greater than 0".into());
+    }
+    let limit = limit.min(MAX_LIST_ENTRIES_PAGE_LIMIT) as usize;
+    let mut iter = entries.range(start..);
+    let page = iter
+        .by_ref()
+        .take(limit)
+        .map(|(idx, entry)| LairEntryInfo {
+            tag: tags.get(*idx).map(|tag| tag.to_string()),
+            aliases: tags.aliases(*idx),
+            trashed: trash.is_trashed(*idx),
+            activated: inactive.is_active(*idx),
+            ..entry.borrow().to_info(*idx)
+        })
+        .collect();
+    let next_start = iter.next().map(|(idx, _)| *idx);
+    Ok((page, next_start))
+}
+
+/// Build a `lair_get_entry_types` response from an index-keyed map.
+/// Indices missing from the map are `LairEntryType::Invalid`.
+pub fn get_entry_types<E>(
+    entries: &std::collections::BTreeMap<KeystoreIndex, E>,
+    indices: &[KeystoreIndex],
+) -> LairResult<Vec<(KeystoreIndex, LairEntryType)>>
+where
+    E: std::borrow::Borrow<LairEntry>,
+{
+    if indices.len() > MAX_GET_ENTRY_TYPES_LEN as usize {
+        return Err(format!(
+            "at most {} entry types per batch, got {}",
+            MAX_GET_ENTRY_TYPES_LEN,
+            indices.len()
+        )
+        .into());
+    }
+    Ok(indices
+        .iter()
+        .map(|idx| {
+            let entry_type = match entries.get(idx) {
+                Some(entry) => entry.borrow().entry_type(),
+                None => LairEntryType::Invalid,
+            };
+            (*idx, entry_type)
+        })
+        .collect())
+}
+
+/// Build a `lair_find_entries_by_prefix` response from an index-keyed
+/// map, skipping the entries in `trash`.
+pub fn find_entries_by_prefix<E>(
+    entries: &std::collections::BTreeMap<KeystoreIndex, E>,
+    trash: &EntryTrash,
+    prefix: &[u8],
+    kind: PrefixKind,
+) -> LairResult<Vec<(KeystoreIndex, LairEntryType)>>
+where
+    E: std::borrow::Borrow<LairEntry>,
+{
+    check_entry_prefix(prefix)?;
+    Ok(entries
+        .iter()
+        .filter(|(idx, _)| !trash.is_trashed(**idx))
+        .filter_map(|(idx, entry)| {
+            let entry = entry.borrow();
+            let entry_type = entry.to_info(*idx).entry_type;
+            if !kind.searches(entry_type) {
+                return None;
+            }
+            match entry.pub_id() {
+                Some(pub_id) if pub_id.starts_with(prefix) => {
+                    Some((*idx, entry_type))
+                }
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Check that an entry tag is non-empty and within `MAX_ENTRY_TAG_LEN`.
+pub fn check_entry_tag(tag: &str) -> LairResult<()> {
+    if tag.is_empty() {
+        return Err("entry tag must not be empty".into());
+    }
+    if tag.len() > MAX_ENTRY_TAG_LEN {
+        return Err(format!(
+            "entry tag exceeds {} byte maximum",
+            MAX_ENTRY_TAG_LEN
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Unique entry tags, each held by at most one entry,
+/// with at most one tag per entry.
+/// Entries may additionally hold up to `MAX_ENTRY_ALIASES` aliases,
+/// resolved like tags. A name is either a tag or an alias, never both.
+#[derive(Debug, Default, Clone)]
+pub struct EntryTags {
+    by_tag: std::collections::HashMap<String, KeystoreIndex>,
+    by_index: std::collections::HashMap<KeystoreIndex, String>,
+    aliases: std::collections::HashMap<String, KeystoreIndex>,
+}
+
+impl EntryTags {
+    /// Set (or with `None` clear) the tag of an entry.
+    /// If another entry held the tag, it is moved to this entry.
+    pub fn set(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: Option<String>,
+    ) -> LairResult<()> {
+        if let Some(tag) = &tag {
+            check_entry_tag(tag)?;
+            if self.aliases.contains_key(tag) {
+                return Err(LairError::TagInUse(tag.clone()));
+            }
+        }
+        if let Some(old_tag) = self.by_index.remove(&keystore_index) {
+            self.by_tag.remove(&old_tag);
+        }
+        if let Some(tag) = tag {
+            if let Some(old_index) =
+                self.by_tag.insert(tag.clone(), keystore_index)
+            {
+                self.by_index.remove(&old_index);
+            }
+            self.by_index.insert(keystore_index, tag);
+        }
+        Ok(())
+    }
+
+    /// Add an alias to an entry. Unlike tags, aliases never move:
+    /// an alias (or tag) already held by any entry is `TagInUse`.
+    pub fn add_alias(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        alias: String,
+    ) -> LairResult<()> {
+        check_entry_tag(&alias)?;
+        if self.by_tag.contains_key(&alias) || self.aliases.contains_key(&alias)
+        {
+            return Err(LairError::TagInUse(alias));
+        }
+        if self.aliases(keystore_index).len() >= MAX_ENTRY_ALIASES {
+            return Err(format!(
+                "entries hold at most {} aliases",
+                MAX_ENTRY_ALIASES
+            )
+            .into());
+        }
+        self.aliases.insert(alias, keystore_index);
+        Ok(())
+    }
+
+    /// Remove an alias, returning the index of the entry that held it.
+    pub fn remove_alias(&mut self, alias: &str) -> LairResult<KeystoreIndex> {
+        self.aliases
+            .remove(alias)
+            .ok_or_else(|| LairError::TagNotFound(alias.to_string()))
+    }
+
+    /// Resolve a tag or alias to the index of the entry holding it.
+    pub fn resolve(&self, tag: &str) -> LairResult<KeystoreIndex> {
+        self.by_tag
+            .get(tag)
+            .or_else(|| self.aliases.get(tag))
+            .copied()
+            .ok_or_else(|| LairError::TagNotFound(tag.to_string()))
+    }
+
+    /// Get the tag held by an entry, if any.
+    pub fn get(&self, keystore_index: KeystoreIndex) -> Option<&str> {
+        self.by_index.get(&keystore_index).map(|t| t.as_str())
+    }
+
+    /// Drop the tag and aliases of an entry that no longer exists.
+    pub fn remove_index(&mut self, keystore_index: KeystoreIndex) {
+        if let Some(tag) = self.by_index.remove(&keystore_index) {
+            self.by_tag.remove(&tag);
+        }
+        self.aliases.retain(|_, idx| *idx != keystore_index);
+    }
+
+    /// Get the aliases held by an entry, sorted.
+    pub fn aliases(&self, keystore_index: KeystoreIndex) -> Vec<String> {
+        let mut out = self
+            .aliases
+            .iter()
+            .filter(|(_, idx)| **idx == keystore_index)
+            .map(|(alias, _)| alias.clone())
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
+
+    /// Encode all tags, then all aliases, for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut tags = self.by_index.iter().collect::<Vec<_>>();
+        tags.sort();
+        let mut aliases = self
+            .aliases
+            .iter()
+            .map(|(alias, idx)| (idx, alias))
+            .collect::<Vec<_>>();
+        aliases.sort();
+        let size = 4
+            + 4
+            + tags.iter().map(|(_, tag)| 8 + 8 + tag.len()).sum::<usize>()
+            + 4
+            + aliases
+                .iter()
+                .map(|(_, alias)| 8 + 8 + alias.len())
+                .sum::<usize>();
+        let mut writer = codec::CodecWriter::new_zeroed(size)?;
+        writer.write_u32(WIDE_INDEX_FORMAT)?;
+        for names in [tags, aliases] {
+            writer.write_u32(names.len() as u32)?;
+            for (keystore_index, name) in names {
+                writer.write_u64(*KeystoreIndex64::from(*keystore_index))?;
+                writer.write_u64(name.len() as u64)?;
+                writer.write_bytes(name.as_bytes())?;
+            }
+        }
+        Ok(writer.into_vec())
+    }
+
+    /// Decode tags previously written with `encode`.
+    pub fn decode(data: &[u8]) -> LairResult<Self> {
+        let mut reader = codec::CodecReader::new(data);
+        let mut out = Self::default();
+        let (count, wide) = read_index_format(&mut reader)?;
+        for _ in 0..count {
+            let keystore_index = read_stored_index(&mut reader, wide)?;
+            let tag_len = reader.read_u64()?;
+            let tag = String::from_utf8(reader.read_bytes(tag_len)?.to_vec())
+                .map_err(LairError::other)?;
+            out.set(keystore_index, Some(tag))?;
+        }
+        // tags written before aliases existed end here
+        if reader.at_end() {
+            return Ok(out);
+        }
+        for _ in 0..reader.read_u32()? {
+            let keystore_index = read_stored_index(&mut reader, wide)?;
+            let alias_len = reader.read_u64()?;
+            let alias =
+                String::from_utf8(reader.read_bytes(alias_len)?.to_vec())
+                    .map_err(LairError::other)?;
+            out.add_alias(keystore_index, alias)?;
         }
+        Ok(out)
     }
 }
 
+/// Trashed entries, with the unix time (in seconds) they were trashed at.
+/// A trashed entry keeps its key material, but may not be used until it is
+/// restored, or is purged for good.
+#[derive(Debug, Default, Clone)]
+pub struct EntryTrash(std::collections::BTreeMap<KeystoreIndex, u64>);
+
+impl EntryTrash {
+    /// Trash an entry at unix time `now`.
+    /// An entry already in the trash keeps its original trash time.
+    pub fn trash(&mut self, keystore_index: KeystoreIndex, now: u64) {
+        self.0.entry(keystore_index).or_insert(now);
+    }
+
+    /// Take an entry back out of the trash.
+    pub fn restore(&mut self, keystore_index: KeystoreIndex) -> LairResult<()> {
+        match self.0.remove(&keystore_index) {
+            Some(_) => Ok(()),
+            None => {
+                Err(format!("entry {} is not trashed", keystore_index).into())
+            }
+        }
+    }
+
+    /// Is the entry in the trash?
+    pub fn is_trashed(&self, keystore_index: KeystoreIndex) -> bool {
+        self.0.contains_key(&keystore_index)
+    }
+
+    /// The entries trashed at or before unix time `cutoff`,
+    /// latest keystore index first.
+    pub fn trashed_before(&self, cutoff: u64) -> Vec<KeystoreIndex> {
+        self.0
+            .iter()
+            .rev()
+            .filter(|(_, trashed_at)| **trashed_at <= cutoff)
+            .map(|(idx, _)| *idx)
+            .collect()
+    }
+
+    /// Drop an entry that no longer exists from the trash.
+    pub fn remove(&mut self, keystore_index: KeystoreIndex) {
+        self.0.remove(&keystore_index);
+    }
+
+    /// The number of entries in the trash.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the trash empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Encode the trash for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer =
+            codec::CodecWriter::new_zeroed(4 + 4 + self.0.len() * (8 + 8))?;
+        writer.write_u32(WIDE_INDEX_FORMAT)?;
+        writer.write_u32(self.0.len() as u32)?;
+        for (keystore_index, trashed_at) in self.0.iter() {
+            writer.write_u64(*KeystoreIndex64::from(*keystore_index))?;
+            writer.write_u64(*trashed_at)?;
+        }
+        Ok(writer.into_vec())
+    }
+
+    /// Decode a trash previously written with `encode`.
+    pub fn decode(data: &[u8]) -> LairResult<Self> {
+        let mut reader = codec::CodecReader::new(data);
+        let mut out = Self::default();
+        let (count, wide) = read_index_format(&mut reader)?;
+        for _ in 0..count {
+            let keystore_index = read_stored_index(&mut reader, wide)?;
+            let trashed_at = reader.read_u64()?;
+            out.trash(keystore_index, trashed_at);
+        }
+        Ok(out)
+    }
+}
+
+/// Imported entries not activated yet, see `lair_activate_entry`.
+/// An inactive entry keeps its key material, but may not be used
+/// until it is activated.
+#[derive(Debug, Default, Clone)]
+pub struct EntryInactive(std::collections::BTreeSet<KeystoreIndex>);
+
+impl EntryInactive {
+    /// Hold back an entry until it is activated.
+    pub fn deactivate(&mut self, keystore_index: KeystoreIndex) {
+        self.0.insert(keystore_index);
+    }
+
+    /// Activate an entry, returning false if it was already active.
+    pub fn activate(&mut self, keystore_index: KeystoreIndex) -> bool {
+        self.0.remove(&keystore_index)
+    }
+
+    /// May the entry be used?
+    pub fn is_active(&self, keystore_index: KeystoreIndex) -> bool {
+        !self.0.contains(&keystore_index)
+    }
+
+    /// Drop an entry that no longer exists.
+    pub fn remove(&mut self, keystore_index: KeystoreIndex) {
+        self.0.remove(&keystore_index);
+    }
+
+    /// Encode the inactive entries for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer =
+            codec::CodecWriter::new_zeroed(4 + 4 + self.0.len() * 8)?;
+        writer.write_u32(WIDE_INDEX_FORMAT)?;
+        writer.write_u32(self.0.len() as u32)?;
+        for keystore_index in self.0.iter() {
+            writer.write_u64(*KeystoreIndex64::from(*keystore_index))?;
+        }
+        Ok(writer.into_vec())
+    }
+
+    /// Decode inactive entries previously written with `encode`.
+    pub fn decode(data: &[u8]) -> LairResult<Self> {
+        let mut reader = codec::CodecReader::new(data);
+        let mut out = Self::default();
+        let (count, wide) = read_index_format(&mut reader)?;
+        for _ in 0..count {
+            out.deactivate(read_stored_index(&mut reader, wide)?);
+        }
+        Ok(out)
+    }
+}
+
+/// Entries written before the key algorithm was stored have none.
 fn entry_decode_tls_cert(
     mut reader: codec::CodecReader<'_>,
+    with_alg: bool,
 ) -> LairResult<EntryTlsCert> {
     let sni_len = reader.read_u64()?;
     let sni = String::from_utf8_lossy(reader.read_bytes(sni_len)?).to_string();
@@ -79,21 +739,172 @@ fn entry_decode_tls_cert(
 
     let cert_digest = reader.read_bytes(32)?.to_vec();
 
+    let alg = match with_alg {
+        true => Some(TlsCertAlg::parse(reader.read_u32()?)?),
+        false => None,
+    };
+
     Ok(EntryTlsCert {
         sni: sni.into(),
         priv_key_der: priv_key_der.into(),
         cert_der: cert_der.into(),
         cert_digest: cert_digest.into(),
+        sign_key_index: None,
+        alg,
+    })
+}
+
+fn entry_decode_tls_cert_bound(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntryTlsCert> {
+    let sni_len = reader.read_u64()?;
+    let sni = String::from_utf8_lossy(reader.read_bytes(sni_len)?).to_string();
+
+    let sign_key_index = reader.read_u32()?;
+
+    let cert_der_len = reader.read_u64()?;
+    let cert_der = reader.read_bytes(cert_der_len)?.to_vec();
+
+    let cert_digest = reader.read_bytes(32)?.to_vec();
+
+    Ok(EntryTlsCert {
+        sni: sni.into(),
+        // the key is not stored, the store rebuilds it from the
+        // signing entry, see `EntryTlsCert::bind_sign_key`
+        priv_key_der: Vec::new().into(),
+        cert_der: cert_der.into(),
+        cert_digest: cert_digest.into(),
+        sign_key_index: Some(sign_key_index.into()),
+        alg: Some(TlsCertAlg::PkcsEd25519),
     })
 }
 
 fn entry_decode_sign_ed25519(
     mut reader: codec::CodecReader<'_>,
+    with_policy: bool,
+    rotated: bool,
 ) -> LairResult<EntrySignEd25519> {
     let priv_key = reader.read_bytes(32)?.to_vec().into();
     let pub_key = reader.read_bytes(32)?.to_vec().into();
 
-    Ok(EntrySignEd25519 { priv_key, pub_key })
+    let policy = if with_policy {
+        let exportable = reader.read_u32()? != 0;
+        let expires_at = match reader.read_u32()? {
+            0 => None,
+            _ => Some(reader.read_u64()?),
+        };
+        let requires_approval = reader.read_u32()? != 0;
+        let rotated_from = match rotated {
+            true => Some(reader.read_u32()?.into()),
+            false => None,
+        };
+        SignKeyPolicy {
+            exportable,
+            expires_at,
+            requires_approval,
+            rotated_from,
+        }
+    } else {
+        SignKeyPolicy::default()
+    };
+
+    Ok(EntrySignEd25519 {
+        priv_key,
+        pub_key,
+        policy,
+    })
+}
+
+fn entry_decode_sign_ed25519_hardware(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntrySignEd25519Hardware> {
+    let pub_key = reader.read_bytes(32)?.to_vec().into();
+
+    let key_id_len = reader.read_u64()?;
+    let key_id = reader.read_bytes(key_id_len)?.to_vec().into();
+
+    let label_len = reader.read_u64()?;
+    let label =
+        String::from_utf8_lossy(reader.read_bytes(label_len)?).to_string();
+
+    Ok(EntrySignEd25519Hardware {
+        pub_key,
+        key_id,
+        label,
+    })
+}
+
+/// Decode the body of an x25519 disk entry.
+fn entry_decode_x25519(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntryX25519> {
+    let priv_key = reader.read_bytes(32)?.to_vec().into();
+    let pub_key = reader.read_bytes(32)?.to_vec().into();
+
+    Ok(EntryX25519 { priv_key, pub_key })
+}
+
+/// Decode the body of a sign secp256k1 disk entry.
+fn entry_decode_sign_secp256k1(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntrySignSecp256k1> {
+    let priv_key = reader.read_bytes(32)?.to_vec().into();
+    let pub_key = reader.read_bytes(33)?.to_vec().into();
+
+    Ok(EntrySignSecp256k1 { priv_key, pub_key })
+}
+
+/// Decode the body of a sign ed448 disk entry.
+#[cfg(feature = "ed448")]
+fn entry_decode_sign_ed448(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntrySignEd448> {
+    let priv_key = reader.read_bytes(57)?.to_vec().into();
+    let pub_key = SignEd448PubKey::from_bytes(reader.read_bytes(57)?)?;
+
+    Ok(EntrySignEd448 { priv_key, pub_key })
+}
+
+fn entry_decode_sign_bls(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntrySignBls> {
+    let priv_key = reader.read_bytes(32)?.to_vec().into();
+    let pub_key = SignBlsPubKey::from_bytes(reader.read_bytes(48)?)?;
+
+    Ok(EntrySignBls { priv_key, pub_key })
+}
+
+fn entry_decode_sign_sr25519(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntrySignSr25519> {
+    let priv_key = reader.read_bytes(32)?.to_vec().into();
+    let pub_key = SignSr25519PubKey::from_bytes(reader.read_bytes(32)?)?;
+
+    Ok(EntrySignSr25519 { priv_key, pub_key })
+}
+
+/// Decode the body of a pw pepper disk entry.
+fn entry_decode_pw_pepper(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntryPwPepper> {
+    let pepper = reader.read_bytes(32)?.to_vec().into();
+
+    Ok(EntryPwPepper { pepper })
+}
+
+/// Decode the body of a totp secret disk entry.
+fn entry_decode_totp_secret(
+    mut reader: codec::CodecReader<'_>,
+) -> LairResult<EntryTotpSecret> {
+    let options = TotpOptions {
+        digits: reader.read_u32()?,
+        period: reader.read_u32()?,
+        alg: TotpAlg::parse(reader.read_u32()?)?,
+    };
+    let secret_len = reader.read_u64()?;
+    let secret = reader.read_bytes(secret_len)?.to_vec().into();
+
+    Ok(EntryTotpSecret { secret, options })
 }
 
 /// File format entry representing Tls Certificate data.
@@ -109,22 +920,67 @@ pub struct EntryTlsCert {
     /// Certificate bytes.
     pub cert_der: Cert,
 
-    /// 32 byte blake2b certificate digest.
+    /// 32 byte certificate digest, computed with `CERT_DIGEST_ALG`.
     pub cert_digest: CertDigest,
+
+    /// The sign ed25519 entry whose keypair this cert is bound to, if any.
+    /// A bound cert references its signing entry, rather than storing a
+    /// copy of the key - `priv_key_der` is rebuilt from the signing entry
+    /// when the cert is loaded, see `bind_sign_key`.
+    pub sign_key_index: Option<KeystoreIndex>,
+
+    /// The algorithm of the cert key. `None` for entries written
+    /// before it was stored, see `alg()`.
+    pub alg: Option<TlsCertAlg>,
 }
 
 impl EntryTlsCert {
+    /// The algorithm of the stored `cert_digest`. Stores hold no
+    /// algorithm tag, every stored digest is blake2b.
+    pub const CERT_DIGEST_ALG: DigestAlg = DigestAlg::Blake2b;
+
+    /// The digest of the cert computed with `alg`,
+    /// the stored `cert_digest` for `CERT_DIGEST_ALG`.
+    pub fn digest(&self, alg: DigestAlg) -> Arc<Vec<u8>> {
+        if alg == Self::CERT_DIGEST_ALG {
+            return self.cert_digest.0.clone();
+        }
+        alg.compute(&self.cert_der)
+    }
+
+    /// The algorithm of the cert key, read from the cert
+    /// if the entry predates storing it.
+    pub fn alg(&self) -> TlsCertAlg {
+        match self.alg {
+            Some(alg) => alg,
+            None => internal::tls::tls_cert_alg(&self.cert_der),
+        }
+    }
+
+    /// Rebuild the `priv_key_der` of a bound cert from its signing entry.
+    pub fn bind_sign_key(&mut self, sign: &EntrySignEd25519) -> LairResult<()> {
+        if self.sign_key_index.is_none() {
+            return Err("tls cert is not bound to a signing entry".into());
+        }
+        self.priv_key_der = internal::tls::ed25519_priv_key_der(sign)?;
+        Ok(())
+    }
+
     /// Encode this entry for writing to disk.
     /// @todo - once we're integrated with sodoken, this should encrypt too
     ///         otherwise we're writing our priv key to unprotected memory.
     pub fn encode(&self) -> LairResult<Vec<u8>> {
+        if let Some(sign_key_index) = self.sign_key_index {
+            return self.encode_bound(sign_key_index);
+        }
+
         let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
 
         // pre padding
         writer.write_pre_padding(16)?;
 
         // tls cert entry type
-        writer.write_entry_type(codec::EntryType::TlsCert)?;
+        writer.write_entry_type(codec::EntryType::TlsCertWithAlg)?;
 
         // write sni
         let sni_bytes = self.sni.as_bytes();
@@ -142,11 +998,97 @@ impl EntryTlsCert {
         // write digest (always 32 bytes)
         writer.write_bytes(&self.cert_digest[0..32])?;
 
+        // write the key algorithm
+        writer.write_u32(self.alg() as u32)?;
+
         Ok(writer.into_vec())
     }
-}
 
-/// File format entry representing Sign Ed25519 Keypair data.
+    /// A bound cert writes the index of its signing entry
+    /// in place of the private key.
+    fn encode_bound(
+        &self,
+        sign_key_index: KeystoreIndex,
+    ) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(16)?;
+
+        // bound tls cert entry type
+        writer.write_entry_type(codec::EntryType::TlsCertBound)?;
+
+        // write sni
+        let sni_bytes = self.sni.as_bytes();
+        writer.write_u64(sni_bytes.len() as u64)?;
+        writer.write_bytes(sni_bytes)?;
+
+        // write signing entry reference
+        writer.write_u32(*sign_key_index)?;
+
+        // write cert
+        writer.write_u64(self.cert_der.len() as u64)?;
+        writer.write_bytes(&self.cert_der)?;
+
+        // write digest (always 32 bytes)
+        writer.write_bytes(&self.cert_digest[0..32])?;
+
+        Ok(writer.into_vec())
+    }
+}
+
+/// How a Sign Ed25519 Keypair may be used, stored with it,
+/// see `SignKeyOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignKeyPolicy {
+    /// May the private key leave the keystore?
+    pub exportable: bool,
+
+    /// Unix time, in seconds, from which the keypair refuses to sign.
+    pub expires_at: Option<u64>,
+
+    /// Must the requesting client approve each signature?
+    pub requires_approval: bool,
+
+    /// The keypair this one replaced, see `sign_ed25519_rotate`.
+    pub rotated_from: Option<KeystoreIndex>,
+}
+
+impl Default for SignKeyPolicy {
+    fn default() -> Self {
+        Self {
+            exportable: true,
+            expires_at: None,
+            requires_approval: false,
+            rotated_from: None,
+        }
+    }
+}
+
+impl From<&SignKeyOptions> for SignKeyPolicy {
+    fn from(options: &SignKeyOptions) -> Self {
+        Self {
+            exportable: options.exportable,
+            expires_at: options.expires_at,
+            requires_approval: options.requires_approval,
+            rotated_from: None,
+        }
+    }
+}
+
+impl SignKeyPolicy {
+    /// The `SignKeyOptions` of this policy, with the entry's `tag`.
+    pub fn to_options(&self, tag: Option<String>) -> SignKeyOptions {
+        SignKeyOptions {
+            tag,
+            exportable: self.exportable,
+            expires_at: self.expires_at,
+            requires_approval: self.requires_approval,
+        }
+    }
+}
+
+/// File format entry representing Sign Ed25519 Keypair data.
 #[derive(Debug, Clone)]
 pub struct EntrySignEd25519 {
     /// Private key bytes.
@@ -155,6 +1097,9 @@ pub struct EntrySignEd25519 {
 
     /// Public key bytes.
     pub pub_key: SignEd25519PubKey,
+
+    /// How the keypair may be used.
+    pub policy: SignKeyPolicy,
 }
 
 impl EntrySignEd25519 {
@@ -167,8 +1112,19 @@ impl EntrySignEd25519 {
         // pre padding
         writer.write_pre_padding(64)?;
 
+        // keypairs with the default policy keep the original entry
+        // type, so lair versions predating policies still read them,
+        // likewise for keypairs that were not rotated
+        let with_policy = self.policy != SignKeyPolicy::default();
+
         // sign ed25519 entry type
-        writer.write_entry_type(codec::EntryType::SignEd25519)?;
+        writer.write_entry_type(
+            match (with_policy, self.policy.rotated_from.is_some()) {
+                (_, true) => codec::EntryType::SignEd25519Rotated,
+                (true, false) => codec::EntryType::SignEd25519WithPolicy,
+                (false, false) => codec::EntryType::SignEd25519,
+            },
+        )?;
 
         // write priv_key (always 32 bytes)
         writer.write_bytes(&self.priv_key[0..32])?;
@@ -176,9 +1132,38 @@ impl EntrySignEd25519 {
         // write pub_key (always 32 bytes)
         writer.write_bytes(&self.pub_key[0..32])?;
 
+        if with_policy {
+            writer.write_u32(self.policy.exportable as u32)?;
+            match self.policy.expires_at {
+                Some(expires_at) => {
+                    writer.write_u32(1)?;
+                    writer.write_u64(expires_at)?;
+                }
+                None => writer.write_u32(0)?,
+            }
+            writer.write_u32(self.policy.requires_approval as u32)?;
+            if let Some(rotated_from) = self.policy.rotated_from {
+                writer.write_u32(*rotated_from)?;
+            }
+        }
+
         Ok(writer.into_vec())
     }
 
+    /// Fail with `LairError::KeyExpired` if the keypair expired by `now`,
+    /// in unix time seconds.
+    pub fn check_not_expired(&self, now: u64) -> LairResult<()> {
+        match self.policy.expires_at {
+            Some(expires_at) if now >= expires_at => {
+                Err(LairError::KeyExpired(format!(
+                    "ed25519 keypair {:?} expired at {}",
+                    self.pub_key, expires_at,
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Create a signature for given message with this entry's priv_key.
     pub fn sign(
         &self,
@@ -190,6 +1175,372 @@ impl EntrySignEd25519 {
     }
 }
 
+/// File format entry representing a Sign Ed25519 Keypair held in a
+/// hardware token, see `hardware`. Only public material is stored.
+#[derive(Debug, Clone)]
+pub struct EntrySignEd25519Hardware {
+    /// Public key bytes.
+    pub pub_key: SignEd25519PubKey,
+
+    /// The CKA_ID of the keypair objects on the token.
+    pub key_id: Arc<Vec<u8>>,
+
+    /// The CKA_LABEL of the keypair objects on the token.
+    pub label: String,
+}
+
+impl EntrySignEd25519Hardware {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign ed25519 hardware entry type
+        writer.write_entry_type(codec::EntryType::SignEd25519Hardware)?;
+
+        // write pub_key (always 32 bytes)
+        writer.write_bytes(&self.pub_key[0..32])?;
+
+        // write token object id
+        writer.write_u64(self.key_id.len() as u64)?;
+        writer.write_bytes(&self.key_id)?;
+
+        // write token object label
+        let label_bytes = self.label.as_bytes();
+        writer.write_u64(label_bytes.len() as u64)?;
+        writer.write_bytes(label_bytes)?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Create a signature for given message with `hardware_token`.
+    pub fn sign(
+        &self,
+        hardware_token: hardware::HardwareTokenHandle,
+        message: Arc<Vec<u8>>,
+    ) -> impl std::future::Future<Output = LairResult<SignEd25519Signature>> + 'static
+    {
+        let key_id = self.key_id.clone();
+        async move {
+            // tokens are slow, and their calls block
+            let signature = tokio::task::spawn_blocking(move || {
+                hardware_token.sign_ed25519(&key_id, &message)
+            })
+            .await
+            .map_err(LairError::other)??;
+            Ok(signature.to_vec().into())
+        }
+    }
+}
+
+/// File format entry representing X25519 Keypair data.
+#[derive(Debug, Clone)]
+pub struct EntryX25519 {
+    /// Private key bytes.
+    pub priv_key: X25519PrivKey,
+
+    /// Public key bytes.
+    pub pub_key: X25519PubKey,
+}
+
+impl EntryX25519 {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // x25519 entry type
+        writer.write_entry_type(codec::EntryType::X25519)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_bytes(&self.priv_key[0..32])?;
+
+        // write pub_key (always 32 bytes)
+        writer.write_bytes(&self.pub_key[0..32])?;
+
+        Ok(writer.into_vec())
+    }
+}
+
+/// File format entry representing Sign Secp256k1 Keypair data.
+#[derive(Debug, Clone)]
+pub struct EntrySignSecp256k1 {
+    /// Private key bytes.
+    pub priv_key: SignSecp256k1PrivKey,
+
+    /// Compressed public key bytes.
+    pub pub_key: SignSecp256k1PubKey,
+}
+
+impl EntrySignSecp256k1 {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign secp256k1 entry type
+        writer.write_entry_type(codec::EntryType::SignSecp256k1)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_bytes(&self.priv_key[0..32])?;
+
+        // write compressed pub_key (always 33 bytes)
+        writer.write_bytes(&self.pub_key[0..33])?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Sign a 32 byte message hash with this entry's priv_key.
+    pub fn sign(
+        &self,
+        message_hash: [u8; 32],
+    ) -> impl std::future::Future<
+        Output = LairResult<(SignSecp256k1Signature, u8)>,
+    > + 'static {
+        let priv_key = self.priv_key.clone();
+        internal::sign_secp256k1::sign_secp256k1(priv_key, message_hash)
+    }
+}
+
+/// File format entry representing Sign Ed448 Keypair data.
+#[cfg(feature = "ed448")]
+#[derive(Debug, Clone)]
+pub struct EntrySignEd448 {
+    /// Private key bytes.
+    pub priv_key: SignEd448PrivKey,
+
+    /// Public key bytes.
+    pub pub_key: SignEd448PubKey,
+}
+
+#[cfg(feature = "ed448")]
+impl EntrySignEd448 {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign ed448 entry type
+        writer.write_entry_type(codec::EntryType::SignEd448)?;
+
+        // write priv_key (always 57 bytes)
+        writer.write_bytes(&self.priv_key[0..57])?;
+
+        // write pub_key (always 57 bytes)
+        writer.write_bytes(&self.pub_key[0..57])?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Sign some data with this entry's priv_key.
+    pub fn sign(
+        &self,
+        message: Arc<Vec<u8>>,
+    ) -> impl std::future::Future<Output = LairResult<SignEd448Signature>> + 'static
+    {
+        let priv_key = self.priv_key.clone();
+        internal::sign_ed448::sign_ed448(priv_key, message)
+    }
+}
+
+/// File format entry representing Sign Bls12-381 Keypair data.
+#[derive(Debug, Clone)]
+pub struct EntrySignBls {
+    /// Private key bytes, the 32 byte big-endian scalar.
+    pub priv_key: SignBlsPrivKey,
+
+    /// Public key bytes, the 48 byte compressed G1 point.
+    pub pub_key: SignBlsPubKey,
+}
+
+impl EntrySignBls {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign bls entry type
+        writer.write_entry_type(codec::EntryType::SignBls)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_bytes(&self.priv_key[0..32])?;
+
+        // write pub_key (always 48 bytes)
+        writer.write_bytes(&self.pub_key[0..48])?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Sign some data with this entry's priv_key,
+    /// under the domain separation tag `dst`.
+    pub fn sign(
+        &self,
+        message: Arc<Vec<u8>>,
+        dst: Vec<u8>,
+    ) -> impl std::future::Future<Output = LairResult<SignBlsSignature>> + 'static
+    {
+        let priv_key = self.priv_key.clone();
+        internal::sign_bls::sign_bls(priv_key, message, dst)
+    }
+}
+
+/// File format entry representing Sign Sr25519 Keypair data.
+#[derive(Debug, Clone)]
+pub struct EntrySignSr25519 {
+    /// Private key bytes, the 32 byte mini secret key.
+    pub priv_key: SignSr25519PrivKey,
+
+    /// Public key bytes.
+    pub pub_key: SignSr25519PubKey,
+}
+
+impl EntrySignSr25519 {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign sr25519 entry type
+        writer.write_entry_type(codec::EntryType::SignSr25519)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_bytes(&self.priv_key[0..32])?;
+
+        // write pub_key (always 32 bytes)
+        writer.write_bytes(&self.pub_key[0..32])?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Sign some data with this entry's priv_key,
+    /// in the signing context `context`.
+    pub fn sign(
+        &self,
+        message: Arc<Vec<u8>>,
+        context: Vec<u8>,
+    ) -> impl std::future::Future<Output = LairResult<SignSr25519Signature>> + 'static
+    {
+        let priv_key = self.priv_key.clone();
+        internal::sign_sr25519::sign_sr25519(priv_key, message, context)
+    }
+}
+
+/// File format entry representing a Pw Pepper secret.
+#[derive(Debug, Clone)]
+pub struct EntryPwPepper {
+    /// Pepper bytes.
+    pub pepper: PwPepper,
+}
+
+impl EntryPwPepper {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // pw pepper entry type
+        writer.write_entry_type(codec::EntryType::PwPepper)?;
+
+        // write pepper (always 32 bytes)
+        writer.write_bytes(&self.pepper[0..32])?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Hash a password with this entry's pepper.
+    pub fn pw_hash(
+        &self,
+        entropy: internal::entropy::EntropySourceHandle,
+        password: Arc<Vec<u8>>,
+        options: PwHashOptions,
+    ) -> impl std::future::Future<Output = LairResult<String>> + 'static {
+        let pepper = self.pepper.clone();
+        internal::pw_hash::pw_hash(entropy, pepper, password, options)
+    }
+
+    /// Verify a password hash with this entry's pepper.
+    pub fn pw_verify(
+        &self,
+        password: Arc<Vec<u8>>,
+        hash: String,
+    ) -> impl std::future::Future<Output = LairResult<bool>> + 'static {
+        let pepper = self.pepper.clone();
+        internal::pw_hash::pw_verify(pepper, password, hash)
+    }
+}
+
+/// File format entry representing a Totp Secret.
+#[derive(Debug, Clone)]
+pub struct EntryTotpSecret {
+    /// Secret bytes.
+    pub secret: TotpSecret,
+
+    /// The parameters of the codes generated from the secret.
+    pub options: TotpOptions,
+}
+
+impl EntryTotpSecret {
+    /// Encode this entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // totp secret entry type
+        writer.write_entry_type(codec::EntryType::TotpSecret)?;
+
+        // write options
+        writer.write_u32(self.options.digits)?;
+        writer.write_u32(self.options.period)?;
+        writer.write_u32(self.options.alg as u32)?;
+
+        // write secret
+        writer.write_u64(self.secret.len() as u64)?;
+        writer.write_bytes(&self.secret)?;
+
+        Ok(writer.into_vec())
+    }
+
+    /// Generate the code of this entry's secret for the unix time
+    /// `at` in seconds, or for now if None.
+    pub fn generate(&self, at: Option<u64>) -> LairResult<String> {
+        let at = match at {
+            Some(at) => at,
+            None => internal::totp::unix_time_now()?,
+        };
+        internal::totp::totp_code(&self.secret, self.options, at)
+    }
+
+    /// The provisioning uri of this entry's secret.
+    pub fn provisioning_uri(
+        &self,
+        label: &str,
+        issuer: &str,
+    ) -> LairResult<String> {
+        internal::totp::totp_provisioning_uri(
+            &self.secret,
+            self.options,
+            label,
+            issuer,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,14 +1550,179 @@ mod tests {
         let e = EntrySignEd25519 {
             priv_key: vec![0xdb; 32].into(),
             pub_key: vec![0x42; 32].into(),
+            policy: Default::default(),
         };
         let d = LairEntry::from(e.clone()).encode().unwrap();
         let e2 = match LairEntry::decode(&d).unwrap() {
             LairEntry::SignEd25519(e2) => e2,
-            e2 @ _ => panic!("unexpected type: {:?}", e2),
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+    }
+
+    #[test]
+    fn it_keeps_the_rotation_link_of_sign_ed25519_entries() {
+        let mut e = EntrySignEd25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 32].into(),
+            policy: Default::default(),
+        };
+        e.policy.rotated_from = Some(7.into());
+        for requires_approval in [false, true] {
+            e.policy.requires_approval = requires_approval;
+            let entry = LairEntry::from(e.clone());
+            let d = entry.encode().unwrap();
+            let e2 = match LairEntry::decode(&d).unwrap() {
+                LairEntry::SignEd25519(e2) => e2,
+                e2 => panic!("unexpected type: {:?}", e2),
+            };
+            assert_eq!(e.policy, e2.policy);
+            assert_eq!(Some(7.into()), entry.to_info(8.into()).rotated_from);
+        }
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_sign_ed25519_hardware_entry() {
+        let e = EntrySignEd25519Hardware {
+            pub_key: vec![0x42; 32].into(),
+            key_id: Arc::new(vec![0xdb; 16]),
+            label: "lair-test".to_string(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignEd25519Hardware(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.pub_key, e2.pub_key);
+        assert_eq!(e.key_id, e2.key_id);
+        assert_eq!(e.label, e2.label);
+        let info = LairEntry::from(e2).to_info(3.into());
+        assert_eq!(LairEntryType::SignEd25519, info.entry_type);
+        assert_eq!(e.pub_key.0, info.pub_id);
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_x25519_entry() {
+        let e = EntryX25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 32].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::X25519(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_sign_secp256k1_entry() {
+        let e = EntrySignSecp256k1 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 33].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignSecp256k1(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+    }
+
+    #[cfg(feature = "ed448")]
+    #[test]
+    fn it_can_encode_and_decode_sign_ed448_entry() {
+        let e = EntrySignEd448 {
+            priv_key: vec![0xdb; 57].into(),
+            pub_key: [0x42; 57].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignEd448(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+        let info = LairEntry::from(e2).to_info(1.into());
+        assert_eq!(LairEntryType::SignEd448, info.entry_type);
+        assert_eq!(57, info.pub_id.len());
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_sign_bls_entry() {
+        let e = EntrySignBls {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: [0x42; 48].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignBls(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+        let info = LairEntry::from(e2).to_info(1.into());
+        assert_eq!(LairEntryType::SignBls, info.entry_type);
+        assert_eq!(48, info.pub_id.len());
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_sign_sr25519_entry() {
+        let e = EntrySignSr25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: [0x42; 32].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignSr25519(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
         };
         assert_eq!(e.priv_key, e2.priv_key);
         assert_eq!(e.pub_key, e2.pub_key);
+        let info = LairEntry::from(e2).to_info(1.into());
+        assert_eq!(LairEntryType::SignSr25519, info.entry_type);
+        assert_eq!(32, info.pub_id.len());
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_pw_pepper_entry() {
+        let e = EntryPwPepper {
+            pepper: vec![0xdb; 32].into(),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::PwPepper(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.pepper, e2.pepper);
+        let info = LairEntry::from(e2).to_info(3.into());
+        assert_eq!(LairEntryType::PwPepper, info.entry_type);
+        assert!(info.pub_id.is_empty());
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_totp_secret_entry() {
+        let e = EntryTotpSecret {
+            secret: vec![0xdb; 64].into(),
+            options: TotpOptions {
+                digits: 8,
+                period: 60,
+                alg: TotpAlg::Sha512,
+            },
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::TotpSecret(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.secret, e2.secret);
+        assert_eq!(e.options, e2.options);
+        let info = LairEntry::from(e2).to_info(3.into());
+        assert_eq!(LairEntryType::TotpSecret, info.entry_type);
+        assert!(info.pub_id.is_empty());
     }
 
     #[test]
@@ -216,15 +1732,286 @@ mod tests {
             priv_key_der: vec![1, 2].into(),
             cert_der: vec![3, 4].into(),
             cert_digest: vec![0x42; 32].into(),
+            sign_key_index: None,
+            alg: Some(TlsCertAlg::PkcsEcdsaP384Sha384),
         };
         let d = LairEntry::from(e.clone()).encode().unwrap();
         let e2 = match LairEntry::decode(&d).unwrap() {
             LairEntry::TlsCert(e2) => e2,
-            e2 @ _ => panic!("unexpected type: {:?}", e2),
+            e2 => panic!("unexpected type: {:?}", e2),
         };
         assert_eq!(e.sni, e2.sni);
         assert_eq!(e.priv_key_der, e2.priv_key_der);
         assert_eq!(e.cert_der, e2.cert_der);
         assert_eq!(e.cert_digest, e2.cert_digest);
+        assert_eq!(None, e2.sign_key_index);
+        assert_eq!(Some(TlsCertAlg::PkcsEcdsaP384Sha384), e2.alg);
+
+        // the stored digest is returned as is, the others computed
+        assert_eq!(e.cert_digest.0, e2.digest(DigestAlg::Blake2b));
+        assert_eq!(
+            DigestAlg::Sha256.compute(&e.cert_der),
+            e2.digest(DigestAlg::Sha256),
+        );
+    }
+
+    #[test]
+    fn it_reads_the_alg_of_tls_cert_entries_predating_it() {
+        let e = EntryTlsCert {
+            sni: "test".to_string().into(),
+            priv_key_der: vec![1, 2].into(),
+            cert_der: vec![3, 4].into(),
+            cert_digest: vec![0x42; 32].into(),
+            sign_key_index: None,
+            alg: Some(TlsCertAlg::PkcsEd448),
+        };
+        let mut d = LairEntry::from(e).encode().unwrap();
+        // the entry type as written before the alg was stored
+        let at = d
+            .windows(8)
+            .position(|w| w == codec::TLS_CERT_WITH_ALG_ENTRY)
+            .unwrap();
+        d[at..at + 8].copy_from_slice(codec::TLS_CERT_ENTRY);
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::TlsCert(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(None, e2.alg);
+        // read from the cert, which is not one at all here
+        assert_eq!(TlsCertAlg::Unknown, e2.alg());
+    }
+
+    #[test]
+    fn it_references_the_signing_entry_of_bound_tls_certs() {
+        let sign = EntrySignEd25519 {
+            priv_key: vec![0x42; 32].into(),
+            pub_key: vec![0x43; 32].into(),
+            policy: Default::default(),
+        };
+        let mut e = EntryTlsCert {
+            sni: "test".to_string().into(),
+            priv_key_der: vec![0x99; 32].into(),
+            cert_der: vec![3, 4].into(),
+            cert_digest: vec![0x42; 32].into(),
+            sign_key_index: Some(7.into()),
+            alg: Some(TlsCertAlg::PkcsEd25519),
+        };
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        // the key is not copied into the entry
+        assert!(!d.windows(32).any(|w| w == [0x99; 32]));
+        let mut e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::TlsCert(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.sni, e2.sni);
+        assert!(e2.priv_key_der.is_empty());
+        assert_eq!(e.cert_der, e2.cert_der);
+        assert_eq!(e.cert_digest, e2.cert_digest);
+        assert_eq!(Some(7.into()), e2.sign_key_index);
+
+        e2.bind_sign_key(&sign).unwrap();
+        assert_eq!(
+            internal::tls::ed25519_priv_key_der(&sign).unwrap(),
+            e2.priv_key_der
+        );
+
+        e.sign_key_index = None;
+        assert!(e.bind_sign_key(&sign).is_err());
+    }
+
+    #[test]
+    fn it_moves_tags_between_entries() {
+        let mut tags = EntryTags::default();
+        tags.set(1.into(), Some("a".to_string())).unwrap();
+        tags.set(2.into(), Some("b".to_string())).unwrap();
+        assert_eq!(KeystoreIndex(1), tags.resolve("a").unwrap());
+
+        // moving a tag clears it from the previous holder
+        tags.set(2.into(), Some("a".to_string())).unwrap();
+        assert_eq!(KeystoreIndex(2), tags.resolve("a").unwrap());
+        assert_eq!(None, tags.get(1.into()));
+        assert!(matches!(tags.resolve("b"), Err(LairError::TagNotFound(_))));
+
+        tags.set(3.into(), Some("c".to_string())).unwrap();
+        let tags = EntryTags::decode(&tags.encode().unwrap()).unwrap();
+        assert_eq!(KeystoreIndex(2), tags.resolve("a").unwrap());
+        assert_eq!(Some("c"), tags.get(3.into()));
+
+        let mut tags = tags;
+        tags.set(3.into(), None).unwrap();
+        assert!(tags.resolve("c").is_err());
+        assert!(tags.set(4.into(), Some("".to_string())).is_err());
+        assert!(tags
+            .set(4.into(), Some("x".repeat(MAX_ENTRY_TAG_LEN + 1)))
+            .is_err());
+    }
+
+    #[test]
+    fn it_resolves_unique_aliases() {
+        let mut tags = EntryTags::default();
+        tags.set(1.into(), Some("old".to_string())).unwrap();
+        tags.add_alias(1.into(), "legacy".to_string()).unwrap();
+        tags.add_alias(1.into(), "legacy-2".to_string()).unwrap();
+        assert_eq!(KeystoreIndex(1), tags.resolve("legacy").unwrap());
+
+        // names are unique across tags and aliases
+        assert!(matches!(
+            tags.add_alias(2.into(), "old".to_string()),
+            Err(LairError::TagInUse(_)),
+        ));
+        assert!(matches!(
+            tags.add_alias(2.into(), "legacy".to_string()),
+            Err(LairError::TagInUse(_)),
+        ));
+        assert!(matches!(
+            tags.set(2.into(), Some("legacy".to_string())),
+            Err(LairError::TagInUse(_)),
+        ));
+
+        // aliases survive tag changes
+        tags.set(1.into(), Some("new".to_string())).unwrap();
+        assert_eq!(KeystoreIndex(1), tags.resolve("legacy").unwrap());
+        assert!(tags.resolve("old").is_err());
+
+        let mut tags = EntryTags::decode(&tags.encode().unwrap()).unwrap();
+        assert_eq!(Some("new"), tags.get(1.into()));
+        assert_eq!(vec!["legacy", "legacy-2"], tags.aliases(1.into()));
+
+        assert_eq!(KeystoreIndex(1), tags.remove_alias("legacy").unwrap());
+        assert!(matches!(
+            tags.remove_alias("legacy"),
+            Err(LairError::TagNotFound(_)),
+        ));
+        assert!(matches!(
+            tags.resolve("legacy"),
+            Err(LairError::TagNotFound(_)),
+        ));
+
+        for i in 0..MAX_ENTRY_ALIASES - 1 {
+            tags.add_alias(3.into(), format!("a{}", i)).unwrap();
+        }
+        tags.add_alias(3.into(), "last".to_string()).unwrap();
+        assert!(tags
+            .add_alias(3.into(), "one-too-many".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn it_trashes_restores_and_expires_entries() {
+        let mut trash = EntryTrash::default();
+        trash.trash(1.into(), 100);
+        trash.trash(2.into(), 200);
+        trash.trash(3.into(), 300);
+        // trashing again keeps the original time
+        trash.trash(1.into(), 400);
+        assert!(trash.is_trashed(1.into()));
+
+        let mut trash = EntryTrash::decode(&trash.encode().unwrap()).unwrap();
+        assert_eq!(
+            vec![KeystoreIndex(2), KeystoreIndex(1)],
+            trash.trashed_before(200)
+        );
+
+        trash.restore(2.into()).unwrap();
+        assert!(!trash.is_trashed(2.into()));
+        assert!(trash.restore(2.into()).is_err());
+        trash.remove(3.into());
+        assert_eq!(vec![KeystoreIndex(1)], trash.trashed_before(u64::MAX));
+    }
+
+    #[test]
+    fn it_deactivates_and_activates_entries() {
+        let mut inactive = EntryInactive::default();
+        inactive.deactivate(1.into());
+        inactive.deactivate(2.into());
+        inactive.deactivate(3.into());
+        assert!(!inactive.is_active(1.into()));
+        assert!(inactive.is_active(4.into()));
+
+        let mut inactive =
+            EntryInactive::decode(&inactive.encode().unwrap()).unwrap();
+        assert!(!inactive.is_active(2.into()));
+        assert!(inactive.activate(2.into()));
+        assert!(inactive.is_active(2.into()));
+        assert!(!inactive.activate(2.into()));
+        inactive.remove(3.into());
+        assert!(inactive.is_active(3.into()));
+        assert!(!inactive.is_active(1.into()));
+    }
+
+    #[test]
+    fn it_refuses_to_decode_erased_entries() {
+        let erased = encode_erased_entry().unwrap();
+        assert_eq!(ENTRY_SIZE, erased.len());
+        assert!(is_erased_entry(&erased).unwrap());
+        assert!(LairEntry::decode(&erased).is_err());
+
+        let sign = LairEntry::from(EntrySignEd25519 {
+            priv_key: vec![1; 32].into(),
+            pub_key: vec![2; 32].into(),
+            policy: Default::default(),
+        });
+        assert!(!is_erased_entry(&sign.encode().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn it_decodes_tags_written_without_aliases() {
+        let mut writer = codec::CodecWriter::new_zeroed(4 + 4 + 8 + 1).unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u32(5).unwrap();
+        writer.write_u64(1).unwrap();
+        writer.write_bytes(b"a").unwrap();
+        let tags = EntryTags::decode(&writer.into_vec()).unwrap();
+        assert_eq!(KeystoreIndex(5), tags.resolve("a").unwrap());
+        assert!(tags.aliases(5.into()).is_empty());
+    }
+
+    #[test]
+    fn it_decodes_tags_and_trash_written_with_narrow_indexes() {
+        let mut writer =
+            codec::CodecWriter::new_zeroed(4 + 4 + 8 + 1 + 4 + 4 + 8 + 1)
+                .unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u32(5).unwrap();
+        writer.write_u64(1).unwrap();
+        writer.write_bytes(b"a").unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u32(6).unwrap();
+        writer.write_u64(1).unwrap();
+        writer.write_bytes(b"b").unwrap();
+        let tags = EntryTags::decode(&writer.into_vec()).unwrap();
+        assert_eq!(KeystoreIndex(5), tags.resolve("a").unwrap());
+        assert_eq!(KeystoreIndex(6), tags.resolve("b").unwrap());
+
+        let mut writer = codec::CodecWriter::new_zeroed(4 + 4 + 8).unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u32(5).unwrap();
+        writer.write_u64(42).unwrap();
+        let trash = EntryTrash::decode(&writer.into_vec()).unwrap();
+        assert_eq!(vec![KeystoreIndex(5)], trash.trashed_before(42));
+
+        // rewritten wide, they decode the same
+        let encoded = tags.encode().unwrap();
+        assert_eq!(WIDE_INDEX_FORMAT.to_le_bytes(), encoded[..4]);
+        let tags = EntryTags::decode(&encoded).unwrap();
+        assert_eq!(KeystoreIndex(5), tags.resolve("a").unwrap());
+        assert_eq!(vec!["b".to_string()], tags.aliases(6.into()));
+        let encoded = trash.encode().unwrap();
+        assert_eq!(WIDE_INDEX_FORMAT.to_le_bytes(), encoded[..4]);
+        let trash = EntryTrash::decode(&encoded).unwrap();
+        assert_eq!(vec![KeystoreIndex(5)], trash.trashed_before(42));
+    }
+
+    #[test]
+    fn it_refuses_stored_indexes_wider_than_entries() {
+        let mut writer = codec::CodecWriter::new_zeroed(4 + 4 + 8 + 8).unwrap();
+        writer.write_u32(WIDE_INDEX_FORMAT).unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u64(u32::MAX as u64 + 1).unwrap();
+        writer.write_u64(42).unwrap();
+        assert!(matches!(
+            EntryTrash::decode(&writer.into_vec()),
+            Err(LairError::IndexOutOfRange(_)),
+        ));
     }
 }
@@ -2,6 +2,7 @@
 #[derive(Debug, thiserror::Error)]
 pub enum LairError {
     /// An error generated from the GhostActor system.
+    #[cfg(feature = "full")]
     #[error("LairError: {0}")]
     GhostError(#[from] ghost_actor::GhostError),
 
@@ -17,6 +18,151 @@ pub enum LairError {
     #[error("Public key not found")]
     PubKeyNotFound,
 
+    /// No entry holds the requested tag.
+    #[error("Tag not found: {0}")]
+    TagNotFound(String),
+
+    /// No entry in the selected store holds the requested index,
+    /// or matches the requested digest / sni / public key.
+    #[error("Entry not found: {0}")]
+    EntryNotFound(String),
+
+    /// An ed25519 public key has no usable x25519 equivalent,
+    /// e.g. it is not a valid point, or is of low order.
+    #[error("Invalid key conversion: {0}")]
+    InvalidKeyConversion(String),
+
+    /// Authenticated decryption failed: the data was tampered with,
+    /// or was not encrypted to this key.
+    #[error("Authentication failed")]
+    AuthenticationFailed,
+
+    /// The entry at `index` is not of the type the request works on,
+    /// e.g. getting a tls cert at the index of an ed25519 keypair.
+    #[error("Wrong entry type: KeystoreIndex {index} is {actual:?}, not {expected:?}")]
+    WrongEntryType {
+        /// The index of the entry.
+        index: crate::actor::KeystoreIndex,
+
+        /// The type of entry the request works on.
+        expected: crate::actor::LairEntryType,
+
+        /// The type of the entry at `index`.
+        actual: crate::actor::LairEntryType,
+    },
+
+    /// The entry is in the trash, it must be restored before it is used.
+    #[error("Entry trashed: {0}")]
+    EntryTrashed(String),
+
+    /// The entry was imported, and must be activated before it is used,
+    /// see `lair_activate_entry`.
+    #[error("Entry not activated: {0}")]
+    EntryNotActivated(String),
+
+    /// The tag or alias is already held by an entry.
+    #[error("Tag in use: {0}")]
+    TagInUse(String),
+
+    /// A new passphrase scored below the configured minimum strength.
+    #[error("Weak passphrase: {0}")]
+    WeakPassphrase(String),
+
+    /// The keystore is locked, and is configured to refuse
+    /// all entry access until it is unlocked, or to park requests
+    /// until it is unlocked, and the queue is full or the wait expired.
+    #[error("Keystore is locked")]
+    KeystoreLocked,
+
+    /// A keystore index was used against a store it does not belong to.
+    #[error("Wrong store: {0}")]
+    WrongStore(String),
+
+    /// The request deadline passed before the request completed.
+    #[error("Deadline exceeded")]
+    Timeout,
+
+    /// The client cancelled the request before it completed.
+    #[error("Request cancelled")]
+    Cancelled,
+
+    /// A connection url failed to parse or validate.
+    #[error("InvalidConnectionUrl({component}): {reason}")]
+    InvalidConnectionUrl {
+        /// The offending url component.
+        component: crate::LairConnectionUrlComponent,
+
+        /// Why the component is invalid.
+        reason: String,
+    },
+
+    /// The server is already serving as many connections as it is
+    /// configured to, and refused this one.
+    #[error("Too many connections")]
+    TooManyConnections,
+
+    /// The server closed the connection after it went idle for
+    /// its configured idle connection timeout.
+    #[error("Connection closed while idle")]
+    ConnectionIdle,
+
+    /// The server is configured to refuse this request.
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// The request is larger than lair sends on the wire. Messages larger
+    /// than `actor::MAX_SIGN_MESSAGE_LEN` must be signed pre-hashed, with
+    /// `sign_ed25519_ph_sign_by_index`, or `SigningKeyHandle::sign_stream`.
+    #[error("Message too large: {len} bytes, the maximum is {max}, sign large messages pre-hashed (sign_ed25519_ph_sign_by_index)")]
+    MessageTooLarge {
+        /// The byte length of the message.
+        len: usize,
+
+        /// The maximum byte length.
+        max: usize,
+    },
+
+    /// The store is bound to a device secret this machine does not hold,
+    /// see `device_secret`. The store was copied off the device it was
+    /// bound to, or the platform keystore entry was removed.
+    #[error("Device secret missing: {0}")]
+    DeviceSecretMissing(String),
+
+    /// The entry may not leave the keystore, e.g. a key held in a
+    /// hardware token, see `hardware`.
+    #[error("Export forbidden: {0}")]
+    ExportForbidden(String),
+
+    /// The keypair expired, see `SignKeyOptions::expires_at`.
+    #[error("Key expired: {0}")]
+    KeyExpired(String),
+
+    /// A `KeystoreIndex64` does not fit in a `KeystoreIndex`, e.g. it
+    /// was sent to a server predating `spec::KEYSTORE_INDEX64_VERSION`.
+    #[error("Index out of range: {0}")]
+    IndexOutOfRange(String),
+
+    /// The entropy source failed its health check, see
+    /// `ConfigBuilder::set_entropy_failure_policy`.
+    #[error("Entropy unavailable: {0}")]
+    EntropyUnavailable(String),
+
+    /// The server failed to prove it holds the identity the client
+    /// expects, see `ConfigBuilder::set_expected_server_identity`.
+    /// The client closed the connection.
+    #[error("Server identity mismatch: {0}")]
+    ServerIdentityMismatch(String),
+
+    /// The algorithm, or entry type, is not supported by this build
+    /// of lair, e.g. Ed448 without the `ed448` feature.
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlg(String),
+
+    /// The server failed while handling the request, e.g. its handler
+    /// panicked. The server, and the connection, carry on.
+    #[error("Internal error: {0}")]
+    Internal(String),
+
     /// Unspecified Internal error.
     #[error(transparent)]
     Other(Box<dyn std::error::Error + Send + Sync>),
@@ -29,6 +175,132 @@ impl LairError {
     ) -> Self {
         LairError::Other(e.into())
     }
+
+    /// Build an "UnsupportedAlg" type LairError, for `alg` needing
+    /// the cargo `feature` this build of lair was built without.
+    pub fn unsupported_alg(alg: &str, feature: &str) -> Self {
+        LairError::UnsupportedAlg(format!(
+            "{}, lair was built without the {} feature",
+            alg, feature
+        ))
+    }
+
+    /// Build an "Other" type LairError, describing what was being done
+    /// when `source` failed. Its `source()` is `source`.
+    pub fn context(
+        context: impl Into<String>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        LairError::other(ErrorContext {
+            context: context.into(),
+            path: None,
+            source: source.into(),
+        })
+    }
+
+    /// Build an "Other" type LairError, describing what was being done
+    /// to the file at `path` when `source` failed. Servers configured
+    /// with `hide_paths` leave the path out of their error responses.
+    pub fn path_context(
+        context: impl Into<String>,
+        path: impl Into<std::path::PathBuf>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        LairError::other(ErrorContext {
+            context: context.into(),
+            path: Some(path.into()),
+            source: source.into(),
+        })
+    }
+
+    /// Build an "Other" type LairError for an unclassified error
+    /// the server returned for request `request_id`.
+    pub fn remote(request_id: u64, message: String) -> Self {
+        Self::remote_with_source(request_id, message, None)
+    }
+
+    /// `remote`, caused by `source`, rebuilt from the cause chain
+    /// the server returned.
+    pub(crate) fn remote_with_source(
+        request_id: u64,
+        message: String,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        LairError::other(RemoteError {
+            request_id,
+            message,
+            source,
+        })
+    }
+
+    /// The server assigned id of the failed request, naming it in the
+    /// server logs (and the `lair_request` tracing span), if this is an
+    /// unclassified error returned by the server. Log it next to the error
+    /// to line the two up. Classified errors, such as `TagNotFound`, are
+    /// returned as their plain variants, and carry no id.
+    pub fn request_id(&self) -> Option<u64> {
+        match self {
+            LairError::Other(e) => e
+                .downcast_ref::<RemoteError>()
+                .map(|e| e.request_id)
+                .filter(|request_id| *request_id != 0),
+            _ => None,
+        }
+    }
+}
+
+/// What was being done when an error occurred, see `LairError::context`.
+#[derive(Debug)]
+pub(crate) struct ErrorContext {
+    context: String,
+    path: Option<std::path::PathBuf>,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl ErrorContext {
+    /// The context, naming its path unless `hide_paths`.
+    pub(crate) fn describe(&self, hide_paths: bool) -> String {
+        match &self.path {
+            Some(path) if !hide_paths => {
+                format!("{} {}", self.context, path.display())
+            }
+            _ => self.context.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe(false))
+    }
+}
+
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// An unclassified error the server returned for a request.
+#[derive(Debug)]
+struct RemoteError {
+    request_id: u64,
+    message: String,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RemoteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn std::error::Error + 'static))
+    }
 }
 
 impl From<String> for LairError {
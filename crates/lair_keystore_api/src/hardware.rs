@@ -0,0 +1,292 @@
+//! Signing keys held in a hardware token (an HSM, a YubiKey, ...).
+//!
+//! An ed25519 keypair created with `KeyBacking::Hardware` is generated
+//! on the token configured with `ConfigBuilder::set_hardware_token`, as a
+//! sensitive, non-extractable object. The lair store only records its
+//! public key, and the id and label of the token objects, see
+//! `entry::EntrySignEd25519Hardware`. Signing by such an entry is routed
+//! to the token, exporting it (`lair_wrap_entry`) fails with
+//! `LairError::ExportForbidden`.
+//!
+//! With the `pkcs11` feature, `Pkcs11Token` drives any PKCS#11 module,
+//! e.g. SoftHSM2, or the YubiKey `libykcs11`.
+
+use crate::*;
+use std::collections::HashMap;
+
+/// The byte length of the random CKA_ID given to hardware keys.
+pub const HARDWARE_KEY_ID_LEN: usize = 16;
+
+/// Abstraction over a hardware token able to generate, and sign with,
+/// non-extractable ed25519 keypairs. The calls block, lair makes them
+/// off the async executor.
+pub trait HardwareToken: 'static + Send + Sync {
+    /// Generate a new ed25519 keypair on the token, identified by `key_id`
+    /// and `label`, returning its 32 byte public key.
+    fn generate_ed25519(
+        &self,
+        key_id: &[u8],
+        label: &str,
+    ) -> LairResult<[u8; 32]>;
+
+    /// Sign `message` with the token ed25519 keypair `key_id`.
+    fn sign_ed25519(
+        &self,
+        key_id: &[u8],
+        message: &[u8],
+    ) -> LairResult<[u8; 64]>;
+}
+
+/// Shared handle to a HardwareToken.
+pub type HardwareTokenHandle = Arc<dyn HardwareToken>;
+
+/// Generate a new ed25519 keypair on `hardware_token`, under a random
+/// key id, and a label derived from it.
+pub async fn sign_ed25519_hardware_keypair_new(
+    hardware_token: HardwareTokenHandle,
+    entropy: internal::entropy::EntropySourceHandle,
+) -> LairResult<entry::EntrySignEd25519Hardware> {
+    let mut key_id = vec![0; HARDWARE_KEY_ID_LEN];
+    entropy.fill(&mut key_id)?;
+    let label = format!(
+        "lair-{}",
+        key_id
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>(),
+    );
+    // tokens are slow, and their calls block
+    tokio::task::spawn_blocking(move || {
+        let pub_key = hardware_token.generate_ed25519(&key_id, &label)?;
+        Ok(entry::EntrySignEd25519Hardware {
+            pub_key: pub_key.to_vec().into(),
+            key_id: Arc::new(key_id),
+            label,
+        })
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+/// An in-memory stand-in for a hardware token, for use in tests.
+/// Its keys are as extractable as any other memory of the process.
+#[derive(Default)]
+pub struct MockHardwareToken(std::sync::Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+impl MockHardwareToken {
+    /// Get a handle to a new, empty mock hardware token.
+    pub fn new_handle() -> HardwareTokenHandle {
+        Arc::new(Self::default())
+    }
+}
+
+impl HardwareToken for MockHardwareToken {
+    fn generate_ed25519(
+        &self,
+        key_id: &[u8],
+        _label: &str,
+    ) -> LairResult<[u8; 32]> {
+        let mut seed = vec![0; 32];
+        internal::entropy::OsEntropy::new_handle().fill(&mut seed)?;
+        let mut pub_key = [0; 32];
+        pub_key.copy_from_slice(&ed25519_backend::pub_key_from_seed(&seed)?);
+        let mut keys = self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned hardware token lock"))?;
+        if keys.contains_key(key_id) {
+            return Err("hardware key id in use".into());
+        }
+        keys.insert(key_id.to_vec(), seed);
+        Ok(pub_key)
+    }
+
+    fn sign_ed25519(
+        &self,
+        key_id: &[u8],
+        message: &[u8],
+    ) -> LairResult<[u8; 64]> {
+        let keys = self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned hardware token lock"))?;
+        let seed = keys.get(key_id).ok_or_else(|| {
+            LairError::EntryNotFound("no such hardware key".to_string())
+        })?;
+        let mut signature = [0; 64];
+        signature.copy_from_slice(&ed25519_backend::sign(seed, message)?);
+        Ok(signature)
+    }
+}
+
+/// The DER encoded CKA_EC_PARAMS of ed25519, the curve oid 1.3.101.112.
+#[cfg(feature = "pkcs11")]
+const ED25519_EC_PARAMS: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+
+/// A token of a PKCS#11 module, e.g. a SoftHSM2 token,
+/// or a YubiKey PIV applet through `libykcs11`.
+/// Calls are made one at a time, on a single logged in session.
+#[cfg(feature = "pkcs11")]
+pub struct Pkcs11Token {
+    session: std::sync::Mutex<cryptoki::session::Session>,
+}
+
+#[cfg(feature = "pkcs11")]
+impl Pkcs11Token {
+    /// Load the PKCS#11 module at `module_path`, and log in to the token
+    /// labeled `token_label` with the user `pin`.
+    pub fn new_handle(
+        module_path: &std::path::Path,
+        token_label: &str,
+        pin: String,
+    ) -> LairResult<HardwareTokenHandle> {
+        use cryptoki::context::{CInitializeArgs, Pkcs11};
+        use cryptoki::error::{Error, RvError};
+        use cryptoki::session::UserType;
+
+        let pkcs11 = Pkcs11::new(module_path).map_err(LairError::other)?;
+        pkcs11
+            .initialize(CInitializeArgs::OsThreads)
+            .map_err(LairError::other)?;
+        let mut slot = None;
+        for s in pkcs11.get_slots_with_token().map_err(LairError::other)? {
+            let info = pkcs11.get_token_info(s).map_err(LairError::other)?;
+            if info.label().trim_end() == token_label {
+                slot = Some(s);
+                break;
+            }
+        }
+        let slot = slot.ok_or_else(|| {
+            LairError::from(format!("no PKCS#11 token {}", token_label))
+        })?;
+        let session = pkcs11.open_rw_session(slot).map_err(LairError::other)?;
+        match session
+            .login(UserType::User, Some(&cryptoki::types::AuthPin::new(pin)))
+        {
+            Ok(()) | Err(Error::Pkcs11(RvError::UserAlreadyLoggedIn)) => (),
+            Err(Error::Pkcs11(RvError::PinIncorrect)) => {
+                return Err(LairError::AuthenticationFailed)
+            }
+            Err(e) => return Err(LairError::other(e)),
+        }
+        Ok(Arc::new(Self {
+            session: std::sync::Mutex::new(session),
+        }))
+    }
+
+    fn session(
+        &self,
+    ) -> LairResult<std::sync::MutexGuard<'_, cryptoki::session::Session>> {
+        self.session
+            .lock()
+            .map_err(|_| LairError::from("poisoned PKCS#11 session lock"))
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+impl HardwareToken for Pkcs11Token {
+    fn generate_ed25519(
+        &self,
+        key_id: &[u8],
+        label: &str,
+    ) -> LairResult<[u8; 32]> {
+        use cryptoki::mechanism::Mechanism;
+        use cryptoki::object::{Attribute, AttributeType};
+
+        let pub_template = [
+            Attribute::Token(true),
+            Attribute::Private(false),
+            Attribute::Verify(true),
+            Attribute::EcParams(ED25519_EC_PARAMS.to_vec()),
+            Attribute::Id(key_id.to_vec()),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let priv_template = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sign(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Id(key_id.to_vec()),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let session = self.session()?;
+        let (pub_handle, _) = session
+            .generate_key_pair(
+                &Mechanism::EccEdwardsKeyPairGen,
+                &pub_template,
+                &priv_template,
+            )
+            .map_err(LairError::other)?;
+        let ec_point = session
+            .get_attributes(pub_handle, &[AttributeType::EcPoint])
+            .map_err(LairError::other)?
+            .into_iter()
+            .find_map(|attr| match attr {
+                Attribute::EcPoint(ec_point) => Some(ec_point),
+                _ => None,
+            })
+            .ok_or_else(|| LairError::from("PKCS#11 key has no EC point"))?;
+        // modules differ on whether the point is a DER octet string
+        let raw = match ec_point.as_slice() {
+            [0x04, 0x20, raw @ ..] if raw.len() == 32 => raw,
+            raw if raw.len() == 32 => raw,
+            _ => return Err("invalid PKCS#11 ed25519 EC point".into()),
+        };
+        let mut pub_key = [0; 32];
+        pub_key.copy_from_slice(raw);
+        Ok(pub_key)
+    }
+
+    fn sign_ed25519(
+        &self,
+        key_id: &[u8],
+        message: &[u8],
+    ) -> LairResult<[u8; 64]> {
+        use cryptoki::mechanism::Mechanism;
+        use cryptoki::object::{Attribute, KeyType, ObjectClass};
+
+        let session = self.session()?;
+        let priv_handle = session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::PRIVATE_KEY),
+                Attribute::KeyType(KeyType::EC_EDWARDS),
+                Attribute::Id(key_id.to_vec()),
+            ])
+            .map_err(LairError::other)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                LairError::EntryNotFound("no such PKCS#11 key".to_string())
+            })?;
+        let signature = session
+            .sign(&Mechanism::Eddsa, priv_handle, message)
+            .map_err(LairError::other)?;
+        if signature.len() != 64 {
+            return Err("invalid PKCS#11 ed25519 signature".into());
+        }
+        let mut out = [0; 64];
+        out.copy_from_slice(&signature);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_signs_with_mock_hardware_keys() {
+        let token = MockHardwareToken::new_handle();
+        let pub_key = token.generate_ed25519(&[1; 16], "test").unwrap();
+        assert!(token.generate_ed25519(&[1; 16], "test").is_err());
+
+        let signature = token.sign_ed25519(&[1; 16], b"hello").unwrap();
+        let pub_key = actor::SignEd25519PubKey::from(pub_key.to_vec());
+        assert!(pub_key.verify_pure(b"hello", &signature.to_vec().into()));
+        assert!(matches!(
+            token.sign_ed25519(&[2; 16], b"hello"),
+            Err(LairError::EntryNotFound(_)),
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+//! Pure blake2b hashing, matching the digests lair computes.
+//!
+//! The length bounds follow libsodium's `crypto_generichash`,
+//! so keyed and unkeyed output is interchangeable with it.
+
+use crate::*;
+
+/// The minimum blake2b output byte length.
+pub const BLAKE2B_BYTES_MIN: usize = 16;
+
+/// The maximum blake2b output byte length.
+pub const BLAKE2B_BYTES_MAX: usize = 64;
+
+/// The minimum blake2b key byte length.
+pub const BLAKE2B_KEY_BYTES_MIN: usize = 16;
+
+/// The maximum blake2b key byte length.
+pub const BLAKE2B_KEY_BYTES_MAX: usize = 64;
+
+/// Compute the `output_len` byte blake2b hash of `data`,
+/// keyed with `key` if supplied.
+/// `CertDigest`s are the unkeyed 32 byte hash of the cert der.
+pub fn blake2b(
+    data: &[u8],
+    output_len: usize,
+    key: Option<&[u8]>,
+) -> LairResult<Vec<u8>> {
+    if !(BLAKE2B_BYTES_MIN..=BLAKE2B_BYTES_MAX).contains(&output_len) {
+        return Err(format!(
+            "blake2b output length must be between {} and {}",
+            BLAKE2B_BYTES_MIN, BLAKE2B_BYTES_MAX
+        )
+        .into());
+    }
+    let mut params = blake2b_simd::Params::new();
+    params.hash_length(output_len);
+    if let Some(key) = key {
+        if !(BLAKE2B_KEY_BYTES_MIN..=BLAKE2B_KEY_BYTES_MAX).contains(&key.len())
+        {
+            return Err(format!(
+                "blake2b key length must be between {} and {}",
+                BLAKE2B_KEY_BYTES_MIN, BLAKE2B_KEY_BYTES_MAX
+            )
+            .into());
+        }
+        params.key(key);
+    }
+    Ok(params.hash(data).as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_vectors::from_hex as hex;
+
+    #[test]
+    fn it_matches_blake2b_vectors() {
+        assert_eq!(
+            hex(test_vectors::BLAKE2B_ABC),
+            blake2b(b"abc", 64, None).unwrap(),
+        );
+
+        let key = (0..64).collect::<Vec<u8>>();
+        assert_eq!(
+            hex(test_vectors::BLAKE2B_KEYED_EMPTY),
+            blake2b(b"", 64, Some(&key)).unwrap(),
+        );
+
+        // libsodium crypto_generichash, short output / short key
+        assert_eq!(
+            hex("cf4ab791c62b8d2b2109c90275287816"),
+            blake2b(b"abc", 16, None).unwrap(),
+        );
+        let data = (0..255).collect::<Vec<u8>>();
+        assert_eq!(
+            hex("5dd6242b6a1533ae8905f49649480e26bd91787af98125bb67d2c154882732b9"),
+            blake2b(&data, 32, Some(&key[..16])).unwrap(),
+        );
+    }
+
+    #[test]
+    fn it_validates_lengths() {
+        assert!(blake2b(b"", BLAKE2B_BYTES_MIN - 1, None).is_err());
+        assert!(blake2b(b"", BLAKE2B_BYTES_MAX + 1, None).is_err());
+        assert!(blake2b(b"", 0, None).is_err());
+
+        let key = vec![0x42; BLAKE2B_KEY_BYTES_MAX + 1];
+        assert!(
+            blake2b(b"", 32, Some(&key[..BLAKE2B_KEY_BYTES_MIN - 1])).is_err()
+        );
+        assert!(blake2b(b"", 32, Some(&key)).is_err());
+        assert!(blake2b(b"", 32, Some(&[])).is_err());
+        assert_eq!(
+            32,
+            blake2b(b"", 32, Some(&key[..BLAKE2B_KEY_BYTES_MAX]))
+                .unwrap()
+                .len(),
+        );
+    }
+}
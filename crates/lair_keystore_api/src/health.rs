@@ -0,0 +1,213 @@
+//! Health check of a running lair server, for liveness and readiness
+//! probes. A check connects like any client and only makes calls that
+//! are answered while the keystore is locked, so it never needs, nor
+//! prompts for, the unlock passphrase.
+
+use crate::actor::*;
+use crate::*;
+
+/// How long a server may take to answer a health check call,
+/// if not otherwise specified.
+pub const DEFAULT_HEALTH_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_millis(1000);
+
+/// The health of a lair server, worst last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    /// Listening, and answering calls against an unlocked store.
+    Ready,
+
+    /// Answering calls, but unable to serve every request:
+    /// the store is locked, or holds quarantined entries.
+    Degraded,
+
+    /// Accepting connections, but not answering calls in time,
+    /// e.g. a wedged event loop.
+    NotLive,
+
+    /// Not accepting connections.
+    NotReady,
+}
+
+impl HealthStatus {
+    /// The `lair-keystore health` exit code reporting this status.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Ready => 0,
+            Self::Degraded => 1,
+            Self::NotLive => 2,
+            Self::NotReady => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ready => "ready",
+            Self::Degraded => "degraded",
+            Self::NotLive => "not live",
+            Self::NotReady => "not ready",
+        })
+    }
+}
+
+/// The outcome of a `check_health`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    /// The overall status.
+    pub status: HealthStatus,
+
+    /// Why the status is not `Ready`, empty if it is.
+    pub reasons: Vec<String>,
+}
+
+impl HealthReport {
+    fn new(status: HealthStatus, reason: impl std::fmt::Display) -> Self {
+        Self {
+            status,
+            reasons: vec![reason.to_string()],
+        }
+    }
+}
+
+/// Check the health of the lair server at the connection url of
+/// `config`, allowing it `timeout` to connect and to answer each call.
+pub async fn check_health(
+    config: Arc<Config>,
+    timeout: std::time::Duration,
+) -> HealthReport {
+    use futures::future::FutureExt;
+
+    let con = tokio::time::timeout(
+        timeout,
+        ipc::connect(ipc::ConnectOptions::from_config(&config)),
+    );
+    // passphrase requests are never answered,
+    // but hold the receiver so they are not errors
+    let (api, _evt_recv) = match con.await {
+        Ok(Ok(con)) => con,
+        Ok(Err(err)) => {
+            return HealthReport::new(HealthStatus::NotReady, err);
+        }
+        Err(_) => {
+            return HealthReport::new(
+                HealthStatus::NotLive,
+                "timed out connecting",
+            );
+        }
+    };
+
+    let info = tokio::time::timeout(timeout, api.lair_get_server_info());
+    let info = match info.await {
+        Ok(Ok(info)) => info,
+        Ok(Err(err)) => return HealthReport::new(HealthStatus::NotLive, err),
+        Err(_) => {
+            return HealthReport::new(
+                HealthStatus::NotLive,
+                "timed out getting server info",
+            );
+        }
+    };
+
+    let mut reasons = Vec::new();
+    if info.is_locked {
+        reasons.push("keystore is locked".to_string());
+    }
+
+    // deep locked stores cannot tell until they are unlocked
+    let quarantined = tokio::time::timeout(
+        timeout,
+        api.lair_list_quarantined().map(|res| match res {
+            Err(LairError::KeystoreLocked) => Ok(Vec::new()),
+            res => res,
+        }),
+    );
+    match quarantined.await {
+        Ok(Ok(quarantined)) if quarantined.is_empty() => (),
+        Ok(Ok(quarantined)) => {
+            reasons.push(format!("{} quarantined entries", quarantined.len()))
+        }
+        Ok(Err(err)) => return HealthReport::new(HealthStatus::NotLive, err),
+        Err(_) => {
+            return HealthReport::new(
+                HealthStatus::NotLive,
+                "timed out listing quarantined entries",
+            );
+        }
+    }
+
+    HealthReport {
+        status: if reasons.is_empty() {
+            HealthStatus::Ready
+        } else {
+            HealthStatus::Degraded
+        },
+        reasons,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream::StreamExt;
+
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reports_ready() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let mut incoming_recv =
+            ipc::spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        internal::util::err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let report = check_health(config, TIMEOUT).await;
+        assert_eq!(HealthStatus::Ready, report.status);
+        assert!(report.reasons.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reports_wedged_server_not_live() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        // accepts connections, but never reads a request off them
+        let (_srv_kill, mut srv_recv) =
+            internal::ipc::spawn_bind_ipc(config.clone()).await?;
+        internal::util::err_spawn("test-wedged-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(con) = srv_recv.next().await {
+                keep_em.push(con);
+            }
+            Ok(())
+        });
+
+        let report = check_health(config, TIMEOUT).await;
+        assert_eq!(HealthStatus::NotLive, report.status);
+        assert_eq!(2, report.status.exit_code());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reports_missing_server_not_ready() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let report = check_health(config, TIMEOUT).await;
+        assert_eq!(HealthStatus::NotReady, report.status);
+        assert_eq!(1, report.reasons.len());
+    }
+}
@@ -4,10 +4,32 @@
 /// utilities for lair build.rs files
 pub mod build;
 
+pub mod age;
+pub mod attest;
+pub mod cancel;
+pub mod clock;
 pub mod codec;
+pub mod deadline;
+pub mod debug_stats;
+pub mod entropy;
+pub mod ephemeral;
 pub mod ipc;
+pub mod pw_hash;
 pub(crate) mod rayon;
+pub mod rotate;
+pub mod secretbox;
+pub mod self_test;
+pub mod sign_bls;
 pub mod sign_ed25519;
+#[cfg(feature = "ed448")]
+pub mod sign_ed448;
+pub mod sign_secp256k1;
+pub mod sign_sr25519;
+pub mod ssh_key;
 pub mod tls;
+pub mod totp;
+pub mod unlock;
 pub mod util;
 pub mod wire;
+pub mod wrap;
+pub mod x25519;
@@ -0,0 +1,257 @@
+//! age (age-encryption.org/v1) X25519 Recipients
+//!
+//! The age recipient of an x25519 keypair is its public key, bech32
+//! encoded under the `age` human readable part (`age1...`). age tools
+//! encrypt to it as to any native X25519 recipient, wrapping the file
+//! key in an `X25519` header stanza:
+//! `-> X25519 <base64 ephemeral share>` over the chacha20-poly1305 sealed
+//! file key, under the HKDF-SHA-256 of the x25519 shared secret.
+//! `age_x25519_unwrap` opens those stanzas in the keystore, so the age
+//! identity (`AGE-SECRET-KEY-1...`) never exists outside of it.
+
+use crate::*;
+use bech32::{FromBase32, ToBase32};
+
+use actor::{AgeStanza, X25519PubKey, AGE_FILE_KEY_LEN};
+use internal::x25519::X25519PrivKey;
+
+/// The human readable part of age X25519 recipients.
+pub const AGE_RECIPIENT_HRP: &str = "age";
+
+/// The tag of age X25519 recipient stanzas.
+pub const AGE_X25519_STANZA_TAG: &str = "X25519";
+
+const AGE_X25519_KEY_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+
+/// The chacha20-poly1305 sealed file key of an X25519 stanza.
+const AGE_X25519_BODY_LEN: usize = AGE_FILE_KEY_LEN + 16;
+
+/// The unpadded base64 length of the 32 byte ephemeral share.
+const AGE_X25519_SHARE_B64_LEN: usize = 43;
+
+/// Encode an x25519 pub key as an age recipient, `age1...`.
+pub fn age_recipient(pub_key: &X25519PubKey) -> LairResult<String> {
+    if pub_key.len() != 32 {
+        return Err("invalid x25519 pub key".into());
+    }
+    bech32::encode(
+        AGE_RECIPIENT_HRP,
+        pub_key.to_base32(),
+        bech32::Variant::Bech32,
+    )
+    .map_err(LairError::other)
+}
+
+/// Decode an age recipient, `age1...`, to its x25519 pub key.
+pub fn age_recipient_decode(recipient: &str) -> LairResult<X25519PubKey> {
+    let pub_key = bech32_decode(AGE_RECIPIENT_HRP, recipient)?;
+    if pub_key.len() != 32 {
+        return Err("invalid age recipient, expected a 32 byte key".into());
+    }
+    Ok(pub_key.into())
+}
+
+/// Unwrap the file key of an age file, from the recipient `stanzas` of
+/// its header, with the x25519 keypair `priv_key`. Stanzas of other
+/// types are skipped, as are X25519 stanzas to other keys. Fails with
+/// `LairError::AuthenticationFailed` if none is to this keypair, and
+/// on any malformed X25519 stanza, as the age spec requires.
+pub async fn age_x25519_unwrap(
+    priv_key: X25519PrivKey,
+    stanzas: Vec<AgeStanza>,
+) -> LairResult<[u8; AGE_FILE_KEY_LEN]> {
+    rayon_exec(move || {
+        let secret = ed25519_compact::x25519::SecretKey::from_slice(&priv_key)
+            .map_err(|e| format!("{:?}", e))?;
+        let pub_key = secret
+            .recover_public_key()
+            .map_err(|e| format!("{:?}", e))?;
+        for stanza in stanzas {
+            if stanza.tag != AGE_X25519_STANZA_TAG {
+                continue;
+            }
+            if let Some(file_key) =
+                x25519_stanza_open(&secret, &pub_key, &stanza)?
+            {
+                return Ok(file_key);
+            }
+        }
+        Err(LairError::AuthenticationFailed)
+    })
+    .await
+}
+
+// -- local helpers -- //
+
+/// Open one X25519 stanza, None if it is to another key.
+fn x25519_stanza_open(
+    secret: &ed25519_compact::x25519::SecretKey,
+    pub_key: &ed25519_compact::x25519::PublicKey,
+    stanza: &AgeStanza,
+) -> LairResult<Option<[u8; AGE_FILE_KEY_LEN]>> {
+    // only canonical encodings, as the spec requires
+    let share = match &stanza.args[..] {
+        [share] if share.len() == AGE_X25519_SHARE_B64_LEN => {
+            base64::decode_config(share, base64::STANDARD_NO_PAD)
+                .map_err(|_| LairError::from("invalid age X25519 stanza"))?
+        }
+        _ => return Err("invalid age X25519 stanza".into()),
+    };
+    if stanza.body.len() != AGE_X25519_BODY_LEN
+        || base64::encode_config(&share, base64::STANDARD_NO_PAD)
+            != stanza.args[0]
+    {
+        return Err("invalid age X25519 stanza".into());
+    }
+
+    let shared = ed25519_compact::x25519::PublicKey::from_slice(&share)
+        .and_then(|share| share.dh(secret))
+        .map_err(|_| LairError::from("invalid age X25519 ephemeral share"))?;
+    if shared.iter().all(|b| *b == 0) {
+        return Err("invalid age X25519 ephemeral share".into());
+    }
+
+    let mut salt = share;
+    salt.extend_from_slice(&pub_key[..]);
+    let mut key = [0; 32];
+    ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, &salt)
+        .extract(&shared[..])
+        .expand(&[AGE_X25519_KEY_LABEL], ring::hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|e| format!("{:?}", e))?;
+
+    let key = ring::aead::LessSafeKey::new(
+        ring::aead::UnboundKey::new(&ring::aead::CHACHA20_POLY1305, &key)
+            .map_err(|e| format!("{:?}", e))?,
+    );
+    let mut body = stanza.body.to_vec();
+    // the key is unique to the ephemeral share, so the nonce is zero
+    match key.open_in_place(
+        ring::aead::Nonce::assume_unique_for_key([0; 12]),
+        ring::aead::Aad::empty(),
+        &mut body,
+    ) {
+        Ok(opened) => {
+            let mut file_key = [0; AGE_FILE_KEY_LEN];
+            file_key.copy_from_slice(opened);
+            Ok(Some(file_key))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Bech32 (BIP 173) decode `s`, which must have the human readable
+/// part `hrp`. Both all lowercase and all uppercase strings decode,
+/// age identities are uppercase. Age strings exceed the 90 character
+/// BIP 173 limit, which the bech32 crate does not enforce.
+fn bech32_decode(hrp: &str, s: &str) -> LairResult<Vec<u8>> {
+    let (s_hrp, data, variant) =
+        bech32::decode(s).map_err(|e| format!("invalid bech32, {}", e))?;
+    if s_hrp != hrp.to_ascii_lowercase() {
+        return Err(format!("invalid bech32, expected {}1...", hrp).into());
+    }
+    if variant != bech32::Variant::Bech32 {
+        return Err("invalid bech32, bech32m checksum".into());
+    }
+    Vec::<u8>::from_base32(&data)
+        .map_err(|e| format!("invalid bech32, {}", e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    fn stanza(stanza: age_core::format::Stanza) -> AgeStanza {
+        AgeStanza {
+            tag: stanza.tag,
+            args: stanza.args,
+            body: Arc::new(stanza.body),
+        }
+    }
+
+    #[test]
+    fn it_round_trips_age_recipients() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let pub_key = age_recipient_decode(&recipient).unwrap();
+        assert_eq!(recipient, age_recipient(&pub_key).unwrap());
+
+        // age identities are the uppercase bech32 of the private key
+        let priv_key = bech32_decode(
+            "AGE-SECRET-KEY-",
+            identity.to_string().expose_secret(),
+        )
+        .unwrap();
+        let secret =
+            ed25519_compact::x25519::SecretKey::from_slice(&priv_key).unwrap();
+        assert_eq!(&pub_key[..], &secret.recover_public_key().unwrap()[..]);
+
+        assert!(age_recipient_decode(&recipient.to_uppercase()).is_ok());
+        let mut bad = recipient.into_bytes();
+        let last = bad.len() - 1;
+        bad[last] = if bad[last] == b'q' { b'p' } else { b'q' };
+        assert!(
+            age_recipient_decode(std::str::from_utf8(&bad).unwrap()).is_err()
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_unwraps_age_x25519_stanzas() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let keypair =
+            internal::x25519::x25519_keypair_new_from_entropy(entropy.clone())
+                .await
+                .unwrap();
+        let other = internal::x25519::x25519_keypair_new_from_entropy(entropy)
+            .await
+            .unwrap();
+        let recipient: age::x25519::Recipient =
+            age_recipient(&keypair.pub_key).unwrap().parse().unwrap();
+        let other: age::x25519::Recipient =
+            age_recipient(&other.pub_key).unwrap().parse().unwrap();
+
+        let file_key = age_core::format::FileKey::new(Box::new([0x42; 16]));
+        let mut stanzas = Vec::new();
+        for r in [&other, &recipient] {
+            let (wrapped, _) =
+                age::Recipient::wrap_file_key(r, &file_key).unwrap();
+            stanzas.extend(wrapped.into_iter().map(stanza));
+        }
+        stanzas.insert(
+            0,
+            AgeStanza {
+                tag: "scrypt".to_string(),
+                args: vec!["salt".to_string(), "18".to_string()],
+                body: Arc::new(vec![0; 32]),
+            },
+        );
+
+        assert_eq!(
+            [0x42; 16],
+            age_x25519_unwrap(keypair.priv_key.clone(), stanzas.clone())
+                .await
+                .unwrap(),
+        );
+        assert!(matches!(
+            age_x25519_unwrap(keypair.priv_key.clone(), stanzas[..2].to_vec())
+                .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // a low order ephemeral share is an error, not a mismatch
+        let mut bad = stanzas[2].clone();
+        bad.args =
+            vec![base64::encode_config([0; 32], base64::STANDARD_NO_PAD)];
+        assert!(matches!(
+            age_x25519_unwrap(keypair.priv_key.clone(), vec![bad]).await,
+            Err(LairError::Other(_)),
+        ));
+        let mut bad = stanzas[2].clone();
+        bad.body = Arc::new(vec![0; 31]);
+        assert!(matches!(
+            age_x25519_unwrap(keypair.priv_key, vec![bad]).await,
+            Err(LairError::Other(_)),
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+//! Entry Attestation Utilities
+
+use crate::*;
+
+use actor::{Attestation, ServerIdentityProof, MAX_ATTEST_CHALLENGE_LEN};
+use internal::sign_ed25519;
+
+/// Sign an attestation, with the server identity keypair `server_id`,
+/// that the server holds `entry`, in response to `challenge`,
+/// at the unix time `timestamp` in seconds, see `Config::get_clock`.
+pub async fn attest_entry(
+    server_id: &entry::EntrySignEd25519,
+    entry: &entry::LairEntry,
+    challenge: Arc<Vec<u8>>,
+    timestamp: u64,
+) -> LairResult<Attestation> {
+    if challenge.is_empty() || challenge.len() > MAX_ATTEST_CHALLENGE_LEN {
+        return Err(format!(
+            "attestation challenge must be between 1 and {} bytes",
+            MAX_ATTEST_CHALLENGE_LEN
+        )
+        .into());
+    }
+    let info = entry.to_info(0.into());
+    if info.pub_id.is_empty() {
+        return Err(format!(
+            "{:?} entries have no public identifier to attest",
+            info.entry_type
+        )
+        .into());
+    }
+    let mut attestation = Attestation {
+        server_id_pub_key: server_id.pub_key.clone(),
+        entry_type: info.entry_type,
+        entry_pub_id: info.pub_id,
+        challenge,
+        timestamp,
+        signature: Vec::new().into(),
+    };
+    attestation.signature = sign_ed25519::sign_ed25519(
+        server_id.priv_key.clone(),
+        Arc::new(attestation.signed_bytes()),
+    )
+    .await?;
+    Ok(attestation)
+}
+
+/// Sign a proof, with the server identity keypair `server_id`, that the
+/// server runs `server_version` speaking this crate's wire protocol
+/// version, in response to `nonce`.
+pub async fn prove_server_identity(
+    server_id: &entry::EntrySignEd25519,
+    server_version: String,
+    nonce: Arc<Vec<u8>>,
+) -> LairResult<ServerIdentityProof> {
+    if nonce.is_empty() || nonce.len() > MAX_ATTEST_CHALLENGE_LEN {
+        return Err(format!(
+            "server identity nonce must be between 1 and {} bytes",
+            MAX_ATTEST_CHALLENGE_LEN
+        )
+        .into());
+    }
+    let mut proof = ServerIdentityProof {
+        server_id_pub_key: server_id.pub_key.clone(),
+        wire_protocol_version: internal::wire::spec::WIRE_PROTOCOL_VERSION,
+        server_version,
+        nonce,
+        signature: Vec::new().into(),
+    };
+    proof.signature = sign_ed25519::sign_ed25519(
+        server_id.priv_key.clone(),
+        Arc::new(proof.signed_bytes()),
+    )
+    .await?;
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use internal::pw_hash;
+    use internal::sign_ed25519::sign_ed25519_keypair_new_from_entropy;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_attest_and_verify() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let server_id = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let entry = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let challenge = Arc::new(b"test-challenge".to_vec());
+
+        let attestation = attest_entry(
+            &server_id,
+            &entry.clone().into(),
+            challenge.clone(),
+            42,
+        )
+        .await
+        .unwrap();
+        assert_eq!(actor::LairEntryType::SignEd25519, attestation.entry_type);
+        assert_eq!(42, attestation.timestamp);
+        assert_eq!(entry.pub_key.0, attestation.entry_pub_id);
+        assert!(attestation.verify(&server_id.pub_key, &challenge));
+
+        // mismatched challenge
+        assert!(!attestation.verify(&server_id.pub_key, b"other-challenge"));
+
+        // some other server identity
+        let other_id = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        assert!(!attestation.verify(&other_id.pub_key, &challenge));
+
+        // altered entry key / type / timestamp
+        let mut bad = attestation.clone();
+        bad.entry_pub_id = Arc::new(vec![0x42; 32]);
+        assert!(!bad.verify(&server_id.pub_key, &challenge));
+        let mut bad = attestation.clone();
+        bad.entry_type = actor::LairEntryType::X25519;
+        assert!(!bad.verify(&server_id.pub_key, &challenge));
+        let mut bad = attestation.clone();
+        bad.timestamp += 1;
+        assert!(!bad.verify(&server_id.pub_key, &challenge));
+
+        // embedding a different server id in the attestation
+        // and re-signing it does not fool the verifier
+        let mut bad = attestation;
+        bad.server_id_pub_key = other_id.pub_key.clone();
+        bad.signature = sign_ed25519::sign_ed25519(
+            other_id.priv_key.clone(),
+            Arc::new(bad.signed_bytes()),
+        )
+        .await
+        .unwrap();
+        assert!(!bad.verify(&server_id.pub_key, &challenge));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_bad_challenges_and_secret_only_entries() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let server_id = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let entry: entry::LairEntry =
+            sign_ed25519_keypair_new_from_entropy(entropy.clone())
+                .await
+                .unwrap()
+                .into();
+
+        assert!(attest_entry(&server_id, &entry, Arc::new(vec![]), 42)
+            .await
+            .is_err());
+        assert!(attest_entry(
+            &server_id,
+            &entry,
+            Arc::new(vec![0x42; MAX_ATTEST_CHALLENGE_LEN + 1]),
+            42
+        )
+        .await
+        .is_err());
+        assert!(attest_entry(
+            &server_id,
+            &entry,
+            Arc::new(vec![0x42; MAX_ATTEST_CHALLENGE_LEN]),
+            42
+        )
+        .await
+        .is_ok());
+
+        let pepper =
+            pw_hash::pw_pepper_new_from_entropy(entropy).await.unwrap();
+        assert!(attest_entry(
+            &server_id,
+            &pepper.into(),
+            Arc::new(b"test-challenge".to_vec()),
+            42
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_prove_and_verify_server_identity() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let server_id = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let nonce = Arc::new(vec![0x42; actor::SERVER_IDENTITY_NONCE_LEN]);
+
+        let proof =
+            prove_server_identity(&server_id, "1.2.3".into(), nonce.clone())
+                .await
+                .unwrap();
+        assert_eq!(
+            internal::wire::spec::WIRE_PROTOCOL_VERSION,
+            proof.wire_protocol_version
+        );
+        assert!(proof.verify(&server_id.pub_key, &nonce));
+
+        // a replayed proof answers another nonce
+        assert!(!proof.verify(&server_id.pub_key, &[0x43; 32]));
+
+        // altered versions
+        let mut bad = proof.clone();
+        bad.server_version = "1.2.4".into();
+        assert!(!bad.verify(&server_id.pub_key, &nonce));
+        let mut bad = proof.clone();
+        bad.wire_protocol_version += 1;
+        assert!(!bad.verify(&server_id.pub_key, &nonce));
+
+        // some other server identity, even signing its own proof
+        let other_id = sign_ed25519_keypair_new_from_entropy(entropy)
+            .await
+            .unwrap();
+        assert!(!proof.verify(&other_id.pub_key, &nonce));
+        let other =
+            prove_server_identity(&other_id, "1.2.3".into(), nonce.clone())
+                .await
+                .unwrap();
+        assert!(!other.verify(&server_id.pub_key, &nonce));
+
+        assert!(
+            prove_server_identity(&server_id, "".into(), Arc::new(vec![]))
+                .await
+                .is_err()
+        );
+    }
+}
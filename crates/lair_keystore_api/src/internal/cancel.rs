@@ -0,0 +1,145 @@
+//! Request cancellation.
+//!
+//! A client dropping a pending request sends a `ToLairCancelRequest`
+//! carrying its message id. The server runs each request with a
+//! `CancelToken`: cancelling it drops the request future, and expensive
+//! work checks `check_cancelled` before it starts, and periodically while
+//! it runs, so it is not finished for a client that is no longer waiting.
+
+use crate::*;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+tokio::task_local! {
+    static CANCEL: CancelToken;
+}
+
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+/// Cancels the request it was handed to with `with_cancel`.
+#[derive(Clone)]
+pub struct CancelToken(Arc<CancelInner>);
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    /// A new, not yet cancelled, token.
+    pub fn new() -> Self {
+        Self(Arc::new(CancelInner {
+            cancelled: AtomicBool::new(false),
+            notify: tokio::sync::Notify::new(),
+        }))
+    }
+
+    /// Cancel the request.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify();
+    }
+
+    /// Has the request been cancelled?
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Is this the same token as `other`?
+    pub fn same(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// `Err(LairError::Cancelled)` if the request has been cancelled.
+    /// Useful on blocking threads, where the task local token is not
+    /// available: capture `current_cancel_token` before moving the
+    /// work there.
+    pub fn check(&self) -> LairResult<()> {
+        if self.is_cancelled() {
+            return Err(LairError::Cancelled);
+        }
+        Ok(())
+    }
+
+    async fn cancelled(&self) {
+        // a notify racing this check leaves a permit,
+        // so `notified` still returns
+        while !self.is_cancelled() {
+            self.0.notify.notified().await;
+        }
+    }
+}
+
+/// The cancel token of the request being handled by this task, if any.
+pub fn current_cancel_token() -> Option<CancelToken> {
+    CANCEL.try_with(|token| token.clone()).ok()
+}
+
+/// `Err(LairError::Cancelled)` if the request being handled
+/// by this task has been cancelled.
+pub fn check_cancelled() -> LairResult<()> {
+    match current_cancel_token() {
+        Some(token) => token.check(),
+        None => Ok(()),
+    }
+}
+
+/// Run `fut` until `token` is cancelled, making it visible to
+/// `check_cancelled`. `fut` is dropped once it is cancelled.
+pub async fn with_cancel<F, T>(token: CancelToken, fut: F) -> LairResult<T>
+where
+    F: Future<Output = LairResult<T>>,
+{
+    token.check()?;
+    let fut = CANCEL.scope(token.clone(), fut);
+    futures::pin_mut!(fut);
+    let cancelled = token.cancelled();
+    futures::pin_mut!(cancelled);
+    match futures::future::select(fut, cancelled).await {
+        futures::future::Either::Left((res, _)) => res,
+        futures::future::Either::Right(_) => Err(LairError::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_scopes_and_cancels_requests() {
+        assert!(current_cancel_token().is_none());
+        assert!(check_cancelled().is_ok());
+
+        let token = CancelToken::new();
+        let inner_token = token.clone();
+        let res = with_cancel(token.clone(), async move {
+            assert!(current_cancel_token().unwrap().same(&inner_token));
+            check_cancelled()?;
+            Ok(42)
+        })
+        .await;
+        assert_eq!(42, res.unwrap());
+
+        let task = tokio::task::spawn(with_cancel(token.clone(), async move {
+            tokio::time::delay_for(Duration::from_secs(10)).await;
+            Ok(())
+        }));
+        tokio::time::delay_for(Duration::from_millis(20)).await;
+        token.cancel();
+        assert!(matches!(task.await.unwrap(), Err(LairError::Cancelled)));
+
+        // a cancelled token never starts the work
+        let res = with_cancel(token, async move {
+            panic!("started after cancel");
+            #[allow(unreachable_code)]
+            Ok(())
+        })
+        .await;
+        assert!(matches!(res, Err(LairError::Cancelled)));
+    }
+}
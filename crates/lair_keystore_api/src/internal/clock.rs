@@ -0,0 +1,323 @@
+//! Clock abstraction used by time dependent features, and detection
+//! of the wall clock jumping backwards.
+//!
+//! Expiry enforcement, TOTP codes, trash timestamps and signed
+//! timestamps all read the wall clock through a `ClockHandle`. The
+//! `ClockMonitor` compares it against the monotonic clock, and against
+//! the last wall clock time seen, which servers persist across restarts:
+//! a wall clock further behind either than `CLOCK_SKEW_THRESHOLD` is
+//! skewed, see `ClockSkewPolicy`.
+
+use crate::*;
+use std::time::Duration;
+
+/// A source of the current time.
+pub trait Clock: 'static + Send + Sync {
+    /// The wall clock, in seconds since the unix epoch.
+    fn unix_now_secs(&self) -> u64;
+
+    /// The wall clock, in milliseconds since the unix epoch.
+    /// Defaults to `unix_now_secs`, in whole seconds.
+    fn unix_now_millis(&self) -> u64 {
+        self.unix_now_secs().saturating_mul(1000)
+    }
+
+    /// The time since some fixed point, never going backwards.
+    fn monotonic(&self) -> Duration;
+}
+
+/// Shared handle to a Clock.
+pub type ClockHandle = Arc<dyn Clock>;
+
+/// How far the wall clock may fall behind the monotonic clock,
+/// or the last wall clock time seen, before it is skewed.
+pub const CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+/// How often servers check for skew, and persist the last wall clock
+/// time seen, besides checking before each expiry check.
+pub const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The production clock, backed by the os clocks.
+pub struct SystemClock(std::time::Instant);
+
+impl SystemClock {
+    /// Get a handle to the os clocks.
+    pub fn new_handle() -> ClockHandle {
+        Arc::new(Self(std::time::Instant::now()))
+    }
+}
+
+impl Clock for SystemClock {
+    fn unix_now_secs(&self) -> u64 {
+        internal::util::unix_now_secs()
+    }
+
+    fn unix_now_millis(&self) -> u64 {
+        internal::util::unix_now_millis()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A clock only moving when told to, for tests.
+pub struct ManualClock {
+    wall_secs: std::sync::atomic::AtomicU64,
+    monotonic_ms: std::sync::atomic::AtomicU64,
+}
+
+impl ManualClock {
+    /// A clock reading `wall_secs`, see `ManualClock::handle`.
+    pub fn new(wall_secs: u64) -> Arc<Self> {
+        Arc::new(Self {
+            wall_secs: std::sync::atomic::AtomicU64::new(wall_secs),
+            monotonic_ms: std::sync::atomic::AtomicU64::new(0),
+        })
+    }
+
+    /// A handle to this clock, to configure with.
+    pub fn handle(self: &Arc<Self>) -> ClockHandle {
+        self.clone()
+    }
+
+    /// Let `by` pass, on both clocks.
+    pub fn advance(&self, by: Duration) {
+        use std::sync::atomic::Ordering::SeqCst;
+        self.wall_secs.fetch_add(by.as_secs(), SeqCst);
+        self.monotonic_ms.fetch_add(by.as_millis() as u64, SeqCst);
+    }
+
+    /// Set the wall clock to `wall_secs`, as an administrator or a
+    /// dead rtc might, leaving the monotonic clock be.
+    pub fn set_wall(&self, wall_secs: u64) {
+        self.wall_secs
+            .store(wall_secs, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn unix_now_secs(&self) -> u64 {
+        self.wall_secs.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn monotonic(&self) -> Duration {
+        Duration::from_millis(
+            self.monotonic_ms.load(std::sync::atomic::Ordering::SeqCst),
+        )
+    }
+}
+
+/// A wall clock found behind where it should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// What the wall clock read, in unix time seconds.
+    pub wall_secs: u64,
+
+    /// The earliest it should have read, in unix time seconds.
+    pub expected_secs: u64,
+}
+
+struct MonitorState {
+    // the wall clock time seen at `monotonic`, or expected then if skewed
+    last_seen_secs: u64,
+    monotonic: Duration,
+    skew: Option<ClockSkew>,
+}
+
+/// Reads a `Clock`, noticing when its wall clock jumps backwards.
+/// Forward jumps, e.g. across a suspend, which the monotonic clock
+/// may not count, are not skew.
+pub struct ClockMonitor {
+    clock: ClockHandle,
+    state: std::sync::Mutex<MonitorState>,
+    skew_send: tokio::sync::broadcast::Sender<ClockSkew>,
+}
+
+impl ClockMonitor {
+    /// Monitor `clock`, from its current time.
+    pub fn new(clock: ClockHandle) -> Self {
+        let state = MonitorState {
+            last_seen_secs: clock.unix_now_secs(),
+            monotonic: clock.monotonic(),
+            skew: None,
+        };
+        Self {
+            clock,
+            state: std::sync::Mutex::new(state),
+            skew_send: tokio::sync::broadcast::channel(8).0,
+        }
+    }
+
+    /// The monitored clock.
+    pub fn clock(&self) -> &ClockHandle {
+        &self.clock
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MonitorState> {
+        // the state is plain data, only updated field by field
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The wall clock, in seconds since the unix epoch, unchecked.
+    pub fn unix_now_secs(&self) -> u64 {
+        self.clock.unix_now_secs()
+    }
+
+    /// The last wall clock time seen, or the time expected now while
+    /// skewed, for servers to persist.
+    pub fn last_seen_secs(&self) -> u64 {
+        let state = self.lock();
+        let elapsed = self.clock.monotonic().saturating_sub(state.monotonic);
+        state.last_seen_secs + elapsed.as_secs()
+    }
+
+    /// Take into account a wall clock time seen before this monitor
+    /// started, e.g. persisted by a previous run, then `check`.
+    pub fn observe_last_seen(&self, last_seen_secs: u64) -> Option<ClockSkew> {
+        {
+            let mut state = self.lock();
+            if last_seen_secs > state.last_seen_secs {
+                state.last_seen_secs = last_seen_secs;
+            }
+        }
+        self.check()
+    }
+
+    /// The current skew, if the wall clock is skewed, checking it now.
+    /// A newly detected skew is logged, and sent to subscribers.
+    pub fn check(&self) -> Option<ClockSkew> {
+        let wall_secs = self.clock.unix_now_secs();
+        let monotonic = self.clock.monotonic();
+        let mut state = self.lock();
+        let expected_secs = state.last_seen_secs
+            + monotonic.saturating_sub(state.monotonic).as_secs();
+        state.monotonic = monotonic;
+        if wall_secs + CLOCK_SKEW_THRESHOLD.as_secs() < expected_secs {
+            // keep expecting time to pass as the monotonic clock says
+            state.last_seen_secs = expected_secs;
+            let skew = ClockSkew {
+                wall_secs,
+                expected_secs,
+            };
+            if state.skew.is_none() {
+                ghost_actor::dependencies::tracing::warn!(
+                    ?skew,
+                    "clock_skew_detected: the wall clock jumped backwards"
+                );
+                let _ = self.skew_send.send(skew);
+            }
+            state.skew = Some(skew);
+        } else {
+            if let Some(skew) = state.skew.take() {
+                ghost_actor::dependencies::tracing::info!(
+                    ?skew,
+                    "the wall clock caught up"
+                );
+            }
+            state.last_seen_secs = wall_secs.max(expected_secs);
+        }
+        state.skew
+    }
+
+    /// Receive each newly detected skew.
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<ClockSkew> {
+        self.skew_send.subscribe()
+    }
+}
+
+impl std::fmt::Debug for ClockMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClockMonitor")
+            .field("skew", &self.lock().skew)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_600_000_000;
+
+    #[test]
+    fn it_detects_backwards_jumps() {
+        let clock = ManualClock::new(NOW);
+        let monitor = ClockMonitor::new(clock.handle());
+        let mut skews = monitor.subscribe();
+        assert_eq!(None, monitor.check());
+
+        // small corrections are not skew
+        clock.set_wall(NOW - 60);
+        assert_eq!(None, monitor.check());
+
+        // time passes on both clocks
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(None, monitor.check());
+        assert_eq!(NOW + 3600, monitor.last_seen_secs());
+
+        // the wall clock jumps back a day
+        clock.set_wall(NOW - 86400);
+        let skew = ClockSkew {
+            wall_secs: NOW - 86400,
+            expected_secs: NOW + 3600,
+        };
+        assert_eq!(Some(skew), monitor.check());
+        assert_eq!(Ok(skew), skews.try_recv());
+        // still skewed, only reported once
+        clock.advance(Duration::from_secs(10));
+        assert!(monitor.check().is_some());
+        assert!(skews.try_recv().is_err());
+        assert_eq!(NOW + 3610, monitor.last_seen_secs());
+
+        // until it is set right again
+        clock.set_wall(NOW + 3610);
+        assert_eq!(None, monitor.check());
+    }
+
+    #[test]
+    fn it_tolerates_forwards_jumps() {
+        let clock = ManualClock::new(NOW);
+        let monitor = ClockMonitor::new(clock.handle());
+        let mut skews = monitor.subscribe();
+
+        // e.g. a suspend the monotonic clock did not count
+        clock.set_wall(NOW + 86400);
+        assert_eq!(None, monitor.check());
+        assert_eq!(NOW + 86400, monitor.last_seen_secs());
+
+        // going back to the old time is then a backwards jump
+        clock.set_wall(NOW);
+        assert!(monitor.check().is_some());
+        assert!(skews.try_recv().is_ok());
+    }
+
+    #[test]
+    fn it_detects_jumps_across_restarts() {
+        // a dead rtc starts the clock at the epoch
+        let clock = ManualClock::new(0);
+        let monitor = ClockMonitor::new(clock.handle());
+        assert_eq!(
+            Some(ClockSkew {
+                wall_secs: 0,
+                expected_secs: NOW,
+            }),
+            monitor.observe_last_seen(NOW)
+        );
+
+        // an older record is no evidence of skew
+        let clock = ManualClock::new(NOW);
+        let monitor = ClockMonitor::new(clock.handle());
+        assert_eq!(None, monitor.observe_last_seen(NOW - 86400));
+        assert_eq!(NOW, monitor.last_seen_secs());
+    }
+
+    #[test]
+    fn system_clock_is_sane() {
+        let clock = SystemClock::new_handle();
+        let monitor = ClockMonitor::new(clock.clone());
+        assert!(clock.unix_now_secs() > NOW);
+        assert!(clock.monotonic() <= clock.monotonic());
+        assert_eq!(None, monitor.check());
+    }
+}
@@ -1,7 +1,7 @@
 //! Utilities for reading/writing lair encodings.
 
+use crate::internal::wire::spec;
 use crate::*;
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Seek, SeekFrom, Write};
 
 /// Tls Cert Entry Type Identifier.
@@ -10,6 +10,45 @@ pub const TLS_CERT_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x10];
 /// Sign Ed25519 Entry Type Identifier.
 pub const SIGN_ED25519_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x20];
 
+/// Sign Ed25519 Held In A Hardware Token Entry Type Identifier.
+pub const SIGN_ED25519_HARDWARE_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x21];
+
+/// Sign Ed25519 With A Non-default Policy Entry Type Identifier.
+pub const SIGN_ED25519_WITH_POLICY_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x22];
+
+/// Sign Ed25519 Rotated From Another Entry Type Identifier.
+pub const SIGN_ED25519_ROTATED_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x23];
+
+/// X25519 Entry Type Identifier.
+pub const X25519_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x30];
+
+/// Pw Pepper Entry Type Identifier.
+pub const PW_PEPPER_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x40];
+
+/// Sign Secp256k1 Entry Type Identifier.
+pub const SIGN_SECP256K1_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x50];
+
+/// Totp Secret Entry Type Identifier.
+pub const TOTP_SECRET_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x60];
+
+/// Sign Ed448 Entry Type Identifier.
+pub const SIGN_ED448_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x70];
+
+/// Sign Bls Entry Type Identifier.
+pub const SIGN_BLS_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x80];
+
+/// Sign Sr25519 Entry Type Identifier.
+pub const SIGN_SR25519_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x90];
+
+/// Tls Cert Bound To A Sign Ed25519 Entry Type Identifier.
+pub const TLS_CERT_BOUND_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x11];
+
+/// Tls Cert With Its Key Algorithm Entry Type Identifier.
+pub const TLS_CERT_WITH_ALG_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x12];
+
+/// Erased Entry Type Identifier.
+pub const ERASED_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0xee];
+
 /// Entry Type Enum
 #[derive(Debug, PartialEq, Eq)]
 pub enum EntryType {
@@ -18,6 +57,45 @@ pub enum EntryType {
 
     /// Sign Ed25519 Entry Type
     SignEd25519,
+
+    /// Sign Ed25519 Held In A Hardware Token Entry Type
+    SignEd25519Hardware,
+
+    /// Sign Ed25519 With A Non-default Policy Entry Type
+    SignEd25519WithPolicy,
+
+    /// Sign Ed25519 Rotated From Another Entry Type
+    SignEd25519Rotated,
+
+    /// X25519 Entry Type
+    X25519,
+
+    /// Pw Pepper Entry Type
+    PwPepper,
+
+    /// Sign Secp256k1 Entry Type
+    SignSecp256k1,
+
+    /// Totp Secret Entry Type
+    TotpSecret,
+
+    /// Sign Ed448 Entry Type
+    SignEd448,
+
+    /// Sign Bls Entry Type
+    SignBls,
+
+    /// Sign Sr25519 Entry Type
+    SignSr25519,
+
+    /// Tls Cert Bound To A Sign Ed25519 Entry Type
+    TlsCertBound,
+
+    /// Tls Cert With Its Key Algorithm Entry Type
+    TlsCertWithAlg,
+
+    /// Erased (Purged) Entry Type
+    Erased,
 }
 
 /// Read from bytes.
@@ -36,7 +114,9 @@ impl<'lt> CodecReader<'lt> {
 
         // get the TOTAL pre-padding len
         // then subtract out 8 bytes for header for remaining-len
-        let rem_len = read_u32(&mut self.0)? - 8;
+        let rem_len = read_u32(&mut self.0)?
+            .checked_sub(8)
+            .ok_or_else(|| LairError::from("invalid pre-padding length"))?;
 
         // seek past the remaining len
         seek_cur(&mut self.0, rem_len as i64)?;
@@ -49,6 +129,21 @@ impl<'lt> CodecReader<'lt> {
         match self.read_bytes(8)? {
             TLS_CERT_ENTRY => Ok(EntryType::TlsCert),
             SIGN_ED25519_ENTRY => Ok(EntryType::SignEd25519),
+            SIGN_ED25519_HARDWARE_ENTRY => Ok(EntryType::SignEd25519Hardware),
+            SIGN_ED25519_WITH_POLICY_ENTRY => {
+                Ok(EntryType::SignEd25519WithPolicy)
+            }
+            SIGN_ED25519_ROTATED_ENTRY => Ok(EntryType::SignEd25519Rotated),
+            X25519_ENTRY => Ok(EntryType::X25519),
+            PW_PEPPER_ENTRY => Ok(EntryType::PwPepper),
+            SIGN_SECP256K1_ENTRY => Ok(EntryType::SignSecp256k1),
+            TOTP_SECRET_ENTRY => Ok(EntryType::TotpSecret),
+            SIGN_ED448_ENTRY => Ok(EntryType::SignEd448),
+            SIGN_BLS_ENTRY => Ok(EntryType::SignBls),
+            SIGN_SR25519_ENTRY => Ok(EntryType::SignSr25519),
+            TLS_CERT_BOUND_ENTRY => Ok(EntryType::TlsCertBound),
+            TLS_CERT_WITH_ALG_ENTRY => Ok(EntryType::TlsCertWithAlg),
+            ERASED_ENTRY => Ok(EntryType::Erased),
             _ => Err("invalid entry type bytes".into()),
         }
     }
@@ -63,11 +158,28 @@ impl<'lt> CodecReader<'lt> {
         read_u64(&mut self.0)
     }
 
+    /// Have all bytes been read?
+    pub fn at_end(&self) -> bool {
+        self.0.position() as usize >= self.0.get_ref().len()
+    }
+
+    /// The count of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.0
+            .get_ref()
+            .len()
+            .saturating_sub(self.0.position() as usize)
+    }
+
     /// Read bytes element.
     pub fn read_bytes(&mut self, size: u64) -> LairResult<&[u8]> {
         let start = self.0.position() as usize;
-        let end = (self.0.position() + size) as usize;
-        let slice = &self.0.get_ref()[start..end];
+        let slice = self
+            .0
+            .position()
+            .checked_add(size)
+            .and_then(|end| self.0.get_ref().get(start..end as usize))
+            .ok_or_else(|| LairError::from("read past end of data"))?;
         seek_cur(&mut self.0, size as i64)?;
         Ok(slice)
     }
@@ -100,7 +212,7 @@ impl CodecWriter {
     /// Write pre-padding element.
     pub fn write_pre_padding(&mut self, size: u32) -> LairResult<()> {
         if self.0.position() + size as u64 > self.0.get_ref().len() as u64 {
-            panic!("pre padding would write beyond end of buffer");
+            return Err("pre padding would write beyond end of buffer".into());
         }
 
         // pre-padding starts with 4 random bytes
@@ -124,6 +236,27 @@ impl CodecWriter {
         match entry_type {
             EntryType::TlsCert => self.0.write_all(TLS_CERT_ENTRY),
             EntryType::SignEd25519 => self.0.write_all(SIGN_ED25519_ENTRY),
+            EntryType::SignEd25519Hardware => {
+                self.0.write_all(SIGN_ED25519_HARDWARE_ENTRY)
+            }
+            EntryType::SignEd25519WithPolicy => {
+                self.0.write_all(SIGN_ED25519_WITH_POLICY_ENTRY)
+            }
+            EntryType::SignEd25519Rotated => {
+                self.0.write_all(SIGN_ED25519_ROTATED_ENTRY)
+            }
+            EntryType::X25519 => self.0.write_all(X25519_ENTRY),
+            EntryType::PwPepper => self.0.write_all(PW_PEPPER_ENTRY),
+            EntryType::SignSecp256k1 => self.0.write_all(SIGN_SECP256K1_ENTRY),
+            EntryType::TotpSecret => self.0.write_all(TOTP_SECRET_ENTRY),
+            EntryType::SignEd448 => self.0.write_all(SIGN_ED448_ENTRY),
+            EntryType::SignBls => self.0.write_all(SIGN_BLS_ENTRY),
+            EntryType::SignSr25519 => self.0.write_all(SIGN_SR25519_ENTRY),
+            EntryType::TlsCertBound => self.0.write_all(TLS_CERT_BOUND_ENTRY),
+            EntryType::TlsCertWithAlg => {
+                self.0.write_all(TLS_CERT_WITH_ALG_ENTRY)
+            }
+            EntryType::Erased => self.0.write_all(ERASED_ENTRY),
         }
         .map_err(LairError::other)?;
         Ok(())
@@ -164,7 +297,7 @@ where
     W: std::io::Write,
 {
     writer
-        .write_u32::<LittleEndian>(val)
+        .write_all(&spec::encode_u32(val))
         .map_err(LairError::other)?;
     Ok(())
 }
@@ -173,7 +306,9 @@ fn read_u32<R>(mut reader: R) -> LairResult<u32>
 where
     R: std::io::Read,
 {
-    reader.read_u32::<LittleEndian>().map_err(LairError::other)
+    let mut buf = [0; spec::U32_LEN];
+    reader.read_exact(&mut buf).map_err(LairError::other)?;
+    spec::decode_u32(&buf)
 }
 
 fn write_u64<W>(mut writer: W, val: u64) -> LairResult<()>
@@ -181,7 +316,7 @@ where
     W: std::io::Write,
 {
     writer
-        .write_u64::<LittleEndian>(val)
+        .write_all(&spec::encode_u64(val))
         .map_err(LairError::other)?;
     Ok(())
 }
@@ -190,7 +325,9 @@ fn read_u64<R>(mut reader: R) -> LairResult<u64>
 where
     R: std::io::Read,
 {
-    reader.read_u64::<LittleEndian>().map_err(LairError::other)
+    let mut buf = [0; spec::U64_LEN];
+    reader.read_exact(&mut buf).map_err(LairError::other)?;
+    spec::decode_u64(&buf)
 }
 
 // -- tests -- //
@@ -227,4 +364,18 @@ mod tests {
         reader.read_pre_padding().unwrap();
         assert_eq!(&[44, 44, 44, 44], reader.read_bytes(4).unwrap());
     }
+
+    #[test]
+    fn it_codec_errors_on_damaged_data() {
+        let mut reader = CodecReader::new(&[0, 0, 0, 0, 4, 0, 0, 0]);
+        assert!(reader.read_pre_padding().is_err());
+
+        let mut reader = CodecReader::new(&[0xff; 8]);
+        assert!(reader.read_pre_padding().is_ok());
+        assert!(reader.read_entry_type().is_err());
+
+        let mut reader = CodecReader::new(&[42; 4]);
+        assert!(reader.read_bytes(8).is_err());
+        assert!(reader.read_bytes(u64::MAX).is_err());
+    }
 }
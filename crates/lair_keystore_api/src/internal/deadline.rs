@@ -0,0 +1,110 @@
+//! Request scoped deadlines.
+//!
+//! A client may send a request with the time it is still willing to wait
+//! (see `LairWire::ToLairRequestDeadline`). The server runs the request
+//! within that deadline: it is dropped once the deadline passes, and
+//! expensive work (passphrase hashing, certificate generation, key
+//! decryption) checks `check_deadline` before it starts, and periodically
+//! while it runs, so it is not done for a client that already gave up.
+
+use crate::*;
+use std::future::Future;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Remaining times longer than this are no deadline at all: the timer
+/// cannot wait (much) longer, and no client waits this long anyway.
+pub const MAX_REMAINING: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60 * 24 * 365);
+
+/// The deadline `remaining` from now, `None` past `MAX_REMAINING`.
+pub fn deadline_in(remaining: std::time::Duration) -> Option<Instant> {
+    if remaining > MAX_REMAINING {
+        return None;
+    }
+    Some(Instant::now() + remaining)
+}
+
+/// The deadline of the request being handled by this task, if any.
+pub fn current_deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// `Err(LairError::Timeout)` if the deadline of the request being handled
+/// by this task has passed.
+pub fn check_deadline() -> LairResult<()> {
+    check_deadline_at(current_deadline())
+}
+
+/// `Err(LairError::Timeout)` if `deadline` has passed. Useful on blocking
+/// threads, where the task local deadline is not available:
+/// capture `current_deadline` before moving the work there.
+pub fn check_deadline_at(deadline: Option<Instant>) -> LairResult<()> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(LairError::Timeout),
+        _ => Ok(()),
+    }
+}
+
+/// Run `fut` within `deadline`, making it visible to `check_deadline`.
+/// `fut` is not started if the deadline has already passed,
+/// and is dropped once it passes.
+pub async fn with_deadline<F, T>(deadline: Instant, fut: F) -> LairResult<T>
+where
+    F: Future<Output = LairResult<T>>,
+{
+    check_deadline_at(Some(deadline))?;
+    match tokio::time::timeout_at(deadline, DEADLINE.scope(deadline, fut)).await
+    {
+        Ok(res) => res,
+        Err(_) => Err(LairError::Timeout),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_scopes_and_enforces_deadlines() {
+        assert!(current_deadline().is_none());
+        assert!(check_deadline().is_ok());
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let res = with_deadline(deadline, async move {
+            assert_eq!(Some(deadline), current_deadline());
+            check_deadline()?;
+            Ok(42)
+        })
+        .await;
+        assert_eq!(42, res.unwrap());
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let res = with_deadline(deadline, async move {
+            tokio::time::delay_for(Duration::from_secs(10)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(res, Err(LairError::Timeout)));
+
+        // the timer takes the longest deadline
+        let deadline = deadline_in(MAX_REMAINING).unwrap();
+        let res = with_deadline(deadline, async move { Ok(42) }).await;
+        assert_eq!(42, res.unwrap());
+        assert!(deadline_in(Duration::from_millis(u64::MAX)).is_none());
+
+        // an expired deadline never starts the work
+        let res = with_deadline(Instant::now(), async move {
+            panic!("started past the deadline");
+            #[allow(unreachable_code)]
+            Ok(())
+        })
+        .await;
+        assert!(matches!(res, Err(LairError::Timeout)));
+        assert!(check_deadline_at(Some(Instant::now())).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Counts of live per-connection objects, for finding leaks.
+//!
+//! Everything a connection holds on to while it is open, on either
+//! side, is counted here from its creation until it is dropped, so once
+//! the connections of a process are closed, however they closed, their
+//! counts are back where they started. The counts are process wide:
+//! tests comparing them should have their process to themselves.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// The kinds of objects counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Open ipc connections, client or server side.
+    IpcConnection,
+
+    /// Requests sent on a connection, awaiting their response.
+    PendingResponse,
+
+    /// Requests received on a connection, still being handled.
+    InFlightRequest,
+
+    /// Connections registered with a server.
+    ServerConnection,
+
+    /// Tasks a server runs for one of its connections.
+    ConnectionTask,
+
+    /// Event queues of server connections.
+    EventQueue,
+
+    /// Per-connection apis created by a server's api factory.
+    ConnectionApi,
+
+    /// Per-connection sets of ephemeral keys.
+    EphemeralKeySet,
+}
+
+const COUNTERS: usize = 8;
+
+static COUNTS: [AtomicI64; COUNTERS] = [
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+    AtomicI64::new(0),
+];
+
+fn count(counter: Counter) -> &'static AtomicI64 {
+    &COUNTS[counter as usize]
+}
+
+/// Counts one `Counter` object for as long as it is held.
+#[derive(Debug)]
+pub struct Tracked(Counter);
+
+impl Tracked {
+    /// Count one more `counter` object, until this is dropped.
+    pub fn new(counter: Counter) -> Self {
+        count(counter).fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        count(self.0).fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The number of live objects of each `Counter` kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DebugStats {
+    /// see `Counter::IpcConnection`
+    pub ipc_connections: i64,
+
+    /// see `Counter::PendingResponse`
+    pub pending_responses: i64,
+
+    /// see `Counter::InFlightRequest`
+    pub in_flight_requests: i64,
+
+    /// see `Counter::ServerConnection`
+    pub server_connections: i64,
+
+    /// see `Counter::ConnectionTask`
+    pub connection_tasks: i64,
+
+    /// see `Counter::EventQueue`
+    pub event_queues: i64,
+
+    /// see `Counter::ConnectionApi`
+    pub connection_apis: i64,
+
+    /// see `Counter::EphemeralKeySet`
+    pub ephemeral_key_sets: i64,
+}
+
+/// The live object counts, as of now.
+pub fn debug_stats() -> DebugStats {
+    let get = |counter| count(counter).load(Ordering::Relaxed);
+    DebugStats {
+        ipc_connections: get(Counter::IpcConnection),
+        pending_responses: get(Counter::PendingResponse),
+        in_flight_requests: get(Counter::InFlightRequest),
+        server_connections: get(Counter::ServerConnection),
+        connection_tasks: get(Counter::ConnectionTask),
+        event_queues: get(Counter::EventQueue),
+        connection_apis: get(Counter::ConnectionApi),
+        ephemeral_key_sets: get(Counter::EphemeralKeySet),
+    }
+}
@@ -0,0 +1,261 @@
+//! Entropy source abstraction used by keypair and certificate generation.
+
+use crate::*;
+
+/// A source of random bytes for lair key material generation.
+pub trait EntropySource: 'static + Send + Sync {
+    /// Fill the entire buffer with random bytes.
+    fn fill(&self, buf: &mut [u8]) -> LairResult<()>;
+}
+
+/// Shared handle to an EntropySource.
+pub type EntropySourceHandle = Arc<dyn EntropySource>;
+
+/// Generate `len` random bytes for `lair_get_random_bytes`.
+pub fn random_bytes(
+    entropy: &EntropySourceHandle,
+    len: u32,
+) -> LairResult<Arc<Vec<u8>>> {
+    if len > actor::MAX_RANDOM_BYTES_LEN {
+        return Err(format!(
+            "random bytes len {} exceeds {} byte maximum",
+            len,
+            actor::MAX_RANDOM_BYTES_LEN,
+        )
+        .into());
+    }
+    let mut out = vec![0; len as usize];
+    entropy.fill(&mut out)?;
+    Ok(Arc::new(out))
+}
+
+/// Generate a random (version 4, RFC 4122 variant) uuid.
+pub fn new_uuid(entropy: &EntropySourceHandle) -> LairResult<[u8; 16]> {
+    let mut out = [0; 16];
+    entropy.fill(&mut out)?;
+    out[6] = (out[6] & 0x0f) | 0x40;
+    out[8] = (out[8] & 0x3f) | 0x80;
+    Ok(out)
+}
+
+/// The byte length of each sample `health_check` draws.
+const HEALTH_CHECK_SAMPLE_LEN: usize = 32;
+
+/// The fewest distinct byte values a sample passing the sanity check
+/// holds. A sample from a working source holds about 30, one holding
+/// fewer than 16 comes from a stuck or broken source.
+const HEALTH_CHECK_MIN_DISTINCT: usize = 16;
+
+/// Check `entropy` fills a sample, failing with
+/// `LairError::EntropyUnavailable` if it does not. With `sanity_check`,
+/// a second sample is drawn, and the check also fails if the samples
+/// repeat, or either is made of only a few byte values.
+pub fn health_check(
+    entropy: &EntropySourceHandle,
+    sanity_check: bool,
+) -> LairResult<()> {
+    let sample = || -> LairResult<[u8; HEALTH_CHECK_SAMPLE_LEN]> {
+        let mut out = [0; HEALTH_CHECK_SAMPLE_LEN];
+        entropy
+            .fill(&mut out)
+            .map_err(|e| LairError::EntropyUnavailable(e.to_string()))?;
+        Ok(out)
+    };
+    let a = sample()?;
+    if !sanity_check {
+        return Ok(());
+    }
+    let b = sample()?;
+    if a == b {
+        return Err(LairError::EntropyUnavailable(
+            "entropy source repeated its output".into(),
+        ));
+    }
+    for s in [a, b].iter() {
+        let mut seen = [false; 256];
+        s.iter().for_each(|b| seen[*b as usize] = true);
+        let distinct = seen.iter().filter(|s| **s).count();
+        if distinct < HEALTH_CHECK_MIN_DISTINCT {
+            return Err(LairError::EntropyUnavailable(format!(
+                "entropy source output looks stuck, {} distinct bytes in {}",
+                distinct, HEALTH_CHECK_SAMPLE_LEN,
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Records the outcome of the last `health_check` run through it,
+/// reported as `LairServerInfo::entropy_status`.
+#[derive(Debug, Default)]
+pub struct EntropyHealth(std::sync::atomic::AtomicU32);
+
+impl EntropyHealth {
+    /// Run `health_check`, recording its outcome.
+    pub fn check(
+        &self,
+        entropy: &EntropySourceHandle,
+        sanity_check: bool,
+    ) -> LairResult<()> {
+        let res = health_check(entropy, sanity_check);
+        let status = match res {
+            Ok(_) => actor::EntropyStatus::Healthy,
+            Err(_) => actor::EntropyStatus::Unavailable,
+        };
+        self.0
+            .store(status as u32, std::sync::atomic::Ordering::SeqCst);
+        res
+    }
+
+    /// The outcome of the last check, `Unchecked` before the first.
+    pub fn status(&self) -> actor::EntropyStatus {
+        actor::EntropyStatus::parse(
+            self.0.load(std::sync::atomic::Ordering::SeqCst),
+        )
+        .unwrap_or_default()
+    }
+}
+
+/// The production entropy source, backed by the OS cryptographic RNG.
+pub struct OsEntropy(ring::rand::SystemRandom);
+
+impl OsEntropy {
+    /// Get a handle to the os entropy source.
+    pub fn new_handle() -> EntropySourceHandle {
+        Arc::new(Self(ring::rand::SystemRandom::new()))
+    }
+}
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, buf: &mut [u8]) -> LairResult<()> {
+        ring::rand::SecureRandom::fill(&self.0, buf)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(())
+    }
+}
+
+/// DANGER - a deterministic entropy source for reproducible tests.
+/// Every key generated from this source is predictable by anyone
+/// who knows the seed. Never use this in production!
+pub struct DangerSeededEntropy(std::sync::Mutex<rand_chacha::ChaCha20Rng>);
+
+impl DangerSeededEntropy {
+    /// DANGER - construct a deterministic entropy source from a u64 seed.
+    /// The output stream for a given seed is identical across runs
+    /// and platforms.
+    pub fn danger_new_from_seed(seed: u64) -> EntropySourceHandle {
+        use rand_core::SeedableRng;
+        Arc::new(Self(std::sync::Mutex::new(
+            rand_chacha::ChaCha20Rng::seed_from_u64(seed),
+        )))
+    }
+}
+
+impl EntropySource for DangerSeededEntropy {
+    fn fill(&self, buf: &mut [u8]) -> LairResult<()> {
+        use rand_core::RngCore;
+        self.0
+            .lock()
+            .map_err(|_| LairError::from("poisoned entropy lock"))?
+            .fill_bytes(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_generates_random_bytes_and_uuids() {
+        let entropy = OsEntropy::new_handle();
+
+        assert!(random_bytes(&entropy, 0).unwrap().is_empty());
+        let max = actor::MAX_RANDOM_BYTES_LEN;
+        assert_eq!(max as usize, random_bytes(&entropy, max).unwrap().len());
+        assert!(random_bytes(&entropy, max + 1).is_err());
+
+        let a = random_bytes(&entropy, 32).unwrap();
+        let b = random_bytes(&entropy, 32).unwrap();
+        assert_eq!(32, a.len());
+        assert_ne!(a, b);
+        assert!(a.iter().any(|b| *b != 0));
+
+        let uuid = new_uuid(&entropy).unwrap();
+        assert_eq!(0x40, uuid[6] & 0xf0);
+        assert_eq!(0x80, uuid[8] & 0xc0);
+        assert_ne!(uuid, new_uuid(&entropy).unwrap());
+    }
+
+    /// Fails to fill, until `failures` runs out.
+    struct FailingEntropy(std::sync::atomic::AtomicU32);
+
+    impl EntropySource for FailingEntropy {
+        fn fill(&self, buf: &mut [u8]) -> LairResult<()> {
+            use std::sync::atomic::Ordering::SeqCst;
+            if self
+                .0
+                .fetch_update(SeqCst, SeqCst, |f| f.checked_sub(1))
+                .is_ok()
+            {
+                return Err("getrandom failed".into());
+            }
+            OsEntropy::new_handle().fill(buf)
+        }
+    }
+
+    /// Fills every buffer with the same byte.
+    struct StuckEntropy;
+
+    impl EntropySource for StuckEntropy {
+        fn fill(&self, buf: &mut [u8]) -> LairResult<()> {
+            buf.iter_mut().for_each(|b| *b = 0x42);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_checks_entropy_health() {
+        let health = EntropyHealth::default();
+        assert_eq!(actor::EntropyStatus::Unchecked, health.status());
+
+        health.check(&OsEntropy::new_handle(), true).unwrap();
+        assert_eq!(actor::EntropyStatus::Healthy, health.status());
+
+        let failing: EntropySourceHandle =
+            Arc::new(FailingEntropy(std::sync::atomic::AtomicU32::new(1)));
+        assert!(matches!(
+            health.check(&failing, false),
+            Err(LairError::EntropyUnavailable(_)),
+        ));
+        assert_eq!(actor::EntropyStatus::Unavailable, health.status());
+        // the source recovered
+        health.check(&failing, true).unwrap();
+        assert_eq!(actor::EntropyStatus::Healthy, health.status());
+
+        // only the sanity check notices stuck output
+        let stuck: EntropySourceHandle = Arc::new(StuckEntropy);
+        health_check(&stuck, false).unwrap();
+        assert!(matches!(
+            health_check(&stuck, true),
+            Err(LairError::EntropyUnavailable(_)),
+        ));
+        let seeded = DangerSeededEntropy::danger_new_from_seed(42);
+        health_check(&seeded, true).unwrap();
+    }
+
+    #[test]
+    fn seeded_entropy_is_reproducible() {
+        let a = DangerSeededEntropy::danger_new_from_seed(42);
+        let b = DangerSeededEntropy::danger_new_from_seed(42);
+        let c = DangerSeededEntropy::danger_new_from_seed(43);
+        let mut buf_a = [0; 32];
+        let mut buf_b = [0; 32];
+        let mut buf_c = [0; 32];
+        a.fill(&mut buf_a).unwrap();
+        b.fill(&mut buf_b).unwrap();
+        c.fill(&mut buf_c).unwrap();
+        assert_eq!(buf_a, buf_b);
+        assert_ne!(buf_a, buf_c);
+    }
+}
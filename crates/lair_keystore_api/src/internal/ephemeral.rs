@@ -0,0 +1,223 @@
+//! Connection scoped ephemeral keys.
+//!
+//! Ephemeral keys live only in server memory: they are never written to
+//! the store, and are not visible to `lair_get_entry_*` requests. Each
+//! connection holds its own `EphemeralKeys`, addressed by the
+//! `EphemeralHandle`s it hands out, and drops them when it closes.
+
+use crate::actor::*;
+use crate::entry::{EntrySignEd25519, EntryX25519};
+use crate::internal::entropy::EntropySourceHandle;
+use crate::internal::{sign_ed25519, x25519};
+use crate::*;
+use std::collections::HashMap;
+
+/// An ephemeral keypair.
+#[derive(Debug, Clone)]
+pub enum EphemeralKey {
+    /// An x25519 keypair, for crypto boxes.
+    X25519(EntryX25519),
+
+    /// An ed25519 signature keypair.
+    SignEd25519(EntrySignEd25519),
+}
+
+struct Inner {
+    max: usize,
+    next: u64,
+    keys: HashMap<EphemeralHandle, EphemeralKey>,
+    _tracked: internal::debug_stats::Tracked,
+}
+
+/// The ephemeral keys of one connection. Clones share the same keys,
+/// which are gone once the last clone is dropped.
+#[derive(Clone)]
+pub struct EphemeralKeys(Arc<std::sync::Mutex<Inner>>);
+
+impl EphemeralKeys {
+    /// No keys, holding at most `max` at once.
+    pub fn new(max: usize) -> Self {
+        Self(Arc::new(std::sync::Mutex::new(Inner {
+            max,
+            next: 1,
+            keys: HashMap::new(),
+            _tracked: internal::debug_stats::Tracked::new(
+                internal::debug_stats::Counter::EphemeralKeySet,
+            ),
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // keys are only inserted / removed, never held across a panic point
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The number of keys held.
+    pub fn len(&self) -> usize {
+        self.lock().keys.len()
+    }
+
+    /// True if no keys are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hold `key`, `Err(LairError::Forbidden)` if `max` keys are
+    /// already held.
+    pub fn insert(&self, key: EphemeralKey) -> LairResult<EphemeralHandle> {
+        let mut inner = self.lock();
+        if inner.keys.len() >= inner.max {
+            return Err(LairError::Forbidden(format!(
+                "connection already holds {} ephemeral keys",
+                inner.max
+            )));
+        }
+        let handle = EphemeralHandle(inner.next);
+        inner.next += 1;
+        inner.keys.insert(handle, key);
+        Ok(handle)
+    }
+
+    /// The key at `handle`.
+    pub fn get(&self, handle: EphemeralHandle) -> LairResult<EphemeralKey> {
+        self.lock()
+            .keys
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| not_found(handle))
+    }
+
+    /// Forget the key at `handle`.
+    pub fn remove(&self, handle: EphemeralHandle) -> LairResult<()> {
+        self.lock()
+            .keys
+            .remove(&handle)
+            .map(|_| ())
+            .ok_or_else(|| not_found(handle))
+    }
+
+    /// Generate and hold a new x25519 keypair.
+    pub async fn x25519_new(
+        &self,
+        entropy: EntropySourceHandle,
+    ) -> LairResult<(EphemeralHandle, X25519PubKey)> {
+        let entry = x25519::x25519_keypair_new_from_entropy(entropy).await?;
+        let pub_key = entry.pub_key.clone();
+        Ok((self.insert(EphemeralKey::X25519(entry))?, pub_key))
+    }
+
+    /// Generate and hold a new ed25519 signature keypair.
+    pub async fn sign_ed25519_new(
+        &self,
+        entropy: EntropySourceHandle,
+    ) -> LairResult<(EphemeralHandle, SignEd25519PubKey)> {
+        let entry =
+            sign_ed25519::sign_ed25519_keypair_new_from_entropy(entropy)
+                .await?;
+        let pub_key = entry.pub_key.clone();
+        Ok((self.insert(EphemeralKey::SignEd25519(entry))?, pub_key))
+    }
+
+    /// Sign `message` with the ed25519 keypair at `handle`.
+    pub async fn sign_ed25519_sign(
+        &self,
+        handle: EphemeralHandle,
+        message: Arc<Vec<u8>>,
+    ) -> LairResult<SignEd25519Signature> {
+        match self.get(handle)? {
+            EphemeralKey::SignEd25519(entry) => {
+                sign_ed25519::sign_ed25519(entry.priv_key, message).await
+            }
+            _ => Err(wrong_kind(handle, "ed25519")),
+        }
+    }
+
+    /// Box `data` from the x25519 keypair at `sender_handle`.
+    pub async fn crypto_box(
+        &self,
+        entropy: EntropySourceHandle,
+        sender_handle: EphemeralHandle,
+        recipient_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairResult<Arc<Vec<u8>>> {
+        match self.get(sender_handle)? {
+            EphemeralKey::X25519(entry) => {
+                x25519::crypto_box(
+                    entropy,
+                    entry.priv_key,
+                    recipient_pub_key,
+                    data,
+                )
+                .await
+            }
+            _ => Err(wrong_kind(sender_handle, "x25519")),
+        }
+    }
+
+    /// Open `data` boxed to the x25519 keypair at `recipient_handle`.
+    pub async fn crypto_box_open(
+        &self,
+        recipient_handle: EphemeralHandle,
+        sender_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairResult<Arc<Vec<u8>>> {
+        match self.get(recipient_handle)? {
+            EphemeralKey::X25519(entry) => {
+                x25519::crypto_box_open(entry.priv_key, sender_pub_key, data)
+                    .await
+            }
+            _ => Err(wrong_kind(recipient_handle, "x25519")),
+        }
+    }
+}
+
+fn not_found(handle: EphemeralHandle) -> LairError {
+    LairError::EntryNotFound(format!("ephemeral key {}", handle))
+}
+
+fn wrong_kind(handle: EphemeralHandle, kind: &str) -> LairError {
+    LairError::other(format!("ephemeral key {} is not an {} key", handle, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::entropy::OsEntropy;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_holds_ephemeral_keys() -> LairResult<()> {
+        let entropy = OsEntropy::new_handle();
+        let keys = EphemeralKeys::new(2);
+
+        let (sign, sign_pub) = keys.sign_ed25519_new(entropy.clone()).await?;
+        let sig = keys.sign_ed25519_sign(sign, Arc::new(vec![1; 8])).await?;
+        assert_eq!(64, sig.len());
+        assert_eq!(32, sign_pub.len());
+
+        let (a, a_pub) = keys.x25519_new(entropy.clone()).await?;
+        assert!(matches!(
+            keys.x25519_new(entropy.clone()).await,
+            Err(LairError::Forbidden(_)),
+        ));
+        assert!(keys.sign_ed25519_sign(a, Arc::new(vec![1])).await.is_err());
+
+        keys.remove(sign)?;
+        assert!(matches!(
+            keys.sign_ed25519_sign(sign, Arc::new(vec![1])).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        assert!(matches!(
+            keys.remove(sign),
+            Err(LairError::EntryNotFound(_))
+        ));
+
+        let (b, b_pub) = keys.x25519_new(entropy.clone()).await?;
+        assert_ne!(sign, b);
+        let data = Arc::new(b"hello".to_vec());
+        let boxed = keys.crypto_box(entropy, a, b_pub, data.clone()).await?;
+        assert_eq!(data, keys.crypto_box_open(b, a_pub, boxed).await?);
+        assert_eq!(2, keys.len());
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use crate::*;
 
 use futures::{future::FutureExt, sink::SinkExt, stream::StreamExt};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 #[cfg(not(windows))]
@@ -27,19 +28,20 @@ pub type IpcSender = ghost_actor::GhostSender<IpcWireApi>;
 /// IpcReceiver
 pub type IpcReceiver = futures::channel::mpsc::Receiver<IpcWireApi>;
 
-/// IncomingIpcReceiver
-pub type IncomingIpcSender = futures::channel::mpsc::Sender<(
+/// An accepted connection, with the url of the listener it came in on,
+/// carrying the token it must present, if any.
+pub type IncomingIpc = (
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
-)>;
+    LairConnectionUrl,
+);
+
+/// IncomingIpcSender
+pub type IncomingIpcSender = futures::channel::mpsc::Sender<IncomingIpc>;
 
 /// IncomingIpcReceiver
-pub type IncomingIpcReceiver = futures::channel::mpsc::Receiver<(
-    KillSwitch,
-    ghost_actor::GhostSender<IpcWireApi>,
-    IpcReceiver,
-)>;
+pub type IncomingIpcReceiver = futures::channel::mpsc::Receiver<IncomingIpc>;
 
 ghost_actor::ghost_chan! {
     /// Ipc wire api for both incoming api requsets and outgoing event requests.
@@ -49,19 +51,33 @@ ghost_actor::ghost_chan! {
     }
 }
 
-/// Spawn/bind a new ipc listener connection awaiting incomming clients.
+/// Spawn/bind a new ipc listener on every `Config::get_listener_urls`
+/// url, all awaiting incomming clients. Either every listener is bound,
+/// or none are. They are all unbound once the kill switch is killed.
 pub async fn spawn_bind_ipc(
     config: Arc<Config>,
 ) -> LairResult<(KillSwitch, IncomingIpcReceiver)> {
     let kill_switch = KillSwitch::new();
     let (in_send, in_recv) = futures::channel::mpsc::channel(10);
 
-    let srv = IpcServer::bind(config)?;
+    let srvs = config
+        .get_listener_urls()
+        .into_iter()
+        .map(|url| IpcServer::bind(config.clone(), url.clone()))
+        .collect::<LairResult<Vec<_>>>()?;
 
-    err_spawn(
-        "srv-bind",
-        srv_main_bind_task(kill_switch.clone(), srv, in_send),
-    );
+    for srv in srvs {
+        err_spawn(
+            "srv-bind",
+            srv_main_bind_task(
+                kill_switch.clone(),
+                srv,
+                in_send.clone(),
+                config.get_hide_paths(),
+                allowed_compression(&config),
+            ),
+        );
+    }
 
     Ok((kill_switch, in_recv))
 }
@@ -70,18 +86,30 @@ async fn srv_main_bind_task(
     kill_switch: KillSwitch,
     mut srv: IpcServer,
     mut in_send: IncomingIpcSender,
+    hide_paths: bool,
+    compression: u32,
 ) -> LairResult<()> {
+    let url = srv.url().clone();
     while let Ok((read_half, write_half)) = kill_switch.mix(srv.accept()).await
     {
         let (con_kill_switch, send, recv) = kill_switch
-            .mix(async { spawn_connection_pair(read_half, write_half).await })
+            .mix(async {
+                spawn_connection_pair(
+                    read_half,
+                    write_half,
+                    None,
+                    hide_paths,
+                    compression,
+                )
+                .await
+            })
             .await?;
 
         kill_switch
             .mix(async {
                 trace!("notify new connection");
                 in_send
-                    .send((con_kill_switch, send, recv))
+                    .send((con_kill_switch, send, recv, url.clone()))
                     .await
                     .map_err(LairError::other)
             })
@@ -98,14 +126,82 @@ pub async fn spawn_ipc_connection(
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
 )> {
+    let request_timeout = config.get_request_timeout();
+    let compression = allowed_compression(&config);
     let (read_half, write_half) = ipc_connect(config).await?;
 
-    spawn_connection_pair(read_half, write_half).await
+    // clients never send their own errors
+    spawn_connection_pair(
+        read_half,
+        write_half,
+        request_timeout,
+        false,
+        compression,
+    )
+    .await
 }
 
+/// An in-process connection, not bound to any listener: the server end,
+/// as an accepted connection, and the client end, as
+/// `spawn_ipc_connection` returns it.
+#[cfg(not(windows))]
+#[allow(clippy::type_complexity)]
+pub(crate) async fn spawn_in_proc_ipc_pair(
+    config: Arc<Config>,
+) -> LairResult<(
+    (
+        KillSwitch,
+        ghost_actor::GhostSender<IpcWireApi>,
+        IpcReceiver,
+    ),
+    (
+        KillSwitch,
+        ghost_actor::GhostSender<IpcWireApi>,
+        IpcReceiver,
+    ),
+)> {
+    let compression = allowed_compression(&config);
+    let request_timeout = config.get_request_timeout();
+    let ((srv_read, srv_write), (cli_read, cli_write)) =
+        ipc_pair(config.clone())?;
+    let srv = spawn_connection_pair(
+        srv_read,
+        srv_write,
+        None,
+        config.get_hide_paths(),
+        compression,
+    )
+    .await?;
+    let cli = spawn_connection_pair(
+        cli_read,
+        cli_write,
+        request_timeout,
+        false,
+        compression,
+    )
+    .await?;
+    Ok((srv, cli))
+}
+
+/// The compression algorithms connections may negotiate.
+fn allowed_compression(config: &Config) -> u32 {
+    if config.get_wire_compression() {
+        compress::SUPPORTED_ALGORITHMS
+    } else {
+        0
+    }
+}
+
+/// With a `request_timeout`, outgoing requests are sent with their
+/// remaining time, and fail locally with `LairError::Timeout` once it is up.
+/// With `hide_paths`, error responses leave out paths.
+/// The connection negotiates the `compression` algorithms, if any.
 async fn spawn_connection_pair(
     read_half: IpcRead,
     write_half: IpcWrite,
+    request_timeout: Option<std::time::Duration>,
+    hide_paths: bool,
+    compression: u32,
 ) -> LairResult<(
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
@@ -134,14 +230,31 @@ async fn spawn_connection_pair(
         }))
         .await;
 
-    let reader = spawn_low_level_read_half(kill_switch.clone(), read_half)?;
+    let compression = FrameCompression::new(compression);
+
+    let reader = spawn_low_level_read_half(
+        kill_switch.clone(),
+        read_half,
+        compression.clone(),
+    )?;
     builder.channel_factory().attach_receiver(reader).await?;
 
-    let writer = spawn_low_level_write_half(kill_switch.clone(), write_half)?;
+    let writer = spawn_low_level_write_half(
+        kill_switch.clone(),
+        write_half,
+        compression.clone(),
+    )?;
 
     tokio::task::spawn(builder.spawn(Internal {
         kill_switch: kill_switch.clone(),
-        pending: HashMap::new(),
+        pending: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        _tracked: internal::debug_stats::Tracked::new(
+            internal::debug_stats::Counter::IpcConnection,
+        ),
+        request_timeout,
+        hide_paths,
+        compression,
         writer,
         evt_send,
     }));
@@ -149,11 +262,452 @@ async fn spawn_connection_pair(
     Ok((kill_switch, sender, evt_recv))
 }
 
+/// ErrorResponse kinds, letting typed errors survive the wire.
+const ERROR_KIND_OTHER: u32 = 0;
+const ERROR_KIND_FORBIDDEN: u32 = 1;
+const ERROR_KIND_TAG_NOT_FOUND: u32 = 2;
+const ERROR_KIND_INVALID_KEY_CONVERSION: u32 = 3;
+const ERROR_KIND_AUTHENTICATION_FAILED: u32 = 4;
+const ERROR_KIND_KEYSTORE_LOCKED: u32 = 5;
+const ERROR_KIND_WRONG_STORE: u32 = 6;
+const ERROR_KIND_ENTRY_NOT_FOUND: u32 = 7;
+const ERROR_KIND_WEAK_PASSPHRASE: u32 = 8;
+const ERROR_KIND_DEADLINE_EXCEEDED: u32 = 9;
+const ERROR_KIND_CANCELLED: u32 = 10;
+const ERROR_KIND_TAG_IN_USE: u32 = 11;
+const ERROR_KIND_ENTRY_TRASHED: u32 = 12;
+const ERROR_KIND_TOO_MANY_CONNECTIONS: u32 = 13;
+const ERROR_KIND_CONNECTION_IDLE: u32 = 14;
+const ERROR_KIND_DEVICE_SECRET_MISSING: u32 = 15;
+const ERROR_KIND_EXPORT_FORBIDDEN: u32 = 16;
+const ERROR_KIND_KEY_EXPIRED: u32 = 17;
+const ERROR_KIND_INDEX_OUT_OF_RANGE: u32 = 18;
+const ERROR_KIND_ENTROPY_UNAVAILABLE: u32 = 19;
+const ERROR_KIND_WRONG_ENTRY_TYPE: u32 = 20;
+const ERROR_KIND_ENTRY_NOT_ACTIVATED: u32 = 21;
+const ERROR_KIND_INTERNAL: u32 = 22;
+const ERROR_KIND_UNSUPPORTED_ALG: u32 = 23;
+
+/// Error messages must fit in an ErrorResponse wire message.
+/// With `hide_paths`, paths added with `LairError::path_context`
+/// are left out.
+pub(crate) fn error_to_wire(e: &LairError, hide_paths: bool) -> (u32, String) {
+    let (kind, mut message) = match e {
+        LairError::Forbidden(reason) => (ERROR_KIND_FORBIDDEN, reason.clone()),
+        LairError::TagNotFound(tag) => (ERROR_KIND_TAG_NOT_FOUND, tag.clone()),
+        LairError::InvalidKeyConversion(reason) => {
+            (ERROR_KIND_INVALID_KEY_CONVERSION, reason.clone())
+        }
+        LairError::AuthenticationFailed => {
+            (ERROR_KIND_AUTHENTICATION_FAILED, e.to_string())
+        }
+        LairError::KeystoreLocked => {
+            (ERROR_KIND_KEYSTORE_LOCKED, e.to_string())
+        }
+        LairError::WrongStore(reason) => {
+            (ERROR_KIND_WRONG_STORE, reason.clone())
+        }
+        LairError::EntryNotFound(reason) => {
+            (ERROR_KIND_ENTRY_NOT_FOUND, reason.clone())
+        }
+        LairError::WeakPassphrase(reason) => {
+            (ERROR_KIND_WEAK_PASSPHRASE, reason.clone())
+        }
+        LairError::Timeout => (ERROR_KIND_DEADLINE_EXCEEDED, e.to_string()),
+        LairError::Cancelled => (ERROR_KIND_CANCELLED, e.to_string()),
+        LairError::TagInUse(tag) => (ERROR_KIND_TAG_IN_USE, tag.clone()),
+        LairError::EntryTrashed(reason) => {
+            (ERROR_KIND_ENTRY_TRASHED, reason.clone())
+        }
+        LairError::TooManyConnections => {
+            (ERROR_KIND_TOO_MANY_CONNECTIONS, e.to_string())
+        }
+        LairError::ConnectionIdle => {
+            (ERROR_KIND_CONNECTION_IDLE, e.to_string())
+        }
+        LairError::DeviceSecretMissing(reason) => {
+            (ERROR_KIND_DEVICE_SECRET_MISSING, reason.clone())
+        }
+        LairError::ExportForbidden(reason) => {
+            (ERROR_KIND_EXPORT_FORBIDDEN, reason.clone())
+        }
+        LairError::KeyExpired(reason) => {
+            (ERROR_KIND_KEY_EXPIRED, reason.clone())
+        }
+        LairError::IndexOutOfRange(reason) => {
+            (ERROR_KIND_INDEX_OUT_OF_RANGE, reason.clone())
+        }
+        LairError::EntropyUnavailable(reason) => {
+            (ERROR_KIND_ENTROPY_UNAVAILABLE, reason.clone())
+        }
+        LairError::WrongEntryType { .. } => {
+            (ERROR_KIND_WRONG_ENTRY_TYPE, e.to_string())
+        }
+        LairError::EntryNotActivated(reason) => {
+            (ERROR_KIND_ENTRY_NOT_ACTIVATED, reason.clone())
+        }
+        LairError::Internal(reason) => (ERROR_KIND_INTERNAL, reason.clone()),
+        LairError::UnsupportedAlg(reason) => {
+            (ERROR_KIND_UNSUPPORTED_ALG, reason.clone())
+        }
+        // an actor serving the request went away, e.g. it panicked
+        LairError::GhostError(e) => (ERROR_KIND_INTERNAL, e.to_string()),
+        LairError::Other(e) => {
+            (ERROR_KIND_OTHER, describe_error(&**e, hide_paths))
+        }
+        e => (ERROR_KIND_OTHER, e.to_string()),
+    };
+    truncate_error_message(&mut message);
+    (kind, message)
+}
+
+/// The per-item results of a bulk entry request, as its response
+/// carries them. Item errors never carry paths.
+pub(crate) fn bulk_results_to_wire(
+    results: Vec<LairResult<()>>,
+) -> Vec<Option<BulkItemError>> {
+    results
+        .iter()
+        .map(|res| {
+            res.as_ref().err().map(|e| {
+                let (kind, message) = error_to_wire(e, true);
+                BulkItemError { kind, message }
+            })
+        })
+        .collect()
+}
+
+/// Rebuild the per-item results of a bulk entry response.
+pub(crate) fn bulk_results_from_wire(
+    results: Vec<Option<BulkItemError>>,
+) -> Vec<LairResult<()>> {
+    results
+        .into_iter()
+        .map(|res| match res {
+            Some(BulkItemError { kind, message }) => {
+                Err(error_from_wire(kind, 0, message, Vec::new(), Vec::new()))
+            }
+            None => Ok(()),
+        })
+        .collect()
+}
+
+/// The `source()` chain of `e`, outermost first, as ErrorResponse
+/// causes. With `hide_paths`, as `error_to_wire`.
+pub(crate) fn error_causes_to_wire(
+    e: &LairError,
+    hide_paths: bool,
+) -> Vec<ErrorCause> {
+    let mut out = Vec::new();
+    let mut cause = std::error::Error::source(e);
+    while let Some(e) = cause {
+        if out.len() >= spec::MAX_ERROR_CAUSES as usize {
+            break;
+        }
+        let code = match e.downcast_ref::<std::io::Error>() {
+            Some(e) => spec::error_cause_io_code(e.kind()),
+            None => spec::ERROR_CAUSE_OTHER,
+        };
+        let mut message = describe_error(e, hide_paths);
+        truncate_error_message(&mut message);
+        out.push(ErrorCause { code, message });
+        cause = e.source();
+    }
+    out
+}
+
+/// The fields of typed errors carrying more than a message,
+/// as ErrorResponse details.
+pub(crate) fn error_details_to_wire(e: &LairError) -> Vec<u64> {
+    match e {
+        LairError::WrongEntryType {
+            index,
+            expected,
+            actual,
+        } => vec![index.0 as u64, *expected as u64, *actual as u64],
+        _ => Vec::new(),
+    }
+}
+
+/// The message of `e` alone, without its causes.
+fn describe_error(
+    e: &(dyn std::error::Error + 'static),
+    hide_paths: bool,
+) -> String {
+    match e.downcast_ref::<crate::error::ErrorContext>() {
+        Some(e) => e.describe(hide_paths),
+        None => e.to_string(),
+    }
+}
+
+fn truncate_error_message(message: &mut String) {
+    if message.len() > MAX_ERROR_MESSAGE_LEN {
+        let mut idx = MAX_ERROR_MESSAGE_LEN;
+        while !message.is_char_boundary(idx) {
+            idx -= 1;
+        }
+        message.truncate(idx);
+    }
+}
+
+/// A cause rebuilt from an ErrorResponse. Io error causes are
+/// rebuilt as `std::io::Error`s of their kind, wrapping this.
+#[derive(Debug)]
+struct RemoteCause {
+    message: String,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl std::fmt::Display for RemoteCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RemoteCause {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Rebuild an ErrorResponse cause chain, as nested `source()`s.
+fn error_causes_from_wire(
+    causes: Vec<ErrorCause>,
+) -> Option<Box<dyn std::error::Error + Send + Sync>> {
+    causes.into_iter().rev().fold(None, |source, cause| {
+        let e = RemoteCause {
+            message: cause.message,
+            source,
+        };
+        Some(match spec::error_cause_io_kind(cause.code) {
+            Some(kind) => Box::new(std::io::Error::new(kind, e)),
+            None => Box::new(e),
+        })
+    })
+}
+
+/// Typed errors keep their plain variants, rebuilt from their
+/// `details`, only unclassified errors carry the `causes`.
+fn error_from_wire(
+    kind: u32,
+    request_id: u64,
+    message: String,
+    causes: Vec<ErrorCause>,
+    details: Vec<u64>,
+) -> LairError {
+    if let (ERROR_KIND_WRONG_ENTRY_TYPE, [index, expected, actual]) =
+        (kind, details.as_slice())
+    {
+        if let (Ok(index), Ok(expected), Ok(actual)) = (
+            u32::try_from(*index),
+            entry_type_from_wire(*expected),
+            entry_type_from_wire(*actual),
+        ) {
+            return LairError::WrongEntryType {
+                index: index.into(),
+                expected,
+                actual,
+            };
+        }
+    }
+    match kind {
+        ERROR_KIND_FORBIDDEN => LairError::Forbidden(message),
+        ERROR_KIND_TAG_NOT_FOUND => LairError::TagNotFound(message),
+        ERROR_KIND_INVALID_KEY_CONVERSION => {
+            LairError::InvalidKeyConversion(message)
+        }
+        ERROR_KIND_AUTHENTICATION_FAILED => LairError::AuthenticationFailed,
+        ERROR_KIND_KEYSTORE_LOCKED => LairError::KeystoreLocked,
+        ERROR_KIND_WRONG_STORE => LairError::WrongStore(message),
+        ERROR_KIND_ENTRY_NOT_FOUND => LairError::EntryNotFound(message),
+        ERROR_KIND_WEAK_PASSPHRASE => LairError::WeakPassphrase(message),
+        ERROR_KIND_DEADLINE_EXCEEDED => LairError::Timeout,
+        ERROR_KIND_CANCELLED => LairError::Cancelled,
+        ERROR_KIND_TAG_IN_USE => LairError::TagInUse(message),
+        ERROR_KIND_ENTRY_TRASHED => LairError::EntryTrashed(message),
+        ERROR_KIND_TOO_MANY_CONNECTIONS => LairError::TooManyConnections,
+        ERROR_KIND_CONNECTION_IDLE => LairError::ConnectionIdle,
+        ERROR_KIND_DEVICE_SECRET_MISSING => {
+            LairError::DeviceSecretMissing(message)
+        }
+        ERROR_KIND_EXPORT_FORBIDDEN => LairError::ExportForbidden(message),
+        ERROR_KIND_KEY_EXPIRED => LairError::KeyExpired(message),
+        ERROR_KIND_INDEX_OUT_OF_RANGE => LairError::IndexOutOfRange(message),
+        ERROR_KIND_ENTROPY_UNAVAILABLE => {
+            LairError::EntropyUnavailable(message)
+        }
+        ERROR_KIND_ENTRY_NOT_ACTIVATED => LairError::EntryNotActivated(message),
+        ERROR_KIND_INTERNAL => LairError::Internal(message),
+        ERROR_KIND_UNSUPPORTED_ALG => LairError::UnsupportedAlg(message),
+        _ => LairError::remote_with_source(
+            request_id,
+            message,
+            error_causes_from_wire(causes),
+        ),
+    }
+}
+
+fn entry_type_from_wire(entry_type: u64) -> LairResult<actor::LairEntryType> {
+    actor::LairEntryType::parse(
+        u32::try_from(entry_type).map_err(LairError::other)?,
+    )
+}
+
+/// Unique (per server process) id of an incoming request, naming it in
+/// the server logs, in its tracing span, and in its ErrorResponse.
+fn next_request_id() -> u64 {
+    static REQUEST_ID: std::sync::atomic::AtomicU64 =
+        std::sync::atomic::AtomicU64::new(1);
+    REQUEST_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+tokio::task_local! {
+    /// The `next_request_id` of the incoming request being handled.
+    static REQUEST_ID: u64;
+}
+
+/// The id of the incoming request whose response is being made, see
+/// `next_request_id`, for the handlers that record it. Zero outside one.
+pub(crate) fn current_request_id() -> u64 {
+    REQUEST_ID.try_with(|request_id| *request_id).unwrap_or(0)
+}
+
+/// Cancel tokens of the incoming requests still being handled.
+type InFlight = Arc<
+    std::sync::Mutex<
+        HashMap<
+            u64,
+            (
+                internal::cancel::CancelToken,
+                internal::debug_stats::Tracked,
+            ),
+        >,
+    >,
+>;
+
+fn lock_in_flight(
+    in_flight: &InFlight,
+) -> std::sync::MutexGuard<
+    '_,
+    HashMap<
+        u64,
+        (
+            internal::cancel::CancelToken,
+            internal::debug_stats::Tracked,
+        ),
+    >,
+> {
+    // tokens are only inserted / removed, never held across a panic point
+    in_flight.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the cancel token of an incoming request once it is handled,
+/// or its future is dropped, e.g. by the connection shutting down.
+struct InFlightGuard {
+    msg_id: u64,
+    token: internal::cancel::CancelToken,
+    in_flight: InFlight,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut in_flight = lock_in_flight(&self.in_flight);
+        // unless a later request reused the id
+        if in_flight
+            .get(&self.msg_id)
+            .is_some_and(|(t, _)| t.same(&self.token))
+        {
+            in_flight.remove(&self.msg_id);
+        }
+    }
+}
+
+/// Where the responses to outgoing requests are sent, by message id.
+type Pending = Arc<
+    std::sync::Mutex<
+        HashMap<
+            u64,
+            (
+                tokio::sync::oneshot::Sender<LairWire>,
+                internal::debug_stats::Tracked,
+            ),
+        >,
+    >,
+>;
+
+fn lock_pending(
+    pending: &Pending,
+) -> std::sync::MutexGuard<
+    '_,
+    HashMap<
+        u64,
+        (
+            tokio::sync::oneshot::Sender<LairWire>,
+            internal::debug_stats::Tracked,
+        ),
+    >,
+> {
+    // senders are only inserted / removed, never held across a panic point
+    pending.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Stops waiting on the response to an outgoing request once its future
+/// is gone, however it ended: answered, timed out, failed or dropped.
+/// Message ids are unique to the process, so never reused meanwhile.
+struct PendingGuard {
+    msg_id: u64,
+    pending: Pending,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        lock_pending(&self.pending).remove(&self.msg_id);
+    }
+}
+
+/// Sends a `ToLairCancelRequest` if dropped before it is disarmed,
+/// i.e. if a request future is dropped before its response arrives.
+struct CancelOnDrop {
+    msg_id: u64,
+    writer: Option<futures::channel::mpsc::Sender<LowLevelWireApi>>,
+}
+
+impl CancelOnDrop {
+    fn disarm(&mut self) {
+        self.writer.take();
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => return,
+        };
+        let msg_id = self.msg_id;
+        // without a runtime, the connection is going away anyway
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            rt.spawn(async move {
+                let _ = writer
+                    .low_level_send(LairWire::ToLairCancelRequest { msg_id })
+                    .await;
+            });
+        }
+    }
+}
+
 struct Internal {
     kill_switch: KillSwitch,
-    pending: HashMap<u64, tokio::sync::oneshot::Sender<LairWire>>,
+    pending: Pending,
+    in_flight: InFlight,
+    request_timeout: Option<std::time::Duration>,
+    hide_paths: bool,
+    compression: Arc<FrameCompression>,
     writer: futures::channel::mpsc::Sender<LowLevelWireApi>,
     evt_send: futures::channel::mpsc::Sender<IpcWireApi>,
+    _tracked: internal::debug_stats::Tracked,
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
@@ -166,23 +720,173 @@ impl LowLevelWireApiHandler for Internal {
         msg: LairWire,
     ) -> LowLevelWireApiHandlerResult<()> {
         trace!(?msg, "RECV MSG");
-        if msg.is_req() {
+        if let LairWire::ToLairCancelRequest { msg_id } = msg {
+            // a cancel racing the response finds nothing left to cancel
+            if let Some((token, _)) =
+                lock_in_flight(&self.in_flight).remove(&msg_id)
+            {
+                token.cancel();
+            }
+            Ok(async move { Ok(()) }.boxed().into())
+        } else if let LairWire::ToCliGoodbye {
+            msg_id,
+            kind,
+            message,
+        } = msg
+        {
+            // the remote is closing the connection, fail whatever
+            // awaits it with its reason, rather than a dropped connection
+            let pending: Vec<_> = lock_pending(&self.pending).drain().collect();
+            for (msg_id, (send, _)) in pending {
+                let _ = send.send(LairWire::ErrorResponse {
+                    msg_id,
+                    kind,
+                    request_id: 0,
+                    message: message.clone(),
+                    causes: Vec::new(),
+                    details: Vec::new(),
+                });
+            }
+            self.kill_switch.set_kill_reason(Arc::new(move || {
+                error_from_wire(
+                    kind,
+                    0,
+                    message.clone(),
+                    Vec::new(),
+                    Vec::new(),
+                )
+            }));
+            let fut = self
+                .writer
+                .low_level_send(LairWire::ToLairGoodbyeResponse { msg_id });
+            Ok(async move {
+                let _ = fut.await;
+                Ok(())
+            }
+            .boxed()
+            .into())
+        } else if let LairWire::ToLairNegotiateCompression {
+            msg_id,
+            algorithms,
+        } = msg
+        {
+            // frames are compressed below the api, so this is answered here
+            let algorithm = compress::choose_algorithm(
+                algorithms,
+                self.compression.allowed(),
+            );
+            self.compression.set_accepted(algorithm);
+            let compression = self.compression.clone();
+            let fut = self.writer.low_level_send(
+                LairWire::ToCliNegotiateCompressionResponse {
+                    msg_id,
+                    algorithm,
+                },
+            );
+            Ok(async move {
+                // the response itself goes out uncompressed
+                if fut.await.is_ok() {
+                    compression.set_algorithm(algorithm);
+                }
+                Ok(())
+            }
+            .boxed()
+            .into())
+        } else if msg.is_req() {
+            let msg_id = msg.get_msg_id();
+            // the remaining time is relative, so the clocks
+            // of both sides need not agree
+            let (msg, deadline) = match msg {
+                LairWire::ToLairRequestDeadline {
+                    remaining_ms,
+                    request,
+                    ..
+                } => (
+                    *request,
+                    internal::deadline::deadline_in(
+                        std::time::Duration::from_millis(remaining_ms),
+                    ),
+                ),
+                msg => (msg, None),
+            };
+            let request_id = next_request_id();
             let fut = self.kill_switch.mix_static(self.evt_send.request(msg));
+            // the handler's response future runs in this scope
+            let fut = REQUEST_ID.scope(request_id, async move {
+                match deadline {
+                    Some(deadline) => {
+                        internal::deadline::with_deadline(deadline, fut).await
+                    }
+                    None => fut.await,
+                }
+            });
+            let fut = catch_request_panic("request handler", fut);
+            let fut = ghost_actor::dependencies::tracing_futures::Instrument::instrument(
+                fut,
+                info_span!("lair_request", request_id, msg_id),
+            );
+            let token = internal::cancel::CancelToken::new();
+            lock_in_flight(&self.in_flight).insert(
+                msg_id,
+                (
+                    token.clone(),
+                    internal::debug_stats::Tracked::new(
+                        internal::debug_stats::Counter::InFlightRequest,
+                    ),
+                ),
+            );
+            let in_flight_guard = InFlightGuard {
+                msg_id,
+                token: token.clone(),
+                in_flight: self.in_flight.clone(),
+            };
             let writer_clone = self.writer.clone();
+            let hide_paths = self.hide_paths;
             let weak_kill_switch = self.kill_switch.weak();
             Ok(async move {
-                if let Ok(res) = fut.await {
-                    let _ = weak_kill_switch
-                        .mix(writer_clone.low_level_send(res))
-                        .await;
+                let res = internal::cancel::with_cancel(token, fut).await;
+                drop(in_flight_guard);
+                let error_response = |e: LairError| {
+                    debug!(request_id, msg_id, ?e, "request failed");
+                    let (kind, message) = error_to_wire(&e, hide_paths);
+                    LairWire::ErrorResponse {
+                        msg_id,
+                        kind,
+                        request_id,
+                        message,
+                        causes: error_causes_to_wire(&e, hide_paths),
+                        details: error_details_to_wire(&e),
+                    }
+                };
+                let res = match res {
+                    Ok(res) => res,
+                    // respond with the error so the remote
+                    // does not wait on a dangling request
+                    Err(e) => error_response(e),
+                };
+                let is_error = matches!(res, LairWire::ErrorResponse { .. });
+                if let Err(e) =
+                    weak_kill_switch.mix(writer_clone.low_level_send(res)).await
+                {
+                    // e.g. the response failed to encode, the remote
+                    // still waits on an answer, if the connection is up
+                    if !is_error {
+                        let res = error_response(LairError::Internal(format!(
+                            "failed to send response: {}",
+                            e
+                        )));
+                        let _ = weak_kill_switch
+                            .mix(writer_clone.low_level_send(res))
+                            .await;
+                    }
                 }
-                // TODO - send errors back so we don't have dangling reqs!!
                 Ok(())
             }
             .boxed()
             .into())
         } else {
-            if let Some(send) = self.pending.remove(&msg.get_msg_id()) {
+            let send = lock_pending(&self.pending).remove(&msg.get_msg_id());
+            if let Some((send, _)) = send {
                 trace!("outgoing response received");
                 let _ = send.send(msg);
             }
@@ -198,21 +902,115 @@ impl IpcWireApiHandler for Internal {
         &mut self,
         msg: LairWire,
     ) -> IpcWireApiHandlerResult<LairWire> {
+        if let Some(err) = self.kill_switch.kill_reason() {
+            return Ok(async move { Err(err) }.boxed().into());
+        }
         let (send, recv) = tokio::sync::oneshot::channel();
-        self.pending.insert(msg.get_msg_id(), send);
+        lock_pending(&self.pending).insert(
+            msg.get_msg_id(),
+            (
+                send,
+                internal::debug_stats::Tracked::new(
+                    internal::debug_stats::Counter::PendingResponse,
+                ),
+            ),
+        );
+        let pending_guard = PendingGuard {
+            msg_id: msg.get_msg_id(),
+            pending: self.pending.clone(),
+        };
+        // tell the server when a request for it is no longer awaited
+        let mut cancel_on_drop = CancelOnDrop {
+            msg_id: msg.get_msg_id(),
+            writer: None,
+        };
+        if msg.is_req() && !msg.is_event() {
+            cancel_on_drop.writer = Some(self.writer.clone());
+        }
+        // the remote answers negotiation itself, and may compress
+        // frames from the response on, only offer what is accepted
+        let (msg, negotiating) = match msg {
+            LairWire::ToLairNegotiateCompression { msg_id, algorithms } => {
+                let algorithms = algorithms & self.compression.allowed();
+                self.compression.set_accepted(algorithms);
+                (
+                    LairWire::ToLairNegotiateCompression { msg_id, algorithms },
+                    Some(self.compression.clone()),
+                )
+            }
+            msg => (msg, None),
+        };
+        let request_timeout = match self.request_timeout {
+            Some(timeout) if msg.is_req() => Some(timeout),
+            _ => None,
+        };
+        let msg = match request_timeout {
+            Some(timeout) if negotiating.is_none() => {
+                LairWire::ToLairRequestDeadline {
+                    msg_id: msg.get_msg_id(),
+                    remaining_ms: timeout.as_millis() as u64,
+                    request: Box::new(msg),
+                }
+            }
+            _ => msg,
+        };
         trace!("con write {:?}", msg);
         let fut = self.kill_switch.mix_static(self.writer.low_level_send(msg));
         let weak_kill_switch = self.kill_switch.weak();
         Ok(async move {
+            let _pending_guard = pending_guard;
             fut.await?;
-            Ok(weak_kill_switch
+            weak_kill_switch
                 .mix(async move {
                     trace!("await incoming request...");
-                    let res = recv.await.map_err(LairError::other);
+                    // the server answers with a DeadlineExceeded error
+                    // once the deadline is up, but do not rely on it
+                    let recv = async move {
+                        match request_timeout {
+                            Some(timeout) => {
+                                tokio::time::timeout(timeout, recv)
+                                    .await
+                                    .map_err(|_| LairError::Timeout)?
+                            }
+                            None => recv.await,
+                        }
+                        .map_err(LairError::other)
+                    };
+                    let res = recv.await?;
+                    cancel_on_drop.disarm();
+                    let res = match res {
+                        LairWire::ErrorResponse {
+                            kind,
+                            request_id,
+                            message,
+                            causes,
+                            details,
+                            ..
+                        } => Err(error_from_wire(
+                            kind, request_id, message, causes, details,
+                        )),
+                        res => Ok(res),
+                    };
+                    if let Some(compression) = negotiating {
+                        let algorithm = match &res {
+                            Ok(
+                                LairWire::ToCliNegotiateCompressionResponse {
+                                    algorithm,
+                                    ..
+                                },
+                            ) => compress::choose_algorithm(
+                                *algorithm,
+                                compression.accepted(),
+                            ),
+                            _ => 0,
+                        };
+                        compression.set_accepted(algorithm);
+                        compression.set_algorithm(algorithm);
+                    }
                     trace!(?res, "respond to incoming request");
                     res
                 })
-                .await?)
+                .await
         }
         .boxed()
         .into())
@@ -234,6 +1032,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_wrong_entry_type_errors_keep_their_fields() {
+        use actor::LairEntryType;
+
+        let e = LairError::WrongEntryType {
+            index: 42.into(),
+            expected: LairEntryType::TlsCert,
+            actual: LairEntryType::SignEd25519,
+        };
+        let (kind, message) = error_to_wire(&e, false);
+        let details = error_details_to_wire(&e);
+        assert_eq!(vec![42, 0x00000100, 0x00000200], details);
+        match error_from_wire(kind, 7, message.clone(), Vec::new(), details) {
+            LairError::WrongEntryType {
+                index,
+                expected,
+                actual,
+            } => {
+                assert_eq!(42, index.0);
+                assert_eq!(LairEntryType::TlsCert, expected);
+                assert_eq!(LairEntryType::SignEd25519, actual);
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        // without usable details, it is only a message
+        for details in
+            &[vec![], vec![42, 0x00000100, 0x42], vec![1 << 32, 0, 0]]
+        {
+            let e = error_from_wire(
+                kind,
+                7,
+                message.clone(),
+                Vec::new(),
+                details.clone(),
+            );
+            assert_eq!(Some(7), e.request_id());
+            assert_eq!(message, e.to_string());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_ipc_listeners_bind_and_unbind() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let shared = tempfile::tempdir().unwrap();
+        let shared_path = shared.path().canonicalize().unwrap().join("sock");
+        let shared_url = LairConnectionUrl::new_unix_socket(&shared_path)?
+            .with_token("shared-token")?;
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .add_listener(shared_url.clone())
+            .build();
+
+        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+        assert!(config.get_socket_path().exists());
+        assert!(shared_path.exists());
+
+        // each connection carries the url of the listener it came in on
+        for (url, token) in &[
+            (config.get_connection_url().clone(), None),
+            (shared_url, Some("shared-token")),
+        ] {
+            let cli_config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_connection_url(url.clone())
+                .build();
+            let _con = spawn_ipc_connection(cli_config).await?;
+            let (_, _, _, con_url) = srv_recv.next().await.unwrap();
+            assert_eq!(url, &con_url);
+            assert_eq!(*token, con_url.token());
+        }
+
+        drop(srv_kill);
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        assert!(!config.get_socket_path().exists());
+        assert!(!shared_path.exists());
+
+        Ok(())
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn test_ipc_raw_wire() -> LairResult<()> {
         init_tracing();
@@ -246,7 +1127,7 @@ mod tests {
 
         let srv_task_kill = srv_kill.clone();
         err_spawn("test-outer", async move {
-            while let Some((con_kill, con_send, mut con_recv)) =
+            while let Some((con_kill, con_send, mut con_recv, _)) =
                 srv_recv.next().await
             {
                 err_spawn("test-inner", async move {
@@ -254,6 +1135,7 @@ mod tests {
                     let r = con_send
                         .request(LairWire::ToCliRequestUnlockPassphrase {
                             msg_id: 0,
+                            store_name: actor::DEFAULT_STORE_NAME.to_string(),
                         })
                         .await
                         .unwrap();
@@ -263,7 +1145,7 @@ mod tests {
                             passphrase,
                             ..
                         } => {
-                            assert_eq!("test-passphrase", &passphrase);
+                            assert_eq!(b"test-passphrase", passphrase.read());
                         }
                         _ => panic!("unexpected: {:?}", r),
                     }
@@ -280,9 +1162,26 @@ mod tests {
                                     respond.respond(Ok(async move {
                                         Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
                                             msg_id,
-                                            last_keystore_index: 42.into(),
+                                            last_keystore_index: Some(42.into()),
                                         })
                                     }.boxed().into()));
+                                } else if let LairWire::ToLairLairGetServerPaths {
+                                    ..
+                                } = msg
+                                {
+                                    respond.respond(Ok(async move {
+                                        Err(LairError::Forbidden(
+                                            "test-forbidden".into(),
+                                        ))
+                                    }
+                                    .boxed()
+                                    .into()));
+                                } else {
+                                    respond.respond(Ok(async move {
+                                        Err("test-error".into())
+                                    }
+                                    .boxed()
+                                    .into()));
                                 }
                             }
                         }
@@ -306,11 +1205,13 @@ mod tests {
             IpcWireApi::Request { respond, msg, .. } => {
                 println!("GOT: {:?}", msg);
                 match msg {
-                    LairWire::ToCliRequestUnlockPassphrase { msg_id } => {
+                    LairWire::ToCliRequestUnlockPassphrase {
+                        msg_id, ..
+                    } => {
                         respond.respond(Ok(async move {
                             Ok(LairWire::ToLairRequestUnlockPassphraseResponse {
                                 msg_id,
-                                passphrase: "test-passphrase".to_string(),
+                                passphrase: "test-passphrase".into(),
                             })
                         }
                         .boxed()
@@ -332,7 +1233,24 @@ mod tests {
                 last_keystore_index,
                 ..
             } => {
-                assert_eq!(42, last_keystore_index.0);
+                assert_eq!(Some(42.into()), last_keystore_index);
+            }
+            _ => panic!("unexpected: {:?}", res),
+        }
+
+        // server side errors are forwarded, not left dangling
+        let res = cli_send
+            .request(LairWire::ToLairLairGetServerInfo { msg_id: 1 })
+            .await;
+        assert_eq!("test-error", &res.unwrap_err().to_string());
+
+        // and keep their type
+        let res = cli_send
+            .request(LairWire::ToLairLairGetServerPaths { msg_id: 2 })
+            .await;
+        match res {
+            Err(LairError::Forbidden(reason)) => {
+                assert_eq!("test-forbidden", &reason);
             }
             _ => panic!("unexpected: {:?}", res),
         }
@@ -347,4 +1265,534 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_ipc_request_deadline() -> LairResult<()> {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_request_timeout(std::time::Duration::from_millis(50))
+            .build();
+
+        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let saw_deadline = Arc::new(AtomicBool::new(false));
+
+        let srv_rounds = rounds.clone();
+        let srv_saw_deadline = saw_deadline.clone();
+        err_spawn("test-outer", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                con_recv.next().await
+            {
+                match msg {
+                    LairWire::ToLairLairGetLastEntryIndex { msg_id } => {
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                                msg_id,
+                                last_keystore_index: None,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    _ => {
+                        // a deliberately slow operation,
+                        // checking its deadline between rounds
+                        let rounds = srv_rounds.clone();
+                        let saw_deadline = srv_saw_deadline.clone();
+                        respond.respond(Ok(async move {
+                            for _ in 0..100 {
+                                if internal::deadline::current_deadline()
+                                    .is_some()
+                                {
+                                    saw_deadline.store(true, Ordering::SeqCst);
+                                }
+                                internal::deadline::check_deadline()?;
+                                tokio::time::delay_for(
+                                    std::time::Duration::from_millis(10),
+                                )
+                                .await;
+                                rounds.fetch_add(1, Ordering::SeqCst);
+                            }
+                            Err("slow operation completed".into())
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                }
+            }
+            LairResult::<()>::Ok(())
+        });
+
+        let (cli_kill, cli_send, _cli_recv) =
+            spawn_ipc_connection(config).await?;
+
+        // fast requests complete within their deadline
+        let res = cli_send
+            .request(LairWire::ToLairLairGetLastEntryIndex { msg_id: 0 })
+            .await?;
+        assert!(matches!(
+            res,
+            LairWire::ToCliLairGetLastEntryIndexResponse { .. }
+        ));
+
+        let res = cli_send
+            .request(LairWire::ToLairLairGetServerInfo { msg_id: 1 })
+            .await;
+        assert!(matches!(res, Err(LairError::Timeout)));
+
+        // the server stopped early, rather than running all 100 rounds
+        tokio::time::delay_for(std::time::Duration::from_millis(300)).await;
+        assert!(saw_deadline.load(Ordering::SeqCst));
+        let stopped_at = rounds.load(Ordering::SeqCst);
+        assert!(stopped_at < 20, "ran {} rounds", stopped_at);
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        assert_eq!(stopped_at, rounds.load(Ordering::SeqCst));
+
+        drop(cli_kill);
+        drop(srv_kill);
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_ipc_request_failures_keep_connection() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+
+        err_spawn("test-outer", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                con_recv.next().await
+            {
+                match msg {
+                    LairWire::ToLairLairGetLastEntryIndex { msg_id } => {
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                                msg_id,
+                                last_keystore_index: None,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToLairLairFindEntriesByPrefix {
+                        msg_id, ..
+                    } => {
+                        // a response that cannot be encoded
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairLairFindEntriesByPrefix {
+                                msg_id,
+                                prefix: Arc::new(vec![0; 100]),
+                                kind: actor::PrefixKind::Any,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    _ => {
+                        respond.respond(Ok(async move {
+                            panic!("test handler panic");
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                }
+            }
+            LairResult::<()>::Ok(())
+        });
+
+        let (cli_kill, cli_send, _cli_recv) =
+            spawn_ipc_connection(config).await?;
+
+        let assert_serving = || async {
+            let res = cli_send
+                .request(LairWire::ToLairLairGetLastEntryIndex { msg_id: 0 })
+                .await
+                .unwrap();
+            assert!(matches!(
+                res,
+                LairWire::ToCliLairGetLastEntryIndexResponse { .. }
+            ));
+        };
+        assert_serving().await;
+
+        // deadlines past what an instant holds, found by fuzzing,
+        // once killed the connection
+        for remaining_ms in &[u64::MAX, 14673286194807458709] {
+            let res = cli_send
+                .request(LairWire::ToLairRequestDeadline {
+                    msg_id: 1,
+                    remaining_ms: *remaining_ms,
+                    request: Box::new(LairWire::ToLairLairGetLastEntryIndex {
+                        msg_id: 1,
+                    }),
+                })
+                .await?;
+            assert!(matches!(
+                res,
+                LairWire::ToCliLairGetLastEntryIndexResponse { .. }
+            ));
+            assert_serving().await;
+        }
+
+        // a panicking handler fails its request only
+        let res = cli_send
+            .request(LairWire::ToLairLairNewUuid { msg_id: 2 })
+            .await;
+        assert!(matches!(res, Err(LairError::Internal(_))), "{:?}", res);
+        assert_serving().await;
+
+        // as does a response that fails to encode
+        let res = cli_send
+            .request(LairWire::ToLairLairFindEntriesByPrefix {
+                msg_id: 3,
+                prefix: Arc::new(vec![0; 4]),
+                kind: actor::PrefixKind::Any,
+            })
+            .await;
+        assert!(matches!(res, Err(LairError::Internal(_))), "{:?}", res);
+        assert_serving().await;
+
+        // and a request that fails to encode
+        let res = cli_send
+            .request(LairWire::ToLairLairFindEntriesByPrefix {
+                msg_id: 4,
+                prefix: Arc::new(vec![0; 95]),
+                kind: actor::PrefixKind::Any,
+            })
+            .await;
+        assert!(res.is_err());
+        assert_serving().await;
+
+        drop(cli_kill);
+        drop(srv_kill);
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_ipc_request_cancel() -> LairResult<()> {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        // flags the slow operation as stopped, however it ends
+        struct StopGuard(Arc<AtomicBool>);
+        impl Drop for StopGuard {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let srv_rounds = rounds.clone();
+        let srv_stopped = stopped.clone();
+        err_spawn("test-outer", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                con_recv.next().await
+            {
+                match msg {
+                    LairWire::ToLairLairGetLastEntryIndex { msg_id } => {
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                                msg_id,
+                                last_keystore_index: None,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    _ => {
+                        // a deliberately slow operation,
+                        // checking for cancellation between rounds
+                        let rounds = srv_rounds.clone();
+                        let guard = StopGuard(srv_stopped.clone());
+                        respond.respond(Ok(async move {
+                            let _guard = guard;
+                            for _ in 0..100 {
+                                internal::cancel::check_cancelled()?;
+                                tokio::time::delay_for(
+                                    std::time::Duration::from_millis(10),
+                                )
+                                .await;
+                                rounds.fetch_add(1, Ordering::SeqCst);
+                            }
+                            Err("slow operation completed".into())
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                }
+            }
+            LairResult::<()>::Ok(())
+        });
+
+        let (cli_kill, cli_send, _cli_recv) =
+            spawn_ipc_connection(config).await?;
+
+        // drop the slow request while it is running
+        let slow =
+            cli_send.request(LairWire::ToLairLairGetServerInfo { msg_id: 1 });
+        assert!(tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            slow
+        )
+        .await
+        .is_err());
+
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        assert!(stopped.load(Ordering::SeqCst));
+        let stopped_at = rounds.load(Ordering::SeqCst);
+        assert!(stopped_at < 20, "ran {} rounds", stopped_at);
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        assert_eq!(stopped_at, rounds.load(Ordering::SeqCst));
+
+        // the connection keeps matching responses to requests
+        let res = cli_send
+            .request(LairWire::ToLairLairGetLastEntryIndex { msg_id: 2 })
+            .await?;
+        assert_eq!(
+            LairWire::ToCliLairGetLastEntryIndexResponse {
+                msg_id: 2,
+                last_keystore_index: None,
+            },
+            res
+        );
+
+        drop(cli_kill);
+        drop(srv_kill);
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_ipc_negotiates_compression() -> LairResult<()> {
+        init_tracing();
+
+        // client compression, server compression, negotiated at all
+        for (cli_compression, srv_compression, negotiate) in &[
+            (true, true, true),
+            (true, false, true),
+            (false, true, true),
+            (true, true, false),
+        ] {
+            let tmpdir = tempfile::tempdir().unwrap();
+            let srv_config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_wire_compression(*srv_compression)
+                .build();
+            let cli_config = Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_wire_compression(*cli_compression)
+                .build();
+
+            let (srv_kill, mut srv_recv) = spawn_bind_ipc(srv_config).await?;
+
+            // echo messages to sign back, in a response at least as large
+            err_spawn("test-outer", async move {
+                let (_con_kill, _con_send, mut con_recv, _) =
+                    srv_recv.next().await.unwrap();
+                while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                    con_recv.next().await
+                {
+                    let res = match msg {
+                        LairWire::ToLairSignEd25519SignByIndex {
+                            msg_id,
+                            message,
+                            ..
+                        } => Ok(LairWire::ToCliLairGetRandomBytesResponse {
+                            msg_id,
+                            bytes: message,
+                        }),
+                        msg => Err(format!("unexpected: {:?}", msg).into()),
+                    };
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                LairResult::<()>::Ok(())
+            });
+
+            let (cli_kill, cli_send, _cli_recv) =
+                spawn_ipc_connection(cli_config).await?;
+
+            if *negotiate {
+                let res = cli_send
+                    .request(LairWire::ToLairNegotiateCompression {
+                        msg_id: 0,
+                        algorithms: compress::SUPPORTED_ALGORITHMS,
+                    })
+                    .await?;
+                let expect = if *cli_compression && *srv_compression {
+                    spec::COMPRESSION_LZ4
+                } else {
+                    0
+                };
+                assert_eq!(
+                    LairWire::ToCliNegotiateCompressionResponse {
+                        msg_id: 0,
+                        algorithm: expect,
+                    },
+                    res
+                );
+            }
+
+            // compressible and incompressible, around the threshold
+            let mut state = 0x2545f491_u32;
+            let noise = (0..spec::COMPRESSION_THRESHOLD * 4)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            let messages = vec![
+                vec![0xa5; 16],
+                vec![0xa5; spec::COMPRESSION_THRESHOLD],
+                vec![0xa5; actor::MAX_RANDOM_BYTES_LEN as usize],
+                noise,
+            ];
+            for (msg_id, message) in messages.into_iter().enumerate() {
+                let msg_id = msg_id as u64 + 1;
+                let message = Arc::new(message);
+                let res = cli_send
+                    .request(LairWire::ToLairSignEd25519SignByIndex {
+                        msg_id,
+                        keystore_index: 1.into(),
+                        message: message.clone(),
+                    })
+                    .await?;
+                assert_eq!(
+                    LairWire::ToCliLairGetRandomBytesResponse {
+                        msg_id,
+                        bytes: message,
+                    },
+                    res
+                );
+            }
+
+            drop(cli_kill);
+            drop(srv_kill);
+            drop(tmpdir);
+        }
+
+        Ok(())
+    }
+
+    /// Captures the request_id of each `lair_request` span.
+    struct RequestIdLayer(Arc<std::sync::Mutex<Vec<u64>>>);
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for RequestIdLayer {
+        fn new_span(
+            &self,
+            attrs: &span::Attributes<'_>,
+            _id: &span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Visitor<'a>(&'a std::sync::Mutex<Vec<u64>>);
+            impl field::Visit for Visitor<'_> {
+                fn record_u64(&mut self, field: &field::Field, value: u64) {
+                    if field.name() == "request_id" {
+                        self.0.lock().unwrap().push(value);
+                    }
+                }
+                fn record_debug(
+                    &mut self,
+                    _field: &field::Field,
+                    _value: &dyn std::fmt::Debug,
+                ) {
+                }
+            }
+            if attrs.metadata().name() == "lair_request" {
+                attrs.record(&mut Visitor(&self.0));
+            }
+        }
+    }
+
+    // single threaded, so the scoped subscriber sees the server side too
+    #[tokio::test]
+    async fn test_ipc_error_request_id() -> LairResult<()> {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let captured = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let _guard = subscriber::set_default(
+            tracing_subscriber::registry()
+                .with(RequestIdLayer(captured.clone())),
+        );
+
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+
+        err_spawn("test-outer", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            while let Some(IpcWireApi::Request { respond, .. }) =
+                con_recv.next().await
+            {
+                respond.respond(Ok(async move { Err("forced-error".into()) }
+                    .boxed()
+                    .into()));
+            }
+            LairResult::<()>::Ok(())
+        });
+
+        let (cli_kill, cli_send, _cli_recv) =
+            spawn_ipc_connection(config).await?;
+
+        let err = cli_send
+            .request(LairWire::ToLairLairGetServerInfo { msg_id: 0 })
+            .await
+            .unwrap_err();
+        assert_eq!("forced-error", &err.to_string());
+        let request_id = err.request_id().expect("server error has an id");
+        assert_eq!(vec![request_id], *captured.lock().unwrap());
+
+        // a second request is told apart
+        let err = cli_send
+            .request(LairWire::ToLairLairGetServerInfo { msg_id: 1 })
+            .await
+            .unwrap_err();
+        assert_ne!(Some(request_id), err.request_id());
+        assert_eq!(
+            vec![request_id, err.request_id().unwrap()],
+            *captured.lock().unwrap()
+        );
+
+        drop(cli_kill);
+        drop(srv_kill);
+        drop(tmpdir);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use super::*;
+use zeroize::Zeroize;
 
 ghost_actor::ghost_chan! {
     /// Low-level send api..
@@ -16,9 +17,57 @@ pub(crate) type LowLevelWireSender =
 pub(crate) type LowLevelWireReceiver =
     futures::channel::mpsc::Receiver<LowLevelWireApi>;
 
+/// The compression state of a connection, shared by its read and
+/// write halves, see `wire::compress`.
+#[derive(Debug, Default)]
+pub(crate) struct FrameCompression {
+    /// the algorithms this side agrees to negotiate
+    allowed: u32,
+    /// the algorithms compressed frames are read with
+    accepted: std::sync::atomic::AtomicU32,
+    /// the algorithm written frames are compressed with, `0` for none
+    algorithm: std::sync::atomic::AtomicU32,
+}
+
+impl FrameCompression {
+    pub(crate) fn new(allowed: u32) -> Arc<Self> {
+        Arc::new(Self {
+            allowed,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn allowed(&self) -> u32 {
+        self.allowed
+    }
+
+    pub(crate) fn accepted(&self) -> u32 {
+        self.accepted.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Read frames compressed with any of `algorithms`. Set before
+    /// asking the remote to send them.
+    pub(crate) fn set_accepted(&self, algorithms: u32) {
+        self.accepted
+            .store(algorithms, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub(crate) fn algorithm(&self) -> u32 {
+        self.algorithm.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Compress frames written from now on with `algorithm`. Set once
+    /// the remote agreed to read them.
+    pub(crate) fn set_algorithm(&self, algorithm: u32) {
+        self.algorithm
+            .store(algorithm, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
 pub(crate) fn spawn_low_level_write_half(
     kill_switch: KillSwitch,
     mut write_half: IpcWrite,
+    compression: Arc<FrameCompression>,
 ) -> LairResult<LowLevelWireSender> {
     let (s, mut r) = futures::channel::mpsc::channel(10);
 
@@ -33,13 +82,27 @@ pub(crate) fn spawn_low_level_write_half(
         {
             match msg {
                 LowLevelWireApi::LowLevelSend { respond, msg, .. } => {
+                    // nothing is written if the message fails to encode,
+                    // so the connection is fine, only this message is not
+                    let mut msg_enc = match encode_frame(&msg, &compression) {
+                        Ok(msg_enc) => msg_enc,
+                        Err(e) => {
+                            respond.respond(Ok(async move { Err(e) }
+                                .boxed()
+                                .into()));
+                            continue;
+                        }
+                    };
                     let res = kill_switch
                         .mix(async {
-                            let msg_enc = msg.encode()?;
-                            write_half
+                            let res = write_half
                                 .write_all(&msg_enc)
                                 .await
-                                .map_err(LairError::other)?;
+                                .map_err(LairError::other);
+                            if msg.carries_secret() {
+                                msg_enc.zeroize();
+                            }
+                            res?;
                             trace!("ll wrote {:?}", msg);
                             Ok(())
                         })
@@ -63,6 +126,7 @@ pub(crate) fn spawn_low_level_write_half(
 pub(crate) fn spawn_low_level_read_half(
     kill_switch: KillSwitch,
     mut read_half: IpcRead,
+    compression: Arc<FrameCompression>,
 ) -> LairResult<LowLevelWireReceiver> {
     let (s, r) = futures::channel::mpsc::channel(10);
 
@@ -84,10 +148,27 @@ pub(crate) fn spawn_low_level_read_half(
             pending_data.extend_from_slice(&buffer[..read]);
             while let Ok(size) = LairWire::peek_size(&pending_data) {
                 trace!(?size, "ll read peek size");
+                if size > spec::MAX_MESSAGE_LEN {
+                    return Err(LairError::MessageTooLarge {
+                        len: size,
+                        max: spec::MAX_MESSAGE_LEN,
+                    });
+                }
                 if pending_data.len() < size {
                     break;
                 }
-                let msg = LairWire::decode(&pending_data)?;
+                let msg = if compress::is_compressed(&pending_data[..size]) {
+                    let mut frame = compress::decompress_frame(
+                        &pending_data[..size],
+                        compression.accepted(),
+                    )?;
+                    decode_frame(&mut frame)?
+                } else {
+                    decode_frame(&mut pending_data[..size])?
+                };
+                if msg.carries_secret() {
+                    buffer.zeroize();
+                }
                 let _ = pending_data.drain(..size);
                 trace!("ll read {:?}", msg);
                 // run this in a task so we don't hold up the read loop
@@ -105,3 +186,55 @@ pub(crate) fn spawn_low_level_read_half(
 
     Ok(r)
 }
+
+/// Encode `msg`, compressed if compression was negotiated, unless
+/// it carries a secret.
+fn encode_frame(
+    msg: &LairWire,
+    compression: &FrameCompression,
+) -> LairResult<Vec<u8>> {
+    let mut frame = msg.encode()?;
+    let algorithm = compression.algorithm();
+    if algorithm == 0 || msg.carries_secret() {
+        return Ok(frame);
+    }
+    if let Some(compressed) = compress::compress_frame(&frame, algorithm)? {
+        frame = compressed;
+    }
+    Ok(frame)
+}
+
+/// Decode the message in `frame`, zeroizing the frame if the message
+/// carries a secret, which it now holds its own copy of.
+fn decode_frame(frame: &mut [u8]) -> LairResult<LairWire> {
+    let msg = LairWire::decode(frame)?;
+    if msg.carries_secret() {
+        frame.zeroize();
+    }
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_zeroizes_frames_carrying_a_passphrase() {
+        let msg = LairWire::ToLairRequestUnlockPassphraseResponse {
+            msg_id: 42,
+            passphrase: "passphrase".into(),
+        };
+        let mut frame = msg.encode().unwrap();
+        assert_eq!(msg, decode_frame(&mut frame).unwrap());
+        assert!(frame.iter().all(|b| *b == 0));
+
+        let msg = LairWire::ToCliRequestUnlockPassphrase {
+            msg_id: 42,
+            store_name: "default".to_string(),
+        };
+        let encoded = msg.encode().unwrap();
+        let mut frame = encoded.clone();
+        assert_eq!(msg, decode_frame(&mut frame).unwrap());
+        assert_eq!(encoded, frame);
+    }
+}
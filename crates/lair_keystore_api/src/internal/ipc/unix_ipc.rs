@@ -58,17 +58,92 @@ impl tokio::io::AsyncWrite for IpcWrite {
     }
 }
 
+/// The address of the unix socket of a connection url.
+enum SocketAddr<'a> {
+    /// a socket file
+    Path(&'a std::path::Path),
+    /// a linux abstract namespace socket, by name
+    Abstract(&'a [u8]),
+}
+
+/// The unix socket address for a connection url,
+/// or an error if this transport is not supported here.
+fn socket_addr(url: &LairConnectionUrl) -> LairResult<SocketAddr<'_>> {
+    match url.endpoint() {
+        LairConnectionEndpoint::UnixSocket(path) => Ok(SocketAddr::Path(path)),
+        LairConnectionEndpoint::UnixAbstract(name) => {
+            Ok(SocketAddr::Abstract(name))
+        }
+        _ => Err(format!("unsupported connection url: {}", url).into()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_addr(
+    name: &[u8],
+) -> std::io::Result<std::os::unix::net::SocketAddr> {
+    use std::os::linux::net::SocketAddrExt;
+    std::os::unix::net::SocketAddr::from_abstract_name(name)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abstract_addr(
+    _name: &[u8],
+) -> std::io::Result<std::os::unix::net::SocketAddr> {
+    // such urls do not parse here
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract unix sockets are only supported on linux",
+    ))
+}
+
+async fn connect_abstract(
+    name: Vec<u8>,
+) -> std::io::Result<tokio::net::UnixStream> {
+    // a blocking connect, it waits while the server's backlog is full
+    let socket = tokio::task::spawn_blocking(move || {
+        std::os::unix::net::UnixStream::connect_addr(&abstract_addr(&name)?)
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+    socket.set_nonblocking(true)?;
+    tokio::net::UnixStream::from_std(socket)
+}
+
+/// Error unless the peer of `socket` runs as `uid`.
+/// Abstract sockets have no file permissions, any process in the
+/// network namespace may connect to one, or bind its name first.
+fn check_peer_uid(socket: &tokio::net::UnixStream, uid: u32) -> LairResult<()> {
+    let cred = socket.peer_cred().map_err(LairError::other)?;
+    if cred.uid != uid {
+        return Err(LairError::Forbidden(format!(
+            "unix socket peer runs as uid {}, not uid {}",
+            cred.uid, uid,
+        )));
+    }
+    Ok(())
+}
+
+fn current_uid() -> u32 {
+    // safe: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
 pub(crate) async fn ipc_connect(
     config: Arc<Config>,
 ) -> LairResult<(IpcRead, IpcWrite)> {
-    let socket = tokio::net::UnixStream::connect(config.get_socket_path())
-        .await
-        .map_err(|e| {
-            LairError::IpcClientConnectError(
-                config.get_socket_path().to_string_lossy().to_string(),
-                e.into(),
-            )
+    let url = config.get_connection_url();
+    let socket = match socket_addr(url)? {
+        SocketAddr::Path(path) => tokio::net::UnixStream::connect(path).await,
+        SocketAddr::Abstract(name) => connect_abstract(name.to_vec()).await,
+    }
+    .map_err(|e| LairError::IpcClientConnectError(url.to_string(), e.into()))?;
+    // never hand a token, or a passphrase, to a server of another user
+    if let SocketAddr::Abstract(_) = socket_addr(url)? {
+        check_peer_uid(&socket, current_uid()).map_err(|e| {
+            LairError::IpcClientConnectError(url.to_string(), e.into())
         })?;
+    }
     //let (read_half, write_half) = socket.into_split();
     let (read_half, write_half) = tokio::io::split(socket);
     Ok((
@@ -80,22 +155,95 @@ pub(crate) async fn ipc_connect(
     ))
 }
 
+/// Both ends of an unnamed, connected socket pair,
+/// for in-process connections.
+pub(crate) fn ipc_pair(
+    config: Arc<Config>,
+) -> LairResult<((IpcRead, IpcWrite), (IpcRead, IpcWrite))> {
+    let (a, b) = tokio::net::UnixStream::pair().map_err(LairError::other)?;
+    let halves = |con| {
+        let (read_half, write_half) = tokio::io::split(con);
+        (
+            IpcRead {
+                config: config.clone(),
+                read_half,
+            },
+            IpcWrite {
+                config: config.clone(),
+                write_half,
+            },
+        )
+    };
+    Ok((halves(a), halves(b)))
+}
+
 #[allow(dead_code)]
 pub(crate) struct IpcServer {
     config: Arc<Config>,
+    url: LairConnectionUrl,
     socket: tokio::net::UnixListener,
 }
 
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        // leave no stale socket behind
+        if let Ok(SocketAddr::Path(path)) = socket_addr(&self.url) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 impl IpcServer {
-    pub fn bind(config: Arc<Config>) -> LairResult<Self> {
-        let _ = std::fs::remove_file(config.get_socket_path());
-        let socket = tokio::net::UnixListener::bind(config.get_socket_path())
-            .map_err(LairError::other)?;
-        Ok(Self { config, socket })
+    pub fn bind(
+        config: Arc<Config>,
+        url: LairConnectionUrl,
+    ) -> LairResult<Self> {
+        let socket = match socket_addr(&url)? {
+            SocketAddr::Path(path) => {
+                crate::internal::util::bind_private_unix_socket(
+                    path,
+                    config.get_socket_mode(),
+                )?
+            }
+            // there is no file to give the socket mode, peers are checked
+            // as they are accepted instead
+            SocketAddr::Abstract(name) => abstract_addr(name)
+                .and_then(|addr| {
+                    std::os::unix::net::UnixListener::bind_addr(&addr)
+                })
+                .map_err(|e| {
+                    LairError::other(format!("failed to bind {}: {}", url, e))
+                })?,
+        };
+        socket.set_nonblocking(true).map_err(LairError::other)?;
+        let socket =
+            tokio::net::UnixListener::from_std(socket).map_err(|e| {
+                LairError::other(format!("failed to bind {}: {}", url, e))
+            })?;
+        Ok(Self {
+            config,
+            url,
+            socket,
+        })
+    }
+
+    /// The url this server is bound to.
+    pub fn url(&self) -> &LairConnectionUrl {
+        &self.url
     }
 
     pub async fn accept(&mut self) -> LairResult<(IpcRead, IpcWrite)> {
-        let (con, _) = self.socket.accept().await.map_err(LairError::other)?;
+        let con = loop {
+            let (con, _) =
+                self.socket.accept().await.map_err(LairError::other)?;
+            if let SocketAddr::Abstract(_) = socket_addr(&self.url)? {
+                if let Err(e) = check_peer_uid(&con, current_uid()) {
+                    warn!("refusing a connection to {}: {}", self.url, e);
+                    continue;
+                }
+            }
+            break con;
+        };
         //let (read_half, write_half) = con.into_split();
         let (read_half, write_half) = tokio::io::split(con);
         Ok((
@@ -110,3 +258,19 @@ impl IpcServer {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_checks_the_peer_uid() {
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        check_peer_uid(&a, current_uid()).unwrap();
+        check_peer_uid(&b, current_uid()).unwrap();
+        assert!(matches!(
+            check_peer_uid(&a, current_uid().wrapping_add(1)),
+            Err(LairError::Forbidden(_)),
+        ));
+    }
+}
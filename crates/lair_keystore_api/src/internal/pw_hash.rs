@@ -0,0 +1,261 @@
+//! Argon2id Password Hashing Utilities
+//!
+//! The pepper is passed to argon2id as its secret key input, so
+//! hashes are standard PHC strings, but can only be verified
+//! by a keystore holding the same pepper.
+
+use crate::*;
+use derive_more::*;
+
+use actor::PwHashOptions;
+use internal::entropy::EntropySourceHandle;
+
+/// The 32 byte secret password hashing pepper.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct PwPepper(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for PwPepper {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+const SALT_BYTES: usize = 16;
+const HASH_BYTES: u32 = 32;
+
+/// Generate a new random password hashing pepper.
+pub async fn pw_pepper_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntryPwPepper> {
+    let mut pepper = vec![0; 32];
+    entropy.fill(&mut pepper)?;
+    Ok(entry::EntryPwPepper {
+        pepper: pepper.into(),
+    })
+}
+
+/// Hash `password` with argon2id, keyed with `pepper`.
+/// Runs on the tokio blocking pool, at high memory costs
+/// a single hash would otherwise stall a shared rayon thread.
+pub async fn pw_hash(
+    entropy: EntropySourceHandle,
+    pepper: PwPepper,
+    password: Arc<Vec<u8>>,
+    options: PwHashOptions,
+) -> LairResult<String> {
+    options.check()?;
+    internal::deadline::check_deadline()?;
+    internal::cancel::check_cancelled()?;
+    let mut salt = [0; SALT_BYTES];
+    entropy.fill(&mut salt)?;
+    tokio::task::spawn_blocking(move || {
+        let config = argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            version: argon2::Version::Version13,
+            mem_cost: options.mem_cost_kib,
+            time_cost: options.time_cost,
+            lanes: options.lanes,
+            thread_mode: argon2::ThreadMode::Sequential,
+            secret: &pepper,
+            ad: &[],
+            hash_length: HASH_BYTES,
+        };
+        argon2::hash_encoded(&password, &salt, &config)
+            .map_err(LairError::other)
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+/// Verify `password` against a `pw_hash` result, keyed with `pepper`.
+/// The hash comparison is constant time.
+pub async fn pw_verify(
+    pepper: PwPepper,
+    password: Arc<Vec<u8>>,
+    hash: String,
+) -> LairResult<bool> {
+    // bound the work a supplied hash can request
+    // before handing it to argon2
+    parse_pw_hash_options(&hash)?.check()?;
+    internal::deadline::check_deadline()?;
+    internal::cancel::check_cancelled()?;
+    tokio::task::spawn_blocking(move || {
+        argon2::verify_encoded_ext(&hash, &password, &pepper, &[])
+            .map_err(LairError::other)
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+/// Parse the parameters out of an argon2id PHC string,
+/// rejecting other variants / versions and non-32-byte hashes.
+pub fn parse_pw_hash_options(hash: &str) -> LairResult<PwHashOptions> {
+    let malformed = || LairError::from("malformed argon2id pw hash");
+    let parts = hash.split('$').collect::<Vec<_>>();
+    if parts.len() != 6
+        || !parts[0].is_empty()
+        || parts[1] != "argon2id"
+        || parts[2] != "v=19"
+    {
+        return Err(malformed());
+    }
+    let mut params = parts[3].split(',').map(|p| {
+        let mut kv = p.splitn(2, '=');
+        match (kv.next(), kv.next().map(str::parse::<u32>)) {
+            (Some(k), Some(Ok(v))) => Ok((k, v)),
+            _ => Err(malformed()),
+        }
+    });
+    let mut next_param = |name| match params.next() {
+        Some(Ok((k, v))) if k == name => Ok(v),
+        _ => Err(malformed()),
+    };
+    let options = PwHashOptions {
+        mem_cost_kib: next_param("m")?,
+        time_cost: next_param("t")?,
+        lanes: next_param("p")?,
+    };
+    if params.next().is_some() {
+        return Err(malformed());
+    }
+    // unpadded base64 of a 32 byte hash
+    if parts[4].is_empty() || parts[5].len() != 43 {
+        return Err(malformed());
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actor::*;
+
+    fn test_options() -> PwHashOptions {
+        PwHashOptions {
+            mem_cost_kib: 64,
+            time_cost: 1,
+            lanes: 1,
+        }
+    }
+
+    fn test_pepper(b: u8) -> PwPepper {
+        vec![b; 32].into()
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_hash_and_verify() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let password = Arc::new(b"correct horse".to_vec());
+        let hash = pw_hash(
+            entropy.clone(),
+            test_pepper(1),
+            password.clone(),
+            test_options(),
+        )
+        .await
+        .unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert_eq!(test_options(), parse_pw_hash_options(&hash).unwrap());
+
+        // salted, hashing again gives a different string
+        let hash2 =
+            pw_hash(entropy, test_pepper(1), password.clone(), test_options())
+                .await
+                .unwrap();
+        assert_ne!(hash, hash2);
+
+        assert!(pw_verify(test_pepper(1), password.clone(), hash.clone())
+            .await
+            .unwrap());
+        assert!(pw_verify(test_pepper(1), password.clone(), hash2)
+            .await
+            .unwrap());
+
+        // wrong password
+        assert!(!pw_verify(
+            test_pepper(1),
+            Arc::new(b"correct horse!".to_vec()),
+            hash.clone()
+        )
+        .await
+        .unwrap());
+
+        // wrong pepper
+        assert!(!pw_verify(test_pepper(2), password.clone(), hash.clone())
+            .await
+            .unwrap());
+
+        // an unpeppered hash of the same password does not verify
+        let unpeppered =
+            argon2::verify_encoded(&hash, &password).unwrap_or(false);
+        assert!(!unpeppered);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_out_of_bounds_options() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let password = Arc::new(b"pw".to_vec());
+        for f in &[
+            (|o: &mut PwHashOptions| o.lanes = 0) as fn(&mut PwHashOptions),
+            |o| o.lanes = MAX_PW_HASH_LANES + 1,
+            |o| o.time_cost = 0,
+            |o| o.time_cost = MAX_PW_HASH_TIME_COST + 1,
+            |o| o.mem_cost_kib = 7,
+            |o| o.mem_cost_kib = MAX_PW_HASH_MEM_COST_KIB + 1,
+        ] {
+            let mut options = test_options();
+            f(&mut options);
+            assert!(pw_hash(
+                entropy.clone(),
+                test_pepper(1),
+                password.clone(),
+                options
+            )
+            .await
+            .is_err());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_malformed_hashes() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let password = Arc::new(b"pw".to_vec());
+        let hash =
+            pw_hash(entropy, test_pepper(1), password.clone(), test_options())
+                .await
+                .unwrap();
+        let parts = hash.split('$').collect::<Vec<_>>();
+
+        for bad in &[
+            "".to_string(),
+            "not a hash".to_string(),
+            hash.replace("argon2id", "argon2i"),
+            hash.replace("v=19", "v=16"),
+            hash.replace("m=64,t=1,p=1", "t=1,m=64,p=1"),
+            hash.replace("m=64,t=1,p=1", "m=64,t=1"),
+            hash.replace("m=64,t=1,p=1", "m=64,t=1,p=1,x=1"),
+            hash.replace("m=64", "m=big"),
+            // demands more work than the options bounds allow
+            hash.replace("m=64", "m=4294967295"),
+            hash.replace("t=1", "t=1000"),
+            format!("{}$", hash),
+            hash[..hash.len() - 1].to_string(),
+            format!("$argon2id$v=19${}$${}", parts[3], parts[5]),
+        ] {
+            assert!(
+                pw_verify(test_pepper(1), password.clone(), bad.clone())
+                    .await
+                    .is_err(),
+                "expected error for {:?}",
+                bad
+            );
+        }
+
+        // well-formed, but not valid base64
+        let mut bad = hash.clone();
+        bad.replace_range(hash.len() - 43..hash.len() - 42, "!");
+        assert!(pw_verify(test_pepper(1), password, bad).await.is_err());
+    }
+}
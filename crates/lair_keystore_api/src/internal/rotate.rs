@@ -0,0 +1,101 @@
+//! Sign Ed25519 Keypair Rotation Utilities
+
+use crate::*;
+
+use actor::{RotationRecord, SignEd25519PubKey};
+use internal::sign_ed25519;
+
+/// The delegation from `old_pub_key` to `new_pub_key`, made at the
+/// unix time `timestamp` in seconds, with both signatures still empty.
+/// Sign its `signed_bytes` with each keypair, or use `sign_rotation`
+/// for software keypairs.
+pub fn unsigned_rotation_record(
+    old_pub_key: SignEd25519PubKey,
+    new_pub_key: SignEd25519PubKey,
+    timestamp: u64,
+) -> RotationRecord {
+    RotationRecord {
+        old_pub_key,
+        new_pub_key,
+        timestamp,
+        old_signature: Vec::new().into(),
+        new_signature: Vec::new().into(),
+    }
+}
+
+/// Sign the delegation from the `old` keypair to the `new` one,
+/// made at `timestamp`, with both.
+pub async fn sign_rotation(
+    old: &entry::EntrySignEd25519,
+    new: &entry::EntrySignEd25519,
+    timestamp: u64,
+) -> LairResult<RotationRecord> {
+    let mut record = unsigned_rotation_record(
+        old.pub_key.clone(),
+        new.pub_key.clone(),
+        timestamp,
+    );
+    let signed = Arc::new(record.signed_bytes());
+    record.old_signature =
+        sign_ed25519::sign_ed25519(old.priv_key.clone(), signed.clone())
+            .await?;
+    record.new_signature =
+        sign_ed25519::sign_ed25519(new.priv_key.clone(), signed).await?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use internal::sign_ed25519::sign_ed25519_keypair_new_from_entropy;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_and_verify_rotations() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let old = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let new = sign_ed25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+
+        let record = sign_rotation(&old, &new, 42).await.unwrap();
+        assert_eq!(old.pub_key, record.old_pub_key);
+        assert_eq!(new.pub_key, record.new_pub_key);
+        assert_eq!(42, record.timestamp);
+        assert!(record.verify());
+        assert!(!unsigned_rotation_record(
+            old.pub_key.clone(),
+            new.pub_key.clone(),
+            42
+        )
+        .verify());
+
+        // altered keys / timestamp
+        let mut bad = record.clone();
+        bad.timestamp += 1;
+        assert!(!bad.verify());
+        let mut bad = record.clone();
+        std::mem::swap(&mut bad.old_pub_key, &mut bad.new_pub_key);
+        assert!(!bad.verify());
+
+        // both keys must have signed
+        let mut bad = record.clone();
+        bad.new_signature = bad.old_signature.clone();
+        assert!(!bad.verify());
+
+        // a third key cannot claim the delegation
+        let other = sign_ed25519_keypair_new_from_entropy(entropy)
+            .await
+            .unwrap();
+        let mut bad = record;
+        bad.new_pub_key = other.pub_key.clone();
+        bad.new_signature = sign_ed25519::sign_ed25519(
+            other.priv_key.clone(),
+            Arc::new(bad.signed_bytes()),
+        )
+        .await
+        .unwrap();
+        assert!(!bad.verify());
+    }
+}
@@ -0,0 +1,117 @@
+//! XSalsa20-Poly1305 secretbox, as libsodium's `crypto_secretbox_easy`,
+//! and the HSalsa20 key derivation used by `crypto_box_beforenm`.
+//! These exist so lair can produce libsodium compatible sealed boxes.
+
+use crate::*;
+use salsa20::cipher::StreamCipher;
+
+/// Byte length of a secretbox key.
+pub const KEY_BYTES: usize = 32;
+
+/// Byte length of a secretbox nonce.
+pub const NONCE_BYTES: usize = 24;
+
+/// Byte length of the poly1305 authentication tag.
+pub const MAC_BYTES: usize = 16;
+
+/// Encrypt `data`, returning `mac || ciphertext`.
+pub fn secretbox_easy(
+    key: &[u8; KEY_BYTES],
+    nonce: &[u8; NONCE_BYTES],
+    data: &[u8],
+) -> Vec<u8> {
+    let (poly_key, mut stream) = xsalsa20_stream(key, nonce);
+    let mut out = vec![0; MAC_BYTES + data.len()];
+    out[MAC_BYTES..].copy_from_slice(data);
+    stream.apply_keystream(&mut out[MAC_BYTES..]);
+    let mac = poly1305(&poly_key, &out[MAC_BYTES..]);
+    out[..MAC_BYTES].copy_from_slice(&mac);
+    out
+}
+
+/// Verify and decrypt `mac || ciphertext`.
+pub fn secretbox_open_easy(
+    key: &[u8; KEY_BYTES],
+    nonce: &[u8; NONCE_BYTES],
+    data: &[u8],
+) -> LairResult<Vec<u8>> {
+    if data.len() < MAC_BYTES {
+        return Err(LairError::AuthenticationFailed);
+    }
+    let (poly_key, mut stream) = xsalsa20_stream(key, nonce);
+    let mac = poly1305(&poly_key, &data[MAC_BYTES..]);
+    if ring::constant_time::verify_slices_are_equal(&mac, &data[..MAC_BYTES])
+        .is_err()
+    {
+        return Err(LairError::AuthenticationFailed);
+    }
+    let mut out = data[MAC_BYTES..].to_vec();
+    stream.apply_keystream(&mut out);
+    Ok(out)
+}
+
+/// HSalsa20 - derive a 32 byte subkey from a key and 16 byte input.
+pub fn hsalsa20(key: &[u8; KEY_BYTES], input: &[u8; 16]) -> [u8; 32] {
+    salsa20::hsalsa::<salsa20::cipher::consts::U10>(key.into(), input.into())
+        .into()
+}
+
+// -- local helpers -- //
+
+/// XSalsa20 keystream. The first 32 bytes of block zero are split off
+/// as the poly1305 key, exactly as `crypto_secretbox` does.
+fn xsalsa20_stream(
+    key: &[u8; KEY_BYTES],
+    nonce: &[u8; NONCE_BYTES],
+) -> ([u8; 32], salsa20::XSalsa20) {
+    use salsa20::cipher::KeyIvInit;
+    let mut stream = salsa20::XSalsa20::new(key.into(), nonce.into());
+    let mut poly_key = [0; 32];
+    stream.apply_keystream(&mut poly_key);
+    (poly_key, stream)
+}
+
+/// One-shot poly1305.
+fn poly1305(key: &[u8; 32], msg: &[u8]) -> [u8; 16] {
+    use poly1305::universal_hash::KeyInit;
+    poly1305::Poly1305::new(key.into())
+        .compute_unpadded(msg)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_vectors::{from_hex as hex, SECRETBOX};
+
+    fn fixture() -> ([u8; 32], [u8; 24], Vec<u8>) {
+        let mut key = [0; 32];
+        let mut nonce = [0; 24];
+        key.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        nonce.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        (key, nonce, (0..200).collect())
+    }
+
+    #[test]
+    fn it_matches_libsodium_secretbox() {
+        let (key, nonce, msg) = fixture();
+        let boxed = secretbox_easy(&key, &nonce, &msg);
+        assert_eq!(hex(SECRETBOX), boxed);
+        assert_eq!(msg, secretbox_open_easy(&key, &nonce, &boxed).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_tampered_secretbox() {
+        let (key, nonce, msg) = fixture();
+        let boxed = secretbox_easy(&key, &nonce, &msg);
+        for i in &[0, MAC_BYTES, boxed.len() - 1] {
+            let mut bad = boxed.clone();
+            bad[*i] ^= 1;
+            assert!(matches!(
+                secretbox_open_easy(&key, &nonce, &bad),
+                Err(LairError::AuthenticationFailed),
+            ));
+        }
+        assert!(secretbox_open_easy(&key, &nonce, &boxed[..8]).is_err());
+    }
+}
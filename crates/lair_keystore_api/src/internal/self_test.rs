@@ -0,0 +1,210 @@
+//! Known-answer self-test of the crypto primitives lair depends on.
+//!
+//! Runs the vectors from `crate::test_vectors` through the same code
+//! paths lair uses, so a build that miscompiled any of them refuses to
+//! start instead of quietly producing bad keys, digests or boxes.
+
+use crate::*;
+use test_vectors::*;
+
+/// The primitives checked by the self-test, in the order they run.
+pub const SELF_TEST_PRIMITIVES: &[&str] =
+    &["ed25519", "ed25519ph", "blake2b", "secretbox", "argon2id"];
+
+/// Run every known-answer test, returning the outcome of each,
+/// named as in `SELF_TEST_PRIMITIVES`.
+pub async fn self_test_report() -> Vec<(&'static str, LairResult<()>)> {
+    vec![
+        ("ed25519", self_test_ed25519().await),
+        ("ed25519ph", self_test_ed25519ph().await),
+        ("blake2b", self_test_blake2b()),
+        ("secretbox", self_test_secretbox()),
+        ("argon2id", self_test_argon2id().await),
+    ]
+}
+
+/// Run every known-answer test, failing with the first primitive
+/// that does not produce the expected output.
+pub async fn self_test() -> LairResult<()> {
+    for (primitive, res) in self_test_report().await {
+        if let Err(err) = res {
+            return Err(format!(
+                "crypto self-test failed for {}: {}",
+                primitive, err
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn check(what: &str, expected: &[u8], actual: &[u8]) -> LairResult<()> {
+    if expected != actual {
+        return Err(format!("{} does not match the known answer", what).into());
+    }
+    Ok(())
+}
+
+async fn self_test_ed25519() -> LairResult<()> {
+    for vector in ED25519_VECTORS {
+        let secret_key = from_hex(vector.secret_key);
+        let message = Arc::new(from_hex(vector.message));
+        let expected: actor::SignEd25519Signature =
+            from_hex(vector.signature).into();
+
+        check(
+            "public key",
+            &from_hex(vector.public_key),
+            &ed25519_backend::pub_key_from_seed(&secret_key)?,
+        )?;
+        let pub_key: actor::SignEd25519PubKey =
+            from_hex(vector.public_key).into();
+
+        let signature = internal::sign_ed25519::sign_ed25519(
+            secret_key.into(),
+            message.clone(),
+        )
+        .await?;
+        check("signature", &expected, &signature)?;
+
+        if !internal::sign_ed25519::sign_ed25519_verify(
+            pub_key.clone(),
+            message.clone(),
+            expected.clone(),
+        )
+        .await?
+            || !pub_key.verify_pure(&message, &expected)
+        {
+            return Err("valid signature failed to verify".into());
+        }
+
+        let mut bad = (*expected.0).clone();
+        bad[0] ^= 1;
+        let bad: actor::SignEd25519Signature = bad.into();
+        if internal::sign_ed25519::sign_ed25519_verify(
+            pub_key.clone(),
+            message.clone(),
+            bad.clone(),
+        )
+        .await?
+            || pub_key.verify_pure(&message, &bad)
+        {
+            return Err("invalid signature verified".into());
+        }
+    }
+    Ok(())
+}
+
+async fn self_test_ed25519ph() -> LairResult<()> {
+    let vector = ED25519PH_VECTOR;
+    let mut prehash = [0; ed25519ph::PREHASH_LEN];
+    prehash.copy_from_slice(
+        ring::digest::digest(&ring::digest::SHA512, &from_hex(vector.message))
+            .as_ref(),
+    );
+    let expected: actor::SignEd25519Signature =
+        from_hex(vector.signature).into();
+    let pub_key: actor::SignEd25519PubKey = from_hex(vector.public_key).into();
+
+    let signature = internal::sign_ed25519::sign_ed25519ph(
+        from_hex(vector.secret_key).into(),
+        Arc::new(Vec::new()),
+        prehash,
+    )
+    .await?;
+    check("signature", &expected, &signature)?;
+    if !pub_key.verify_ph(b"", &prehash, &expected) {
+        return Err("valid signature failed to verify".into());
+    }
+
+    let mut bad = (*expected.0).clone();
+    bad[0] ^= 1;
+    if pub_key.verify_ph(b"", &prehash, &bad.into()) {
+        return Err("invalid signature verified".into());
+    }
+    Ok(())
+}
+
+fn self_test_blake2b() -> LairResult<()> {
+    check(
+        "unkeyed digest",
+        &from_hex(BLAKE2B_ABC),
+        &hash::blake2b(b"abc", 64, None)?,
+    )?;
+    let key = (0..64).collect::<Vec<u8>>();
+    check(
+        "keyed digest",
+        &from_hex(BLAKE2B_KEYED_EMPTY),
+        &hash::blake2b(b"", 64, Some(&key))?,
+    )
+}
+
+fn self_test_secretbox() -> LairResult<()> {
+    use internal::secretbox::*;
+    let mut key = [0; KEY_BYTES];
+    let mut nonce = [0; NONCE_BYTES];
+    key.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+    nonce.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+    let message = (0..200).collect::<Vec<u8>>();
+
+    let expected = from_hex(SECRETBOX);
+    check("box", &expected, &secretbox_easy(&key, &nonce, &message))?;
+    check(
+        "opened box",
+        &message,
+        &secretbox_open_easy(&key, &nonce, &expected)?,
+    )?;
+
+    let mut bad = expected;
+    bad[0] ^= 1;
+    if secretbox_open_easy(&key, &nonce, &bad).is_ok() {
+        return Err("tampered box opened".into());
+    }
+    Ok(())
+}
+
+async fn self_test_argon2id() -> LairResult<()> {
+    tokio::task::spawn_blocking(|| {
+        let config = argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            version: argon2::Version::Version13,
+            mem_cost: 32,
+            time_cost: 3,
+            lanes: 4,
+            thread_mode: argon2::ThreadMode::Sequential,
+            secret: &[0x03; 8],
+            ad: &[0x04; 12],
+            hash_length: 32,
+        };
+        let tag = argon2::hash_raw(&[0x01; 32], &[0x02; 16], &config)
+            .map_err(LairError::other)?;
+        check("tag", &from_hex(ARGON2ID_TAG), &tag)
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_passes_self_test() {
+        let report = self_test_report().await;
+        assert_eq!(
+            SELF_TEST_PRIMITIVES,
+            &report.iter().map(|(p, _)| *p).collect::<Vec<_>>()[..],
+        );
+        for (primitive, res) in report {
+            assert!(res.is_ok(), "{}: {:?}", primitive, res);
+        }
+        self_test().await.unwrap();
+    }
+
+    #[test]
+    fn it_reports_mismatches() {
+        assert!(check("digest", &[1, 2, 3], &[1, 2, 3]).is_ok());
+        let err = check("digest", &[1, 2, 3], &[1, 2, 4]).unwrap_err();
+        assert!(err.to_string().contains("digest"));
+    }
+}
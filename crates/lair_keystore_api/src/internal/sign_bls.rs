@@ -0,0 +1,78 @@
+//! Bls12-381 Signature Utilities
+
+use crate::*;
+use derive_more::*;
+
+/// The 32 byte big-endian bls12-381 private key scalar.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignBlsPrivKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignBlsPrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+use actor::{SignBlsPubKey, SignBlsSignature};
+use internal::entropy::EntropySourceHandle;
+
+/// Generate a new random bls signature keypair,
+/// running KeyGen on 32 bytes of entropy.
+pub async fn sign_bls_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntrySignBls> {
+    rayon_exec(move || {
+        let mut ikm = [0; 32];
+        entropy.fill(&mut ikm)?;
+        let priv_key = bls::key_gen(&ikm, &[])?;
+        let pub_key =
+            SignBlsPubKey::from_bytes(&bls::pub_key_from_priv_key(&priv_key)?)?;
+        Ok(entry::EntrySignBls {
+            priv_key: priv_key.to_vec().into(),
+            pub_key,
+        })
+    })
+    .await
+}
+
+/// Generate a bls signature for given priv key / message,
+/// under the domain separation tag `dst`.
+pub async fn sign_bls(
+    priv_key: SignBlsPrivKey,
+    message: Arc<Vec<u8>>,
+    dst: Vec<u8>,
+) -> LairResult<SignBlsSignature> {
+    rayon_exec(move || Ok(bls::sign(&priv_key, &message, &dst)?.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_and_verify() {
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+
+        let entry::EntrySignBls { priv_key, pub_key } =
+            sign_bls_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await
+            .unwrap();
+
+        let sig = sign_bls(priv_key, msg.clone(), bls::DST_POP.to_vec())
+            .await
+            .unwrap();
+        assert!(pub_key.verify(&msg, &sig, bls::DST_POP));
+        assert!(!pub_key.verify(&msg, &sig, bls::DST_BASIC));
+        assert!(!pub_key.verify(&[0, 1, 2], &sig, bls::DST_POP));
+
+        let agg =
+            SignBlsSignature::aggregate_signatures(vec![sig.clone()]).unwrap();
+        assert!(
+            agg.aggregate_verify(vec![(pub_key, msg.to_vec())], bls::DST_POP)
+        );
+    }
+}
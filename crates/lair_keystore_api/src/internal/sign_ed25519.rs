@@ -1,5 +1,5 @@
 //! Ed25519 Signature Utilities
-//! NOTE - temporarily using RING crate until we switch to sodoken
+//! on the backend the `ed25519_*` features select.
 
 use crate::*;
 use derive_more::*;
@@ -17,40 +17,55 @@ impl From<Vec<u8>> for SignEd25519PrivKey {
 }
 
 use actor::{SignEd25519PubKey, SignEd25519Signature};
+use internal::entropy::EntropySourceHandle;
 
 /// Generate a new random ed25519 signature keypair.
 pub async fn sign_ed25519_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
 ) -> LairResult<entry::EntrySignEd25519> {
     rayon_exec(move || {
-        let sys_rand = ring::rand::SystemRandom::new();
-        let mut priv_key = vec![0; 32];
-        ring::rand::SecureRandom::fill(&sys_rand, &mut priv_key)
-            .map_err(|e| format!("{:?}", e))?;
-        let keypair =
-            ring::signature::Ed25519KeyPair::from_seed_unchecked(&priv_key)
-                .map_err(|e| format!("{:?}", e))?;
-        let pub_key = ring::signature::KeyPair::public_key(&keypair)
-            .as_ref()
-            .to_vec();
-        Ok(entry::EntrySignEd25519 {
-            priv_key: priv_key.into(),
-            pub_key: pub_key.into(),
-        })
+        let mut seed = [0; 32];
+        entropy.fill(&mut seed)?;
+        keypair_from_seed(seed)
     })
     .await
 }
 
+/// Derive the ed25519 signature keypair of a 32 byte seed.
+pub async fn sign_ed25519_keypair_new_from_seed(
+    seed: [u8; 32],
+) -> LairResult<entry::EntrySignEd25519> {
+    rayon_exec(move || keypair_from_seed(seed)).await
+}
+
+fn keypair_from_seed(seed: [u8; 32]) -> LairResult<entry::EntrySignEd25519> {
+    let pub_key = ed25519_backend::pub_key_from_seed(&seed)?;
+    Ok(entry::EntrySignEd25519 {
+        priv_key: seed.to_vec().into(),
+        pub_key: pub_key.into(),
+        policy: Default::default(),
+    })
+}
+
 /// Generate detached signature bytes for given ed25519 priv key / message.
 pub async fn sign_ed25519(
     priv_key: SignEd25519PrivKey,
     message: Arc<Vec<u8>>,
+) -> LairResult<SignEd25519Signature> {
+    rayon_exec(move || Ok(ed25519_backend::sign(&priv_key, &message)?.into()))
+        .await
+}
+
+/// Ed25519ph sign the 64 byte sha-512 `prehash` of a message
+/// under `context`, see `ed25519ph`.
+pub async fn sign_ed25519ph(
+    priv_key: SignEd25519PrivKey,
+    context: Arc<Vec<u8>>,
+    prehash: [u8; ed25519ph::PREHASH_LEN],
 ) -> LairResult<SignEd25519Signature> {
     rayon_exec(move || {
-        let keypair =
-            ring::signature::Ed25519KeyPair::from_seed_unchecked(&priv_key)
-                .map_err(|e| format!("{:?}", e))?;
-        let signature = keypair.sign(&message);
-        Ok(signature.as_ref().to_vec().into())
+        let signature = ed25519ph::sign(&priv_key, &context, &prehash)?;
+        Ok(signature.to_vec().into())
     })
     .await
 }
@@ -62,15 +77,53 @@ pub async fn sign_ed25519_verify(
     signature: SignEd25519Signature,
 ) -> LairResult<bool> {
     rayon_exec(move || {
-        let pub_key = ring::signature::UnparsedPublicKey::new(
-            &ring::signature::ED25519,
-            &**pub_key,
-        );
-        Ok(pub_key.verify(&message, &signature).is_ok())
+        Ok(sign_ed25519_verify_sync(&pub_key, &message, &signature))
     })
     .await
 }
 
+/// Verify signature on given message with given public key,
+/// synchronously, on borrowed data.
+pub fn sign_ed25519_verify_sync(
+    pub_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    ed25519_backend::verify(pub_key, message, signature)
+}
+
+/// The seed of the ed25519 keypair `entry`, which
+/// `sign_ed25519_keypair_new_from_seed` derives it from again.
+/// Hardware backed keypairs, and keypairs created not exportable,
+/// fail with `LairError::ExportForbidden`.
+pub fn sign_ed25519_export_seed(
+    entry: &entry::LairEntry,
+) -> LairResult<[u8; 32]> {
+    match entry {
+        entry::LairEntry::SignEd25519(e) if !e.policy.exportable => {
+            Err(LairError::ExportForbidden(format!(
+                "ed25519 keypair {:?} is not exportable",
+                e.pub_key,
+            )))
+        }
+        entry::LairEntry::SignEd25519(e) => {
+            let mut seed = [0; 32];
+            if e.priv_key.len() != seed.len() {
+                return Err("invalid ed25519 private key length".into());
+            }
+            seed.copy_from_slice(&e.priv_key);
+            Ok(seed)
+        }
+        entry::LairEntry::SignEd25519Hardware(e) => {
+            Err(LairError::ExportForbidden(format!(
+                "ed25519 keypair {:?} is held in a hardware token",
+                e.pub_key,
+            )))
+        }
+        _ => Err("invalid entry type".into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,8 +132,13 @@ mod tests {
     async fn it_can_sign_and_verify() {
         let msg = Arc::new(vec![0, 1, 2, 3]);
 
-        let entry::EntrySignEd25519 { priv_key, pub_key } =
-            sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let entry::EntrySignEd25519 {
+            priv_key, pub_key, ..
+        } = sign_ed25519_keypair_new_from_entropy(
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await
+        .unwrap();
 
         let sig = sign_ed25519(priv_key.clone(), msg.clone()).await.unwrap();
 
@@ -101,4 +159,76 @@ mod tests {
         .await
         .unwrap());
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_matches_rfc8032_vectors() {
+        use test_vectors::from_hex;
+        for vector in test_vectors::ED25519_VECTORS {
+            let message = Arc::new(from_hex(vector.message));
+            let signature = sign_ed25519(
+                from_hex(vector.secret_key).into(),
+                message.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(from_hex(vector.signature), *signature.0);
+            assert!(sign_ed25519_verify(
+                from_hex(vector.public_key).into(),
+                message,
+                signature,
+            )
+            .await
+            .unwrap());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_matches_the_timestamped_vector() {
+        use test_vectors::from_hex;
+        let v = test_vectors::TIMESTAMPED_ED25519_VECTOR;
+        let signature = sign_ed25519(
+            from_hex(v.vector.secret_key).into(),
+            Arc::new(actor::timestamped_message(
+                v.timestamp_millis,
+                &from_hex(v.vector.message),
+            )),
+        )
+        .await
+        .unwrap();
+        assert_eq!(from_hex(v.vector.signature), *signature.0);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_generate_deterministic_keypairs() {
+        let gen = |seed| async move {
+            sign_ed25519_keypair_new_from_entropy(
+                internal::entropy::DangerSeededEntropy::danger_new_from_seed(
+                    seed,
+                ),
+            )
+            .await
+            .unwrap()
+            .pub_key
+        };
+
+        let pub_key = gen(42).await;
+        assert_eq!(pub_key, gen(42).await);
+        assert_ne!(pub_key, gen(43).await);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_exports_the_seed_of_exportable_keypairs() {
+        let keypair = sign_ed25519_keypair_new_from_seed([0x42; 32])
+            .await
+            .unwrap();
+        let seed = sign_ed25519_export_seed(&keypair.clone().into()).unwrap();
+        assert_eq!([0x42; 32], seed);
+
+        let mut keypair = keypair;
+        keypair.policy.exportable = false;
+        assert!(matches!(
+            sign_ed25519_export_seed(&keypair.into()),
+            Err(LairError::ExportForbidden(_)),
+        ));
+    }
 }
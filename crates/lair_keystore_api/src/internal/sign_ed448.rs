@@ -0,0 +1,129 @@
+//! Ed448 Signature Utilities
+
+use crate::*;
+use derive_more::*;
+
+/// The 57 byte signature ed448 private key.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignEd448PrivKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignEd448PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+use actor::{SignEd448PubKey, SignEd448Signature};
+use internal::entropy::EntropySourceHandle;
+
+/// The ed448 signing key of a 57 byte priv key.
+pub(crate) fn signing_key(
+    priv_key: &[u8],
+) -> LairResult<ed448_goldilocks::SigningKey> {
+    use std::convert::TryFrom;
+    ed448_goldilocks::SigningKey::try_from(priv_key)
+        .map_err(|e| format!("{:?}", e).into())
+}
+
+fn pub_key_from_priv_key(priv_key: &[u8]) -> LairResult<SignEd448PubKey> {
+    Ok(signing_key(priv_key)?.verifying_key().to_bytes().into())
+}
+
+/// Generate a new random ed448 signature keypair.
+pub async fn sign_ed448_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntrySignEd448> {
+    rayon_exec(move || {
+        let mut priv_key = vec![0; 57];
+        entropy.fill(&mut priv_key)?;
+        let pub_key = pub_key_from_priv_key(&priv_key)?;
+        Ok(entry::EntrySignEd448 {
+            priv_key: priv_key.into(),
+            pub_key,
+        })
+    })
+    .await
+}
+
+/// Generate detached RFC 8032 Ed448 signature bytes, with an empty
+/// context, for given ed448 priv key / message.
+pub async fn sign_ed448(
+    priv_key: SignEd448PrivKey,
+    message: Arc<Vec<u8>>,
+) -> LairResult<SignEd448Signature> {
+    rayon_exec(move || {
+        let signature = signing_key(&priv_key)?.sign_raw(&message);
+        Ok(signature.to_bytes().into())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// RFC 8032 section 7.4, the "Ed448" vectors
+    /// (secret key, public key, message, signature).
+    const RFC8032_VECTORS: &[(&str, &str, &str, &str)] = &[
+        (
+            "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b",
+            "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180",
+            "",
+            "533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4dbb61149f05a7363268c71d95808ff2e652600",
+        ),
+        (
+            "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463afbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e",
+            "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c0866aea01eb00742802b8438ea4cb82169c235160627b4c3a9480",
+            "03",
+            "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f4352541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cbcee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0ff3348ab21aa4adafd1d234441cf807c03a00",
+        ),
+    ];
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_passes_rfc8032_vectors() {
+        for (priv_key, pub_key, message, signature) in RFC8032_VECTORS {
+            let priv_key = hex(priv_key);
+            let pub_key = SignEd448PubKey::from_bytes(&hex(pub_key)).unwrap();
+            let message = Arc::new(hex(message));
+            let signature =
+                SignEd448Signature::from_bytes(&hex(signature)).unwrap();
+
+            assert_eq!(pub_key, pub_key_from_priv_key(&priv_key).unwrap());
+            assert_eq!(
+                signature,
+                sign_ed448(priv_key.into(), message.clone()).await.unwrap(),
+            );
+            assert!(pub_key.verify(&message, &signature));
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_and_verify() {
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+
+        let entry::EntrySignEd448 { priv_key, pub_key } =
+            sign_ed448_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await
+            .unwrap();
+
+        let sig = sign_ed448(priv_key, msg.clone()).await.unwrap();
+        assert!(pub_key.verify(&msg, &sig));
+        assert!(!pub_key.verify(&[0, 1, 2], &sig));
+
+        let mut bad_sig = sig.to_vec();
+        bad_sig[0] ^= 1;
+        let bad_sig = SignEd448Signature::from_bytes(&bad_sig).unwrap();
+        assert!(!pub_key.verify(&msg, &bad_sig));
+    }
+}
@@ -0,0 +1,106 @@
+//! Secp256k1 Signature Utilities
+
+use crate::*;
+use derive_more::*;
+
+/// The 32 byte secp256k1 private key scalar.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignSecp256k1PrivKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignSecp256k1PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+use actor::SignSecp256k1Signature;
+use internal::entropy::EntropySourceHandle;
+
+/// Generate a new random secp256k1 signature keypair.
+pub async fn sign_secp256k1_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntrySignSecp256k1> {
+    rayon_exec(move || {
+        let mut priv_key = vec![0; 32];
+        // out of range scalars have negligible probability, but redraw
+        loop {
+            entropy.fill(&mut priv_key)?;
+            if let Ok(pub_key) = secp256k1::pub_key_from_priv_key(&priv_key) {
+                return Ok(entry::EntrySignSecp256k1 {
+                    priv_key: priv_key.into(),
+                    pub_key: pub_key.into(),
+                });
+            }
+        }
+    })
+    .await
+}
+
+/// Import a secp256k1 signature keypair from its 32 byte private key.
+/// Errors if the key is zero or not less than the curve order.
+pub async fn sign_secp256k1_keypair_new_from_seed(
+    seed: [u8; 32],
+) -> LairResult<entry::EntrySignSecp256k1> {
+    rayon_exec(move || {
+        let pub_key = secp256k1::pub_key_from_priv_key(&seed)?;
+        Ok(entry::EntrySignSecp256k1 {
+            priv_key: seed.to_vec().into(),
+            pub_key: pub_key.into(),
+        })
+    })
+    .await
+}
+
+/// Sign a 32 byte message hash, returning the signature and recovery id.
+pub async fn sign_secp256k1(
+    priv_key: SignSecp256k1PrivKey,
+    message_hash: [u8; 32],
+) -> LairResult<(SignSecp256k1Signature, u8)> {
+    rayon_exec(move || {
+        let (signature, recovery_id) =
+            secp256k1::sign(&priv_key, &message_hash)?;
+        Ok((signature.to_vec().into(), recovery_id))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_and_recover() {
+        let entry = sign_secp256k1_keypair_new_from_entropy(
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await
+        .unwrap();
+        let message_hash = [0x42; 32];
+        let (signature, recovery_id) =
+            sign_secp256k1(entry.priv_key.clone(), message_hash)
+                .await
+                .unwrap();
+        assert!(entry.pub_key.verify(&message_hash, &signature));
+        assert_eq!(
+            entry.pub_key,
+            actor::SignSecp256k1PubKey::recover(
+                &message_hash,
+                &signature,
+                recovery_id
+            )
+            .unwrap(),
+        );
+
+        let mut seed = [0; 32];
+        seed.copy_from_slice(&entry.priv_key);
+        let entry2 = sign_secp256k1_keypair_new_from_seed(seed).await.unwrap();
+        assert_eq!(entry.pub_key, entry2.pub_key);
+
+        assert!(sign_secp256k1_keypair_new_from_seed([0; 32]).await.is_err());
+        assert!(sign_secp256k1_keypair_new_from_seed([0xff; 32])
+            .await
+            .is_err());
+    }
+}
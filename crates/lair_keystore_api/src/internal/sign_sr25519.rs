@@ -0,0 +1,88 @@
+//! Sr25519 Signature Utilities
+
+use crate::*;
+use derive_more::*;
+
+/// The 32 byte sr25519 mini secret key (the substrate "seed"),
+/// expanded on use, see the `sr25519` module.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SignSr25519PrivKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignSr25519PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+use actor::{SignSr25519PubKey, SignSr25519Signature};
+use internal::entropy::EntropySourceHandle;
+
+/// Generate a new random sr25519 signature keypair,
+/// from a 32 byte mini secret key of entropy.
+pub async fn sign_sr25519_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntrySignSr25519> {
+    rayon_exec(move || {
+        let mut seed = [0; 32];
+        entropy.fill(&mut seed)?;
+        keypair_from_seed(seed)
+    })
+    .await
+}
+
+/// Import an sr25519 signature keypair from its 32 byte mini secret key.
+pub async fn sign_sr25519_keypair_new_from_seed(
+    seed: [u8; 32],
+) -> LairResult<entry::EntrySignSr25519> {
+    rayon_exec(move || keypair_from_seed(seed)).await
+}
+
+fn keypair_from_seed(seed: [u8; 32]) -> LairResult<entry::EntrySignSr25519> {
+    let pub_key = sr25519::pub_key_from_seed(&seed)?;
+    Ok(entry::EntrySignSr25519 {
+        priv_key: seed.to_vec().into(),
+        pub_key: SignSr25519PubKey::from(pub_key),
+    })
+}
+
+/// Generate an sr25519 signature for given priv key / message,
+/// in the signing context `context`.
+pub async fn sign_sr25519(
+    priv_key: SignSr25519PrivKey,
+    message: Arc<Vec<u8>>,
+    context: Vec<u8>,
+) -> LairResult<SignSr25519Signature> {
+    rayon_exec(move || Ok(sr25519::sign(&priv_key, &message, &context)?.into()))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_and_verify() {
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+
+        let entry::EntrySignSr25519 { priv_key, pub_key } =
+            sign_sr25519_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await
+            .unwrap();
+
+        let sig = sign_sr25519(priv_key.clone(), msg.clone(), b"ctx".to_vec())
+            .await
+            .unwrap();
+        assert!(pub_key.verify(&msg, &sig, b"ctx"));
+        assert!(!pub_key.verify(&msg, &sig, b"other"));
+        assert!(!pub_key.verify(&[0, 1, 2], &sig, b"ctx"));
+
+        let mut seed = [0; 32];
+        seed.copy_from_slice(&priv_key);
+        let entry = sign_sr25519_keypair_new_from_seed(seed).await.unwrap();
+        assert_eq!(pub_key, entry.pub_key);
+    }
+}
@@ -1,7 +1,8 @@
 //! Utilities for generating / managing TLS certificates and keypairs.
 
 use crate::*;
-use actor::{TlsCertAlg, TlsCertOptions};
+use actor::{Cert, CertDigest, CertSni, TlsCertAlg, TlsCertOptions};
+use internal::entropy::EntropySourceHandle;
 use once_cell::sync::Lazy;
 
 /// The well-known CA keypair in plaintext pem format.
@@ -17,6 +18,29 @@ xkzPZovign1qmbu0vZstKoVLXoGvlA/Kral9txqhSEGqIL7TdbKyMMQz
 /// The well-known pseudo name/id for the well-known lair CA root.
 pub const WK_CA_ID: &str = "aKdjnmYOn1HVc_RwSdxR6qa.aQLW3d5D1nYiSSO2cOrcT7a";
 
+/// The well-known CA signs with deterministic (RFC 6979) ecdsa nonces,
+/// so a cert's bytes depend only on the leaf keypair and sni.
+struct WkCaSigner {
+    signing_key: p256::ecdsa::SigningKey,
+    public_key: Vec<u8>,
+}
+
+impl rcgen::RemoteKeyPair for WkCaSigner {
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, rcgen::RcgenError> {
+        use p256::ecdsa::signature::Signer;
+        let sig: p256::ecdsa::Signature = self.signing_key.sign(msg);
+        Ok(sig.to_der().as_bytes().to_vec())
+    }
+
+    fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        &rcgen::PKCS_ECDSA_P256_SHA256
+    }
+}
+
 /// This doesn't need to be pub... We need the rcgen::Certificate
 /// with the private keys still integrated in order to sign certs.
 static WK_CA_RCGEN_CERT: Lazy<Arc<rcgen::Certificate>> = Lazy::new(|| {
@@ -34,8 +58,20 @@ static WK_CA_RCGEN_CERT: Lazy<Arc<rcgen::Certificate>> = Lazy::new(|| {
     params
         .distinguished_name
         .push(rcgen::DnType::OrganizationName, "Holochain Foundation");
+    use p256::pkcs8::DecodePrivateKey;
+    let secret = p256::SecretKey::from_pkcs8_pem(WK_CA_KEYPAIR_PEM).unwrap();
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    let public_key = secret
+        .public_key()
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec();
+    let signer = WkCaSigner {
+        signing_key: secret.into(),
+        public_key,
+    };
     params.key_pair =
-        Some(rcgen::KeyPair::from_pem(WK_CA_KEYPAIR_PEM).unwrap());
+        Some(rcgen::KeyPair::from_remote(Box::new(signer)).unwrap());
     let cert = rcgen::Certificate::from_params(params).unwrap();
     Arc::new(cert)
 });
@@ -47,12 +83,491 @@ pub static WK_CA_CERT_DER: Lazy<Arc<Vec<u8>>> = Lazy::new(|| {
     Arc::new(cert)
 });
 
+/// The alphabet of generated sni labels, lowercase letters and digits
+/// as `CertSni::parse` requires, 32 of them for an unbiased pick
+/// from 5 bits.
+const SNI_LABEL_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Generate a random 105 bit sni label from the entropy source.
+fn gen_sni_label(entropy: &EntropySourceHandle) -> LairResult<String> {
+    let mut bytes = [0; 21];
+    entropy.fill(&mut bytes)?;
+    Ok(bytes
+        .iter()
+        .map(|b| SNI_LABEL_ALPHABET[(b & 31) as usize] as char)
+        .collect())
+}
+
+/// Generate a pkcs #8 der keypair for the given algorithm,
+/// with the private key drawn from the entropy source.
+fn gen_key_pair(
+    alg: TlsCertAlg,
+    entropy: &EntropySourceHandle,
+) -> LairResult<rcgen::KeyPair> {
+    use p256::pkcs8::EncodePrivateKey;
+
+    let ecdsa = |seed_len, to_der: &dyn Fn(&[u8]) -> Option<Vec<u8>>| {
+        // out-of-range scalars are rejected, draw again if that happens
+        for _ in 0..16 {
+            let mut seed = vec![0; seed_len];
+            entropy.fill(&mut seed)?;
+            if let Some(der) = to_der(&seed) {
+                return Ok(der);
+            }
+        }
+        Err(LairError::from("failed to generate ecdsa keypair"))
+    };
+
+    #[allow(unreachable_patterns)]
+    let der = match alg {
+        TlsCertAlg::PkcsEd25519 => {
+            let mut seed = [0; 32];
+            entropy.fill(&mut seed)?;
+            let pub_key = ed25519_backend::pub_key_from_seed(&seed)?;
+            ed25519_pkcs8_der(&seed, &pub_key)
+        }
+        TlsCertAlg::PkcsEcdsaP256Sha256 => ecdsa(32, &|seed| {
+            let secret = p256::SecretKey::from_slice(seed).ok()?;
+            Some(secret.to_pkcs8_der().ok()?.as_bytes().to_vec())
+        })?,
+        TlsCertAlg::PkcsEcdsaP384Sha384 => ecdsa(48, &|seed| {
+            let secret = p384::SecretKey::from_slice(seed).ok()?;
+            Some(secret.to_pkcs8_der().ok()?.as_bytes().to_vec())
+        })?,
+        _ => return Err(format!("unhandled cert alg: {:?}", alg).into()),
+    };
+
+    use std::convert::TryFrom;
+    rcgen::KeyPair::try_from(der.as_slice()).map_err(LairError::other)
+}
+
+/// The pkcs #8 (v2, with the public key, as generated by ring) der
+/// encoding of an ed25519 signing keypair, usable as a tls cert key.
+pub fn ed25519_priv_key_der(
+    sign: &entry::EntrySignEd25519,
+) -> LairResult<actor::CertPrivKey> {
+    if sign.priv_key.0.len() != 32 || sign.pub_key.0.len() != 32 {
+        return Err("invalid ed25519 keypair".into());
+    }
+    Ok(ed25519_pkcs8_der(&sign.priv_key.0, &sign.pub_key.0).into())
+}
+
+/// `ed25519_priv_key_der` of a 32 byte seed and pub key.
+fn ed25519_pkcs8_der(seed: &[u8], pub_key: &[u8]) -> Vec<u8> {
+    let mut der = vec![
+        0x30, 0x53, 0x02, 0x01, 0x01, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70,
+        0x04, 0x22, 0x04, 0x20,
+    ];
+    der.extend_from_slice(seed);
+    der.extend_from_slice(&[0xa1, 0x23, 0x03, 0x21, 0x00]);
+    der.extend_from_slice(pub_key);
+    der
+}
+
+/// Generate a certificate for the keypair of the sign ed25519 entry at
+/// `sign_key_index`, self signed with that same key. The returned entry
+/// is bound to the signing entry, see `EntryTlsCert::sign_key_index`.
+/// Only `TlsCertAlg::PkcsEd25519` is supported.
+pub async fn tls_cert_self_signed_new_from_sign_key(
+    sign_key_index: actor::KeystoreIndex,
+    sign: entry::EntrySignEd25519,
+    options: TlsCertOptions,
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntryTlsCert> {
+    if options.alg != TlsCertAlg::PkcsEd25519 {
+        return Err(format!(
+            "tls certs bound to a signing key must use {:?}, not {:?}",
+            TlsCertAlg::PkcsEd25519,
+            options.alg
+        )
+        .into());
+    }
+    internal::deadline::check_deadline()?;
+    internal::cancel::check_cancelled()?;
+    rayon_exec(move || {
+        let sni = format!(
+            "a{}a.a{}a",
+            gen_sni_label(&entropy)?,
+            gen_sni_label(&entropy)?,
+        );
+
+        let priv_key_der = ed25519_priv_key_der(&sign)?;
+        use std::convert::TryFrom;
+        let key_pair = rcgen::KeyPair::try_from(&priv_key_der[..])
+            .map_err(LairError::other)?;
+        // ring checks the pub key against the seed, but be explicit,
+        // the cert must carry exactly the stored pub key
+        if key_pair.public_key_raw() != &sign.pub_key.0[..] {
+            return Err("ed25519 pub key does not match priv key".into());
+        }
+
+        let mut params = rcgen::CertificateParams::new(vec![sni.clone()]);
+        params.alg = &rcgen::PKCS_ED25519;
+        params.key_pair = Some(key_pair);
+        params
+            .extended_key_usages
+            .push(rcgen::ExtendedKeyUsagePurpose::Any);
+        params
+            .extended_key_usages
+            .push(rcgen::ExtendedKeyUsagePurpose::ServerAuth);
+        params
+            .extended_key_usages
+            .push(rcgen::ExtendedKeyUsagePurpose::ClientAuth);
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params.distinguished_name.push(
+            rcgen::DnType::CommonName,
+            format!("Lair Self-Signed Cert {}", &sni),
+        );
+
+        let cert = rcgen::Certificate::from_params(params)
+            .map_err(LairError::other)?;
+
+        let cert_der: Cert =
+            cert.serialize_der().map_err(LairError::other)?.into();
+        let cert_digest = CertDigest::compute(&cert_der);
+
+        Ok(entry::EntryTlsCert {
+            sni: sni.into(),
+            priv_key_der,
+            cert_der,
+            cert_digest,
+            sign_key_index: Some(sign_key_index),
+            alg: Some(TlsCertAlg::PkcsEd25519),
+        })
+    })
+    .await
+}
+
+/// A der element's tag, value, whole encoding and the data following it.
+type DerElement<'a> = (u8, &'a [u8], &'a [u8], &'a [u8]);
+
+/// Read one der tag / length / value element off the front of `data`.
+fn der_next(data: &[u8]) -> LairResult<DerElement<'_>> {
+    let malformed = || LairError::from("malformed certificate der");
+    if data.len() < 2 {
+        return Err(malformed());
+    }
+    let tag = data[0];
+    let (len, hdr) = match data[1] {
+        l if l < 0x80 => (l as usize, 2),
+        0x81 => (*data.get(2).ok_or_else(malformed)? as usize, 3),
+        0x82 => {
+            let b = data.get(2..4).ok_or_else(malformed)?;
+            (((b[0] as usize) << 8) | b[1] as usize, 4)
+        }
+        _ => return Err(malformed()),
+    };
+    let end = hdr + len;
+    if data.len() < end {
+        return Err(malformed());
+    }
+    Ok((tag, &data[hdr..end], &data[..end], &data[end..]))
+}
+
+/// The ed25519 algorithm identifier, `SEQUENCE { OID 1.3.101.112 }`.
+const ED25519_ALG_ID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+
+/// Check that `cert_der` is an ed25519 certificate self signed with its
+/// own key, such as those of `tls_cert_self_signed_new_from_sign_key`,
+/// and return that key - e.g. to check a peer's tls cert is bound to
+/// the signing key it claims.
+pub fn tls_cert_self_signed_ed25519_pub_key(
+    cert_der: &[u8],
+) -> LairResult<actor::SignEd25519PubKey> {
+    let (_, cert, _, _) = der_next(cert_der)?;
+    let (_, tbs, tbs_der, rest) = der_next(cert)?;
+    let (_, sig_alg, _, rest) = der_next(rest)?;
+    let (_, sig, _, _) = der_next(rest)?;
+    if sig_alg != ED25519_ALG_ID || sig.len() != 65 || sig[0] != 0 {
+        return Err("certificate is not ed25519 signed".into());
+    }
+
+    // optional explicit version, serial, signature alg,
+    // issuer, validity, subject, then the subject pub key info
+    let mut rest = tbs;
+    if rest.first() == Some(&0xa0) {
+        rest = der_next(rest)?.3;
+    }
+    for _ in 0..5 {
+        rest = der_next(rest)?.3;
+    }
+    let (_, spki, _, _) = der_next(rest)?;
+    let (_, key_alg, _, rest) = der_next(spki)?;
+    let (_, key, _, _) = der_next(rest)?;
+    if key_alg != ED25519_ALG_ID || key.len() != 33 || key[0] != 0 {
+        return Err("certificate key is not ed25519".into());
+    }
+    let pub_key = &key[1..];
+
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, pub_key)
+        .verify(tbs_der, &sig[1..])
+        .map_err(|_| {
+            LairError::from("certificate is not self signed by its key")
+        })?;
+
+    Ok(pub_key.to_vec().into())
+}
+
+/// The ecdsa algorithm identifier prefix, `OID 1.2.840.10045.2.1`,
+/// followed by the named curve.
+const ECDSA_ALG_OID: &[u8] =
+    &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+
+/// The P-256 named curve, `OID 1.2.840.10045.3.1.7`.
+const P256_CURVE_OID: &[u8] =
+    &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+/// The P-384 named curve, `OID 1.3.132.0.34`.
+const P384_CURVE_OID: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+/// The algorithm of the key of a certificate, read from its subject pub
+/// key info. `TlsCertAlg::Unknown` if lair does not generate keys of
+/// that algorithm, or the certificate does not parse.
+pub fn tls_cert_alg(cert_der: &[u8]) -> TlsCertAlg {
+    let key_alg = || -> LairResult<&[u8]> {
+        let (_, cert, _, _) = der_next(cert_der)?;
+        let (_, tbs, _, _) = der_next(cert)?;
+        let mut rest = tbs;
+        if rest.first() == Some(&0xa0) {
+            rest = der_next(rest)?.3;
+        }
+        for _ in 0..5 {
+            rest = der_next(rest)?.3;
+        }
+        let (_, spki, _, _) = der_next(rest)?;
+        Ok(der_next(spki)?.1)
+    };
+    let key_alg = match key_alg() {
+        Ok(key_alg) => key_alg,
+        Err(_) => return TlsCertAlg::Unknown,
+    };
+    if key_alg == ED25519_ALG_ID {
+        TlsCertAlg::PkcsEd25519
+    } else if key_alg == ED448_ALG_ID {
+        TlsCertAlg::PkcsEd448
+    } else if key_alg == [ECDSA_ALG_OID, P256_CURVE_OID].concat() {
+        TlsCertAlg::PkcsEcdsaP256Sha256
+    } else if key_alg == [ECDSA_ALG_OID, P384_CURVE_OID].concat() {
+        TlsCertAlg::PkcsEcdsaP384Sha384
+    } else {
+        TlsCertAlg::Unknown
+    }
+}
+
+/// The subject alt name extension id, `OID 2.5.29.17`.
+const SUBJECT_ALT_NAME_OID: &[u8] = &[0x06, 0x03, 0x55, 0x1d, 0x11];
+
+/// The first dns name in the subject alt names of a certificate,
+/// `None` if it has none.
+pub fn tls_cert_sni(cert_der: &[u8]) -> LairResult<Option<String>> {
+    let (_, cert, _, _) = der_next(cert_der)?;
+    let (_, tbs, _, _) = der_next(cert)?;
+    let mut rest = tbs;
+    if rest.first() == Some(&0xa0) {
+        rest = der_next(rest)?.3;
+    }
+    // serial, signature alg, issuer, validity, subject, pub key info
+    for _ in 0..6 {
+        rest = der_next(rest)?.3;
+    }
+    // then the optional unique ids, and extensions
+    while !rest.is_empty() {
+        let (tag, value, _, next) = der_next(rest)?;
+        rest = next;
+        if tag != 0xa3 {
+            continue;
+        }
+        let (_, mut exts, _, _) = der_next(value)?;
+        while !exts.is_empty() {
+            let (_, ext, _, next) = der_next(exts)?;
+            exts = next;
+            let (_, _, oid, ext) = der_next(ext)?;
+            if oid != SUBJECT_ALT_NAME_OID {
+                continue;
+            }
+            // skip the critical flag, if present
+            let (mut tag, mut names, _, ext) = der_next(ext)?;
+            if tag == 0x01 {
+                let next = der_next(ext)?;
+                tag = next.0;
+                names = next.1;
+            }
+            if tag != 0x04 {
+                return Err("malformed subject alt names".into());
+            }
+            let (_, mut names, _, _) = der_next(names)?;
+            while !names.is_empty() {
+                let (tag, name, _, next) = der_next(names)?;
+                names = next;
+                if tag == 0x82 {
+                    return Ok(Some(String::from_utf8_lossy(name).to_string()));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The sni of a certificate imported e.g. by `lair_unwrap_entry`, read
+/// from the subject alt names of the certificate, not trusting the
+/// entry, and checked with `CertSni::parse`. With `allow_invalid`,
+/// only with `CertSni::parse_lenient`, and the entry's own sni is used
+/// for certificates without a dns name.
+pub fn tls_cert_import_sni(
+    cert: &entry::EntryTlsCert,
+    allow_invalid: bool,
+) -> LairResult<CertSni> {
+    match (tls_cert_sni(&cert.cert_der)?, allow_invalid) {
+        (Some(sni), false) => CertSni::parse(&sni),
+        (Some(sni), true) => CertSni::parse_lenient(&sni),
+        (None, false) => Err("certificate holds no dns name".into()),
+        (None, true) => CertSni::parse_lenient(&cert.sni),
+    }
+}
+
+/// The ed448 algorithm identifier, `SEQUENCE { OID 1.3.101.113 }`.
+const ED448_ALG_ID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x71];
+
+/// Der encode one tag / length / value element.
+#[cfg(feature = "ed448")]
+fn der_write(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match value.len() {
+        len if len < 0x80 => out.push(len as u8),
+        len if len < 0x100 => out.extend_from_slice(&[0x81, len as u8]),
+        len => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+/// Der encode a sequence of already encoded elements.
+#[cfg(feature = "ed448")]
+fn der_seq(elements: &[&[u8]]) -> Vec<u8> {
+    der_write(0x30, &elements.concat())
+}
+
+/// The pkcs #8 (v1, RFC 8410) der encoding of a 57 byte ed448 priv key.
+#[cfg(feature = "ed448")]
+fn ed448_priv_key_der(priv_key: &[u8]) -> Vec<u8> {
+    der_seq(&[
+        &der_write(0x02, &[0]),
+        &der_write(0x30, ED448_ALG_ID),
+        &der_write(0x04, &der_write(0x04, priv_key)),
+    ])
+}
+
+/// Generate an ed448 keypair and a certificate for it, self signed with
+/// that same key: rcgen cannot sign with ed448, so unlike the other
+/// algorithms, the der is built here, and not signed by the well-known CA.
+#[cfg(feature = "ed448")]
+fn ed448_self_signed_cert(
+    sni: String,
+    entropy: &EntropySourceHandle,
+) -> LairResult<entry::EntryTlsCert> {
+    let mut priv_key = [0; 57];
+    entropy.fill(&mut priv_key)?;
+    let signing_key = internal::sign_ed448::signing_key(&priv_key)?;
+    let pub_key = signing_key.verifying_key().to_bytes();
+
+    // positive and minimally encoded
+    let mut serial = [0; 16];
+    entropy.fill(&mut serial)?;
+    serial[0] = (serial[0] & 0x7f) | 0x40;
+
+    // CN=Lair Self-Signed Cert <sni>, both issuer and subject
+    let name = der_seq(&[&der_write(
+        0x31,
+        &der_seq(&[
+            &der_write(0x06, &[0x55, 0x04, 0x03]),
+            &der_write(
+                0x0c,
+                format!("Lair Self-Signed Cert {}", &sni).as_bytes(),
+            ),
+        ]),
+    )]);
+    // the rcgen defaults, as for the other algorithms
+    let validity = der_seq(&[
+        &der_write(0x17, b"750101000000Z"),
+        &der_write(0x18, b"40960101000000Z"),
+    ]);
+    let mut bit_string = vec![0];
+    bit_string.extend_from_slice(&pub_key);
+    let spki = der_seq(&[
+        &der_write(0x30, ED448_ALG_ID),
+        &der_write(0x03, &bit_string),
+    ]);
+    let subject_alt_name = der_seq(&[
+        &der_write(0x06, &[0x55, 0x1d, 0x11]),
+        &der_write(0x04, &der_seq(&[&der_write(0x82, sni.as_bytes())])),
+    ]);
+    let ext_key_usage = der_seq(&[
+        &der_write(0x06, &[0x55, 0x1d, 0x25]),
+        &der_write(
+            0x04,
+            &der_seq(&[
+                // any, server auth, client auth
+                &der_write(0x06, &[0x55, 0x1d, 0x25, 0x00]),
+                &der_write(0x06, &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 3, 1]),
+                &der_write(0x06, &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 3, 2]),
+            ]),
+        ),
+    ]);
+    let tbs = der_seq(&[
+        // explicit v3
+        &der_write(0xa0, &der_write(0x02, &[2])),
+        &der_write(0x02, &serial),
+        &der_write(0x30, ED448_ALG_ID),
+        &name,
+        &validity,
+        &name,
+        &spki,
+        &der_write(0xa3, &der_seq(&[&subject_alt_name, &ext_key_usage])),
+    ]);
+
+    let mut signature = vec![0];
+    signature.extend_from_slice(&signing_key.sign_raw(&tbs).to_bytes());
+    let cert_der: Cert = der_seq(&[
+        &tbs,
+        &der_write(0x30, ED448_ALG_ID),
+        &der_write(0x03, &signature),
+    ])
+    .into();
+    let cert_digest = CertDigest::compute(&cert_der);
+
+    Ok(entry::EntryTlsCert {
+        sni: sni.into(),
+        priv_key_der: ed448_priv_key_der(&priv_key).into(),
+        cert_der,
+        cert_digest,
+        sign_key_index: None,
+        alg: Some(TlsCertAlg::PkcsEd448),
+    })
+}
+
 /// Generate a new random Tls keypair and self signed certificate.
+/// `TlsCertAlg::PkcsEd448` certificates are self signed with their own
+/// key, those of the other algorithms by the well-known CA. Without the
+/// `ed448` feature, they fail with `LairError::UnsupportedAlg`.
 pub async fn tls_cert_self_signed_new_from_entropy(
     options: TlsCertOptions,
+    entropy: EntropySourceHandle,
 ) -> LairResult<entry::EntryTlsCert> {
+    internal::deadline::check_deadline()?;
+    internal::cancel::check_cancelled()?;
     rayon_exec(move || {
-        let sni = format!("a{}a.a{}a", nanoid::nanoid!(), nanoid::nanoid!());
+        let sni = format!(
+            "a{}a.a{}a",
+            gen_sni_label(&entropy)?,
+            gen_sni_label(&entropy)?,
+        );
+
+        if options.alg == TlsCertAlg::PkcsEd448 {
+            #[cfg(feature = "ed448")]
+            return ed448_self_signed_cert(sni, &entropy);
+            #[cfg(not(feature = "ed448"))]
+            return Err(LairError::unsupported_alg("PkcsEd448 cert", "ed448"));
+        }
 
         let mut params = rcgen::CertificateParams::new(vec![sni.clone()]);
 
@@ -65,7 +580,6 @@ pub async fn tls_cert_self_signed_new_from_entropy(
             TlsCertAlg::PkcsEcdsaP384Sha384 => {
                 params.alg = &rcgen::PKCS_ECDSA_P384_SHA384
             }
-            TlsCertAlg::PkcsEd25519 => params.alg = &rcgen::PKCS_ED25519,
             _ => {
                 return Err(
                     format!("unhandled cert alg: {:?}", options.alg).into()
@@ -73,6 +587,8 @@ pub async fn tls_cert_self_signed_new_from_entropy(
             }
         };
 
+        params.key_pair = Some(gen_key_pair(options.alg, &entropy)?);
+
         params
             .extended_key_usages
             .push(rcgen::ExtendedKeyUsagePurpose::Any);
@@ -98,19 +614,16 @@ pub async fn tls_cert_self_signed_new_from_entropy(
             .serialize_der_with_signer(root_cert)
             .map_err(LairError::other)?;
 
-        let cert_digest = blake2b_simd::Params::new()
-            .hash_length(32)
-            .to_state()
-            .update(&cert_der)
-            .finalize()
-            .as_bytes()
-            .to_vec();
+        let cert_der: Cert = cert_der.into();
+        let cert_digest = CertDigest::compute(&cert_der);
 
         Ok(entry::EntryTlsCert {
             sni: sni.into(),
             priv_key_der: priv_key_der.into(),
-            cert_der: cert_der.into(),
-            cert_digest: cert_digest.into(),
+            cert_der,
+            cert_digest,
+            sign_key_index: None,
+            alg: Some(options.alg),
         })
     })
     .await
@@ -120,16 +633,285 @@ pub async fn tls_cert_self_signed_new_from_entropy(
 mod tests {
     use super::*;
 
+    /// Every algorithm lair generates certs of, in this build.
+    fn test_algs() -> Vec<TlsCertAlg> {
+        #[allow(unused_mut)]
+        let mut algs = vec![
+            TlsCertAlg::PkcsEd25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256,
+            TlsCertAlg::PkcsEcdsaP384Sha384,
+        ];
+        #[cfg(feature = "ed448")]
+        algs.push(TlsCertAlg::PkcsEd448);
+        algs
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn it_can_tls_cert_gen() {
-        let cert_res =
-            tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
-                .await
-                .unwrap();
+        let cert_res = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await
+        .unwrap();
         println!("cert: {:?}", cert_res);
         // we can't assert any values here as they are all random
         // when we have more functionality an integration test can be written
         // that takes the generated cert and makes sure it is usable
         // to encrypt / decrypt
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_tls_cert_gen_deterministic() {
+        use internal::entropy::DangerSeededEntropy;
+
+        for alg in &test_algs() {
+            let gen = |seed| async move {
+                tls_cert_self_signed_new_from_entropy(
+                    TlsCertOptions { alg: *alg },
+                    DangerSeededEntropy::danger_new_from_seed(seed),
+                )
+                .await
+                .unwrap()
+            };
+
+            let a = gen(42).await;
+            let b = gen(42).await;
+            let c = gen(43).await;
+
+            assert_eq!(a.sni, b.sni);
+            assert_eq!(a.sni, CertSni::parse(&a.sni).unwrap());
+            assert_eq!(a.priv_key_der, b.priv_key_der);
+            assert_eq!(a.cert_der, b.cert_der);
+            assert_eq!(a.cert_digest, b.cert_digest);
+
+            assert_ne!(a.sni, c.sni);
+            assert_ne!(a.cert_digest, c.cert_digest);
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reads_the_cert_sni() {
+        use internal::entropy::DangerSeededEntropy;
+
+        for alg in &test_algs() {
+            let mut cert = tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions { alg: *alg },
+                DangerSeededEntropy::danger_new_from_seed(42),
+            )
+            .await
+            .unwrap();
+            let sni = cert.sni.clone();
+            assert_eq!(
+                Some(sni.to_string()),
+                tls_cert_sni(&cert.cert_der).unwrap()
+            );
+
+            // imports trust the cert, not the entry
+            cert.sni = "spoofed.example.com".to_string().into();
+            assert_eq!(sni, tls_cert_import_sni(&cert, false).unwrap());
+        }
+
+        let mut cert = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+            DangerSeededEntropy::danger_new_from_seed(42),
+        )
+        .await
+        .unwrap();
+        cert.cert_der = vec![0x30, 0x00].into();
+        assert!(tls_cert_sni(&cert.cert_der).is_err());
+        assert!(tls_cert_import_sni(&cert, false).is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reads_the_cert_alg() {
+        use internal::entropy::DangerSeededEntropy;
+
+        for alg in &test_algs() {
+            let cert = tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions { alg: *alg },
+                DangerSeededEntropy::danger_new_from_seed(42),
+            )
+            .await
+            .unwrap();
+            assert_eq!(Some(*alg), cert.alg);
+            assert_eq!(*alg, tls_cert_alg(&cert.cert_der));
+        }
+
+        // a key of an algorithm lair does not generate,
+        // here x25519 (1.3.101.110), is unknown rather than an error
+        let cert = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+            DangerSeededEntropy::danger_new_from_seed(42),
+        )
+        .await
+        .unwrap();
+        let mut der = cert.cert_der.to_vec();
+        for at in 0..der.len() - ED25519_ALG_ID.len() {
+            if der[at..].starts_with(ED25519_ALG_ID) {
+                der[at + ED25519_ALG_ID.len() - 1] = 0x6e;
+            }
+        }
+        assert_eq!(TlsCertAlg::Unknown, tls_cert_alg(&der));
+        assert_eq!(TlsCertAlg::Unknown, tls_cert_alg(&[0x30, 0x00]));
+
+        assert!(tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions {
+                alg: TlsCertAlg::Unknown
+            },
+            DangerSeededEntropy::danger_new_from_seed(42),
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_binds_tls_certs_to_sign_keys() {
+        use internal::entropy::DangerSeededEntropy;
+
+        let entropy = DangerSeededEntropy::danger_new_from_seed(42);
+        let sign =
+            internal::sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                entropy.clone(),
+            )
+            .await
+            .unwrap();
+
+        // the same pkcs #8 encoding ring generates for the same seed
+        let ring_der = ring::signature::Ed25519KeyPair::generate_pkcs8(
+            &ring::test::rand::FixedSliceRandom {
+                bytes: &sign.priv_key.0,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            ring_der.as_ref(),
+            &ed25519_priv_key_der(&sign).unwrap()[..]
+        );
+
+        let cert = tls_cert_self_signed_new_from_sign_key(
+            3.into(),
+            sign.clone(),
+            TlsCertOptions::default(),
+            entropy.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(3.into()), cert.sign_key_index);
+        assert_eq!(CertDigest::compute(&cert.cert_der), cert.cert_digest);
+        assert_eq!(
+            sign.pub_key,
+            tls_cert_self_signed_ed25519_pub_key(&cert.cert_der).unwrap()
+        );
+
+        // other algs cannot be bound to an ed25519 key
+        assert!(tls_cert_self_signed_new_from_sign_key(
+            3.into(),
+            sign.clone(),
+            TlsCertOptions {
+                alg: TlsCertAlg::PkcsEcdsaP256Sha256,
+            },
+            entropy.clone(),
+        )
+        .await
+        .is_err());
+
+        // well-known ca signed certs are not self signed,
+        // and a tampered cert fails to verify
+        let wk_signed = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+            entropy,
+        )
+        .await
+        .unwrap();
+        assert!(
+            tls_cert_self_signed_ed25519_pub_key(&wk_signed.cert_der).is_err()
+        );
+        let mut tampered = cert.cert_der.to_vec();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(tls_cert_self_signed_ed25519_pub_key(&tampered).is_err());
+        assert!(tls_cert_self_signed_ed25519_pub_key(&[0x30, 0x05]).is_err());
+    }
+
+    #[cfg(not(feature = "ed448"))]
+    #[tokio::test(threaded_scheduler)]
+    async fn it_refuses_tls_cert_gen_ed448_without_the_feature() {
+        let res = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions {
+                alg: TlsCertAlg::PkcsEd448,
+            },
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await;
+        assert!(
+            matches!(res, Err(LairError::UnsupportedAlg(_))),
+            "{:?}",
+            res
+        );
+    }
+
+    #[cfg(feature = "ed448")]
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_tls_cert_gen_ed448() {
+        let cert = tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions {
+                alg: TlsCertAlg::PkcsEd448,
+            },
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(CertDigest::compute(&cert.cert_der), cert.cert_digest);
+
+        // the priv key parses as an RFC 8410 ed448 pkcs #8 key
+        use ed448_goldilocks::pkcs8::DecodePrivateKey;
+        let signing_key =
+            ed448_goldilocks::SigningKey::from_pkcs8_der(&cert.priv_key_der)
+                .unwrap();
+
+        // certificate, tbs certificate, signature alg, signature
+        let (tag, cert_body, _, rest) = der_next(&cert.cert_der).unwrap();
+        assert_eq!(0x30, tag);
+        assert!(rest.is_empty());
+        let (_, tbs, tbs_der, rest) = der_next(cert_body).unwrap();
+        let (_, sig_alg, _, rest) = der_next(rest).unwrap();
+        let (tag, sig, _, rest) = der_next(rest).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(ED448_ALG_ID, sig_alg);
+        assert_eq!((0x03, 115), (tag, sig.len()));
+
+        // version, serial, signature alg, issuer, validity, subject
+        let (tag, version, _, rest) = der_next(tbs).unwrap();
+        assert_eq!((0xa0, &[0x02, 0x01, 0x02][..]), (tag, version));
+        let (_, _, _, rest) = der_next(rest).unwrap();
+        let (_, tbs_sig_alg, _, rest) = der_next(rest).unwrap();
+        assert_eq!(ED448_ALG_ID, tbs_sig_alg);
+        let (_, _, issuer, rest) = der_next(rest).unwrap();
+        let (_, _, _, rest) = der_next(rest).unwrap();
+        let (_, _, subject, rest) = der_next(rest).unwrap();
+        assert_eq!(issuer, subject);
+
+        // the subject pub key info carries the ed448 oid and pub key
+        let (_, _, spki, rest) = der_next(rest).unwrap();
+        use ed448_goldilocks::pkcs8::DecodePublicKey;
+        let verifying_key =
+            ed448_goldilocks::VerifyingKey::from_public_key_der(spki).unwrap();
+        assert_eq!(signing_key.verifying_key(), verifying_key);
+        let (_, spki, _, _) = der_next(spki).unwrap();
+        assert_eq!(ED448_ALG_ID, der_next(spki).unwrap().1);
+
+        // extensions, holding the sni
+        let (tag, extensions, _, rest) = der_next(rest).unwrap();
+        assert_eq!(0xa3, tag);
+        assert!(rest.is_empty());
+        assert!(extensions
+            .windows(cert.sni.len())
+            .any(|w| w == cert.sni.as_bytes()));
+
+        // self signed with the cert key
+        let signature =
+            ed448_goldilocks::Signature::from_slice(&sig[1..]).unwrap();
+        assert!(verifying_key.verify_raw(&signature, tbs_der).is_ok());
+    }
 }
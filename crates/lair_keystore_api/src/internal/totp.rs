@@ -0,0 +1,413 @@
+//! Time-based One-Time Password Utilities (RFC 6238)
+//!
+//! Codes are computed in the keystore, so a secret only ever
+//! leaves it in a provisioning uri, for enrolling an authenticator.
+
+use crate::*;
+use derive_more::*;
+
+use actor::*;
+use internal::entropy::EntropySourceHandle;
+
+/// The secret key of a TOTP generator.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct TotpSecret(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for TotpSecret {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// The RFC 4648 base32 alphabet.
+const B32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generate a new random TOTP secret, as long as the hash output
+/// of `options.alg`, as RFC 4226 recommends.
+pub async fn totp_new_from_entropy(
+    entropy: EntropySourceHandle,
+    options: TotpOptions,
+) -> LairResult<entry::EntryTotpSecret> {
+    options.check()?;
+    let mut secret = vec![0; options.alg.hash_len()];
+    entropy.fill(&mut secret)?;
+    Ok(entry::EntryTotpSecret {
+        secret: secret.into(),
+        options,
+    })
+}
+
+/// Import a base32 encoded TOTP secret.
+pub async fn totp_import(
+    secret_b32: String,
+    options: TotpOptions,
+) -> LairResult<entry::EntryTotpSecret> {
+    options.check()?;
+    if secret_b32.len() > MAX_TOTP_SECRET_B32_LEN {
+        return Err(format!(
+            "base32 totp secret exceeds {} characters",
+            MAX_TOTP_SECRET_B32_LEN
+        )
+        .into());
+    }
+    let secret = base32_decode(&secret_b32)?;
+    if secret.len() < MIN_TOTP_SECRET_LEN || secret.len() > MAX_TOTP_SECRET_LEN
+    {
+        return Err(format!(
+            "totp secret must be between {} and {} bytes",
+            MIN_TOTP_SECRET_LEN, MAX_TOTP_SECRET_LEN
+        )
+        .into());
+    }
+    Ok(entry::EntryTotpSecret {
+        secret: secret.into(),
+        options,
+    })
+}
+
+/// The current unix time in seconds, the default `at` of `totp_code`.
+pub fn unix_time_now() -> LairResult<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(LairError::other)?
+        .as_secs())
+}
+
+/// The code of `secret` for the unix time `at` in seconds:
+/// the HOTP (RFC 4226) code of the count of `options.period`
+/// steps since the epoch, zero padded to `options.digits`.
+pub fn totp_code(
+    secret: &TotpSecret,
+    options: TotpOptions,
+    at: u64,
+) -> LairResult<String> {
+    options.check()?;
+    let counter = at / options.period as u64;
+    let alg = match options.alg {
+        TotpAlg::Sha1 => ring::hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+        TotpAlg::Sha256 => ring::hmac::HMAC_SHA256,
+        TotpAlg::Sha512 => ring::hmac::HMAC_SHA512,
+    };
+    let key = ring::hmac::Key::new(alg, secret);
+    let tag = ring::hmac::sign(&key, &counter.to_be_bytes());
+    let tag = tag.as_ref();
+
+    // dynamic truncation, RFC 4226 section 5.3
+    let offset = (tag[tag.len() - 1] & 0x0f) as usize;
+    let mut bin = [0; 4];
+    bin.copy_from_slice(&tag[offset..offset + 4]);
+    let bin = u32::from_be_bytes(bin) & 0x7fff_ffff;
+
+    let code = bin as u64 % 10_u64.pow(options.digits);
+    Ok(format!("{:0width$}", code, width = options.digits as usize))
+}
+
+/// The `otpauth://totp/` provisioning uri of `secret`,
+/// in the key uri format authenticator apps scan.
+pub fn totp_provisioning_uri(
+    secret: &TotpSecret,
+    options: TotpOptions,
+    label: &str,
+    issuer: &str,
+) -> LairResult<String> {
+    options.check()?;
+    if label.is_empty() {
+        return Err("totp provisioning uri label is required".into());
+    }
+    for s in &[label, issuer] {
+        if s.len() > MAX_TOTP_LABEL_LEN {
+            return Err(format!(
+                "totp provisioning uri label / issuer exceeds {} bytes",
+                MAX_TOTP_LABEL_LEN
+            )
+            .into());
+        }
+        if s.contains(':') {
+            return Err(
+                "totp provisioning uri label / issuer may not contain ':'"
+                    .into(),
+            );
+        }
+    }
+    let mut uri = "otpauth://totp/".to_string();
+    if !issuer.is_empty() {
+        uri.push_str(&percent_encode(issuer));
+        uri.push(':');
+    }
+    uri.push_str(&percent_encode(label));
+    uri.push_str("?secret=");
+    uri.push_str(base32_encode(secret).trim_end_matches('='));
+    if !issuer.is_empty() {
+        uri.push_str("&issuer=");
+        uri.push_str(&percent_encode(issuer));
+    }
+    uri.push_str(&format!(
+        "&algorithm={}&digits={}&period={}",
+        options.alg.name(),
+        options.digits,
+        options.period,
+    ));
+    Ok(uri)
+}
+
+/// Encode `data` as padded RFC 4648 base32.
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    for chunk in data.chunks(5) {
+        let mut buf = [0; 5];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let bits = buf.iter().fold(0_u64, |acc, b| acc << 8 | *b as u64);
+        // each input byte starts one more output character
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..8 {
+            if i < chars {
+                let idx = (bits >> (35 - i * 5)) & 0x1f;
+                out.push(B32_ALPHABET[idx as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode RFC 4648 base32, ignoring case, spaces and padding.
+/// Trailing bits short of a whole byte are dropped.
+pub fn base32_decode(data: &str) -> LairResult<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    let mut bits = 0_u32;
+    let mut bit_count = 0;
+    for c in data.trim_end_matches(['=', ' ']).bytes() {
+        if c == b' ' {
+            continue;
+        }
+        let val = match B32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())
+        {
+            Some(val) => val as u32,
+            None => return Err("invalid base32".into()),
+        };
+        bits = (bits << 5 | val) & 0xffff;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Percent encode everything but the uri unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(digits: u32, period: u32, alg: TotpAlg) -> TotpOptions {
+        TotpOptions {
+            digits,
+            period,
+            alg,
+        }
+    }
+
+    #[test]
+    fn it_passes_rfc6238_test_vectors() {
+        // RFC 6238 appendix B, the secret repeats "1234567890"
+        // to the hash output length of each algorithm
+        let secret = |len| -> TotpSecret {
+            b"1234567890"
+                .iter()
+                .cycle()
+                .take(len)
+                .cloned()
+                .collect::<Vec<_>>()
+                .into()
+        };
+        let vectors: &[(u64, &str, &str, &str)] = &[
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (at, sha1, sha256, sha512) in vectors {
+            for (alg, expect) in &[
+                (TotpAlg::Sha1, sha1),
+                (TotpAlg::Sha256, sha256),
+                (TotpAlg::Sha512, sha512),
+            ] {
+                let code = totp_code(
+                    &secret(alg.hash_len()),
+                    options(8, 30, *alg),
+                    *at,
+                )
+                .unwrap();
+                assert_eq!(*expect, &code, "{:?} at {}", alg, at);
+            }
+        }
+    }
+
+    #[test]
+    fn it_handles_period_and_digit_edges() {
+        let secret: TotpSecret = b"12345678901234567890".to_vec().into();
+        let code = |digits, period, at| {
+            totp_code(&secret, options(digits, period, TotpAlg::Sha1), at)
+                .unwrap()
+        };
+
+        // a code holds for its whole step, and changes with the next
+        assert_eq!(code(8, 30, 30), code(8, 30, 59));
+        assert_ne!(code(8, 30, 59), code(8, 30, 60));
+        assert_eq!(code(8, 30, 0), code(8, 30, 29));
+
+        // the code of step n is the same whatever the period
+        assert_eq!(code(8, 30, 59), code(8, 1, 1));
+        assert_eq!(
+            code(8, 30, 59),
+            code(8, MAX_TOTP_PERIOD, MAX_TOTP_PERIOD as u64)
+        );
+
+        // shorter codes are the trailing digits, zero padded
+        assert_eq!("287082", code(6, 30, 59));
+        assert_eq!("081804", code(6, 30, 1111111109));
+        assert_eq!(10, code(MAX_TOTP_DIGITS, 30, 59).len());
+        assert!(code(MAX_TOTP_DIGITS, 30, 59).ends_with("94287082"));
+
+        // the end of time still has a step
+        assert_eq!(6, code(6, 30, u64::MAX).len());
+
+        for bad in &[
+            options(MIN_TOTP_DIGITS - 1, 30, TotpAlg::Sha1),
+            options(MAX_TOTP_DIGITS + 1, 30, TotpAlg::Sha1),
+            options(6, 0, TotpAlg::Sha1),
+            options(6, MAX_TOTP_PERIOD + 1, TotpAlg::Sha1),
+        ] {
+            assert!(totp_code(&secret, *bad, 59).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn it_encodes_rfc4648_base32() {
+        for (data, b32) in &[
+            ("", ""),
+            ("f", "MY======"),
+            ("fo", "MZXQ===="),
+            ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI======"),
+        ] {
+            assert_eq!(*b32, base32_encode(data.as_bytes()));
+            assert_eq!(data.as_bytes(), &base32_decode(b32).unwrap()[..]);
+            // as shown for typing in, lower case and unpadded
+            assert_eq!(
+                data.as_bytes(),
+                &base32_decode(&b32.trim_end_matches('=').to_lowercase())
+                    .unwrap()[..],
+            );
+        }
+        assert_eq!(b"foobar".to_vec(), base32_decode("mzxw 6ytb oi").unwrap());
+        assert!(base32_decode("MZXW1").is_err());
+        assert!(base32_decode("MZ=XW").is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_imports_and_generates_secrets() {
+        let secret = base32_encode(b"12345678901234567890");
+        let entry = totp_import(secret.clone(), options(8, 30, TotpAlg::Sha1))
+            .await
+            .unwrap();
+        assert_eq!(b"12345678901234567890", &entry.secret[..]);
+        assert_eq!("94287082", entry.generate(Some(59)).unwrap());
+
+        // too short, too long, or not base32
+        for bad in &[
+            base32_encode(&[0; MIN_TOTP_SECRET_LEN - 1]),
+            base32_encode(&[0; MAX_TOTP_SECRET_LEN + 1]),
+            "not base32!".to_string(),
+            " ".repeat(MAX_TOTP_SECRET_B32_LEN + 1),
+        ] {
+            assert!(totp_import(bad.clone(), TotpOptions::default())
+                .await
+                .is_err());
+        }
+        assert!(totp_import(secret, options(5, 30, TotpAlg::Sha1))
+            .await
+            .is_err());
+
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        for alg in &[TotpAlg::Sha1, TotpAlg::Sha256, TotpAlg::Sha512] {
+            let entry =
+                totp_new_from_entropy(entropy.clone(), options(6, 30, *alg))
+                    .await
+                    .unwrap();
+            assert_eq!(alg.hash_len(), entry.secret.len());
+            assert_eq!(6, entry.generate(None).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn it_builds_provisioning_uris() {
+        let secret: TotpSecret = b"12345678901234567890".to_vec().into();
+        assert_eq!(
+            "otpauth://totp/ACME%20Co:alice%40example.com\
+             ?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co\
+             &algorithm=SHA1&digits=6&period=30",
+            totp_provisioning_uri(
+                &secret,
+                TotpOptions::default(),
+                "alice@example.com",
+                "ACME Co",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            "otpauth://totp/svc?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &algorithm=SHA512&digits=8&period=60",
+            totp_provisioning_uri(
+                &secret,
+                options(8, 60, TotpAlg::Sha512),
+                "svc",
+                "",
+            )
+            .unwrap(),
+        );
+        let long = "a".repeat(MAX_TOTP_LABEL_LEN + 1);
+        for (label, issuer) in &[
+            ("", "ACME"),
+            ("a:b", "ACME"),
+            ("svc", "AC:ME"),
+            (long.as_str(), ""),
+            ("svc", long.as_str()),
+        ] {
+            assert!(totp_provisioning_uri(
+                &secret,
+                TotpOptions::default(),
+                label,
+                issuer
+            )
+            .is_err());
+        }
+    }
+}
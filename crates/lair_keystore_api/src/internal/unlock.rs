@@ -0,0 +1,664 @@
+//! Unlock Passphrase Key Derivation Utilities
+//!
+//! The unlock key is derived from the passphrase as a chain of
+//! `ops_limit` single-pass argon2id rounds, each taking the previous
+//! round's output as its password, so progress can be reported between
+//! rounds. The unlock entry (the first entry of the store file) holds
+//! the kdf limits, salt, and a verifier of the derived key:
+//! `version (4 bytes) || mem_limit_kib (4) || ops_limit (4)
+//! || salt (16) || verifier (32)`, zero padded to `entry::ENTRY_SIZE`.
+//! An all-zero entry means no passphrase has been set yet.
+//!
+//! Stores bound to a device (see `device_secret`) mix the device secret
+//! into every round, as the argon2 secret key, and are written as
+//! version 2, appending `flags (4)` to the version 1 layout. Older lair
+//! versions then refuse them outright, rather than failing the
+//! passphrase.
+//!
+//! Stores whose passphrase is set by this lair version are written as
+//! version 3, with the same `flags (4)`, setting the encrypted index
+//! flag: their entries, entry tags, trash and inactive entries are
+//! sealed with an `IndexKey`, derived from the unlock key alongside the
+//! verifier, so neither public nor private material can be read from
+//! the files of a locked store. Each entry following the unlock entry
+//! is then a `SEALED_ENTRY_SIZE` record. Version 1 and 2 stores are of
+//! the legacy layout, keeping all of those in plaintext, until they are
+//! migrated.
+
+use crate::*;
+
+use device_secret::DeviceSecret;
+use internal::codec;
+use internal::entropy::EntropySourceHandle;
+use internal::secretbox;
+use passphrase::Passphrase;
+use zeroize::Zeroizing;
+
+/// The unlock entry version of stores not bound to a device.
+pub const UNLOCK_ENTRY_VERSION: u32 = 1;
+
+/// The unlock entry version of stores bound to a device.
+pub const UNLOCK_ENTRY_VERSION_DEVICE_BOUND: u32 = 2;
+
+/// The unlock entry version of stores with an encrypted index.
+pub const UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX: u32 = 3;
+
+/// The version 2 / 3 flag of an unlock key derived with a device secret.
+const FLAG_DEVICE_BOUND: u32 = 0x00000001;
+
+/// The version 3 flag of a store sealing its index with the `IndexKey`.
+const FLAG_ENCRYPTED_INDEX: u32 = 0x00000002;
+
+const SALT_BYTES: usize = 16;
+const KEY_BYTES: usize = 32;
+const VERIFIER_CONTEXT: &[u8] = b"lair-unlock-verifier";
+const INDEX_KEY_CONTEXT: &[u8] = b"lair-index-key";
+
+/// The version of index files sealed with an `IndexKey`.
+const SEALED_INDEX_VERSION: u32 = 1;
+
+/// The byte length of a store entry sealed with `IndexKey::seal_entry`:
+/// `version (4 bytes) || nonce (24) || mac (16) || entry`.
+pub const SEALED_ENTRY_SIZE: usize =
+    4 + secretbox::NONCE_BYTES + secretbox::MAC_BYTES + entry::ENTRY_SIZE;
+
+/// The argon2id cost of deriving the unlock key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnlockKdfLimits {
+    /// Memory cost of each round, in KiB. At least 8.
+    pub mem_limit_kib: u32,
+
+    /// Number of chained single-pass rounds. At least 1.
+    /// Progress is reported after each round.
+    pub ops_limit: u32,
+}
+
+impl Default for UnlockKdfLimits {
+    fn default() -> Self {
+        Self {
+            mem_limit_kib: 64 * 1024,
+            ops_limit: 3,
+        }
+    }
+}
+
+impl UnlockKdfLimits {
+    fn check(&self) -> LairResult<()> {
+        if self.mem_limit_kib < 8 || self.ops_limit < 1 {
+            return Err(format!("invalid unlock kdf limits: {:?}", self).into());
+        }
+        Ok(())
+    }
+}
+
+/// The decoded unlock entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnlockEntry {
+    /// The limits the unlock key is derived with.
+    pub limits: UnlockKdfLimits,
+
+    /// The argon2id salt.
+    pub salt: [u8; SALT_BYTES],
+
+    /// A keyed hash proving knowledge of the unlock key,
+    /// without revealing it.
+    pub verifier: [u8; KEY_BYTES],
+
+    /// The unlock key is derived with the device secret,
+    /// as well as the passphrase.
+    pub device_bound: bool,
+
+    /// The index of the store is sealed with its `IndexKey`,
+    /// false for stores of the legacy layout.
+    pub encrypted_index: bool,
+}
+
+impl UnlockEntry {
+    /// Encode as a store file entry.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new_zeroed(entry::ENTRY_SIZE)?;
+        let mut flags = 0;
+        if self.device_bound {
+            flags |= FLAG_DEVICE_BOUND;
+        }
+        if self.encrypted_index {
+            flags |= FLAG_ENCRYPTED_INDEX;
+        }
+        let version = match flags {
+            0 => UNLOCK_ENTRY_VERSION,
+            FLAG_DEVICE_BOUND => UNLOCK_ENTRY_VERSION_DEVICE_BOUND,
+            _ => UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX,
+        };
+        writer.write_u32(version)?;
+        writer.write_u32(self.limits.mem_limit_kib)?;
+        writer.write_u32(self.limits.ops_limit)?;
+        writer.write_bytes(&self.salt)?;
+        writer.write_bytes(&self.verifier)?;
+        if version != UNLOCK_ENTRY_VERSION {
+            writer.write_u32(flags)?;
+        }
+        Ok(writer.into_vec())
+    }
+
+    /// Decode a store file unlock entry.
+    /// Returns None for the all-zero entry of a store with no passphrase.
+    pub fn decode(data: &[u8]) -> LairResult<Option<Self>> {
+        if data.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+        let mut reader = codec::CodecReader::new(data);
+        let version = reader.read_u32()?;
+        if version != UNLOCK_ENTRY_VERSION
+            && version != UNLOCK_ENTRY_VERSION_DEVICE_BOUND
+            && version != UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX
+        {
+            return Err(format!(
+                "unsupported unlock entry version: {}",
+                version
+            )
+            .into());
+        }
+        let limits = UnlockKdfLimits {
+            mem_limit_kib: reader.read_u32()?,
+            ops_limit: reader.read_u32()?,
+        };
+        limits.check()?;
+        let mut salt = [0; SALT_BYTES];
+        salt.copy_from_slice(reader.read_bytes(SALT_BYTES as u64)?);
+        let mut verifier = [0; KEY_BYTES];
+        verifier.copy_from_slice(reader.read_bytes(KEY_BYTES as u64)?);
+        let (flags, allowed) = match version {
+            UNLOCK_ENTRY_VERSION => (0, 0),
+            UNLOCK_ENTRY_VERSION_DEVICE_BOUND => {
+                (reader.read_u32()?, FLAG_DEVICE_BOUND)
+            }
+            _ => (reader.read_u32()?, FLAG_DEVICE_BOUND | FLAG_ENCRYPTED_INDEX),
+        };
+        let encrypted_index = flags & FLAG_ENCRYPTED_INDEX != 0;
+        // only version 3 entries, and all of them, encrypt their index
+        if flags & !allowed != 0
+            || encrypted_index
+                != (version == UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX)
+        {
+            return Err(format!(
+                "unsupported unlock entry flags: {:#010x}",
+                flags
+            )
+            .into());
+        }
+        Ok(Some(Self {
+            limits,
+            salt,
+            verifier,
+            device_bound: flags & FLAG_DEVICE_BOUND != 0,
+            encrypted_index,
+        }))
+    }
+}
+
+/// The key sealing the entries and index files of a store with an
+/// encrypted index, see `UnlockEntry::encrypted_index`.
+#[derive(Clone)]
+pub struct IndexKey(Arc<Zeroizing<[u8; KEY_BYTES]>>);
+
+impl std::fmt::Debug for IndexKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IndexKey([redacted])")
+    }
+}
+
+impl IndexKey {
+    /// The key of the index file `name`, so a sealed file only
+    /// opens as the file it was sealed as.
+    fn file_key(&self, name: &str) -> LairResult<Zeroizing<[u8; KEY_BYTES]>> {
+        let key = hash::blake2b(name.as_bytes(), KEY_BYTES, Some(&self.0[..]))?;
+        let mut out = Zeroizing::new([0; KEY_BYTES]);
+        out.copy_from_slice(&key);
+        Ok(out)
+    }
+
+    /// Seal the encoded index file `name`, e.g. `tags`, as
+    /// `version (4 bytes) || nonce (24) || mac (16) || ciphertext`.
+    pub fn seal(
+        &self,
+        entropy: &EntropySourceHandle,
+        name: &str,
+        data: &[u8],
+    ) -> LairResult<Vec<u8>> {
+        let mut nonce = [0; secretbox::NONCE_BYTES];
+        entropy.fill(&mut nonce)?;
+        let sealed =
+            secretbox::secretbox_easy(&*self.file_key(name)?, &nonce, data);
+        let mut out = Vec::with_capacity(4 + nonce.len() + sealed.len());
+        out.extend_from_slice(&SEALED_INDEX_VERSION.to_le_bytes());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Open the index file `name` sealed with `seal`. Files sealed with
+    /// another key, as another file, or altered, fail with
+    /// `LairError::AuthenticationFailed`.
+    pub fn open(&self, name: &str, data: &[u8]) -> LairResult<Vec<u8>> {
+        let mut reader = codec::CodecReader::new(data);
+        let version = reader.read_u32()?;
+        if version != SEALED_INDEX_VERSION {
+            return Err(format!(
+                "unsupported sealed index version: {}",
+                version
+            )
+            .into());
+        }
+        let mut nonce = [0; secretbox::NONCE_BYTES];
+        nonce
+            .copy_from_slice(reader.read_bytes(secretbox::NONCE_BYTES as u64)?);
+        secretbox::secretbox_open_easy(
+            &*self.file_key(name)?,
+            &nonce,
+            &data[4 + secretbox::NONCE_BYTES..],
+        )
+    }
+
+    /// Seal the encoded store entry numbered `entry`, as a
+    /// `SEALED_ENTRY_SIZE` record. It only opens as the same entry
+    /// number, so records cannot be swapped within the store file.
+    pub fn seal_entry(
+        &self,
+        entropy: &EntropySourceHandle,
+        entry: u32,
+        data: &[u8],
+    ) -> LairResult<Vec<u8>> {
+        if data.len() != entry::ENTRY_SIZE {
+            return Err(format!(
+                "bad entry size, expected {}, got {}",
+                entry::ENTRY_SIZE,
+                data.len(),
+            )
+            .into());
+        }
+        self.seal(entropy, &format!("entry-{}", entry), data)
+    }
+
+    /// Open the store entry numbered `entry` sealed with `seal_entry`.
+    pub fn open_entry(&self, entry: u32, data: &[u8]) -> LairResult<Vec<u8>> {
+        if data.len() != SEALED_ENTRY_SIZE {
+            return Err(LairError::AuthenticationFailed);
+        }
+        self.open(&format!("entry-{}", entry), data)
+    }
+}
+
+/// Set a new passphrase, deriving an unlock entry with a fresh salt,
+/// for a store with an encrypted index, and its `IndexKey`.
+/// With a `device_secret`, the entry is bound to it.
+/// `progress` is called with the percent complete after each round.
+pub async fn unlock_entry_new(
+    entropy: EntropySourceHandle,
+    passphrase: Passphrase,
+    device_secret: Option<DeviceSecret>,
+    limits: UnlockKdfLimits,
+    progress: impl FnMut(u8) + Send + 'static,
+) -> LairResult<(UnlockEntry, IndexKey)> {
+    limits.check()?;
+    let mut salt = [0; SALT_BYTES];
+    entropy.fill(&mut salt)?;
+    let device_bound = device_secret.is_some();
+    let (verifier, index_key) =
+        derive_keys(passphrase, device_secret, limits, salt, progress).await?;
+    let unlock_entry = UnlockEntry {
+        limits,
+        salt,
+        verifier,
+        device_bound,
+        encrypted_index: true,
+    };
+    Ok((unlock_entry, index_key))
+}
+
+/// Check `passphrase` against an unlock entry, returning the
+/// `IndexKey` it derives, whether or not the store encrypts its index.
+/// `progress` is called with the percent complete after each round.
+/// A wrong passphrase fails with `LairError::AuthenticationFailed`,
+/// a device bound entry without its `device_secret` fails with
+/// `LairError::DeviceSecretMissing`. The device secret of an entry
+/// not bound to a device is ignored.
+pub async fn unlock_entry_verify(
+    unlock_entry: &UnlockEntry,
+    passphrase: Passphrase,
+    device_secret: Option<DeviceSecret>,
+    progress: impl FnMut(u8) + Send + 'static,
+) -> LairResult<IndexKey> {
+    let device_secret = match (unlock_entry.device_bound, device_secret) {
+        (false, _) => None,
+        (true, Some(device_secret)) => Some(device_secret),
+        (true, None) => {
+            return Err(LairError::DeviceSecretMissing(
+                "the store is bound to a device secret, \
+                and this machine holds none"
+                    .into(),
+            ))
+        }
+    };
+    let (verifier, index_key) = derive_keys(
+        passphrase,
+        device_secret,
+        unlock_entry.limits,
+        unlock_entry.salt,
+        progress,
+    )
+    .await?;
+    // constant time comparison
+    let diff = verifier
+        .iter()
+        .zip(unlock_entry.verifier.iter())
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(LairError::AuthenticationFailed);
+    }
+    Ok(index_key)
+}
+
+/// Runs on the tokio blocking pool, like `pw_hash`,
+/// at high memory costs a round would otherwise stall a rayon thread.
+/// The request deadline and cancellation, if any,
+/// are checked before each round.
+async fn derive_keys(
+    passphrase: Passphrase,
+    device_secret: Option<DeviceSecret>,
+    limits: UnlockKdfLimits,
+    salt: [u8; SALT_BYTES],
+    mut progress: impl FnMut(u8) + Send + 'static,
+) -> LairResult<([u8; KEY_BYTES], IndexKey)> {
+    let deadline = internal::deadline::current_deadline();
+    let cancel = internal::cancel::current_cancel_token();
+    tokio::task::spawn_blocking(move || {
+        let secret = match &device_secret {
+            Some(device_secret) => &device_secret[..],
+            None => &[],
+        };
+        let config = argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            version: argon2::Version::Version13,
+            mem_cost: limits.mem_limit_kib,
+            time_cost: 1,
+            lanes: 1,
+            thread_mode: argon2::ThreadMode::Sequential,
+            secret,
+            ad: &[],
+            hash_length: KEY_BYTES as u32,
+        };
+        let mut key = Zeroizing::new(passphrase.read().to_vec());
+        for round in 0..limits.ops_limit {
+            internal::deadline::check_deadline_at(deadline)?;
+            if let Some(cancel) = &cancel {
+                cancel.check()?;
+            }
+            key = Zeroizing::new(
+                argon2::hash_raw(&key, &salt, &config)
+                    .map_err(LairError::other)?,
+            );
+            progress(
+                ((round + 1) as u64 * 100 / limits.ops_limit as u64) as u8,
+            );
+        }
+        let verifier = hash::blake2b(VERIFIER_CONTEXT, KEY_BYTES, Some(&key))?;
+        let mut out = [0; KEY_BYTES];
+        out.copy_from_slice(&verifier);
+        let index_key =
+            hash::blake2b(INDEX_KEY_CONTEXT, KEY_BYTES, Some(&key))?;
+        let mut index_key_out = Zeroizing::new([0; KEY_BYTES]);
+        index_key_out.copy_from_slice(&index_key);
+        Ok((out, IndexKey(Arc::new(index_key_out))))
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: UnlockKdfLimits = UnlockKdfLimits {
+        mem_limit_kib: 8,
+        ops_limit: 1,
+    };
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_set_and_verify_a_passphrase() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let passphrase = Passphrase::from("passphrase");
+        let (unlock_entry, index_key) = unlock_entry_new(
+            entropy.clone(),
+            passphrase.clone(),
+            None,
+            FAST,
+            |_| (),
+        )
+        .await
+        .unwrap();
+        assert!(unlock_entry.encrypted_index);
+
+        let encoded = unlock_entry.encode().unwrap();
+        assert_eq!(entry::ENTRY_SIZE, encoded.len());
+        assert_eq!(UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX, encoded[0] as u32);
+        let decoded = UnlockEntry::decode(&encoded).unwrap().unwrap();
+        assert_eq!(unlock_entry, decoded);
+        assert_eq!(None, UnlockEntry::decode(&[0; entry::ENTRY_SIZE]).unwrap());
+
+        // the same passphrase derives the same index key
+        let verified =
+            unlock_entry_verify(&decoded, passphrase.clone(), None, |_| ())
+                .await
+                .unwrap();
+        let sealed = index_key.seal(&entropy, "tags", b"tags").unwrap();
+        assert_eq!(b"tags".to_vec(), verified.open("tags", &sealed).unwrap());
+        assert!(matches!(
+            unlock_entry_verify(
+                &decoded,
+                Passphrase::from("wrong"),
+                None,
+                |_| ()
+            )
+            .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // a fresh salt gives a different verifier for the same passphrase
+        let (other, other_index_key) =
+            unlock_entry_new(entropy, passphrase, None, FAST, |_| ())
+                .await
+                .unwrap();
+        assert_ne!(unlock_entry.verifier, other.verifier);
+        assert!(matches!(
+            other_index_key.open("tags", &sealed),
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        let mut bad = encoded;
+        bad[0] = 4;
+        assert!(UnlockEntry::decode(&bad).is_err());
+        assert!(unlock_entry_new(
+            internal::entropy::OsEntropy::new_handle(),
+            Passphrase::from(""),
+            None,
+            UnlockKdfLimits {
+                mem_limit_kib: 8,
+                ops_limit: 0,
+            },
+            |_| ()
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_binds_the_unlock_key_to_a_device_secret() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let passphrase = Passphrase::from("passphrase");
+        let device_secret = [0x42; device_secret::DEVICE_SECRET_LEN];
+        let (unlock_entry, _) = unlock_entry_new(
+            entropy,
+            passphrase.clone(),
+            Some(device_secret),
+            FAST,
+            |_| (),
+        )
+        .await
+        .unwrap();
+        assert!(unlock_entry.device_bound);
+
+        let encoded = unlock_entry.encode().unwrap();
+        assert_eq!(UNLOCK_ENTRY_VERSION_ENCRYPTED_INDEX, encoded[0] as u32);
+        let decoded = UnlockEntry::decode(&encoded).unwrap().unwrap();
+        assert_eq!(unlock_entry, decoded);
+
+        unlock_entry_verify(
+            &decoded,
+            passphrase.clone(),
+            Some(device_secret),
+            |_| (),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            unlock_entry_verify(&decoded, passphrase.clone(), None, |_| ())
+                .await,
+            Err(LairError::DeviceSecretMissing(_)),
+        ));
+        assert!(matches!(
+            unlock_entry_verify(
+                &decoded,
+                passphrase,
+                Some([0; device_secret::DEVICE_SECRET_LEN]),
+                |_| ()
+            )
+            .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // unknown flags are refused
+        let mut bad = encoded;
+        bad[4 + 4 + 4 + SALT_BYTES + KEY_BYTES] = 0x07;
+        assert!(UnlockEntry::decode(&bad).is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_reports_progress_after_each_round() {
+        let limits = UnlockKdfLimits {
+            mem_limit_kib: 256,
+            ops_limit: 8,
+        };
+        let (send, recv) = std::sync::mpsc::channel();
+        let (unlock_entry, _) = unlock_entry_new(
+            internal::entropy::OsEntropy::new_handle(),
+            Passphrase::from("passphrase"),
+            None,
+            limits,
+            move |percent| send.send(percent).unwrap(),
+        )
+        .await
+        .unwrap();
+        let progress = recv.try_iter().collect::<Vec<_>>();
+        assert_eq!(vec![12, 25, 37, 50, 62, 75, 87, 100], progress);
+        assert_eq!(limits, unlock_entry.limits);
+    }
+
+    #[test]
+    fn it_decodes_legacy_layout_entries() {
+        for device_bound in [false, true] {
+            let legacy = UnlockEntry {
+                limits: FAST,
+                salt: [1; SALT_BYTES],
+                verifier: [2; KEY_BYTES],
+                device_bound,
+                encrypted_index: false,
+            };
+            let encoded = legacy.encode().unwrap();
+            assert_eq!(
+                match device_bound {
+                    true => UNLOCK_ENTRY_VERSION_DEVICE_BOUND,
+                    false => UNLOCK_ENTRY_VERSION,
+                },
+                encoded[0] as u32,
+            );
+            assert_eq!(legacy, UnlockEntry::decode(&encoded).unwrap().unwrap());
+
+            // an encrypted index is only flagged in version 3
+            let mut bad = encoded;
+            bad[0] = 2;
+            bad[4 + 4 + 4 + SALT_BYTES + KEY_BYTES] = 0x02;
+            assert!(UnlockEntry::decode(&bad).is_err());
+            bad[0] = 3;
+            bad[4 + 4 + 4 + SALT_BYTES + KEY_BYTES] = 0x01;
+            assert!(UnlockEntry::decode(&bad).is_err());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_seals_index_files_by_name() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let (_, index_key) = unlock_entry_new(
+            entropy.clone(),
+            Passphrase::from("passphrase"),
+            None,
+            FAST,
+            |_| (),
+        )
+        .await
+        .unwrap();
+
+        let sealed = index_key.seal(&entropy, "trash", b"trashed").unwrap();
+        assert_ne!(
+            sealed,
+            index_key.seal(&entropy, "trash", b"trashed").unwrap()
+        );
+        assert_eq!(
+            b"trashed".to_vec(),
+            index_key.open("trash", &sealed).unwrap()
+        );
+        // sealed as another file
+        assert!(matches!(
+            index_key.open("inactive", &sealed),
+            Err(LairError::AuthenticationFailed),
+        ));
+        let mut altered = sealed.clone();
+        *altered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            index_key.open("trash", &altered),
+            Err(LairError::AuthenticationFailed),
+        ));
+        assert!(index_key.open("trash", &sealed[..20]).is_err());
+        let mut bad = sealed;
+        bad[0] = 2;
+        assert!(index_key.open("trash", &bad).is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_seals_entries_by_number() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let (_, index_key) = unlock_entry_new(
+            entropy.clone(),
+            Passphrase::from("passphrase"),
+            None,
+            FAST,
+            |_| (),
+        )
+        .await
+        .unwrap();
+
+        let entry = entry::encode_erased_entry().unwrap();
+        let sealed = index_key.seal_entry(&entropy, 7, &entry).unwrap();
+        assert_eq!(SEALED_ENTRY_SIZE, sealed.len());
+        assert_eq!(entry, index_key.open_entry(7, &sealed).unwrap());
+        // sealed as another entry
+        assert!(matches!(
+            index_key.open_entry(8, &sealed),
+            Err(LairError::AuthenticationFailed),
+        ));
+        assert!(matches!(
+            index_key.open_entry(7, &sealed[..entry::ENTRY_SIZE]),
+            Err(LairError::AuthenticationFailed),
+        ));
+        assert!(index_key.seal_entry(&entropy, 7, &entry[1..]).is_err());
+    }
+}
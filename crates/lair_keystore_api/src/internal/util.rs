@@ -1,8 +1,18 @@
 //! Internal utility logic.
 
+mod catch_panic;
+pub use catch_panic::*;
 mod err_spawn;
 pub use err_spawn::*;
 mod kill_switch;
 pub use kill_switch::*;
 mod msg_id;
 pub use msg_id::*;
+mod private_fs;
+pub use private_fs::*;
+#[cfg(unix)]
+mod private_socket;
+#[cfg(unix)]
+pub use private_socket::*;
+mod unix_time;
+pub use unix_time::*;
@@ -0,0 +1,57 @@
+use crate::*;
+use futures::future::FutureExt;
+use std::panic::AssertUnwindSafe;
+
+/// The message a panic was raised with, if it was raised with one.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "(no message)"
+    }
+}
+
+/// Call `f`, resolving to `LairError::Internal` if it panics.
+pub fn catch_handler_panic<T, F>(hint: &'static str, f: F) -> LairResult<T>
+where
+    F: FnOnce() -> LairResult<T>,
+{
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            error!("HANDLER {} PANICKED: {}", hint, panic_message(&*payload));
+            Err(LairError::Internal(format!("{} panicked", hint)))
+        }
+    }
+}
+
+/// Dispatch an incoming actor message with `f`, keeping the actor alive
+/// if the handler panics. The panicked request's responder is dropped,
+/// so its caller gets a `GhostError`, sent on the wire as `Internal`.
+/// Use it in `GhostHandler::ghost_actor_dispatch`.
+pub fn catch_dispatch_panic<F: FnOnce()>(hint: &'static str, f: F) {
+    let _ = catch_handler_panic(hint, || {
+        f();
+        Ok(())
+    });
+}
+
+/// Await `f`, resolving to `LairError::Internal` if it panics.
+pub async fn catch_request_panic<T, F>(
+    hint: &'static str,
+    f: F,
+) -> LairResult<T>
+where
+    F: std::future::Future<Output = LairResult<T>>,
+{
+    match AssertUnwindSafe(f).catch_unwind().await {
+        Ok(res) => res,
+        Err(payload) => {
+            let message = panic_message(&*payload);
+            error!("FUTURE {} PANICKED: {}", hint, message);
+            Err(LairError::Internal(format!("{} panicked", hint)))
+        }
+    }
+}
@@ -9,12 +9,16 @@ pub type KillCallback = Box<
         + Send,
 >;
 
+/// Kill Reason - The error mixed futures fail with once killed
+pub type KillReason = Arc<dyn Fn() -> LairError + 'static + Send + Sync>;
+
 type IsWeak = bool;
 
 type InnerArc = Arc<(
     std::sync::atomic::AtomicBool,
     tokio::sync::broadcast::Sender<()>,
     tokio::sync::Mutex<Vec<KillCallback>>,
+    std::sync::Mutex<Option<KillReason>>,
 )>;
 
 /// If any clones of this struct are dropped,
@@ -31,19 +35,32 @@ fn cont(inner: &InnerArc) -> bool {
     inner.0.load(std::sync::atomic::Ordering::SeqCst)
 }
 
+fn lock_reason(
+    inner: &InnerArc,
+) -> std::sync::MutexGuard<'_, Option<KillReason>> {
+    // only ever set, never held across a panic point
+    inner.3.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn kill_reason(inner: &InnerArc) -> Option<LairError> {
+    lock_reason(inner).as_ref().map(|reason| reason())
+}
+
+fn killed(inner: &InnerArc) -> LairError {
+    kill_reason(inner).unwrap_or_else(|| "kill_switch triggered".into())
+}
+
 macro_rules! inner_mix {
     ($inner:expr, $f:ident) => {{
         let mut r = $inner.1.subscribe();
         let r = r.recv();
         if !cont(&$inner) {
             // check *after* we create the receiver
-            return Err("kill_switch triggered".into());
+            return Err(killed(&$inner));
         }
         tokio::pin!(r, $f);
         match futures::future::select(r, $f).await {
-            futures::future::Either::Left(_) => {
-                Err("kill_switch triggered".into())
-            }
+            futures::future::Either::Left(_) => Err(killed(&$inner)),
             futures::future::Either::Right((res, _)) => res,
         }
     }};
@@ -76,6 +93,7 @@ impl KillSwitch {
                 std::sync::atomic::AtomicBool::new(true),
                 tx,
                 tokio::sync::Mutex::new(Vec::new()),
+                std::sync::Mutex::new(None),
             )),
             is_weak: false,
         }
@@ -102,6 +120,17 @@ impl KillSwitch {
         lock.push(cb);
     }
 
+    /// Set the error mixed futures fail with once this kill
+    /// switch is triggered, instead of a generic one.
+    pub fn set_kill_reason(&self, reason: KillReason) {
+        *lock_reason(&self.inner) = Some(reason);
+    }
+
+    /// The error set with `set_kill_reason`, if any.
+    pub fn kill_reason(&self) -> Option<LairError> {
+        kill_reason(&self.inner)
+    }
+
     /// Should we continue?
     pub fn cont(&self) -> bool {
         cont(&self.inner)
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+/// Create a directory (and any missing parents)
+/// only accessible by the current user.
+pub fn create_dir_all_private(path: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(path)
+}
+
+/// File open options creating files only accessible by the current user.
+pub fn private_open_options() -> std::fs::OpenOptions {
+    #[allow(unused_mut)]
+    let mut options = std::fs::OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+/// Restrict an existing file to only be accessible by the current user.
+pub fn set_private_permissions(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn it_creates_private_dirs_and_files() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let dir = tmpdir.path().join("a").join("b");
+        create_dir_all_private(&dir).unwrap();
+        assert_eq!(0o700, mode(&dir));
+        assert_eq!(0o700, mode(&tmpdir.path().join("a")));
+
+        let file = dir.join("file");
+        private_open_options()
+            .write(true)
+            .create(true)
+            .open(&file)
+            .unwrap();
+        assert_eq!(0o600, mode(&file));
+
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o666))
+            .unwrap();
+        set_private_permissions(&file).unwrap();
+        assert_eq!(0o600, mode(&file));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::*;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// True on platforms known to check the permissions of a unix socket
+/// when connecting to it. POSIX leaves this open, and some systems
+/// ignore them, leaving only the socket's directory to protect it.
+pub const SOCKET_MODES_ENFORCED: bool = cfg!(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+));
+
+/// Bind a unix socket at `path` with the permission bits `mode`.
+///
+/// The socket is bound in a fresh 0700 directory next to `path`, given
+/// `mode` there, then renamed into place, so it never appears at `path`
+/// with the permissions of the process umask. A socket left at `path`
+/// by a crashed server is replaced, one a live process still accepts
+/// connections on, or any other file, is not. Once bound, the socket
+/// must be owned by the current user, with exactly `mode`.
+pub fn bind_private_unix_socket(
+    path: &Path,
+    mode: u32,
+) -> LairResult<std::os::unix::net::UnixListener> {
+    if mode & !0o777 != 0 || mode & 0o600 != 0o600 {
+        return Err(format!(
+            "invalid socket mode {:04o}, expected at most 0777, \
+            readable and writable by the owner",
+            mode
+        )
+        .into());
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => return Err(format!("no directory in {}", path.display()).into()),
+    };
+    if !parent.exists() {
+        super::create_dir_all_private(parent)
+            .map_err(|e| LairError::path_context("creating", parent, e))?;
+    }
+    remove_stale_socket(path)?;
+
+    let staging = staging_dir(parent);
+    // left over by a server that crashed while binding
+    let _ = std::fs::remove_dir_all(&staging);
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&staging)
+            .map_err(|e| LairError::path_context("creating", &staging, e))?;
+    }
+    let res = bind_staged(&staging, path, mode);
+    let _ = std::fs::remove_dir_all(&staging);
+    let listener = res?;
+
+    if let Err(e) = verify_socket(path, mode) {
+        let _ = std::fs::remove_file(path);
+        return Err(e);
+    }
+    check_socket_dir(parent);
+
+    Ok(listener)
+}
+
+/// A directory next to the sockets of `parent`, private to this bind.
+/// Kept short, bound socket paths are limited to around 100 bytes.
+fn staging_dir(parent: &Path) -> PathBuf {
+    static NEXT: std::sync::atomic::AtomicU64 =
+        std::sync::atomic::AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    parent.join(format!(".lair-bind-{}-{}", std::process::id(), n))
+}
+
+fn bind_staged(
+    staging: &Path,
+    path: &Path,
+    mode: u32,
+) -> LairResult<std::os::unix::net::UnixListener> {
+    let staged = staging.join("s");
+    let listener = std::os::unix::net::UnixListener::bind(&staged)
+        .map_err(|e| LairError::path_context("binding", &staged, e))?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))
+        .map_err(|e| {
+            LairError::path_context("setting the mode of", &staged, e)
+        })?;
+    std::fs::rename(&staged, path).map_err(|e| {
+        LairError::path_context("moving the socket to", path, e)
+    })?;
+    Ok(listener)
+}
+
+/// Remove the socket at `path`, if any, once no process accepts
+/// connections on it anymore. Anything else at `path` is left alone.
+pub fn remove_stale_socket(path: &Path) -> LairResult<()> {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(LairError::path_context("reading", path, e)),
+    };
+    if !meta.file_type().is_socket() {
+        return Err(format!(
+            "refusing to replace {}, it is not a socket",
+            path.display()
+        )
+        .into());
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => {
+            Err(format!("{} is in use by a running process", path.display())
+                .into())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            info!(
+                "removing the stale socket {} of a previous run",
+                path.display()
+            );
+            std::fs::remove_file(path)
+                .map_err(|e| LairError::path_context("removing", path, e))
+        }
+        Err(e) => Err(LairError::path_context("connecting to", path, e)),
+    }
+}
+
+/// The socket at `path` must be owned by the current user, with `mode`.
+fn verify_socket(path: &Path, mode: u32) -> LairResult<()> {
+    let meta = std::fs::symlink_metadata(path)
+        .map_err(|e| LairError::path_context("reading", path, e))?;
+    // safe: geteuid has no preconditions and cannot fail
+    let euid = unsafe { libc::geteuid() };
+    if !meta.file_type().is_socket() || meta.uid() != euid {
+        return Err(format!(
+            "{} was replaced after binding, it is not our socket",
+            path.display()
+        )
+        .into());
+    }
+    if meta.mode() & 0o7777 != mode {
+        return Err(format!(
+            "{} has mode {:04o}, expected {:04o}",
+            path.display(),
+            meta.mode() & 0o7777,
+            mode
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Warn about a socket directory that does not protect the socket:
+/// one other users could replace the socket in, or, where socket modes
+/// are not enforced, one they could connect to the socket through.
+fn check_socket_dir(parent: &Path) {
+    let meta = match std::fs::metadata(parent) {
+        Ok(meta) => meta,
+        Err(e) => {
+            warn!("cannot read {}: {}", parent.display(), e);
+            return;
+        }
+    };
+    let mode = meta.mode() & 0o7777;
+    // a sticky directory, e.g. /tmp, keeps others from renaming our socket
+    if mode & 0o022 != 0 && mode & 0o1000 == 0 {
+        warn!(
+            "{} has mode {:04o}, other users could replace the socket in it",
+            parent.display(),
+            mode
+        );
+    }
+    if !SOCKET_MODES_ENFORCED {
+        if mode & 0o077 == 0 {
+            info!(
+                "socket modes are not enforced on this platform, \
+                relying on the mode {:04o} of {}",
+                mode,
+                parent.display()
+            );
+        } else {
+            warn!(
+                "socket modes are not enforced on this platform, \
+                and {} has mode {:04o}, other users may connect",
+                parent.display(),
+                mode
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::symlink_metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn it_binds_sockets_with_the_given_mode() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("lair").join("socket");
+
+        let listener = bind_private_unix_socket(&path, 0o600).unwrap();
+        assert_eq!(0o600, mode(&path));
+        // created private, and nothing left behind
+        assert_eq!(0o700, mode(path.parent().unwrap()));
+        assert_eq!(
+            1,
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count()
+        );
+        std::os::unix::net::UnixStream::connect(&path).unwrap();
+        drop(listener);
+
+        let path = tmpdir.path().join("group");
+        let _listener = bind_private_unix_socket(&path, 0o660).unwrap();
+        assert_eq!(0o660, mode(&path));
+
+        for bad in &[0o400, 0o200, 0o1600, 0o7777] {
+            assert!(bind_private_unix_socket(&tmpdir.path().join("bad"), *bad)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn it_only_replaces_stale_sockets() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("socket");
+
+        // a live socket is kept
+        let live = bind_private_unix_socket(&path, 0o600).unwrap();
+        let err = bind_private_unix_socket(&path, 0o600).unwrap_err();
+        assert!(err.to_string().contains("in use"), "{}", err);
+        std::os::unix::net::UnixStream::connect(&path).unwrap();
+
+        // once its process is gone, it is replaced
+        drop(live);
+        assert!(path.exists());
+        let _listener = bind_private_unix_socket(&path, 0o600).unwrap();
+        std::os::unix::net::UnixStream::connect(&path).unwrap();
+
+        // other files are never removed
+        let file = tmpdir.path().join("file");
+        std::fs::write(&file, b"keep").unwrap();
+        let err = bind_private_unix_socket(&file, 0o600).unwrap_err();
+        assert!(err.to_string().contains("not a socket"), "{}", err);
+        assert_eq!(b"keep".to_vec(), std::fs::read(&file).unwrap());
+    }
+}
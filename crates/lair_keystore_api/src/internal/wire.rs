@@ -1,486 +1,4106 @@
 //! Lair Wire Protocol Utilities
 
-use crate::{actor::*, internal::codec, *};
+use crate::{actor::*, internal::codec, passphrase::Passphrase, *};
 
-macro_rules! default_encode_setup {
-    ($msg_id:ident, $wire_type:ident) => {{
-        let mut writer = codec::CodecWriter::new(256)?;
-        writer.write_u32(256)?;
-        writer.write_u32($wire_type)?;
-        writer.write_u64(*$msg_id)?;
+pub mod compress;
+pub mod spec;
+
+/// Max byte length of the message in an ErrorResponse.
+pub(crate) const MAX_ERROR_MESSAGE_LEN: usize = 4096;
+
+/// One cause in the chain of an ErrorResponse, outermost first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCause {
+    /// `spec::ERROR_CAUSE_OTHER`, or for io errors the code of their
+    /// kind, see `spec::error_cause_io_code`.
+    pub code: u32,
+
+    /// The message of the cause.
+    pub message: String,
+}
+
+/// The error of one item of a bulk entry response, items without
+/// one succeeded. Typed as an ErrorResponse is, without its causes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkItemError {
+    /// The ErrorResponse kind of the error.
+    pub kind: u32,
+
+    /// The message of the error.
+    pub message: String,
+}
+
+/// Max byte length of the error in an UnlockComplete event.
+pub(crate) const MAX_UNLOCK_ERROR_LEN: usize = 128;
+
+/// Max byte length of the operation name in an AuditEvent.
+const MAX_AUDIT_OPERATION_LEN: usize = 64;
+
+/// Max byte length of the pub key in an AuditEvent, that of ed448.
+const MAX_AUDIT_PUB_KEY_LEN: usize = 57;
+
+/// Max byte length of paths and urls in a GetServerPaths response.
+const MAX_PATH_LEN: usize = 4096;
+
+/// Max byte length of a cert digest of any `DigestAlg`.
+const MAX_DIGEST_LEN: usize = 64;
+
+/// Error if an ssh key import would not fit its wire message.
+pub(crate) fn check_ssh_key_import(
+    key: &[u8],
+    passphrase: Option<&str>,
+) -> LairResult<()> {
+    if key.len() > MAX_SSH_KEY_LEN {
+        return Err(format!(
+            "ssh key exceeds {} byte maximum",
+            MAX_SSH_KEY_LEN
+        )
+        .into());
+    }
+    if passphrase.map_or(0, str::len) > MAX_SSH_KEY_PASSPHRASE_LEN {
+        return Err(format!(
+            "ssh key passphrase exceeds {} byte maximum",
+            MAX_SSH_KEY_PASSPHRASE_LEN
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Error unless `request` can travel in a `ToLairRequestDeadline`
+/// with id `msg_id`: a request sharing its id, and not itself
+/// carrying a deadline.
+fn check_deadline_request(msg_id: u64, request: &LairWire) -> LairResult<()> {
+    if !request.is_req()
+        || request.is_event()
+        || request.get_msg_id() != msg_id
+        || matches!(request, LairWire::ToLairRequestDeadline { .. })
+    {
+        return Err("invalid request in deadline request".into());
+    }
+    Ok(())
+}
+
+/// Error if `len` exceeds `MAX_BULK_ENTRIES_LEN`.
+pub(crate) fn check_bulk_entries_len(len: usize) -> LairResult<()> {
+    if len > MAX_BULK_ENTRIES_LEN as usize {
+        return Err(format!(
+            "at most {} entries per bulk request, got {}",
+            MAX_BULK_ENTRIES_LEN, len
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Error if `len` exceeds `MAX_GET_ENTRY_TYPES_LEN`.
+pub(crate) fn check_entry_types_len(len: usize) -> LairResult<()> {
+    if len > MAX_GET_ENTRY_TYPES_LEN as usize {
+        return Err(format!(
+            "at most {} entry types per batch, got {}",
+            MAX_GET_ENTRY_TYPES_LEN, len
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Start encoding a message of `$len` bytes, header included,
+/// zero padded past the last field written.
+macro_rules! sized_encode_setup {
+    ($len:expr, $msg_id:ident, $wire_type:ident) => {{
+        let len = $len;
+        let mut writer = codec::CodecWriter::new_zeroed(len)?;
+        writer.write_bytes(&spec::encode_header(len, $wire_type, *$msg_id)?)?;
         writer
     }};
 }
 
+/// Start encoding a message padded to `spec::PADDED_MESSAGE_LEN`.
+macro_rules! default_encode_setup {
+    ($msg_id:ident, $wire_type:ident) => {
+        sized_encode_setup!(spec::PADDED_MESSAGE_LEN, $msg_id, $wire_type)
+    };
+}
+
 macro_rules! wire_type_meta_macro {
     ($macro_name:ident) => {
         $macro_name! {
-            ToCliRequestUnlockPassphrase 0xff000010 true true {
+            ErrorResponse 0x00000001 false false {
+                kind: u32,
+                request_id: u64,
+                message: String,
+                causes: Vec<ErrorCause>,
+                details: Vec<u64>,
             } |msg_id, wire_type| {
-                let writer = default_encode_setup!(msg_id, wire_type);
+                if causes.len() > spec::MAX_ERROR_CAUSES as usize {
+                    return Err("too many error causes".into());
+                }
+                if details.len() > spec::MAX_ERROR_DETAILS as usize {
+                    return Err("too many error details".into());
+                }
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*kind)?;
+                writer.write_u64(*request_id)?;
+                writer.write_str(message, MAX_ERROR_MESSAGE_LEN)?;
+                writer.write_u32(causes.len() as u32)?;
+                for cause in causes.iter() {
+                    writer.write_u32(cause.code)?;
+                    writer.write_str(&cause.message, MAX_ERROR_MESSAGE_LEN)?;
+                }
+                writer.write_u32(details.len() as u32)?;
+                for detail in details.iter() {
+                    writer.write_u64(*detail)?;
+                }
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToCliRequestUnlockPassphrase { msg_id }
+                let kind = reader.read_u32()?;
+                let request_id = reader.read_u64()?;
+                let message = reader.read_str()?;
+                // peers predating causes leave the zero padding,
+                // or nothing, here
+                let causes = if reader.remaining() >= spec::U32_LEN {
+                    reader.read_error_causes()?
+                } else {
+                    Vec::new()
+                };
+                // as are peers predating details
+                let details = if reader.remaining() >= spec::U32_LEN {
+                    reader.read_error_details()?
+                } else {
+                    Vec::new()
+                };
+                LairWire::ErrorResponse {
+                    msg_id,
+                    kind,
+                    request_id,
+                    message,
+                    causes,
+                    details,
+                }
             },
-            ToLairRequestUnlockPassphraseResponse 0xff000011 true false {
-                passphrase: String,
+            ToLairRequestDeadline 0x00000002 false true {
+                remaining_ms: u64,
+                request: Box<LairWire>,
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(passphrase, 128)?;
+                check_deadline_request(*msg_id, request)?;
+                let request = request.encode()?;
+                let size = spec::HEADER_LEN
+                    + 8 // remaining ms
+                    + spec::LEN_PREFIX_LEN // request length
+                    + request.len(); // request content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(*remaining_ms)?;
+                writer.write_sized_bytes(&request, u32::MAX as usize)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let passphrase = reader.read_str()?;
-                LairWire::ToLairRequestUnlockPassphraseResponse {
+                let remaining_ms = reader.read_u64()?;
+                let request =
+                    Box::new(LairWire::decode(&reader.read_sized_bytes()?)?);
+                check_deadline_request(msg_id, &request)?;
+                LairWire::ToLairRequestDeadline {
                     msg_id,
-                    passphrase,
+                    remaining_ms,
+                    request,
                 }
             },
-            ToLairLairGetLastEntryIndex 0x00000010 false true {
+            ToLairCancelRequest 0x00000003 false true {
             } |msg_id, wire_type| {
                 let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairLairGetLastEntryIndex { msg_id }
+                LairWire::ToLairCancelRequest { msg_id }
             },
-            ToCliLairGetLastEntryIndexResponse 0x00000011 false false {
-                last_keystore_index: KeystoreIndex,
+            ToLairNegotiateCompression 0x00000004 false true {
+                algorithms: u32,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**last_keystore_index)?;
+                writer.write_u32(*algorithms)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let last_keystore_index = reader.read_u32()?;
-                LairWire::ToCliLairGetLastEntryIndexResponse {
-                    msg_id,
-                    last_keystore_index: last_keystore_index.into(),
-                }
+                let algorithms = reader.read_u32()?;
+                LairWire::ToLairNegotiateCompression { msg_id, algorithms }
             },
-            ToLairLairGetEntryType 0x00000020 false true {
-                keystore_index: KeystoreIndex,
+            ToCliNegotiateCompressionResponse 0x00000005 false false {
+                algorithm: u32,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*algorithm)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairLairGetEntryType {
+                let algorithm = reader.read_u32()?;
+                LairWire::ToCliNegotiateCompressionResponse {
                     msg_id,
-                    keystore_index: keystore_index.into(),
+                    algorithm,
                 }
             },
-            ToCliLairGetEntryTypeResponse 0x00000021 false false {
-                lair_entry_type: LairEntryType,
+            ToLairAuthenticate 0x00000100 false true {
+                token: String,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(*lair_entry_type as u32)?;
-                Ok(writer.into_vec())
-            } |reader| {
-                let msg_id = reader.read_u64()?;
-                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
-                LairWire::ToCliLairGetEntryTypeResponse {
-                    msg_id,
-                    lair_entry_type,
-                }
-            },
-            ToLairLairGetServerInfo 0x00000030 false true {
-            } |msg_id, wire_type| {
-                let writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(token, MAX_CONNECTION_TOKEN_LEN)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairLairGetServerInfo { msg_id }
+                let token = reader.read_str()?;
+                LairWire::ToLairAuthenticate { msg_id, token }
             },
-            ToCliLairGetServerInfoResponse 0x00000031 false false {
-                info: LairServerInfo,
+            ToCliAuthenticateResponse 0x00000101 false false {
+                capabilities: LairCapabilities,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(&info.name, 64)?;
-                writer.write_str(&info.version, 64)?;
+                writer.write_u32(capabilities.0)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let name = reader.read_str()?;
-                let version = reader.read_str()?;
-                LairWire::ToCliLairGetServerInfoResponse {
+                let capabilities = LairCapabilities(reader.read_u32()?);
+                LairWire::ToCliAuthenticateResponse {
                     msg_id,
-                    info: LairServerInfo { name, version },
+                    capabilities,
                 }
             },
-            ToLairTlsCertNewSelfSignedFromEntropy 0x00000110 false true {
-                cert_alg: TlsCertAlg,
+            ToCliRequestUnlockPassphrase 0xff000010 true true {
+                store_name: String,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(*cert_alg as u32)?;
+                writer.write_str(store_name, MAX_STORE_NAME_LEN)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
-                LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
-                    msg_id,
-                    cert_alg,
-                }
+                let store_name = reader.read_str()?;
+                LairWire::ToCliRequestUnlockPassphrase { msg_id, store_name }
             },
-            ToCliTlsCertNewSelfSignedFromEntropyResponse 0x00000111 false false {
-                keystore_index: KeystoreIndex,
-                cert_sni: CertSni,
-                cert_digest: CertDigest,
+            ToLairRequestUnlockPassphraseResponse 0xff000011 true false {
+                passphrase: Passphrase,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
-                writer.write_str(cert_sni, 128)?;
-                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_sized_bytes(passphrase.read(), 128)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                let cert_sni = reader.read_str()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                // straight from the frame, which the ipc read loop
+                // zeroizes, see `LairWire::carries_secret`
+                let len = reader.read_u64()?;
+                let passphrase = Passphrase::from(reader.read_bytes(len)?);
+                LairWire::ToLairRequestUnlockPassphraseResponse {
                     msg_id,
-                    keystore_index: keystore_index.into(),
-                    cert_sni: cert_sni.into(),
-                    cert_digest: cert_digest.into(),
+                    passphrase,
                 }
             },
-            ToLairTlsCertGet 0x00000120 false true {
-                keystore_index: KeystoreIndex,
+            ToCliUnlockProgress 0xff000020 true true {
+                percent: u8,
             } |msg_id, wire_type| {
+                if *percent > 100 {
+                    return Err(format!(
+                        "invalid unlock progress: {}",
+                        percent
+                    )
+                    .into());
+                }
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*percent as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGet {
+                let percent = reader.read_u32()?;
+                if percent > 100 {
+                    return Err(format!(
+                        "invalid unlock progress: {}",
+                        percent
+                    )
+                    .into());
+                }
+                LairWire::ToCliUnlockProgress {
                     msg_id,
-                    keystore_index: keystore_index.into(),
+                    percent: percent as u8,
                 }
             },
-            ToCliTlsCertGetResponse 0x00000121 false false {
-                cert_sni: CertSni,
-                cert_digest: CertDigest,
+            ToLairUnlockProgressResponse 0xff000021 true false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
-                writer.write_bytes_exact(cert_digest, 32)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliTlsCertGetResponse {
-                    msg_id,
-                    cert_sni: cert_sni.into(),
-                    cert_digest: cert_digest.into(),
-                }
+                LairWire::ToLairUnlockProgressResponse { msg_id }
             },
-            ToLairTlsCertGetCertByIndex 0x00000130 false true {
-                keystore_index: KeystoreIndex,
+            ToCliUnlockComplete 0xff000030 true true {
+                error: Option<String>,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                match error {
+                    Some(error) => {
+                        writer.write_bool(true)?;
+                        writer.write_str(error, MAX_UNLOCK_ERROR_LEN)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGetCertByIndex {
-                    msg_id,
-                    keystore_index: keystore_index.into(),
-                }
+                let error = match reader.read_bool()? {
+                    true => Some(reader.read_str()?),
+                    false => None,
+                };
+                LairWire::ToCliUnlockComplete { msg_id, error }
             },
-            ToCliTlsCertGetCertByIndexResponse 0x00000131 false false {
-                cert: Cert,
+            ToLairUnlockCompleteResponse 0xff000031 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertByIndexResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairUnlockCompleteResponse { msg_id }
             },
-            ToLairTlsCertGetCertByDigest 0x00000140 false true {
-                cert_digest: CertDigest,
+            ToCliEventsDropped 0xff000040 true true {
+                count: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_u64(*count)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToLairTlsCertGetCertByDigest {
-                    msg_id,
-                    cert_digest: cert_digest.into(),
-                }
+                let count = reader.read_u64()?;
+                LairWire::ToCliEventsDropped { msg_id, count }
             },
-            ToCliTlsCertGetCertByDigestResponse 0x00000141 false false {
-                cert: Cert,
+            ToLairEventsDroppedResponse 0xff000041 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertByDigestResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairEventsDroppedResponse { msg_id }
             },
-            ToLairTlsCertGetCertBySni 0x00000150 false true {
-                cert_sni: CertSni,
+            ToCliEntryCreated 0xff000060 true true {
+                keystore_index: KeystoreIndex,
+                entry_type: LairEntryType,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*entry_type as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                LairWire::ToLairTlsCertGetCertBySni {
+                let keystore_index = reader.read_u32()?.into();
+                let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliEntryCreated {
                     msg_id,
-                    cert_sni: cert_sni.into(),
+                    keystore_index,
+                    entry_type,
                 }
             },
-            ToCliTlsCertGetCertBySniResponse 0x00000151 false false {
-                cert: Cert,
+            ToLairEntryCreatedResponse 0xff000061 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertBySniResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairEntryCreatedResponse { msg_id }
             },
-            ToLairTlsCertGetPrivKeyByIndex 0x00000160 false true {
+            ToCliRequestSignApproval 0xff000070 true true {
                 keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                let keystore_index = reader.read_u32()?.into();
+                let pub_key = reader.read_bytes(32)?.to_vec().into();
+                LairWire::ToCliRequestSignApproval {
                     msg_id,
-                    keystore_index: keystore_index.into(),
+                    keystore_index,
+                    pub_key,
                 }
             },
-            ToCliTlsCertGetPrivKeyByIndexResponse 0x00000161 false false {
-                cert_priv_key: CertPrivKey,
+            ToLairRequestSignApprovalResponse 0xff000071 true false {
+                approved: bool,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                writer.write_bool(*approved)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
-                    msg_id,
-                    cert_priv_key: cert_priv_key.into(),
-                }
+                let approved = reader.read_bool()?;
+                LairWire::ToLairRequestSignApprovalResponse { msg_id, approved }
             },
-            ToLairTlsCertGetPrivKeyByDigest 0x00000170 false true {
-                cert_digest: CertDigest,
+            ToCliClockSkewDetected 0xff000080 true true {
+                wall_secs: u64,
+                expected_secs: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_u64(*wall_secs)?;
+                writer.write_u64(*expected_secs)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                let wall_secs = reader.read_u64()?;
+                let expected_secs = reader.read_u64()?;
+                LairWire::ToCliClockSkewDetected {
                     msg_id,
-                    cert_digest: cert_digest.into(),
+                    wall_secs,
+                    expected_secs,
                 }
             },
-            ToCliTlsCertGetPrivKeyByDigestResponse 0x00000171 false false {
-                cert_priv_key: CertPrivKey,
+            ToLairClockSkewDetectedResponse 0xff000081 true false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
-                    msg_id,
-                    cert_priv_key: cert_priv_key.into(),
-                }
+                LairWire::ToLairClockSkewDetectedResponse { msg_id }
             },
-            ToLairTlsCertGetPrivKeyBySni 0x00000180 false true {
-                cert_sni: CertSni,
+            ToCliEntryActivated 0xff000090 true true {
+                keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
+                writer.write_u32(**keystore_index)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                LairWire::ToLairTlsCertGetPrivKeyBySni {
+                let keystore_index = reader.read_u32()?.into();
+                LairWire::ToCliEntryActivated {
                     msg_id,
-                    cert_sni: cert_sni.into(),
+                    keystore_index,
                 }
             },
-            ToCliTlsCertGetPrivKeyBySniResponse 0x00000181 false false {
-                cert_priv_key: CertPrivKey,
+            ToLairEntryActivatedResponse 0xff000091 true false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairEntryActivatedResponse { msg_id }
+            },
+            ToCliAuditEvent 0xff0000a0 true true {
+                record: LairAuditRecord,
+                dropped: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                writer.write_audit_record(record)?;
+                writer.write_u64(*dropped)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                let record = reader.read_audit_record()?;
+                let dropped = reader.read_u64()?;
+                LairWire::ToCliAuditEvent {
                     msg_id,
-                    cert_priv_key: cert_priv_key.into(),
+                    record,
+                    dropped,
                 }
             },
-            ToLairSignEd25519NewFromEntropy 0x00000210 false true {
+            ToLairAuditEventResponse 0xff0000a1 true false {
             } |msg_id, wire_type| {
                 let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairSignEd25519NewFromEntropy { msg_id }
+                LairWire::ToLairAuditEventResponse { msg_id }
             },
-            ToCliSignEd25519NewFromEntropyResponse 0x00000211 false false {
+            ToCliEntryRemoved 0xff0000b0 true true {
                 keystore_index: KeystoreIndex,
-                pub_key: SignEd25519PubKey,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
-                writer.write_bytes_exact(pub_key, 32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                let keystore_index = reader.read_u32()?.into();
+                LairWire::ToCliEntryRemoved {
                     msg_id,
-                    keystore_index: keystore_index.into(),
-                    pub_key: pub_key.into(),
+                    keystore_index,
                 }
             },
-            ToLairSignEd25519Get 0x00000220 false true {
-                keystore_index: KeystoreIndex,
+            ToLairEntryRemovedResponse 0xff0000b1 true false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairSignEd25519Get {
-                    msg_id,
-                    keystore_index: keystore_index.into(),
-                }
+                LairWire::ToLairEntryRemovedResponse { msg_id }
             },
-            ToCliSignEd25519GetResponse 0x00000221 false false {
-                pub_key: SignEd25519PubKey,
+            ToCliGoodbye 0xff000050 true true {
+                kind: u32,
+                message: String,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_u32(*kind)?;
+                writer.write_str(message, MAX_ERROR_MESSAGE_LEN)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
+                let kind = reader.read_u32()?;
+                let message = reader.read_str()?;
+                LairWire::ToCliGoodbye {
+                    msg_id,
+                    kind,
+                    message,
+                }
+            },
+            ToLairGoodbyeResponse 0xff000051 true false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairGoodbyeResponse { msg_id }
+            },
+            ToLairLairSelectStore 0x000000d0 false true {
+                name: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(name, MAX_STORE_NAME_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let name = reader.read_str()?;
+                LairWire::ToLairLairSelectStore { msg_id, name }
+            },
+            ToCliLairSelectStoreResponse 0x000000d1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSelectStoreResponse { msg_id }
+            },
+            ToLairLairGetLastEntryIndex 0x00000010 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetLastEntryIndex { msg_id }
+            },
+            ToCliLairGetLastEntryIndexResponse 0x00000011 false false {
+                last_keystore_index: Option<KeystoreIndex>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                match last_keystore_index {
+                    Some(last_keystore_index) => {
+                        writer.write_bool(true)?;
+                        writer.write_u32(**last_keystore_index)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let last_keystore_index = match reader.read_bool()? {
+                    true => Some(reader.read_u32()?.into()),
+                    false => None,
+                };
+                LairWire::ToCliLairGetLastEntryIndexResponse {
+                    msg_id,
+                    last_keystore_index,
+                }
+            },
+            ToLairLairGetEntryCount 0x000000f0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetEntryCount { msg_id }
+            },
+            ToCliLairGetEntryCountResponse 0x000000f1 false false {
+                entry_count: u64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(*entry_count)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let entry_count = reader.read_u64()?;
+                LairWire::ToCliLairGetEntryCountResponse { msg_id, entry_count }
+            },
+            ToLairLairGetStoreStats 0x00001100 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetStoreStats { msg_id }
+            },
+            ToCliLairGetStoreStatsResponse 0x00001101 false false {
+                stats: StoreStats,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 8 // file size
+                    + spec::COUNT_LEN // entry type count
+                    + stats.entry_counts.len() * (
+                        4 // entry type
+                        + 8 // entry count
+                    )
+                    + 8 // erased count
+                    + 8 // quarantined count
+                    + 8; // trashed count
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(stats.file_size)?;
+                writer.write_u32(stats.entry_counts.len() as u32)?;
+                for (entry_type, count) in stats.entry_counts.iter() {
+                    writer.write_u32(*entry_type as u32)?;
+                    writer.write_u64(*count)?;
+                }
+                writer.write_u64(stats.erased_count)?;
+                writer.write_u64(stats.quarantined_count)?;
+                writer.write_u64(stats.trashed_count)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let file_size = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                let mut entry_counts = Vec::new();
+                for _ in 0..count {
+                    let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                    entry_counts.push((entry_type, reader.read_u64()?));
+                }
+                let erased_count = reader.read_u64()?;
+                let quarantined_count = reader.read_u64()?;
+                let trashed_count = reader.read_u64()?;
+                LairWire::ToCliLairGetStoreStatsResponse {
+                    msg_id,
+                    stats: StoreStats {
+                        file_size,
+                        entry_counts,
+                        erased_count,
+                        quarantined_count,
+                        trashed_count,
+                    },
+                }
+            },
+            ToLairLairGetEntryType 0x00000020 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairLairGetEntryType {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliLairGetEntryTypeResponse 0x00000021 false false {
+                lair_entry_type: LairEntryType,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*lair_entry_type as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetEntryTypeResponse {
+                    msg_id,
+                    lair_entry_type,
+                }
+            },
+            ToLairLairGetServerInfo 0x00000030 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetServerInfo { msg_id }
+            },
+            ToCliLairGetServerInfoResponse 0x00000031 false false {
+                info: LairServerInfo,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(&info.name, 64)?;
+                writer.write_str(&info.version, 64)?;
+                writer.write_sized_bytes(&info.id_pub_key, 32)?;
+                writer.write_bool(info.is_locked)?;
+                writer.write_u32(info.wire_protocol_version)?;
+                writer.write_u32(info.entropy_status as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let name = reader.read_str()?;
+                let version = reader.read_str()?;
+                let id_pub_key = reader.read_sized_bytes()?.into();
+                let is_locked = reader.read_bool()?;
+                // servers predating it leave the zero padding here
+                let wire_protocol_version = reader.read_u32()?;
+                let entropy_status = EntropyStatus::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetServerInfoResponse {
+                    msg_id,
+                    info: LairServerInfo {
+                        name,
+                        version,
+                        id_pub_key,
+                        is_locked,
+                        wire_protocol_version,
+                        entropy_status,
+                    },
+                }
+            },
+            ToLairLairGetServerPaths 0x00000050 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetServerPaths { msg_id }
+            },
+            ToCliLairGetServerPathsResponse 0x00000051 false false {
+                paths: LairServerPaths,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(
+                    &paths.root_path.to_string_lossy(),
+                    MAX_PATH_LEN,
+                )?;
+                writer.write_str(
+                    &paths.store_path.to_string_lossy(),
+                    MAX_PATH_LEN,
+                )?;
+                writer.write_str(
+                    &paths.connection_url.to_string(),
+                    MAX_PATH_LEN,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let root_path = reader.read_str()?.into();
+                let store_path = reader.read_str()?.into();
+                let connection_url = reader.read_str()?.parse()?;
+                LairWire::ToCliLairGetServerPathsResponse {
+                    msg_id,
+                    paths: LairServerPaths {
+                        root_path,
+                        store_path,
+                        connection_url,
+                    },
+                }
+            },
+            ToLairLairGetEntryTypes 0x000000e0 false true {
+                indices: Vec<KeystoreIndex>,
+            } |msg_id, wire_type| {
+                check_entry_types_len(indices.len())?;
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(indices.len() as u32)?;
+                for index in indices.iter() {
+                    writer.write_u32(**index)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                check_entry_types_len(count as usize)?;
+                let mut indices = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    indices.push(reader.read_u32()?.into());
+                }
+                LairWire::ToLairLairGetEntryTypes { msg_id, indices }
+            },
+            ToCliLairGetEntryTypesResponse 0x000000e1 false false {
+                entry_types: Vec<(KeystoreIndex, LairEntryType)>,
+            } |msg_id, wire_type| {
+                check_entry_types_len(entry_types.len())?;
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(entry_types.len() as u32)?;
+                for (index, entry_type) in entry_types.iter() {
+                    writer.write_u32(**index)?;
+                    writer.write_u32(*entry_type as u32)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                check_entry_types_len(count as usize)?;
+                let mut entry_types = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let index = reader.read_u32()?.into();
+                    let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                    entry_types.push((index, entry_type));
+                }
+                LairWire::ToCliLairGetEntryTypesResponse { msg_id, entry_types }
+            },
+            ToLairLairListEntriesPage 0x00000040 false true {
+                start: KeystoreIndex,
+                limit: u32,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**start)?;
+                writer.write_u32(*limit)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let start = reader.read_u32()?;
+                let limit = reader.read_u32()?;
+                LairWire::ToLairLairListEntriesPage {
+                    msg_id,
+                    start: start.into(),
+                    limit,
+                }
+            },
+            ToCliLairListEntriesPageResponse 0x00000041 false false {
+                entries: Vec<LairEntryInfo>,
+                next_start: Option<KeystoreIndex>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(entries.len() as u32)?;
+                for entry in entries.iter() {
+                    writer.write_u32(*entry.keystore_index)?;
+                    writer.write_u32(entry.entry_type as u32)?;
+                    writer.write_sized_bytes(
+                        &entry.pub_id,
+                        MAX_ENTRY_PUB_ID_LEN,
+                    )?;
+                    writer.write_u32(entry.aliases.len() as u32)?;
+                    for alias in entry.aliases.iter() {
+                        writer.write_str(alias, MAX_ENTRY_TAG_LEN)?;
+                    }
+                    writer.write_bool(entry.trashed)?;
+                }
+                match next_start {
+                    Some(next_start) => {
+                        writer.write_bool(true)?;
+                        writer.write_u32(**next_start)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                // trailing, so peers predating rotation links ignore them
+                writer.write_u32(entries.len() as u32)?;
+                for entry in entries.iter() {
+                    match entry.rotated_from {
+                        Some(rotated_from) => {
+                            writer.write_bool(true)?;
+                            writer.write_u32(*rotated_from)?;
+                        }
+                        None => writer.write_bool(false)?,
+                    }
+                }
+                // trailing, so peers predating activation ignore them
+                writer.write_u32(entries.len() as u32)?;
+                for entry in entries.iter() {
+                    writer.write_bool(entry.activated)?;
+                }
+                // trailing, so peers predating tags in listings ignore them
+                writer.write_u32(entries.len() as u32)?;
+                for entry in entries.iter() {
+                    match &entry.tag {
+                        Some(tag) => {
+                            writer.write_bool(true)?;
+                            writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                        }
+                        None => writer.write_bool(false)?,
+                    }
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                if count > MAX_LIST_ENTRIES_PAGE_LIMIT {
+                    return Err("too many entries in page".into());
+                }
+                let mut entries = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let keystore_index = reader.read_u32()?.into();
+                    let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                    let pub_id = Arc::new(reader.read_sized_bytes()?);
+                    let alias_count = reader.read_u32()?;
+                    if alias_count as usize > MAX_ENTRY_ALIASES {
+                        return Err("too many aliases in entry".into());
+                    }
+                    let mut aliases = Vec::with_capacity(alias_count as usize);
+                    for _ in 0..alias_count {
+                        aliases.push(reader.read_str()?);
+                    }
+                    let trashed = reader.read_bool()?;
+                    entries.push(LairEntryInfo {
+                        keystore_index,
+                        entry_type,
+                        pub_id,
+                        tag: None,
+                        aliases,
+                        trashed,
+                        rotated_from: None,
+                        activated: true,
+                    });
+                }
+                let next_start = match reader.read_bool()? {
+                    true => Some(reader.read_u32()?.into()),
+                    false => None,
+                };
+                // peers predating rotation links leave the zero padding,
+                // or nothing, where they would be
+                let link_count = match reader.remaining() >= spec::U32_LEN {
+                    true => reader.read_u32()?,
+                    false => 0,
+                };
+                if link_count != 0 {
+                    if link_count as usize != entries.len() {
+                        return Err("rotation links do not match entries".into());
+                    }
+                    for entry in entries.iter_mut() {
+                        if reader.read_bool()? {
+                            entry.rotated_from = Some(reader.read_u32()?.into());
+                        }
+                    }
+                }
+                // peers predating activation leave the zero padding,
+                // or nothing, where the states would be: all activated
+                let activation_count = match reader.remaining() >= spec::U32_LEN {
+                    true => reader.read_u32()?,
+                    false => 0,
+                };
+                if activation_count != 0 {
+                    if activation_count as usize != entries.len() {
+                        return Err(
+                            "activation states do not match entries".into()
+                        );
+                    }
+                    for entry in entries.iter_mut() {
+                        entry.activated = reader.read_bool()?;
+                    }
+                }
+                // peers predating tags in listings leave the zero padding,
+                // or nothing, where they would be
+                let tag_count = match reader.remaining() >= spec::U32_LEN {
+                    true => reader.read_u32()?,
+                    false => 0,
+                };
+                if tag_count != 0 {
+                    if tag_count as usize != entries.len() {
+                        return Err("tags do not match entries".into());
+                    }
+                    for entry in entries.iter_mut() {
+                        if reader.read_bool()? {
+                            entry.tag = Some(reader.read_str()?);
+                        }
+                    }
+                }
+                LairWire::ToCliLairListEntriesPageResponse {
+                    msg_id,
+                    entries,
+                    next_start,
+                }
+            },
+            ToLairLairGetRandomBytes 0x00000080 false true {
+                len: u32,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*len)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let len = reader.read_u32()?;
+                LairWire::ToLairLairGetRandomBytes { msg_id, len }
+            },
+            ToCliLairGetRandomBytesResponse 0x00000081 false false {
+                bytes: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // bytes length
+                    + bytes.len(); // bytes content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(
+                    bytes,
+                    MAX_RANDOM_BYTES_LEN as usize,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let bytes = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliLairGetRandomBytesResponse { msg_id, bytes }
+            },
+            ToLairLairNewUuid 0x00000090 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairNewUuid { msg_id }
+            },
+            ToCliLairNewUuidResponse 0x00000091 false false {
+                uuid: [u8; 16],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes(uuid)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut uuid = [0; 16];
+                uuid.copy_from_slice(reader.read_bytes(16)?);
+                LairWire::ToCliLairNewUuidResponse { msg_id, uuid }
+            },
+            ToLairLairSetEntryTag 0x00000060 false true {
+                keystore_index: KeystoreIndex,
+                tag: Option<String>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                match tag {
+                    Some(tag) => {
+                        writer.write_bool(true)?;
+                        writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let tag = match reader.read_bool()? {
+                    true => Some(reader.read_str()?),
+                    false => None,
+                };
+                LairWire::ToLairLairSetEntryTag {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    tag,
+                }
+            },
+            ToCliLairSetEntryTagResponse 0x00000061 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSetEntryTagResponse { msg_id }
+            },
+            ToLairLairGetEntryByTag 0x00000070 false true {
+                tag: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairLairGetEntryByTag { msg_id, tag }
+            },
+            ToCliLairGetEntryByTagResponse 0x00000071 false false {
+                keystore_index: KeystoreIndex,
+                lair_entry_type: LairEntryType,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*lair_entry_type as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetEntryByTagResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    lair_entry_type,
+                }
+            },
+            ToLairLairAddEntryAlias 0x00001010 false true {
+                keystore_index: KeystoreIndex,
+                alias: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(alias, MAX_ENTRY_TAG_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let alias = reader.read_str()?;
+                LairWire::ToLairLairAddEntryAlias {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    alias,
+                }
+            },
+            ToCliLairAddEntryAliasResponse 0x00001011 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairAddEntryAliasResponse { msg_id }
+            },
+            ToLairLairRemoveEntryAlias 0x00001020 false true {
+                alias: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(alias, MAX_ENTRY_TAG_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let alias = reader.read_str()?;
+                LairWire::ToLairLairRemoveEntryAlias { msg_id, alias }
+            },
+            ToCliLairRemoveEntryAliasResponse 0x00001021 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairRemoveEntryAliasResponse { msg_id }
+            },
+            ToLairLairTrashEntry 0x00001030 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                LairWire::ToLairLairTrashEntry { msg_id, keystore_index }
+            },
+            ToCliLairTrashEntryResponse 0x00001031 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairTrashEntryResponse { msg_id }
+            },
+            ToLairLairRestoreEntry 0x00001040 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                LairWire::ToLairLairRestoreEntry { msg_id, keystore_index }
+            },
+            ToCliLairRestoreEntryResponse 0x00001041 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairRestoreEntryResponse { msg_id }
+            },
+            ToLairLairActivateEntry 0x000010f0 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                LairWire::ToLairLairActivateEntry { msg_id, keystore_index }
+            },
+            ToCliLairActivateEntryResponse 0x000010f1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairActivateEntryResponse { msg_id }
+            },
+            ToLairLairPurgeTrashed 0x00001050 false true {
+                older_than_ms: u64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(*older_than_ms)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let older_than_ms = reader.read_u64()?;
+                LairWire::ToLairLairPurgeTrashed { msg_id, older_than_ms }
+            },
+            ToCliLairPurgeTrashedResponse 0x00001051 false false {
+                purged: u64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(*purged)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let purged = reader.read_u64()?;
+                LairWire::ToCliLairPurgeTrashedResponse { msg_id, purged }
+            },
+            ToLairLairListQuarantined 0x00001060 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairListQuarantined { msg_id }
+            },
+            ToCliLairListQuarantinedResponse 0x00001061 false false {
+                entries: Vec<QuarantinedEntry>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::COUNT_LEN // entry count
+                    + entries.iter().map(|entry| {
+                        4 // keystore index
+                        + spec::LEN_PREFIX_LEN // reason length
+                        + entry.reason.len() // reason content
+                        + spec::LEN_PREFIX_LEN // data length
+                        + entry.data.len() // data content
+                    }).sum::<usize>();
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(entries.len() as u32)?;
+                for entry in entries.iter() {
+                    writer.write_u32(*entry.keystore_index)?;
+                    writer.write_str(&entry.reason, MAX_QUARANTINE_REASON_LEN)?;
+                    writer.write_sized_bytes(&entry.data, entry::ENTRY_SIZE)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let keystore_index = reader.read_u32()?.into();
+                    let reason = reader.read_str()?;
+                    let data = Arc::new(reader.read_sized_bytes()?);
+                    entries.push(QuarantinedEntry {
+                        keystore_index,
+                        reason,
+                        data,
+                    });
+                }
+                LairWire::ToCliLairListQuarantinedResponse { msg_id, entries }
+            },
+            ToLairLairFindEntriesByPrefix 0x00001080 false true {
+                prefix: Arc<Vec<u8>>,
+                kind: PrefixKind,
+            } |msg_id, wire_type| {
+                check_entry_prefix(prefix)?;
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(prefix, MAX_ENTRY_PUB_ID_LEN)?;
+                writer.write_u32(*kind as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let prefix = reader.read_sized_bytes()?;
+                check_entry_prefix(&prefix)?;
+                let kind = PrefixKind::parse(reader.read_u32()?)?;
+                LairWire::ToLairLairFindEntriesByPrefix {
+                    msg_id,
+                    prefix: Arc::new(prefix),
+                    kind,
+                }
+            },
+            ToCliLairFindEntriesByPrefixResponse 0x00001081 false false {
+                entries: Vec<(KeystoreIndex, LairEntryType)>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::COUNT_LEN // entry count
+                    + entries.len() * (
+                        4 // keystore index
+                        + 4 // entry type
+                    );
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(entries.len() as u32)?;
+                for (index, entry_type) in entries.iter() {
+                    writer.write_u32(**index)?;
+                    writer.write_u32(*entry_type as u32)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let index = reader.read_u32()?.into();
+                    let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                    entries.push((index, entry_type));
+                }
+                LairWire::ToCliLairFindEntriesByPrefixResponse {
+                    msg_id,
+                    entries,
+                }
+            },
+            ToLairLairGetCapabilities 0x00001090 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetCapabilities { msg_id }
+            },
+            ToCliLairGetCapabilitiesResponse 0x00001091 false false {
+                capabilities: LairCapabilities,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(capabilities.0)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let capabilities = LairCapabilities(reader.read_u32()?);
+                LairWire::ToCliLairGetCapabilitiesResponse {
+                    msg_id,
+                    capabilities,
+                }
+            },
+            ToLairLairListConnections 0x000010a0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairListConnections { msg_id }
+            },
+            ToCliLairListConnectionsResponse 0x000010a1 false false {
+                connections: Vec<LairConnectionInfo>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::COUNT_LEN // connection count
+                    + connections.len() * (
+                        8 // connection id
+                        + 8 // idle ms
+                        + 4 // requests in flight
+                    );
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(connections.len() as u32)?;
+                for con in connections.iter() {
+                    writer.write_u64(con.connection_id)?;
+                    writer.write_u64(con.idle.as_millis() as u64)?;
+                    writer.write_u32(con.in_flight)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                let mut connections = Vec::new();
+                for _ in 0..count {
+                    let connection_id = reader.read_u64()?;
+                    let idle =
+                        std::time::Duration::from_millis(reader.read_u64()?);
+                    let in_flight = reader.read_u32()?;
+                    connections.push(LairConnectionInfo {
+                        connection_id,
+                        idle,
+                        in_flight,
+                    });
+                }
+                LairWire::ToCliLairListConnectionsResponse {
+                    msg_id,
+                    connections,
+                }
+            },
+            ToLairLairSetEventFilter 0x000010c0 false true {
+                kinds: Vec<EventKind>,
+            } |msg_id, wire_type| {
+                if kinds.len() > MAX_EVENT_FILTER_KINDS {
+                    return Err("too many event kinds".into());
+                }
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(kinds.len() as u32)?;
+                for kind in kinds.iter() {
+                    writer.write_u32(*kind as u32)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                if count as usize > MAX_EVENT_FILTER_KINDS {
+                    return Err("too many event kinds".into());
+                }
+                let mut kinds = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    kinds.push(EventKind::parse(reader.read_u32()?)?);
+                }
+                LairWire::ToLairLairSetEventFilter { msg_id, kinds }
+            },
+            ToCliLairSetEventFilterResponse 0x000010c1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSetEventFilterResponse { msg_id }
+            },
+            ToLairLairGetLastEntryIndex64 0x000010d0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetLastEntryIndex64 { msg_id }
+            },
+            ToCliLairGetLastEntryIndex64Response 0x000010d1 false false {
+                last_keystore_index: Option<KeystoreIndex64>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                match last_keystore_index {
+                    Some(last_keystore_index) => {
+                        writer.write_bool(true)?;
+                        writer.write_u64(**last_keystore_index)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let last_keystore_index = match reader.read_bool()? {
+                    true => Some(reader.read_u64()?.into()),
+                    false => None,
+                };
+                LairWire::ToCliLairGetLastEntryIndex64Response {
+                    msg_id,
+                    last_keystore_index,
+                }
+            },
+            ToLairLairGetEntryType64 0x000010e0 false true {
+                keystore_index: KeystoreIndex64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u64()?;
+                LairWire::ToLairLairGetEntryType64 {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliLairGetEntryType64Response 0x000010e1 false false {
+                lair_entry_type: LairEntryType,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*lair_entry_type as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetEntryType64Response {
+                    msg_id,
+                    lair_entry_type,
+                }
+            },
+            ToLairLairExportDeviceSecret 0x000010b0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairExportDeviceSecret { msg_id }
+            },
+            ToCliLairExportDeviceSecretResponse 0x000010b1 false false {
+                device_secret: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // device secret length
+                    + device_secret.len(); // device secret content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(
+                    device_secret,
+                    crate::device_secret::DEVICE_SECRET_LEN,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let device_secret = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliLairExportDeviceSecretResponse {
+                    msg_id,
+                    device_secret,
+                }
+            },
+            ToLairLairSelfTest 0x00001070 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairSelfTest { msg_id }
+            },
+            ToCliLairSelfTestResponse 0x00001071 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSelfTestResponse { msg_id }
+            },
+            ToLairLairAttestEntry 0x000000c0 false true {
+                keystore_index: KeystoreIndex,
+                challenge: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // challenge length
+                    + challenge.len(); // challenge content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(challenge, MAX_ATTEST_CHALLENGE_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let challenge = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairLairAttestEntry {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    challenge,
+                }
+            },
+            ToCliLairAttestEntryResponse 0x000000c1 false false {
+                attestation: Attestation,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 32 // server id pub key
+                    + 4 // entry type
+                    + spec::LEN_PREFIX_LEN // entry pub id length
+                    + attestation.entry_pub_id.len() // entry pub id content
+                    + spec::LEN_PREFIX_LEN // challenge length
+                    + attestation.challenge.len() // challenge content
+                    + 8 // timestamp
+                    + 64; // signature
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(&attestation.server_id_pub_key, 32)?;
+                writer.write_u32(attestation.entry_type as u32)?;
+                writer.write_sized_bytes(
+                    &attestation.entry_pub_id,
+                    MAX_ENTRY_PUB_ID_LEN,
+                )?;
+                writer.write_sized_bytes(
+                    &attestation.challenge,
+                    MAX_ATTEST_CHALLENGE_LEN,
+                )?;
+                writer.write_u64(attestation.timestamp)?;
+                writer.write_bytes_exact(&attestation.signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let server_id_pub_key = reader.read_bytes(32)?.to_vec().into();
+                let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                let entry_pub_id = Arc::new(reader.read_sized_bytes()?);
+                let challenge = Arc::new(reader.read_sized_bytes()?);
+                let timestamp = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec().into();
+                LairWire::ToCliLairAttestEntryResponse {
+                    msg_id,
+                    attestation: Attestation {
+                        server_id_pub_key,
+                        entry_type,
+                        entry_pub_id,
+                        challenge,
+                        timestamp,
+                        signature,
+                    },
+                }
+            },
+            ToLairLairProveServerIdentity 0x00001110 false true {
+                nonce: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // nonce length
+                    + nonce.len(); // nonce content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(nonce, MAX_ATTEST_CHALLENGE_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let nonce = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairLairProveServerIdentity { msg_id, nonce }
+            },
+            ToCliLairProveServerIdentityResponse 0x00001111 false false {
+                proof: ServerIdentityProof,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 32 // server id pub key
+                    + 4 // wire protocol version
+                    + spec::LEN_PREFIX_LEN // server version length
+                    + proof.server_version.len() // server version content
+                    + spec::LEN_PREFIX_LEN // nonce length
+                    + proof.nonce.len() // nonce content
+                    + 64; // signature
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(&proof.server_id_pub_key, 32)?;
+                writer.write_u32(proof.wire_protocol_version)?;
+                writer.write_str(&proof.server_version, 64)?;
+                writer.write_sized_bytes(
+                    &proof.nonce,
+                    MAX_ATTEST_CHALLENGE_LEN,
+                )?;
+                writer.write_bytes_exact(&proof.signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let server_id_pub_key = reader.read_bytes(32)?.to_vec().into();
+                let wire_protocol_version = reader.read_u32()?;
+                let server_version = reader.read_str()?;
+                let nonce = Arc::new(reader.read_sized_bytes()?);
+                let signature = reader.read_bytes(64)?.to_vec().into();
+                LairWire::ToCliLairProveServerIdentityResponse {
+                    msg_id,
+                    proof: ServerIdentityProof {
+                        server_id_pub_key,
+                        wire_protocol_version,
+                        server_version,
+                        nonce,
+                        signature,
+                    },
+                }
+            },
+            ToLairSignEd25519SignByIndexWithOptions 0x00001120 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+                options: SignOptions,
+            } |msg_id, wire_type| {
+                let options = encode_sign_options(options)?;
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len() // message content
+                    + options.len(); // options
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                writer.write_bytes(&options)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                let options = reader.read_sign_options()?;
+                LairWire::ToLairSignEd25519SignByIndexWithOptions {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                    options,
+                }
+            },
+            ToCliSignEd25519SignByIndexWithOptionsResponse 0x00001121 false false {
+                signature: SignEd25519Signature,
+                timestamp: Option<u64>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                match timestamp {
+                    Some(timestamp) => {
+                        writer.write_bool(true)?;
+                        writer.write_u64(*timestamp)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                let timestamp = match reader.read_bool()? {
+                    true => Some(reader.read_u64()?),
+                    false => None,
+                };
+                LairWire::ToCliSignEd25519SignByIndexWithOptionsResponse {
+                    msg_id,
+                    signature: signature.into(),
+                    timestamp,
+                }
+            },
+            ToLairLairEraseEntries 0x00001130 false true {
+                indices: Vec<KeystoreIndex>,
+            } |msg_id, wire_type| {
+                check_bulk_entries_len(indices.len())?;
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(indices.len() as u32)?;
+                for index in indices.iter() {
+                    writer.write_u32(**index)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                check_bulk_entries_len(count as usize)?;
+                let mut indices = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    indices.push(reader.read_u32()?.into());
+                }
+                LairWire::ToLairLairEraseEntries { msg_id, indices }
+            },
+            ToCliLairEraseEntriesResponse 0x00001131 false false {
+                results: Vec<Option<BulkItemError>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bulk_results(results)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let results = reader.read_bulk_results()?;
+                LairWire::ToCliLairEraseEntriesResponse { msg_id, results }
+            },
+            ToLairLairSetEntryTags 0x00001132 false true {
+                tags: Vec<(KeystoreIndex, String)>,
+            } |msg_id, wire_type| {
+                check_bulk_entries_len(tags.len())?;
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(tags.len() as u32)?;
+                for (index, tag) in tags.iter() {
+                    writer.write_u32(**index)?;
+                    writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u32()?;
+                check_bulk_entries_len(count as usize)?;
+                let mut tags = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let index = reader.read_u32()?.into();
+                    tags.push((index, reader.read_str()?));
+                }
+                LairWire::ToLairLairSetEntryTags { msg_id, tags }
+            },
+            ToCliLairSetEntryTagsResponse 0x00001133 false false {
+                results: Vec<Option<BulkItemError>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bulk_results(results)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let results = reader.read_bulk_results()?;
+                LairWire::ToCliLairSetEntryTagsResponse { msg_id, results }
+            },
+            ToLairLairRequestElevation 0x00001140 false true {
+                capabilities: LairCapabilities,
+                duration_ms: u64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(capabilities.0)?;
+                writer.write_u64(*duration_ms)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let capabilities = LairCapabilities(reader.read_u32()?);
+                let duration_ms = reader.read_u64()?;
+                LairWire::ToLairLairRequestElevation {
+                    msg_id,
+                    capabilities,
+                    duration_ms,
+                }
+            },
+            ToCliLairRequestElevationResponse 0x00001141 false false {
+                elevation: LairElevation,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_elevation(elevation)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let elevation = reader.read_elevation()?;
+                LairWire::ToCliLairRequestElevationResponse { msg_id, elevation }
+            },
+            ToLairLairGetElevation 0x00001142 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetElevation { msg_id }
+            },
+            ToCliLairGetElevationResponse 0x00001143 false false {
+                elevation: Option<LairElevation>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                match elevation {
+                    Some(elevation) => {
+                        writer.write_bool(true)?;
+                        writer.write_elevation(elevation)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let elevation = match reader.read_bool()? {
+                    true => Some(reader.read_elevation()?),
+                    false => None,
+                };
+                LairWire::ToCliLairGetElevationResponse { msg_id, elevation }
+            },
+            ToLairLairDropElevation 0x00001144 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairDropElevation { msg_id }
+            },
+            ToCliLairDropElevationResponse 0x00001145 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairDropElevationResponse { msg_id }
+            },
+            ToLairLairReloadSettings 0x00001150 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairReloadSettings { msg_id }
+            },
+            ToCliLairReloadSettingsResponse 0x00001151 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairReloadSettingsResponse { msg_id }
+            },
+            ToLairLairWrapEntry 0x000000a0 false true {
+                target_index: KeystoreIndex,
+                recipient_pub_key: X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**target_index)?;
+                writer.write_bytes_exact(recipient_pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let target_index = reader.read_u32()?;
+                let recipient_pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairLairWrapEntry {
+                    msg_id,
+                    target_index: target_index.into(),
+                    recipient_pub_key: recipient_pub_key.into(),
+                }
+            },
+            ToCliLairWrapEntryResponse 0x000000a1 false false {
+                wrapped: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // wrapped length
+                    + wrapped.len(); // wrapped content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(wrapped, wrapped.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let wrapped = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliLairWrapEntryResponse { msg_id, wrapped }
+            },
+            ToLairLairUnwrapEntry 0x000000b0 false true {
+                recipient_index: KeystoreIndex,
+                wrapped: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // recipient index
+                    + spec::LEN_PREFIX_LEN // wrapped length
+                    + wrapped.len(); // wrapped content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**recipient_index)?;
+                writer.write_sized_bytes(wrapped, wrapped.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let recipient_index = reader.read_u32()?;
+                let wrapped = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairLairUnwrapEntry {
+                    msg_id,
+                    recipient_index: recipient_index.into(),
+                    wrapped,
+                }
+            },
+            ToCliLairUnwrapEntryResponse 0x000000b1 false false {
+                keystore_index: KeystoreIndex,
+                lair_entry_type: LairEntryType,
+                already_existed: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*lair_entry_type as u32)?;
+                writer.write_bool(*already_existed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                let already_existed = reader.read_bool()?;
+                LairWire::ToCliLairUnwrapEntryResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    lair_entry_type,
+                    already_existed,
+                }
+            },
+            ToLairTlsCertNewSelfSignedFromEntropy 0x00000110 false true {
+                cert_alg: TlsCertAlg,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*cert_alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                    msg_id,
+                    cert_alg,
+                }
+            },
+            ToCliTlsCertNewSelfSignedFromEntropyResponse 0x00000111 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertNewSelfSignedFromSignKey 0x000001c0 false true {
+                keystore_index: KeystoreIndex,
+                cert_alg: TlsCertAlg,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*cert_alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                LairWire::ToLairTlsCertNewSelfSignedFromSignKey {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_alg,
+                }
+            },
+            ToCliTlsCertNewSelfSignedFromSignKeyResponse 0x000001c1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertNewSelfSignedFromSignKeyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertGet 0x00000120 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGet {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetResponse 0x00000121 false false {
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertGetResponse {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertGetInfo 0x000001f0 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGetInfo {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetInfoResponse 0x000001f1 false false {
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+                cert_alg: TlsCertAlg,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_u32(*cert_alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                LairWire::ToCliTlsCertGetInfoResponse {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                    cert_alg,
+                }
+            },
+            ToLairTlsCertGetCertByIndex 0x00000130 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGetCertByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetCertByIndexResponse 0x00000131 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = sized_encode_setup!(1024, msg_id, wire_type);
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetCertByDigest 0x00000140 false true {
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairTlsCertGetCertByDigest {
+                    msg_id,
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToCliTlsCertGetCertByDigestResponse 0x00000141 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = sized_encode_setup!(1024, msg_id, wire_type);
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetCertByDigestResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetCertBySni 0x00000150 false true {
+                cert_sni: CertSni,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                // lookups compare normalized names
+                let cert_sni = CertSni::parse_lenient(&reader.read_str()?)?;
+                LairWire::ToLairTlsCertGetCertBySni { msg_id, cert_sni }
+            },
+            ToCliTlsCertGetCertBySniResponse 0x00000151 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = sized_encode_setup!(1024, msg_id, wire_type);
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetCertBySniResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyByIndex 0x00000160 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetPrivKeyByIndexResponse 0x00000161 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyByDigest 0x00000170 false true {
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                    msg_id,
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToCliTlsCertGetPrivKeyByDigestResponse 0x00000171 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyBySni 0x00000180 false true {
+                cert_sni: CertSni,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                // lookups compare normalized names
+                let cert_sni = CertSni::parse_lenient(&reader.read_str()?)?;
+                LairWire::ToLairTlsCertGetPrivKeyBySni { msg_id, cert_sni }
+            },
+            ToCliTlsCertGetPrivKeyBySniResponse 0x00000181 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertGetBundleByTag 0x00000190 false true {
+                tag: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairTlsCertGetBundleByTag { msg_id, tag }
+            },
+            ToCliTlsCertGetBundleByTagResponse 0x00000191 false false {
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+                cert: Cert,
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = sized_encode_setup!(2048, msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_sized_bytes(cert, 968)?;
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                let cert = reader.read_sized_bytes()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetBundleByTagResponse {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                    cert: cert.into(),
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertResolveByDigest 0x000001a0 false true {
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairTlsCertResolveByDigest {
+                    msg_id,
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToCliTlsCertResolveByDigestResponse 0x000001a1 false false {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliTlsCertResolveByDigestResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairTlsCertResolveBySni 0x000001b0 false true {
+                cert_sni: CertSni,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                // lookups compare normalized names
+                let cert_sni = CertSni::parse_lenient(&reader.read_str()?)?;
+                LairWire::ToLairTlsCertResolveBySni { msg_id, cert_sni }
+            },
+            ToCliTlsCertResolveBySniResponse 0x000001b1 false false {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliTlsCertResolveBySniResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairTlsCertGetDigest 0x000001d0 false true {
+                keystore_index: KeystoreIndex,
+                digest_alg: DigestAlg,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*digest_alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let digest_alg = DigestAlg::parse(reader.read_u32()?)?;
+                LairWire::ToLairTlsCertGetDigest {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    digest_alg,
+                }
+            },
+            ToCliTlsCertGetDigestResponse 0x000001d1 false false {
+                digest: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(digest, MAX_DIGEST_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let digest = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliTlsCertGetDigestResponse { msg_id, digest }
+            },
+            ToLairTlsCertGetCertByTypedDigest 0x000001e0 false true {
+                digest_alg: DigestAlg,
+                digest: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(digest, digest_alg.digest_len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let digest_alg = DigestAlg::parse(reader.read_u32()?)?;
+                let digest = reader.read_bytes(digest_alg.digest_len() as u64)?;
+                LairWire::ToLairTlsCertGetCertByTypedDigest {
+                    msg_id,
+                    digest_alg,
+                    digest: Arc::new(digest.to_vec()),
+                }
+            },
+            ToCliTlsCertGetCertByTypedDigestResponse 0x000001e1 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = sized_encode_setup!(1024, msg_id, wire_type);
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetCertByTypedDigestResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairSignEd25519NewFromEntropy 0x00000210 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignEd25519NewFromEntropy { msg_id }
+            },
+            ToCliSignEd25519NewFromEntropyResponse 0x00000211 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519New 0x00000290 false true {
+                backing: KeyBacking,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*backing as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let backing = KeyBacking::parse(reader.read_u32()?)?;
+                LairWire::ToLairSignEd25519New { msg_id, backing }
+            },
+            ToCliSignEd25519NewResponse 0x00000291 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519ImportSshKey 0x00000270 false true {
+                key: Arc<Vec<u8>>,
+                passphrase: Option<String>,
+            } |msg_id, wire_type| {
+                check_ssh_key_import(key, passphrase.as_deref())?;
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // key length
+                    + key.len() // key content
+                    + spec::BOOL_LEN // passphrase flag
+                    + spec::LEN_PREFIX_LEN // passphrase length
+                    + passphrase.as_ref().map_or(0, String::len);
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(key, MAX_SSH_KEY_LEN)?;
+                match passphrase {
+                    Some(passphrase) => {
+                        writer.write_bool(true)?;
+                        writer.write_str(
+                            passphrase,
+                            MAX_SSH_KEY_PASSPHRASE_LEN,
+                        )?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let key = Arc::new(reader.read_sized_bytes()?);
+                let passphrase = match reader.read_bool()? {
+                    true => Some(reader.read_str()?),
+                    false => None,
+                };
+                LairWire::ToLairSignEd25519ImportSshKey {
+                    msg_id,
+                    key,
+                    passphrase,
+                }
+            },
+            ToCliSignEd25519ImportSshKeyResponse 0x00000271 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
+                already_existed: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bool(*already_existed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let already_existed = reader.read_bool()?;
+                LairWire::ToCliSignEd25519ImportSshKeyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                    already_existed,
+                }
+            },
+            ToLairSignEd25519Get 0x00000220 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd25519Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd25519GetResponse 0x00000221 false false {
+                pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
                 LairWire::ToCliSignEd25519GetResponse {
                     msg_id,
-                    pub_key: pub_key.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519SignByIndex 0x00000230 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByIndexResponse 0x00000231 false false {
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByIndexResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByPubKey 0x00000240 false true {
+                pub_key: SignEd25519PubKey,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 32 // pub_key
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByPubKeyResponse 0x00000241 false false {
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByIndexV2 0x000002a0 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByIndexV2 {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByIndexV2Response 0x000002a1 false false {
+                pub_key: SignEd25519PubKey,
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByIndexV2Response {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByPubKeyV2 0x000002b0 false true {
+                pub_key: SignEd25519PubKey,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 32 // pub_key
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByPubKeyV2 {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByPubKeyV2Response 0x000002b1 false false {
+                pub_key: SignEd25519PubKey,
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByPubKeyV2Response {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519NewFromEntropyWithOptions 0x000002c0 false true {
+                options: SignKeyOptions,
+            } |msg_id, wire_type| {
+                let options = encode_sign_key_options(options)?;
+                let size = spec::HEADER_LEN + options.len();
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes(&options)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let options = reader.read_sign_key_options()?;
+                LairWire::ToLairSignEd25519NewFromEntropyWithOptions {
+                    msg_id,
+                    options,
+                }
+            },
+            ToCliSignEd25519NewFromEntropyWithOptionsResponse 0x000002c1 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromEntropyWithOptionsResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519Rotate 0x00000200 false true {
+                old_keystore_index: KeystoreIndex,
+                options: RotateKeyOptions,
+            } |msg_id, wire_type| {
+                let key_options = encode_sign_key_options(&options.key_options)?;
+                let size = spec::HEADER_LEN
+                    + 4 // old keystore index
+                    + spec::BOOL_LEN // trash old key
+                    + key_options.len();
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**old_keystore_index)?;
+                writer.write_bool(options.trash_old_key)?;
+                writer.write_bytes(&key_options)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let old_keystore_index = reader.read_u32()?;
+                let mut options = RotateKeyOptions::default();
+                options.trash_old_key = reader.read_bool()?;
+                options.key_options = reader.read_sign_key_options()?;
+                LairWire::ToLairSignEd25519Rotate {
+                    msg_id,
+                    old_keystore_index: old_keystore_index.into(),
+                    options,
+                }
+            },
+            ToCliSignEd25519RotateResponse 0x00000201 false false {
+                keystore_index: KeystoreIndex,
+                record: RotationRecord,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(&record.old_pub_key, 32)?;
+                writer.write_bytes_exact(&record.new_pub_key, 32)?;
+                writer.write_u64(record.timestamp)?;
+                writer.write_bytes_exact(&record.old_signature, 64)?;
+                writer.write_bytes_exact(&record.new_signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let old_pub_key = reader.read_bytes(32)?.to_vec();
+                let new_pub_key = reader.read_bytes(32)?.to_vec();
+                let timestamp = reader.read_u64()?;
+                let old_signature = reader.read_bytes(64)?.to_vec();
+                let new_signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519RotateResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    record: RotationRecord {
+                        old_pub_key: old_pub_key.into(),
+                        new_pub_key: new_pub_key.into(),
+                        timestamp,
+                        old_signature: old_signature.into(),
+                        new_signature: new_signature.into(),
+                    },
+                }
+            },
+            ToLairSignEd25519ExportSeed 0x00000910 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd25519ExportSeed {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd25519ExportSeedResponse 0x00000911 false false {
+                seed: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes(seed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut seed = [0; 32];
+                seed.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToCliSignEd25519ExportSeedResponse {
+                    msg_id,
+                    seed,
+                }
+            },
+            ToLairSignEd25519NewFromSeedWithOptions 0x000002d0 false true {
+                seed: [u8; 32],
+                options: SignKeyOptions,
+            } |msg_id, wire_type| {
+                let options = encode_sign_key_options(options)?;
+                let size = spec::HEADER_LEN
+                    + 32 // seed
+                    + options.len();
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes(seed)?;
+                writer.write_bytes(&options)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut seed = [0; 32];
+                seed.copy_from_slice(reader.read_bytes(32)?);
+                let options = reader.read_sign_key_options()?;
+                LairWire::ToLairSignEd25519NewFromSeedWithOptions {
+                    msg_id,
+                    seed,
+                    options,
+                }
+            },
+            ToCliSignEd25519NewFromSeedWithOptionsResponse 0x000002d1 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd25519PubKey,
+                already_existed: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bool(*already_existed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let already_existed = reader.read_bool()?;
+                LairWire::ToCliSignEd25519NewFromSeedWithOptionsResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                    already_existed,
+                }
+            },
+            ToLairSignEd25519GetOptions 0x000002e0 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd25519GetOptions {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd25519GetOptionsResponse 0x000002e1 false false {
+                options: SignKeyOptions,
+            } |msg_id, wire_type| {
+                let options = encode_sign_key_options(options)?;
+                let size = spec::HEADER_LEN + options.len();
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes(&options)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let options = reader.read_sign_key_options()?;
+                LairWire::ToCliSignEd25519GetOptionsResponse { msg_id, options }
+            },
+            ToLairSignEd25519SignByIndex64 0x000002f0 false true {
+                keystore_index: KeystoreIndex64,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 8 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u64()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByIndex64 {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByIndex64Response 0x000002f1 false false {
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByIndex64Response {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByTag 0x00000250 false true {
+                tag: String,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // tag length
+                    + tag.len() // tag content
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_str(tag, MAX_ENTRY_TAG_LEN)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519SignByTag {
+                    msg_id,
+                    tag,
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByTagResponse 0x00000251 false false {
+                pub_key: SignEd25519PubKey,
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByTagResponse {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519PhSignByIndex 0x00000280 false true {
+                keystore_index: KeystoreIndex,
+                prehash: [u8; 64],
+                context: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes(prehash)?;
+                writer.write_sized_bytes(context, ed25519ph::MAX_CONTEXT_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let mut prehash = [0; 64];
+                prehash.copy_from_slice(reader.read_bytes(64)?);
+                let context = reader.read_sized_bytes()?;
+                LairWire::ToLairSignEd25519PhSignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    prehash,
+                    context: Arc::new(context),
+                }
+            },
+            ToCliSignEd25519PhSignByIndexResponse 0x00000281 false false {
+                signature: SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519PhSignByIndexResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519ResolveByPubKey 0x00000260 false true {
+                pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairSignEd25519ResolveByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToCliSignEd25519ResolveByPubKeyResponse 0x00000261 false false {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliSignEd25519ResolveByPubKeyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairSignSecp256k1NewFromEntropy 0x00000510 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignSecp256k1NewFromEntropy { msg_id }
+            },
+            ToCliSignSecp256k1NewFromEntropyResponse 0x00000511 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignSecp256k1PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 33)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(33)?.to_vec();
+                LairWire::ToCliSignSecp256k1NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignSecp256k1NewFromSeed 0x00000520 false true {
+                seed: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes(seed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut seed = [0; 32];
+                seed.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSignSecp256k1NewFromSeed { msg_id, seed }
+            },
+            ToCliSignSecp256k1NewFromSeedResponse 0x00000521 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignSecp256k1PubKey,
+                already_existed: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 33)?;
+                writer.write_bool(*already_existed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(33)?.to_vec();
+                let already_existed = reader.read_bool()?;
+                LairWire::ToCliSignSecp256k1NewFromSeedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                    already_existed,
+                }
+            },
+            ToLairSignSecp256k1Get 0x00000530 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignSecp256k1Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignSecp256k1GetResponse 0x00000531 false false {
+                pub_key: SignSecp256k1PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 33)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(33)?.to_vec();
+                LairWire::ToCliSignSecp256k1GetResponse {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignSecp256k1SignByIndex 0x00000540 false true {
+                keystore_index: KeystoreIndex,
+                message_hash: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes(message_hash)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let mut message_hash = [0; 32];
+                message_hash.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSignSecp256k1SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message_hash,
+                }
+            },
+            ToCliSignSecp256k1SignByIndexResponse 0x00000541 false false {
+                signature: SignSecp256k1Signature,
+                recovery_id: u8,
+            } |msg_id, wire_type| {
+                if *recovery_id > 3 {
+                    return Err("invalid secp256k1 recovery id".into());
+                }
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                writer.write_u32(*recovery_id as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                let recovery_id = reader.read_u32()?;
+                if recovery_id > 3 {
+                    return Err("invalid secp256k1 recovery id".into());
+                }
+                LairWire::ToCliSignSecp256k1SignByIndexResponse {
+                    msg_id,
+                    signature: signature.into(),
+                    recovery_id: recovery_id as u8,
+                }
+            },
+            ToLairSignEd448NewFromEntropy 0x00000710 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignEd448NewFromEntropy { msg_id }
+            },
+            ToCliSignEd448NewFromEntropyResponse 0x00000711 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignEd448PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 57)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = SignEd448PubKey::from_bytes(
+                    reader.read_bytes(57)?,
+                )?;
+                LairWire::ToCliSignEd448NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairSignEd448Get 0x00000720 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd448Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd448GetResponse 0x00000721 false false {
+                pub_key: SignEd448PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 57)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = SignEd448PubKey::from_bytes(
+                    reader.read_bytes(57)?,
+                )?;
+                LairWire::ToCliSignEd448GetResponse { msg_id, pub_key }
+            },
+            ToLairSignEd448SignByIndex 0x00000730 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd448SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                }
+            },
+            ToCliSignEd448SignByIndexResponse 0x00000731 false false {
+                signature: SignEd448Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 114)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = SignEd448Signature::from_bytes(
+                    reader.read_bytes(114)?,
+                )?;
+                LairWire::ToCliSignEd448SignByIndexResponse { msg_id, signature }
+            },
+            ToLairSignEd448SignByPubKey 0x00000740 false true {
+                pub_key: SignEd448PubKey,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 57 // pub_key
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len(); // message content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 57)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = SignEd448PubKey::from_bytes(
+                    reader.read_bytes(57)?,
+                )?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd448SignByPubKey {
+                    msg_id,
+                    pub_key,
+                    message,
+                }
+            },
+            ToCliSignEd448SignByPubKeyResponse 0x00000741 false false {
+                signature: SignEd448Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 114)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = SignEd448Signature::from_bytes(
+                    reader.read_bytes(114)?,
+                )?;
+                LairWire::ToCliSignEd448SignByPubKeyResponse { msg_id, signature }
+            },
+            ToLairSignBlsNewFromEntropy 0x00000a10 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignBlsNewFromEntropy { msg_id }
+            },
+            ToCliSignBlsNewFromEntropyResponse 0x00000a11 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignBlsPubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 48)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = SignBlsPubKey::from_bytes(
+                    reader.read_bytes(48)?,
+                )?;
+                LairWire::ToCliSignBlsNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairSignBlsGet 0x00000a20 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignBlsGet {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignBlsGetResponse 0x00000a21 false false {
+                pub_key: SignBlsPubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 48)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = SignBlsPubKey::from_bytes(
+                    reader.read_bytes(48)?,
+                )?;
+                LairWire::ToCliSignBlsGetResponse { msg_id, pub_key }
+            },
+            ToLairSignBlsSignByIndex 0x00000a30 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+                dst: Vec<u8>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len() // message content
+                    + spec::LEN_PREFIX_LEN // dst length
+                    + dst.len(); // dst content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                writer.write_sized_bytes(dst, dst.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                let dst = reader.read_sized_bytes()?;
+                LairWire::ToLairSignBlsSignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                    dst,
+                }
+            },
+            ToCliSignBlsSignByIndexResponse 0x00000a31 false false {
+                signature: SignBlsSignature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 96)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = SignBlsSignature::from_bytes(
+                    reader.read_bytes(96)?,
+                )?;
+                LairWire::ToCliSignBlsSignByIndexResponse { msg_id, signature }
+            },
+            ToLairSignSr25519NewFromEntropy 0x00000b10 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignSr25519NewFromEntropy { msg_id }
+            },
+            ToCliSignSr25519NewFromEntropyResponse 0x00000b11 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignSr25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = SignSr25519PubKey::from_bytes(
+                    reader.read_bytes(32)?,
+                )?;
+                LairWire::ToCliSignSr25519NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairSignSr25519NewFromSeed 0x00000b20 false true {
+                seed: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes(seed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut seed = [0; 32];
+                seed.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSignSr25519NewFromSeed { msg_id, seed }
+            },
+            ToCliSignSr25519NewFromSeedResponse 0x00000b21 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: SignSr25519PubKey,
+                already_existed: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_bool(*already_existed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = SignSr25519PubKey::from_bytes(
+                    reader.read_bytes(32)?,
+                )?;
+                let already_existed = reader.read_bool()?;
+                LairWire::ToCliSignSr25519NewFromSeedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                    already_existed,
+                }
+            },
+            ToLairSignSr25519Get 0x00000b30 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignSr25519Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignSr25519GetResponse 0x00000b31 false false {
+                pub_key: SignSr25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = SignSr25519PubKey::from_bytes(
+                    reader.read_bytes(32)?,
+                )?;
+                LairWire::ToCliSignSr25519GetResponse { msg_id, pub_key }
+            },
+            ToLairSignSr25519SignByIndex 0x00000b40 false true {
+                keystore_index: KeystoreIndex,
+                message: Arc<Vec<u8>>,
+                context: Vec<u8>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // message length
+                    + message.len() // message content
+                    + spec::LEN_PREFIX_LEN // context length
+                    + context.len(); // context content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                writer.write_sized_bytes(context, context.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                let context = reader.read_sized_bytes()?;
+                LairWire::ToLairSignSr25519SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                    context,
+                }
+            },
+            ToCliSignSr25519SignByIndexResponse 0x00000b41 false false {
+                signature: SignSr25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = SignSr25519Signature::from_bytes(
+                    reader.read_bytes(64)?,
+                )?;
+                LairWire::ToCliSignSr25519SignByIndexResponse {
+                    msg_id,
+                    signature,
+                }
+            },
+            ToLairX25519NewFromEntropy 0x00000310 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairX25519NewFromEntropy { msg_id }
+            },
+            ToCliX25519NewFromEntropyResponse 0x00000311 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliX25519NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairX25519Get 0x00000320 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairX25519Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliX25519GetResponse 0x00000321 false false {
+                pub_key: X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliX25519GetResponse {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairCryptoBoxToSignPubKey 0x00000330 false true {
+                sender_x25519_index: KeystoreIndex,
+                recipient_sign_pub_key: SignEd25519PubKey,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // sender keystore index
+                    + 32 // recipient pub key
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**sender_x25519_index)?;
+                writer.write_bytes_exact(recipient_sign_pub_key, 32)?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let sender_x25519_index = reader.read_u32()?;
+                let recipient_sign_pub_key = reader.read_bytes(32)?.to_vec();
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairCryptoBoxToSignPubKey {
+                    msg_id,
+                    sender_x25519_index: sender_x25519_index.into(),
+                    recipient_sign_pub_key: recipient_sign_pub_key.into(),
+                    data,
+                }
+            },
+            ToCliCryptoBoxToSignPubKeyResponse 0x00000331 false false {
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliCryptoBoxToSignPubKeyResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxOpenBySignPubKey 0x00000340 false true {
+                recipient_sign_pub_key: SignEd25519PubKey,
+                sender_x25519_pub_key: X25519PubKey,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 32 // recipient pub key
+                    + 32 // sender pub key
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_bytes_exact(recipient_sign_pub_key, 32)?;
+                writer.write_bytes_exact(sender_x25519_pub_key, 32)?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let recipient_sign_pub_key = reader.read_bytes(32)?.to_vec();
+                let sender_x25519_pub_key = reader.read_bytes(32)?.to_vec();
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairCryptoBoxOpenBySignPubKey {
+                    msg_id,
+                    recipient_sign_pub_key: recipient_sign_pub_key.into(),
+                    sender_x25519_pub_key: sender_x25519_pub_key.into(),
+                    data,
+                }
+            },
+            ToCliCryptoBoxOpenBySignPubKeyResponse 0x00000341 false false {
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliCryptoBoxOpenBySignPubKeyResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxSealOpenByIndex 0x00000350 false true {
+                keystore_index: KeystoreIndex,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairCryptoBoxSealOpenByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    data,
+                }
+            },
+            ToCliCryptoBoxSealOpenByIndexResponse 0x00000351 false false {
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairAgeDecryptByIndex 0x00000360 false true {
+                keystore_index: KeystoreIndex,
+                stanzas: Vec<AgeStanza>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                if stanzas.len() > MAX_AGE_STANZAS {
+                    return Err("too many age stanzas".into());
+                }
+                writer.write_u32(stanzas.len() as u32)?;
+                for stanza in stanzas.iter() {
+                    writer.write_str(&stanza.tag, MAX_AGE_STANZA_ARG_LEN)?;
+                    if stanza.args.len() > MAX_AGE_STANZA_ARGS {
+                        return Err("too many age stanza args".into());
+                    }
+                    writer.write_u32(stanza.args.len() as u32)?;
+                    for arg in stanza.args.iter() {
+                        writer.write_str(arg, MAX_AGE_STANZA_ARG_LEN)?;
+                    }
+                    writer.write_sized_bytes(
+                        &stanza.body,
+                        MAX_AGE_STANZA_BODY_LEN,
+                    )?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let count = reader.read_u32()?;
+                if count as usize > MAX_AGE_STANZAS {
+                    return Err("too many age stanzas".into());
+                }
+                let mut stanzas = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let tag = reader.read_str()?;
+                    let arg_count = reader.read_u32()?;
+                    if arg_count as usize > MAX_AGE_STANZA_ARGS {
+                        return Err("too many age stanza args".into());
+                    }
+                    let mut args = Vec::with_capacity(arg_count as usize);
+                    for _ in 0..arg_count {
+                        args.push(reader.read_str()?);
+                    }
+                    let body = Arc::new(reader.read_sized_bytes()?);
+                    stanzas.push(AgeStanza { tag, args, body });
+                }
+                LairWire::ToLairAgeDecryptByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    stanzas,
+                }
+            },
+            ToCliAgeDecryptByIndexResponse 0x00000361 false false {
+                file_key: [u8; AGE_FILE_KEY_LEN],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(file_key, AGE_FILE_KEY_LEN)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mut file_key = [0; AGE_FILE_KEY_LEN];
+                file_key.copy_from_slice(
+                    reader.read_bytes(AGE_FILE_KEY_LEN as u64)?,
+                );
+                LairWire::ToCliAgeDecryptByIndexResponse { msg_id, file_key }
+            },
+            ToLairPwPepperNewFromEntropy 0x00000410 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairPwPepperNewFromEntropy { msg_id }
+            },
+            ToCliPwPepperNewFromEntropyResponse 0x00000411 false false {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliPwPepperNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairPwHash 0x00000420 false true {
+                keystore_index: KeystoreIndex,
+                password: Arc<Vec<u8>>,
+                options: PwHashOptions,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // password length
+                    + password.len() // password content
+                    + 4 // mem cost
+                    + 4 // time cost
+                    + 4; // lanes
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(password, password.len())?;
+                writer.write_u32(options.mem_cost_kib)?;
+                writer.write_u32(options.time_cost)?;
+                writer.write_u32(options.lanes)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let password = Arc::new(reader.read_sized_bytes()?);
+                let options = PwHashOptions {
+                    mem_cost_kib: reader.read_u32()?,
+                    time_cost: reader.read_u32()?,
+                    lanes: reader.read_u32()?,
+                };
+                LairWire::ToLairPwHash {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    password,
+                    options,
+                }
+            },
+            ToCliPwHashResponse 0x00000421 false false {
+                hash: String,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // hash length
+                    + hash.len(); // hash content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_str(hash, hash.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let hash = reader.read_str()?;
+                LairWire::ToCliPwHashResponse { msg_id, hash }
+            },
+            ToLairPwVerify 0x00000430 false true {
+                keystore_index: KeystoreIndex,
+                password: Arc<Vec<u8>>,
+                hash: String,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 4 // keystore index
+                    + spec::LEN_PREFIX_LEN // password length
+                    + password.len() // password content
+                    + spec::LEN_PREFIX_LEN // hash length
+                    + hash.len(); // hash content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(password, password.len())?;
+                writer.write_str(hash, hash.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let password = Arc::new(reader.read_sized_bytes()?);
+                let hash = reader.read_str()?;
+                LairWire::ToLairPwVerify {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    password,
+                    hash,
                 }
             },
-            ToLairSignEd25519SignByIndex 0x00000230 false true {
+            ToCliPwVerifyResponse 0x00000431 false false {
+                valid: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*valid as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let valid = reader.read_bool()?;
+                LairWire::ToCliPwVerifyResponse { msg_id, valid }
+            },
+            ToLairTotpNewFromEntropy 0x00000610 false true {
+                options: TotpOptions,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(options.digits)?;
+                writer.write_u32(options.period)?;
+                writer.write_u32(options.alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let options = TotpOptions {
+                    digits: reader.read_u32()?,
+                    period: reader.read_u32()?,
+                    alg: TotpAlg::parse(reader.read_u32()?)?,
+                };
+                LairWire::ToLairTotpNewFromEntropy { msg_id, options }
+            },
+            ToCliTotpNewFromEntropyResponse 0x00000611 false false {
                 keystore_index: KeystoreIndex,
-                message: Arc<Vec<u8>>,
             } |msg_id, wire_type| {
-                // outgoing sig requests just need to be the right size...
-                let size = 4 // msg len
-                    + 4 // msg type
-                    + 8 // msg id
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliTotpNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairTotpImport 0x00000620 false true {
+                secret_b32: String,
+                options: TotpOptions,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // secret length
+                    + secret_b32.len() // secret content
+                    + 4 // digits
+                    + 4 // period
+                    + 4; // alg
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_str(secret_b32, MAX_TOTP_SECRET_B32_LEN)?;
+                writer.write_u32(options.digits)?;
+                writer.write_u32(options.period)?;
+                writer.write_u32(options.alg as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let secret_b32 = reader.read_str()?;
+                let options = TotpOptions {
+                    digits: reader.read_u32()?,
+                    period: reader.read_u32()?,
+                    alg: TotpAlg::parse(reader.read_u32()?)?,
+                };
+                LairWire::ToLairTotpImport {
+                    msg_id,
+                    secret_b32,
+                    options,
+                }
+            },
+            ToCliTotpImportResponse 0x00000621 false false {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToCliTotpImportResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToLairTotpGenerate 0x00000630 false true {
+                keystore_index: KeystoreIndex,
+                at: Option<u64>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                match at {
+                    Some(at) => {
+                        writer.write_bool(true)?;
+                        writer.write_u64(*at)?;
+                    }
+                    None => writer.write_bool(false)?,
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let at = match reader.read_bool()? {
+                    true => Some(reader.read_u64()?),
+                    false => None,
+                };
+                LairWire::ToLairTotpGenerate {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    at,
+                }
+            },
+            ToCliTotpGenerateResponse 0x00000631 false false {
+                code: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(code, MAX_TOTP_DIGITS as usize)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let code = reader.read_str()?;
+                LairWire::ToCliTotpGenerateResponse { msg_id, code }
+            },
+            ToLairTotpExportProvisioningUri 0x00000640 false true {
+                keystore_index: KeystoreIndex,
+                label: String,
+                issuer: String,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
                     + 4 // keystore index
-                    + 8 // message length
-                    + message.len(); // message content
-                let mut writer = codec::CodecWriter::new_zeroed(size)?;
-                writer.write_u32(size as u32)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
+                    + spec::LEN_PREFIX_LEN // label length
+                    + label.len() // label content
+                    + spec::LEN_PREFIX_LEN // issuer length
+                    + issuer.len(); // issuer content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
-                writer.write_sized_bytes(message, message.len())?;
+                writer.write_str(label, MAX_TOTP_LABEL_LEN)?;
+                writer.write_str(issuer, MAX_TOTP_LABEL_LEN)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                let message = Arc::new(reader.read_sized_bytes()?);
-                LairWire::ToLairSignEd25519SignByIndex {
+                let label = reader.read_str()?;
+                let issuer = reader.read_str()?;
+                LairWire::ToLairTotpExportProvisioningUri {
                     msg_id,
                     keystore_index: keystore_index.into(),
-                    message,
+                    label,
+                    issuer,
                 }
             },
-            ToCliSignEd25519SignByIndexResponse 0x00000231 false false {
-                signature: SignEd25519Signature,
+            ToCliTotpExportProvisioningUriResponse 0x00000641 false false {
+                uri: String,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // uri length
+                    + uri.len(); // uri content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_str(uri, uri.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let uri = reader.read_str()?;
+                LairWire::ToCliTotpExportProvisioningUriResponse { msg_id, uri }
+            },
+            ToLairEphemeralX25519New 0x00000810 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairEphemeralX25519New { msg_id }
+            },
+            ToCliEphemeralX25519NewResponse 0x00000811 false false {
+                handle: EphemeralHandle,
+                pub_key: X25519PubKey,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(signature, 64)?;
+                writer.write_u64(**handle)?;
+                writer.write_bytes_exact(pub_key, 32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let signature = reader.read_bytes(64)?.to_vec();
-                LairWire::ToCliSignEd25519SignByIndexResponse {
+                let handle = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliEphemeralX25519NewResponse {
                     msg_id,
-                    signature: signature.into(),
+                    handle: handle.into(),
+                    pub_key: pub_key.into(),
                 }
             },
-            ToLairSignEd25519SignByPubKey 0x00000240 false true {
+            ToLairEphemeralSignEd25519New 0x00000820 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairEphemeralSignEd25519New { msg_id }
+            },
+            ToCliEphemeralSignEd25519NewResponse 0x00000821 false false {
+                handle: EphemeralHandle,
                 pub_key: SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(**handle)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let handle = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliEphemeralSignEd25519NewResponse {
+                    msg_id,
+                    handle: handle.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairEphemeralSignEd25519Sign 0x00000830 false true {
+                handle: EphemeralHandle,
                 message: Arc<Vec<u8>>,
             } |msg_id, wire_type| {
-                // outgoing sig requests just need to be the right size...
-                let size = 4 // msg len
-                    + 4 // msg type
-                    + 8 // msg id
-                    + 32 // pub_key
-                    + 8 // message length
+                let size = spec::HEADER_LEN
+                    + 8 // handle
+                    + spec::LEN_PREFIX_LEN // message length
                     + message.len(); // message content
-                let mut writer = codec::CodecWriter::new_zeroed(size)?;
-                writer.write_u32(size as u32)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_bytes_exact(pub_key, 32)?;
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(**handle)?;
                 writer.write_sized_bytes(message, message.len())?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
+                let handle = reader.read_u64()?;
                 let message = Arc::new(reader.read_sized_bytes()?);
-                LairWire::ToLairSignEd25519SignByPubKey {
+                LairWire::ToLairEphemeralSignEd25519Sign {
                     msg_id,
-                    pub_key: pub_key.into(),
+                    handle: handle.into(),
                     message,
                 }
             },
-            ToCliSignEd25519SignByPubKeyResponse 0x00000241 false false {
+            ToCliEphemeralSignEd25519SignResponse 0x00000831 false false {
                 signature: SignEd25519Signature,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
@@ -489,11 +4109,115 @@ macro_rules! wire_type_meta_macro {
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let signature = reader.read_bytes(64)?.to_vec();
-                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                LairWire::ToCliEphemeralSignEd25519SignResponse {
                     msg_id,
                     signature: signature.into(),
                 }
             },
+            ToLairEphemeralCryptoBox 0x00000840 false true {
+                sender_handle: EphemeralHandle,
+                recipient_pub_key: X25519PubKey,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 8 // sender handle
+                    + 32 // recipient pub key
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(**sender_handle)?;
+                writer.write_bytes_exact(recipient_pub_key, 32)?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let sender_handle = reader.read_u64()?;
+                let recipient_pub_key = reader.read_bytes(32)?.to_vec();
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairEphemeralCryptoBox {
+                    msg_id,
+                    sender_handle: sender_handle.into(),
+                    recipient_pub_key: recipient_pub_key.into(),
+                    data,
+                }
+            },
+            ToCliEphemeralCryptoBoxResponse 0x00000841 false false {
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliEphemeralCryptoBoxResponse { msg_id, data }
+            },
+            ToLairEphemeralCryptoBoxOpen 0x00000850 false true {
+                recipient_handle: EphemeralHandle,
+                sender_pub_key: X25519PubKey,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + 8 // recipient handle
+                    + 32 // sender pub key
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_u64(**recipient_handle)?;
+                writer.write_bytes_exact(sender_pub_key, 32)?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let recipient_handle = reader.read_u64()?;
+                let sender_pub_key = reader.read_bytes(32)?.to_vec();
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairEphemeralCryptoBoxOpen {
+                    msg_id,
+                    recipient_handle: recipient_handle.into(),
+                    sender_pub_key: sender_pub_key.into(),
+                    data,
+                }
+            },
+            ToCliEphemeralCryptoBoxOpenResponse 0x00000851 false false {
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = spec::HEADER_LEN
+                    + spec::LEN_PREFIX_LEN // data length
+                    + data.len(); // data content
+                let mut writer = sized_encode_setup!(size, msg_id, wire_type);
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliEphemeralCryptoBoxOpenResponse { msg_id, data }
+            },
+            ToLairEphemeralDrop 0x00000860 false true {
+                handle: EphemeralHandle,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(**handle)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let handle = reader.read_u64()?;
+                LairWire::ToLairEphemeralDrop {
+                    msg_id,
+                    handle: handle.into(),
+                }
+            },
+            ToCliEphemeralDropResponse 0x00000861 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliEphemeralDropResponse { msg_id }
+            },
         }
     };
 }
@@ -574,6 +4298,15 @@ macro_rules! lair_wire_enum {
                 )*}
             }
 
+            /// The name of this variant, e.g. `ToLairLairGetEntryType`.
+            pub fn variant_name(&self) -> &'static str {
+                match self {$(
+                    LairWire::$variant {
+                        ..
+                    } => stringify!($variant),
+                )*}
+            }
+
             /// Get the msg_id associated with this variant.
             pub fn get_msg_id(&self) -> u64 {
                 match self {$(
@@ -587,7 +4320,7 @@ macro_rules! lair_wire_enum {
             /// Encode this variant into lair wire protocol binary data.
             #[allow(unused_variables)]
             pub fn encode(&self) -> LairResult<Vec<u8>> {
-                match self {$(
+                let data: LairResult<Vec<u8>> = match self {$(
                     LairWire::$variant {
                         msg_id: $msg_id,
                         $(
@@ -597,20 +4330,25 @@ macro_rules! lair_wire_enum {
                         let $wire_type: u32 = $repr;
                         $encode
                     }
-                )*}
+                )*};
+                let mut data = data?;
+                if data.len() > spec::MAX_MESSAGE_LEN {
+                    return Err(LairError::MessageTooLarge {
+                        len: data.len(),
+                        max: spec::MAX_MESSAGE_LEN,
+                    });
+                }
+                // padded messages may outgrow their padding
+                spec::set_message_len(&mut data)?;
+                Ok(data)
             }
 
             /// Returns the amount of data we need to decode the next item.
             pub fn peek_size(data: &[u8]) -> LairResult<usize> {
-                if data.len() < 4 {
+                if data.len() < spec::MESSAGE_LEN_LEN {
                     return Err("not enough to read size".into());
                 }
-                use byteorder::ReadBytesExt;
-                let size = match (&data[0..4]).read_u32::<byteorder::LittleEndian>() {
-                    Ok(size) => size,
-                    Err(e) => return Err(LairError::other(e)),
-                };
-                Ok(size as usize)
+                spec::decode_message_len(data)
             }
 
             /// Returns true if we have enough bytes to decode.
@@ -622,46 +4360,572 @@ macro_rules! lair_wire_enum {
                 data.len() >= size as usize
             }
 
-            /// Decode lair wire protocol binary data into enum variant.
-            #[allow(unused_mut)]
-            #[allow(unused_variables)]
-            pub fn decode(data: &[u8]) -> LairResult<Self> {
-                if !Self::peek_size_ok(data) {
-                    return Err("not enough data to decode".into());
-                }
-                let mut reader = codec::CodecReader::new(data);
-                let _size = reader.read_u32()?;
+            /// Decode lair wire protocol binary data into enum variant.
+            #[allow(unused_mut)]
+            #[allow(unused_variables)]
+            pub fn decode(data: &[u8]) -> LairResult<Self> {
+                if !Self::peek_size_ok(data) {
+                    return Err("not enough data to decode".into());
+                }
+                let mut reader = codec::CodecReader::new(data);
+                let _size = reader.read_u32()?;
+
+                let wire_type = LairWireType::parse(reader.read_u32()?)?;
+
+                Ok(match wire_type {
+                    $(
+                        LairWireType::$variant => {
+                            let mut $reader = reader;
+                            $decode
+                        }
+                    )*
+                })
+            }
+        }
+    };
+}
+
+wire_type_meta_macro!(lair_wire_enum);
+
+impl LairWire {
+    /// True if the encoding of this message carries a secret, which the
+    /// ipc read and write loops zeroize once done with the frame.
+    pub fn carries_secret(&self) -> bool {
+        matches!(
+            self,
+            LairWire::ToLairRequestUnlockPassphraseResponse { .. }
+                | LairWire::ToCliSignEd25519ExportSeedResponse { .. }
+        )
+    }
+
+    /// The capability a connection needs for the server to handle
+    /// this request, if any. Responses and events need `Admin`,
+    /// servers never handle them as requests.
+    pub fn required_capability(&self) -> Option<LairCapability> {
+        use LairCapability::*;
+        match self {
+            LairWire::ToLairAuthenticate { .. }
+            | LairWire::ToLairCancelRequest { .. }
+            | LairWire::ToLairNegotiateCompression { .. }
+            | LairWire::ToLairRequestDeadline { .. }
+            | LairWire::ToLairLairSelectStore { .. }
+            | LairWire::ToLairLairGetServerInfo { .. }
+            | LairWire::ToLairLairProveServerIdentity { .. }
+            | LairWire::ToLairLairGetCapabilities { .. }
+            | LairWire::ToLairLairSetEventFilter { .. }
+            | LairWire::ToLairLairRequestElevation { .. }
+            | LairWire::ToLairLairGetElevation { .. }
+            | LairWire::ToLairLairDropElevation { .. } => None,
+            LairWire::ToLairLairGetLastEntryIndex { .. }
+            | LairWire::ToLairLairGetLastEntryIndex64 { .. }
+            | LairWire::ToLairLairGetEntryCount { .. }
+            | LairWire::ToLairLairGetStoreStats { .. }
+            | LairWire::ToLairLairGetEntryType { .. }
+            | LairWire::ToLairLairGetEntryType64 { .. }
+            | LairWire::ToLairLairGetEntryTypes { .. }
+            | LairWire::ToLairLairListEntriesPage { .. }
+            | LairWire::ToLairLairGetEntryByTag { .. }
+            | LairWire::ToLairLairListQuarantined { .. }
+            | LairWire::ToLairLairFindEntriesByPrefix { .. }
+            | LairWire::ToLairLairGetRandomBytes { .. }
+            | LairWire::ToLairLairNewUuid { .. }
+            | LairWire::ToLairTlsCertGet { .. }
+            | LairWire::ToLairTlsCertGetInfo { .. }
+            | LairWire::ToLairTlsCertGetCertByIndex { .. }
+            | LairWire::ToLairTlsCertGetCertByDigest { .. }
+            | LairWire::ToLairTlsCertGetCertBySni { .. }
+            | LairWire::ToLairTlsCertResolveByDigest { .. }
+            | LairWire::ToLairTlsCertResolveBySni { .. }
+            | LairWire::ToLairTlsCertGetDigest { .. }
+            | LairWire::ToLairTlsCertGetCertByTypedDigest { .. }
+            | LairWire::ToLairSignEd25519Get { .. }
+            | LairWire::ToLairSignEd25519ResolveByPubKey { .. }
+            | LairWire::ToLairSignEd25519GetOptions { .. }
+            | LairWire::ToLairSignSecp256k1Get { .. }
+            | LairWire::ToLairSignEd448Get { .. }
+            | LairWire::ToLairSignBlsGet { .. }
+            | LairWire::ToLairSignSr25519Get { .. }
+            | LairWire::ToLairX25519Get { .. } => Some(ReadPublic),
+            LairWire::ToLairSignEd25519SignByIndex { .. }
+            | LairWire::ToLairSignEd25519SignByPubKey { .. }
+            | LairWire::ToLairSignEd25519SignByIndexV2 { .. }
+            | LairWire::ToLairSignEd25519SignByIndexWithOptions { .. }
+            | LairWire::ToLairSignEd25519SignByPubKeyV2 { .. }
+            | LairWire::ToLairSignEd25519SignByIndex64 { .. }
+            | LairWire::ToLairSignEd25519SignByTag { .. }
+            | LairWire::ToLairSignEd25519PhSignByIndex { .. }
+            | LairWire::ToLairSignSecp256k1SignByIndex { .. }
+            | LairWire::ToLairSignEd448SignByIndex { .. }
+            | LairWire::ToLairSignEd448SignByPubKey { .. }
+            | LairWire::ToLairSignBlsSignByIndex { .. }
+            | LairWire::ToLairSignSr25519SignByIndex { .. }
+            | LairWire::ToLairLairAttestEntry { .. }
+            | LairWire::ToLairCryptoBoxToSignPubKey { .. }
+            | LairWire::ToLairCryptoBoxOpenBySignPubKey { .. }
+            | LairWire::ToLairCryptoBoxSealOpenByIndex { .. }
+            | LairWire::ToLairAgeDecryptByIndex { .. }
+            | LairWire::ToLairPwHash { .. }
+            | LairWire::ToLairPwVerify { .. }
+            | LairWire::ToLairTotpGenerate { .. }
+            | LairWire::ToLairEphemeralX25519New { .. }
+            | LairWire::ToLairEphemeralSignEd25519New { .. }
+            | LairWire::ToLairEphemeralSignEd25519Sign { .. }
+            | LairWire::ToLairEphemeralCryptoBox { .. }
+            | LairWire::ToLairEphemeralCryptoBoxOpen { .. }
+            | LairWire::ToLairEphemeralDrop { .. } => Some(Sign),
+            LairWire::ToLairLairSetEntryTag { .. }
+            | LairWire::ToLairLairSetEntryTags { .. }
+            | LairWire::ToLairLairAddEntryAlias { .. }
+            | LairWire::ToLairLairRemoveEntryAlias { .. }
+            | LairWire::ToLairLairUnwrapEntry { .. }
+            | LairWire::ToLairTlsCertNewSelfSignedFromEntropy { .. }
+            | LairWire::ToLairTlsCertNewSelfSignedFromSignKey { .. }
+            | LairWire::ToLairSignEd25519NewFromEntropy { .. }
+            | LairWire::ToLairSignEd25519New { .. }
+            | LairWire::ToLairSignEd25519NewFromEntropyWithOptions { .. }
+            | LairWire::ToLairSignEd25519NewFromSeedWithOptions { .. }
+            | LairWire::ToLairSignEd25519Rotate { .. }
+            | LairWire::ToLairSignEd25519ImportSshKey { .. }
+            | LairWire::ToLairSignSecp256k1NewFromEntropy { .. }
+            | LairWire::ToLairSignSecp256k1NewFromSeed { .. }
+            | LairWire::ToLairSignEd448NewFromEntropy { .. }
+            | LairWire::ToLairSignBlsNewFromEntropy { .. }
+            | LairWire::ToLairSignSr25519NewFromEntropy { .. }
+            | LairWire::ToLairSignSr25519NewFromSeed { .. }
+            | LairWire::ToLairX25519NewFromEntropy { .. }
+            | LairWire::ToLairPwPepperNewFromEntropy { .. }
+            | LairWire::ToLairTotpNewFromEntropy { .. }
+            | LairWire::ToLairTotpImport { .. } => Some(Create),
+            LairWire::ToLairTlsCertGetPrivKeyByIndex { .. }
+            | LairWire::ToLairTlsCertGetPrivKeyByDigest { .. }
+            | LairWire::ToLairTlsCertGetPrivKeyBySni { .. }
+            | LairWire::ToLairTlsCertGetBundleByTag { .. }
+            | LairWire::ToLairLairWrapEntry { .. }
+            | LairWire::ToLairSignEd25519ExportSeed { .. }
+            | LairWire::ToLairTotpExportProvisioningUri { .. } => {
+                Some(ExportPrivate)
+            }
+            _ => Some(Admin),
+        }
+    }
+
+    /// The `LairClientApi` function making this request, as its
+    /// `LairAuditRecord` names it, e.g. `sign_ed25519_sign_by_index`
+    /// for `ToLairSignEd25519SignByIndex`.
+    pub(crate) fn audit_operation(&self) -> String {
+        let name = self.variant_name();
+        let name = name.strip_prefix("ToLair").unwrap_or(name);
+        let mut operation = String::with_capacity(name.len() * 2);
+        for (i, c) in name.chars().enumerate() {
+            if c.is_ascii_uppercase() {
+                if i > 0 {
+                    operation.push('_');
+                }
+                operation.push(c.to_ascii_lowercase());
+            } else {
+                operation.push(c);
+            }
+        }
+        operation
+    }
+
+    /// The entry this request names, by index or by pub key,
+    /// as its `LairAuditRecord` records it.
+    pub(crate) fn audit_entry(
+        &self,
+    ) -> (Option<KeystoreIndex64>, Option<Arc<Vec<u8>>>) {
+        match self {
+            LairWire::ToLairAgeDecryptByIndex { keystore_index, .. }
+            | LairWire::ToLairCryptoBoxSealOpenByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairLairActivateEntry { keystore_index, .. }
+            | LairWire::ToLairLairAddEntryAlias { keystore_index, .. }
+            | LairWire::ToLairLairAttestEntry { keystore_index, .. }
+            | LairWire::ToLairLairGetEntryType { keystore_index, .. }
+            | LairWire::ToLairLairRestoreEntry { keystore_index, .. }
+            | LairWire::ToLairLairSetEntryTag { keystore_index, .. }
+            | LairWire::ToLairLairTrashEntry { keystore_index, .. }
+            | LairWire::ToLairPwHash { keystore_index, .. }
+            | LairWire::ToLairPwVerify { keystore_index, .. }
+            | LairWire::ToLairSignEd25519ExportSeed {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd25519Get { keystore_index, .. }
+            | LairWire::ToLairSignEd25519GetOptions {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd25519PhSignByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd25519SignByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd25519SignByIndexV2 {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd25519SignByIndexWithOptions {
+                keystore_index,
+                ..
+            }
+            | LairWire::ToLairSignBlsGet { keystore_index, .. }
+            | LairWire::ToLairSignBlsSignByIndex { keystore_index, .. }
+            | LairWire::ToLairSignSr25519Get { keystore_index, .. }
+            | LairWire::ToLairSignSr25519SignByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairSignEd448Get { keystore_index, .. }
+            | LairWire::ToLairSignEd448SignByIndex { keystore_index, .. }
+            | LairWire::ToLairSignSecp256k1Get { keystore_index, .. }
+            | LairWire::ToLairSignSecp256k1SignByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairTlsCertGet { keystore_index, .. }
+            | LairWire::ToLairTlsCertGetCertByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairTlsCertGetDigest { keystore_index, .. }
+            | LairWire::ToLairTlsCertGetInfo { keystore_index, .. }
+            | LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                keystore_index, ..
+            }
+            | LairWire::ToLairTlsCertNewSelfSignedFromSignKey {
+                keystore_index,
+                ..
+            }
+            | LairWire::ToLairTotpExportProvisioningUri {
+                keystore_index,
+                ..
+            }
+            | LairWire::ToLairTotpGenerate { keystore_index, .. }
+            | LairWire::ToLairX25519Get { keystore_index, .. } => {
+                (Some((*keystore_index).into()), None)
+            }
+            LairWire::ToLairLairGetEntryType64 { keystore_index, .. }
+            | LairWire::ToLairSignEd25519SignByIndex64 {
+                keystore_index, ..
+            } => (Some(*keystore_index), None),
+            LairWire::ToLairLairWrapEntry { target_index, .. } => {
+                (Some((*target_index).into()), None)
+            }
+            LairWire::ToLairLairUnwrapEntry {
+                recipient_index, ..
+            } => (Some((*recipient_index).into()), None),
+            LairWire::ToLairSignEd25519SignByPubKey { pub_key, .. }
+            | LairWire::ToLairSignEd25519SignByPubKeyV2 { pub_key, .. }
+            | LairWire::ToLairSignEd25519ResolveByPubKey { pub_key, .. }
+            | LairWire::ToLairCryptoBoxOpenBySignPubKey {
+                recipient_sign_pub_key: pub_key,
+                ..
+            } => (None, Some(pub_key.0.clone())),
+            LairWire::ToLairSignEd448SignByPubKey { pub_key, .. } => {
+                (None, Some(pub_key.0.clone()))
+            }
+            _ => (None, None),
+        }
+    }
+}
+
+/// The strategy generating a `LairWire` field.
+#[cfg(any(test, feature = "proptest"))]
+pub trait WireField: Sized {
+    /// Generate values of this field.
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self>;
+}
+
+#[cfg(any(test, feature = "proptest"))]
+macro_rules! wire_field_any {
+    ($($t:ty,)*) => {$(
+        impl WireField for $t {
+            fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+                use proptest::prelude::*;
+                any::<$t>().boxed()
+            }
+        }
+    )*};
+}
+
+#[cfg(any(test, feature = "proptest"))]
+wire_field_any! {
+    bool,
+    u8,
+    u32,
+    u64,
+    [u8; 16],
+    [u8; 32],
+    String,
+    Option<String>,
+    Passphrase,
+    Arc<Vec<u8>>,
+    KeystoreIndex,
+    Option<KeystoreIndex>,
+    Vec<KeystoreIndex>,
+    Vec<(KeystoreIndex, String)>,
+    Vec<Option<BulkItemError>>,
+    KeystoreIndex64,
+    Option<KeystoreIndex64>,
+    EphemeralHandle,
+    Vec<(KeystoreIndex, LairEntryType)>,
+    LairEntryType,
+    Vec<LairEntryInfo>,
+    Vec<QuarantinedEntry>,
+    RotateKeyOptions,
+    RotationRecord,
+    TlsCertAlg,
+    DigestAlg,
+    PrefixKind,
+    KeyBacking,
+    PwHashOptions,
+    TotpOptions,
+    SignKeyOptions,
+    SignOptions,
+    Option<u64>,
+    LairCapabilities,
+    Vec<LairConnectionInfo>,
+    LairElevation,
+    Option<LairElevation>,
+    LairAuditRecord,
+    StoreStats,
+    Vec<EventKind>,
+    Vec<AgeStanza>,
+    LairServerInfo,
+    LairServerPaths,
+    Attestation,
+    ServerIdentityProof,
+    Cert,
+    CertPrivKey,
+    CertSni,
+    CertDigest,
+    SignEd25519PubKey,
+    SignEd25519Signature,
+    X25519PubKey,
+    SignSecp256k1PubKey,
+    SignSecp256k1Signature,
+    SignEd448PubKey,
+    SignEd448Signature,
+    SignBlsPubKey,
+    SignBlsSignature,
+    SignSr25519PubKey,
+    SignSr25519Signature,
+}
+
+/// proptest only generates arrays of up to 32 elements.
+#[cfg(any(test, feature = "proptest"))]
+impl WireField for [u8; 64] {
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+        use proptest::prelude::*;
+        any::<([u8; 32], [u8; 32])>()
+            .prop_map(|(a, b)| {
+                let mut out = [0; 64];
+                out[..32].copy_from_slice(&a);
+                out[32..].copy_from_slice(&b);
+                out
+            })
+            .boxed()
+    }
+}
+
+/// Error cause chains within `spec::MAX_ERROR_CAUSES`.
+#[cfg(any(test, feature = "proptest"))]
+impl WireField for Vec<ErrorCause> {
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+        use proptest::prelude::*;
+        proptest::collection::vec(
+            any::<ErrorCause>(),
+            0..=spec::MAX_ERROR_CAUSES as usize,
+        )
+        .boxed()
+    }
+}
+
+/// Error details within `spec::MAX_ERROR_DETAILS`.
+#[cfg(any(test, feature = "proptest"))]
+impl WireField for Vec<u64> {
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+        use proptest::prelude::*;
+        proptest::collection::vec(
+            any::<u64>(),
+            0..=spec::MAX_ERROR_DETAILS as usize,
+        )
+        .boxed()
+    }
+}
 
-                let wire_type = LairWireType::parse(reader.read_u32()?)?;
+/// BLS dsts, within `bls::MAX_DST_LEN`, and sr25519 signing contexts.
+/// Empty contexts are valid too, see `it_sends_empty_sr25519_contexts`.
+#[cfg(any(test, feature = "proptest"))]
+impl WireField for Vec<u8> {
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+        use proptest::prelude::*;
+        proptest::collection::vec(any::<u8>(), 1..=bls::MAX_DST_LEN).boxed()
+    }
+}
 
-                Ok(match wire_type {
-                    $(
-                        LairWireType::$variant => {
-                            let mut $reader = reader;
-                            $decode
-                        }
-                    )*
-                })
+/// A request without fields, generating a whole `LairWire`
+/// here would never finish building the strategy.
+/// See `arbitrary_deadline_request` for deadline requests
+/// carrying any request.
+#[cfg(any(test, feature = "proptest"))]
+impl WireField for Box<LairWire> {
+    fn wire_field() -> proptest::strategy::BoxedStrategy<Self> {
+        use proptest::prelude::*;
+        any::<u64>()
+            .prop_map(|msg_id| {
+                Box::new(LairWire::ToLairLairGetServerInfo { msg_id })
+            })
+            .boxed()
+    }
+}
+
+#[cfg(any(test, feature = "proptest"))]
+macro_rules! lair_wire_arbitrary {
+    ($(
+        $variant:ident $repr:literal $is_evt:literal $is_req:literal {$(
+            $p_name:ident: $p_ty:ty,
+        )*}
+        |$msg_id:ident, $wire_type:ident| $encode:block
+        |$reader:ident| $decode:block,
+    )*) => {
+        impl proptest::arbitrary::Arbitrary for LairWire {
+            type Parameters = ();
+            type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use proptest::prelude::*;
+                proptest::strategy::Union::new(vec![$(
+                    (
+                        any::<u64>(),
+                        $(<$p_ty as WireField>::wire_field(),)*
+                    )
+                        .prop_map(|(msg_id, $($p_name,)*)| {
+                            LairWire::$variant {
+                                msg_id,
+                                $($p_name,)*
+                            }
+                        })
+                        .boxed(),
+                )*])
+                .boxed()
             }
         }
     };
 }
 
-wire_type_meta_macro!(lair_wire_enum);
+#[cfg(any(test, feature = "proptest"))]
+wire_type_meta_macro!(lair_wire_arbitrary);
+
+/// Deadline requests carrying any plain request,
+/// as `LairWire::ToLairRequestDeadline` must.
+#[cfg(any(test, feature = "proptest"))]
+pub fn arbitrary_deadline_request(
+) -> proptest::strategy::BoxedStrategy<LairWire> {
+    use proptest::prelude::*;
+    (any::<LairWire>(), any::<u64>())
+        .prop_filter("a plain request", |(request, _)| {
+            request.is_req()
+                && !request.is_event()
+                && !matches!(request, LairWire::ToLairRequestDeadline { .. })
+        })
+        .prop_map(|(request, remaining_ms)| LairWire::ToLairRequestDeadline {
+            msg_id: request.get_msg_id(),
+            remaining_ms,
+            request: Box::new(request),
+        })
+        .boxed()
+}
+
+/// Encode `options` as described at `spec::SIGN_KEY_OPTION_TAG`.
+fn encode_sign_key_options(options: &SignKeyOptions) -> LairResult<Vec<u8>> {
+    let defaults = SignKeyOptions::default();
+    let mut fields: Vec<(u32, Vec<u8>)> = Vec::new();
+    if let Some(tag) = &options.tag {
+        if tag.len() > MAX_ENTRY_TAG_LEN {
+            return Err(
+                format!("exceeded {} byte maximum", MAX_ENTRY_TAG_LEN).into()
+            );
+        }
+        fields.push((spec::SIGN_KEY_OPTION_TAG, tag.as_bytes().to_vec()));
+    }
+    if options.exportable != defaults.exportable {
+        fields.push((
+            spec::SIGN_KEY_OPTION_EXPORTABLE,
+            spec::encode_bool(options.exportable).to_vec(),
+        ));
+    }
+    if let Some(expires_at) = options.expires_at {
+        fields.push((
+            spec::SIGN_KEY_OPTION_EXPIRES_AT,
+            spec::encode_u64(expires_at).to_vec(),
+        ));
+    }
+    if options.requires_approval != defaults.requires_approval {
+        fields.push((
+            spec::SIGN_KEY_OPTION_REQUIRES_APPROVAL,
+            spec::encode_bool(options.requires_approval).to_vec(),
+        ));
+    }
+    encode_option_fields(&fields)
+}
+
+/// Encode `options` as described at `spec::SIGN_OPTION_INCLUDE_TIMESTAMP`.
+fn encode_sign_options(options: &SignOptions) -> LairResult<Vec<u8>> {
+    let defaults = SignOptions::default();
+    let mut fields: Vec<(u32, Vec<u8>)> = Vec::new();
+    if options.include_timestamp != defaults.include_timestamp {
+        fields.push((
+            spec::SIGN_OPTION_INCLUDE_TIMESTAMP,
+            spec::encode_bool(options.include_timestamp).to_vec(),
+        ));
+    }
+    encode_option_fields(&fields)
+}
+
+/// Encode a count of option `fields`, then each as its `u32` id
+/// and its length prefixed value.
+fn encode_option_fields(fields: &[(u32, Vec<u8>)]) -> LairResult<Vec<u8>> {
+    let size = spec::COUNT_LEN
+        + fields
+            .iter()
+            .map(|(_, value)| {
+                spec::U32_LEN + spec::LEN_PREFIX_LEN + value.len()
+            })
+            .sum::<usize>();
+    let mut writer = codec::CodecWriter::new_zeroed(size)?;
+    writer.write_u32(fields.len() as u32)?;
+    for (id, value) in fields.iter() {
+        writer.write_u32(*id)?;
+        writer.write_sized_bytes(value, value.len())?;
+    }
+    Ok(writer.into_vec())
+}
 
 trait WriterExt {
+    fn write_bool(&mut self, b: bool) -> LairResult<()>;
     fn write_str(&mut self, s: &str, max: usize) -> LairResult<()>;
     fn write_bytes_exact(&mut self, b: &[u8], len: usize) -> LairResult<()>;
     fn write_sized_bytes(&mut self, b: &[u8], max: usize) -> LairResult<()>;
+    fn write_bulk_results(
+        &mut self,
+        results: &[Option<BulkItemError>],
+    ) -> LairResult<()>;
+    fn write_audit_record(
+        &mut self,
+        record: &LairAuditRecord,
+    ) -> LairResult<()>;
+    fn write_elevation(&mut self, elevation: &LairElevation) -> LairResult<()>;
 }
 
 impl WriterExt for codec::CodecWriter {
+    fn write_bool(&mut self, b: bool) -> LairResult<()> {
+        self.write_bytes(&spec::encode_bool(b))
+    }
+
     fn write_str(&mut self, s: &str, max: usize) -> LairResult<()> {
         let s = s.as_bytes();
         if s.len() > max {
             return Err(format!("exceeded {} byte maximum", max).into());
         }
-        self.write_u64(s.len() as u64)?;
+        self.write_bytes(&spec::encode_len_prefix(s.len()))?;
         self.write_bytes(s)?;
         Ok(())
     }
@@ -683,27 +4947,229 @@ impl WriterExt for codec::CodecWriter {
         if b.len() > max {
             return Err(format!("exceeded {} byte maximum", max).into());
         }
-        self.write_u64(b.len() as u64)?;
+        self.write_bytes(&spec::encode_len_prefix(b.len()))?;
         self.write_bytes(b)?;
         Ok(())
     }
+
+    fn write_bulk_results(
+        &mut self,
+        results: &[Option<BulkItemError>],
+    ) -> LairResult<()> {
+        check_bulk_entries_len(results.len())?;
+        self.write_u32(results.len() as u32)?;
+        for result in results.iter() {
+            match result {
+                Some(e) => {
+                    self.write_bool(true)?;
+                    self.write_u32(e.kind)?;
+                    self.write_str(&e.message, MAX_ERROR_MESSAGE_LEN)?;
+                }
+                None => self.write_bool(false)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_audit_record(
+        &mut self,
+        record: &LairAuditRecord,
+    ) -> LairResult<()> {
+        self.write_str(&record.operation, MAX_AUDIT_OPERATION_LEN)?;
+        match record.keystore_index {
+            Some(keystore_index) => {
+                self.write_bool(true)?;
+                self.write_u64(*keystore_index)?;
+            }
+            None => self.write_bool(false)?,
+        }
+        match &record.pub_key {
+            Some(pub_key) => {
+                self.write_bool(true)?;
+                self.write_sized_bytes(pub_key, MAX_AUDIT_PUB_KEY_LEN)?;
+            }
+            None => self.write_bool(false)?,
+        }
+        self.write_u64(record.connection_id)?;
+        self.write_u32(record.capabilities.0)?;
+        self.write_u64(record.request_id)?;
+        match &record.error {
+            Some(error) => {
+                self.write_bool(true)?;
+                self.write_str(error, MAX_ERROR_MESSAGE_LEN)?;
+            }
+            None => self.write_bool(false)?,
+        }
+        Ok(())
+    }
+
+    fn write_elevation(&mut self, elevation: &LairElevation) -> LairResult<()> {
+        self.write_u32(elevation.capabilities.0)?;
+        self.write_u64(elevation.remaining.as_millis() as u64)?;
+        Ok(())
+    }
 }
 
 trait ReaderExt {
+    fn read_bool(&mut self) -> LairResult<bool>;
     fn read_str(&mut self) -> LairResult<String>;
     fn read_sized_bytes(&mut self) -> LairResult<Vec<u8>>;
+    fn read_sign_key_options(&mut self) -> LairResult<SignKeyOptions>;
+    fn read_sign_options(&mut self) -> LairResult<SignOptions>;
+    fn read_error_causes(&mut self) -> LairResult<Vec<ErrorCause>>;
+    fn read_error_details(&mut self) -> LairResult<Vec<u64>>;
+    fn read_bulk_results(&mut self) -> LairResult<Vec<Option<BulkItemError>>>;
+    fn read_audit_record(&mut self) -> LairResult<LairAuditRecord>;
+    fn read_elevation(&mut self) -> LairResult<LairElevation>;
 }
 
 impl ReaderExt for codec::CodecReader<'_> {
+    fn read_bool(&mut self) -> LairResult<bool> {
+        spec::decode_bool(self.read_bytes(spec::BOOL_LEN as u64)?)
+    }
+
     fn read_str(&mut self) -> LairResult<String> {
         let len = self.read_u64()?;
-        Ok(String::from_utf8_lossy(self.read_bytes(len)?).to_string())
+        Ok(spec::decode_str(self.read_bytes(len)?))
     }
 
     fn read_sized_bytes(&mut self) -> LairResult<Vec<u8>> {
         let len = self.read_u64()?;
         Ok(self.read_bytes(len)?.to_vec())
     }
+
+    fn read_sign_key_options(&mut self) -> LairResult<SignKeyOptions> {
+        let count = self.read_u32()?;
+        if count > spec::MAX_SIGN_KEY_OPTION_FIELDS {
+            return Err("too many sign key options".into());
+        }
+        let mut options = SignKeyOptions::default();
+        for _ in 0..count {
+            let id = self.read_u32()?;
+            let value = self.read_sized_bytes()?;
+            match id {
+                spec::SIGN_KEY_OPTION_TAG => {
+                    options.tag = Some(spec::decode_str(&value));
+                }
+                spec::SIGN_KEY_OPTION_EXPORTABLE => {
+                    options.exportable = spec::decode_bool(&value)?;
+                }
+                spec::SIGN_KEY_OPTION_EXPIRES_AT => {
+                    options.expires_at = Some(spec::decode_u64(&value)?);
+                }
+                spec::SIGN_KEY_OPTION_REQUIRES_APPROVAL => {
+                    options.requires_approval = spec::decode_bool(&value)?;
+                }
+                _ => {
+                    return Err(format!(
+                        "unsupported sign key option: {:#010x}",
+                        id
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    fn read_sign_options(&mut self) -> LairResult<SignOptions> {
+        let count = self.read_u32()?;
+        if count > spec::MAX_SIGN_OPTION_FIELDS {
+            return Err("too many sign options".into());
+        }
+        let mut options = SignOptions::default();
+        for _ in 0..count {
+            let id = self.read_u32()?;
+            let value = self.read_sized_bytes()?;
+            match id {
+                spec::SIGN_OPTION_INCLUDE_TIMESTAMP => {
+                    options.include_timestamp = spec::decode_bool(&value)?;
+                }
+                _ => {
+                    return Err(format!(
+                        "unsupported sign option: {:#010x}",
+                        id
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    fn read_error_causes(&mut self) -> LairResult<Vec<ErrorCause>> {
+        let count = self.read_u32()?;
+        if count > spec::MAX_ERROR_CAUSES {
+            return Err("too many error causes".into());
+        }
+        (0..count)
+            .map(|_| {
+                let code = self.read_u32()?;
+                let message = self.read_str()?;
+                Ok(ErrorCause { code, message })
+            })
+            .collect()
+    }
+
+    fn read_error_details(&mut self) -> LairResult<Vec<u64>> {
+        let count = self.read_u32()?;
+        if count > spec::MAX_ERROR_DETAILS {
+            return Err("too many error details".into());
+        }
+        (0..count).map(|_| self.read_u64()).collect()
+    }
+
+    fn read_bulk_results(&mut self) -> LairResult<Vec<Option<BulkItemError>>> {
+        let count = self.read_u32()?;
+        check_bulk_entries_len(count as usize)?;
+        (0..count)
+            .map(|_| {
+                if !self.read_bool()? {
+                    return Ok(None);
+                }
+                let kind = self.read_u32()?;
+                let message = self.read_str()?;
+                Ok(Some(BulkItemError { kind, message }))
+            })
+            .collect()
+    }
+
+    fn read_audit_record(&mut self) -> LairResult<LairAuditRecord> {
+        let operation = self.read_str()?;
+        let keystore_index = match self.read_bool()? {
+            true => Some(self.read_u64()?.into()),
+            false => None,
+        };
+        let pub_key = match self.read_bool()? {
+            true => Some(Arc::new(self.read_sized_bytes()?)),
+            false => None,
+        };
+        let connection_id = self.read_u64()?;
+        let capabilities = LairCapabilities(self.read_u32()?);
+        let request_id = self.read_u64()?;
+        let error = match self.read_bool()? {
+            true => Some(self.read_str()?),
+            false => None,
+        };
+        Ok(LairAuditRecord {
+            operation,
+            keystore_index,
+            pub_key,
+            connection_id,
+            capabilities,
+            request_id,
+            error,
+        })
+    }
+
+    fn read_elevation(&mut self) -> LairResult<LairElevation> {
+        let capabilities = LairCapabilities(self.read_u32()?);
+        let remaining = std::time::Duration::from_millis(self.read_u64()?);
+        Ok(LairElevation {
+            capabilities,
+            remaining,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -728,17 +5194,231 @@ pub(crate) mod tests {
         }
     }
     test_val!(String, "test-val".to_string());
+    test_val!(Passphrase, Passphrase::from("test-val"));
+    test_val!(Option<String>, Some("test-val".to_string()));
     test_val!(Vec<u8>, vec![0x42; 32]);
-    test_val!(LairServerInfo, Default::default());
+    test_val!(
+        LairServerInfo,
+        LairServerInfo {
+            name: "test-val".to_string(),
+            version: "test-val".to_string(),
+            id_pub_key: vec![0x42; 32].into(),
+            is_locked: true,
+            wire_protocol_version: spec::WIRE_PROTOCOL_VERSION,
+            entropy_status: EntropyStatus::Healthy,
+        }
+    );
+    test_val!(
+        LairServerPaths,
+        LairServerPaths {
+            root_path: "/test-val".into(),
+            store_path: "/test-val/store".into(),
+            connection_url: LairConnectionUrl::new_unix_socket(
+                "/test-val/socket"
+            )
+            .unwrap(),
+        }
+    );
     test_val!(LairEntryType, Default::default());
     test_val!(TlsCertAlg, Default::default());
+    test_val!(DigestAlg, Default::default());
+    test_val!(PrefixKind, PrefixKind::Any);
+    test_val!(KeyBacking, KeyBacking::Hardware);
+    test_val!(
+        Vec<EventKind>,
+        vec![EventKind::UnlockComplete, EventKind::EventsDropped]
+    );
+    test_val!(
+        LairCapabilities,
+        LairCapabilities::NONE.with(LairCapability::Sign)
+    );
     test_val!(KeystoreIndex, 42.into());
+    test_val!(KeystoreIndex64, (u32::MAX as u64 + 42).into());
+    test_val!(EphemeralHandle, 42.into());
+    test_val!(u32, 42);
+    test_val!(u64, 42);
+    test_val!(bool, true);
+    test_val!(PwHashOptions, Default::default());
+    test_val!(TotpOptions, Default::default());
+    test_val!(
+        SignKeyOptions,
+        SignKeyOptions {
+            tag: Some("test-val".to_string()),
+            exportable: false,
+            expires_at: Some(42),
+            requires_approval: true,
+        }
+    );
+    test_val!(
+        SignOptions,
+        SignOptions {
+            include_timestamp: true,
+        }
+    );
+    test_val!(
+        RotateKeyOptions,
+        RotateKeyOptions {
+            key_options: SignKeyOptions::test_val(),
+            trash_old_key: true,
+        }
+    );
+    test_val!(
+        RotationRecord,
+        RotationRecord {
+            old_pub_key: vec![0x42; 32].into(),
+            new_pub_key: vec![0x43; 32].into(),
+            timestamp: 42,
+            old_signature: vec![0x42; 64].into(),
+            new_signature: vec![0x43; 64].into(),
+        }
+    );
+    test_val!(Option<u64>, Some(42));
+    test_val!([u8; 16], [0x42; 16]);
+    test_val!([u8; 32], [0x42; 32]);
+    test_val!([u8; 64], [0x42; 64]);
+    test_val!(u8, 3);
+    test_val!(Option<KeystoreIndex>, Some(42.into()));
+    test_val!(Option<KeystoreIndex64>, Some((u32::MAX as u64 + 42).into()));
+    test_val!(Vec<KeystoreIndex>, vec![42.into(), 0.into(), 42.into()]);
+    test_val!(
+        Vec<(KeystoreIndex, LairEntryType)>,
+        vec![
+            (42.into(), LairEntryType::SignEd25519),
+            (0.into(), LairEntryType::Invalid),
+        ]
+    );
+    test_val!(
+        Vec<LairEntryInfo>,
+        vec![LairEntryInfo {
+            keystore_index: 42.into(),
+            entry_type: LairEntryType::SignEd25519,
+            pub_id: Arc::new(vec![0x42; 32]),
+            tag: Some("signing".to_string()),
+            aliases: vec!["legacy".to_string()],
+            trashed: true,
+            rotated_from: Some(7.into()),
+            activated: false,
+        }]
+    );
+    test_val!(
+        Vec<LairConnectionInfo>,
+        vec![LairConnectionInfo {
+            connection_id: 42,
+            idle: std::time::Duration::from_millis(42),
+            in_flight: 42,
+        }]
+    );
+    test_val!(
+        LairElevation,
+        LairElevation {
+            capabilities: LairCapabilities(0x18),
+            remaining: std::time::Duration::from_millis(42),
+        }
+    );
+    test_val!(Option<LairElevation>, Some(TestVal::test_val()));
+    test_val!(
+        LairAuditRecord,
+        LairAuditRecord {
+            operation: "sign_ed25519_sign_by_index".to_string(),
+            keystore_index: Some(42.into()),
+            pub_key: Some(Arc::new(vec![0x42; 32])),
+            connection_id: 42,
+            capabilities: LairCapabilities::ALL,
+            request_id: 42,
+            error: Some("test-val".to_string()),
+        }
+    );
+    test_val!(
+        StoreStats,
+        StoreStats {
+            file_size: 42,
+            entry_counts: vec![(LairEntryType::SignEd25519, 42)],
+            erased_count: 42,
+            quarantined_count: 42,
+            trashed_count: 42,
+        }
+    );
+    test_val!(
+        Vec<AgeStanza>,
+        vec![AgeStanza {
+            tag: "X25519".to_string(),
+            args: vec!["test-val".to_string()],
+            body: Arc::new(vec![0x42; 32]),
+        }]
+    );
+    test_val!(
+        Vec<QuarantinedEntry>,
+        vec![QuarantinedEntry {
+            keystore_index: 42.into(),
+            reason: "test-val".to_string(),
+            data: Arc::new(vec![0x42; 1024]),
+        }]
+    );
     test_val!(Cert, vec![0x42; 32].into());
     test_val!(CertPrivKey, vec![0x42; 32].into());
     test_val!(CertSni, "test-val".to_string().into());
     test_val!(CertDigest, vec![0x42; 32].into());
     test_val!(SignEd25519PubKey, vec![0x42; 32].into());
     test_val!(SignEd25519Signature, vec![0x42; 64].into());
+    test_val!(X25519PubKey, vec![0x42; 32].into());
+    test_val!(SignSecp256k1PubKey, vec![0x42; 33].into());
+    test_val!(SignEd448PubKey, [0x42; SIGN_ED448_PUB_KEY_LEN].into());
+    test_val!(SignEd448Signature, [0x42; SIGN_ED448_SIGNATURE_LEN].into());
+    test_val!(SignBlsPubKey, [0x42; SIGN_BLS_PUB_KEY_LEN].into());
+    test_val!(SignBlsSignature, [0x42; SIGN_BLS_SIGNATURE_LEN].into());
+    test_val!(SignSr25519PubKey, [0x42; SIGN_SR25519_PUB_KEY_LEN].into());
+    test_val!(
+        SignSr25519Signature,
+        [0x42; SIGN_SR25519_SIGNATURE_LEN].into()
+    );
+    test_val!(
+        Attestation,
+        Attestation {
+            server_id_pub_key: vec![0x42; 32].into(),
+            entry_type: LairEntryType::SignEd25519,
+            entry_pub_id: Arc::new(vec![0x42; 32]),
+            challenge: Arc::new(vec![0x42; MAX_ATTEST_CHALLENGE_LEN]),
+            timestamp: 42,
+            signature: vec![0x42; 64].into(),
+        }
+    );
+    test_val!(
+        ServerIdentityProof,
+        ServerIdentityProof {
+            server_id_pub_key: vec![0x42; 32].into(),
+            wire_protocol_version: 42,
+            server_version: "test-val".to_string(),
+            nonce: Arc::new(vec![0x42; SERVER_IDENTITY_NONCE_LEN]),
+            signature: vec![0x42; 64].into(),
+        }
+    );
+    test_val!(SignSecp256k1Signature, vec![0x42; 64].into());
+    test_val!(
+        Vec<ErrorCause>,
+        vec![ErrorCause {
+            code: spec::error_cause_io_code(std::io::ErrorKind::NotFound),
+            message: "test-val".to_string(),
+        }]
+    );
+    test_val!(Vec<u64>, vec![42, 0x00000100, 0x00000200]);
+    test_val!(
+        Vec<(KeystoreIndex, String)>,
+        vec![(42.into(), "test-val".to_string())]
+    );
+    test_val!(
+        Vec<Option<BulkItemError>>,
+        vec![
+            None,
+            Some(BulkItemError {
+                kind: 7,
+                message: "test-val".to_string(),
+            }),
+        ]
+    );
+    test_val!(
+        Box<LairWire>,
+        Box::new(LairWire::ToLairLairGetServerInfo { msg_id: 0 })
+    );
 
     macro_rules! lair_wire_enum_test {
         ($(
@@ -765,4 +5445,593 @@ pub(crate) mod tests {
     }
 
     wire_type_meta_macro!(lair_wire_enum_test);
+
+    /// The golden fixtures, the encoding of the test value of every
+    /// wire type, see `spec`.
+    const WIRE_FIXTURES_PATH: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/wire.txt");
+
+    /// Set to regenerate the golden fixtures.
+    const BLESS_ENV: &str = "LAIR_BLESS_WIRE_FIXTURES";
+
+    macro_rules! lair_wire_fixtures {
+        ($(
+            $variant:ident $repr:literal $is_evt:literal $is_req:literal {$(
+                $p_name:ident: $p_ty:ty,
+            )*}
+            |$msg_id:ident, $wire_type:ident| $encode:block
+            |$reader:ident| $decode:block,
+        )*) => {
+            fn wire_types() -> Vec<(&'static str, u32)> {
+                vec![$((stringify!($variant), $repr),)*]
+            }
+
+            fn fixture_items() -> Vec<(&'static str, LairWire)> {
+                vec![$(
+                    (stringify!($variant), LairWire::$variant {
+                        msg_id: 0,
+                        $(
+                            $p_name: TestVal::test_val(),
+                        )*
+                    }),
+                )*]
+            }
+        };
+    }
+
+    wire_type_meta_macro!(lair_wire_fixtures);
+
+    #[test]
+    fn it_has_unique_wire_types() {
+        let mut seen = std::collections::HashMap::new();
+        for (name, wire_type) in wire_types() {
+            if let Some(other) = seen.insert(wire_type, name) {
+                panic!("{} and {} share {:#010x}", other, name, wire_type);
+            }
+        }
+    }
+
+    fn to_hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// The version and fixtures recorded in the fixtures file.
+    fn read_fixtures() -> (u32, std::collections::BTreeMap<String, String>) {
+        let file =
+            std::fs::read_to_string(WIRE_FIXTURES_PATH).unwrap_or_default();
+        let mut version = 0;
+        let mut fixtures = std::collections::BTreeMap::new();
+        for line in file.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once(' ').unwrap();
+            if name == "version" {
+                version = value.parse().unwrap();
+            } else {
+                fixtures.insert(name.to_string(), value.to_string());
+            }
+        }
+        (version, fixtures)
+    }
+
+    #[test]
+    fn it_matches_the_golden_fixtures() {
+        let (version, fixtures) = read_fixtures();
+        let items = fixture_items();
+
+        // a changed fixture is a protocol change
+        let changed = items
+            .iter()
+            .filter(|(name, item)| match fixtures.get(*name) {
+                Some(fixture) => *fixture != to_hex(&item.encode().unwrap()),
+                None => false,
+            })
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+
+        if std::env::var_os(BLESS_ENV).is_some() {
+            assert!(
+                changed.is_empty() || version != spec::WIRE_PROTOCOL_VERSION,
+                "{:?} changed, bump spec::WIRE_PROTOCOL_VERSION first",
+                changed,
+            );
+            let mut out = format!(
+                "# lair wire golden fixtures, see `internal::wire::spec`\n\
+                 # regenerate with {}=1 cargo test\n\
+                 version {}\n",
+                BLESS_ENV,
+                spec::WIRE_PROTOCOL_VERSION,
+            );
+            for (name, item) in items.iter() {
+                out.push_str(&format!(
+                    "{} {}\n",
+                    name,
+                    to_hex(&item.encode().unwrap())
+                ));
+            }
+            std::fs::write(WIRE_FIXTURES_PATH, out).unwrap();
+            return;
+        }
+
+        assert_eq!(
+            spec::WIRE_PROTOCOL_VERSION,
+            version,
+            "regenerate the fixtures with {}=1",
+            BLESS_ENV,
+        );
+        assert!(changed.is_empty(), "{:?} changed the encoding", changed);
+        for (name, item) in items.iter() {
+            let fixture = fixtures.get(*name).unwrap_or_else(|| {
+                panic!(
+                    "no fixture for {}, regenerate with {}=1",
+                    name, BLESS_ENV
+                )
+            });
+            assert_eq!(*item, LairWire::decode(&from_hex(fixture)).unwrap());
+        }
+        assert_eq!(items.len(), fixtures.len(), "stale fixtures");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn it_round_trips_arbitrary_messages(
+            item in proptest::prelude::any::<LairWire>(),
+        ) {
+            // generated fields may exceed what a message allows,
+            // those are refused when encoding
+            if let Ok(encoded) = item.encode() {
+                proptest::prop_assert_eq!(
+                    encoded.len(),
+                    LairWire::peek_size(&encoded)?
+                );
+                proptest::prop_assert_eq!(item, LairWire::decode(&encoded)?);
+            }
+        }
+
+        #[test]
+        fn it_round_trips_arbitrary_deadline_requests(
+            item in arbitrary_deadline_request(),
+        ) {
+            if let Ok(encoded) = item.encode() {
+                proptest::prop_assert_eq!(item, LairWire::decode(&encoded)?);
+            }
+        }
+
+        #[test]
+        fn it_refuses_invalid_entry_types(
+            entry_type in crate::arbitrary::invalid_discriminant(
+                LairEntryType::parse,
+            ),
+        ) {
+            let item = LairWire::ToCliLairGetEntryTypeResponse {
+                msg_id: 0,
+                lair_entry_type: LairEntryType::TlsCert,
+            };
+            let mut encoded = item.encode().unwrap();
+            encoded[16..20].copy_from_slice(&entry_type.to_le_bytes());
+            proptest::prop_assert!(LairWire::decode(&encoded).is_err());
+        }
+    }
+
+    #[test]
+    fn it_caps_entry_types_batches() {
+        let max = MAX_GET_ENTRY_TYPES_LEN as usize;
+        let item = LairWire::ToLairLairGetEntryTypes {
+            msg_id: 0,
+            indices: vec![1.into(); max],
+        };
+        let mut encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        let too_many = LairWire::ToLairLairGetEntryTypes {
+            msg_id: 0,
+            indices: vec![1.into(); max + 1],
+        };
+        assert!(too_many.encode().is_err());
+        let too_many = LairWire::ToCliLairGetEntryTypesResponse {
+            msg_id: 0,
+            entry_types: vec![(1.into(), LairEntryType::X25519); max + 1],
+        };
+        assert!(too_many.encode().is_err());
+
+        // a peer claiming too many is refused before anything is read
+        encoded[16..20].copy_from_slice(&(max as u32 + 1).to_le_bytes());
+        assert!(LairWire::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn it_caps_bulk_entries() {
+        let max = MAX_BULK_ENTRIES_LEN as usize;
+        let item = LairWire::ToLairLairSetEntryTags {
+            msg_id: 0,
+            tags: vec![(1.into(), "t".repeat(MAX_ENTRY_TAG_LEN)); max],
+        };
+        assert_eq!(item, LairWire::decode(&item.encode().unwrap()).unwrap());
+        let item = LairWire::ToLairLairEraseEntries {
+            msg_id: 0,
+            indices: vec![1.into(); max],
+        };
+        let mut encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        let too_many = LairWire::ToLairLairEraseEntries {
+            msg_id: 0,
+            indices: vec![1.into(); max + 1],
+        };
+        assert!(too_many.encode().is_err());
+        let too_many = LairWire::ToLairLairSetEntryTags {
+            msg_id: 0,
+            tags: vec![(1.into(), "t".to_string()); max + 1],
+        };
+        assert!(too_many.encode().is_err());
+        let too_many = LairWire::ToCliLairEraseEntriesResponse {
+            msg_id: 0,
+            results: vec![None; max + 1],
+        };
+        assert!(too_many.encode().is_err());
+
+        // a peer claiming too many is refused before anything is read
+        encoded[16..20].copy_from_slice(&(max as u32 + 1).to_le_bytes());
+        assert!(LairWire::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn it_caps_message_len() {
+        // the largest message clients sign whole fits in a message
+        let item = LairWire::ToLairSignEd25519SignByTag {
+            msg_id: 0,
+            tag: "t".repeat(256),
+            message: Arc::new(vec![0xa5; MAX_SIGN_MESSAGE_LEN]),
+        };
+        let encoded = item.encode().unwrap();
+        assert!(encoded.len() <= spec::MAX_MESSAGE_LEN);
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        let too_large = LairWire::ToLairSignEd25519SignByIndex {
+            msg_id: 0,
+            keystore_index: 1.into(),
+            message: Arc::new(vec![0xa5; spec::MAX_MESSAGE_LEN]),
+        };
+        assert!(matches!(
+            too_large.encode(),
+            Err(LairError::MessageTooLarge { max, .. })
+                if max == spec::MAX_MESSAGE_LEN,
+        ));
+    }
+
+    #[test]
+    fn it_sizes_ed448_keys_and_signatures() {
+        let item = LairWire::ToCliSignEd448SignByIndexResponse {
+            msg_id: 0,
+            signature: SignEd448Signature::test_val(),
+        };
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // a peer sending an ed25519 sized signature is refused
+        let mut short = encoded[..spec::HEADER_LEN + 64].to_vec();
+        spec::set_message_len(&mut short).unwrap();
+        assert!(LairWire::decode(&short).is_err());
+
+        assert!(SignEd448PubKey::from_bytes(&[0x42; 32]).is_err());
+        assert!(SignEd448Signature::from_bytes(&[0x42; 64]).is_err());
+    }
+
+    #[test]
+    fn it_sizes_bls_keys_and_signatures() {
+        let item = LairWire::ToCliSignBlsSignByIndexResponse {
+            msg_id: 0,
+            signature: SignBlsSignature::test_val(),
+        };
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // a peer sending an ed25519 sized signature is refused
+        let mut short = encoded[..spec::HEADER_LEN + 64].to_vec();
+        spec::set_message_len(&mut short).unwrap();
+        assert!(LairWire::decode(&short).is_err());
+
+        assert!(SignBlsPubKey::from_bytes(&[0x42; 32]).is_err());
+        assert!(SignBlsSignature::from_bytes(&[0x42; 64]).is_err());
+    }
+
+    #[test]
+    fn it_sizes_sr25519_keys_and_signatures() {
+        let item = LairWire::ToCliSignSr25519SignByIndexResponse {
+            msg_id: 0,
+            signature: SignSr25519Signature::test_val(),
+        };
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // a peer sending a truncated signature is refused
+        let mut short = encoded[..spec::HEADER_LEN + 32].to_vec();
+        spec::set_message_len(&mut short).unwrap();
+        assert!(LairWire::decode(&short).is_err());
+
+        assert!(SignSr25519PubKey::from_bytes(&[0x42; 33]).is_err());
+        assert!(SignSr25519Signature::from_bytes(&[0x42; 65]).is_err());
+    }
+
+    #[test]
+    fn it_sends_empty_sr25519_contexts() {
+        let item = LairWire::ToLairSignSr25519SignByIndex {
+            msg_id: 0,
+            keystore_index: 1.into(),
+            message: Arc::new(b"message".to_vec()),
+            context: Vec::new(),
+        };
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+    }
+
+    #[test]
+    fn it_leaves_default_sign_key_options_out() {
+        let item = LairWire::ToLairSignEd25519NewFromEntropyWithOptions {
+            msg_id: 0,
+            options: Default::default(),
+        };
+        let encoded = item.encode().unwrap();
+        assert_eq!(spec::HEADER_LEN + spec::COUNT_LEN, encoded.len());
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // a server refuses options it does not know, rather than
+        // create a key without them
+        let item = LairWire::ToLairSignEd25519NewFromEntropyWithOptions {
+            msg_id: 0,
+            options: SignKeyOptions {
+                requires_approval: true,
+                ..Default::default()
+            },
+        };
+        let mut encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+        let id = spec::HEADER_LEN + spec::COUNT_LEN;
+        encoded[id..id + 4].copy_from_slice(&spec::encode_u32(0x42));
+        assert!(LairWire::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn it_leaves_default_sign_options_out() {
+        let item =
+            |options| LairWire::ToLairSignEd25519SignByIndexWithOptions {
+                msg_id: 0,
+                keystore_index: 1.into(),
+                message: Arc::new(vec![0x42; 8]),
+                options,
+            };
+        let default = item(SignOptions::default()).encode().unwrap();
+        let timestamped = item(SignOptions {
+            include_timestamp: true,
+        })
+        .encode()
+        .unwrap();
+        assert_eq!(
+            spec::U32_LEN + spec::LEN_PREFIX_LEN + spec::BOOL_LEN,
+            timestamped.len() - default.len(),
+        );
+
+        // a server refuses options it does not know, rather than
+        // sign without them
+        let mut encoded = timestamped;
+        let id = encoded.len() - spec::BOOL_LEN - spec::LEN_PREFIX_LEN - 4;
+        encoded[id..id + 4].copy_from_slice(&spec::encode_u32(0x42));
+        assert!(LairWire::decode(&encoded).is_err());
+    }
+
+    fn entry_page(activated: bool) -> LairWire {
+        tagged_entry_page(activated, None)
+    }
+
+    fn tagged_entry_page(activated: bool, tag: Option<&str>) -> LairWire {
+        let entries: Vec<LairEntryInfo> = (0..20)
+            .map(|i| LairEntryInfo {
+                keystore_index: i.into(),
+                entry_type: LairEntryType::SignEd25519,
+                pub_id: Arc::new(vec![0x42; 32]),
+                tag: tag.map(|tag| format!("{}-{}", tag, i)),
+                aliases: Vec::new(),
+                trashed: false,
+                rotated_from: None,
+                activated,
+            })
+            .collect();
+        LairWire::ToCliLairListEntriesPageResponse {
+            msg_id: 0,
+            entries,
+            next_start: None,
+        }
+    }
+
+    /// Unpadded, the rotation links, the activation states and, untagged,
+    /// the tags are each a trailing count and 20 flags.
+    const PAGE_TRAILER_LEN: usize = spec::U32_LEN + 20 * spec::BOOL_LEN;
+
+    #[test]
+    fn it_reads_entry_pages_predating_rotation_links() {
+        let item = entry_page(true);
+        let encoded = item.encode().unwrap();
+        let links_len = 3 * PAGE_TRAILER_LEN;
+        assert!(encoded.len() > 256);
+
+        // older servers wrote nothing there
+        let mut old = encoded[..encoded.len() - links_len].to_vec();
+        spec::set_message_len(&mut old).unwrap();
+        assert_eq!(item, LairWire::decode(&old).unwrap());
+
+        // or left zero padding
+        let mut old = encoded.clone();
+        let len = old.len();
+        old[len - links_len..].iter_mut().for_each(|b| *b = 0);
+        assert_eq!(item, LairWire::decode(&old).unwrap());
+    }
+
+    #[test]
+    fn it_reads_entry_pages_predating_activation() {
+        let item = entry_page(false);
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // older servers wrote nothing there, or left zero padding,
+        // and have no inactive entries
+        let trailer_len = 2 * PAGE_TRAILER_LEN;
+        let mut old = encoded[..encoded.len() - trailer_len].to_vec();
+        spec::set_message_len(&mut old).unwrap();
+        assert_eq!(entry_page(true), LairWire::decode(&old).unwrap());
+        let mut old = encoded.clone();
+        let len = old.len();
+        old[len - trailer_len..].iter_mut().for_each(|b| *b = 0);
+        assert_eq!(entry_page(true), LairWire::decode(&old).unwrap());
+    }
+
+    #[test]
+    fn it_reads_entry_pages_predating_tags() {
+        let item = tagged_entry_page(true, Some("tag"));
+        let encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // older servers wrote nothing there, or left zero padding
+        let tags_len = encoded.len() - entry_page(true).encode().unwrap().len()
+            + PAGE_TRAILER_LEN;
+        let mut old = encoded[..encoded.len() - tags_len].to_vec();
+        spec::set_message_len(&mut old).unwrap();
+        assert_eq!(entry_page(true), LairWire::decode(&old).unwrap());
+        let mut old = encoded.clone();
+        let len = old.len();
+        old[len - tags_len..].iter_mut().for_each(|b| *b = 0);
+        assert_eq!(entry_page(true), LairWire::decode(&old).unwrap());
+    }
+
+    #[test]
+    fn it_reads_error_responses_predating_causes() {
+        let item = |message: String| LairWire::ErrorResponse {
+            msg_id: 0,
+            kind: 0,
+            request_id: 42,
+            message,
+            causes: Vec::new(),
+            details: Vec::new(),
+        };
+        // short messages end in the zero padding, long messages
+        // before the cause count, which older peers never send
+        for len in [8, MAX_ERROR_MESSAGE_LEN] {
+            let item = item("m".repeat(len));
+            let mut encoded = item.encode().unwrap();
+            encoded.truncate(encoded.len() - spec::U32_LEN - spec::U32_LEN);
+            if encoded.len() < spec::PADDED_MESSAGE_LEN {
+                encoded.resize(spec::PADDED_MESSAGE_LEN, 0);
+            }
+            spec::set_message_len(&mut encoded).unwrap();
+            assert_eq!(item, LairWire::decode(&encoded).unwrap());
+        }
+
+        let too_many = LairWire::ErrorResponse {
+            msg_id: 0,
+            kind: 0,
+            request_id: 42,
+            message: "m".to_string(),
+            causes: vec![
+                Vec::<ErrorCause>::test_val().remove(0);
+                spec::MAX_ERROR_CAUSES as usize + 1
+            ],
+            details: Vec::new(),
+        };
+        assert!(too_many.encode().is_err());
+    }
+
+    #[test]
+    fn it_reads_error_responses_predating_details() {
+        let item = |message: String| LairWire::ErrorResponse {
+            msg_id: 0,
+            kind: 0,
+            request_id: 42,
+            message,
+            causes: Vec::<ErrorCause>::test_val(),
+            details: Vec::new(),
+        };
+        // as causes, the details count is left out or zero padded
+        for len in [8, MAX_ERROR_MESSAGE_LEN] {
+            let item = item("m".repeat(len));
+            let mut encoded = item.encode().unwrap();
+            encoded.truncate(encoded.len() - spec::U32_LEN);
+            if encoded.len() < spec::PADDED_MESSAGE_LEN {
+                encoded.resize(spec::PADDED_MESSAGE_LEN, 0);
+            }
+            spec::set_message_len(&mut encoded).unwrap();
+            assert_eq!(item, LairWire::decode(&encoded).unwrap());
+        }
+
+        let too_many = LairWire::ErrorResponse {
+            msg_id: 0,
+            kind: 0,
+            request_id: 42,
+            message: "m".to_string(),
+            causes: Vec::new(),
+            details: vec![42; spec::MAX_ERROR_DETAILS as usize + 1],
+        };
+        assert!(too_many.encode().is_err());
+    }
+
+    #[test]
+    fn it_reads_server_info_predating_wire_versions() {
+        // the largest server info still ends in the padding, which
+        // servers predating the wire protocol version leave zeroed
+        let info = LairServerInfo {
+            name: "n".repeat(64),
+            version: "v".repeat(64),
+            id_pub_key: vec![0x42; 32].into(),
+            ..Default::default()
+        };
+        let item = LairWire::ToCliLairGetServerInfoResponse { msg_id: 0, info };
+        let encoded = item.encode().unwrap();
+        assert_eq!(spec::PADDED_MESSAGE_LEN, encoded.len());
+        match LairWire::decode(&encoded).unwrap() {
+            LairWire::ToCliLairGetServerInfoResponse { info, .. } => {
+                assert_eq!(0, info.wire_protocol_version);
+                assert_eq!(EntropyStatus::Unchecked, info.entropy_status);
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+    }
+
+    #[test]
+    fn it_only_carries_plain_requests_in_deadline_requests() {
+        let with_deadline = |request| LairWire::ToLairRequestDeadline {
+            msg_id: 7,
+            remaining_ms: 42,
+            request: Box::new(request),
+        };
+        let item =
+            with_deadline(LairWire::ToLairLairGetServerInfo { msg_id: 7 });
+        let mut encoded = item.encode().unwrap();
+        assert_eq!(item, LairWire::decode(&encoded).unwrap());
+
+        // a peer sending a mismatched id is refused
+        encoded[40..48].copy_from_slice(&8_u64.to_le_bytes());
+        assert!(LairWire::decode(&encoded).is_err());
+
+        // another id, a response, an event, or a nested deadline
+        for request in [
+            LairWire::ToLairLairGetServerInfo { msg_id: 8 },
+            LairWire::ToCliLairGetLastEntryIndexResponse {
+                msg_id: 7,
+                last_keystore_index: None,
+            },
+            LairWire::ToCliRequestUnlockPassphrase {
+                msg_id: 7,
+                store_name: DEFAULT_STORE_NAME.to_string(),
+            },
+            item,
+        ] {
+            assert!(with_deadline(request).encode().is_err());
+        }
+    }
 }
@@ -0,0 +1,305 @@
+//! Per frame compression of the lair wire encoding.
+//!
+//! Peers agree on an algorithm with `ToLairNegotiateCompression`, then
+//! each may compress the frames it sends that are longer than
+//! `spec::COMPRESSION_THRESHOLD`, when compressing makes them shorter.
+//! A compressed frame keeps the header of the original frame, but with
+//! `spec::WIRE_TYPE_COMPRESSED` set in the wire type, and the length of
+//! the compressed frame. Its payload is the algorithm (`u32`), the
+//! length of the original payload (`u32`), then the compressed original
+//! payload, unpadded. Frames carrying secrets are never compressed.
+
+use super::spec;
+use crate::*;
+
+/// The algorithms this build compresses with, as a bitmask.
+pub const SUPPORTED_ALGORITHMS: u32 = if cfg!(feature = "compression") {
+    spec::COMPRESSION_LZ4
+} else {
+    0
+};
+
+/// The algorithm to use, of those `offered` and `allowed`,
+/// `0` if there is none.
+pub fn choose_algorithm(offered: u32, allowed: u32) -> u32 {
+    let common = offered & allowed & SUPPORTED_ALGORITHMS;
+    // the lowest bit set
+    common & common.wrapping_neg()
+}
+
+/// True if the wire type in the header of `frame` is flagged
+/// `spec::WIRE_TYPE_COMPRESSED`.
+pub fn is_compressed(frame: &[u8]) -> bool {
+    match frame.get(spec::MESSAGE_LEN_LEN..) {
+        Some(data) => match spec::decode_u32(data) {
+            Ok(wire_type) => wire_type & spec::WIRE_TYPE_COMPRESSED != 0,
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+/// The wire type and message id in the header of `frame`.
+fn decode_header(frame: &[u8]) -> LairResult<(u32, u64)> {
+    if frame.len() < spec::HEADER_LEN {
+        return Err("not enough data for header".into());
+    }
+    let wire_type = spec::decode_u32(&frame[spec::MESSAGE_LEN_LEN..])?;
+    let msg_id = spec::decode_u64(
+        &frame[spec::MESSAGE_LEN_LEN + spec::WIRE_TYPE_LEN..],
+    )?;
+    Ok((wire_type, msg_id))
+}
+
+/// Compress the encoded `frame` with `algorithm`, if it is longer
+/// than `spec::COMPRESSION_THRESHOLD` and compressing makes it shorter.
+pub fn compress_frame(
+    frame: &[u8],
+    algorithm: u32,
+) -> LairResult<Option<Vec<u8>>> {
+    if frame.len() <= spec::COMPRESSION_THRESHOLD {
+        return Ok(None);
+    }
+    let (wire_type, msg_id) = decode_header(frame)?;
+    let payload = &frame[spec::HEADER_LEN..];
+    let compressed = compress(algorithm, payload)?;
+    let len = spec::HEADER_LEN + spec::COMPRESSED_PREFIX_LEN + compressed.len();
+    if len >= frame.len() {
+        return Ok(None);
+    }
+    let mut out = Vec::with_capacity(len);
+    out.extend_from_slice(&spec::encode_header(
+        len,
+        wire_type | spec::WIRE_TYPE_COMPRESSED,
+        msg_id,
+    )?);
+    out.extend_from_slice(&spec::encode_u32(algorithm));
+    out.extend_from_slice(&spec::encode_u32(payload.len() as u32));
+    out.extend_from_slice(&compressed);
+    Ok(Some(out))
+}
+
+/// Restore the original frame from the compressed `frame`, if it is
+/// compressed with one of the `accepted` algorithms. Frames whose
+/// original would be longer than `spec::MAX_MESSAGE_LEN` are refused
+/// before anything is decompressed, and nothing decompresses past the
+/// original length they claim.
+pub fn decompress_frame(frame: &[u8], accepted: u32) -> LairResult<Vec<u8>> {
+    if frame.len() < spec::HEADER_LEN + spec::COMPRESSED_PREFIX_LEN {
+        return Err("not enough data for compressed frame".into());
+    }
+    let (wire_type, msg_id) = decode_header(frame)?;
+    let algorithm = spec::decode_u32(&frame[spec::HEADER_LEN..])?;
+    let payload_len =
+        spec::decode_u32(&frame[spec::HEADER_LEN + spec::U32_LEN..])? as usize;
+    if algorithm.count_ones() != 1
+        || choose_algorithm(algorithm, accepted) != algorithm
+    {
+        return Err(format!(
+            "frame compressed with unnegotiated algorithm {:#x}",
+            algorithm
+        )
+        .into());
+    }
+    let len = spec::HEADER_LEN + payload_len;
+    if len > spec::MAX_MESSAGE_LEN {
+        return Err(LairError::MessageTooLarge {
+            len,
+            max: spec::MAX_MESSAGE_LEN,
+        });
+    }
+    let mut out = vec![0; len];
+    out[..spec::HEADER_LEN].copy_from_slice(&spec::encode_header(
+        len,
+        wire_type & !spec::WIRE_TYPE_COMPRESSED,
+        msg_id,
+    )?);
+    let written = decompress(
+        algorithm,
+        &frame[spec::HEADER_LEN + spec::COMPRESSED_PREFIX_LEN..],
+        &mut out[spec::HEADER_LEN..],
+    )?;
+    if written != payload_len {
+        return Err(format!(
+            "corrupt compressed frame: {} bytes decompressed, expected {}",
+            written, payload_len
+        )
+        .into());
+    }
+    Ok(out)
+}
+
+#[allow(unused_variables)]
+fn compress(algorithm: u32, data: &[u8]) -> LairResult<Vec<u8>> {
+    match algorithm {
+        #[cfg(feature = "compression")]
+        spec::COMPRESSION_LZ4 => Ok(lz4_flex::block::compress(data)),
+        _ => Err(
+            format!("unsupported compression algorithm {:#x}", algorithm)
+                .into(),
+        ),
+    }
+}
+
+#[allow(unused_variables)]
+fn decompress(
+    algorithm: u32,
+    data: &[u8],
+    out: &mut [u8],
+) -> LairResult<usize> {
+    match algorithm {
+        #[cfg(feature = "compression")]
+        spec::COMPRESSION_LZ4 => lz4_flex::block::decompress_into(data, out)
+            .map_err(|e| format!("corrupt compressed frame: {}", e).into()),
+        _ => Err(
+            format!("unsupported compression algorithm {:#x}", algorithm)
+                .into(),
+        ),
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+    use crate::internal::wire::LairWire;
+
+    /// A compressible message encoding to exactly `len` bytes.
+    fn frame_of_len(len: usize) -> Vec<u8> {
+        let msg = LairWire::ToLairSignEd25519SignByIndex {
+            msg_id: 42,
+            keystore_index: 1.into(),
+            message: Arc::new(vec![0xa5; len - spec::HEADER_LEN - 4 - 8]),
+        };
+        let frame = msg.encode().unwrap();
+        assert_eq!(len, frame.len());
+        frame
+    }
+
+    #[test]
+    fn it_negotiates_the_lowest_common_algorithm() {
+        assert_eq!(0, choose_algorithm(0, SUPPORTED_ALGORITHMS));
+        assert_eq!(0, choose_algorithm(SUPPORTED_ALGORITHMS, 0));
+        assert_eq!(0, choose_algorithm(0x80000000, 0x80000000));
+        assert_eq!(
+            spec::COMPRESSION_LZ4,
+            choose_algorithm(0xffffffff, 0xffffffff)
+        );
+    }
+
+    #[test]
+    fn it_only_compresses_frames_past_the_threshold() {
+        let frame = frame_of_len(spec::COMPRESSION_THRESHOLD);
+        assert_eq!(
+            None,
+            compress_frame(&frame, spec::COMPRESSION_LZ4).unwrap()
+        );
+
+        let frame = frame_of_len(spec::COMPRESSION_THRESHOLD + 1);
+        let compressed = compress_frame(&frame, spec::COMPRESSION_LZ4)
+            .unwrap()
+            .unwrap();
+        assert!(compressed.len() < frame.len());
+        assert!(is_compressed(&compressed));
+        assert!(!is_compressed(&frame));
+        assert_eq!(compressed.len(), LairWire::peek_size(&compressed).unwrap());
+        assert_eq!(
+            frame,
+            decompress_frame(&compressed, spec::COMPRESSION_LZ4).unwrap()
+        );
+        // to peers that did not negotiate it, an unknown wire type
+        assert!(LairWire::decode(&compressed).is_err());
+
+        // incompressible frames are sent as they are
+        let mut frame = frame_of_len(spec::COMPRESSION_THRESHOLD * 2);
+        let mut noise = 0x2545f491_u32;
+        for b in frame[spec::HEADER_LEN + 12..].iter_mut() {
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            *b = noise as u8;
+        }
+        assert_eq!(
+            None,
+            compress_frame(&frame, spec::COMPRESSION_LZ4).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_refuses_corrupt_compressed_frames() {
+        let frame = frame_of_len(spec::COMPRESSION_THRESHOLD * 4);
+        let compressed = compress_frame(&frame, spec::COMPRESSION_LZ4)
+            .unwrap()
+            .unwrap();
+
+        // not negotiated
+        assert!(decompress_frame(&compressed, 0).is_err());
+
+        // truncated
+        assert!(decompress_frame(&compressed[..spec::HEADER_LEN], 1).is_err());
+        assert!(decompress_frame(
+            &compressed[..compressed.len() - 4],
+            spec::COMPRESSION_LZ4
+        )
+        .is_err());
+
+        // mangled compressed bytes
+        let mut bad = compressed.clone();
+        for b in
+            bad[spec::HEADER_LEN + spec::COMPRESSED_PREFIX_LEN..].iter_mut()
+        {
+            *b = !*b;
+        }
+        assert!(decompress_frame(&bad, spec::COMPRESSION_LZ4).is_err());
+
+        // claiming a shorter, or longer, original
+        for payload_len in &[
+            frame.len() - spec::HEADER_LEN - 1,
+            frame.len() - spec::HEADER_LEN + 1,
+        ] {
+            let mut bad = compressed.clone();
+            bad[spec::HEADER_LEN + spec::U32_LEN..][..spec::U32_LEN]
+                .copy_from_slice(&spec::encode_u32(*payload_len as u32));
+            assert!(decompress_frame(&bad, spec::COMPRESSION_LZ4).is_err());
+        }
+
+        // an unknown algorithm
+        let mut bad = compressed;
+        bad[spec::HEADER_LEN..][..spec::U32_LEN]
+            .copy_from_slice(&spec::encode_u32(0x00000002));
+        assert!(decompress_frame(&bad, 0xffffffff).is_err());
+    }
+
+    #[test]
+    fn it_refuses_frames_decompressing_past_the_max_len() {
+        // 16 MiB of zeros compresses to a few KiB
+        let payload = vec![0; spec::MAX_MESSAGE_LEN];
+        let compressed = lz4_flex::block::compress(&payload);
+        assert!(compressed.len() < spec::MAX_MESSAGE_LEN / 100);
+
+        let mut frame = Vec::new();
+        let len =
+            spec::HEADER_LEN + spec::COMPRESSED_PREFIX_LEN + compressed.len();
+        frame.extend_from_slice(
+            &spec::encode_header(
+                len,
+                0x00000230 | spec::WIRE_TYPE_COMPRESSED,
+                42,
+            )
+            .unwrap(),
+        );
+        frame.extend_from_slice(&spec::encode_u32(spec::COMPRESSION_LZ4));
+        frame.extend_from_slice(&spec::encode_u32(payload.len() as u32));
+        frame.extend_from_slice(&compressed);
+        assert!(matches!(
+            decompress_frame(&frame, spec::COMPRESSION_LZ4),
+            Err(LairError::MessageTooLarge { len, max })
+                if len == spec::HEADER_LEN + spec::MAX_MESSAGE_LEN
+                    && max == spec::MAX_MESSAGE_LEN,
+        ));
+
+        // nor past the length the frame claims
+        frame[spec::HEADER_LEN + spec::U32_LEN..][..spec::U32_LEN]
+            .copy_from_slice(&spec::encode_u32(1024));
+        assert!(decompress_frame(&frame, spec::COMPRESSION_LZ4).is_err());
+    }
+}
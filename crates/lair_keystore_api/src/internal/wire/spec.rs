@@ -0,0 +1,389 @@
+//! The lair wire encoding, the single source of truth for the layout
+//! of every message. `docs/protocol.md` describes the same rules,
+//! and the payload of each wire type, for clients in other languages.
+//!
+//! - Integers are unsigned little-endian, `u32` or `u64`.
+//! - A message starts with a `HEADER_LEN` byte header: the message
+//!   length (`u32`, header included), the wire type (`u32`),
+//!   and the message id (`u64`).
+//! - The payload follows, its fields in the order documented for
+//!   the wire type, with no alignment between them.
+//! - Strings and variable length bytes are a `u64` length prefix
+//!   followed by the bytes. Strings are utf8, decoders replace
+//!   invalid sequences with U+FFFD.
+//! - Fixed length bytes (keys, signatures, digests) have no prefix.
+//! - Booleans are a `u32`, `1` for true, `0` for false. Decoders
+//!   take any non-zero value as true.
+//! - An optional field is a boolean presence flag, followed by the
+//!   value only if present.
+//! - A list is a `u32` item count, followed by the items.
+//! - Messages with small payloads are zero padded to
+//!   `PADDED_MESSAGE_LEN` (or a larger wire type specific length).
+//!   Decoders ignore anything after the last field.
+//! - No message is longer than `MAX_MESSAGE_LEN`. Peers close the
+//!   connection on reading a longer message length.
+//! - Peers that negotiated compression may send long messages
+//!   compressed, flagged with `WIRE_TYPE_COMPRESSED`, see
+//!   `wire::compress`.
+//!
+//! The golden fixtures in `fixtures/wire.txt` hold the encoding of
+//! every wire type. Changing any of them means a new
+//! `WIRE_PROTOCOL_VERSION`.
+
+use crate::*;
+
+/// The version of the wire encoding the golden fixtures record.
+/// Bump it with any change to the bytes of an existing wire type.
+/// Servers report theirs in Get Server Info, servers predating
+/// that report `0`.
+pub const WIRE_PROTOCOL_VERSION: u32 = 22;
+
+/// The first wire protocol version whose servers answer failed
+/// requests with an `ErrorResponse`. Servers predating wire protocol
+/// versions, which report `0`, leave failed requests unanswered,
+/// clients bound those with a deadline.
+pub const ERROR_RESPONSE_VERSION: u32 = 1;
+
+/// The first wire protocol version whose servers answer the signing
+/// requests returning the signing pub key, e.g.
+/// `ToLairSignEd25519SignByIndexV2`. Older servers close the
+/// connection on reading them, clients check the version first.
+pub const SIGN_WITH_PUB_KEY_VERSION: u32 = 3;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairTlsCertGetInfo`.
+pub const TLS_CERT_INFO_VERSION: u32 = 4;
+
+/// The first wire protocol version whose servers answer the requests
+/// carrying `SignKeyOptions`, e.g.
+/// `ToLairSignEd25519NewFromEntropyWithOptions`.
+pub const SIGN_KEY_OPTIONS_VERSION: u32 = 5;
+
+/// The first wire protocol version whose `ErrorResponse` carries the
+/// cause chain of the error. Older peers leave the zero padding, or
+/// nothing, where it would be, which decodes as no causes, and ignore
+/// the chain newer peers send.
+pub const ERROR_CAUSES_VERSION: u32 = 6;
+
+/// The first wire protocol version whose servers answer the requests
+/// carrying a `KeystoreIndex64`, e.g. `ToLairLairGetEntryType64`.
+pub const KEYSTORE_INDEX64_VERSION: u32 = 7;
+
+/// The first wire protocol version whose servers report their
+/// `EntropyStatus` in Get Server Info. Older servers leave the zero
+/// padding where it would be, which decodes as `Unchecked`.
+pub const ENTROPY_STATUS_VERSION: u32 = 8;
+
+/// The first wire protocol version whose servers answer the ephemeral
+/// key requests, e.g. `ToLairEphemeralX25519New`.
+pub const EPHEMERAL_KEYS_VERSION: u32 = 9;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairSignEd25519Rotate`, and send the rotation links of the entries
+/// in List Entries Page. Older servers leave the zero padding, or
+/// nothing, where the links would be, which decodes as no links.
+pub const ROTATION_VERSION: u32 = 10;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairSignEd25519ExportSeed`.
+pub const SEED_EXPORT_VERSION: u32 = 11;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairNegotiateCompression`.
+pub const COMPRESSION_VERSION: u32 = 12;
+
+/// The first wire protocol version whose `ErrorResponse` carries the
+/// details of typed errors, e.g. `LairError::WrongEntryType`. Older
+/// peers leave the zero padding, or nothing, where they would be, which
+/// decodes as no details, and ignore the details newer peers send.
+pub const ERROR_DETAILS_VERSION: u32 = 13;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairLairActivateEntry`, and send the activation state of the
+/// entries in List Entries Page. Older servers send nothing where the
+/// states would be, which decodes as every entry activated.
+pub const ACTIVATION_VERSION: u32 = 14;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairLairGetStoreStats`.
+pub const STORE_STATS_VERSION: u32 = 15;
+
+/// The first wire protocol version whose servers answer
+/// `ToLairLairProveServerIdentity`.
+pub const SERVER_IDENTITY_VERSION: u32 = 16;
+
+/// The first wire protocol version whose servers answer the requests
+/// carrying `SignOptions`, e.g. `ToLairSignEd25519SignByIndexWithOptions`.
+pub const SIGN_OPTIONS_VERSION: u32 = 17;
+
+/// The first wire protocol version whose servers answer the bulk
+/// entry requests, `ToLairLairEraseEntries` and `ToLairLairSetEntryTags`.
+pub const BULK_ENTRIES_VERSION: u32 = 18;
+
+/// The first wire protocol version whose servers send `ToCliAuditEvent`,
+/// and take `EventKind::AuditEvent` in a `ToLairLairSetEventFilter`.
+/// Older servers refuse a filter naming it.
+pub const AUDIT_EVENT_VERSION: u32 = 19;
+
+/// The first wire protocol version whose servers answer the elevation
+/// requests, e.g. `ToLairLairRequestElevation`.
+pub const ELEVATION_VERSION: u32 = 20;
+
+/// The first wire protocol version whose servers send the tags of the
+/// entries in List Entries Page. Older servers leave the zero padding,
+/// or nothing, where the tags would be, which decodes as no tags.
+pub const ENTRY_TAGS_VERSION: u32 = 21;
+
+/// The first wire protocol version whose servers send `ToCliEntryRemoved`,
+/// and take `EventKind::EntryRemoved` in a `ToLairLairSetEventFilter`.
+/// Older servers refuse a filter naming it.
+pub const ENTRY_REMOVED_VERSION: u32 = 22;
+
+/// Set in the wire type of a compressed frame, see `wire::compress`.
+pub const WIRE_TYPE_COMPRESSED: u32 = 0x00800000;
+
+/// Only frames longer than this, header included, are compressed.
+pub const COMPRESSION_THRESHOLD: usize = 4096;
+
+/// The lz4 block format, a bit in compression algorithm bitmasks.
+pub const COMPRESSION_LZ4: u32 = 0x00000001;
+
+/// Byte length of the algorithm and original payload length
+/// starting the payload of a compressed frame.
+pub const COMPRESSED_PREFIX_LEN: usize = U32_LEN + U32_LEN;
+
+/// The maximum count of causes in an `ErrorResponse`, deeper
+/// causes are left out.
+pub const MAX_ERROR_CAUSES: u32 = 8;
+
+/// The maximum count of details in an `ErrorResponse`.
+pub const MAX_ERROR_DETAILS: u32 = 4;
+
+/// The code of an `ErrorResponse` cause that is not an io error.
+pub const ERROR_CAUSE_OTHER: u32 = 0x00000000;
+
+/// The code of an `ErrorResponse` cause that is an io error is this,
+/// plus the position of its kind in `ERROR_CAUSE_IO_KINDS`.
+pub const ERROR_CAUSE_IO: u32 = 0x00000100;
+
+/// The io error kinds `ErrorResponse` causes carry, kinds missing
+/// here are sent as `Other`. Only ever append to this.
+pub const ERROR_CAUSE_IO_KINDS: &[std::io::ErrorKind] = &[
+    std::io::ErrorKind::Other,
+    std::io::ErrorKind::NotFound,
+    std::io::ErrorKind::PermissionDenied,
+    std::io::ErrorKind::ConnectionRefused,
+    std::io::ErrorKind::ConnectionReset,
+    std::io::ErrorKind::ConnectionAborted,
+    std::io::ErrorKind::NotConnected,
+    std::io::ErrorKind::AddrInUse,
+    std::io::ErrorKind::AddrNotAvailable,
+    std::io::ErrorKind::BrokenPipe,
+    std::io::ErrorKind::AlreadyExists,
+    std::io::ErrorKind::WouldBlock,
+    std::io::ErrorKind::InvalidInput,
+    std::io::ErrorKind::InvalidData,
+    std::io::ErrorKind::TimedOut,
+    std::io::ErrorKind::WriteZero,
+    std::io::ErrorKind::Interrupted,
+    std::io::ErrorKind::UnexpectedEof,
+    std::io::ErrorKind::Unsupported,
+    std::io::ErrorKind::OutOfMemory,
+];
+
+/// The `ErrorResponse` cause code of an io error of `kind`.
+pub fn error_cause_io_code(kind: std::io::ErrorKind) -> u32 {
+    let pos = ERROR_CAUSE_IO_KINDS
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(0);
+    ERROR_CAUSE_IO + pos as u32
+}
+
+/// The io error kind of an `ErrorResponse` cause `code`,
+/// if it is an io error.
+pub fn error_cause_io_kind(code: u32) -> Option<std::io::ErrorKind> {
+    let pos = code.checked_sub(ERROR_CAUSE_IO)?;
+    ERROR_CAUSE_IO_KINDS.get(pos as usize).copied()
+}
+
+/// `SignKeyOptions` are encoded as a count of fields, then each field
+/// present as its `u32` id and its length prefixed value. Options at
+/// their default are left out. Servers refuse ids they do not know,
+/// rather than create a key without the option asked for.
+/// The `tag` option, a utf8 string.
+pub const SIGN_KEY_OPTION_TAG: u32 = 0x00000001;
+
+/// The `exportable` option, a bool.
+pub const SIGN_KEY_OPTION_EXPORTABLE: u32 = 0x00000002;
+
+/// The `expires_at` option, a `u64`.
+pub const SIGN_KEY_OPTION_EXPIRES_AT: u32 = 0x00000003;
+
+/// The `requires_approval` option, a bool.
+pub const SIGN_KEY_OPTION_REQUIRES_APPROVAL: u32 = 0x00000004;
+
+/// The maximum count of `SignKeyOptions` fields, repeats included.
+pub const MAX_SIGN_KEY_OPTION_FIELDS: u32 = 16;
+
+/// `SignOptions` are encoded as `SignKeyOptions` are, with ids of
+/// their own. The `include_timestamp` option, a bool.
+pub const SIGN_OPTION_INCLUDE_TIMESTAMP: u32 = 0x00000001;
+
+/// The maximum count of `SignOptions` fields, repeats included.
+pub const MAX_SIGN_OPTION_FIELDS: u32 = 16;
+
+/// Byte length of a `u32` field.
+pub const U32_LEN: usize = 4;
+
+/// Byte length of a `u64` field.
+pub const U64_LEN: usize = 8;
+
+/// Byte length of the message length at the start of each message.
+pub const MESSAGE_LEN_LEN: usize = U32_LEN;
+
+/// Byte length of the wire type, following the message length.
+pub const WIRE_TYPE_LEN: usize = U32_LEN;
+
+/// Byte length of the message id, following the wire type.
+pub const MSG_ID_LEN: usize = U64_LEN;
+
+/// Byte length of the header starting each message.
+pub const HEADER_LEN: usize = MESSAGE_LEN_LEN + WIRE_TYPE_LEN + MSG_ID_LEN;
+
+/// Byte length of the length prefix of strings and variable length bytes.
+pub const LEN_PREFIX_LEN: usize = U64_LEN;
+
+/// Byte length of the item count of lists.
+pub const COUNT_LEN: usize = U32_LEN;
+
+/// Byte length of boolean fields, and optional field presence flags.
+pub const BOOL_LEN: usize = U32_LEN;
+
+/// The length messages with small payloads are zero padded to.
+pub const PADDED_MESSAGE_LEN: usize = 256;
+
+/// The maximum length of a message, header included.
+pub const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Encode a `u32` field.
+pub fn encode_u32(val: u32) -> [u8; U32_LEN] {
+    val.to_le_bytes()
+}
+
+/// Decode a `u32` field from the start of `data`.
+pub fn decode_u32(data: &[u8]) -> LairResult<u32> {
+    let mut buf = [0; U32_LEN];
+    buf.copy_from_slice(
+        data.get(..U32_LEN)
+            .ok_or_else(|| LairError::from("not enough data for u32"))?,
+    );
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Encode a `u64` field.
+pub fn encode_u64(val: u64) -> [u8; U64_LEN] {
+    val.to_le_bytes()
+}
+
+/// Decode a `u64` field from the start of `data`.
+pub fn decode_u64(data: &[u8]) -> LairResult<u64> {
+    let mut buf = [0; U64_LEN];
+    buf.copy_from_slice(
+        data.get(..U64_LEN)
+            .ok_or_else(|| LairError::from("not enough data for u64"))?,
+    );
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Encode a boolean field, or an optional field presence flag.
+pub fn encode_bool(val: bool) -> [u8; BOOL_LEN] {
+    encode_u32(val as u32)
+}
+
+/// Decode a boolean field, or an optional field presence flag,
+/// from the start of `data`.
+pub fn decode_bool(data: &[u8]) -> LairResult<bool> {
+    Ok(decode_u32(data)? != 0)
+}
+
+/// Encode the length prefix of a string, or of variable length bytes.
+pub fn encode_len_prefix(len: usize) -> [u8; LEN_PREFIX_LEN] {
+    encode_u64(len as u64)
+}
+
+/// Decode the bytes of a string, following its length prefix.
+pub fn decode_str(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).to_string()
+}
+
+/// Encode the header of a message `message_len` bytes long,
+/// header included.
+pub fn encode_header(
+    message_len: usize,
+    wire_type: u32,
+    msg_id: u64,
+) -> LairResult<[u8; HEADER_LEN]> {
+    if message_len < HEADER_LEN || message_len > u32::MAX as usize {
+        return Err(format!("invalid message length: {}", message_len).into());
+    }
+    let mut header = [0; HEADER_LEN];
+    header[..MESSAGE_LEN_LEN].copy_from_slice(&encode_u32(message_len as u32));
+    header[MESSAGE_LEN_LEN..MESSAGE_LEN_LEN + WIRE_TYPE_LEN]
+        .copy_from_slice(&encode_u32(wire_type));
+    header[MESSAGE_LEN_LEN + WIRE_TYPE_LEN..]
+        .copy_from_slice(&encode_u64(msg_id));
+    Ok(header)
+}
+
+/// Decode the message length from the start of a message,
+/// the number of bytes to read before decoding it.
+pub fn decode_message_len(data: &[u8]) -> LairResult<usize> {
+    Ok(decode_u32(data)? as usize)
+}
+
+/// Set the message length in the header of `message`
+/// to its actual length, for messages whose payload
+/// outgrew their padded length.
+pub fn set_message_len(message: &mut [u8]) -> LairResult<()> {
+    if message.len() < HEADER_LEN || message.len() > u32::MAX as usize {
+        return Err(format!("invalid message length: {}", message.len()).into());
+    }
+    let len = encode_u32(message.len() as u32);
+    message[..MESSAGE_LEN_LEN].copy_from_slice(&len);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_little_endian() {
+        assert_eq!([0x04, 0x03, 0x02, 0x01], encode_u32(0x01020304));
+        assert_eq!(
+            [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+            encode_u64(0x0102030405060708),
+        );
+        assert_eq!(0x01020304, decode_u32(&[4, 3, 2, 1, 0xff]).unwrap());
+        assert!(decode_u32(&[4, 3, 2]).is_err());
+        assert_eq!([1, 0, 0, 0], encode_bool(true));
+        assert!(decode_bool(&[2, 0, 0, 0]).unwrap());
+    }
+
+    #[test]
+    fn it_encodes_headers() {
+        let header = encode_header(256, 0x130, 42).unwrap();
+        assert_eq!(
+            [0, 1, 0, 0, 0x30, 1, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0],
+            header,
+        );
+        assert_eq!(256, decode_message_len(&header).unwrap());
+        assert!(encode_header(HEADER_LEN - 1, 0x130, 42).is_err());
+
+        let mut message = vec![0; 300];
+        message[..HEADER_LEN].copy_from_slice(&header);
+        set_message_len(&mut message).unwrap();
+        assert_eq!(300, decode_message_len(&message).unwrap());
+    }
+}
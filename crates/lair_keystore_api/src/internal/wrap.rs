@@ -0,0 +1,205 @@
+//! Entry Key Wrapping Utilities
+//!
+//! A wrapped entry is a versioned container:
+//! `version (4 bytes, unsigned-LE) || sealed box`, where the sealed box
+//! is a libsodium compatible `crypto_box_seal` to the recipient x25519
+//! pub key of `version (4 bytes, unsigned-LE) || encoded entry`.
+//! The encoded entry is the same fixed-size record as the store file,
+//! so the entry type is preserved, and the sealed version must match
+//! the outer one, so the container version cannot be swapped.
+
+use crate::*;
+
+use actor::X25519PubKey;
+use internal::codec;
+use internal::entropy::EntropySourceHandle;
+use internal::x25519::{self, X25519PrivKey};
+
+/// The current (and only) wrapped entry container version.
+pub const WRAPPED_ENTRY_VERSION: u32 = 1;
+
+/// Byte length of a wrapped entry container.
+pub const WRAPPED_ENTRY_LEN: usize =
+    4 + x25519::CRYPTO_BOX_SEAL_OVERHEAD + 4 + entry::ENTRY_SIZE;
+
+/// Wrap the secret material of `entry` to `recipient_pub_key`.
+/// Hardware backed entries, and ed25519 keypairs created not exportable,
+/// fail with `LairError::ExportForbidden`.
+/// A tls cert bound to a signing entry is wrapped with a copy of its key,
+/// the reference would be meaningless in another store.
+pub async fn wrap_entry(
+    entropy: EntropySourceHandle,
+    entry: &entry::LairEntry,
+    recipient_pub_key: X25519PubKey,
+) -> LairResult<Arc<Vec<u8>>> {
+    let encoded = match entry {
+        entry::LairEntry::SignEd25519Hardware(e) => {
+            return Err(LairError::ExportForbidden(format!(
+                "ed25519 keypair {:?} is held in a hardware token",
+                e.pub_key,
+            )))
+        }
+        entry::LairEntry::SignEd25519(e) if !e.policy.exportable => {
+            return Err(LairError::ExportForbidden(format!(
+                "ed25519 keypair {:?} is not exportable",
+                e.pub_key,
+            )))
+        }
+        entry::LairEntry::TlsCert(cert) if cert.sign_key_index.is_some() => {
+            let mut cert = cert.clone();
+            cert.sign_key_index = None;
+            cert.encode()?
+        }
+        entry => entry.encode()?,
+    };
+    let mut writer = codec::CodecWriter::new_zeroed(4 + encoded.len())?;
+    writer.write_u32(WRAPPED_ENTRY_VERSION)?;
+    writer.write_bytes(&encoded)?;
+    let sealed = x25519::crypto_box_seal(
+        entropy,
+        recipient_pub_key,
+        writer.into_vec().into(),
+    )
+    .await?;
+
+    let mut writer = codec::CodecWriter::new_zeroed(4 + sealed.len())?;
+    writer.write_u32(WRAPPED_ENTRY_VERSION)?;
+    writer.write_bytes(&sealed)?;
+    Ok(Arc::new(writer.into_vec()))
+}
+
+/// Unwrap a `wrap_entry` container with the recipient's private key.
+/// Tampered containers, or containers wrapped to a different recipient,
+/// fail with `LairError::AuthenticationFailed`.
+pub async fn unwrap_entry(
+    recipient_priv_key: X25519PrivKey,
+    wrapped: Arc<Vec<u8>>,
+) -> LairResult<entry::LairEntry> {
+    check_version(&wrapped)?;
+    if wrapped.len() != WRAPPED_ENTRY_LEN {
+        return Err(LairError::AuthenticationFailed);
+    }
+    let sealed = Arc::new(wrapped[4..].to_vec());
+    let opened =
+        x25519::crypto_box_seal_open(recipient_priv_key, sealed).await?;
+    check_version(&opened)?;
+    match entry::LairEntry::decode(&opened[4..])? {
+        // never produced by `wrap_entry`
+        entry::LairEntry::SignEd25519Hardware(_) => {
+            Err("wrapped entry references a hardware key".into())
+        }
+        entry::LairEntry::TlsCert(cert) if cert.sign_key_index.is_some() => {
+            Err("wrapped tls cert references a signing entry".into())
+        }
+        entry => Ok(entry),
+    }
+}
+
+fn check_version(data: &[u8]) -> LairResult<()> {
+    let version = codec::CodecReader::new(data)
+        .read_u32()
+        .map_err(|_| LairError::from("wrapped entry too short"))?;
+    if version != WRAPPED_ENTRY_VERSION {
+        return Err(
+            format!("unsupported wrapped entry version: {}", version).into()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use internal::pw_hash;
+
+    async fn new_recipient() -> entry::EntryX25519 {
+        x25519::x25519_keypair_new_from_entropy(
+            internal::entropy::OsEntropy::new_handle(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_wrap_and_unwrap() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let recipient = new_recipient().await;
+        let target =
+            internal::sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                entropy.clone(),
+            )
+            .await
+            .unwrap();
+
+        let wrapped = wrap_entry(
+            entropy,
+            &target.clone().into(),
+            recipient.pub_key.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(WRAPPED_ENTRY_LEN, wrapped.len());
+
+        match unwrap_entry(recipient.priv_key, wrapped).await.unwrap() {
+            entry::LairEntry::SignEd25519(e) => {
+                assert_eq!(target.priv_key, e.priv_key);
+                assert_eq!(target.pub_key, e.pub_key);
+            }
+            e => panic!("unexpected entry type: {:?}", e),
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_tampered_containers() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let recipient = new_recipient().await;
+        let target = pw_hash::pw_pepper_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let wrapped =
+            wrap_entry(entropy, &target.into(), recipient.pub_key.clone())
+                .await
+                .unwrap();
+
+        // wrong recipient
+        let other = new_recipient().await;
+        assert!(matches!(
+            unwrap_entry(other.priv_key, wrapped.clone()).await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // any flipped ciphertext bit
+        for i in &[4, 40, 60, WRAPPED_ENTRY_LEN - 1] {
+            let mut bad = (*wrapped).clone();
+            bad[*i] ^= 0x01;
+            assert!(matches!(
+                unwrap_entry(recipient.priv_key.clone(), Arc::new(bad)).await,
+                Err(LairError::AuthenticationFailed),
+            ));
+        }
+
+        // truncated / extended
+        let mut bad = (*wrapped).clone();
+        bad.pop();
+        assert!(unwrap_entry(recipient.priv_key.clone(), Arc::new(bad))
+            .await
+            .is_err());
+        let mut bad = (*wrapped).clone();
+        bad.push(0);
+        assert!(unwrap_entry(recipient.priv_key.clone(), Arc::new(bad))
+            .await
+            .is_err());
+
+        // unknown container version
+        let mut bad = (*wrapped).clone();
+        bad[0] = 2;
+        let err = unwrap_entry(recipient.priv_key.clone(), Arc::new(bad))
+            .await
+            .unwrap_err();
+        assert!(format!("{}", err).contains("version"));
+
+        assert!(unwrap_entry(recipient.priv_key, Arc::new(vec![]))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,552 @@
+//! X25519 Key Agreement / Crypto Box Utilities
+//!
+//! NOTE - the box construction here is lair specific and is NOT
+//! wire compatible with libsodium's `crypto_box_easy`. The shared secret
+//! is the x25519 scalar multiplication, the box key is a personalized
+//! blake2b-256 hash of that secret and both public keys, and the payload
+//! is sealed with xsalsa20-poly1305 under a random 24 byte nonce:
+//! `nonce (24 bytes) || mac (16 bytes) || ciphertext`.
+//! The box key is fixed for a sender and recipient, and 24 random bytes
+//! will not repeat however many boxes they exchange.
+//!
+//! The ed25519 -> x25519 conversions ARE compatible with libsodium's
+//! `crypto_sign_ed25519_pk_to_curve25519` and
+//! `crypto_sign_ed25519_sk_to_curve25519`, and the sealed boxes
+//! are byte-for-byte libsodium `crypto_box_seal` boxes.
+
+use crate::*;
+use derive_more::*;
+
+use actor::X25519PubKey;
+use internal::entropy::EntropySourceHandle;
+use internal::secretbox;
+use internal::sign_ed25519::SignEd25519PrivKey;
+
+/// The 32 byte x25519 private key.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct X25519PrivKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for X25519PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// Byte overhead a crypto box adds to the plaintext length.
+pub const CRYPTO_BOX_OVERHEAD: usize =
+    secretbox::NONCE_BYTES + secretbox::MAC_BYTES;
+
+const BOX_KEY_PERSONAL: &[u8; 16] = b"lair_xsalsa_box_";
+
+/// Byte overhead a sealed box adds to the plaintext length
+/// (the ephemeral sender pub key, and the poly1305 mac).
+pub const CRYPTO_BOX_SEAL_OVERHEAD: usize = 32 + secretbox::MAC_BYTES;
+
+/// Generate a new random x25519 keypair.
+pub async fn x25519_keypair_new_from_entropy(
+    entropy: EntropySourceHandle,
+) -> LairResult<entry::EntryX25519> {
+    rayon_exec(move || {
+        let mut priv_key = vec![0; 32];
+        entropy.fill(&mut priv_key)?;
+        let pub_key = x25519_secret(&priv_key)?
+            .recover_public_key()
+            .map_err(|e| format!("{:?}", e))?
+            .to_vec();
+        Ok(entry::EntryX25519 {
+            priv_key: priv_key.into(),
+            pub_key: pub_key.into(),
+        })
+    })
+    .await
+}
+
+/// Convert an ed25519 signature private key (seed) into the
+/// x25519 private key for the same identity.
+pub fn sign_ed25519_priv_key_to_x25519(
+    priv_key: &SignEd25519PrivKey,
+) -> LairResult<X25519PrivKey> {
+    let seed = ed25519_compact::Seed::from_slice(priv_key)
+        .map_err(|e| format!("{:?}", e))?;
+    let keypair = ed25519_compact::KeyPair::from_seed(seed);
+    let priv_key =
+        ed25519_compact::x25519::SecretKey::from_ed25519(&keypair.sk)
+            .map_err(|e| format!("{:?}", e))?;
+    Ok(priv_key.to_vec().into())
+}
+
+/// Encrypt `data` from `sender_priv_key` to `recipient_pub_key`.
+pub async fn crypto_box(
+    entropy: EntropySourceHandle,
+    sender_priv_key: X25519PrivKey,
+    recipient_pub_key: X25519PubKey,
+    data: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    rayon_exec(move || {
+        let sender = x25519_secret(&sender_priv_key)?;
+        let sender_pub_key = sender
+            .recover_public_key()
+            .map_err(|e| format!("{:?}", e))?;
+        let key =
+            box_key(&sender, &sender_pub_key[..], &recipient_pub_key, true)?;
+
+        // never derived or counted, so no state can repeat it
+        let mut nonce = [0; secretbox::NONCE_BYTES];
+        entropy.fill(&mut nonce)?;
+
+        let mut out = Vec::with_capacity(data.len() + CRYPTO_BOX_OVERHEAD);
+        out.extend_from_slice(&nonce);
+        out.append(&mut secretbox::secretbox_easy(&key, &nonce, &data));
+        Ok(Arc::new(out))
+    })
+    .await
+}
+
+/// Decrypt `data` boxed by `sender_pub_key` to `recipient_priv_key`.
+/// Tampered data, or data boxed to a different recipient,
+/// fails with `LairError::AuthenticationFailed`.
+pub async fn crypto_box_open(
+    recipient_priv_key: X25519PrivKey,
+    sender_pub_key: X25519PubKey,
+    data: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    rayon_exec(move || {
+        let recipient = x25519_secret(&recipient_priv_key)?;
+        let recipient_pub_key = recipient
+            .recover_public_key()
+            .map_err(|e| format!("{:?}", e))?;
+
+        if data.len() < CRYPTO_BOX_OVERHEAD {
+            return Err(LairError::AuthenticationFailed);
+        }
+        let key = box_key(
+            &recipient,
+            &recipient_pub_key[..],
+            &sender_pub_key,
+            false,
+        )?;
+        let mut nonce = [0; secretbox::NONCE_BYTES];
+        nonce.copy_from_slice(&data[..secretbox::NONCE_BYTES]);
+        Ok(Arc::new(secretbox::secretbox_open_easy(
+            &key,
+            &nonce,
+            &data[secretbox::NONCE_BYTES..],
+        )?))
+    })
+    .await
+}
+
+/// Seal `data` to `recipient_pub_key` anonymously, as libsodium's
+/// `crypto_box_seal` does: `ephemeral pub key || mac || ciphertext`.
+pub async fn crypto_box_seal(
+    entropy: EntropySourceHandle,
+    recipient_pub_key: X25519PubKey,
+    data: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    rayon_exec(move || {
+        let mut ephemeral_priv_key = vec![0; 32];
+        entropy.fill(&mut ephemeral_priv_key)?;
+        Ok(Arc::new(crypto_box_seal_with_ephemeral(
+            &ephemeral_priv_key,
+            &recipient_pub_key,
+            &data,
+        )?))
+    })
+    .await
+}
+
+/// Open a sealed box with the recipient's private key.
+/// Tampered data, or data sealed to a different recipient,
+/// fails with `LairError::AuthenticationFailed`.
+pub async fn crypto_box_seal_open(
+    recipient_priv_key: X25519PrivKey,
+    data: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    rayon_exec(move || {
+        if data.len() < CRYPTO_BOX_SEAL_OVERHEAD {
+            return Err(LairError::AuthenticationFailed);
+        }
+        let recipient = x25519_secret(&recipient_priv_key)?;
+        let recipient_pub_key = recipient
+            .recover_public_key()
+            .map_err(|e| format!("{:?}", e))?;
+        let ephemeral_pub_key = &data[..32];
+        let key = box_beforenm(&recipient, ephemeral_pub_key)
+            .map_err(|_| LairError::AuthenticationFailed)?;
+        let nonce = seal_nonce(ephemeral_pub_key, &recipient_pub_key[..]);
+        Ok(Arc::new(secretbox::secretbox_open_easy(
+            &key,
+            &nonce,
+            &data[32..],
+        )?))
+    })
+    .await
+}
+
+fn crypto_box_seal_with_ephemeral(
+    ephemeral_priv_key: &[u8],
+    recipient_pub_key: &[u8],
+    data: &[u8],
+) -> LairResult<Vec<u8>> {
+    let ephemeral = x25519_secret(ephemeral_priv_key)?;
+    let ephemeral_pub_key = ephemeral
+        .recover_public_key()
+        .map_err(|e| format!("{:?}", e))?;
+    let key = box_beforenm(&ephemeral, recipient_pub_key)?;
+    let nonce = seal_nonce(&ephemeral_pub_key[..], recipient_pub_key);
+    let mut out = ephemeral_pub_key.to_vec();
+    out.append(&mut secretbox::secretbox_easy(&key, &nonce, data));
+    Ok(out)
+}
+
+// -- local helpers -- //
+
+fn x25519_secret(
+    priv_key: &[u8],
+) -> LairResult<ed25519_compact::x25519::SecretKey> {
+    ed25519_compact::x25519::SecretKey::from_slice(priv_key)
+        .map_err(|e| format!("{:?}", e).into())
+}
+
+/// libsodium `crypto_box_beforenm`: hsalsa20 of the x25519 shared secret.
+fn box_beforenm(
+    our_priv_key: &ed25519_compact::x25519::SecretKey,
+    their_pub_key: &[u8],
+) -> LairResult<[u8; 32]> {
+    let shared = ed25519_compact::x25519::PublicKey::from_slice(their_pub_key)
+        .and_then(|their| their.dh(our_priv_key))
+        .map_err(|e| format!("{:?}", e))?;
+    let mut shared_key = [0; 32];
+    shared_key.copy_from_slice(&shared[..]);
+    Ok(secretbox::hsalsa20(&shared_key, &[0; 16]))
+}
+
+/// libsodium sealed box nonce: blake2b-192 of both pub keys.
+fn seal_nonce(
+    ephemeral_pub_key: &[u8],
+    recipient_pub_key: &[u8],
+) -> [u8; secretbox::NONCE_BYTES] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(secretbox::NONCE_BYTES)
+        .to_state()
+        .update(ephemeral_pub_key)
+        .update(recipient_pub_key)
+        .finalize();
+    let mut nonce = [0; secretbox::NONCE_BYTES];
+    nonce.copy_from_slice(hash.as_bytes());
+    nonce
+}
+
+/// Derive the symmetric box key. Both sides hash the public keys
+/// in (sender, recipient) order, so `our_pub_key` goes first
+/// only if we are the sender.
+fn box_key(
+    our_priv_key: &ed25519_compact::x25519::SecretKey,
+    our_pub_key: &[u8],
+    their_pub_key: &[u8],
+    we_are_sender: bool,
+) -> LairResult<[u8; 32]> {
+    let their =
+        ed25519_compact::x25519::PublicKey::from_slice(their_pub_key)
+            .map_err(|e| LairError::InvalidKeyConversion(format!("{:?}", e)))?;
+    let shared = their
+        .dh(our_priv_key)
+        .map_err(|e| LairError::InvalidKeyConversion(format!("{:?}", e)))?;
+
+    let (sender_pub_key, recipient_pub_key) = if we_are_sender {
+        (our_pub_key, their_pub_key)
+    } else {
+        (their_pub_key, our_pub_key)
+    };
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(BOX_KEY_PERSONAL)
+        .to_state()
+        .update(&shared[..])
+        .update(sender_pub_key)
+        .update(recipient_pub_key)
+        .finalize();
+    let mut key = [0; 32];
+    key.copy_from_slice(hash.as_bytes());
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actor::SignEd25519PubKey;
+
+    // generated with libsodium `crypto_sign_seed_keypair` /
+    // `crypto_sign_ed25519_pk_to_curve25519` /
+    // `crypto_sign_ed25519_sk_to_curve25519`
+    const SEED: &str =
+        "421151a459faeade3d247115f94aedae42318124095afabe4d1451a559faedee";
+    const ED_PUB_KEY: &str =
+        "b5076a8474a832daee4dd5b4040983b6623b5f344aca57d4d6ee4baf3f259e6e";
+    const X_PUB_KEY: &str =
+        "f1814f0e8ff1043d8a44d25babff3cedcae6c22c3edaa48f857ae70de2baae50";
+    const X_PRIV_KEY: &str =
+        "8052030376d47112be7f73ed7a019293dd12ad910b654455798b4667d73de166";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_converts_keys_like_libsodium() {
+        let ed_pub_key: SignEd25519PubKey = hex(ED_PUB_KEY).into();
+        assert_eq!(hex(X_PUB_KEY), *ed_pub_key.to_x25519_pub_key().unwrap().0,);
+
+        let ed_priv_key: SignEd25519PrivKey = hex(SEED).into();
+        assert_eq!(
+            hex(X_PRIV_KEY),
+            *sign_ed25519_priv_key_to_x25519(&ed_priv_key).unwrap().0,
+        );
+    }
+
+    #[test]
+    fn it_rejects_low_order_ed25519_pub_keys() {
+        // libsodium rejects both of these
+        for bad in &[
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+        ] {
+            let bad: SignEd25519PubKey = hex(bad).into();
+            assert!(matches!(
+                bad.to_x25519_pub_key(),
+                Err(LairError::InvalidKeyConversion(_)),
+            ));
+        }
+    }
+
+    // generated with libsodium: recipient priv key = 1..=32,
+    // ephemeral priv key = 101..=132
+    const SEAL_RECIPIENT_PUB_KEY: &str =
+        "07a37cbc142093c8b755dc1b10e86cb426374ad16aa853ed0bdfc0b2b86d1c7c";
+    const SEAL_MESSAGE: &[u8] = b"lair sealed box test vector";
+    // `crypto_box_easy` under the sealed box nonce, with the fixed
+    // ephemeral keypair, i.e. what `crypto_box_seal` does internally
+    const SEAL_DETERMINISTIC: &str = "5714769d116bf76436ae74bc793d2c30ad1903c59ac5273805c7e2698b410c36e80b84dccfcb55b18e4523822555debd4dff4a102376f96776995f93ab18c6d3820888e7a6e5e6cffbf362";
+    // `crypto_box_seal` output with a random ephemeral keypair
+    const SEAL_LIBSODIUM: &str = "6edb6fc00965993df6b393c8b13d203c6c97f07945f361803e0d6e012d1a7b3adad6522cdd7745d742bfe5155fed52a2e2a0448c3a2010277fe9d76f7b39933625fe9ff86f1136a6780fc9";
+
+    fn seal_recipient() -> X25519PrivKey {
+        (1..=32).collect::<Vec<u8>>().into()
+    }
+
+    #[test]
+    fn it_seals_like_libsodium() {
+        let ephemeral = (101..=132).collect::<Vec<u8>>();
+        let sealed = crypto_box_seal_with_ephemeral(
+            &ephemeral,
+            &hex(SEAL_RECIPIENT_PUB_KEY),
+            SEAL_MESSAGE,
+        )
+        .unwrap();
+        assert_eq!(hex(SEAL_DETERMINISTIC), sealed);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_opens_libsodium_sealed_boxes() {
+        for sealed in &[SEAL_DETERMINISTIC, SEAL_LIBSODIUM] {
+            let opened =
+                crypto_box_seal_open(seal_recipient(), Arc::new(hex(sealed)))
+                    .await
+                    .unwrap();
+            assert_eq!(SEAL_MESSAGE, opened.as_slice());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_seal_and_open() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let recipient = x25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let other = x25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let data = Arc::new(b"hello".to_vec());
+
+        let sealed =
+            crypto_box_seal(entropy, recipient.pub_key.clone(), data.clone())
+                .await
+                .unwrap();
+        assert_eq!(data.len() + CRYPTO_BOX_SEAL_OVERHEAD, sealed.len());
+        assert_eq!(
+            data,
+            crypto_box_seal_open(recipient.priv_key.clone(), sealed.clone())
+                .await
+                .unwrap(),
+        );
+
+        // wrong recipient
+        assert!(matches!(
+            crypto_box_seal_open(other.priv_key, sealed.clone()).await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // tampered ephemeral key, mac, and ciphertext
+        for i in &[0, 32, sealed.len() - 1] {
+            let mut bad = (*sealed).clone();
+            bad[*i] ^= 1;
+            assert!(matches!(
+                crypto_box_seal_open(recipient.priv_key.clone(), Arc::new(bad))
+                    .await,
+                Err(LairError::AuthenticationFailed),
+            ));
+        }
+
+        // truncated
+        assert!(matches!(
+            crypto_box_seal_open(
+                recipient.priv_key,
+                Arc::new(sealed[..CRYPTO_BOX_SEAL_OVERHEAD - 1].to_vec()),
+            )
+            .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_box_and_open_to_sign_identity() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let sender = x25519_keypair_new_from_entropy(entropy.clone())
+            .await
+            .unwrap();
+        let recipient =
+            internal::sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                entropy.clone(),
+            )
+            .await
+            .unwrap();
+
+        let recipient_x_pub_key =
+            recipient.pub_key.to_x25519_pub_key().unwrap();
+        let recipient_x_priv_key =
+            sign_ed25519_priv_key_to_x25519(&recipient.priv_key).unwrap();
+
+        let data = Arc::new(b"hello".to_vec());
+        let boxed = crypto_box(
+            entropy,
+            sender.priv_key.clone(),
+            recipient_x_pub_key,
+            data.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(data.len() + CRYPTO_BOX_OVERHEAD, boxed.len());
+
+        let opened = crypto_box_open(
+            recipient_x_priv_key.clone(),
+            sender.pub_key.clone(),
+            boxed.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(data, opened);
+
+        let mut bad = (*boxed).clone();
+        bad[CRYPTO_BOX_OVERHEAD] ^= 1;
+        assert!(crypto_box_open(
+            recipient_x_priv_key,
+            sender.pub_key,
+            Arc::new(bad),
+        )
+        .await
+        .is_err());
+    }
+
+    async fn box_pair() -> (entry::EntryX25519, entry::EntryX25519) {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        (
+            x25519_keypair_new_from_entropy(entropy.clone())
+                .await
+                .unwrap(),
+            x25519_keypair_new_from_entropy(entropy).await.unwrap(),
+        )
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_never_reuses_box_nonces() {
+        let entropy = internal::entropy::OsEntropy::new_handle();
+        let (sender, recipient) = box_pair().await;
+        let data = Arc::new(b"same data, same keys".to_vec());
+
+        let mut boxes = Vec::new();
+        for _ in 0..2 {
+            boxes.push(
+                crypto_box(
+                    entropy.clone(),
+                    sender.priv_key.clone(),
+                    recipient.pub_key.clone(),
+                    data.clone(),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let nonce = |b: &[u8]| b[..secretbox::NONCE_BYTES].to_vec();
+        assert_ne!(nonce(&boxes[0]), nonce(&boxes[1]));
+        assert_ne!(boxes[0], boxes[1]);
+        for boxed in boxes {
+            assert_eq!(
+                data,
+                crypto_box_open(
+                    recipient.priv_key.clone(),
+                    sender.pub_key.clone(),
+                    boxed,
+                )
+                .await
+                .unwrap(),
+            );
+        }
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn it_boxes_under_unique_nonces(
+            data in proptest::collection::vec(
+                proptest::prelude::any::<u8>(),
+                0..512,
+            ),
+        ) {
+            futures::executor::block_on(async move {
+                let entropy = internal::entropy::OsEntropy::new_handle();
+                let (sender, recipient) = box_pair().await;
+                let data = Arc::new(data);
+                let mut nonces = std::collections::HashSet::new();
+                for _ in 0..64 {
+                    let boxed = crypto_box(
+                        entropy.clone(),
+                        sender.priv_key.clone(),
+                        recipient.pub_key.clone(),
+                        data.clone(),
+                    )
+                    .await
+                    .unwrap();
+                    assert_eq!(data.len() + CRYPTO_BOX_OVERHEAD, boxed.len());
+                    assert!(nonces
+                        .insert(boxed[..secretbox::NONCE_BYTES].to_vec()));
+                    assert_eq!(
+                        data,
+                        crypto_box_open(
+                            recipient.priv_key.clone(),
+                            sender.pub_key.clone(),
+                            boxed,
+                        )
+                        .await
+                        .unwrap(),
+                    );
+                }
+            });
+        }
+    }
+}
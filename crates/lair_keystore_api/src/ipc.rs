@@ -2,11 +2,24 @@
 
 use crate::actor::*;
 use crate::internal::util::*;
+use crate::passphrase::Passphrase;
 use crate::*;
 
 mod spawn_client_ipc;
 
+mod connect;
+pub use connect::*;
+
+mod client_pool;
+pub use client_pool::*;
+
+mod client_cache;
+pub use client_cache::*;
+
 /// Spawn a client Ipc connection.
+/// Handlers of the events received may call the api before answering,
+/// as long as they do not hold up the receiver meanwhile.
+#[deprecated(note = "use `connect(ConnectOptions::from_config(&config))`")]
 pub async fn spawn_client_ipc(
     config: Arc<Config>,
 ) -> LairResult<(
@@ -20,13 +33,85 @@ pub async fn spawn_client_ipc(
     Ok((api_send, evt_recv))
 }
 
+/// Answer `request_unlock_passphrase` events on `evt_recv` with
+/// `passphrase`. Returns a future resolving with the result of the unlock
+/// the passphrase starts, and a stream of its progress percentages,
+/// which ends when the unlock completes.
+pub fn spawn_passphrase_unlock_responder(
+    mut evt_recv: LairClientEventReceiver,
+    passphrase: Passphrase,
+) -> (
+    futures::future::BoxFuture<'static, LairResult<()>>,
+    futures::stream::BoxStream<'static, u8>,
+) {
+    use futures::{future::FutureExt, stream::StreamExt};
+
+    let (progress_send, progress_recv) = futures::channel::mpsc::unbounded();
+    let (complete_send, complete_recv) = futures::channel::oneshot::channel();
+
+    err_spawn("passphrase-unlock-responder", async move {
+        let mut progress_send = Some(progress_send);
+        let mut complete_send = Some(complete_send);
+        while let Some(evt) = evt_recv.next().await {
+            match evt {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond, ..
+                } => {
+                    let passphrase = passphrase.clone();
+                    respond.respond(Ok(async move { Ok(passphrase) }
+                        .boxed()
+                        .into()));
+                }
+                LairClientEvent::UnlockProgress {
+                    respond, percent, ..
+                } => {
+                    if let Some(progress_send) = &progress_send {
+                        let _ = progress_send.unbounded_send(percent);
+                    }
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                }
+                LairClientEvent::UnlockComplete { respond, error, .. } => {
+                    // end the stream, ignoring any late progress
+                    progress_send = None;
+                    if let Some(complete_send) = complete_send.take() {
+                        let _ = complete_send.send(match error {
+                            None => Ok(()),
+                            Some(error) => Err(error.into()),
+                        });
+                    }
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                }
+                // only unlocks are answered here
+                evt => evt.respond_default(),
+            }
+        }
+        Ok(())
+    });
+
+    let unlock = async move {
+        complete_recv.await.map_err(|_| {
+            LairError::from("connection closed before the unlock completed")
+        })?
+    }
+    .boxed();
+
+    (unlock, progress_recv.boxed())
+}
+
 /// Incoming Connection Receiver.
 pub type IncomingIpcConnectionReceiver =
     futures::channel::mpsc::Receiver<LairClientEventSenderType>;
 
+mod audit;
+mod connections;
+mod elevation;
+mod event_queue;
 mod spawn_bind_server_ipc;
+pub use spawn_bind_server_ipc::LairInProcListener;
 
 /// Bind a server Ipc connection.
+/// Every connection is served by the same `api_sender`.
+/// The event sender of each new connection is sent to the returned receiver.
 pub async fn spawn_bind_server_ipc<S>(
     config: Arc<Config>,
     api_sender: S,
@@ -34,16 +119,67 @@ pub async fn spawn_bind_server_ipc<S>(
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    let (mut kill_switch, incoming_recv) =
+        bind_server_ipc(config, api_sender).await?;
+    // the server runs for as long as the process
+    kill_switch.make_weak();
+    Ok(incoming_recv)
+}
+
+/// As `spawn_bind_server_ipc`, the server is unbound, and every
+/// connection closed, once the returned kill switch is dropped.
+pub(crate) async fn bind_server_ipc<S>(
+    config: Arc<Config>,
+    api_sender: S,
+) -> LairResult<(KillSwitch, IncomingIpcConnectionReceiver)>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    use futures::{future::FutureExt, sink::SinkExt};
+
     let (incoming_send, incoming_recv) = futures::channel::mpsc::channel(10);
 
-    spawn_bind_server_ipc::spawn_bind_server_ipc(
-        config,
-        api_sender,
-        incoming_send,
-    )
-    .await?;
+    let (kill_switch, _) =
+        spawn_bind_server_ipc::spawn_bind_server_ipc(config, move |evt_send| {
+            let api_sender = api_sender.clone();
+            let mut incoming_send = incoming_send.clone();
+            async move {
+                incoming_send
+                    .send(evt_send)
+                    .await
+                    .map_err(LairError::other)?;
+                Ok(api_sender)
+            }
+            .boxed()
+        })
+        .await?;
 
-    Ok(incoming_recv)
+    Ok((kill_switch, incoming_recv))
+}
+
+/// Bind a server Ipc connection, serving each connection with its own
+/// api sender, built by `api_factory` from the connection's event sender.
+/// Lets the server keep per-connection state, such as the selected store.
+/// The returned listener opens in-process connections to the server.
+pub async fn spawn_bind_server_ipc_per_connection<S, F>(
+    config: Arc<Config>,
+    api_factory: F,
+) -> LairResult<LairInProcListener>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+    F: Fn(
+            LairClientEventSenderType,
+        ) -> futures::future::BoxFuture<'static, LairResult<S>>
+        + 'static
+        + Send
+        + Sync,
+{
+    let (mut kill_switch, in_proc) =
+        spawn_bind_server_ipc::spawn_bind_server_ipc(config, api_factory)
+            .await?;
+    // the server runs for as long as the process
+    kill_switch.make_weak();
+    Ok(in_proc)
 }
 
 #[cfg(test)]
@@ -80,9 +216,80 @@ mod tests {
             ) -> LairClientApiHandlerResult<LairServerInfo> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_lair_get_capabilities(
+                &mut self,
+            ) -> LairClientApiHandlerResult<LairCapabilities> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_request_elevation(
+                &mut self,
+                _capabilities: LairCapabilities,
+                _duration: std::time::Duration,
+            ) -> LairClientApiHandlerResult<LairElevation> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_elevation(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Option<LairElevation>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_drop_elevation(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_list_connections(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Vec<LairConnectionInfo>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_reload_settings(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_set_event_filter(
+                &mut self,
+                _kinds: Vec<EventKind>,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_get_server_paths(
+                &mut self,
+            ) -> LairClientApiHandlerResult<LairServerPaths> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_export_device_secret(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_select_store(
+                &mut self,
+                _name: String,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
             fn handle_lair_get_last_entry_index(
                 &mut self,
-            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+            ) -> LairClientApiHandlerResult<Option<KeystoreIndex>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_last_entry_index_64(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Option<KeystoreIndex64>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_entry_count(
+                &mut self,
+            ) -> LairClientApiHandlerResult<u64> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_store_stats(
+                &mut self,
+            ) -> LairClientApiHandlerResult<StoreStats> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
             fn handle_lair_get_entry_type(
@@ -91,6 +298,119 @@ mod tests {
             ) -> LairClientApiHandlerResult<LairEntryType> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_lair_get_entry_type_64(
+                &mut self,
+                _keystore_index: KeystoreIndex64,
+            ) -> LairClientApiHandlerResult<LairEntryType> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_entry_types(
+                &mut self,
+                _indices: Vec<KeystoreIndex>,
+            ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_erase_entries(
+                &mut self,
+                _indices: Vec<KeystoreIndex>,
+            ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+                Ok(async move {
+                    Ok(vec![
+                        Ok(()),
+                        Err(LairError::EntryNotFound("test-val".to_string())),
+                    ])
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_lair_set_entry_tags(
+                &mut self,
+                _tags: Vec<(KeystoreIndex, String)>,
+            ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+                Ok(async move {
+                    Ok(vec![
+                        Err(LairError::TagInUse("test-val".to_string())),
+                        Ok(()),
+                    ])
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_lair_set_entry_tag(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _tag: Option<String>,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_get_entry_by_tag(
+                &mut self,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_lair_find_entries_by_prefix(
+                &mut self,
+                _prefix: Arc<Vec<u8>>,
+                _kind: PrefixKind,
+            ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_add_entry_alias(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _alias: String,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_remove_entry_alias(
+                &mut self,
+                _alias: String,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_trash_entry(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_restore_entry(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_activate_entry(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_purge_trashed(
+                &mut self,
+                older_than: std::time::Duration,
+            ) -> LairClientApiHandlerResult<u64> {
+                let purged = older_than.as_millis() as u64;
+                Ok(async move { Ok(purged) }.boxed().into())
+            }
+            fn handle_lair_list_quarantined(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Vec<QuarantinedEntry>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_self_test(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
             fn handle_tls_cert_new_self_signed_from_entropy(
                 &mut self,
                 _options: TlsCertOptions,
@@ -106,6 +426,22 @@ mod tests {
                 .boxed()
                 .into())
             }
+            fn handle_tls_cert_new_self_signed_from_sign_key(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _options: TlsCertOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_tls_cert_get(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -115,6 +451,21 @@ mod tests {
                     TestVal::test_val(),
                 )) }.boxed().into())
             }
+            fn handle_tls_cert_get_info(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<(CertSni, CertDigest, TlsCertAlg)>
+            {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_tls_cert_get_cert_by_index(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -127,12 +478,38 @@ mod tests {
             ) -> LairClientApiHandlerResult<Cert> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_tls_cert_get_digest(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _digest_alg: DigestAlg,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_tls_cert_get_cert_by_typed_digest(
+                &mut self,
+                _digest_alg: DigestAlg,
+                _digest: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Cert> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
             fn handle_tls_cert_get_cert_by_sni(
                 &mut self,
                 _cert_sni: CertSni,
             ) -> LairClientApiHandlerResult<Cert> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_tls_cert_resolve_by_digest(
+                &mut self,
+                _cert_digest: CertDigest,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_tls_cert_resolve_by_sni(
+                &mut self,
+                _cert_sni: CertSni,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
             fn handle_tls_cert_get_priv_key_by_index(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -151,6 +528,26 @@ mod tests {
             ) -> LairClientApiHandlerResult<CertPrivKey> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_tls_cert_get_bundle_by_tag(
+                &mut self,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<(
+                CertSni,
+                CertDigest,
+                Cert,
+                CertPrivKey,
+            )> {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_sign_ed25519_new_from_entropy(
                 &mut self,
             ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)>
@@ -160,12 +557,101 @@ mod tests {
                     TestVal::test_val(),
                 )) }.boxed().into())
             }
+            fn handle_sign_ed25519_new(
+                &mut self,
+                _backing: KeyBacking,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_sign_ed25519_new_from_entropy_with_options(
+                &mut self,
+                _options: SignKeyOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_new_from_seed_with_options(
+                &mut self,
+                _seed: [u8; 32],
+                _options: SignKeyOptions,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                SignEd25519PubKey,
+                bool,
+            )> {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_get_options(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<SignKeyOptions> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed25519_rotate(
+                &mut self,
+                _old_keystore_index: KeystoreIndex,
+                _options: RotateKeyOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, RotationRecord)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_export_seed(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<[u8; 32]> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed25519_import_ssh_key(
+                &mut self,
+                _key: Arc<Vec<u8>>,
+                _passphrase: Option<String>,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                SignEd25519PubKey,
+                bool,
+            )> {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_sign_ed25519_get(
                 &mut self,
                 _keystore_index: KeystoreIndex,
             ) -> LairClientApiHandlerResult<SignEd25519PubKey> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_sign_ed25519_resolve_by_pub_key(
+                &mut self,
+                _pub_key: SignEd25519PubKey,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
             fn handle_sign_ed25519_sign_by_index(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -173,6 +659,13 @@ mod tests {
             ) -> LairClientApiHandlerResult<SignEd25519Signature> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_sign_ed25519_sign_by_index_64(
+                &mut self,
+                _keystore_index: KeystoreIndex64,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
             fn handle_sign_ed25519_sign_by_pub_key(
                 &mut self,
                 _pub_key: SignEd25519PubKey,
@@ -180,11 +673,423 @@ mod tests {
             ) -> LairClientApiHandlerResult<SignEd25519Signature> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-        }
-
-        let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
-        let api_sender = builder
-            .channel_factory()
+            fn handle_sign_ed25519_sign_by_index_v2(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(
+                SignEd25519PubKey,
+                SignEd25519Signature,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_sign_by_index_with_options(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message: Arc<Vec<u8>>,
+                _options: SignOptions,
+            ) -> LairClientApiHandlerResult<(SignEd25519Signature, Option<u64>)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_sign_by_pub_key_v2(
+                &mut self,
+                _pub_key: SignEd25519PubKey,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(
+                SignEd25519PubKey,
+                SignEd25519Signature,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_sign_by_tag(
+                &mut self,
+                _tag: String,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(
+                SignEd25519PubKey,
+                SignEd25519Signature,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_ph_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _prehash: [u8; 64],
+                _context: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_x25519_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, X25519PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_x25519_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<X25519PubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_to_sign_pub_key(
+                &mut self,
+                _sender_x25519_index: KeystoreIndex,
+                _recipient_sign_pub_key: SignEd25519PubKey,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_open_by_sign_pub_key(
+                &mut self,
+                _recipient_sign_pub_key: SignEd25519PubKey,
+                _sender_x25519_pub_key: X25519PubKey,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_seal_open_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_age_decrypt_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _stanzas: Vec<AgeStanza>,
+            ) -> LairClientApiHandlerResult<[u8; AGE_FILE_KEY_LEN]>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_random_bytes(
+                &mut self,
+                _len: u32,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_new_uuid(
+                &mut self,
+            ) -> LairClientApiHandlerResult<[u8; 16]> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_pw_pepper_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_pw_hash(
+                &mut self,
+                _pepper_index: KeystoreIndex,
+                _password: Arc<Vec<u8>>,
+                _options: PwHashOptions,
+            ) -> LairClientApiHandlerResult<String> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_pw_verify(
+                &mut self,
+                _pepper_index: KeystoreIndex,
+                _password: Arc<Vec<u8>>,
+                _hash: String,
+            ) -> LairClientApiHandlerResult<bool> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_totp_new_from_entropy(
+                &mut self,
+                _options: TotpOptions,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_totp_import(
+                &mut self,
+                _secret_b32: String,
+                _options: TotpOptions,
+            ) -> LairClientApiHandlerResult<KeystoreIndex> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_totp_generate(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _at: Option<u64>,
+            ) -> LairClientApiHandlerResult<String> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_totp_export_provisioning_uri(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _label: String,
+                _issuer: String,
+            ) -> LairClientApiHandlerResult<String> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_secp256k1_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_secp256k1_new_from_seed(
+                &mut self,
+                _seed: [u8; 32],
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                SignSecp256k1PubKey,
+                bool,
+            )> {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_secp256k1_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<SignSecp256k1PubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_secp256k1_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message_hash: [u8; 32],
+            ) -> LairClientApiHandlerResult<(SignSecp256k1Signature, u8)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed448_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd448PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed448_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<SignEd448PubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed448_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<SignEd448Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed448_sign_by_pub_key(
+                &mut self,
+                _pub_key: SignEd448PubKey,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<SignEd448Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_bls_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignBlsPubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_bls_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<SignBlsPubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_bls_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message: Arc<Vec<u8>>,
+                _dst: Vec<u8>,
+            ) -> LairClientApiHandlerResult<SignBlsSignature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_sr25519_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_sr25519_new_from_seed(
+                &mut self,
+                _seed: [u8; 32],
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                SignSr25519PubKey,
+                bool,
+            )> {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_sr25519_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<SignSr25519PubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_sr25519_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _message: Arc<Vec<u8>>,
+                _context: Vec<u8>,
+            ) -> LairClientApiHandlerResult<SignSr25519Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_attest_entry(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _challenge: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Attestation> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_prove_server_identity(
+                &mut self,
+                _nonce: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<ServerIdentityProof> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_ephemeral_x25519_new(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(EphemeralHandle, X25519PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_ephemeral_sign_ed25519_new(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(EphemeralHandle, SignEd25519PubKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_ephemeral_sign_ed25519_sign(
+                &mut self,
+                _handle: EphemeralHandle,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_ephemeral_crypto_box(
+                &mut self,
+                _sender_handle: EphemeralHandle,
+                _recipient_pub_key: X25519PubKey,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_ephemeral_crypto_box_open(
+                &mut self,
+                _recipient_handle: EphemeralHandle,
+                _sender_pub_key: X25519PubKey,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_ephemeral_drop(
+                &mut self,
+                _handle: EphemeralHandle,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_wrap_entry(
+                &mut self,
+                _target_index: KeystoreIndex,
+                _recipient_pub_key: X25519PubKey,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_unwrap_entry(
+                &mut self,
+                _recipient_index: KeystoreIndex,
+                _wrapped: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType, bool)>
+            {
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+
+            fn handle_lair_list_entries_page(
+                &mut self,
+                _start: KeystoreIndex,
+                _limit: u32,
+            ) -> LairClientApiHandlerResult<(
+                Vec<LairEntryInfo>,
+                Option<KeystoreIndex>,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+        }
+
+        let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+        let api_sender = builder
+            .channel_factory()
             .create_channel::<LairClientApi>()
             .await?;
         err_spawn("test-api-actor", async move {
@@ -197,118 +1102,1381 @@ mod tests {
         err_spawn("test-incoming-loop", async move {
             let mut keep_em = Vec::new();
             while let Some(evt_send) = incoming_recv.next().await {
-                let passphrase = evt_send.request_unlock_passphrase().await?;
-                assert_eq!("test-val", passphrase);
+                let passphrase = evt_send
+                    .request_unlock_passphrase(DEFAULT_STORE_NAME.to_string())
+                    .await?;
+                assert_eq!(b"test-val", passphrase.read());
                 keep_em.push(evt_send);
             }
             Ok(())
         });
 
-        let (cli_send, mut cli_recv) = spawn_client_ipc(config).await?;
+        let (cli_send, mut cli_recv) =
+            connect(ConnectOptions::from_config(&config)).await?;
 
         err_spawn("test-evt-loop", async move {
             while let Some(msg) = cli_recv.next().await {
-                match msg {
-                    LairClientEvent::RequestUnlockPassphrase {
-                        respond,
-                        ..
-                    } => {
-                        respond.respond(Ok(
-                            async move { Ok(TestVal::test_val()) }
-                                .boxed()
-                                .into(),
-                        ));
-                    }
+                if let LairClientEvent::RequestUnlockPassphrase {
+                    respond,
+                    ..
+                } = msg
+                {
+                    respond.respond(Ok(async move { Ok(TestVal::test_val()) }
+                        .boxed()
+                        .into()));
                 }
             }
             Ok(())
         });
 
         assert_eq!(
-            LairServerInfo::test_val(),
-            cli_send.lair_get_server_info().await?
+            LairServerInfo::test_val(),
+            cli_send.lair_get_server_info().await?
+        );
+        // answered by the ipc server, not the api
+        assert_eq!(
+            LairCapabilities::ALL,
+            cli_send.lair_get_capabilities().await?
+        );
+        assert_eq!(None, cli_send.lair_get_elevation().await?);
+        assert!(matches!(
+            cli_send
+                .lair_request_elevation(
+                    LairCapabilities::ALL,
+                    std::time::Duration::from_secs(1)
+                )
+                .await,
+            Err(LairError::Forbidden(_)),
+        ));
+        let connections = cli_send.lair_list_connections().await?;
+        assert_eq!(1, connections.len());
+        // the listing request itself
+        assert_eq!(1, connections[0].in_flight);
+        cli_send.lair_reload_settings().await?;
+        assert_eq!(
+            LairServerPaths::test_val(),
+            cli_send.lair_get_server_paths().await?
+        );
+        assert_eq!(
+            Arc::<Vec<u8>>::test_val(),
+            cli_send.lair_export_device_secret().await?
+        );
+        cli_send.lair_select_store("test-val".to_string()).await?;
+        assert_eq!(
+            Option::<KeystoreIndex>::test_val(),
+            cli_send.lair_get_last_entry_index().await?
+        );
+        assert_eq!(
+            Option::<KeystoreIndex64>::test_val(),
+            cli_send.lair_get_last_entry_index_64().await?
+        );
+        assert_eq!(u64::test_val(), cli_send.lair_get_entry_count().await?);
+        assert_eq!(
+            StoreStats::test_val(),
+            cli_send.lair_get_store_stats().await?
+        );
+        assert_eq!(
+            LairEntryType::test_val(),
+            cli_send.lair_get_entry_type(0.into()).await?
+        );
+        assert_eq!(
+            LairEntryType::test_val(),
+            cli_send
+                .lair_get_entry_type_64(KeystoreIndex64::test_val())
+                .await?
+        );
+        assert_eq!(
+            Vec::<(KeystoreIndex, LairEntryType)>::test_val(),
+            cli_send.lair_get_entry_types(TestVal::test_val()).await?
+        );
+        let results = cli_send.lair_erase_entries(TestVal::test_val()).await?;
+        assert_eq!(2, results.len());
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            &results[1],
+            Err(LairError::EntryNotFound(reason)) if reason == "test-val",
+        ));
+        let results = cli_send.lair_set_entry_tags(TestVal::test_val()).await?;
+        assert_eq!(2, results.len());
+        assert!(matches!(
+            &results[0],
+            Err(LairError::TagInUse(tag)) if tag == "test-val",
+        ));
+        assert!(results[1].is_ok());
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_new_self_signed_from_entropy(
+                    TlsCertOptions::default(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_new_self_signed_from_sign_key(
+                    1.into(),
+                    TlsCertOptions::default(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            (CertSni::test_val(), CertDigest::test_val(),),
+            cli_send.tls_cert_get(0.into()).await?,
+        );
+        assert_eq!(
+            (
+                CertSni::test_val(),
+                CertDigest::test_val(),
+                TlsCertAlg::test_val()
+            ),
+            cli_send.tls_cert_get_info(0.into()).await?,
+        );
+        assert_eq!(
+            Cert::test_val(),
+            cli_send.tls_cert_get_cert_by_index(0.into()).await?,
+        );
+        assert_eq!(
+            Cert::test_val(),
+            cli_send
+                .tls_cert_get_cert_by_digest(CertDigest::test_val())
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .tls_cert_get_digest(0.into(), DigestAlg::Sha256)
+                .await?,
+        );
+        assert_eq!(
+            Cert::test_val(),
+            cli_send
+                .tls_cert_get_cert_by_typed_digest(
+                    DigestAlg::Sha256,
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            Cert::test_val(),
+            cli_send
+                .tls_cert_get_cert_by_sni(CertSni::test_val())
+                .await?,
+        );
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send
+                .tls_cert_resolve_by_digest(CertDigest::test_val())
+                .await?,
+        );
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send
+                .tls_cert_resolve_by_sni(CertSni::test_val())
+                .await?,
+        );
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send
+                .sign_ed25519_resolve_by_pub_key(SignEd25519PubKey::test_val())
+                .await?,
+        );
+        assert_eq!(
+            CertPrivKey::test_val(),
+            cli_send.tls_cert_get_priv_key_by_index(0.into()).await?,
+        );
+        assert_eq!(
+            CertPrivKey::test_val(),
+            cli_send
+                .tls_cert_get_priv_key_by_digest(CertDigest::test_val())
+                .await?,
+        );
+        assert_eq!(
+            CertPrivKey::test_val(),
+            cli_send
+                .tls_cert_get_priv_key_by_sni(CertSni::test_val())
+                .await?,
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), SignEd25519PubKey::test_val(),),
+            cli_send.sign_ed25519_new_from_entropy().await?,
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), SignEd25519PubKey::test_val(),),
+            cli_send.sign_ed25519_new(KeyBacking::test_val()).await?,
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), SignEd25519PubKey::test_val()),
+            cli_send
+                .sign_ed25519_new_from_entropy_with_options(
+                    SignKeyOptions::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                SignEd25519PubKey::test_val(),
+                bool::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_new_from_seed_with_options(
+                    [0x42; 32],
+                    SignKeyOptions::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            SignKeyOptions::test_val(),
+            cli_send.sign_ed25519_get_options(0.into()).await?,
+        );
+        assert_eq!(
+            <[u8; 32]>::test_val(),
+            cli_send.sign_ed25519_export_seed(0.into()).await?,
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), RotationRecord::test_val()),
+            cli_send
+                .sign_ed25519_rotate(0.into(), RotateKeyOptions::test_val())
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                SignEd25519PubKey::test_val(),
+                bool::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_import_ssh_key(
+                    b"key".to_vec().into(),
+                    Some("pass".to_string()),
+                )
+                .await?,
+        );
+        assert_eq!(
+            SignEd25519PubKey::test_val(),
+            cli_send.sign_ed25519_get(0.into()).await?,
+        );
+        assert_eq!(
+            SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_index(0.into(), b"".to_vec().into())
+                .await?,
+        );
+        assert_eq!(
+            SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_index_64(
+                    KeystoreIndex64::test_val(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_pub_key(
+                    SignEd25519PubKey::test_val(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                SignEd25519PubKey::test_val(),
+                SignEd25519Signature::test_val()
+            ),
+            cli_send
+                .sign_ed25519_sign_by_index_v2(0.into(), b"".to_vec().into())
+                .await?,
+        );
+        assert_eq!(
+            (SignEd25519Signature::test_val(), Option::<u64>::test_val()),
+            cli_send
+                .sign_ed25519_sign_by_index_with_options(
+                    0.into(),
+                    b"".to_vec().into(),
+                    SignOptions::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                SignEd25519PubKey::test_val(),
+                SignEd25519Signature::test_val()
+            ),
+            cli_send
+                .sign_ed25519_sign_by_pub_key_v2(
+                    SignEd25519PubKey::test_val(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+
+        assert_eq!(
+            (
+                Vec::<LairEntryInfo>::test_val(),
+                Option::<KeystoreIndex>::test_val(),
+            ),
+            cli_send.lair_list_entries_page(0.into(), 10).await?,
+        );
+
+        cli_send
+            .lair_set_entry_tag(0.into(), Some(String::test_val()))
+            .await?;
+        assert_eq!(
+            (KeystoreIndex::test_val(), LairEntryType::test_val()),
+            cli_send.lair_get_entry_by_tag(String::test_val()).await?,
+        );
+        assert_eq!(
+            <Vec<(KeystoreIndex, LairEntryType)>>::test_val(),
+            cli_send
+                .lair_find_entries_by_prefix(
+                    TestVal::test_val(),
+                    PrefixKind::test_val(),
+                )
+                .await?,
+        );
+        cli_send
+            .lair_add_entry_alias(0.into(), String::test_val())
+            .await?;
+        cli_send.lair_remove_entry_alias(String::test_val()).await?;
+        cli_send.lair_trash_entry(0.into()).await?;
+        cli_send.lair_restore_entry(0.into()).await?;
+        cli_send.lair_activate_entry(0.into()).await?;
+        assert_eq!(
+            1500,
+            cli_send
+                .lair_purge_trashed(std::time::Duration::from_millis(1500))
+                .await?,
+        );
+        assert_eq!(
+            <Vec<QuarantinedEntry>>::test_val(),
+            cli_send.lair_list_quarantined().await?,
+        );
+        cli_send.lair_self_test().await?;
+        assert_eq!(
+            (
+                CertSni::test_val(),
+                CertDigest::test_val(),
+                Cert::test_val(),
+                CertPrivKey::test_val(),
+            ),
+            cli_send
+                .tls_cert_get_bundle_by_tag(String::test_val())
+                .await?,
+        );
+        assert_eq!(
+            (
+                SignEd25519PubKey::test_val(),
+                SignEd25519Signature::test_val()
+            ),
+            cli_send
+                .sign_ed25519_sign_by_tag(
+                    String::test_val(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_ph_sign_by_index(
+                    0.into(),
+                    [0; 64],
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), X25519PubKey::test_val()),
+            cli_send.x25519_new_from_entropy().await?,
+        );
+        assert_eq!(
+            X25519PubKey::test_val(),
+            cli_send.x25519_get(0.into()).await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .crypto_box_to_sign_pub_key(
+                    0.into(),
+                    SignEd25519PubKey::test_val(),
+                    b"".to_vec().into(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .crypto_box_open_by_sign_pub_key(
+                    SignEd25519PubKey::test_val(),
+                    X25519PubKey::test_val(),
+                    b"".to_vec().into(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .crypto_box_seal_open_by_index(0.into(), b"".to_vec().into())
+                .await?,
+        );
+        assert_eq!(
+            <[u8; AGE_FILE_KEY_LEN]>::test_val(),
+            cli_send
+                .age_decrypt_by_index(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send.lair_get_random_bytes(32).await?,
+        );
+        assert_eq!(<[u8; 16]>::test_val(), cli_send.lair_new_uuid().await?);
+
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send.pw_pepper_new_from_entropy().await?,
+        );
+        assert_eq!(
+            String::test_val(),
+            cli_send
+                .pw_hash(
+                    0.into(),
+                    b"".to_vec().into(),
+                    PwHashOptions::default(),
+                )
+                .await?,
+        );
+        assert!(
+            cli_send
+                .pw_verify(0.into(), b"".to_vec().into(), String::test_val())
+                .await?
+        );
+
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send
+                .totp_new_from_entropy(TotpOptions::default())
+                .await?,
+        );
+        assert_eq!(
+            KeystoreIndex::test_val(),
+            cli_send
+                .totp_import(String::test_val(), TotpOptions::default())
+                .await?,
+        );
+        assert_eq!(
+            String::test_val(),
+            cli_send.totp_generate(0.into(), Some(59)).await?,
+        );
+        assert_eq!(
+            String::test_val(),
+            cli_send
+                .totp_export_provisioning_uri(
+                    0.into(),
+                    String::test_val(),
+                    String::test_val(),
+                )
+                .await?,
+        );
+
+        assert_eq!(
+            (EphemeralHandle::test_val(), X25519PubKey::test_val()),
+            cli_send.ephemeral_x25519_new().await?,
+        );
+        assert_eq!(
+            (EphemeralHandle::test_val(), SignEd25519PubKey::test_val()),
+            cli_send.ephemeral_sign_ed25519_new().await?,
+        );
+        assert_eq!(
+            SignEd25519Signature::test_val(),
+            cli_send
+                .ephemeral_sign_ed25519_sign(
+                    42.into(),
+                    Arc::new(b"test-data".to_vec()),
+                )
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .ephemeral_crypto_box(
+                    42.into(),
+                    X25519PubKey::test_val(),
+                    Arc::new(b"test-data".to_vec()),
+                )
+                .await?,
+        );
+        assert_eq!(
+            <Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .ephemeral_crypto_box_open(
+                    42.into(),
+                    X25519PubKey::test_val(),
+                    Arc::new(b"test-data".to_vec()),
+                )
+                .await?,
+        );
+        cli_send.ephemeral_drop(42.into()).await?;
+
+        assert_eq!(
+            (KeystoreIndex::test_val(), SignSecp256k1PubKey::test_val()),
+            cli_send.sign_secp256k1_new_from_entropy().await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                SignSecp256k1PubKey::test_val(),
+                bool::test_val(),
+            ),
+            cli_send.sign_secp256k1_new_from_seed([0x42; 32]).await?,
+        );
+        assert_eq!(
+            SignSecp256k1PubKey::test_val(),
+            cli_send.sign_secp256k1_get(0.into()).await?,
+        );
+        assert_eq!(
+            (SignSecp256k1Signature::test_val(), u8::test_val()),
+            cli_send
+                .sign_secp256k1_sign_by_index(0.into(), [0x42; 32])
+                .await?,
+        );
+
+        assert_eq!(
+            (KeystoreIndex::test_val(), SignEd448PubKey::test_val()),
+            cli_send.sign_ed448_new_from_entropy().await?,
+        );
+        assert_eq!(
+            SignEd448PubKey::test_val(),
+            cli_send.sign_ed448_get(0.into()).await?,
+        );
+        assert_eq!(
+            SignEd448Signature::test_val(),
+            cli_send
+                .sign_ed448_sign_by_index(0.into(), Arc::new(vec![0x42; 8]))
+                .await?,
+        );
+        assert_eq!(
+            SignEd448Signature::test_val(),
+            cli_send
+                .sign_ed448_sign_by_pub_key(
+                    SignEd448PubKey::test_val(),
+                    Arc::new(vec![0x42; 8]),
+                )
+                .await?,
         );
+
         assert_eq!(
-            KeystoreIndex::test_val(),
-            cli_send.lair_get_last_entry_index().await?
+            (KeystoreIndex::test_val(), SignBlsPubKey::test_val()),
+            cli_send.sign_bls_new_from_entropy().await?,
         );
         assert_eq!(
-            LairEntryType::test_val(),
-            cli_send.lair_get_entry_type(0.into()).await?
+            SignBlsPubKey::test_val(),
+            cli_send.sign_bls_get(0.into()).await?,
         );
         assert_eq!(
-            (
-                KeystoreIndex::test_val(),
-                CertSni::test_val(),
-                CertDigest::test_val(),
-            ),
+            SignBlsSignature::test_val(),
             cli_send
-                .tls_cert_new_self_signed_from_entropy(
-                    TlsCertOptions::default(),
+                .sign_bls_sign_by_index(
+                    0.into(),
+                    Arc::new(vec![0x42; 8]),
+                    bls::DST_POP.to_vec(),
                 )
                 .await?,
         );
+
         assert_eq!(
-            (CertSni::test_val(), CertDigest::test_val(),),
-            cli_send.tls_cert_get(0.into()).await?,
+            (KeystoreIndex::test_val(), SignSr25519PubKey::test_val()),
+            cli_send.sign_sr25519_new_from_entropy().await?,
         );
         assert_eq!(
-            Cert::test_val(),
-            cli_send.tls_cert_get_cert_by_index(0.into()).await?,
+            (
+                KeystoreIndex::test_val(),
+                SignSr25519PubKey::test_val(),
+                bool::test_val(),
+            ),
+            cli_send.sign_sr25519_new_from_seed([0x42; 32]).await?,
         );
         assert_eq!(
-            Cert::test_val(),
+            SignSr25519PubKey::test_val(),
+            cli_send.sign_sr25519_get(0.into()).await?,
+        );
+        assert_eq!(
+            SignSr25519Signature::test_val(),
             cli_send
-                .tls_cert_get_cert_by_digest(CertDigest::test_val())
+                .sign_sr25519_sign_by_index(
+                    0.into(),
+                    Arc::new(vec![0x42; 8]),
+                    b"substrate".to_vec(),
+                )
                 .await?,
         );
+
         assert_eq!(
-            Cert::test_val(),
+            Attestation::test_val(),
             cli_send
-                .tls_cert_get_cert_by_sni(CertSni::test_val())
+                .lair_attest_entry(0.into(), TestVal::test_val())
                 .await?,
         );
         assert_eq!(
-            CertPrivKey::test_val(),
-            cli_send.tls_cert_get_priv_key_by_index(0.into()).await?,
+            ServerIdentityProof::test_val(),
+            cli_send
+                .lair_prove_server_identity(TestVal::test_val())
+                .await?,
         );
         assert_eq!(
-            CertPrivKey::test_val(),
+            <Arc<Vec<u8>>>::test_val(),
             cli_send
-                .tls_cert_get_priv_key_by_digest(CertDigest::test_val())
+                .lair_wrap_entry(0.into(), X25519PubKey::test_val())
                 .await?,
         );
         assert_eq!(
-            CertPrivKey::test_val(),
+            (
+                KeystoreIndex::test_val(),
+                LairEntryType::test_val(),
+                bool::test_val(),
+            ),
             cli_send
-                .tls_cert_get_priv_key_by_sni(CertSni::test_val())
+                .lair_unwrap_entry(0.into(), TestVal::test_val())
                 .await?,
         );
+
+        cli_send.ghost_actor_shutdown().await?;
+
+        // the server outlives its clients
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        let (cli_send, mut cli_recv) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        err_spawn("test-evt-loop-2", async move {
+            while let Some(msg) = cli_recv.next().await {
+                if let LairClientEvent::RequestUnlockPassphrase {
+                    respond,
+                    ..
+                } = msg
+                {
+                    respond.respond(Ok(async move { Ok(TestVal::test_val()) }
+                        .boxed()
+                        .into()));
+                }
+            }
+            Ok(())
+        });
         assert_eq!(
-            (KeystoreIndex::test_val(), SignEd25519PubKey::test_val(),),
-            cli_send.sign_ed25519_new_from_entropy().await?,
+            LairServerInfo::test_val(),
+            cli_send.lair_get_server_info().await?,
         );
+
+        cli_send.ghost_actor_shutdown().await?;
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_multiple_listeners() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let shared = tempfile::tempdir().unwrap();
+        let shared_path =
+            shared.path().canonicalize().unwrap().join("lair.sock");
+        let shared_url = LairConnectionUrl::new_unix_socket(&shared_path)?
+            .with_token("shared-token")?;
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .add_listener(shared_url.clone())
+            .build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        let (con_send, mut con_recv) = futures::channel::mpsc::unbounded();
+        err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                let _ = con_send.unbounded_send(());
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let cli_config = |url: LairConnectionUrl| {
+            Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_connection_url(url)
+                .build()
+        };
+
+        let (private_send, _private_evt) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        let (shared_send, _shared_evt) =
+            connect(ConnectOptions::from_config(&cli_config(shared_url)))
+                .await?;
+
+        // both listeners serve the same keystore, concurrently
+        let (private_res, shared_res) = futures::future::join(
+            private_send.sign_ed25519_new_from_entropy(),
+            shared_send.sign_ed25519_new_from_entropy(),
+        )
+        .await;
+        let (private_idx, private_pk) = private_res?;
+        let (shared_idx, shared_pk) = shared_res?;
+        assert_ne!(private_idx, shared_idx);
         assert_eq!(
-            SignEd25519PubKey::test_val(),
-            cli_send.sign_ed25519_get(0.into()).await?,
+            private_pk,
+            shared_send.sign_ed25519_get(private_idx).await?
         );
+        assert_eq!(shared_pk, private_send.sign_ed25519_get(shared_idx).await?);
+
+        // the shared listener serves nothing without its token
+        let (no_token_send, _no_token_evt) =
+            connect(ConnectOptions::from_config(&cli_config(
+                LairConnectionUrl::new_unix_socket(&shared_path)?,
+            )))
+            .await?;
+        assert!(matches!(
+            no_token_send.lair_get_server_info().await,
+            Err(LairError::Forbidden(_)),
+        ));
+        assert!(matches!(
+            connect(ConnectOptions::from_config(&cli_config(
+                LairConnectionUrl::new_unix_socket(&shared_path)?
+                    .with_token("wrong-token")?,
+            )))
+            .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        // and only authenticated connections ever got an api
+        tokio::time::delay_for(std::time::Duration::from_millis(50)).await;
+        let mut con_count = 0;
+        while let Ok(()) = con_recv.try_recv() {
+            con_count += 1;
+        }
+        assert_eq!(2, con_count);
+
+        drop(tmpdir);
+        drop(shared);
+
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_in_proc_capabilities() -> LairResult<()> {
+        use futures::future::FutureExt;
+
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let in_proc =
+            spawn_bind_server_ipc_per_connection(config, move |_evt_send| {
+                let api_sender = api_sender.clone();
+                async move { Ok(api_sender) }.boxed()
+            })
+            .await?;
+
+        // held to its grant, not to the capabilities
+        // of the connection url listener
+        let read_public =
+            LairCapabilities::NONE.with(LairCapability::ReadPublic);
+        let (cli, _evt) = in_proc.connect(read_public).await?;
+        assert_eq!(read_public, cli.lair_get_capabilities().await?);
+        assert!(matches!(
+            cli.sign_ed25519_new_from_entropy().await,
+            Err(LairError::Forbidden(_)),
+        ));
+        assert!(matches!(
+            cli.lair_request_elevation(
+                LairCapabilities::ALL,
+                std::time::Duration::from_secs(1),
+            )
+            .await,
+            Err(LairError::Forbidden(_)),
+        ));
+
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_capabilities() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().canonicalize().unwrap();
+        let private_url = LairConnectionUrl::new_unix_socket(root.join("p"))?;
+        let shared_url = LairConnectionUrl::new_unix_socket(root.join("s"))?
+            .with_token("admin-token")?;
+        let read_public =
+            LairCapabilities::NONE.with(LairCapability::ReadPublic);
+        let sign = LairCapabilities::NONE.with(LairCapability::Sign);
+        let config = Config::builder()
+            .set_root_path(&root)
+            .set_connection_url(private_url.clone())
+            .set_listener_capabilities(private_url, read_public)
+            .add_listener(shared_url.clone())
+            .add_connection_token("sign-token", sign)
+            .build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let connect_with = |token: &str| {
+            let url = LairConnectionUrl::new_unix_socket(root.join("s"))
+                .unwrap()
+                .with_token(token)
+                .unwrap();
+            connect(ConnectOptions::from_config(
+                &Config::builder()
+                    .set_root_path(&root)
+                    .set_connection_url(url)
+                    .build(),
+            ))
+        };
+
+        // the url token grants every capability, the private
+        // listener only its own, an unknown token nothing
+        let (admin_send, _admin_evt) = connect_with("admin-token").await?;
         assert_eq!(
-            SignEd25519Signature::test_val(),
-            cli_send
-                .sign_ed25519_sign_by_index(0.into(), b"".to_vec().into())
-                .await?,
+            LairCapabilities::ALL,
+            admin_send.lair_get_capabilities().await?
         );
+        let (private_send, _private_evt) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        assert_eq!(read_public, private_send.lair_get_capabilities().await?);
+        assert!(matches!(
+            connect_with("other-token").await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        let (cli, _evt) = connect_with("sign-token").await?;
+        assert_eq!(sign, cli.lair_get_capabilities().await?);
+
+        // each request is refused as forbidden, or not, by capability
+        macro_rules! check {
+            ($cap:expr, $call:expr) => {{
+                let cap: Option<LairCapability> = $cap;
+                let res = $call.await;
+                let forbidden = match &res {
+                    Err(LairError::Forbidden(msg)) => {
+                        assert_eq!(
+                            format!("missing capability: {}", cap.unwrap()),
+                            *msg
+                        );
+                        true
+                    }
+                    _ => false,
+                };
+                assert_eq!(
+                    cap.map_or(false, |cap| !sign.contains(cap)),
+                    forbidden,
+                    "{}",
+                    stringify!($call),
+                );
+            }};
+        }
+        use LairCapability::*;
+        let idx = KeystoreIndex::from(1);
+        let bytes = Arc::new(vec![0; 32]);
+        let pub_key = SignEd25519PubKey::from(vec![0; 32]);
+        let x_pub_key = X25519PubKey::from(vec![0; 32]);
+        let digest = CertDigest::from(vec![0; 32]);
+        let sni = CertSni::from("a.b".to_string());
+        let tag = "tag".to_string();
+
+        check!(None, cli.lair_get_server_info());
+        check!(None, cli.lair_prove_server_identity(bytes.clone()));
+        check!(None, cli.lair_get_capabilities());
+        check!(None, cli.lair_set_event_filter(EventKind::ALL.to_vec()));
+        check!(None, cli.lair_get_elevation());
+        check!(None, cli.lair_drop_elevation());
+        check!(Some(Admin), cli.lair_get_server_paths());
+        check!(Some(Admin), cli.lair_export_device_secret());
+        check!(None, cli.lair_select_store("default".to_string()));
+        check!(Some(ReadPublic), cli.lair_get_last_entry_index());
+        check!(Some(ReadPublic), cli.lair_get_last_entry_index_64());
+        check!(Some(ReadPublic), cli.lair_get_entry_count());
+        check!(Some(ReadPublic), cli.lair_get_store_stats());
+        check!(Some(ReadPublic), cli.lair_list_entries_page(idx, 1));
+        check!(Some(ReadPublic), cli.lair_get_entry_type(idx));
+        check!(Some(ReadPublic), cli.lair_get_entry_type_64(idx.into()));
+        check!(Some(ReadPublic), cli.lair_get_entry_types(vec![idx]));
+        check!(Some(ReadPublic), cli.lair_get_random_bytes(32));
+        check!(Some(ReadPublic), cli.lair_new_uuid());
+        check!(Some(Create), cli.lair_set_entry_tag(idx, Some(tag.clone())));
+        check!(
+            Some(Create),
+            cli.lair_set_entry_tags(vec![(idx, tag.clone())])
+        );
+        check!(Some(ReadPublic), cli.lair_get_entry_by_tag(tag.clone()));
+        check!(
+            Some(ReadPublic),
+            cli.lair_find_entries_by_prefix(bytes.clone(), PrefixKind::Any)
+        );
+        check!(Some(Create), cli.lair_add_entry_alias(idx, tag.clone()));
+        check!(Some(Create), cli.lair_remove_entry_alias(tag.clone()));
+        check!(Some(Admin), cli.lair_trash_entry(idx));
+        check!(Some(Admin), cli.lair_restore_entry(idx));
+        check!(Some(Admin), cli.lair_erase_entries(vec![idx]));
+        check!(Some(Admin), cli.lair_activate_entry(idx));
+        check!(
+            Some(Admin),
+            cli.lair_purge_trashed(std::time::Duration::from_secs(1))
+        );
+        check!(Some(ReadPublic), cli.lair_list_quarantined());
+        check!(Some(Admin), cli.lair_self_test());
+        check!(Some(Sign), cli.lair_attest_entry(idx, bytes.clone()));
+        check!(
+            Some(ExportPrivate),
+            cli.lair_wrap_entry(idx, x_pub_key.clone())
+        );
+        check!(Some(Create), cli.lair_unwrap_entry(idx, bytes.clone()));
+        check!(
+            Some(Create),
+            cli.tls_cert_new_self_signed_from_entropy(Default::default())
+        );
+        check!(
+            Some(Create),
+            cli.tls_cert_new_self_signed_from_sign_key(idx, Default::default())
+        );
+        check!(Some(ReadPublic), cli.tls_cert_get(idx));
+        check!(Some(ReadPublic), cli.tls_cert_get_info(idx));
+        check!(Some(ReadPublic), cli.tls_cert_get_cert_by_index(idx));
+        check!(
+            Some(ReadPublic),
+            cli.tls_cert_get_cert_by_digest(digest.clone())
+        );
+        check!(
+            Some(ReadPublic),
+            cli.tls_cert_get_digest(idx, DigestAlg::Sha256)
+        );
+        check!(
+            Some(ReadPublic),
+            cli.tls_cert_get_cert_by_typed_digest(
+                DigestAlg::Sha256,
+                bytes.clone()
+            )
+        );
+        check!(Some(ReadPublic), cli.tls_cert_get_cert_by_sni(sni.clone()));
+        check!(
+            Some(ReadPublic),
+            cli.tls_cert_resolve_by_digest(digest.clone())
+        );
+        check!(Some(ReadPublic), cli.tls_cert_resolve_by_sni(sni.clone()));
+        check!(Some(ExportPrivate), cli.tls_cert_get_priv_key_by_index(idx));
+        check!(
+            Some(ExportPrivate),
+            cli.tls_cert_get_priv_key_by_digest(digest.clone())
+        );
+        check!(
+            Some(ExportPrivate),
+            cli.tls_cert_get_priv_key_by_sni(sni.clone())
+        );
+        check!(
+            Some(ExportPrivate),
+            cli.tls_cert_get_bundle_by_tag(tag.clone())
+        );
+        check!(Some(Create), cli.sign_ed25519_new_from_entropy());
+        check!(Some(Create), cli.sign_ed25519_new(KeyBacking::Hardware));
+        check!(
+            Some(Create),
+            cli.sign_ed25519_new_from_entropy_with_options(
+                SignKeyOptions::default()
+            )
+        );
+        check!(
+            Some(Create),
+            cli.sign_ed25519_new_from_seed_with_options(
+                [1; 32],
+                SignKeyOptions::default()
+            )
+        );
+        check!(Some(ReadPublic), cli.sign_ed25519_get_options(idx));
+        check!(
+            Some(Create),
+            cli.sign_ed25519_rotate(idx, RotateKeyOptions::default())
+        );
+        check!(Some(ExportPrivate), cli.sign_ed25519_export_seed(idx));
+        check!(
+            Some(Create),
+            cli.sign_ed25519_import_ssh_key(bytes.clone(), None)
+        );
+        check!(Some(ReadPublic), cli.sign_ed25519_get(idx));
+        check!(
+            Some(ReadPublic),
+            cli.sign_ed25519_resolve_by_pub_key(pub_key.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_index(idx, bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_pub_key(pub_key.clone(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_index_v2(idx, bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_index_with_options(
+                idx,
+                bytes.clone(),
+                SignOptions::default(),
+            )
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_index_64(idx.into(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_pub_key_v2(pub_key.clone(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_sign_by_tag(tag.clone(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.sign_ed25519_ph_sign_by_index(idx, [0; 64], bytes.clone())
+        );
+        check!(Some(Create), cli.sign_secp256k1_new_from_entropy());
+        check!(Some(Create), cli.sign_secp256k1_new_from_seed([1; 32]));
+        check!(Some(ReadPublic), cli.sign_secp256k1_get(idx));
+        check!(Some(Sign), cli.sign_secp256k1_sign_by_index(idx, [1; 32]));
+        check!(Some(Create), cli.sign_ed448_new_from_entropy());
+        check!(Some(ReadPublic), cli.sign_ed448_get(idx));
+        check!(Some(Sign), cli.sign_ed448_sign_by_index(idx, bytes.clone()));
+        check!(
+            Some(Sign),
+            cli.sign_ed448_sign_by_pub_key(
+                SignEd448PubKey::test_val(),
+                bytes.clone()
+            )
+        );
+        check!(Some(Create), cli.sign_bls_new_from_entropy());
+        check!(Some(ReadPublic), cli.sign_bls_get(idx));
+        check!(
+            Some(Sign),
+            cli.sign_bls_sign_by_index(idx, bytes.clone(), Vec::new())
+        );
+        check!(Some(Create), cli.sign_sr25519_new_from_entropy());
+        check!(Some(Create), cli.sign_sr25519_new_from_seed([1; 32]));
+        check!(Some(ReadPublic), cli.sign_sr25519_get(idx));
+        check!(
+            Some(Sign),
+            cli.sign_sr25519_sign_by_index(idx, bytes.clone(), Vec::new())
+        );
+        check!(Some(Create), cli.x25519_new_from_entropy());
+        check!(Some(ReadPublic), cli.x25519_get(idx));
+        check!(
+            Some(Sign),
+            cli.crypto_box_to_sign_pub_key(idx, pub_key.clone(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.crypto_box_open_by_sign_pub_key(
+                pub_key.clone(),
+                x_pub_key.clone(),
+                bytes.clone()
+            )
+        );
+        check!(
+            Some(Sign),
+            cli.crypto_box_seal_open_by_index(idx, bytes.clone())
+        );
+        check!(Some(Sign), cli.age_decrypt_by_index(idx, Vec::new()));
+        check!(Some(Create), cli.pw_pepper_new_from_entropy());
+        check!(
+            Some(Sign),
+            cli.pw_hash(idx, bytes.clone(), PwHashOptions::default())
+        );
+        check!(Some(Sign), cli.pw_verify(idx, bytes.clone(), tag.clone()));
+        check!(Some(Create), cli.totp_new_from_entropy(Default::default()));
+        check!(
+            Some(Create),
+            cli.totp_import(tag.clone(), Default::default())
+        );
+        check!(Some(Sign), cli.totp_generate(idx, None));
+        check!(
+            Some(ExportPrivate),
+            cli.totp_export_provisioning_uri(idx, tag.clone(), tag.clone())
+        );
+        let handle = EphemeralHandle::from(1);
+        check!(Some(Sign), cli.ephemeral_x25519_new());
+        check!(Some(Sign), cli.ephemeral_sign_ed25519_new());
+        check!(
+            Some(Sign),
+            cli.ephemeral_sign_ed25519_sign(handle, bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.ephemeral_crypto_box(handle, x_pub_key.clone(), bytes.clone())
+        );
+        check!(
+            Some(Sign),
+            cli.ephemeral_crypto_box_open(
+                handle,
+                x_pub_key.clone(),
+                bytes.clone()
+            )
+        );
+        check!(Some(Sign), cli.ephemeral_drop(handle));
+
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_max_connections() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().canonicalize().unwrap();
+        let shared_url = LairConnectionUrl::new_unix_socket(root.join("s"))?
+            .with_token("shared-token")?;
+        let config = Config::builder()
+            .set_root_path(&root)
+            .add_listener(shared_url.clone())
+            .set_max_connections(2)
+            .build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let (cli1, _evt1) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        let (cli2, _evt2) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        cli1.lair_get_server_info().await?;
+        cli2.lair_get_server_info().await?;
+        let connections = cli1.lair_list_connections().await?;
+        assert_eq!(2, connections.len());
+        assert!(connections[0].connection_id < connections[1].connection_id);
+
+        // past the limit, refused whether the connection needs
+        // a handshake or not, and however often it retries
+        let (cli3, _evt3) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        for _ in 0..2 {
+            assert!(matches!(
+                cli3.lair_get_server_info().await,
+                Err(LairError::TooManyConnections),
+            ));
+        }
+        assert!(matches!(
+            connect(ConnectOptions::from_config(
+                &Config::builder()
+                    .set_root_path(&root)
+                    .set_connection_url(shared_url)
+                    .build(),
+            ))
+            .await,
+            Err(LairError::TooManyConnections),
+        ));
+
+        // while those served already are unaffected
+        cli1.lair_get_server_info().await?;
+        assert_eq!(2, cli2.lair_list_connections().await?.len());
+
+        drop(tmpdir);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_idle_connection_timeout() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_idle_connection_timeout(std::time::Duration::from_millis(300))
+            .build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let (parked, _parked_evt) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        let (busy, _busy_evt) =
+            connect(ConnectOptions::from_config(&config)).await?;
+        parked.lair_get_server_info().await?;
+
+        // the busy connection keeps going, the parked one sits idle
+        let mut parked_idle = std::time::Duration::default();
+        for _ in 0..6 {
+            tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+            let connections = busy.lair_list_connections().await?;
+            if connections.len() == 2 {
+                parked_idle = connections[0].idle;
+                assert!(connections[1].idle < parked_idle);
+            }
+        }
+        assert!(parked_idle >= std::time::Duration::from_millis(100));
+
+        let res = parked.lair_get_server_info().await;
+        assert!(matches!(res, Err(LairError::ConnectionIdle)), "{:?}", res);
+        busy.lair_get_server_info().await?;
+        assert_eq!(1, busy.lair_list_connections().await?.len());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_expected_server_identity() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().canonicalize().unwrap();
+        let config = Config::builder().set_root_path(&root).build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let id_pub_key = api_sender.lair_get_server_info().await?.id_pub_key;
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+        err_spawn("test-incoming-loop", async move {
+            let mut keep_em = Vec::new();
+            while let Some(evt_send) = incoming_recv.next().await {
+                keep_em.push(evt_send);
+            }
+            Ok(())
+        });
+
+        let expecting = |id_pub_key: SignEd25519PubKey, allow: bool| {
+            Config::builder()
+                .set_root_path(&root)
+                .set_expected_server_identity(id_pub_key)
+                .set_allow_unproven_server_identity(allow)
+                .build()
+        };
+
+        // the server proves the identity expected
+        let (cli, _cli_evt) = connect(ConnectOptions::from_config(&expecting(
+            id_pub_key.clone(),
+            false,
+        )))
+        .await?;
+        cli.lair_get_server_info().await?;
+        let nonce = Arc::new(vec![0x42; SERVER_IDENTITY_NONCE_LEN]);
+        let proof = cli.lair_prove_server_identity(nonce.clone()).await?;
+        assert!(proof.verify(&id_pub_key, &nonce));
         assert_eq!(
-            SignEd25519Signature::test_val(),
-            cli_send
-                .sign_ed25519_sign_by_pub_key(
-                    SignEd25519PubKey::test_val(),
-                    b"".to_vec().into()
-                )
-                .await?,
+            crate::internal::wire::spec::WIRE_PROTOCOL_VERSION,
+            proof.wire_protocol_version
         );
+        assert_eq!(crate::LAIR_VER, proof.server_version);
+
+        // but not another one
+        let other = SignEd25519PubKey::from(vec![0x42; 32]);
+        assert!(matches!(
+            connect(ConnectOptions::from_config(&expecting(
+                other.clone(),
+                true
+            )))
+            .await,
+            Err(LairError::ServerIdentityMismatch(_)),
+        ));
+
+        // a server predating identity proofs is refused, unless allowed
+        let legacy_root = root.join("legacy");
+        let legacy_config =
+            Config::builder().set_root_path(&legacy_root).build();
+        let (_legacy_kill, mut legacy_recv) =
+            crate::internal::ipc::spawn_bind_ipc(legacy_config).await?;
+        err_spawn("test-legacy-server", async move {
+            use crate::internal::ipc::IpcWireApi;
+            use crate::internal::wire::LairWire;
+            let mut keep_em = Vec::new();
+            while let Some((kill, _send, mut recv, _url)) =
+                legacy_recv.next().await
+            {
+                keep_em.push(kill);
+                err_spawn("test-legacy-con", async move {
+                    while let Some(IpcWireApi::Request {
+                        respond, msg, ..
+                    }) = recv.next().await
+                    {
+                        let res = match msg {
+                            LairWire::ToLairLairGetServerInfo { msg_id } => {
+                                let mut info = LairServerInfo::test_val();
+                                info.wire_protocol_version = 0;
+                                Ok(LairWire::ToCliLairGetServerInfoResponse {
+                                    msg_id,
+                                    info,
+                                })
+                            }
+                            _ => Err("unknown wire type".into()),
+                        };
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    Ok(())
+                });
+            }
+            Ok(())
+        });
+        let legacy = |allow: bool| {
+            Config::builder()
+                .set_root_path(&legacy_root)
+                .set_expected_server_identity(id_pub_key.clone())
+                .set_allow_unproven_server_identity(allow)
+                .build()
+        };
+        assert!(matches!(
+            connect(ConnectOptions::from_config(&legacy(false))).await,
+            Err(LairError::ServerIdentityMismatch(_)),
+        ));
+        let (cli, _cli_evt) =
+            connect(ConnectOptions::from_config(&legacy(true))).await?;
+        assert_eq!(0, cli.lair_get_server_info().await?.wire_protocol_version);
+        assert!(cli.lair_prove_server_identity(nonce).await.is_err());
 
-        cli_send.ghost_actor_shutdown().await?;
         drop(tmpdir);
 
         Ok(())
@@ -0,0 +1,132 @@
+//! The live audit feed of an ipc server.
+//!
+//! Every request a connection makes is recorded as a `LairAuditRecord`
+//! once answered, and broadcast to the connections granted
+//! `LairCapability::Audit` that subscribed to `EventKind::AuditEvent`.
+//! The feed is best effort: records a connection is too slow for are
+//! dropped, and counted in the next `LairClientEvent::AuditEvent`.
+
+use super::event_queue::*;
+use super::*;
+use crate::internal::ipc::*;
+use crate::internal::wire::*;
+use tokio::sync::broadcast;
+
+/// The records buffered for each audit connection, past which
+/// the oldest are dropped.
+pub(crate) const AUDIT_BACKLOG: usize = 1024;
+
+/// Records the requests of the connections of an ipc server.
+#[derive(Clone)]
+pub(crate) struct Auditor(broadcast::Sender<LairAuditRecord>);
+
+impl Auditor {
+    pub fn new() -> Self {
+        Self(broadcast::channel(AUDIT_BACKLOG).0)
+    }
+
+    /// Start the record of `msg`, a request of connection `connection_id`,
+    /// granted `capabilities`. None if no connection is listening.
+    pub fn begin(
+        &self,
+        msg: &LairWire,
+        connection_id: u64,
+        capabilities: LairCapabilities,
+    ) -> Option<PendingAudit> {
+        if self.0.receiver_count() == 0 {
+            return None;
+        }
+        let (keystore_index, pub_key) = msg.audit_entry();
+        Some(PendingAudit {
+            records: self.0.clone(),
+            record: LairAuditRecord {
+                operation: msg.audit_operation(),
+                keystore_index,
+                pub_key,
+                connection_id,
+                capabilities,
+                request_id: 0,
+                error: None,
+            },
+        })
+    }
+
+    /// Record `operation`, something connection `connection_id`,
+    /// granted `capabilities`, did without making a request.
+    pub fn record(
+        &self,
+        operation: &str,
+        connection_id: u64,
+        capabilities: LairCapabilities,
+    ) {
+        // no connection listening is not an error
+        let _ = self.0.send(LairAuditRecord {
+            operation: operation.to_string(),
+            keystore_index: None,
+            pub_key: None,
+            connection_id,
+            capabilities,
+            request_id: 0,
+            error: None,
+        });
+    }
+
+    /// Send the records to the connection `evt_send` raises events on,
+    /// while it is subscribed to them, until `kill_switch` is killed.
+    pub fn feed(
+        &self,
+        kill_switch: KillSwitch,
+        evt_send: LairClientEventSenderType,
+        events: Arc<EventQueue>,
+    ) -> impl std::future::Future<Output = LairResult<()>> + 'static + Send
+    {
+        let mut records = self.0.subscribe();
+        async move {
+            let mut dropped = 0;
+            loop {
+                let record = match kill_switch
+                    .mix(async { Ok(records.recv().await) })
+                    .await
+                {
+                    Ok(Ok(record)) => record,
+                    Ok(Err(broadcast::RecvError::Lagged(count))) => {
+                        dropped += count;
+                        continue;
+                    }
+                    Ok(Err(broadcast::RecvError::Closed)) | Err(_) => break,
+                };
+                if !events.accepts(EventKind::AuditEvent) {
+                    // not dropped, the client does not want them
+                    dropped = 0;
+                    continue;
+                }
+                // one at a time, so a slow client lags behind in the
+                // backlog, rather than crowding its other events out
+                match evt_send.audit_event(record, dropped).await {
+                    Ok(()) => dropped = 0,
+                    Err(_) => dropped += 1,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A record begun with `Auditor::begin`.
+pub(crate) struct PendingAudit {
+    records: broadcast::Sender<LairAuditRecord>,
+    record: LairAuditRecord,
+}
+
+impl PendingAudit {
+    /// Finish the record with the outcome of its request, and send it.
+    /// Called from the response future, where the id of the request
+    /// is known.
+    pub fn finish<T>(mut self, res: &LairResult<T>) {
+        self.record.request_id = current_request_id();
+        self.record.error =
+            res.as_ref().err().map(|e| error_to_wire(e, true).1);
+        // no connection listening anymore is not an error
+        let _ = self.records.send(self.record);
+    }
+}
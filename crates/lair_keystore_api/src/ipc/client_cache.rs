@@ -0,0 +1,532 @@
+use super::*;
+use futures::future::FutureExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Public material held by a `LairClientCache`.
+#[derive(Clone)]
+enum Cached {
+    SignEd25519(SignEd25519PubKey),
+    X25519(X25519PubKey),
+    TlsCert(CertSni, CertDigest),
+}
+
+#[derive(Default)]
+struct Entries {
+    // bumped when the cache is cleared, so reads started
+    // before cannot refill it
+    generation: u64,
+    by_idx: HashMap<KeystoreIndex, (std::time::Instant, Cached)>,
+}
+
+/// Counters of a `LairClientCache`, for debugging.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LairClientCacheStats {
+    /// Reads answered from the cache.
+    pub hits: u64,
+
+    /// Reads passed on to the server.
+    pub misses: u64,
+
+    /// Entries currently cached, some may have expired.
+    pub entries: usize,
+}
+
+/// A cache of public material in front of a client connection or pool,
+/// itself a `LairClientApiSender`. `sign_ed25519_get`, `x25519_get` and
+/// `tls_cert_get` are answered locally once cached, every other request
+/// is passed through. Private keys and signatures are never cached.
+///
+/// Entries never change, but may be trashed, purged or erased, possibly
+/// by other clients, so cached reads expire after a ttl. Trashing or
+/// erasing entries through the cache drops the affected reads, purging
+/// the trash or selecting a store drops them all. Either is done again
+/// once the server responds, so reads racing the request cannot refill
+/// the cache with what it removed. Built `with_events`, the cache also
+/// drops the reads of entries other clients remove, as the server
+/// reports them.
+#[derive(Clone)]
+pub struct LairClientCache<S>(Arc<CacheInner<S>>);
+
+struct CacheInner<S> {
+    api: S,
+    ttl: std::time::Duration,
+    entries: std::sync::Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<S> LairClientCache<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    /// Wrap `api`, prefetching the public material of every entry of
+    /// the selected store, kept for `ttl`.
+    pub async fn new(api: S, ttl: std::time::Duration) -> LairResult<Self> {
+        let cache = Self::build(api, ttl);
+        cache.prefetch().await?;
+        Ok(cache)
+    }
+
+    /// `new`, subscribing the connection to `EventKind::EntryRemoved`
+    /// (on top of `EventKind::DEFAULT`), so reads of entries trashed,
+    /// purged or erased by any client are dropped as soon as the server
+    /// reports them, rather than once they expire. `evt` is the event
+    /// receiver of the connection `api` sends on. The cache answers the
+    /// `entry_removed` events, every other event is passed on to the
+    /// returned receiver, to be answered there. A later
+    /// `lair_set_event_filter` must keep `EventKind::EntryRemoved`.
+    /// Should the server drop events, everything cached is dropped too.
+    pub async fn with_events(
+        api: S,
+        evt: LairClientEventReceiver,
+        ttl: std::time::Duration,
+    ) -> LairResult<(Self, LairClientEventReceiver)> {
+        let mut kinds = EventKind::DEFAULT.to_vec();
+        kinds.push(EventKind::EntryRemoved);
+        api.lair_set_event_filter(kinds).await?;
+
+        let cache = Self::build(api, ttl);
+        let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
+        spawn_evt_loop(Arc::downgrade(&cache.0), evt, evt_send);
+        // subscribed first, so no removal is missed in between
+        cache.prefetch().await?;
+        Ok((cache, evt_recv))
+    }
+
+    fn build(api: S, ttl: std::time::Duration) -> Self {
+        Self(Arc::new(CacheInner {
+            api,
+            ttl,
+            entries: std::sync::Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }))
+    }
+
+    /// Fetch the public material of every entry of the selected store,
+    /// replacing anything cached.
+    pub async fn prefetch(&self) -> LairResult<()> {
+        let inner = &self.0;
+        let generation = inner.clear();
+        let mut start = Some(KeystoreIndex::from(0));
+        while let Some(page_start) = start {
+            let (page, next_start) = inner
+                .api
+                .lair_list_entries_page(page_start, MAX_LIST_ENTRIES_PAGE_LIMIT)
+                .await?;
+            for info in page.into_iter().filter(|info| !info.trashed) {
+                let idx = info.keystore_index;
+                let cached = match info.entry_type {
+                    LairEntryType::SignEd25519 => {
+                        Cached::SignEd25519(info.pub_id.into())
+                    }
+                    LairEntryType::X25519 => Cached::X25519(info.pub_id.into()),
+                    LairEntryType::TlsCert => {
+                        let (sni, digest) = inner.api.tls_cert_get(idx).await?;
+                        Cached::TlsCert(sni, digest)
+                    }
+                    _ => continue,
+                };
+                inner.insert(generation, idx, cached);
+            }
+            start = next_start;
+        }
+        Ok(())
+    }
+
+    /// The cache counters.
+    pub fn stats(&self) -> LairClientCacheStats {
+        LairClientCacheStats {
+            hits: self.0.hits.load(Ordering::Relaxed),
+            misses: self.0.misses.load(Ordering::Relaxed),
+            entries: self.0.entries.lock().unwrap().by_idx.len(),
+        }
+    }
+}
+
+impl<S> CacheInner<S> {
+    /// Drop everything cached, returning the new generation.
+    fn clear(&self) -> u64 {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.by_idx.clear();
+        entries.generation
+    }
+
+    fn generation(&self) -> u64 {
+        self.entries.lock().unwrap().generation
+    }
+
+    fn insert(&self, generation: u64, idx: KeystoreIndex, cached: Cached) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation {
+            entries
+                .by_idx
+                .insert(idx, (std::time::Instant::now(), cached));
+        }
+    }
+
+    /// Drop the reads of `indices`, and keep reads started before
+    /// from refilling the cache.
+    fn invalidate(&self, indices: &[KeystoreIndex]) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        for idx in indices {
+            entries.by_idx.remove(idx);
+        }
+    }
+
+    /// Look up a fresh cached read, counting the hit or miss.
+    fn lookup<T>(
+        &self,
+        idx: KeystoreIndex,
+        f: impl FnOnce(&Cached) -> Option<T>,
+    ) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        let found = match entries.by_idx.get(&idx) {
+            Some((at, _)) if at.elapsed() >= self.ttl => {
+                entries.by_idx.remove(&idx);
+                None
+            }
+            Some((_, cached)) => f(cached),
+            None => None,
+        };
+        match found {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        found
+    }
+}
+
+/// Drop the reads of removed entries, and pass every other event on to
+/// `evt_send`. Holds the cache weakly, so it does not keep the
+/// connection open.
+fn spawn_evt_loop<S: 'static + Send + Sync>(
+    cache: std::sync::Weak<CacheInner<S>>,
+    mut evt: LairClientEventReceiver,
+    mut evt_send: LairClientEventSenderType,
+) {
+    use futures::{sink::SinkExt, stream::StreamExt};
+
+    tokio::task::spawn(async move {
+        while let Some(e) = evt.next().await {
+            let cache = cache.upgrade();
+            let e = match e {
+                // answered right away, the server sends no other
+                // event until it is
+                LairClientEvent::EntryRemoved {
+                    respond,
+                    keystore_index,
+                    ..
+                } => {
+                    if let Some(cache) = cache {
+                        cache.invalidate(&[keystore_index]);
+                    }
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                    continue;
+                }
+                // a removal may have been dropped with them
+                e @ LairClientEvent::EventsDropped { .. } => {
+                    if let Some(cache) = cache {
+                        cache.clear();
+                    }
+                    e
+                }
+                e => e,
+            };
+            // with the receiver gone, the events are dropped unanswered
+            let _ = evt_send.send(e).await;
+        }
+    });
+}
+
+fn sent() -> ghost_actor::GhostFuture<()> {
+    ghost_actor::dependencies::must_future::MustBoxFuture::new(
+        async move { Ok(()) },
+    )
+}
+
+impl<S> ghost_actor::GhostChannelSender<LairClientApi> for LairClientCache<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    fn ghost_actor_channel_send(
+        &self,
+        event: LairClientApi,
+    ) -> ghost_actor::GhostFuture<()> {
+        let inner = self.0.clone();
+        match event {
+            LairClientApi::SignEd25519Get {
+                respond,
+                keystore_index,
+                ..
+            } => {
+                let found = inner.lookup(keystore_index, |c| match c {
+                    Cached::SignEd25519(pub_key) => Some(pub_key.clone()),
+                    _ => None,
+                });
+                let generation = inner.generation();
+                respond.respond(Ok(async move {
+                    if let Some(pub_key) = found {
+                        return Ok(pub_key);
+                    }
+                    let pub_key =
+                        inner.api.sign_ed25519_get(keystore_index).await?;
+                    inner.insert(
+                        generation,
+                        keystore_index,
+                        Cached::SignEd25519(pub_key.clone()),
+                    );
+                    Ok(pub_key)
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::X25519Get {
+                respond,
+                keystore_index,
+                ..
+            } => {
+                let found = inner.lookup(keystore_index, |c| match c {
+                    Cached::X25519(pub_key) => Some(pub_key.clone()),
+                    _ => None,
+                });
+                let generation = inner.generation();
+                respond.respond(Ok(async move {
+                    if let Some(pub_key) = found {
+                        return Ok(pub_key);
+                    }
+                    let pub_key = inner.api.x25519_get(keystore_index).await?;
+                    inner.insert(
+                        generation,
+                        keystore_index,
+                        Cached::X25519(pub_key.clone()),
+                    );
+                    Ok(pub_key)
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::TlsCertGet {
+                respond,
+                keystore_index,
+                ..
+            } => {
+                let found = inner.lookup(keystore_index, |c| match c {
+                    Cached::TlsCert(sni, digest) => {
+                        Some((sni.clone(), digest.clone()))
+                    }
+                    _ => None,
+                });
+                let generation = inner.generation();
+                respond.respond(Ok(async move {
+                    if let Some(found) = found {
+                        return Ok(found);
+                    }
+                    let (sni, digest) =
+                        inner.api.tls_cert_get(keystore_index).await?;
+                    inner.insert(
+                        generation,
+                        keystore_index,
+                        Cached::TlsCert(sni.clone(), digest.clone()),
+                    );
+                    Ok((sni, digest))
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::LairTrashEntry {
+                respond,
+                keystore_index,
+                ..
+            } => {
+                inner.invalidate(&[keystore_index]);
+                respond.respond(Ok(async move {
+                    let res = inner.api.lair_trash_entry(keystore_index).await;
+                    inner.invalidate(&[keystore_index]);
+                    res
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::LairEraseEntries {
+                respond, indices, ..
+            } => {
+                inner.invalidate(&indices);
+                respond.respond(Ok(async move {
+                    let res =
+                        inner.api.lair_erase_entries(indices.clone()).await;
+                    inner.invalidate(&indices);
+                    res
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::LairPurgeTrashed {
+                respond,
+                older_than,
+                ..
+            } => {
+                inner.clear();
+                respond.respond(Ok(async move {
+                    let res = inner.api.lair_purge_trashed(older_than).await;
+                    inner.clear();
+                    res
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            LairClientApi::LairSelectStore { respond, name, .. } => {
+                inner.clear();
+                respond.respond(Ok(async move {
+                    let res = inner.api.lair_select_store(name).await;
+                    inner.clear();
+                    res
+                }
+                .boxed()
+                .into()));
+                sent()
+            }
+            event => inner.api.ghost_actor_channel_send(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Counts the requests reaching the test keystore.
+    #[derive(Clone)]
+    struct Counting(ghost_actor::GhostSender<LairClientApi>, Arc<AtomicUsize>);
+
+    impl ghost_actor::GhostChannelSender<LairClientApi> for Counting {
+        fn ghost_actor_channel_send(
+            &self,
+            event: LairClientApi,
+        ) -> ghost_actor::GhostFuture<()> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.ghost_actor_channel_send(event)
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_answers_public_reads_from_the_cache() -> LairResult<()> {
+        let (api, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let (old_sign, old_pub_key) =
+            api.sign_ed25519_new_from_entropy().await?;
+        let (old_x25519, old_x25519_pub_key) =
+            api.x25519_new_from_entropy().await?;
+        let (old_cert, old_sni, old_digest) = api
+            .tls_cert_new_self_signed_from_entropy(Default::default())
+            .await?;
+
+        let wire = Arc::new(AtomicUsize::new(0));
+        let cache = LairClientCache::new(
+            Counting(api, wire.clone()),
+            std::time::Duration::from_secs(60),
+        )
+        .await?;
+        assert_eq!(3, cache.stats().entries);
+
+        // prefetched
+        let before = wire.load(Ordering::SeqCst);
+        assert_eq!(old_pub_key, cache.sign_ed25519_get(old_sign).await?);
+        assert_eq!(old_x25519_pub_key, cache.x25519_get(old_x25519).await?);
+        assert_eq!((old_sni, old_digest), cache.tls_cert_get(old_cert).await?);
+        assert_eq!(before, wire.load(Ordering::SeqCst));
+
+        // fetched once, then cached
+        let (sign, pub_key) = cache.sign_ed25519_new_from_entropy().await?;
+        let before = wire.load(Ordering::SeqCst);
+        assert_eq!(pub_key, cache.sign_ed25519_get(sign).await?);
+        assert_eq!(before + 1, wire.load(Ordering::SeqCst));
+        assert_eq!(pub_key, cache.sign_ed25519_get(sign).await?);
+        assert_eq!(before + 1, wire.load(Ordering::SeqCst));
+
+        // never cached
+        let before = wire.load(Ordering::SeqCst);
+        cache
+            .sign_ed25519_sign_by_index(sign, Arc::new(vec![1]))
+            .await?;
+        cache
+            .sign_ed25519_sign_by_index(sign, Arc::new(vec![1]))
+            .await?;
+        assert_eq!(before + 2, wire.load(Ordering::SeqCst));
+
+        // the wrong type is not answered from the cache
+        assert!(cache.x25519_get(sign).await.is_err());
+
+        let stats = cache.stats();
+        assert_eq!(4, stats.hits);
+        assert_eq!(2, stats.misses);
+
+        // trashing drops the entry, the server refuses the read
+        cache.lair_trash_entry(sign).await?;
+        assert!(cache.sign_ed25519_get(sign).await.is_err());
+
+        // selecting a store drops everything
+        cache
+            .lair_select_store(DEFAULT_STORE_NAME.to_string())
+            .await?;
+        assert_eq!(0, cache.stats().entries);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_drops_erased_entries() -> LairResult<()> {
+        let (api, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let (sign, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (x25519, x25519_pub_key) = api.x25519_new_from_entropy().await?;
+
+        let cache =
+            LairClientCache::new(api, std::time::Duration::from_secs(60))
+                .await?;
+        assert_eq!(2, cache.stats().entries);
+
+        for res in cache.lair_erase_entries(vec![sign]).await? {
+            res?;
+        }
+        assert_eq!(1, cache.stats().entries);
+        assert!(cache.sign_ed25519_get(sign).await.is_err());
+        assert_eq!(x25519_pub_key, cache.x25519_get(x25519).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_expires_cached_reads() -> LairResult<()> {
+        let (api, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+        let (sign, _) = api.sign_ed25519_new_from_entropy().await?;
+
+        let wire = Arc::new(AtomicUsize::new(0));
+        let cache = LairClientCache::new(
+            Counting(api, wire.clone()),
+            std::time::Duration::from_millis(50),
+        )
+        .await?;
+
+        let before = wire.load(Ordering::SeqCst);
+        cache.sign_ed25519_get(sign).await?;
+        assert_eq!(before, wire.load(Ordering::SeqCst));
+
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        cache.sign_ed25519_get(sign).await?;
+        assert_eq!(before + 1, wire.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+}
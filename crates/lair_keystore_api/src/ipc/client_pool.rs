@@ -0,0 +1,423 @@
+use super::*;
+use futures::{future::FutureExt, stream::StreamExt};
+use ghost_actor::GhostControlSender;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+type PassphraseFuture = futures::future::Shared<
+    futures::future::BoxFuture<'static, Result<Passphrase, String>>,
+>;
+
+/// A pool of client connections to one lair server, itself a
+/// `LairClientApiSender`, see `spawn_client_pool`.
+#[derive(Clone)]
+pub struct LairClientPool(Arc<Inner>);
+
+struct Con {
+    sender: ghost_actor::GhostSender<LairClientApi>,
+    kill_switch: KillSwitch,
+}
+
+impl Con {
+    /// Whether the connection still serves requests. A connection the
+    /// server closed with a reason, e.g. as idle, keeps its sender
+    /// active, failing requests with it.
+    fn is_active(&self) -> bool {
+        self.sender.ghost_actor_is_active() && self.kill_switch.cont()
+    }
+}
+
+struct Inner {
+    config: Arc<Config>,
+    evt_send: LairClientEventSenderType,
+    cons: Vec<futures::lock::Mutex<Con>>,
+    next_con: AtomicUsize,
+    store_name: std::sync::Mutex<Option<String>>,
+    closed: AtomicBool,
+}
+
+impl LairClientPool {
+    /// The number of connections in the pool.
+    pub fn size(&self) -> usize {
+        self.0.cons.len()
+    }
+}
+
+impl Inner {
+    async fn connect(&self) -> LairResult<Con> {
+        let con = spawn_con(self.config.clone(), self.evt_send.clone()).await?;
+        let store_name = self.store_name.lock().unwrap().clone();
+        if let Some(store_name) = store_name {
+            con.sender.lair_select_store(store_name).await?;
+        }
+        Ok(con)
+    }
+
+    /// The next connection, round-robin,
+    /// replaced first if it has died.
+    async fn next_con(
+        &self,
+    ) -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
+        let idx = self.next_con.fetch_add(1, Ordering::Relaxed);
+        self.con(idx % self.cons.len()).await
+    }
+
+    /// The connection at `idx`, replaced first if it has died.
+    async fn con(
+        &self,
+        idx: usize,
+    ) -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
+        let mut con = self.cons[idx].lock().await;
+        if self.closed.load(Ordering::SeqCst) {
+            return Err("client pool closed".into());
+        }
+        if !con.is_active() {
+            *con = self.connect().await?;
+        }
+        Ok(con.sender.clone())
+    }
+
+    /// Store selection is per connection, select on all of them,
+    /// and on any replacements.
+    async fn select_store(&self, name: String) -> LairResult<()> {
+        *self.store_name.lock().unwrap() = Some(name.clone());
+        for con in self.cons.iter() {
+            let con = con.lock().await;
+            // dead connections select it when replaced
+            if con.is_active() {
+                con.sender.lair_select_store(name.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LairClientPool {
+    /// Close the pool for good, shutting down every connection.
+    fn close(&self, immediate: bool) -> ghost_actor::GhostFuture<()> {
+        let inner = self.0.clone();
+        inner.closed.store(true, Ordering::SeqCst);
+        ghost_actor::dependencies::must_future::MustBoxFuture::new(async move {
+            for con in inner.cons.iter() {
+                let con = con.lock().await;
+                let _ = match immediate {
+                    true => con.sender.ghost_actor_shutdown_immediate().await,
+                    false => con.sender.ghost_actor_shutdown().await,
+                };
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Shutting the pool down closes it for good: its connections are
+/// no longer replaced, and it refuses any further requests.
+impl ghost_actor::GhostControlSender<LairClientApi> for LairClientPool {
+    fn ghost_actor_shutdown(&self) -> ghost_actor::GhostFuture<()> {
+        self.close(false)
+    }
+
+    fn ghost_actor_shutdown_immediate(&self) -> ghost_actor::GhostFuture<()> {
+        self.close(true)
+    }
+
+    fn ghost_actor_is_active(&self) -> bool {
+        !self.0.closed.load(Ordering::SeqCst)
+    }
+}
+
+impl ghost_actor::GhostChannelSender<LairClientApi> for LairClientPool {
+    fn ghost_actor_channel_send(
+        &self,
+        event: LairClientApi,
+    ) -> ghost_actor::GhostFuture<()> {
+        let inner = self.0.clone();
+        if let LairClientApi::LairSelectStore { respond, name, .. } = event {
+            respond.respond(Ok(async move { inner.select_store(name).await }
+                .boxed()
+                .into()));
+            return ghost_actor::dependencies::must_future::MustBoxFuture::new(
+                async move { Ok(()) },
+            );
+        }
+        // ephemeral keys belong to the connection that created them,
+        // keep them all on the first one
+        let pinned = matches!(
+            event,
+            LairClientApi::EphemeralX25519New { .. }
+                | LairClientApi::EphemeralSignEd25519New { .. }
+                | LairClientApi::EphemeralSignEd25519Sign { .. }
+                | LairClientApi::EphemeralCryptoBox { .. }
+                | LairClientApi::EphemeralCryptoBoxOpen { .. }
+                | LairClientApi::EphemeralDrop { .. }
+        );
+        ghost_actor::dependencies::must_future::MustBoxFuture::new(async move {
+            let con = match pinned {
+                true => inner.con(0).await,
+                false => inner.next_con().await,
+            }
+            .map_err(ghost_actor::GhostError::other)?;
+            con.ghost_actor_channel_send(event).await
+        })
+    }
+}
+
+/// Spawn a pool of `size` client connections to the server at the
+/// connection url of `config`. Requests are spread round-robin over
+/// the connections, and a connection that has died is replaced before
+/// its next request. A request in flight on a connection when it dies
+/// fails, as it may or may not have been handled. `lair_select_store`
+/// selects the store on every connection. Ephemeral key requests all
+/// go to the first connection, which holds the keys.
+///
+/// The events of all connections arrive on the one returned receiver.
+/// Concurrent passphrase requests for the same store, such as every
+/// connection of a pool spawned against a locked server makes,
+/// are answered by a single `RequestUnlockPassphrase` event.
+pub async fn spawn_client_pool(
+    config: Arc<Config>,
+    size: usize,
+) -> LairResult<(LairClientPool, LairClientEventReceiver)> {
+    spawn_client_pool_with_event_buffer(config, size, 10).await
+}
+
+/// `spawn_client_pool`, buffering up to `event_buffer` events
+/// on the returned receiver.
+pub(crate) async fn spawn_client_pool_with_event_buffer(
+    config: Arc<Config>,
+    size: usize,
+    event_buffer: usize,
+) -> LairResult<(LairClientPool, LairClientEventReceiver)> {
+    if size == 0 {
+        return Err("a client pool needs at least one connection".into());
+    }
+
+    let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
+    let (pool_evt_send, pool_evt_recv) =
+        futures::channel::mpsc::channel(event_buffer);
+    spawn_pool_evt_loop(evt_recv, pool_evt_send);
+
+    let mut cons = Vec::with_capacity(size);
+    for _ in 0..size {
+        let con = spawn_con(config.clone(), evt_send.clone()).await?;
+        cons.push(futures::lock::Mutex::new(con));
+    }
+
+    Ok((
+        LairClientPool(Arc::new(Inner {
+            config,
+            evt_send,
+            cons,
+            next_con: AtomicUsize::new(0),
+            store_name: std::sync::Mutex::new(None),
+            closed: AtomicBool::new(false),
+        })),
+        pool_evt_recv,
+    ))
+}
+
+async fn spawn_con(
+    config: Arc<Config>,
+    evt_send: LairClientEventSenderType,
+) -> LairResult<Con> {
+    let (sender, kill_switch) =
+        spawn_client_ipc::spawn_client_ipc_with_kill_switch(config, evt_send)
+            .await?;
+    Ok(Con {
+        sender,
+        kill_switch,
+    })
+}
+
+fn spawn_pool_evt_loop(
+    mut evt_recv: LairClientEventReceiver,
+    pool_evt_send: LairClientEventSenderType,
+) {
+    use ghost_actor::GhostChannelSender;
+
+    // passphrase requests awaiting an answer, by store name
+    let pending: Arc<std::sync::Mutex<HashMap<String, PassphraseFuture>>> =
+        Arc::new(std::sync::Mutex::new(HashMap::new()));
+
+    err_spawn("client-pool-evt-loop", async move {
+        while let Some(evt) = evt_recv.next().await {
+            match evt {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond,
+                    store_name,
+                    ..
+                } => {
+                    let passphrase = pending
+                        .lock()
+                        .unwrap()
+                        .entry(store_name.clone())
+                        .or_insert_with(|| {
+                            let fut = pool_evt_send
+                                .request_unlock_passphrase(store_name.clone());
+                            let pending = pending.clone();
+                            async move {
+                                let res = fut.await.map_err(|e| e.to_string());
+                                pending.lock().unwrap().remove(&store_name);
+                                res
+                            }
+                            .boxed()
+                            .shared()
+                        })
+                        .clone();
+                    respond.respond(Ok(async move {
+                        passphrase.await.map_err(LairError::from)
+                    }
+                    .boxed()
+                    .into()));
+                }
+                // the application may not listen for the others
+                evt => {
+                    let _ = pool_evt_send.ghost_actor_channel_send(evt).await;
+                }
+            }
+        }
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::ipc::*;
+    use crate::internal::wire::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_spreads_requests_over_the_pool() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let (api_sender, _evt) =
+            crate::test::spawn_test_keystore(vec![], vec![]).await?;
+
+        // every connection asks for the passphrase, as a locked server does
+        let (answer_send, mut answer_recv) =
+            futures::channel::mpsc::unbounded();
+        let con_count = Arc::new(AtomicUsize::new(0));
+        let con_count2 = con_count.clone();
+        spawn_bind_server_ipc_per_connection(config.clone(), move |evt_send| {
+            con_count2.fetch_add(1, Ordering::SeqCst);
+            let answer_send = answer_send.clone();
+            tokio::task::spawn(async move {
+                let _ = answer_send.unbounded_send(
+                    evt_send
+                        .request_unlock_passphrase(
+                            DEFAULT_STORE_NAME.to_string(),
+                        )
+                        .await,
+                );
+                // hold the connection open
+                futures::future::pending::<()>().await;
+            });
+            let api_sender = api_sender.clone();
+            async move { Ok(api_sender) }.boxed()
+        })
+        .await?;
+
+        let (pool, mut evt_recv) = spawn_client_pool(config, 3).await?;
+        assert_eq!(3, pool.size());
+
+        let respond = match evt_recv.next().await.unwrap() {
+            LairClientEvent::RequestUnlockPassphrase {
+                respond,
+                store_name,
+                ..
+            } => {
+                assert_eq!(DEFAULT_STORE_NAME, store_name);
+                respond
+            }
+            evt => panic!("unexpected: {:?}", evt),
+        };
+        // let every connection's request reach the pool
+        while con_count.load(Ordering::SeqCst) < 3 {
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        respond
+            .respond(Ok(async move { Ok("passphrase".into()) }.boxed().into()));
+
+        // one prompt answered all three
+        for _ in 0..3 {
+            assert_eq!(
+                b"passphrase",
+                answer_recv.next().await.unwrap()?.read()
+            );
+        }
+        assert!(evt_recv.try_recv().is_err());
+
+        let news = futures::future::try_join_all(
+            (0..30).map(|_| pool.sign_ed25519_new_from_entropy()),
+        )
+        .await?;
+        let mut idxs = news.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
+        idxs.sort_unstable();
+        idxs.dedup();
+        assert_eq!(30, idxs.len());
+
+        // selection reaches every connection
+        pool.lair_select_store(DEFAULT_STORE_NAME.to_string())
+            .await?;
+        assert!(pool.lair_select_store("other".to_string()).await.is_err());
+
+        assert_eq!(3, con_count.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_replaces_a_connection_dying_mid_request() -> LairResult<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        // the first connection dies on its first request,
+        // the rest answer server info
+        let (_srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+        let con_count = Arc::new(AtomicUsize::new(0));
+        let con_count2 = con_count.clone();
+        err_spawn("test-dying-loop", async move {
+            while let Some((kill, _send, mut recv, _)) = srv_recv.next().await {
+                let doomed = con_count2.fetch_add(1, Ordering::SeqCst) == 0;
+                tokio::task::spawn(async move {
+                    while let Some(IpcWireApi::Request {
+                        respond, msg, ..
+                    }) = recv.next().await
+                    {
+                        if doomed {
+                            drop(kill);
+                            return;
+                        }
+                        let msg_id = msg.get_msg_id();
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToCliLairGetServerInfoResponse {
+                                msg_id,
+                                info: LairServerInfo::default(),
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                });
+            }
+            Ok(())
+        });
+
+        let (pool, _evt_recv) = spawn_client_pool(config, 2).await?;
+
+        // round-robin, the first request lands on the doomed connection
+        assert!(pool.lair_get_server_info().await.is_err());
+        pool.lair_get_server_info().await?;
+
+        // let the death be noticed
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        for _ in 0..4 {
+            pool.lair_get_server_info().await?;
+        }
+        assert_eq!(3, con_count.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use super::*;
+
+/// What a client does once its connection closes, see
+/// `ConnectOptions::reconnect`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReconnectPolicy {
+    /// Requests fail once the connection closes.
+    #[default]
+    Never,
+
+    /// A closed connection is replaced before the next request,
+    /// authenticating, checking the server identity, and selecting the
+    /// store again. A request in flight when it closes fails, as it may
+    /// or may not have been handled. Ephemeral keys do not survive it.
+    OnNextRequest,
+}
+
+/// How to connect a client, see `connect`.
+///
+/// ```no_run
+/// # async fn doc() -> lair_keystore_api::LairResult<()> {
+/// use lair_keystore_api::ipc::*;
+/// use lair_keystore_api::LairConnectionUrl;
+///
+/// let url = LairConnectionUrl::new_unix_socket("/run/lair/socket")?;
+/// let (api_send, evt_recv) = connect(
+///     ConnectOptions::new(url)
+///         .auth_token("token")
+///         .timeout(std::time::Duration::from_secs(30))
+///         .reconnect(ReconnectPolicy::OnNextRequest),
+/// )
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ConnectOptions {
+    url: LairConnectionUrl,
+    config: ConfigBuilder,
+    auth_token: Option<String>,
+    event_buffer: usize,
+    reconnect: ReconnectPolicy,
+}
+
+impl ConnectOptions {
+    /// Connect to the server at `url`, presenting the token it carries,
+    /// if any.
+    pub fn new(url: LairConnectionUrl) -> Self {
+        Self::from_builder(url, Config::builder())
+    }
+
+    /// Connect as `config` describes: to its connection url, with its
+    /// request timeout, expected server identity and wire compression.
+    pub fn from_config(config: &Config) -> Self {
+        Self::from_builder(
+            config.get_connection_url().clone(),
+            ConfigBuilder::from_config(config),
+        )
+    }
+
+    fn from_builder(url: LairConnectionUrl, config: ConfigBuilder) -> Self {
+        Self {
+            url,
+            config,
+            auth_token: None,
+            event_buffer: 10,
+            reconnect: ReconnectPolicy::default(),
+        }
+    }
+
+    /// Fail requests with `LairError::Timeout` once `timeout` has passed,
+    /// see `ConfigBuilder::set_request_timeout`. Requests wait for as long
+    /// as the server takes by default.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.config = self.config.set_request_timeout(timeout);
+        self
+    }
+
+    /// Present `token` as the connection is made, in place of the token
+    /// of the url. An invalid token fails `connect` with
+    /// `LairError::InvalidConnectionUrl`.
+    pub fn auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Buffer up to `event_buffer` events the application has not yet
+    /// received from the returned receiver. Defaults to `10`.
+    pub fn event_buffer(mut self, event_buffer: usize) -> Self {
+        self.event_buffer = event_buffer;
+        self
+    }
+
+    /// Require the server to prove it holds the identity keypair of
+    /// `id_pub_key`, see `ConfigBuilder::set_expected_server_identity`.
+    /// Servers are not checked by default.
+    pub fn expected_server_identity(
+        mut self,
+        id_pub_key: SignEd25519PubKey,
+    ) -> Self {
+        self.config = self.config.set_expected_server_identity(id_pub_key);
+        self
+    }
+
+    /// What to do once the connection closes.
+    /// Defaults to `ReconnectPolicy::Never`.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+}
+
+/// A connected client, see `connect`. Call the api with
+/// `LairClientApiSender`. Connections stay open until shut down with
+/// `GhostControlSender::ghost_actor_shutdown`, or the server closes them.
+/// Converts from a `ghost_actor::GhostSender<LairClientApi>`, or a
+/// `LairClientPool`, for the apis taking one.
+#[derive(Clone)]
+pub struct LairClientSender(ClientSender);
+
+#[derive(Clone)]
+enum ClientSender {
+    Connection(ghost_actor::GhostSender<LairClientApi>),
+    Pool(LairClientPool),
+}
+
+impl From<ghost_actor::GhostSender<LairClientApi>> for LairClientSender {
+    fn from(con: ghost_actor::GhostSender<LairClientApi>) -> Self {
+        Self(ClientSender::Connection(con))
+    }
+}
+
+impl From<LairClientPool> for LairClientSender {
+    fn from(pool: LairClientPool) -> Self {
+        Self(ClientSender::Pool(pool))
+    }
+}
+
+impl ghost_actor::GhostChannelSender<LairClientApi> for LairClientSender {
+    fn ghost_actor_channel_send(
+        &self,
+        event: LairClientApi,
+    ) -> ghost_actor::GhostFuture<()> {
+        match &self.0 {
+            ClientSender::Connection(con) => {
+                con.ghost_actor_channel_send(event)
+            }
+            ClientSender::Pool(pool) => pool.ghost_actor_channel_send(event),
+        }
+    }
+}
+
+impl ghost_actor::GhostControlSender<LairClientApi> for LairClientSender {
+    fn ghost_actor_shutdown(&self) -> ghost_actor::GhostFuture<()> {
+        match &self.0 {
+            ClientSender::Connection(con) => con.ghost_actor_shutdown(),
+            ClientSender::Pool(pool) => pool.ghost_actor_shutdown(),
+        }
+    }
+
+    fn ghost_actor_shutdown_immediate(&self) -> ghost_actor::GhostFuture<()> {
+        match &self.0 {
+            ClientSender::Connection(con) => {
+                con.ghost_actor_shutdown_immediate()
+            }
+            ClientSender::Pool(pool) => pool.ghost_actor_shutdown_immediate(),
+        }
+    }
+
+    fn ghost_actor_is_active(&self) -> bool {
+        match &self.0 {
+            ClientSender::Connection(con) => con.ghost_actor_is_active(),
+            ClientSender::Pool(pool) => pool.ghost_actor_is_active(),
+        }
+    }
+}
+
+/// Connect a client as `options` describe.
+/// Handlers of the events received may call the api before answering,
+/// as long as they do not hold up the receiver meanwhile.
+pub async fn connect(
+    options: ConnectOptions,
+) -> LairResult<(LairClientSender, LairClientEventReceiver)> {
+    let ConnectOptions {
+        mut url,
+        config,
+        auth_token,
+        event_buffer,
+        reconnect,
+    } = options;
+    if let Some(token) = auth_token {
+        url = url.with_token(token)?;
+    }
+    let config = config.set_connection_url(url).build_client();
+
+    match reconnect {
+        ReconnectPolicy::Never => {
+            let (evt_send, evt_recv) =
+                futures::channel::mpsc::channel(event_buffer);
+            let api_send =
+                spawn_client_ipc::spawn_client_ipc(config, evt_send).await?;
+            Ok((api_send.into(), evt_recv))
+        }
+        // a pool of one replaces its connection once it dies
+        ReconnectPolicy::OnNextRequest => {
+            let (pool, evt_recv) =
+                spawn_client_pool_with_event_buffer(config, 1, event_buffer)
+                    .await?;
+            Ok((pool.into(), evt_recv))
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! The connections an ipc server is serving.
+//!
+//! Every accepted connection registers here, refused once
+//! `Config::get_max_connections` are registered, and records its
+//! traffic so those idle for `Config::get_idle_connection_timeout`
+//! can be closed.
+
+use super::*;
+use crate::internal::ipc::*;
+use crate::internal::wire::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// How long a server waits for a client to acknowledge a
+/// `ToCliGoodbye` before closing the connection anyway.
+pub(crate) const GOODBYE_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(1);
+
+/// The traffic of a single connection.
+pub(crate) struct Activity {
+    last: std::sync::Mutex<std::time::Instant>,
+    in_flight: AtomicU32,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            last: std::sync::Mutex::new(std::time::Instant::now()),
+            in_flight: AtomicU32::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, std::time::Instant> {
+        // an instant is never left half written
+        self.last.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record traffic on the connection.
+    pub fn touch(&self) {
+        *self.lock() = std::time::Instant::now();
+    }
+
+    /// Record a request arriving, and, once the returned
+    /// guard is dropped, its response leaving.
+    pub fn start_request(self: &Arc<Self>) -> RequestGuard {
+        self.touch();
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        RequestGuard(self.clone())
+    }
+
+    /// The time since the last traffic.
+    pub fn idle(&self) -> std::time::Duration {
+        self.lock().elapsed()
+    }
+
+    /// The number of requests still being handled.
+    pub fn in_flight(&self) -> u32 {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+}
+
+/// A request being handled, see `Activity::start_request`.
+pub(crate) struct RequestGuard(Arc<Activity>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.0.touch();
+    }
+}
+
+/// The connections of an ipc server.
+pub(crate) struct Connections {
+    next_id: AtomicU64,
+    inner: std::sync::Mutex<HashMap<u64, Arc<Activity>>>,
+}
+
+impl Connections {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            next_id: AtomicU64::new(1),
+            inner: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<Activity>>> {
+        // only inserted / removed, never held across a panic point
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register a new connection, until the returned registration is
+    /// dropped. None if the server is serving `max` connections already.
+    pub fn register(
+        self: &Arc<Self>,
+        max: Option<usize>,
+    ) -> Option<Registration> {
+        let mut inner = self.lock();
+        if max.is_some_and(|max| inner.len() >= max) {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let activity = Arc::new(Activity::new());
+        inner.insert(id, activity.clone());
+        Some(Registration {
+            id,
+            activity,
+            connections: self.clone(),
+            _tracked: internal::debug_stats::Tracked::new(
+                internal::debug_stats::Counter::ServerConnection,
+            ),
+        })
+    }
+
+    /// The connections being served, oldest first.
+    pub fn list(&self) -> Vec<LairConnectionInfo> {
+        let mut list = self
+            .lock()
+            .iter()
+            .map(|(id, activity)| LairConnectionInfo {
+                connection_id: *id,
+                // to the millisecond, as it travels on the wire
+                idle: std::time::Duration::from_millis(
+                    activity.idle().as_millis() as u64,
+                ),
+                in_flight: activity.in_flight(),
+            })
+            .collect::<Vec<_>>();
+        list.sort_by_key(|con| con.connection_id);
+        list
+    }
+}
+
+/// A connection registered with `Connections::register`.
+pub(crate) struct Registration {
+    id: u64,
+    activity: Arc<Activity>,
+    connections: Arc<Connections>,
+    _tracked: internal::debug_stats::Tracked,
+}
+
+impl Registration {
+    /// The `LairConnectionInfo::connection_id` of the connection.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn activity(&self) -> &Arc<Activity> {
+        &self.activity
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.connections.lock().remove(&self.id);
+    }
+}
+
+/// Tell a client its connection is closing, and why, waiting at most
+/// `GOODBYE_TIMEOUT` for it to acknowledge. Its requests still awaiting
+/// a response, and any it makes after, fail with `reason`.
+pub(crate) async fn say_goodbye(ipc_send: &IpcSender, reason: LairError) {
+    // goodbye reasons never name paths
+    let (kind, message) = error_to_wire(&reason, true);
+    let _ = tokio::time::timeout(
+        GOODBYE_TIMEOUT,
+        ipc_send.request(LairWire::ToCliGoodbye {
+            msg_id: next_msg_id(),
+            kind,
+            message,
+        }),
+    )
+    .await;
+}
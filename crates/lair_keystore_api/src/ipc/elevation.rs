@@ -0,0 +1,219 @@
+//! Capabilities a connection holds on top of its own, for a time,
+//! see `LairClientApi::lair_request_elevation`.
+//!
+//! Expiry is read off the monotonic clock of the configured `Clock`,
+//! so setting the wall clock neither extends nor cuts an elevation.
+
+use super::*;
+use crate::internal::clock::ClockHandle;
+use std::time::Duration;
+
+/// The capabilities elevation never grants: they decide the events
+/// a connection is sent, set up once it authenticates.
+const NEVER_ELEVATED: LairCapabilities = LairCapabilities(
+    LairCapability::CanUnlock as u32 | LairCapability::Audit as u32,
+);
+
+/// The elevation of a single connection.
+pub(crate) struct Elevation {
+    clock: ClockHandle,
+    options: Option<ElevationOptions>,
+    listener_capabilities: LairCapabilities,
+    // the capabilities held, and the monotonic time they expire at
+    held: Option<(LairCapabilities, Duration)>,
+}
+
+impl Elevation {
+    /// No elevation, for a connection to a listener
+    /// granting `listener_capabilities`.
+    pub fn new(
+        config: &Config,
+        listener_capabilities: LairCapabilities,
+    ) -> Self {
+        Self {
+            clock: config.get_clock().clone(),
+            options: config.get_elevation(),
+            listener_capabilities,
+            held: None,
+        }
+    }
+
+    /// The capabilities held, none once they expired.
+    pub fn capabilities(&self) -> LairCapabilities {
+        self.get()
+            .map(|elevation| elevation.capabilities)
+            .unwrap_or(LairCapabilities::NONE)
+    }
+
+    /// The elevation held, None if none, or if it expired.
+    pub fn get(&self) -> Option<LairElevation> {
+        let (capabilities, expires_at) = self.held?;
+        let remaining = expires_at.checked_sub(self.clock.monotonic())?;
+        if remaining == Duration::from_secs(0) {
+            return None;
+        }
+        Some(LairElevation {
+            capabilities,
+            remaining,
+        })
+    }
+
+    /// Forget an elevation that expired, returning the capabilities
+    /// it held, so its expiry is audited once.
+    pub fn take_expired(&mut self) -> Option<LairCapabilities> {
+        match self.held {
+            Some((capabilities, _)) if self.get().is_none() => {
+                self.held = None;
+                Some(capabilities)
+            }
+            _ => None,
+        }
+    }
+
+    /// Grant `capabilities` for `duration`, to a connection granted
+    /// `granted`, replacing any elevation held. Only those it lacks are
+    /// held, each must be allowed by the `ElevationOptions`.
+    pub fn request(
+        &mut self,
+        granted: LairCapabilities,
+        capabilities: LairCapabilities,
+        duration: Duration,
+    ) -> LairResult<LairElevation> {
+        let options = self.options.ok_or_else(|| {
+            LairError::Forbidden(
+                "this server does not grant elevation".to_string(),
+            )
+        })?;
+        let allowed = LairCapabilities(
+            options.capabilities.intersect(self.listener_capabilities).0
+                & !NEVER_ELEVATED.0,
+        );
+        let capabilities = capabilities.intersect(LairCapabilities::ALL);
+        if capabilities == LairCapabilities::NONE {
+            return Err("elevation names no capabilities".into());
+        }
+        let elevated = LairCapabilities(capabilities.0 & !granted.0);
+        if let Some(cap) = elevated.iter().find(|cap| !allowed.contains(*cap)) {
+            return Err(LairError::Forbidden(format!(
+                "capability may not be elevated: {}",
+                cap
+            )));
+        }
+        if duration == Duration::from_secs(0) || duration > options.max_duration
+        {
+            return Err(LairError::Forbidden(format!(
+                "elevation lasts from 1 to {} ms, not {} ms",
+                options.max_duration.as_millis(),
+                duration.as_millis(),
+            )));
+        }
+        self.held = Some((elevated, self.clock.monotonic() + duration));
+        Ok(LairElevation {
+            capabilities: elevated,
+            remaining: duration,
+        })
+    }
+
+    /// Drop the elevation held, if any.
+    pub fn drop_elevation(&mut self) {
+        self.held = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::clock::ManualClock;
+
+    fn elevation(clock: &Arc<ManualClock>) -> Elevation {
+        let config = Config::builder()
+            .set_clock(clock.handle())
+            .set_elevation(ElevationOptions {
+                capabilities: LairCapabilities::ALL,
+                max_duration: Duration::from_secs(60),
+            })
+            .build();
+        // a listener not granting admin
+        Elevation::new(
+            &config,
+            LairCapabilities::ALL
+                .intersect(LairCapabilities(!(LairCapability::Admin as u32))),
+        )
+    }
+
+    #[test]
+    fn elevation_is_bounded_by_policy_and_time() {
+        let clock = ManualClock::new(1_600_000_000);
+        let mut elevation = elevation(&clock);
+        let read_public =
+            LairCapabilities::NONE.with(LairCapability::ReadPublic);
+        let export = LairCapabilities::NONE.with(LairCapability::ExportPrivate);
+
+        // only what the connection lacks is held
+        let granted = elevation
+            .request(
+                read_public,
+                read_public.with(LairCapability::ExportPrivate),
+                Duration::from_secs(30),
+            )
+            .unwrap();
+        assert_eq!(export, granted.capabilities);
+        assert_eq!(export, elevation.capabilities());
+
+        // the listener, and the events capabilities, bound it
+        for cap in &[
+            LairCapability::Admin,
+            LairCapability::CanUnlock,
+            LairCapability::Audit,
+        ] {
+            assert!(matches!(
+                elevation.request(
+                    read_public,
+                    LairCapabilities::NONE.with(*cap),
+                    Duration::from_secs(1)
+                ),
+                Err(LairError::Forbidden(_)),
+            ));
+        }
+        // a refused request leaves the elevation held
+        assert_eq!(export, elevation.capabilities());
+        assert!(elevation
+            .request(read_public, export, Duration::from_secs(61))
+            .is_err());
+        assert!(elevation
+            .request(read_public, export, Duration::from_secs(0))
+            .is_err());
+        assert!(elevation
+            .request(
+                read_public,
+                LairCapabilities::NONE,
+                Duration::from_secs(1)
+            )
+            .is_err());
+
+        // the wall clock does not matter
+        clock.set_wall(0);
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(Duration::from_secs(1), elevation.get().unwrap().remaining);
+        assert_eq!(None, elevation.take_expired());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(LairCapabilities::NONE, elevation.capabilities());
+        assert_eq!(None, elevation.get());
+        assert_eq!(Some(export), elevation.take_expired());
+        assert_eq!(None, elevation.take_expired());
+    }
+
+    #[test]
+    fn no_elevation_by_default() {
+        let mut elevation =
+            Elevation::new(&Config::builder().build(), LairCapabilities::ALL);
+        assert!(matches!(
+            elevation.request(
+                LairCapabilities::NONE,
+                LairCapabilities::ALL,
+                Duration::from_secs(1)
+            ),
+            Err(LairError::Forbidden(_)),
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+//! Per-connection server event buffering.
+//!
+//! Events are taken off the server's event channel as soon as they are
+//! sent, so a client that stops reading them cannot stall whoever raised
+//! them. Once `Config::get_event_buffer_size` events are waiting, the
+//! `Config::get_slow_consumer_policy` decides what gives.
+//!
+//! Events of a kind the client filtered out (`lair_set_event_filter`)
+//! are never buffered.
+
+use super::*;
+use std::collections::VecDeque;
+
+/// An event taken off an `EventQueue`.
+pub(crate) enum QueuedEvent {
+    /// `count` events were dropped since the last one was taken.
+    Dropped(u64),
+
+    /// The next buffered event.
+    Event(LairClientEvent),
+}
+
+struct Inner {
+    queue: VecDeque<LairClientEvent>,
+    dropped: u64,
+    closed: bool,
+    filter: u32,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            dropped: 0,
+            closed: false,
+            filter: kind_mask(EventKind::DEFAULT),
+        }
+    }
+}
+
+/// One bit per `EventKind`.
+fn kind_mask(kinds: &[EventKind]) -> u32 {
+    kinds
+        .iter()
+        .fold(0, |mask, kind| mask | 1 << (*kind as u32))
+}
+
+impl Inner {
+    fn accepts(&self, kind: EventKind) -> bool {
+        self.filter & 1 << (kind as u32) != 0
+    }
+}
+
+/// A bounded event buffer, applying a `SlowConsumerPolicy` when full.
+pub(crate) struct EventQueue {
+    capacity: usize,
+    policy: SlowConsumerPolicy,
+    inner: std::sync::Mutex<Inner>,
+    notify: tokio::sync::Notify,
+    _tracked: internal::debug_stats::Tracked,
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize, policy: SlowConsumerPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy,
+            inner: std::sync::Mutex::new(Inner::default()),
+            notify: tokio::sync::Notify::new(),
+            _tracked: internal::debug_stats::Tracked::new(
+                internal::debug_stats::Counter::EventQueue,
+            ),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // events are only moved around under the lock, never inspected,
+        // so a poisoned queue is still consistent
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Buffer `evt`. Under `SlowConsumerPolicy::Disconnect`, errors if
+    /// the buffer is full, after failing `evt` and every buffered event -
+    /// the caller should then disconnect the client.
+    pub fn push(&self, evt: LairClientEvent) -> LairResult<()> {
+        let mut inner = self.lock();
+        if inner.closed {
+            drop(inner);
+            evt.respond_error("connection closed".into());
+            return Err("connection closed".into());
+        }
+
+        if !inner.accepts(EventKind::of(&evt)) {
+            drop(inner);
+            evt.respond_error("event kind filtered out by the client".into());
+            return Ok(());
+        }
+
+        if inner.queue.len() < self.capacity {
+            inner.queue.push_back(evt);
+            drop(inner);
+            self.notify.notify();
+            return Ok(());
+        }
+
+        match self.policy {
+            SlowConsumerPolicy::DropOldest => {
+                if let Some(idx) = inner.queue.iter().position(is_droppable) {
+                    let old = inner.queue.remove(idx);
+                    inner.queue.push_back(evt);
+                    inner.dropped += 1;
+                    drop(inner);
+                    if let Some(old) = old {
+                        old.respond_error(
+                            "event dropped, client too slow".into(),
+                        );
+                    }
+                } else if is_droppable(&evt) {
+                    inner.dropped += 1;
+                    drop(inner);
+                    evt.respond_error("event dropped, client too slow".into());
+                } else {
+                    // only critical events are waiting,
+                    // fail this one rather than lose any of them
+                    drop(inner);
+                    evt.respond_error(
+                        "event buffer full, client too slow".into(),
+                    );
+                }
+                Ok(())
+            }
+            SlowConsumerPolicy::Disconnect => {
+                inner.closed = true;
+                let queue = std::mem::take(&mut inner.queue);
+                drop(inner);
+                self.notify.notify();
+                for old in queue {
+                    old.respond_error("client too slow, disconnected".into());
+                }
+                evt.respond_error("client too slow, disconnected".into());
+                Err("event buffer full, disconnecting slow client".into())
+            }
+        }
+    }
+
+    /// Only buffer events of the given `kinds` from now on. Buffered
+    /// events of other kinds are failed, as are drop counts if
+    /// `EventKind::EventsDropped` is filtered out.
+    pub fn set_filter(&self, kinds: &[EventKind]) {
+        let mut inner = self.lock();
+        inner.filter = kind_mask(kinds);
+        let (keep, filtered): (VecDeque<_>, VecDeque<_>) =
+            std::mem::take(&mut inner.queue)
+                .into_iter()
+                .partition(|evt| inner.accepts(EventKind::of(evt)));
+        inner.queue = keep;
+        if !inner.accepts(EventKind::EventsDropped) {
+            inner.dropped = 0;
+        }
+        drop(inner);
+        for evt in filtered {
+            evt.respond_error("event kind filtered out by the client".into());
+        }
+    }
+
+    /// Would an event of `kind` be buffered, rather than filtered out?
+    pub fn accepts(&self, kind: EventKind) -> bool {
+        self.lock().accepts(kind)
+    }
+
+    /// No more events will be pushed.
+    /// Those already buffered can still be taken.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.notify.notify();
+    }
+
+    /// Take the next event, waiting for one if none are buffered.
+    /// Any drops are reported ahead of the event following them.
+    /// Resolves None once closed, and drained.
+    pub async fn next(&self) -> Option<QueuedEvent> {
+        loop {
+            {
+                let mut inner = self.lock();
+                if inner.dropped > 0 && inner.accepts(EventKind::EventsDropped)
+                {
+                    let count = std::mem::take(&mut inner.dropped);
+                    return Some(QueuedEvent::Dropped(count));
+                }
+                if let Some(evt) = inner.queue.pop_front() {
+                    return Some(QueuedEvent::Event(evt));
+                }
+                if inner.closed {
+                    return None;
+                }
+            }
+            // a notify() since the check above leaves a permit,
+            // so this cannot miss a push
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Progress, entry creations, activations and removals, clock skew and
+/// audit records are informational, every other event must be answered
+/// by the client, or fail.
+fn is_droppable(evt: &LairClientEvent) -> bool {
+    matches!(
+        evt,
+        LairClientEvent::UnlockProgress { .. }
+            | LairClientEvent::EntryCreated { .. }
+            | LairClientEvent::ClockSkewDetected { .. }
+            | LairClientEvent::EntryActivated { .. }
+            | LairClientEvent::AuditEvent { .. }
+            | LairClientEvent::EntryRemoved { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future::FutureExt, stream::StreamExt};
+
+    /// Queue every event sent on the returned sender, as the server does,
+    /// while nobody takes them off - a stalled client.
+    fn stalled(
+        capacity: usize,
+        policy: SlowConsumerPolicy,
+    ) -> (
+        Arc<EventQueue>,
+        LairClientEventSenderType,
+        futures::channel::oneshot::Receiver<LairResult<()>>,
+    ) {
+        let queue = Arc::new(EventQueue::new(capacity, policy));
+        let (evt_send, mut evt_recv) = futures::channel::mpsc::channel(1);
+        let (pump_send, pump_recv) = futures::channel::oneshot::channel();
+        let pump_queue = queue.clone();
+        tokio::task::spawn(async move {
+            let mut res = Ok(());
+            while let Some(evt) = evt_recv.next().await {
+                if let Err(e) = pump_queue.push(evt) {
+                    res = Err(e);
+                    break;
+                }
+            }
+            let _ = pump_send.send(res);
+        });
+        (queue, evt_send, pump_recv)
+    }
+
+    /// Wait for the pump to have handled everything sent so far.
+    async fn settle(queue: &EventQueue, len: usize, dropped: u64) {
+        loop {
+            {
+                let inner = queue.lock();
+                if inner.queue.len() == len && inner.dropped == dropped {
+                    return;
+                }
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_drops_the_oldest_progress_for_a_stalled_client() {
+        let (queue, evt_send, _pump) =
+            stalled(2, SlowConsumerPolicy::DropOldest);
+
+        let s = evt_send.clone();
+        let unlock = tokio::task::spawn(async move {
+            s.request_unlock_passphrase("default".into()).await
+        });
+        settle(&queue, 1, 0).await;
+        let mut progress = Vec::new();
+        for (percent, dropped) in &[(25, 0), (50, 1), (75, 2)] {
+            let s = evt_send.clone();
+            let percent = *percent;
+            progress.push(tokio::task::spawn(async move {
+                s.unlock_progress(percent).await
+            }));
+            settle(&queue, 2, *dropped).await;
+        }
+
+        // the oldest progress made room each time,
+        // the passphrase request was kept
+        let mut progress = progress.into_iter();
+        assert!(progress.next().unwrap().await.unwrap().is_err());
+        assert!(progress.next().unwrap().await.unwrap().is_err());
+        assert!(matches!(queue.next().await, Some(QueuedEvent::Dropped(2))));
+        match queue.next().await {
+            Some(QueuedEvent::Event(
+                LairClientEvent::RequestUnlockPassphrase { respond, .. },
+            )) => {
+                respond.respond(Ok(async move { Ok("pass".into()) }
+                    .boxed()
+                    .into()));
+            }
+            _ => panic!("expected the passphrase request"),
+        }
+        assert_eq!(b"pass", unlock.await.unwrap().unwrap().read());
+        match queue.next().await {
+            Some(QueuedEvent::Event(LairClientEvent::UnlockProgress {
+                respond,
+                percent,
+                ..
+            })) => {
+                assert_eq!(75, percent);
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            _ => panic!("expected unlock progress"),
+        }
+        progress.next().unwrap().await.unwrap().unwrap();
+
+        // with only critical events waiting, a new critical event
+        // fails instead of evicting one, and new progress is dropped
+        let mut pending = Vec::new();
+        for _ in 0..2 {
+            let s = evt_send.clone();
+            pending.push(tokio::task::spawn(async move {
+                s.unlock_complete(None).await
+            }));
+        }
+        settle(&queue, 2, 0).await;
+        let s = evt_send.clone();
+        assert!(s.request_unlock_passphrase("default".into()).await.is_err());
+        settle(&queue, 2, 0).await;
+        assert!(s.unlock_progress(100).await.is_err());
+        settle(&queue, 2, 1).await;
+
+        // nothing is left hanging on close
+        queue.close();
+        assert!(matches!(queue.next().await, Some(QueuedEvent::Dropped(1))));
+        while let Some(evt) = queue.next().await {
+            if let QueuedEvent::Event(evt) = evt {
+                evt.respond_error("closed".into());
+            }
+        }
+        for p in pending {
+            assert!(p.await.unwrap().is_err());
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_fails_filtered_out_events() {
+        let (queue, evt_send, _pump) =
+            stalled(4, SlowConsumerPolicy::DropOldest);
+
+        let s = evt_send.clone();
+        let progress =
+            tokio::task::spawn(async move { s.unlock_progress(50).await });
+        settle(&queue, 1, 0).await;
+
+        // buffered events of a filtered kind fail
+        queue.set_filter(&[
+            EventKind::RequestUnlockPassphrase,
+            EventKind::UnlockComplete,
+        ]);
+        assert!(progress.await.unwrap().is_err());
+        settle(&queue, 0, 0).await;
+
+        // and so do new ones, without being buffered
+        assert!(evt_send.unlock_progress(75).await.is_err());
+        settle(&queue, 0, 0).await;
+
+        let s = evt_send.clone();
+        let complete =
+            tokio::task::spawn(async move { s.unlock_complete(None).await });
+        match queue.next().await {
+            Some(QueuedEvent::Event(LairClientEvent::UnlockComplete {
+                respond,
+                ..
+            })) => {
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            _ => panic!("expected unlock complete"),
+        }
+        complete.await.unwrap().unwrap();
+
+        queue.set_filter(EventKind::ALL);
+        let s = evt_send.clone();
+        let progress =
+            tokio::task::spawn(async move { s.unlock_progress(100).await });
+        settle(&queue, 1, 0).await;
+        queue.close();
+        while let Some(evt) = queue.next().await {
+            if let QueuedEvent::Event(evt) = evt {
+                evt.respond_error("closed".into());
+            }
+        }
+        assert!(progress.await.unwrap().is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_disconnects_a_stalled_client() {
+        let (queue, evt_send, pump) =
+            stalled(2, SlowConsumerPolicy::Disconnect);
+
+        let mut pending = Vec::new();
+        for _ in 0..2 {
+            let evt_send = evt_send.clone();
+            pending.push(tokio::task::spawn(async move {
+                evt_send.request_unlock_passphrase("default".into()).await
+            }));
+        }
+        settle(&queue, 2, 0).await;
+        assert!(evt_send.unlock_progress(50).await.is_err());
+
+        // the pump is told to disconnect, and nothing is left hanging
+        assert!(pump.await.unwrap().is_err());
+        for p in pending {
+            assert!(p.await.unwrap().is_err());
+        }
+        assert!(queue.next().await.is_none());
+        assert!(evt_send.unlock_complete(None).await.is_err());
+    }
+}
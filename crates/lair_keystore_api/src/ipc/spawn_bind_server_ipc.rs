@@ -1,23 +1,39 @@
+use super::audit::*;
+use super::connections::*;
+use super::elevation::*;
+use super::event_queue::*;
 use super::*;
 use crate::internal::ipc::*;
 use crate::internal::wire::*;
-use futures::{future::FutureExt, sink::SinkExt, stream::StreamExt};
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::StreamExt,
+};
 
-pub(crate) async fn spawn_bind_server_ipc<S>(
+/// Returns a (non-weak) clone of the server kill switch,
+/// dropping it unbinds the server and closes every connection,
+/// and the in-process listener of the server.
+pub(crate) async fn spawn_bind_server_ipc<S, F>(
     config: Arc<Config>,
-    api_sender: S,
-    incoming_send: futures::channel::mpsc::Sender<LairClientEventSenderType>,
-) -> LairResult<()>
+    api_factory: F,
+) -> LairResult<(KillSwitch, LairInProcListener)>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
+    F: Fn(LairClientEventSenderType) -> BoxFuture<'static, LairResult<S>>
+        + 'static
+        + Send
+        + Sync,
 {
-    let (kill_switch, mut incoming_ipc_recv) = spawn_bind_ipc(config).await?;
+    let connections = Connections::new();
+    let (kill_switch, mut incoming_ipc_recv) =
+        spawn_bind_ipc(config.clone()).await?;
 
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
-    let channel_factory = builder.channel_factory().clone();
-
-    let i_s = channel_factory.create_channel::<InternalApi>().await?;
+    let i_s = builder
+        .channel_factory()
+        .create_channel::<InternalApi>()
+        .await?;
 
     let kill_sender = i_s.clone();
     kill_switch
@@ -31,9 +47,14 @@ where
         }))
         .await;
 
+    let out_kill_switch = kill_switch.clone();
+    let in_proc = LairInProcListener {
+        config: config.clone(),
+        i_s: i_s.clone(),
+    };
     let i_kill_switch = kill_switch.clone();
     err_spawn("srv-ipc-incoming-loop", async move {
-        while let Ok((k, s, r)) = i_kill_switch
+        while let Ok((k, s, r, url)) = i_kill_switch
             .mix(async {
                 incoming_ipc_recv
                     .next()
@@ -42,7 +63,12 @@ where
             })
             .await
         {
-            if i_kill_switch.mix(i_s.incoming(k, s, r)).await.is_err() {
+            let listener = Listener::Url(url);
+            if i_kill_switch
+                .mix(i_s.incoming(k, s, r, listener))
+                .await
+                .is_err()
+            {
                 break;
             }
         }
@@ -52,16 +78,60 @@ where
     err_spawn("srv-ipc-actor", async move {
         builder
             .spawn(Internal {
+                config,
+                connections,
                 kill_switch,
-                channel_factory,
-                api_sender,
-                incoming_send,
+                api_factory: Arc::new(api_factory),
+                auditor: Auditor::new(),
             })
             .await
             .map_err(LairError::other)
     });
 
-    Ok(())
+    Ok((out_kill_switch, in_proc))
+}
+
+/// Opens in-process connections to a server, see
+/// `spawn_bind_server_ipc_per_connection`. They are served as any other
+/// connection - authorized, audited and counted the same - but granted
+/// the capabilities they are opened with, rather than those of a
+/// listener, and not bound to any socket path. Holding one does not keep
+/// the server running.
+#[derive(Clone)]
+#[cfg_attr(windows, allow(dead_code))]
+pub struct LairInProcListener {
+    config: Arc<Config>,
+    i_s: ghost_actor::GhostSender<InternalApi>,
+}
+
+impl LairInProcListener {
+    /// Open a connection granted `capabilities`, as `spawn_client_ipc`
+    /// would over a socket. Events are sent to the returned receiver.
+    #[cfg(not(windows))]
+    pub async fn connect(
+        &self,
+        capabilities: LairCapabilities,
+    ) -> LairResult<(
+        ghost_actor::GhostSender<LairClientApi>,
+        LairClientEventReceiver,
+    )> {
+        let (srv, cli) =
+            crate::internal::ipc::spawn_in_proc_ipc_pair(self.config.clone())
+                .await?;
+        let (k, s, r) = srv;
+        self.i_s
+            .incoming(k, s, r, Listener::InProc(capabilities))
+            .await?;
+        let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
+        let (api_send, _) =
+            super::spawn_client_ipc::spawn_client_on_connection(
+                self.config.clone(),
+                cli,
+                evt_send,
+            )
+            .await?;
+        Ok((api_send, evt_recv))
+    }
 }
 
 ghost_actor::ghost_chan! {
@@ -70,18 +140,38 @@ ghost_actor::ghost_chan! {
             con_kill_switch: KillSwitch,
             ipc_send: IpcSender,
             ipc_recv: IpcReceiver,
+            listener: Listener,
         ) -> ();
     }
 }
 
+/// Where a connection came in, deciding its capabilities.
+pub(crate) enum Listener {
+    /// One of the `Config::get_listener_urls`, carrying the token
+    /// connections must present, if any.
+    Url(LairConnectionUrl),
+    /// An in-process connection, see `LairInProcListener`,
+    /// granted these capabilities.
+    #[cfg_attr(windows, allow(dead_code))]
+    InProc(LairCapabilities),
+}
+
+type ApiFactory<S> = Arc<
+    dyn Fn(LairClientEventSenderType) -> BoxFuture<'static, LairResult<S>>
+        + 'static
+        + Send
+        + Sync,
+>;
+
 struct Internal<S>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    config: Arc<Config>,
+    connections: Arc<Connections>,
     kill_switch: KillSwitch,
-    channel_factory: ghost_actor::actor_builder::GhostActorChannelFactory<Self>,
-    api_sender: S,
-    incoming_send: futures::channel::mpsc::Sender<LairClientEventSenderType>,
+    api_factory: ApiFactory<S>,
+    auditor: Auditor,
 }
 
 impl<S> ghost_actor::GhostControlHandler for Internal<S> where
@@ -104,50 +194,396 @@ where
         mut con_kill_switch: KillSwitch,
         ipc_send: IpcSender,
         ipc_recv: IpcReceiver,
+        listener: Listener,
     ) -> InternalApiHandlerResult<()> {
+        // read as each connection is accepted, so a reload
+        // applies to the connections accepted after it
+        let settings = self.config.get_runtime_settings();
+        let registration =
+            match self.connections.register(settings.max_connections) {
+                Some(registration) => registration,
+                None => {
+                    ghost_actor::dependencies::tracing::warn!(
+                        "refusing connection, serving max connections"
+                    );
+                    refuse(con_kill_switch, ipc_send, ipc_recv);
+                    return Ok(async move { Ok(()) }.boxed().into());
+                }
+            };
+        let activity = registration.activity().clone();
+        let connection_id = registration.id();
+
+        if let Some(timeout) = settings.idle_connection_timeout {
+            // holds a strong clone, to close the connection once idle
+            spawn_idle_check(
+                con_kill_switch.clone(),
+                ipc_send.clone(),
+                activity.clone(),
+                timeout,
+            );
+        }
+
+        // Dropping this one, rather than the weak one below,
+        // disconnects a client too slow to read its events.
+        let mut slow_consumer_kill_switch = con_kill_switch.clone();
+
         // We don't actually want to kill this connection if the server
         // decides to drop the event sender. Make this kill switch weak.
         con_kill_switch.make_weak();
 
-        let (evt_send, mut evt_recv) = futures::channel::mpsc::channel(10);
+        // Events are moved off the channel straight into the queue, so a
+        // slow client never blocks whoever raised them - the queue's
+        // slow consumer policy handles it instead.
+        let queue = Arc::new(EventQueue::new(
+            settings.event_buffer_size,
+            settings.slow_consumer_policy,
+        ));
+        let (evt_send, mut evt_recv) = futures::channel::mpsc::channel(1);
+        let pump_queue = queue.clone();
+        let con_queue = queue.clone();
+        con_spawn("srv-con-evt-pump", async move {
+            while let Some(evt) = evt_recv.next().await {
+                if let Err(err) = pump_queue.push(evt) {
+                    ghost_actor::dependencies::tracing::warn!(?err);
+                    drop(slow_consumer_kill_switch);
+                    return Ok(());
+                }
+            }
+            pump_queue.close();
+            slow_consumer_kill_switch.make_weak();
+            Ok(())
+        });
+
+        // set once the connection is authenticated, before
+        // the api that raises the events exists
+        let granted = Arc::new(std::sync::atomic::AtomicU32::new(
+            LairCapabilities::NONE.0,
+        ));
+        let evt_granted = granted.clone();
         let evt_ipc_send = ipc_send;
-        err_spawn("srv-con-evt-loop", async move {
-            while let Ok(msg) = evt_recv
-                .next()
-                .await
-                .ok_or_else::<LairError, _>(|| "stream end".into())
-            {
+        let evt_kill_switch = con_kill_switch.clone();
+        let audit_kill_switch = con_kill_switch.clone();
+        let evt_activity = activity.clone();
+        // Events the client answers, unlock passphrases and sign
+        // approvals, are awaited outside this loop, as are the requests
+        // raising them. A client handling an event may call back in,
+        // raising more events, which must not queue behind the first.
+        con_spawn("srv-con-evt-loop", async move {
+            while let Some(msg) = queue.next().await {
+                evt_activity.touch();
+                let msg = match msg {
+                    QueuedEvent::Dropped(count) => {
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliEventsDropped {
+                                    msg_id: next_msg_id(),
+                                    count,
+                                },
+                            ))
+                            .await;
+                        continue;
+                    }
+                    QueuedEvent::Event(msg) => msg,
+                };
                 match msg {
                     LairClientEvent::RequestUnlockPassphrase {
                         respond,
+                        store_name,
+                        ..
+                    } => {
+                        let capabilities = LairCapabilities(
+                            evt_granted
+                                .load(std::sync::atomic::Ordering::SeqCst),
+                        );
+                        if !capabilities.contains(LairCapability::CanUnlock) {
+                            ghost_actor::dependencies::tracing::info!(
+                                %store_name,
+                                "not asking a connection without the \
+                                can_unlock capability for the passphrase, \
+                                waiting for one that has it",
+                            );
+                            respond.respond(Ok(async move {
+                                Err(LairError::KeystoreLocked)
+                            }
+                            .boxed()
+                            .into()));
+                            continue;
+                        }
+                        // answered in its own task, see the note above
+                        let fut =
+                            evt_kill_switch.mix_static(evt_ipc_send.request(
+                                LairWire::ToCliRequestUnlockPassphrase {
+                                    msg_id: next_msg_id(),
+                                    store_name,
+                                },
+                            ));
+                        respond.respond(Ok(async move {
+                            match fut.await? {
+                                LairWire::ToLairRequestUnlockPassphraseResponse {
+                                    passphrase,
+                                    ..
+                                } => Ok(passphrase),
+                                o => Err(format!("unexpected: {:?}", o).into()),
+                            }
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairClientEvent::UnlockProgress {
+                        respond,
+                        percent,
+                        ..
+                    } => {
+                        // progress is best effort, the client may be gone
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliUnlockProgress {
+                                    msg_id: next_msg_id(),
+                                    percent,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::UnlockComplete {
+                        respond,
+                        mut error,
                         ..
                     } => {
-                        match evt_ipc_send.request(LairWire::ToCliRequestUnlockPassphrase {
-                            msg_id: next_msg_id(),
-                        }).await {
-                            Ok(LairWire::ToLairRequestUnlockPassphraseResponse {
-                                passphrase,
-                                ..
-                            }) => {
-                                respond.respond(Ok(async move {
-                                    Ok(passphrase)
-                                }.boxed().into()));
+                        if let Some(error) = &mut error {
+                            let mut idx = MAX_UNLOCK_ERROR_LEN.min(error.len());
+                            while !error.is_char_boundary(idx) {
+                                idx -= 1;
                             }
-                            _ => (),
+                            error.truncate(idx);
+                        }
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliUnlockComplete {
+                                    msg_id: next_msg_id(),
+                                    error,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::EventsDropped { respond, .. } => {
+                        // only ever raised by the queue itself
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::EntryCreated {
+                        respond,
+                        keystore_index,
+                        entry_type,
+                        ..
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliEntryCreated {
+                                    msg_id: next_msg_id(),
+                                    keystore_index,
+                                    entry_type,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::RequestSignApproval {
+                        respond,
+                        keystore_index,
+                        pub_key,
+                        ..
+                    } => {
+                        let fut =
+                            evt_kill_switch.mix_static(evt_ipc_send.request(
+                                LairWire::ToCliRequestSignApproval {
+                                    msg_id: next_msg_id(),
+                                    keystore_index,
+                                    pub_key,
+                                },
+                            ));
+                        respond.respond(Ok(async move {
+                            // anything but an approval refuses
+                            Ok(matches!(
+                                fut.await,
+                                Ok(LairWire::ToLairRequestSignApprovalResponse {
+                                    approved: true,
+                                    ..
+                                })
+                            ))
                         }
+                        .boxed()
+                        .into()));
+                    }
+                    LairClientEvent::ClockSkewDetected {
+                        respond,
+                        wall_secs,
+                        expected_secs,
+                        ..
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliClockSkewDetected {
+                                    msg_id: next_msg_id(),
+                                    wall_secs,
+                                    expected_secs,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::EntryActivated {
+                        respond,
+                        keystore_index,
+                        ..
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliEntryActivated {
+                                    msg_id: next_msg_id(),
+                                    keystore_index,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    LairClientEvent::AuditEvent {
+                        respond,
+                        record,
+                        dropped,
+                        ..
+                    } => {
+                        // the feed counts the records that fail to arrive
+                        let res = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliAuditEvent {
+                                    msg_id: next_msg_id(),
+                                    record,
+                                    dropped,
+                                },
+                            ))
+                            .await
+                            .map(|_| ());
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairClientEvent::EntryRemoved {
+                        respond,
+                        keystore_index,
+                        ..
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_ipc_send.request(
+                                LairWire::ToCliEntryRemoved {
+                                    msg_id: next_msg_id(),
+                                    keystore_index,
+                                },
+                            ))
+                            .await;
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
                     }
                 }
             }
             Ok(())
         });
-        let channel_factory = self.channel_factory.clone();
-        let mut in_send_clone = self.incoming_send.clone();
+
+        let api_factory = self.api_factory.clone();
+        let config = self.config.clone();
+        let connections = self.connections.clone();
+        let auditor = self.auditor.clone();
+        // a connection ending must not kill the server, make it weak
+        let mut kill_switch = self.kill_switch.clone();
+        kill_switch.make_weak();
         Ok(async move {
-            channel_factory.attach_receiver(ipc_recv).await?;
-            in_send_clone
-                .send(evt_send)
-                .await
-                .map_err(LairError::other)?;
+            con_spawn("srv-con-req-loop", async move {
+                let mut ipc_recv = ipc_recv;
+                let (listener_capabilities, token) = match &listener {
+                    Listener::Url(url) => {
+                        (config.get_listener_capabilities(url), url.token())
+                    }
+                    Listener::InProc(capabilities) => (*capabilities, None),
+                };
+                // the api, which may start asking for an unlock
+                // passphrase, only exists once a token is presented
+                let capabilities = match token {
+                    Some(token) => match authenticate(
+                        token,
+                        &config,
+                        listener_capabilities,
+                        &mut ipc_recv,
+                        &auditor,
+                        connection_id,
+                    )
+                    .await
+                    {
+                        Some(capabilities) => capabilities,
+                        None => return Ok(()),
+                    },
+                    None => listener_capabilities,
+                };
+                granted
+                    .store(capabilities.0, std::sync::atomic::Ordering::SeqCst);
+                if capabilities.contains(LairCapability::Audit) {
+                    con_spawn(
+                        "srv-con-audit-feed",
+                        auditor.feed(
+                            audit_kill_switch,
+                            evt_send.clone(),
+                            con_queue.clone(),
+                        ),
+                    );
+                }
+                // each connection gets its own api sender, so any
+                // per-connection state (e.g. the selected store) stays put
+                let api_sender = api_factory(evt_send).await?;
+                let mut con = Con {
+                    kill_switch,
+                    api_sender,
+                    capabilities,
+                    elevation: Elevation::new(&config, listener_capabilities),
+                    connections,
+                    events: con_queue,
+                };
+                while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                    ipc_recv.next().await
+                {
+                    let guard = activity.start_request();
+                    // noticed here, it took effect when it expired
+                    con.audit_expired_elevation(&auditor, connection_id);
+                    let audit = auditor.begin(
+                        &msg,
+                        connection_id,
+                        con.granted_capabilities(),
+                    );
+                    // a panicking handler fails its request, not the loop
+                    let res = catch_handler_panic("request handler", || {
+                        con.handle_request(msg)
+                    });
+                    respond.respond(Ok(async move {
+                        let res = match res {
+                            Ok(fut) => fut.await,
+                            Err(e) => Err(e),
+                        };
+                        if let Some(audit) = audit {
+                            audit.finish(&res);
+                        }
+                        drop(guard);
+                        res
+                    }
+                    .boxed()
+                    .into()));
+                }
+                con.audit_expired_elevation(&auditor, connection_id);
+                // served until the connection closes
+                drop(registration);
+                Ok(())
+            });
             Ok(())
         }
         .boxed()
@@ -155,26 +591,305 @@ where
     }
 }
 
-impl<S> ghost_actor::GhostHandler<IpcWireApi> for Internal<S> where
-    S: ghost_actor::GhostChannelSender<LairClientApi>
+/// `err_spawn` a task serving a single connection, counted as a
+/// `Counter::ConnectionTask` until it ends.
+fn con_spawn<F>(hint: &'static str, f: F)
+where
+    F: std::future::Future<Output = LairResult<()>> + 'static + Send,
+{
+    let tracked = internal::debug_stats::Tracked::new(
+        internal::debug_stats::Counter::ConnectionTask,
+    );
+    err_spawn(hint, async move {
+        let _tracked = tracked;
+        f.await
+    });
+}
+
+/// Refuse a connection past `Config::get_max_connections`: answer
+/// whatever it requests with `LairError::TooManyConnections`, say
+/// goodbye, and close it.
+fn refuse(
+    con_kill_switch: KillSwitch,
+    ipc_send: IpcSender,
+    ipc_recv: IpcReceiver,
+) {
+    con_spawn("srv-con-refuse-loop", async move {
+        let mut ipc_recv = ipc_recv;
+        while let Some(IpcWireApi::Request { respond, .. }) =
+            ipc_recv.next().await
+        {
+            respond.respond(Ok(
+                async move { Err(LairError::TooManyConnections) }
+                    .boxed()
+                    .into(),
+            ));
+        }
+        Ok(())
+    });
+    con_spawn("srv-con-refuse", async move {
+        say_goodbye(&ipc_send, LairError::TooManyConnections).await;
+        // the strong kill switch, dropping it closes the connection
+        drop(con_kill_switch);
+        Ok(())
+    });
+}
+
+/// Close the connection of `kill_switch` once it goes `timeout`
+/// without traffic and with no request in flight, saying goodbye.
+fn spawn_idle_check(
+    mut kill_switch: KillSwitch,
+    ipc_send: IpcSender,
+    activity: Arc<Activity>,
+    timeout: std::time::Duration,
+) {
+    con_spawn("srv-con-idle-check", async move {
+        let weak_kill_switch = kill_switch.weak();
+        loop {
+            let idle = activity.idle();
+            if idle >= timeout && activity.in_flight() == 0 {
+                break;
+            }
+            // a request in flight is checked again a timeout later
+            let wait = timeout.checked_sub(idle).unwrap_or(timeout);
+            let wait = weak_kill_switch
+                .mix(async move {
+                    tokio::time::delay_for(wait).await;
+                    Ok(())
+                })
+                .await;
+            if wait.is_err() {
+                // closed by the client, or the server
+                kill_switch.make_weak();
+                return Ok(());
+            }
+        }
+        ghost_actor::dependencies::tracing::debug!("closing idle connection");
+        say_goodbye(&ipc_send, LairError::ConnectionIdle).await;
+        drop(kill_switch);
+        Ok(())
+    });
+}
+
+/// Refuse every request on a connection but `ToLairAuthenticate`,
+/// until one presents `token`, granting every capability, or one of
+/// the config connection tokens, granting its capabilities - those of
+/// `listener_capabilities` in either case. Every attempt is audited.
+/// None if the connection closed first.
+async fn authenticate(
+    token: &str,
+    config: &Config,
+    listener_capabilities: LairCapabilities,
+    ipc_recv: &mut IpcReceiver,
+    auditor: &Auditor,
+    connection_id: u64,
+) -> Option<LairCapabilities> {
+    let tokens = std::iter::once((token, LairCapabilities::ALL)).chain(
+        config
+            .get_connection_tokens()
+            .iter()
+            .map(|(token, capabilities)| (token.as_str(), *capabilities)),
+    );
+    while let Some(IpcWireApi::Request { respond, msg, .. }) =
+        ipc_recv.next().await
+    {
+        let audit = auditor.begin(&msg, connection_id, LairCapabilities::NONE);
+        let (res, granted) = match msg {
+            LairWire::ToLairAuthenticate {
+                msg_id,
+                token: presented,
+            } => {
+                // check every token, whichever matches
+                let granted = tokens.clone().fold(None, |granted, token| {
+                    let is_match =
+                        ring::constant_time::verify_slices_are_equal(
+                            token.0.as_bytes(),
+                            presented.as_bytes(),
+                        )
+                        .is_ok();
+                    match (granted, is_match) {
+                        (None, true) => {
+                            Some(token.1.intersect(listener_capabilities))
+                        }
+                        (granted, _) => granted,
+                    }
+                });
+                match granted {
+                    Some(capabilities) => (
+                        Ok(LairWire::ToCliAuthenticateResponse {
+                            msg_id,
+                            capabilities,
+                        }),
+                        granted,
+                    ),
+                    None => (Err(LairError::AuthenticationFailed), None),
+                }
+            }
+            _ => (
+                Err(LairError::Forbidden(
+                    "this listener requires a connection token".to_string(),
+                )),
+                None,
+            ),
+        };
+        respond.respond(Ok(async move {
+            if let Some(audit) = audit {
+                audit.finish(&res);
+            }
+            res
+        }
+        .boxed()
+        .into()));
+        if granted.is_some() {
+            return granted;
+        }
+    }
+    None
+}
+
+/// The request side of a single incoming connection.
+struct Con<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    kill_switch: KillSwitch,
+    api_sender: S,
+    capabilities: LairCapabilities,
+    elevation: Elevation,
+    connections: Arc<Connections>,
+    events: Arc<EventQueue>,
 }
 
-impl<S> IpcWireApiHandler for Internal<S>
+impl<S> Con<S>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    /// The capabilities granted on authenticating,
+    /// and those held elevated.
+    fn granted_capabilities(&self) -> LairCapabilities {
+        self.capabilities.union(self.elevation.capabilities())
+    }
+
+    /// Record the expiry of an elevation, once, with the
+    /// capabilities the connection held until then.
+    fn audit_expired_elevation(
+        &mut self,
+        auditor: &Auditor,
+        connection_id: u64,
+    ) {
+        if let Some(expired) = self.elevation.take_expired() {
+            auditor.record(
+                "elevation_expired",
+                connection_id,
+                self.capabilities.union(expired),
+            );
+        }
+    }
+
     fn handle_request(
         &mut self,
         msg: LairWire,
     ) -> IpcWireApiHandlerResult<LairWire> {
+        // refused before any handler, or the api, sees the request
+        let capabilities = self.granted_capabilities();
+        if let Some(capability) = msg.required_capability() {
+            if !capabilities.contains(capability) {
+                return Ok(async move {
+                    Err(LairError::Forbidden(format!(
+                        "missing capability: {}",
+                        capability
+                    )))
+                }
+                .boxed()
+                .into());
+            }
+        }
         match msg {
+            // already authenticated, or no token is required
+            LairWire::ToLairAuthenticate { msg_id, .. } => Ok(async move {
+                Ok(LairWire::ToCliAuthenticateResponse {
+                    msg_id,
+                    capabilities,
+                })
+            }
+            .boxed()
+            .into()),
+            LairWire::ToLairLairListConnections { msg_id } => {
+                let connections = self.connections.list();
+                Ok(async move {
+                    Ok(LairWire::ToCliLairListConnectionsResponse {
+                        msg_id,
+                        connections,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairRequestElevation {
+                msg_id,
+                capabilities: requested,
+                duration_ms,
+            } => {
+                let res = self.elevation.request(
+                    self.capabilities,
+                    requested,
+                    std::time::Duration::from_millis(duration_ms),
+                );
+                Ok(async move {
+                    res.map(|elevation| {
+                        LairWire::ToCliLairRequestElevationResponse {
+                            msg_id,
+                            elevation,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetElevation { msg_id } => {
+                let elevation = self.elevation.get();
+                Ok(async move {
+                    Ok(LairWire::ToCliLairGetElevationResponse {
+                        msg_id,
+                        elevation,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairDropElevation { msg_id } => {
+                self.elevation.drop_elevation();
+                Ok(async move {
+                    Ok(LairWire::ToCliLairDropElevationResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSetEventFilter { msg_id, kinds } => {
+                self.events.set_filter(&kinds);
+                Ok(async move {
+                    Ok(LairWire::ToCliLairSetEventFilterResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetCapabilities { msg_id } => Ok(async move {
+                Ok(LairWire::ToCliLairGetCapabilitiesResponse {
+                    msg_id,
+                    capabilities,
+                })
+            }
+            .boxed()
+            .into()),
             LairWire::ToLairLairGetServerInfo { msg_id } => {
                 let fut = self
                     .kill_switch
                     .mix_static(self.api_sender.lair_get_server_info());
                 Ok(async move {
-                    fut.await.map(|info| {
+                    fut.await.map(|mut info| {
+                        // the version this end of the connection speaks
+                        info.wire_protocol_version =
+                            spec::WIRE_PROTOCOL_VERSION;
                         LairWire::ToCliLairGetServerInfoResponse {
                             msg_id,
                             info,
@@ -184,6 +899,63 @@ where
                 .boxed()
                 .into())
             }
+            LairWire::ToLairLairProveServerIdentity { msg_id, nonce } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_prove_server_identity(nonce),
+                );
+                Ok(async move {
+                    fut.await.map(|proof| {
+                        LairWire::ToCliLairProveServerIdentityResponse {
+                            msg_id,
+                            proof,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetServerPaths { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_server_paths());
+                Ok(async move {
+                    fut.await.map(|paths| {
+                        LairWire::ToCliLairGetServerPathsResponse {
+                            msg_id,
+                            paths,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairExportDeviceSecret { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_export_device_secret());
+                Ok(async move {
+                    fut.await.map(|device_secret| {
+                        LairWire::ToCliLairExportDeviceSecretResponse {
+                            msg_id,
+                            device_secret,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSelectStore { msg_id, name } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_select_store(name));
+                Ok(async move {
+                    fut.await.map(|()| LairWire::ToCliLairSelectStoreResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
             LairWire::ToLairLairGetLastEntryIndex { msg_id } => {
                 let fut = self
                     .kill_switch
@@ -199,196 +971,1516 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairLairGetEntryType {
-                msg_id,
-                keystore_index,
-            } => {
-                let fut = self.kill_switch.mix_static(
-                    self.api_sender.lair_get_entry_type(keystore_index),
-                );
+            LairWire::ToLairLairGetLastEntryIndex64 { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_last_entry_index_64());
                 Ok(async move {
-                    fut.await.map(|lair_entry_type| {
-                        LairWire::ToCliLairGetEntryTypeResponse {
+                    fut.await.map(|last_keystore_index| {
+                        LairWire::ToCliLairGetLastEntryIndex64Response {
                             msg_id,
-                            lair_entry_type,
+                            last_keystore_index,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
-                msg_id,
-                cert_alg,
-            } => {
-                let options = TlsCertOptions { alg: cert_alg };
-                let fut = self.kill_switch.mix_static(
-                    self.api_sender
-                        .tls_cert_new_self_signed_from_entropy(options),
-                );
+            LairWire::ToLairLairGetEntryCount { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_entry_count());
                 Ok(async move {
-                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
-                        LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    fut.await.map(|entry_count| {
+                        LairWire::ToCliLairGetEntryCountResponse {
                             msg_id,
-                            keystore_index,
-                            cert_sni,
-                            cert_digest,
+                            entry_count,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGet {
-                msg_id,
-                keystore_index,
-            } => {
+            LairWire::ToLairLairGetStoreStats { msg_id } => {
                 let fut = self
                     .kill_switch
-                    .mix_static(self.api_sender.tls_cert_get(keystore_index));
+                    .mix_static(self.api_sender.lair_get_store_stats());
                 Ok(async move {
-                    fut.await.map(|(cert_sni, cert_digest)| {
-                        LairWire::ToCliTlsCertGetResponse {
+                    fut.await.map(|stats| {
+                        LairWire::ToCliLairGetStoreStatsResponse {
                             msg_id,
-                            cert_sni,
-                            cert_digest,
+                            stats,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertByIndex {
+            LairWire::ToLairLairListEntriesPage {
                 msg_id,
-                keystore_index,
+                start,
+                limit,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_cert_by_index(keystore_index),
+                    self.api_sender.lair_list_entries_page(start, limit),
                 );
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    fut.await.map(|(entries, next_start)| {
+                        LairWire::ToCliLairListEntriesPageResponse {
                             msg_id,
-                            cert,
+                            entries,
+                            next_start,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertByDigest {
+            LairWire::ToLairLairGetEntryType {
                 msg_id,
-                cert_digest,
+                keystore_index,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_cert_by_digest(cert_digest),
+                    self.api_sender.lair_get_entry_type(keystore_index),
                 );
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertByDigestResponse {
+                    fut.await.map(|lair_entry_type| {
+                        LairWire::ToCliLairGetEntryTypeResponse {
                             msg_id,
-                            cert,
+                            lair_entry_type,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertBySni { msg_id, cert_sni } => {
-                let fut = self.kill_switch.mix_static(
+            LairWire::ToLairLairGetEntryType64 {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_get_entry_type_64(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|lair_entry_type| {
+                        LairWire::ToCliLairGetEntryType64Response {
+                            msg_id,
+                            lair_entry_type,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetEntryTypes { msg_id, indices } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_entry_types(indices));
+                Ok(async move {
+                    fut.await.map(|entry_types| {
+                        LairWire::ToCliLairGetEntryTypesResponse {
+                            msg_id,
+                            entry_types,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairEraseEntries { msg_id, indices } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_erase_entries(indices));
+                Ok(async move {
+                    fut.await.map(|results| {
+                        LairWire::ToCliLairEraseEntriesResponse {
+                            msg_id,
+                            results: bulk_results_to_wire(results),
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSetEntryTags { msg_id, tags } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_set_entry_tags(tags));
+                Ok(async move {
+                    fut.await.map(|results| {
+                        LairWire::ToCliLairSetEntryTagsResponse {
+                            msg_id,
+                            results: bulk_results_to_wire(results),
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSetEntryTag {
+                msg_id,
+                keystore_index,
+                tag,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_set_entry_tag(keystore_index, tag),
+                );
+                Ok(async move {
+                    fut.await.map(|()| LairWire::ToCliLairSetEntryTagResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetEntryByTag { msg_id, tag } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_entry_by_tag(tag));
+                Ok(async move {
+                    fut.await.map(|(keystore_index, lair_entry_type)| {
+                        LairWire::ToCliLairGetEntryByTagResponse {
+                            msg_id,
+                            keystore_index,
+                            lair_entry_type,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairFindEntriesByPrefix {
+                msg_id,
+                prefix,
+                kind,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_find_entries_by_prefix(prefix, kind),
+                );
+                Ok(async move {
+                    fut.await.map(|entries| {
+                        LairWire::ToCliLairFindEntriesByPrefixResponse {
+                            msg_id,
+                            entries,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairAddEntryAlias {
+                msg_id,
+                keystore_index,
+                alias,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_add_entry_alias(keystore_index, alias),
+                );
+                Ok(async move {
+                    fut.await.map(|()| {
+                        LairWire::ToCliLairAddEntryAliasResponse { msg_id }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairRemoveEntryAlias { msg_id, alias } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_remove_entry_alias(alias));
+                Ok(async move {
+                    fut.await.map(|()| {
+                        LairWire::ToCliLairRemoveEntryAliasResponse { msg_id }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairTrashEntry {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_trash_entry(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|()| LairWire::ToCliLairTrashEntryResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairRestoreEntry {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_restore_entry(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|()| {
+                        LairWire::ToCliLairRestoreEntryResponse { msg_id }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairActivateEntry {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_activate_entry(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|()| {
+                        LairWire::ToCliLairActivateEntryResponse { msg_id }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairReloadSettings { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_reload_settings());
+                Ok(async move {
+                    fut.await.map(|()| {
+                        LairWire::ToCliLairReloadSettingsResponse { msg_id }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairPurgeTrashed {
+                msg_id,
+                older_than_ms,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_purge_trashed(
+                        std::time::Duration::from_millis(older_than_ms),
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|purged| {
+                        LairWire::ToCliLairPurgeTrashedResponse {
+                            msg_id,
+                            purged,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairListQuarantined { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_list_quarantined());
+                Ok(async move {
+                    fut.await.map(|entries| {
+                        LairWire::ToCliLairListQuarantinedResponse {
+                            msg_id,
+                            entries,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSelfTest { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_self_test());
+                Ok(async move {
+                    fut.await.map(|()| LairWire::ToCliLairSelfTestResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                msg_id,
+                cert_alg,
+            } => {
+                let options = TlsCertOptions { alg: cert_alg };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_new_self_signed_from_entropy(options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertNewSelfSignedFromSignKey {
+                msg_id,
+                keystore_index,
+                cert_alg,
+            } => {
+                let options = TlsCertOptions { alg: cert_alg };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_new_self_signed_from_sign_key(
+                        keystore_index,
+                        options,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertNewSelfSignedFromSignKeyResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGet {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.tls_cert_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|(cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertGetResponse {
+                            msg_id,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetInfo {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_info(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|(cert_sni, cert_digest, cert_alg)| {
+                        LairWire::ToCliTlsCertGetInfoResponse {
+                            msg_id,
+                            cert_sni,
+                            cert_digest,
+                            cert_alg,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertByIndex {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_cert_by_index(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertByIndexResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertByDigest {
+                msg_id,
+                cert_digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_cert_by_digest(cert_digest),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertByDigestResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetDigest {
+                msg_id,
+                keystore_index,
+                digest_alg,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_digest(keystore_index, digest_alg),
+                );
+                Ok(async move {
+                    fut.await.map(|digest| {
+                        LairWire::ToCliTlsCertGetDigestResponse {
+                            msg_id,
+                            digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertByTypedDigest {
+                msg_id,
+                digest_alg,
+                digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_cert_by_typed_digest(digest_alg, digest),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertByTypedDigestResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertBySni { msg_id, cert_sni } => {
+                let fut = self.kill_switch.mix_static(
                     self.api_sender.tls_cert_get_cert_by_sni(cert_sni),
                 );
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertBySniResponse {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertBySniResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertResolveByDigest {
+                msg_id,
+                cert_digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_resolve_by_digest(cert_digest),
+                );
+                Ok(async move {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliTlsCertResolveByDigestResponse {
+                            msg_id,
+                            keystore_index,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertResolveBySni { msg_id, cert_sni } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_resolve_by_sni(cert_sni),
+                );
+                Ok(async move {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliTlsCertResolveBySniResponse {
+                            msg_id,
+                            keystore_index,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_priv_key_by_index(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                msg_id,
+                cert_digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_priv_key_by_digest(cert_digest),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyBySni { msg_id, cert_sni } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_priv_key_by_sni(cert_sni),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetBundleByTag { msg_id, tag } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_bundle_by_tag(tag),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(cert_sni, cert_digest, cert, cert_priv_key)| {
+                            LairWire::ToCliTlsCertGetBundleByTagResponse {
+                                msg_id,
+                                cert_sni,
+                                cert_digest,
+                                cert,
+                                cert_priv_key,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromEntropy { msg_id } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_new_from_entropy(),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519New { msg_id, backing } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_ed25519_new(backing));
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromEntropyWithOptions {
+                msg_id,
+                options,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_new_from_entropy_with_options(options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromEntropyWithOptionsResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromSeedWithOptions {
+                msg_id,
+                seed,
+                options,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_new_from_seed_with_options(seed, options),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(keystore_index, pub_key, already_existed)| {
+                            LairWire::ToCliSignEd25519NewFromSeedWithOptionsResponse {
+                                msg_id,
+                                keystore_index,
+                                pub_key,
+                                already_existed,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519GetOptions {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_get_options(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|options| {
+                        LairWire::ToCliSignEd25519GetOptionsResponse {
+                            msg_id,
+                            options,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519Rotate {
+                msg_id,
+                old_keystore_index,
+                options,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_rotate(old_keystore_index, options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, record)| {
+                        LairWire::ToCliSignEd25519RotateResponse {
+                            msg_id,
+                            keystore_index,
+                            record,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519ExportSeed {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_export_seed(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|seed| {
+                        LairWire::ToCliSignEd25519ExportSeedResponse {
+                            msg_id,
+                            seed,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519ImportSshKey {
+                msg_id,
+                key,
+                passphrase,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_import_ssh_key(key, passphrase),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(keystore_index, pub_key, already_existed)| {
+                            LairWire::ToCliSignEd25519ImportSshKeyResponse {
+                                msg_id,
+                                keystore_index,
+                                pub_key,
+                                already_existed,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_get(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSignEd25519GetResponse {
+                            msg_id,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519ResolveByPubKey { msg_id, pub_key } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_resolve_by_pub_key(pub_key),
+                );
+                Ok(async move {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliSignEd25519ResolveByPubKeyResponse {
+                            msg_id,
+                            keystore_index,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndex {
+                msg_id,
+                keystore_index,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_index(keystore_index, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndex64 {
+                msg_id,
+                keystore_index,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_index_64(keystore_index, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByIndex64Response {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByPubKey {
+                msg_id,
+                pub_key,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_pub_key(pub_key, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndexV2 {
+                msg_id,
+                keystore_index,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_index_v2(keystore_index, message),
+                );
+                Ok(async move {
+                    fut.await.map(|(pub_key, signature)| {
+                        LairWire::ToCliSignEd25519SignByIndexV2Response {
+                            msg_id,
+                            pub_key,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndexWithOptions {
+                msg_id,
+                keystore_index,
+                message,
+                options,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_by_index_with_options(
+                        keystore_index,
+                        message,
+                        options,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(signature, timestamp)| {
+                        LairWire::ToCliSignEd25519SignByIndexWithOptionsResponse {
+                            msg_id,
+                            signature,
+                            timestamp,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByPubKeyV2 {
+                msg_id,
+                pub_key,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_pub_key_v2(pub_key, message),
+                );
+                Ok(async move {
+                    fut.await.map(|(pub_key, signature)| {
+                        LairWire::ToCliSignEd25519SignByPubKeyV2Response {
+                            msg_id,
+                            pub_key,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByTag {
+                msg_id,
+                tag,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_by_tag(tag, message),
+                );
+                Ok(async move {
+                    fut.await.map(|(pub_key, signature)| {
+                        LairWire::ToCliSignEd25519SignByTagResponse {
+                            msg_id,
+                            pub_key,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519PhSignByIndex {
+                msg_id,
+                keystore_index,
+                prehash,
+                context,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_ph_sign_by_index(
+                        keystore_index,
+                        prehash,
+                        context,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519PhSignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSecp256k1NewFromEntropy { msg_id } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_secp256k1_new_from_entropy(),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignSecp256k1NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSecp256k1NewFromSeed { msg_id, seed } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_secp256k1_new_from_seed(seed),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(keystore_index, pub_key, already_existed)| {
+                            LairWire::ToCliSignSecp256k1NewFromSeedResponse {
+                                msg_id,
+                                keystore_index,
+                                pub_key,
+                                already_existed,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSecp256k1Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_secp256k1_get(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSignSecp256k1GetResponse {
+                            msg_id,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSecp256k1SignByIndex {
+                msg_id,
+                keystore_index,
+                message_hash,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_secp256k1_sign_by_index(
+                        keystore_index,
+                        message_hash,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(signature, recovery_id)| {
+                        LairWire::ToCliSignSecp256k1SignByIndexResponse {
+                            msg_id,
+                            signature,
+                            recovery_id,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd448NewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_ed448_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd448NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd448Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_ed448_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSignEd448GetResponse { msg_id, pub_key }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd448SignByIndex {
+                msg_id,
+                keystore_index,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed448_sign_by_index(keystore_index, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd448SignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd448SignByPubKey {
+                msg_id,
+                pub_key,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed448_sign_by_pub_key(pub_key, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd448SignByPubKeyResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignBlsNewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_bls_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignBlsNewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignBlsGet {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_bls_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|pub_key| LairWire::ToCliSignBlsGetResponse {
+                        msg_id,
+                        pub_key,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignBlsSignByIndex {
+                msg_id,
+                keystore_index,
+                message,
+                dst,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_bls_sign_by_index(
+                        keystore_index,
+                        message,
+                        dst,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignBlsSignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSr25519NewFromEntropy { msg_id } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_sr25519_new_from_entropy(),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignSr25519NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSr25519NewFromSeed { msg_id, seed } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_sr25519_new_from_seed(seed),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(keystore_index, pub_key, already_existed)| {
+                            LairWire::ToCliSignSr25519NewFromSeedResponse {
+                                msg_id,
+                                keystore_index,
+                                pub_key,
+                                already_existed,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignSr25519Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_sr25519_get(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSignSr25519GetResponse {
                             msg_id,
-                            cert,
+                            pub_key,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+            LairWire::ToLairSignSr25519SignByIndex {
+                msg_id,
+                keystore_index,
+                message,
+                context,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_sr25519_sign_by_index(
+                        keystore_index,
+                        message,
+                        context,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignSr25519SignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519NewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.x25519_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliX25519NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.x25519_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|pub_key| LairWire::ToCliX25519GetResponse {
+                        msg_id,
+                        pub_key,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairCryptoBoxToSignPubKey {
+                msg_id,
+                sender_x25519_index,
+                recipient_sign_pub_key,
+                data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.crypto_box_to_sign_pub_key(
+                        sender_x25519_index,
+                        recipient_sign_pub_key,
+                        data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxToSignPubKeyResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairCryptoBoxOpenBySignPubKey {
+                msg_id,
+                recipient_sign_pub_key,
+                sender_x25519_pub_key,
+                data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.crypto_box_open_by_sign_pub_key(
+                        recipient_sign_pub_key,
+                        sender_x25519_pub_key,
+                        data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxOpenBySignPubKeyResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairCryptoBoxSealOpenByIndex {
                 msg_id,
                 keystore_index,
+                data,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .tls_cert_get_priv_key_by_index(keystore_index),
+                        .crypto_box_seal_open_by_index(keystore_index, data),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
                             msg_id,
-                            cert_priv_key,
+                            data,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+            LairWire::ToLairAgeDecryptByIndex {
                 msg_id,
-                cert_digest,
+                keystore_index,
+                stanzas,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .tls_cert_get_priv_key_by_digest(cert_digest),
+                        .age_decrypt_by_index(keystore_index, stanzas),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    fut.await.map(|file_key| {
+                        LairWire::ToCliAgeDecryptByIndexResponse {
                             msg_id,
-                            cert_priv_key,
+                            file_key,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyBySni { msg_id, cert_sni } => {
+            LairWire::ToLairPwPepperNewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.pw_pepper_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliPwPepperNewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairPwHash {
+                msg_id,
+                keystore_index,
+                password,
+                options,
+            } => {
+                let fut = self.kill_switch.mix_static(self.api_sender.pw_hash(
+                    keystore_index,
+                    password,
+                    options,
+                ));
+                Ok(async move {
+                    fut.await.map(|hash| LairWire::ToCliPwHashResponse {
+                        msg_id,
+                        hash,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairPwVerify {
+                msg_id,
+                keystore_index,
+                password,
+                hash,
+            } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_priv_key_by_sni(cert_sni),
+                    self.api_sender.pw_verify(keystore_index, password, hash),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    fut.await.map(|valid| LairWire::ToCliPwVerifyResponse {
+                        msg_id,
+                        valid,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTotpNewFromEntropy { msg_id, options } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.totp_new_from_entropy(options));
+                Ok(async move {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliTotpNewFromEntropyResponse {
                             msg_id,
-                            cert_priv_key,
+                            keystore_index,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519NewFromEntropy { msg_id } => {
+            LairWire::ToLairTotpImport {
+                msg_id,
+                secret_b32,
+                options,
+            } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.sign_ed25519_new_from_entropy(),
+                    self.api_sender.totp_import(secret_b32, options),
                 );
                 Ok(async move {
-                    fut.await.map(|(keystore_index, pub_key)| {
-                        LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                    fut.await.map(|keystore_index| {
+                        LairWire::ToCliTotpImportResponse {
                             msg_id,
                             keystore_index,
-                            pub_key,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519Get {
+            LairWire::ToLairTotpGenerate {
                 msg_id,
                 keystore_index,
+                at,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.sign_ed25519_get(keystore_index),
+                    self.api_sender.totp_generate(keystore_index, at),
                 );
                 Ok(async move {
-                    fut.await.map(|pub_key| {
-                        LairWire::ToCliSignEd25519GetResponse {
+                    fut.await.map(|code| LairWire::ToCliTotpGenerateResponse {
+                        msg_id,
+                        code,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTotpExportProvisioningUri {
+                msg_id,
+                keystore_index,
+                label,
+                issuer,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.totp_export_provisioning_uri(
+                        keystore_index,
+                        label,
+                        issuer,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|uri| {
+                        LairWire::ToCliTotpExportProvisioningUriResponse {
+                            msg_id,
+                            uri,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairEphemeralX25519New { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.ephemeral_x25519_new());
+                Ok(async move {
+                    fut.await.map(|(handle, pub_key)| {
+                        LairWire::ToCliEphemeralX25519NewResponse {
                             msg_id,
+                            handle,
                             pub_key,
                         }
                     })
@@ -396,18 +2488,34 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519SignByIndex {
+            LairWire::ToLairEphemeralSignEd25519New { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.ephemeral_sign_ed25519_new());
+                Ok(async move {
+                    fut.await.map(|(handle, pub_key)| {
+                        LairWire::ToCliEphemeralSignEd25519NewResponse {
+                            msg_id,
+                            handle,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairEphemeralSignEd25519Sign {
                 msg_id,
-                keystore_index,
+                handle,
                 message,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .sign_ed25519_sign_by_index(keystore_index, message),
+                        .ephemeral_sign_ed25519_sign(handle, message),
                 );
                 Ok(async move {
                     fut.await.map(|signature| {
-                        LairWire::ToCliSignEd25519SignByIndexResponse {
+                        LairWire::ToCliEphemeralSignEd25519SignResponse {
                             msg_id,
                             signature,
                         }
@@ -416,26 +2524,154 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519SignByPubKey {
+            LairWire::ToLairEphemeralCryptoBox {
                 msg_id,
-                pub_key,
-                message,
+                sender_handle,
+                recipient_pub_key,
+                data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.ephemeral_crypto_box(
+                        sender_handle,
+                        recipient_pub_key,
+                        data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliEphemeralCryptoBoxResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairEphemeralCryptoBoxOpen {
+                msg_id,
+                recipient_handle,
+                sender_pub_key,
+                data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.ephemeral_crypto_box_open(
+                        recipient_handle,
+                        sender_pub_key,
+                        data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliEphemeralCryptoBoxOpenResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairEphemeralDrop { msg_id, handle } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.ephemeral_drop(handle));
+                Ok(async move {
+                    fut.await.map(|()| LairWire::ToCliEphemeralDropResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairAttestEntry {
+                msg_id,
+                keystore_index,
+                challenge,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .sign_ed25519_sign_by_pub_key(pub_key, message),
+                        .lair_attest_entry(keystore_index, challenge),
                 );
                 Ok(async move {
-                    fut.await.map(|signature| {
-                        LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    fut.await.map(|attestation| {
+                        LairWire::ToCliLairAttestEntryResponse {
                             msg_id,
-                            signature,
+                            attestation,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairWrapEntry {
+                msg_id,
+                target_index,
+                recipient_pub_key,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .lair_wrap_entry(target_index, recipient_pub_key),
+                );
+                Ok(async move {
+                    fut.await.map(|wrapped| {
+                        LairWire::ToCliLairWrapEntryResponse { msg_id, wrapped }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairUnwrapEntry {
+                msg_id,
+                recipient_index,
+                wrapped,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_unwrap_entry(recipient_index, wrapped),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(keystore_index, lair_entry_type, already_existed)| {
+                            LairWire::ToCliLairUnwrapEntryResponse {
+                                msg_id,
+                                keystore_index,
+                                lair_entry_type,
+                                already_existed,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetRandomBytes { msg_id, len } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_random_bytes(len));
+                Ok(async move {
+                    fut.await.map(|bytes| {
+                        LairWire::ToCliLairGetRandomBytesResponse {
+                            msg_id,
+                            bytes,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
+            LairWire::ToLairLairNewUuid { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_new_uuid());
+                Ok(async move {
+                    fut.await.map(|uuid| LairWire::ToCliLairNewUuidResponse {
+                        msg_id,
+                        uuid,
+                    })
+                }
+                .boxed()
+                .into())
+            }
             o => Err(format!("unexpected: {:?}", o).into()),
         }
     }
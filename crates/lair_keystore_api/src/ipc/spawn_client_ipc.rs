@@ -3,13 +3,73 @@ use crate::internal::ipc::*;
 use crate::internal::wire::*;
 use futures::{future::FutureExt, stream::StreamExt};
 
-#[allow(clippy::single_match)]
 pub(crate) async fn spawn_client_ipc(
     config: Arc<Config>,
     evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
 ) -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
-    let (kill_switch, ipc_send, mut ipc_recv) =
-        spawn_ipc_connection(config).await?;
+    let (sender, _) =
+        spawn_client_ipc_with_kill_switch(config, evt_send).await?;
+    Ok(sender)
+}
+
+/// `spawn_client_ipc`, also returning a weak kill switch of the
+/// connection, which stops continuing once it closes. The sender stays
+/// active after a server closes the connection with a reason,
+/// such as `LairError::ConnectionIdle`, to keep failing requests with it.
+pub(crate) async fn spawn_client_ipc_with_kill_switch(
+    config: Arc<Config>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+) -> LairResult<(ghost_actor::GhostSender<LairClientApi>, KillSwitch)> {
+    let con = spawn_ipc_connection(config.clone()).await?;
+    spawn_client_on_connection(config, con, evt_send).await
+}
+
+/// `spawn_client_ipc_with_kill_switch`, on the already open `con`.
+#[allow(clippy::single_match)]
+pub(crate) async fn spawn_client_on_connection(
+    config: Arc<Config>,
+    con: (KillSwitch, IpcSender, IpcReceiver),
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+) -> LairResult<(ghost_actor::GhostSender<LairClientApi>, KillSwitch)> {
+    let token = config.get_connection_url().token().map(str::to_string);
+    let wire_compression = config.get_wire_compression();
+    let expected_server_identity =
+        config.get_expected_server_identity().cloned();
+    let allow_unproven_server_identity =
+        config.get_allow_unproven_server_identity();
+    let entropy = config.get_entropy().clone();
+    let (kill_switch, ipc_send, mut ipc_recv) = con;
+
+    // present the token before anything else, the server
+    // serves nothing until it has
+    if let Some(token) = token {
+        match kill_switch
+            .mix(ipc_send.request(LairWire::ToLairAuthenticate {
+                msg_id: next_msg_id(),
+                token,
+            }))
+            .await?
+        {
+            LairWire::ToCliAuthenticateResponse { .. } => (),
+            o => return Err(format!("unexpected: {:?}", o).into()),
+        }
+    }
+
+    // check the server identity before answering any event,
+    // so an impostor is never sent the unlock passphrase
+    let mut server_wire_version = None;
+    if let Some(expected) = expected_server_identity {
+        server_wire_version = Some(
+            check_server_identity(
+                &kill_switch,
+                &ipc_send,
+                &entropy,
+                &expected,
+                allow_unproven_server_identity,
+            )
+            .await?,
+        );
+    }
 
     let evt_kill_switch = kill_switch.clone();
     err_spawn("client-ipc-evt-loop", async move {
@@ -24,16 +84,157 @@ pub(crate) async fn spawn_client_ipc(
         {
             match msg {
                 IpcWireApi::Request { respond, msg, .. } => match msg {
-                    LairWire::ToCliRequestUnlockPassphrase { msg_id } => {
-                        let res = evt_kill_switch.mix(evt_send
-                                .request_unlock_passphrase()).await
-                                .map(|passphrase| {
-                                    LairWire::ToLairRequestUnlockPassphraseResponse {
-                                        msg_id,
-                                        passphrase,
-                                    }
-                                });
-                        respond.respond(Ok(async move { res }.boxed().into()));
+                    LairWire::ToCliRequestUnlockPassphrase {
+                        msg_id,
+                        store_name,
+                    } => {
+                        // answered in its own task, so the application
+                        // may call back in while handling it
+                        let fut = evt_kill_switch.mix_static(
+                            evt_send.request_unlock_passphrase(store_name),
+                        );
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairRequestUnlockPassphraseResponse {
+                                msg_id,
+                                passphrase: fut.await?,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliUnlockProgress { msg_id, percent } => {
+                        // the application may not listen for progress
+                        let _ = evt_kill_switch
+                            .mix(evt_send.unlock_progress(percent))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairUnlockProgressResponse {
+                                msg_id,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliUnlockComplete { msg_id, error } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_send.unlock_complete(error))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairUnlockCompleteResponse {
+                                msg_id,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliEventsDropped { msg_id, count } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_send.events_dropped(count))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairEventsDroppedResponse { msg_id })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliEntryCreated {
+                        msg_id,
+                        keystore_index,
+                        entry_type,
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(
+                                evt_send
+                                    .entry_created(keystore_index, entry_type),
+                            )
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairEntryCreatedResponse { msg_id })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliRequestSignApproval {
+                        msg_id,
+                        keystore_index,
+                        pub_key,
+                    } => {
+                        let fut = evt_kill_switch.mix_static(
+                            evt_send
+                                .request_sign_approval(keystore_index, pub_key),
+                        );
+                        respond.respond(Ok(async move {
+                            // an application not answering refuses
+                            let approved = fut.await.unwrap_or(false);
+                            Ok(LairWire::ToLairRequestSignApprovalResponse {
+                                msg_id,
+                                approved,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliClockSkewDetected {
+                        msg_id,
+                        wall_secs,
+                        expected_secs,
+                    } => {
+                        let _ =
+                            evt_kill_switch
+                                .mix(evt_send.clock_skew_detected(
+                                    wall_secs,
+                                    expected_secs,
+                                ))
+                                .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairClockSkewDetectedResponse {
+                                msg_id,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliEntryActivated {
+                        msg_id,
+                        keystore_index,
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_send.entry_activated(keystore_index))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairEntryActivatedResponse {
+                                msg_id,
+                            })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliAuditEvent {
+                        msg_id,
+                        record,
+                        dropped,
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_send.audit_event(record, dropped))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairAuditEventResponse { msg_id })
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairWire::ToCliEntryRemoved {
+                        msg_id,
+                        keystore_index,
+                    } => {
+                        let _ = evt_kill_switch
+                            .mix(evt_send.entry_removed(keystore_index))
+                            .await;
+                        respond.respond(Ok(async move {
+                            Ok(LairWire::ToLairEntryRemovedResponse { msg_id })
+                        }
+                        .boxed()
+                        .into()));
                     }
                     _ => (),
                 },
@@ -42,6 +243,27 @@ pub(crate) async fn spawn_client_ipc(
         Ok(())
     });
 
+    // agree on compressing large frames, with servers able to
+    if wire_compression && compress::SUPPORTED_ALGORITHMS != 0 {
+        let version = match server_wire_version {
+            Some(version) => version,
+            None => get_server_wire_version(&kill_switch, &ipc_send).await?,
+        };
+        server_wire_version = Some(version);
+        if version >= spec::COMPRESSION_VERSION {
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairNegotiateCompression {
+                    msg_id: next_msg_id(),
+                    algorithms: compress::SUPPORTED_ALGORITHMS,
+                }))
+                .await?
+            {
+                LairWire::ToCliNegotiateCompressionResponse { .. } => (),
+                o => return Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+    }
+
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
     let sender = builder
@@ -50,10 +272,17 @@ pub(crate) async fn spawn_client_ipc(
         .await?;
 
     let kill_sender = sender.clone();
+    let weak_kill_switch = kill_switch.weak();
+    let con_kill_switch = kill_switch.weak();
     kill_switch
         .register_kill_callback(Box::new(move || {
             Box::pin(async move {
                 use ghost_actor::GhostControlSender;
+                // a server closing with a reason keeps failing
+                // requests with it, until the last sender is dropped
+                if weak_kill_switch.kill_reason().is_some() {
+                    return;
+                }
                 if let Err(err) = kill_sender.ghost_actor_shutdown().await {
                     ghost_actor::dependencies::tracing::error!(?err);
                 }
@@ -66,17 +295,273 @@ pub(crate) async fn spawn_client_ipc(
             .spawn(Internal {
                 kill_switch,
                 ipc_send,
+                server_wire_version: Arc::new(std::sync::Mutex::new(
+                    server_wire_version,
+                )),
             })
             .await
             .map_err(LairError::other)
     });
 
-    Ok(sender)
+    Ok((sender, con_kill_switch))
+}
+
+/// Ask the server its wire protocol version with Get Server Info.
+async fn get_server_wire_version(
+    kill_switch: &KillSwitch,
+    ipc_send: &IpcSender,
+) -> LairResult<u32> {
+    match kill_switch
+        .mix(ipc_send.request(LairWire::ToLairLairGetServerInfo {
+            msg_id: next_msg_id(),
+        }))
+        .await?
+    {
+        LairWire::ToCliLairGetServerInfoResponse { info, .. } => {
+            Ok(info.wire_protocol_version)
+        }
+        o => Err(format!("unexpected: {:?}", o).into()),
+    }
+}
+
+/// Have the server prove it holds the `expected` identity keypair,
+/// signing its versions and a fresh nonce. Servers predating
+/// `spec::SERVER_IDENTITY_VERSION` cannot, they are refused unless
+/// `allow_unproven`. Resolves to the server wire protocol version.
+async fn check_server_identity(
+    kill_switch: &KillSwitch,
+    ipc_send: &IpcSender,
+    entropy: &crate::internal::entropy::EntropySourceHandle,
+    expected: &SignEd25519PubKey,
+    allow_unproven: bool,
+) -> LairResult<u32> {
+    let version = get_server_wire_version(kill_switch, ipc_send).await?;
+    if version < spec::SERVER_IDENTITY_VERSION {
+        let reason = format!(
+            "the server (wire protocol version {}) cannot prove its identity",
+            version
+        );
+        if allow_unproven {
+            warn!("{}, connecting anyway", reason);
+            return Ok(version);
+        }
+        return Err(LairError::ServerIdentityMismatch(reason));
+    }
+    let nonce = crate::internal::entropy::random_bytes(
+        entropy,
+        SERVER_IDENTITY_NONCE_LEN as u32,
+    )?;
+    let proof = match kill_switch
+        .mix(ipc_send.request(LairWire::ToLairLairProveServerIdentity {
+            msg_id: next_msg_id(),
+            nonce: nonce.clone(),
+        }))
+        .await
+    {
+        Ok(LairWire::ToCliLairProveServerIdentityResponse {
+            proof, ..
+        }) => proof,
+        Ok(o) => return Err(format!("unexpected: {:?}", o).into()),
+        Err(e) => {
+            return Err(LairError::ServerIdentityMismatch(format!(
+                "the server failed to prove its identity: {}",
+                e
+            )))
+        }
+    };
+    if !proof.verify(expected, &nonce) {
+        return Err(LairError::ServerIdentityMismatch(format!(
+            "expected server identity {}, got a proof by {}",
+            base64::encode(&**expected.0),
+            base64::encode(&**proof.server_id_pub_key.0),
+        )));
+    }
+    if proof.wire_protocol_version != version {
+        return Err(LairError::ServerIdentityMismatch(format!(
+            "the server proved wire protocol version {}, but reports {}",
+            proof.wire_protocol_version, version
+        )));
+    }
+    Ok(version)
+}
+
+/// Servers predating `spec::SIGN_KEY_OPTIONS_VERSION` close the
+/// connection on requests carrying sign key options, never send them.
+fn sign_key_options_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support sign key options",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::ROTATION_VERSION` close the
+/// connection on rotation requests, never send them.
+fn rotation_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support key rotation",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::SEED_EXPORT_VERSION` close the
+/// connection on seed export requests, never send them.
+fn seed_export_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support seed export",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::ACTIVATION_VERSION` close the
+/// connection on activation requests, never send them.
+fn activation_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support activation",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::STORE_STATS_VERSION` close the
+/// connection on store stats requests, never send them.
+fn store_stats_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support store stats",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::SERVER_IDENTITY_VERSION` close the
+/// connection on server identity requests, never send them.
+fn server_identity_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support server identity proofs",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::SIGN_OPTIONS_VERSION` close the
+/// connection on requests carrying sign options, never send them.
+fn sign_options_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support sign options",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::BULK_ENTRIES_VERSION` close the
+/// connection on bulk entry requests, never send them.
+fn bulk_entries_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support bulk entry requests",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::ELEVATION_VERSION` close the
+/// connection on elevation requests, never send them.
+fn elevation_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support elevation",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::AUDIT_EVENT_VERSION` refuse
+/// event filters naming `EventKind::AuditEvent`.
+fn audit_events_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not send audit events",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::ENTRY_REMOVED_VERSION` refuse
+/// event filters naming `EventKind::EntryRemoved`.
+fn entry_removed_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not send entry removals",
+        server_wire_version
+    )
+    .into()
+}
+
+/// Servers predating `spec::EPHEMERAL_KEYS_VERSION` close the
+/// connection on ephemeral key requests, never send them.
+fn ephemeral_keys_unsupported(server_wire_version: u32) -> LairError {
+    format!(
+        "the server (wire protocol version {}) does not support ephemeral keys",
+        server_wire_version
+    )
+    .into()
 }
 
 struct Internal {
     kill_switch: KillSwitch,
     ipc_send: IpcSender,
+    /// the wire protocol version of the server, once asked
+    server_wire_version: Arc<std::sync::Mutex<Option<u32>>>,
+}
+
+impl Internal {
+    /// Resolves to the wire protocol version of the server,
+    /// asking it with Get Server Info the first time.
+    fn server_wire_version(
+        &self,
+    ) -> impl std::future::Future<Output = LairResult<u32>> + 'static + Send
+    {
+        let cached = *self.server_wire_version.lock().unwrap();
+        let server_wire_version = self.server_wire_version.clone();
+        let fut = match cached {
+            Some(_) => None,
+            None => Some(self.kill_switch.mix_static(self.ipc_send.request(
+                LairWire::ToLairLairGetServerInfo {
+                    msg_id: next_msg_id(),
+                },
+            ))),
+        };
+        async move {
+            let fut = match fut {
+                None => return Ok(cached.unwrap_or(0)),
+                Some(fut) => fut,
+            };
+            match fut.await? {
+                LairWire::ToCliLairGetServerInfoResponse { info, .. } => {
+                    *server_wire_version.lock().unwrap() =
+                        Some(info.wire_protocol_version);
+                    Ok(info.wire_protocol_version)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+    }
+
+    /// Send an ephemeral key `request`, if the server supports them.
+    fn ephemeral_request(
+        &self,
+        request: LairWire,
+    ) -> impl std::future::Future<Output = LairResult<LairWire>> + 'static + Send
+    {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        async move {
+            let version = version.await?;
+            if version < spec::EPHEMERAL_KEYS_VERSION {
+                return Err(ephemeral_keys_unsupported(version));
+            }
+            kill_switch.mix(ipc_send.request(request)).await
+        }
+    }
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
@@ -106,20 +591,19 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_lair_get_last_entry_index(
+    fn handle_lair_get_server_paths(
         &mut self,
-    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+    ) -> LairClientApiHandlerResult<LairServerPaths> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairLairGetLastEntryIndex {
+            LairWire::ToLairLairGetServerPaths {
                 msg_id: next_msg_id(),
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliLairGetLastEntryIndexResponse {
-                    last_keystore_index,
-                    ..
-                } => Ok(last_keystore_index),
+                LairWire::ToCliLairGetServerPathsResponse { paths, .. } => {
+                    Ok(paths)
+                }
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -127,22 +611,20 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_lair_get_entry_type(
+    fn handle_lair_get_capabilities(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<LairEntryType> {
+    ) -> LairClientApiHandlerResult<LairCapabilities> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairLairGetEntryType {
+            LairWire::ToLairLairGetCapabilities {
                 msg_id: next_msg_id(),
-                keystore_index,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliLairGetEntryTypeResponse {
-                    lair_entry_type,
+                LairWire::ToCliLairGetCapabilitiesResponse {
+                    capabilities,
                     ..
-                } => Ok(lair_entry_type),
+                } => Ok(capabilities),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -150,24 +632,31 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_new_self_signed_from_entropy(
+    fn handle_lair_request_elevation(
         &mut self,
-        options: TlsCertOptions,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
-                msg_id: next_msg_id(),
-                cert_alg: options.alg,
-            },
-        ));
+        capabilities: LairCapabilities,
+        duration: std::time::Duration,
+    ) -> LairClientApiHandlerResult<LairElevation> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
         Ok(async move {
-            match fut.await? {
-                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
-                    keystore_index,
-                    cert_sni,
-                    cert_digest,
+            let version = version.await?;
+            if version < spec::ELEVATION_VERSION {
+                return Err(elevation_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairRequestElevation {
+                    msg_id: next_msg_id(),
+                    capabilities,
+                    duration_ms: duration.as_millis() as u64,
+                }))
+                .await?
+            {
+                LairWire::ToCliLairRequestElevationResponse {
+                    elevation,
                     ..
-                } => Ok((keystore_index, cert_sni, cert_digest)),
+                } => Ok(elevation),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -175,23 +664,70 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get(
+    fn handle_lair_get_elevation(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+    ) -> LairClientApiHandlerResult<Option<LairElevation>> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            // older servers grant no elevation
+            if version.await? < spec::ELEVATION_VERSION {
+                return Ok(None);
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairGetElevation {
+                    msg_id: next_msg_id(),
+                }))
+                .await?
+            {
+                LairWire::ToCliLairGetElevationResponse {
+                    elevation, ..
+                } => Ok(elevation),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_drop_elevation(&mut self) -> LairClientApiHandlerResult<()> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            // older servers grant no elevation, there is none to drop
+            if version.await? < spec::ELEVATION_VERSION {
+                return Ok(());
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairDropElevation {
+                    msg_id: next_msg_id(),
+                }))
+                .await?
+            {
+                LairWire::ToCliLairDropElevationResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_export_device_secret(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGet {
+            LairWire::ToLairLairExportDeviceSecret {
                 msg_id: next_msg_id(),
-                keystore_index,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetResponse {
-                    cert_sni,
-                    cert_digest,
+                LairWire::ToCliLairExportDeviceSecretResponse {
+                    device_secret,
                     ..
-                } => Ok((cert_sni, cert_digest)),
+                } => Ok(device_secret),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -199,21 +735,20 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_index(
+    fn handle_lair_list_connections(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<Cert> {
+    ) -> LairClientApiHandlerResult<Vec<LairConnectionInfo>> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertByIndex {
+            LairWire::ToLairLairListConnections {
                 msg_id: next_msg_id(),
-                keystore_index,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetCertByIndexResponse {
-                    cert, ..
-                } => Ok(cert),
+                LairWire::ToCliLairListConnectionsResponse {
+                    connections,
+                    ..
+                } => Ok(connections),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -221,21 +756,17 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_digest(
+    fn handle_lair_reload_settings(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<Cert> {
+    ) -> LairClientApiHandlerResult<()> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertByDigest {
+            LairWire::ToLairLairReloadSettings {
                 msg_id: next_msg_id(),
-                cert_digest,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetCertByDigestResponse {
-                    cert, ..
-                } => Ok(cert),
+                LairWire::ToCliLairReloadSettingsResponse { .. } => Ok(()),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -243,21 +774,33 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_sni(
+    fn handle_lair_set_event_filter(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertBySni {
-                msg_id: next_msg_id(),
-                cert_sni,
-            },
-        ));
+        kinds: Vec<EventKind>,
+    ) -> LairClientApiHandlerResult<()> {
+        let audit = kinds.contains(&EventKind::AuditEvent);
+        let removed = kinds.contains(&EventKind::EntryRemoved);
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
         Ok(async move {
-            match fut.await? {
-                LairWire::ToCliTlsCertGetCertBySniResponse { cert, .. } => {
-                    Ok(cert)
+            if audit || removed {
+                let version = version.await?;
+                if audit && version < spec::AUDIT_EVENT_VERSION {
+                    return Err(audit_events_unsupported(version));
+                }
+                if removed && version < spec::ENTRY_REMOVED_VERSION {
+                    return Err(entry_removed_unsupported(version));
                 }
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairSetEventFilter {
+                    msg_id: next_msg_id(),
+                    kinds,
+                }))
+                .await?
+            {
+                LairWire::ToCliLairSetEventFilterResponse { .. } => Ok(()),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -265,22 +808,19 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_index(
+    fn handle_lair_select_store(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        name: String,
+    ) -> LairClientApiHandlerResult<()> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+            LairWire::ToLairLairSelectStore {
                 msg_id: next_msg_id(),
-                keystore_index,
+                name,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
-                    cert_priv_key,
-                    ..
-                } => Ok(cert_priv_key),
+                LairWire::ToCliLairSelectStoreResponse { .. } => Ok(()),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -288,22 +828,20 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_digest(
+    fn handle_lair_get_last_entry_index(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex>> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+            LairWire::ToLairLairGetLastEntryIndex {
                 msg_id: next_msg_id(),
-                cert_digest,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
-                    cert_priv_key,
+                LairWire::ToCliLairGetLastEntryIndexResponse {
+                    last_keystore_index,
                     ..
-                } => Ok(cert_priv_key),
+                } => Ok(last_keystore_index),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -311,22 +849,40 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_sni(
+    fn handle_lair_get_last_entry_index_64(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyBySni {
-                msg_id: next_msg_id(),
-                cert_sni,
-            },
-        ));
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex64>> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
         Ok(async move {
-            match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
-                    cert_priv_key,
+            if version.await? >= spec::KEYSTORE_INDEX64_VERSION {
+                return match kill_switch
+                    .mix(ipc_send.request(
+                        LairWire::ToLairLairGetLastEntryIndex64 {
+                            msg_id: next_msg_id(),
+                        },
+                    ))
+                    .await?
+                {
+                    LairWire::ToCliLairGetLastEntryIndex64Response {
+                        last_keystore_index,
+                        ..
+                    } => Ok(last_keystore_index),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairGetLastEntryIndex {
+                    msg_id: next_msg_id(),
+                }))
+                .await?
+            {
+                LairWire::ToCliLairGetLastEntryIndexResponse {
+                    last_keystore_index,
                     ..
-                } => Ok(cert_priv_key),
+                } => Ok(last_keystore_index.map(KeystoreIndex64::from)),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -334,21 +890,20 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_sign_ed25519_new_from_entropy(
+    fn handle_lair_get_entry_count(
         &mut self,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+    ) -> LairClientApiHandlerResult<u64> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519NewFromEntropy {
+            LairWire::ToLairLairGetEntryCount {
                 msg_id: next_msg_id(),
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519NewFromEntropyResponse {
-                    keystore_index,
-                    pub_key,
+                LairWire::ToCliLairGetEntryCountResponse {
+                    entry_count,
                     ..
-                } => Ok((keystore_index, pub_key)),
+                } => Ok(entry_count),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -356,20 +911,25 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_sign_ed25519_get(
+    fn handle_lair_get_store_stats(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<SignEd25519PubKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519Get {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+    ) -> LairClientApiHandlerResult<StoreStats> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
         Ok(async move {
-            match fut.await? {
-                LairWire::ToCliSignEd25519GetResponse { pub_key, .. } => {
-                    Ok(pub_key)
+            let version = version.await?;
+            if version < spec::STORE_STATS_VERSION {
+                return Err(store_stats_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairGetStoreStats {
+                    msg_id: next_msg_id(),
+                }))
+                .await?
+            {
+                LairWire::ToCliLairGetStoreStatsResponse { stats, .. } => {
+                    Ok(stats)
                 }
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
@@ -378,24 +938,26 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_index(
+    fn handle_lair_list_entries_page(
         &mut self,
-        keystore_index: KeystoreIndex,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        start: KeystoreIndex,
+        limit: u32,
+    ) -> LairClientApiHandlerResult<(Vec<LairEntryInfo>, Option<KeystoreIndex>)>
+    {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519SignByIndex {
+            LairWire::ToLairLairListEntriesPage {
                 msg_id: next_msg_id(),
-                keystore_index,
-                message,
+                start,
+                limit,
             },
         ));
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519SignByIndexResponse {
-                    signature,
+                LairWire::ToCliLairListEntriesPageResponse {
+                    entries,
+                    next_start,
                     ..
-                } => Ok(signature),
+                } => Ok((entries, next_start)),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
         }
@@ -403,23 +965,2303 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_pub_key(
+    fn handle_lair_get_entry_type(
         &mut self,
-        pub_key: SignEd25519PubKey,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
         let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519SignByPubKey {
+            LairWire::ToLairLairGetEntryType {
                 msg_id: next_msg_id(),
-                pub_key,
-                message,
+                keystore_index,
             },
         ));
         Ok(async move {
             match fut.await? {
+                LairWire::ToCliLairGetEntryTypeResponse {
+                    lair_entry_type,
+                    ..
+                } => Ok(lair_entry_type),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_type_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let request = if version.await? >= spec::KEYSTORE_INDEX64_VERSION {
+                LairWire::ToLairLairGetEntryType64 {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }
+            } else {
+                LairWire::ToLairLairGetEntryType {
+                    msg_id: next_msg_id(),
+                    keystore_index: std::convert::TryInto::try_into(
+                        keystore_index,
+                    )?,
+                }
+            };
+            match kill_switch.mix(ipc_send.request(request)).await? {
+                LairWire::ToCliLairGetEntryType64Response {
+                    lair_entry_type,
+                    ..
+                }
+                | LairWire::ToCliLairGetEntryTypeResponse {
+                    lair_entry_type,
+                    ..
+                } => Ok(lair_entry_type),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_types(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        // an unencodable request would take down the connection
+        check_entry_types_len(indices.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairGetEntryTypes {
+                msg_id: next_msg_id(),
+                indices,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetEntryTypesResponse {
+                    entry_types,
+                    ..
+                } => Ok(entry_types),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_erase_entries(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        // an unencodable request would take down the connection
+        check_bulk_entries_len(indices.len())?;
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::BULK_ENTRIES_VERSION {
+                return Err(bulk_entries_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairEraseEntries {
+                    msg_id: next_msg_id(),
+                    indices,
+                }))
+                .await?
+            {
+                LairWire::ToCliLairEraseEntriesResponse { results, .. } => {
+                    Ok(bulk_results_from_wire(results))
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_set_entry_tags(
+        &mut self,
+        tags: Vec<(KeystoreIndex, String)>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        // an unencodable request would take down the connection
+        check_bulk_entries_len(tags.len())?;
+        for (_, tag) in tags.iter() {
+            if tag.len() > MAX_ENTRY_TAG_LEN {
+                return Err(format!(
+                    "entry tag exceeds {} byte maximum",
+                    MAX_ENTRY_TAG_LEN
+                )
+                .into());
+            }
+        }
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::BULK_ENTRIES_VERSION {
+                return Err(bulk_entries_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairSetEntryTags {
+                    msg_id: next_msg_id(),
+                    tags,
+                }))
+                .await?
+            {
+                LairWire::ToCliLairSetEntryTagsResponse { results, .. } => {
+                    Ok(bulk_results_from_wire(results))
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_set_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: Option<String>,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairSetEntryTag {
+                msg_id: next_msg_id(),
+                keystore_index,
+                tag,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairSetEntryTagResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairGetEntryByTag {
+                msg_id: next_msg_id(),
+                tag,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetEntryByTagResponse {
+                    keystore_index,
+                    lair_entry_type,
+                    ..
+                } => Ok((keystore_index, lair_entry_type)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_find_entries_by_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+        kind: PrefixKind,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairFindEntriesByPrefix {
+                msg_id: next_msg_id(),
+                prefix,
+                kind,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairFindEntriesByPrefixResponse {
+                    entries,
+                    ..
+                } => Ok(entries),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_add_entry_alias(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairAddEntryAlias {
+                msg_id: next_msg_id(),
+                keystore_index,
+                alias,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairAddEntryAliasResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_remove_entry_alias(
+        &mut self,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairRemoveEntryAlias {
+                msg_id: next_msg_id(),
+                alias,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairRemoveEntryAliasResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_trash_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairTrashEntry {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairTrashEntryResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_restore_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairRestoreEntry {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairRestoreEntryResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_activate_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::ACTIVATION_VERSION {
+                return Err(activation_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairLairActivateEntry {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliLairActivateEntryResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_purge_trashed(
+        &mut self,
+        older_than: std::time::Duration,
+    ) -> LairClientApiHandlerResult<u64> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairPurgeTrashed {
+                msg_id: next_msg_id(),
+                older_than_ms: older_than.as_millis() as u64,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairPurgeTrashedResponse { purged, .. } => {
+                    Ok(purged)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_list_quarantined(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<QuarantinedEntry>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairListQuarantined {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairListQuarantinedResponse {
+                    entries, ..
+                } => Ok(entries),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_self_test(&mut self) -> LairClientApiHandlerResult<()> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairSelfTest {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairSelfTestResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_self_signed_from_entropy(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                msg_id: next_msg_id(),
+                cert_alg: options.alg,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_self_signed_from_sign_key(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertNewSelfSignedFromSignKey {
+                msg_id: next_msg_id(),
+                keystore_index,
+                cert_alg: options.alg,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertNewSelfSignedFromSignKeyResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGet {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetResponse {
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((cert_sni, cert_digest)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_info(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, TlsCertAlg)> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            if version.await? >= spec::TLS_CERT_INFO_VERSION {
+                return match kill_switch
+                    .mix(ipc_send.request(LairWire::ToLairTlsCertGetInfo {
+                        msg_id: next_msg_id(),
+                        keystore_index,
+                    }))
+                    .await?
+                {
+                    LairWire::ToCliTlsCertGetInfoResponse {
+                        cert_sni,
+                        cert_digest,
+                        cert_alg,
+                        ..
+                    } => Ok((cert_sni, cert_digest, cert_alg)),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+
+            // older servers: read the algorithm from the cert
+            let (cert_sni, cert_digest) = match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairTlsCertGet {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliTlsCertGetResponse {
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => (cert_sni, cert_digest),
+                o => return Err(format!("unexpected: {:?}", o).into()),
+            };
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairTlsCertGetCertByIndex {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    cert, ..
+                } => {
+                    let cert_alg = crate::internal::tls::tls_cert_alg(&cert);
+                    Ok((cert_sni, cert_digest, cert_alg))
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetCertByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    cert, ..
+                } => Ok(cert),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetCertByDigest {
+                msg_id: next_msg_id(),
+                cert_digest,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertByDigestResponse {
+                    cert, ..
+                } => Ok(cert),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_digest(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest_alg: DigestAlg,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetDigest {
+                msg_id: next_msg_id(),
+                keystore_index,
+                digest_alg,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetDigestResponse { digest, .. } => {
+                    Ok(digest)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_typed_digest(
+        &mut self,
+        digest_alg: DigestAlg,
+        digest: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetCertByTypedDigest {
+                msg_id: next_msg_id(),
+                digest_alg,
+                digest,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertByTypedDigestResponse {
+                    cert,
+                    ..
+                } => Ok(cert),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<Cert> {
+        // refuse what the server would close the connection over
+        let cert_sni = CertSni::parse_lenient(&cert_sni)?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetCertBySni {
+                msg_id: next_msg_id(),
+                cert_sni,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertBySniResponse { cert, .. } => {
+                    Ok(cert)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_resolve_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertResolveByDigest {
+                msg_id: next_msg_id(),
+                cert_digest,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertResolveByDigestResponse {
+                    keystore_index,
+                    ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_resolve_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        // refuse what the server would close the connection over
+        let cert_sni = CertSni::parse_lenient(&cert_sni)?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertResolveBySni {
+                msg_id: next_msg_id(),
+                cert_sni,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertResolveBySniResponse {
+                    keystore_index,
+                    ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                msg_id: next_msg_id(),
+                cert_digest,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        // refuse what the server would close the connection over
+        let cert_sni = CertSni::parse_lenient(&cert_sni)?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetPrivKeyBySni {
+                msg_id: next_msg_id(),
+                cert_sni,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_bundle_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, Cert, CertPrivKey)>
+    {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTlsCertGetBundleByTag {
+                msg_id: next_msg_id(),
+                tag,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetBundleByTagResponse {
+                    cert_sni,
+                    cert_digest,
+                    cert,
+                    cert_priv_key,
+                    ..
+                } => Ok((cert_sni, cert_digest, cert, cert_priv_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519NewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_with_options(
+        &mut self,
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SIGN_KEY_OPTIONS_VERSION {
+                // older servers can still create keys with no options
+                if options != SignKeyOptions::default() {
+                    return Err(sign_key_options_unsupported(version));
+                }
+                return match kill_switch
+                    .mix(ipc_send.request(
+                        LairWire::ToLairSignEd25519NewFromEntropy {
+                            msg_id: next_msg_id(),
+                        },
+                    ))
+                    .await?
+                {
+                    LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                        keystore_index,
+                        pub_key,
+                        ..
+                    } => Ok((keystore_index, pub_key)),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+            match kill_switch
+                .mix(ipc_send.request(
+                    LairWire::ToLairSignEd25519NewFromEntropyWithOptions {
+                        msg_id: next_msg_id(),
+                        options,
+                    },
+                ))
+                .await?
+            {
+                LairWire::ToCliSignEd25519NewFromEntropyWithOptionsResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_seed_with_options(
+        &mut self,
+        seed: [u8; 32],
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SIGN_KEY_OPTIONS_VERSION {
+                return Err(sign_key_options_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(
+                    LairWire::ToLairSignEd25519NewFromSeedWithOptions {
+                        msg_id: next_msg_id(),
+                        seed,
+                        options,
+                    },
+                ))
+                .await?
+            {
+                LairWire::ToCliSignEd25519NewFromSeedWithOptionsResponse {
+                    keystore_index,
+                    pub_key,
+                    already_existed,
+                    ..
+                } => Ok((keystore_index, pub_key, already_existed)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_get_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignKeyOptions> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SIGN_KEY_OPTIONS_VERSION {
+                return Err(sign_key_options_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairSignEd25519GetOptions {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliSignEd25519GetOptionsResponse {
+                    options,
+                    ..
+                } => Ok(options),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_rotate(
+        &mut self,
+        old_keystore_index: KeystoreIndex,
+        options: RotateKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, RotationRecord)> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::ROTATION_VERSION {
+                return Err(rotation_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairSignEd25519Rotate {
+                    msg_id: next_msg_id(),
+                    old_keystore_index,
+                    options,
+                }))
+                .await?
+            {
+                LairWire::ToCliSignEd25519RotateResponse {
+                    keystore_index,
+                    record,
+                    ..
+                } => Ok((keystore_index, record)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_export_seed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<[u8; 32]> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SEED_EXPORT_VERSION {
+                return Err(seed_export_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairSignEd25519ExportSeed {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliSignEd25519ExportSeedResponse {
+                    seed, ..
+                } => Ok(seed),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new(
+        &mut self,
+        backing: KeyBacking,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519New {
+                msg_id: next_msg_id(),
+                backing,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_import_ssh_key(
+        &mut self,
+        key: Arc<Vec<u8>>,
+        passphrase: Option<String>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        // an unencodable request would take down the connection
+        check_ssh_key_import(&key, passphrase.as_deref())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519ImportSshKey {
+                msg_id: next_msg_id(),
+                key,
+                passphrase,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519ImportSshKeyResponse {
+                    keystore_index,
+                    pub_key,
+                    already_existed,
+                    ..
+                } => Ok((keystore_index, pub_key, already_existed)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignEd25519PubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519Get {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_resolve_by_pub_key(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519ResolveByPubKey {
+                msg_id: next_msg_id(),
+                pub_key,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519ResolveByPubKeyResponse {
+                    keystore_index,
+                    ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519SignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                message,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        check_sign_message_len(message.len())?;
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let request = if version.await? >= spec::KEYSTORE_INDEX64_VERSION {
+                LairWire::ToLairSignEd25519SignByIndex64 {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                    message,
+                }
+            } else {
+                LairWire::ToLairSignEd25519SignByIndex {
+                    msg_id: next_msg_id(),
+                    keystore_index: std::convert::TryInto::try_into(
+                        keystore_index,
+                    )?,
+                    message,
+                }
+            };
+            match kill_switch.mix(ipc_send.request(request)).await? {
+                LairWire::ToCliSignEd25519SignByIndex64Response {
+                    signature,
+                    ..
+                }
+                | LairWire::ToCliSignEd25519SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519SignByPubKey {
+                msg_id: next_msg_id(),
+                pub_key,
+                message,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_v2(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        check_sign_message_len(message.len())?;
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            if version.await? >= spec::SIGN_WITH_PUB_KEY_VERSION {
+                return match kill_switch
+                    .mix(ipc_send.request(
+                        LairWire::ToLairSignEd25519SignByIndexV2 {
+                            msg_id: next_msg_id(),
+                            keystore_index,
+                            message,
+                        },
+                    ))
+                    .await?
+                {
+                    LairWire::ToCliSignEd25519SignByIndexV2Response {
+                        pub_key,
+                        signature,
+                        ..
+                    } => Ok((pub_key, signature)),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+
+            // older servers: sign, then fetch the pub key, which must
+            // still verify the signature, else the keypair changed
+            let signature = match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairSignEd25519SignByIndex {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                    message: message.clone(),
+                }))
+                .await?
+            {
+                LairWire::ToCliSignEd25519SignByIndexResponse {
+                    signature,
+                    ..
+                } => signature,
+                o => return Err(format!("unexpected: {:?}", o).into()),
+            };
+            let pub_key = match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairSignEd25519Get {
+                    msg_id: next_msg_id(),
+                    keystore_index,
+                }))
+                .await?
+            {
+                LairWire::ToCliSignEd25519GetResponse { pub_key, .. } => {
+                    pub_key
+                }
+                o => return Err(format!("unexpected: {:?}", o).into()),
+            };
+            if !pub_key.verify_pure(&message, &signature) {
+                return Err(format!(
+                    "the keypair at {:?} changed while signing",
+                    keystore_index
+                )
+                .into());
+            }
+            Ok((pub_key, signature))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_with_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        options: SignOptions,
+    ) -> LairClientApiHandlerResult<(SignEd25519Signature, Option<u64>)> {
+        check_sign_message_len(message.len())?;
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SIGN_OPTIONS_VERSION {
+                // older servers sign with the default options only
+                if options != SignOptions::default() {
+                    return Err(sign_options_unsupported(version));
+                }
+                return match kill_switch
+                    .mix(ipc_send.request(
+                        LairWire::ToLairSignEd25519SignByIndex {
+                            msg_id: next_msg_id(),
+                            keystore_index,
+                            message,
+                        },
+                    ))
+                    .await?
+                {
+                    LairWire::ToCliSignEd25519SignByIndexResponse {
+                        signature,
+                        ..
+                    } => Ok((signature, None)),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+            match kill_switch
+                .mix(ipc_send.request(
+                    LairWire::ToLairSignEd25519SignByIndexWithOptions {
+                        msg_id: next_msg_id(),
+                        keystore_index,
+                        message,
+                        options,
+                    },
+                ))
+                .await?
+            {
+                LairWire::ToCliSignEd25519SignByIndexWithOptionsResponse {
+                    signature,
+                    timestamp,
+                    ..
+                } => Ok((signature, timestamp)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key_v2(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        check_sign_message_len(message.len())?;
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            if version.await? >= spec::SIGN_WITH_PUB_KEY_VERSION {
+                return match kill_switch
+                    .mix(ipc_send.request(
+                        LairWire::ToLairSignEd25519SignByPubKeyV2 {
+                            msg_id: next_msg_id(),
+                            pub_key,
+                            message,
+                        },
+                    ))
+                    .await?
+                {
+                    LairWire::ToCliSignEd25519SignByPubKeyV2Response {
+                        pub_key,
+                        signature,
+                        ..
+                    } => Ok((pub_key, signature)),
+                    o => Err(format!("unexpected: {:?}", o).into()),
+                };
+            }
+
+            // older servers: the signing key is the one asked for
+            match kill_switch
+                .mix(ipc_send.request(
+                    LairWire::ToLairSignEd25519SignByPubKey {
+                        msg_id: next_msg_id(),
+                        pub_key: pub_key.clone(),
+                        message,
+                    },
+                ))
+                .await?
+            {
                 LairWire::ToCliSignEd25519SignByPubKeyResponse {
                     signature,
                     ..
+                } => Ok((pub_key, signature)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519SignByTag {
+                msg_id: next_msg_id(),
+                tag,
+                message,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByTagResponse {
+                    pub_key,
+                    signature,
+                    ..
+                } => Ok((pub_key, signature)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_ph_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        prehash: [u8; 64],
+        context: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd25519PhSignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                prehash,
+                context,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519PhSignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, X25519PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairX25519NewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<X25519PubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairX25519Get {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519GetResponse { pub_key, .. } => Ok(pub_key),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_to_sign_pub_key(
+        &mut self,
+        sender_x25519_index: KeystoreIndex,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairCryptoBoxToSignPubKey {
+                msg_id: next_msg_id(),
+                sender_x25519_index,
+                recipient_sign_pub_key,
+                data,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliCryptoBoxToSignPubKeyResponse {
+                    data, ..
+                } => Ok(data),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_open_by_sign_pub_key(
+        &mut self,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        sender_x25519_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairCryptoBoxOpenBySignPubKey {
+                msg_id: next_msg_id(),
+                recipient_sign_pub_key,
+                sender_x25519_pub_key,
+                data,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliCryptoBoxOpenBySignPubKeyResponse {
+                    data,
+                    ..
+                } => Ok(data),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_seal_open_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairCryptoBoxSealOpenByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                data,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
+                    data,
+                    ..
+                } => Ok(data),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_age_decrypt_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        stanzas: Vec<AgeStanza>,
+    ) -> LairClientApiHandlerResult<[u8; AGE_FILE_KEY_LEN]> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairAgeDecryptByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                stanzas,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliAgeDecryptByIndexResponse {
+                    file_key, ..
+                } => Ok(file_key),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_random_bytes(
+        &mut self,
+        len: u32,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairGetRandomBytes {
+                msg_id: next_msg_id(),
+                len,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetRandomBytesResponse { bytes, .. } => {
+                    Ok(bytes)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_new_uuid(&mut self) -> LairClientApiHandlerResult<[u8; 16]> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairNewUuid {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairNewUuidResponse { uuid, .. } => Ok(uuid),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_pepper_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairPwPepperNewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliPwPepperNewFromEntropyResponse {
+                    keystore_index,
+                    ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_hash(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        options: PwHashOptions,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairPwHash {
+                msg_id: next_msg_id(),
+                keystore_index: pepper_index,
+                password,
+                options,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliPwHashResponse { hash, .. } => Ok(hash),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_verify(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        hash: String,
+    ) -> LairClientApiHandlerResult<bool> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairPwVerify {
+                msg_id: next_msg_id(),
+                keystore_index: pepper_index,
+                password,
+                hash,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliPwVerifyResponse { valid, .. } => Ok(valid),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_new_from_entropy(
+        &mut self,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTotpNewFromEntropy {
+                msg_id: next_msg_id(),
+                options,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTotpNewFromEntropyResponse {
+                    keystore_index,
+                    ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_import(
+        &mut self,
+        secret_b32: String,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTotpImport {
+                msg_id: next_msg_id(),
+                secret_b32,
+                options,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTotpImportResponse {
+                    keystore_index, ..
+                } => Ok(keystore_index),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_generate(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        at: Option<u64>,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTotpGenerate {
+                msg_id: next_msg_id(),
+                keystore_index,
+                at,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTotpGenerateResponse { code, .. } => Ok(code),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_export_provisioning_uri(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        label: String,
+        issuer: String,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairTotpExportProvisioningUri {
+                msg_id: next_msg_id(),
+                keystore_index,
+                label,
+                issuer,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTotpExportProvisioningUriResponse {
+                    uri,
+                    ..
+                } => Ok(uri),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSecp256k1NewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSecp256k1NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey, bool)>
+    {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSecp256k1NewFromSeed {
+                msg_id: next_msg_id(),
+                seed,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSecp256k1NewFromSeedResponse {
+                    keystore_index,
+                    pub_key,
+                    already_existed,
+                    ..
+                } => Ok((keystore_index, pub_key, already_existed)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignSecp256k1PubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSecp256k1Get {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSecp256k1GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message_hash: [u8; 32],
+    ) -> LairClientApiHandlerResult<(SignSecp256k1Signature, u8)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSecp256k1SignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                message_hash,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSecp256k1SignByIndexResponse {
+                    signature,
+                    recovery_id,
+                    ..
+                } => Ok((signature, recovery_id)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed448_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd448PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd448NewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd448NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed448_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignEd448PubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd448Get {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd448GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed448_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd448SignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                message,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd448SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed448_sign_by_pub_key(
+        &mut self,
+        pub_key: SignEd448PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignEd448SignByPubKey {
+                msg_id: next_msg_id(),
+                pub_key,
+                message,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd448SignByPubKeyResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_bls_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignBlsPubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignBlsNewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignBlsNewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_bls_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignBlsPubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignBlsGet {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignBlsGetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_bls_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        dst: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignBlsSignature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignBlsSignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                message,
+                dst,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignBlsSignByIndexResponse {
+                    signature, ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSr25519NewFromEntropy {
+                msg_id: next_msg_id(),
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSr25519NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey, bool)>
+    {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSr25519NewFromSeed {
+                msg_id: next_msg_id(),
+                seed,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSr25519NewFromSeedResponse {
+                    keystore_index,
+                    pub_key,
+                    already_existed,
+                    ..
+                } => Ok((keystore_index, pub_key, already_existed)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignSr25519PubKey> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSr25519Get {
+                msg_id: next_msg_id(),
+                keystore_index,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSr25519GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        context: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignSr25519Signature> {
+        check_sign_message_len(message.len())?;
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairSignSr25519SignByIndex {
+                msg_id: next_msg_id(),
+                keystore_index,
+                message,
+                context,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignSr25519SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_wrap_entry(
+        &mut self,
+        target_index: KeystoreIndex,
+        recipient_pub_key: X25519PubKey,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairWrapEntry {
+                msg_id: next_msg_id(),
+                target_index,
+                recipient_pub_key,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairWrapEntryResponse { wrapped, .. } => {
+                    Ok(wrapped)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_unwrap_entry(
+        &mut self,
+        recipient_index: KeystoreIndex,
+        wrapped: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType, bool)> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairUnwrapEntry {
+                msg_id: next_msg_id(),
+                recipient_index,
+                wrapped,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairUnwrapEntryResponse {
+                    keystore_index,
+                    lair_entry_type,
+                    already_existed,
+                    ..
+                } => Ok((keystore_index, lair_entry_type, already_existed)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_attest_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        challenge: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Attestation> {
+        let fut = self.kill_switch.mix_static(self.ipc_send.request(
+            LairWire::ToLairLairAttestEntry {
+                msg_id: next_msg_id(),
+                keystore_index,
+                challenge,
+            },
+        ));
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairAttestEntryResponse {
+                    attestation, ..
+                } => Ok(attestation),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_prove_server_identity(
+        &mut self,
+        nonce: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<ServerIdentityProof> {
+        let version = self.server_wire_version();
+        let kill_switch = self.kill_switch.weak();
+        let ipc_send = self.ipc_send.clone();
+        Ok(async move {
+            let version = version.await?;
+            if version < spec::SERVER_IDENTITY_VERSION {
+                return Err(server_identity_unsupported(version));
+            }
+            match kill_switch
+                .mix(ipc_send.request(
+                    LairWire::ToLairLairProveServerIdentity {
+                        msg_id: next_msg_id(),
+                        nonce,
+                    },
+                ))
+                .await?
+            {
+                LairWire::ToCliLairProveServerIdentityResponse {
+                    proof,
+                    ..
+                } => Ok(proof),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_x25519_new(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, X25519PubKey)> {
+        let fut = self.ephemeral_request(LairWire::ToLairEphemeralX25519New {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralX25519NewResponse {
+                    handle,
+                    pub_key,
+                    ..
+                } => Ok((handle, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_sign_ed25519_new(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, SignEd25519PubKey)> {
+        let fut =
+            self.ephemeral_request(LairWire::ToLairEphemeralSignEd25519New {
+                msg_id: next_msg_id(),
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralSignEd25519NewResponse {
+                    handle,
+                    pub_key,
+                    ..
+                } => Ok((handle, pub_key)),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_sign_ed25519_sign(
+        &mut self,
+        handle: EphemeralHandle,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let fut =
+            self.ephemeral_request(LairWire::ToLairEphemeralSignEd25519Sign {
+                msg_id: next_msg_id(),
+                handle,
+                message,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralSignEd25519SignResponse {
+                    signature,
+                    ..
                 } => Ok(signature),
                 o => Err(format!("unexpected: {:?}", o).into()),
             }
@@ -427,4 +3269,71 @@ impl LairClientApiHandler for Internal {
         .boxed()
         .into())
     }
+
+    fn handle_ephemeral_crypto_box(
+        &mut self,
+        sender_handle: EphemeralHandle,
+        recipient_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.ephemeral_request(LairWire::ToLairEphemeralCryptoBox {
+            msg_id: next_msg_id(),
+            sender_handle,
+            recipient_pub_key,
+            data,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralCryptoBoxResponse { data, .. } => {
+                    Ok(data)
+                }
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_crypto_box_open(
+        &mut self,
+        recipient_handle: EphemeralHandle,
+        sender_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut =
+            self.ephemeral_request(LairWire::ToLairEphemeralCryptoBoxOpen {
+                msg_id: next_msg_id(),
+                recipient_handle,
+                sender_pub_key,
+                data,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralCryptoBoxOpenResponse {
+                    data, ..
+                } => Ok(data),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_drop(
+        &mut self,
+        handle: EphemeralHandle,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.ephemeral_request(LairWire::ToLairEphemeralDrop {
+            msg_id: next_msg_id(),
+            handle,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliEphemeralDropResponse { .. } => Ok(()),
+                o => Err(format!("unexpected: {:?}", o).into()),
+            }
+        }
+        .boxed()
+        .into())
+    }
 }
@@ -0,0 +1,281 @@
+//! JWS / JWT EdDSA helpers backed by lair ed25519 signing keys.
+
+use crate::actor::*;
+use crate::*;
+
+const B64: base64::Config = base64::URL_SAFE_NO_PAD;
+
+/// How the `kid` protected header parameter should be populated.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum JwsKid {
+    /// Do not include a `kid` header.
+    None,
+
+    /// The base64url encoded ed25519 public key of the signer.
+    #[default]
+    PubKey,
+
+    /// A caller-specified key id.
+    Custom(String),
+}
+
+/// Sign `claims_json` as a compact JWS / JWT using the ed25519 keypair
+/// at `keystore_index`. The `kid` header is set to the signer pub key.
+pub async fn jwt_sign_eddsa(
+    client: &impl LairClientApiSender,
+    keystore_index: KeystoreIndex,
+    claims_json: &str,
+) -> LairResult<String> {
+    jwt_sign_eddsa_with_kid(
+        client,
+        keystore_index,
+        claims_json,
+        JwsKid::default(),
+    )
+    .await
+}
+
+/// Sign `claims_json` as a compact JWS / JWT using the ed25519 keypair
+/// at `keystore_index`, specifying how the `kid` header is populated.
+pub async fn jwt_sign_eddsa_with_kid(
+    client: &impl LairClientApiSender,
+    keystore_index: KeystoreIndex,
+    claims_json: &str,
+    kid: JwsKid,
+) -> LairResult<String> {
+    // jwt claims must be a json object
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(
+        claims_json,
+    )
+    .map_err(LairError::other)?;
+
+    let mut header = serde_json::Map::new();
+    header.insert("alg".into(), "EdDSA".into());
+    header.insert("typ".into(), "JWT".into());
+    match kid {
+        JwsKid::None => (),
+        JwsKid::PubKey => {
+            let pub_key = client.sign_ed25519_get(keystore_index).await?;
+            header.insert(
+                "kid".into(),
+                base64::encode_config(&*pub_key.0, B64).into(),
+            );
+        }
+        JwsKid::Custom(kid) => {
+            header.insert("kid".into(), kid.into());
+        }
+    }
+    let header = serde_json::to_vec(&header).map_err(LairError::other)?;
+
+    let mut out = base64::encode_config(&header, B64);
+    out.push('.');
+    base64::encode_config_buf(claims_json.as_bytes(), B64, &mut out);
+
+    let signature = client
+        .sign_ed25519_sign_by_index(
+            keystore_index,
+            Arc::new(out.as_bytes().to_vec()),
+        )
+        .await?;
+
+    out.push('.');
+    base64::encode_config_buf(&*signature.0, B64, &mut out);
+    Ok(out)
+}
+
+/// Verify a compact EdDSA JWS against `pub_key`,
+/// returning the decoded payload bytes on success.
+pub async fn jws_verify(
+    pub_key: &SignEd25519PubKey,
+    jws: &str,
+) -> LairResult<Vec<u8>> {
+    let mut parts = jws.split('.');
+    let (header, payload, signature) =
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(h), Some(p), Some(s), None) => (h, p, s),
+            _ => return Err("invalid compact jws".into()),
+        };
+
+    let header: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(
+            &base64::decode_config(header, B64).map_err(LairError::other)?,
+        )
+        .map_err(LairError::other)?;
+    if header.get("alg").and_then(|a| a.as_str()) != Some("EdDSA") {
+        return Err("jws alg is not EdDSA".into());
+    }
+
+    let signature: SignEd25519Signature = base64::decode_config(signature, B64)
+        .map_err(LairError::other)?
+        .into();
+
+    // the signing input is the encoded header and payload, exactly as sent
+    let signing_input = jws.as_bytes()[..jws.rfind('.').unwrap()].to_vec();
+    if !pub_key.verify(Arc::new(signing_input), signature).await? {
+        return Err("invalid jws signature".into());
+    }
+
+    base64::decode_config(payload, B64).map_err(LairError::other)
+}
+
+/// Format an ed25519 pub key as an RFC 8037 `OKP` / `Ed25519` JWK.
+/// Members are sorted, as for an RFC 7638 thumbprint.
+pub fn ed25519_pub_key_to_jwk(pub_key: &SignEd25519PubKey) -> String {
+    serde_json::json!({
+        "crv": "Ed25519",
+        "kty": "OKP",
+        "x": base64::encode_config(&*pub_key.0, B64),
+    })
+    .to_string()
+}
+
+/// Parse an RFC 8037 `OKP` / `Ed25519` JWK into an ed25519 pub key.
+/// A JWK holding the private key `d` is refused,
+/// so it is not mistaken for a public one.
+pub fn ed25519_pub_key_from_jwk(jwk: &str) -> LairResult<SignEd25519PubKey> {
+    let jwk: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(jwk).map_err(LairError::other)?;
+    let member = |name: &str| jwk.get(name).and_then(|v| v.as_str());
+    if member("kty") != Some("OKP") || member("crv") != Some("Ed25519") {
+        return Err("expected an OKP / Ed25519 jwk".into());
+    }
+    if jwk.contains_key("d") {
+        return Err("refusing a jwk holding a private key".into());
+    }
+    let x = member("x").ok_or_else(|| LairError::from("jwk has no x"))?;
+    let x = base64::decode_config(x, B64).map_err(LairError::other)?;
+    if x.len() != 32 {
+        return Err("invalid jwk ed25519 pub key length".into());
+    }
+    Ok(x.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn check(claims_json: &str) {
+        let (api, _evt) =
+            test::spawn_test_keystore(vec![], vec![]).await.unwrap();
+        let (idx, pub_key) = api.sign_ed25519_new_from_entropy().await.unwrap();
+
+        let jwt = jwt_sign_eddsa(&api, idx, claims_json).await.unwrap();
+
+        let payload = jws_verify(&pub_key, &jwt).await.unwrap();
+        assert_eq!(claims_json.as_bytes(), &payload[..]);
+
+        // validate with an independent jose implementation
+        let x = base64::encode_config(&*pub_key.0, B64);
+        let key = jsonwebtoken::DecodingKey::from_ed_components(&x).unwrap();
+        let mut validation =
+            jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        let data =
+            jsonwebtoken::decode::<serde_json::Value>(&jwt, &key, &validation)
+                .unwrap();
+        assert_eq!(Some(x), data.header.kid);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(claims_json).unwrap(),
+            data.claims,
+        );
+
+        // tampering with the payload must invalidate the signature
+        let mut parts = jwt.split('.').collect::<Vec<_>>();
+        let evil = base64::encode_config(br#"{"evil":true}"#, B64);
+        parts[1] = &evil;
+        assert!(jws_verify(&pub_key, &parts.join(".")).await.is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_jwt() {
+        check(r#"{"sub":"lair","iat":1600000000}"#).await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_empty_claims() {
+        check("{}").await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_large_payload() {
+        let claims = serde_json::json!({
+            "sub": "lair",
+            "data": "x".repeat(1024 * 1024),
+        });
+        check(&claims.to_string()).await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_honors_kid_option() {
+        let (api, _evt) =
+            test::spawn_test_keystore(vec![], vec![]).await.unwrap();
+        let (idx, _) = api.sign_ed25519_new_from_entropy().await.unwrap();
+
+        let jwt = jwt_sign_eddsa_with_kid(&api, idx, "{}", JwsKid::None)
+            .await
+            .unwrap();
+        let header = jsonwebtoken::decode_header(&jwt).unwrap();
+        assert_eq!(None, header.kid);
+
+        let jwt = jwt_sign_eddsa_with_kid(
+            &api,
+            idx,
+            "{}",
+            JwsKid::Custom("my-key".into()),
+        )
+        .await
+        .unwrap();
+        let header = jsonwebtoken::decode_header(&jwt).unwrap();
+        assert_eq!(Some("my-key".to_string()), header.kid);
+
+        assert!(jwt_sign_eddsa(&api, idx, "[1, 2]").await.is_err());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_converts_pub_keys_to_and_from_jwk() {
+        // RFC 8037 appendix A.2, and the A.3 thumbprint input
+        let x = "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo";
+        let rfc_jwk = format!(r#"{{"kty":"OKP","crv":"Ed25519","x":"{}"}}"#, x);
+        let pub_key = SignEd25519PubKey::from_jwk(&rfc_jwk).unwrap();
+        assert_eq!(base64::decode_config(x, B64).unwrap(), *pub_key.0);
+        assert_eq!(
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x),
+            pub_key.to_jwk(),
+        );
+
+        // an independent jose implementation verifies with our jwk
+        let (api, _evt) =
+            test::spawn_test_keystore(vec![], vec![]).await.unwrap();
+        let (idx, pub_key) = api.sign_ed25519_new_from_entropy().await.unwrap();
+        let jwt = jwt_sign_eddsa(&api, idx, "{}").await.unwrap();
+        let jwk: jsonwebtoken::jwk::Jwk =
+            serde_json::from_str(&pub_key.to_jwk()).unwrap();
+        let key = jsonwebtoken::DecodingKey::from_jwk(&jwk).unwrap();
+        let mut validation =
+            jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        jsonwebtoken::decode::<serde_json::Value>(&jwt, &key, &validation)
+            .unwrap();
+        assert_eq!(
+            pub_key,
+            SignEd25519PubKey::from_jwk(&pub_key.to_jwk()).unwrap()
+        );
+
+        for bad in &[
+            "[]".to_string(),
+            format!(r#"{{"kty":"EC","crv":"Ed25519","x":"{}"}}"#, x),
+            format!(r#"{{"kty":"OKP","crv":"X25519","x":"{}"}}"#, x),
+            r#"{"kty":"OKP","crv":"Ed25519"}"#.to_string(),
+            r#"{"kty":"OKP","crv":"Ed25519","x":"AAAA"}"#.to_string(),
+            r#"{"kty":"OKP","crv":"Ed25519","x":"!!"}"#.to_string(),
+            format!(
+                r#"{{"kty":"OKP","crv":"Ed25519","x":"{}","d":"{}"}}"#,
+                x, "nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A"
+            ),
+        ] {
+            assert!(SignEd25519PubKey::from_jwk(bad).is_err(), "{}", bad);
+        }
+    }
+}
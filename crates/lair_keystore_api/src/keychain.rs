@@ -0,0 +1,180 @@
+//! Store the lair unlock passphrase in the OS keychain
+//! (macOS Keychain, Windows Credential Manager, Linux Secret Service).
+
+use crate::actor::*;
+use crate::internal::util::*;
+use crate::passphrase::Passphrase;
+use crate::*;
+use futures::{future::FutureExt, stream::StreamExt};
+use zeroize::Zeroizing;
+
+/// Platform abstraction over a passphrase keychain.
+/// Implementations must never log the passphrase.
+pub trait PassphraseKeychain: 'static + Send + Sync {
+    /// Fetch the stored passphrase, if an entry exists.
+    fn get_passphrase(&self) -> LairResult<Option<Zeroizing<String>>>;
+
+    /// Store (or replace) the passphrase.
+    fn set_passphrase(&self, passphrase: &str) -> LairResult<()>;
+
+    /// Remove any stored passphrase.
+    fn clear_passphrase(&self) -> LairResult<()>;
+}
+
+/// Shared handle to a PassphraseKeychain.
+pub type PassphraseKeychainHandle = Arc<dyn PassphraseKeychain>;
+
+const KEYCHAIN_SERVICE: &str = "lair-keystore";
+
+/// The OS keychain. Entries are keyed by the lair root path,
+/// so multiple lair instances on one machine do not collide.
+pub struct OsKeychain(String);
+
+impl OsKeychain {
+    /// Get a handle to the os keychain entry for this lair config.
+    pub fn new_handle(config: &Config) -> PassphraseKeychainHandle {
+        Arc::new(Self(config.get_root_path().to_string_lossy().to_string()))
+    }
+
+    fn entry(&self) -> keyring::Entry {
+        keyring::Entry::new(KEYCHAIN_SERVICE, &self.0)
+    }
+}
+
+impl PassphraseKeychain for OsKeychain {
+    fn get_passphrase(&self) -> LairResult<Option<Zeroizing<String>>> {
+        match self.entry().get_password() {
+            Ok(passphrase) => Ok(Some(Zeroizing::new(passphrase))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(LairError::other(e)),
+        }
+    }
+
+    fn set_passphrase(&self, passphrase: &str) -> LairResult<()> {
+        self.entry()
+            .set_password(passphrase)
+            .map_err(LairError::other)
+    }
+
+    fn clear_passphrase(&self) -> LairResult<()> {
+        match self.entry().delete_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(LairError::other(e)),
+        }
+    }
+}
+
+/// An in-memory keychain, for use in tests.
+#[derive(Default)]
+pub struct MockKeychain(std::sync::Mutex<Option<Zeroizing<String>>>);
+
+impl MockKeychain {
+    /// Get a handle to a new, empty mock keychain.
+    pub fn new_handle() -> PassphraseKeychainHandle {
+        Arc::new(Self::default())
+    }
+}
+
+impl PassphraseKeychain for MockKeychain {
+    fn get_passphrase(&self) -> LairResult<Option<Zeroizing<String>>> {
+        Ok(self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned keychain lock"))?
+            .clone())
+    }
+
+    fn set_passphrase(&self, passphrase: &str) -> LairResult<()> {
+        *self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned keychain lock"))? =
+            Some(Zeroizing::new(passphrase.to_string()));
+        Ok(())
+    }
+
+    fn clear_passphrase(&self) -> LairResult<()> {
+        *self
+            .0
+            .lock()
+            .map_err(|_| LairError::from("poisoned keychain lock"))? = None;
+        Ok(())
+    }
+}
+
+/// Answer `request_unlock_passphrase` events on `evt_recv` from the
+/// keychain. The passphrase is only fetched when an event fires.
+/// If the keychain has no entry, the request is answered with an error.
+/// The keychain only holds the default store's passphrase, requests
+/// for other stores are answered with an error too.
+pub fn spawn_keychain_unlock_responder(
+    mut evt_recv: LairClientEventReceiver,
+    keychain: PassphraseKeychainHandle,
+) {
+    err_spawn("keychain-unlock-responder", async move {
+        while let Some(evt) = evt_recv.next().await {
+            match evt {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond,
+                    store_name,
+                    ..
+                } => {
+                    let keychain = keychain.clone();
+                    respond.respond(Ok(async move {
+                        if store_name != DEFAULT_STORE_NAME {
+                            return Err(format!(
+                                "no keychain passphrase for store {}",
+                                store_name
+                            )
+                            .into());
+                        }
+                        match keychain.get_passphrase()? {
+                            // ours zeroizes once copied to locked memory
+                            Some(passphrase) => {
+                                Ok(Passphrase::from(passphrase.as_str()))
+                            }
+                            None => Err("no passphrase in keychain".into()),
+                        }
+                    }
+                    .boxed()
+                    .into()));
+                }
+                evt => evt.respond_default(),
+            }
+        }
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_answers_unlock_from_keychain() {
+        let keychain = MockKeychain::new_handle();
+        let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
+        spawn_keychain_unlock_responder(evt_recv, keychain.clone());
+
+        let default = || DEFAULT_STORE_NAME.to_string();
+
+        assert!(evt_send.request_unlock_passphrase(default()).await.is_err());
+
+        keychain.set_passphrase("passphrase").unwrap();
+        assert_eq!(
+            b"passphrase",
+            evt_send
+                .request_unlock_passphrase(default())
+                .await
+                .unwrap()
+                .read(),
+        );
+        assert!(evt_send
+            .request_unlock_passphrase("other".to_string())
+            .await
+            .is_err());
+
+        keychain.clear_passphrase().unwrap();
+        assert!(evt_send.request_unlock_passphrase(default()).await.is_err());
+    }
+}
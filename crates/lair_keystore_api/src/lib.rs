@@ -1,26 +1,102 @@
 #![deny(missing_docs)]
 #![deny(warnings)]
 //! secret lair private keystore types
+//!
+//! With `default-features = false`, only the plain types in [`actor`],
+//! the error type, [`hash`], [`passphrase_strength`], [`test_vectors`],
+//! and pure signature
+//! verification are available.
+//! This minimal build supports `wasm32-unknown-unknown`.
+//!
+//! ed25519 signs and verifies on pure rust curve25519-dalek by default,
+//! or on the system libsodium with `default-features = false` and the
+//! `ed25519_libsodium` feature. Both behave identically.
 
 include!(concat!(env!("OUT_DIR"), "/ver.rs"));
 
+#[cfg(feature = "full")]
 use ghost_actor::dependencies::tracing::*;
 use std::sync::Arc;
 
 mod error;
 pub use error::*;
 
+mod connection_url;
+pub use connection_url::*;
+
+#[cfg(feature = "full")]
 mod config;
+#[cfg(feature = "full")]
 pub use config::*;
 
+#[cfg(feature = "full")]
 pub mod internal;
+#[cfg(feature = "full")]
 pub use internal::rayon::init_once_rayon_thread_pool;
+#[cfg(feature = "full")]
 pub(crate) use internal::rayon::rayon_exec;
 
+#[cfg(feature = "full")]
 pub mod entry;
 
 pub mod actor;
 
+pub mod bls;
+
+pub mod ed25519ph;
+
+// without "full" only verification is used
+#[cfg_attr(not(feature = "full"), allow(dead_code))]
+mod ed25519_backend;
+
+pub mod hash;
+
+#[cfg(feature = "full")]
+pub mod passphrase;
+
+pub mod passphrase_strength;
+
+pub mod secp256k1;
+
+pub mod sr25519;
+
+pub mod test_vectors;
+
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
+
+#[cfg(feature = "full")]
 pub mod ipc;
 
+#[cfg(feature = "full")]
+pub mod health;
+
+#[cfg(feature = "full")]
+pub mod device_secret;
+
+#[cfg(feature = "full")]
+pub mod hardware;
+
+#[cfg(feature = "age")]
+pub mod age_identity;
+
+#[cfg(feature = "cose")]
+pub mod cose;
+
+#[cfg(feature = "jose")]
+pub mod jose;
+
+#[cfg(feature = "minisign")]
+pub mod minisign;
+
+#[cfg(feature = "multiformats")]
+pub mod multiformats;
+
+#[cfg(feature = "keychain")]
+pub mod keychain;
+
+#[cfg(feature = "full")]
 pub mod test;
+
+#[cfg(feature = "test_utils")]
+pub mod test_keystore;
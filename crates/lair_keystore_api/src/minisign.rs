@@ -0,0 +1,149 @@
+//! Minisign compatible signatures backed by lair ed25519 signing keys.
+//!
+//! Output can be verified with the standard `minisign` / `rsign2` tools.
+//! Signatures always use the pre-hashed ("ED") mode.
+
+use crate::actor::*;
+use crate::*;
+
+const SIG_ALG_PUB_KEY: &[u8; 2] = b"Ed";
+const SIG_ALG_PREHASHED: &[u8; 2] = b"ED";
+
+/// Minisign generates key ids randomly at key creation time.
+/// Lair keys predate their use with minisign, so we derive the id
+/// from the first 8 bytes of the blake2b-512 hash of the pub key.
+/// The same lair key will always produce the same key id.
+fn key_id(pub_key: &SignEd25519PubKey) -> [u8; 8] {
+    let hash = blake2b_simd::blake2b(&pub_key.0);
+    let mut out = [0; 8];
+    out.copy_from_slice(&hash.as_bytes()[..8]);
+    out
+}
+
+/// The hex representation minisign uses when displaying key ids.
+fn key_id_hex(key_id: &[u8; 8]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+/// Export the minisign public key file contents for the ed25519
+/// keypair at `keystore_index`.
+pub async fn sign_ed25519_minisign_pub_key(
+    client: &impl LairClientApiSender,
+    keystore_index: KeystoreIndex,
+) -> LairResult<String> {
+    let pub_key = client.sign_ed25519_get(keystore_index).await?;
+    let key_id = key_id(&pub_key);
+
+    let mut bin = Vec::with_capacity(42);
+    bin.extend_from_slice(SIG_ALG_PUB_KEY);
+    bin.extend_from_slice(&key_id);
+    bin.extend_from_slice(&pub_key.0);
+
+    Ok(format!(
+        "untrusted comment: minisign public key {}\n{}\n",
+        key_id_hex(&key_id),
+        base64::encode(&bin),
+    ))
+}
+
+/// Sign `data` with the ed25519 keypair at `keystore_index`,
+/// producing minisign signature file contents.
+pub async fn sign_ed25519_sign_minisign(
+    client: &impl LairClientApiSender,
+    keystore_index: KeystoreIndex,
+    data: Arc<Vec<u8>>,
+    trusted_comment: &str,
+) -> LairResult<String> {
+    if trusted_comment.contains('\n') || trusted_comment.contains('\r') {
+        return Err("minisign trusted comment cannot contain newlines".into());
+    }
+
+    let pub_key = client.sign_ed25519_get(keystore_index).await?;
+    let key_id = key_id(&pub_key);
+
+    let hash = rayon_exec(move || blake2b_simd::blake2b(&data)).await;
+    let signature = client
+        .sign_ed25519_sign_by_index(
+            keystore_index,
+            Arc::new(hash.as_bytes().to_vec()),
+        )
+        .await?;
+
+    let mut global = signature.0.to_vec();
+    global.extend_from_slice(trusted_comment.as_bytes());
+    let global_signature = client
+        .sign_ed25519_sign_by_index(keystore_index, Arc::new(global))
+        .await?;
+
+    let mut bin = Vec::with_capacity(74);
+    bin.extend_from_slice(SIG_ALG_PREHASHED);
+    bin.extend_from_slice(&key_id);
+    bin.extend_from_slice(&signature.0);
+
+    Ok(format!(
+        "untrusted comment: signature from lair-keystore secret key\n{}\ntrusted comment: {}\n{}\n",
+        base64::encode(&bin),
+        trusted_comment,
+        base64::encode(&*global_signature.0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_can_sign_minisign() {
+        let (api, _evt) =
+            test::spawn_test_keystore(vec![], vec![]).await.unwrap();
+        let (idx, _) = api.sign_ed25519_new_from_entropy().await.unwrap();
+
+        let pub_key_file =
+            sign_ed25519_minisign_pub_key(&api, idx).await.unwrap();
+        let pub_key = pub_key_file.lines().nth(1).unwrap();
+
+        let data = Arc::new(b"release-tarball-bytes".to_vec());
+        let sig_file = sign_ed25519_sign_minisign(
+            &api,
+            idx,
+            data.clone(),
+            "timestamp:1600000000\tfile:release.tar.gz",
+        )
+        .await
+        .unwrap();
+
+        // verify with an independent minisign implementation
+        let pk = minisign_verify::PublicKey::from_base64(pub_key).unwrap();
+        let sig = minisign_verify::Signature::decode(&sig_file).unwrap();
+        assert_eq!(
+            "timestamp:1600000000\tfile:release.tar.gz",
+            sig.trusted_comment(),
+        );
+        pk.verify(&data, &sig, false).unwrap();
+        assert!(pk.verify(b"evil-bytes", &sig, false).is_err());
+
+        // the key id shown in the pub key comment must match the encoded one
+        let key_id = pub_key_file
+            .lines()
+            .next()
+            .unwrap()
+            .rsplit(' ')
+            .next()
+            .unwrap();
+        let bin = base64::decode(pub_key).unwrap();
+        let mut expect = [0; 8];
+        expect.copy_from_slice(&bin[2..10]);
+        assert_eq!(key_id_hex(&expect), key_id);
+        let pub_key2 = sign_ed25519_minisign_pub_key(&api, idx).await.unwrap();
+        assert_eq!(pub_key_file, pub_key2);
+
+        // tampering with the trusted comment invalidates the global sig
+        let evil = sig_file.replace("release.tar.gz", "evil.tar.gz");
+        let sig = minisign_verify::Signature::decode(&evil).unwrap();
+        assert!(pk.verify(&data, &sig, false).is_err());
+
+        assert!(sign_ed25519_sign_minisign(&api, idx, data, "a\nb")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Multihash and multibase encodings of lair identifiers, for tooling
+//! expecting self describing ids, e.g. `did:key` or IPFS cids.
+//!
+//! These are explicit conversions, the `Display` impls are unchanged.
+//! Only the codes lair produces are parsed, anything else is an error.
+
+use crate::actor::*;
+use crate::*;
+
+/// The multicodec code of a blake2b-256 multihash.
+const BLAKE2B_256: u64 = 0xb220;
+
+/// The multicodec code of an ed25519 public key.
+const ED25519_PUB: u64 = 0xed;
+
+/// The multibase encodings of `SignEd25519PubKey::to_multibase`.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Multibase {
+    /// The bitcoin alphabet base58, prefix `z`, as in `did:key`.
+    Base58Btc,
+    /// Url safe base64 without padding, prefix `u`.
+    Base64Url,
+}
+
+impl Multibase {
+    /// The prefix character identifying this encoding.
+    pub fn prefix(&self) -> char {
+        match self {
+            Multibase::Base58Btc => 'z',
+            Multibase::Base64Url => 'u',
+        }
+    }
+
+    /// The encoding identified by prefix `c`.
+    pub fn from_prefix(c: char) -> LairResult<Self> {
+        Ok(match c {
+            'z' => Multibase::Base58Btc,
+            'u' => Multibase::Base64Url,
+            _ => {
+                return Err(
+                    format!("unsupported multibase prefix: {:?}", c).into()
+                )
+            }
+        })
+    }
+
+    /// Encode `data`, prefix included.
+    pub fn encode(&self, data: &[u8]) -> String {
+        let mut out = self.prefix().to_string();
+        match self {
+            Multibase::Base58Btc => out.push_str(&base58btc_encode(data)),
+            Multibase::Base64Url => out.push_str(&base64::encode_config(
+                data,
+                base64::URL_SAFE_NO_PAD,
+            )),
+        }
+        out
+    }
+
+    /// Decode a prefixed multibase string.
+    pub fn decode(s: &str) -> LairResult<(Self, Vec<u8>)> {
+        let mut chars = s.chars();
+        let base = Self::from_prefix(
+            chars
+                .next()
+                .ok_or_else(|| LairError::from("empty multibase"))?,
+        )?;
+        let data = match base {
+            Multibase::Base58Btc => base58btc_decode(chars.as_str())?,
+            // base64 0.13 decodes padding even without it configured
+            Multibase::Base64Url if chars.as_str().contains('=') => {
+                return Err("padded base64url multibase".into())
+            }
+            Multibase::Base64Url => {
+                base64::decode_config(chars.as_str(), base64::URL_SAFE_NO_PAD)
+                    .map_err(LairError::other)?
+            }
+        };
+        Ok((base, data))
+    }
+}
+
+const BASE58BTC: &[u8; 58] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58btc_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // little endian base 58 digits
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for byte in &data[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|d| BASE58BTC[*d as usize] as char))
+        .collect()
+}
+
+fn base58btc_decode(s: &str) -> LairResult<Vec<u8>> {
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    // little endian bytes
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+    for c in s.bytes().skip(zeros) {
+        let mut carry = BASE58BTC
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| LairError::from("invalid base58btc character"))?
+            as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.resize(bytes.len() + zeros, 0);
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Append the unsigned varint encoding of `n` to `out`.
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Read an unsigned varint off the front of `data`, returning it and
+/// the bytes after it. Multiformats varints are at most 9 bytes,
+/// and minimally encoded.
+fn read_varint(data: &[u8]) -> LairResult<(u64, &[u8])> {
+    let mut n = 0_u64;
+    for (i, byte) in data.iter().enumerate().take(9) {
+        n |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            if *byte == 0 && i > 0 {
+                return Err("varint is not minimally encoded".into());
+            }
+            return Ok((n, &data[i + 1..]));
+        }
+    }
+    Err("truncated or overlong varint".into())
+}
+
+/// Read the multicodec `code` off the front of `data`, returning the
+/// bytes after it.
+fn strip_code<'a>(
+    data: &'a [u8],
+    code: u64,
+    what: &str,
+) -> LairResult<&'a [u8]> {
+    let (read, rest) = read_varint(data)?;
+    if read != code {
+        return Err(format!(
+            "expected {} code {:#x}, got {:#x}",
+            what, code, read
+        )
+        .into());
+    }
+    Ok(rest)
+}
+
+impl CertDigest {
+    /// The blake2b-256 multihash of this digest:
+    /// the varint code `0xb220`, the varint length 32, then the digest.
+    pub fn to_multihash(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.0.len());
+        write_varint(&mut out, BLAKE2B_256);
+        write_varint(&mut out, self.0.len() as u64);
+        out.extend_from_slice(&self.0);
+        out
+    }
+
+    /// Parse a blake2b-256 multihash, see `to_multihash`.
+    pub fn from_multihash(multihash: &[u8]) -> LairResult<Self> {
+        let rest = strip_code(multihash, BLAKE2B_256, "blake2b-256")?;
+        let (len, digest) = read_varint(rest)?;
+        if len != 32 || digest.len() != 32 {
+            return Err(format!(
+                "blake2b-256 multihash of length {} with {} digest bytes",
+                len,
+                digest.len()
+            )
+            .into());
+        }
+        Ok(digest.to_vec().into())
+    }
+}
+
+impl SignEd25519PubKey {
+    /// This key as a `base` encoded multibase string of the
+    /// ed25519-pub multicodec, e.g. the `z6Mk..` of a `did:key`.
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        let mut data = Vec::with_capacity(2 + self.0.len());
+        write_varint(&mut data, ED25519_PUB);
+        data.extend_from_slice(&self.0);
+        base.encode(&data)
+    }
+
+    /// Parse a multibase ed25519-pub key, in any `Multibase`,
+    /// see `to_multibase`.
+    pub fn from_multibase(s: &str) -> LairResult<Self> {
+        let (_, data) = Multibase::decode(s)?;
+        let key = strip_code(&data, ED25519_PUB, "ed25519-pub")?;
+        if key.len() != 32 {
+            return Err(format!(
+                "ed25519-pub key of {} bytes, expected 32",
+                key.len()
+            )
+            .into());
+        }
+        Ok(key.to_vec().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::*;
+
+    // reference values from the rust-multibase / js-multiformats
+    // implementations, the base58btc one is the did:key of the key
+    const PUB_KEY_BASE58BTC: &str =
+        "z6MktwupdmLXVVqTzCw4i46r4uGyosGXRnR3XjN4Zq7oMMsw";
+    const PUB_KEY_BASE64URL: &str =
+        "u7QHXWpgBgrEKt9VL_tPJZAc6DuFy89qmIyWvAhpo9wdRGg";
+    // the multihash of blake2b-256("hello world")
+    const HELLO_MULTIHASH: &str =
+        "a0e40220256c83b297114d201b30179f3f0ef0cace9783622da5974326b436178aeef610";
+    const HELLO_MULTIHASH_BASE58BTC: &str =
+        "z2Drjgb6mymtFmrnLpYFVYj3KnZ1KMTGv1EKXNnkNM9xbVrUaCf";
+
+    fn pub_key() -> SignEd25519PubKey {
+        from_hex(ED25519_VECTORS[0].public_key).into()
+    }
+
+    #[test]
+    fn pub_key_multibase_golden() {
+        let pub_key = pub_key();
+        for (base, expect) in &[
+            (Multibase::Base58Btc, PUB_KEY_BASE58BTC),
+            (Multibase::Base64Url, PUB_KEY_BASE64URL),
+        ] {
+            assert_eq!(*expect, pub_key.to_multibase(*base));
+            assert_eq!(
+                pub_key,
+                SignEd25519PubKey::from_multibase(expect).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn cert_digest_multihash_golden() {
+        let digest: CertDigest =
+            hash::blake2b(b"hello world", 32, None).unwrap().into();
+        let multihash = digest.to_multihash();
+        assert_eq!(from_hex(HELLO_MULTIHASH), multihash);
+        assert_eq!(
+            HELLO_MULTIHASH_BASE58BTC,
+            Multibase::Base58Btc.encode(&multihash)
+        );
+        assert_eq!(digest, CertDigest::from_multihash(&multihash).unwrap());
+    }
+
+    #[test]
+    fn parsing_validates_codes_and_lengths() {
+        let multihash = from_hex(HELLO_MULTIHASH);
+        // sha2-256
+        let mut sha256 = vec![0x12, 0x20];
+        sha256.extend_from_slice(&multihash[4..]);
+        assert!(CertDigest::from_multihash(&sha256).is_err());
+        assert!(CertDigest::from_multihash(&multihash[..35]).is_err());
+        let mut long = multihash.clone();
+        long.push(0);
+        assert!(CertDigest::from_multihash(&long).is_err());
+        // 0xb220 with a redundant trailing varint byte
+        let mut overlong = vec![0xa0, 0xe4, 0x82, 0x00, 0x20];
+        overlong.extend_from_slice(&multihash[4..]);
+        assert!(CertDigest::from_multihash(&overlong).is_err());
+        assert!(CertDigest::from_multihash(&[]).is_err());
+
+        let pub_key = pub_key();
+        // x25519-pub
+        let mut x25519 = vec![0xec, 0x01];
+        x25519.extend_from_slice(&pub_key.0);
+        assert!(SignEd25519PubKey::from_multibase(
+            &Multibase::Base58Btc.encode(&x25519)
+        )
+        .is_err());
+        let mut short = vec![0xed, 0x01];
+        short.extend_from_slice(&pub_key.0[..31]);
+        assert!(SignEd25519PubKey::from_multibase(
+            &Multibase::Base64Url.encode(&short)
+        )
+        .is_err());
+        for bad in &[
+            "",
+            // base32, not supported
+            "bfsaqxwuytabqfmikw7kux7wtzfsaoopa5yxpg6w2myszlqi2nd3qouirji",
+            // '0' is not in the base58btc alphabet
+            "z6Mktwupdm0XVVqTzCw4i46r4uGyosGXRnR3XjN4Zq7oMMsw",
+            // padded
+            "u7QHXWpgBgrEKt9VL_tPJZAc6DuFy89qmIyWvAhpo9wdRGg=",
+        ] {
+            assert!(SignEd25519PubKey::from_multibase(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn base58btc_keeps_leading_zeros() {
+        for data in &[&[][..], &[0], &[0, 0, 1], &[0, 0xff, 0xff], &[57], &[58]]
+        {
+            let encoded = base58btc_encode(data);
+            assert_eq!(*data, &base58btc_decode(&encoded).unwrap()[..]);
+        }
+        assert_eq!("11", base58btc_encode(&[0, 0]));
+        assert_eq!("21", base58btc_encode(&[58]));
+    }
+}
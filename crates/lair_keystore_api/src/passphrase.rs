@@ -0,0 +1,200 @@
+//! The unlock passphrase, held in locked, zeroizing memory.
+//!
+//! A `Passphrase` is copied once, into a buffer the os is asked not to
+//! swap out (best effort, on unix), which is zeroized when the last clone
+//! is dropped. Clones share the buffer, so passing one through the event
+//! channels, or the `request_unlock_passphrase` round trip, copies nothing.
+
+use crate::*;
+use zeroize::Zeroize;
+
+struct Locked(Box<[u8]>);
+
+impl Locked {
+    fn new(len: usize) -> Self {
+        let buf = vec![0; len].into_boxed_slice();
+        #[cfg(unix)]
+        if !buf.is_empty() {
+            // a failure, e.g. RLIMIT_MEMLOCK, leaves the buffer swappable
+            unsafe {
+                libc::mlock(buf.as_ptr() as *const libc::c_void, buf.len());
+            }
+        }
+        Self(buf)
+    }
+}
+
+impl Drop for Locked {
+    fn drop(&mut self) {
+        self.0.zeroize();
+        #[cfg(unix)]
+        if !self.0.is_empty() {
+            unsafe {
+                libc::munlock(
+                    self.0.as_ptr() as *const libc::c_void,
+                    self.0.len(),
+                );
+            }
+        }
+    }
+}
+
+/// An unlock passphrase, see the module docs.
+#[derive(Clone)]
+pub struct Passphrase(Arc<Locked>);
+
+impl Passphrase {
+    /// Move the passphrase in `source` to locked memory,
+    /// zeroizing `source`.
+    pub fn from_bytes_mut(source: &mut [u8]) -> Self {
+        let out = Self::from(&*source);
+        source.zeroize();
+        out
+    }
+
+    /// Move the passphrase in a plain `String` to locked memory,
+    /// zeroizing the `String`. Copies of the passphrase the `String`
+    /// left behind, e.g. as it grew, are out of reach: read it into
+    /// a fixed buffer and use `from_bytes_mut` instead.
+    #[deprecated(note = "use `Passphrase::from_bytes_mut`, or `From<&str>`, \
+        a plain String may leave copies of the passphrase behind")]
+    pub fn from_string(mut passphrase: String) -> Self {
+        warn!(
+            "unlock passphrase supplied as a plain String, which is deprecated"
+        );
+        // the bytes are zeroed, which is valid utf8
+        Self::from_bytes_mut(unsafe { passphrase.as_bytes_mut() })
+    }
+
+    /// The passphrase bytes.
+    pub fn read(&self) -> &[u8] {
+        &(self.0).0
+    }
+
+    /// The passphrase as `utf8`.
+    pub fn as_str(&self) -> LairResult<&str> {
+        std::str::from_utf8(self.read()).map_err(LairError::other)
+    }
+
+    /// The passphrase length, in bytes.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// True for the empty passphrase.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Copies the passphrase, `source` is still the caller's to zeroize.
+impl From<&[u8]> for Passphrase {
+    fn from(source: &[u8]) -> Self {
+        let mut locked = Locked::new(source.len());
+        locked.0.copy_from_slice(source);
+        Self(Arc::new(locked))
+    }
+}
+
+/// Copies the passphrase, `source` is still the caller's to zeroize.
+impl From<&str> for Passphrase {
+    fn from(source: &str) -> Self {
+        Self::from(source.as_bytes())
+    }
+}
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Passphrase([{} bytes redacted])", self.len())
+    }
+}
+
+impl PartialEq for Passphrase {
+    fn eq(&self, other: &Self) -> bool {
+        // constant time for passphrases of equal length
+        self.len() == other.len()
+            && self
+                .read()
+                .iter()
+                .zip(other.read().iter())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+impl Eq for Passphrase {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_moves_the_passphrase_out_of_its_source() {
+        let mut source = *b"passphrase";
+        let passphrase = Passphrase::from_bytes_mut(&mut source);
+        assert_eq!([0; 10], source);
+        assert_eq!(b"passphrase", passphrase.read());
+        assert_eq!("passphrase", passphrase.as_str().unwrap());
+
+        // clones share the locked buffer
+        let clone = passphrase.clone();
+        assert_eq!(passphrase.read().as_ptr(), clone.read().as_ptr());
+        assert_eq!(Passphrase::from("passphrase"), clone);
+        assert_ne!(Passphrase::from("passphrase!"), clone);
+        assert_ne!(Passphrase::from("pbssphrase"), clone);
+
+        assert_eq!(
+            "Passphrase([10 bytes redacted])",
+            format!("{:?}", passphrase)
+        );
+        assert!(Passphrase::from("").is_empty());
+    }
+
+    /// Captures the message of each warning.
+    struct WarnLayer(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for WarnLayer {
+        fn on_event(
+            &self,
+            event: &Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Visitor<'a>(&'a std::sync::Mutex<Vec<String>>);
+            impl field::Visit for Visitor<'_> {
+                fn record_debug(
+                    &mut self,
+                    field: &field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.0.lock().unwrap().push(format!("{:?}", value));
+                    }
+                }
+            }
+            if *event.metadata().level() == Level::WARN {
+                event.record(&mut Visitor(&self.0));
+            }
+        }
+    }
+
+    #[test]
+    fn it_warns_of_plain_string_passphrases() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let captured = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let _guard = subscriber::set_default(
+            tracing_subscriber::registry().with(WarnLayer(captured.clone())),
+        );
+
+        let _ = Passphrase::from_bytes_mut(&mut b"passphrase".to_vec());
+        let _ = Passphrase::from("passphrase");
+        assert!(captured.lock().unwrap().is_empty());
+
+        #[allow(deprecated)]
+        let passphrase = Passphrase::from_string("passphrase".to_string());
+        assert_eq!(b"passphrase", passphrase.read());
+        let captured = captured.lock().unwrap();
+        assert_eq!(1, captured.len());
+        assert!(captured[0].contains("deprecated"));
+    }
+}
@@ -0,0 +1,502 @@
+//! Pure passphrase strength estimation, in the style of zxcvbn.
+//!
+//! A passphrase is split into the cheapest sequence of guessable
+//! patterns - common passwords and words (with capitalization and leet
+//! substitutions), repeated characters, sequences, years, and brute
+//! forced runs - and scored by how many guesses that sequence takes.
+//!
+//! The estimator only borrows the passphrase. It keeps no copy of it,
+//! and a `PassphraseStrength` holds only the score and the estimate,
+//! so it is safe to log, or to show live as a passphrase is typed.
+
+use std::fmt;
+
+/// The best possible score.
+pub const MAX_PASSPHRASE_SCORE: u8 = 4;
+
+/// The minimum score lair accepts for a new passphrase by default.
+pub const DEFAULT_MIN_PASSPHRASE_SCORE: u8 = 3;
+
+/// The guess rate crack times are estimated at: an offline attack
+/// against a slow hash, as the argon2id unlock key derivation is.
+pub const GUESSES_PER_SECOND: f64 = 1.0e4;
+
+/// Only this many leading characters are matched against patterns,
+/// the rest are counted as brute forced, keeping estimation cheap.
+const MAX_ANALYZED_CHARS: usize = 100;
+
+/// Ranked most to least common. Keyboard walks are listed as words.
+const COMMON: &[&str] = &[
+    "password",
+    "123456",
+    "12345678",
+    "qwerty",
+    "abc123",
+    "football",
+    "monkey",
+    "letmein",
+    "dragon",
+    "111111",
+    "baseball",
+    "iloveyou",
+    "trustno1",
+    "sunshine",
+    "master",
+    "welcome",
+    "shadow",
+    "ashley",
+    "jesus",
+    "michael",
+    "ninja",
+    "mustang",
+    "secret",
+    "passphrase",
+    "passw0rd",
+    "admin",
+    "login",
+    "princess",
+    "qwertyuiop",
+    "asdfgh",
+    "asdf",
+    "zxcvbn",
+    "azerty",
+    "starwars",
+    "solo",
+    "hello",
+    "freedom",
+    "whatever",
+    "charlie",
+    "superman",
+    "batman",
+    "pokemon",
+    "computer",
+    "internet",
+    "changeme",
+    "default",
+    "test",
+    "testing",
+    "guest",
+    "root",
+    "pass",
+    "love",
+    "god",
+    "money",
+    "lair",
+    "keystore",
+    "holochain",
+    "holo",
+    "secure",
+    "private",
+    "key",
+    "access",
+    "summer",
+    "winter",
+    "spring",
+    "autumn",
+    "flower",
+    "orange",
+    "purple",
+    "cookie",
+    "chocolate",
+    "banana",
+    "apple",
+    "pepper",
+    "tiger",
+    "soccer",
+    "hockey",
+    "killer",
+    "hunter",
+    "ranger",
+    "buster",
+    "thomas",
+    "jordan",
+    "daniel",
+    "andrew",
+    "jessica",
+    "matthew",
+    "hannah",
+    "family",
+    "friend",
+    "forever",
+    "lovely",
+    "angel",
+    "baby",
+    "blue",
+    "red",
+    "green",
+    "black",
+    "white",
+    "house",
+    "horse",
+    "correct",
+    "battery",
+    "staple",
+];
+
+/// An estimate of how hard a passphrase is to guess.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassphraseStrength {
+    /// 0 (trivially guessable) to `MAX_PASSPHRASE_SCORE`
+    /// (very unlikely to be guessed).
+    pub score: u8,
+
+    /// log10 of the estimated number of guesses needed.
+    pub guesses_log10: f64,
+}
+
+impl PassphraseStrength {
+    /// The estimated crack time, in seconds, at `GUESSES_PER_SECOND`.
+    pub fn crack_time_seconds(&self) -> f64 {
+        10.0_f64.powf(self.guesses_log10) / GUESSES_PER_SECOND
+    }
+
+    /// The estimated crack time, for humans, e.g. "3 hours".
+    pub fn crack_time_display(&self) -> String {
+        const MINUTE: f64 = 60.0;
+        const HOUR: f64 = MINUTE * 60.0;
+        const DAY: f64 = HOUR * 24.0;
+        const MONTH: f64 = DAY * 31.0;
+        const YEAR: f64 = MONTH * 12.0;
+        const CENTURY: f64 = YEAR * 100.0;
+
+        let secs = self.crack_time_seconds();
+        let (count, unit) = if secs < 1.0 {
+            return "less than a second".to_string();
+        } else if secs < MINUTE {
+            (secs, "second")
+        } else if secs < HOUR {
+            (secs / MINUTE, "minute")
+        } else if secs < DAY {
+            (secs / HOUR, "hour")
+        } else if secs < MONTH {
+            (secs / DAY, "day")
+        } else if secs < YEAR {
+            (secs / MONTH, "month")
+        } else if secs < CENTURY {
+            (secs / YEAR, "year")
+        } else {
+            return "centuries".to_string();
+        };
+        let count = count.round() as u64;
+        if count == 1 {
+            format!("1 {}", unit)
+        } else {
+            format!("{} {}s", count, unit)
+        }
+    }
+}
+
+impl fmt::Display for PassphraseStrength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "score {}/{}, estimated crack time {}",
+            self.score,
+            MAX_PASSPHRASE_SCORE,
+            self.crack_time_display(),
+        )
+    }
+}
+
+/// Estimate the strength of `passphrase`.
+pub fn estimate_passphrase_strength(passphrase: &str) -> PassphraseStrength {
+    // char boundaries only, so the passphrase is never copied
+    let offsets = passphrase
+        .char_indices()
+        .map(|(i, _)| i)
+        .take(MAX_ANALYZED_CHARS)
+        .chain(std::iter::once(passphrase.len()))
+        .collect::<Vec<_>>();
+    let analyzed = &passphrase[..*offsets.last().unwrap_or(&0)];
+    let analyzed_len = offsets.len() - 1;
+    let rest_len = passphrase.chars().count() - analyzed_len;
+
+    let guesses_log10 = min_guesses_log10(analyzed, &offsets) + rest_len as f64;
+    let score = if guesses_log10 < 3.0 {
+        0
+    } else if guesses_log10 < 6.0 {
+        1
+    } else if guesses_log10 < 8.0 {
+        2
+    } else if guesses_log10 < 10.0 {
+        3
+    } else {
+        4
+    };
+    PassphraseStrength {
+        score,
+        guesses_log10,
+    }
+}
+
+/// A pattern match covering chars `start..end`.
+struct Match {
+    start: usize,
+    end: usize,
+    guesses_log10: f64,
+}
+
+/// The cheapest cover of the passphrase by matches, where a cover of
+/// `l` matches costs `l! * product(match guesses)`.
+fn min_guesses_log10(s: &str, offsets: &[usize]) -> f64 {
+    let n = offsets.len() - 1;
+    if n == 0 {
+        return 0.0;
+    }
+
+    let mut by_end: Vec<Vec<Match>> = (0..=n).map(|_| Vec::new()).collect();
+    for m in pattern_matches(s, offsets) {
+        by_end[m.end].push(m);
+    }
+    // brute force fills any gap, at 10 guesses per char
+    for (end, matches) in by_end.iter_mut().enumerate().skip(1) {
+        for start in 0..end {
+            let len = end - start;
+            let min = if len == 1 { 11.0_f64 } else { 51.0_f64 };
+            matches.push(Match {
+                start,
+                end,
+                guesses_log10: (len as f64).max(min.log10()),
+            });
+        }
+    }
+
+    // best[end][l] - cheapest product of l matches covering 0..end
+    let mut best = vec![vec![f64::INFINITY; n + 1]; n + 1];
+    best[0][0] = 0.0;
+    for end in 1..=n {
+        for m in &by_end[end] {
+            for l in 0..=m.start {
+                let prev = best[m.start][l];
+                if prev.is_finite() && prev + m.guesses_log10 < best[end][l + 1]
+                {
+                    best[end][l + 1] = prev + m.guesses_log10;
+                }
+            }
+        }
+    }
+    (1..=n)
+        .map(|l| best[n][l] + log10_factorial(l))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn log10_factorial(n: usize) -> f64 {
+    (2..=n).map(|i| (i as f64).log10()).sum()
+}
+
+fn pattern_matches(s: &str, offsets: &[usize]) -> Vec<Match> {
+    let n = offsets.len() - 1;
+    let c = |i: usize| s[offsets[i]..].chars().next().unwrap_or('\0');
+    let mut out = Vec::new();
+
+    // common passwords and words
+    for (rank, word) in COMMON.iter().enumerate() {
+        let word_len = word.len();
+        for start in 0..=n.saturating_sub(word_len) {
+            if start + word_len > n {
+                break;
+            }
+            let mut upper = 0;
+            let mut leet = false;
+            let matched = word.bytes().enumerate().all(|(i, w)| {
+                let ch = c(start + i);
+                if ch.is_ascii_uppercase() {
+                    upper += 1;
+                }
+                if ch.to_ascii_lowercase() == w as char {
+                    true
+                } else if is_leet_for(ch, w as char) {
+                    leet = true;
+                    true
+                } else {
+                    false
+                }
+            });
+            if !matched {
+                continue;
+            }
+            let first_upper = c(start).is_ascii_uppercase();
+            let case_guesses = if upper == 0 {
+                1.0
+            } else if upper == word_len || (upper == 1 && first_upper) {
+                2.0
+            } else {
+                // any mix of cases
+                2.0_f64.powi(word_len as i32)
+            };
+            let leet_guesses = if leet { 2.0 } else { 1.0 };
+            out.push(Match {
+                start,
+                end: start + word_len,
+                guesses_log10: ((rank + 1) as f64
+                    * case_guesses
+                    * leet_guesses)
+                    .log10(),
+            });
+        }
+    }
+
+    // repeats, e.g. "aaaa", and sequences, e.g. "abcd", "9876"
+    let mut start = 0;
+    while start < n {
+        let first = c(start);
+        let mut end = start + 1;
+        while end < n && c(end) == first {
+            end += 1;
+        }
+        if end - start >= 3 {
+            out.push(Match {
+                start,
+                end,
+                guesses_log10: (cardinality(first) * (end - start) as f64)
+                    .log10(),
+            });
+        }
+
+        if start + 1 < n {
+            let delta = c(start + 1) as i64 - first as i64;
+            if delta == 1 || delta == -1 {
+                let mut end = start + 2;
+                while end < n && c(end) as i64 - c(end - 1) as i64 == delta {
+                    end += 1;
+                }
+                if end - start >= 3 {
+                    let base = if "aA1z9Z0".contains(first) {
+                        4.0
+                    } else if first.is_ascii_digit() {
+                        10.0
+                    } else {
+                        26.0
+                    };
+                    let direction = if delta < 0 { 2.0 } else { 1.0 };
+                    out.push(Match {
+                        start,
+                        end,
+                        guesses_log10: (base
+                            * (end - start) as f64
+                            * direction)
+                            .log10(),
+                    });
+                }
+            }
+        }
+        start += 1;
+    }
+
+    // recent years
+    for start in 0..n.saturating_sub(3) {
+        let mut year = 0;
+        let all_digits = (start..start + 4).all(|i| match c(i).to_digit(10) {
+            Some(d) => {
+                year = year * 10 + d;
+                true
+            }
+            None => false,
+        });
+        if all_digits && (1900..=2099).contains(&year) {
+            out.push(Match {
+                start,
+                end: start + 4,
+                guesses_log10: 200.0_f64.log10(),
+            });
+        }
+    }
+
+    out
+}
+
+fn cardinality(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii_alphabetic() {
+        26.0
+    } else if c.is_ascii() {
+        33.0
+    } else {
+        100.0
+    }
+}
+
+fn is_leet_for(c: char, letter: char) -> bool {
+    matches!(
+        (c, letter),
+        ('4', 'a')
+            | ('@', 'a')
+            | ('8', 'b')
+            | ('(', 'c')
+            | ('3', 'e')
+            | ('6', 'g')
+            | ('9', 'g')
+            | ('1', 'i')
+            | ('!', 'i')
+            | ('|', 'i')
+            | ('1', 'l')
+            | ('|', 'l')
+            | ('0', 'o')
+            | ('$', 's')
+            | ('5', 's')
+            | ('7', 't')
+            | ('+', 't')
+            | ('2', 'z')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(passphrase: &str) -> u8 {
+        estimate_passphrase_strength(passphrase).score
+    }
+
+    #[test]
+    fn it_scores_common_and_patterned_passphrases_low() {
+        for weak in &[
+            "",
+            "password",
+            "Password",
+            "P@ssw0rd",
+            "passphrase",
+            "123456",
+            "aaaaaaaaaa",
+            "abcdefghij",
+            "qwerty2021",
+        ] {
+            assert!(score(weak) < 2, "{:?} scored {}", weak, score(weak));
+        }
+        assert!(score("password") <= score("P@ssw0rd"));
+    }
+
+    #[test]
+    fn it_scores_long_unpatterned_passphrases_high() {
+        for strong in &[
+            "ochre-fjord-tangent-47",
+            "vN8#qL2!zR5w",
+            "plinth quasar mauve gravel oboe",
+        ] {
+            assert_eq!(MAX_PASSPHRASE_SCORE, score(strong), "{:?}", strong);
+        }
+        // very long input is still estimated, and only gets stronger
+        let long = "ochre-fjord-tangent-47".repeat(10);
+        assert_eq!(MAX_PASSPHRASE_SCORE, score(&long));
+    }
+
+    #[test]
+    fn it_describes_crack_times() {
+        let at = |guesses_log10: f64| {
+            PassphraseStrength {
+                score: 0,
+                guesses_log10,
+            }
+            .crack_time_display()
+        };
+        assert_eq!("less than a second", at(3.0));
+        assert_eq!("1 second", at(4.0));
+        assert_eq!("17 minutes", at(7.0));
+        assert_eq!("centuries", at(20.0));
+        assert_eq!(
+            "score 0/4, estimated crack time less than a second",
+            estimate_passphrase_strength("password").to_string(),
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! secp256k1 ecdsa, as used by `SignSecp256k1` entries,
+//! on the RustCrypto `k256` crate.
+//!
+//! Signatures are `r || s` (64 bytes) over a 32 byte message hash, with
+//! a recovery id, as used by bitcoin / ethereum. Signing is deterministic
+//! (RFC 6979 with hmac-sha256) and always produces low-s signatures.
+
+use crate::*;
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+
+/// Byte length of a compressed public key.
+pub const PUB_KEY_COMPRESSED_LEN: usize = 33;
+
+/// Byte length of an uncompressed public key.
+pub const PUB_KEY_UNCOMPRESSED_LEN: usize = 65;
+
+/// Byte length of an `r || s` signature.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Byte length of a private key.
+const PRIV_KEY_LEN: usize = 32;
+
+fn decode_priv_key(priv_key: &[u8]) -> LairResult<SigningKey> {
+    if priv_key.len() != PRIV_KEY_LEN {
+        return Err("invalid secp256k1 priv key".into());
+    }
+    SigningKey::from_bytes(priv_key.into())
+        .map_err(|_| "invalid secp256k1 priv key".into())
+}
+
+fn decode_pub_key(pub_key: &[u8]) -> LairResult<k256::PublicKey> {
+    match pub_key.len() {
+        PUB_KEY_COMPRESSED_LEN | PUB_KEY_UNCOMPRESSED_LEN => (),
+        _ => return Err("invalid secp256k1 pub key".into()),
+    }
+    k256::PublicKey::from_sec1_bytes(pub_key)
+        .map_err(|_| "invalid secp256k1 pub key".into())
+}
+
+/// Check a private key is a valid (non-zero, less than the curve order)
+/// scalar, and derive its compressed public key.
+pub fn pub_key_from_priv_key(priv_key: &[u8]) -> LairResult<Vec<u8>> {
+    let key = decode_priv_key(priv_key)?;
+    Ok(key
+        .verifying_key()
+        .to_encoded_point(true)
+        .as_bytes()
+        .to_vec())
+}
+
+/// Convert a compressed or uncompressed public key
+/// to its 33 byte compressed encoding.
+pub fn compress_pub_key(pub_key: &[u8]) -> LairResult<Vec<u8>> {
+    let key = decode_pub_key(pub_key)?;
+    Ok(key.to_encoded_point(true).as_bytes().to_vec())
+}
+
+/// Convert a compressed or uncompressed public key
+/// to its 65 byte uncompressed encoding.
+pub fn decompress_pub_key(pub_key: &[u8]) -> LairResult<Vec<u8>> {
+    let key = decode_pub_key(pub_key)?;
+    Ok(key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Verify an `r || s` signature of `message_hash`.
+/// Both low and high s values are accepted.
+pub fn verify(
+    pub_key: &[u8],
+    message_hash: &[u8; 32],
+    signature: &[u8],
+) -> bool {
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    let key = match decode_pub_key(pub_key) {
+        Ok(key) => VerifyingKey::from(key),
+        Err(_) => return false,
+    };
+    let signature = match Signature::from_slice(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    // k256 only verifies low-s signatures
+    let signature = signature.normalize_s().unwrap_or(signature);
+    key.verify_prehash(message_hash, &signature).is_ok()
+}
+
+/// Recover the compressed public key that produced an `r || s` signature
+/// of `message_hash`, given its recovery id.
+pub fn recover(
+    message_hash: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+) -> LairResult<Vec<u8>> {
+    let invalid = || LairError::from("invalid secp256k1 signature");
+    let recovery_id = RecoveryId::from_byte(recovery_id).ok_or_else(invalid)?;
+    let signature = Signature::from_slice(signature).map_err(|_| invalid())?;
+    let key = VerifyingKey::recover_from_prehash(
+        message_hash,
+        &signature,
+        recovery_id,
+    )
+    .map_err(|_| invalid())?;
+    Ok(key.to_encoded_point(true).as_bytes().to_vec())
+}
+
+/// Deterministically sign a 32 byte message hash, returning the
+/// low-s `r || s` signature and its recovery id.
+pub fn sign(
+    priv_key: &[u8],
+    message_hash: &[u8; 32],
+) -> LairResult<([u8; SIGNATURE_LEN], u8)> {
+    let key = decode_priv_key(priv_key)?;
+    let (signature, recovery_id) =
+        key.sign_prehash_recoverable(message_hash)
+            .map_err(|_| "secp256k1 signature degenerate")?;
+    let mut out = [0; SIGNATURE_LEN];
+    out.copy_from_slice(&signature.to_bytes());
+    Ok((out, recovery_id.to_byte()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the curve order
+    const N: &str =
+        "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn hash(s: &str) -> [u8; 32] {
+        let mut out = [0; 32];
+        out.copy_from_slice(&hex(s));
+        out
+    }
+
+    // reference vectors generated with OpenSSL (python cryptography),
+    // deterministic signatures are RFC 6979, normalized to low-s
+    struct Vector {
+        priv_key: &'static str,
+        compressed: &'static str,
+        uncompressed: &'static str,
+        message_hash: &'static str,
+        signature: &'static str,
+        random_signature: &'static str,
+    }
+
+    const VECTORS: &[Vector] = &[
+        // sha256("Satoshi Nakamoto")
+        Vector {
+            priv_key: "0000000000000000000000000000000000000000000000000000000000000001",
+            compressed: "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            uncompressed: "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+            message_hash: "a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e",
+            signature: "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d82442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            random_signature: "c87b4505b0ce759c786bc43ba4c015b6323dd52b50576aedd0e48e9fcafb84c44b7c95fdce1b91bafe23fa626acde68d0500a0f6ac4df06f597b52087f97305a",
+        },
+        // sha256("sample")
+        Vector {
+            priv_key: "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+            compressed: "032c8c31fc9f990c6b55e3865a184a4ce50e09481f2eaeb3e60ec1cea13a6ae645",
+            uncompressed: "042c8c31fc9f990c6b55e3865a184a4ce50e09481f2eaeb3e60ec1cea13a6ae64564b95e4fdb6948c0386e189b006a29f686769b011704275e4459822dc3328085",
+            message_hash: "af2bdbe1aa9b6ec1e2ade1d694f41fc71a831d0268e9891562113d8a62add1bf",
+            signature: "432310e32cb80eb6503a26ce83cc165c783b870845fb8aad6d970889fcd7a6c8530128b6b81c548874a6305d93ed071ca6e05074d85863d4056ce89b02bfab69",
+            random_signature: "773f3008421715084fcb42a47d879810285315ed0b02fc76a18ace06382c056f64f8b51af8f7f72b850be58c2bc10ed2e59df13705a6ead800018b56942f78bf",
+        },
+        // n - 1, sha256("test-data")
+        Vector {
+            priv_key: "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+            compressed: "0379be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            uncompressed: "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798b7c52588d95c3b9aa25b0403f1eef75702e84bb7597aabe663b82f6f04ef2777",
+            message_hash: "a186000422feab857329c684e9fe91412b1a5db084100b37a98cfc95b62aa867",
+            signature: "9eb9a67954657b6a65d87d85826672b51122375315bf710fc51494d44d20105a3c03508171448170ec20cbf62216ddd2c2211353f10879760fd4c57f2d55d929",
+            random_signature: "7a5cc0a111aeec1de0559c6560c92335c6e1db4b183e0cf21f63feb52f0010db27db3c52745fcb7e7bda3fcaa73924ffe22afec90f50e54bdde125a7abc8b2cc",
+        },
+    ];
+
+    #[test]
+    fn it_matches_reference_pub_keys() {
+        for v in VECTORS {
+            let compressed = hex(v.compressed);
+            let uncompressed = hex(v.uncompressed);
+            assert_eq!(
+                compressed,
+                pub_key_from_priv_key(&hex(v.priv_key)).unwrap()
+            );
+            assert_eq!(compressed, compress_pub_key(&uncompressed).unwrap());
+            assert_eq!(compressed, compress_pub_key(&compressed).unwrap());
+            assert_eq!(uncompressed, decompress_pub_key(&compressed).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_keys() {
+        assert!(pub_key_from_priv_key(&[0; 32]).is_err());
+        assert!(pub_key_from_priv_key(&hex(N)).is_err());
+        assert!(pub_key_from_priv_key(&[1; 31]).is_err());
+
+        let mut bad = hex(VECTORS[0].uncompressed);
+        bad[64] ^= 1;
+        assert!(compress_pub_key(&bad).is_err());
+        let mut bad = hex(VECTORS[0].compressed);
+        bad[0] = 0x04;
+        assert!(compress_pub_key(&bad).is_err());
+        // x = 5 is not on the curve
+        let mut bad = vec![0; 33];
+        bad[0] = 0x02;
+        bad[32] = 5;
+        assert!(compress_pub_key(&bad).is_err());
+        assert!(compress_pub_key(&[]).is_err());
+    }
+
+    #[test]
+    fn it_matches_reference_signatures() {
+        for v in VECTORS {
+            let message_hash = hash(v.message_hash);
+            let (signature, recovery_id) =
+                sign(&hex(v.priv_key), &message_hash).unwrap();
+            assert_eq!(hex(v.signature), signature.to_vec());
+            assert_eq!(
+                hex(v.compressed),
+                recover(&message_hash, &signature, recovery_id).unwrap(),
+            );
+            assert_ne!(
+                hex(v.compressed),
+                recover(&message_hash, &signature, recovery_id ^ 1).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn it_verifies_reference_signatures() {
+        for v in VECTORS {
+            let message_hash = hash(v.message_hash);
+            for pub_key in &[hex(v.compressed), hex(v.uncompressed)] {
+                for sig in &[hex(v.signature), hex(v.random_signature)] {
+                    assert!(verify(pub_key, &message_hash, sig));
+
+                    let mut bad = sig.clone();
+                    bad[10] ^= 1;
+                    assert!(!verify(pub_key, &message_hash, &bad));
+
+                    let mut bad_hash = message_hash;
+                    bad_hash[0] ^= 1;
+                    assert!(!verify(pub_key, &bad_hash, sig));
+                }
+
+                // high-s form of the same signature also verifies
+                let sig = Signature::from_slice(&hex(v.signature)).unwrap();
+                let (r, s) = sig.split_scalars();
+                let high = Signature::from_scalars(r, -*s).unwrap();
+                assert!(verify(pub_key, &message_hash, &high.to_bytes()));
+            }
+        }
+        assert!(!verify(
+            &hex(VECTORS[0].compressed),
+            &hash(VECTORS[0].message_hash),
+            &hex(VECTORS[1].signature),
+        ));
+        assert!(!verify(
+            &hex(VECTORS[0].compressed),
+            &hash(VECTORS[0].message_hash),
+            &[0; 64],
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+//! sr25519 schnorr signatures over ristretto255, as used by `SignSr25519`
+//! entries and substrate, on the `schnorrkel` crate.
+//!
+//! Keys are kept as their 32 byte mini secret key (substrate's "seed"),
+//! expanded ed25519 style as substrate does, so substrate hard / soft
+//! junction derivation can later be applied to stored keys.
+//! Signatures are 64 bytes, randomized, and bound to a signing context,
+//! as schnorrkel's `sign_simple` / `verify_simple`.
+
+use crate::*;
+use schnorrkel::{ExpansionMode, MiniSecretKey, PublicKey, Signature};
+
+/// Byte length of a public key.
+pub const PUB_KEY_LEN: usize = 32;
+
+/// Byte length of a signature.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Byte length of a mini secret key.
+const SEED_LEN: usize = 32;
+
+fn decode_seed(seed: &[u8]) -> LairResult<schnorrkel::Keypair> {
+    if seed.len() != SEED_LEN {
+        return Err("invalid sr25519 seed".into());
+    }
+    let seed =
+        MiniSecretKey::from_bytes(seed).map_err(|_| "invalid sr25519 seed")?;
+    Ok(seed.expand_to_keypair(ExpansionMode::Ed25519))
+}
+
+/// Derive the public key of a 32 byte mini secret key.
+pub fn pub_key_from_seed(seed: &[u8]) -> LairResult<[u8; PUB_KEY_LEN]> {
+    Ok(decode_seed(seed)?.public.to_bytes())
+}
+
+/// Sign a message in the signing context `context`.
+/// Only with "full", the randomized nonce is drawn from the os.
+#[cfg(feature = "full")]
+pub fn sign(
+    seed: &[u8],
+    message: &[u8],
+    context: &[u8],
+) -> LairResult<[u8; SIGNATURE_LEN]> {
+    let keypair = decode_seed(seed)?;
+    Ok(keypair.sign_simple(context, message).to_bytes())
+}
+
+/// Verify a signature of `message` in the signing context `context`,
+/// as schnorrkel's `verify_simple`.
+pub fn verify(
+    pub_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+    context: &[u8],
+) -> bool {
+    let pub_key = match PublicKey::from_bytes(pub_key) {
+        Ok(pub_key) => pub_key,
+        Err(_) => return false,
+    };
+    let signature = match Signature::from_bytes(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    pub_key.verify_simple(context, message, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// The substrate development account `//Alice`.
+    const ALICE_SEED: &str =
+        "e5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a";
+    const ALICE_PUB_KEY: &str =
+        "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+    #[test]
+    fn it_derives_substrate_pub_keys() {
+        assert_eq!(
+            hex(ALICE_PUB_KEY),
+            pub_key_from_seed(&hex(ALICE_SEED)).unwrap().to_vec(),
+        );
+        assert!(pub_key_from_seed(&[0x42; 31]).is_err());
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn it_cross_verifies_with_schnorrkel() {
+        let seed = hex(ALICE_SEED);
+        let pub_key = pub_key_from_seed(&seed).unwrap();
+        let keypair = MiniSecretKey::from_bytes(&seed)
+            .unwrap()
+            .expand_to_keypair(ExpansionMode::Ed25519);
+
+        // ours verifies with schnorrkel
+        let signature = sign(&seed, b"message", b"substrate").unwrap();
+        let schnorrkel_signature = Signature::from_bytes(&signature).unwrap();
+        assert!(keypair
+            .public
+            .verify_simple(b"substrate", b"message", &schnorrkel_signature)
+            .is_ok());
+        assert!(keypair
+            .public
+            .verify_simple(b"other", b"message", &schnorrkel_signature)
+            .is_err());
+
+        // schnorrkel's verifies with ours
+        let signature = keypair.sign_simple(b"substrate", b"message");
+        let signature = signature.to_bytes();
+        assert!(verify(&pub_key, b"message", &signature, b"substrate"));
+        assert!(!verify(&pub_key, b"message", &signature, b"other"));
+        assert!(!verify(&pub_key, b"other", &signature, b"substrate"));
+
+        let mut corrupted = signature;
+        corrupted[0] ^= 1;
+        assert!(!verify(&pub_key, b"message", &corrupted, b"substrate"));
+        // missing the schnorrkel marker bit
+        let mut unmarked = signature;
+        unmarked[63] &= 0x7f;
+        assert!(!verify(&pub_key, b"message", &unmarked, b"substrate"));
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn it_randomizes_signatures() {
+        let seed = hex(ALICE_SEED);
+        assert_ne!(
+            sign(&seed, b"message", b"substrate").unwrap().to_vec(),
+            sign(&seed, b"message", b"substrate").unwrap().to_vec(),
+        );
+    }
+}
@@ -4,7 +4,8 @@ use crate::actor::*;
 use crate::internal::*;
 use crate::*;
 use futures::future::FutureExt;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
 
 static NEXT_KEYSTORE_ID: std::sync::atomic::AtomicU32 =
     std::sync::atomic::AtomicU32::new(0);
@@ -50,7 +51,7 @@ pub async fn spawn_test_keystore(
     ghost_actor::GhostSender<LairClientApi>,
     LairClientEventReceiver,
 )> {
-    let (_evt_send, evt_recv) = futures::channel::mpsc::channel(10);
+    let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
 
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
@@ -64,15 +65,29 @@ pub async fn spawn_test_keystore(
         .create_channel::<InternalApi>()
         .await?;
 
+    let server_id = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+        internal::entropy::OsEntropy::new_handle(),
+    )
+    .await?;
+
     tokio::task::spawn(builder.spawn(Internal {
         i_s,
+        evt_send,
+        server_id,
+        clock: clock::SystemClock::new_handle(),
         fixture_sign_ed25519_keypairs,
         fixture_tls_certs,
-        by_idx: HashMap::new(),
+        by_idx: BTreeMap::new(),
         cert_by_digest: HashMap::new(),
         cert_by_sni: HashMap::new(),
         sign_by_pub: HashMap::new(),
-        last_idx: 0.into(),
+        tags: entry::EntryTags::default(),
+        trash: entry::EntryTrash::default(),
+        trashed: BTreeMap::new(),
+        last_idx: None,
+        ephemeral: ephemeral::EphemeralKeys::new(
+            crate::config::DEFAULT_MAX_EPHEMERAL_KEYS,
+        ),
     }));
 
     Ok((sender, evt_recv))
@@ -80,13 +95,131 @@ pub async fn spawn_test_keystore(
 
 struct Internal {
     i_s: ghost_actor::GhostSender<InternalApi>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    server_id: entry::EntrySignEd25519,
+    clock: clock::ClockHandle,
     fixture_sign_ed25519_keypairs: Vec<FixtureSignEd25519Keypair>,
     fixture_tls_certs: Vec<FixtureTlsCert>,
-    by_idx: HashMap<KeystoreIndex, entry::LairEntry>,
+    by_idx: BTreeMap<KeystoreIndex, entry::LairEntry>,
     cert_by_digest: HashMap<CertDigest, entry::EntryTlsCert>,
     cert_by_sni: HashMap<CertSni, entry::EntryTlsCert>,
     sign_by_pub: HashMap<SignEd25519PubKey, entry::EntrySignEd25519>,
-    last_idx: KeystoreIndex,
+    tags: entry::EntryTags,
+    trash: entry::EntryTrash,
+    // trashed entries are moved out of the lookup maps above
+    trashed: BTreeMap<KeystoreIndex, entry::LairEntry>,
+    last_idx: Option<KeystoreIndex>,
+    // the test keystore is a single connection, holding its keys
+    ephemeral: ephemeral::EphemeralKeys,
+}
+
+impl Internal {
+    fn index_entry(&mut self, idx: KeystoreIndex, entry: entry::LairEntry) {
+        self.by_idx.insert(idx, entry.clone());
+        match entry {
+            entry::LairEntry::TlsCert(cert) => {
+                self.cert_by_digest
+                    .insert(cert.cert_digest.clone(), cert.clone());
+                self.cert_by_sni.insert(cert.sni.normalized(), cert);
+            }
+            entry::LairEntry::SignEd25519(keypair) => {
+                // with duplicates, the lowest index keeps resolving
+                self.sign_by_pub
+                    .entry(keypair.pub_key.clone())
+                    .or_insert(keypair);
+            }
+            entry::LairEntry::SignEd25519Hardware(_) => (),
+            entry::LairEntry::X25519(_) => (),
+            entry::LairEntry::PwPepper(_) => (),
+            entry::LairEntry::SignSecp256k1(_) => (),
+            entry::LairEntry::TotpSecret(_) => (),
+            #[cfg(feature = "ed448")]
+            entry::LairEntry::SignEd448(_) => (),
+            entry::LairEntry::SignBls(_) => (),
+            entry::LairEntry::SignSr25519(_) => (),
+        }
+    }
+
+    fn unindex_entry(
+        &mut self,
+        idx: KeystoreIndex,
+    ) -> LairResult<entry::LairEntry> {
+        let entry = match self.by_idx.remove(&idx) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        };
+        match &entry {
+            entry::LairEntry::TlsCert(cert) => {
+                self.cert_by_digest.remove(&cert.cert_digest);
+                self.cert_by_sni.remove(&cert.sni.normalized());
+            }
+            entry::LairEntry::SignEd25519(keypair) => {
+                self.sign_by_pub.remove(&keypair.pub_key);
+            }
+            _ => (),
+        }
+        Ok(entry)
+    }
+
+    /// Refuse the tag of new keypair options if it is malformed or taken.
+    fn check_new_tag(&self, options: &SignKeyOptions) -> LairResult<()> {
+        if let Some(tag) = &options.tag {
+            entry::check_entry_tag(tag)?;
+            if self.tags.resolve(tag).is_ok() {
+                return Err(LairError::TagInUse(tag.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the policy of the ed25519 keypair at `keystore_index` before
+    /// signing with it, the returned future asks the client for approval
+    /// if the keypair requires it.
+    fn check_sign_policy(
+        &self,
+        keystore_index: KeystoreIndex,
+        keypair: &entry::EntrySignEd25519,
+    ) -> LairResult<impl std::future::Future<Output = LairResult<()>>> {
+        keypair.check_not_expired(self.clock.unix_now_secs())?;
+        let approval = if keypair.policy.requires_approval {
+            Some((self.evt_send.clone(), keypair.pub_key.clone()))
+        } else {
+            None
+        };
+        Ok(async move {
+            if let Some((evt_send, pub_key)) = approval {
+                if !evt_send
+                    .request_sign_approval(keystore_index, pub_key)
+                    .await
+                    .unwrap_or(false)
+                {
+                    return Err(LairError::Forbidden(format!(
+                        "signing with entry {} was not approved",
+                        keystore_index
+                    )));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Find the lowest indexed ed25519 keypair with `pub_key`.
+    fn sign_index_by_pub(
+        &self,
+        pub_key: &SignEd25519PubKey,
+    ) -> LairResult<(KeystoreIndex, entry::EntrySignEd25519)> {
+        self.by_idx
+            .iter()
+            .find_map(|(idx, entry)| match entry {
+                entry::LairEntry::SignEd25519(keypair)
+                    if &keypair.pub_key == pub_key =>
+                {
+                    Some((*idx, keypair.clone()))
+                }
+                _ => None,
+            })
+            .ok_or(LairError::PubKeyNotFound)
+    }
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
@@ -97,6 +230,15 @@ ghost_actor::ghost_chan! {
             idx: KeystoreIndex,
             entry: entry::LairEntry,
         ) -> ();
+
+        /// finalize an imported entry, unless an entry with the same
+        /// public identifier exists: then return its index instead,
+        /// with the already existed flag set
+        fn import_unique(entry: entry::LairEntry) -> (KeystoreIndex, bool);
+
+        fn tag_entry(idx: KeystoreIndex, tag: String) -> ();
+
+        fn trash_entry(idx: KeystoreIndex) -> ();
     }
 }
 
@@ -108,20 +250,61 @@ impl InternalApiHandler for Internal {
         idx: KeystoreIndex,
         entry: entry::LairEntry,
     ) -> InternalApiHandlerResult<()> {
-        if idx.0 > self.last_idx.0 {
-            self.last_idx = idx;
+        if Some(idx) > self.last_idx {
+            self.last_idx = Some(idx);
         }
-        self.by_idx.insert(idx, entry.clone());
-        match entry {
-            entry::LairEntry::TlsCert(cert) => {
-                self.cert_by_digest
-                    .insert(cert.cert_digest.clone(), cert.clone());
-                self.cert_by_sni.insert(cert.sni.clone(), cert);
-            }
-            entry::LairEntry::SignEd25519(keypair) => {
-                self.sign_by_pub.insert(keypair.pub_key.clone(), keypair);
+        self.index_entry(idx, entry);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_import_unique(
+        &mut self,
+        entry: entry::LairEntry,
+    ) -> InternalApiHandlerResult<(KeystoreIndex, bool)> {
+        if let Some(pub_id) = entry.pub_id() {
+            if let Some(idx) = self
+                .by_idx
+                .iter()
+                .chain(self.trashed.iter())
+                .filter(|(_, e)| e.pub_id().as_ref() == Some(&pub_id))
+                .map(|(idx, _)| *idx)
+                .min()
+            {
+                if self.trash.is_trashed(idx) {
+                    return Err(LairError::EntryTrashed(format!(
+                        "entry {}",
+                        idx
+                    )));
+                }
+                return Ok(async move { Ok((idx, true)) }.boxed().into());
             }
         }
+        let idx = next_keystore_idx();
+        if Some(idx) > self.last_idx {
+            self.last_idx = Some(idx);
+        }
+        self.index_entry(idx, entry);
+        Ok(async move { Ok((idx, false)) }.boxed().into())
+    }
+
+    fn handle_tag_entry(
+        &mut self,
+        idx: KeystoreIndex,
+        tag: String,
+    ) -> InternalApiHandlerResult<()> {
+        self.tags.set(idx, Some(tag))?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_trash_entry(
+        &mut self,
+        idx: KeystoreIndex,
+    ) -> InternalApiHandlerResult<()> {
+        if !self.trash.is_trashed(idx) {
+            let entry = self.unindex_entry(idx)?;
+            self.trashed.insert(idx, entry);
+            self.trash.trash(idx, self.clock.unix_now_secs());
+        }
         Ok(async move { Ok(()) }.boxed().into())
     }
 }
@@ -132,20 +315,166 @@ impl LairClientApiHandler for Internal {
     fn handle_lair_get_server_info(
         &mut self,
     ) -> LairClientApiHandlerResult<LairServerInfo> {
-        let mut out = LairServerInfo::default();
-        out.name = "[LAIR-TEST-KEYSTORE]".to_string();
-        out.version = crate::LAIR_VER.to_string();
+        let out = LairServerInfo {
+            name: "[LAIR-TEST-KEYSTORE]".to_string(),
+            version: crate::LAIR_VER.to_string(),
+            id_pub_key: self.server_id.pub_key.clone(),
+            is_locked: false,
+            wire_protocol_version:
+                crate::internal::wire::spec::WIRE_PROTOCOL_VERSION,
+            // the test keystore runs no entropy health checks
+            entropy_status: EntropyStatus::Unchecked,
+        };
 
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_lair_get_server_paths(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairServerPaths> {
+        Ok(async move {
+            Err("test keystore does not keep on-disk state".into())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_export_device_secret(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        Ok(
+            async move { Err("test keystore is not bound to a device".into()) }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_lair_get_capabilities(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairCapabilities> {
+        Ok(async move { Ok(LairCapabilities::ALL) }.boxed().into())
+    }
+
+    fn handle_lair_request_elevation(
+        &mut self,
+        _capabilities: LairCapabilities,
+        _duration: std::time::Duration,
+    ) -> LairClientApiHandlerResult<LairElevation> {
+        Ok(async move {
+            Err(LairError::Forbidden(
+                "test keystore grants every capability".to_string(),
+            ))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_elevation(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Option<LairElevation>> {
+        Ok(async move { Ok(None) }.boxed().into())
+    }
+
+    fn handle_lair_drop_elevation(&mut self) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_list_connections(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<LairConnectionInfo>> {
+        Ok(async move { Ok(Vec::new()) }.boxed().into())
+    }
+
+    fn handle_lair_reload_settings(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(
+            async move { Err("test keystore has no settings file".into()) }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_lair_set_event_filter(
+        &mut self,
+        _kinds: Vec<EventKind>,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_select_store(
+        &mut self,
+        name: String,
+    ) -> LairClientApiHandlerResult<()> {
+        // the test keystore only holds the default store
+        if name != DEFAULT_STORE_NAME {
+            return Err(format!("no such store: {}", name).into());
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
     fn handle_lair_get_last_entry_index(
         &mut self,
-    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex>> {
         let last_idx = self.last_idx;
         Ok(async move { Ok(last_idx) }.boxed().into())
     }
 
+    fn handle_lair_get_last_entry_index_64(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Option<KeystoreIndex64>> {
+        let last_idx = self.last_idx.map(KeystoreIndex64::from);
+        Ok(async move { Ok(last_idx) }.boxed().into())
+    }
+
+    fn handle_lair_get_entry_count(
+        &mut self,
+    ) -> LairClientApiHandlerResult<u64> {
+        let count = self.by_idx.len() as u64;
+        Ok(async move { Ok(count) }.boxed().into())
+    }
+
+    fn handle_lair_get_store_stats(
+        &mut self,
+    ) -> LairClientApiHandlerResult<StoreStats> {
+        let mut counts = BTreeMap::new();
+        for entry in self.by_idx.values() {
+            let entry_type = entry.entry_type();
+            counts.entry(entry_type as u32).or_insert((entry_type, 0)).1 += 1;
+        }
+        // nothing is written to disk, so nothing is erased or quarantined
+        let stats = StoreStats {
+            entry_counts: counts.into_values().collect(),
+            trashed_count: self.trashed.len() as u64,
+            ..Default::default()
+        };
+        Ok(async move { Ok(stats) }.boxed().into())
+    }
+
+    fn handle_lair_list_entries_page(
+        &mut self,
+        start: KeystoreIndex,
+        limit: u32,
+    ) -> LairClientApiHandlerResult<(Vec<LairEntryInfo>, Option<KeystoreIndex>)>
+    {
+        let all = self
+            .by_idx
+            .iter()
+            .chain(self.trashed.iter())
+            .map(|(idx, entry)| (*idx, entry))
+            .collect::<BTreeMap<_, _>>();
+        let out = entry::list_entries_page(
+            &all,
+            &self.tags,
+            &self.trash,
+            // imports are active right away, as with auto_activate_imports
+            &entry::EntryInactive::default(),
+            start,
+            limit,
+        );
+        Ok(async move { out }.boxed().into())
+    }
+
     fn handle_lair_get_entry_type(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -154,13 +483,300 @@ impl LairClientApiHandler for Internal {
             Some(entry) => entry,
             None => return Err("bad index".into()),
         };
-        let t = match entry {
-            entry::LairEntry::TlsCert(_) => LairEntryType::TlsCert,
-            entry::LairEntry::SignEd25519(_) => LairEntryType::SignEd25519,
-        };
+        let t = entry.to_info(keystore_index).entry_type;
         Ok(async move { Ok(t) }.boxed().into())
     }
 
+    fn handle_lair_get_entry_type_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
+        self.handle_lair_get_entry_type(keystore_index.try_into()?)
+    }
+
+    fn handle_lair_get_entry_types(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        let out = entry::get_entry_types(&self.by_idx, &indices);
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_lair_set_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: Option<String>,
+    ) -> LairClientApiHandlerResult<()> {
+        if !self.by_idx.contains_key(&keystore_index) {
+            return Err("bad index".into());
+        }
+        self.tags.set(keystore_index, tag)?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let idx = self.tags.resolve(&tag)?;
+        let t = match self.by_idx.get(&idx) {
+            Some(entry) => entry.to_info(idx).entry_type,
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { Ok((idx, t)) }.boxed().into())
+    }
+
+    fn handle_lair_find_entries_by_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+        kind: PrefixKind,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, LairEntryType)>> {
+        // trashed entries are already out of `by_idx`
+        let out = entry::find_entries_by_prefix(
+            &self.by_idx,
+            &self.trash,
+            &prefix,
+            kind,
+        );
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_lair_add_entry_alias(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        if !self.by_idx.contains_key(&keystore_index) {
+            return Err("bad index".into());
+        }
+        self.tags.add_alias(keystore_index, alias)?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_remove_entry_alias(
+        &mut self,
+        alias: String,
+    ) -> LairClientApiHandlerResult<()> {
+        self.tags.remove_alias(&alias)?;
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_trash_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        if !self.trash.is_trashed(keystore_index) {
+            let entry = self.unindex_entry(keystore_index)?;
+            self.trashed.insert(keystore_index, entry);
+            self.trash.trash(keystore_index, self.clock.unix_now_secs());
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_restore_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        self.trash.restore(keystore_index)?;
+        if let Some(entry) = self.trashed.remove(&keystore_index) {
+            self.index_entry(keystore_index, entry);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_activate_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        if !self.by_idx.contains_key(&keystore_index) {
+            return Err(LairError::EntryNotFound(format!(
+                "entry {}",
+                keystore_index
+            )));
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_purge_trashed(
+        &mut self,
+        older_than: std::time::Duration,
+    ) -> LairClientApiHandlerResult<u64> {
+        let cutoff = self
+            .clock
+            .unix_now_secs()
+            .saturating_sub(older_than.as_secs());
+        let mut purged = 0;
+        // latest first, so bound certs go before their signing entry
+        for idx in self.trash.trashed_before(cutoff) {
+            let bound = self.by_idx.values().chain(self.trashed.values()).any(
+                |entry| match entry {
+                    entry::LairEntry::TlsCert(cert) => {
+                        cert.sign_key_index == Some(idx)
+                    }
+                    _ => false,
+                },
+            );
+            if bound {
+                continue;
+            }
+            self.trashed.remove(&idx);
+            self.trash.remove(idx);
+            self.tags.remove_index(idx);
+            purged += 1;
+        }
+        Ok(async move { Ok(purged) }.boxed().into())
+    }
+
+    fn handle_lair_erase_entries(
+        &mut self,
+        indices: Vec<KeystoreIndex>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        internal::wire::check_bulk_entries_len(indices.len())?;
+        let mut results = Vec::with_capacity(indices.len());
+        results.resize_with(indices.len(), || Ok(()));
+        // latest first, so bound certs go before their signing entry
+        let mut order = (0..indices.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| indices[*b].cmp(&indices[*a]));
+        for i in order {
+            let idx = indices[i];
+            let bound = self.by_idx.values().chain(self.trashed.values()).any(
+                |entry| match entry {
+                    entry::LairEntry::TlsCert(cert) => {
+                        cert.sign_key_index == Some(idx)
+                    }
+                    _ => false,
+                },
+            );
+            results[i] = if self.trashed.remove(&idx).is_some() {
+                self.trash.remove(idx);
+                self.tags.remove_index(idx);
+                Ok(())
+            } else if !self.by_idx.contains_key(&idx) {
+                Err(LairError::EntryNotFound(format!("entry {}", idx)))
+            } else if bound {
+                Err(LairError::Forbidden(format!(
+                    "entry {} is referenced by bound tls certs",
+                    idx
+                )))
+            } else {
+                self.unindex_entry(idx).map(|_| {
+                    self.tags.remove_index(idx);
+                })
+            };
+        }
+        Ok(async move { Ok(results) }.boxed().into())
+    }
+
+    fn handle_lair_set_entry_tags(
+        &mut self,
+        tags: Vec<(KeystoreIndex, String)>,
+    ) -> LairClientApiHandlerResult<Vec<LairResult<()>>> {
+        internal::wire::check_bulk_entries_len(tags.len())?;
+        let results = tags
+            .into_iter()
+            .map(|(idx, tag)| {
+                if !self.by_idx.contains_key(&idx) {
+                    return Err("bad index".into());
+                }
+                self.tags.set(idx, Some(tag))
+            })
+            .collect();
+        Ok(async move { Ok(results) }.boxed().into())
+    }
+
+    fn handle_lair_list_quarantined(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<QuarantinedEntry>> {
+        // nothing is loaded from disk, so nothing can fail to load
+        Ok(async move { Ok(Vec::new()) }.boxed().into())
+    }
+
+    fn handle_lair_self_test(&mut self) -> LairClientApiHandlerResult<()> {
+        Ok(internal::self_test::self_test().boxed().into())
+    }
+
+    fn handle_lair_attest_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        challenge: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Attestation> {
+        let entry = match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry.clone(),
+            None => return Err("bad index".into()),
+        };
+        let server_id = self.server_id.clone();
+        let timestamp = self.clock.unix_now_secs();
+        Ok(async move {
+            attest::attest_entry(&server_id, &entry, challenge, timestamp).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_prove_server_identity(
+        &mut self,
+        nonce: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<ServerIdentityProof> {
+        let server_id = self.server_id.clone();
+        Ok(async move {
+            attest::prove_server_identity(
+                &server_id,
+                crate::LAIR_VER.to_string(),
+                nonce,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_wrap_entry(
+        &mut self,
+        target_index: KeystoreIndex,
+        recipient_pub_key: X25519PubKey,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let entry = match self.by_idx.get(&target_index) {
+            Some(entry) => entry.clone(),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move {
+            wrap::wrap_entry(
+                internal::entropy::OsEntropy::new_handle(),
+                &entry,
+                recipient_pub_key,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_unwrap_entry(
+        &mut self,
+        recipient_index: KeystoreIndex,
+        wrapped: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType, bool)> {
+        let priv_key = match match self.by_idx.get(&recipient_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let mut entry = wrap::unwrap_entry(priv_key, wrapped).await?;
+            if let entry::LairEntry::TlsCert(cert) = &mut entry {
+                cert.sni = tls::tls_cert_import_sni(cert, false)?;
+            }
+            let entry_type = entry.to_info(0.into()).entry_type;
+            let (idx, already_existed) = i_s.import_unique(entry).await?;
+            Ok((idx, entry_type, already_existed))
+        }
+        .boxed()
+        .into())
+    }
+
     fn handle_tls_cert_new_self_signed_from_entropy(
         &mut self,
         options: TlsCertOptions,
@@ -175,6 +791,9 @@ impl LairClientApiHandler for Internal {
                     priv_key_der: cert.priv_key_der.into(),
                     cert_der: cert.cert_der.into(),
                     cert_digest: cert.cert_digest.into(),
+                    sign_key_index: None,
+                    // fixture certs are imported, read it from the cert
+                    alg: None,
                 };
                 let sni = entry.sni.clone();
                 let digest = entry.cert_digest.clone();
@@ -188,8 +807,11 @@ impl LairClientApiHandler for Internal {
         let i_s = self.i_s.clone();
         Ok(async move {
             let idx = next_keystore_idx();
-            let entry =
-                tls::tls_cert_self_signed_new_from_entropy(options).await?;
+            let entry = tls::tls_cert_self_signed_new_from_entropy(
+                options,
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
             let sni = entry.sni.clone();
             let digest = entry.cert_digest.clone();
             let entry = entry::LairEntry::from(entry);
@@ -200,23 +822,77 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
-    fn handle_tls_cert_get(
+    fn handle_tls_cert_new_self_signed_from_sign_key(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
-        let out = match match self.by_idx.get(&keystore_index) {
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let sign = match match self.by_idx.get(&keystore_index) {
             Some(entry) => entry,
             None => return Err("bad index".into()),
         } {
-            entry::LairEntry::TlsCert(cert) => {
-                (cert.sni.clone(), cert.cert_digest.clone())
-            }
+            entry::LairEntry::SignEd25519(sign) => sign.clone(),
             _ => return Err("bad type".into()),
         };
-        Ok(async move { Ok(out) }.boxed().into())
-    }
-
-    fn handle_tls_cert_get_cert_by_index(
+        if !sign.policy.exportable {
+            return Err(LairError::ExportForbidden(format!(
+                "entry {} is not exportable",
+                keystore_index
+            )));
+        }
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = tls::tls_cert_self_signed_new_from_sign_key(
+                keystore_index,
+                sign,
+                options,
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let sni = entry.sni.clone();
+            let digest = entry.cert_digest.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, sni, digest))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::TlsCert(cert) => {
+                (cert.sni.clone(), cert.cert_digest.clone())
+            }
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_tls_cert_get_info(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, TlsCertAlg)> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::TlsCert(cert) => {
+                (cert.sni.clone(), cert.cert_digest.clone(), cert.alg())
+            }
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_tls_cert_get_cert_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
     ) -> LairClientApiHandlerResult<Cert> {
@@ -241,17 +917,103 @@ impl LairClientApiHandler for Internal {
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_tls_cert_get_digest(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest_alg: DigestAlg,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::TlsCert(cert) => cert.digest(digest_alg),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_tls_cert_get_cert_by_typed_digest(
+        &mut self,
+        digest_alg: DigestAlg,
+        digest: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let out = self
+            .by_idx
+            .values()
+            .find_map(|entry| match entry {
+                entry::LairEntry::TlsCert(cert)
+                    if cert.digest(digest_alg) == digest =>
+                {
+                    Some(cert.cert_der.clone())
+                }
+                _ => None,
+            })
+            .ok_or_else(|| {
+                LairError::EntryNotFound(format!(
+                    "no tls cert with {:?} digest {:?}",
+                    digest_alg, digest
+                ))
+            });
+        Ok(async move { out }.boxed().into())
+    }
+
     fn handle_tls_cert_get_cert_by_sni(
         &mut self,
         cert_sni: CertSni,
     ) -> LairClientApiHandlerResult<Cert> {
-        let out = match self.cert_by_sni.get(&cert_sni) {
+        let out = match self.cert_by_sni.get(&cert_sni.normalized()) {
             Some(cert) => cert.cert_der.clone(),
             None => return Err("bad sni".into()),
         };
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_tls_cert_resolve_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let out = self
+            .by_idx
+            .iter()
+            .find(|(_, entry)| match entry {
+                entry::LairEntry::TlsCert(cert) => {
+                    cert.cert_digest == cert_digest
+                }
+                _ => false,
+            })
+            .map(|(idx, _)| *idx)
+            .ok_or_else(|| {
+                LairError::EntryNotFound(format!(
+                    "no tls cert with digest {:?}",
+                    cert_digest
+                ))
+            });
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_tls_cert_resolve_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let out = self
+            .by_idx
+            .iter()
+            .find(|(_, entry)| match entry {
+                entry::LairEntry::TlsCert(cert) => {
+                    cert.sni.normalized() == cert_sni.normalized()
+                }
+                _ => false,
+            })
+            .map(|(idx, _)| *idx)
+            .ok_or_else(|| {
+                LairError::EntryNotFound(format!(
+                    "no tls cert with sni {:?}",
+                    cert_sni
+                ))
+            });
+        Ok(async move { out }.boxed().into())
+    }
+
     fn handle_tls_cert_get_priv_key_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -281,13 +1043,30 @@ impl LairClientApiHandler for Internal {
         &mut self,
         cert_sni: CertSni,
     ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let out = match self.cert_by_sni.get(&cert_sni) {
+        let out = match self.cert_by_sni.get(&cert_sni.normalized()) {
             Some(cert) => cert.priv_key_der.clone(),
             None => return Err("bad sni".into()),
         };
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_tls_cert_get_bundle_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest, Cert, CertPrivKey)>
+    {
+        let out = match self.by_idx.get(&self.tags.resolve(&tag)?) {
+            Some(entry::LairEntry::TlsCert(cert)) => (
+                cert.sni.clone(),
+                cert.cert_digest.clone(),
+                cert.cert_der.clone(),
+                cert.priv_key_der.clone(),
+            ),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
     fn handle_sign_ed25519_new_from_entropy(
         &mut self,
     ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
@@ -299,6 +1078,7 @@ impl LairClientApiHandler for Internal {
                 let entry = entry::EntrySignEd25519 {
                     priv_key: keypair.priv_key.into(),
                     pub_key: keypair.pub_key.into(),
+                    policy: Default::default(),
                 };
                 let pk = entry.pub_key.clone();
                 let entry = entry::LairEntry::from(entry);
@@ -311,17 +1091,172 @@ impl LairClientApiHandler for Internal {
         let i_s = self.i_s.clone();
         Ok(async move {
             let idx = next_keystore_idx();
-            let entry =
-                sign_ed25519::sign_ed25519_keypair_new_from_entropy().await?;
+            let entry = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new(
+        &mut self,
+        backing: KeyBacking,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        match backing {
+            KeyBacking::Software => self.handle_sign_ed25519_new_from_entropy(),
+            _ => Err("the test keystore has no hardware token".into()),
+        }
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_with_options(
+        &mut self,
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey)> {
+        self.check_new_tag(&options)?;
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let mut entry =
+                sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                    internal::entropy::OsEntropy::new_handle(),
+                )
+                .await?;
+            entry.policy = entry::SignKeyPolicy::from(&options);
             let pk = entry.pub_key.clone();
             let entry = entry::LairEntry::from(entry);
             i_s.finalize_entry(idx, entry).await?;
+            if let Some(tag) = options.tag {
+                i_s.tag_entry(idx, tag).await?;
+            }
             Ok((idx, pk))
         }
         .boxed()
         .into())
     }
 
+    fn handle_sign_ed25519_new_from_seed_with_options(
+        &mut self,
+        seed: [u8; 32],
+        options: SignKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        self.check_new_tag(&options)?;
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let mut entry =
+                sign_ed25519::sign_ed25519_keypair_new_from_seed(seed).await?;
+            entry.policy = entry::SignKeyPolicy::from(&options);
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            let (idx, already_existed) = i_s.import_unique(entry).await?;
+            if !already_existed {
+                if let Some(tag) = options.tag {
+                    i_s.tag_entry(idx, tag).await?;
+                }
+            }
+            Ok((idx, pk, already_existed))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_get_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignKeyOptions> {
+        let policy = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.policy,
+            entry::LairEntry::SignEd25519Hardware(_) => {
+                entry::SignKeyPolicy::default()
+            }
+            _ => return Err("bad type".into()),
+        };
+        let tag = self.tags.get(keystore_index).map(|tag| tag.to_string());
+        let out = policy.to_options(tag);
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_sign_ed25519_rotate(
+        &mut self,
+        old_keystore_index: KeystoreIndex,
+        options: RotateKeyOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, RotationRecord)> {
+        let old = match self.by_idx.get(&old_keystore_index) {
+            Some(entry::LairEntry::SignEd25519(keypair)) => keypair.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        self.check_new_tag(&options.key_options)?;
+        let policy = self.check_sign_policy(old_keystore_index, &old)?;
+        let i_s = self.i_s.clone();
+        let timestamp = self.clock.unix_now_secs();
+        Ok(async move {
+            policy.await?;
+            let idx = next_keystore_idx();
+            let mut new = sign_ed25519::sign_ed25519_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            new.policy = entry::SignKeyPolicy::from(&options.key_options);
+            new.policy.rotated_from = Some(old_keystore_index);
+            let record =
+                internal::rotate::sign_rotation(&old, &new, timestamp).await?;
+            i_s.finalize_entry(idx, entry::LairEntry::from(new)).await?;
+            if let Some(tag) = options.key_options.tag {
+                i_s.tag_entry(idx, tag).await?;
+            }
+            if options.trash_old_key {
+                i_s.trash_entry(old_keystore_index).await?;
+            }
+            Ok((idx, record))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_export_seed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<[u8; 32]> {
+        let seed = match self.by_idx.get(&keystore_index) {
+            Some(entry) => sign_ed25519::sign_ed25519_export_seed(entry)?,
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { Ok(seed) }.boxed().into())
+    }
+
+    fn handle_sign_ed25519_import_ssh_key(
+        &mut self,
+        key: Arc<Vec<u8>>,
+        passphrase: Option<String>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd25519PubKey, bool)>
+    {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let key = internal::ssh_key::ssh_key_read_ed25519(key, passphrase)
+                .await?;
+            let pk = key.keypair.pub_key.clone();
+            let entry = entry::LairEntry::from(key.keypair);
+            let (idx, already_existed) = i_s.import_unique(entry).await?;
+            if !already_existed && entry::check_entry_tag(&key.comment).is_ok()
+            {
+                i_s.tag_entry(idx, key.comment).await?;
+            }
+            Ok((idx, pk, already_existed))
+        }
+        .boxed()
+        .into())
+    }
+
     fn handle_sign_ed25519_get(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -336,22 +1271,58 @@ impl LairClientApiHandler for Internal {
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_sign_ed25519_resolve_by_pub_key(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let out = self
+            .by_idx
+            .iter()
+            .find(|(_, entry)| match entry {
+                entry::LairEntry::SignEd25519(keypair) => {
+                    keypair.pub_key == pub_key
+                }
+                _ => false,
+            })
+            .map(|(idx, _)| *idx)
+            .ok_or_else(|| {
+                LairError::EntryNotFound(format!(
+                    "no ed25519 keypair with pub key {:?}",
+                    pub_key
+                ))
+            });
+        Ok(async move { out }.boxed().into())
+    }
+
     fn handle_sign_ed25519_sign_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
         message: Arc<Vec<u8>>,
     ) -> LairClientApiHandlerResult<SignEd25519Signature> {
-        let priv_key = match match self.by_idx.get(&keystore_index) {
+        let keypair = match match self.by_idx.get(&keystore_index) {
             Some(entry) => entry,
             None => return Err("bad index".into()),
         } {
-            entry::LairEntry::SignEd25519(keypair) => keypair.priv_key.clone(),
+            entry::LairEntry::SignEd25519(keypair) => keypair.clone(),
             _ => return Err("bad type".into()),
         };
-        Ok(
-            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
-                .boxed()
-                .into(),
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            sign_ed25519::sign_ed25519(keypair.priv_key, message).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_64(
+        &mut self,
+        keystore_index: KeystoreIndex64,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        self.handle_sign_ed25519_sign_by_index(
+            keystore_index.try_into()?,
+            message,
         )
     }
 
@@ -360,65 +1331,815 @@ impl LairClientApiHandler for Internal {
         pub_key: SignEd25519PubKey,
         message: Arc<Vec<u8>>,
     ) -> LairClientApiHandlerResult<SignEd25519Signature> {
-        let priv_key = match self.sign_by_pub.get(&pub_key) {
-            Some(keypair) => keypair.priv_key.clone(),
-            None => return Err(LairError::PubKeyNotFound),
+        let (keystore_index, keypair) = self.sign_index_by_pub(&pub_key)?;
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            sign_ed25519::sign_ed25519(keypair.priv_key, message).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_v2(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let keypair = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.clone(),
+            _ => return Err("bad type".into()),
         };
-        Ok(
-            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
-                .boxed()
-                .into(),
-        )
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            let signature =
+                sign_ed25519::sign_ed25519(keypair.priv_key, message).await?;
+            Ok((keypair.pub_key, signature))
+        }
+        .boxed()
+        .into())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn handle_sign_ed25519_sign_by_index_with_options(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        options: SignOptions,
+    ) -> LairClientApiHandlerResult<(SignEd25519Signature, Option<u64>)> {
+        let keypair = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.clone(),
+            _ => return Err("bad type".into()),
+        };
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        let clock = self.clock.clone();
+        Ok(async move {
+            policy.await?;
+            let timestamp = match options.include_timestamp {
+                true => Some(clock.unix_now_millis()),
+                false => None,
+            };
+            let message = match timestamp {
+                Some(timestamp) => {
+                    Arc::new(timestamped_message(timestamp, &message))
+                }
+                None => message,
+            };
+            let signature =
+                sign_ed25519::sign_ed25519(keypair.priv_key, message).await?;
+            Ok((signature, timestamp))
+        }
+        .boxed()
+        .into())
+    }
 
-    const PUB1: &[u8] = &[
-        154, 185, 40, 0, 115, 213, 127, 247, 174, 124, 110, 222, 11, 151, 230,
-        233, 2, 171, 91, 154, 79, 50, 137, 45, 188, 110, 75, 56, 45, 18, 156,
-        158,
-    ];
-    const SEC1: &[u8] = &[
-        207, 84, 35, 155, 191, 10, 211, 240, 254, 92, 222, 153, 125, 241, 80,
-        102, 189, 217, 201, 140, 112, 159, 21, 148, 138, 41, 85, 90, 169, 56,
-        174, 72,
-    ];
-    const PUB2: &[u8] = &[
-        123, 88, 252, 103, 102, 190, 254, 104, 167, 210, 29, 41, 26, 225, 12,
-        113, 137, 104, 253, 93, 101, 214, 107, 125, 58, 208, 110, 203, 2, 166,
-        30, 88,
-    ];
-    const SEC2: &[u8] = &[
-        59, 31, 135, 117, 115, 107, 84, 52, 95, 216, 51, 180, 79, 81, 14, 169,
-        163, 149, 166, 174, 167, 143, 3, 211, 123, 224, 24, 25, 201, 40, 81,
-        188,
-    ];
+    fn handle_sign_ed25519_sign_by_pub_key_v2(
+        &mut self,
+        pub_key: SignEd25519PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let (keystore_index, keypair) = self.sign_index_by_pub(&pub_key)?;
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            let signature =
+                sign_ed25519::sign_ed25519(keypair.priv_key, message).await?;
+            Ok((keypair.pub_key, signature))
+        }
+        .boxed()
+        .into())
+    }
 
-    const CERT_SNI: &str = "ar1J-HVz0EO4CzS9CN8EFta.ad471maBa70w5vn6nNilfUa";
-    const CERT_SEC: &[u8] = &[
-        48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32, 135, 101, 23,
-        181, 167, 183, 114, 94, 169, 84, 144, 224, 192, 41, 112, 118, 149, 226,
-        42, 187, 247, 210, 54, 43, 83, 125, 13, 209, 93, 207, 33, 153, 161, 35,
-        3, 33, 0, 83, 74, 255, 70, 132, 118, 51, 92, 85, 250, 176, 123, 49,
-        206, 237, 79, 161, 136, 99, 44, 52, 128, 94, 174, 55, 174, 198, 113,
-        79, 135, 111, 26,
-    ];
-    const CERT: &[u8] = &[
-        48, 130, 1, 48, 48, 129, 227, 160, 3, 2, 1, 2, 2, 1, 42, 48, 5, 6, 3,
-        43, 101, 112, 48, 33, 49, 31, 48, 29, 6, 3, 85, 4, 3, 12, 22, 114, 99,
-        103, 101, 110, 32, 115, 101, 108, 102, 32, 115, 105, 103, 110, 101,
-        100, 32, 99, 101, 114, 116, 48, 32, 23, 13, 55, 53, 48, 49, 48, 49, 48,
-        48, 48, 48, 48, 48, 90, 24, 15, 52, 48, 57, 54, 48, 49, 48, 49, 48, 48,
-        48, 48, 48, 48, 90, 48, 33, 49, 31, 48, 29, 6, 3, 85, 4, 3, 12, 22,
-        114, 99, 103, 101, 110, 32, 115, 101, 108, 102, 32, 115, 105, 103, 110,
-        101, 100, 32, 99, 101, 114, 116, 48, 42, 48, 5, 6, 3, 43, 101, 112, 3,
-        33, 0, 83, 74, 255, 70, 132, 118, 51, 92, 85, 250, 176, 123, 49, 206,
-        237, 79, 161, 136, 99, 44, 52, 128, 94, 174, 55, 174, 198, 113, 79,
-        135, 111, 26, 163, 62, 48, 60, 48, 58, 6, 3, 85, 29, 17, 4, 51, 48, 49,
-        130, 47, 97, 114, 49, 74, 45, 72, 86, 122, 48, 69, 79, 52, 67, 122, 83,
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(SignEd25519PubKey, SignEd25519Signature)>
+    {
+        let keystore_index = self.tags.resolve(&tag)?;
+        let keypair = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SignEd25519(keypair)) => keypair.clone(),
+            _ => return Err("bad type".into()),
+        };
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            let signature =
+                sign_ed25519::sign_ed25519(keypair.priv_key, message).await?;
+            Ok((keypair.pub_key, signature))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_ph_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        prehash: [u8; 64],
+        context: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let keypair = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.clone(),
+            _ => return Err("bad type".into()),
+        };
+        let policy = self.check_sign_policy(keystore_index, &keypair)?;
+        Ok(async move {
+            policy.await?;
+            sign_ed25519::sign_ed25519ph(keypair.priv_key, context, prehash)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry =
+                sign_secp256k1::sign_secp256k1_keypair_new_from_entropy(
+                    internal::entropy::OsEntropy::new_handle(),
+                )
+                .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSecp256k1PubKey, bool)>
+    {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry =
+                sign_secp256k1::sign_secp256k1_keypair_new_from_seed(seed)
+                    .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            let (idx, already_existed) = i_s.import_unique(entry).await?;
+            Ok((idx, pk, already_existed))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignSecp256k1PubKey> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignSecp256k1(keypair) => keypair.pub_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_sign_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message_hash: [u8; 32],
+    ) -> LairClientApiHandlerResult<(SignSecp256k1Signature, u8)> {
+        let fut = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignSecp256k1(keypair) => {
+                keypair.sign(message_hash)
+            }
+            _ => return Err("bad type".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
+    fn handle_sign_ed448_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignEd448PubKey)> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let i_s = self.i_s.clone();
+            Ok(async move {
+                let idx = next_keystore_idx();
+                let entry = sign_ed448::sign_ed448_keypair_new_from_entropy(
+                    internal::entropy::OsEntropy::new_handle(),
+                )
+                .await?;
+                let pk = entry.pub_key.clone();
+                let entry = entry::LairEntry::from(entry);
+                i_s.finalize_entry(idx, entry).await?;
+                Ok((idx, pk))
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    fn handle_sign_ed448_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignEd448PubKey> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = keystore_index;
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let out = match match self.by_idx.get(&keystore_index) {
+                Some(entry) => entry,
+                None => return Err("bad index".into()),
+            } {
+                entry::LairEntry::SignEd448(keypair) => keypair.pub_key.clone(),
+                _ => return Err("bad type".into()),
+            };
+            Ok(async move { Ok(out) }.boxed().into())
+        }
+    }
+
+    fn handle_sign_ed448_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = (keystore_index, message);
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            let fut = match match self.by_idx.get(&keystore_index) {
+                Some(entry) => entry,
+                None => return Err("bad index".into()),
+            } {
+                entry::LairEntry::SignEd448(keypair) => keypair.sign(message),
+                _ => return Err("bad type".into()),
+            };
+            Ok(fut.boxed().into())
+        }
+    }
+
+    fn handle_sign_ed448_sign_by_pub_key(
+        &mut self,
+        pub_key: SignEd448PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd448Signature> {
+        #[cfg(not(feature = "ed448"))]
+        {
+            let _ = (pub_key, message);
+            Err(LairError::unsupported_alg("Ed448", "ed448"))
+        }
+        #[cfg(feature = "ed448")]
+        {
+            // few enough entries in tests to scan, lowest index first
+            let fut = match self.by_idx.values().find_map(|entry| match entry {
+                entry::LairEntry::SignEd448(keypair)
+                    if keypair.pub_key == pub_key =>
+                {
+                    Some(keypair.sign(message.clone()))
+                }
+                _ => None,
+            }) {
+                Some(fut) => fut,
+                None => return Err(LairError::PubKeyNotFound),
+            };
+            Ok(fut.boxed().into())
+        }
+    }
+
+    fn handle_sign_bls_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignBlsPubKey)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = sign_bls::sign_bls_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_bls_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignBlsPubKey> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignBls(keypair) => keypair.pub_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_sign_bls_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        dst: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignBlsSignature> {
+        let fut = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignBls(keypair) => keypair.sign(message, dst),
+            _ => return Err("bad type".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
+    fn handle_sign_sr25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = sign_sr25519::sign_sr25519_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_new_from_seed(
+        &mut self,
+        seed: [u8; 32],
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, SignSr25519PubKey, bool)>
+    {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry =
+                sign_sr25519::sign_sr25519_keypair_new_from_seed(seed).await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            let (idx, already_existed) = i_s.import_unique(entry).await?;
+            Ok((idx, pk, already_existed))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_sr25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<SignSr25519PubKey> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignSr25519(keypair) => keypair.pub_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_sign_sr25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: Arc<Vec<u8>>,
+        context: Vec<u8>,
+    ) -> LairClientApiHandlerResult<SignSr25519Signature> {
+        let fut = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignSr25519(keypair) => {
+                keypair.sign(message, context)
+            }
+            _ => return Err("bad type".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
+    fn handle_x25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, X25519PubKey)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = x25519::x25519_keypair_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<X25519PubKey> {
+        let out = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.pub_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_crypto_box_to_sign_pub_key(
+        &mut self,
+        sender_x25519_index: KeystoreIndex,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let priv_key = match match self.by_idx.get(&sender_x25519_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        let recipient_pub_key = recipient_sign_pub_key.to_x25519_pub_key()?;
+        Ok(async move {
+            x25519::crypto_box(
+                internal::entropy::OsEntropy::new_handle(),
+                priv_key,
+                recipient_pub_key,
+                data,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_open_by_sign_pub_key(
+        &mut self,
+        recipient_sign_pub_key: SignEd25519PubKey,
+        sender_x25519_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let priv_key = match self.sign_by_pub.get(&recipient_sign_pub_key) {
+            Some(keypair) => {
+                x25519::sign_ed25519_priv_key_to_x25519(&keypair.priv_key)?
+            }
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            x25519::crypto_box_open(priv_key, sender_x25519_pub_key, data).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_seal_open_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(
+            async move { x25519::crypto_box_seal_open(priv_key, data).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_age_decrypt_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        stanzas: Vec<AgeStanza>,
+    ) -> LairClientApiHandlerResult<[u8; AGE_FILE_KEY_LEN]> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            internal::age::age_x25519_unwrap(priv_key, stanzas).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_random_bytes(
+        &mut self,
+        len: u32,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let out = entropy::random_bytes(
+            &internal::entropy::OsEntropy::new_handle(),
+            len,
+        );
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_lair_new_uuid(&mut self) -> LairClientApiHandlerResult<[u8; 16]> {
+        let out =
+            entropy::new_uuid(&internal::entropy::OsEntropy::new_handle());
+        Ok(async move { out }.boxed().into())
+    }
+
+    fn handle_pw_pepper_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = pw_hash::pw_pepper_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+            )
+            .await?;
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok(idx)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_pw_hash(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        options: PwHashOptions,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = match match self.by_idx.get(&pepper_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::PwPepper(pepper) => pepper.pw_hash(
+                internal::entropy::OsEntropy::new_handle(),
+                password,
+                options,
+            ),
+            _ => return Err("bad type".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
+    fn handle_pw_verify(
+        &mut self,
+        pepper_index: KeystoreIndex,
+        password: Arc<Vec<u8>>,
+        hash: String,
+    ) -> LairClientApiHandlerResult<bool> {
+        let fut = match match self.by_idx.get(&pepper_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::PwPepper(pepper) => {
+                pepper.pw_verify(password, hash)
+            }
+            _ => return Err("bad type".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
+    fn handle_totp_new_from_entropy(
+        &mut self,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = totp::totp_new_from_entropy(
+                internal::entropy::OsEntropy::new_handle(),
+                options,
+            )
+            .await?;
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok(idx)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_import(
+        &mut self,
+        secret_b32: String,
+        options: TotpOptions,
+    ) -> LairClientApiHandlerResult<KeystoreIndex> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = totp::totp_import(secret_b32, options).await?;
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok(idx)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_totp_generate(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        at: Option<u64>,
+    ) -> LairClientApiHandlerResult<String> {
+        let res = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::TotpSecret(secret)) => secret.generate(
+                Some(at.unwrap_or_else(|| self.clock.unix_now_secs())),
+            ),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { res }.boxed().into())
+    }
+
+    fn handle_totp_export_provisioning_uri(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        label: String,
+        issuer: String,
+    ) -> LairClientApiHandlerResult<String> {
+        let res = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::TotpSecret(secret)) => {
+                secret.provisioning_uri(&label, &issuer)
+            }
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { res }.boxed().into())
+    }
+
+    fn handle_ephemeral_x25519_new(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, X25519PubKey)> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(async move {
+            ephemeral
+                .x25519_new(internal::entropy::OsEntropy::new_handle())
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_sign_ed25519_new(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(EphemeralHandle, SignEd25519PubKey)> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(async move {
+            ephemeral
+                .sign_ed25519_new(internal::entropy::OsEntropy::new_handle())
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_sign_ed25519_sign(
+        &mut self,
+        handle: EphemeralHandle,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<SignEd25519Signature> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(
+            async move { ephemeral.sign_ed25519_sign(handle, message).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_ephemeral_crypto_box(
+        &mut self,
+        sender_handle: EphemeralHandle,
+        recipient_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(async move {
+            ephemeral
+                .crypto_box(
+                    internal::entropy::OsEntropy::new_handle(),
+                    sender_handle,
+                    recipient_pub_key,
+                    data,
+                )
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_crypto_box_open(
+        &mut self,
+        recipient_handle: EphemeralHandle,
+        sender_pub_key: X25519PubKey,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let ephemeral = self.ephemeral.clone();
+        Ok(async move {
+            ephemeral
+                .crypto_box_open(recipient_handle, sender_pub_key, data)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_ephemeral_drop(
+        &mut self,
+        handle: EphemeralHandle,
+    ) -> LairClientApiHandlerResult<()> {
+        let res = self.ephemeral.remove(handle);
+        Ok(async move { res }.boxed().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUB1: &[u8] = &[
+        154, 185, 40, 0, 115, 213, 127, 247, 174, 124, 110, 222, 11, 151, 230,
+        233, 2, 171, 91, 154, 79, 50, 137, 45, 188, 110, 75, 56, 45, 18, 156,
+        158,
+    ];
+    const SEC1: &[u8] = &[
+        207, 84, 35, 155, 191, 10, 211, 240, 254, 92, 222, 153, 125, 241, 80,
+        102, 189, 217, 201, 140, 112, 159, 21, 148, 138, 41, 85, 90, 169, 56,
+        174, 72,
+    ];
+    const PUB2: &[u8] = &[
+        123, 88, 252, 103, 102, 190, 254, 104, 167, 210, 29, 41, 26, 225, 12,
+        113, 137, 104, 253, 93, 101, 214, 107, 125, 58, 208, 110, 203, 2, 166,
+        30, 88,
+    ];
+    const SEC2: &[u8] = &[
+        59, 31, 135, 117, 115, 107, 84, 52, 95, 216, 51, 180, 79, 81, 14, 169,
+        163, 149, 166, 174, 167, 143, 3, 211, 123, 224, 24, 25, 201, 40, 81,
+        188,
+    ];
+
+    const CERT_SNI: &str = "ar1J-HVz0EO4CzS9CN8EFta.ad471maBa70w5vn6nNilfUa";
+    const CERT_SEC: &[u8] = &[
+        48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32, 135, 101, 23,
+        181, 167, 183, 114, 94, 169, 84, 144, 224, 192, 41, 112, 118, 149, 226,
+        42, 187, 247, 210, 54, 43, 83, 125, 13, 209, 93, 207, 33, 153, 161, 35,
+        3, 33, 0, 83, 74, 255, 70, 132, 118, 51, 92, 85, 250, 176, 123, 49,
+        206, 237, 79, 161, 136, 99, 44, 52, 128, 94, 174, 55, 174, 198, 113,
+        79, 135, 111, 26,
+    ];
+    const CERT: &[u8] = &[
+        48, 130, 1, 48, 48, 129, 227, 160, 3, 2, 1, 2, 2, 1, 42, 48, 5, 6, 3,
+        43, 101, 112, 48, 33, 49, 31, 48, 29, 6, 3, 85, 4, 3, 12, 22, 114, 99,
+        103, 101, 110, 32, 115, 101, 108, 102, 32, 115, 105, 103, 110, 101,
+        100, 32, 99, 101, 114, 116, 48, 32, 23, 13, 55, 53, 48, 49, 48, 49, 48,
+        48, 48, 48, 48, 48, 90, 24, 15, 52, 48, 57, 54, 48, 49, 48, 49, 48, 48,
+        48, 48, 48, 48, 90, 48, 33, 49, 31, 48, 29, 6, 3, 85, 4, 3, 12, 22,
+        114, 99, 103, 101, 110, 32, 115, 101, 108, 102, 32, 115, 105, 103, 110,
+        101, 100, 32, 99, 101, 114, 116, 48, 42, 48, 5, 6, 3, 43, 101, 112, 3,
+        33, 0, 83, 74, 255, 70, 132, 118, 51, 92, 85, 250, 176, 123, 49, 206,
+        237, 79, 161, 136, 99, 44, 52, 128, 94, 174, 55, 174, 198, 113, 79,
+        135, 111, 26, 163, 62, 48, 60, 48, 58, 6, 3, 85, 29, 17, 4, 51, 48, 49,
+        130, 47, 97, 114, 49, 74, 45, 72, 86, 122, 48, 69, 79, 52, 67, 122, 83,
         57, 67, 78, 56, 69, 70, 116, 97, 46, 97, 100, 52, 55, 49, 109, 97, 66,
         97, 55, 48, 119, 53, 118, 110, 54, 110, 78, 105, 108, 102, 85, 97, 48,
         5, 6, 3, 43, 101, 112, 3, 65, 0, 211, 114, 220, 25, 145, 60, 41, 144,
@@ -433,109 +2154,1413 @@ mod tests {
         240, 239,
     ];
 
-    async fn setup() -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
+    async fn setup() -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
+        let (api, _evt) = spawn_test_keystore(
+            vec![
+                FixtureSignEd25519Keypair {
+                    pub_key: PUB1.to_vec(),
+                    priv_key: SEC1.to_vec(),
+                },
+                FixtureSignEd25519Keypair {
+                    pub_key: PUB2.to_vec(),
+                    priv_key: SEC2.to_vec(),
+                },
+            ],
+            vec![FixtureTlsCert {
+                priv_key_der: CERT_SEC.to_vec(),
+                sni: CERT_SNI.to_string(),
+                cert_der: CERT.to_vec(),
+                cert_digest: CERT_DIGEST.to_vec(),
+            }],
+        )
+        .await?;
+        Ok(api)
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_signing() -> LairResult<()> {
+        let api = setup().await?;
+        let api2 = api.clone();
+
+        let sys_info = api.lair_get_server_info().await?;
+        assert_eq!(crate::LAIR_VER, sys_info.version);
+        let sys_info = api2.lair_get_server_info().await?;
+        assert_eq!(crate::LAIR_VER, sys_info.version);
+        api.lair_self_test().await?;
+
+        let (idx1, pk1) = api.sign_ed25519_new_from_entropy().await?;
+        assert_eq!(pk1.0.as_slice(), PUB1);
+        assert_eq!(pk1, api.sign_ed25519_get(idx1).await?);
+        let (idx2, pk2) = api2.sign_ed25519_new_from_entropy().await?;
+        assert_eq!(pk2.0.as_slice(), PUB2);
+        assert_eq!(pk2, api2.sign_ed25519_get(idx2).await?);
+        let (idx3, pk3) = api.sign_ed25519_new_from_entropy().await?;
+        assert_eq!(pk3, api.sign_ed25519_get(idx3).await?);
+        assert_ne!(pk3.0.as_slice(), PUB1);
+        assert_ne!(pk3.0.as_slice(), PUB2);
+
+        assert_eq!(Some(idx3), api.lair_get_last_entry_index().await?);
+
+        let data = std::sync::Arc::new(b"test-data".to_vec());
+
+        let sig1 = api.sign_ed25519_sign_by_index(idx1, data.clone()).await?;
+        let sig2 = api
+            .sign_ed25519_sign_by_pub_key(pk1.clone(), data.clone())
+            .await?;
+        assert_eq!(sig1, sig2);
+        assert!(pk1.verify(data.clone(), sig1).await?);
+
+        let sig1 = api2.sign_ed25519_sign_by_index(idx2, data.clone()).await?;
+        let sig2 = api2
+            .sign_ed25519_sign_by_pub_key(pk2.clone(), data.clone())
+            .await?;
+        assert_eq!(sig1, sig2);
+        assert!(pk2.verify(data.clone(), sig1).await?);
+
+        let sig1 = api.sign_ed25519_sign_by_index(idx3, data.clone()).await?;
+        let sig2 = api
+            .sign_ed25519_sign_by_pub_key(pk3.clone(), data.clone())
+            .await?;
+        assert_eq!(sig1, sig2);
+        assert!(pk3.verify(data.clone(), sig1).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_receipt() -> LairResult<()> {
+        let api = setup().await?;
+        let data = std::sync::Arc::new(b"test-data".to_vec());
+
+        let (idx1, pk1) = api.sign_ed25519_new_from_entropy().await?;
+        let sig = api.sign_ed25519_sign_by_index(idx1, data.clone()).await?;
+        assert_eq!(
+            (pk1.clone(), sig.clone()),
+            api.sign_ed25519_sign_by_index_v2(idx1, data.clone())
+                .await?,
+        );
+        assert_eq!(
+            (pk1.clone(), sig.clone()),
+            api.sign_ed25519_sign_by_pub_key_v2(pk1.clone(), data.clone())
+                .await?,
+        );
+
+        let receipt = SigningKeyHandle::new(api.clone(), idx1)
+            .sign_receipt(data.clone())
+            .await?;
+        assert_eq!(pk1, receipt.pub_key);
+        assert_eq!(sig, receipt.signature);
+        assert!(receipt.signed_at > 0);
+        assert!(receipt.verify(&data));
+        assert!(!receipt.verify(b"other-data"));
+        let mut forged = receipt.clone();
+        forged.message_digest = SignedReceipt::digest(b"other-data");
+        assert!(!forged.verify(b"other-data"));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_timestamped() -> LairResult<()> {
+        let api = setup().await?;
+        let data = std::sync::Arc::new(b"test-data".to_vec());
+        let (idx, pk) = api.sign_ed25519_new_from_entropy().await?;
+
+        // the default options sign as sign_ed25519_sign_by_index does
+        let sig = api.sign_ed25519_sign_by_index(idx, data.clone()).await?;
+        assert_eq!(
+            (sig, None),
+            api.sign_ed25519_sign_by_index_with_options(
+                idx,
+                data.clone(),
+                SignOptions::default(),
+            )
+            .await?,
+        );
+
+        let before = internal::util::unix_now_millis();
+        let (sig, timestamp) = api
+            .sign_ed25519_sign_by_index_with_options(
+                idx,
+                data.clone(),
+                SignOptions {
+                    include_timestamp: true,
+                },
+            )
+            .await?;
+        let timestamp = timestamp.unwrap();
+        assert!(timestamp >= before);
+        assert!(timestamp <= internal::util::unix_now_millis());
+        assert!(pk.verify_timestamped(&data, timestamp, &sig));
+        assert!(!pk.verify_pure(&data, &sig));
+
+        Ok(())
+    }
+
+    /// Yields `remaining` bytes, at most `chunk` at a time,
+    /// and only every other time it is polled.
+    struct SlowReader {
+        remaining: usize,
+        chunk: usize,
+        ready: bool,
+        largest_read: usize,
+    }
+
+    impl tokio::io::AsyncRead for SlowReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return std::task::Poll::Pending;
+            }
+            self.ready = false;
+            self.largest_read = self.largest_read.max(buf.len());
+            let len = self.remaining.min(self.chunk).min(buf.len());
+            for (i, b) in buf[..len].iter_mut().enumerate() {
+                *b = ((self.remaining - i) % 251) as u8;
+            }
+            self.remaining -= len;
+            std::task::Poll::Ready(Ok(len))
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_stream() -> LairResult<()> {
+        use tokio::io::AsyncReadExt;
+
+        let api = setup().await?;
+        let (idx1, pk1) = api.sign_ed25519_new_from_entropy().await?;
+        let key = SigningKeyHandle::new(api.clone(), idx1);
+
+        let slow = |remaining| SlowReader {
+            remaining,
+            chunk: 1000,
+            ready: false,
+            largest_read: 0,
+        };
+        let len = 3 * ed25519ph::READ_CHUNK_LEN + 17;
+        let mut message = Vec::new();
+        slow(len).read_to_end(&mut message).await.unwrap();
+        assert_eq!(len, message.len());
+
+        // the same signature as signing the buffer through the prehash api
+        let mut reader = slow(len);
+        let sig = key.sign_stream(&mut reader).await?;
+        assert_eq!(
+            sig,
+            api.sign_ed25519_ph_sign_by_index(
+                idx1,
+                ed25519ph::prehash(&message),
+                Arc::new(Vec::new()),
+            )
+            .await?,
+        );
+        // read a chunk at a time, never holding more
+        assert_eq!(ed25519ph::READ_CHUNK_LEN, reader.largest_read);
+        assert_eq!(sig, key.sign_large(slow(len)).await?);
+
+        assert!(pk1.verify_stream(slow(len), &sig).await?);
+        assert!(pk1.verify_stream(&message[..], &sig).await?);
+        assert!(!pk1.verify_stream(&message[1..], &sig).await?);
+        assert!(!pk1.verify_stream(slow(len + 1), &sig).await?);
+        // never a pure ed25519 signature
+        assert!(!pk1.verify_pure(&message, &sig));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_tls() -> LairResult<()> {
+        let api = setup().await?;
+
+        let (idx1, sni1, digest1) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        assert_eq!(CERT_SNI, sni1.as_str());
+        assert_eq!(CERT_DIGEST, digest1.as_slice());
+
+        let (sni1, digest1) = api.tls_cert_get(idx1).await?;
+        assert_eq!(CERT_SNI, sni1.as_str());
+        assert_eq!(CERT_DIGEST, digest1.as_slice());
+
+        let cert1 = api.tls_cert_get_cert_by_index(idx1).await?;
+        let cert2 = api.tls_cert_get_cert_by_sni(sni1.clone()).await?;
+        let cert3 = api.tls_cert_get_cert_by_digest(digest1.clone()).await?;
+        assert_eq!(cert1, cert2);
+        assert_eq!(cert2, cert3);
+
+        let priv1 = api.tls_cert_get_priv_key_by_index(idx1).await?;
+        let priv2 = api.tls_cert_get_priv_key_by_sni(sni1).await?;
+        let priv3 = api.tls_cert_get_priv_key_by_digest(digest1).await?;
+        assert_eq!(priv1, priv2);
+        assert_eq!(priv2, priv3);
+
+        let (_idx2, sni2, digest2) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        assert_ne!(CERT_SNI, sni2.as_str());
+        assert_ne!(CERT_DIGEST, digest2.as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_tls_cert_alg() -> LairResult<()> {
+        let api = setup().await?;
+
+        // the fixture cert is imported, its alg is read from the cert
+        let (idx, sni, digest) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        assert_eq!(
+            (sni, digest, TlsCertAlg::PkcsEd25519),
+            api.tls_cert_get_info(idx).await?,
+        );
+
+        #[allow(unused_mut)]
+        let mut algs = vec![
+            TlsCertAlg::PkcsEd25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256,
+            TlsCertAlg::PkcsEcdsaP384Sha384,
+        ];
+        #[cfg(feature = "ed448")]
+        algs.push(TlsCertAlg::PkcsEd448);
+        for alg in &algs {
+            let options = TlsCertOptions {
+                alg: *alg,
+                ..Default::default()
+            };
+            let (idx, _, _) =
+                api.tls_cert_new_self_signed_from_entropy(options).await?;
+            assert_eq!(*alg, api.tls_cert_get_info(idx).await?.2);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_key_options() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let options = SignKeyOptions {
+            tag: Some("options".to_string()),
+            exportable: false,
+            expires_at: Some(u64::MAX),
+            requires_approval: false,
+        };
+        let (idx, pk) = api
+            .sign_ed25519_new_from_entropy_with_options(options.clone())
+            .await?;
+        assert_eq!(options, api.sign_ed25519_get_options(idx).await?);
+        assert_eq!(
+            (idx, LairEntryType::SignEd25519),
+            api.lair_get_entry_by_tag("options".into()).await?,
+        );
+        let (used, _) = api
+            .sign_ed25519_sign_by_tag("options".into(), data.clone())
+            .await?;
+        assert_eq!(pk, used);
+        let (_, recipient) = api.x25519_new_from_entropy().await?;
+        assert!(matches!(
+            api.lair_wrap_entry(idx, recipient).await,
+            Err(LairError::ExportForbidden(_)),
+        ));
+
+        let (idx, _) = api
+            .sign_ed25519_new_from_entropy_with_options(SignKeyOptions {
+                expires_at: Some(1),
+                ..Default::default()
+            })
+            .await?;
+        assert!(matches!(
+            api.sign_ed25519_sign_by_index(idx, data.clone()).await,
+            Err(LairError::KeyExpired(_)),
+        ));
+
+        // nobody is listening for approval requests
+        let approval = SignKeyOptions {
+            requires_approval: true,
+            ..Default::default()
+        };
+        let (idx, pk, already_existed) = api
+            .sign_ed25519_new_from_seed_with_options([1; 32], approval.clone())
+            .await?;
+        assert!(!already_existed);
+        assert_eq!(
+            (idx, pk.clone(), true),
+            api.sign_ed25519_new_from_seed_with_options(
+                [1; 32],
+                SignKeyOptions::default()
+            )
+            .await?,
+        );
+        assert_eq!(approval, api.sign_ed25519_get_options(idx).await?);
+        assert!(matches!(
+            api.sign_ed25519_sign_by_pub_key(pk, data).await,
+            Err(LairError::Forbidden(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_tags() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        match api
+            .sign_ed25519_sign_by_tag("agent".into(), data.clone())
+            .await
+        {
+            Err(LairError::TagNotFound(tag)) => assert_eq!("agent", &tag),
+            res => panic!("expected tag not found, got: {:?}", res),
+        }
+
+        let (idx1, pk1) = api.sign_ed25519_new_from_entropy().await?;
+        api.lair_set_entry_tag(idx1, Some("agent".into())).await?;
+        assert_eq!(
+            (idx1, LairEntryType::SignEd25519),
+            api.lair_get_entry_by_tag("agent".into()).await?,
+        );
+        let (used, sig) = api
+            .sign_ed25519_sign_by_tag("agent".into(), data.clone())
+            .await?;
+        assert_eq!(pk1, used);
+        assert!(pk1.verify(data.clone(), sig).await?);
+
+        // rotate the tag onto a new key under the caller's feet
+        let (idx2, pk2) = api.sign_ed25519_new_from_entropy().await?;
+        api.lair_set_entry_tag(idx2, Some("agent".into())).await?;
+        let (used, sig) = api
+            .sign_ed25519_sign_by_tag("agent".into(), data.clone())
+            .await?;
+        assert_eq!(pk2, used);
+        assert!(pk2.verify(data.clone(), sig).await?);
+
+        let (cert_idx, sni, digest) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        api.lair_set_entry_tag(cert_idx, Some("tls".into())).await?;
+        let (b_sni, b_digest, b_cert, b_priv) =
+            api.tls_cert_get_bundle_by_tag("tls".into()).await?;
+        assert_eq!(sni, b_sni);
+        assert_eq!(digest, b_digest);
+        assert_eq!(api.tls_cert_get_cert_by_index(cert_idx).await?, b_cert);
+        assert_eq!(api.tls_cert_get_priv_key_by_index(cert_idx).await?, b_priv);
+
+        // tags resolve to the wrong entry type
+        assert!(api
+            .tls_cert_get_bundle_by_tag("agent".into())
+            .await
+            .is_err());
+        assert!(api
+            .sign_ed25519_sign_by_tag("tls".into(), data.clone())
+            .await
+            .is_err());
+
+        api.lair_set_entry_tag(cert_idx, None).await?;
+        assert!(matches!(
+            api.tls_cert_get_bundle_by_tag("tls".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_aliases() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (idx, pk) = api.sign_ed25519_new_from_entropy().await?;
+        api.lair_set_entry_tag(idx, Some("agent-v1".into())).await?;
+        api.lair_add_entry_alias(idx, "agent".into()).await?;
+        assert_eq!(
+            (idx, LairEntryType::SignEd25519),
+            api.lair_get_entry_by_tag("agent".into()).await?,
+        );
+        let (used, _) = api
+            .sign_ed25519_sign_by_tag("agent".into(), data.clone())
+            .await?;
+        assert_eq!(pk, used);
+
+        // aliases keep resolving after the tag changes
+        api.lair_set_entry_tag(idx, Some("agent-v2".into())).await?;
+        assert_eq!(idx, api.lair_get_entry_by_tag("agent".into()).await?.0);
+
+        let (idx2, _) = api.x25519_new_from_entropy().await?;
+        assert!(matches!(
+            api.lair_add_entry_alias(idx2, "agent-v2".into()).await,
+            Err(LairError::TagInUse(_)),
+        ));
+        assert!(matches!(
+            api.lair_set_entry_tag(idx2, Some("agent".into())).await,
+            Err(LairError::TagInUse(_)),
+        ));
+        // indices are shared by every test keystore in the process
+        assert!(api
+            .lair_add_entry_alias(u32::MAX.into(), "x".into())
+            .await
+            .is_err());
+
+        let (page, _) = api.lair_list_entries_page(idx, 1).await?;
+        assert_eq!(vec!["agent".to_string()], page[0].aliases);
+
+        api.lair_remove_entry_alias("agent".into()).await?;
+        assert!(matches!(
+            api.lair_get_entry_by_tag("agent".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+        assert!(matches!(
+            api.lair_remove_entry_alias("agent".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_trash() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (idx, pk) = api.sign_ed25519_new_from_entropy().await?;
+        api.lair_set_entry_tag(idx, Some("agent".into())).await?;
+
+        // trash -> restore
+        api.lair_trash_entry(idx).await?;
+        assert!(api
+            .sign_ed25519_sign_by_pub_key(pk.clone(), data.clone())
+            .await
+            .is_err());
+        let (page, _) = api.lair_list_entries_page(idx, 1).await?;
+        assert_eq!(idx, page[0].keystore_index);
+        assert!(page[0].trashed);
+        // nothing is purged before it is old enough
+        assert_eq!(
+            0,
+            api.lair_purge_trashed(std::time::Duration::from_secs(3600))
+                .await?
+        );
+        api.lair_restore_entry(idx).await?;
+        let (used, _) = api
+            .sign_ed25519_sign_by_tag("agent".into(), data.clone())
+            .await?;
+        assert_eq!(pk, used);
+        assert!(!api.lair_list_entries_page(idx, 1).await?.0[0].trashed);
+
+        // trash -> purge -> not restorable
+        api.lair_trash_entry(idx).await?;
+        assert_eq!(
+            1,
+            api.lair_purge_trashed(std::time::Duration::from_secs(0))
+                .await?
+        );
+        assert!(api.lair_restore_entry(idx).await.is_err());
+        assert!(api.lair_list_entries_page(idx, 1).await?.0.is_empty());
+        assert!(matches!(
+            api.lair_get_entry_by_tag("agent".into()).await,
+            Err(LairError::TagNotFound(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_crypto_box_to_sign_pub_key() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (x_idx, x_pk) = api.x25519_new_from_entropy().await?;
+        assert_eq!(x_pk, api.x25519_get(x_idx).await?);
+
+        let (_, sign_pk) = api.sign_ed25519_new_from_entropy().await?;
+        let boxed = api
+            .crypto_box_to_sign_pub_key(x_idx, sign_pk.clone(), data.clone())
+            .await?;
+        assert_ne!(data, boxed);
+        assert_eq!(
+            data,
+            api.crypto_box_open_by_sign_pub_key(sign_pk, x_pk, boxed)
+                .await?,
+        );
+
+        let low_order: SignEd25519PubKey = {
+            let mut k = vec![0; 32];
+            k[0] = 1;
+            k.into()
+        };
+        assert!(matches!(
+            api.crypto_box_to_sign_pub_key(x_idx, low_order, data).await,
+            Err(LairError::InvalidKeyConversion(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_crypto_box_seal() -> LairResult<()> {
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (idx1, pk1) = api.x25519_new_from_entropy().await?;
+        let (idx2, _) = api.x25519_new_from_entropy().await?;
+
+        let sealed = crypto_box_seal(pk1, data.clone()).await?;
+        assert_eq!(
+            data,
+            api.crypto_box_seal_open_by_index(idx1, sealed.clone())
+                .await?,
+        );
+        assert!(matches!(
+            api.crypto_box_seal_open_by_index(idx2, sealed).await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_pw_hash() -> LairResult<()> {
+        let api = setup().await?;
+        let password = Arc::new(b"test-password".to_vec());
+        let options = PwHashOptions {
+            mem_cost_kib: 64,
+            ..Default::default()
+        };
+
+        let idx1 = api.pw_pepper_new_from_entropy().await?;
+        let idx2 = api.pw_pepper_new_from_entropy().await?;
+        assert_eq!(
+            LairEntryType::PwPepper,
+            api.lair_get_entry_type(idx1).await?
+        );
+
+        let hash = api.pw_hash(idx1, password.clone(), options).await?;
+        assert!(api.pw_verify(idx1, password.clone(), hash.clone()).await?);
+        assert!(
+            !api.pw_verify(idx1, Arc::new(b"wrong".to_vec()), hash.clone())
+                .await?
+        );
+        assert!(!api.pw_verify(idx2, password.clone(), hash).await?);
+        assert!(api
+            .pw_verify(idx1, password, "$argon2id$bad".to_string())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_totp() -> LairResult<()> {
+        let api = setup().await?;
+        let options = TotpOptions {
+            digits: 8,
+            ..Default::default()
+        };
+
+        // the RFC 6238 sha-1 secret, spaced and lower case
+        let idx1 = api
+            .totp_import(
+                "gezd gnbv gy3t qojq gezd gnbv gy3t qojq".to_string(),
+                options,
+            )
+            .await?;
+        assert_eq!(
+            LairEntryType::TotpSecret,
+            api.lair_get_entry_type(idx1).await?
+        );
+        assert_eq!("94287082", api.totp_generate(idx1, Some(59)).await?);
+        assert_eq!(8, api.totp_generate(idx1, None).await?.len());
+        assert_eq!(
+            "otpauth://totp/svc?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &algorithm=SHA1&digits=8&period=30",
+            api.totp_export_provisioning_uri(
+                idx1,
+                "svc".to_string(),
+                String::new()
+            )
+            .await?,
+        );
+
+        let idx2 = api.totp_new_from_entropy(TotpOptions::default()).await?;
+        assert_ne!(
+            api.totp_generate(idx1, Some(59)).await?,
+            api.totp_generate(idx2, Some(59)).await?,
+        );
+
+        assert!(api
+            .totp_import("GEZDGNBV".to_string(), options)
+            .await
+            .is_err());
+        let idx3 = api.pw_pepper_new_from_entropy().await?;
+        assert!(api.totp_generate(idx3, None).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_secp256k1() -> LairResult<()> {
+        let api = setup().await?;
+        let hash = [0x42; 32];
+
+        let (idx1, pk1) = api.sign_secp256k1_new_from_entropy().await?;
+        assert_eq!(pk1, api.sign_secp256k1_get(idx1).await?);
+        assert_eq!(
+            LairEntryType::SignSecp256k1,
+            api.lair_get_entry_type(idx1).await?
+        );
+
+        let (sig, recovery_id) =
+            api.sign_secp256k1_sign_by_index(idx1, hash).await?;
+        assert!(pk1.verify(&hash, &sig));
+        assert_eq!(
+            pk1,
+            SignSecp256k1PubKey::recover(&hash, &sig, recovery_id)?
+        );
+        assert!(!pk1.verify(&[0x43; 32], &sig));
+
+        // private key 1 has the generator as its public key
+        let mut seed = [0; 32];
+        seed[31] = 1;
+        let (idx2, pk2, existed) =
+            api.sign_secp256k1_new_from_seed(seed).await?;
+        assert!(!existed);
+        assert_eq!(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            pk2.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        );
+        // importing the same seed again finds the existing entry
+        assert_eq!(
+            (idx2, pk2.clone(), true),
+            api.sign_secp256k1_new_from_seed(seed).await?,
+        );
+        let (sig, _) = api.sign_secp256k1_sign_by_index(idx2, hash).await?;
+        assert!(pk2.verify(&hash, &sig));
+
+        assert!(api.sign_secp256k1_new_from_seed([0; 32]).await.is_err());
+        let (idx3, _) = api.x25519_new_from_entropy().await?;
+        assert!(api.sign_secp256k1_get(idx3).await.is_err());
+        assert!(api.sign_secp256k1_sign_by_index(idx3, hash).await.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "ed448")]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_ed448() -> LairResult<()> {
+        let api = setup().await?;
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+
+        let (idx1, pk1) = api.sign_ed448_new_from_entropy().await?;
+        assert_eq!(57, pk1.len());
+        assert_eq!(pk1, api.sign_ed448_get(idx1).await?);
+        assert_eq!(
+            LairEntryType::SignEd448,
+            api.lair_get_entry_type(idx1).await?
+        );
+
+        let sig = api.sign_ed448_sign_by_index(idx1, msg.clone()).await?;
+        assert_eq!(114, sig.len());
+        assert!(pk1.verify(&msg, &sig));
+        assert!(!pk1.verify(&[0, 1, 2], &sig));
+        // signatures are deterministic
+        assert_eq!(
+            sig,
+            api.sign_ed448_sign_by_pub_key(pk1.clone(), msg.clone())
+                .await?
+        );
+
+        let (idx2, _) = api.x25519_new_from_entropy().await?;
+        assert!(api.sign_ed448_get(idx2).await.is_err());
+        assert!(api
+            .sign_ed448_sign_by_index(idx2, msg.clone())
+            .await
+            .is_err());
+        assert!(api
+            .sign_ed448_sign_by_pub_key([0x42; 57].into(), msg)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_bls() -> LairResult<()> {
+        let api = setup().await?;
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+        let dst = bls::DST_POP.to_vec();
+
+        let (idx1, pk1) = api.sign_bls_new_from_entropy().await?;
+        assert_eq!(48, pk1.len());
+        assert_eq!(pk1, api.sign_bls_get(idx1).await?);
+        assert_eq!(
+            LairEntryType::SignBls,
+            api.lair_get_entry_type(idx1).await?
+        );
+
+        let sig1 = api
+            .sign_bls_sign_by_index(idx1, msg.clone(), dst.clone())
+            .await?;
+        assert_eq!(96, sig1.len());
+        assert!(pk1.verify(&msg, &sig1, &dst));
+        assert!(!pk1.verify(&msg, &sig1, bls::DST_BASIC));
+        assert!(!pk1.verify(&[0, 1, 2], &sig1, &dst));
+        assert!(api
+            .sign_bls_sign_by_index(idx1, msg.clone(), Vec::new())
+            .await
+            .is_err());
+
+        // signatures of two keys aggregate into one
+        let (idx2, pk2) = api.sign_bls_new_from_entropy().await?;
+        let sig2 = api
+            .sign_bls_sign_by_index(idx2, msg.clone(), dst.clone())
+            .await?;
+        let agg = SignBlsSignature::aggregate_signatures(vec![sig1, sig2])?;
+        assert!(agg.aggregate_verify(
+            vec![(pk1.clone(), msg.to_vec()), (pk2, msg.to_vec())],
+            &dst,
+        ));
+        assert!(!agg.aggregate_verify(vec![(pk1, msg.to_vec())], &dst));
+
+        let (idx3, _) = api.x25519_new_from_entropy().await?;
+        assert!(api.sign_bls_get(idx3).await.is_err());
+        assert!(api.sign_bls_sign_by_index(idx3, msg, dst).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_sign_sr25519() -> LairResult<()> {
+        let api = setup().await?;
+        let msg = Arc::new(vec![0, 1, 2, 3]);
+
+        let (idx1, pk1) = api.sign_sr25519_new_from_entropy().await?;
+        assert_eq!(32, pk1.len());
+        assert_eq!(pk1, api.sign_sr25519_get(idx1).await?);
+        assert_eq!(
+            LairEntryType::SignSr25519,
+            api.lair_get_entry_type(idx1).await?
+        );
+
+        let sig1 = api
+            .sign_sr25519_sign_by_index(idx1, msg.clone(), b"ctx".to_vec())
+            .await?;
+        assert_eq!(64, sig1.len());
+        assert!(pk1.verify(&msg, &sig1, b"ctx"));
+        assert!(!pk1.verify(&msg, &sig1, b"other"));
+        assert!(!pk1.verify(&[0, 1, 2], &sig1, b"ctx"));
+
+        let seed = [0x42; 32];
+        let (idx2, pk2, existed) = api.sign_sr25519_new_from_seed(seed).await?;
+        assert!(!existed);
+        assert_eq!(&sr25519::pub_key_from_seed(&seed)?[..], &pk2[..]);
+        // importing the same seed again finds the existing entry
+        assert_eq!(
+            (idx2, pk2.clone(), true),
+            api.sign_sr25519_new_from_seed(seed).await?,
+        );
+        let sig2 = api
+            .sign_sr25519_sign_by_index(idx2, msg.clone(), b"ctx".to_vec())
+            .await?;
+        assert!(pk2.verify(&msg, &sig2, b"ctx"));
+
+        let (idx3, _) = api.x25519_new_from_entropy().await?;
+        assert!(api.sign_sr25519_get(idx3).await.is_err());
+        assert!(api
+            .sign_sr25519_sign_by_index(idx3, msg, b"ctx".to_vec())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_attest_entry() -> LairResult<()> {
+        let api = setup().await?;
+        let server_id = api.lair_get_server_info().await?.id_pub_key;
+        assert_eq!(32, server_id.len());
+        let challenge = Arc::new(b"test-challenge".to_vec());
+
+        let (idx, pk) = api.sign_ed25519_new_from_entropy().await?;
+        let attestation = api.lair_attest_entry(idx, challenge.clone()).await?;
+        assert_eq!(pk.0, attestation.entry_pub_id);
+        assert!(attestation.verify(&server_id, &challenge));
+        assert!(!attestation.verify(&server_id, b"other-challenge"));
+
+        let mut bad = attestation;
+        bad.entry_pub_id = Arc::new(vec![0x42; 32]);
+        assert!(!bad.verify(&server_id, &challenge));
+
+        // each test keystore has its own identity
+        let (api2, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+        assert_ne!(server_id, api2.lair_get_server_info().await?.id_pub_key);
+
+        let pepper_idx = api.pw_pepper_new_from_entropy().await?;
+        assert!(api.lair_attest_entry(pepper_idx, challenge).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_wrap_entry() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+        let (recovery, _evt2) = spawn_test_keystore(vec![], vec![]).await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (r_idx, r_pk) = recovery.x25519_new_from_entropy().await?;
+        let (idx, pk) = api.sign_ed25519_new_from_entropy().await?;
+        let wrapped = api.lair_wrap_entry(idx, r_pk).await?;
+
+        let (u_idx, u_type, existed) =
+            recovery.lair_unwrap_entry(r_idx, wrapped.clone()).await?;
+        assert!(!existed);
+        assert_eq!(LairEntryType::SignEd25519, u_type);
+        assert_eq!(
+            (u_idx, u_type, true),
+            recovery.lair_unwrap_entry(r_idx, wrapped.clone()).await?,
+        );
+        assert_eq!(pk, recovery.sign_ed25519_get(u_idx).await?);
+        assert_eq!(
+            api.sign_ed25519_sign_by_index(idx, data.clone()).await?,
+            recovery.sign_ed25519_sign_by_index(u_idx, data).await?,
+        );
+
+        // the wrapping keystore cannot open it with its own x25519 key
+        let (x_idx, _) = api.x25519_new_from_entropy().await?;
+        assert!(matches!(
+            api.lair_unwrap_entry(x_idx, wrapped).await,
+            Err(LairError::AuthenticationFailed),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_select_store() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+        api.lair_select_store(DEFAULT_STORE_NAME.to_string())
+            .await?;
+        assert!(api.lair_select_store("other".to_string()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_entry_count_and_indices() -> LairResult<()> {
+        use futures::stream::StreamExt;
+
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+
+        // empty is None / 0, never confused with an entry at index 0
+        assert_eq!(None, api.lair_get_last_entry_index().await?);
+        assert_eq!(0, api.lair_get_entry_count().await?);
+        assert!(lair_entry_indices_stream(api.clone())
+            .collect::<Vec<_>>()
+            .await
+            .is_empty());
+
+        let (sign_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (x_idx, _) = api.x25519_new_from_entropy().await?;
+        assert_eq!(Some(x_idx), api.lair_get_last_entry_index().await?);
+        assert_eq!(2, api.lair_get_entry_count().await?);
+        assert_eq!(
+            vec![
+                (sign_idx, LairEntryType::SignEd25519),
+                (x_idx, LairEntryType::X25519),
+            ],
+            lair_entry_indices_stream(api.clone())
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<LairResult<Vec<_>>>()?,
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_store_stats() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+        assert_eq!(StoreStats::default(), api.lair_get_store_stats().await?);
+
+        let (sign_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        api.sign_ed25519_new_from_entropy().await?;
+        api.x25519_new_from_entropy().await?;
+        api.lair_trash_entry(sign_idx).await?;
+        let stats = api.lair_get_store_stats().await?;
+        assert_eq!(
+            vec![(LairEntryType::SignEd25519, 1), (LairEntryType::X25519, 1)],
+            stats.entry_counts,
+        );
+        assert_eq!(1, stats.trashed_count);
+
+        api.lair_restore_entry(sign_idx).await?;
+        let stats = api.lair_get_store_stats().await?;
+        assert_eq!(2, stats.entry_count(LairEntryType::SignEd25519));
+        assert_eq!(0, stats.entry_count(LairEntryType::TlsCert));
+        assert_eq!(0, stats.trashed_count);
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_wide_indexes() -> LairResult<()> {
+        use std::convert::TryFrom;
+
+        let api = setup().await?;
+        let data = Arc::new(b"test-data".to_vec());
+
+        let (idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        let wide = KeystoreIndex64::from(idx);
+        assert_eq!(*idx as u64, *wide);
+        assert_eq!(Some(wide), api.lair_get_last_entry_index_64().await?);
+        assert_eq!(
+            LairEntryType::SignEd25519,
+            api.lair_get_entry_type_64(wide).await?
+        );
+        assert_eq!(
+            api.sign_ed25519_sign_by_index(idx, data.clone()).await?,
+            api.sign_ed25519_sign_by_index_64(wide, data.clone())
+                .await?,
+        );
+
+        // the widest narrow index converts back, wider ones fail typed
+        let max = KeystoreIndex64::from(KeystoreIndex(u32::MAX));
+        assert_eq!(KeystoreIndex(u32::MAX), max.try_into()?);
+        let too_wide = KeystoreIndex64(u32::MAX as u64 + 1);
+        assert!(matches!(
+            KeystoreIndex::try_from(too_wide),
+            Err(LairError::IndexOutOfRange(_)),
+        ));
+        assert!(matches!(
+            api.lair_get_entry_type_64(too_wide).await,
+            Err(LairError::IndexOutOfRange(_)),
+        ));
+        assert!(matches!(
+            api.sign_ed25519_sign_by_index_64(too_wide, data).await,
+            Err(LairError::IndexOutOfRange(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_typed_cert_digests() -> LairResult<()> {
+        use test_vectors::from_hex as hex;
+
+        let cert_der = hex(test_vectors::FIXTURE_CERT_DER);
+        let cert_digest = CertDigest::compute(&cert_der.clone().into());
+        let (api, _evt) = spawn_test_keystore(
+            vec![],
+            vec![FixtureTlsCert {
+                priv_key_der: vec![],
+                sni: "lair-fixture.example".to_string(),
+                cert_der: cert_der.clone(),
+                cert_digest: cert_digest.to_vec(),
+            }],
+        )
+        .await?;
+        let (idx, _, digest) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        assert_eq!(cert_digest, digest);
+
+        let sha256 = Arc::new(hex(test_vectors::FIXTURE_CERT_SHA256));
+        let sha1 = Arc::new(hex(test_vectors::FIXTURE_CERT_SHA1));
+        assert_eq!(
+            digest.0,
+            api.tls_cert_get_digest(idx, DigestAlg::Blake2b).await?,
+        );
+        assert_eq!(
+            sha256,
+            api.tls_cert_get_digest(idx, DigestAlg::Sha256).await?,
+        );
+        assert_eq!(
+            sha1,
+            api.tls_cert_get_digest(idx, DigestAlg::Sha1Legacy).await?,
+        );
+
+        for (alg, digest) in [
+            (DigestAlg::Blake2b, digest.0.clone()),
+            (DigestAlg::Sha256, sha256.clone()),
+            (DigestAlg::Sha1Legacy, sha1),
+        ] {
+            assert_eq!(
+                cert_der,
+                **api.tls_cert_get_cert_by_typed_digest(alg, digest).await?,
+            );
+        }
+
+        // the digest of one kind is no digest of another
+        assert!(matches!(
+            api.tls_cert_get_cert_by_typed_digest(DigestAlg::Blake2b, sha256)
+                .await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_find_entries_by_prefix() -> LairResult<()> {
+        // pub ids colliding on their first 5 bytes
+        let id = |last: u8| {
+            let mut id = vec![0xab, 0xcd, 0xef, 0x01, 0x23];
+            id.resize(32, last);
+            id
+        };
         let (api, _evt) = spawn_test_keystore(
             vec![
                 FixtureSignEd25519Keypair {
-                    pub_key: PUB1.to_vec(),
-                    priv_key: SEC1.to_vec(),
+                    pub_key: id(1),
+                    priv_key: vec![0x11; 32],
                 },
                 FixtureSignEd25519Keypair {
-                    pub_key: PUB2.to_vec(),
-                    priv_key: SEC2.to_vec(),
+                    pub_key: id(2),
+                    priv_key: vec![0x12; 32],
                 },
             ],
             vec![FixtureTlsCert {
-                priv_key_der: CERT_SEC.to_vec(),
-                sni: CERT_SNI.to_string(),
-                cert_der: CERT.to_vec(),
-                cert_digest: CERT_DIGEST.to_vec(),
+                priv_key_der: vec![],
+                sni: "prefix.example".to_string(),
+                cert_der: vec![],
+                cert_digest: id(3),
             }],
         )
         .await?;
-        Ok(api)
+        let (sign1, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (sign2, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (cert, _, _) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        let (_, x_pub_key) = api.x25519_new_from_entropy().await?;
+
+        let find = |prefix: Vec<u8>, kind| {
+            api.lair_find_entries_by_prefix(Arc::new(prefix), kind)
+        };
+
+        // ambiguous prefixes return every match
+        assert_eq!(
+            vec![
+                (sign1, LairEntryType::SignEd25519),
+                (sign2, LairEntryType::SignEd25519),
+                (cert, LairEntryType::TlsCert),
+            ],
+            find(id(0)[..5].to_vec(), PrefixKind::Any).await?,
+        );
+        assert_eq!(
+            vec![
+                (sign1, LairEntryType::SignEd25519),
+                (sign2, LairEntryType::SignEd25519),
+            ],
+            find(id(0)[..4].to_vec(), PrefixKind::SignPubKey).await?,
+        );
+        assert_eq!(
+            vec![(cert, LairEntryType::TlsCert)],
+            find(id(0)[..4].to_vec(), PrefixKind::CertDigest).await?,
+        );
+        assert_eq!(
+            vec![(sign2, LairEntryType::SignEd25519)],
+            find(id(2)[..6].to_vec(), PrefixKind::Any).await?,
+        );
+
+        // x25519 pub keys are not searched
+        assert!(find(x_pub_key.0[..8].to_vec(), PrefixKind::Any)
+            .await?
+            .is_empty());
+
+        // trashed entries are not found
+        api.lair_trash_entry(sign1).await?;
+        assert_eq!(
+            vec![(sign2, LairEntryType::SignEd25519)],
+            find(id(0)[..5].to_vec(), PrefixKind::SignPubKey).await?,
+        );
+
+        assert!(find(id(0)[..3].to_vec(), PrefixKind::Any).await.is_err());
+        assert!(find(vec![0; 65], PrefixKind::Any).await.is_err());
+
+        Ok(())
     }
 
     #[tokio::test(threaded_scheduler)]
-    async fn test_test_keystore_signing() -> LairResult<()> {
-        let api = setup().await?;
-        let api2 = api.clone();
+    async fn test_test_keystore_resolve_indices() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
 
-        let sys_info = api.lair_get_server_info().await?;
-        assert_eq!(crate::LAIR_VER, sys_info.version);
-        let sys_info = api2.lair_get_server_info().await?;
-        assert_eq!(crate::LAIR_VER, sys_info.version);
+        let (cert_idx, sni, digest) = api
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await?;
+        let (sign_idx, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+        let (_, x_pub_key) = api.x25519_new_from_entropy().await?;
 
-        let (idx1, pk1) = api.sign_ed25519_new_from_entropy().await?;
-        assert_eq!(pk1.0.as_slice(), PUB1);
-        assert_eq!(pk1, api.sign_ed25519_get(idx1).await?);
-        let (idx2, pk2) = api2.sign_ed25519_new_from_entropy().await?;
-        assert_eq!(pk2.0.as_slice(), PUB2);
-        assert_eq!(pk2, api2.sign_ed25519_get(idx2).await?);
-        let (idx3, pk3) = api.sign_ed25519_new_from_entropy().await?;
-        assert_eq!(pk3, api.sign_ed25519_get(idx3).await?);
-        assert_ne!(pk3.0.as_slice(), PUB1);
-        assert_ne!(pk3.0.as_slice(), PUB2);
+        assert_eq!(cert_idx, api.tls_cert_resolve_by_digest(digest).await?);
+        assert_eq!(cert_idx, api.tls_cert_resolve_by_sni(sni).await?);
+        assert_eq!(
+            sign_idx,
+            api.sign_ed25519_resolve_by_pub_key(pub_key).await?
+        );
 
-        assert_eq!(idx3, api.lair_get_last_entry_index().await?);
+        // unknown, or held by an entry of another type
+        assert!(matches!(
+            api.tls_cert_resolve_by_digest(vec![0; 32].into()).await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        assert!(matches!(
+            api.tls_cert_resolve_by_sni("nope.test".to_string().into())
+                .await,
+            Err(LairError::EntryNotFound(_)),
+        ));
+        assert!(matches!(
+            api.sign_ed25519_resolve_by_pub_key(x_pub_key.0.to_vec().into())
+                .await,
+            Err(LairError::EntryNotFound(_)),
+        ));
 
-        let data = std::sync::Arc::new(b"test-data".to_vec());
+        Ok(())
+    }
 
-        let sig1 = api.sign_ed25519_sign_by_index(idx1, data.clone()).await?;
-        let sig2 = api
-            .sign_ed25519_sign_by_pub_key(pk1.clone(), data.clone())
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_import_ssh_key() -> LairResult<()> {
+        use internal::ssh_key::{ssh_ed25519_pub_key_line, tests::*};
+
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+
+        let (idx, pub_key, existed) = api
+            .sign_ed25519_import_ssh_key(PLAIN.as_bytes().to_vec().into(), None)
             .await?;
-        assert_eq!(sig1, sig2);
-        assert!(pk1.verify(data.clone(), sig1).await?);
+        assert!(!existed);
+        assert_eq!(
+            PLAIN_PUB,
+            ssh_ed25519_pub_key_line(&pub_key, "alice@example.com")
+        );
+        assert_eq!(
+            idx,
+            api.lair_get_entry_by_tag("alice@example.com".into())
+                .await?
+                .0
+        );
 
-        let sig1 = api2.sign_ed25519_sign_by_index(idx2, data.clone()).await?;
-        let sig2 = api2
-            .sign_ed25519_sign_by_pub_key(pk2.clone(), data.clone())
+        // a second import is no duplicate, and keeps the tags as they are
+        api.lair_set_entry_tag(idx, Some("alice".into())).await?;
+        assert_eq!(
+            (idx, pub_key.clone(), true),
+            api.sign_ed25519_import_ssh_key(
+                PLAIN.as_bytes().to_vec().into(),
+                None
+            )
+            .await?,
+        );
+        assert_eq!(1, api.lair_get_entry_count().await?);
+        assert!(api
+            .lair_get_entry_by_tag("alice@example.com".into())
+            .await
+            .is_err());
+
+        let key: Arc<Vec<u8>> = ENCRYPTED.as_bytes().to_vec().into();
+        assert!(matches!(
+            api.sign_ed25519_import_ssh_key(key.clone(), Some("wrong".into()))
+                .await,
+            Err(LairError::AuthenticationFailed),
+        ));
+        let (idx, pub_key, _) = api
+            .sign_ed25519_import_ssh_key(
+                key,
+                Some(ENCRYPTED_PASSPHRASE.to_string()),
+            )
             .await?;
-        assert_eq!(sig1, sig2);
-        assert!(pk2.verify(data.clone(), sig1).await?);
+        assert_eq!(
+            ENCRYPTED_PUB,
+            ssh_ed25519_pub_key_line(&pub_key, "bob@example.com")
+        );
 
-        let sig1 = api.sign_ed25519_sign_by_index(idx3, data.clone()).await?;
-        let sig2 = api
-            .sign_ed25519_sign_by_pub_key(pk3.clone(), data.clone())
+        // the imported keypair signs
+        let sig = api
+            .sign_ed25519_sign_by_index(idx, b"hello".to_vec().into())
             .await?;
-        assert_eq!(sig1, sig2);
-        assert!(pk3.verify(data.clone(), sig1).await?);
+        assert!(pub_key.verify(b"hello".to_vec().into(), sig).await?);
+
+        assert!(api
+            .sign_ed25519_import_ssh_key(
+                vec![0; MAX_SSH_KEY_LEN + 1].into(),
+                None
+            )
+            .await
+            .is_err());
 
         Ok(())
     }
 
     #[tokio::test(threaded_scheduler)]
-    async fn test_test_keystore_tls() -> LairResult<()> {
+    async fn test_test_keystore_tls_from_sign_key() -> LairResult<()> {
         let api = setup().await?;
 
-        let (idx1, sni1, digest1) = api
-            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+        let (sign_idx, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+        let (idx, sni, digest) = api
+            .tls_cert_new_self_signed_from_sign_key(
+                sign_idx,
+                TlsCertOptions::default(),
+            )
             .await?;
-        assert_eq!(CERT_SNI, sni1.as_str());
-        assert_eq!(CERT_DIGEST, digest1.as_slice());
+        assert_eq!((sni.clone(), digest), api.tls_cert_get(idx).await?);
 
-        let (sni1, digest1) = api.tls_cert_get(idx1).await?;
-        assert_eq!(CERT_SNI, sni1.as_str());
-        assert_eq!(CERT_DIGEST, digest1.as_slice());
+        let cert = api.tls_cert_get_cert_by_sni(sni).await?;
+        assert_eq!(pub_key, tls::tls_cert_self_signed_ed25519_pub_key(&cert)?);
 
-        let cert1 = api.tls_cert_get_cert_by_index(idx1).await?;
-        let cert2 = api.tls_cert_get_cert_by_sni(sni1.clone()).await?;
-        let cert3 = api.tls_cert_get_cert_by_digest(digest1.clone()).await?;
-        assert_eq!(cert1, cert2);
-        assert_eq!(cert2, cert3);
+        // only signing entries can back a cert
+        let (x_idx, _) = api.x25519_new_from_entropy().await?;
+        assert!(api
+            .tls_cert_new_self_signed_from_sign_key(
+                x_idx,
+                TlsCertOptions::default(),
+            )
+            .await
+            .is_err());
 
-        let priv1 = api.tls_cert_get_priv_key_by_index(idx1).await?;
-        let priv2 = api.tls_cert_get_priv_key_by_sni(sni1).await?;
-        let priv3 = api.tls_cert_get_priv_key_by_digest(digest1).await?;
-        assert_eq!(priv1, priv2);
-        assert_eq!(priv2, priv3);
+        Ok(())
+    }
 
-        let (_idx2, sni2, digest2) = api
-            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_get_entry_types() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+
+        let (sign_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (x_idx, _) = api.x25519_new_from_entropy().await?;
+        let missing: KeystoreIndex = (x_idx.0 + 1000).into();
+
+        // request order, duplicates included, missing indices invalid
+        assert_eq!(
+            vec![
+                (x_idx, LairEntryType::X25519),
+                (missing, LairEntryType::Invalid),
+                (sign_idx, LairEntryType::SignEd25519),
+                (x_idx, LairEntryType::X25519),
+            ],
+            api.lair_get_entry_types(vec![x_idx, missing, sign_idx, x_idx])
+                .await?,
+        );
+        assert!(api.lair_get_entry_types(vec![]).await?.is_empty());
+
+        let max = MAX_GET_ENTRY_TYPES_LEN as usize;
+        assert_eq!(
+            max,
+            api.lair_get_entry_types(vec![sign_idx; max]).await?.len()
+        );
+        assert!(api
+            .lair_get_entry_types(vec![sign_idx; max + 1])
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_bulk_entries() -> LairResult<()> {
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+
+        let (a_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        let (b_idx, _) = api.sign_ed25519_new_from_entropy().await?;
+        let missing: KeystoreIndex = (b_idx.0 + 1000).into();
+
+        // later items move tags earlier ones set, failures are per item
+        let results = api
+            .lair_set_entry_tags(vec![
+                (a_idx, "one".to_string()),
+                (missing, "two".to_string()),
+                (b_idx, "one".to_string()),
+            ])
             .await?;
-        assert_ne!(CERT_SNI, sni2.as_str());
-        assert_ne!(CERT_DIGEST, digest2.as_slice());
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        assert_eq!(b_idx, api.lair_get_entry_by_tag("one".into()).await?.0);
+        assert!(api.lair_get_entry_by_tag("two".into()).await.is_err());
+
+        // a missing entry leaves the others erased
+        api.lair_trash_entry(a_idx).await?;
+        let results =
+            api.lair_erase_entries(vec![a_idx, missing, b_idx]).await?;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(LairError::EntryNotFound(_))));
+        assert!(results[2].is_ok());
+        assert!(api.lair_get_entry_by_tag("one".into()).await.is_err());
+        assert!(api.lair_restore_entry(a_idx).await.is_err());
+        assert!(matches!(
+            api.lair_erase_entries(vec![b_idx]).await?[0],
+            Err(LairError::EntryNotFound(_)),
+        ));
+
+        let max = MAX_BULK_ENTRIES_LEN as usize;
+        assert!(api.lair_erase_entries(vec![a_idx; max + 1]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_test_keystore_list_entries() -> LairResult<()> {
+        use futures::stream::StreamExt;
+
+        let (api, _evt) = spawn_test_keystore(vec![], vec![]).await?;
+
+        let mut expect = Vec::new();
+        for _ in 0..10_000 {
+            let (idx, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+            expect.push((idx, pub_key.0));
+        }
+
+        // -- raw paging -- //
+
+        assert!(api.lair_list_entries_page(0.into(), 0).await.is_err());
+
+        let (page, next) =
+            api.lair_list_entries_page(0.into(), u32::MAX).await?;
+        assert_eq!(MAX_LIST_ENTRIES_PAGE_LIMIT as usize, page.len());
+        assert_eq!(expect[0].0, page[0].keystore_index);
+        assert_eq!(Some(expect[page.len()].0), next);
+
+        let last = expect.last().unwrap().0;
+        let (page, next) = api.lair_list_entries_page(last, 10).await?;
+        assert_eq!(1, page.len());
+        assert_eq!(None, next);
+
+        let (page, next) =
+            api.lair_list_entries_page((last.0 + 1).into(), 10).await?;
+        assert!(page.is_empty());
+        assert_eq!(None, next);
+
+        // -- stream -- //
+
+        let all = lair_list_entries_stream(api.clone(), 333)
+            .map(|e| e.map(|e| (e.keystore_index, e.pub_id)))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<LairResult<Vec<_>>>()?;
+        assert_eq!(expect, all);
+
+        // early abort
+        let first = lair_list_entries_stream(api.clone(), 100)
+            .take(5)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(5, first.len());
+
+        // entries created mid-iteration may or may not show up,
+        // but pre-existing entries appear exactly once, in order
+        let mut stream = Box::pin(lair_list_entries_stream(api.clone(), 100));
+        let mut seen = Vec::new();
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            if seen.len() % 1000 == 0 {
+                api.sign_ed25519_new_from_entropy().await?;
+            }
+            seen.push(entry.keystore_index);
+        }
+        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+        let seen = seen.into_iter().collect::<std::collections::HashSet<_>>();
+        assert!(expect.iter().all(|(idx, _)| seen.contains(idx)));
 
         Ok(())
     }
@@ -0,0 +1,199 @@
+//! A keystore for integration tests, served over ipc from a temporary
+//! directory, but backed by the in-memory `test` keystore.
+//! DANGER - Not for production!
+//!
+//! ```no_run
+//! # async fn doc() -> lair_keystore_api::LairResult<()> {
+//! use lair_keystore_api::actor::LairClientApiSender;
+//!
+//! let (api_send, _evt_recv, _guard) =
+//!     lair_keystore_api::test_keystore::spawn_test_keystore().await?;
+//! let (_, pub_key) = api_send.sign_ed25519_new_from_entropy().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::actor::*;
+use crate::internal::util::*;
+use crate::passphrase::Passphrase;
+use crate::*;
+use futures::{future::FutureExt, sink::SinkExt, stream::StreamExt};
+
+/// The passphrase the test keystore asks each new connection for.
+/// The client returned by `spawn_test_keystore` answers with it.
+pub const TEST_PASSPHRASE: &str = "test-passphrase";
+
+/// Keeps the test keystore running. Dropping it unbinds the keystore,
+/// closing every connection, and removes its directory.
+/// Must be dropped within the tokio runtime.
+pub struct TestKeystoreGuard {
+    config: Arc<Config>,
+    keystore: ghost_actor::GhostSender<LairClientApi>,
+    client: ipc::LairClientSender,
+    _kill_switch: KillSwitch,
+    _tmpdir: tempfile::TempDir,
+}
+
+impl TestKeystoreGuard {
+    /// The config the keystore is bound with, for connecting more
+    /// clients with `ipc::connect`. They are asked for
+    /// `TEST_PASSPHRASE` on connecting.
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+}
+
+impl Drop for TestKeystoreGuard {
+    fn drop(&mut self) {
+        use ghost_actor::GhostControlSender;
+        let keystore = self.keystore.ghost_actor_shutdown();
+        let client = self.client.ghost_actor_shutdown();
+        tokio::task::spawn(async move {
+            let _ = client.await;
+            let _ = keystore.await;
+        });
+    }
+}
+
+/// Spawn an unlocked test keystore, and a client connected to it.
+/// Unlock events are answered on the returned receiver's behalf,
+/// every other event is passed on. No setup is needed beyond a
+/// tokio runtime, e.g. `#[tokio::test(threaded_scheduler)]`.
+/// DANGER - Not for production!
+pub async fn spawn_test_keystore() -> LairResult<(
+    ipc::LairClientSender,
+    LairClientEventReceiver,
+    TestKeystoreGuard,
+)> {
+    let tmpdir = tempfile::tempdir().map_err(LairError::other)?;
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+    let (keystore, keystore_evt_recv) =
+        test::spawn_test_keystore(vec![], vec![]).await?;
+    let (kill_switch, mut incoming) =
+        ipc::bind_server_ipc(config.clone(), keystore.clone()).await?;
+
+    let (client, client_evt_recv) =
+        ipc::connect(ipc::ConnectOptions::from_config(&config)).await?;
+    let evt_recv = spawn_unlock_responder(client_evt_recv);
+
+    let evt_send = incoming.next().await.ok_or_else::<LairError, _>(|| {
+        "test keystore closed before the client connected".into()
+    })?;
+    check_passphrase(&evt_send).await?;
+    err_spawn(
+        "test-keystore-events",
+        forward_keystore_events(keystore_evt_recv, evt_send),
+    );
+    err_spawn("test-keystore-incoming", async move {
+        while let Some(evt_send) = incoming.next().await {
+            tokio::task::spawn(async move {
+                if let Err(err) = check_passphrase(&evt_send).await {
+                    warn!(?err, "test keystore connection refused");
+                }
+            });
+        }
+        Ok(())
+    });
+
+    Ok((
+        client.clone(),
+        evt_recv,
+        TestKeystoreGuard {
+            config,
+            keystore,
+            client,
+            _kill_switch: kill_switch,
+            _tmpdir: tmpdir,
+        },
+    ))
+}
+
+/// Ask the connection of `evt_send` for the passphrase, as a locked
+/// keystore would, and tell it whether it is `TEST_PASSPHRASE`.
+async fn check_passphrase(
+    evt_send: &LairClientEventSenderType,
+) -> LairResult<()> {
+    let passphrase = evt_send
+        .request_unlock_passphrase(DEFAULT_STORE_NAME.to_string())
+        .await?;
+    let error = if passphrase == Passphrase::from(TEST_PASSPHRASE) {
+        None
+    } else {
+        Some("invalid passphrase".to_string())
+    };
+    evt_send.unlock_complete(error.clone()).await?;
+    match error {
+        None => Ok(()),
+        Some(error) => Err(error.into()),
+    }
+}
+
+/// Answer the unlock events on `evt_recv` with `TEST_PASSPHRASE`,
+/// passing every other event on to the returned receiver.
+fn spawn_unlock_responder(
+    mut evt_recv: LairClientEventReceiver,
+) -> LairClientEventReceiver {
+    let (mut evt_send, out_recv) = futures::channel::mpsc::channel(10);
+    err_spawn("test-keystore-unlock-responder", async move {
+        while let Some(evt) = evt_recv.next().await {
+            match evt {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond, ..
+                } => {
+                    respond.respond(Ok(async move {
+                        Ok(Passphrase::from(TEST_PASSPHRASE))
+                    }
+                    .boxed()
+                    .into()));
+                }
+                LairClientEvent::UnlockProgress { respond, .. } => {
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                }
+                LairClientEvent::UnlockComplete { respond, .. } => {
+                    respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                }
+                // once the receiver is dropped, events fail unanswered
+                evt => {
+                    let _ = evt_send.send(evt).await;
+                }
+            }
+        }
+        Ok(())
+    });
+    out_recv
+}
+
+/// Pass the events of the in-memory keystore on to the connection
+/// of `evt_send`, the client returned by `spawn_test_keystore`.
+async fn forward_keystore_events(
+    mut keystore_evt_recv: LairClientEventReceiver,
+    evt_send: LairClientEventSenderType,
+) -> LairResult<()> {
+    while let Some(evt) = keystore_evt_recv.next().await {
+        match evt {
+            LairClientEvent::RequestSignApproval {
+                respond,
+                keystore_index,
+                pub_key,
+                ..
+            } => {
+                let fut =
+                    evt_send.request_sign_approval(keystore_index, pub_key);
+                respond.respond(Ok(fut.boxed().into()));
+            }
+            LairClientEvent::EntryCreated {
+                respond,
+                keystore_index,
+                entry_type,
+                ..
+            } => {
+                let fut = evt_send.entry_created(keystore_index, entry_type);
+                respond.respond(Ok(fut.boxed().into()));
+            }
+            // the in-memory keystore is never locked, nor asks for more
+            _ => (),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Known-answer test vectors for the primitives lair depends on.
+//!
+//! These are shared by the unit tests and the startup self-test
+//! (see `lair_self_test`), so a miscompiled primitive is caught
+//! before it can touch a store.
+
+/// Decode a lowercase hex string, as the vectors are written.
+/// Panics on invalid hex, the vectors are all constants.
+pub fn from_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("valid hex"))
+        .collect()
+}
+
+/// An ed25519 signing vector, all fields hex encoded.
+pub struct Ed25519Vector {
+    /// The 32 byte secret key (seed).
+    pub secret_key: &'static str,
+
+    /// The 32 byte public key.
+    pub public_key: &'static str,
+
+    /// The signed message.
+    pub message: &'static str,
+
+    /// The 64 byte signature.
+    pub signature: &'static str,
+}
+
+/// RFC 8032 section 7.1, tests 1 to 3.
+pub const ED25519_VECTORS: &[Ed25519Vector] = &[
+    Ed25519Vector {
+        secret_key: "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        public_key: "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        message: "",
+        signature: "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    },
+    Ed25519Vector {
+        secret_key: "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        public_key: "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        message: "72",
+        signature: "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+    },
+    Ed25519Vector {
+        secret_key: "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        public_key: "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        message: "af82",
+        signature: "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+    },
+];
+
+/// RFC 8032 section 7.3, Ed25519ph of `abc`, with no context.
+pub const ED25519PH_VECTOR: Ed25519Vector = Ed25519Vector {
+    secret_key: "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+    public_key: "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+    message: "616263",
+    signature: "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+};
+
+/// RFC 7693 appendix A, the 64 byte unkeyed blake2b of `abc`.
+pub const BLAKE2B_ABC: &str = "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923";
+
+/// blake2 reference keyed KAT, the 64 byte blake2b of the empty
+/// input keyed with the bytes `0..64`.
+pub const BLAKE2B_KEYED_EMPTY: &str = "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568";
+
+/// libsodium `crypto_secretbox_easy` (XSalsa20-Poly1305) output for
+/// key `0..32`, nonce `0..24` and message `0..200`, as `mac || ciphertext`.
+pub const SECRETBOX: &str = "f7e080af1bd9298baad4b0b699189beb5efe3a4cc3cfa417b33585356482449842b454cc983029f80fbb7d8f49dabffea3fad7f66195d32591a4cab2564b856c13705aacc7a2ea7884261d86265ca9ca9cc1bd5a41538faa5cbbcb9a863ec6cda68280a8c64b78afc161c4517e66b9f1e13b66e53be7340f4703bb24f492316753bd50eea657e81ee5bb60fa01b42ac41b488f6fbf1e5538a81208517b04ba05ce857ec7bff8781cf887e4f67f5f14b2f71787444e16e57f43892f1d5a2fa7f7298492f796d5d6d66a34533056e511007a6cb2c05cdc6604";
+
+/// RFC 9106 section 5.3, argon2id version 0x13 with
+/// 32 KiB memory, 3 passes and 4 lanes.
+/// The password is 32 bytes of `0x01`, the salt 16 bytes of `0x02`,
+/// the secret 8 bytes of `0x03` and the associated data 12 bytes of `0x04`.
+pub const ARGON2ID_TAG: &str =
+    "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659";
+
+/// A der encoded self-signed ed25519 certificate, generated by
+/// `openssl req -x509 -newkey ed25519 -subj /CN=lair-fixture.example`.
+pub const FIXTURE_CERT_DER: &str = "3082015330820105a003020102021433641934fcfff3b9b225a66293583aa21ab3c1c3300506032b6570301f311d301b06035504030c146c6169722d666978747572652e6578616d706c65301e170d3236313031353034353935375a170d3336313031323034353935375a301f311d301b06035504030c146c6169722d666978747572652e6578616d706c65302a300506032b6570032100538e5d4d05bcaf19c3b122615c025e6c2cd2200758b7b4051c36e6383d9812d3a3533051301d0603551d0e041604141a0e6c2d8b1f47d39b9a704176494b9511433449301f0603551d230418301680141a0e6c2d8b1f47d39b9a704176494b9511433449300f0603551d130101ff040530030101ff300506032b6570034100d6cf4bb961c3508e270d2364ad0f194082d859447ff766673166b232f148dc6dc263e54dc2e229e57651845de6e752c340f6bf5151888b23fed3c3aa43f24c09";
+
+/// `openssl x509 -fingerprint -sha256` of `FIXTURE_CERT_DER`.
+pub const FIXTURE_CERT_SHA256: &str =
+    "0c86c0ba9c8c027e7c17691cb9a6a3933bb48f4180b7dad4799b01ed4bfd064c";
+
+/// `openssl x509 -fingerprint -sha1` of `FIXTURE_CERT_DER`.
+pub const FIXTURE_CERT_SHA1: &str = "087f1a0fb5ca956dd6d0686ca8fdc730e7dd49b2";
+
+/// A signature made with `SignOptions::include_timestamp`, all fields
+/// but the timestamp hex encoded. See `actor::timestamped_message`.
+pub struct TimestampedEd25519Vector {
+    /// The key, and the message, signed.
+    pub vector: Ed25519Vector,
+
+    /// The unix time in milliseconds it was signed at.
+    pub timestamp_millis: u64,
+
+    /// The bytes the signature signs, the timestamp then the message.
+    pub timestamped_message: &'static str,
+}
+
+/// The RFC 8032 section 7.1 test 2 key and message, timestamped
+/// `2023-11-14T22:13:20Z`. Signed with python `cryptography`, over the
+/// 8 byte big-endian timestamp followed by the message.
+pub const TIMESTAMPED_ED25519_VECTOR: TimestampedEd25519Vector = TimestampedEd25519Vector {
+    vector: Ed25519Vector {
+        secret_key: "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        public_key: "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        message: "72",
+        signature: "dc750575fdf47ee4939368d979ece75a3f69d2bea97ccc8536826c881932d48fcc22d67fbe1d9ec304f004b987d2cb17cf143519c69b3708b1bfa9c39d2c3101",
+    },
+    timestamp_millis: 1_700_000_000_000,
+    timestamped_message: "0000018bcfe5680072",
+};
@@ -0,0 +1,74 @@
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::test_keystore::*;
+use lair_keystore_api::LairResult;
+use std::sync::Arc;
+
+/// Fails rather than hanging on a keystore that outlives its guard.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[tokio::test(threaded_scheduler)]
+async fn test_keystore_test() -> LairResult<()> {
+    let (api_send, mut evt_recv, guard) = spawn_test_keystore().await?;
+
+    // already unlocked
+    let (index, pub_key) = api_send.sign_ed25519_new_from_entropy().await?;
+    let signature = api_send
+        .sign_ed25519_sign_by_index(index, Arc::new(vec![1; 8]))
+        .await?;
+    assert!(pub_key.verify(Arc::new(vec![1; 8]), signature).await?);
+
+    // events other than unlocks reach the returned receiver
+    api_send
+        .lair_set_event_filter(vec![EventKind::RequestSignApproval])
+        .await?;
+    let mut options = SignKeyOptions::default();
+    options.requires_approval = true;
+    let (approval_index, _) = api_send
+        .sign_ed25519_new_from_entropy_with_options(options)
+        .await?;
+    let sign = tokio::task::spawn(
+        api_send
+            .sign_ed25519_sign_by_index(approval_index, Arc::new(vec![2; 8])),
+    );
+    match evt_recv.next().await {
+        Some(LairClientEvent::RequestSignApproval {
+            respond,
+            keystore_index,
+            ..
+        }) => {
+            assert_eq!(approval_index, keystore_index);
+            respond.respond(Ok(async move { Ok(false) }.boxed().into()));
+        }
+        _ => panic!("expected a sign approval request"),
+    }
+    assert!(matches!(
+        sign.await.unwrap(),
+        Err(lair_keystore_api::LairError::Forbidden(_))
+    ));
+
+    // more clients are asked for the passphrase
+    let (other_send, other_evt_recv) = lair_keystore_api::ipc::connect(
+        lair_keystore_api::ipc::ConnectOptions::from_config(guard.config()),
+    )
+    .await?;
+    let (unlock, _) = lair_keystore_api::ipc::spawn_passphrase_unlock_responder(
+        other_evt_recv,
+        TEST_PASSPHRASE.into(),
+    );
+    unlock.await?;
+    assert_eq!(pub_key, other_send.sign_ed25519_get(index).await?);
+
+    // torn down with the guard
+    let socket_path = guard.config().get_socket_path().to_path_buf();
+    drop(guard);
+    assert!(
+        tokio::time::timeout(TIMEOUT, other_send.lair_get_server_info())
+            .await
+            .expect("the test keystore outlived its guard")
+            .is_err()
+    );
+    assert!(!socket_path.exists());
+
+    Ok(())
+}
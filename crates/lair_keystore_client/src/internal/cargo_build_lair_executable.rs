@@ -3,7 +3,7 @@ use crate::*;
 /// If cargo exists on the system, try to build lair manually.
 pub fn cargo_build_lair_executable() -> LairResult<()> {
     match std::process::Command::new("cargo")
-        .args(&[
+        .args([
             "install",
             "lair_keystore",
             "-f",
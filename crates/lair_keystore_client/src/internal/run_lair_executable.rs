@@ -1,17 +1,18 @@
 use crate::*;
+use lair_keystore_api::internal::util;
 
 /// Manually run a lair executable.
 /// Child returned mainly so tests can kill the process.
 pub async fn run_lair_executable(
     config: Arc<Config>,
 ) -> LairResult<std::process::Child> {
-    let stdout = std::fs::OpenOptions::new()
+    let stdout = util::private_open_options()
         .write(true)
         .create(true)
         .truncate(true)
         .open(config.get_stdout_path())
         .map_err(LairError::other)?;
-    let stderr = std::fs::OpenOptions::new()
+    let stderr = util::private_open_options()
         .write(true)
         .create(true)
         .truncate(true)
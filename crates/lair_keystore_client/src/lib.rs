@@ -34,10 +34,7 @@ macro_rules! e {
 /// experience.
 pub async fn assert_running_lair_and_connect(
     config: Arc<Config>,
-) -> LairResult<(
-    ghost_actor::GhostSender<LairClientApi>,
-    LairClientEventReceiver,
-)> {
+) -> LairResult<(LairClientSender, LairClientEventReceiver)> {
     // step 1 - just try to connect
     if let Ok(r) = e!(check_ipc_connect(config.clone()).await) {
         trace!("first try check Ok");
@@ -78,11 +75,9 @@ pub async fn assert_running_lair_and_connect(
 
 async fn check_ipc_connect(
     config: Arc<Config>,
-) -> LairResult<(
-    ghost_actor::GhostSender<LairClientApi>,
-    LairClientEventReceiver,
-)> {
-    let (api, evt) = ipc::spawn_client_ipc(config.clone()).await?;
+) -> LairResult<(LairClientSender, LairClientEventReceiver)> {
+    let (api, evt) =
+        ipc::connect(ipc::ConnectOptions::from_config(&config)).await?;
 
     trace!("send check server info");
     let srv_info = api.lair_get_server_info().await?;
@@ -0,0 +1,13 @@
+pub fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&crate_dir)
+        .expect("can generate c bindings")
+        .write_to_file(
+            std::path::Path::new(&crate_dir)
+                .join("include")
+                .join("lair_keystore_client.h"),
+        );
+}
@@ -0,0 +1,136 @@
+/*
+ * Lair keystore client C ABI.
+ *
+ * Memory ownership:
+ * - strings written to `out_err` are owned by the caller,
+ *   release them with lair_string_free().
+ * - LairBytes written to out-params are owned by the caller,
+ *   release them with lair_bytes_free() (which zeroes them first).
+ * - a LairClient from lair_client_connect() is owned by the caller,
+ *   release it with lair_client_free().
+ * - all input pointers are only borrowed for the duration of a call.
+ *
+ * Thread safety:
+ * - a LairClient may be used from multiple threads concurrently.
+ * - lair_client_free() must not race with other calls on that client.
+ * - the passphrase callback may run on a lair internal thread.
+ */
+
+#ifndef LAIR_KEYSTORE_CLIENT_H
+#define LAIR_KEYSTORE_CLIENT_H
+
+/* Generated by cbindgen from src/lib.rs - do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result codes returned by all fallible lair ffi functions.
+typedef enum LairFfiCode {
+  // Success.
+  LAIR_FFI_CODE_OK = 0,
+  // A null pointer or otherwise invalid argument was supplied.
+  LAIR_FFI_CODE_INVALID_ARGUMENT = 1,
+  // Could not connect to the lair keystore.
+  LAIR_FFI_CODE_CONNECT = 2,
+  // The lair keystore returned an error.
+  LAIR_FFI_CODE_API = 3,
+  // An internal panic was caught at the ffi boundary.
+  LAIR_FFI_CODE_PANIC = 4,
+} LairFfiCode;
+
+// An opaque handle to a connected lair client.
+typedef struct LairClient LairClient;
+
+// Callback used to supply the unlock passphrase.
+// Write a NUL terminated passphrase of at most `buf_len` bytes
+// (including the NUL) into `buf`, and return 0 on success.
+// The buffer is zeroed by lair after use.
+typedef int32_t (*LairPassphraseCb)(void *user_data, char *buf, size_t buf_len);
+
+// A lair-allocated byte buffer. Release with [`lair_bytes_free`].
+typedef struct LairBytes {
+  // Pointer to the first byte.
+  uint8_t *ptr;
+  // Number of bytes.
+  size_t len;
+} LairBytes;
+
+// Connect to the lair keystore at `connection_url`
+// (e.g. `unix:///path/to/socket`, as written by the server to the
+// `connection_url` file in its data directory).
+// `passphrase_cb` is invoked whenever the keystore requests the
+// unlock passphrase. On success, `*out_client` owns a new handle.
+enum LairFfiCode lair_client_connect(const char *connection_url,
+                                     LairPassphraseCb passphrase_cb,
+                                     void *user_data,
+                                     struct LairClient **out_client,
+                                     char **out_err);
+
+// Release a client handle obtained from [`lair_client_connect`].
+// Passing null is a no-op.
+void lair_client_free(struct LairClient *client);
+
+// Release a message string written to an `out_err` param.
+// Passing null is a no-op.
+void lair_string_free(char *s);
+
+// Zero and release a byte buffer written to an out-param.
+// Passing a null `ptr` is a no-op.
+void lair_bytes_free(struct LairBytes bytes);
+
+// Create a new ed25519 signing keypair.
+enum LairFfiCode lair_sign_ed25519_new(const struct LairClient *client,
+                                       uint32_t *out_index,
+                                       struct LairBytes *out_pub_key,
+                                       char **out_err);
+
+// Sign a message with the ed25519 keypair at `index`.
+enum LairFfiCode lair_sign_ed25519_sign_by_index(const struct LairClient *client,
+                                                 uint32_t index,
+                                                 const uint8_t *message,
+                                                 size_t message_len,
+                                                 struct LairBytes *out_signature,
+                                                 char **out_err);
+
+// Sign a message with the ed25519 keypair identified by `pub_key`.
+enum LairFfiCode lair_sign_ed25519_sign_by_pub_key(const struct LairClient *client,
+                                                   const uint8_t *pub_key,
+                                                   size_t pub_key_len,
+                                                   const uint8_t *message,
+                                                   size_t message_len,
+                                                   struct LairBytes *out_signature,
+                                                   char **out_err);
+
+// Verify an ed25519 signature. Does not require a client connection.
+// `*out_valid` is set to whether the signature is valid.
+enum LairFfiCode lair_sign_ed25519_verify(const uint8_t *pub_key,
+                                          size_t pub_key_len,
+                                          const uint8_t *message,
+                                          size_t message_len,
+                                          const uint8_t *signature,
+                                          size_t signature_len,
+                                          bool *out_valid,
+                                          char **out_err);
+
+// Create a new self-signed tls certificate with default options.
+enum LairFfiCode lair_tls_cert_new_self_signed(const struct LairClient *client,
+                                               uint32_t *out_index,
+                                               char **out_err);
+
+// Fetch the der encoded tls certificate at `index`.
+enum LairFfiCode lair_tls_cert_get_cert_by_index(const struct LairClient *client,
+                                                 uint32_t index,
+                                                 struct LairBytes *out_cert,
+                                                 char **out_err);
+
+// Fetch the der encoded pkcs #8 private key of the tls certificate
+// at `index`. Release promptly with [`lair_bytes_free`].
+enum LairFfiCode lair_tls_cert_get_priv_key_by_index(const struct LairClient *client,
+                                                     uint32_t index,
+                                                     struct LairBytes *out_priv_key,
+                                                     char **out_err);
+
+#endif /* LAIR_KEYSTORE_CLIENT_H */
@@ -0,0 +1,484 @@
+#![deny(missing_docs)]
+#![deny(warnings)]
+#![allow(clippy::missing_safety_doc)]
+//! C ABI bindings for the secret lair private keystore client.
+//!
+//! # Conventions
+//!
+//! Every fallible function returns a [`LairFfiCode`]. On anything other
+//! than `LAIR_FFI_CODE_OK`, if `out_err` is non-null, `*out_err` is set
+//! to a NUL terminated message allocated by lair.
+//!
+//! # Memory ownership
+//!
+//! - Strings written to `out_err` are owned by the caller and must be
+//!   released with [`lair_string_free`].
+//! - [`LairBytes`] written to out-params are owned by the caller and
+//!   must be released with [`lair_bytes_free`] (which zeroes them first).
+//! - A [`LairClient`] returned by [`lair_client_connect`] is owned by
+//!   the caller and must be released with [`lair_client_free`].
+//! - All input pointers are borrowed for the duration of the call only.
+//!
+//! # Thread safety
+//!
+//! A [`LairClient`] may be used from multiple threads concurrently.
+//! [`lair_client_free`] must not race with any other call on the same
+//! handle. The passphrase callback may be invoked on a lair internal
+//! thread, at any time until the client is freed.
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::passphrase::Passphrase;
+use lair_keystore_api::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::sync::Arc;
+
+/// Result codes returned by all fallible lair ffi functions.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LairFfiCode {
+    /// Success.
+    Ok = 0,
+
+    /// A null pointer or otherwise invalid argument was supplied.
+    InvalidArgument = 1,
+
+    /// Could not connect to the lair keystore.
+    Connect = 2,
+
+    /// The lair keystore returned an error.
+    Api = 3,
+
+    /// An internal panic was caught at the ffi boundary.
+    Panic = 4,
+}
+
+/// A lair-allocated byte buffer. Release with [`lair_bytes_free`].
+#[repr(C)]
+pub struct LairBytes {
+    /// Pointer to the first byte.
+    pub ptr: *mut u8,
+
+    /// Number of bytes.
+    pub len: usize,
+}
+
+impl From<Vec<u8>> for LairBytes {
+    fn from(v: Vec<u8>) -> Self {
+        let b = v.into_boxed_slice();
+        let len = b.len();
+        let ptr = Box::into_raw(b) as *mut u8;
+        Self { ptr, len }
+    }
+}
+
+/// Callback used to supply the unlock passphrase.
+/// Write a NUL terminated passphrase of at most `buf_len` bytes
+/// (including the NUL) into `buf`, and return 0 on success.
+/// The buffer is zeroed by lair after use.
+pub type LairPassphraseCb = extern "C" fn(
+    user_data: *mut c_void,
+    buf: *mut c_char,
+    buf_len: usize,
+) -> i32;
+
+const PASSPHRASE_BUF_LEN: usize = 1024;
+
+struct PassphraseCb {
+    cb: LairPassphraseCb,
+    user_data: *mut c_void,
+}
+
+// the caller is required to make user_data usable from any thread
+unsafe impl Send for PassphraseCb {}
+
+impl PassphraseCb {
+    fn call(&self) -> LairResult<Passphrase> {
+        let mut buf = zeroize::Zeroizing::new(vec![0_u8; PASSPHRASE_BUF_LEN]);
+        let res = (self.cb)(
+            self.user_data,
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len(),
+        );
+        if res != 0 {
+            return Err(format!("passphrase callback failed: {}", res).into());
+        }
+        let len = buf
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| LairError::from("passphrase not NUL terminated"))?;
+        std::str::from_utf8(&buf[..len]).map_err(LairError::other)?;
+        Ok(Passphrase::from_bytes_mut(&mut buf[..len]))
+    }
+}
+
+/// An opaque handle to a connected lair client.
+pub struct LairClient {
+    runtime: tokio::runtime::Runtime,
+    api: LairClientSender,
+}
+
+fn _assert_client_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<LairClient>();
+}
+
+impl LairClient {
+    /// Execute a future on our runtime, blocking the calling thread.
+    fn exec<F, R>(&self, f: F) -> LairResult<R>
+    where
+        F: 'static + Send + std::future::Future<Output = LairResult<R>>,
+        R: 'static + Send,
+    {
+        futures::executor::block_on(self.runtime.handle().spawn(f))
+            .map_err(LairError::other)?
+    }
+}
+
+unsafe fn set_err(out_err: *mut *mut c_char, msg: String) {
+    if out_err.is_null() {
+        return;
+    }
+    let msg =
+        CString::new(msg.replace('\0', "")).expect("nul bytes were removed");
+    *out_err = msg.into_raw();
+}
+
+/// Run `f` behind a panic guard, translating the result to ffi codes.
+unsafe fn ffi_guard<F>(out_err: *mut *mut c_char, f: F) -> LairFfiCode
+where
+    F: FnOnce() -> Result<(), (LairFfiCode, String)>,
+{
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => LairFfiCode::Ok,
+        Ok(Err((code, msg))) => {
+            set_err(out_err, msg);
+            code
+        }
+        Err(_) => {
+            set_err(out_err, "panic in lair ffi".to_string());
+            LairFfiCode::Panic
+        }
+    }
+}
+
+fn api_err(e: LairError) -> (LairFfiCode, String) {
+    (LairFfiCode::Api, e.to_string())
+}
+
+fn invalid(what: &str) -> (LairFfiCode, String) {
+    (
+        LairFfiCode::InvalidArgument,
+        format!("invalid argument: {}", what),
+    )
+}
+
+unsafe fn client_ref<'a>(
+    client: *const LairClient,
+) -> Result<&'a LairClient, (LairFfiCode, String)> {
+    client.as_ref().ok_or_else(|| invalid("client is null"))
+}
+
+unsafe fn slice_ref<'a>(
+    ptr: *const u8,
+    len: usize,
+    what: &str,
+) -> Result<&'a [u8], (LairFfiCode, String)> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(invalid(what));
+    }
+    Ok(std::slice::from_raw_parts(ptr, len))
+}
+
+/// Connect to the lair keystore at `connection_url`
+/// (e.g. `unix:///path/to/socket`, as written by the server to the
+/// `connection_url` file in its data directory).
+/// `passphrase_cb` is invoked whenever the keystore requests the
+/// unlock passphrase. On success, `*out_client` owns a new handle.
+#[no_mangle]
+pub unsafe extern "C" fn lair_client_connect(
+    connection_url: *const c_char,
+    passphrase_cb: LairPassphraseCb,
+    user_data: *mut c_void,
+    out_client: *mut *mut LairClient,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        if connection_url.is_null() {
+            return Err(invalid("connection_url is null"));
+        }
+        if out_client.is_null() {
+            return Err(invalid("out_client is null"));
+        }
+        let connection_url: LairConnectionUrl = CStr::from_ptr(connection_url)
+            .to_str()
+            .map_err(|_| invalid("connection_url is not utf8"))?
+            .parse()
+            .map_err(|e: LairError| {
+                (LairFfiCode::InvalidArgument, e.to_string())
+            })?;
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .build()
+            .map_err(|e| (LairFfiCode::Connect, e.to_string()))?;
+
+        let cb = PassphraseCb {
+            cb: passphrase_cb,
+            user_data,
+        };
+
+        let api = runtime
+            .block_on(async move {
+                let config = Config::builder()
+                    .set_connection_url(connection_url)
+                    .build();
+                let (api, mut evt_recv) =
+                    ipc::connect(ipc::ConnectOptions::from_config(&config))
+                        .await?;
+                tokio::task::spawn(async move {
+                    while let Some(evt) = evt_recv.next().await {
+                        // unlock progress is not exposed over ffi
+                        if let LairClientEvent::RequestUnlockPassphrase {
+                            respond,
+                            ..
+                        } = evt
+                        {
+                            let res = cb.call();
+                            respond
+                                .respond(Ok(async move { res }.boxed().into()));
+                        }
+                    }
+                });
+                LairResult::Ok(api)
+            })
+            .map_err(|e| (LairFfiCode::Connect, e.to_string()))?;
+
+        *out_client = Box::into_raw(Box::new(LairClient { runtime, api }));
+        Ok(())
+    })
+}
+
+/// Release a client handle obtained from [`lair_client_connect`].
+/// Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn lair_client_free(client: *mut LairClient) {
+    if !client.is_null() {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(Box::from_raw(client))
+        }));
+    }
+}
+
+/// Release a message string written to an `out_err` param.
+/// Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn lair_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Zero and release a byte buffer written to an out-param.
+/// Passing a null `ptr` is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn lair_bytes_free(bytes: LairBytes) {
+    if !bytes.ptr.is_null() {
+        let mut b = Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            bytes.ptr, bytes.len,
+        ));
+        zeroize::Zeroize::zeroize(&mut *b);
+    }
+}
+
+/// Create a new ed25519 signing keypair.
+#[no_mangle]
+pub unsafe extern "C" fn lair_sign_ed25519_new(
+    client: *const LairClient,
+    out_index: *mut u32,
+    out_pub_key: *mut LairBytes,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        if out_index.is_null() || out_pub_key.is_null() {
+            return Err(invalid("out param is null"));
+        }
+        let api = client.api.clone();
+        let (index, pub_key) = client
+            .exec(async move { api.sign_ed25519_new_from_entropy().await })
+            .map_err(api_err)?;
+        *out_index = index.0;
+        *out_pub_key = pub_key.0.to_vec().into();
+        Ok(())
+    })
+}
+
+/// Sign a message with the ed25519 keypair at `index`.
+#[no_mangle]
+pub unsafe extern "C" fn lair_sign_ed25519_sign_by_index(
+    client: *const LairClient,
+    index: u32,
+    message: *const u8,
+    message_len: usize,
+    out_signature: *mut LairBytes,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        let message =
+            Arc::new(slice_ref(message, message_len, "message")?.to_vec());
+        if out_signature.is_null() {
+            return Err(invalid("out_signature is null"));
+        }
+        let api = client.api.clone();
+        let sig = client
+            .exec(async move {
+                api.sign_ed25519_sign_by_index(index.into(), message).await
+            })
+            .map_err(api_err)?;
+        *out_signature = sig.0.to_vec().into();
+        Ok(())
+    })
+}
+
+/// Sign a message with the ed25519 keypair identified by `pub_key`.
+#[no_mangle]
+pub unsafe extern "C" fn lair_sign_ed25519_sign_by_pub_key(
+    client: *const LairClient,
+    pub_key: *const u8,
+    pub_key_len: usize,
+    message: *const u8,
+    message_len: usize,
+    out_signature: *mut LairBytes,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        let pub_key: SignEd25519PubKey =
+            slice_ref(pub_key, pub_key_len, "pub_key")?.to_vec().into();
+        let message =
+            Arc::new(slice_ref(message, message_len, "message")?.to_vec());
+        if out_signature.is_null() {
+            return Err(invalid("out_signature is null"));
+        }
+        let api = client.api.clone();
+        let sig = client
+            .exec(async move {
+                api.sign_ed25519_sign_by_pub_key(pub_key, message).await
+            })
+            .map_err(api_err)?;
+        *out_signature = sig.0.to_vec().into();
+        Ok(())
+    })
+}
+
+/// Verify an ed25519 signature. Does not require a client connection.
+/// `*out_valid` is set to whether the signature is valid.
+#[no_mangle]
+pub unsafe extern "C" fn lair_sign_ed25519_verify(
+    pub_key: *const u8,
+    pub_key_len: usize,
+    message: *const u8,
+    message_len: usize,
+    signature: *const u8,
+    signature_len: usize,
+    out_valid: *mut bool,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let pub_key: SignEd25519PubKey =
+            slice_ref(pub_key, pub_key_len, "pub_key")?.to_vec().into();
+        let message = slice_ref(message, message_len, "message")?;
+        let signature: SignEd25519Signature =
+            slice_ref(signature, signature_len, "signature")?
+                .to_vec()
+                .into();
+        if out_valid.is_null() {
+            return Err(invalid("out_valid is null"));
+        }
+        *out_valid = pub_key.verify_pure(message, &signature);
+        Ok(())
+    })
+}
+
+/// Create a new self-signed tls certificate with default options.
+#[no_mangle]
+pub unsafe extern "C" fn lair_tls_cert_new_self_signed(
+    client: *const LairClient,
+    out_index: *mut u32,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        if out_index.is_null() {
+            return Err(invalid("out_index is null"));
+        }
+        let api = client.api.clone();
+        let (index, _, _) = client
+            .exec(async move {
+                api.tls_cert_new_self_signed_from_entropy(
+                    TlsCertOptions::default(),
+                )
+                .await
+            })
+            .map_err(api_err)?;
+        *out_index = index.0;
+        Ok(())
+    })
+}
+
+/// Fetch the der encoded tls certificate at `index`.
+#[no_mangle]
+pub unsafe extern "C" fn lair_tls_cert_get_cert_by_index(
+    client: *const LairClient,
+    index: u32,
+    out_cert: *mut LairBytes,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        if out_cert.is_null() {
+            return Err(invalid("out_cert is null"));
+        }
+        let api = client.api.clone();
+        let cert =
+            client
+                .exec(async move {
+                    api.tls_cert_get_cert_by_index(index.into()).await
+                })
+                .map_err(api_err)?;
+        *out_cert = cert.0.to_vec().into();
+        Ok(())
+    })
+}
+
+/// Fetch the der encoded pkcs #8 private key of the tls certificate
+/// at `index`. Release promptly with [`lair_bytes_free`].
+#[no_mangle]
+pub unsafe extern "C" fn lair_tls_cert_get_priv_key_by_index(
+    client: *const LairClient,
+    index: u32,
+    out_priv_key: *mut LairBytes,
+    out_err: *mut *mut c_char,
+) -> LairFfiCode {
+    ffi_guard(out_err, || {
+        let client = client_ref(client)?;
+        if out_priv_key.is_null() {
+            return Err(invalid("out_priv_key is null"));
+        }
+        let api = client.api.clone();
+        let priv_key = client
+            .exec(async move {
+                api.tls_cert_get_priv_key_by_index(index.into()).await
+            })
+            .map_err(api_err)?;
+        *out_priv_key = priv_key.0.to_vec().into();
+        Ok(())
+    })
+}
@@ -0,0 +1,68 @@
+//! Build and run tests/ffi_test.c against the lair ffi cdylib.
+
+#![cfg(not(windows))]
+
+#[tokio::test(threaded_scheduler)]
+async fn c_ffi_test() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+
+    lair_keystore::execute_lair().await?;
+
+    // the cdylib built alongside this test binary lives next to it
+    // in target/<profile>/deps (it is only uplifted on `cargo build`)
+    let lib_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_owned();
+
+    let crate_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut exe = tmpdir.path().to_owned();
+    exe.push("ffi_test");
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = std::process::Command::new(cc)
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-o")
+        .arg(&exe)
+        .arg(crate_dir.join("tests").join("ffi_test.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-llair_keystore_client_ffi")
+        .status()
+        .expect("can execute c compiler");
+    assert!(status.success(), "failed to compile ffi_test.c");
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    let connection_url =
+        std::fs::read_to_string(config.get_connection_url_path()).unwrap();
+    let output = tokio::task::spawn_blocking(move || {
+        std::process::Command::new(&exe)
+            .arg(connection_url)
+            .env("LD_LIBRARY_PATH", &lib_dir)
+            .env("DYLD_LIBRARY_PATH", &lib_dir)
+            .output()
+            .expect("can execute ffi_test")
+    })
+    .await
+    .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("#ffi-test-ok#"),
+        "ffi_test failed\n--- stdout ---\n{}\n--- stderr ---\n{}",
+        stdout,
+        stderr,
+    );
+
+    drop(tmpdir);
+
+    Ok(())
+}
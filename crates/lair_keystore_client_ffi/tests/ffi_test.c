@@ -0,0 +1,141 @@
+/* exercises the lair ffi from C, run by tests/ffi.rs */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "lair_keystore_client.h"
+
+static int passphrase_calls = 0;
+
+static int32_t passphrase_cb(void *user_data, char *buf, size_t buf_len) {
+  const char *passphrase = (const char *)user_data;
+  passphrase_calls += 1;
+  if (strlen(passphrase) + 1 > buf_len) {
+    return 1;
+  }
+  strcpy(buf, passphrase);
+  return 0;
+}
+
+/* expects a `char *err` in scope */
+#define CHECK(expr)                                                            \
+  do {                                                                         \
+    LairFfiCode code = (expr);                                                 \
+    if (code != LAIR_FFI_CODE_OK) {                                            \
+      fprintf(stderr, "%s:%d: %s failed with %d: %s\n", __FILE__, __LINE__,   \
+              #expr, code, err ? err : "");                                    \
+      return 1;                                                                \
+    }                                                                          \
+  } while (0)
+
+#define ASSERT(cond)                                                           \
+  do {                                                                         \
+    if (!(cond)) {                                                             \
+      fprintf(stderr, "%s:%d: assert failed: %s\n", __FILE__, __LINE__,       \
+              #cond);                                                          \
+      return 1;                                                                \
+    }                                                                          \
+  } while (0)
+
+int main(int argc, char **argv) {
+  if (argc != 2) {
+    fprintf(stderr, "usage: ffi_test <connection_url>\n");
+    return 1;
+  }
+
+  char *err = NULL;
+  LairClient *client = NULL;
+
+  /* errors come back as codes + a message we must free */
+  ASSERT(lair_client_connect(NULL, passphrase_cb, "passphrase", &client,
+                             &err) == LAIR_FFI_CODE_INVALID_ARGUMENT);
+  ASSERT(err != NULL);
+  ASSERT(strstr(err, "connection_url") != NULL);
+  lair_string_free(err);
+  err = NULL;
+
+  ASSERT(lair_client_connect("unix://relative/socket", passphrase_cb,
+                             "passphrase", &client,
+                             &err) == LAIR_FFI_CODE_INVALID_ARGUMENT);
+  ASSERT(err != NULL);
+  ASSERT(strstr(err, "InvalidConnectionUrl") != NULL);
+  lair_string_free(err);
+  err = NULL;
+
+  CHECK(lair_client_connect(argv[1], passphrase_cb, "passphrase", &client,
+                            &err));
+  ASSERT(client != NULL);
+
+  /* -- ed25519 -- */
+
+  uint32_t sign_index = 0;
+  LairBytes pub_key = {0};
+  CHECK(lair_sign_ed25519_new(client, &sign_index, &pub_key, &err));
+  ASSERT(pub_key.len == 32);
+
+  const char *msg = "test-data";
+  LairBytes sig1 = {0};
+  LairBytes sig2 = {0};
+  CHECK(lair_sign_ed25519_sign_by_index(
+      client, sign_index, (const uint8_t *)msg, strlen(msg), &sig1, &err));
+  CHECK(lair_sign_ed25519_sign_by_pub_key(client, pub_key.ptr, pub_key.len,
+                                          (const uint8_t *)msg, strlen(msg),
+                                          &sig2, &err));
+  ASSERT(sig1.len == 64);
+  ASSERT(sig2.len == 64);
+  ASSERT(memcmp(sig1.ptr, sig2.ptr, 64) == 0);
+
+  bool valid = false;
+  CHECK(lair_sign_ed25519_verify(pub_key.ptr, pub_key.len,
+                                 (const uint8_t *)msg, strlen(msg), sig1.ptr,
+                                 sig1.len, &valid, &err));
+  ASSERT(valid);
+
+  const char *bad = "bad-data";
+  CHECK(lair_sign_ed25519_verify(pub_key.ptr, pub_key.len,
+                                 (const uint8_t *)bad, strlen(bad), sig1.ptr,
+                                 sig1.len, &valid, &err));
+  ASSERT(!valid);
+
+  lair_bytes_free(sig1);
+  lair_bytes_free(sig2);
+  lair_bytes_free(pub_key);
+
+  /* unknown entries give an api error */
+  LairBytes none = {0};
+  ASSERT(lair_sign_ed25519_sign_by_index(client, 9999, (const uint8_t *)msg,
+                                         strlen(msg), &none,
+                                         &err) == LAIR_FFI_CODE_API);
+  ASSERT(err != NULL);
+  lair_string_free(err);
+  err = NULL;
+
+  /* -- tls -- */
+
+  uint32_t cert_index = 0;
+  CHECK(lair_tls_cert_new_self_signed(client, &cert_index, &err));
+
+  LairBytes cert = {0};
+  LairBytes priv_key = {0};
+  CHECK(lair_tls_cert_get_cert_by_index(client, cert_index, &cert, &err));
+  CHECK(lair_tls_cert_get_priv_key_by_index(client, cert_index, &priv_key,
+                                            &err));
+  ASSERT(cert.len > 0);
+  ASSERT(priv_key.len > 0);
+  /* both are der encoded SEQUENCEs */
+  ASSERT(cert.ptr[0] == 0x30);
+  ASSERT(priv_key.ptr[0] == 0x30);
+  lair_bytes_free(cert);
+  lair_bytes_free(priv_key);
+
+  lair_client_free(client);
+
+  /* freeing null is a no-op */
+  lair_client_free(NULL);
+  lair_string_free(NULL);
+  lair_bytes_free(none);
+
+  printf("passphrase callback invoked %d time(s)\n", passphrase_calls);
+  printf("#ffi-test-ok#\n");
+  return 0;
+}